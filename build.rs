@@ -0,0 +1,5 @@
+fn main() {
+    // The Node.js addon needs platform link flags (e.g. `-undefined dynamic_lookup` on macOS).
+    #[cfg(feature = "nodejs")]
+    napi_build::setup();
+}
@@ -434,7 +434,7 @@ where
             .push(verdict.to_string());
     }
 
-    fn entry_mappings<'a>(entry: &'a Value) -> Vec<&'a Value> {
+    fn entry_mappings(entry: &Value) -> Vec<&Value> {
         entry
             .get("fullbleed_rule_mapping")
             .and_then(Value::as_array)
@@ -496,7 +496,7 @@ where
             continue;
         }
         implemented_evaluated += 1;
-        match worst_verdict(verdicts.into_iter()).unwrap_or("unknown") {
+        match worst_verdict(verdicts).unwrap_or("unknown") {
             "pass" => result_counts.pass += 1,
             "fail" => result_counts.fail += 1,
             "warn" => result_counts.warn += 1,
@@ -596,7 +596,7 @@ where
             .push(verdict.to_string());
     }
 
-    fn entry_mappings<'a>(entry: &'a Value) -> Vec<&'a Value> {
+    fn entry_mappings(entry: &Value) -> Vec<&Value> {
         entry
             .get("fullbleed_rule_mapping")
             .and_then(Value::as_array)
@@ -650,7 +650,7 @@ where
             continue;
         }
         specific_implemented_evaluated += 1;
-        match worst_verdict(verdicts.into_iter()).unwrap_or("unknown") {
+        match worst_verdict(verdicts).unwrap_or("unknown") {
             "pass" => specific_result_counts.pass += 1,
             "fail" => specific_result_counts.fail += 1,
             "warn" => specific_result_counts.warn += 1,
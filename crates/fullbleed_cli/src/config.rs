@@ -0,0 +1,274 @@
+// JSON engine configuration (`--config <file>`): one key per builder option, so shell
+// pipelines can keep page setup and fonts in a checked-in file instead of long flag lists.
+use fullbleed::{FullBleed, FullBleedBuilder, Margins, PdfProfile, PdfVersion, Pt, Size};
+use serde_json::{Map, Value};
+
+const KNOWN_KEYS: &[&str] = &[
+    "page_size",
+    "page_width",
+    "page_height",
+    "margin",
+    "font_dirs",
+    "font_files",
+    "font_fallbacks",
+    "pdf_profile",
+    "pdf_version",
+    "shape_text",
+    "unicode_support",
+    "unicode_metrics",
+    "font_subsetting",
+    "reuse_xobjects",
+    "svg_form_xobjects",
+    "max_image_dpi",
+    "document_lang",
+    "document_title",
+    "outline_levels",
+    "watermark_text",
+    "enforce_audit_gates",
+    "strict_fidelity",
+    "parallel_sections",
+];
+
+pub fn build_engine(config: Option<&Value>) -> Result<FullBleed, String> {
+    let mut builder = FullBleed::builder();
+    if let Some(config) = config {
+        let Value::Object(fields) = config else {
+            return Err("config must be a JSON object".to_string());
+        };
+        builder = apply(builder, fields)?;
+    }
+    builder.build().map_err(|err| err.to_string())
+}
+
+fn apply(
+    mut builder: FullBleedBuilder,
+    fields: &Map<String, Value>,
+) -> Result<FullBleedBuilder, String> {
+    // Unknown keys are an error rather than ignored: a typo would otherwise silently fall
+    // back to the default.
+    if let Some(key) = fields
+        .keys()
+        .find(|key| !KNOWN_KEYS.contains(&key.as_str()))
+    {
+        return Err(format!("unknown config key: {key}"));
+    }
+
+    if let Some(value) = fields.get("page_size") {
+        builder = builder.page_size(page_size(value)?);
+    }
+    match (fields.get("page_width"), fields.get("page_height")) {
+        (Some(width), Some(height)) => {
+            builder = builder.page_size(Size {
+                width: length(width, "page_width")?,
+                height: length(height, "page_height")?,
+            });
+        }
+        (None, None) => {}
+        _ => return Err("page_width and page_height must be given together".to_string()),
+    }
+    if let Some(value) = fields.get("margin") {
+        builder = builder.margins(margins(value)?);
+    }
+    for dir in strings(fields, "font_dirs")? {
+        builder = builder.register_font_dir(dir);
+    }
+    for file in strings(fields, "font_files")? {
+        builder = builder.register_font_file(file);
+    }
+    for family in strings(fields, "font_fallbacks")? {
+        builder = builder.font_fallback(family);
+    }
+    if let Some(value) = string(fields, "pdf_profile")? {
+        builder = builder.pdf_profile(pdf_profile(&value)?);
+    }
+    if let Some(value) = string(fields, "pdf_version")? {
+        builder = builder.pdf_version(pdf_version(&value)?);
+    }
+    if let Some(enabled) = boolean(fields, "shape_text")? {
+        builder = builder.shape_text(enabled);
+    }
+    if let Some(enabled) = boolean(fields, "unicode_support")? {
+        builder = builder.unicode_support(enabled);
+    }
+    if let Some(enabled) = boolean(fields, "unicode_metrics")? {
+        builder = builder.unicode_metrics(enabled);
+    }
+    if let Some(enabled) = boolean(fields, "font_subsetting")? {
+        builder = builder.font_subsetting(enabled);
+    }
+    if let Some(enabled) = boolean(fields, "reuse_xobjects")? {
+        builder = builder.reuse_xobjects(enabled);
+    }
+    if let Some(enabled) = boolean(fields, "svg_form_xobjects")? {
+        builder = builder.svg_form_xobjects(enabled);
+    }
+    if let Some(dpi) = integer(fields, "max_image_dpi")? {
+        builder = builder.max_image_dpi(u32::try_from(dpi).unwrap_or(u32::MAX));
+    }
+    if let Some(lang) = string(fields, "document_lang")? {
+        builder = builder.document_lang(lang);
+    }
+    if let Some(title) = string(fields, "document_title")? {
+        builder = builder.document_title(title);
+    }
+    if let Some(levels) = integer(fields, "outline_levels")? {
+        builder = builder.outline_from_headings(u8::try_from(levels).unwrap_or(u8::MAX));
+    }
+    if let Some(text) = string(fields, "watermark_text")? {
+        builder = builder.watermark_text(text);
+    }
+    if let Some(profile) = string(fields, "enforce_audit_gates")? {
+        builder = builder.enforce_audit_gates(&profile);
+    }
+    if let Some(enabled) = boolean(fields, "strict_fidelity")? {
+        builder = builder.strict_fidelity(enabled);
+    }
+    if let Some(enabled) = boolean(fields, "parallel_sections")? {
+        builder = builder.parallel_sections(enabled);
+    }
+    Ok(builder)
+}
+
+fn page_size(value: &Value) -> Result<Size, String> {
+    if let Value::Object(fields) = value {
+        let (Some(width), Some(height)) = (fields.get("width"), fields.get("height")) else {
+            return Err("page_size object needs width and height".to_string());
+        };
+        return Ok(Size {
+            width: length(width, "page_size.width")?,
+            height: length(height, "page_size.height")?,
+        });
+    }
+    let name = value
+        .as_str()
+        .ok_or("page_size must be a name or {width, height}")?;
+    match name.trim().to_ascii_lowercase().as_str() {
+        "a4" => Ok(Size::a4()),
+        "letter" => Ok(Size::letter()),
+        "legal" => Ok(Size::from_inches(8.5, 14.0)),
+        "a3" => Ok(Size::from_mm(297.0, 420.0)),
+        "a5" => Ok(Size::from_mm(148.0, 210.0)),
+        other => Err(format!("unknown page_size: {other}")),
+    }
+}
+
+fn margins(value: &Value) -> Result<Margins, String> {
+    let Value::Object(fields) = value else {
+        let all = length(value, "margin")?;
+        return Ok(Margins {
+            top: all,
+            right: all,
+            bottom: all,
+            left: all,
+        });
+    };
+    let side = |name: &str| match fields.get(name) {
+        Some(value) => length(value, &format!("margin.{name}")),
+        None => Ok(Pt::ZERO),
+    };
+    Ok(Margins {
+        top: side("top")?,
+        right: side("right")?,
+        bottom: side("bottom")?,
+        left: side("left")?,
+    })
+}
+
+// A number of points, or a string with a `pt`, `px`, `in`, `mm` or `cm` unit.
+pub fn length(value: &Value, key: &str) -> Result<Pt, String> {
+    if let Some(points) = value.as_f64() {
+        return Ok(Pt::from_f32(points as f32));
+    }
+    let raw = value
+        .as_str()
+        .ok_or_else(|| format!("{key}: expected a length such as 12, \"12pt\" or \"20mm\""))?;
+    Pt::parse_length(raw, key).map_err(|err| err.to_string())
+}
+
+pub fn pdf_profile(raw: &str) -> Result<PdfProfile, String> {
+    match raw.trim().to_ascii_lowercase().as_str() {
+        "" | "none" => Ok(PdfProfile::None),
+        "pdfa2b" | "pdfa-2b" | "pdfa_2b" => Ok(PdfProfile::PdfA2b),
+        "pdfa3b" | "pdfa-3b" | "pdfa_3b" => Ok(PdfProfile::PdfA3b),
+        "pdfx4" | "pdfx-4" | "pdfx_4" => Ok(PdfProfile::PdfX4),
+        "tagged" | "pdfua" | "pdf/ua" => Ok(PdfProfile::Tagged),
+        other => Err(format!("unknown pdf profile: {other}")),
+    }
+}
+
+fn pdf_version(raw: &str) -> Result<PdfVersion, String> {
+    match raw.trim().to_ascii_lowercase().as_str() {
+        "1.7" | "17" | "pdf1.7" => Ok(PdfVersion::Pdf17),
+        "2.0" | "2" | "20" | "pdf2.0" => Ok(PdfVersion::Pdf20),
+        other => Err(format!("unknown pdf version: {other}")),
+    }
+}
+
+fn string(fields: &Map<String, Value>, key: &str) -> Result<Option<String>, String> {
+    match fields.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(text)) => Ok(Some(text.clone())),
+        Some(_) => Err(format!("{key} must be a string")),
+    }
+}
+
+fn strings(fields: &Map<String, Value>, key: &str) -> Result<Vec<String>, String> {
+    match fields.get(key) {
+        None | Some(Value::Null) => Ok(Vec::new()),
+        Some(Value::String(text)) => Ok(vec![text.clone()]),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| {
+                item.as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| format!("{key} must be a list of strings"))
+            })
+            .collect(),
+        Some(_) => Err(format!("{key} must be a list of strings")),
+    }
+}
+
+fn boolean(fields: &Map<String, Value>, key: &str) -> Result<Option<bool>, String> {
+    match fields.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Bool(value)) => Ok(Some(*value)),
+        Some(_) => Err(format!("{key} must be true or false")),
+    }
+}
+
+fn integer(fields: &Map<String, Value>, key: &str) -> Result<Option<u64>, String> {
+    match fields.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => value
+            .as_u64()
+            .map(Some)
+            .ok_or_else(|| format!("{key} must be a non-negative integer")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn lengths_accept_points_and_units() {
+        assert_eq!(length(&json!(12), "m").unwrap(), Pt::from_f32(12.0));
+        assert_eq!(length(&json!("1in"), "m").unwrap(), Pt::from_f32(72.0));
+        assert_eq!(length(&json!("16px"), "m").unwrap(), Pt::from_f32(12.0));
+        assert!(length(&json!("3em"), "m").is_err());
+    }
+
+    #[test]
+    fn unknown_keys_and_wrong_types_are_rejected() {
+        let err = build_engine(Some(&json!({ "page_sise": "A4" })))
+            .err()
+            .unwrap();
+        assert!(err.contains("page_sise"), "{err}");
+        let err = build_engine(Some(&json!({ "shape_text": "yes" })))
+            .err()
+            .unwrap();
+        assert!(err.contains("shape_text"), "{err}");
+        assert!(build_engine(Some(&json!({ "page_size": "Letter", "margin": "0.5in" }))).is_ok());
+    }
+}
@@ -0,0 +1,608 @@
+// `fullbleed`: the engine's library APIs as shell commands. Every command prints one JSON
+// payload (`schema`, `ok`, ...) on stdout, or on stderr when stdout carries the PDF itself.
+// Exit codes follow cli_schema.md: 0 success, 1 command failure, 2 usage error.
+mod config;
+
+use fullbleed::{FullBleed, PdfInspectReport};
+use serde_json::{Value, json};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+usage: fullbleed <command> [options]
+
+commands:
+  render       --html <file|-> [--css <file>]... [--config <json>] --out <file.pdf|->
+               [--layout-profile <file.folded>]
+  render-many  --html <file>... [--css <file>]... [--config <json>] (--out <file.pdf> | --out-dir <dir>)
+  inspect      <file.pdf> [--profile <none|pdfa2b|pdfa3b|pdfx4|tagged>]
+  compose      --template <file.pdf> --overlay <file.pdf> --out <file.pdf>
+               [--page-map <t:o,...>] [--dx <pt>] [--dy <pt>]
+  raster       (--html <file> [--css <file>]... | --pdf <file.pdf>) --out-dir <dir>
+               [--config <json>] [--dpi <n>] [--stem <name>]
+  perf-compare --baseline <perf.log> --current <perf.log>
+               [--threshold <ratio>] [--min-delta-ms <ms>]
+
+`--config` is a JSON object of engine options, e.g. {\"page_size\": \"Letter\", \"margin\": \"0.5in\"}.
+";
+
+struct Failure {
+    code: &'static str,
+    message: String,
+}
+
+impl Failure {
+    fn new(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<fullbleed::FullBleedError> for Failure {
+    fn from(err: fullbleed::FullBleedError) -> Self {
+        Failure::new(err.code().as_str(), err.to_string())
+    }
+}
+
+impl From<std::io::Error> for Failure {
+    fn from(err: std::io::Error) -> Self {
+        Failure::new("IO_ERROR", err.to_string())
+    }
+}
+
+// Options parsed from the command line; flags that may repeat keep every value.
+#[derive(Default)]
+struct Args {
+    positional: Vec<String>,
+    options: Vec<(String, String)>,
+}
+
+impl Args {
+    fn parse(raw: &[String], flags_with_values: &[&str]) -> Result<Self, String> {
+        let mut args = Args::default();
+        let mut iter = raw.iter();
+        while let Some(arg) = iter.next() {
+            if let Some(name) = arg.strip_prefix("--") {
+                if !flags_with_values.contains(&name) {
+                    return Err(format!("unknown option: {arg}"));
+                }
+                let value = iter.next().ok_or_else(|| format!("{arg} needs a value"))?;
+                args.options.push((name.to_string(), value.clone()));
+            } else {
+                args.positional.push(arg.clone());
+            }
+        }
+        Ok(args)
+    }
+
+    fn all(&self, name: &str) -> Vec<&str> {
+        self.options
+            .iter()
+            .filter(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+
+    fn one(&self, name: &str) -> Option<&str> {
+        self.all(name).last().copied()
+    }
+
+    fn required(&self, name: &str) -> Result<&str, String> {
+        self.one(name)
+            .ok_or_else(|| format!("--{name} is required"))
+    }
+}
+
+enum Outcome {
+    Usage(String),
+    Done { payload: Value, to_stderr: bool },
+}
+
+fn main() -> ExitCode {
+    let raw: Vec<String> = std::env::args().skip(1).collect();
+    let outcome = match raw.split_first() {
+        None => Outcome::Usage(String::new()),
+        Some((command, _)) if command == "-h" || command == "--help" => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Some((command, rest)) => run(command, rest),
+    };
+    match outcome {
+        Outcome::Usage(message) => {
+            if !message.is_empty() {
+                eprintln!("fullbleed: {message}");
+            }
+            eprint!("{USAGE}");
+            ExitCode::from(2)
+        }
+        Outcome::Done { payload, to_stderr } => {
+            let ok = payload["ok"].as_bool().unwrap_or(false);
+            let text = serde_json::to_string_pretty(&payload).unwrap_or_default();
+            if to_stderr {
+                eprintln!("{text}");
+            } else {
+                println!("{text}");
+            }
+            if ok {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::from(1)
+            }
+        }
+    }
+}
+
+fn run(command: &str, rest: &[String]) -> Outcome {
+    let flags: &[&str] = match command {
+        "render" => &["html", "css", "config", "out", "layout-profile"],
+        "render-many" => &["html", "css", "config", "out", "out-dir"],
+        "inspect" => &["profile"],
+        "compose" => &["template", "overlay", "out", "page-map", "dx", "dy"],
+        "raster" => &["html", "css", "pdf", "config", "out-dir", "dpi", "stem"],
+        "perf-compare" => &["baseline", "current", "threshold", "min-delta-ms"],
+        other => return Outcome::Usage(format!("unknown command: {other}")),
+    };
+    let args = match Args::parse(rest, flags) {
+        Ok(args) => args,
+        Err(message) => return Outcome::Usage(message),
+    };
+    let to_stderr = command == "render" && args.one("out") == Some("-");
+    let result = match command {
+        "render" => render(&args),
+        "render-many" => render_many(&args),
+        "inspect" => inspect(&args),
+        "compose" => compose(&args),
+        "perf-compare" => perf_compare(&args),
+        _ => raster(&args),
+    };
+    let payload = match result {
+        Ok(payload) => payload,
+        Err(Usage(message)) => return Outcome::Usage(message),
+        Err(Failed(failure)) => json!({
+            "schema": "fullbleed.error.v1",
+            "ok": false,
+            "code": failure.code,
+            "message": failure.message,
+        }),
+    };
+    Outcome::Done { payload, to_stderr }
+}
+
+enum CommandError {
+    Usage(String),
+    Failed(Failure),
+}
+use CommandError::{Failed, Usage};
+
+impl From<Failure> for CommandError {
+    fn from(failure: Failure) -> Self {
+        Failed(failure)
+    }
+}
+
+impl From<fullbleed::FullBleedError> for CommandError {
+    fn from(err: fullbleed::FullBleedError) -> Self {
+        Failed(err.into())
+    }
+}
+
+impl From<std::io::Error> for CommandError {
+    fn from(err: std::io::Error) -> Self {
+        Failed(err.into())
+    }
+}
+
+type CommandResult = Result<Value, CommandError>;
+
+fn render(args: &Args) -> CommandResult {
+    let html_path = args.required("html").map_err(Usage)?;
+    let out = args.required("out").map_err(Usage)?;
+    let engine = engine(args)?;
+    let html = read_input(html_path)?;
+    let css = read_css(args)?;
+    let profile_path = args.one("layout-profile");
+    let pdf = match profile_path {
+        None => engine.render_to_buffer(&html, &css)?,
+        Some(path) => {
+            let (pdf, profile) = engine.render_with_layout_profile(&html, &css)?;
+            write_file(Path::new(path), profile.to_folded().as_bytes())?;
+            pdf
+        }
+    };
+    if out == "-" {
+        std::io::stdout().lock().write_all(&pdf)?;
+    } else {
+        write_file(Path::new(out), &pdf)?;
+    }
+    let mut outputs = json!({ "pdf": out });
+    if let Some(path) = profile_path {
+        outputs["layout_profile"] = json!(path);
+    }
+    Ok(json!({
+        "schema": "fullbleed.render_result.v1",
+        "ok": true,
+        "bytes_written": pdf.len(),
+        "outputs": outputs,
+    }))
+}
+
+fn render_many(args: &Args) -> CommandResult {
+    let html_paths = args.all("html");
+    if html_paths.is_empty() {
+        return Err(Usage("--html is required".to_string()));
+    }
+    let engine = engine(args)?;
+    let html_list = html_paths
+        .iter()
+        .map(|path| read_input(path))
+        .collect::<Result<Vec<_>, _>>()?;
+    let css = read_css(args)?;
+    match (args.one("out"), args.one("out-dir")) {
+        (Some(out), None) => {
+            let pdf = engine.render_many_to_buffer(&html_list, &css)?;
+            write_file(Path::new(out), &pdf)?;
+            Ok(json!({
+                "schema": "fullbleed.render_result.v1",
+                "ok": true,
+                "bytes_written": pdf.len(),
+                "outputs": { "pdf": out },
+            }))
+        }
+        (None, Some(dir)) => {
+            // One PDF per input, named after the input file.
+            let stems: Vec<String> = html_paths
+                .iter()
+                .enumerate()
+                .map(|(index, path)| {
+                    Path::new(path)
+                        .file_stem()
+                        .map(|stem| stem.to_string_lossy().into_owned())
+                        .unwrap_or_else(|| format!("document_{}", index + 1))
+                })
+                .collect();
+            let paths = engine.render_many_to_files(&html_list, &css, dir, |index, _| {
+                format!("{}.pdf", stems[index])
+            })?;
+            Ok(json!({
+                "schema": "fullbleed.render_result.v1",
+                "ok": true,
+                "outputs": { "pdfs": display_paths(&paths) },
+            }))
+        }
+        _ => Err(Usage("give exactly one of --out or --out-dir".to_string())),
+    }
+}
+
+fn inspect(args: &Args) -> CommandResult {
+    let [path] = args.positional.as_slice() else {
+        return Err(Usage("inspect takes exactly one PDF path".to_string()));
+    };
+    let path = Path::new(path);
+    let inspect_failure =
+        |err: fullbleed::PdfInspectError| Failure::new(err.code.as_str(), err.message);
+    let Some(profile) = args.one("profile") else {
+        let report = fullbleed::inspect_pdf_path(path).map_err(inspect_failure)?;
+        return Ok(inspect_json(path, &report));
+    };
+    let profile = config::pdf_profile(profile).map_err(Usage)?;
+    let preflight =
+        fullbleed::inspect_pdf_path_with_profile(path, profile).map_err(inspect_failure)?;
+    let mut payload = inspect_json(path, &preflight.report);
+    payload["ok"] = json!(preflight.passed());
+    payload["preflight"] = json!({
+        "passed": preflight.passed(),
+        "violations": preflight
+            .violations
+            .iter()
+            .map(|violation| json!({
+                "rule": violation.rule.as_str(),
+                "page_number": violation.page_number,
+                "message": violation.message,
+            }))
+            .collect::<Vec<_>>(),
+    });
+    Ok(payload)
+}
+
+fn inspect_json(path: &Path, report: &PdfInspectReport) -> Value {
+    let issues = fullbleed::composition_compatibility_issues(report);
+    json!({
+        "schema": "fullbleed.inspect_pdf.v1",
+        "ok": true,
+        "path": path.display().to_string(),
+        "pdf_version": report.pdf_version,
+        "page_count": report.page_count,
+        "encrypted": report.encrypted,
+        "file_size_bytes": report.file_size_bytes,
+        "warnings": report
+            .warnings
+            .iter()
+            .map(|warning| json!({ "code": warning.code, "message": warning.message }))
+            .collect::<Vec<_>>(),
+        "fonts": report
+            .fonts
+            .iter()
+            .map(|font| json!({
+                "base_font": font.base_font,
+                "subtype": font.subtype,
+                "embedded": font.embedded,
+                "pages": font.pages,
+            }))
+            .collect::<Vec<_>>(),
+        "composition": {
+            "supported": issues.is_empty(),
+            "issues": issues.iter().map(|code| code.as_str()).collect::<Vec<_>>(),
+        },
+    })
+}
+
+fn compose(args: &Args) -> CommandResult {
+    let template = args.required("template").map_err(Usage)?;
+    let overlay = args.required("overlay").map_err(Usage)?;
+    let out = args.required("out").map_err(Usage)?;
+    let page_map = args
+        .one("page-map")
+        .map(parse_page_map)
+        .transpose()
+        .map_err(Usage)?;
+    let dx = offset(args, "dx")?;
+    let dy = offset(args, "dy")?;
+    let summary = fullbleed::stamp_overlay_on_template_pdf(
+        Path::new(template),
+        Path::new(overlay),
+        Path::new(out),
+        page_map.as_deref(),
+        dx,
+        dy,
+    )?;
+    Ok(json!({
+        "schema": "fullbleed.compose_result.v1",
+        "ok": true,
+        "pages_written": summary.pages_written,
+        "outputs": { "pdf": out },
+    }))
+}
+
+// `1:1,2:1` stamps overlay page 1 on template pages 1 and 2 (1-based, template first).
+fn parse_page_map(raw: &str) -> Result<Vec<(usize, usize)>, String> {
+    raw.split(',')
+        .map(|pair| {
+            let parsed = pair.split_once(':').and_then(|(template, overlay)| {
+                let template: usize = template.trim().parse().ok()?;
+                let overlay: usize = overlay.trim().parse().ok()?;
+                Some((template.checked_sub(1)?, overlay.checked_sub(1)?))
+            });
+            parsed.ok_or_else(|| format!("invalid --page-map entry: {pair}"))
+        })
+        .collect()
+}
+
+fn offset(args: &Args, name: &str) -> Result<f32, CommandError> {
+    match args.one(name) {
+        None => Ok(0.0),
+        Some(raw) => config::length(&Value::String(raw.to_string()), name)
+            .map(|pt| pt.to_f32())
+            .map_err(Usage),
+    }
+}
+
+fn raster(args: &Args) -> CommandResult {
+    let out_dir = args.required("out-dir").map_err(Usage)?;
+    let dpi = match args.one("dpi") {
+        None => 150,
+        Some(raw) => raw
+            .parse::<u32>()
+            .ok()
+            .filter(|dpi| *dpi > 0)
+            .ok_or_else(|| Usage(format!("invalid --dpi: {raw}")))?,
+    };
+    let stem = args.one("stem").unwrap_or("render");
+    let engine = engine(args)?;
+    let paths = match (args.one("html"), args.one("pdf")) {
+        (Some(html_path), None) => {
+            let html = read_input(html_path)?;
+            let css = read_css(args)?;
+            engine.render_image_pages_to_dir(&html, &css, out_dir, stem, dpi)?
+        }
+        (None, Some(pdf)) => {
+            engine.render_finalized_pdf_image_pages_to_dir(pdf, out_dir, stem, dpi)?
+        }
+        _ => return Err(Usage("give exactly one of --html or --pdf".to_string())),
+    };
+    Ok(json!({
+        "schema": "fullbleed.raster_result.v1",
+        "ok": true,
+        "dpi": dpi,
+        "outputs": { "images": display_paths(&paths) },
+    }))
+}
+
+// Reports `ok: false` (exit 1) when a span regressed, so CI can gate on it.
+fn perf_compare(args: &Args) -> CommandResult {
+    let baseline = args.required("baseline").map_err(Usage)?;
+    let current = args.required("current").map_err(Usage)?;
+    let mut options = fullbleed::PerfCompareOptions::default();
+    if let Some(raw) = args.one("threshold") {
+        options.threshold = non_negative(raw, "threshold")?;
+    }
+    if let Some(raw) = args.one("min-delta-ms") {
+        options.min_delta_ms = non_negative(raw, "min-delta-ms")?;
+    }
+    let comparison = fullbleed::compare_perf_logs(baseline, current, options)?;
+    let report: Value = serde_json::from_str(&comparison.to_json())
+        .map_err(|err| Failure::new("PERF_REPORT", err.to_string()))?;
+    Ok(json!({
+        "schema": "fullbleed.perf_comparison.v1",
+        "ok": !comparison.has_regressions(),
+        "comparison": report,
+    }))
+}
+
+fn non_negative(raw: &str, name: &str) -> Result<f64, CommandError> {
+    raw.parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite() && *value >= 0.0)
+        .ok_or_else(|| Usage(format!("invalid --{name}: {raw}")))
+}
+
+fn engine(args: &Args) -> Result<FullBleed, CommandError> {
+    let config = match args.one("config") {
+        None => None,
+        Some(path) => {
+            let text = std::fs::read_to_string(path)?;
+            let value: Value = serde_json::from_str(&text)
+                .map_err(|err| Failure::new("CONFIG_ERROR", format!("{path}: {err}")))?;
+            Some(value)
+        }
+    };
+    config::build_engine(config.as_ref())
+        .map_err(|message| Failed(Failure::new("CONFIG_ERROR", message)))
+}
+
+// `-` reads standard input.
+fn read_input(path: &str) -> Result<String, Failure> {
+    if path == "-" {
+        let mut text = String::new();
+        std::io::stdin().lock().read_to_string(&mut text)?;
+        return Ok(text);
+    }
+    std::fs::read_to_string(path).map_err(|err| Failure::new("IO_ERROR", format!("{path}: {err}")))
+}
+
+// Stylesheets are concatenated in command-line order.
+fn read_css(args: &Args) -> Result<String, Failure> {
+    let mut css = String::new();
+    for path in args.all("css") {
+        css.push_str(&read_input(path)?);
+        css.push('\n');
+    }
+    Ok(css)
+}
+
+fn write_file(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, bytes)
+}
+
+fn display_paths(paths: &[PathBuf]) -> Vec<String> {
+    paths
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(raw: &[&str]) -> Vec<String> {
+        raw.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn options_repeat_and_unknown_flags_are_usage_errors() {
+        let args = Args::parse(
+            &strings(&["--css", "a.css", "--css", "b.css", "--out", "x.pdf"]),
+            &["css", "out"],
+        )
+        .unwrap();
+        assert_eq!(args.all("css"), ["a.css", "b.css"]);
+        assert_eq!(args.one("out"), Some("x.pdf"));
+        assert!(Args::parse(&strings(&["--bogus", "1"]), &["css"]).is_err());
+        assert!(Args::parse(&strings(&["--css"]), &["css"]).is_err());
+    }
+
+    #[test]
+    fn page_map_is_one_based_on_the_command_line() {
+        assert_eq!(parse_page_map("1:1,2:1").unwrap(), [(0, 0), (1, 0)]);
+        assert!(parse_page_map("0:1").is_err());
+        assert!(parse_page_map("1-1").is_err());
+    }
+
+    #[test]
+    fn render_writes_pdf_and_reports_result() {
+        let dir = std::env::temp_dir().join(format!("fullbleed_cli_render_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let html = dir.join("in.html");
+        std::fs::write(&html, "<p>Hello</p>").unwrap();
+        let out = dir.join("out.pdf");
+        let args = Args::parse(
+            &strings(&[
+                "--html",
+                html.to_str().unwrap(),
+                "--out",
+                out.to_str().unwrap(),
+            ]),
+            &["html", "css", "config", "out"],
+        )
+        .unwrap();
+        let Ok(payload) = render(&args) else {
+            panic!("render failed");
+        };
+        assert_eq!(payload["schema"], "fullbleed.render_result.v1");
+        assert!(std::fs::read(&out).unwrap().starts_with(b"%PDF-"));
+
+        let args = Args::parse(&strings(&[out.to_str().unwrap()]), &["profile"]).unwrap();
+        let Ok(payload) = inspect(&args) else {
+            panic!("inspect failed");
+        };
+        assert_eq!(payload["page_count"], 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn perf_compare_fails_on_regressed_spans() {
+        let dir = std::env::temp_dir().join(format!("fullbleed_cli_perf_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let log = |name: &str, ms: f64| {
+            let path = dir.join(name);
+            let line = format!(
+                "{{\"type\":\"perf.span\",\"name\":\"layout\",\"doc_id\":0,\"unit\":\"ms\",\"ms\":{ms}}}\n"
+            );
+            std::fs::write(&path, line).unwrap();
+            path.to_str().unwrap().to_string()
+        };
+        let baseline = log("baseline.log", 10.0);
+        let current = log("current.log", 20.0);
+        let flags = &["baseline", "current", "threshold", "min-delta-ms"];
+
+        let args = Args::parse(
+            &strings(&["--baseline", &baseline, "--current", &current]),
+            flags,
+        )
+        .unwrap();
+        let Ok(payload) = perf_compare(&args) else {
+            panic!("perf-compare failed");
+        };
+        assert_eq!(payload["schema"], "fullbleed.perf_comparison.v1");
+        assert_eq!(payload["ok"], false);
+        assert_eq!(payload["comparison"]["regressions"], json!(["layout"]));
+
+        let args = Args::parse(
+            &strings(&[
+                "--baseline",
+                &baseline,
+                "--current",
+                &current,
+                "--threshold",
+                "1.5",
+            ]),
+            flags,
+        )
+        .unwrap();
+        let Ok(payload) = perf_compare(&args) else {
+            panic!("perf-compare failed");
+        };
+        assert_eq!(payload["ok"], true);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,77 @@
+/*
+ * C API for the fullbleed engine (cargo feature `capi`, cdylib output).
+ *
+ * Every fallible call returns an fb_status; on failure fb_last_error() returns a message
+ * that stays valid until the next call on the same thread. Engines are thread-safe and may
+ * be shared; builders are not. Buffers returned by the library are released with
+ * fb_buffer_free and never with free().
+ */
+#ifndef FULLBLEED_H
+#define FULLBLEED_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define FB_ABI_VERSION 1
+
+typedef enum fb_status {
+    FB_OK = 0,
+    FB_INVALID_ARGUMENT = 1,
+    FB_INVALID_CONFIGURATION = 2,
+    FB_IO = 3,
+    FB_ASSET = 4,
+    FB_LAYOUT = 5,
+    FB_AUDIT_GATE_FAILED = 6,
+    FB_PANIC = 7,
+    FB_FIDELITY_LOSS = 8
+} fb_status;
+
+typedef enum fb_pdf_profile {
+    FB_PDF_PROFILE_NONE = 0,
+    FB_PDF_PROFILE_PDFA2B = 1,
+    FB_PDF_PROFILE_PDFA3B = 2,
+    FB_PDF_PROFILE_PDFX4 = 3,
+    FB_PDF_PROFILE_TAGGED = 4
+} fb_pdf_profile;
+
+typedef struct fb_buffer {
+    uint8_t *data;
+    size_t len;
+} fb_buffer;
+
+typedef struct FbBuilder fb_builder;
+typedef struct FbEngine fb_engine;
+
+uint32_t fb_abi_version(void);
+const char *fb_last_error(void);
+
+fb_builder *fb_builder_new(void);
+fb_status fb_builder_page_size(fb_builder *builder, float width_pt, float height_pt);
+fb_status fb_builder_margins(fb_builder *builder, float top_pt, float right_pt,
+                             float bottom_pt, float left_pt);
+fb_status fb_builder_register_font_file(fb_builder *builder, const char *path);
+fb_status fb_builder_register_font_dir(fb_builder *builder, const char *path);
+fb_status fb_builder_font_fallback(fb_builder *builder, const char *family);
+fb_status fb_builder_pdf_profile(fb_builder *builder, uint32_t profile);
+/* Spends the builder whether or not it succeeds; free it afterwards either way. */
+fb_status fb_builder_build(fb_builder *builder, fb_engine **out_engine);
+void fb_builder_free(fb_builder *builder);
+void fb_engine_free(fb_engine *engine);
+
+fb_status fb_render_to_buffer(const fb_engine *engine, const char *html, const char *css,
+                              fb_buffer *out);
+fb_status fb_render_many(const fb_engine *engine, const char *const *html_list, size_t count,
+                         const char *css, fb_buffer *out);
+fb_status fb_compose(const char *template_path, const char *overlay_path, const char *out_path,
+                     float dx, float dy, size_t *pages_written);
+void fb_buffer_free(fb_buffer buffer);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* FULLBLEED_H */
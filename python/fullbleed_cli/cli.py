@@ -2435,7 +2435,7 @@ def cmd_doctor(args):
         "python": sys.version.split()[0],
         "platform": sys.platform,
         "pdf_versions": ["1.7", "2.0"],
-        "pdf_profiles": ["none", "pdfa2b", "pdfx4", "tagged"],
+        "pdf_profiles": ["none", "pdfa2b", "pdfa3b", "pdfx4", "tagged"],
         "color_spaces": ["rgb", "cmyk"],
         "assets": {
             "bootstrap": str(bootstrap),
@@ -2858,6 +2858,14 @@ def cmd_capabilities(args):
                 "svg_form_xobjects": True,
                 "svg_raster_fallback": True,
             },
+            "vector_features": [
+                "linear_gradient",
+                "radial_gradient",
+                "clip_path",
+                "use",
+                "symbol",
+                "nested_svg",
+            ],
         },
         "profiles": list(PROFILES.keys()),
         "fail_on": FAIL_ON_CHOICES,
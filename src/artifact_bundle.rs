@@ -0,0 +1,269 @@
+// Reproducibility sidecars for a delivered PDF: the exact input HTML, the merged CSS the
+// engine laid out with, the PMR audit report and the audit contract fingerprint, written
+// next to the PDF into a directory or a single zip.
+use crate::{PmrCoreAudit, PmrCoreEvidence, PmrCoreReport};
+use fullbleed_audit_contract as audit_contract;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+pub const BUNDLE_PDF: &str = "document.pdf";
+pub const BUNDLE_HTML: &str = "input.html";
+pub const BUNDLE_CSS: &str = "merged.css";
+pub const BUNDLE_REPORT: &str = "pmr_report.json";
+pub const BUNDLE_MANIFEST: &str = "manifest.json";
+
+// Where `FullBleed::render_with_artifact_bundle` writes its bundle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArtifactBundleTarget {
+    // One file per artifact in this directory, created if missing.
+    Directory(PathBuf),
+    // A single uncompressed zip archive at this path.
+    Zip(PathBuf),
+}
+
+// One file of a bundle with its size and SHA-256.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArtifactFile {
+    pub name: String,
+    pub bytes: usize,
+    pub sha256: String,
+}
+
+// What a bundle holds; also written into it as `manifest.json`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArtifactBundleManifest {
+    pub profile: String,
+    pub contract_fingerprint_sha256: String,
+    pub gate_ok: bool,
+    pub files: Vec<ArtifactFile>,
+}
+
+pub(crate) fn write_bundle(
+    target: &ArtifactBundleTarget,
+    pdf: &[u8],
+    html: &str,
+    merged_css: &str,
+    report: &PmrCoreReport,
+) -> io::Result<ArtifactBundleManifest> {
+    let report_json = serde_json::to_vec_pretty(&pmr_report_json(report))
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    let entries: [(&str, &[u8]); 4] = [
+        (BUNDLE_PDF, pdf),
+        (BUNDLE_HTML, html.as_bytes()),
+        (BUNDLE_CSS, merged_css.as_bytes()),
+        (BUNDLE_REPORT, &report_json),
+    ];
+    let manifest = ArtifactBundleManifest {
+        profile: report.profile.clone(),
+        contract_fingerprint_sha256: audit_contract::contract_fingerprint_sha256(),
+        gate_ok: report.gate.ok,
+        files: entries
+            .iter()
+            .map(|(name, data)| ArtifactFile {
+                name: name.to_string(),
+                bytes: data.len(),
+                sha256: crate::encrypt::hex(&Sha256::digest(data)),
+            })
+            .collect(),
+    };
+    let manifest_json = serde_json::to_vec_pretty(&manifest_json(&manifest))
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+    let mut files: Vec<(&str, &[u8])> = entries.to_vec();
+    files.push((BUNDLE_MANIFEST, &manifest_json));
+    match target {
+        ArtifactBundleTarget::Directory(dir) => {
+            std::fs::create_dir_all(dir)?;
+            for (name, data) in files {
+                std::fs::write(dir.join(name), data)?;
+            }
+        }
+        ArtifactBundleTarget::Zip(path) => write_zip(path, &files)?,
+    }
+    Ok(manifest)
+}
+
+fn manifest_json(manifest: &ArtifactBundleManifest) -> Value {
+    let meta = audit_contract::metadata();
+    json!({
+        "schema": "fullbleed.artifact_bundle.v1",
+        "engine_version": env!("CARGO_PKG_VERSION"),
+        "profile": manifest.profile,
+        "gate_ok": manifest.gate_ok,
+        "contract": {
+            "contract_id": meta.contract_id,
+            "contract_version": meta.contract_version,
+            "contract_fingerprint_sha256": meta.contract_fingerprint_sha256,
+            "audit_registry_id": meta.audit_registry_id,
+            "audit_registry_hash_sha256": meta.audit_registry_hash_sha256,
+            "wcag20aa_registry_id": meta.wcag20aa_registry_id,
+            "wcag20aa_registry_hash_sha256": meta.wcag20aa_registry_hash_sha256,
+            "section508_html_registry_id": meta.section508_html_registry_id,
+            "section508_html_registry_hash_sha256": meta.section508_html_registry_hash_sha256,
+        },
+        "files": manifest
+            .files
+            .iter()
+            .map(|file| json!({
+                "name": file.name,
+                "bytes": file.bytes,
+                "sha256": file.sha256,
+            }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+// Same shape as the Python `fullbleed.pmr.v1` report, without the diagnostic-signal guards
+// the Python adapters add.
+fn pmr_report_json(report: &PmrCoreReport) -> Value {
+    json!({
+        "schema": "fullbleed.pmr.v1",
+        "target": { "html_path": BUNDLE_HTML, "css_path": BUNDLE_CSS },
+        "profile": report.profile,
+        "mode": report.mode,
+        "rank": {
+            "score": report.rank.score,
+            "confidence": report.rank.confidence,
+            "band": report.rank.band,
+            "raw_score": report.rank.raw_score,
+        },
+        "gate": {
+            "ok": report.gate.ok,
+            "mode": report.gate.mode,
+            "error_count": report.gate.error_count,
+            "warn_count": report.gate.warn_count,
+            "failed_audit_ids": report.gate.failed_audit_ids,
+        },
+        "coverage": {
+            "evaluated_audit_count": report.coverage.evaluated_audit_count,
+            "applicable_audit_count": report.coverage.applicable_audit_count,
+            "scored_audit_count": report.coverage.scored_audit_count,
+            "manual_needed_count": report.coverage.manual_needed_count,
+            "not_evaluated_audit_count": report.coverage.not_evaluated_audit_count,
+        },
+        "categories": report
+            .categories
+            .iter()
+            .map(|category| json!({
+                "id": category.id,
+                "name": category.name,
+                "weight": category.weight,
+                "score": category.score,
+                "confidence": category.confidence,
+                "audit_count": category.audit_count,
+                "fail_count": category.fail_count,
+                "warn_count": category.warn_count,
+            }))
+            .collect::<Vec<_>>(),
+        "audits": report.audits.iter().map(audit_json).collect::<Vec<_>>(),
+        "manual_debt": {
+            "item_count": report.manual_debt_item_count,
+            "high_risk_count": report.manual_debt_high_risk_count,
+            "items": report
+                .manual_debt_items
+                .iter()
+                .map(|item| json!({
+                    "id": item.id,
+                    "reason": item.reason,
+                    "severity": item.severity,
+                    "category": item.category,
+                }))
+                .collect::<Vec<_>>(),
+        },
+    })
+}
+
+fn audit_json(audit: &PmrCoreAudit) -> Value {
+    json!({
+        "audit_id": audit.audit_id,
+        "category": audit.category,
+        "weight": audit.weight,
+        "class": audit.class_name,
+        "verification_mode": audit.verification_mode,
+        "severity": audit.severity,
+        "stage": audit.stage,
+        "source": audit.source,
+        "verdict": audit.verdict,
+        "scored": audit.scored,
+        "score": audit.score,
+        "message": audit.message,
+        "fix_hint": audit.fix_hint,
+        "evidence": audit.evidence.iter().map(evidence_json).collect::<Vec<_>>(),
+    })
+}
+
+fn evidence_json(evidence: &PmrCoreEvidence) -> Value {
+    json!({
+        "selector": evidence.selector,
+        "diagnostic_ref": evidence.diagnostic_ref,
+        "values": evidence
+            .values
+            .iter()
+            .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+            .collect::<serde_json::Map<_, _>>(),
+    })
+}
+
+// Stored (method 0) entries with a fixed 1980-01-01 timestamp, so equal inputs give
+// byte-identical archives.
+fn write_zip(path: &Path, files: &[(&str, &[u8])]) -> io::Result<()> {
+    const DOS_DATE: u16 = 0x21; // 1980-01-01
+    let too_large = || io::Error::new(io::ErrorKind::InvalidInput, "bundle exceeds zip32 limits");
+    let mut out: Vec<u8> = Vec::new();
+    let mut central: Vec<u8> = Vec::new();
+    for (name, data) in files {
+        let mut crc = flate2::Crc::new();
+        crc.update(data);
+        let crc = crc.sum();
+        let size = u32::try_from(data.len()).map_err(|_| too_large())?;
+        let offset = u32::try_from(out.len()).map_err(|_| too_large())?;
+        let name_len = name.len() as u16;
+
+        out.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        out.extend_from_slice(&20u16.to_le_bytes()); // version needed
+        out.extend_from_slice(&0u16.to_le_bytes()); // flags
+        out.extend_from_slice(&0u16.to_le_bytes()); // stored
+        out.extend_from_slice(&0u16.to_le_bytes()); // time
+        out.extend_from_slice(&DOS_DATE.to_le_bytes());
+        out.extend_from_slice(&crc.to_le_bytes());
+        out.extend_from_slice(&size.to_le_bytes());
+        out.extend_from_slice(&size.to_le_bytes());
+        out.extend_from_slice(&name_len.to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes()); // extra length
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(data);
+
+        central.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes()); // version made by
+        central.extend_from_slice(&20u16.to_le_bytes()); // version needed
+        central.extend_from_slice(&0u16.to_le_bytes()); // flags
+        central.extend_from_slice(&0u16.to_le_bytes()); // stored
+        central.extend_from_slice(&0u16.to_le_bytes()); // time
+        central.extend_from_slice(&DOS_DATE.to_le_bytes());
+        central.extend_from_slice(&crc.to_le_bytes());
+        central.extend_from_slice(&size.to_le_bytes());
+        central.extend_from_slice(&size.to_le_bytes());
+        central.extend_from_slice(&name_len.to_le_bytes());
+        central.extend_from_slice(&[0u8; 8]); // extra, comment, disk, internal attrs
+        central.extend_from_slice(&0u32.to_le_bytes()); // external attrs
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+    }
+    let central_offset = u32::try_from(out.len()).map_err(|_| too_large())?;
+    let central_size = central.len() as u32;
+    out.extend_from_slice(&central);
+    out.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    out.extend_from_slice(&[0u8; 4]); // disk numbers
+    out.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    out.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    out.extend_from_slice(&central_size.to_le_bytes());
+    out.extend_from_slice(&central_offset.to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes()); // comment length
+
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::File::create(path)?.write_all(&out)
+}
@@ -1,3 +1,4 @@
+use crate::fetch::{RemoteAssets, is_remote_url};
 use base64::Engine;
 use std::path::{Path, PathBuf};
 
@@ -70,6 +71,35 @@ impl Asset {
 #[derive(Debug, Clone, Default)]
 pub struct AssetBundle {
     pub assets: Vec<Asset>,
+    pub(crate) remote: Option<RemoteAssets>,
+    pub(crate) resolver: Option<SharedResolver>,
+}
+
+// Supplies the bytes behind local (non-URL) asset paths in place of the filesystem, e.g. from
+// object storage, a database, or an in-memory map. `path` is the reference as written (with
+// any `file://` prefix removed); `kind` says what the engine wants it for.
+pub trait ResourceResolver: Send + Sync {
+    fn read(&self, path: &str, kind: AssetKind) -> std::io::Result<Vec<u8>>;
+}
+
+impl ResourceResolver for std::collections::HashMap<String, Vec<u8>> {
+    fn read(&self, path: &str, _kind: AssetKind) -> std::io::Result<Vec<u8>> {
+        self.get(path).cloned().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("{path}: not found in resource map"),
+            )
+        })
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct SharedResolver(pub(crate) std::sync::Arc<dyn ResourceResolver>);
+
+impl std::fmt::Debug for SharedResolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ResourceResolver")
+    }
 }
 
 impl AssetBundle {
@@ -116,7 +146,10 @@ pub fn is_supported_font_path(path: &Path) -> bool {
     let Some(ext) = path.extension().and_then(|v| v.to_str()) else {
         return false;
     };
-    matches!(ext.to_ascii_lowercase().as_str(), "ttf" | "otf")
+    matches!(
+        ext.to_ascii_lowercase().as_str(),
+        "ttf" | "otf" | "woff" | "woff2"
+    )
 }
 
 #[cfg_attr(not(feature = "python"), allow(dead_code))]
@@ -223,7 +256,7 @@ pub fn renderable_image_source(bundle: Option<&AssetBundle>, source: &str) -> Op
         return None;
     }
     match resolved.trace.resolver.as_str() {
-        "bundle" => {
+        "bundle" | "remote" | "resource_resolver" => {
             let mime = resolved
                 .trace
                 .mime
@@ -269,7 +302,7 @@ pub fn resolve_image_asset(bundle: Option<&AssetBundle>, source: &str) -> Resolv
                 content_kind: content_kind.to_string(),
                 render_outcome: render_outcome_for(&content_kind).to_string(),
                 asset_name: None,
-                message: None,
+                message: unsupported_image_message(&content_kind, &data),
             },
             bytes: data,
         };
@@ -293,18 +326,93 @@ pub fn resolve_image_asset(bundle: Option<&AssetBundle>, source: &str) -> Resolv
                     content_kind: content_kind.to_string(),
                     render_outcome: render_outcome_for(&content_kind).to_string(),
                     asset_name: Some(asset.name.clone()),
-                    message: None,
+                    message: unsupported_image_message(&content_kind, &asset.data),
                 },
                 bytes: asset.data.clone(),
             };
         }
     }
 
-    if let Some(path) = file_uri_to_path_buf(trimmed) {
+    if is_remote_url(trimmed) {
+        return resolve_remote(bundle.and_then(|bundle| bundle.remote.as_ref()), trimmed);
+    }
+
+    let path = file_uri_to_path_buf(trimmed).unwrap_or_else(|| PathBuf::from(trimmed));
+    if let Some(SharedResolver(resolver)) = bundle.and_then(|bundle| bundle.resolver.as_ref()) {
+        let normalized = path.to_string_lossy().to_string();
+        let read = resolver
+            .read(&normalized, AssetKind::Image)
+            .map_err(|err| err.to_string());
+        return fetched_asset(trimmed, normalized, "resource_resolver", read);
+    }
+    if file_uri_to_path_buf(trimmed).is_some() {
         return resolve_local_path(trimmed, path, "file_uri");
     }
 
-    resolve_local_path(trimmed, PathBuf::from(trimmed), "local_path")
+    resolve_local_path(trimmed, path, "local_path")
+}
+
+fn resolve_remote(remote: Option<&RemoteAssets>, source: &str) -> ResolvedAsset {
+    let fetched = match remote {
+        Some(remote) => remote
+            .fetch(source)
+            .map(|bytes| bytes.as_ref().clone())
+            .map_err(|err| err.to_string()),
+        None => {
+            Err("remote assets are disabled; configure FullBleedBuilder::remote_assets".to_string())
+        }
+    };
+    fetched_asset(source, source.to_string(), "remote", fetched)
+}
+
+// Trace for bytes produced by a fetcher or resolver rather than read from disk here. The
+// renderable form of these is always a data URI, so later stages never touch the path again.
+fn fetched_asset(
+    source: &str,
+    normalized: String,
+    resolver: &str,
+    fetched: Result<Vec<u8>, String>,
+) -> ResolvedAsset {
+    let mime = infer_image_mime_from_label(&normalized);
+    match fetched {
+        Ok(bytes) => {
+            // URLs and virtual paths often carry no extension; fall back to sniffing the body.
+            let mime = mime.or_else(|| {
+                image::guess_format(&bytes)
+                    .ok()
+                    .map(|format| format.to_mime_type().to_string())
+            });
+            let content_kind = content_kind_for(mime.as_deref(), &normalized, &bytes);
+            ResolvedAsset {
+                trace: AssetResolutionTrace {
+                    source_uri: display_source_uri(source),
+                    normalized_uri: Some(normalized),
+                    resolver: resolver.to_string(),
+                    success: true,
+                    mime,
+                    content_kind: content_kind.to_string(),
+                    render_outcome: render_outcome_for(&content_kind).to_string(),
+                    asset_name: None,
+                    message: unsupported_image_message(&content_kind, &bytes),
+                },
+                bytes,
+            }
+        }
+        Err(message) => ResolvedAsset {
+            trace: AssetResolutionTrace {
+                source_uri: display_source_uri(source),
+                normalized_uri: Some(normalized),
+                resolver: resolver.to_string(),
+                success: false,
+                mime,
+                content_kind: "unknown".to_string(),
+                render_outcome: "unresolved".to_string(),
+                asset_name: None,
+                message: Some(message),
+            },
+            bytes: Vec::new(),
+        },
+    }
 }
 
 fn resolve_local_path(source: &str, path: PathBuf, resolver: &str) -> ResolvedAsset {
@@ -323,7 +431,7 @@ fn resolve_local_path(source: &str, path: PathBuf, resolver: &str) -> ResolvedAs
                     content_kind: content_kind.to_string(),
                     render_outcome: render_outcome_for(&content_kind).to_string(),
                     asset_name: None,
-                    message: None,
+                    message: unsupported_image_message(&content_kind, &bytes),
                 },
                 bytes,
             }
@@ -345,6 +453,46 @@ fn resolve_local_path(source: &str, path: PathBuf, resolver: &str) -> ResolvedAs
     }
 }
 
+// Bytes behind a CSS `url()` or other non-image reference: data URI, bundle asset (any kind,
+// matched by name or source), remote URL, then the resource resolver or the filesystem.
+pub(crate) fn resolve_asset_bytes(
+    bundle: Option<&AssetBundle>,
+    source: &str,
+    kind: AssetKind,
+) -> Result<Vec<u8>, String> {
+    let trimmed = source.trim();
+    if let Some((_mime, data)) = parse_data_uri_bytes(trimmed) {
+        return Ok(data);
+    }
+    if let Some(bundle) = bundle {
+        let keys = lookup_keys(trimmed);
+        if let Some(asset) = bundle.assets.iter().find(|asset| {
+            asset_lookup_keys(asset)
+                .iter()
+                .any(|key| keys.contains(key))
+        }) {
+            return Ok(asset.data.clone());
+        }
+    }
+    if is_remote_url(trimmed) {
+        return match bundle.and_then(|bundle| bundle.remote.as_ref()) {
+            Some(remote) => remote
+                .fetch(trimmed)
+                .map(|bytes| bytes.as_ref().clone())
+                .map_err(|err| err.to_string()),
+            None => Err(
+                "remote assets are disabled; configure FullBleedBuilder::remote_assets".to_string(),
+            ),
+        };
+    }
+    let path = file_uri_to_path_buf(trimmed).unwrap_or_else(|| PathBuf::from(trimmed));
+    match bundle.and_then(|bundle| bundle.resolver.as_ref()) {
+        Some(SharedResolver(resolver)) => resolver.read(&path.to_string_lossy(), kind),
+        None => std::fs::read(&path),
+    }
+    .map_err(|err| format!("{}: {err}", path.to_string_lossy()))
+}
+
 fn bundle_lookup<'a>(bundle: &'a AssetBundle, source: &str) -> Option<&'a Asset> {
     let keys = lookup_keys(source);
     bundle.image_assets().find(|asset| {
@@ -436,6 +584,8 @@ fn infer_image_mime_from_label(label: &str) -> Option<String> {
         Some("image/gif".to_string())
     } else if lower.ends_with(".webp") {
         Some("image/webp".to_string())
+    } else if lower.ends_with(".avif") {
+        Some("image/avif".to_string())
     } else if lower.ends_with(".bmp") {
         Some("image/bmp".to_string())
     } else if lower.ends_with(".svg") || lower.ends_with(".svgz") {
@@ -460,12 +610,44 @@ fn content_kind_for(mime: Option<&str>, label: &str, bytes: &[u8]) -> String {
             return "svg".to_string();
         }
     }
-    if image::guess_format(bytes).is_ok() {
+    if image::guess_format(bytes).is_ok_and(|format| format.reading_enabled()) {
         return "raster_image".to_string();
     }
     "unknown".to_string()
 }
 
+// Image formats this build can place. AVIF decoding needs the system dav1d
+// library, so it is only listed when the `avif` feature is enabled.
+pub fn supported_image_formats() -> &'static [&'static str] {
+    if cfg!(feature = "avif") {
+        &["png", "jpeg", "webp", "avif", "svg"]
+    } else {
+        &["png", "jpeg", "webp", "svg"]
+    }
+}
+
+// Human-readable reason an image could not be decoded, naming the detected
+// container (when recognizable) and the formats this build supports.
+pub fn image_decode_error_message(bytes: &[u8]) -> String {
+    let detected = match image::guess_format(bytes) {
+        Ok(format) => format
+            .extensions_str()
+            .first()
+            .copied()
+            .unwrap_or("unknown")
+            .to_string(),
+        Err(_) => "unrecognized".to_string(),
+    };
+    format!(
+        "unsupported image format ({detected}); supported formats: {}",
+        supported_image_formats().join(", ")
+    )
+}
+
+fn unsupported_image_message(content_kind: &str, bytes: &[u8]) -> Option<String> {
+    (content_kind == "unknown").then(|| image_decode_error_message(bytes))
+}
+
 fn render_outcome_for(content_kind: &str) -> &'static str {
     match content_kind {
         "svg" => "vector_svg",
@@ -488,7 +670,7 @@ fn display_source_uri(source: &str) -> String {
 mod tests {
     use super::{
         Asset, AssetBundle, AssetKind, file_uri_to_path_buf, parse_data_uri_bytes,
-        renderable_image_source, resolve_image_asset,
+        renderable_image_source, resolve_image_asset, supported_image_formats,
     };
     use base64::Engine;
     use std::path::Path;
@@ -503,7 +685,9 @@ mod tests {
     fn supported_font_path_accepts_ttf_and_otf() {
         assert!(super::is_supported_font_path(Path::new("demo.ttf")));
         assert!(super::is_supported_font_path(Path::new("demo.otf")));
-        assert!(!super::is_supported_font_path(Path::new("demo.woff2")));
+        assert!(super::is_supported_font_path(Path::new("demo.woff")));
+        assert!(super::is_supported_font_path(Path::new("demo.woff2")));
+        assert!(!super::is_supported_font_path(Path::new("demo.pfb")));
     }
 
     #[test]
@@ -542,4 +726,18 @@ mod tests {
         let renderable = renderable_image_source(Some(&bundle), "diagram.png").expect("renderable");
         assert!(renderable.starts_with("data:image/png;base64,"));
     }
+
+    #[test]
+    fn undecodable_image_reports_supported_formats() {
+        let encoded = base64::engine::general_purpose::STANDARD.encode(b"GIF89a\x01\x00\x01\x00");
+        let uri = format!("data:image/gif;base64,{encoded}");
+        let resolved = resolve_image_asset(None, &uri);
+        assert_eq!(resolved.trace.render_outcome, "unsupported");
+        let message = resolved.trace.message.expect("decode message");
+        assert!(message.contains("(gif)"), "{message}");
+        for format in supported_image_formats() {
+            assert!(message.contains(format), "{message}");
+        }
+        assert!(supported_image_formats().contains(&"webp"));
+    }
 }
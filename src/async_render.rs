@@ -0,0 +1,124 @@
+// Async facade over the blocking render paths (feature `async`). Layout and PDF writing stay
+// on Tokio's blocking pool so they never stall the caller's executor; batch output flows
+// through a bounded channel, so a slow consumer pauses rendering instead of buffering the
+// whole batch.
+use crate::progress::BatchProgress;
+use crate::{FullBleed, FullBleedError, pdf};
+use futures_core::Stream;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+
+// Finished records waiting for the consumer; rendering blocks once this many are queued.
+const RECORD_STREAM_CAPACITY: usize = 2;
+
+// One rendered record from [`FullBleed::render_many_stream`].
+#[derive(Debug, Clone)]
+pub struct RecordPdf {
+    // Position of the record in the input list.
+    pub index: usize,
+    pub page_count: usize,
+    // A complete PDF for this record alone.
+    pub bytes: Vec<u8>,
+}
+
+// Records in input order. Rendering stops at the first error, which is the last item, and
+// when the stream is dropped.
+pub struct RecordPdfStream {
+    receiver: mpsc::Receiver<Result<RecordPdf, FullBleedError>>,
+}
+
+impl RecordPdfStream {
+    // The next record, or `None` once the batch is done.
+    pub async fn recv(&mut self) -> Option<Result<RecordPdf, FullBleedError>> {
+        self.receiver.recv().await
+    }
+}
+
+impl Stream for RecordPdfStream {
+    type Item = Result<RecordPdf, FullBleedError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+impl FullBleed {
+    // Async [`FullBleed::render_to_writer`]: renders on the blocking pool, then writes the PDF
+    // to `writer`. Returns the number of bytes written.
+    pub async fn render_to_writer_async<W>(
+        self: &Arc<Self>,
+        html: &str,
+        css: &str,
+        writer: &mut W,
+    ) -> Result<usize, FullBleedError>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let engine = Arc::clone(self);
+        let html = html.to_string();
+        let css = css.to_string();
+        let bytes = tokio::task::spawn_blocking(move || engine.render_to_buffer(&html, &css))
+            .await
+            .map_err(join_error)??;
+        writer.write_all(&bytes).await?;
+        writer.flush().await?;
+        Ok(bytes.len())
+    }
+
+    // Renders each record into its own PDF and yields them in input order. Must be called
+    // from within a Tokio runtime.
+    pub fn render_many_stream(
+        self: &Arc<Self>,
+        html_list: Vec<String>,
+        css: String,
+    ) -> RecordPdfStream {
+        let (sender, receiver) = mpsc::channel(RECORD_STREAM_CAPACITY);
+        let engine = Arc::clone(self);
+        tokio::task::spawn_blocking(move || {
+            let context = engine.build_render_context(&css, None);
+            let progress = BatchProgress::new(engine.pdf_options.progress.clone(), html_list.len());
+            for (index, html) in html_list.iter().enumerate() {
+                let record = engine
+                    .render_to_document_and_page_data_with_resolver_and_report_at(
+                        index,
+                        html,
+                        &context.page_templates,
+                        &context.resolver,
+                        None,
+                    )
+                    .and_then(|(doc, _)| {
+                        let bytes = pdf::document_to_pdf_with_metrics_and_registry_with_logs(
+                            &doc,
+                            None,
+                            Some(engine.font_registry.as_ref()),
+                            &engine.pdf_options,
+                            engine.debug.clone(),
+                            engine.perf.clone(),
+                        )?;
+                        Ok(RecordPdf {
+                            index,
+                            page_count: doc.pages.len(),
+                            bytes,
+                        })
+                    });
+                let failed = record.is_err();
+                if !failed {
+                    progress.record_done(index);
+                }
+                // A send error means the stream was dropped; stop rendering.
+                if sender.blocking_send(record).is_err() || failed {
+                    return;
+                }
+            }
+            engine.emit_debug_summary("render_many_stream");
+        });
+        RecordPdfStream { receiver }
+    }
+}
+
+fn join_error(err: tokio::task::JoinError) -> FullBleedError {
+    FullBleedError::Io(std::io::Error::other(format!("render task failed: {err}")))
+}
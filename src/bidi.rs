@@ -0,0 +1,149 @@
+// Unicode bidirectional reordering for laid-out lines. Line breaking stays in logical order;
+// each finished line is split into level runs and returned in visual (left-to-right) order.
+// Right-to-left runs keep their logical text so the shaper can join and mirror them; callers
+// fall back to `visual_order` when a run will not be shaped.
+use unicode_bidi::{BidiClass, BidiInfo, Level, bidi_class};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BidiRun {
+    pub(crate) text: String,
+    pub(crate) rtl: bool,
+}
+
+pub(crate) fn is_strong_rtl(ch: char) -> bool {
+    matches!(bidi_class(ch), BidiClass::R | BidiClass::AL)
+}
+
+pub(crate) fn has_strong_rtl(text: &str) -> bool {
+    text.chars().any(is_strong_rtl)
+}
+
+// Plain left-to-right lines skip the algorithm entirely.
+pub(crate) fn needs_reordering(text: &str, rtl: bool) -> bool {
+    !text.is_empty() && (rtl || has_strong_rtl(text))
+}
+
+pub(crate) fn visual_runs(text: &str, rtl: bool) -> Vec<BidiRun> {
+    let base = if rtl { Level::rtl() } else { Level::ltr() };
+    let info = BidiInfo::new(text, Some(base));
+    let mut out = Vec::new();
+    for para in &info.paragraphs {
+        let (levels, runs) = info.visual_runs(para, para.range.clone());
+        for range in runs {
+            if range.is_empty() {
+                continue;
+            }
+            out.push(BidiRun {
+                rtl: levels[range.start].is_rtl(),
+                text: text[range].to_string(),
+            });
+        }
+    }
+    out
+}
+
+// Reverses a right-to-left run into display order and swaps mirrored brackets, for text drawn
+// without shaping (base-14 fonts, neutral-only runs).
+pub(crate) fn visual_order(text: &str) -> String {
+    text.chars().rev().map(mirror).collect()
+}
+
+pub(crate) fn mirror(ch: char) -> char {
+    match ch {
+        '(' => ')',
+        ')' => '(',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '<' => '>',
+        '>' => '<',
+        '«' => '»',
+        '»' => '«',
+        '‹' => '›',
+        '›' => '‹',
+        '⁅' => '⁆',
+        '⁆' => '⁅',
+        '⁽' => '⁾',
+        '⁾' => '⁽',
+        '₍' => '₎',
+        '₎' => '₍',
+        '≤' => '≥',
+        '≥' => '≤',
+        '≪' => '≫',
+        '≫' => '≪',
+        '∈' => '∋',
+        '∋' => '∈',
+        '⊂' => '⊃',
+        '⊃' => '⊂',
+        '⊆' => '⊇',
+        '⊇' => '⊆',
+        '〈' => '〉',
+        '〉' => '〈',
+        '《' => '》',
+        '》' => '《',
+        '「' => '」',
+        '」' => '「',
+        '『' => '』',
+        '』' => '『',
+        '【' => '】',
+        '】' => '【',
+        '（' => '）',
+        '）' => '（',
+        '［' => '］',
+        '］' => '［',
+        '｛' => '｝',
+        '｝' => '｛',
+        _ => ch,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runs(text: &str, rtl: bool) -> Vec<(String, bool)> {
+        visual_runs(text, rtl)
+            .into_iter()
+            .map(|run| (run.text, run.rtl))
+            .collect()
+    }
+
+    #[test]
+    fn embedded_rtl_run_stays_in_place_inside_ltr_line() {
+        assert!(!needs_reordering("plain text", false));
+        assert!(needs_reordering("abc \u{5d0}\u{5d1}\u{5d2} def", false));
+        assert_eq!(
+            runs("abc \u{5d0}\u{5d1}\u{5d2} def", false),
+            vec![
+                ("abc ".to_string(), false),
+                ("\u{5d0}\u{5d1}\u{5d2}".to_string(), true),
+                (" def".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn rtl_line_reverses_runs_and_keeps_numbers_ltr() {
+        let visual = runs("\u{5d0}\u{5d1}\u{5d2} (12)", true);
+        assert_eq!(
+            visual,
+            vec![
+                (")".to_string(), true),
+                ("12".to_string(), false),
+                ("\u{5d0}\u{5d1}\u{5d2} (".to_string(), true),
+            ]
+        );
+        let drawn: String = visual
+            .iter()
+            .map(|(text, rtl)| {
+                if *rtl {
+                    visual_order(text)
+                } else {
+                    text.clone()
+                }
+            })
+            .collect();
+        assert_eq!(drawn, "(12) \u{5d2}\u{5d1}\u{5d0}");
+    }
+}
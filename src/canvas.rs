@@ -1,11 +1,22 @@
-use crate::flowable::PaintFilterSpec;
+use crate::command_buffer::CommandBuffer;
+use crate::flowable::{Flowable, PaintFilterSpec};
 use crate::types::{Color, MixBlendMode, Pt, Rect, Shading, Size};
+use std::collections::HashSet;
+use std::sync::Arc;
 
 pub const META_FLOWABLE_BBOX_KEY: &str = "__fb_bbox";
 pub const META_PAGINATION_EVENT_KEY: &str = "__fb_pagination_event";
 pub const META_DIAGNOSTIC_SCOPE_BEGIN_KEY: &str = "__fb_diag_scope_begin";
 pub const META_DIAGNOSTIC_SCOPE_END_KEY: &str = "__fb_diag_scope_end";
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkTarget {
+    // Named destination in the same document (for example `href="#intro"`).
+    Internal(String),
+    // External URI opened by the viewer (for example `href="https://example.com"`).
+    Uri(String),
+}
+
 #[derive(Debug, Clone)]
 pub enum Command {
     SaveState,
@@ -52,7 +63,9 @@ pub enum Command {
         radius: Pt,
         filter: PaintFilterSpec,
     },
-    SetFontName(String),
+    // Font names and resource ids are interned per canvas: every page naming the same font
+    // or image shares one allocation.
+    SetFontName(Arc<str>),
     SetFontSize(Pt),
     ClipRect {
         x: Pt,
@@ -125,20 +138,20 @@ pub enum Command {
         y: Pt,
         width: Pt,
         height: Pt,
-        resource_id: String,
+        resource_id: Arc<str>,
     },
     DefineForm {
-        resource_id: String,
+        resource_id: Arc<str>,
         width: Pt,
         height: Pt,
-        commands: Vec<Command>,
+        commands: CommandBuffer,
     },
     DrawForm {
         x: Pt,
         y: Pt,
         width: Pt,
         height: Pt,
-        resource_id: String,
+        resource_id: Arc<str>,
     },
     BeginTag {
         role: String,
@@ -157,27 +170,169 @@ pub enum Command {
         name: String,
     },
     EndMarkedContent,
+    // Clickable area in page space (top-left origin). Emitted as a /Link annotation.
+    LinkAnnotation {
+        x: Pt,
+        y: Pt,
+        width: Pt,
+        height: Pt,
+        target: LinkTarget,
+    },
+    // Anchor position used to resolve internal link targets. Exported destinations are
+    // also published in the PDF /Dests name tree. Not painted.
+    NamedDestination {
+        name: String,
+        x: Pt,
+        y: Pt,
+        exported: bool,
+    },
+    // Document outline (bookmark) entry; level 1 is the top of the tree. Not painted.
+    Bookmark {
+        level: u8,
+        title: String,
+        x: Pt,
+        y: Pt,
+    },
+    // Unsigned signature form field whose widget covers the rect, left for a downstream
+    // signing service to fill. Not painted.
+    SignatureField {
+        name: String,
+        x: Pt,
+        y: Pt,
+        width: Pt,
+        height: Pt,
+    },
 }
 
 #[derive(Debug, Clone)]
 pub struct Page {
-    pub commands: Vec<Command>,
+    pub commands: CommandBuffer,
+    // Size of this page when it differs from the document's `page_size`.
+    pub size: Option<Size>,
+    // Clockwise display rotation in degrees (0, 90, 180 or 270). Commands stay in the
+    // upright, displayed page space; the PDF stores the page unrotated with a `/Rotate` entry.
+    pub rotate: u16,
 }
 
 impl Page {
     fn new() -> Self {
         Self {
-            commands: Vec::new(),
+            commands: CommandBuffer::new(),
+            size: None,
+            rotate: 0,
         }
     }
 }
 
+// Normalizes a rotation in degrees to a clockwise multiple of 90 in `0..360`.
+pub fn normalize_rotate(degrees: i32) -> u16 {
+    let quarter_turns = (degrees as f32 / 90.0).round() as i32;
+    (quarter_turns.rem_euclid(4) * 90) as u16
+}
+
 #[derive(Debug, Clone)]
 pub struct Document {
     pub page_size: Size,
     pub pages: Vec<Page>,
 }
 
+impl Document {
+    pub fn page_size_at(&self, index: usize) -> Size {
+        self.pages
+            .get(index)
+            .and_then(|page| page.size)
+            .unwrap_or(self.page_size)
+    }
+
+    pub fn has_mixed_page_sizes(&self) -> bool {
+        self.pages.iter().any(|page| page.size.is_some())
+    }
+
+    // Drops spare command capacity left by passes that appended to finished pages (page
+    // furniture, finalization), for documents that stay queued in memory.
+    pub(crate) fn shrink_to_fit(&mut self) {
+        for page in &mut self.pages {
+            page.commands.shrink_to_fit();
+        }
+    }
+
+    // Approximate heap bytes held by the pages: the packed command buffers, including spare
+    // capacity, plus the text and vectors the commands own. Interned font names and resource
+    // ids count once however many commands share them, so an image drawn on every page (a
+    // data URI logo in a header) is paid for once.
+    pub fn memory_footprint(&self) -> usize {
+        let mut shared = HashSet::new();
+        self.pages
+            .iter()
+            .map(|page| {
+                std::mem::size_of::<Page>() + commands_footprint(&page.commands, &mut shared)
+            })
+            .sum()
+    }
+}
+
+fn commands_footprint(commands: &CommandBuffer, shared: &mut HashSet<*const u8>) -> usize {
+    // Packed commands own no heap data; only the commands kept whole do.
+    let owned: usize = commands
+        .owned()
+        .iter()
+        .map(|command| match command {
+            Command::Meta { key, value } => key.capacity() + value.capacity(),
+            Command::SetFontName(name) => interned_footprint(name, shared),
+            Command::SetDash { pattern, .. } => pattern.capacity() * std::mem::size_of::<Pt>(),
+            Command::ShadingFill(Shading::Axial { stops, .. })
+            | Command::ShadingFill(Shading::Radial { stops, .. }) => {
+                stops.capacity() * std::mem::size_of::<crate::types::ShadingStop>()
+            }
+            Command::DrawString { text, .. } | Command::DrawStringTransformed { text, .. } => {
+                text.capacity()
+            }
+            Command::DrawGlyphRun {
+                glyph_ids,
+                advances,
+                ..
+            } => {
+                glyph_ids.capacity() * std::mem::size_of::<u16>()
+                    + advances.capacity() * std::mem::size_of::<(Pt, Pt)>()
+            }
+            Command::DrawImage { resource_id, .. } | Command::DrawForm { resource_id, .. } => {
+                interned_footprint(resource_id, shared)
+            }
+            Command::DefineForm {
+                resource_id,
+                commands,
+                ..
+            } => interned_footprint(resource_id, shared) + commands_footprint(commands, shared),
+            Command::BeginTag {
+                role, alt, scope, ..
+            } => {
+                role.capacity()
+                    + alt.as_ref().map_or(0, String::capacity)
+                    + scope.as_ref().map_or(0, String::capacity)
+            }
+            Command::BeginArtifact { subtype } => subtype.as_ref().map_or(0, String::capacity),
+            Command::LinkAnnotation { target, .. } => match target {
+                LinkTarget::Internal(value) | LinkTarget::Uri(value) => value.capacity(),
+            },
+            Command::BeginOptionalContent { name }
+            | Command::NamedDestination { name, .. }
+            | Command::SignatureField { name, .. } => name.capacity(),
+            Command::Bookmark { title, .. } => title.capacity(),
+            _ => 0,
+        })
+        .sum();
+    commands.slot_bytes() + owned
+}
+
+fn interned_footprint(value: &Arc<str>, shared: &mut HashSet<*const u8>) -> usize {
+    if shared.insert(value.as_ptr()) {
+        // The strong and weak counts sit in front of the bytes.
+        2 * std::mem::size_of::<usize>() + value.len()
+    } else {
+        0
+    }
+}
+
 #[derive(Debug, Clone)]
 struct GraphicsState {
     fill_color: Color,
@@ -187,26 +342,83 @@ struct GraphicsState {
     line_join: u8,
     blend_mode: MixBlendMode,
     font_size: Pt,
-    font_name: String,
+    font_name: Arc<str>,
+}
+
+// Shared copies of repeated strings (font names, image and form resource ids).
+#[derive(Debug, Default)]
+pub(crate) struct StringTable {
+    strings: HashSet<Arc<str>>,
+}
+
+impl StringTable {
+    pub(crate) fn intern(&mut self, value: &str) -> Arc<str> {
+        if let Some(existing) = self.strings.get(value) {
+            return existing.clone();
+        }
+        let value: Arc<str> = Arc::from(value);
+        self.strings.insert(value.clone());
+        value
+    }
 }
 
 pub struct Canvas {
     page_size: Size,
     pages: Vec<Page>,
     current: Page,
+    // Commands of the page being drawn; packed into the page's buffer when it is shown.
+    commands: Vec<Command>,
     state_stack: Vec<GraphicsState>,
     current_state: GraphicsState,
     current_mcid: u32,
     // Nearest positioned-ancestor containing block stack for out-of-flow absolute placement.
     abs_containing_block_stack: Vec<Rect>,
+    // Footnote bodies referenced since the layout last collected them.
+    footnotes: Vec<Box<dyn Flowable>>,
+    // Absolutely positioned content that ran past the page bottom, continued on the next page.
+    abs_continuations: Vec<AbsContinuation>,
+    // Heights of absolutely positioned content cut off at the page bottom.
+    clipped_overflow: Vec<Pt>,
+    // Open stacking contexts, innermost last; the first one is the page itself.
+    stacking: Vec<StackingLayer>,
+    strings: StringTable,
+}
+
+// z-index layers collected for one stacking context. Negative layers paint under the
+// context's own content (inserted at `base`), positive layers on top of it.
+struct StackingLayer {
+    base: usize,
+    below: Vec<(i32, Vec<Command>)>,
+    above: Vec<(i32, Vec<Command>)>,
+}
+
+impl StackingLayer {
+    fn new(base: usize) -> Self {
+        Self {
+            base,
+            below: Vec::new(),
+            above: Vec::new(),
+        }
+    }
+}
+
+// Rest of an absolutely positioned box, drawn at the same position on the next page.
+pub(crate) struct AbsContinuation {
+    pub(crate) x: Pt,
+    pub(crate) y: Pt,
+    pub(crate) width: Pt,
+    pub(crate) flowable: Box<dyn Flowable>,
 }
 
 impl Canvas {
     pub fn new(page_size: Size) -> Self {
+        let mut strings = StringTable::default();
+        let font_name = strings.intern("Helvetica");
         Self {
             page_size,
             pages: Vec::new(),
             current: Page::new(),
+            commands: Vec::new(),
             state_stack: Vec::new(),
             current_state: GraphicsState {
                 fill_color: Color::BLACK,
@@ -216,15 +428,33 @@ impl Canvas {
                 line_join: 0,
                 blend_mode: MixBlendMode::Normal,
                 font_size: Pt::from_f32(12.0),
-                font_name: "Helvetica".to_string(),
+                font_name,
             },
             current_mcid: 0,
             abs_containing_block_stack: Vec::new(),
+            footnotes: Vec::new(),
+            abs_continuations: Vec::new(),
+            clipped_overflow: Vec::new(),
+            stacking: vec![StackingLayer::new(0)],
+            strings,
         }
     }
 
+    // Size of the page being drawn.
     pub fn page_size(&self) -> Size {
-        self.page_size
+        self.current.size.unwrap_or(self.page_size)
+    }
+
+    // Sets the size of the page being drawn; the document keeps the size the canvas was
+    // created with.
+    pub fn set_page_size(&mut self, size: Size) {
+        let size = size.quantized();
+        self.current.size = (size != self.page_size.quantized()).then_some(size);
+    }
+
+    // Sets the `/Rotate` of the page being drawn.
+    pub fn set_page_rotate(&mut self, rotate: u16) {
+        self.current.rotate = rotate;
     }
 
     pub fn push_abs_containing_block(&mut self, rect: Rect) {
@@ -241,31 +471,97 @@ impl Canvas {
 
     pub fn save_state(&mut self) {
         self.state_stack.push(self.current_state.clone());
-        self.current.commands.push(Command::SaveState);
+        self.commands.push(Command::SaveState);
     }
 
     pub fn restore_state(&mut self) {
         if let Some(state) = self.state_stack.pop() {
             self.current_state = state;
-            self.current.commands.push(Command::RestoreState);
+            self.commands.push(Command::RestoreState);
+        }
+    }
+
+    // Opens a stacking context: z-index layers drawn until the matching
+    // `end_stacking_context` are ordered within it instead of the enclosing one.
+    pub fn begin_stacking_context(&mut self) {
+        let base = self.commands.len();
+        self.stacking.push(StackingLayer::new(base));
+    }
+
+    pub fn end_stacking_context(&mut self) {
+        if self.stacking.len() > 1
+            && let Some(layer) = self.stacking.pop()
+        {
+            self.flush_stacking_layer(layer);
+        }
+    }
+
+    // Draws `draw` as a z-index layer of the innermost stacking context. The layer is a
+    // self-contained q/Q block so it can be moved in the command stream; the cached
+    // graphics state is invalidated so it never relies on state set outside it.
+    pub fn draw_z_layer(&mut self, z_index: i32, draw: impl FnOnce(&mut Canvas)) {
+        if z_index == 0 {
+            draw(self);
+            return;
+        }
+        let start = self.commands.len();
+        self.save_state();
+        self.current_state.fill_color = Color {
+            r: f32::NAN,
+            ..Color::BLACK
+        };
+        self.current_state.stroke_color = self.current_state.fill_color;
+        self.current_state.line_width = Pt::from_f32(-1.0);
+        self.current_state.line_cap = u8::MAX;
+        self.current_state.line_join = u8::MAX;
+        self.current_state.font_size = Pt::from_f32(-1.0);
+        self.current_state.font_name = self.strings.intern("");
+        draw(self);
+        self.restore_state();
+        let commands: Vec<Command> = self.commands.drain(start..).collect();
+        if let Some(layer) = self.stacking.last_mut() {
+            if z_index < 0 {
+                layer.below.push((z_index, commands));
+            } else {
+                layer.above.push((z_index, commands));
+            }
+        }
+    }
+
+    fn flush_stacking_layer(&mut self, layer: StackingLayer) {
+        let StackingLayer {
+            base,
+            mut below,
+            mut above,
+        } = layer;
+        // Stable sorts: equal z-index layers keep document order.
+        below.sort_by_key(|(z, _)| *z);
+        above.sort_by_key(|(z, _)| *z);
+        let base = base.min(self.commands.len());
+        let below: Vec<Command> = below
+            .into_iter()
+            .flat_map(|(_, commands)| commands)
+            .collect();
+        self.commands.splice(base..base, below);
+        for (_, commands) in above {
+            self.commands.extend(commands);
         }
     }
 
     pub fn translate(&mut self, x: Pt, y: Pt) {
-        self.current.commands.push(Command::Translate(x, y));
+        self.commands.push(Command::Translate(x, y));
     }
 
     pub fn scale(&mut self, x: f32, y: f32) {
-        self.current.commands.push(Command::Scale(x, y));
+        self.commands.push(Command::Scale(x, y));
     }
 
     pub fn rotate(&mut self, angle_radians: f32) {
-        self.current.commands.push(Command::Rotate(angle_radians));
+        self.commands.push(Command::Rotate(angle_radians));
     }
 
     pub fn concat_matrix(&mut self, a: f32, b: f32, c: f32, d: f32, e: Pt, f: Pt) {
-        self.current
-            .commands
+        self.commands
             .push(Command::ConcatMatrix { a, b, c, d, e, f });
     }
 
@@ -277,14 +573,14 @@ impl Canvas {
             rect.width.to_milli_i64(),
             rect.height.to_milli_i64()
         );
-        self.current.commands.push(Command::Meta {
+        self.commands.push(Command::Meta {
             key: META_FLOWABLE_BBOX_KEY.to_string(),
             value,
         });
     }
 
     pub fn meta(&mut self, key: impl Into<String>, value: impl Into<String>) {
-        self.current.commands.push(Command::Meta {
+        self.commands.push(Command::Meta {
             key: key.into(),
             value: value.into(),
         });
@@ -295,7 +591,7 @@ impl Canvas {
             return;
         }
         self.current_state.fill_color = color;
-        self.current.commands.push(Command::SetFillColor(color));
+        self.commands.push(Command::SetFillColor(color));
     }
 
     pub fn set_stroke_color(&mut self, color: Color) {
@@ -303,7 +599,7 @@ impl Canvas {
             return;
         }
         self.current_state.stroke_color = color;
-        self.current.commands.push(Command::SetStrokeColor(color));
+        self.commands.push(Command::SetStrokeColor(color));
     }
 
     pub fn set_line_width(&mut self, width: Pt) {
@@ -312,7 +608,7 @@ impl Canvas {
             return;
         }
         self.current_state.line_width = width;
-        self.current.commands.push(Command::SetLineWidth(width));
+        self.commands.push(Command::SetLineWidth(width));
     }
 
     pub fn set_line_cap(&mut self, cap: u8) {
@@ -320,7 +616,7 @@ impl Canvas {
             return;
         }
         self.current_state.line_cap = cap;
-        self.current.commands.push(Command::SetLineCap(cap));
+        self.commands.push(Command::SetLineCap(cap));
     }
 
     pub fn set_line_join(&mut self, join: u8) {
@@ -328,22 +624,20 @@ impl Canvas {
             return;
         }
         self.current_state.line_join = join;
-        self.current.commands.push(Command::SetLineJoin(join));
+        self.commands.push(Command::SetLineJoin(join));
     }
 
     pub fn set_miter_limit(&mut self, limit: Pt) {
         let limit = if limit < Pt::ZERO { Pt::ZERO } else { limit };
-        self.current.commands.push(Command::SetMiterLimit(limit));
+        self.commands.push(Command::SetMiterLimit(limit));
     }
 
     pub fn set_dash(&mut self, pattern: Vec<Pt>, phase: Pt) {
-        self.current
-            .commands
-            .push(Command::SetDash { pattern, phase });
+        self.commands.push(Command::SetDash { pattern, phase });
     }
 
     pub fn set_opacity(&mut self, fill: f32, stroke: f32) {
-        self.current.commands.push(Command::SetOpacity {
+        self.commands.push(Command::SetOpacity {
             fill: fill.clamp(0.0, 1.0),
             stroke: stroke.clamp(0.0, 1.0),
         });
@@ -354,7 +648,7 @@ impl Canvas {
             return;
         }
         self.current_state.blend_mode = mode;
-        self.current.commands.push(Command::SetBlendMode { mode });
+        self.commands.push(Command::SetBlendMode { mode });
     }
 
     pub fn apply_backdrop_filter(
@@ -366,7 +660,7 @@ impl Canvas {
         radius: Pt,
         filter: PaintFilterSpec,
     ) {
-        self.current.commands.push(Command::ApplyBackdropFilter {
+        self.commands.push(Command::ApplyBackdropFilter {
             x,
             y,
             width,
@@ -377,12 +671,11 @@ impl Canvas {
     }
 
     pub fn set_font_name(&mut self, name: &str) {
-        if self.current_state.font_name == name {
+        if *self.current_state.font_name == *name {
             return;
         }
-        self.current_state.font_name = name.to_string();
-        self.current
-            .commands
+        self.current_state.font_name = self.strings.intern(name);
+        self.commands
             .push(Command::SetFontName(self.current_state.font_name.clone()));
     }
 
@@ -391,11 +684,11 @@ impl Canvas {
             return;
         }
         self.current_state.font_size = size;
-        self.current.commands.push(Command::SetFontSize(size));
+        self.commands.push(Command::SetFontSize(size));
     }
 
     pub fn clip_rect(&mut self, x: Pt, y: Pt, width: Pt, height: Pt) {
-        self.current.commands.push(Command::ClipRect {
+        self.commands.push(Command::ClipRect {
             x,
             y,
             width,
@@ -404,23 +697,23 @@ impl Canvas {
     }
 
     pub fn clip_path(&mut self, evenodd: bool) {
-        self.current.commands.push(Command::ClipPath { evenodd });
+        self.commands.push(Command::ClipPath { evenodd });
     }
 
     pub fn shading_fill(&mut self, shading: Shading) {
-        self.current.commands.push(Command::ShadingFill(shading));
+        self.commands.push(Command::ShadingFill(shading));
     }
 
     pub fn move_to(&mut self, x: Pt, y: Pt) {
-        self.current.commands.push(Command::MoveTo { x, y });
+        self.commands.push(Command::MoveTo { x, y });
     }
 
     pub fn line_to(&mut self, x: Pt, y: Pt) {
-        self.current.commands.push(Command::LineTo { x, y });
+        self.commands.push(Command::LineTo { x, y });
     }
 
     pub fn curve_to(&mut self, x1: Pt, y1: Pt, x2: Pt, y2: Pt, x: Pt, y: Pt) {
-        self.current.commands.push(Command::CurveTo {
+        self.commands.push(Command::CurveTo {
             x1,
             y1,
             x2,
@@ -431,31 +724,87 @@ impl Canvas {
     }
 
     pub fn close_path(&mut self) {
-        self.current.commands.push(Command::ClosePath);
+        self.commands.push(Command::ClosePath);
+    }
+
+    // Appends a circular arc around (`cx`, `cy`) from `start` to `end` (radians; with y
+    // pointing down, positive sweeps run clockwise) as cubic segments of at most a quarter
+    // turn. Like the HTML canvas `arc`, it starts with a line from the current point, so
+    // begin the subpath with `move_to` (the arc's first point for a bare arc).
+    pub fn arc(&mut self, cx: Pt, cy: Pt, radius: Pt, start: f32, end: f32) {
+        let (cx, cy, r) = (cx.to_f32(), cy.to_f32(), radius.to_f32());
+        let point = |angle: f32| (cx + r * libm::cosf(angle), cy + r * libm::sinf(angle));
+        let (sx, sy) = point(start);
+        self.line_to(Pt::from_f32(sx), Pt::from_f32(sy));
+        // The epsilon keeps an exact quarter turn from picking up a sliver segment.
+        let quarters = (end - start).abs() / std::f32::consts::FRAC_PI_2;
+        let segments = libm::ceilf(quarters - 1e-4).max(1.0) as usize;
+        let delta = (end - start) / segments as f32;
+        let k = 4.0 / 3.0 * libm::tanf(delta / 4.0) * r;
+        for i in 0..segments {
+            let a0 = start + delta * i as f32;
+            let a1 = a0 + delta;
+            let (x0, y0) = point(a0);
+            let (x1, y1) = point(a1);
+            self.curve_to(
+                Pt::from_f32(x0 - k * libm::sinf(a0)),
+                Pt::from_f32(y0 + k * libm::cosf(a0)),
+                Pt::from_f32(x1 + k * libm::sinf(a1)),
+                Pt::from_f32(y1 - k * libm::cosf(a1)),
+                Pt::from_f32(x1),
+                Pt::from_f32(y1),
+            );
+        }
+    }
+
+    // Appends a closed ellipse subpath centred on (`cx`, `cy`).
+    pub fn ellipse(&mut self, cx: Pt, cy: Pt, rx: Pt, ry: Pt) {
+        const KAPPA: f32 = 0.552_284_8;
+        let kx = rx * KAPPA;
+        let ky = ry * KAPPA;
+        self.move_to(cx + rx, cy);
+        self.curve_to(cx + rx, cy + ky, cx + kx, cy + ry, cx, cy + ry);
+        self.curve_to(cx - kx, cy + ry, cx - rx, cy + ky, cx - rx, cy);
+        self.curve_to(cx - rx, cy - ky, cx - kx, cy - ry, cx, cy - ry);
+        self.curve_to(cx + kx, cy - ry, cx + rx, cy - ky, cx + rx, cy);
+        self.close_path();
+    }
+
+    pub fn circle(&mut self, cx: Pt, cy: Pt, radius: Pt) {
+        self.ellipse(cx, cy, radius, radius);
+    }
+
+    // Appends a closed rectangle subpath without painting it (`draw_rect` fills).
+    pub fn rect_path(&mut self, x: Pt, y: Pt, width: Pt, height: Pt) {
+        self.move_to(x, y);
+        self.line_to(x + width, y);
+        self.line_to(x + width, y + height);
+        self.line_to(x, y + height);
+        self.close_path();
     }
 
     pub fn fill(&mut self) {
-        self.current.commands.push(Command::Fill);
+        self.commands.push(Command::Fill);
     }
 
     pub fn fill_evenodd(&mut self) {
-        self.current.commands.push(Command::FillEvenOdd);
+        self.commands.push(Command::FillEvenOdd);
     }
 
     pub fn stroke(&mut self) {
-        self.current.commands.push(Command::Stroke);
+        self.commands.push(Command::Stroke);
     }
 
     pub fn fill_stroke(&mut self) {
-        self.current.commands.push(Command::FillStroke);
+        self.commands.push(Command::FillStroke);
     }
 
     pub fn fill_stroke_evenodd(&mut self) {
-        self.current.commands.push(Command::FillStrokeEvenOdd);
+        self.commands.push(Command::FillStrokeEvenOdd);
     }
 
     pub fn draw_string(&mut self, x: Pt, y: Pt, text: impl Into<String>) {
-        self.current.commands.push(Command::DrawString {
+        self.commands.push(Command::DrawString {
             x,
             y,
             text: text.into(),
@@ -463,7 +812,7 @@ impl Canvas {
     }
 
     pub fn draw_rect(&mut self, x: Pt, y: Pt, width: Pt, height: Pt) {
-        self.current.commands.push(Command::DrawRect {
+        self.commands.push(Command::DrawRect {
             x,
             y,
             width,
@@ -477,51 +826,51 @@ impl Canvas {
         y: Pt,
         width: Pt,
         height: Pt,
-        resource_id: impl Into<String>,
+        resource_id: impl AsRef<str>,
     ) {
-        self.current.commands.push(Command::DrawImage {
+        self.commands.push(Command::DrawImage {
             x,
             y,
             width,
             height,
-            resource_id: resource_id.into(),
+            resource_id: self.strings.intern(resource_id.as_ref()),
         });
     }
 
     pub fn define_form(
         &mut self,
-        resource_id: impl Into<String>,
+        resource_id: impl AsRef<str>,
         width: Pt,
         height: Pt,
-        commands: Vec<Command>,
+        commands: CommandBuffer,
     ) {
-        self.current.commands.push(Command::DefineForm {
-            resource_id: resource_id.into(),
+        self.commands.push(Command::DefineForm {
+            resource_id: self.strings.intern(resource_id.as_ref()),
             width,
             height,
             commands,
         });
     }
 
-    pub fn draw_form(
-        &mut self,
-        x: Pt,
-        y: Pt,
-        width: Pt,
-        height: Pt,
-        resource_id: impl Into<String>,
-    ) {
-        self.current.commands.push(Command::DrawForm {
+    pub fn draw_form(&mut self, x: Pt, y: Pt, width: Pt, height: Pt, resource_id: impl AsRef<str>) {
+        self.commands.push(Command::DrawForm {
             x,
             y,
             width,
             height,
-            resource_id: resource_id.into(),
+            resource_id: self.strings.intern(resource_id.as_ref()),
         });
     }
 
     pub fn show_page(&mut self) {
-        let current = std::mem::replace(&mut self.current, Page::new());
+        while let Some(layer) = self.stacking.pop() {
+            self.flush_stacking_layer(layer);
+        }
+        self.stacking.push(StackingLayer::new(0));
+        let mut current = std::mem::replace(&mut self.current, Page::new());
+        current.commands = std::mem::take(&mut self.commands).into();
+        // Finished pages are never appended to again; drop the growth slack.
+        current.commands.shrink_to_fit();
         self.pages.push(current);
         self.state_stack.clear();
         self.current_state = GraphicsState {
@@ -532,7 +881,7 @@ impl Canvas {
             line_join: 0,
             blend_mode: MixBlendMode::Normal,
             font_size: Pt::from_f32(12.0),
-            font_name: "Helvetica".to_string(),
+            font_name: self.strings.intern("Helvetica"),
         };
         self.current_mcid = 0;
     }
@@ -553,7 +902,7 @@ impl Canvas {
             self.current_mcid = self.current_mcid.saturating_add(1);
             Some(mcid)
         };
-        self.current.commands.push(Command::BeginTag {
+        self.commands.push(Command::BeginTag {
             role: role.into(),
             mcid,
             alt,
@@ -566,35 +915,123 @@ impl Canvas {
     }
 
     pub fn end_tag(&mut self) {
-        self.current.commands.push(Command::EndTag);
+        self.commands.push(Command::EndTag);
     }
 
     pub fn begin_artifact(&mut self, subtype: Option<String>) {
-        self.current
-            .commands
-            .push(Command::BeginArtifact { subtype });
+        self.commands.push(Command::BeginArtifact { subtype });
     }
 
     pub fn begin_optional_content(&mut self, name: impl Into<String>) {
-        self.current
-            .commands
+        self.commands
             .push(Command::BeginOptionalContent { name: name.into() });
     }
 
     pub fn end_marked_content(&mut self) {
-        self.current.commands.push(Command::EndMarkedContent);
+        self.commands.push(Command::EndMarkedContent);
+    }
+
+    pub fn link_annotation(&mut self, x: Pt, y: Pt, width: Pt, height: Pt, target: LinkTarget) {
+        if width <= Pt::ZERO || height <= Pt::ZERO {
+            return;
+        }
+        self.commands.push(Command::LinkAnnotation {
+            x,
+            y,
+            width,
+            height,
+            target,
+        });
+    }
+
+    pub fn named_destination(&mut self, name: impl Into<String>, x: Pt, y: Pt) {
+        self.commands.push(Command::NamedDestination {
+            name: name.into(),
+            x,
+            y,
+            exported: false,
+        });
+    }
+
+    pub fn exported_destination(&mut self, name: impl Into<String>, x: Pt, y: Pt) {
+        self.commands.push(Command::NamedDestination {
+            name: name.into(),
+            x,
+            y,
+            exported: true,
+        });
+    }
+
+    pub fn signature_field(
+        &mut self,
+        name: impl Into<String>,
+        x: Pt,
+        y: Pt,
+        width: Pt,
+        height: Pt,
+    ) {
+        self.commands.push(Command::SignatureField {
+            name: name.into(),
+            x,
+            y,
+            width,
+            height,
+        });
+    }
+
+    pub fn bookmark(&mut self, level: u8, title: impl Into<String>, x: Pt, y: Pt) {
+        self.commands.push(Command::Bookmark {
+            level: level.max(1),
+            title: title.into(),
+            x,
+            y,
+        });
+    }
+
+    // Queue a footnote body for the page layout, which places it at the bottom of the frame
+    // that holds the reference.
+    pub(crate) fn defer_footnote(&mut self, body: Box<dyn Flowable>) {
+        self.footnotes.push(body);
+    }
+
+    pub(crate) fn take_footnotes(&mut self) -> Vec<Box<dyn Flowable>> {
+        std::mem::take(&mut self.footnotes)
+    }
+
+    pub(crate) fn defer_abs_continuation(&mut self, continuation: AbsContinuation) {
+        self.abs_continuations.push(continuation);
+    }
+
+    pub(crate) fn take_abs_continuations(&mut self) -> Vec<AbsContinuation> {
+        std::mem::take(&mut self.abs_continuations)
+    }
+
+    pub(crate) fn has_abs_continuations(&self) -> bool {
+        !self.abs_continuations.is_empty()
+    }
+
+    pub(crate) fn record_clipped_overflow(&mut self, height: Pt) {
+        self.clipped_overflow.push(height);
+    }
+
+    pub(crate) fn take_clipped_overflow(&mut self) -> Vec<Pt> {
+        std::mem::take(&mut self.clipped_overflow)
     }
 
     pub fn current_command_count(&self) -> usize {
-        self.current.commands.len()
+        self.commands.len()
     }
 
     pub fn is_current_empty(&self) -> bool {
-        self.current.commands.is_empty()
+        self.commands.is_empty()
+            && self
+                .stacking
+                .iter()
+                .all(|layer| layer.below.is_empty() && layer.above.is_empty())
     }
 
     pub fn finish(mut self) -> Document {
-        if !self.current.commands.is_empty() || self.pages.is_empty() {
+        if !self.is_current_empty() || self.pages.is_empty() {
             self.show_page();
         }
         Document {
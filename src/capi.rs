@@ -0,0 +1,472 @@
+// C ABI (feature `capi`) for hosts that embed the engine without Python: .NET P/Invoke,
+// Node ffi, plain C. Engines are opaque handles built through a builder handle; every call
+// returns an `FbStatus` and leaves a message for `fb_last_error`. The matching declarations
+// are in `include/fullbleed.h`; keep the two in step.
+use crate::{
+    ErrorCode, FullBleed, FullBleedBuilder, FullBleedError, Margins, PdfProfile, Pt, Size,
+};
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::path::Path;
+
+// Bumped whenever a signature or `FbStatus` value changes meaning; additions keep it.
+pub const FB_ABI_VERSION: u32 = 1;
+
+// Result of every C API call. Values are stable; new codes are only appended.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FbStatus {
+    Ok = 0,
+    // A null handle or pointer, a string that is not UTF-8, or an out-of-range value.
+    InvalidArgument = 1,
+    InvalidConfiguration = 2,
+    Io = 3,
+    Asset = 4,
+    // The document could not be laid out (unplaceable content, no page template, ...).
+    Layout = 5,
+    AuditGateFailed = 6,
+    // The engine panicked; the handle stays usable but the call produced nothing.
+    Panic = 7,
+    // A render under strict fidelity hit a known loss.
+    FidelityLoss = 8,
+}
+
+// Bytes owned by the engine; release with `fb_buffer_free`.
+#[repr(C)]
+pub struct FbBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+// Opaque builder handle from `fb_builder_new`.
+pub struct FbBuilder {
+    // Taken by `fb_builder_build`; builder methods consume `self`.
+    inner: Option<FullBleedBuilder>,
+}
+
+// Opaque engine handle from `fb_builder_build`. Safe to share across threads.
+pub struct FbEngine {
+    engine: FullBleed,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+type CallResult = Result<(), (FbStatus, String)>;
+
+fn status_for(err: &FullBleedError) -> FbStatus {
+    match err.code() {
+        ErrorCode::InvalidConfiguration | ErrorCode::InvalidLength | ErrorCode::Css => {
+            FbStatus::InvalidConfiguration
+        }
+        ErrorCode::Io => FbStatus::Io,
+        ErrorCode::Asset | ErrorCode::FontNotFound | ErrorCode::InvalidFont => FbStatus::Asset,
+        ErrorCode::AuditGateFailed => FbStatus::AuditGateFailed,
+        ErrorCode::FidelityLoss => FbStatus::FidelityLoss,
+        ErrorCode::MissingPageTemplate
+        | ErrorCode::UnplaceableContent
+        | ErrorCode::EmptyDocumentSet
+        | ErrorCode::InconsistentPageSize => FbStatus::Layout,
+    }
+}
+
+fn engine_err(err: FullBleedError) -> (FbStatus, String) {
+    (status_for(&err), err.to_string())
+}
+
+fn invalid(message: &str) -> (FbStatus, String) {
+    (FbStatus::InvalidArgument, message.to_string())
+}
+
+// Runs one call with panics contained and records its error message for `fb_last_error`.
+fn guard(call: impl FnOnce() -> CallResult) -> FbStatus {
+    let (status, message) = match catch_unwind(AssertUnwindSafe(call)) {
+        Ok(Ok(())) => (FbStatus::Ok, None),
+        Ok(Err((status, message))) => (status, Some(message)),
+        Err(_) => (FbStatus::Panic, Some("engine panicked".to_string())),
+    };
+    LAST_ERROR.with(|slot| {
+        *slot.borrow_mut() = message.map(|text| {
+            CString::new(text.replace('\0', " ")).unwrap_or_else(|_| CString::default())
+        });
+    });
+    status
+}
+
+unsafe fn str_arg<'a>(ptr: *const c_char, name: &str) -> Result<&'a str, (FbStatus, String)> {
+    if ptr.is_null() {
+        return Err(invalid(&format!("{name} is null")));
+    }
+    unsafe { CStr::from_ptr(ptr) }
+        .to_str()
+        .map_err(|_| invalid(&format!("{name} is not valid UTF-8")))
+}
+
+unsafe fn builder_arg<'a>(
+    builder: *mut FbBuilder,
+) -> Result<&'a mut FbBuilder, (FbStatus, String)> {
+    match unsafe { builder.as_mut() } {
+        Some(builder) if builder.inner.is_some() => Ok(builder),
+        _ => Err(invalid("builder is null or already built")),
+    }
+}
+
+unsafe fn engine_arg<'a>(engine: *const FbEngine) -> Result<&'a FullBleed, (FbStatus, String)> {
+    unsafe { engine.as_ref() }
+        .map(|handle| &handle.engine)
+        .ok_or_else(|| invalid("engine is null"))
+}
+
+fn update(builder: &mut FbBuilder, f: impl FnOnce(FullBleedBuilder) -> FullBleedBuilder) {
+    if let Some(inner) = builder.inner.take() {
+        builder.inner = Some(f(inner));
+    }
+}
+
+unsafe fn write_buffer(out: *mut FbBuffer, bytes: Vec<u8>) -> CallResult {
+    let Some(out) = (unsafe { out.as_mut() }) else {
+        return Err(invalid("out is null"));
+    };
+    let bytes = bytes.into_boxed_slice();
+    out.len = bytes.len();
+    out.data = Box::into_raw(bytes).cast::<u8>();
+    Ok(())
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn fb_abi_version() -> u32 {
+    FB_ABI_VERSION
+}
+
+// Message of the last failed call on this thread, or null after a successful call.
+// The pointer stays valid until the next C API call on the same thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn fb_last_error() -> *const c_char {
+    LAST_ERROR.with(|slot| {
+        slot.borrow()
+            .as_ref()
+            .map_or(std::ptr::null(), |message| message.as_ptr())
+    })
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn fb_builder_new() -> *mut FbBuilder {
+    Box::into_raw(Box::new(FbBuilder {
+        inner: Some(FullBleed::builder()),
+    }))
+}
+
+// # Safety
+// `builder` must come from `fb_builder_new` and not have been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fb_builder_page_size(
+    builder: *mut FbBuilder,
+    width_pt: f32,
+    height_pt: f32,
+) -> FbStatus {
+    guard(|| {
+        let builder = unsafe { builder_arg(builder) }?;
+        if !(width_pt > 0.0 && height_pt > 0.0) {
+            return Err(invalid("page size must be positive"));
+        }
+        update(builder, |inner| {
+            inner.page_size(Size {
+                width: Pt::from_f32(width_pt),
+                height: Pt::from_f32(height_pt),
+            })
+        });
+        Ok(())
+    })
+}
+
+// # Safety
+// `builder` must come from `fb_builder_new` and not have been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fb_builder_margins(
+    builder: *mut FbBuilder,
+    top_pt: f32,
+    right_pt: f32,
+    bottom_pt: f32,
+    left_pt: f32,
+) -> FbStatus {
+    guard(|| {
+        let builder = unsafe { builder_arg(builder) }?;
+        update(builder, |inner| {
+            inner.margins(Margins {
+                top: Pt::from_f32(top_pt),
+                right: Pt::from_f32(right_pt),
+                bottom: Pt::from_f32(bottom_pt),
+                left: Pt::from_f32(left_pt),
+            })
+        });
+        Ok(())
+    })
+}
+
+// # Safety
+// `builder` must come from `fb_builder_new`; `path` must be a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fb_builder_register_font_file(
+    builder: *mut FbBuilder,
+    path: *const c_char,
+) -> FbStatus {
+    guard(|| {
+        let builder = unsafe { builder_arg(builder) }?;
+        let path = unsafe { str_arg(path, "path") }?;
+        update(builder, |inner| inner.register_font_file(path));
+        Ok(())
+    })
+}
+
+// # Safety
+// `builder` must come from `fb_builder_new`; `path` must be a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fb_builder_register_font_dir(
+    builder: *mut FbBuilder,
+    path: *const c_char,
+) -> FbStatus {
+    guard(|| {
+        let builder = unsafe { builder_arg(builder) }?;
+        let path = unsafe { str_arg(path, "path") }?;
+        update(builder, |inner| inner.register_font_dir(path));
+        Ok(())
+    })
+}
+
+// # Safety
+// `builder` must come from `fb_builder_new`; `family` must be a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fb_builder_font_fallback(
+    builder: *mut FbBuilder,
+    family: *const c_char,
+) -> FbStatus {
+    guard(|| {
+        let builder = unsafe { builder_arg(builder) }?;
+        let family = unsafe { str_arg(family, "family") }?;
+        update(builder, |inner| inner.font_fallback(family));
+        Ok(())
+    })
+}
+
+// `profile`: 0 none, 1 PDF/A-2b, 2 PDF/A-3b, 3 PDF/X-4, 4 tagged.
+//
+// # Safety
+// `builder` must come from `fb_builder_new` and not have been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fb_builder_pdf_profile(builder: *mut FbBuilder, profile: u32) -> FbStatus {
+    guard(|| {
+        let builder = unsafe { builder_arg(builder) }?;
+        let profile = match profile {
+            0 => PdfProfile::None,
+            1 => PdfProfile::PdfA2b,
+            2 => PdfProfile::PdfA3b,
+            3 => PdfProfile::PdfX4,
+            4 => PdfProfile::Tagged,
+            _ => return Err(invalid("unknown pdf profile")),
+        };
+        update(builder, |inner| inner.pdf_profile(profile));
+        Ok(())
+    })
+}
+
+// Builds the engine into `*out_engine`. The builder is spent either way; it still has to
+// be released with `fb_builder_free`.
+//
+// # Safety
+// `builder` must come from `fb_builder_new`; `out_engine` must be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fb_builder_build(
+    builder: *mut FbBuilder,
+    out_engine: *mut *mut FbEngine,
+) -> FbStatus {
+    guard(|| {
+        let builder = unsafe { builder_arg(builder) }?;
+        if out_engine.is_null() {
+            return Err(invalid("out_engine is null"));
+        }
+        let inner = builder.inner.take().expect("checked by builder_arg");
+        let engine = inner.build().map_err(engine_err)?;
+        unsafe { *out_engine = Box::into_raw(Box::new(FbEngine { engine })) };
+        Ok(())
+    })
+}
+
+// # Safety
+// `builder` must be null or come from `fb_builder_new`, and is invalid afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fb_builder_free(builder: *mut FbBuilder) {
+    if !builder.is_null() {
+        drop(unsafe { Box::from_raw(builder) });
+    }
+}
+
+// # Safety
+// `engine` must be null or come from `fb_builder_build`, and is invalid afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fb_engine_free(engine: *mut FbEngine) {
+    if !engine.is_null() {
+        drop(unsafe { Box::from_raw(engine) });
+    }
+}
+
+// Renders one document into `*out`.
+//
+// # Safety
+// `engine` must come from `fb_builder_build`; `html` and `css` must be NUL-terminated
+// strings; `out` must be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fb_render_to_buffer(
+    engine: *const FbEngine,
+    html: *const c_char,
+    css: *const c_char,
+    out: *mut FbBuffer,
+) -> FbStatus {
+    guard(|| {
+        let engine = unsafe { engine_arg(engine) }?;
+        let html = unsafe { str_arg(html, "html") }?;
+        let css = unsafe { str_arg(css, "css") }?;
+        let pdf = engine.render_to_buffer(html, css).map_err(engine_err)?;
+        unsafe { write_buffer(out, pdf) }
+    })
+}
+
+// Renders `count` documents sharing `css` into one merged PDF in `*out`.
+//
+// # Safety
+// `engine` must come from `fb_builder_build`; `html_list` must point to `count`
+// NUL-terminated strings; `css` must be a NUL-terminated string; `out` must be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fb_render_many(
+    engine: *const FbEngine,
+    html_list: *const *const c_char,
+    count: usize,
+    css: *const c_char,
+    out: *mut FbBuffer,
+) -> FbStatus {
+    guard(|| {
+        let engine = unsafe { engine_arg(engine) }?;
+        if html_list.is_null() || count == 0 {
+            return Err(invalid("html_list is null or empty"));
+        }
+        let pointers = unsafe { std::slice::from_raw_parts(html_list, count) };
+        let html_list = pointers
+            .iter()
+            .map(|ptr| unsafe { str_arg(*ptr, "html_list entry") }.map(str::to_string))
+            .collect::<Result<Vec<_>, _>>()?;
+        let css = unsafe { str_arg(css, "css") }?;
+        let pdf = engine
+            .render_many_to_buffer(&html_list, css)
+            .map_err(engine_err)?;
+        unsafe { write_buffer(out, pdf) }
+    })
+}
+
+// Stamps each page of `overlay_path` onto the matching page of `template_path` (offset by
+// `dx`, `dy` points) and writes `out_path`. `pages_written` may be null.
+//
+// # Safety
+// The paths must be NUL-terminated strings; `pages_written` must be null or writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fb_compose(
+    template_path: *const c_char,
+    overlay_path: *const c_char,
+    out_path: *const c_char,
+    dx: f32,
+    dy: f32,
+    pages_written: *mut usize,
+) -> FbStatus {
+    guard(|| {
+        let template = unsafe { str_arg(template_path, "template_path") }?;
+        let overlay = unsafe { str_arg(overlay_path, "overlay_path") }?;
+        let out = unsafe { str_arg(out_path, "out_path") }?;
+        let summary = crate::stamp_overlay_on_template_pdf(
+            Path::new(template),
+            Path::new(overlay),
+            Path::new(out),
+            None,
+            dx,
+            dy,
+        )
+        .map_err(engine_err)?;
+        if let Some(pages_written) = unsafe { pages_written.as_mut() } {
+            *pages_written = summary.pages_written;
+        }
+        Ok(())
+    })
+}
+
+// # Safety
+// `buffer` must be zeroed or filled by this library, and not freed before.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fb_buffer_free(buffer: FbBuffer) {
+    if !buffer.data.is_null() {
+        let slice = std::ptr::slice_from_raw_parts_mut(buffer.data, buffer.len);
+        drop(unsafe { Box::from_raw(slice) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn c_api_builds_renders_and_reports_errors() {
+        unsafe {
+            let builder = fb_builder_new();
+            assert_eq!(fb_builder_page_size(builder, 612.0, 792.0), FbStatus::Ok);
+            assert_eq!(
+                fb_builder_pdf_profile(builder, 9),
+                FbStatus::InvalidArgument
+            );
+            assert!(!fb_last_error().is_null());
+            let mut engine: *mut FbEngine = std::ptr::null_mut();
+            assert_eq!(fb_builder_build(builder, &mut engine), FbStatus::Ok);
+            assert!(fb_last_error().is_null());
+            assert_eq!(
+                fb_builder_build(builder, &mut engine),
+                FbStatus::InvalidArgument
+            );
+            fb_builder_free(builder);
+
+            let html = CString::new("<p>Hello</p>").unwrap();
+            let css = CString::new("p { color: red; }").unwrap();
+            let mut out = FbBuffer {
+                data: std::ptr::null_mut(),
+                len: 0,
+            };
+            assert_eq!(
+                fb_render_to_buffer(engine, html.as_ptr(), css.as_ptr(), &mut out),
+                FbStatus::Ok
+            );
+            let pdf = std::slice::from_raw_parts(out.data, out.len);
+            assert!(pdf.starts_with(b"%PDF-"));
+            fb_buffer_free(out);
+
+            let list = [html.as_ptr(), html.as_ptr()];
+            let mut merged = FbBuffer {
+                data: std::ptr::null_mut(),
+                len: 0,
+            };
+            assert_eq!(
+                fb_render_many(engine, list.as_ptr(), list.len(), css.as_ptr(), &mut merged),
+                FbStatus::Ok
+            );
+            assert!(merged.len > 0);
+            fb_buffer_free(merged);
+
+            let mut out = FbBuffer {
+                data: std::ptr::null_mut(),
+                len: 0,
+            };
+            assert_eq!(
+                fb_render_to_buffer(engine, std::ptr::null(), css.as_ptr(), &mut out),
+                FbStatus::InvalidArgument
+            );
+            let message = CStr::from_ptr(fb_last_error()).to_str().unwrap();
+            assert_eq!(message, "html is null");
+            fb_engine_free(engine);
+        }
+    }
+}
@@ -0,0 +1,666 @@
+use std::sync::Arc;
+
+use crate::canvas::Canvas;
+use crate::flowable::{Flowable, Pagination, Paragraph, TextAlign, TextStyle, huge_pt};
+use crate::font::FontRegistry;
+use crate::types::{Color, Pt, Size};
+
+// Small vector chart subsystem: grouped bars, lines and a pie drawn straight to canvas
+// commands, with a value axis, category labels, an optional title and a legend. Labels are
+// laid out as paragraphs in the chart's TextStyle, so fonts, fallbacks and colors resolve the
+// same way as body text.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChartKind {
+    Bar,
+    Line,
+    Pie,
+}
+
+impl ChartKind {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "bar" | "column" => Some(Self::Bar),
+            "line" => Some(Self::Line),
+            "pie" => Some(Self::Pie),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChartSeries {
+    pub name: String,
+    pub values: Vec<f32>,
+    pub color: Option<Color>,
+}
+
+impl ChartSeries {
+    pub fn new(name: impl Into<String>, values: Vec<f32>) -> Self {
+        Self {
+            name: name.into(),
+            values,
+            color: None,
+        }
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+}
+
+// Series colors used when a series (or pie slice) has none of its own.
+const DEFAULT_PALETTE: [(f32, f32, f32); 8] = [
+    (0.306, 0.475, 0.655),
+    (0.949, 0.557, 0.169),
+    (0.882, 0.341, 0.349),
+    (0.463, 0.718, 0.698),
+    (0.349, 0.631, 0.310),
+    (0.929, 0.788, 0.282),
+    (0.690, 0.478, 0.631),
+    (0.612, 0.459, 0.373),
+];
+
+// Target number of value-axis intervals; the scale rounds to 1/2/5 steps around it.
+const TARGET_TICKS: f32 = 5.0;
+
+#[derive(Debug, Clone)]
+pub struct ChartFlowable {
+    kind: ChartKind,
+    width: Pt,
+    height: Pt,
+    categories: Vec<String>,
+    series: Vec<ChartSeries>,
+    title: Option<String>,
+    // `None` shows the legend for pies and multi-series charts.
+    legend: Option<bool>,
+    text_style: TextStyle,
+    axis_color: Color,
+    palette: Vec<Color>,
+    font_registry: Option<Arc<FontRegistry>>,
+    tag_role: Option<Arc<str>>,
+    alt: Option<String>,
+    pagination: Pagination,
+}
+
+impl ChartFlowable {
+    pub fn new(kind: ChartKind, width: f32, height: f32) -> Self {
+        Self::new_pt(kind, Pt::from_f32(width), Pt::from_f32(height))
+    }
+
+    pub fn new_pt(kind: ChartKind, width: Pt, height: Pt) -> Self {
+        let text_style = TextStyle {
+            font_size: Pt::from_f32(8.0),
+            line_height: Pt::from_f32(9.6),
+            ..TextStyle::default()
+        };
+        Self {
+            kind,
+            width: width.max(Pt::ZERO),
+            height: height.max(Pt::ZERO),
+            categories: Vec::new(),
+            series: Vec::new(),
+            title: None,
+            legend: None,
+            axis_color: text_style.color,
+            text_style,
+            palette: DEFAULT_PALETTE
+                .iter()
+                .map(|&(r, g, b)| Color::rgb(r, g, b))
+                .collect(),
+            font_registry: None,
+            tag_role: Some(Arc::from("Figure")),
+            alt: None,
+            pagination: Pagination::default(),
+        }
+    }
+
+    pub fn with_categories(mut self, categories: Vec<String>) -> Self {
+        self.categories = categories;
+        self
+    }
+
+    pub fn with_series(mut self, series: ChartSeries) -> Self {
+        self.series.push(series);
+        self
+    }
+
+    pub fn with_title(mut self, title: Option<String>) -> Self {
+        self.title = title.filter(|v| !v.trim().is_empty());
+        self
+    }
+
+    pub fn with_legend(mut self, legend: bool) -> Self {
+        self.legend = Some(legend);
+        self
+    }
+
+    // Font, size and color for titles and labels; axes and gridlines follow the text color
+    // unless `with_axis_color` overrides it.
+    pub fn with_text_style(mut self, text_style: TextStyle) -> Self {
+        self.axis_color = text_style.color;
+        self.text_style = text_style;
+        self
+    }
+
+    pub fn with_axis_color(mut self, color: Color) -> Self {
+        self.axis_color = color;
+        self
+    }
+
+    pub fn with_palette(mut self, palette: Vec<Color>) -> Self {
+        if !palette.is_empty() {
+            self.palette = palette;
+        }
+        self
+    }
+
+    pub fn with_pagination(mut self, pagination: Pagination) -> Self {
+        self.pagination = pagination;
+        self
+    }
+
+    pub fn with_tag_role(mut self, role: impl Into<Arc<str>>) -> Self {
+        self.tag_role = Some(role.into());
+        self
+    }
+
+    pub fn with_alt(mut self, alt: Option<String>) -> Self {
+        self.alt = alt.filter(|v| !v.trim().is_empty());
+        self
+    }
+
+    pub(crate) fn with_font_registry(mut self, registry: Option<Arc<FontRegistry>>) -> Self {
+        self.font_registry = registry;
+        self
+    }
+
+    fn palette_color(&self, index: usize) -> Color {
+        self.palette[index % self.palette.len()]
+    }
+
+    fn series_color(&self, index: usize) -> Color {
+        self.series[index]
+            .color
+            .unwrap_or_else(|| self.palette_color(index))
+    }
+
+    fn shows_legend(&self) -> bool {
+        self.legend
+            .unwrap_or(self.kind == ChartKind::Pie || self.series.len() > 1)
+    }
+
+    // (label, color) pairs: pie slices are the categories, other charts list the series.
+    fn legend_entries(&self) -> Vec<(String, Color)> {
+        if self.kind == ChartKind::Pie {
+            let slices = self.series.first().map_or(0, |s| s.values.len());
+            (0..slices)
+                .map(|i| {
+                    let name = self.categories.get(i).cloned().unwrap_or_default();
+                    (name, self.palette_color(i))
+                })
+                .collect()
+        } else {
+            (0..self.series.len())
+                .map(|i| (self.series[i].name.clone(), self.series_color(i)))
+                .collect()
+        }
+    }
+
+    fn label(&self, text: &str, align: TextAlign) -> Paragraph {
+        Paragraph::new(text)
+            .with_style(self.text_style.clone())
+            .with_align(align)
+            .with_whitespace(false, true)
+            .with_font_registry(self.font_registry.clone())
+    }
+
+    fn label_width(&self, text: &str) -> f32 {
+        self.label(text, TextAlign::Left)
+            .intrinsic_width()
+            .unwrap_or(Pt::ZERO)
+            .to_f32()
+    }
+
+    fn line_height(&self) -> f32 {
+        self.text_style
+            .line_height
+            .max(self.text_style.font_size)
+            .to_f32()
+    }
+
+    fn draw_label(&self, canvas: &mut Canvas, text: &str, area: (f32, f32, f32), align: TextAlign) {
+        let (x, y, width) = area;
+        if text.is_empty() || width <= 0.0 {
+            return;
+        }
+        let width = Pt::from_f32(width);
+        let label = self.label(text, align);
+        let height = label.wrap(width, huge_pt()).height;
+        label.draw(canvas, Pt::from_f32(x), Pt::from_f32(y), width, height);
+    }
+
+    // Draws the legend rows bottom-aligned at `bottom`; returns the height they take.
+    fn draw_legend(&self, canvas: &mut Canvas, x: f32, bottom: f32, width: f32) -> f32 {
+        let entries = self.legend_entries();
+        if entries.is_empty() {
+            return 0.0;
+        }
+        let font_size = self.text_style.font_size.to_f32();
+        let line_height = self.line_height();
+        let swatch = font_size * 0.8;
+        let gap = font_size * 0.5;
+        let mut rows: Vec<Vec<(f32, &str, Color)>> = vec![Vec::new()];
+        let mut cursor = 0.0;
+        for (name, color) in &entries {
+            let item_width = swatch + gap * 0.5 + self.label_width(name);
+            if cursor > 0.0 && cursor + item_width > width {
+                rows.push(Vec::new());
+                cursor = 0.0;
+            }
+            rows.last_mut()
+                .expect("legend row")
+                .push((cursor, name.as_str(), *color));
+            cursor += item_width + gap * 1.5;
+        }
+        let height = line_height * rows.len() as f32;
+        let top = bottom - height;
+        for (row_index, row) in rows.iter().enumerate() {
+            let row_width = row
+                .last()
+                .map(|(offset, name, _)| offset + swatch + gap * 0.5 + self.label_width(name))
+                .unwrap_or(0.0);
+            let row_x = x + ((width - row_width) * 0.5).max(0.0);
+            let row_y = top + line_height * row_index as f32;
+            for (offset, name, color) in row {
+                canvas.set_fill_color(*color);
+                canvas.draw_rect(
+                    Pt::from_f32(row_x + offset),
+                    Pt::from_f32(row_y + (line_height - swatch) * 0.5),
+                    Pt::from_f32(swatch),
+                    Pt::from_f32(swatch),
+                );
+                let text_x = row_x + offset + swatch + gap * 0.5;
+                self.draw_label(
+                    canvas,
+                    name,
+                    (text_x, row_y, x + width - text_x),
+                    TextAlign::Left,
+                );
+            }
+        }
+        height
+    }
+
+    fn value_range(&self) -> (f32, f32) {
+        let mut min = 0.0f32;
+        let mut max = 0.0f32;
+        for value in self.series.iter().flat_map(|s| s.values.iter()) {
+            if value.is_finite() {
+                min = min.min(*value);
+                max = max.max(*value);
+            }
+        }
+        (min, max)
+    }
+
+    fn slot_count(&self) -> usize {
+        let longest = self.series.iter().map(|s| s.values.len()).max();
+        longest.unwrap_or(0).max(self.categories.len())
+    }
+
+    fn draw_axes_chart(&self, canvas: &mut Canvas, x: f32, top: f32, width: f32, bottom: f32) {
+        let slots = self.slot_count();
+        if slots == 0 {
+            return;
+        }
+        let font_size = self.text_style.font_size.to_f32();
+        let line_height = self.line_height();
+        let gap = font_size * 0.5;
+        let (lo, hi, step) = nice_scale(self.value_range());
+        let ticks: Vec<f32> = (0..)
+            .map(|i| lo + step * i as f32)
+            .take_while(|v| *v <= hi + step * 0.5)
+            .collect();
+        let tick_labels: Vec<String> = ticks.iter().map(|v| format_tick(*v, step)).collect();
+        let axis_width = tick_labels
+            .iter()
+            .map(|label| self.label_width(label))
+            .fold(0.0, f32::max)
+            + gap;
+        let category_height = if self.categories.is_empty() {
+            0.0
+        } else {
+            line_height + gap * 0.5
+        };
+        let plot_x = x + axis_width;
+        let plot_y = top + line_height * 0.5;
+        let plot_w = width - axis_width - gap * 0.5;
+        let plot_h = bottom - category_height - plot_y;
+        if plot_w <= 0.0 || plot_h <= 0.0 {
+            return;
+        }
+        let y_of = |value: f32| plot_y + plot_h * (hi - value) / (hi - lo);
+
+        // Gridlines and value labels.
+        let grid = mix_with_white(self.axis_color, 0.8);
+        canvas.set_stroke_color(grid);
+        canvas.set_line_width(Pt::from_f32(0.5));
+        for (value, label) in ticks.iter().zip(&tick_labels) {
+            let y = y_of(*value);
+            canvas.move_to(Pt::from_f32(plot_x), Pt::from_f32(y));
+            canvas.line_to(Pt::from_f32(plot_x + plot_w), Pt::from_f32(y));
+            canvas.stroke();
+            self.draw_label(
+                canvas,
+                label,
+                (x, y - line_height * 0.5, axis_width - gap),
+                TextAlign::Right,
+            );
+        }
+
+        let slot_w = plot_w / slots as f32;
+        let baseline = y_of(0.0f32.clamp(lo, hi));
+        match self.kind {
+            ChartKind::Bar => {
+                let series_count = self.series.len().max(1) as f32;
+                let bar_w = slot_w * 0.8 / series_count;
+                for (series_index, series) in self.series.iter().enumerate() {
+                    canvas.set_fill_color(self.series_color(series_index));
+                    for (slot, value) in series.values.iter().enumerate() {
+                        if !value.is_finite() || slot >= slots {
+                            continue;
+                        }
+                        let bar_x =
+                            plot_x + slot_w * (slot as f32 + 0.1) + bar_w * series_index as f32;
+                        let value_y = y_of(*value);
+                        canvas.draw_rect(
+                            Pt::from_f32(bar_x),
+                            Pt::from_f32(value_y.min(baseline)),
+                            Pt::from_f32(bar_w),
+                            Pt::from_f32((value_y - baseline).abs()),
+                        );
+                    }
+                }
+            }
+            ChartKind::Line => {
+                let marker = font_size * 0.35;
+                for (series_index, series) in self.series.iter().enumerate() {
+                    let color = self.series_color(series_index);
+                    let points: Vec<(f32, f32)> = series
+                        .values
+                        .iter()
+                        .enumerate()
+                        .filter(|(slot, value)| value.is_finite() && *slot < slots)
+                        .map(|(slot, value)| (plot_x + slot_w * (slot as f32 + 0.5), y_of(*value)))
+                        .collect();
+                    if points.len() > 1 {
+                        canvas.set_stroke_color(color);
+                        canvas.set_line_width(Pt::from_f32((font_size * 0.15).max(1.0)));
+                        canvas.move_to(Pt::from_f32(points[0].0), Pt::from_f32(points[0].1));
+                        for (px, py) in &points[1..] {
+                            canvas.line_to(Pt::from_f32(*px), Pt::from_f32(*py));
+                        }
+                        canvas.stroke();
+                    }
+                    canvas.set_fill_color(color);
+                    for (px, py) in &points {
+                        canvas.draw_rect(
+                            Pt::from_f32(px - marker * 0.5),
+                            Pt::from_f32(py - marker * 0.5),
+                            Pt::from_f32(marker),
+                            Pt::from_f32(marker),
+                        );
+                    }
+                }
+            }
+            ChartKind::Pie => {}
+        }
+
+        // Value axis and zero baseline over the data.
+        canvas.set_stroke_color(self.axis_color);
+        canvas.set_line_width(Pt::from_f32(0.75));
+        canvas.move_to(Pt::from_f32(plot_x), Pt::from_f32(plot_y));
+        canvas.line_to(Pt::from_f32(plot_x), Pt::from_f32(plot_y + plot_h));
+        canvas.stroke();
+        canvas.move_to(Pt::from_f32(plot_x), Pt::from_f32(baseline));
+        canvas.line_to(Pt::from_f32(plot_x + plot_w), Pt::from_f32(baseline));
+        canvas.stroke();
+
+        for (slot, category) in self.categories.iter().enumerate().take(slots) {
+            self.draw_label(
+                canvas,
+                category,
+                (
+                    plot_x + slot_w * slot as f32,
+                    plot_y + plot_h + gap * 0.5,
+                    slot_w,
+                ),
+                TextAlign::Center,
+            );
+        }
+    }
+
+    fn draw_pie(&self, canvas: &mut Canvas, x: f32, top: f32, width: f32, bottom: f32) {
+        let Some(series) = self.series.first() else {
+            return;
+        };
+        let values: Vec<f32> = series
+            .values
+            .iter()
+            .map(|v| if v.is_finite() { v.max(0.0) } else { 0.0 })
+            .collect();
+        let total: f32 = values.iter().sum();
+        let gap = self.text_style.font_size.to_f32() * 0.5;
+        let radius = (width.min(bottom - top) * 0.5 - gap).max(0.0);
+        if total <= 0.0 || radius <= 0.0 {
+            return;
+        }
+        let cx = x + width * 0.5;
+        let cy = (top + bottom) * 0.5;
+        canvas.set_stroke_color(Color::rgb(1.0, 1.0, 1.0));
+        canvas.set_line_width(Pt::from_f32(0.75));
+        // Slices run clockwise from twelve o'clock.
+        let mut start = -std::f32::consts::FRAC_PI_2;
+        for (index, value) in values.iter().enumerate() {
+            if *value <= 0.0 {
+                continue;
+            }
+            let sweep = value / total * std::f32::consts::TAU;
+            canvas.set_fill_color(self.palette_color(index));
+            canvas.move_to(Pt::from_f32(cx), Pt::from_f32(cy));
+            canvas.arc(
+                Pt::from_f32(cx),
+                Pt::from_f32(cy),
+                Pt::from_f32(radius),
+                start,
+                start + sweep,
+            );
+            canvas.close_path();
+            canvas.fill_stroke();
+            start += sweep;
+        }
+    }
+}
+
+impl Flowable for ChartFlowable {
+    fn wrap(&self, _avail_width: Pt, _avail_height: Pt) -> Size {
+        Size {
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    fn intrinsic_width(&self) -> Option<Pt> {
+        Some(self.width)
+    }
+
+    fn split(
+        &self,
+        _avail_width: Pt,
+        _avail_height: Pt,
+    ) -> Option<(Box<dyn Flowable>, Box<dyn Flowable>)> {
+        None
+    }
+
+    fn draw(&self, canvas: &mut Canvas, x: Pt, y: Pt, _avail_width: Pt, _avail_height: Pt) {
+        let tagged = self.tag_role.as_ref().map(|role| {
+            canvas.begin_tag(role.as_ref(), self.alt.clone(), None, None, None, false);
+        });
+        canvas.save_state();
+        canvas.clip_rect(x, y, self.width, self.height);
+
+        let left = x.to_f32();
+        let width = self.width.to_f32();
+        let gap = self.text_style.font_size.to_f32() * 0.5;
+        let mut top = y.to_f32();
+        let mut bottom = top + self.height.to_f32();
+        if let Some(title) = self.title.as_deref() {
+            self.draw_label(canvas, title, (left, top, width), TextAlign::Center);
+            top += self.line_height() + gap;
+        }
+        if self.shows_legend() {
+            let legend_height = self.draw_legend(canvas, left, bottom, width);
+            if legend_height > 0.0 {
+                bottom -= legend_height + gap;
+            }
+        }
+        if bottom > top {
+            match self.kind {
+                ChartKind::Pie => self.draw_pie(canvas, left, top, width, bottom),
+                ChartKind::Bar | ChartKind::Line => {
+                    self.draw_axes_chart(canvas, left, top, width, bottom)
+                }
+            }
+        }
+
+        canvas.restore_state();
+        if tagged.is_some() {
+            canvas.end_tag();
+        }
+    }
+
+    fn pagination(&self) -> Pagination {
+        self.pagination
+    }
+}
+
+// Value-axis bounds and step: always includes zero, with 1/2/5 x 10^n steps.
+fn nice_scale((min, max): (f32, f32)) -> (f32, f32, f32) {
+    let span = max - min;
+    let span = if span > f32::EPSILON { span } else { 1.0 };
+    let raw = span / TARGET_TICKS;
+    let magnitude = libm::powf(10.0, libm::floorf(libm::log10f(raw)));
+    let normalized = raw / magnitude;
+    let step = if normalized <= 1.0 {
+        1.0
+    } else if normalized <= 2.0 {
+        2.0
+    } else if normalized <= 5.0 {
+        5.0
+    } else {
+        10.0
+    } * magnitude;
+    let lo = libm::floorf(min / step) * step;
+    let mut hi = libm::ceilf(max / step) * step;
+    if hi <= lo {
+        hi = lo + step;
+    }
+    (lo, hi, step)
+}
+
+fn format_tick(value: f32, step: f32) -> String {
+    let decimals = if step >= 1.0 {
+        0
+    } else {
+        libm::ceilf(-libm::log10f(step)).max(0.0) as usize
+    };
+    let text = format!("{value:.decimals$}");
+    // Avoid "-0" for values that round to zero.
+    if text
+        .trim_start_matches('-')
+        .chars()
+        .all(|c| c == '0' || c == '.')
+    {
+        text.trim_start_matches('-').to_string()
+    } else {
+        text
+    }
+}
+
+fn mix_with_white(color: Color, amount: f32) -> Color {
+    let mix = |c: f32| c + (1.0 - c) * amount;
+    Color::rgb(mix(color.r), mix(color.g), mix(color.b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Command, FullBleed};
+
+    #[test]
+    fn chart_elements_render_vector_bars_lines_and_pies() {
+        let css = "body { margin: 0; } \
+            .chart { width: 240pt; height: 160pt; font-size: 8pt; color: #333333; } \
+            .s1 { background: #0d6efd; }";
+        let html = "<div class=\"chart\" data-fb-chart=\"bar\" data-fb-chart-title=\"Revenue\" \
+            data-fb-chart-categories=\"Q1, Q2, Q3\">\
+            <data class=\"s1\" data-fb-series=\"2025\" value=\"12, 18, 9\"></data>\
+            <data data-fb-series=\"2026\" value=\"14 20 -3\"></data></div>\
+            <div data-fb-chart=\"pie\" data-fb-chart-categories=\"A,B\" \
+            style=\"width: 100pt; height: 100pt; font-size: 8pt\">\
+            <data data-fb-series=\"Share\" value=\"3 1\"></data></div>";
+        let engine = FullBleed::builder().build().expect("engine");
+        let doc = engine.render_to_document(html, css).expect("document");
+        let commands = &doc.pages[0].commands;
+        let texts: Vec<String> = commands
+            .iter()
+            .filter_map(|cmd| match &*cmd {
+                Command::DrawString { text, .. } => Some(text.trim().to_string()),
+                _ => None,
+            })
+            .collect();
+        for expected in ["Revenue", "Q1", "Q3", "2025", "2026", "-5", "20", "A", "B"] {
+            assert!(
+                texts.iter().any(|text| text == expected),
+                "{expected} missing from {texts:?}"
+            );
+        }
+        assert!(!texts.iter().any(|text| text.contains("12, 18")));
+
+        // Series colors come from the series element's background: one legend swatch and
+        // three bars.
+        let blue = Color::rgb(13.0 / 255.0, 110.0 / 255.0, 253.0 / 255.0);
+        let mut fill = None;
+        let mut blue_rects = 0;
+        for cmd in commands {
+            match &*cmd {
+                Command::SetFillColor(color) => fill = Some(*color),
+                Command::DrawRect { .. }
+                    if fill.is_some_and(|c| {
+                        (c.r - blue.r).abs() < 0.01
+                            && (c.g - blue.g).abs() < 0.01
+                            && (c.b - blue.b).abs() < 0.01
+                    }) =>
+                {
+                    blue_rects += 1;
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(blue_rects, 4);
+
+        // A 3:1 pie draws a three-quarter slice (three arc segments) and a quarter slice.
+        let curves = commands
+            .iter()
+            .filter(|cmd| matches!(**cmd, Command::CurveTo { .. }))
+            .count();
+        assert_eq!(curves, 4);
+        let figures = commands
+            .iter()
+            .filter(|cmd| matches!(&**cmd, Command::BeginTag { role, .. } if role == "Figure"))
+            .count();
+        assert_eq!(figures, 2);
+    }
+}
@@ -0,0 +1,759 @@
+use crate::canvas::Command;
+use crate::flowable::PaintFilterSpec;
+use crate::types::{Color, MixBlendMode, Pt};
+use std::borrow::Cow;
+use std::fmt;
+
+// Drawing commands of a page or form, stored compactly. Commands that own no heap data
+// (paths, colors, state changes: most of a page) are packed as a tag byte followed by their
+// fields, instead of each taking a full `Command` slot; commands that own text or vectors
+// are kept whole in a side table. Iteration yields `Cow<Command>`: packed commands are
+// rebuilt on the fly and the others are borrowed, so match through `&*command`.
+#[derive(Clone, Default)]
+pub struct CommandBuffer {
+    // One tag per command; `OWNED` stands for the next entry of `owned`.
+    tags: Vec<u8>,
+    // Fields of the packed commands, little-endian, in command order.
+    fields: Vec<u8>,
+    owned: Vec<Command>,
+}
+
+const OWNED: u8 = 0;
+const SAVE_STATE: u8 = 1;
+const RESTORE_STATE: u8 = 2;
+const TRANSLATE: u8 = 3;
+const SCALE: u8 = 4;
+const ROTATE: u8 = 5;
+const CONCAT_MATRIX: u8 = 6;
+const SET_FILL_COLOR: u8 = 7;
+const SET_STROKE_COLOR: u8 = 8;
+const SET_LINE_WIDTH: u8 = 9;
+const SET_LINE_CAP: u8 = 10;
+const SET_LINE_JOIN: u8 = 11;
+const SET_MITER_LIMIT: u8 = 12;
+const SET_OPACITY: u8 = 13;
+const SET_BLEND_MODE: u8 = 14;
+const APPLY_BACKDROP_FILTER: u8 = 15;
+const SET_FONT_SIZE: u8 = 16;
+const CLIP_RECT: u8 = 17;
+const CLIP_PATH: u8 = 18;
+const MOVE_TO: u8 = 19;
+const LINE_TO: u8 = 20;
+const CURVE_TO: u8 = 21;
+const CLOSE_PATH: u8 = 22;
+const FILL: u8 = 23;
+const FILL_EVEN_ODD: u8 = 24;
+const STROKE: u8 = 25;
+const FILL_STROKE: u8 = 26;
+const FILL_STROKE_EVEN_ODD: u8 = 27;
+const DRAW_RECT: u8 = 28;
+const END_TAG: u8 = 29;
+const END_MARKED_CONTENT: u8 = 30;
+
+impl CommandBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    pub fn push(&mut self, command: Command) {
+        let start = self.fields.len();
+        let tag = pack(&command, &mut self.fields);
+        debug_assert_eq!(self.fields.len() - start, packed_len(tag));
+        if tag == OWNED {
+            self.owned.push(command);
+        }
+        self.tags.push(tag);
+    }
+
+    // Appends a copy of `other`; packed commands are copied as they are stored.
+    pub fn append(&mut self, other: &CommandBuffer) {
+        self.tags.extend_from_slice(&other.tags);
+        self.fields.extend_from_slice(&other.fields);
+        self.owned.extend(other.owned.iter().cloned());
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            tags: self.tags.iter(),
+            fields: &self.fields,
+            front: 0,
+            back: self.fields.len(),
+            owned: self.owned.iter(),
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<Command> {
+        self.iter().map(Cow::into_owned).collect()
+    }
+
+    pub fn into_vec(self) -> Vec<Command> {
+        let mut fields = Fields {
+            bytes: &self.fields,
+            pos: 0,
+        };
+        let mut owned = self.owned.into_iter();
+        self.tags
+            .iter()
+            .filter_map(|&tag| match tag {
+                OWNED => owned.next(),
+                tag => Some(fields.unpack(tag)),
+            })
+            .collect()
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.tags.shrink_to_fit();
+        self.fields.shrink_to_fit();
+        self.owned.shrink_to_fit();
+    }
+
+    // Bytes of the buffers themselves, spare capacity included; heap data the kept commands
+    // own comes on top (see `owned`).
+    pub(crate) fn slot_bytes(&self) -> usize {
+        self.tags.capacity()
+            + self.fields.capacity()
+            + self.owned.capacity() * std::mem::size_of::<Command>()
+    }
+
+    // The commands kept whole, in order; the only ones that own heap data.
+    pub(crate) fn owned(&self) -> &[Command] {
+        &self.owned
+    }
+
+    // Mutable access to the commands kept whole, for rewriting resource ids in place.
+    pub(crate) fn owned_mut(&mut self) -> &mut [Command] {
+        &mut self.owned
+    }
+}
+
+impl fmt::Debug for CommandBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl From<Vec<Command>> for CommandBuffer {
+    fn from(commands: Vec<Command>) -> Self {
+        commands.into_iter().collect()
+    }
+}
+
+impl FromIterator<Command> for CommandBuffer {
+    fn from_iter<I: IntoIterator<Item = Command>>(iter: I) -> Self {
+        let mut buffer = CommandBuffer::new();
+        buffer.extend(iter);
+        buffer
+    }
+}
+
+impl Extend<Command> for CommandBuffer {
+    fn extend<I: IntoIterator<Item = Command>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.tags.reserve(iter.size_hint().0);
+        for command in iter {
+            self.push(command);
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a CommandBuffer {
+    type Item = Cow<'a, Command>;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+impl IntoIterator for CommandBuffer {
+    type Item = Command;
+    type IntoIter = std::vec::IntoIter<Command>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.into_vec().into_iter()
+    }
+}
+
+pub struct Iter<'a> {
+    tags: std::slice::Iter<'a, u8>,
+    fields: &'a [u8],
+    // Packed fields not yet read from either end.
+    front: usize,
+    back: usize,
+    owned: std::slice::Iter<'a, Command>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = Cow<'a, Command>;
+
+    fn next(&mut self) -> Option<Cow<'a, Command>> {
+        match *self.tags.next()? {
+            OWNED => self.owned.next().map(Cow::Borrowed),
+            tag => {
+                let mut fields = Fields {
+                    bytes: self.fields,
+                    pos: self.front,
+                };
+                let command = fields.unpack(tag);
+                self.front = fields.pos;
+                Some(Cow::Owned(command))
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.tags.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match *self.tags.next_back()? {
+            OWNED => self.owned.next_back().map(Cow::Borrowed),
+            tag => {
+                self.back -= packed_len(tag);
+                let mut fields = Fields {
+                    bytes: self.fields,
+                    pos: self.back,
+                };
+                Some(Cow::Owned(fields.unpack(tag)))
+            }
+        }
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+// Bytes `pack` writes for a command with `tag`.
+fn packed_len(tag: u8) -> usize {
+    const PT: usize = 8;
+    const F32: usize = 4;
+    const COLOR: usize = 3 * F32;
+    match tag {
+        TRANSLATE | MOVE_TO | LINE_TO => 2 * PT,
+        SCALE | SET_OPACITY => 2 * F32,
+        ROTATE => F32,
+        CONCAT_MATRIX => 4 * F32 + 2 * PT,
+        SET_FILL_COLOR | SET_STROKE_COLOR => COLOR,
+        SET_LINE_WIDTH | SET_MITER_LIMIT | SET_FONT_SIZE => PT,
+        SET_LINE_CAP | SET_LINE_JOIN | SET_BLEND_MODE | CLIP_PATH => 1,
+        APPLY_BACKDROP_FILTER => 6 * PT + F32,
+        CLIP_RECT | DRAW_RECT => 4 * PT,
+        CURVE_TO => 6 * PT,
+        _ => 0,
+    }
+}
+
+// Writes the fields of `command` when it owns no heap data and returns its tag; `OWNED`
+// when it has to be kept whole.
+fn pack(command: &Command, out: &mut Vec<u8>) -> u8 {
+    let pts = |out: &mut Vec<u8>, values: &[Pt]| {
+        for value in values {
+            out.extend_from_slice(&value.to_bits().to_le_bytes());
+        }
+    };
+    let f32s = |out: &mut Vec<u8>, values: &[f32]| {
+        for value in values {
+            out.extend_from_slice(&value.to_bits().to_le_bytes());
+        }
+    };
+    match *command {
+        Command::SaveState => SAVE_STATE,
+        Command::RestoreState => RESTORE_STATE,
+        Command::Translate(x, y) => {
+            pts(out, &[x, y]);
+            TRANSLATE
+        }
+        Command::Scale(x, y) => {
+            f32s(out, &[x, y]);
+            SCALE
+        }
+        Command::Rotate(angle) => {
+            f32s(out, &[angle]);
+            ROTATE
+        }
+        Command::ConcatMatrix { a, b, c, d, e, f } => {
+            f32s(out, &[a, b, c, d]);
+            pts(out, &[e, f]);
+            CONCAT_MATRIX
+        }
+        // Process colors carry their spot or CMYK values and stay owned.
+        Command::SetFillColor(value) if value.process.is_none() => {
+            f32s(out, &[value.r, value.g, value.b]);
+            SET_FILL_COLOR
+        }
+        Command::SetStrokeColor(value) if value.process.is_none() => {
+            f32s(out, &[value.r, value.g, value.b]);
+            SET_STROKE_COLOR
+        }
+        Command::SetLineWidth(width) => {
+            pts(out, &[width]);
+            SET_LINE_WIDTH
+        }
+        Command::SetLineCap(cap) => {
+            out.push(cap);
+            SET_LINE_CAP
+        }
+        Command::SetLineJoin(join) => {
+            out.push(join);
+            SET_LINE_JOIN
+        }
+        Command::SetMiterLimit(limit) => {
+            pts(out, &[limit]);
+            SET_MITER_LIMIT
+        }
+        Command::SetOpacity { fill, stroke } => {
+            f32s(out, &[fill, stroke]);
+            SET_OPACITY
+        }
+        Command::SetBlendMode { mode } => {
+            out.push(match mode {
+                MixBlendMode::Normal => 0,
+                MixBlendMode::Multiply => 1,
+                MixBlendMode::Screen => 2,
+            });
+            SET_BLEND_MODE
+        }
+        Command::ApplyBackdropFilter {
+            x,
+            y,
+            width,
+            height,
+            radius,
+            filter,
+        } => {
+            pts(out, &[x, y, width, height, radius, filter.blur_radius]);
+            f32s(out, &[filter.saturate]);
+            APPLY_BACKDROP_FILTER
+        }
+        Command::SetFontSize(size) => {
+            pts(out, &[size]);
+            SET_FONT_SIZE
+        }
+        Command::ClipRect {
+            x,
+            y,
+            width,
+            height,
+        } => {
+            pts(out, &[x, y, width, height]);
+            CLIP_RECT
+        }
+        Command::ClipPath { evenodd } => {
+            out.push(evenodd as u8);
+            CLIP_PATH
+        }
+        Command::MoveTo { x, y } => {
+            pts(out, &[x, y]);
+            MOVE_TO
+        }
+        Command::LineTo { x, y } => {
+            pts(out, &[x, y]);
+            LINE_TO
+        }
+        Command::CurveTo {
+            x1,
+            y1,
+            x2,
+            y2,
+            x,
+            y,
+        } => {
+            pts(out, &[x1, y1, x2, y2, x, y]);
+            CURVE_TO
+        }
+        Command::ClosePath => CLOSE_PATH,
+        Command::Fill => FILL,
+        Command::FillEvenOdd => FILL_EVEN_ODD,
+        Command::Stroke => STROKE,
+        Command::FillStroke => FILL_STROKE,
+        Command::FillStrokeEvenOdd => FILL_STROKE_EVEN_ODD,
+        Command::DrawRect {
+            x,
+            y,
+            width,
+            height,
+        } => {
+            pts(out, &[x, y, width, height]);
+            DRAW_RECT
+        }
+        Command::EndTag => END_TAG,
+        Command::EndMarkedContent => END_MARKED_CONTENT,
+        _ => OWNED,
+    }
+}
+
+// Read cursor over the packed fields; only ever reads what `pack` wrote.
+struct Fields<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Fields<'_> {
+    fn take<const N: usize>(&mut self) -> [u8; N] {
+        let bytes = self.bytes[self.pos..self.pos + N]
+            .try_into()
+            .expect("packed command fields");
+        self.pos += N;
+        bytes
+    }
+
+    fn u8(&mut self) -> u8 {
+        self.take::<1>()[0]
+    }
+
+    fn f32(&mut self) -> f32 {
+        f32::from_bits(u32::from_le_bytes(self.take()))
+    }
+
+    fn pt(&mut self) -> Pt {
+        Pt::from_bits(i64::from_le_bytes(self.take()))
+    }
+
+    fn color(&mut self) -> Color {
+        Color {
+            r: self.f32(),
+            g: self.f32(),
+            b: self.f32(),
+            process: None,
+        }
+    }
+
+    fn unpack(&mut self, tag: u8) -> Command {
+        match tag {
+            SAVE_STATE => Command::SaveState,
+            RESTORE_STATE => Command::RestoreState,
+            TRANSLATE => Command::Translate(self.pt(), self.pt()),
+            SCALE => Command::Scale(self.f32(), self.f32()),
+            ROTATE => Command::Rotate(self.f32()),
+            CONCAT_MATRIX => Command::ConcatMatrix {
+                a: self.f32(),
+                b: self.f32(),
+                c: self.f32(),
+                d: self.f32(),
+                e: self.pt(),
+                f: self.pt(),
+            },
+            SET_FILL_COLOR => Command::SetFillColor(self.color()),
+            SET_STROKE_COLOR => Command::SetStrokeColor(self.color()),
+            SET_LINE_WIDTH => Command::SetLineWidth(self.pt()),
+            SET_LINE_CAP => Command::SetLineCap(self.u8()),
+            SET_LINE_JOIN => Command::SetLineJoin(self.u8()),
+            SET_MITER_LIMIT => Command::SetMiterLimit(self.pt()),
+            SET_OPACITY => Command::SetOpacity {
+                fill: self.f32(),
+                stroke: self.f32(),
+            },
+            SET_BLEND_MODE => Command::SetBlendMode {
+                mode: match self.u8() {
+                    0 => MixBlendMode::Normal,
+                    1 => MixBlendMode::Multiply,
+                    2 => MixBlendMode::Screen,
+                    mode => unreachable!("unknown packed blend mode {mode}"),
+                },
+            },
+            APPLY_BACKDROP_FILTER => {
+                let (x, y, width, height, radius) =
+                    (self.pt(), self.pt(), self.pt(), self.pt(), self.pt());
+                let blur_radius = self.pt();
+                Command::ApplyBackdropFilter {
+                    x,
+                    y,
+                    width,
+                    height,
+                    radius,
+                    filter: PaintFilterSpec {
+                        saturate: self.f32(),
+                        blur_radius,
+                    },
+                }
+            }
+            SET_FONT_SIZE => Command::SetFontSize(self.pt()),
+            CLIP_RECT => Command::ClipRect {
+                x: self.pt(),
+                y: self.pt(),
+                width: self.pt(),
+                height: self.pt(),
+            },
+            CLIP_PATH => Command::ClipPath {
+                evenodd: self.u8() != 0,
+            },
+            MOVE_TO => Command::MoveTo {
+                x: self.pt(),
+                y: self.pt(),
+            },
+            LINE_TO => Command::LineTo {
+                x: self.pt(),
+                y: self.pt(),
+            },
+            CURVE_TO => Command::CurveTo {
+                x1: self.pt(),
+                y1: self.pt(),
+                x2: self.pt(),
+                y2: self.pt(),
+                x: self.pt(),
+                y: self.pt(),
+            },
+            CLOSE_PATH => Command::ClosePath,
+            FILL => Command::Fill,
+            FILL_EVEN_ODD => Command::FillEvenOdd,
+            STROKE => Command::Stroke,
+            FILL_STROKE => Command::FillStroke,
+            FILL_STROKE_EVEN_ODD => Command::FillStrokeEvenOdd,
+            DRAW_RECT => Command::DrawRect {
+                x: self.pt(),
+                y: self.pt(),
+                width: self.pt(),
+                height: self.pt(),
+            },
+            END_TAG => Command::EndTag,
+            END_MARKED_CONTENT => Command::EndMarkedContent,
+            tag => unreachable!("unknown packed command tag {tag}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CommandBuffer;
+    use crate::canvas::{Command, LinkTarget};
+    use crate::flowable::PaintFilterSpec;
+    use crate::types::{Color, MixBlendMode, Pt, Shading, ShadingStop};
+
+    fn one_of_each() -> Vec<Command> {
+        let pt = Pt::from_f32;
+        vec![
+            Command::SaveState,
+            Command::RestoreState,
+            Command::Translate(pt(1.5), pt(-2.25)),
+            Command::Scale(0.5, 2.0),
+            Command::Rotate(90.0),
+            Command::ConcatMatrix {
+                a: 1.0,
+                b: 0.25,
+                c: -0.25,
+                d: 1.0,
+                e: pt(10.0),
+                f: pt(20.0),
+            },
+            Command::Meta {
+                key: "section".to_string(),
+                value: "intro".to_string(),
+            },
+            Command::SetFillColor(Color::rgb(0.1, 0.2, 0.3)),
+            Command::SetFillColor(Color::cmyk(0.1, 0.2, 0.3, 0.4)),
+            Command::SetStrokeColor(Color::rgb(0.9, 0.8, 0.7)),
+            Command::SetStrokeColor(Color::spot("Gold", 0.5, [0.0, 0.2, 0.8, 0.1]).unwrap()),
+            Command::SetLineWidth(pt(0.75)),
+            Command::SetLineCap(1),
+            Command::SetLineJoin(2),
+            Command::SetMiterLimit(pt(4.0)),
+            Command::SetDash {
+                pattern: vec![pt(3.0), pt(1.0)],
+                phase: pt(0.5),
+            },
+            Command::SetOpacity {
+                fill: 0.5,
+                stroke: 0.25,
+            },
+            Command::SetBlendMode {
+                mode: MixBlendMode::Normal,
+            },
+            Command::SetBlendMode {
+                mode: MixBlendMode::Multiply,
+            },
+            Command::SetBlendMode {
+                mode: MixBlendMode::Screen,
+            },
+            Command::ApplyBackdropFilter {
+                x: pt(1.0),
+                y: pt(2.0),
+                width: pt(30.0),
+                height: pt(40.0),
+                radius: pt(5.0),
+                filter: PaintFilterSpec {
+                    saturate: 1.5,
+                    blur_radius: pt(6.0),
+                },
+            },
+            Command::SetFontName("Helvetica".into()),
+            Command::SetFontSize(pt(12.0)),
+            Command::ClipRect {
+                x: pt(0.0),
+                y: pt(0.0),
+                width: pt(100.0),
+                height: pt(50.0),
+            },
+            Command::ClipPath { evenodd: true },
+            Command::ShadingFill(Shading::Axial {
+                x0: 0.0,
+                y0: 0.0,
+                x1: 1.0,
+                y1: 0.0,
+                stops: vec![
+                    ShadingStop {
+                        offset: 0.0,
+                        color: Color::BLACK,
+                    },
+                    ShadingStop {
+                        offset: 1.0,
+                        color: Color::rgb(1.0, 1.0, 1.0),
+                    },
+                ],
+            }),
+            Command::MoveTo {
+                x: pt(1.0),
+                y: pt(2.0),
+            },
+            Command::LineTo {
+                x: pt(3.0),
+                y: pt(4.0),
+            },
+            Command::CurveTo {
+                x1: pt(1.0),
+                y1: pt(2.0),
+                x2: pt(3.0),
+                y2: pt(4.0),
+                x: pt(5.0),
+                y: pt(6.0),
+            },
+            Command::ClosePath,
+            Command::Fill,
+            Command::FillEvenOdd,
+            Command::Stroke,
+            Command::FillStroke,
+            Command::FillStrokeEvenOdd,
+            Command::DrawString {
+                x: pt(10.0),
+                y: pt(20.0),
+                text: "Hello".to_string(),
+            },
+            Command::DrawStringTransformed {
+                x: pt(10.0),
+                y: pt(20.0),
+                text: "Tilted".to_string(),
+                m00: 1.0,
+                m01: 0.5,
+                m10: -0.5,
+                m11: 1.0,
+            },
+            Command::DrawGlyphRun {
+                x: pt(10.0),
+                y: pt(20.0),
+                glyph_ids: vec![3, 4, 5],
+                advances: vec![(pt(5.0), pt(0.0)); 3],
+                m00: 1.0,
+                m01: 0.0,
+                m10: 0.0,
+                m11: 1.0,
+            },
+            Command::DrawRect {
+                x: pt(1.0),
+                y: pt(2.0),
+                width: pt(3.0),
+                height: pt(4.0),
+            },
+            Command::DrawImage {
+                x: pt(0.0),
+                y: pt(0.0),
+                width: pt(64.0),
+                height: pt(32.0),
+                resource_id: "img-1".into(),
+            },
+            Command::DefineForm {
+                resource_id: "form-1".into(),
+                width: pt(10.0),
+                height: pt(10.0),
+                commands: vec![Command::SaveState, Command::Fill].into(),
+            },
+            Command::DrawForm {
+                x: pt(5.0),
+                y: pt(5.0),
+                width: pt(10.0),
+                height: pt(10.0),
+                resource_id: "form-1".into(),
+            },
+            Command::BeginTag {
+                role: "P".to_string(),
+                mcid: Some(7),
+                alt: Some("alt".to_string()),
+                scope: None,
+                table_id: Some(1),
+                col_index: Some(2),
+                group_only: false,
+            },
+            Command::EndTag,
+            Command::BeginArtifact {
+                subtype: Some("Pagination".to_string()),
+            },
+            Command::BeginOptionalContent {
+                name: "Draft".to_string(),
+            },
+            Command::EndMarkedContent,
+            Command::LinkAnnotation {
+                x: pt(1.0),
+                y: pt(2.0),
+                width: pt(3.0),
+                height: pt(4.0),
+                target: LinkTarget::Uri("https://example.com".to_string()),
+            },
+            Command::NamedDestination {
+                name: "intro".to_string(),
+                x: pt(0.0),
+                y: pt(100.0),
+                exported: true,
+            },
+            Command::Bookmark {
+                level: 1,
+                title: "Intro".to_string(),
+                x: pt(0.0),
+                y: pt(100.0),
+            },
+            Command::SignatureField {
+                name: "sig".to_string(),
+                x: pt(10.0),
+                y: pt(10.0),
+                width: pt(100.0),
+                height: pt(30.0),
+            },
+        ]
+    }
+
+    fn debug(commands: impl IntoIterator<Item = impl std::fmt::Debug>) -> Vec<String> {
+        commands.into_iter().map(|cmd| format!("{cmd:?}")).collect()
+    }
+
+    #[test]
+    fn every_command_round_trips_through_the_buffer() {
+        let commands = one_of_each();
+        let expected = debug(&commands);
+        let buffer = CommandBuffer::from(commands);
+        assert_eq!(buffer.len(), expected.len());
+        assert_eq!(debug(buffer.iter()), expected);
+        let mut reversed = debug(buffer.iter().rev());
+        reversed.reverse();
+        assert_eq!(reversed, expected);
+        let mut appended = CommandBuffer::new();
+        appended.append(&buffer);
+        assert_eq!(debug(appended.to_vec()), expected);
+        assert_eq!(debug(buffer.into_vec()), expected);
+    }
+
+    #[test]
+    fn only_commands_owning_heap_data_or_process_colors_are_kept_whole() {
+        let buffer = CommandBuffer::from(one_of_each());
+        let owned = debug(buffer.owned());
+        assert_eq!(owned.len(), 19);
+        assert!(owned.iter().any(|cmd| cmd.starts_with("SetFillColor")));
+        assert!(owned.iter().any(|cmd| cmd.starts_with("SetStrokeColor")));
+        assert!(!owned.iter().any(|cmd| cmd.starts_with("MoveTo")));
+    }
+}
@@ -0,0 +1,325 @@
+// CSS counters: scoped counter instances, list marker numbering styles, and expansion of the
+// `counter()`/`counters()` references that generated content carries between `COUNTER_MARK`s.
+use crate::style::{COUNTER_FIELD, COUNTER_MARK, ListStyleTypeMode};
+use std::collections::HashMap;
+
+// Counter instances in document order. A `counter-reset` starts a new instance that is visible
+// to the element, its descendants and its following siblings; the instance ends when the
+// parent of the resetting element is left.
+#[derive(Debug)]
+pub(crate) struct CounterState {
+    instances: HashMap<String, Vec<i32>>,
+    // Names reset at each open nesting level.
+    scopes: Vec<Vec<String>>,
+}
+
+impl CounterState {
+    pub(crate) fn new() -> Self {
+        Self {
+            instances: HashMap::new(),
+            scopes: vec![Vec::new()],
+        }
+    }
+
+    pub(crate) fn enter(&mut self) {
+        self.scopes.push(Vec::new());
+    }
+
+    pub(crate) fn exit(&mut self) {
+        let Some(names) = self.scopes.pop() else {
+            return;
+        };
+        for name in names {
+            if let Some(stack) = self.instances.get_mut(&name) {
+                stack.pop();
+            }
+        }
+        if self.scopes.is_empty() {
+            self.scopes.push(Vec::new());
+        }
+    }
+
+    pub(crate) fn reset(&mut self, name: &str, value: i32) {
+        let scope = self.scopes.last_mut().expect("counter scope");
+        let stack = self.instances.entry(name.to_string()).or_default();
+        // A sibling's reset replaces the instance this level already created.
+        let replaces_sibling = scope.iter().any(|existing| existing == name);
+        match stack.last_mut() {
+            Some(top) if replaces_sibling => *top = value,
+            _ => {
+                scope.push(name.to_string());
+                stack.push(value);
+            }
+        }
+    }
+
+    pub(crate) fn increment(&mut self, name: &str, by: i32) {
+        if !self.has(name) {
+            self.reset(name, 0);
+        }
+        if let Some(top) = self
+            .instances
+            .get_mut(name)
+            .and_then(|stack| stack.last_mut())
+        {
+            *top = top.saturating_add(by);
+        }
+    }
+
+    pub(crate) fn set(&mut self, name: &str, value: i32) {
+        if !self.has(name) {
+            self.reset(name, value);
+            return;
+        }
+        if let Some(top) = self
+            .instances
+            .get_mut(name)
+            .and_then(|stack| stack.last_mut())
+        {
+            *top = value;
+        }
+    }
+
+    fn has(&self, name: &str) -> bool {
+        self.instances
+            .get(name)
+            .is_some_and(|stack| !stack.is_empty())
+    }
+
+    pub(crate) fn value(&self, name: &str) -> i32 {
+        self.instances
+            .get(name)
+            .and_then(|stack| stack.last().copied())
+            .unwrap_or(0)
+    }
+
+    pub(crate) fn values(&self, name: &str) -> Vec<i32> {
+        match self.instances.get(name) {
+            Some(stack) if !stack.is_empty() => stack.clone(),
+            _ => vec![0],
+        }
+    }
+}
+
+// Replaces each encoded counter reference in `text` with its formatted value.
+pub(crate) fn expand_counters(text: &str, counters: &CounterState) -> String {
+    if !text.contains(COUNTER_MARK) {
+        return text.to_string();
+    }
+    let mut out = String::new();
+    let mut parts = text.split(COUNTER_MARK);
+    if let Some(head) = parts.next() {
+        out.push_str(head);
+    }
+    // Parts alternate between a reference and the literal text after it.
+    let mut in_reference = true;
+    for part in parts {
+        if in_reference {
+            let mut fields = part.split(COUNTER_FIELD);
+            let name = fields.next().unwrap_or("");
+            let style = fields
+                .next()
+                .and_then(ListStyleTypeMode::from_keyword)
+                .unwrap_or(ListStyleTypeMode::Decimal);
+            match fields.next() {
+                Some(separator) => {
+                    let values: Vec<String> = counters
+                        .values(name)
+                        .into_iter()
+                        .map(|value| format_counter(value, style))
+                        .collect();
+                    out.push_str(&values.join(separator));
+                }
+                None => out.push_str(&format_counter(counters.value(name), style)),
+            }
+        } else {
+            out.push_str(part);
+        }
+        in_reference = !in_reference;
+    }
+    out
+}
+
+// Style of the first `counter(page, <style>)` reference in generated content, if any.
+pub(crate) fn page_counter_style(text: &str) -> Option<ListStyleTypeMode> {
+    // Odd parts sit between marks and are references.
+    text.split(COUNTER_MARK)
+        .skip(1)
+        .step_by(2)
+        .find_map(|reference| {
+            let mut fields = reference.split(COUNTER_FIELD);
+            if fields.next() != Some("page") {
+                return None;
+            }
+            let style = fields.next();
+            if fields.next().is_some() {
+                return None;
+            }
+            Some(
+                style
+                    .and_then(ListStyleTypeMode::from_keyword)
+                    .unwrap_or(ListStyleTypeMode::Decimal),
+            )
+        })
+}
+
+// Default list marker text for item number `value`, including its trailing separator.
+pub(crate) fn list_marker(value: i32, style: ListStyleTypeMode, ordered: bool) -> String {
+    let style = match style {
+        ListStyleTypeMode::Auto if ordered => ListStyleTypeMode::Decimal,
+        ListStyleTypeMode::Auto => ListStyleTypeMode::Disc,
+        other => other,
+    };
+    match style {
+        ListStyleTypeMode::None => String::new(),
+        _ if style.is_bullet() => format!("{} ", format_counter(value, style)),
+        _ => format!("{}. ", format_counter(value, style)),
+    }
+}
+
+pub(crate) fn format_counter(value: i32, style: ListStyleTypeMode) -> String {
+    match style {
+        ListStyleTypeMode::None => String::new(),
+        ListStyleTypeMode::Disc => "\u{2022}".to_string(),
+        ListStyleTypeMode::Circle => "\u{25e6}".to_string(),
+        ListStyleTypeMode::Square => "\u{25aa}".to_string(),
+        ListStyleTypeMode::DecimalLeadingZero => {
+            if (0..10).contains(&value) {
+                format!("0{value}")
+            } else if (-9..0).contains(&value) {
+                format!("-0{}", -value)
+            } else {
+                value.to_string()
+            }
+        }
+        ListStyleTypeMode::LowerAlpha => alphabetic(value, &LATIN_LOWER),
+        ListStyleTypeMode::UpperAlpha => alphabetic(value, &LATIN_UPPER),
+        ListStyleTypeMode::LowerGreek => alphabetic(value, &GREEK_LOWER),
+        ListStyleTypeMode::LowerRoman => roman(value).to_ascii_lowercase(),
+        ListStyleTypeMode::UpperRoman => roman(value),
+        ListStyleTypeMode::Auto | ListStyleTypeMode::Decimal => value.to_string(),
+    }
+}
+
+const LATIN_LOWER: [char; 26] = [
+    'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 's',
+    't', 'u', 'v', 'w', 'x', 'y', 'z',
+];
+const LATIN_UPPER: [char; 26] = [
+    'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O', 'P', 'Q', 'R', 'S',
+    'T', 'U', 'V', 'W', 'X', 'Y', 'Z',
+];
+const GREEK_LOWER: [char; 24] = [
+    'α', 'β', 'γ', 'δ', 'ε', 'ζ', 'η', 'θ', 'ι', 'κ', 'λ', 'μ', 'ν', 'ξ', 'ο', 'π', 'ρ', 'σ', 'τ',
+    'υ', 'φ', 'χ', 'ψ', 'ω',
+];
+
+// Bijective numbering (a..z, aa..az, ...). Values below 1 fall back to decimal.
+fn alphabetic(value: i32, symbols: &[char]) -> String {
+    if value < 1 {
+        return value.to_string();
+    }
+    let base = symbols.len() as i64;
+    let mut n = value as i64;
+    let mut out = Vec::new();
+    while n > 0 {
+        n -= 1;
+        out.push(symbols[(n % base) as usize]);
+        n /= base;
+    }
+    out.iter().rev().collect()
+}
+
+// Roman numerals cover 1..=3999; other values fall back to decimal.
+fn roman(value: i32) -> String {
+    if !(1..=3999).contains(&value) {
+        return value.to_string();
+    }
+    const NUMERALS: [(i32, &str); 13] = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    let mut n = value;
+    let mut out = String::new();
+    for (amount, numeral) in NUMERALS {
+        while n >= amount {
+            out.push_str(numeral);
+            n -= amount;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbering_styles_format_values() {
+        assert_eq!(format_counter(4, ListStyleTypeMode::LowerAlpha), "d");
+        assert_eq!(format_counter(28, ListStyleTypeMode::UpperAlpha), "AB");
+        assert_eq!(
+            format_counter(1994, ListStyleTypeMode::UpperRoman),
+            "MCMXCIV"
+        );
+        assert_eq!(format_counter(9, ListStyleTypeMode::LowerRoman), "ix");
+        assert_eq!(format_counter(0, ListStyleTypeMode::LowerRoman), "0");
+        assert_eq!(
+            format_counter(7, ListStyleTypeMode::DecimalLeadingZero),
+            "07"
+        );
+        assert_eq!(format_counter(3, ListStyleTypeMode::LowerGreek), "γ");
+        assert_eq!(list_marker(3, ListStyleTypeMode::Auto, true), "3. ");
+        assert_eq!(list_marker(3, ListStyleTypeMode::Auto, false), "\u{2022} ");
+    }
+
+    #[test]
+    fn nested_resets_scope_to_their_parent() {
+        let mut counters = CounterState::new();
+        counters.reset("item", 0);
+        counters.increment("item", 1);
+        counters.enter();
+        counters.reset("item", 0);
+        counters.increment("item", 1);
+        counters.increment("item", 1);
+        let reference =
+            format!("{COUNTER_MARK}item{COUNTER_FIELD}lower-alpha{COUNTER_FIELD}.{COUNTER_MARK})");
+        assert_eq!(expand_counters(&reference, &counters), "a.b)");
+        counters.exit();
+        counters.increment("item", 1);
+        assert_eq!(counters.value("item"), 2);
+        assert_eq!(counters.values("missing"), vec![0]);
+    }
+
+    #[test]
+    fn page_counter_style_finds_the_page_reference() {
+        let page = |style: &str| format!("{COUNTER_MARK}page{COUNTER_FIELD}{style}{COUNTER_MARK}");
+        let content = format!(
+            "{COUNTER_MARK}chapter{COUNTER_FIELD}decimal{COUNTER_MARK}-{}",
+            page("lower-roman")
+        );
+        assert_eq!(
+            page_counter_style(&content),
+            Some(ListStyleTypeMode::LowerRoman)
+        );
+        assert_eq!(
+            page_counter_style(&format!("Page {COUNTER_MARK}page{COUNTER_MARK}")),
+            Some(ListStyleTypeMode::Decimal)
+        );
+        let nested =
+            format!("{COUNTER_MARK}page{COUNTER_FIELD}decimal{COUNTER_FIELD}.{COUNTER_MARK}");
+        assert_eq!(page_counter_style(&nested), None);
+        assert_eq!(page_counter_style("Page 1"), None);
+    }
+}
@@ -0,0 +1,224 @@
+// What the engine could not use from a stylesheet: rules and declarations lightningcss failed
+// to parse (skipped with error recovery), property values it could not type and the engine did
+// not apply, unknown properties and selectors the engine cannot match. Collected while the
+// style resolver is built, logged as one `css.diagnostics` record and returned by
+// `FullBleed::css_diagnostics`.
+use crate::debug::json_escape;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CssDiagnosticKind {
+    // A rule or declaration did not parse and was skipped.
+    ParseError,
+    // A known property whose value could not be read; the declaration has no effect.
+    InvalidValue,
+    // A property the engine does not implement.
+    UnknownProperty,
+    // A selector the engine cannot match; its rule applies to nothing.
+    UnsupportedSelector,
+}
+
+impl CssDiagnosticKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CssDiagnosticKind::ParseError => "parse_error",
+            CssDiagnosticKind::InvalidValue => "invalid_value",
+            CssDiagnosticKind::UnknownProperty => "unknown_property",
+            CssDiagnosticKind::UnsupportedSelector => "unsupported_selector",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CssDiagnostic {
+    pub kind: CssDiagnosticKind,
+    pub selector: Option<String>,
+    pub property: Option<String>,
+    // 1-based line and column. For declarations this is where their rule starts.
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    pub reason: String,
+    // The CSS came from an asset bundle rather than the `css` argument; `line` counts from
+    // the start of the bundle CSS.
+    pub from_bundle: bool,
+}
+
+impl CssDiagnostic {
+    pub(crate) fn new(kind: CssDiagnosticKind, reason: impl Into<String>) -> Self {
+        Self {
+            kind,
+            selector: None,
+            property: None,
+            line: None,
+            column: None,
+            reason: reason.into(),
+            from_bundle: false,
+        }
+    }
+
+    pub(crate) fn selector(mut self, selector: &str) -> Self {
+        self.selector = Some(selector.to_string());
+        self
+    }
+
+    pub(crate) fn property(mut self, property: impl Into<String>) -> Self {
+        self.property = Some(property.into());
+        self
+    }
+
+    // `line` is 0-based, as lightningcss reports it.
+    pub(crate) fn at(mut self, line: u32, column: u32) -> Self {
+        self.line = Some(line + 1);
+        self.column = Some(column);
+        self
+    }
+}
+
+impl fmt::Display for CssDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, "{line}:{column}: ")?;
+        }
+        if let Some(selector) = &self.selector {
+            write!(f, "{selector} ")?;
+        }
+        if let Some(property) = &self.property {
+            write!(f, "{{ {property} }} ")?;
+        }
+        f.write_str(&self.reason)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CssDiagnostics {
+    pub entries: Vec<CssDiagnostic>,
+}
+
+impl CssDiagnostics {
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, CssDiagnostic> {
+        self.entries.iter()
+    }
+
+    pub(crate) fn push(&mut self, diagnostic: CssDiagnostic) {
+        self.entries.push(diagnostic);
+    }
+
+    // Rebases lines onto the caller's CSS when `bundle_lines` lines of bundle CSS preceded it
+    // in the parsed text.
+    pub(crate) fn rebase(&mut self, bundle_lines: u32) {
+        for entry in &mut self.entries {
+            match entry.line {
+                Some(line) if line > bundle_lines => entry.line = Some(line - bundle_lines),
+                Some(_) => entry.from_bundle = true,
+                None => {}
+            }
+        }
+    }
+
+    pub(crate) fn to_json(&self) -> String {
+        let entries: Vec<String> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                format!(
+                    "{{\"kind\":\"{}\",\"selector\":{},\"property\":{},\"line\":{},\"column\":{},\"reason\":\"{}\",\"from_bundle\":{}}}",
+                    entry.kind.as_str(),
+                    json_opt(entry.selector.as_deref()),
+                    json_opt(entry.property.as_deref()),
+                    entry
+                        .line
+                        .map_or_else(|| "null".to_string(), |v| v.to_string()),
+                    entry
+                        .column
+                        .map_or_else(|| "null".to_string(), |v| v.to_string()),
+                    json_escape(&entry.reason),
+                    entry.from_bundle
+                )
+            })
+            .collect();
+        format!(
+            "{{\"type\":\"css.diagnostics\",\"count\":{},\"entries\":[{}]}}",
+            self.entries.len(),
+            entries.join(",")
+        )
+    }
+}
+
+impl<'a> IntoIterator for &'a CssDiagnostics {
+    type Item = &'a CssDiagnostic;
+    type IntoIter = std::slice::Iter<'a, CssDiagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
+
+fn json_opt(value: Option<&str>) -> String {
+    value.map_or_else(
+        || "null".to_string(),
+        |value| format!("\"{}\"", json_escape(value)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::style::{ElementInfo, StyleResolver};
+    use crate::types::Color;
+    use std::collections::HashMap;
+
+    #[test]
+    fn css_diagnostics_report_skipped_rules_and_dropped_declarations() {
+        let css = "h1 ! p { color: blue; }\n\
+                   p { width: 12 parsecs; frobnicate: 1; }\n\
+                   .ok { color: #ff0000; }";
+        let resolver = StyleResolver::new(css);
+        let diagnostics = resolver.css_diagnostics();
+        let kinds: Vec<_> = diagnostics.iter().map(|d| d.kind).collect();
+        assert!(
+            kinds.contains(&CssDiagnosticKind::ParseError),
+            "{diagnostics:?}"
+        );
+
+        let invalid = diagnostics
+            .iter()
+            .find(|d| d.kind == CssDiagnosticKind::InvalidValue)
+            .expect("invalid width value");
+        assert_eq!(invalid.property.as_deref(), Some("width"));
+        assert_eq!(invalid.selector.as_deref(), Some("p"));
+        assert_eq!(invalid.line, Some(2));
+        let unknown = diagnostics
+            .iter()
+            .find(|d| d.kind == CssDiagnosticKind::UnknownProperty)
+            .expect("unknown property");
+        assert_eq!(unknown.property.as_deref(), Some("frobnicate"));
+
+        // The broken rule no longer takes the rest of the sheet with it.
+        let root = resolver.default_style();
+        let ok = ElementInfo {
+            tag: "p".to_string(),
+            id: None,
+            classes: vec!["ok".to_string()],
+            attrs: HashMap::new(),
+            is_root: false,
+            child_index: 1,
+            child_count: 1,
+            prev_siblings: Vec::new(),
+        };
+        let style = resolver.compute_style(&ok, &root, None, &[]);
+        assert_eq!(style.color, Color::rgb(1.0, 0.0, 0.0));
+        assert!(
+            StyleResolver::new(".ok { color: red; }")
+                .css_diagnostics()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,304 @@
+use kuchiki::traits::TendrilSink;
+use kuchiki::{NodeData, NodeRef};
+use serde_json::Value;
+
+const EACH_ATTR: &str = "data-fb-each";
+const IF_ATTR: &str = "data-fb-if";
+// Elements whose text the serializer writes out verbatim.
+const RAW_TEXT_ELEMENTS: [&str; 8] = [
+    "style",
+    "script",
+    "xmp",
+    "iframe",
+    "noembed",
+    "noframes",
+    "noscript",
+    "plaintext",
+];
+
+// One level of the binding scope: the record at the root, then the current item of each
+// enclosing `data-fb-each`.
+struct Frame<'a> {
+    value: &'a Value,
+    index: Option<usize>,
+}
+
+// Expands a data-bound HTML template against `data`:
+// - `{{path.to.field}}` in text and attribute values is replaced with the value at that path
+//   (array elements by number, e.g. `{{items.0.name}}`). Strings are inserted as text, so
+//   markup in the data is escaped; missing values and `null` insert nothing. Inside raw-text
+//   elements (`<style>`, `<script>`, ...) nothing is escaped on output, so `</` in a value is
+//   written as `<\/` and cannot close the element.
+// - `data-fb-each="items"` repeats the element once per array element. Inside it, paths
+//   resolve against the item first and then the enclosing scopes; `{{this}}` is the item and
+//   `{{@index}}` its 0-based position.
+// - `data-fb-if="path"` keeps the element only when the value is truthy (not missing,
+//   `null`, `false`, `0`, `""`, `[]` or `{}`); `data-fb-if="!path"` negates the test. On a
+//   repeated element it is checked per item.
+// The result is a complete HTML document ready for the normal render path.
+pub fn bind_template(html: &str, data: &Value) -> String {
+    ParsedTemplate::parse(html).bind(data)
+}
+
+// A template parsed once and bound many times; each bind works on a copy of the DOM.
+pub(crate) struct ParsedTemplate {
+    document: NodeRef,
+}
+
+impl ParsedTemplate {
+    pub(crate) fn parse(html: &str) -> Self {
+        Self {
+            document: kuchiki::parse_html().one(html),
+        }
+    }
+
+    pub(crate) fn bind(&self, data: &Value) -> String {
+        self.bind_document(data).to_string()
+    }
+
+    // The bound copy of the DOM, for callers that build a story from it directly.
+    pub(crate) fn bind_document(&self, data: &Value) -> NodeRef {
+        let document = deep_clone(&self.document);
+        let mut scope = vec![Frame {
+            value: data,
+            index: None,
+        }];
+        bind_children(&document, &mut scope);
+        document
+    }
+}
+
+fn bind_children(parent: &NodeRef, scope: &mut Vec<Frame<'_>>) {
+    let children: Vec<NodeRef> = parent.children().collect();
+    for child in children {
+        bind_node(&child, scope);
+    }
+}
+
+fn bind_node(node: &NodeRef, scope: &mut Vec<Frame<'_>>) {
+    match node.data() {
+        NodeData::Text(text) => {
+            let raw_text = node.parent().is_some_and(|parent| {
+                parent
+                    .as_element()
+                    .is_some_and(|element| RAW_TEXT_ELEMENTS.contains(&element.name.local.as_ref()))
+            });
+            let bound = interpolate(&text.borrow(), scope, raw_text);
+            if let Some(bound) = bound {
+                *text.borrow_mut() = bound;
+            }
+        }
+        NodeData::Element(element) => {
+            let each = element.attributes.borrow_mut().remove(EACH_ATTR);
+            if let Some(each) = each {
+                // Copies go in front of the template element, which is then dropped.
+                let items = resolve(each.value.trim(), scope);
+                if let Some(Value::Array(items)) = items {
+                    for (index, item) in items.iter().enumerate() {
+                        let copy = deep_clone(node);
+                        node.insert_before(copy.clone());
+                        scope.push(Frame {
+                            value: item,
+                            index: Some(index),
+                        });
+                        bind_node(&copy, scope);
+                        scope.pop();
+                    }
+                }
+                node.detach();
+                return;
+            }
+            let condition = element.attributes.borrow_mut().remove(IF_ATTR);
+            if let Some(condition) = condition {
+                let condition = condition.value.trim();
+                let (negate, path) = match condition.strip_prefix('!') {
+                    Some(path) => (true, path.trim()),
+                    None => (false, condition),
+                };
+                if truthy(resolve(path, scope)) == negate {
+                    node.detach();
+                    return;
+                }
+            }
+            for attribute in element.attributes.borrow_mut().map.values_mut() {
+                if let Some(bound) = interpolate(&attribute.value, scope, false) {
+                    attribute.value = bound;
+                }
+            }
+            bind_children(node, scope);
+        }
+        _ => bind_children(node, scope),
+    }
+}
+
+// Replaces every `{{path}}` in `text`; `None` when there is nothing to replace. `raw_text`
+// values have `</` broken up so they stay inside their element.
+fn interpolate(text: &str, scope: &[Frame<'_>], raw_text: bool) -> Option<String> {
+    if !text.contains("{{") {
+        return None;
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let path = rest[start + 2..start + 2 + len].trim();
+        if path == "@index" {
+            if let Some(index) = scope.iter().rev().find_map(|frame| frame.index) {
+                out.push_str(&index.to_string());
+            }
+        } else if let Some(value) = resolve(path, scope) {
+            if raw_text {
+                let mut inserted = String::new();
+                push_value(&mut inserted, value);
+                out.push_str(&inserted.replace("</", "<\\/"));
+            } else {
+                push_value(&mut out, value);
+            }
+        }
+        rest = &rest[start + 2 + len + 2..];
+    }
+    out.push_str(rest);
+    Some(out)
+}
+
+fn push_value(out: &mut String, value: &Value) {
+    match value {
+        Value::Null => {}
+        Value::String(text) => out.push_str(text),
+        Value::Bool(flag) => out.push_str(if *flag { "true" } else { "false" }),
+        Value::Number(number) => out.push_str(&number.to_string()),
+        Value::Array(_) | Value::Object(_) => out.push_str(&value.to_string()),
+    }
+}
+
+// Looks `path` up from the innermost scope outwards. The first segment picks the scope
+// (`this` is the innermost item); later segments walk object keys and array indices.
+fn resolve<'a>(path: &str, scope: &[Frame<'a>]) -> Option<&'a Value> {
+    let mut segments = path.split('.').map(str::trim);
+    let first = segments.next().filter(|segment| !segment.is_empty())?;
+    let mut value = if first == "this" {
+        scope.last()?.value
+    } else {
+        scope
+            .iter()
+            .rev()
+            .find_map(|frame| child(frame.value, first))?
+    };
+    for segment in segments {
+        value = child(value, segment)?;
+    }
+    Some(value)
+}
+
+fn child<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    match value {
+        Value::Object(map) => map.get(key),
+        Value::Array(items) => key.parse::<usize>().ok().and_then(|index| items.get(index)),
+        _ => None,
+    }
+}
+
+fn truthy(value: Option<&Value>) -> bool {
+    match value {
+        None | Some(Value::Null) => false,
+        Some(Value::Bool(flag)) => *flag,
+        Some(Value::Number(number)) => number.as_f64().is_some_and(|n| n != 0.0),
+        Some(Value::String(text)) => !text.is_empty(),
+        Some(Value::Array(items)) => !items.is_empty(),
+        Some(Value::Object(map)) => !map.is_empty(),
+    }
+}
+
+// Copy of a whole subtree; kuchiki has no deep clone of its own.
+fn deep_clone(node: &NodeRef) -> NodeRef {
+    let copy = match node.data() {
+        NodeData::Element(element) => NodeRef::new_element(
+            element.name.clone(),
+            element
+                .attributes
+                .borrow()
+                .map
+                .iter()
+                .map(|(name, attribute)| (name.clone(), attribute.clone())),
+        ),
+        NodeData::Text(text) => NodeRef::new_text(text.borrow().clone()),
+        NodeData::Comment(comment) => NodeRef::new_comment(comment.borrow().clone()),
+        NodeData::ProcessingInstruction(instruction) => {
+            let (target, data) = instruction.borrow().clone();
+            NodeRef::new_processing_instruction(target, data)
+        }
+        NodeData::Doctype(doctype) => NodeRef::new_doctype(
+            doctype.name.clone(),
+            doctype.public_id.clone(),
+            doctype.system_id.clone(),
+        ),
+        NodeData::Document(_) => NodeRef::new_document(),
+        NodeData::DocumentFragment => NodeRef::new(NodeData::DocumentFragment),
+    };
+    for child in node.children() {
+        copy.append(deep_clone(&child));
+    }
+    copy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FullBleed;
+
+    #[test]
+    fn render_with_data_binds_fields_loops_and_conditionals() {
+        let data = serde_json::json!({
+            "customer": { "name": "Ada <Lovelace>", "vip": true },
+            "currency": "EUR",
+            "items": [
+                { "sku": "A-1", "qty": 2, "note": "fragile" },
+                { "sku": "B-7", "qty": 1, "note": "" }
+            ],
+            "discount": 0
+        });
+        let template = concat!(
+            "<h1 title=\"{{customer.name}}\">Invoice for {{customer.name}}</h1>",
+            "<p data-fb-if=\"customer.vip\">VIP</p>",
+            "<p data-fb-if=\"discount\">Discount</p>",
+            "<p data-fb-if=\"!discount\">No discount</p>",
+            "<ul><li data-fb-each=\"items\">{{@index}}:{{sku}}x{{qty}} {{currency}}",
+            "<em data-fb-if=\"note\">({{this.note}})</em></li></ul>",
+            "<p>First: {{items.0.sku}}{{missing.path}}</p>"
+        );
+        let html = bind_template(template, &data);
+        // Serializers differ on escaping `<` inside quoted attributes; read the value back.
+        let title = {
+            let bound = kuchiki::parse_html().one(html.as_str());
+            let h1 = bound.select_first("h1").expect("h1");
+            h1.attributes.borrow().get("title").map(str::to_string)
+        };
+        assert_eq!(title.as_deref(), Some("Ada <Lovelace>"));
+        assert!(html.contains("Invoice for Ada &lt;Lovelace&gt;"));
+        assert!(html.contains("<p>VIP</p>"));
+        assert!(!html.contains(">Discount<"));
+        assert!(html.contains("<p>No discount</p>"));
+        assert!(html.contains("<li>0:A-1x2 EUR<em>(fragile)</em></li><li>1:B-7x1 EUR</li>"));
+        assert!(html.contains("<p>First: A-1</p>"));
+        assert!(!html.contains("data-fb-"));
+        assert!(!html.contains("{{"));
+
+        let engine = FullBleed::builder().build().expect("engine");
+        let pdf = engine.render_with_data(template, "", &data).expect("pdf");
+        assert!(pdf.starts_with(b"%PDF"));
+    }
+
+    #[test]
+    fn bound_values_stay_inside_raw_text_elements() {
+        let data = serde_json::json!({ "accent": "red}</style><img src=x>" });
+        let template = "<style>.a{color:{{accent}}}</style><p class=\"a\">Hi</p>";
+        let html = bind_template(template, &data);
+        assert!(html.contains("<style>.a{color:red}<\\/style><img src=x>}</style>"));
+        let bound = kuchiki::parse_html().one(html.as_str());
+        assert!(bound.select_first("img").is_err());
+        assert_eq!(bound.select("style").expect("style").count(), 1);
+    }
+}
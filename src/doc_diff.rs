@@ -0,0 +1,713 @@
+// Structured comparison of two rendered documents. Text runs and placed images are aligned
+// page by page (longest common subsequence over their text / resource ids), so an inserted
+// line shows up as one addition instead of shifting every later run; the remaining paint
+// commands are compared as a whole. The optional raster mode renders both sides and counts
+// differing pixels per page.
+use crate::FullBleed;
+use crate::canvas::{Command, Document};
+use crate::error::FullBleedError;
+use crate::font::FontRegistry;
+use crate::raster;
+use crate::types::{Pt, Size};
+use std::fmt;
+use std::ops::Range;
+use tiny_skia::Pixmap;
+
+// Alignment tables larger than this fall back to pairing items by position in the list.
+const MAX_ALIGN_CELLS: usize = 4_000_000;
+
+// Options for [`Document::diff_with`].
+#[derive(Debug, Clone, Copy)]
+pub struct DiffOptions {
+    // Position changes up to this distance on either axis are not reported.
+    pub tolerance: Pt,
+    // Also rasterize both documents at this DPI and compare them pixel by pixel.
+    pub raster_dpi: Option<u32>,
+    // Largest per-channel difference (0-255) that still counts as the same pixel.
+    pub channel_tolerance: u8,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            tolerance: Pt::from_f32(0.5),
+            raster_dpi: None,
+            channel_tolerance: 0,
+        }
+    }
+}
+
+// What moved in a [`PageChange::Moved`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffItem {
+    Text(String),
+    // An image or form XObject, by resource id.
+    Image(String),
+}
+
+// One difference on a page, going from the first document to the second. Positions are
+// in page space and include enclosing translations.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PageChange {
+    // The page exists only in the second document.
+    PageAdded,
+    // The page exists only in the first document.
+    PageRemoved,
+    PageResized {
+        from: Size,
+        to: Size,
+    },
+    TextChanged {
+        from: String,
+        to: String,
+        x: Pt,
+        y: Pt,
+    },
+    TextAdded {
+        text: String,
+        x: Pt,
+        y: Pt,
+    },
+    TextRemoved {
+        text: String,
+        x: Pt,
+        y: Pt,
+    },
+    // Same content, drawn more than the tolerance away from where it was.
+    Moved {
+        item: DiffItem,
+        from: (Pt, Pt),
+        to: (Pt, Pt),
+    },
+    ImageSwapped {
+        from: String,
+        to: String,
+        x: Pt,
+        y: Pt,
+    },
+    ImageAdded {
+        resource_id: String,
+        x: Pt,
+        y: Pt,
+    },
+    ImageRemoved {
+        resource_id: String,
+        x: Pt,
+        y: Pt,
+    },
+    // Paths, fills, colors or other paint state differ. Counts are the paint commands on
+    // each side.
+    GraphicsChanged {
+        from: usize,
+        to: usize,
+    },
+}
+
+// Pixel comparison of one page in raster mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PixelDiff {
+    pub width: u32,
+    pub height: u32,
+    pub differing_pixels: u64,
+    // Smallest pixel box `(x0, y0, x1, y1)`, end-exclusive, holding every differing pixel.
+    pub bounds: Option<(u32, u32, u32, u32)>,
+}
+
+impl PixelDiff {
+    // Share of the page's pixels that differ, from 0.0 to 1.0.
+    pub fn ratio(&self) -> f64 {
+        let total = self.width as u64 * self.height as u64;
+        if total == 0 {
+            0.0
+        } else {
+            self.differing_pixels as f64 / total as f64
+        }
+    }
+}
+
+// Differences on one page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageDiff {
+    // 1-based page number.
+    pub page_number: usize,
+    pub changes: Vec<PageChange>,
+    // Set in raster mode when both documents have the page.
+    pub pixels: Option<PixelDiff>,
+}
+
+// Result of [`Document::diff`]; lists only pages that differ.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DocumentDiff {
+    pub pages: Vec<PageDiff>,
+}
+
+impl DocumentDiff {
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+
+    pub fn page(&self, page_number: usize) -> Option<&PageDiff> {
+        self.pages
+            .iter()
+            .find(|page| page.page_number == page_number)
+    }
+}
+
+impl fmt::Display for DocumentDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.pages.is_empty() {
+            return writeln!(f, "no differences");
+        }
+        for page in &self.pages {
+            writeln!(f, "page {}:", page.page_number)?;
+            for change in &page.changes {
+                writeln!(f, "  {}", change)?;
+            }
+            if let Some(pixels) = page.pixels {
+                writeln!(
+                    f,
+                    "  {} of {}x{} pixels differ",
+                    pixels.differing_pixels, pixels.width, pixels.height
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for PageChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let at = |x: &Pt, y: &Pt| format!("({:.2}, {:.2})", x.to_f32(), y.to_f32());
+        match self {
+            PageChange::PageAdded => write!(f, "page added"),
+            PageChange::PageRemoved => write!(f, "page removed"),
+            PageChange::PageResized { from, to } => write!(
+                f,
+                "page resized {:.2}x{:.2} -> {:.2}x{:.2}",
+                from.width.to_f32(),
+                from.height.to_f32(),
+                to.width.to_f32(),
+                to.height.to_f32()
+            ),
+            PageChange::TextChanged { from, to, x, y } => {
+                write!(f, "text {:?} -> {:?} at {}", from, to, at(x, y))
+            }
+            PageChange::TextAdded { text, x, y } => {
+                write!(f, "text added {:?} at {}", text, at(x, y))
+            }
+            PageChange::TextRemoved { text, x, y } => {
+                write!(f, "text removed {:?} at {}", text, at(x, y))
+            }
+            PageChange::Moved { item, from, to } => {
+                let label = match item {
+                    DiffItem::Text(text) => format!("text {:?}", text),
+                    DiffItem::Image(id) => format!("image {}", id),
+                };
+                write!(
+                    f,
+                    "{} moved {} -> {}",
+                    label,
+                    at(&from.0, &from.1),
+                    at(&to.0, &to.1)
+                )
+            }
+            PageChange::ImageSwapped { from, to, x, y } => {
+                write!(f, "image {} -> {} at {}", from, to, at(x, y))
+            }
+            PageChange::ImageAdded { resource_id, x, y } => {
+                write!(f, "image added {} at {}", resource_id, at(x, y))
+            }
+            PageChange::ImageRemoved { resource_id, x, y } => {
+                write!(f, "image removed {} at {}", resource_id, at(x, y))
+            }
+            PageChange::GraphicsChanged { from, to } => {
+                write!(f, "graphics changed ({} -> {} commands)", from, to)
+            }
+        }
+    }
+}
+
+impl Document {
+    // Command-level differences from `self` to `other` with the default options.
+    pub fn diff(&self, other: &Document) -> DocumentDiff {
+        diff_commands(self, other, &DiffOptions::default())
+    }
+
+    // Like [`Document::diff`]; raster mode draws text with the fallback renderer only. Use
+    // [`FullBleed::diff_documents`] to rasterize with the engine's fonts.
+    pub fn diff_with(
+        &self,
+        other: &Document,
+        options: &DiffOptions,
+    ) -> Result<DocumentDiff, FullBleedError> {
+        diff_documents(self, other, options, None, true)
+    }
+}
+
+impl FullBleed {
+    // [`Document::diff_with`] rasterizing with this engine's fonts and shaping settings.
+    pub fn diff_documents(
+        &self,
+        before: &Document,
+        after: &Document,
+        options: &DiffOptions,
+    ) -> Result<DocumentDiff, FullBleedError> {
+        diff_documents(
+            before,
+            after,
+            options,
+            Some(self.font_registry.as_ref()),
+            self.pdf_options.shape_text,
+        )
+    }
+}
+
+fn diff_documents(
+    before: &Document,
+    after: &Document,
+    options: &DiffOptions,
+    registry: Option<&FontRegistry>,
+    shape_text: bool,
+) -> Result<DocumentDiff, FullBleedError> {
+    let mut diff = diff_commands(before, after, options);
+    let Some(dpi) = options.raster_dpi else {
+        return Ok(diff);
+    };
+    let before_pixmaps = raster::document_to_pixmaps(before, dpi, registry, shape_text)?;
+    let after_pixmaps = raster::document_to_pixmaps(after, dpi, registry, shape_text)?;
+    for (index, (old, new)) in before_pixmaps.iter().zip(&after_pixmaps).enumerate() {
+        let pixels = compare_pixmaps(old, new, options.channel_tolerance);
+        let page_number = index + 1;
+        match diff
+            .pages
+            .iter_mut()
+            .find(|page| page.page_number == page_number)
+        {
+            Some(page) => page.pixels = Some(pixels),
+            None if pixels.differing_pixels > 0 => diff.pages.push(PageDiff {
+                page_number,
+                changes: Vec::new(),
+                pixels: Some(pixels),
+            }),
+            None => {}
+        }
+    }
+    diff.pages.sort_by_key(|page| page.page_number);
+    Ok(diff)
+}
+
+fn diff_commands(before: &Document, after: &Document, options: &DiffOptions) -> DocumentDiff {
+    let mut pages = Vec::new();
+    let page_count = before.pages.len().max(after.pages.len());
+    for index in 0..page_count {
+        let changes = match (before.pages.get(index), after.pages.get(index)) {
+            (Some(_), None) => vec![PageChange::PageRemoved],
+            (None, Some(_)) => vec![PageChange::PageAdded],
+            (Some(old), Some(new)) => {
+                let mut changes = Vec::new();
+                let (from, to) = (before.page_size_at(index), after.page_size_at(index));
+                if from != to {
+                    changes.push(PageChange::PageResized { from, to });
+                }
+                let (old, new) = (old.commands.to_vec(), new.commands.to_vec());
+                let (old, new) = (PageItems::collect(&old), PageItems::collect(&new));
+                diff_text(&old.text, &new.text, options.tolerance, &mut changes);
+                diff_images(&old.images, &new.images, options.tolerance, &mut changes);
+                if old.graphics != new.graphics {
+                    changes.push(PageChange::GraphicsChanged {
+                        from: old.graphics.len(),
+                        to: new.graphics.len(),
+                    });
+                }
+                changes
+            }
+            (None, None) => Vec::new(),
+        };
+        if !changes.is_empty() {
+            pages.push(PageDiff {
+                page_number: index + 1,
+                changes,
+                pixels: None,
+            });
+        }
+    }
+    DocumentDiff { pages }
+}
+
+struct Placed<'a> {
+    key: &'a str,
+    x: Pt,
+    y: Pt,
+}
+
+impl Placed<'_> {
+    fn moved(&self, other: &Placed<'_>, tolerance: Pt) -> bool {
+        (self.x - other.x).abs() > tolerance || (self.y - other.y).abs() > tolerance
+    }
+}
+
+struct PageItems<'a> {
+    text: Vec<Placed<'a>>,
+    images: Vec<Placed<'a>>,
+    // Debug form of every other paint command, in order.
+    graphics: Vec<String>,
+}
+
+impl<'a> PageItems<'a> {
+    // Only translations are tracked for positions; scaled or rotated content still compares
+    // by content and order.
+    fn collect(commands: &'a [Command]) -> Self {
+        let mut items = Self {
+            text: Vec::new(),
+            images: Vec::new(),
+            graphics: Vec::new(),
+        };
+        // Translations are in PDF space, where y grows upwards.
+        let mut offset = (Pt::ZERO, Pt::ZERO);
+        let mut stack = Vec::new();
+        for command in commands {
+            match command {
+                Command::SaveState => stack.push(offset),
+                Command::RestoreState => offset = stack.pop().unwrap_or((Pt::ZERO, Pt::ZERO)),
+                Command::Translate(x, y) => offset = (offset.0 + *x, offset.1 + *y),
+                Command::ConcatMatrix { e, f, .. } => offset = (offset.0 + *e, offset.1 + *f),
+                Command::DrawString { x, y, text }
+                | Command::DrawStringTransformed { x, y, text, .. } => {
+                    items.text.push(Placed {
+                        key: text,
+                        x: offset.0 + *x,
+                        y: *y - offset.1,
+                    });
+                    continue;
+                }
+                Command::DrawImage {
+                    x, y, resource_id, ..
+                }
+                | Command::DrawForm {
+                    x, y, resource_id, ..
+                } => {
+                    items.images.push(Placed {
+                        key: resource_id,
+                        x: offset.0 + *x,
+                        y: *y - offset.1,
+                    });
+                    continue;
+                }
+                // Not painted, or compared through what they define.
+                Command::Meta { .. }
+                | Command::DefineForm { .. }
+                | Command::BeginTag { .. }
+                | Command::EndTag
+                | Command::BeginArtifact { .. }
+                | Command::BeginOptionalContent { .. }
+                | Command::EndMarkedContent
+                | Command::LinkAnnotation { .. }
+                | Command::NamedDestination { .. }
+                | Command::Bookmark { .. }
+                | Command::SignatureField { .. } => continue,
+                _ => {}
+            }
+            items.graphics.push(format!("{:?}", command));
+        }
+        items
+    }
+}
+
+fn diff_text(old: &[Placed<'_>], new: &[Placed<'_>], tolerance: Pt, out: &mut Vec<PageChange>) {
+    for step in align(old, new) {
+        match step {
+            Aligned::Same(i, j) => {
+                if old[i].moved(&new[j], tolerance) {
+                    out.push(PageChange::Moved {
+                        item: DiffItem::Text(new[j].key.to_string()),
+                        from: (old[i].x, old[i].y),
+                        to: (new[j].x, new[j].y),
+                    });
+                }
+            }
+            Aligned::Changed(i, j) => out.push(PageChange::TextChanged {
+                from: old[i].key.to_string(),
+                to: new[j].key.to_string(),
+                x: new[j].x,
+                y: new[j].y,
+            }),
+            Aligned::Removed(i) => out.push(PageChange::TextRemoved {
+                text: old[i].key.to_string(),
+                x: old[i].x,
+                y: old[i].y,
+            }),
+            Aligned::Added(j) => out.push(PageChange::TextAdded {
+                text: new[j].key.to_string(),
+                x: new[j].x,
+                y: new[j].y,
+            }),
+        }
+    }
+}
+
+fn diff_images(old: &[Placed<'_>], new: &[Placed<'_>], tolerance: Pt, out: &mut Vec<PageChange>) {
+    for step in align(old, new) {
+        match step {
+            Aligned::Same(i, j) => {
+                if old[i].moved(&new[j], tolerance) {
+                    out.push(PageChange::Moved {
+                        item: DiffItem::Image(new[j].key.to_string()),
+                        from: (old[i].x, old[i].y),
+                        to: (new[j].x, new[j].y),
+                    });
+                }
+            }
+            Aligned::Changed(i, j) => out.push(PageChange::ImageSwapped {
+                from: old[i].key.to_string(),
+                to: new[j].key.to_string(),
+                x: new[j].x,
+                y: new[j].y,
+            }),
+            Aligned::Removed(i) => out.push(PageChange::ImageRemoved {
+                resource_id: old[i].key.to_string(),
+                x: old[i].x,
+                y: old[i].y,
+            }),
+            Aligned::Added(j) => out.push(PageChange::ImageAdded {
+                resource_id: new[j].key.to_string(),
+                x: new[j].x,
+                y: new[j].y,
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Aligned {
+    Same(usize, usize),
+    // Unmatched items facing each other between two matches.
+    Changed(usize, usize),
+    Removed(usize),
+    Added(usize),
+}
+
+fn align(old: &[Placed<'_>], new: &[Placed<'_>]) -> Vec<Aligned> {
+    let prefix = old
+        .iter()
+        .zip(new)
+        .take_while(|(a, b)| a.key == b.key)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a.key == b.key)
+        .count();
+    let old_mid = prefix..old.len() - suffix;
+    let new_mid = prefix..new.len() - suffix;
+
+    let mut out: Vec<Aligned> = (0..prefix).map(|i| Aligned::Same(i, i)).collect();
+    let matches = if old_mid.len().saturating_mul(new_mid.len()) <= MAX_ALIGN_CELLS {
+        common_subsequence(&old[old_mid.clone()], &new[new_mid.clone()])
+    } else {
+        Vec::new()
+    };
+    let (mut i, mut j) = (old_mid.start, new_mid.start);
+    for (a, b) in matches {
+        let (a, b) = (a + prefix, b + prefix);
+        push_gap(&mut out, i..a, j..b);
+        out.push(Aligned::Same(a, b));
+        i = a + 1;
+        j = b + 1;
+    }
+    push_gap(&mut out, i..old_mid.end, j..new_mid.end);
+    out.extend((0..suffix).map(|k| Aligned::Same(old_mid.end + k, new_mid.end + k)));
+    out
+}
+
+fn push_gap(out: &mut Vec<Aligned>, old: Range<usize>, new: Range<usize>) {
+    let paired = old.len().min(new.len());
+    out.extend(
+        old.clone()
+            .zip(new.clone())
+            .map(|(i, j)| Aligned::Changed(i, j)),
+    );
+    out.extend(old.skip(paired).map(Aligned::Removed));
+    out.extend(new.skip(paired).map(Aligned::Added));
+}
+
+// Index pairs of a longest common subsequence of the two key lists.
+fn common_subsequence(old: &[Placed<'_>], new: &[Placed<'_>]) -> Vec<(usize, usize)> {
+    let width = new.len() + 1;
+    // lengths[i * width + j] is the LCS length of old[i..] and new[j..].
+    let mut lengths = vec![0u32; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i * width + j] = if old[i].key == new[j].key {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i].key == new[j].key {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+// Pixels outside the overlap of differently sized pages all count as differing.
+fn compare_pixmaps(old: &Pixmap, new: &Pixmap, channel_tolerance: u8) -> PixelDiff {
+    let width = old.width().max(new.width());
+    let height = old.height().max(new.height());
+    let mut differing_pixels = 0u64;
+    let mut bounds: Option<(u32, u32, u32, u32)> = None;
+    for y in 0..height {
+        for x in 0..width {
+            let same = match (old.pixel(x, y), new.pixel(x, y)) {
+                (Some(a), Some(b)) => {
+                    a.red().abs_diff(b.red()) <= channel_tolerance
+                        && a.green().abs_diff(b.green()) <= channel_tolerance
+                        && a.blue().abs_diff(b.blue()) <= channel_tolerance
+                        && a.alpha().abs_diff(b.alpha()) <= channel_tolerance
+                }
+                _ => false,
+            };
+            if !same {
+                differing_pixels += 1;
+                bounds = Some(match bounds {
+                    Some((x0, y0, x1, y1)) => (x0.min(x), y0.min(y), x1.max(x + 1), y1.max(y + 1)),
+                    None => (x, y, x + 1, y + 1),
+                });
+            }
+        }
+    }
+    PixelDiff {
+        width,
+        height,
+        differing_pixels,
+        bounds,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canvas::Page;
+
+    #[test]
+    fn document_diff_reports_text_image_and_geometry_changes() {
+        let page = |commands: Vec<Command>| Page {
+            commands: commands.into(),
+            size: None,
+            rotate: 0,
+        };
+        let text = |x: f32, y: f32, text: &str| Command::DrawString {
+            x: Pt::from_f32(x),
+            y: Pt::from_f32(y),
+            text: text.to_string(),
+        };
+        let image = |resource_id: &str| Command::DrawImage {
+            x: Pt::from_f32(72.0),
+            y: Pt::from_f32(400.0),
+            width: Pt::from_f32(100.0),
+            height: Pt::from_f32(50.0),
+            resource_id: resource_id.into(),
+        };
+        let size = Size::from_inches(8.5, 11.0);
+        let before = Document {
+            page_size: size,
+            pages: vec![
+                page(vec![
+                    text(72.0, 72.0, "Invoice"),
+                    text(72.0, 90.0, "Total 10.00"),
+                    text(72.0, 108.0, "Thanks"),
+                    image("logo-a"),
+                ]),
+                page(vec![text(72.0, 72.0, "Terms")]),
+            ],
+        };
+        assert!(before.diff(&before.clone()).is_empty());
+
+        let after = Document {
+            page_size: size,
+            pages: vec![page(vec![
+                text(72.0, 72.2, "Invoice"),
+                text(72.0, 90.0, "Total 12.50"),
+                text(72.0, 100.0, "Due today"),
+                text(72.0, 126.0, "Thanks"),
+                image("logo-b"),
+                Command::DrawRect {
+                    x: Pt::ZERO,
+                    y: Pt::ZERO,
+                    width: Pt::from_f32(10.0),
+                    height: Pt::from_f32(10.0),
+                },
+            ])],
+        };
+        let diff = before.diff(&after);
+        let first = diff.page(1).expect("page 1 differs");
+        assert_eq!(
+            first.changes,
+            vec![
+                PageChange::TextChanged {
+                    from: "Total 10.00".to_string(),
+                    to: "Total 12.50".to_string(),
+                    x: Pt::from_f32(72.0),
+                    y: Pt::from_f32(90.0),
+                },
+                PageChange::TextAdded {
+                    text: "Due today".to_string(),
+                    x: Pt::from_f32(72.0),
+                    y: Pt::from_f32(100.0),
+                },
+                PageChange::Moved {
+                    item: DiffItem::Text("Thanks".to_string()),
+                    from: (Pt::from_f32(72.0), Pt::from_f32(108.0)),
+                    to: (Pt::from_f32(72.0), Pt::from_f32(126.0)),
+                },
+                PageChange::ImageSwapped {
+                    from: "logo-a".to_string(),
+                    to: "logo-b".to_string(),
+                    x: Pt::from_f32(72.0),
+                    y: Pt::from_f32(400.0),
+                },
+                PageChange::GraphicsChanged { from: 0, to: 1 },
+            ]
+        );
+        assert_eq!(
+            diff.page(2).expect("page 2 removed").changes,
+            vec![PageChange::PageRemoved]
+        );
+        assert!(
+            diff.to_string()
+                .contains("text \"Total 10.00\" -> \"Total 12.50\"")
+        );
+
+        // Filled boxes rather than text, so the raster diff does not depend on system fonts.
+        let engine = FullBleed::builder().build().expect("engine");
+        let options = DiffOptions {
+            raster_dpi: Some(36),
+            ..DiffOptions::default()
+        };
+        let css = "div { width: 200pt; height: 40pt; margin-bottom: 20pt; background: #000; }";
+        let old = engine
+            .render_to_document("<div></div>", css)
+            .expect("render");
+        let new = engine
+            .render_to_document("<div></div><div></div>", css)
+            .expect("render");
+        let same = engine.diff_documents(&old, &old, &options).expect("diff");
+        assert!(same.is_empty());
+        let visual = engine.diff_documents(&old, &new, &options).expect("diff");
+        let pixels = visual.page(1).and_then(|page| page.pixels).expect("pixels");
+        assert!(pixels.differing_pixels > 0);
+        assert!(pixels.bounds.is_some());
+        assert!(pixels.ratio() < 0.5);
+    }
+}
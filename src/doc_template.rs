@@ -1,16 +1,45 @@
-use crate::canvas::{Canvas, Document, META_PAGINATION_EVENT_KEY};
+use crate::canvas::{Canvas, Command, Document, META_PAGINATION_EVENT_KEY};
+use crate::command_buffer::CommandBuffer;
 use crate::debug::{DebugLogger, json_escape};
 use crate::doc_context::DocContext;
-use crate::error::FullBleedError;
-use crate::flowable::{BreakAfter, BreakBefore, Flowable};
-use crate::frame::{AddResult, AddTrace};
+use crate::error::{FullBleedError, SourceLocation};
+use crate::flowable::{
+    BreakAfter, BreakBefore, Flowable, META_FRAME_KEY, MetaFlowable, can_start_in,
+    draw_abs_continuation,
+};
+use crate::frame::{AddResult, AddTrace, Frame};
+use crate::known_loss::KnownLoss;
+use crate::layout_profile;
 use crate::metrics::{DocumentMetrics, PageMetrics};
-use crate::page_template::PageTemplate;
-use crate::types::Pt;
+use crate::page_template::{OnPageCallback, PageParity, PageTemplate};
+use crate::platform::Instant;
+use crate::progress::{ProgressEvent, ProgressHandle};
+use crate::types::{Color, Pt, Rect, Size};
+use crate::warning::{self, Warning, WarningHandle, WarningKind};
 use base64::Engine;
 use std::collections::VecDeque;
 use std::sync::Arc;
-use std::time::Instant;
+
+// Element a flowable was built from, as recorded in its `fb.owner.*` metadata.
+fn owner_location(meta: &[(String, String)]) -> SourceLocation {
+    let value = |key: &str| {
+        meta.iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.clone())
+    };
+    SourceLocation {
+        element_path: value("fb.owner.dom_path"),
+        selector: value("fb.owner.selector"),
+        ..SourceLocation::default()
+    }
+}
+
+// Layout profile frame of a top-level flowable: its element path, else its type.
+fn profiled_element(flowable: &dyn Flowable) -> String {
+    owner_location(&flowable.diagnostic_metadata())
+        .element_path
+        .unwrap_or_else(|| flowable.debug_name().to_string())
+}
 
 fn bool_to_flag(value: bool) -> u8 {
     if value { 1 } else { 0 }
@@ -59,7 +88,7 @@ fn emit_pagination_layout_event(
     trace: AddTrace,
     overflow_severity: Option<&str>,
 ) {
-    let placed = trace.placed_rect.unwrap_or(crate::types::Rect {
+    let placed = trace.placed_rect.unwrap_or(Rect {
         x: Pt::ZERO,
         y: Pt::ZERO,
         width: Pt::ZERO,
@@ -146,29 +175,439 @@ fn emit_pagination_transition_event(
     logger.increment("jit.page_break.trigger", 1);
 }
 
+// `position: fixed` overlays painted on every page. The overlays are drawn once per page size
+// and reused as a Form XObject; each page only places the form. Overlays that record links,
+// anchors, bookmarks or structure tags are drawn directly on every page instead, since those
+// records must stay in the page's own command list.
+struct FixedOverlays {
+    layer: &'static str,
+    overlays: Vec<Box<dyn Flowable>>,
+    forms: Vec<(Size, Option<FixedOverlayForm>)>,
+}
+
+struct FixedOverlayForm {
+    resource_id: String,
+    commands: CommandBuffer,
+    // Metadata records are not painted; they stay on the page, ahead of the form.
+    meta: Vec<(String, String)>,
+}
+
+impl FixedOverlays {
+    fn new(layer: &'static str) -> Self {
+        Self {
+            layer,
+            overlays: Vec::new(),
+            forms: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, overlay: Box<dyn Flowable>) {
+        self.overlays.push(overlay);
+    }
+
+    fn sort_by_z_index(&mut self) {
+        // Lower z-index paints first, higher z-index paints later (on top).
+        self.overlays.sort_by_key(|overlay| overlay.z_index());
+    }
+
+    fn is_empty(&self) -> bool {
+        self.overlays.is_empty()
+    }
+
+    fn draw(&mut self, canvas: &mut Canvas, page_flowables: &mut usize) {
+        if self.overlays.is_empty() {
+            return;
+        }
+        let page_size = canvas.page_size();
+        let index = match self.forms.iter().position(|(size, _)| *size == page_size) {
+            Some(index) => index,
+            None => {
+                let form = self.record_form(page_size);
+                self.forms.push((page_size, form));
+                self.forms.len() - 1
+            }
+        };
+        *page_flowables += self.overlays.len();
+        let Some(form) = &self.forms[index].1 else {
+            for overlay in &self.overlays {
+                overlay.draw(
+                    canvas,
+                    Pt::ZERO,
+                    Pt::ZERO,
+                    page_size.width,
+                    page_size.height,
+                );
+            }
+            return;
+        };
+        for (key, value) in &form.meta {
+            canvas.meta(key.clone(), value.clone());
+        }
+        canvas.define_form(
+            form.resource_id.clone(),
+            page_size.width,
+            page_size.height,
+            form.commands.clone(),
+        );
+        canvas.draw_form(
+            Pt::ZERO,
+            Pt::ZERO,
+            page_size.width,
+            page_size.height,
+            form.resource_id.clone(),
+        );
+    }
+
+    fn record_form(&self, page_size: Size) -> Option<FixedOverlayForm> {
+        let mut scratch = Canvas::new(page_size);
+        for overlay in &self.overlays {
+            overlay.draw(
+                &mut scratch,
+                Pt::ZERO,
+                Pt::ZERO,
+                page_size.width,
+                page_size.height,
+            );
+        }
+        let drawn = scratch.finish().pages.pop()?.commands;
+        // The form inherits the graphics state in effect where it is placed, so start it
+        // from the canvas defaults the overlay commands were recorded against.
+        let mut commands = vec![
+            Command::SetFillColor(Color::BLACK),
+            Command::SetStrokeColor(Color::BLACK),
+            Command::SetLineWidth(Pt::from_f32(1.0)),
+        ];
+        let mut meta = Vec::new();
+        for command in drawn {
+            match command {
+                Command::Meta { key, value } => meta.push((key, value)),
+                Command::BeginTag { .. }
+                | Command::EndTag
+                | Command::BeginArtifact { .. }
+                | Command::BeginOptionalContent { .. }
+                | Command::EndMarkedContent
+                | Command::LinkAnnotation { .. }
+                | Command::NamedDestination { .. }
+                | Command::Bookmark { .. }
+                | Command::SignatureField { .. } => return None,
+                _ => commands.push(command),
+            }
+        }
+        let resource_id = format!(
+            "fixed-{}-{:016x}",
+            self.layer,
+            crate::hash_bytes_local(format!("{commands:?}").as_bytes())
+        );
+        Some(FixedOverlayForm {
+            resource_id,
+            commands: commands.into(),
+            meta,
+        })
+    }
+}
+
+// Space between body text and the first footnote of a frame; the separator rule sits in it.
+const FOOTNOTE_SEPARATOR_HEIGHT: f32 = 9.0;
+
+// Footnotes of the frame being filled. Space is reserved at the bottom of the frame as each
+// reference is placed, and the bodies are drawn when layout leaves the frame. A body that does
+// not fit is split; the rest, and every later body, continues in the next frame.
+struct FootnoteArea {
+    frame: Option<(usize, usize)>,
+    bounds: Rect,
+    placed: Vec<(Box<dyn Flowable>, Pt)>,
+    carried: VecDeque<Box<dyn Flowable>>,
+}
+
+impl FootnoteArea {
+    fn new() -> Self {
+        Self {
+            frame: None,
+            bounds: Rect {
+                x: Pt::ZERO,
+                y: Pt::ZERO,
+                width: Pt::ZERO,
+                height: Pt::ZERO,
+            },
+            placed: Vec::new(),
+            carried: VecDeque::new(),
+        }
+    }
+
+    // Switch to the frame identified by `(page_number, frame_index)`, drawing the previous
+    // frame's footnotes and starting the new frame with any carried bodies.
+    fn enter_frame(&mut self, key: (usize, usize), frame: &mut Frame, canvas: &mut Canvas) {
+        if self.frame == Some(key) {
+            return;
+        }
+        self.flush(canvas);
+        self.frame = Some(key);
+        self.reserve(frame, Vec::new());
+    }
+
+    fn reserve(&mut self, frame: &mut Frame, notes: Vec<Box<dyn Flowable>>) {
+        self.carried.extend(notes);
+        let width = frame.rect().width;
+        while let Some(note) = self.carried.pop_front() {
+            let separator = if self.placed.is_empty() {
+                Pt::from_f32(FOOTNOTE_SEPARATOR_HEIGHT)
+            } else {
+                Pt::ZERO
+            };
+            let avail = frame.remaining_height() - separator;
+            let height = note.wrap(width, avail.max(Pt::ZERO)).height;
+            if avail > Pt::ZERO && height <= avail {
+                frame.reserve_bottom(separator + height);
+                self.placed.push((note, height));
+                continue;
+            }
+            if avail > Pt::ZERO
+                && let Some((first, rest)) = note.split(width, avail)
+            {
+                let first_height = first.wrap(width, avail).height;
+                if first_height > Pt::ZERO && first_height <= avail {
+                    frame.reserve_bottom(separator + first_height);
+                    self.placed.push((first, first_height));
+                    self.carried.push_front(rest);
+                    break;
+                }
+            }
+            if frame.is_empty() && self.placed.is_empty() {
+                // Nothing else competes for this frame: place the body overfull instead of
+                // carrying it forward forever.
+                frame.reserve_bottom(separator + height);
+                self.placed.push((note, height));
+                continue;
+            }
+            self.carried.push_front(note);
+            break;
+        }
+        self.bounds = frame.rect();
+    }
+
+    fn flush(&mut self, canvas: &mut Canvas) {
+        if self.placed.is_empty() {
+            return;
+        }
+        let Rect {
+            x,
+            y,
+            width,
+            height,
+        } = self.bounds;
+        let separator = Pt::from_f32(FOOTNOTE_SEPARATOR_HEIGHT);
+        let mut cursor = y + height;
+        let rule_y = cursor + separator / 2;
+        canvas.save_state();
+        canvas.set_stroke_color(Color::BLACK);
+        canvas.set_line_width(Pt::from_f32(0.5));
+        canvas.move_to(x, rule_y);
+        canvas.line_to(x + width / 3, rule_y);
+        canvas.stroke();
+        canvas.restore_state();
+        cursor += separator;
+        for (note, note_height) in self.placed.drain(..) {
+            note.draw(canvas, x, cursor, width, note_height);
+            cursor += note_height;
+        }
+    }
+}
+
 pub struct DocTemplate {
     page_templates: Vec<PageTemplate>,
+    // Templates chosen by page name or page parity instead of by index.
+    keyed_page_templates: Vec<PageTemplate>,
     story: Vec<Box<dyn Flowable>>,
     debug: Option<Arc<DebugLogger>>,
     debug_doc_id: Option<usize>,
+    first_page_number: usize,
+    on_page_start: Option<OnPageCallback>,
+    on_page_end: Option<OnPageCallback>,
+    // Told about every finished page, tagged with the document id.
+    progress: Option<(ProgressHandle, usize)>,
+    // Known-loss warnings, tagged with the document id.
+    warnings: Option<WarningHandle>,
+    warning_doc_id: Option<usize>,
+    frame_threads: bool,
+}
+
+// Story threaded into the page frames called `frame` (`data-fb-frame`), continued in those
+// frames on each following page until it runs out.
+struct FrameThread {
+    frame: String,
+    story: VecDeque<Box<dyn Flowable>>,
+}
+
+// Fill the page's frames for each thread with as much of its story as fits. A block with
+// `break-after: page` or `column` ends the thread's content for the frame. Returns whether
+// anything was placed.
+fn fill_frame_threads(
+    canvas: &mut Canvas,
+    template: &PageTemplate,
+    threads: &mut [FrameThread],
+) -> bool {
+    let mut placed = false;
+    for thread in threads.iter_mut() {
+        for rect in template.named_frame_rects(&thread.frame) {
+            let mut frame = Frame::new(rect);
+            while let Some(flowable) = thread.story.pop_front() {
+                let _profile =
+                    layout_profile::element_scope(|| profiled_element(flowable.as_ref()));
+                let breaks_after = matches!(
+                    flowable.pagination().break_after,
+                    BreakAfter::Page | BreakAfter::Frame
+                );
+                match frame.add(flowable, canvas) {
+                    AddResult::Placed(_) => {
+                        placed = true;
+                        if breaks_after {
+                            break;
+                        }
+                    }
+                    AddResult::Split(remaining, _) => {
+                        placed = true;
+                        thread.story.push_front(remaining);
+                        break;
+                    }
+                    AddResult::Overflow(remaining, _) => {
+                        thread.story.push_front(remaining);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    placed
+}
+
+// Page span (first page index, last page index) of each in-flow story flowable,
+// indexed by source order. Page indices are relative to the built document.
+pub(crate) type SourcePageSpans = Vec<(usize, usize)>;
+
+fn record_source_page(spans: &mut SourcePageSpans, source_order: usize, page_index: usize) {
+    if let Some(span) = spans.get_mut(source_order) {
+        span.1 = span.1.max(page_index);
+    } else {
+        spans.push((page_index, page_index));
+    }
+}
+
+// Find where a partially laid out story can be resumed without losing content.
+//
+// Returns `(source_order, page_index)`: pages before `page_index` only hold flowables
+// with a smaller source order, so they can be emitted as-is while every flowable from
+// `source_order` onwards is laid out again starting at `page_index`. The trailing page
+// is always held back because later content may still flow onto it.
+pub(crate) fn resume_point(spans: &[(usize, usize)], page_count: usize) -> (usize, usize) {
+    let Some(last_page) = page_count.checked_sub(1) else {
+        return (spans.len(), 0);
+    };
+    let first_touching = |page: usize| {
+        spans
+            .iter()
+            .position(|&(_, end)| end >= page)
+            .unwrap_or(spans.len())
+    };
+    let mut source = first_touching(last_page);
+    let mut page = spans.get(source).map(|span| span.0).unwrap_or(last_page);
+    loop {
+        let earlier = first_touching(page);
+        if earlier >= source {
+            return (source, page);
+        }
+        source = earlier;
+        page = spans[earlier].0;
+    }
 }
 
 impl DocTemplate {
     pub fn new(page_templates: Vec<PageTemplate>) -> Self {
+        let (keyed_page_templates, page_templates) = page_templates
+            .into_iter()
+            .partition(|template| template.page_name().is_some() || template.parity().is_some());
         Self {
             page_templates,
+            keyed_page_templates,
             story: Vec::new(),
             debug: None,
             debug_doc_id: None,
+            first_page_number: 1,
+            on_page_start: None,
+            on_page_end: None,
+            progress: None,
+            warnings: None,
+            warning_doc_id: None,
+            frame_threads: true,
         }
     }
 
+    // Start numbering (and template selection) at `page_number` instead of 1. Used when a
+    // story is laid out in several slices that continue an already emitted document.
+    pub(crate) fn with_first_page_number(mut self, page_number: usize) -> Self {
+        self.first_page_number = page_number.max(1);
+        self
+    }
+
     pub(crate) fn with_debug(mut self, debug: Arc<DebugLogger>, doc_id: Option<usize>) -> Self {
         self.debug = Some(debug);
         self.debug_doc_id = doc_id;
         self
     }
 
+    // Called at every page start after the page template's own `on_page` callback, before
+    // any content is placed. The callback draws into the new page.
+    pub fn set_on_page_start<F>(self, callback: F) -> Self
+    where
+        F: Fn(&mut Canvas, &DocContext) + Send + Sync + 'static,
+    {
+        self.with_on_page_start(Some(Arc::new(callback)))
+    }
+
+    // Called once each page's content is complete, just before the page is emitted; what
+    // the callback draws paints over the page content.
+    pub fn set_on_page_end<F>(self, callback: F) -> Self
+    where
+        F: Fn(&mut Canvas, &DocContext) + Send + Sync + 'static,
+    {
+        self.with_on_page_end(Some(Arc::new(callback)))
+    }
+
+    pub(crate) fn with_on_page_start(mut self, callback: Option<OnPageCallback>) -> Self {
+        self.on_page_start = callback;
+        self
+    }
+
+    pub(crate) fn with_on_page_end(mut self, callback: Option<OnPageCallback>) -> Self {
+        self.on_page_end = callback;
+        self
+    }
+
+    pub(crate) fn with_progress(mut self, progress: Option<ProgressHandle>, doc_id: usize) -> Self {
+        self.progress = progress.map(|handle| (handle, doc_id));
+        self
+    }
+
+    pub(crate) fn with_warnings(mut self, warnings: Option<WarningHandle>, doc_id: usize) -> Self {
+        self.warnings = warnings;
+        self.warning_doc_id = Some(doc_id);
+        self
+    }
+
+    // Thread `flowable` into the page frames called `frame` instead of the main flow (the
+    // Rust side of `data-fb-frame`). Frames a thread uses are left out of the main flow.
+    pub fn add_flowable_to_frame(&mut self, frame: impl Into<String>, flowable: Box<dyn Flowable>) {
+        self.story.push(Box::new(MetaFlowable::new(
+            flowable,
+            vec![(META_FRAME_KEY.to_string(), frame.into())],
+        )));
+    }
+
+    // Lay `data-fb-frame` content out in the main flow instead (stream slices).
+    pub(crate) fn with_frame_threads(mut self, enabled: bool) -> Self {
+        self.frame_threads = enabled;
+        self
+    }
+
     pub fn add_flowable(&mut self, flowable: Box<dyn Flowable>) {
         self.story.push(flowable);
     }
@@ -178,39 +617,131 @@ impl DocTemplate {
     }
 
     pub fn build_with_metrics(self) -> Result<(Document, DocumentMetrics), FullBleedError> {
-        if self.page_templates.is_empty() {
+        self.build_with_source_spans()
+            .map(|(document, metrics, _spans)| (document, metrics))
+    }
+
+    pub(crate) fn build_with_source_spans(
+        self,
+    ) -> Result<(Document, DocumentMetrics, SourcePageSpans), FullBleedError> {
+        self.build_pages()
+            .map(|(document, metrics, spans, _)| (document, metrics, spans))
+    }
+
+    // The trailing flag is whether the story ended with something still to place on a
+    // following page: footnote bodies that did not fit, frame thread content or positioned
+    // content running past the page.
+    fn build_pages(
+        self,
+    ) -> Result<(Document, DocumentMetrics, SourcePageSpans, bool), FullBleedError> {
+        // Odd/even templates alone (mirrored margins) are enough; `select_template` falls back
+        // to the keyed templates.
+        if self.page_templates.is_empty() && self.keyed_page_templates.is_empty() {
             return Err(FullBleedError::MissingPageTemplate);
         }
 
         let debug = self.debug.clone();
         let debug_doc_id = self.debug_doc_id;
+        let warnings = self.warnings.clone();
+        let warning_doc_id = self.warning_doc_id;
 
         fn select_template<'a>(
             page_templates: &'a [PageTemplate],
+            keyed_page_templates: &'a [PageTemplate],
             page_number: usize,
+            page_name: &str,
         ) -> &'a PageTemplate {
+            let parity = PageParity::of(page_number);
+            let keyed = |name: Option<&str>| {
+                let mut candidates = keyed_page_templates
+                    .iter()
+                    .filter(|template| template.page_name() == name);
+                candidates
+                    .clone()
+                    .find(|template| template.parity() == Some(parity))
+                    .or_else(|| candidates.find(|template| template.parity().is_none()))
+            };
+            // Content on a named page uses that page's template when there is one.
+            if !page_name.is_empty()
+                && let Some(template) = keyed(Some(page_name))
+            {
+                return template;
+            }
             // Selection rule:
             // - page 1 -> templates[0]
             // - page 2 -> templates[1] (if present)
             // - ...
             // - page n -> templates[min(n-1, templates.len()-1)] (last template repeats)
+            // Pages past the indexed templates use the template for their parity when there
+            // is one.
             let idx = page_number.saturating_sub(1);
-            let idx = idx.min(page_templates.len() - 1);
-            &page_templates[idx]
+            if idx < page_templates.len() {
+                return &page_templates[idx];
+            }
+            match keyed(None) {
+                Some(template) => template,
+                None => page_templates
+                    .last()
+                    .unwrap_or_else(|| &keyed_page_templates[0]),
+            }
+        }
+
+        // Top-level flowables targeting a frame that some template declares are threaded into
+        // that frame on every page instead of the main flow.
+        let mut threads: Vec<FrameThread> = Vec::new();
+        let mut main_story: Vec<Box<dyn Flowable>> = Vec::with_capacity(self.story.len());
+        for flowable in self.story {
+            let target = flowable
+                .target_frame()
+                .filter(|_| self.frame_threads)
+                .filter(|_| !flowable.is_fixed_positioned() && !flowable.out_of_flow())
+                .filter(|name| {
+                    self.page_templates
+                        .iter()
+                        .chain(self.keyed_page_templates.iter())
+                        .any(|template| template.has_frame_named(name))
+                });
+            let Some(name) = target else {
+                main_story.push(flowable);
+                continue;
+            };
+            match threads.iter_mut().find(|thread| thread.frame == name) {
+                Some(thread) => thread.story.push_back(flowable),
+                None => threads.push(FrameThread {
+                    frame: name,
+                    story: VecDeque::from([flowable]),
+                }),
+            }
         }
+        let reserved: Vec<String> = threads.iter().map(|thread| thread.frame.clone()).collect();
 
-        let template = select_template(&self.page_templates, 1);
+        // Named page in effect; each new page takes the name its first content asks for.
+        let mut page_name = main_story
+            .iter()
+            .find(|flowable| !flowable.is_fixed_positioned() && !flowable.out_of_flow())
+            .and_then(|flowable| flowable.page_name())
+            .unwrap_or_default();
+        let template = select_template(
+            &self.page_templates,
+            &self.keyed_page_templates,
+            self.first_page_number,
+            &page_name,
+        );
         let mut canvas = Canvas::new(template.page_size);
-        let mut page_number = 1usize;
-        let mut frames = template.instantiate_frames();
+        canvas.set_page_rotate(template.rotate());
+        let mut current_template = template;
+        let mut page_number = self.first_page_number;
+        let mut frames = template.instantiate_flow_frames(&reserved);
         let mut frame_index = 0usize;
         let mut placed_on_page = false;
         let mut metrics = DocumentMetrics::default();
         let mut page_start = Instant::now();
         let mut page_flowables = 0usize;
         let mut source_order = 0usize;
+        let mut source_spans: SourcePageSpans = Vec::new();
+        let mut footnotes = FootnoteArea::new();
 
-        let draw_fixed_overlays =
+        let draw_overlays =
             |canvas: &mut Canvas, overlays: &[Box<dyn Flowable>], page_flowables: &mut usize| {
                 if overlays.is_empty() {
                     return;
@@ -228,12 +759,12 @@ impl DocTemplate {
                 }
             };
 
-        let mut fixed_overlays_back: Vec<Box<dyn Flowable>> = Vec::new();
-        let mut fixed_overlays_front: Vec<Box<dyn Flowable>> = Vec::new();
+        let mut fixed_overlays_back = FixedOverlays::new("back");
+        let mut fixed_overlays_front = FixedOverlays::new("front");
         let mut root_out_of_flow_back: Vec<Box<dyn Flowable>> = Vec::new();
         let mut root_out_of_flow_front: Vec<Box<dyn Flowable>> = Vec::new();
         let mut story: VecDeque<Box<dyn Flowable>> = VecDeque::new();
-        for flowable in self.story {
+        for flowable in main_story {
             if flowable.is_fixed_positioned() {
                 if flowable.z_index() < 0 {
                     fixed_overlays_back.push(flowable);
@@ -254,30 +785,66 @@ impl DocTemplate {
         }
         // Keep fixed overlay paint order deterministic and z-index aware.
         // Lower z-index paints first, higher z-index paints later (on top).
-        fixed_overlays_back.sort_by(|left, right| left.z_index().cmp(&right.z_index()));
-        fixed_overlays_front.sort_by(|left, right| left.z_index().cmp(&right.z_index()));
+        fixed_overlays_back.sort_by_z_index();
+        fixed_overlays_front.sort_by_z_index();
         root_out_of_flow_back.sort_by(|left, right| left.z_index().cmp(&right.z_index()));
         root_out_of_flow_front.sort_by(|left, right| left.z_index().cmp(&right.z_index()));
 
+        // Absolutely positioned content that ran past the previous page continues at the same
+        // position on the new one.
+        let draw_abs_continuations = |canvas: &mut Canvas, page_flowables: &mut usize| {
+            for continuation in canvas.take_abs_continuations() {
+                draw_abs_continuation(canvas, continuation);
+                *page_flowables += 1;
+            }
+        };
+
+        let on_page_start = self.on_page_start.clone();
+        let on_page_end = self.on_page_end.clone();
+        let progress = self.progress.clone();
+        let begin_page = |canvas: &mut Canvas, page_number: usize, template: &PageTemplate| {
+            let context = DocContext::new(page_number, &template.name);
+            if let Some(callback) = template.on_page() {
+                callback(canvas, &context);
+            }
+            if let Some(callback) = on_page_start.as_deref() {
+                callback(canvas, &context);
+            }
+            canvas.meta(
+                crate::META_PAGE_TEMPLATE_KEY.to_string(),
+                template.name.clone(),
+            );
+        };
+
         let finish_page = |canvas: &mut Canvas,
                            page_number: usize,
+                           template: &PageTemplate,
+                           threads: &mut [FrameThread],
                            page_flowables: &mut usize,
                            metrics: &mut DocumentMetrics,
                            page_start: &mut Instant,
-                           fixed_overlays_front: &[Box<dyn Flowable>],
-                           root_out_of_flow_front: &[Box<dyn Flowable>]| {
+                           footnotes: &mut FootnoteArea,
+                           fixed_overlays_front: &mut FixedOverlays,
+                           root_out_of_flow_front: &[Box<dyn Flowable>]|
+         -> bool {
+            let threaded = fill_frame_threads(canvas, template, threads);
+            footnotes.flush(canvas);
             if canvas.is_current_empty()
                 && fixed_overlays_front.is_empty()
                 && (page_number != 1 || root_out_of_flow_front.is_empty())
             {
-                return;
+                return threaded;
             }
             if page_number == 1 {
-                draw_fixed_overlays(canvas, root_out_of_flow_front, page_flowables);
+                draw_overlays(canvas, root_out_of_flow_front, page_flowables);
             }
-            draw_fixed_overlays(canvas, fixed_overlays_front, page_flowables);
+            fixed_overlays_front.draw(canvas, page_flowables);
             if canvas.is_current_empty() {
-                return;
+                return threaded;
+            }
+            // Runs last so its commands paint over everything else on the page.
+            if let Some(callback) = on_page_end.as_deref() {
+                callback(canvas, &DocContext::new(page_number, &template.name));
             }
             let elapsed = page_start.elapsed().as_secs_f64() * 1000.0;
             metrics.total_render_ms += elapsed;
@@ -288,22 +855,59 @@ impl DocTemplate {
                 flowable_count: *page_flowables,
                 content_bytes: 0,
             });
+            let clipped = canvas.take_clipped_overflow();
+            if let Some(logger) = debug.as_deref().filter(|_| !clipped.is_empty()) {
+                let doc_id = debug_doc_id
+                    .map(|id| id.to_string())
+                    .unwrap_or_else(|| "null".to_string());
+                for height in &clipped {
+                    let json = format!(
+                        "{{\"type\":\"jit.known_loss\",\"doc_id\":{},\"code\":\"ABS_OVERFLOW_CLIPPED\",\"page\":{},\"clipped_h\":{:.3}}}",
+                        doc_id,
+                        page_number,
+                        height.to_f32()
+                    );
+                    logger.log_json(&json);
+                    logger.increment("jit.known_loss.abs_overflow_clipped", 1);
+                }
+            }
+            if !clipped.is_empty() && warning::wanted(warnings.as_ref()) {
+                for height in &clipped {
+                    warning::emit(
+                        warnings.as_ref(),
+                        Warning::for_loss(
+                            WarningKind::KnownLoss,
+                            KnownLoss::AbsOverflowClipped {
+                                clipped_height: *height,
+                            },
+                            format!(
+                                "{:.1}pt of an absolutely positioned element ran past the page and was clipped",
+                                height.to_f32()
+                            ),
+                        )
+                        .doc(warning_doc_id)
+                        .page(page_number),
+                    );
+                }
+            }
             canvas.show_page();
+            if let Some((handle, doc_id)) = progress.as_ref() {
+                handle.report(ProgressEvent::PageLaidOut {
+                    doc_id: *doc_id,
+                    page_number,
+                });
+            }
             *page_flowables = 0;
             *page_start = Instant::now();
+            threaded
         };
 
-        if let Some(callback) = template.on_page() {
-            callback(&mut canvas, &DocContext::new(page_number, &template.name));
-        }
-        canvas.meta(
-            crate::META_PAGE_TEMPLATE_KEY.to_string(),
-            template.name.clone(),
-        );
-        draw_fixed_overlays(&mut canvas, &fixed_overlays_back, &mut page_flowables);
-        draw_fixed_overlays(&mut canvas, &root_out_of_flow_back, &mut page_flowables);
+        begin_page(&mut canvas, page_number, template);
+        fixed_overlays_back.draw(&mut canvas, &mut page_flowables);
+        draw_overlays(&mut canvas, &root_out_of_flow_back, &mut page_flowables);
 
         while let Some(flowable) = story.pop_front() {
+            let _profile = layout_profile::element_scope(|| profiled_element(flowable.as_ref()));
             let mut current = flowable;
             let current_source_order = source_order;
             source_order = source_order.saturating_add(1);
@@ -334,25 +938,59 @@ impl DocTemplate {
                     finish_page(
                         &mut canvas,
                         page_number,
+                        current_template,
+                        &mut threads,
                         &mut page_flowables,
                         &mut metrics,
                         &mut page_start,
-                        &fixed_overlays_front,
+                        &mut footnotes,
+                        &mut fixed_overlays_front,
                         &root_out_of_flow_front,
                     );
                     page_number += 1;
-                    let template = select_template(&self.page_templates, page_number);
-                    frames = template.instantiate_frames();
+                    if let Some(name) = current.page_name() {
+                        page_name = name;
+                    }
+                    let template = select_template(
+                        &self.page_templates,
+                        &self.keyed_page_templates,
+                        page_number,
+                        &page_name,
+                    );
+                    canvas.set_page_size(template.page_size);
+                    canvas.set_page_rotate(template.rotate());
+                    frames = template.instantiate_flow_frames(&reserved);
                     frame_index = 0;
                     placed_on_page = false;
-                    if let Some(callback) = template.on_page() {
-                        callback(&mut canvas, &DocContext::new(page_number, &template.name));
-                    }
-                    canvas.meta(
-                        crate::META_PAGE_TEMPLATE_KEY.to_string(),
-                        template.name.clone(),
+                    begin_page(&mut canvas, page_number, template);
+                    current_template = template;
+                    fixed_overlays_back.draw(&mut canvas, &mut page_flowables);
+                    draw_abs_continuations(&mut canvas, &mut page_flowables);
+                }
+
+                // A frame break moves on unless the current frame is still empty.
+                if !suppress_break_before
+                    && matches!(pagination.break_before, BreakBefore::Frame)
+                    && frames
+                        .get(frame_index)
+                        .is_some_and(|frame| !frame.is_empty())
+                {
+                    let is_last_frame = frame_index + 1 >= frames.len();
+                    emit_pagination_transition_event(
+                        &mut canvas,
+                        debug.as_deref(),
+                        debug_doc_id,
+                        page_number,
+                        page_number + usize::from(is_last_frame),
+                        frame_index,
+                        if is_last_frame { 0 } else { frame_index + 1 },
+                        "break_before_frame",
+                        Some(&current_name),
+                        &current_owner_meta,
+                        Some(current_source_order),
+                        Some(segment_index),
                     );
-                    draw_fixed_overlays(&mut canvas, &fixed_overlays_back, &mut page_flowables);
+                    frame_index += 1;
                 }
 
                 if frame_index >= frames.len() {
@@ -373,25 +1011,34 @@ impl DocTemplate {
                     finish_page(
                         &mut canvas,
                         page_number,
+                        current_template,
+                        &mut threads,
                         &mut page_flowables,
                         &mut metrics,
                         &mut page_start,
-                        &fixed_overlays_front,
+                        &mut footnotes,
+                        &mut fixed_overlays_front,
                         &root_out_of_flow_front,
                     );
                     page_number += 1;
-                    let template = select_template(&self.page_templates, page_number);
-                    frames = template.instantiate_frames();
-                    frame_index = 0;
-                    placed_on_page = false;
-                    if let Some(callback) = template.on_page() {
-                        callback(&mut canvas, &DocContext::new(page_number, &template.name));
+                    if let Some(name) = current.page_name() {
+                        page_name = name;
                     }
-                    canvas.meta(
-                        crate::META_PAGE_TEMPLATE_KEY.to_string(),
-                        template.name.clone(),
+                    let template = select_template(
+                        &self.page_templates,
+                        &self.keyed_page_templates,
+                        page_number,
+                        &page_name,
                     );
-                    draw_fixed_overlays(&mut canvas, &fixed_overlays_back, &mut page_flowables);
+                    canvas.set_page_size(template.page_size);
+                    canvas.set_page_rotate(template.rotate());
+                    frames = template.instantiate_flow_frames(&reserved);
+                    frame_index = 0;
+                    placed_on_page = false;
+                    begin_page(&mut canvas, page_number, template);
+                    current_template = template;
+                    fixed_overlays_back.draw(&mut canvas, &mut page_flowables);
+                    draw_abs_continuations(&mut canvas, &mut page_flowables);
                 }
 
                 if frames.is_empty() {
@@ -418,9 +1065,52 @@ impl DocTemplate {
                     None
                 };
 
+                // A flowable kept with the next one (`break-after: avoid`, headings by default)
+                // moves on when the next one cannot start in the space left below it.
+                if pagination.break_after == BreakAfter::Avoid && !frames[frame_index].is_empty() {
+                    let avail_height = frames[frame_index].remaining_height();
+                    let height = current.wrap(frame_rect.width, avail_height).height;
+                    let strands_next = story.front().is_some_and(|next| {
+                        !matches!(
+                            next.pagination().break_before,
+                            BreakBefore::Page | BreakBefore::Frame
+                        ) && height <= avail_height
+                            && !can_start_in(next.as_ref(), frame_rect.width, avail_height - height)
+                    });
+                    if strands_next {
+                        emit_pagination_transition_event(
+                            &mut canvas,
+                            debug.as_deref(),
+                            debug_doc_id,
+                            page_number,
+                            page_number + usize::from(is_last_frame),
+                            frame_index,
+                            if is_last_frame { 0 } else { frame_index + 1 },
+                            "keep_with_next",
+                            Some(&current_name),
+                            &current_owner_meta,
+                            Some(current_source_order),
+                            Some(segment_index),
+                        );
+                        frame_index += 1;
+                        continue;
+                    }
+                }
+
+                footnotes.enter_frame(
+                    (page_number, frame_index),
+                    &mut frames[frame_index],
+                    &mut canvas,
+                );
                 let frame = &mut frames[frame_index];
                 match frame.add(current, &mut canvas) {
                     AddResult::Placed(trace) => {
+                        footnotes.reserve(&mut frames[frame_index], canvas.take_footnotes());
+                        record_source_page(
+                            &mut source_spans,
+                            current_source_order,
+                            metrics.pages.len(),
+                        );
                         emit_pagination_layout_event(
                             &mut canvas,
                             current_source_order,
@@ -453,36 +1143,61 @@ impl DocTemplate {
                             finish_page(
                                 &mut canvas,
                                 page_number,
+                                current_template,
+                                &mut threads,
                                 &mut page_flowables,
                                 &mut metrics,
                                 &mut page_start,
-                                &fixed_overlays_front,
+                                &mut footnotes,
+                                &mut fixed_overlays_front,
                                 &root_out_of_flow_front,
                             );
                             page_number += 1;
-                            let template = select_template(&self.page_templates, page_number);
-                            frames = template.instantiate_frames();
-                            frame_index = 0;
-                            placed_on_page = false;
-                            if let Some(callback) = template.on_page() {
-                                callback(
-                                    &mut canvas,
-                                    &DocContext::new(page_number, &template.name),
-                                );
+                            if let Some(name) = story.front().and_then(|next| next.page_name()) {
+                                page_name = name;
                             }
-                            canvas.meta(
-                                crate::META_PAGE_TEMPLATE_KEY.to_string(),
-                                template.name.clone(),
+                            let template = select_template(
+                                &self.page_templates,
+                                &self.keyed_page_templates,
+                                page_number,
+                                &page_name,
                             );
-                            draw_fixed_overlays(
+                            canvas.set_page_size(template.page_size);
+                            canvas.set_page_rotate(template.rotate());
+                            frames = template.instantiate_flow_frames(&reserved);
+                            frame_index = 0;
+                            placed_on_page = false;
+                            begin_page(&mut canvas, page_number, template);
+                            current_template = template;
+                            fixed_overlays_back.draw(&mut canvas, &mut page_flowables);
+                            draw_abs_continuations(&mut canvas, &mut page_flowables);
+                        } else if matches!(pagination.break_after, BreakAfter::Frame) {
+                            emit_pagination_transition_event(
                                 &mut canvas,
-                                &fixed_overlays_back,
-                                &mut page_flowables,
+                                debug.as_deref(),
+                                debug_doc_id,
+                                page_number,
+                                page_number + usize::from(is_last_frame),
+                                frame_index,
+                                if is_last_frame { 0 } else { frame_index + 1 },
+                                "break_after_frame",
+                                Some(&current_name),
+                                &current_owner_meta,
+                                Some(current_source_order),
+                                Some(segment_index),
                             );
+                            // The next flowable starts a new page when this was the last frame.
+                            frame_index += 1;
                         }
                         break;
                     }
                     AddResult::Split(remaining, trace) => {
+                        footnotes.reserve(&mut frames[frame_index], canvas.take_footnotes());
+                        record_source_page(
+                            &mut source_spans,
+                            current_source_order,
+                            metrics.pages.len(),
+                        );
                         emit_pagination_layout_event(
                             &mut canvas,
                             current_source_order,
@@ -552,7 +1267,8 @@ impl DocTemplate {
                         );
                         if !placed_on_page && is_last_frame {
                             let details = debug_details.unwrap_or_else(|| "unknown".to_string());
-                            return Err(FullBleedError::UnplaceableFlowable(details));
+                            return Err(FullBleedError::UnplaceableFlowable(details)
+                                .at(owner_location(&current_owner_meta)));
                         }
                         current = remaining;
                         frame_index += 1;
@@ -561,18 +1277,286 @@ impl DocTemplate {
             }
         }
 
+        let carried_over = !footnotes.carried.is_empty()
+            || canvas.has_abs_continuations()
+            || threads.iter().any(|thread| !thread.story.is_empty());
+
+        // Bodies that did not fit continue in the following frames, on new pages if needed.
+        while !footnotes.carried.is_empty() {
+            if footnotes.frame == Some((page_number, frame_index)) {
+                frame_index += 1;
+            }
+            if frame_index >= frames.len() {
+                finish_page(
+                    &mut canvas,
+                    page_number,
+                    current_template,
+                    &mut threads,
+                    &mut page_flowables,
+                    &mut metrics,
+                    &mut page_start,
+                    &mut footnotes,
+                    &mut fixed_overlays_front,
+                    &root_out_of_flow_front,
+                );
+                page_number += 1;
+                let template = select_template(
+                    &self.page_templates,
+                    &self.keyed_page_templates,
+                    page_number,
+                    &page_name,
+                );
+                canvas.set_page_size(template.page_size);
+                canvas.set_page_rotate(template.rotate());
+                frames = template.instantiate_flow_frames(&reserved);
+                frame_index = 0;
+                begin_page(&mut canvas, page_number, template);
+                current_template = template;
+                fixed_overlays_back.draw(&mut canvas, &mut page_flowables);
+                draw_abs_continuations(&mut canvas, &mut page_flowables);
+            }
+            if frames.is_empty() {
+                return Err(FullBleedError::MissingPageTemplate);
+            }
+            footnotes.enter_frame(
+                (page_number, frame_index),
+                &mut frames[frame_index],
+                &mut canvas,
+            );
+        }
+
         if !canvas.is_current_empty() || metrics.pages.is_empty() {
             finish_page(
                 &mut canvas,
                 page_number,
+                current_template,
+                &mut threads,
+                &mut page_flowables,
+                &mut metrics,
+                &mut page_start,
+                &mut footnotes,
+                &mut fixed_overlays_front,
+                &root_out_of_flow_front,
+            );
+        }
+        // Threaded frame content left once the main story ends continues on new pages.
+        while let Some(thread) = threads.iter().find(|thread| !thread.story.is_empty()) {
+            let frame = thread.frame.clone();
+            page_number += 1;
+            let template = select_template(
+                &self.page_templates,
+                &self.keyed_page_templates,
+                page_number,
+                &page_name,
+            );
+            canvas.set_page_size(template.page_size);
+            canvas.set_page_rotate(template.rotate());
+            begin_page(&mut canvas, page_number, template);
+            current_template = template;
+            fixed_overlays_back.draw(&mut canvas, &mut page_flowables);
+            draw_abs_continuations(&mut canvas, &mut page_flowables);
+            let threaded = finish_page(
+                &mut canvas,
+                page_number,
+                current_template,
+                &mut threads,
                 &mut page_flowables,
                 &mut metrics,
                 &mut page_start,
-                &fixed_overlays_front,
+                &mut footnotes,
+                &mut fixed_overlays_front,
                 &root_out_of_flow_front,
             );
+            if !threaded {
+                return Err(FullBleedError::UnplaceableFlowable(format!(
+                    "content threaded into frame {frame:?} does not fit on page {page_number}"
+                )));
+            }
         }
 
-        Ok((canvas.finish_without_show(), metrics))
+        // Absolutely positioned content still running past the page bottom gets its own pages.
+        while canvas.has_abs_continuations() {
+            page_number += 1;
+            let template = select_template(
+                &self.page_templates,
+                &self.keyed_page_templates,
+                page_number,
+                &page_name,
+            );
+            canvas.set_page_size(template.page_size);
+            canvas.set_page_rotate(template.rotate());
+            begin_page(&mut canvas, page_number, template);
+            current_template = template;
+            fixed_overlays_back.draw(&mut canvas, &mut page_flowables);
+            draw_abs_continuations(&mut canvas, &mut page_flowables);
+            finish_page(
+                &mut canvas,
+                page_number,
+                current_template,
+                &mut threads,
+                &mut page_flowables,
+                &mut metrics,
+                &mut page_start,
+                &mut footnotes,
+                &mut fixed_overlays_front,
+                &root_out_of_flow_front,
+            );
+        }
+
+        Ok((
+            canvas.finish_without_show(),
+            metrics,
+            source_spans,
+            carried_over,
+        ))
+    }
+
+    // Lays the story out in sections split at forced page breaks, each on its own thread, and
+    // joins their pages in order. A section after the first starts on a new page either way,
+    // so this gives the pages `build` would when nothing else crosses the break: pages from
+    // the second on share one template, no page callback or debug log sees page numbers, and
+    // the story has no frame threads, named pages, fixed or root out-of-flow content. Stories
+    // that fail those checks, and ones where a section ends with something still to place
+    // (carried footnotes, positioned content running past the page), are laid out by `build`.
+    pub(crate) fn build_sections(self) -> Result<Document, FullBleedError> {
+        use rayon::prelude::*;
+
+        let Some(starts) = self.section_starts() else {
+            return self.build();
+        };
+        let DocTemplate {
+            page_templates,
+            story,
+            progress,
+            warnings,
+            warning_doc_id,
+            frame_threads,
+            ..
+        } = self;
+        let mut story = story;
+        let mut sections: Vec<Vec<Box<dyn Flowable>>> = starts
+            .iter()
+            .skip(1)
+            .rev()
+            .map(|&start| story.split_off(start))
+            .collect();
+        sections.push(story);
+        sections.reverse();
+        let whole: Vec<Box<dyn Flowable>> = sections
+            .iter()
+            .flatten()
+            .map(|flowable| flowable.clone_box())
+            .collect();
+
+        let (perf, perf_doc_id) = crate::flowable::perf_context();
+        // Pages from the second on share a template, so a later section can be laid out from
+        // page 2 before the pages ahead of it are known. Warnings are held back and sent once
+        // the real page numbers are.
+        let built: Vec<_> = sections
+            .into_par_iter()
+            .enumerate()
+            .map(|(index, section)| {
+                let first_page_number = if index == 0 { 1 } else { 2 };
+                let doc = DocTemplate {
+                    story: section,
+                    first_page_number,
+                    warning_doc_id,
+                    frame_threads,
+                    ..DocTemplate::new(page_templates.clone())
+                };
+                let _perf_guard = crate::flowable::set_perf_context(perf.clone(), perf_doc_id);
+                let (result, section_warnings) = warning::collect_detached(|| doc.build_pages());
+                result.map(|(document, _, _, carried_over)| {
+                    (document, first_page_number, carried_over, section_warnings)
+                })
+            })
+            .collect();
+
+        let mut document: Option<Document> = None;
+        let mut held_warnings = Vec::new();
+        for (index, section) in built.into_iter().enumerate() {
+            let (section_document, first_page_number, carried_over, section_warnings) = section?;
+            if carried_over && index + 1 < starts.len() {
+                return DocTemplate {
+                    story: whole,
+                    progress,
+                    warnings,
+                    warning_doc_id,
+                    frame_threads,
+                    ..DocTemplate::new(page_templates)
+                }
+                .build();
+            }
+            let page_offset = document.as_ref().map_or(0, |doc| doc.pages.len()) + 1;
+            held_warnings.extend(section_warnings.into_iter().map(|mut warning| {
+                warning.page = warning
+                    .page
+                    .map(|page| page + page_offset - first_page_number);
+                warning
+            }));
+            match document.as_mut() {
+                Some(document) => append_pages(document, section_document),
+                None => document = Some(section_document),
+            }
+        }
+        let document = document.expect("story has at least two sections");
+        for warning in held_warnings {
+            warning::emit(warnings.as_ref(), warning);
+        }
+        if let Some((handle, doc_id)) = progress.as_ref() {
+            for page_number in 1..=document.pages.len() {
+                handle.report(ProgressEvent::PageLaidOut {
+                    doc_id: *doc_id,
+                    page_number,
+                });
+            }
+        }
+        Ok(document)
+    }
+
+    // Indices of the top-level flowables that start a section (the first is 0), or `None`
+    // when the story cannot be laid out in sections or has only one.
+    fn section_starts(&self) -> Option<Vec<usize>> {
+        let independent = self.first_page_number == 1
+            && self.page_templates.len() <= 2
+            && self.keyed_page_templates.is_empty()
+            && self
+                .page_templates
+                .iter()
+                .all(|template| template.on_page().is_none())
+            && self.on_page_start.is_none()
+            && self.on_page_end.is_none()
+            && self.debug.is_none()
+            && !layout_profile::active();
+        if !independent {
+            return None;
+        }
+        let mut starts = vec![0];
+        for (index, flowable) in self.story.iter().enumerate() {
+            if flowable.is_fixed_positioned()
+                || flowable.out_of_flow()
+                || flowable.page_name().is_some()
+                || (self.frame_threads && flowable.target_frame().is_some())
+            {
+                return None;
+            }
+            // After `break-after: page` the new page is already open and the break is a no-op.
+            if index > 0
+                && matches!(flowable.pagination().break_before, BreakBefore::Page)
+                && self.story[index - 1].pagination().break_after != BreakAfter::Page
+            {
+                starts.push(index);
+            }
+        }
+        (starts.len() > 1).then_some(starts)
+    }
+}
+
+// Appends the pages of a separately built `section`, keeping each page's size.
+fn append_pages(document: &mut Document, section: Document) {
+    for mut page in section.pages {
+        let size = page.size.unwrap_or(section.page_size);
+        page.size = (size != document.page_size).then_some(size);
+        document.pages.push(page);
     }
 }
@@ -0,0 +1,389 @@
+// Standard security handler, AES-256 (/V 5 /R 6, ISO 32000-2 7.6.4). The file key is random;
+// every string and stream is encrypted with it as AES-256-CBC with a 16-byte IV prefix.
+use aes::cipher::{BlockEncrypt, KeyInit, generic_array::GenericArray};
+use aes::{Aes128, Aes256};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use std::io;
+
+// Password protection and permissions for the output PDF (AES-256).
+//
+// The user password opens the document (empty: opens without a prompt, restrictions still
+// apply); the owner password lifts the restrictions. Text extraction for accessibility
+// tools is always permitted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptionSpec {
+    pub user_password: String,
+    pub owner_password: String,
+    pub allow_print: bool,
+    pub allow_copy: bool,
+    pub allow_modify: bool,
+    pub allow_annotations: bool,
+}
+
+impl EncryptionSpec {
+    pub fn aes256(user_password: impl Into<String>, owner_password: impl Into<String>) -> Self {
+        Self {
+            user_password: user_password.into(),
+            owner_password: owner_password.into(),
+            allow_print: true,
+            allow_copy: true,
+            allow_modify: true,
+            allow_annotations: true,
+        }
+    }
+
+    pub fn no_print(mut self) -> Self {
+        self.allow_print = false;
+        self
+    }
+
+    pub fn no_copy(mut self) -> Self {
+        self.allow_copy = false;
+        self
+    }
+
+    pub fn no_modify(mut self) -> Self {
+        self.allow_modify = false;
+        self
+    }
+
+    pub fn no_annotations(mut self) -> Self {
+        self.allow_annotations = false;
+        self
+    }
+
+    // /P permission bits (ISO 32000-2 Table 22). Reserved bits stay set; bit 10
+    // (accessibility extraction) is always granted. Modify also covers form filling and
+    // page assembly.
+    pub(crate) fn permission_bits(&self) -> i32 {
+        let mut bits: u32 = 0xFFFF_F0C0 | (1 << 9);
+        if self.allow_print {
+            bits |= (1 << 2) | (1 << 11);
+        }
+        if self.allow_modify {
+            bits |= (1 << 3) | (1 << 8) | (1 << 10);
+        }
+        if self.allow_copy {
+            bits |= 1 << 4;
+        }
+        if self.allow_annotations {
+            bits |= 1 << 5;
+        }
+        bits as i32
+    }
+}
+
+pub(crate) struct PdfEncryptor {
+    file_key: [u8; 32],
+    iv_counter: u64,
+    encrypt_dict: String,
+}
+
+impl PdfEncryptor {
+    pub(crate) fn new(spec: &EncryptionSpec) -> io::Result<Self> {
+        let mut random = [0u8; 32 + 16 + 16 + 4];
+        getrandom::getrandom(&mut random).map_err(|err| io::Error::other(err.to_string()))?;
+        let mut file_key = [0u8; 32];
+        file_key.copy_from_slice(&random[..32]);
+        let (user_salts, rest) = random[32..].split_at(16);
+        let (owner_salts, perms_tail) = rest.split_at(16);
+
+        let user_password = password_bytes(&spec.user_password);
+        let owner_password = password_bytes(&spec.owner_password);
+
+        // Algorithm 8: U and UE.
+        let mut u = hash_2b(user_password, &user_salts[..8], &[]).to_vec();
+        u.extend_from_slice(user_salts);
+        let ue_key = hash_2b(user_password, &user_salts[8..], &[]);
+        let ue = aes256_cbc_no_padding(&ue_key, &[0u8; 16], &file_key);
+
+        // Algorithm 9: O and OE, bound to U.
+        let mut o = hash_2b(owner_password, &owner_salts[..8], &u).to_vec();
+        o.extend_from_slice(owner_salts);
+        let oe_key = hash_2b(owner_password, &owner_salts[8..], &u);
+        let oe = aes256_cbc_no_padding(&oe_key, &[0u8; 16], &file_key);
+
+        // Algorithm 10: Perms.
+        let p = spec.permission_bits();
+        let mut perms = [0u8; 16];
+        perms[..4].copy_from_slice(&p.to_le_bytes());
+        perms[4..8].copy_from_slice(&[0xFF; 4]);
+        perms[8..12].copy_from_slice(b"Tadb");
+        perms[12..].copy_from_slice(perms_tail);
+        let perms = aes256_ecb_block(&file_key, &perms);
+
+        let encrypt_dict = format!(
+            "<< /Filter /Standard /V 5 /R 6 /Length 256 /CF << /StdCF << /AuthEvent /DocOpen /CFM /AESV3 /Length 32 >> >> /StmF /StdCF /StrF /StdCF /O <{}> /U <{}> /OE <{}> /UE <{}> /P {} /Perms <{}> /EncryptMetadata true >>",
+            hex(&o),
+            hex(&u),
+            hex(&oe),
+            hex(&ue),
+            p,
+            hex(&perms)
+        );
+        Ok(Self {
+            file_key,
+            iv_counter: 0,
+            encrypt_dict,
+        })
+    }
+
+    pub(crate) fn encrypt_dict(&self) -> &str {
+        &self.encrypt_dict
+    }
+
+    // IV || AES-256-CBC(PKCS#7-padded data). IVs derive from the secret file key and a
+    // counter, so they are unique and unpredictable without the key.
+    pub(crate) fn encrypt(&mut self, data: &[u8]) -> Vec<u8> {
+        let mut hasher = Sha256::new();
+        hasher.update(self.file_key);
+        hasher.update(self.iv_counter.to_le_bytes());
+        self.iv_counter += 1;
+        let mut iv = [0u8; 16];
+        iv.copy_from_slice(&hasher.finalize()[..16]);
+
+        let pad = 16 - data.len() % 16;
+        let mut padded = Vec::with_capacity(data.len() + pad);
+        padded.extend_from_slice(data);
+        padded.resize(data.len() + pad, pad as u8);
+
+        let mut out = iv.to_vec();
+        out.extend(aes256_cbc_no_padding(&self.file_key, &iv, &padded));
+        out
+    }
+
+    // Re-encode every literal `( )` and hex `< >` string in an object body as an
+    // encrypted hex string. Names, numbers, references and dictionaries pass through.
+    pub(crate) fn encrypt_object_strings(&mut self, body: &str) -> String {
+        let bytes = body.as_bytes();
+        let mut out = String::with_capacity(body.len() + 64);
+        let mut i = 0usize;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'(' => {
+                    let (decoded, end) = parse_literal_string(bytes, i);
+                    out.push('<');
+                    out.push_str(&hex(&self.encrypt(&decoded)));
+                    out.push('>');
+                    i = end;
+                }
+                b'<' if bytes.get(i + 1) != Some(&b'<') => {
+                    let end = bytes[i..]
+                        .iter()
+                        .position(|b| *b == b'>')
+                        .map_or(bytes.len(), |pos| i + pos + 1);
+                    let decoded = decode_hex(&bytes[i + 1..end.saturating_sub(1).max(i + 1)]);
+                    out.push('<');
+                    out.push_str(&hex(&self.encrypt(&decoded)));
+                    out.push('>');
+                    i = end;
+                }
+                b'<' => {
+                    out.push_str("<<");
+                    i += 2;
+                }
+                _ => {
+                    let start = i;
+                    while i < bytes.len() && bytes[i] != b'(' && bytes[i] != b'<' {
+                        i += 1;
+                    }
+                    out.push_str(&body[start..i]);
+                }
+            }
+        }
+        out
+    }
+}
+
+// Passwords are UTF-8, truncated to 127 bytes.
+fn password_bytes(password: &str) -> &[u8] {
+    let bytes = password.as_bytes();
+    let mut len = bytes.len().min(127);
+    while !password.is_char_boundary(len) {
+        len -= 1;
+    }
+    &bytes[..len]
+}
+
+// Algorithm 2.B: iterated SHA-256/384/512 + AES-128 hash of a password.
+fn hash_2b(password: &[u8], salt: &[u8], user_key: &[u8]) -> [u8; 32] {
+    let mut k: Vec<u8> = {
+        let mut hasher = Sha256::new();
+        hasher.update(password);
+        hasher.update(salt);
+        hasher.update(user_key);
+        hasher.finalize().to_vec()
+    };
+    let mut round = 0i32;
+    let mut last = 0u8;
+    while round < 64 || i32::from(last) > round - 32 {
+        let mut k1 = Vec::with_capacity(64 * (password.len() + k.len() + user_key.len()));
+        for _ in 0..64 {
+            k1.extend_from_slice(password);
+            k1.extend_from_slice(&k);
+            k1.extend_from_slice(user_key);
+        }
+        let e = aes128_cbc_no_padding(&k[..16], &k[16..32], &k1);
+        let selector = e[..16].iter().map(|b| u32::from(*b)).sum::<u32>() % 3;
+        k = match selector {
+            0 => Sha256::digest(&e).to_vec(),
+            1 => Sha384::digest(&e).to_vec(),
+            _ => Sha512::digest(&e).to_vec(),
+        };
+        last = *e.last().unwrap_or(&0);
+        round += 1;
+    }
+    let mut out = [0u8; 32];
+    out.copy_from_slice(&k[..32]);
+    out
+}
+
+fn aes128_cbc_no_padding(key: &[u8], iv: &[u8], data: &[u8]) -> Vec<u8> {
+    let cipher = Aes128::new(GenericArray::from_slice(key));
+    cbc_encrypt(|block| cipher.encrypt_block(block), iv, data)
+}
+
+fn aes256_cbc_no_padding(key: &[u8; 32], iv: &[u8], data: &[u8]) -> Vec<u8> {
+    let cipher = Aes256::new(GenericArray::from_slice(key));
+    cbc_encrypt(|block| cipher.encrypt_block(block), iv, data)
+}
+
+fn aes256_ecb_block(key: &[u8; 32], block: &[u8; 16]) -> [u8; 16] {
+    let cipher = Aes256::new(GenericArray::from_slice(key));
+    let mut out = GenericArray::clone_from_slice(block);
+    cipher.encrypt_block(&mut out);
+    out.into()
+}
+
+// CBC over whole 16-byte blocks; callers pad.
+fn cbc_encrypt(
+    encrypt_block: impl Fn(&mut GenericArray<u8, aes::cipher::consts::U16>),
+    iv: &[u8],
+    data: &[u8],
+) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut prev = [0u8; 16];
+    prev.copy_from_slice(&iv[..16]);
+    for chunk in data.chunks_exact(16) {
+        let mut block = GenericArray::clone_from_slice(chunk);
+        for (b, p) in block.iter_mut().zip(prev.iter()) {
+            *b ^= p;
+        }
+        encrypt_block(&mut block);
+        prev.copy_from_slice(&block);
+        out.extend_from_slice(&block);
+    }
+    out
+}
+
+// Decode a literal string starting at `start` (the opening paren). Returns the bytes and the
+// index just past the closing paren.
+fn parse_literal_string(bytes: &[u8], start: usize) -> (Vec<u8>, usize) {
+    let mut out = Vec::new();
+    let mut depth = 0usize;
+    let mut i = start;
+    while i < bytes.len() {
+        let b = bytes[i];
+        match b {
+            b'(' => {
+                if depth > 0 {
+                    out.push(b);
+                }
+                depth += 1;
+            }
+            b')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return (out, i + 1);
+                }
+                out.push(b);
+            }
+            b'\\' => {
+                i += 1;
+                match bytes.get(i).copied() {
+                    Some(b'n') => out.push(b'\n'),
+                    Some(b'r') => out.push(b'\r'),
+                    Some(b't') => out.push(b'\t'),
+                    Some(b'b') => out.push(0x08),
+                    Some(b'f') => out.push(0x0C),
+                    Some(d @ b'0'..=b'7') => {
+                        let mut value = u32::from(d - b'0');
+                        for _ in 0..2 {
+                            match bytes.get(i + 1).copied() {
+                                Some(d @ b'0'..=b'7') => {
+                                    value = value * 8 + u32::from(d - b'0');
+                                    i += 1;
+                                }
+                                _ => break,
+                            }
+                        }
+                        out.push(value as u8);
+                    }
+                    Some(b'\r') if bytes.get(i + 1) == Some(&b'\n') => i += 1,
+                    Some(b'\r' | b'\n') => {}
+                    Some(other) => out.push(other),
+                    None => {}
+                }
+            }
+            _ => out.push(b),
+        }
+        i += 1;
+    }
+    (out, bytes.len())
+}
+
+fn decode_hex(digits: &[u8]) -> Vec<u8> {
+    let nibbles = digits
+        .iter()
+        .filter_map(|b| (*b as char).to_digit(16))
+        .map(|d| d as u8)
+        .collect::<Vec<_>>();
+    nibbles
+        .chunks(2)
+        .map(|pair| (pair[0] << 4) | pair.get(1).copied().unwrap_or(0))
+        .collect()
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dict_hex(dict: &str, key: &str) -> Vec<u8> {
+        let start = dict.find(&format!("{} <", key)).expect("key") + key.len() + 2;
+        let end = start + dict[start..].find('>').expect("end");
+        decode_hex(&dict.as_bytes()[start..end])
+    }
+
+    #[test]
+    fn user_and_owner_entries_validate_their_passwords() {
+        let spec = EncryptionSpec::aes256("user", "owner");
+        let encryptor = PdfEncryptor::new(&spec).expect("encryptor");
+        let dict = encryptor.encrypt_dict();
+        let u = dict_hex(dict, "/U");
+        let o = dict_hex(dict, "/O");
+        assert_eq!((u.len(), o.len()), (48, 48));
+        assert_eq!(hash_2b(b"user", &u[32..40], &[]), u[..32]);
+        assert_ne!(hash_2b(b"wrong", &u[32..40], &[]), u[..32]);
+        assert_eq!(hash_2b(b"owner", &o[32..40], &u), o[..32]);
+        assert!(dict.contains(&format!("/P {} ", spec.permission_bits())));
+    }
+
+    #[test]
+    fn object_strings_are_rewritten_as_encrypted_hex() {
+        let spec = EncryptionSpec::aes256("", "owner");
+        let mut encryptor = PdfEncryptor::new(&spec).expect("encryptor");
+        let body = "<< /Title (Ledger \\(Q1\\)) /Lang <656E> /Pages 2 0 R >>";
+        let out = encryptor.encrypt_object_strings(body);
+        assert!(out.starts_with("<< /Title <"));
+        assert!(out.ends_with("> /Pages 2 0 R >>"));
+        assert!(!out.contains("Ledger"));
+        assert!(!out.contains("<656E>"));
+        // IV + one padded block per string.
+        let title = dict_hex(&out, "/Title");
+        assert_eq!(title.len(), 32);
+    }
+}
@@ -1,3 +1,5 @@
+use crate::css_diagnostics::CssDiagnostic;
+use crate::warning::Warning;
 use std::fmt;
 
 #[derive(Debug)]
@@ -7,8 +9,242 @@ pub enum FullBleedError {
     EmptyDocumentSet,
     InconsistentPageSize,
     InvalidConfiguration(String),
+    // A length that could not be parsed, with the option or property it was given for.
+    InvalidLength {
+        value: String,
+        context: String,
+    },
+    // A font file, resolver entry or family the configuration names that does not exist.
+    FontNotFound(String),
+    // Font data that could not be decoded; `source` names the file or asset.
+    InvalidFont {
+        source: String,
+        message: String,
+    },
     Asset(String),
+    // Audits gated at "error" for `profile` failed under `enforce_audit_gates`.
+    AuditGateFailed {
+        profile: String,
+        audit_ids: Vec<String>,
+    },
     Io(std::io::Error),
+    // Known losses a render hit under `strict_fidelity`, one warning each.
+    FidelityLoss(Vec<Warning>),
+    // `error` with where in the input it happened and anything else noticed on the way.
+    // Built with `at` and `with_related`; `code`, `location` and `related` see through it.
+    Located {
+        error: Box<FullBleedError>,
+        location: SourceLocation,
+        related: Vec<Diagnostic>,
+    },
+}
+
+// Stable, machine-readable error codes. The strings from `as_str` never change meaning, so
+// callers can match on them across versions and language bindings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    MissingPageTemplate,
+    UnplaceableContent,
+    EmptyDocumentSet,
+    InconsistentPageSize,
+    InvalidConfiguration,
+    InvalidLength,
+    FontNotFound,
+    InvalidFont,
+    Asset,
+    AuditGateFailed,
+    Io,
+    FidelityLoss,
+    // Only on related diagnostics: CSS the engine could not use (see `CssDiagnostics`).
+    Css,
+}
+
+impl ErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::MissingPageTemplate => "MISSING_PAGE_TEMPLATE",
+            ErrorCode::UnplaceableContent => "UNPLACEABLE_CONTENT",
+            ErrorCode::EmptyDocumentSet => "EMPTY_DOCUMENT_SET",
+            ErrorCode::InconsistentPageSize => "INCONSISTENT_PAGE_SIZE",
+            ErrorCode::InvalidConfiguration => "INVALID_CONFIGURATION",
+            ErrorCode::InvalidLength => "INVALID_LENGTH",
+            ErrorCode::FontNotFound => "FONT_NOT_FOUND",
+            ErrorCode::InvalidFont => "INVALID_FONT",
+            ErrorCode::Asset => "ASSET",
+            ErrorCode::AuditGateFailed => "AUDIT_GATE_FAILED",
+            ErrorCode::Io => "IO",
+            ErrorCode::FidelityLoss => "FIDELITY_LOSS",
+            ErrorCode::Css => "CSS",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// Where in the input an error or diagnostic points. Fields are filled where the engine
+// knows them: element path and selector for laid-out content, byte offset or line/column
+// (1-based) for parsed text.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub element_path: Option<String>,
+    pub selector: Option<String>,
+    pub byte_offset: Option<usize>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+}
+
+impl SourceLocation {
+    pub fn is_empty(&self) -> bool {
+        *self == SourceLocation::default()
+    }
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(selector) = &self.selector {
+            parts.push(format!("selector {selector}"));
+        }
+        if let Some(path) = &self.element_path {
+            parts.push(format!("element {path}"));
+        }
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => parts.push(format!("line {line}:{column}")),
+            (Some(line), None) => parts.push(format!("line {line}")),
+            _ => {}
+        }
+        if let Some(offset) = self.byte_offset {
+            parts.push(format!("byte {offset}"));
+        }
+        f.write_str(&parts.join(", "))
+    }
+}
+
+// A secondary finding attached to an error, such as an earlier problem that led to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub code: ErrorCode,
+    pub message: String,
+    pub location: SourceLocation,
+}
+
+impl From<&CssDiagnostic> for Diagnostic {
+    fn from(diagnostic: &CssDiagnostic) -> Self {
+        Diagnostic {
+            code: ErrorCode::Css,
+            message: match &diagnostic.property {
+                Some(property) => format!("{property}: {}", diagnostic.reason),
+                None => diagnostic.reason.clone(),
+            },
+            location: SourceLocation {
+                selector: diagnostic.selector.clone(),
+                line: diagnostic.line.filter(|_| !diagnostic.from_bundle),
+                column: diagnostic.column.filter(|_| !diagnostic.from_bundle),
+                ..SourceLocation::default()
+            },
+        }
+    }
+}
+
+// A known-loss warning; its element is the selector of the rule concerned.
+impl From<&Warning> for Diagnostic {
+    fn from(warning: &Warning) -> Self {
+        Diagnostic {
+            code: ErrorCode::FidelityLoss,
+            message: warning.to_string(),
+            location: SourceLocation {
+                selector: warning.element.clone(),
+                ..SourceLocation::default()
+            },
+        }
+    }
+}
+
+impl FullBleedError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            FullBleedError::MissingPageTemplate => ErrorCode::MissingPageTemplate,
+            FullBleedError::UnplaceableFlowable(_) => ErrorCode::UnplaceableContent,
+            FullBleedError::EmptyDocumentSet => ErrorCode::EmptyDocumentSet,
+            FullBleedError::InconsistentPageSize => ErrorCode::InconsistentPageSize,
+            FullBleedError::InvalidConfiguration(_) => ErrorCode::InvalidConfiguration,
+            FullBleedError::InvalidLength { .. } => ErrorCode::InvalidLength,
+            FullBleedError::FontNotFound(_) => ErrorCode::FontNotFound,
+            FullBleedError::InvalidFont { .. } => ErrorCode::InvalidFont,
+            FullBleedError::Asset(_) => ErrorCode::Asset,
+            FullBleedError::AuditGateFailed { .. } => ErrorCode::AuditGateFailed,
+            FullBleedError::Io(_) => ErrorCode::Io,
+            FullBleedError::FidelityLoss(_) => ErrorCode::FidelityLoss,
+            FullBleedError::Located { error, .. } => error.code(),
+        }
+    }
+
+    // The error without its location wrapper, for matching on the variant.
+    pub fn kind(&self) -> &FullBleedError {
+        match self {
+            FullBleedError::Located { error, .. } => error.kind(),
+            other => other,
+        }
+    }
+
+    pub fn location(&self) -> Option<&SourceLocation> {
+        match self {
+            FullBleedError::Located { location, .. } => Some(location),
+            _ => None,
+        }
+    }
+
+    pub fn related(&self) -> &[Diagnostic] {
+        match self {
+            FullBleedError::Located { related, .. } => related,
+            _ => &[],
+        }
+    }
+
+    // Attaches (or replaces) the location. An empty location leaves the error as it is.
+    pub fn at(self, location: SourceLocation) -> Self {
+        if location.is_empty() {
+            return self;
+        }
+        match self {
+            FullBleedError::Located { error, related, .. } => FullBleedError::Located {
+                error,
+                location,
+                related,
+            },
+            error => FullBleedError::Located {
+                error: Box::new(error),
+                location,
+                related: Vec::new(),
+            },
+        }
+    }
+
+    pub fn with_related(self, diagnostic: Diagnostic) -> Self {
+        match self {
+            FullBleedError::Located {
+                error,
+                location,
+                mut related,
+            } => {
+                related.push(diagnostic);
+                FullBleedError::Located {
+                    error,
+                    location,
+                    related,
+                }
+            }
+            error => FullBleedError::Located {
+                error: Box::new(error),
+                location: SourceLocation::default(),
+                related: vec![diagnostic],
+            },
+        }
+    }
 }
 
 impl fmt::Display for FullBleedError {
@@ -25,8 +261,42 @@ impl fmt::Display for FullBleedError {
             FullBleedError::InvalidConfiguration(message) => {
                 write!(f, "invalid configuration: {}", message)
             }
+            FullBleedError::InvalidLength { value, context } => write!(
+                f,
+                "invalid length {:?} for {}: expected a number with pt, px, in, mm or cm",
+                value, context
+            ),
+            FullBleedError::FontNotFound(name) => write!(f, "font not found: {}", name),
+            FullBleedError::InvalidFont { source, message } => {
+                write!(f, "invalid font {}: {}", source, message)
+            }
             FullBleedError::Asset(message) => write!(f, "asset error: {}", message),
+            FullBleedError::AuditGateFailed { profile, audit_ids } => write!(
+                f,
+                "audit gate failed for profile {}: {}",
+                profile,
+                audit_ids.join(", ")
+            ),
             FullBleedError::Io(err) => write!(f, "io error: {}", err),
+            FullBleedError::FidelityLoss(losses) => {
+                write!(f, "strict fidelity: {} known loss(es)", losses.len())?;
+                for loss in losses {
+                    write!(f, "; {}", loss)?;
+                    if !loss.details.is_empty() {
+                        write!(f, " [{}]", loss.details.join(", "))?;
+                    }
+                }
+                Ok(())
+            }
+            FullBleedError::Located {
+                error, location, ..
+            } => {
+                write!(f, "{}", error)?;
+                if !location.is_empty() {
+                    write!(f, " (at {})", location)?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -35,6 +305,7 @@ impl std::error::Error for FullBleedError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             FullBleedError::Io(err) => Some(err),
+            FullBleedError::Located { error, .. } => error.source(),
             _ => None,
         }
     }
@@ -45,3 +316,77 @@ impl From<std::io::Error> for FullBleedError {
         FullBleedError::Io(value)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FullBleed, Pt, Size};
+
+    #[test]
+    fn errors_carry_stable_codes_locations_and_related_diagnostics() {
+        let err = FullBleedError::UnplaceableFlowable("image".to_string())
+            .at(SourceLocation {
+                selector: Some("img.hero".to_string()),
+                element_path: Some("html>body>img".to_string()),
+                ..SourceLocation::default()
+            })
+            .with_related(Diagnostic {
+                code: ErrorCode::InvalidLength,
+                message: "height ignored".to_string(),
+                location: SourceLocation::default(),
+            });
+        assert_eq!(err.code(), ErrorCode::UnplaceableContent);
+        assert_eq!(err.code().as_str(), "UNPLACEABLE_CONTENT");
+        assert!(matches!(err.kind(), FullBleedError::UnplaceableFlowable(_)));
+        assert_eq!(
+            err.location().and_then(|l| l.selector.as_deref()),
+            Some("img.hero")
+        );
+        assert_eq!(err.related().len(), 1);
+        assert_eq!(
+            err.to_string(),
+            "flowable cannot fit on any page: image (at selector img.hero, element html>body>img)"
+        );
+
+        // An empty location leaves the error unwrapped.
+        let plain = FullBleedError::MissingPageTemplate.at(SourceLocation::default());
+        assert!(plain.location().is_none());
+
+        let err = Pt::parse_length("3em", "margin").unwrap_err();
+        assert_eq!(err.code(), ErrorCode::InvalidLength);
+        assert_eq!(err.location().and_then(|l| l.byte_offset), Some(1));
+        assert_eq!(
+            Pt::parse_length("1in", "margin").unwrap(),
+            Pt::from_f32(72.0)
+        );
+
+        // A failed render carries the CSS and `@font-face` problems it ran into.
+        let engine = FullBleed::builder()
+            .page_size(Size::letter())
+            .strict_fidelity(true)
+            .build()
+            .expect("engine");
+        let err = engine
+            .render_to_buffer(
+                "<p>Hello</p>",
+                "p { frobnicate: 1; }\n@font-face { font-family: Gone; src: url(gone.woff2); }",
+            )
+            .expect_err("strict render should fail");
+        assert_eq!(err.code(), ErrorCode::FidelityLoss);
+        let css = err
+            .related()
+            .iter()
+            .find(|d| d.code == ErrorCode::Css)
+            .expect("css diagnostic");
+        assert!(css.message.contains("frobnicate"), "{css:?}");
+        assert_eq!(css.location.line, Some(1));
+        let face = err
+            .related()
+            .iter()
+            .find(|d| d.location.selector.as_deref() == Some("@font-face"))
+            .expect("font-face diagnostic");
+        assert_eq!(face.code, ErrorCode::Asset);
+        assert!(face.message.contains("Gone"), "{face:?}");
+        assert_eq!(face.location.line, Some(2));
+    }
+}
@@ -0,0 +1,507 @@
+// Remote (http/https) asset resolution. A fetcher does the transfer; `RemoteAssets` applies the
+// host allowlist to the URL and to every redirect target, memoizes recent bodies per engine and
+// keeps a content-addressed cache on disk: `urls/<sha256(url)>` holds the hex digest of the
+// body stored at `objects/<sha256(body)>`.
+use crate::lru::LruCache;
+use sha2::{Digest, Sha256};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "remote_assets")]
+use std::time::Duration;
+use url::{Host, Url};
+
+// Transfers the bytes behind a remote URL. Implement this to route requests through an
+// existing HTTP client, a proxy, or test fixtures.
+pub trait AssetFetcher: Send + Sync {
+    fn fetch(&self, url: &str) -> io::Result<Vec<u8>>;
+
+    // Like `fetch`, but hands redirects back instead of following them, so `RemoteAssets` can
+    // check each target against the allowlist. Fetchers that never redirect keep the default.
+    fn fetch_response(&self, url: &str) -> io::Result<FetchResponse> {
+        self.fetch(url).map(FetchResponse::Body)
+    }
+}
+
+// Outcome of `AssetFetcher::fetch_response`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FetchResponse {
+    Body(Vec<u8>),
+    // The server redirected to this absolute URL.
+    Redirect(String),
+}
+
+// Blocking HTTP(S) fetcher (feature `remote_assets`).
+#[cfg(feature = "remote_assets")]
+#[derive(Debug, Clone)]
+pub struct HttpFetcher {
+    pub timeout: Duration,
+    pub max_bytes: u64,
+}
+
+#[cfg(feature = "remote_assets")]
+impl HttpFetcher {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            max_bytes: DEFAULT_MAX_REMOTE_BYTES,
+        }
+    }
+
+    pub fn max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+}
+
+#[cfg(feature = "remote_assets")]
+impl AssetFetcher for HttpFetcher {
+    // Redirects are refused here; `RemoteAssets` follows them through `fetch_response`.
+    fn fetch(&self, url: &str) -> io::Result<Vec<u8>> {
+        match self.fetch_response(url)? {
+            FetchResponse::Body(bytes) => Ok(bytes),
+            FetchResponse::Redirect(target) => Err(io::Error::other(format!(
+                "remote asset redirects to {target}"
+            ))),
+        }
+    }
+
+    fn fetch_response(&self, url: &str) -> io::Result<FetchResponse> {
+        use std::io::Read;
+        let agent = ureq::AgentBuilder::new()
+            .timeout(self.timeout)
+            .redirects(0)
+            .build();
+        let response = agent
+            .get(url)
+            .call()
+            .map_err(|err| io::Error::other(err.to_string()))?;
+        if (300..400).contains(&response.status()) {
+            let location = response.header("location").ok_or_else(|| {
+                io::Error::other(format!("redirect {} without a location", response.status()))
+            })?;
+            let target = resolve_redirect(url, location).ok_or_else(|| {
+                io::Error::other(format!("redirect to unsupported location {location:?}"))
+            })?;
+            return Ok(FetchResponse::Redirect(target));
+        }
+        let mut bytes = Vec::new();
+        response
+            .into_reader()
+            .take(self.max_bytes + 1)
+            .read_to_end(&mut bytes)?;
+        if bytes.len() as u64 > self.max_bytes {
+            return Err(io::Error::other(format!(
+                "remote asset exceeds {} bytes",
+                self.max_bytes
+            )));
+        }
+        Ok(FetchResponse::Body(bytes))
+    }
+}
+
+#[cfg(feature = "remote_assets")]
+const DEFAULT_MAX_REMOTE_BYTES: u64 = 64 * 1024 * 1024;
+
+// Redirect hops followed before a fetch fails.
+const MAX_REDIRECTS: usize = 5;
+
+// Bodies kept in memory per engine, by count and by total size (a single response may be as
+// large as `HttpFetcher::max_bytes`); older ones are read back from `cache_dir` or refetched.
+const MEMO_CAPACITY: usize = 64;
+const MEMO_MAX_BYTES: usize = 32 * 1024 * 1024;
+
+// Policy for resolving `http://` and `https://` asset references.
+//
+// Nothing is fetched unless its host is allowlisted: `allow_host("cdn.example.com")` matches
+// that host exactly, `"*.example.com"` matches its subdomains, and `"*"` matches any host.
+#[derive(Clone)]
+pub struct RemoteAssets {
+    fetcher: Arc<dyn AssetFetcher>,
+    allowed_hosts: Vec<String>,
+    cache_dir: Option<PathBuf>,
+    memo: Arc<Mutex<LruCache<String, Arc<Vec<u8>>>>>,
+}
+
+impl std::fmt::Debug for RemoteAssets {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RemoteAssets")
+            .field("allowed_hosts", &self.allowed_hosts)
+            .field("cache_dir", &self.cache_dir)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RemoteAssets {
+    pub fn new(fetcher: impl AssetFetcher + 'static) -> Self {
+        Self {
+            fetcher: Arc::new(fetcher),
+            allowed_hosts: Vec::new(),
+            cache_dir: None,
+            memo: Arc::new(Mutex::new(LruCache::weighted(
+                MEMO_CAPACITY,
+                MEMO_MAX_BYTES,
+                |body: &Arc<Vec<u8>>| body.len(),
+            ))),
+        }
+    }
+
+    // Built-in HTTP fetcher with the given per-request timeout.
+    #[cfg(feature = "remote_assets")]
+    pub fn http(timeout: Duration) -> Self {
+        Self::new(HttpFetcher::new(timeout))
+    }
+
+    pub fn allow_host(mut self, host: impl Into<String>) -> Self {
+        self.allowed_hosts
+            .push(host.into().trim().to_ascii_lowercase());
+        self
+    }
+
+    pub fn cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(dir.into());
+        self
+    }
+
+    pub(crate) fn fetch(&self, url: &str) -> io::Result<Arc<Vec<u8>>> {
+        // Everything past the check uses the URL as the transport will read it.
+        let url = self.check_host(url)?;
+        let url = url.as_str();
+        if let Some(bytes) = self
+            .memo
+            .lock()
+            .ok()
+            .and_then(|mut memo| memo.get(&url.to_string()).cloned())
+        {
+            return Ok(bytes);
+        }
+        let bytes = match self
+            .cache_dir
+            .as_deref()
+            .and_then(|dir| cache_read(dir, url))
+        {
+            Some(bytes) => bytes,
+            None => {
+                let bytes = self.fetch_following_redirects(url)?;
+                if let Some(dir) = self.cache_dir.as_deref() {
+                    // A read-only or full cache dir must not fail the render.
+                    let _ = cache_write(dir, url, &bytes);
+                }
+                bytes
+            }
+        };
+        let bytes = Arc::new(bytes);
+        if let Ok(mut memo) = self.memo.lock() {
+            memo.insert(url.to_string(), bytes.clone());
+        }
+        Ok(bytes)
+    }
+
+    // Returns the normalised URL whose host passed the allowlist.
+    fn check_host(&self, url: &str) -> io::Result<Url> {
+        let parsed = parse_remote_url(url)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not an http(s) URL"))?;
+        let host = url_host(&parsed)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "URL has no host"))?;
+        if !self.host_allowed(&host) {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("remote host {host:?} is not in the asset allowlist"),
+            ));
+        }
+        Ok(parsed)
+    }
+
+    // An allowlisted host may redirect anywhere, so each hop is checked before it is fetched.
+    fn fetch_following_redirects(&self, url: &str) -> io::Result<Vec<u8>> {
+        let mut current = url.to_string();
+        for _ in 0..=MAX_REDIRECTS {
+            match self.fetcher.fetch_response(&current)? {
+                FetchResponse::Body(bytes) => return Ok(bytes),
+                FetchResponse::Redirect(target) => {
+                    current = self.check_host(&target)?.into();
+                }
+            }
+        }
+        Err(io::Error::other(format!(
+            "remote asset exceeds {MAX_REDIRECTS} redirects"
+        )))
+    }
+
+    fn host_allowed(&self, host: &str) -> bool {
+        self.allowed_hosts.iter().any(|pattern| {
+            if pattern == "*" {
+                return true;
+            }
+            match pattern.strip_prefix("*.") {
+                Some(suffix) => host
+                    .strip_suffix(suffix)
+                    .is_some_and(|prefix| prefix.ends_with('.')),
+                None => host == pattern,
+            }
+        })
+    }
+}
+
+pub(crate) fn is_remote_url(source: &str) -> bool {
+    let lower = source
+        .trim_start()
+        .get(..8)
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://")
+}
+
+// An http(s) URL parsed with the WHATWG rules the transport applies, so `\`, userinfo and
+// percent-encoding resolve to the host that would actually be contacted.
+fn parse_remote_url(url: &str) -> Option<Url> {
+    if !is_remote_url(url) {
+        return None;
+    }
+    let parsed = Url::parse(url.trim()).ok()?;
+    matches!(parsed.scheme(), "http" | "https").then_some(parsed)
+}
+
+// Lowercased host of a parsed URL, without userinfo, port or IPv6 brackets.
+fn url_host(url: &Url) -> Option<String> {
+    match url.host()? {
+        Host::Domain(domain) if !domain.is_empty() => Some(domain.to_ascii_lowercase()),
+        Host::Domain(_) => None,
+        Host::Ipv4(addr) => Some(addr.to_string()),
+        Host::Ipv6(addr) => Some(addr.to_string()),
+    }
+}
+
+#[cfg(test)]
+fn remote_host(url: &str) -> Option<String> {
+    url_host(&parse_remote_url(url)?)
+}
+
+// Absolute URL of a redirect `location` relative to `base`; None for non-http(s) targets.
+#[cfg(feature = "remote_assets")]
+fn resolve_redirect(base: &str, location: &str) -> Option<String> {
+    let target = Url::parse(base.trim()).ok()?.join(location.trim()).ok()?;
+    // Another scheme (`file:`, `data:`, ...) is never followed.
+    matches!(target.scheme(), "http" | "https").then(|| target.into())
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    crate::encrypt::hex(&Sha256::digest(data))
+}
+
+fn cache_read(dir: &Path, url: &str) -> Option<Vec<u8>> {
+    let digest = std::fs::read_to_string(dir.join("urls").join(sha256_hex(url.as_bytes()))).ok()?;
+    let digest = digest.trim();
+    let bytes = std::fs::read(dir.join("objects").join(digest)).ok()?;
+    // A truncated or edited object is treated as a miss and refetched.
+    (sha256_hex(&bytes) == digest).then_some(bytes)
+}
+
+fn cache_write(dir: &Path, url: &str, bytes: &[u8]) -> io::Result<()> {
+    let digest = sha256_hex(bytes);
+    let objects = dir.join("objects");
+    let urls = dir.join("urls");
+    std::fs::create_dir_all(&objects)?;
+    std::fs::create_dir_all(&urls)?;
+    let object = objects.join(&digest);
+    if !object.exists() {
+        // Unique per call: concurrent writers of the same body must not share a temp file.
+        static TMP_SEQ: AtomicU64 = AtomicU64::new(0);
+        let seq = TMP_SEQ.fetch_add(1, Ordering::Relaxed);
+        let tmp = objects.join(format!("{digest}.tmp{}-{seq}", std::process::id()));
+        std::fs::write(&tmp, bytes)?;
+        std::fs::rename(&tmp, &object)?;
+    }
+    std::fs::write(urls.join(sha256_hex(url.as_bytes())), digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        AssetFetcher, FetchResponse, MEMO_CAPACITY, MEMO_MAX_BYTES, RemoteAssets, remote_host,
+    };
+    use std::io;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct CountingFetcher(Arc<AtomicUsize>);
+
+    impl AssetFetcher for CountingFetcher {
+        fn fetch(&self, url: &str) -> io::Result<Vec<u8>> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(url.as_bytes().to_vec())
+        }
+    }
+
+    #[test]
+    fn allowlist_matches_exact_and_wildcard_hosts() {
+        assert_eq!(
+            remote_host("https://user@CDN.Example.com:8443/a.png?x#y").as_deref(),
+            Some("cdn.example.com")
+        );
+        assert_eq!(remote_host("http://[::1]:8080/x").as_deref(), Some("::1"));
+        let calls = Arc::new(AtomicUsize::new(0));
+        let remote = RemoteAssets::new(CountingFetcher(calls.clone()))
+            .allow_host("img.example.org")
+            .allow_host("*.example.com");
+        assert!(remote.fetch("https://img.example.org/logo.png").is_ok());
+        assert!(remote.fetch("https://cdn.example.com/logo.png").is_ok());
+        let err = remote
+            .fetch("https://example.com.evil.net/logo.png")
+            .expect_err("host outside the allowlist");
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert!(remote.fetch("https://badexample.com/logo.png").is_err());
+        // The transport reads `\` as `/`, so this connects to evil.com, not the CDN.
+        assert_eq!(
+            remote_host("https://evil.com\\@cdn.example.com/x").as_deref(),
+            Some("evil.com")
+        );
+        let err = remote
+            .fetch("https://evil.com\\@cdn.example.com/logo.png")
+            .expect_err("backslash authority bypass");
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    // Redirects `/start` to `target` and serves every other URL as its own bytes.
+    struct RedirectingFetcher {
+        target: &'static str,
+        fetched: Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    impl AssetFetcher for RedirectingFetcher {
+        fn fetch(&self, url: &str) -> io::Result<Vec<u8>> {
+            Ok(url.as_bytes().to_vec())
+        }
+
+        fn fetch_response(&self, url: &str) -> io::Result<FetchResponse> {
+            self.fetched.lock().expect("log").push(url.to_string());
+            if url.ends_with("/start") {
+                Ok(FetchResponse::Redirect(self.target.to_string()))
+            } else {
+                self.fetch(url).map(FetchResponse::Body)
+            }
+        }
+    }
+
+    #[test]
+    fn redirects_are_checked_against_the_allowlist() {
+        let fetched = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let remote = |target| {
+            RemoteAssets::new(RedirectingFetcher {
+                target,
+                fetched: fetched.clone(),
+            })
+            .allow_host("cdn.example.com")
+        };
+
+        let followed = remote("https://cdn.example.com/logo.png")
+            .fetch("https://cdn.example.com/start")
+            .expect("allowed redirect");
+        assert_eq!(followed.as_slice(), b"https://cdn.example.com/logo.png");
+
+        let err = remote("http://169.254.169.254/latest/meta-data")
+            .fetch("https://cdn.example.com/start")
+            .expect_err("redirect off the allowlist");
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert!(
+            !fetched
+                .lock()
+                .expect("log")
+                .iter()
+                .any(|url| url.contains("169.254"))
+        );
+
+        let err = remote("https://cdn.example.com/start")
+            .fetch("https://cdn.example.com/start")
+            .expect_err("redirect loop");
+        assert!(err.to_string().contains("redirects"));
+    }
+
+    #[cfg(feature = "remote_assets")]
+    #[test]
+    fn redirect_locations_resolve_against_the_request_url() {
+        use super::resolve_redirect;
+        let base = "https://cdn.example.com/img/logo.png?v=2";
+        let resolve = |location| resolve_redirect(base, location);
+        assert_eq!(
+            resolve("http://127.0.0.1/x").as_deref(),
+            Some("http://127.0.0.1/x")
+        );
+        assert_eq!(
+            resolve("//other.example.com/a").as_deref(),
+            Some("https://other.example.com/a")
+        );
+        assert_eq!(
+            resolve("/a.png").as_deref(),
+            Some("https://cdn.example.com/a.png")
+        );
+        assert_eq!(
+            resolve("b.png").as_deref(),
+            Some("https://cdn.example.com/img/b.png")
+        );
+        assert_eq!(resolve("file:///etc/passwd"), None);
+    }
+
+    #[test]
+    fn memo_keeps_a_bounded_number_of_bodies() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let remote =
+            RemoteAssets::new(CountingFetcher(calls.clone())).allow_host("cdn.example.com");
+        for idx in 0..=MEMO_CAPACITY {
+            remote
+                .fetch(&format!("https://cdn.example.com/{idx}.png"))
+                .expect("fetch");
+        }
+        assert_eq!(remote.memo.lock().expect("memo").len(), MEMO_CAPACITY);
+        // The first body was dropped and is fetched again.
+        remote
+            .fetch("https://cdn.example.com/0.png")
+            .expect("refetch");
+        assert_eq!(calls.load(Ordering::SeqCst), MEMO_CAPACITY + 2);
+    }
+
+    struct SizedFetcher(usize);
+
+    impl AssetFetcher for SizedFetcher {
+        fn fetch(&self, _url: &str) -> io::Result<Vec<u8>> {
+            Ok(vec![0; self.0])
+        }
+    }
+
+    #[test]
+    fn memo_keeps_a_bounded_number_of_bytes() {
+        let remote =
+            RemoteAssets::new(SizedFetcher(MEMO_MAX_BYTES / 3 + 1)).allow_host("cdn.example.com");
+        for idx in 0..4 {
+            remote
+                .fetch(&format!("https://cdn.example.com/{idx}.png"))
+                .expect("fetch");
+        }
+        let memo = remote.memo.lock().expect("memo");
+        assert_eq!(memo.len(), 2);
+        assert!(memo.weight() <= MEMO_MAX_BYTES);
+    }
+
+    #[test]
+    fn cache_dir_serves_repeat_fetches_without_the_network() {
+        let dir = std::env::temp_dir().join(format!("fullbleed-fetch-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let url = "https://cdn.example.com/a.png";
+        let calls = Arc::new(AtomicUsize::new(0));
+        let first = RemoteAssets::new(CountingFetcher(calls.clone()))
+            .allow_host("cdn.example.com")
+            .cache_dir(&dir);
+        assert_eq!(first.fetch(url).expect("fetch").as_slice(), url.as_bytes());
+        assert_eq!(first.fetch(url).expect("memo").as_slice(), url.as_bytes());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let second = RemoteAssets::new(CountingFetcher(calls.clone()))
+            .allow_host("cdn.example.com")
+            .cache_dir(&dir);
+        assert_eq!(second.fetch(url).expect("disk").as_slice(), url.as_bytes());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -10,6 +10,8 @@ use std::collections::{BTreeMap, BTreeSet};
 use std::path::PathBuf;
 
 pub const META_PAGE_TEMPLATE_KEY: &str = "fb.page_template";
+// PDF page label style (`D`, `r`, ...) of the page counter a page's margin boxes print.
+pub(crate) const META_PAGE_LABEL_KEY: &str = "fb.page_label";
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum BindingSource {
@@ -610,7 +612,7 @@ pub fn collect_page_feature_flags(doc: &Document, feature_prefix: &str) -> Vec<B
         .map(|page| {
             let mut features = BTreeSet::new();
             for cmd in &page.commands {
-                let Command::Meta { key, value } = cmd else {
+                let Command::Meta { key, value } = &*cmd else {
                     continue;
                 };
                 if !key.starts_with(feature_prefix) {
@@ -634,7 +636,7 @@ pub fn collect_page_template_names(doc: &Document, template_key: &str) -> Vec<Op
         .iter()
         .map(|page| {
             for cmd in &page.commands {
-                let Command::Meta { key, value } = cmd else {
+                let Command::Meta { key, value } = &*cmd else {
                     continue;
                 };
                 if key == template_key && !value.trim().is_empty() {
@@ -1022,13 +1024,19 @@ mod tests {
                             key: "fb.feature.i9".to_string(),
                             value: "true".to_string(),
                         },
-                    ],
+                    ]
+                    .into(),
+                    size: None,
+                    rotate: 0,
                 },
                 Page {
                     commands: vec![Command::Meta {
                         key: "fb.feature.w2".to_string(),
                         value: "1".to_string(),
-                    }],
+                    }]
+                    .into(),
+                    size: None,
+                    rotate: 0,
                 },
             ],
         };
@@ -1099,7 +1107,10 @@ mod tests {
                         key: META_PAGE_TEMPLATE_KEY.to_string(),
                         value: "page_ignored".to_string(),
                     },
-                ],
+                ]
+                .into(),
+                size: None,
+                rotate: 0,
             }],
         };
         let out = collect_page_template_names(&doc, META_PAGE_TEMPLATE_KEY);
@@ -1121,13 +1132,19 @@ mod tests {
                             key: "fb.feature.vip".to_string(),
                             value: "1".to_string(),
                         },
-                    ],
+                    ]
+                    .into(),
+                    size: None,
+                    rotate: 0,
                 },
                 Page {
                     commands: vec![Command::Meta {
                         key: META_PAGE_TEMPLATE_KEY.to_string(),
                         value: "page_2".to_string(),
-                    }],
+                    }]
+                    .into(),
+                    size: None,
+                    rotate: 0,
                 },
             ],
         };
@@ -1,15 +1,23 @@
-use crate::canvas::{Canvas, META_DIAGNOSTIC_SCOPE_BEGIN_KEY, META_DIAGNOSTIC_SCOPE_END_KEY};
+use crate::canvas::{
+    AbsContinuation, Canvas, LinkTarget, META_DIAGNOSTIC_SCOPE_BEGIN_KEY,
+    META_DIAGNOSTIC_SCOPE_END_KEY,
+};
 use crate::font::FontRegistry;
+use crate::frame::{ColumnFill, ColumnSet};
+use crate::layout_profile;
+use crate::lru::LruCache;
 use crate::perf::PerfLogger;
+use crate::platform::Instant;
 use crate::svg;
 use crate::types::{BoxSizingMode, Color, MixBlendMode, Pt, Rect, Shading, ShadingStop, Size};
 use rayon::prelude::*;
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
 
-fn huge_pt() -> Pt {
+pub(crate) fn huge_pt() -> Pt {
     // Large but safe sentinel for "unbounded" layout measurements.
     Pt::from_f32(1.0e9)
 }
@@ -72,6 +80,14 @@ impl Drop for PerfGuard {
     }
 }
 
+// The logger and document id set on this thread, to carry over to layout on other threads.
+pub(crate) fn perf_context() -> (Option<Arc<PerfLogger>>, Option<usize>) {
+    PERF_CTX.with(|ctx| match ctx.borrow().as_ref() {
+        Some(ctx) => (Some(ctx.logger.clone()), ctx.doc_id),
+        None => (None, None),
+    })
+}
+
 fn perf_enabled() -> bool {
     PERF_CTX.with(|ctx| ctx.borrow().is_some())
 }
@@ -108,7 +124,7 @@ fn perf_end(name: &str, start: Option<Instant>) {
 }
 
 #[derive(Debug, Clone)]
-struct LineLayout {
+pub(crate) struct LineLayout {
     text: String,
     width: Pt,
 }
@@ -161,16 +177,92 @@ impl TextLayoutCache {
     }
 }
 
+// Paragraphs broken into lines, shared by every paragraph of an engine through its font
+// registry, so text repeated across batch records (terms and conditions, disclaimers) is broken
+// once. Entries keep their text, so a hash collision is a miss rather than wrong lines.
+#[derive(Debug)]
+pub(crate) struct LineBreakCache {
+    entries: Mutex<LruCache<LineBreakKey, LineBreakEntry>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+// The paragraph text an entry was broken from, and its lines.
+type LineBreakEntry = (Arc<str>, Arc<Vec<LineLayout>>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct LineBreakKey {
+    text_hash: u64,
+    // Style and paragraph settings line breaking reads, and the registry's CSS face count
+    // (a later `@font-face` can change what a font name measures).
+    style_hash: u64,
+    width_milli: i64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct LineBreakStats {
+    pub(crate) hits: u64,
+    pub(crate) misses: u64,
+    pub(crate) entries: usize,
+}
+
+impl LineBreakCache {
+    const MAX_ENTRIES: usize = 2048;
+
+    pub(crate) fn new() -> Self {
+        Self {
+            entries: Mutex::new(LruCache::new(Self::MAX_ENTRIES)),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    fn get(&self, key: &LineBreakKey, text: &str) -> Option<Arc<Vec<LineLayout>>> {
+        let lines = self.entries.lock().ok().and_then(|mut entries| {
+            entries
+                .get(key)
+                .filter(|(cached, _)| cached.as_ref() == text)
+                .map(|(_, lines)| lines.clone())
+        });
+        let counter = if lines.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        lines
+    }
+
+    fn insert(&self, key: LineBreakKey, text: &str, lines: Arc<Vec<LineLayout>>) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(key, (Arc::from(text), lines));
+        }
+    }
+
+    pub(crate) fn stats(&self) -> LineBreakStats {
+        LineBreakStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.entries.lock().map_or(0, |entries| entries.len()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BreakBefore {
     Auto,
     Page,
+    // Continue in the next frame of the page template (`break-before: column | frame`).
+    Frame,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BreakAfter {
     Auto,
     Page,
+    Frame,
+    // Keep with the next flowable (`break-after: avoid`; headings by default).
+    Avoid,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -316,6 +408,81 @@ impl CssTransformOrigin {
     }
 }
 
+// Saves the graphics state and applies `ops` around `origin` within `area`; returns false
+// (and leaves the canvas untouched) when there is nothing to apply. The caller restores.
+fn begin_css_transform(
+    canvas: &mut Canvas,
+    ops: &[CssTransformOp],
+    origin: CssTransformOrigin,
+    area: Rect,
+    font_size: Pt,
+    root_font_size: Pt,
+) -> bool {
+    if ops.is_empty() {
+        return false;
+    }
+    let origin_x = area.x
+        + origin
+            .x
+            .resolve_width(area.width, font_size, root_font_size);
+    let origin_y = area.y
+        + origin
+            .y
+            .resolve_height(area.height, font_size, root_font_size);
+    canvas.save_state();
+    canvas.translate(-origin_x, -origin_y);
+    apply_css_transform_ops(
+        canvas,
+        ops,
+        area.width,
+        area.height,
+        font_size,
+        root_font_size,
+    );
+    canvas.translate(origin_x, origin_y);
+    true
+}
+
+fn apply_css_transform_ops(
+    canvas: &mut Canvas,
+    ops: &[CssTransformOp],
+    ref_width: Pt,
+    ref_height: Pt,
+    font_size: Pt,
+    root_font_size: Pt,
+) {
+    for op in ops {
+        match op {
+            CssTransformOp::Translate { x, y } => {
+                let tx = x.resolve_width(ref_width, font_size, root_font_size);
+                let ty = y.resolve_height(ref_height, font_size, root_font_size);
+                canvas.translate(tx, ty);
+            }
+            CssTransformOp::Scale { x, y } => {
+                canvas.scale(*x, *y);
+            }
+            CssTransformOp::Rotate { radians } => {
+                canvas.rotate(*radians);
+            }
+            CssTransformOp::Skew {
+                x_radians,
+                y_radians,
+            } => {
+                let c = x_radians.tan();
+                let b = y_radians.tan();
+                if c.is_finite() && b.is_finite() {
+                    canvas.concat_matrix(1.0, b, c, 1.0, Pt::ZERO, Pt::ZERO);
+                }
+            }
+            CssTransformOp::Matrix { a, b, c, d, e, f } => {
+                if a.is_finite() && b.is_finite() && c.is_finite() && d.is_finite() {
+                    canvas.concat_matrix(*a, *b, *c, *d, *e, *f);
+                }
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct EdgeSizes {
     pub top: LengthSpec,
@@ -440,6 +607,143 @@ pub struct BoxShadowSpec {
     pub color_var: Option<String>,
 }
 
+// Shadow geometry resolved against the box it is drawn for.
+#[derive(Debug, Clone, Copy)]
+struct ResolvedShadow {
+    offset_x: Pt,
+    offset_y: Pt,
+    blur: Pt,
+    spread: Pt,
+    color: Color,
+    opacity: f32,
+}
+
+impl BoxShadowSpec {
+    fn resolve(&self, width: Pt, height: Pt, font_size: Pt, root_font_size: Pt) -> ResolvedShadow {
+        ResolvedShadow {
+            offset_x: self
+                .offset_x
+                .resolve_width(width, font_size, root_font_size),
+            offset_y: self
+                .offset_y
+                .resolve_height(height, font_size, root_font_size),
+            blur: self
+                .blur
+                .resolve_width(width, font_size, root_font_size)
+                .max(Pt::ZERO),
+            spread: self.spread.resolve_width(width, font_size, root_font_size),
+            color: self.color,
+            opacity: self.opacity.clamp(0.0, 1.0),
+        }
+    }
+}
+
+// Standard normal CDF (Abramowitz-Stegun 7.1.26 erf approximation).
+fn normal_cdf(x: f32) -> f32 {
+    let z = x / std::f32::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.327_591_1 * z.abs());
+    let poly = ((((1.061_405_4 * t - 1.453_152) * t + 1.421_413_8) * t - 0.284_496_72) * t
+        + 0.254_829_6)
+        * t;
+    let erf = 1.0 - poly * (-z * z).exp();
+    0.5 * (1.0 + if z >= 0.0 { erf } else { -erf })
+}
+
+// Nested fills approximating a Gaussian-blurred shadow edge (sigma = blur / 2), outermost
+// first. Each entry is how far the layer reaches past the shadow edge and its alpha; the
+// alphas are chosen so the composited stack matches the blurred coverage at each layer edge.
+fn shadow_blur_layers(blur: Pt, opacity: f32) -> Vec<(Pt, f32)> {
+    let blur_f = blur.to_f32();
+    if blur_f <= 0.0 {
+        return vec![(Pt::ZERO, opacity)];
+    }
+    let sigma = blur_f / 2.0;
+    let steps = ((blur_f / 0.75).ceil() as usize).clamp(6, 32);
+    let mut layers = Vec::with_capacity(steps + 1);
+    let mut covered = 0.0_f32;
+    for k in 0..=steps {
+        let reach = blur_f * (1.0 - 2.0 * k as f32 / steps as f32);
+        let target = if k == steps {
+            opacity
+        } else {
+            opacity * (1.0 - normal_cdf(reach / sigma))
+        };
+        if target <= covered || covered >= 1.0 {
+            continue;
+        }
+        let alpha = 1.0 - (1.0 - target) / (1.0 - covered);
+        layers.push((Pt::from_f32(reach), alpha.clamp(0.0, 1.0)));
+        covered = target;
+    }
+    layers
+}
+
+fn shadow_shape_path(canvas: &mut Canvas, x: Pt, y: Pt, width: Pt, height: Pt, radius: Pt) {
+    if radius > Pt::ZERO {
+        ContainerFlowable::rounded_rect_path(canvas, x, y, width, height, radius);
+        return;
+    }
+    canvas.move_to(x, y);
+    canvas.line_to(x + width, y);
+    canvas.line_to(x + width, y + height);
+    canvas.line_to(x, y + height);
+    canvas.close_path();
+}
+
+// `inset` box shadow painted inside `area` (the padding box): everything outside the area
+// shifted by the offset and shrunk by the spread is shaded, fading over the blur radius.
+fn draw_inset_shadow(canvas: &mut Canvas, area: Rect, radius: Pt, shadow: ResolvedShadow) {
+    if shadow.opacity <= 0.0 || area.width <= Pt::ZERO || area.height <= Pt::ZERO {
+        return;
+    }
+    let hole_x = area.x + shadow.offset_x + shadow.spread;
+    let hole_y = area.y + shadow.offset_y + shadow.spread;
+    let hole_w = area.width - shadow.spread * 2;
+    let hole_h = area.height - shadow.spread * 2;
+    let hole_r = (radius - shadow.spread).max(Pt::ZERO);
+    canvas.save_state();
+    shadow_shape_path(canvas, area.x, area.y, area.width, area.height, radius);
+    canvas.clip_path(false);
+    canvas.set_fill_color(shadow.color);
+    for (reach, alpha) in shadow_blur_layers(shadow.blur, shadow.opacity) {
+        canvas.set_opacity(alpha, alpha);
+        shadow_shape_path(canvas, area.x, area.y, area.width, area.height, radius);
+        let w = hole_w - reach * 2;
+        let h = hole_h - reach * 2;
+        if w > Pt::ZERO && h > Pt::ZERO {
+            shadow_shape_path(
+                canvas,
+                hole_x + reach,
+                hole_y + reach,
+                w,
+                h,
+                (hole_r - reach).max(Pt::ZERO),
+            );
+            canvas.fill_evenodd();
+        } else {
+            canvas.fill();
+        }
+    }
+    canvas.set_opacity(1.0, 1.0);
+    canvas.restore_state();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutlineStyle {
+    Solid,
+    Dashed,
+    Dotted,
+}
+
+// CSS `outline`: drawn outside the border box (plus `offset`) without affecting layout.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutlineSpec {
+    pub width: LengthSpec,
+    pub offset: LengthSpec,
+    pub style: OutlineStyle,
+    pub color: Color,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct PaintFilterSpec {
     pub saturate: f32,
@@ -478,6 +782,202 @@ pub enum BackgroundPaint {
     },
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BackgroundSize {
+    Auto,
+    Cover,
+    Contain,
+    // Width and height; either may be `Auto` to keep the image's aspect ratio.
+    Explicit(LengthSpec, LengthSpec),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackgroundRepeat {
+    Repeat,
+    RepeatX,
+    RepeatY,
+    NoRepeat,
+}
+
+// Upper bound on tiles drawn for one repeated background image.
+const MAX_BACKGROUND_TILES: usize = 4096;
+
+// `background-image: url(...)` laid out in a box's padding box. `intrinsic` is the image
+// size in points when it could be decoded; without it the image is sized to the box.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackgroundImage {
+    pub source: String,
+    pub intrinsic: Option<Size>,
+    pub size: BackgroundSize,
+    pub position_x: LengthSpec,
+    pub position_y: LengthSpec,
+    pub repeat: BackgroundRepeat,
+}
+
+impl BackgroundImage {
+    pub fn new(source: impl Into<String>) -> Self {
+        Self {
+            source: source.into(),
+            intrinsic: None,
+            size: BackgroundSize::Auto,
+            position_x: LengthSpec::Percent(0.0),
+            position_y: LengthSpec::Percent(0.0),
+            repeat: BackgroundRepeat::Repeat,
+        }
+    }
+
+    pub fn with_intrinsic_size(mut self, size: Option<Size>) -> Self {
+        self.intrinsic = size;
+        self
+    }
+
+    pub fn with_size(mut self, size: BackgroundSize) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn with_position(mut self, x: LengthSpec, y: LengthSpec) -> Self {
+        self.position_x = x;
+        self.position_y = y;
+        self
+    }
+
+    pub fn with_repeat(mut self, repeat: BackgroundRepeat) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    fn tile_size(&self, area: Size, font_size: Pt, root_font_size: Pt) -> Size {
+        let intrinsic = self
+            .intrinsic
+            .filter(|size| size.width > Pt::ZERO && size.height > Pt::ZERO)
+            .unwrap_or(area);
+        let ratio = if intrinsic.width > Pt::ZERO {
+            intrinsic.height.to_f32() / intrinsic.width.to_f32()
+        } else {
+            1.0
+        };
+        match self.size {
+            BackgroundSize::Auto => intrinsic,
+            BackgroundSize::Cover | BackgroundSize::Contain => {
+                let sx = area.width.to_f32() / intrinsic.width.to_f32().max(1.0e-3);
+                let sy = area.height.to_f32() / intrinsic.height.to_f32().max(1.0e-3);
+                let scale = if self.size == BackgroundSize::Cover {
+                    sx.max(sy)
+                } else {
+                    sx.min(sy)
+                };
+                Size {
+                    width: intrinsic.width * scale,
+                    height: intrinsic.height * scale,
+                }
+            }
+            BackgroundSize::Explicit(width, height) => {
+                let width = (!matches!(width, LengthSpec::Auto))
+                    .then(|| width.resolve_width(area.width, font_size, root_font_size));
+                let height = (!matches!(height, LengthSpec::Auto))
+                    .then(|| height.resolve_height(area.height, font_size, root_font_size));
+                match (width, height) {
+                    (Some(width), Some(height)) => Size { width, height },
+                    (Some(width), None) => Size {
+                        width,
+                        height: width * ratio,
+                    },
+                    (None, Some(height)) => Size {
+                        width: height * (1.0 / ratio.max(1.0e-3)),
+                        height,
+                    },
+                    (None, None) => intrinsic,
+                }
+            }
+        }
+    }
+
+    // Percent positions align the same point of the image and the box, as in CSS.
+    fn offset(spec: LengthSpec, free: Pt, basis: Pt, font_size: Pt, root_font_size: Pt) -> Pt {
+        match spec {
+            LengthSpec::Percent(pct) => free * pct,
+            other => other.resolve_width(basis, font_size, root_font_size),
+        }
+    }
+
+    // First tile start along one axis: repeated tiles start at or before the box edge.
+    fn tile_starts(origin: Pt, extent: Pt, offset: Pt, tile: Pt, repeat: bool) -> Vec<Pt> {
+        let first = origin + offset;
+        if !repeat {
+            return vec![first];
+        }
+        let tile_f = tile.to_f32();
+        let back = ((first - origin).to_f32() / tile_f).ceil().max(0.0);
+        let mut pos = first - tile * back;
+        let end = origin + extent;
+        let mut starts = Vec::new();
+        while pos < end && starts.len() < MAX_BACKGROUND_TILES {
+            starts.push(pos);
+            pos += tile;
+        }
+        starts
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas, area: Rect, font_size: Pt, root_font_size: Pt) {
+        let area_size = Size {
+            width: area.width,
+            height: area.height,
+        };
+        let tile = self.tile_size(area_size, font_size, root_font_size);
+        if tile.width <= Pt::from_f32(0.01) || tile.height <= Pt::from_f32(0.01) {
+            return;
+        }
+        let offset_x = Self::offset(
+            self.position_x,
+            area.width - tile.width,
+            area.width,
+            font_size,
+            root_font_size,
+        );
+        let offset_y = Self::offset(
+            self.position_y,
+            area.height - tile.height,
+            area.height,
+            font_size,
+            root_font_size,
+        );
+        let xs = Self::tile_starts(
+            area.x,
+            area.width,
+            offset_x,
+            tile.width,
+            matches!(
+                self.repeat,
+                BackgroundRepeat::Repeat | BackgroundRepeat::RepeatX
+            ),
+        );
+        let ys = Self::tile_starts(
+            area.y,
+            area.height,
+            offset_y,
+            tile.height,
+            matches!(
+                self.repeat,
+                BackgroundRepeat::Repeat | BackgroundRepeat::RepeatY
+            ),
+        );
+        canvas.save_state();
+        canvas.clip_rect(area.x, area.y, area.width, area.height);
+        let mut drawn = 0usize;
+        'rows: for y in &ys {
+            for x in &xs {
+                if drawn >= MAX_BACKGROUND_TILES {
+                    break 'rows;
+                }
+                canvas.draw_image(*x, *y, tile.width, tile.height, self.source.clone());
+                drawn += 1;
+            }
+        }
+        canvas.restore_state();
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct ClipPathInsetSpec {
     pub top: LengthSpec,
@@ -519,8 +1019,41 @@ struct ResolvedBorder {
     colors: ResolvedEdgeColors,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatSide {
+    Left,
+    Right,
+}
+
+// Space taken by a float next to the top of a flowable: `width` from the float's side, for
+// `height` measured down from the flowable's top edge.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FloatExclusion {
+    pub side: FloatSide,
+    pub width: Pt,
+    pub height: Pt,
+}
+
+// A block of content laid out by frames. Custom flowables implement `wrap`, `split` and
+// `draw`, derive `Clone` (which provides `FlowableClone`), and are added with
+// `DocTemplate::add_flowable` or returned from a `FullBleedBuilder::register_element`
+// factory. The other methods have defaults suited to plain in-flow blocks.
+//
+// Layout calls these in order for each placement:
+// - `wrap` measures the flowable for the space left in the frame. It may be called many
+//   times with different sizes and must not depend on earlier calls.
+// - When the wrapped height does not fit, `split` is asked to break the content at
+//   `avail_height`. It returns the part that fits (whose `wrap` height is at most
+//   `avail_height`) and the remainder, which continues in the next frame or page. `None`
+//   means the flowable cannot break there; it then moves on whole, and a flowable that fits
+//   no empty frame fails layout with `FullBleedError::UnplaceableFlowable`.
+// - `draw` paints at (`x`, `y`), the top-left corner in page points with y growing down,
+//   using the `avail_width`/`avail_height` it was wrapped with. Balance every
+//   `save_state`/`begin_tag` with its `restore_state`/`end_tag`.
 pub trait Flowable: FlowableClone + Send + Sync {
+    // Size the flowable needs when given `avail_width` x `avail_height`.
     fn wrap(&self, avail_width: Pt, avail_height: Pt) -> Size;
+    // Part fitting in `avail_height` and the rest, or `None` when it cannot break there.
     fn split(
         &self,
         avail_width: Pt,
@@ -532,6 +1065,12 @@ pub trait Flowable: FlowableClone + Send + Sync {
         None
     }
 
+    // Narrowest width the content fits without overflowing (widest unbreakable run).
+    // Used by automatic table layout; content that cannot shrink reports its intrinsic width.
+    fn min_content_width(&self) -> Option<Pt> {
+        self.intrinsic_width()
+    }
+
     // Out-of-flow items (e.g. position:absolute) should not affect normal flow placement.
     fn out_of_flow(&self) -> bool {
         false
@@ -555,6 +1094,21 @@ pub trait Flowable: FlowableClone + Send + Sync {
         false
     }
 
+    // Side of a `float` wrapper; content that follows it in the same container wraps around it.
+    fn float_side(&self) -> Option<FloatSide> {
+        None
+    }
+
+    // Copy whose line boxes are shortened to flow around `exclusion`. `None` means the
+    // flowable cannot wrap around a float and is laid out beside it instead.
+    fn wrap_around_float(
+        &self,
+        _avail_width: Pt,
+        _exclusion: FloatExclusion,
+    ) -> Option<Box<dyn Flowable>> {
+        None
+    }
+
     fn debug_name(&self) -> &'static str {
         std::any::type_name::<Self>()
     }
@@ -562,6 +1116,46 @@ pub trait Flowable: FlowableClone + Send + Sync {
     fn diagnostic_metadata(&self) -> Vec<(String, String)> {
         Vec::new()
     }
+
+    // Named page (`page: <name>`) the flowable starts; `Some("")` returns to the unnamed page
+    // and `None` keeps the current one.
+    fn page_name(&self) -> Option<String> {
+        None
+    }
+
+    // Named frame (`data-fb-frame`) a top-level story flowable is threaded into instead of the
+    // main flow.
+    fn target_frame(&self) -> Option<String> {
+        None
+    }
+
+    // Whether splitting at `avail_height` would break a keep group at the start of the
+    // flowable (a leading `break-inside: avoid` block, or a heading kept with what follows).
+    // Frames that already hold content move such a flowable on instead of splitting it.
+    fn breaks_leading_keep(&self, _avail_width: Pt, _avail_height: Pt) -> bool {
+        false
+    }
+}
+
+// Whether the start of `flowable` can be placed in `avail_height`: it fits, or it splits
+// without breaking a keep group.
+pub(crate) fn can_start_in(flowable: &dyn Flowable, avail_width: Pt, avail_height: Pt) -> bool {
+    if flowable.wrap(avail_width, avail_height).height <= avail_height {
+        return true;
+    }
+    if matches!(
+        flowable.pagination().break_inside,
+        BreakInside::Avoid | BreakInside::AvoidPage
+    ) || flowable.breaks_leading_keep(avail_width, avail_height)
+    {
+        return false;
+    }
+    flowable
+        .split(avail_width, avail_height)
+        .is_some_and(|(first, _)| {
+            let height = first.wrap(avail_width, avail_height).height;
+            height > Pt::ZERO && height <= avail_height
+        })
 }
 
 pub trait FlowableClone {
@@ -597,6 +1191,7 @@ pub struct TextStyle {
     pub text_overflow: crate::style::TextOverflowMode,
     pub word_break: crate::style::WordBreakMode,
     pub letter_spacing: Pt,
+    pub direction: crate::style::DirectionMode,
 }
 
 impl Default for TextStyle {
@@ -615,6 +1210,7 @@ impl Default for TextStyle {
             text_overflow: crate::style::TextOverflowMode::Clip,
             word_break: crate::style::WordBreakMode::Normal,
             letter_spacing: Pt::ZERO,
+            direction: crate::style::DirectionMode::Ltr,
         }
     }
 }
@@ -624,6 +1220,20 @@ fn resolve_font_variant_name(
     base: &Arc<str>,
     weight: u16,
     style: crate::style::FontStyleMode,
+) -> Arc<str> {
+    let name = resolve_named_font_variant(registry, base, weight, style);
+    // A variable face then gets an instance at exactly `weight` instead of its own.
+    match registry.and_then(|registry| registry.weight_instance_name(&name, weight)) {
+        Some(instance) => Arc::<str>::from(instance),
+        None => name,
+    }
+}
+
+fn resolve_named_font_variant(
+    registry: Option<&FontRegistry>,
+    base: &Arc<str>,
+    weight: u16,
+    style: crate::style::FontStyleMode,
 ) -> Arc<str> {
     let italic = matches!(style, crate::style::FontStyleMode::Italic);
     let bold = weight >= 600;
@@ -854,6 +1464,115 @@ fn emit_font_resolution_meta(
     canvas.meta("font.fallback_reason", reason);
 }
 
+// Draws one line in visual order when it is right-to-left or carries right-to-left text.
+// Left-to-right runs go through `draw_ltr`; right-to-left runs are placed font run by font run
+// from their right edge. A run that will be shaped keeps its logical order (the shaper reorders
+// and mirrors it); anything else is reversed and mirrored here.
+#[allow(clippy::too_many_arguments)]
+fn draw_bidi_line(
+    canvas: &mut Canvas,
+    registry: Option<&FontRegistry>,
+    style: &TextStyle,
+    x: Pt,
+    y: Pt,
+    text: &str,
+    measure: &dyn Fn(&str) -> Pt,
+    draw_ltr: &dyn Fn(&mut Canvas, Pt, Pt, &str),
+) {
+    let rtl = style.direction == crate::style::DirectionMode::Rtl;
+    if !crate::bidi::needs_reordering(text, rtl) {
+        draw_ltr(canvas, x, y, text);
+        return;
+    }
+    let mut cursor_x = x;
+    for run in crate::bidi::visual_runs(text, rtl) {
+        if !run.rtl {
+            draw_ltr(canvas, cursor_x, y, &run.text);
+            cursor_x += measure(&run.text);
+            continue;
+        }
+        let Some(registry) = registry else {
+            let font_name = resolve_font_variant_name(
+                None,
+                &style.font_name,
+                style.font_weight,
+                style.font_style,
+            );
+            canvas.set_font_name(font_name.as_ref());
+            canvas.draw_string(cursor_x, y, crate::bidi::visual_order(&run.text));
+            cursor_x += measure(&run.text);
+            continue;
+        };
+        let (primary, fallbacks) = resolve_font_stack(Some(registry), style);
+        let font_runs = registry.split_text_by_fallbacks(&primary, &fallbacks, &run.text);
+        let widths: Vec<Pt> = font_runs
+            .iter()
+            .map(|font_run| {
+                registry.measure_text_width(&font_run.font_name, style.font_size, &font_run.text)
+            })
+            .collect();
+        let run_width = widths.iter().fold(Pt::ZERO, |acc, w| acc + *w);
+        let mut right = cursor_x + run_width;
+        for (font_run, width) in font_runs.into_iter().zip(widths) {
+            emit_font_resolution_meta(
+                canvas,
+                registry,
+                style,
+                &font_run.font_name,
+                primary.as_ref(),
+            );
+            canvas.set_font_name(&font_run.font_name);
+            right -= width;
+            let shaped = registry.resolve(&font_run.font_name).is_some()
+                && crate::bidi::has_strong_rtl(&font_run.text);
+            if shaped {
+                canvas.draw_string(right, y, font_run.text);
+            } else {
+                canvas.draw_string(right, y, crate::bidi::visual_order(&font_run.text));
+            }
+        }
+        cursor_x += run_width;
+    }
+}
+
+// Byte range of a paragraph's text that acts as a hyperlink.
+#[derive(Debug, Clone)]
+pub(crate) struct TextLinkSpan {
+    pub(crate) range: std::ops::Range<usize>,
+    pub(crate) target: LinkTarget,
+}
+
+// Tab with a leader: in a paragraph with a leader, the text after the last tab is set flush
+// with the right edge of the final line and the gap before it is filled with `fill` repeated
+// (dots for a table of contents, a space for a plain right tab stop). Every line of the text
+// before the tab stops short of the space the right-hand text takes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeaderSpec {
+    pub fill: String,
+}
+
+impl LeaderSpec {
+    pub fn new(fill: impl Into<String>) -> Self {
+        Self { fill: fill.into() }
+    }
+
+    pub fn dots() -> Self {
+        Self::new(". ")
+    }
+
+    pub fn space() -> Self {
+        Self::new(" ")
+    }
+}
+
+// Leading lines of a paragraph shortened by a float beside it.
+#[derive(Debug, Clone, Copy)]
+struct LineInset {
+    side: FloatSide,
+    width: Pt,
+    lines: usize,
+}
+
 #[derive(Debug, Clone)]
 pub struct Paragraph {
     text: String,
@@ -864,6 +1583,9 @@ pub struct Paragraph {
     no_wrap: bool,
     tag_role: Option<Arc<str>>,
     font_registry: Option<Arc<FontRegistry>>,
+    links: Arc<Vec<TextLinkSpan>>,
+    float_inset: Option<LineInset>,
+    leader: Option<LeaderSpec>,
     layout_cache: Arc<Mutex<TextLayoutCache>>,
     width_cache: Arc<Mutex<TextWidthCache>>,
 }
@@ -879,6 +1601,9 @@ impl Paragraph {
             no_wrap: false,
             tag_role: None,
             font_registry: None,
+            links: Arc::new(Vec::new()),
+            float_inset: None,
+            leader: None,
             layout_cache: Arc::new(Mutex::new(TextLayoutCache::default())),
             width_cache: Arc::new(Mutex::new(TextWidthCache::default())),
         }
@@ -915,6 +1640,16 @@ impl Paragraph {
         self
     }
 
+    pub(crate) fn with_links(mut self, links: Vec<TextLinkSpan>) -> Self {
+        self.links = Arc::new(links);
+        self
+    }
+
+    pub fn with_leader(mut self, leader: LeaderSpec) -> Self {
+        self.leader = Some(leader);
+        self
+    }
+
     pub fn text(&self) -> &str {
         &self.text
     }
@@ -988,22 +1723,127 @@ impl Paragraph {
         }
     }
 
-    fn effective_line_height(&self) -> Pt {
-        if self.style.line_height_is_auto {
-            if let Some(registry) = &self.font_registry {
-                return registry.line_height(
-                    &self.style.font_name,
-                    self.style.font_size,
-                    self.style.line_height,
-                );
-            }
-            return self.style.font_size.mul_ratio(6, 5);
-        }
-        self.style.line_height
+    // Text before the leader tab and the right-aligned text after it.
+    fn leader_parts(&self) -> Option<(&str, &str)> {
+        self.leader.as_ref()?;
+        self.text.rsplit_once('\t')
     }
 
-    fn draw_text_with_fallbacks(&self, canvas: &mut Canvas, x: Pt, y: Pt, text: &str) {
-        if let Some(registry) = &self.font_registry {
+    // Text that wraps into lines: everything except the right-aligned leader text.
+    fn flow_text(&self) -> &str {
+        self.leader_parts()
+            .map_or(self.text.as_str(), |(left, _)| left)
+    }
+
+    // Minimum space on either side of the leader fill.
+    fn leader_gap(&self) -> Pt {
+        self.style.font_size.mul_ratio(1, 2)
+    }
+
+    // Width kept free at the end of each line for the leader and the right-aligned text.
+    fn leader_reserve(&self) -> Pt {
+        match self.leader_parts() {
+            Some((_, right)) => self.measure_text_width(right) + self.leader_gap() * 2,
+            None => Pt::ZERO,
+        }
+    }
+
+    // Links inside the right-aligned leader text, rebased to start at `new_start`.
+    fn leader_links(&self, new_start: usize) -> Vec<TextLinkSpan> {
+        let Some((_, right)) = self.leader_parts() else {
+            return Vec::new();
+        };
+        let right_start = self.text.len() - right.len();
+        self.links
+            .iter()
+            .filter(|link| link.range.end > right_start)
+            .map(|link| TextLinkSpan {
+                range: (link.range.start.max(right_start) - right_start + new_start)
+                    ..(link.range.end - right_start + new_start),
+                target: link.target.clone(),
+            })
+            .collect()
+    }
+
+    // Draws the leader fill and the right-aligned text on line `line_index`, after a line of
+    // text `line_width` wide. The fill is aligned to the right edge, so leaders in consecutive
+    // paragraphs of the same width line up.
+    #[allow(clippy::too_many_arguments)]
+    fn draw_leader(
+        &self,
+        canvas: &mut Canvas,
+        x: Pt,
+        y: Pt,
+        avail_width: Pt,
+        line_index: usize,
+        line_width: Pt,
+        right: &str,
+    ) {
+        let Some(leader) = &self.leader else {
+            return;
+        };
+        let (line_x, line_avail) = self.line_box(avail_width, line_index);
+        let right_width = self.measure_text_width(right);
+        let right_x = x + line_x + line_avail - right_width;
+        let fill_width = self.measure_text_width(&leader.fill);
+        let fill_end = right_x - self.leader_gap();
+        let fill_space = fill_end - (x + line_x + line_width + self.leader_gap());
+        if !leader.fill.trim().is_empty() && fill_width > Pt::ZERO && fill_space > Pt::ZERO {
+            let count = (fill_space.to_milli_i64() / fill_width.to_milli_i64()) as usize;
+            if count > 0 {
+                let fill = leader.fill.repeat(count);
+                let fill_x = fill_end - fill_width * count as i32;
+                self.draw_text_with_fallbacks(canvas, fill_x, y, fill.trim_end());
+            }
+        }
+        self.draw_text_with_fallbacks(canvas, right_x, y, right);
+        draw_text_decorations(
+            canvas,
+            &self.style,
+            self.font_registry.as_deref(),
+            right_x,
+            y,
+            right_width,
+        );
+        let right_line = LineLayout {
+            text: right.to_string(),
+            width: right_width,
+        };
+        let right_start = self.text.len() - right.len();
+        let line_height = self.effective_line_height();
+        self.draw_line_links(canvas, &right_line, right_start, right_x, y, line_height);
+    }
+
+    fn effective_line_height(&self) -> Pt {
+        if self.style.line_height_is_auto {
+            if let Some(registry) = &self.font_registry {
+                return registry.line_height(
+                    &self.style.font_name,
+                    self.style.font_size,
+                    self.style.line_height,
+                );
+            }
+            return self.style.font_size.mul_ratio(6, 5);
+        }
+        self.style.line_height
+    }
+
+    // Left offset and width of line `index` inside `avail_width`, after any float inset.
+    fn line_box(&self, avail_width: Pt, index: usize) -> (Pt, Pt) {
+        match self.float_inset {
+            Some(inset) if index < inset.lines => {
+                let width = (avail_width - inset.width).max(Pt::from_f32(1.0));
+                match inset.side {
+                    FloatSide::Left => (avail_width - width, width),
+                    FloatSide::Right => (Pt::ZERO, width),
+                }
+            }
+            _ => (Pt::ZERO, avail_width),
+        }
+    }
+
+    fn draw_text_with_fallbacks(&self, canvas: &mut Canvas, x: Pt, y: Pt, text: &str) {
+        if let Some(registry) = &self.font_registry {
             let (primary, fallbacks) = resolve_font_stack(Some(registry), &self.style);
             let runs = registry.split_text_by_fallbacks(&primary, &fallbacks, text);
             let mut cursor_x = x;
@@ -1081,10 +1921,61 @@ impl Paragraph {
         }
     }
 
+    fn draw_line_links(
+        &self,
+        canvas: &mut Canvas,
+        line: &LineLayout,
+        line_start: usize,
+        x: Pt,
+        y: Pt,
+        line_height: Pt,
+    ) {
+        let line_end = line_start + line.text.len();
+        for link in self.links.iter() {
+            let start = link.range.start.max(line_start);
+            let end = link.range.end.min(line_end);
+            if start >= end {
+                continue;
+            }
+            let lead = self.measure_text_width(&line.text[..start - line_start]);
+            let width = self.measure_text_width(&line.text[start - line_start..end - line_start]);
+            canvas.link_annotation(x + lead, y, width, line_height, link.target.clone());
+        }
+    }
+
+    // Key of this paragraph's lines at `width_milli` in the engine-wide line break cache.
+    fn line_break_key(&self, registry: &FontRegistry, width_milli: i64) -> LineBreakKey {
+        let mut text = std::collections::hash_map::DefaultHasher::new();
+        self.text.hash(&mut text);
+        let mut style = std::collections::hash_map::DefaultHasher::new();
+        self.style.font_size.to_milli_i64().hash(&mut style);
+        self.style.font_name.hash(&mut style);
+        self.style.font_fallbacks.hash(&mut style);
+        self.style.font_weight.hash(&mut style);
+        (self.style.font_style as u8).hash(&mut style);
+        (self.style.text_overflow as u8).hash(&mut style);
+        (self.style.word_break as u8).hash(&mut style);
+        self.style.letter_spacing.to_milli_i64().hash(&mut style);
+        self.preserve_whitespace.hash(&mut style);
+        self.no_wrap.hash(&mut style);
+        self.leader.is_some().hash(&mut style);
+        self.float_inset
+            .map(|inset| (inset.side as u8, inset.width.to_milli_i64(), inset.lines))
+            .hash(&mut style);
+        registry.css_face_count().hash(&mut style);
+        LineBreakKey {
+            text_hash: text.finish(),
+            style_hash: style.finish(),
+            width_milli,
+        }
+    }
+
     fn layout_lines(&self, avail_width: Pt) -> Arc<Vec<LineLayout>> {
+        let _profile = layout_profile::op_scope("paragraph.lines");
         let perf = perf_start();
-        let max_width = avail_width.max(Pt::from_f32(1.0));
-        let key = max_width.to_milli_i64();
+        let key = avail_width.max(Pt::from_f32(1.0)).to_milli_i64();
+        let max_width = (avail_width - self.leader_reserve()).max(Pt::from_f32(1.0));
+        let text = self.flow_text();
         if let Ok(cache) = self.layout_cache.lock() {
             if let Some(lines) = cache.get(key) {
                 if perf_enabled() {
@@ -1101,9 +1992,32 @@ impl Paragraph {
                 return lines;
             }
         }
+        let shared = self
+            .font_registry
+            .as_deref()
+            .map(|registry| (registry.line_breaks(), self.line_break_key(registry, key)));
+        if let Some((cache, shared_key)) = &shared
+            && let Some(lines) = cache.get(shared_key, &self.text)
+        {
+            if let Ok(mut cache) = self.layout_cache.lock() {
+                cache.insert(key, lines.clone());
+            }
+            if perf_enabled() {
+                log_perf_counts(
+                    "layout.text.counts",
+                    &[
+                        ("bytes", self.text.len() as u64),
+                        ("lines", lines.len() as u64),
+                        ("shared_cache_hit", 1),
+                    ],
+                );
+            }
+            perf_end("layout.text.lines", perf);
+            return lines;
+        }
         if self.no_wrap {
             let mut line_layouts = Vec::new();
-            for line in self.text.split('\n') {
+            for line in text.split('\n') {
                 let resolved = if line.is_empty() {
                     String::new()
                 } else if matches!(
@@ -1128,6 +2042,9 @@ impl Paragraph {
             if let Ok(mut cache) = self.layout_cache.lock() {
                 cache.insert(key, lines.clone());
             }
+            if let Some((cache, shared_key)) = shared {
+                cache.insert(shared_key, &self.text, lines.clone());
+            }
             if perf_enabled() {
                 log_perf_counts(
                     "layout.text.counts",
@@ -1153,13 +2070,13 @@ impl Paragraph {
         let mut word_widths: HashMap<&str, Pt> = HashMap::new();
         if self.preserve_whitespace {
             if !allow_break_long {
-                for segment in self.text.split('\n') {
+                for segment in text.split('\n') {
                     lines.push(segment.to_string());
                 }
             } else {
                 let mut ascii_widths: [Option<Pt>; 128] = std::array::from_fn(|_| None);
                 let mut non_ascii_widths: HashMap<char, Pt> = HashMap::new();
-                for segment in self.text.split('\n') {
+                for segment in text.split('\n') {
                     if segment.is_empty() {
                         lines.push(String::new());
                         continue;
@@ -1199,7 +2116,7 @@ impl Paragraph {
             }
         } else {
             let space_width = self.measure_text_width(" ");
-            for segment in self.text.split('\n') {
+            for segment in text.split('\n') {
                 if segment.is_empty() {
                     lines.push(String::new());
                     continue;
@@ -1220,10 +2137,11 @@ impl Paragraph {
                     })
                     .collect();
                 for (word, word_width) in words {
+                    let limit = self.line_box(max_width, lines.len()).1;
                     if current.is_empty() {
-                        if word_width > max_width {
+                        if word_width > limit {
                             if allow_break_long {
-                                lines.extend(split_long_word_by_width(self, word, max_width));
+                                lines.extend(split_long_word_by_width(self, word, limit));
                                 current.clear();
                             } else {
                                 lines.push(word.to_string());
@@ -1235,16 +2153,17 @@ impl Paragraph {
                         }
                     } else {
                         let next_width = current_width + space_width + word_width;
-                        if next_width <= max_width {
+                        if next_width <= limit {
                             current.push(' ');
                             current.push_str(word);
                             current_width = next_width;
                         } else {
                             lines.push(current);
                             current = String::new();
-                            if word_width > max_width {
+                            let limit = self.line_box(max_width, lines.len()).1;
+                            if word_width > limit {
                                 if allow_break_long {
-                                    lines.extend(split_long_word_by_width(self, word, max_width));
+                                    lines.extend(split_long_word_by_width(self, word, limit));
                                 } else {
                                     lines.push(word.to_string());
                                 }
@@ -1278,6 +2197,9 @@ impl Paragraph {
         if let Ok(mut cache) = self.layout_cache.lock() {
             cache.insert(key, lines.clone());
         }
+        if let Some((cache, shared_key)) = shared {
+            cache.insert(shared_key, &self.text, lines.clone());
+        }
         if perf_enabled() {
             log_perf_counts(
                 "layout.text.counts",
@@ -1293,6 +2215,58 @@ impl Paragraph {
     }
 }
 
+// Byte offset of each laid-out line within the source text. Wrapping only drops collapsed
+// whitespace, so lines are found in order; synthesized lines (ellipsis) map to None.
+fn line_source_offsets(text: &str, lines: &[LineLayout]) -> Vec<Option<usize>> {
+    let mut cursor = 0usize;
+    lines
+        .iter()
+        .map(|line| {
+            if line.text.is_empty() {
+                return None;
+            }
+            let start = cursor + text.get(cursor..)?.find(line.text.as_str())?;
+            cursor = start + line.text.len();
+            Some(start)
+        })
+        .collect()
+}
+
+// Rebase link ranges onto the text formed by joining `lines` with '\n' (see Paragraph::split).
+fn remap_text_links(
+    links: &[TextLinkSpan],
+    lines: &[LineLayout],
+    starts: &[Option<usize>],
+) -> Vec<TextLinkSpan> {
+    let mut out: Vec<TextLinkSpan> = Vec::new();
+    for link in links {
+        let mut merged: Option<std::ops::Range<usize>> = None;
+        let mut new_start = 0usize;
+        for (line, start) in lines.iter().zip(starts) {
+            if let Some(line_start) = *start {
+                let line_end = line_start + line.text.len();
+                let a = link.range.start.max(line_start);
+                let b = link.range.end.min(line_end);
+                if a < b {
+                    let range = (a - line_start + new_start)..(b - line_start + new_start);
+                    merged = Some(match merged {
+                        Some(prev) => prev.start..range.end,
+                        None => range,
+                    });
+                }
+            }
+            new_start += line.text.len() + 1;
+        }
+        if let Some(range) = merged {
+            out.push(TextLinkSpan {
+                range,
+                target: link.target.clone(),
+            });
+        }
+    }
+    out
+}
+
 fn truncate_text_with_ellipsis(paragraph: &Paragraph, text: &str, max_width: Pt) -> String {
     if text.is_empty() {
         return String::new();
@@ -1388,24 +2362,45 @@ fn split_long_word_by_width(paragraph: &Paragraph, word: &str, max_width: Pt) ->
 
 impl Flowable for Paragraph {
     fn wrap(&self, avail_width: Pt, _avail_height: Pt) -> Size {
+        let _profile = layout_profile::op_scope("paragraph.wrap");
         let perf = perf_start();
         let lines = self.layout_lines(avail_width);
         let line_height = self.effective_line_height();
         let height = line_height * (lines.len() as i32);
         let width = lines
             .iter()
-            .fold(Pt::ZERO, |acc, line| acc.max(line.width))
+            .enumerate()
+            .fold(Pt::ZERO, |acc, (index, line)| {
+                acc.max(self.line_box(avail_width, index).0 + line.width)
+            })
             .min(avail_width);
+        // A leader stretches the final line to the full width.
+        let width = if self.leader_parts().is_some() {
+            avail_width
+        } else {
+            width
+        };
         perf_end("layout.text.wrap", perf);
         Size { width, height }
     }
 
     fn intrinsic_width(&self) -> Option<Pt> {
         let mut max_w = Pt::ZERO;
-        for line in self.text.split('\n') {
+        for line in self.flow_text().split('\n') {
             max_w = max_w.max(self.measure_text_width(line));
         }
-        Some(max_w)
+        Some(max_w + self.leader_reserve())
+    }
+
+    fn min_content_width(&self) -> Option<Pt> {
+        if self.no_wrap {
+            return self.intrinsic_width();
+        }
+        let mut max_w = Pt::ZERO;
+        for word in self.flow_text().split_whitespace() {
+            max_w = max_w.max(self.measure_text_width(word));
+        }
+        Some(max_w + self.leader_reserve())
     }
 
     fn split(
@@ -1413,6 +2408,7 @@ impl Flowable for Paragraph {
         avail_width: Pt,
         avail_height: Pt,
     ) -> Option<(Box<dyn Flowable>, Box<dyn Flowable>)> {
+        let _profile = layout_profile::op_scope("paragraph.split");
         let lines = self.layout_lines(avail_width);
         let line_height = self.effective_line_height();
         let lh = line_height.to_milli_i64();
@@ -1461,16 +2457,31 @@ impl Flowable for Paragraph {
             return None;
         }
 
+        let (first_links, mut second_links) = if self.links.is_empty() {
+            (Vec::new(), Vec::new())
+        } else {
+            let starts = line_source_offsets(&self.text, &lines);
+            (
+                remap_text_links(&self.links, &lines[..split_at], &starts[..split_at]),
+                remap_text_links(&self.links, &lines[split_at..], &starts[split_at..]),
+            )
+        };
         let first_text = lines[..split_at]
             .iter()
             .map(|line| line.text.as_str())
             .collect::<Vec<_>>()
             .join("\n");
-        let second_text = lines[split_at..]
+        let mut second_text = lines[split_at..]
             .iter()
             .map(|line| line.text.as_str())
             .collect::<Vec<_>>()
             .join("\n");
+        // The leader and its right-aligned text stay with the final line.
+        if let Some((_, right)) = self.leader_parts() {
+            second_text.push('\t');
+            second_links.extend(self.leader_links(second_text.len()));
+            second_text.push_str(right);
+        }
         let first = Paragraph {
             text: first_text,
             style: self.style.clone(),
@@ -1484,6 +2495,9 @@ impl Flowable for Paragraph {
             no_wrap: self.no_wrap,
             tag_role: self.tag_role.clone(),
             font_registry: self.font_registry.clone(),
+            links: Arc::new(first_links),
+            float_inset: self.float_inset,
+            leader: None,
             layout_cache: Arc::new(Mutex::new(TextLayoutCache::default())),
             width_cache: Arc::new(Mutex::new(TextWidthCache::default())),
         };
@@ -1499,6 +2513,9 @@ impl Flowable for Paragraph {
             no_wrap: self.no_wrap,
             tag_role: self.tag_role.clone(),
             font_registry: self.font_registry.clone(),
+            links: Arc::new(second_links),
+            float_inset: None,
+            leader: self.leader.clone(),
             layout_cache: Arc::new(Mutex::new(TextLayoutCache::default())),
             width_cache: Arc::new(Mutex::new(TextWidthCache::default())),
         };
@@ -1506,6 +2523,7 @@ impl Flowable for Paragraph {
     }
 
     fn draw(&self, canvas: &mut Canvas, x: Pt, y: Pt, avail_width: Pt, _avail_height: Pt) {
+        let _profile = layout_profile::op_scope("paragraph.draw");
         let perf = perf_start();
         let lines = self.layout_lines(avail_width);
         let tagged = self.tag_role.as_ref().map(|role| {
@@ -1516,14 +2534,30 @@ impl Flowable for Paragraph {
 
         let mut cursor_y = y;
         let line_height = self.effective_line_height();
-        for line in lines.iter() {
+        let link_starts = if self.links.is_empty() {
+            Vec::new()
+        } else {
+            line_source_offsets(&self.text, &lines)
+        };
+        for (line_index, line) in lines.iter().enumerate() {
             let line_width = line.width;
-            let offset = match self.align {
-                TextAlign::Left => Pt::ZERO,
-                TextAlign::Center => ((avail_width - line_width).max(Pt::ZERO)).mul_ratio(1, 2),
-                TextAlign::Right => (avail_width - line_width).max(Pt::ZERO),
-            };
-            self.draw_text_with_fallbacks(canvas, x + offset, cursor_y, &line.text);
+            let (line_x, line_avail) = self.line_box(avail_width, line_index);
+            let offset = line_x
+                + match self.align {
+                    TextAlign::Left => Pt::ZERO,
+                    TextAlign::Center => ((line_avail - line_width).max(Pt::ZERO)).mul_ratio(1, 2),
+                    TextAlign::Right => (line_avail - line_width).max(Pt::ZERO),
+                };
+            draw_bidi_line(
+                canvas,
+                self.font_registry.as_deref(),
+                &self.style,
+                x + offset,
+                cursor_y,
+                &line.text,
+                &|text| self.measure_text_width(text),
+                &|canvas, x, y, text| self.draw_text_with_fallbacks(canvas, x, y, text),
+            );
             draw_text_decorations(
                 canvas,
                 &self.style,
@@ -1532,8 +2566,23 @@ impl Flowable for Paragraph {
                 cursor_y,
                 line_width,
             );
+            if let Some(Some(line_start)) = link_starts.get(line_index) {
+                self.draw_line_links(canvas, line, *line_start, x + offset, cursor_y, line_height);
+            }
             cursor_y = cursor_y + line_height;
         }
+        if let (Some((_, right)), Some(last)) = (self.leader_parts(), lines.last()) {
+            let last_index = lines.len() - 1;
+            self.draw_leader(
+                canvas,
+                x,
+                y + line_height * last_index as i32,
+                avail_width,
+                last_index,
+                last.width,
+                right,
+            );
+        }
         if tagged.is_some() {
             canvas.end_tag();
         }
@@ -1543,85 +2592,165 @@ impl Flowable for Paragraph {
     fn pagination(&self) -> Pagination {
         self.pagination
     }
+
+    fn wrap_around_float(
+        &self,
+        avail_width: Pt,
+        exclusion: FloatExclusion,
+    ) -> Option<Box<dyn Flowable>> {
+        if exclusion.width >= avail_width {
+            return None;
+        }
+        let line_height = self.effective_line_height().to_milli_i64();
+        let mut out = self.clone();
+        out.float_inset = None;
+        if line_height > 0 && exclusion.height > Pt::ZERO && exclusion.width > Pt::ZERO {
+            let lines = (exclusion.height.to_milli_i64() + line_height - 1) / line_height;
+            out.float_inset = Some(LineInset {
+                side: exclusion.side,
+                width: exclusion.width,
+                lines: lines as usize,
+            });
+        }
+        out.layout_cache = Arc::new(Mutex::new(TextLayoutCache::default()));
+        Some(Box::new(out))
+    }
 }
 
-#[derive(Clone)]
-pub struct ListItemFlowable {
-    label: Paragraph,
-    body: Box<dyn Flowable>,
-    gap: Pt,
-    pagination: Pagination,
+// One block of text in a vertical writing-mode flow; each block starts a new column.
+#[derive(Debug, Clone)]
+pub struct VerticalParagraph {
+    pub text: String,
+    pub style: TextStyle,
 }
 
-impl ListItemFlowable {
-    pub fn new(label: Paragraph, body: Box<dyn Flowable>, gap: Pt) -> Self {
+impl VerticalParagraph {
+    pub fn new(text: impl Into<String>, style: TextStyle) -> Self {
         Self {
-            label,
-            body,
-            gap,
-            pagination: Pagination::default(),
+            text: text.into(),
+            style,
         }
     }
-
-    pub fn with_pagination(mut self, pagination: Pagination) -> Self {
-        self.pagination = pagination;
-        self
-    }
 }
 
-impl Flowable for ListItemFlowable {
-    fn wrap(&self, avail_width: Pt, avail_height: Pt) -> Size {
-        let label_size = self.label.wrap(avail_width, huge_pt());
-        let body_width = (avail_width - label_size.width - self.gap).max(Pt::from_f32(1.0));
-        let body_size = self.body.wrap(body_width, avail_height);
-        Size {
-            width: avail_width,
-            height: label_size.height.max(body_size.height),
-        }
-    }
+// `writing-mode: vertical-rl` text. Characters stack top to bottom in columns that advance
+// right to left. CJK characters stay upright; other scripts and CJK brackets and dashes are
+// set sideways, a quarter turn clockwise (`text-orientation: mixed`). Columns have a fixed
+// length (the block's height, or the page frame height) so measuring and drawing agree however
+// much height the parent offers. Columns that do not fit the available width continue in a
+// band below, and the flowable splits between bands.
+#[derive(Clone)]
+pub struct VerticalTextFlowable {
+    paragraphs: Vec<VerticalParagraph>,
+    column_length: Pt,
+    font_registry: Option<Arc<FontRegistry>>,
+    pagination: Pagination,
+    tag_role: Option<Arc<str>>,
+}
 
-    fn split(
-        &self,
-        _avail_width: Pt,
-        _avail_height: Pt,
-    ) -> Option<(Box<dyn Flowable>, Box<dyn Flowable>)> {
-        None
-    }
+#[derive(Debug, Clone)]
+struct VerticalRun {
+    text: String,
+    sideways: bool,
+    // Distance from the top of the column.
+    offset: Pt,
+}
 
-    fn draw(&self, canvas: &mut Canvas, x: Pt, y: Pt, avail_width: Pt, avail_height: Pt) {
-        let label_size = self.label.wrap(avail_width, huge_pt());
-        let body_width = (avail_width - label_size.width - self.gap).max(Pt::from_f32(1.0));
-        self.label
-            .draw(canvas, x, y, label_size.width, avail_height);
-        self.body.draw(
-            canvas,
-            x + label_size.width + self.gap,
-            y,
-            body_width,
-            avail_height,
-        );
-    }
+#[derive(Debug, Clone)]
+struct VerticalColumn {
+    paragraph: usize,
+    // Byte offset in the paragraph text where the column starts.
+    start: usize,
+    runs: Vec<VerticalRun>,
+    length: Pt,
+    width: Pt,
+}
 
-    fn pagination(&self) -> Pagination {
-        self.pagination
+// Upright in vertical text: CJK ideographs, kana, hangul, fullwidth forms and emoji, except
+// brackets and dashes, which turn with the line.
+fn is_vertical_upright(ch: char) -> bool {
+    if matches!(
+        ch,
+        '\u{3008}'..='\u{3011}'
+            | '\u{3014}'..='\u{301B}'
+            | 'ー'
+            | '〜'
+            | '～'
+            | '－'
+            | '（'
+            | '）'
+            | '［'
+            | '］'
+            | '｛'
+            | '｝'
+            | '｢'
+            | '｣'
+    ) {
+        return false;
     }
+    matches!(
+        crate::font::FontScript::of(ch),
+        Some(crate::font::FontScript::Cjk | crate::font::FontScript::Emoji)
+    )
 }
 
-#[derive(Debug, Clone)]
-pub struct Spacer {
-    height: Pt,
-    pagination: Pagination,
+// Characters that may not start a column; they stay at the end of the previous one.
+fn is_vertical_no_break_before(ch: char) -> bool {
+    matches!(
+        ch,
+        '、' | '。'
+            | '，'
+            | '．'
+            | '・'
+            | '！'
+            | '？'
+            | '」'
+            | '』'
+            | '）'
+            | '〕'
+            | '】'
+            | '》'
+            | '〉'
+            | 'ー'
+            | '々'
+            | 'ぁ'
+            | 'ぃ'
+            | 'ぅ'
+            | 'ぇ'
+            | 'ぉ'
+            | 'っ'
+            | 'ゃ'
+            | 'ゅ'
+            | 'ょ'
+            | 'ァ'
+            | 'ィ'
+            | 'ゥ'
+            | 'ェ'
+            | 'ォ'
+            | 'ッ'
+            | 'ャ'
+            | 'ュ'
+            | 'ョ'
+    )
 }
 
-impl Spacer {
-    pub fn new(height: f32) -> Self {
-        Self::new_pt(Pt::from_f32(height))
-    }
+// Commas and full stops sit in the upper right of their cell in vertical text.
+fn is_vertical_corner_punctuation(ch: char) -> bool {
+    matches!(ch, '、' | '。' | '，' | '．')
+}
 
-    pub fn new_pt(height: Pt) -> Self {
+impl VerticalTextFlowable {
+    pub fn new(
+        paragraphs: Vec<VerticalParagraph>,
+        column_length: Pt,
+        font_registry: Option<Arc<FontRegistry>>,
+    ) -> Self {
         Self {
-            height,
+            paragraphs,
+            column_length: column_length.max(Pt::from_f32(1.0)),
+            font_registry,
             pagination: Pagination::default(),
+            tag_role: None,
         }
     }
 
@@ -1629,33 +2758,423 @@ impl Spacer {
         self.pagination = pagination;
         self
     }
-}
-
-impl Flowable for Spacer {
-    fn wrap(&self, avail_width: Pt, _avail_height: Pt) -> Size {
-        Size {
-            width: avail_width,
-            height: self.height.max(Pt::ZERO),
-        }
-    }
 
-    fn intrinsic_width(&self) -> Option<Pt> {
-        // A line break / spacer contributes vertical rhythm, not horizontal demand.
-        Some(Pt::ZERO)
+    pub fn with_tag_role(mut self, role: impl Into<Arc<str>>) -> Self {
+        self.tag_role = Some(role.into());
+        self
     }
 
-    fn split(
-        &self,
-        _avail_width: Pt,
-        _avail_height: Pt,
-    ) -> Option<(Box<dyn Flowable>, Box<dyn Flowable>)> {
-        None
+    fn measure(&self, style: &TextStyle, text: &str) -> Pt {
+        if let Some(registry) = self.font_registry.as_deref() {
+            let (primary, fallbacks) = resolve_font_stack(Some(registry), style);
+            return registry.measure_text_width_with_fallbacks(
+                &primary,
+                &fallbacks,
+                style.font_size,
+                text,
+            );
+        }
+        (style.font_size * 0.6).max(Pt::from_f32(1.0)) * (text.chars().count() as i32)
     }
 
-    fn draw(&self, _canvas: &mut Canvas, _x: Pt, _y: Pt, _avail_width: Pt, _avail_height: Pt) {}
+    fn layout_columns(&self, column_len: Pt) -> Vec<VerticalColumn> {
+        let mut columns = Vec::new();
+        for (paragraph, para) in self.paragraphs.iter().enumerate() {
+            let style = &para.style;
+            let em = style.font_size;
+            let mut column = VerticalColumn {
+                paragraph,
+                start: 0,
+                runs: Vec::new(),
+                length: Pt::ZERO,
+                width: style.line_height,
+            };
+            // Tokens: one upright character, one sideways word, or one sideways space.
+            let mut tokens: Vec<(usize, &str, bool)> = Vec::new();
+            let mut word_start: Option<usize> = None;
+            for (idx, ch) in para.text.char_indices() {
+                let upright = is_vertical_upright(ch);
+                if upright || ch.is_whitespace() {
+                    if let Some(start) = word_start.take() {
+                        tokens.push((start, &para.text[start..idx], false));
+                    }
+                    tokens.push((idx, &para.text[idx..idx + ch.len_utf8()], upright));
+                } else if word_start.is_none() {
+                    word_start = Some(idx);
+                }
+            }
+            if let Some(start) = word_start {
+                tokens.push((start, &para.text[start..], false));
+            }
 
-    fn pagination(&self) -> Pagination {
-        self.pagination
+            for (start, token, upright) in tokens {
+                if token == "\n" {
+                    columns.push(column.clone());
+                    column.start = start + 1;
+                    column.runs.clear();
+                    column.length = Pt::ZERO;
+                    continue;
+                }
+                let is_space = token.chars().all(char::is_whitespace);
+                if column.runs.is_empty() && is_space {
+                    column.start = start + token.len();
+                    continue;
+                }
+                let advance = if upright {
+                    em
+                } else {
+                    self.measure(style, token)
+                };
+                let first = token.chars().next().unwrap_or(' ');
+                if !column.runs.is_empty()
+                    && column.length + advance > column_len
+                    && !is_vertical_no_break_before(first)
+                {
+                    columns.push(column.clone());
+                    column.runs.clear();
+                    column.length = Pt::ZERO;
+                    if is_space {
+                        column.start = start + token.len();
+                        continue;
+                    }
+                    column.start = start;
+                }
+                match column.runs.last_mut() {
+                    Some(last) if !upright && last.sideways => last.text.push_str(token),
+                    _ => column.runs.push(VerticalRun {
+                        text: token.to_string(),
+                        sideways: !upright,
+                        offset: column.length,
+                    }),
+                }
+                column.length += advance;
+            }
+            if !column.runs.is_empty() {
+                columns.push(column);
+            }
+        }
+        columns
+    }
+
+    // Groups columns into bands no wider than `avail_width` (at least one column each).
+    fn bands(columns: &[VerticalColumn], avail_width: Pt) -> Vec<std::ops::Range<usize>> {
+        let mut bands = Vec::new();
+        let mut start = 0;
+        let mut width = Pt::ZERO;
+        for (idx, column) in columns.iter().enumerate() {
+            if idx > start && width + column.width > avail_width {
+                bands.push(start..idx);
+                start = idx;
+                width = Pt::ZERO;
+            }
+            width += column.width;
+        }
+        if start < columns.len() {
+            bands.push(start..columns.len());
+        }
+        bands
+    }
+
+    fn band_height(&self, columns: &[VerticalColumn]) -> Pt {
+        columns
+            .iter()
+            .fold(Pt::ZERO, |acc, column| acc.max(column.length))
+    }
+
+    // Paragraphs before and after byte `start` of paragraph `paragraph`.
+    fn split_paragraphs(
+        &self,
+        paragraph: usize,
+        start: usize,
+    ) -> (Vec<VerticalParagraph>, Vec<VerticalParagraph>) {
+        let mut first = self.paragraphs[..paragraph].to_vec();
+        let mut second = Vec::new();
+        let para = &self.paragraphs[paragraph];
+        if start > 0 {
+            first.push(VerticalParagraph::new(
+                &para.text[..start],
+                para.style.clone(),
+            ));
+        }
+        second.push(VerticalParagraph::new(
+            &para.text[start..],
+            para.style.clone(),
+        ));
+        second.extend(self.paragraphs[paragraph + 1..].iter().cloned());
+        (first, second)
+    }
+
+    fn draw_horizontal(&self, canvas: &mut Canvas, style: &TextStyle, x: Pt, y: Pt, text: &str) {
+        if let Some(registry) = self.font_registry.as_deref() {
+            let (primary, fallbacks) = resolve_font_stack(Some(registry), style);
+            let mut cursor_x = x;
+            for run in registry.split_text_by_fallbacks(&primary, &fallbacks, text) {
+                emit_font_resolution_meta(canvas, registry, style, &run.font_name, &primary);
+                canvas.set_font_name(&run.font_name);
+                let width = registry.measure_text_width(&run.font_name, style.font_size, &run.text);
+                canvas.draw_string(cursor_x, y, run.text);
+                cursor_x += width;
+            }
+            return;
+        }
+        let font_name =
+            resolve_font_variant_name(None, &style.font_name, style.font_weight, style.font_style);
+        canvas.set_font_name(font_name.as_ref());
+        canvas.draw_string(x, y, text);
+    }
+}
+
+impl Flowable for VerticalTextFlowable {
+    fn wrap(&self, avail_width: Pt, _avail_height: Pt) -> Size {
+        let column_len = self.column_length;
+        let columns = self.layout_columns(column_len);
+        let bands = Self::bands(&columns, avail_width);
+        let Some(last) = bands.last() else {
+            return Size {
+                width: Pt::ZERO,
+                height: Pt::ZERO,
+            };
+        };
+        if bands.len() == 1 {
+            return Size {
+                width: columns.iter().fold(Pt::ZERO, |acc, c| acc + c.width),
+                height: self.band_height(&columns),
+            };
+        }
+        Size {
+            width: avail_width,
+            height: column_len * ((bands.len() - 1) as i32)
+                + self.band_height(&columns[last.clone()]),
+        }
+    }
+
+    fn split(
+        &self,
+        avail_width: Pt,
+        avail_height: Pt,
+    ) -> Option<(Box<dyn Flowable>, Box<dyn Flowable>)> {
+        let column_len = self.column_length;
+        let ah = avail_height.to_milli_i64();
+        let cl = column_len.to_milli_i64();
+        if cl <= 0 || ah < cl {
+            return None;
+        }
+        let columns = self.layout_columns(column_len);
+        let bands = Self::bands(&columns, avail_width);
+        let fit = (ah / cl) as usize;
+        if fit >= bands.len() {
+            return None;
+        }
+        let at = &columns[bands[fit].start];
+        let (first_paragraphs, second_paragraphs) = self.split_paragraphs(at.paragraph, at.start);
+        let first = VerticalTextFlowable {
+            paragraphs: first_paragraphs,
+            pagination: Pagination {
+                break_before: BreakBefore::Auto,
+                break_after: BreakAfter::Auto,
+                ..self.pagination
+            },
+            ..self.clone()
+        };
+        let second = VerticalTextFlowable {
+            paragraphs: second_paragraphs,
+            pagination: Pagination {
+                break_before: BreakBefore::Auto,
+                ..self.pagination
+            },
+            ..self.clone()
+        };
+        Some((Box::new(first), Box::new(second)))
+    }
+
+    fn draw(&self, canvas: &mut Canvas, x: Pt, y: Pt, avail_width: Pt, _avail_height: Pt) {
+        let column_len = self.column_length;
+        let columns = self.layout_columns(column_len);
+        let bands = Self::bands(&columns, avail_width);
+        let tagged = self.tag_role.as_ref().map(|role| {
+            canvas.begin_tag(role.as_ref(), None, None, None, None, false);
+        });
+        let page_height = canvas.page_size().height;
+        for (band_index, band) in bands.iter().enumerate() {
+            let band_width = if bands.len() == 1 {
+                columns.iter().fold(Pt::ZERO, |acc, c| acc + c.width)
+            } else {
+                avail_width
+            };
+            let top = y + column_len * (band_index as i32);
+            let mut right = x + band_width;
+            for column in &columns[band.clone()] {
+                let style = &self.paragraphs[column.paragraph].style;
+                let em = style.font_size;
+                canvas.set_fill_color(style.color);
+                canvas.set_font_size(em);
+                let center = right - column.width.mul_ratio(1, 2);
+                for run in &column.runs {
+                    let run_top = top + run.offset;
+                    if run.sideways {
+                        // A quarter turn clockwise about the run's top centre, in PDF user
+                        // space (y up); the run is drawn so its baseline sits left of centre.
+                        let px = center.to_f32();
+                        let py = (page_height - run_top).to_f32();
+                        canvas.save_state();
+                        canvas.concat_matrix(
+                            0.0,
+                            -1.0,
+                            1.0,
+                            0.0,
+                            Pt::from_f32(px - py),
+                            Pt::from_f32(py + px),
+                        );
+                        self.draw_horizontal(
+                            canvas,
+                            style,
+                            center,
+                            run_top - em.mul_ratio(13, 20),
+                            &run.text,
+                        );
+                        canvas.restore_state();
+                        continue;
+                    }
+                    let glyph_width = self.measure(style, &run.text);
+                    let mut glyph_x = center - glyph_width.mul_ratio(1, 2);
+                    // Ideographs sit slightly below the top of the line box; lift them into
+                    // the em cell.
+                    let mut glyph_y = run_top - em.mul_ratio(1, 8);
+                    if run.text.chars().all(is_vertical_corner_punctuation) {
+                        glyph_x += em.mul_ratio(1, 2);
+                        glyph_y -= em.mul_ratio(1, 2);
+                    }
+                    self.draw_horizontal(canvas, style, glyph_x, glyph_y, &run.text);
+                }
+                right -= column.width;
+            }
+        }
+        if tagged.is_some() {
+            canvas.end_tag();
+        }
+    }
+
+    fn intrinsic_width(&self) -> Option<Pt> {
+        let columns = self.layout_columns(self.column_length);
+        Some(columns.iter().fold(Pt::ZERO, |acc, c| acc + c.width))
+    }
+
+    fn pagination(&self) -> Pagination {
+        self.pagination
+    }
+}
+
+#[derive(Clone)]
+pub struct ListItemFlowable {
+    label: Paragraph,
+    body: Box<dyn Flowable>,
+    gap: Pt,
+    pagination: Pagination,
+}
+
+impl ListItemFlowable {
+    pub fn new(label: Paragraph, body: Box<dyn Flowable>, gap: Pt) -> Self {
+        Self {
+            label,
+            body,
+            gap,
+            pagination: Pagination::default(),
+        }
+    }
+
+    pub fn with_pagination(mut self, pagination: Pagination) -> Self {
+        self.pagination = pagination;
+        self
+    }
+}
+
+impl Flowable for ListItemFlowable {
+    fn wrap(&self, avail_width: Pt, avail_height: Pt) -> Size {
+        let label_size = self.label.wrap(avail_width, huge_pt());
+        let body_width = (avail_width - label_size.width - self.gap).max(Pt::from_f32(1.0));
+        let body_size = self.body.wrap(body_width, avail_height);
+        Size {
+            width: avail_width,
+            height: label_size.height.max(body_size.height),
+        }
+    }
+
+    fn split(
+        &self,
+        _avail_width: Pt,
+        _avail_height: Pt,
+    ) -> Option<(Box<dyn Flowable>, Box<dyn Flowable>)> {
+        None
+    }
+
+    fn draw(&self, canvas: &mut Canvas, x: Pt, y: Pt, avail_width: Pt, avail_height: Pt) {
+        let label_size = self.label.wrap(avail_width, huge_pt());
+        let body_width = (avail_width - label_size.width - self.gap).max(Pt::from_f32(1.0));
+        self.label
+            .draw(canvas, x, y, label_size.width, avail_height);
+        self.body.draw(
+            canvas,
+            x + label_size.width + self.gap,
+            y,
+            body_width,
+            avail_height,
+        );
+    }
+
+    fn pagination(&self) -> Pagination {
+        self.pagination
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Spacer {
+    height: Pt,
+    pagination: Pagination,
+}
+
+impl Spacer {
+    pub fn new(height: f32) -> Self {
+        Self::new_pt(Pt::from_f32(height))
+    }
+
+    pub fn new_pt(height: Pt) -> Self {
+        Self {
+            height,
+            pagination: Pagination::default(),
+        }
+    }
+
+    pub fn with_pagination(mut self, pagination: Pagination) -> Self {
+        self.pagination = pagination;
+        self
+    }
+}
+
+impl Flowable for Spacer {
+    fn wrap(&self, avail_width: Pt, _avail_height: Pt) -> Size {
+        Size {
+            width: avail_width,
+            height: self.height.max(Pt::ZERO),
+        }
+    }
+
+    fn intrinsic_width(&self) -> Option<Pt> {
+        // A line break / spacer contributes vertical rhythm, not horizontal demand.
+        Some(Pt::ZERO)
+    }
+
+    fn split(
+        &self,
+        _avail_width: Pt,
+        _avail_height: Pt,
+    ) -> Option<(Box<dyn Flowable>, Box<dyn Flowable>)> {
+        None
+    }
+
+    fn draw(&self, _canvas: &mut Canvas, _x: Pt, _y: Pt, _avail_width: Pt, _avail_height: Pt) {}
+
+    fn pagination(&self) -> Pagination {
+        self.pagination
     }
 }
 
@@ -1898,6 +3417,10 @@ pub struct TableCell {
     pub tag_role: Option<Arc<str>>,
     pub scope: Option<String>,
     col_span: usize,
+    row_span: usize,
+    // Placeholder for a slot covered by a row-spanning cell above; `row_span` then counts
+    // the covered rows left, including this one.
+    spanned: bool,
     pub root_font_size: Pt,
     row_min_height: Pt,
     preferred_width: Option<LengthSpec>,
@@ -1908,6 +3431,8 @@ pub struct TableCell {
     cached_line_height: Pt,
     preserve_whitespace: bool,
     no_wrap: bool,
+    transforms: Vec<CssTransformOp>,
+    transform_origin: CssTransformOrigin,
     layout_cache: Arc<Mutex<TextLayoutCache>>,
     width_cache: Arc<Mutex<TextWidthCache>>,
 }
@@ -1953,6 +3478,8 @@ impl TableCell {
             tag_role,
             scope,
             col_span: col_span.max(1),
+            row_span: 1,
+            spanned: false,
             root_font_size,
             row_min_height: Pt::ZERO,
             preferred_width: None,
@@ -1963,6 +3490,8 @@ impl TableCell {
             cached_line_height,
             preserve_whitespace,
             no_wrap,
+            transforms: Vec::new(),
+            transform_origin: CssTransformOrigin::center(),
             layout_cache: Arc::new(Mutex::new(TextLayoutCache::default())),
             width_cache: Arc::new(Mutex::new(TextWidthCache::default())),
         }
@@ -1973,44 +3502,118 @@ impl TableCell {
         self
     }
 
+    pub(crate) fn with_transforms(
+        mut self,
+        transforms: Vec<CssTransformOp>,
+        transform_origin: CssTransformOrigin,
+    ) -> Self {
+        self.transforms = transforms;
+        self.transform_origin = transform_origin;
+        self
+    }
+
     pub(crate) fn col_span(&self) -> usize {
         self.col_span.max(1)
     }
 
-    pub(crate) fn with_row_min_height(mut self, min_height: Pt) -> Self {
-        self.row_min_height = min_height.max(Pt::ZERO);
+    pub(crate) fn with_row_span(mut self, row_span: usize) -> Self {
+        self.row_span = row_span.max(1);
         self
     }
 
-    pub(crate) fn with_preferred_width(
-        mut self,
-        width: LengthSpec,
-        font_size: Pt,
-        root_font_size: Pt,
-    ) -> Self {
-        self.preferred_width = Some(width);
-        self.preferred_width_font_size = font_size;
-        self.preferred_width_root_font_size = root_font_size;
-        self
+    pub(crate) fn row_span(&self) -> usize {
+        self.row_span.max(1)
     }
 
-    fn measure_text_width(&self, text: &str) -> Pt {
-        if let Ok(cache) = self.width_cache.lock() {
-            if let Some(value) = cache.get(text) {
-                if perf_enabled() {
-                    log_perf_counts("layout.tablecell.width", &[("cache_hit", 1)]);
-                }
-                return value;
-            }
-        }
-        if let Some(registry) = self.font_registry.as_deref() {
-            let (primary, fallbacks) = resolve_font_stack(Some(registry), &self.style);
-            let base = if fallbacks.is_empty() {
-                registry.measure_text_width(&primary, self.style.font_size, text)
-            } else {
-                registry.measure_text_width_with_fallbacks(
-                    &primary,
-                    &fallbacks,
+    // Whether the cell's own height sizes its row. Row-spanning cells and their placeholders
+    // are sized across the spanned rows instead.
+    fn lays_out_in_row(&self) -> bool {
+        self.row_span() == 1 && !self.spanned
+    }
+
+    // Empty cell with this cell's box styling, standing in for a slot it covers `rows_left`
+    // rows further down. A table fragment that starts inside the span paints the placeholder.
+    fn row_span_placeholder(&self, rows_left: usize) -> Self {
+        Self {
+            text: String::new(),
+            style: self.style.clone(),
+            align: self.align,
+            valign: self.valign,
+            padding: self.padding,
+            background: self.background,
+            border: self.border.clone(),
+            box_shadow: self.box_shadow.clone(),
+            tag_role: None,
+            scope: None,
+            col_span: self.col_span,
+            row_span: rows_left.max(1),
+            spanned: true,
+            root_font_size: self.root_font_size,
+            row_min_height: Pt::ZERO,
+            preferred_width: None,
+            preferred_width_font_size: self.preferred_width_font_size,
+            preferred_width_root_font_size: self.preferred_width_root_font_size,
+            content: None,
+            font_registry: self.font_registry.clone(),
+            cached_line_height: self.cached_line_height,
+            preserve_whitespace: self.preserve_whitespace,
+            no_wrap: self.no_wrap,
+            transforms: self.transforms.clone(),
+            transform_origin: self.transform_origin,
+            layout_cache: Arc::new(Mutex::new(TextLayoutCache::default())),
+            width_cache: Arc::new(Mutex::new(TextWidthCache::default())),
+        }
+    }
+
+    // Height of the cell's box at `col_width`, as it would size a row of its own.
+    fn box_height(&self, col_width: Pt) -> Pt {
+        let padding = self.resolved_padding(col_width);
+        let border = self.resolved_border(col_width);
+        let content_width =
+            (col_width - padding.left - padding.right - border.left - border.right).max(Pt::ZERO);
+        let content_height = if let Some(content) = self.content.as_ref() {
+            content.wrap(content_width, huge_pt()).height
+        } else {
+            self.effective_line_height() * (self.layout_lines(content_width).len() as i32)
+        };
+        (content_height + padding.top + padding.bottom + border.top + border.bottom)
+            .max(self.row_min_height)
+    }
+
+    pub(crate) fn with_row_min_height(mut self, min_height: Pt) -> Self {
+        self.row_min_height = min_height.max(Pt::ZERO);
+        self
+    }
+
+    pub(crate) fn with_preferred_width(
+        mut self,
+        width: LengthSpec,
+        font_size: Pt,
+        root_font_size: Pt,
+    ) -> Self {
+        self.preferred_width = Some(width);
+        self.preferred_width_font_size = font_size;
+        self.preferred_width_root_font_size = root_font_size;
+        self
+    }
+
+    fn measure_text_width(&self, text: &str) -> Pt {
+        if let Ok(cache) = self.width_cache.lock() {
+            if let Some(value) = cache.get(text) {
+                if perf_enabled() {
+                    log_perf_counts("layout.tablecell.width", &[("cache_hit", 1)]);
+                }
+                return value;
+            }
+        }
+        if let Some(registry) = self.font_registry.as_deref() {
+            let (primary, fallbacks) = resolve_font_stack(Some(registry), &self.style);
+            let base = if fallbacks.is_empty() {
+                registry.measure_text_width(&primary, self.style.font_size, text)
+            } else {
+                registry.measure_text_width_with_fallbacks(
+                    &primary,
+                    &fallbacks,
                     self.style.font_size,
                     text,
                 )
@@ -2076,7 +3679,7 @@ impl TableCell {
 
     fn min_word_width(&self) -> Pt {
         if let Some(content) = self.content.as_ref() {
-            return content.intrinsic_width().unwrap_or(Pt::ZERO);
+            return content.min_content_width().unwrap_or(Pt::ZERO);
         }
         if self.no_wrap {
             return self.max_line_width();
@@ -2102,6 +3705,7 @@ impl TableCell {
     }
 
     fn layout_lines(&self, avail_width: Pt) -> Arc<Vec<LineLayout>> {
+        let _profile = layout_profile::op_scope("table_cell.lines");
         let perf = perf_start();
         let max_width = avail_width.max(Pt::from_f32(1.0));
         let key = max_width.to_milli_i64();
@@ -2301,39 +3905,18 @@ impl TableCell {
             return;
         }
 
-        let offset_x =
-            shadow
-                .offset_x
-                .resolve_width(width, self.style.font_size, self.root_font_size);
-        let offset_y =
-            shadow
-                .offset_y
-                .resolve_height(height, self.style.font_size, self.root_font_size);
-        let blur = shadow
-            .blur
-            .resolve_width(width, self.style.font_size, self.root_font_size)
-            .max(Pt::ZERO);
-        let spread = shadow
-            .spread
-            .resolve_width(width, self.style.font_size, self.root_font_size)
-            .max(Pt::ZERO);
-
-        let extra = spread + blur;
-        let shadow_x = x + offset_x - extra;
-        let shadow_y = y + offset_y - extra;
-        let shadow_w = (width + extra * 2).max(Pt::ZERO);
-        let shadow_h = (height + extra * 2).max(Pt::ZERO);
-
-        canvas.save_state();
-        canvas.clip_rect(x, y, width, height);
-        canvas.set_opacity(
-            shadow.opacity.clamp(0.0, 1.0),
-            shadow.opacity.clamp(0.0, 1.0),
+        let resolved = shadow.resolve(width, height, self.style.font_size, self.root_font_size);
+        draw_inset_shadow(
+            canvas,
+            Rect {
+                x,
+                y,
+                width,
+                height,
+            },
+            Pt::ZERO,
+            resolved,
         );
-        canvas.set_fill_color(shadow.color);
-        canvas.draw_rect(shadow_x, shadow_y, shadow_w, shadow_h);
-        canvas.set_opacity(1.0, 1.0);
-        canvas.restore_state();
     }
 
     fn draw_text_line(&self, canvas: &mut Canvas, x: Pt, y: Pt, text: &str) {
@@ -2425,9 +4008,12 @@ impl std::fmt::Debug for TableCell {
             .field("tag_role", &self.tag_role)
             .field("scope", &self.scope)
             .field("col_span", &self.col_span)
+            .field("row_span", &self.row_span)
+            .field("spanned", &self.spanned)
             .field("root_font_size", &self.root_font_size)
             .field("row_min_height", &self.row_min_height)
             .field("preferred_width", &self.preferred_width)
+            .field("transforms", &self.transforms)
             .field("has_content_flowable", &self.content.is_some())
             .finish()
     }
@@ -2474,12 +4060,65 @@ fn split_long_word_by_width_paragraph(cell: &TableCell, word: &str, max_width: P
     parts
 }
 
+// Inserts placeholders for the slots row-spanning cells cover in the rows below them, so
+// every row lists its cells in column order. Spans are clipped to the rows given.
+fn place_row_spans(rows: Vec<Vec<TableCell>>) -> Vec<Vec<TableCell>> {
+    if !rows.iter().flatten().any(|cell| cell.row_span() > 1) {
+        return rows;
+    }
+    let row_count = rows.len();
+    // (first column, rows still covered, placeholder template)
+    let mut active: Vec<(usize, usize, TableCell)> = Vec::new();
+    let mut out = Vec::with_capacity(row_count);
+    for (row_index, row) in rows.into_iter().enumerate() {
+        let mut placed = Vec::with_capacity(row.len() + active.len());
+        let mut cells = row.into_iter();
+        let mut col = 0usize;
+        loop {
+            if let Some(span) = active
+                .iter_mut()
+                .find(|(start, left, _)| *start == col && *left > 0)
+            {
+                placed.push(span.2.row_span_placeholder(span.1));
+                col += span.2.col_span();
+                span.1 -= 1;
+                continue;
+            }
+            let Some(mut cell) = cells.next() else {
+                // Spans right of the last cell; a short row cannot leave a gap before them.
+                active.sort_by_key(|(start, _, _)| *start);
+                for span in active
+                    .iter_mut()
+                    .filter(|(start, left, _)| *start > col && *left > 0)
+                {
+                    placed.push(span.2.row_span_placeholder(span.1));
+                    span.1 -= 1;
+                }
+                break;
+            };
+            if cell.row_span() > 1 {
+                cell.row_span = cell.row_span().min(row_count - row_index);
+                if cell.row_span > 1 {
+                    active.push((col, cell.row_span - 1, cell.row_span_placeholder(1)));
+                }
+            }
+            col += cell.col_span();
+            placed.push(cell);
+        }
+        active.retain(|(_, left, _)| *left > 0);
+        out.push(placed);
+    }
+    out
+}
+
 #[derive(Debug, Clone)]
 pub struct TableFlowable {
     data: Arc<TableFlowableData>,
     body_range: std::ops::Range<usize>,
     include_header: bool,
     repeat_header: bool,
+    include_footer: bool,
+    repeat_footer: bool,
     draw_background: bool,
     tag_role: Option<Arc<str>>,
     table_id: u32,
@@ -2489,6 +4128,8 @@ pub struct TableFlowable {
     font_size: Pt,
     root_font_size: Pt,
     pagination: Pagination,
+    // Fewest body rows left before and after a split (row orphans and widows).
+    min_rows: (usize, usize),
 }
 
 impl TableFlowable {
@@ -2499,13 +4140,17 @@ impl TableFlowable {
         Self {
             data: Arc::new(TableFlowableData {
                 header_rows: Vec::new(),
-                body_rows: rows,
+                body_rows: place_row_spans(rows),
+                footer_rows: Vec::new(),
                 body_row_meta: vec![Vec::new(); len],
                 layout_cache: std::sync::OnceLock::new(),
+                content_bounds: std::sync::OnceLock::new(),
             }),
             body_range: 0..len,
             include_header: true,
             repeat_header: false,
+            include_footer: true,
+            repeat_footer: false,
             draw_background: false,
             tag_role: None,
             table_id,
@@ -2515,13 +4160,16 @@ impl TableFlowable {
             font_size: Pt::from_f32(12.0),
             root_font_size: Pt::from_f32(12.0),
             pagination: Pagination::default(),
+            min_rows: (1, 1),
         }
     }
 
     pub fn with_header(mut self, header_rows: Vec<Vec<TableCell>>) -> Self {
+        let header_rows = place_row_spans(header_rows);
         if let Some(data) = Arc::get_mut(&mut self.data) {
             data.header_rows = header_rows;
             data.layout_cache = std::sync::OnceLock::new();
+            data.content_bounds = std::sync::OnceLock::new();
         } else {
             let mut owned = (*self.data).clone();
             owned.header_rows = header_rows;
@@ -2535,6 +4183,27 @@ impl TableFlowable {
         self
     }
 
+    pub fn with_footer(mut self, footer_rows: Vec<Vec<TableCell>>) -> Self {
+        let footer_rows = place_row_spans(footer_rows);
+        if let Some(data) = Arc::get_mut(&mut self.data) {
+            data.footer_rows = footer_rows;
+            data.layout_cache = std::sync::OnceLock::new();
+            data.content_bounds = std::sync::OnceLock::new();
+        } else {
+            let mut owned = (*self.data).clone();
+            owned.footer_rows = footer_rows;
+            self.data = Arc::new(owned);
+        }
+        self
+    }
+
+    // Repeat the footer rows at the bottom of every fragment when the table splits. Without
+    // it the footer is drawn once, after the last body row.
+    pub fn repeat_footer(mut self, repeat: bool) -> Self {
+        self.repeat_footer = repeat;
+        self
+    }
+
     pub fn with_row_backgrounds(mut self, enabled: bool) -> Self {
         self.draw_background = enabled;
         self
@@ -2550,6 +4219,13 @@ impl TableFlowable {
         self
     }
 
+    // Keep at least `orphans` body rows before a page break and `widows` after it, so a
+    // header is never followed by a lone row at the bottom of a page.
+    pub fn with_min_rows(mut self, orphans: usize, widows: usize) -> Self {
+        self.min_rows = (orphans.max(1), widows.max(1));
+        self
+    }
+
     pub fn with_border_collapse(mut self, mode: BorderCollapseMode) -> Self {
         self.border_collapse = mode;
         self
@@ -2609,6 +4285,23 @@ impl TableFlowable {
         self
     }
 
+    // Min/max-content widths including column gaps. Fixed layout sizes columns from the
+    // available width alone, so it has no content-based width to report.
+    fn content_width_bounds(&self) -> Option<(Pt, Pt)> {
+        if matches!(self.table_layout, TableLayoutMode::Fixed) {
+            return None;
+        }
+        let columns = self.max_columns();
+        let (col_gap, _) = self.resolve_spacing(Pt::ZERO);
+        let gap_total = if columns > 1 {
+            col_gap * ((columns - 1) as i32)
+        } else {
+            Pt::ZERO
+        };
+        let (min_width, max_width) = self.data.content_bounds(columns);
+        Some((min_width + gap_total, max_width + gap_total))
+    }
+
     fn max_columns(&self) -> usize {
         let mut max_cols = 0usize;
         for row in &self.data.header_rows {
@@ -2617,9 +4310,161 @@ impl TableFlowable {
         for row in &self.data.body_rows {
             max_cols = max_cols.max(Self::row_total_columns(row));
         }
+        for row in &self.data.footer_rows {
+            max_cols = max_cols.max(Self::row_total_columns(row));
+        }
         max_cols.max(1)
     }
 
+    fn footer_rows(&self) -> &[Vec<TableCell>] {
+        if self.include_footer {
+            &self.data.footer_rows
+        } else {
+            &[]
+        }
+    }
+
+    fn header_len(&self) -> usize {
+        if self.include_header {
+            self.data.header_rows.len()
+        } else {
+            0
+        }
+    }
+
+    // Height of the footer rows of this fragment, including the gap that separates them from
+    // the body rows above.
+    fn footer_height(&self, col_widths: &[Pt], row_gap: Pt) -> Pt {
+        let footer_rows = self.footer_rows();
+        if footer_rows.is_empty() {
+            return Pt::ZERO;
+        }
+        let first_draw_index = self.header_len() + self.body_range.len();
+        let mut height = self
+            .group_row_heights(first_draw_index, footer_rows, col_widths)
+            .into_iter()
+            .fold(Pt::ZERO, |acc, h| acc + h);
+        height += row_gap * ((footer_rows.len() - 1) as i32);
+        if first_draw_index > 0 {
+            height += row_gap;
+        }
+        height
+    }
+
+    // Heights of `rows`, drawn from `first_draw_index` on, including the height row-spanning
+    // cells add to the rows they cover.
+    fn group_row_heights(
+        &self,
+        first_draw_index: usize,
+        rows: &[Vec<TableCell>],
+        col_widths: &[Pt],
+    ) -> Vec<Pt> {
+        let mut heights: Vec<Pt> = rows
+            .iter()
+            .enumerate()
+            .map(|(idx, row)| {
+                self.row_height_for_draw_index(first_draw_index + idx, row, col_widths)
+            })
+            .collect();
+        Self::add_row_span_heights(rows, &mut heights, col_widths);
+        heights
+    }
+
+    // Grows the last row each row-spanning cell covers until the spanned rows are tall enough
+    // for the cell. Gaps between the rows are not counted.
+    fn add_row_span_heights(rows: &[Vec<TableCell>], heights: &mut [Pt], col_widths: &[Pt]) {
+        let total_columns = col_widths.len().max(1);
+        for (row_index, row) in rows.iter().enumerate() {
+            let mut cursor_col = 0usize;
+            for cell in row {
+                let col_span = Self::cell_span_for_start(cell, cursor_col, total_columns);
+                if cell.row_span() > 1 && !cell.spanned {
+                    let end = (row_index + cell.row_span()).min(heights.len());
+                    let covered = heights[row_index..end]
+                        .iter()
+                        .fold(Pt::ZERO, |acc, h| acc + *h);
+                    let needed =
+                        cell.box_height(Self::span_width(col_widths, cursor_col, col_span));
+                    if needed > covered {
+                        heights[end - 1] += needed - covered;
+                    }
+                }
+                cursor_col = cursor_col.saturating_add(col_span);
+            }
+        }
+    }
+
+    // First body row of a fragment that would start at `split_at`, moved up so the rows a
+    // row-spanning cell covers stay together. Spans that start at `start` are cut anyway.
+    fn row_span_split_point(&self, start: usize, split_at: usize) -> usize {
+        let mut split = split_at;
+        for row_index in start..split_at {
+            let Some(row) = self.data.body_rows.get(row_index) else {
+                break;
+            };
+            let crosses = row
+                .iter()
+                .any(|cell| !cell.spanned && row_index + cell.row_span() > split_at);
+            if crosses {
+                split = row_index;
+                break;
+            }
+        }
+        if split > start { split } else { split_at }
+    }
+
+    fn row_span_geometry(
+        &self,
+        cache: Option<&TableLayoutCache>,
+        col_widths: &[Pt],
+        row_gap: Pt,
+    ) -> RowSpanGeometry {
+        let header: &[Vec<TableCell>] = if self.include_header {
+            &self.data.header_rows
+        } else {
+            &[]
+        };
+        let body = &self.data.body_rows[self.body_range.clone()];
+        let footer = self.footer_rows();
+        let body_end = header.len() + body.len();
+        let group_ends = [header.len(), body_end, body_end + footer.len()];
+        let has_spans = header
+            .iter()
+            .chain(body)
+            .chain(footer)
+            .flatten()
+            .any(|cell| cell.row_span() > 1);
+        if !has_spans {
+            return RowSpanGeometry {
+                heights: Vec::new(),
+                group_ends,
+                row_gap,
+            };
+        }
+        let mut heights = match cache {
+            Some(cache) => {
+                let mut heights = if self.include_header {
+                    cache.header_row_heights.clone()
+                } else {
+                    Vec::new()
+                };
+                heights.extend_from_slice(&cache.body_row_heights[self.body_range.clone()]);
+                heights
+            }
+            None => {
+                let mut heights = self.group_row_heights(0, header, col_widths);
+                heights.extend(self.group_row_heights(header.len(), body, col_widths));
+                heights
+            }
+        };
+        heights.extend(self.group_row_heights(body_end, footer, col_widths));
+        RowSpanGeometry {
+            heights,
+            group_ends,
+            row_gap,
+        }
+    }
+
     fn row_total_columns(row: &[TableCell]) -> usize {
         row.iter().map(TableCell::col_span).sum::<usize>().max(1)
     }
@@ -2658,7 +4503,9 @@ impl TableFlowable {
                 cell.effective_line_height() * (lines.len() as i32)
             };
             let height = (content_height + pad_top + pad_bottom).max(cell.row_min_height);
-            max_height = max_height.max(height);
+            if cell.lays_out_in_row() {
+                max_height = max_height.max(height);
+            }
             cursor_col = cursor_col.saturating_add(col_span);
         }
         max_height.max(Pt::ZERO)
@@ -2695,7 +4542,9 @@ impl TableFlowable {
                 cell.effective_line_height() * (lines.len() as i32)
             };
             let height = (content_height + pad_top + pad_bottom).max(cell.row_min_height);
-            max_height = max_height.max(height);
+            if cell.lays_out_in_row() {
+                max_height = max_height.max(height);
+            }
             cursor_col = cursor_col.saturating_add(col_span);
         }
         max_height.max(Pt::ZERO)
@@ -2740,7 +4589,9 @@ impl TableFlowable {
                     lines,
                 )
             };
-            max_height = max_height.max(height);
+            if cell.lays_out_in_row() {
+                max_height = max_height.max(height);
+            }
             lines_out.push(lines);
             cursor_col = cursor_col.saturating_add(col_span);
         }
@@ -2748,11 +4599,7 @@ impl TableFlowable {
     }
 
     fn row_by_draw_index(&self, draw_row_index: usize) -> Option<&[TableCell]> {
-        let header_len = if self.include_header {
-            self.data.header_rows.len()
-        } else {
-            0
-        };
+        let header_len = self.header_len();
         if draw_row_index < header_len {
             return self
                 .data
@@ -2763,7 +4610,10 @@ impl TableFlowable {
         let body_local = draw_row_index.saturating_sub(header_len);
         let body_index = self.body_range.start + body_local;
         if body_index >= self.body_range.end {
-            return None;
+            return self
+                .footer_rows()
+                .get(body_local - self.body_range.len())
+                .map(|row| row.as_slice());
         }
         self.data
             .body_rows
@@ -2875,6 +4725,7 @@ impl TableFlowable {
         row_height: Pt,
         row_index: usize,
         row_lines: Option<&[Arc<Vec<LineLayout>>]>,
+        span_geometry: &RowSpanGeometry,
     ) -> Pt {
         let row_tagged = self.tag_role.as_ref().map(|_| {
             canvas.begin_tag("TR", None, None, Some(self.table_id), None, true);
@@ -2892,6 +4743,23 @@ impl TableFlowable {
             let col_width = Self::span_width(col_widths, cursor_col, col_span) + internal_gaps;
             let cell_x = cursor_x;
             let cell_y = y;
+            if cell.spanned && !span_geometry.starts_group(row_index) {
+                // Painted by the row-spanning cell above.
+                cursor_x += col_width;
+                cursor_col = cursor_col.saturating_add(col_span);
+                if cursor_col < total_columns {
+                    cursor_x += col_gap;
+                }
+                continue;
+            }
+            // A row-spanning cell's box covers every row it spans.
+            let row_height = if cell.row_span() > 1 {
+                span_geometry
+                    .span_height(row_index, cell.row_span())
+                    .unwrap_or(row_height)
+            } else {
+                row_height
+            };
             let padding = cell.resolved_padding(col_width);
             let (border, border_colors) =
                 if matches!(self.border_collapse, BorderCollapseMode::Collapse) {
@@ -2921,6 +4789,21 @@ impl TableFlowable {
                     false,
                 );
             });
+            // Transforms rotate the painted cell (e.g. vertical headers) without changing
+            // column or row geometry.
+            let transformed = begin_css_transform(
+                canvas,
+                &cell.transforms,
+                cell.transform_origin,
+                Rect {
+                    x: cell_x,
+                    y: cell_y,
+                    width: col_width,
+                    height: row_height,
+                },
+                cell.style.font_size,
+                cell.root_font_size,
+            );
 
             if let Some(bg) = cell.background {
                 canvas.set_fill_color(bg);
@@ -2988,7 +4871,16 @@ impl TableFlowable {
                         }
                         TextAlign::Right => cell_x + col_width - pad_right - line_width,
                     };
-                    cell.draw_text_line(canvas, text_x, cursor_y, &line.text);
+                    draw_bidi_line(
+                        canvas,
+                        cell.font_registry.as_deref(),
+                        &cell.style,
+                        text_x,
+                        cursor_y,
+                        &line.text,
+                        &|text| cell.measure_text_width(text),
+                        &|canvas, x, y, text| cell.draw_text_line(canvas, x, y, text),
+                    );
                     draw_text_decorations(
                         canvas,
                         &cell.style,
@@ -3001,6 +4893,9 @@ impl TableFlowable {
                 }
             }
 
+            if transformed {
+                canvas.restore_state();
+            }
             if tagged.is_some() {
                 canvas.end_tag();
             }
@@ -3045,7 +4940,16 @@ impl TableFlowable {
 }
 
 impl Flowable for TableFlowable {
+    fn intrinsic_width(&self) -> Option<Pt> {
+        self.content_width_bounds().map(|(_, max_width)| max_width)
+    }
+
+    fn min_content_width(&self) -> Option<Pt> {
+        self.content_width_bounds().map(|(min_width, _)| min_width)
+    }
+
     fn wrap(&self, avail_width: Pt, _avail_height: Pt) -> Size {
+        let _profile = layout_profile::op_scope("table.wrap");
         let perf = perf_start();
         let columns = self.max_columns();
         let (col_gap, row_gap) = self.resolve_spacing(avail_width);
@@ -3082,15 +4986,14 @@ impl Flowable for TableFlowable {
                     height += row_gap * ((body_count - 1) as i32);
                 }
             }
+            height += self.footer_height(&cache.col_widths, row_gap);
         } else {
             let col_widths =
                 self.data
                     .compute_column_widths(avail_cols_width, columns, self.table_layout);
-            let mut row_index = 0usize;
             if self.include_header {
-                for row in &self.data.header_rows {
-                    height += self.row_height_for_draw_index(row_index, row, &col_widths);
-                    row_index += 1;
+                for row_height in self.group_row_heights(0, &self.data.header_rows, &col_widths) {
+                    height += row_height;
                 }
                 if !self.data.header_rows.is_empty()
                     && !self.data.body_rows[self.body_range.clone()].is_empty()
@@ -3101,14 +5004,15 @@ impl Flowable for TableFlowable {
                     height += row_gap * ((self.data.header_rows.len() - 1) as i32);
                 }
             }
-            for row in &self.data.body_rows[self.body_range.clone()] {
-                height += self.row_height_for_draw_index(row_index, row, &col_widths);
-                row_index += 1;
+            let body_rows = &self.data.body_rows[self.body_range.clone()];
+            for row_height in self.group_row_heights(self.header_len(), body_rows, &col_widths) {
+                height += row_height;
             }
             let body_count = self.body_range.end.saturating_sub(self.body_range.start);
             if body_count > 1 {
                 height += row_gap * ((body_count - 1) as i32);
             }
+            height += self.footer_height(&col_widths, row_gap);
         }
         if perf_enabled() {
             let header_rows = self.data.header_rows.len() as u64;
@@ -3146,6 +5050,7 @@ impl Flowable for TableFlowable {
         avail_width: Pt,
         avail_height: Pt,
     ) -> Option<(Box<dyn Flowable>, Box<dyn Flowable>)> {
+        let _profile = layout_profile::op_scope("table.split");
         let columns = self.max_columns();
         let (col_gap, row_gap) = self.resolve_spacing(avail_width);
         let gap_total = if columns > 1 {
@@ -3177,8 +5082,8 @@ impl Flowable for TableFlowable {
                     self.data
                         .compute_column_widths(avail_cols_width, columns, self.table_layout);
                 let mut height = Pt::ZERO;
-                for (row_index, row) in self.data.header_rows.iter().enumerate() {
-                    height += self.row_height_for_draw_index(row_index, row, &col_widths);
+                for row_height in self.group_row_heights(0, &self.data.header_rows, &col_widths) {
+                    height += row_height;
                 }
                 if self.data.header_rows.len() > 1 {
                     height += row_gap * ((self.data.header_rows.len() - 1) as i32);
@@ -3193,6 +5098,16 @@ impl Flowable for TableFlowable {
         } else {
             Pt::ZERO
         };
+        let footer_height = match cache {
+            _ if self.footer_rows().is_empty() => Pt::ZERO,
+            Some(cache) => self.footer_height(&cache.col_widths, row_gap),
+            None => self.footer_height(
+                &self
+                    .data
+                    .compute_column_widths(avail_cols_width, columns, self.table_layout),
+                row_gap,
+            ),
+        };
         let available = avail_height - header_height;
         if available <= Pt::ZERO {
             return None;
@@ -3202,52 +5117,68 @@ impl Flowable for TableFlowable {
         let end = self.body_range.end;
         let body_len = end.saturating_sub(start);
 
-        let split_at = if let Some(cache) = cache {
-            // Binary search for the largest end index where sum(row_heights[start..end]) <= available.
-            let prefix = &cache.body_prefix;
-            let mut lo = start;
-            let mut hi = end;
-            while lo < hi {
-                let mid = lo + (hi - lo + 1) / 2;
-                let mut used = prefix[mid] - prefix[start];
-                let count = mid.saturating_sub(start);
-                if count > 1 {
-                    used = used + row_gap * ((count - 1) as i32);
-                }
-                if used <= available {
-                    lo = mid;
-                } else {
-                    hi = mid - 1;
+        // Largest end index whose rows fit in `available`.
+        let fit_rows = |available: Pt| {
+            if let Some(cache) = cache {
+                // Binary search for the largest end index where sum(row_heights[start..end]) <= available.
+                let prefix = &cache.body_prefix;
+                let mut lo = start;
+                let mut hi = end;
+                while lo < hi {
+                    let mid = lo + (hi - lo + 1) / 2;
+                    let mut used = prefix[mid] - prefix[start];
+                    let count = mid.saturating_sub(start);
+                    if count > 1 {
+                        used += row_gap * ((count - 1) as i32);
+                    }
+                    if used <= available {
+                        lo = mid;
+                    } else {
+                        hi = mid - 1;
+                    }
                 }
-            }
-            lo
-        } else {
-            // Fallback: scan rows (should be rare; widths should be stable).
-            let col_widths =
-                self.data
-                    .compute_column_widths(avail_cols_width, columns, self.table_layout);
-            let mut used = Pt::ZERO;
-            let mut idx = start;
-            let mut draw_row_index = if self.include_header {
-                self.data.header_rows.len()
+                lo
             } else {
-                0
-            };
-            for row in &self.data.body_rows[self.body_range.clone()] {
-                let row_height = self.row_height_for_draw_index(draw_row_index, row, &col_widths);
-                let gap = if idx > start { row_gap } else { Pt::ZERO };
-                if used + gap + row_height > available {
-                    break;
+                // Fallback: scan rows (should be rare; widths should be stable).
+                let col_widths =
+                    self.data
+                        .compute_column_widths(avail_cols_width, columns, self.table_layout);
+                let mut used = Pt::ZERO;
+                let mut idx = start;
+                let body_rows = &self.data.body_rows[self.body_range.clone()];
+                for row_height in self.group_row_heights(self.header_len(), body_rows, &col_widths)
+                {
+                    let gap = if idx > start { row_gap } else { Pt::ZERO };
+                    if used + gap + row_height > available {
+                        break;
+                    }
+                    used += gap + row_height;
+                    idx += 1;
                 }
-                used = used + gap + row_height;
-                idx += 1;
-                draw_row_index += 1;
+                idx
             }
-            idx
         };
 
+        // A repeated footer closes every fragment; otherwise only the last fragment carries
+        // it, so the rows only need to leave room for it when they would all fit.
+        let mut split_at = if self.repeat_footer {
+            fit_rows(available - footer_height)
+        } else {
+            fit_rows(available)
+        };
+        if !self.repeat_footer && split_at >= end && footer_height > Pt::ZERO {
+            split_at = fit_rows(available - footer_height);
+        }
+        if split_at < end {
+            split_at = self.row_span_split_point(start, split_at);
+        }
+        let (min_before, min_after) = self.min_rows;
+        if split_at < end && end - split_at < min_after {
+            split_at = self.row_span_split_point(start, end.saturating_sub(min_after));
+        }
+
         let max_rows = split_at.saturating_sub(start);
-        if max_rows == 0 || max_rows >= body_len {
+        if max_rows == 0 || max_rows < min_before.min(body_len) || max_rows >= body_len {
             return None;
         }
 
@@ -3256,6 +5187,8 @@ impl Flowable for TableFlowable {
             body_range: start..split_at,
             include_header: self.include_header,
             repeat_header: self.repeat_header,
+            include_footer: self.include_footer && self.repeat_footer,
+            repeat_footer: self.repeat_footer,
             draw_background: self.draw_background,
             tag_role: self.tag_role.clone(),
             table_id: self.table_id,
@@ -3269,12 +5202,15 @@ impl Flowable for TableFlowable {
                 break_after: BreakAfter::Auto,
                 ..self.pagination
             },
+            min_rows: self.min_rows,
         };
         let second = TableFlowable {
             data: self.data.clone(),
             body_range: split_at..end,
             include_header: self.repeat_header,
             repeat_header: self.repeat_header,
+            include_footer: self.include_footer,
+            repeat_footer: self.repeat_footer,
             draw_background: self.draw_background,
             tag_role: self.tag_role.clone(),
             table_id: self.table_id,
@@ -3287,11 +5223,13 @@ impl Flowable for TableFlowable {
                 break_before: BreakBefore::Auto,
                 ..self.pagination
             },
+            min_rows: self.min_rows,
         };
         Some((Box::new(first), Box::new(second)))
     }
 
     fn draw(&self, canvas: &mut Canvas, x: Pt, y: Pt, avail_width: Pt, _avail_height: Pt) {
+        let _profile = layout_profile::op_scope("table.draw");
         let perf = perf_start();
         let tagged = self.tag_role.as_ref().map(|role| {
             canvas.begin_tag(role.as_ref(), None, None, None, None, true);
@@ -3337,6 +5275,7 @@ impl Flowable for TableFlowable {
                 y.to_f32()
             );
         }
+        let span_geometry = self.row_span_geometry(cache, col_widths.as_ref(), row_gap);
         let mut cursor_y = y;
         let mut row_index = 0usize;
         if self.include_header && !self.data.header_rows.is_empty() {
@@ -3359,6 +5298,7 @@ impl Flowable for TableFlowable {
                     owned_row_lines = Some(lines);
                     height
                 };
+                let row_height = span_geometry.row_height(row_index).unwrap_or(row_height);
                 let row_lines = if let Some(lines) = cached_row_lines {
                     Some(lines.as_slice())
                 } else {
@@ -3378,6 +5318,7 @@ impl Flowable for TableFlowable {
                     row_height,
                     row_index,
                     row_lines,
+                    &span_geometry,
                 );
                 cursor_y = cursor_y + row_height;
                 row_index += 1;
@@ -3413,6 +5354,7 @@ impl Flowable for TableFlowable {
                 owned_row_lines = Some(lines);
                 height
             };
+            let row_height = span_geometry.row_height(row_index).unwrap_or(row_height);
             if row_height <= Pt::ZERO {
                 row_index += 1;
                 if i + 1 < self.data.body_rows[self.body_range.clone()].len() {
@@ -3446,6 +5388,7 @@ impl Flowable for TableFlowable {
                 row_height,
                 row_index,
                 row_lines,
+                &span_geometry,
             );
             cursor_y = cursor_y + row_height;
             row_index += 1;
@@ -3456,6 +5399,45 @@ impl Flowable for TableFlowable {
         if body_tagged.is_some() {
             canvas.end_tag();
         }
+
+        let footer_rows = self.footer_rows();
+        if !footer_rows.is_empty() {
+            let foot_tagged = self.tag_role.as_ref().map(|_| {
+                canvas.begin_tag("TFoot", None, None, Some(self.table_id), None, true);
+            });
+            if row_index > 0 {
+                cursor_y += row_gap;
+            }
+            for (idx, row) in footer_rows.iter().enumerate() {
+                let (row_height, lines) =
+                    self.row_height_and_lines_for_draw_index(row_index, row, col_widths.as_ref());
+                let row_height = span_geometry.row_height(row_index).unwrap_or(row_height);
+                if self.draw_background {
+                    canvas.set_fill_color(Color::rgb(0.9, 0.9, 0.9));
+                    canvas.draw_rect(x, cursor_y, avail_width, row_height);
+                }
+                let row_height = self.draw_row_at(
+                    canvas,
+                    x,
+                    cursor_y,
+                    col_widths.as_ref(),
+                    col_gap,
+                    row,
+                    row_height,
+                    row_index,
+                    Some(lines.as_slice()),
+                    &span_geometry,
+                );
+                cursor_y += row_height;
+                row_index += 1;
+                if idx + 1 < footer_rows.len() {
+                    cursor_y += row_gap;
+                }
+            }
+            if foot_tagged.is_some() {
+                canvas.end_tag();
+            }
+        }
         if tagged.is_some() {
             canvas.end_tag();
         }
@@ -3467,12 +5449,49 @@ impl Flowable for TableFlowable {
     }
 }
 
-#[derive(Debug)]
-struct TableFlowableData {
+// Row heights of a table fragment in draw order (header, body, footer), used to size the
+// boxes of cells that span rows. Empty when the fragment has no row spans.
+struct RowSpanGeometry {
+    heights: Vec<Pt>,
+    // Draw index just past the header, body and footer rows.
+    group_ends: [usize; 3],
+    row_gap: Pt,
+}
+
+impl RowSpanGeometry {
+    fn row_height(&self, row_index: usize) -> Option<Pt> {
+        self.heights.get(row_index).copied()
+    }
+
+    fn starts_group(&self, row_index: usize) -> bool {
+        row_index == 0 || self.group_ends.contains(&row_index)
+    }
+
+    // Height of a box from row `row_index` down `rows` rows, clipped to its row group.
+    fn span_height(&self, row_index: usize, rows: usize) -> Option<Pt> {
+        let group_end = self
+            .group_ends
+            .iter()
+            .copied()
+            .find(|end| *end > row_index)?;
+        let end = (row_index + rows).min(group_end);
+        let covered = self.heights.get(row_index..end)?;
+        let mut height = covered.iter().fold(Pt::ZERO, |acc, h| acc + *h);
+        if covered.len() > 1 {
+            height += self.row_gap * ((covered.len() - 1) as i32);
+        }
+        Some(height)
+    }
+}
+
+#[derive(Debug)]
+struct TableFlowableData {
     header_rows: Vec<Vec<TableCell>>,
     body_rows: Vec<Vec<TableCell>>,
+    footer_rows: Vec<Vec<TableCell>>,
     body_row_meta: Vec<Vec<(String, String)>>,
     layout_cache: std::sync::OnceLock<TableLayoutCache>,
+    content_bounds: std::sync::OnceLock<(Pt, Pt)>,
 }
 
 impl TableFlowableData {
@@ -3499,35 +5518,17 @@ impl TableFlowableData {
         )
     }
 
-    fn compute_column_widths(
+    // Per-column (min-content, max-content) widths in milli-points, with any
+    // preferred cell widths folded in.
+    fn measure_column_bounds(
         &self,
         avail_width: Pt,
         columns: usize,
-        table_layout: TableLayoutMode,
-    ) -> Vec<Pt> {
-        let columns = columns.max(1);
-        let debug_verbose = table_debug_enabled() && table_debug_verbose_enabled();
-        let data_ptr = self as *const TableFlowableData as usize;
-
-        if matches!(table_layout, TableLayoutMode::Fixed) {
-            return self.compute_fixed_column_widths(avail_width, columns, debug_verbose, data_ptr);
-        }
-
+        debug_verbose: bool,
+        data_ptr: usize,
+    ) -> (Vec<i64>, Vec<i64>) {
         let approx_col = avail_width / (columns as i32);
-        if debug_verbose {
-            eprintln!(
-                "[table.debug.widths.begin] data_ptr=0x{:x} mode={:?} columns={} avail_width_pt={:.3} approx_col_pt={:.3} header_rows={} body_rows={}",
-                data_ptr,
-                table_layout,
-                columns,
-                avail_width.to_f32(),
-                approx_col.to_f32(),
-                self.header_rows.len(),
-                self.body_rows.len()
-            );
-        }
-
-        let row_count = self.header_rows.len() + self.body_rows.len();
+        let row_count = self.header_rows.len() + self.body_rows.len() + self.footer_rows.len();
         let mut min_widths = vec![0i64; columns];
         let mut max_widths = vec![0i64; columns];
         let mut preferred_widths = vec![0i64; columns];
@@ -3587,6 +5588,10 @@ impl TableFlowableData {
                     .col_span()
                     .min(columns.saturating_sub(cursor_col))
                     .max(1);
+                if cell.spanned {
+                    cursor_col = cursor_col.saturating_add(col_span);
+                    continue;
+                }
                 let span_width = approx_col * (col_span as i32);
                 let resolved_preferred = cell.preferred_width.map(|width_spec| {
                     width_spec
@@ -3605,11 +5610,13 @@ impl TableFlowableData {
                 let border = cell.resolved_border(span_width);
                 let extra = padding.left + padding.right + border.left + border.right;
                 let (min_text, max_text) = if let Some(content) = cell.content.as_ref() {
-                    let intrinsic = content.intrinsic_width().unwrap_or(Pt::ZERO);
-                    let wrapped = content
-                        .wrap(span_width.max(Pt::from_f32(1.0)), huge_pt())
-                        .width;
-                    (intrinsic, wrapped.max(intrinsic))
+                    let max_content = content.intrinsic_width().unwrap_or_else(|| {
+                        content
+                            .wrap(span_width.max(Pt::from_f32(1.0)), huge_pt())
+                            .width
+                    });
+                    let min_content = content.min_content_width().unwrap_or(Pt::ZERO);
+                    (min_content, max_content.max(min_content))
                 } else {
                     (cell.min_word_width(), cell.max_line_width())
                 };
@@ -3746,6 +5753,16 @@ impl TableFlowableData {
                 );
             }
         }
+        for (row_index, row) in self.footer_rows.iter().enumerate() {
+            update_row(
+                "footer",
+                row_index,
+                row,
+                &mut min_widths,
+                &mut max_widths,
+                &mut preferred_widths,
+            );
+        }
 
         for i in 0..columns {
             if preferred_widths[i] > 0 {
@@ -3754,6 +5771,52 @@ impl TableFlowableData {
             }
         }
 
+        (min_widths, max_widths)
+    }
+
+    // Content-based (min, max) widths of the whole column set, excluding
+    // gaps. Used when this table is itself measured as cell content.
+    fn content_bounds(&self, columns: usize) -> (Pt, Pt) {
+        *self.content_bounds.get_or_init(|| {
+            let (min_widths, max_widths) =
+                self.measure_column_bounds(Pt::ZERO, columns.max(1), false, 0);
+            (
+                Pt::from_milli_i64(min_widths.iter().sum()),
+                Pt::from_milli_i64(max_widths.iter().sum()),
+            )
+        })
+    }
+
+    fn compute_column_widths(
+        &self,
+        avail_width: Pt,
+        columns: usize,
+        table_layout: TableLayoutMode,
+    ) -> Vec<Pt> {
+        let columns = columns.max(1);
+        let debug_verbose = table_debug_enabled() && table_debug_verbose_enabled();
+        let data_ptr = self as *const TableFlowableData as usize;
+
+        if matches!(table_layout, TableLayoutMode::Fixed) {
+            return self.compute_fixed_column_widths(avail_width, columns, debug_verbose, data_ptr);
+        }
+
+        if debug_verbose {
+            eprintln!(
+                "[table.debug.widths.begin] data_ptr=0x{:x} mode={:?} columns={} avail_width_pt={:.3} approx_col_pt={:.3} header_rows={} body_rows={}",
+                data_ptr,
+                table_layout,
+                columns,
+                avail_width.to_f32(),
+                (avail_width / (columns as i32)).to_f32(),
+                self.header_rows.len(),
+                self.body_rows.len()
+            );
+        }
+
+        let (min_widths, max_widths) =
+            self.measure_column_bounds(avail_width, columns, debug_verbose, data_ptr);
+
         let avail = avail_width.to_milli_i64().max(1);
         let total_min: i64 = min_widths.iter().sum();
         let total_max: i64 = max_widths.iter().sum();
@@ -3838,16 +5901,8 @@ impl TableFlowableData {
 
         if debug_verbose {
             eprintln!(
-                "[table.debug.widths.end] data_ptr=0x{:x} mode={:?} avail_milli={} total_min={} total_max={} min={:?} max={:?} pref={:?} out={:?}",
-                data_ptr,
-                table_layout,
-                avail,
-                total_min,
-                total_max,
-                min_widths,
-                max_widths,
-                preferred_widths,
-                widths
+                "[table.debug.widths.end] data_ptr=0x{:x} mode={:?} avail_milli={} total_min={} total_max={} min={:?} max={:?} out={:?}",
+                data_ptr, table_layout, avail, total_min, total_max, min_widths, max_widths, widths
             );
         }
 
@@ -4024,8 +6079,10 @@ impl Clone for TableFlowableData {
         Self {
             header_rows: self.header_rows.clone(),
             body_rows: self.body_rows.clone(),
+            footer_rows: self.footer_rows.clone(),
             body_row_meta: self.body_row_meta.clone(),
             layout_cache: std::sync::OnceLock::new(),
+            content_bounds: std::sync::OnceLock::new(),
         }
     }
 }
@@ -4068,7 +6125,14 @@ impl TableLayoutCache {
         for (h, lines) in header_results {
             header_row_heights.push(h);
             header_row_lines.push(lines);
-            header_total = header_total + h;
+        }
+        TableFlowable::add_row_span_heights(
+            &data.header_rows,
+            &mut header_row_heights,
+            &col_widths,
+        );
+        for h in &header_row_heights {
+            header_total += *h;
         }
 
         let body_results: Vec<(Pt, Vec<Arc<Vec<LineLayout>>>)> = if data.body_rows.len() >= 32 {
@@ -4091,7 +6155,10 @@ impl TableLayoutCache {
         for (h, lines) in body_results {
             body_row_heights.push(h);
             body_row_lines.push(lines);
-            acc = acc + h;
+        }
+        TableFlowable::add_row_span_heights(&data.body_rows, &mut body_row_heights, &col_widths);
+        for h in &body_row_heights {
+            acc += *h;
             body_prefix.push(acc);
         }
 
@@ -4140,7 +6207,9 @@ impl TableLayoutCache {
                     lines,
                 )
             };
-            max_height = max_height.max(height);
+            if cell.lays_out_in_row() {
+                max_height = max_height.max(height);
+            }
             lines_out.push(lines);
             cursor_col = cursor_col.saturating_add(col_span);
         }
@@ -4220,7 +6289,11 @@ impl InlineBlockLayoutFlowable {
         };
 
         for (idx, (child, valign)) in self.children.iter().enumerate() {
-            let size = child.wrap(avail_width, huge_pt());
+            // Boxes without a set width shrink to their content, as inline-blocks do.
+            let child_width = child
+                .intrinsic_width()
+                .map_or(avail_width, |width| width.min(avail_width));
+            let size = child.wrap(child_width, huge_pt());
             let next_width = if line_items.is_empty() {
                 size.width
             } else {
@@ -5280,8 +7353,10 @@ pub struct ContainerFlowable {
     box_sizing: BoxSizingMode,
     background: Option<Color>,
     background_paint: Option<BackgroundPaint>,
+    background_image: Option<BackgroundImage>,
     clip_path_inset: Option<ClipPathInsetSpec>,
     box_shadow: Option<BoxShadowSpec>,
+    outline: Option<OutlineSpec>,
     paint_filter: Option<PaintFilterSpec>,
     backdrop_filter: Option<PaintFilterSpec>,
     mix_blend_mode: MixBlendMode,
@@ -5293,6 +7368,8 @@ pub struct ContainerFlowable {
     font_size: Pt,
     root_font_size: Pt,
     pagination: Pagination,
+    // Fewest in-flow children left before and after a split (list item orphans and widows).
+    min_items: (usize, usize),
     layout_cache: Arc<Mutex<Option<ContainerLayoutCache>>>,
 }
 
@@ -5319,8 +7396,10 @@ impl ContainerFlowable {
             box_sizing: BoxSizingMode::ContentBox,
             background: None,
             background_paint: None,
+            background_image: None,
             clip_path_inset: None,
             box_shadow: None,
+            outline: None,
             paint_filter: None,
             backdrop_filter: None,
             mix_blend_mode: MixBlendMode::Normal,
@@ -5332,6 +7411,7 @@ impl ContainerFlowable {
             font_size,
             root_font_size,
             pagination: Pagination::default(),
+            min_items: (1, 1),
             layout_cache: Arc::new(Mutex::new(None)),
         }
     }
@@ -5403,6 +7483,11 @@ impl ContainerFlowable {
         self
     }
 
+    pub fn with_background_image(mut self, image: Option<BackgroundImage>) -> Self {
+        self.background_image = image;
+        self
+    }
+
     pub fn with_clip_path_inset(mut self, clip_path_inset: Option<ClipPathInsetSpec>) -> Self {
         self.clip_path_inset = clip_path_inset;
         self
@@ -5413,6 +7498,11 @@ impl ContainerFlowable {
         self
     }
 
+    pub fn with_outline(mut self, outline: Option<OutlineSpec>) -> Self {
+        self.outline = outline;
+        self
+    }
+
     pub fn with_paint_filter(mut self, filter: Option<PaintFilterSpec>) -> Self {
         self.paint_filter = filter.and_then(|value| {
             if value.is_identity() {
@@ -5470,6 +7560,13 @@ impl ContainerFlowable {
         self
     }
 
+    // Keep at least `orphans` children before a page break and `widows` after it (list
+    // items, for example).
+    pub fn with_min_items(mut self, orphans: usize, widows: usize) -> Self {
+        self.min_items = (orphans.max(1), widows.max(1));
+        self
+    }
+
     fn resolve_fixed_height(&self, avail_height: Pt) -> Option<Pt> {
         match self.height {
             LengthSpec::Auto | LengthSpec::Inherit | LengthSpec::Initial => None,
@@ -5568,43 +7665,6 @@ impl ContainerFlowable {
         (margin, border, padding, content_width, border_box_width)
     }
 
-    fn has_transforms(&self) -> bool {
-        !self.transforms.is_empty()
-    }
-
-    fn apply_transforms(&self, canvas: &mut Canvas, ref_width: Pt, ref_height: Pt) {
-        for op in &self.transforms {
-            match op {
-                CssTransformOp::Translate { x, y } => {
-                    let tx = x.resolve_width(ref_width, self.font_size, self.root_font_size);
-                    let ty = y.resolve_height(ref_height, self.font_size, self.root_font_size);
-                    canvas.translate(tx, ty);
-                }
-                CssTransformOp::Scale { x, y } => {
-                    canvas.scale(*x, *y);
-                }
-                CssTransformOp::Rotate { radians } => {
-                    canvas.rotate(*radians);
-                }
-                CssTransformOp::Skew {
-                    x_radians,
-                    y_radians,
-                } => {
-                    let c = x_radians.tan();
-                    let b = y_radians.tan();
-                    if c.is_finite() && b.is_finite() {
-                        canvas.concat_matrix(1.0, b, c, 1.0, Pt::ZERO, Pt::ZERO);
-                    }
-                }
-                CssTransformOp::Matrix { a, b, c, d, e, f } => {
-                    if a.is_finite() && b.is_finite() && c.is_finite() && d.is_finite() {
-                        canvas.concat_matrix(*a, *b, *c, *d, *e, *f);
-                    }
-                }
-            }
-        }
-    }
-
     fn compute_layout(&self, avail_width: Pt, avail_height: Pt) -> ContainerLayoutCache {
         let (margin, border, padding, content_width, border_box_width) =
             self.resolve_box(avail_width);
@@ -5647,11 +7707,16 @@ impl ContainerFlowable {
         if fixed_content_height.is_none() && !in_flow_pagination.is_empty() {
             let mut forced_breaks = 0usize;
             for (idx, pagination) in in_flow_pagination.iter().enumerate() {
-                if idx > 0 && matches!(pagination.break_before, BreakBefore::Page) {
+                if idx > 0
+                    && matches!(
+                        pagination.break_before,
+                        BreakBefore::Page | BreakBefore::Frame
+                    )
+                {
                     forced_breaks += 1;
                 }
                 if idx + 1 < in_flow_pagination.len()
-                    && matches!(pagination.break_after, BreakAfter::Page)
+                    && matches!(pagination.break_after, BreakAfter::Page | BreakAfter::Frame)
                 {
                     forced_breaks += 1;
                 }
@@ -6160,6 +8225,7 @@ impl ContainerFlowable {
         }
     }
 
+    // Outer (non-inset) box shadow; inset shadows are drawn over the background.
     fn draw_box_shadow(
         &self,
         canvas: &mut Canvas,
@@ -6171,92 +8237,86 @@ impl ContainerFlowable {
         shadow: &BoxShadowSpec,
     ) {
         if shadow.inset {
-            let opacity = shadow.opacity.clamp(0.0, 1.0);
-            canvas.set_opacity(opacity, opacity);
-            canvas.set_fill_color(self.apply_paint_filter_color(shadow.color));
-            if radius > Pt::ZERO {
-                Self::draw_rounded_rect_fill(canvas, x, y, width, height, radius);
-            } else {
-                canvas.draw_rect(x, y, width, height);
-            }
-            canvas.set_opacity(1.0, 1.0);
-            return;
-        }
-        let offset_x = shadow
-            .offset_x
-            .resolve_width(width, self.font_size, self.root_font_size);
-        let offset_y = shadow
-            .offset_y
-            .resolve_height(height, self.font_size, self.root_font_size);
-        let blur = shadow
-            .blur
-            .resolve_width(width, self.font_size, self.root_font_size)
-            .max(Pt::ZERO);
-        let spread = shadow
-            .spread
-            .resolve_width(width, self.font_size, self.root_font_size);
-
-        let base_x = x + offset_x - spread;
-        let base_y = y + offset_y - spread;
-        let base_w = (width + spread * 2).max(Pt::ZERO);
-        let base_h = (height + spread * 2).max(Pt::ZERO);
-        let base_r = (radius + spread).max(Pt::ZERO);
-        if base_w <= Pt::ZERO || base_h <= Pt::ZERO {
             return;
         }
-
-        let shadow_color = self.apply_paint_filter_color(shadow.color);
-        if blur <= Pt::ZERO {
-            let opacity = shadow.opacity.clamp(0.0, 1.0);
-            canvas.set_opacity(opacity, opacity);
-            canvas.set_fill_color(shadow_color);
-            if base_r > Pt::ZERO {
-                Self::draw_rounded_rect_fill(canvas, base_x, base_y, base_w, base_h, base_r);
-            } else {
-                canvas.draw_rect(base_x, base_y, base_w, base_h);
-            }
-            canvas.set_opacity(1.0, 1.0);
+        let resolved = shadow.resolve(width, height, self.font_size, self.root_font_size);
+        let base_x = x + resolved.offset_x - resolved.spread;
+        let base_y = y + resolved.offset_y - resolved.spread;
+        let base_w = width + resolved.spread * 2;
+        let base_h = height + resolved.spread * 2;
+        let base_r = (radius + resolved.spread).max(Pt::ZERO);
+        if base_w <= Pt::ZERO || base_h <= Pt::ZERO || resolved.opacity <= 0.0 {
             return;
         }
 
-        let blur_f = blur.to_f32().max(0.0);
-        let steps = ((blur_f / 1.5).ceil() as usize).clamp(6, 24);
-        let sigma = 0.38_f32;
-        let mut weights = Vec::with_capacity(steps);
-        let mut weight_sum = 0.0_f32;
-        for i in 0..steps {
-            let t = (i as f32 + 0.5) / (steps as f32);
-            let z = t / sigma;
-            let w = (-0.5 * z * z).exp();
-            weights.push(w);
-            weight_sum += w;
-        }
-        let norm = if weight_sum > 0.0 { weight_sum } else { 1.0 };
-        for i in (0..steps).rev() {
-            let frac = (i + 1) as f32 / (steps as f32);
-            let extra = blur * frac;
-            let opacity = (shadow.opacity * (weights[i] / norm)).clamp(0.0, 1.0);
-            if opacity <= 0.0 {
-                continue;
-            }
-            let x0 = base_x - extra;
-            let y0 = base_y - extra;
-            let w0 = (base_w + extra * 2).max(Pt::ZERO);
-            let h0 = (base_h + extra * 2).max(Pt::ZERO);
+        canvas.set_fill_color(self.apply_paint_filter_color(resolved.color));
+        for (reach, alpha) in shadow_blur_layers(resolved.blur, resolved.opacity) {
+            let w0 = base_w + reach * 2;
+            let h0 = base_h + reach * 2;
             if w0 <= Pt::ZERO || h0 <= Pt::ZERO {
                 continue;
             }
-            let r0 = (base_r + extra).max(Pt::ZERO);
-            canvas.set_opacity(opacity, opacity);
-            canvas.set_fill_color(shadow_color);
+            let r0 = (base_r + reach).max(Pt::ZERO);
+            canvas.set_opacity(alpha, alpha);
             if r0 > Pt::ZERO {
-                Self::draw_rounded_rect_fill(canvas, x0, y0, w0, h0, r0);
+                Self::draw_rounded_rect_fill(canvas, base_x - reach, base_y - reach, w0, h0, r0);
             } else {
-                canvas.draw_rect(x0, y0, w0, h0);
+                canvas.draw_rect(base_x - reach, base_y - reach, w0, h0);
             }
         }
         canvas.set_opacity(1.0, 1.0);
     }
+
+    fn draw_outline(
+        &self,
+        canvas: &mut Canvas,
+        border_box: Rect,
+        radius: Pt,
+        outline: &OutlineSpec,
+    ) {
+        let Rect {
+            x,
+            y,
+            width,
+            height,
+        } = border_box;
+        let line_width = outline
+            .width
+            .resolve_width(width, self.font_size, self.root_font_size);
+        if line_width <= Pt::ZERO {
+            return;
+        }
+        let offset = outline
+            .offset
+            .resolve_width(width, self.font_size, self.root_font_size);
+        let grow = offset + line_width / 2.0;
+        let w = width + grow * 2;
+        let h = height + grow * 2;
+        if w <= Pt::ZERO || h <= Pt::ZERO {
+            return;
+        }
+        canvas.save_state();
+        canvas.set_stroke_color(self.apply_paint_filter_color(outline.color));
+        canvas.set_line_width(line_width);
+        match outline.style {
+            OutlineStyle::Solid => {}
+            OutlineStyle::Dashed => {
+                canvas.set_dash(vec![line_width * 3.0, line_width * 3.0], Pt::ZERO)
+            }
+            OutlineStyle::Dotted => {
+                canvas.set_line_cap(1);
+                canvas.set_dash(vec![Pt::ZERO, line_width * 2.0], Pt::ZERO);
+            }
+        }
+        let r = if radius > Pt::ZERO {
+            (radius + grow).max(Pt::ZERO)
+        } else {
+            Pt::ZERO
+        };
+        shadow_shape_path(canvas, x - grow, y - grow, w, h, r);
+        canvas.stroke();
+        canvas.restore_state();
+    }
 }
 
 impl Flowable for ContainerFlowable {
@@ -6294,6 +8354,37 @@ impl Flowable for ContainerFlowable {
         Some((border_box_width + margin.left + margin.right).max(Pt::ZERO))
     }
 
+    fn min_content_width(&self) -> Option<Pt> {
+        if !matches!(
+            self.width,
+            LengthSpec::Auto | LengthSpec::Inherit | LengthSpec::Initial
+        ) {
+            return None;
+        }
+
+        let mut max_child = Pt::ZERO;
+        for child in &self.children {
+            if child.out_of_flow() {
+                continue;
+            }
+            max_child = max_child.max(child.min_content_width()?);
+        }
+
+        let margin = self
+            .margin
+            .resolve(max_child, self.font_size, self.root_font_size);
+        let border = self
+            .border_width
+            .resolve(max_child, self.font_size, self.root_font_size);
+        let padding = self
+            .padding
+            .resolve(max_child, self.font_size, self.root_font_size);
+        let extra =
+            margin.left + border.left + padding.left + padding.right + border.right + margin.right;
+
+        Some((max_child + extra).max(Pt::ZERO))
+    }
+
     fn wrap(&self, avail_width: Pt, avail_height: Pt) -> Size {
         let cache = self.cached_layout(avail_width, avail_height);
         Size {
@@ -6323,6 +8414,8 @@ impl Flowable for ContainerFlowable {
         let mut remaining_height = available_content_height;
         let mut placed: Vec<Box<dyn Flowable>> = Vec::new();
         let mut remaining: Vec<Box<dyn Flowable>> = Vec::new();
+        // Child whose first fragment ends `placed`, to undo the split when moving it on.
+        let mut split_child: Option<Box<dyn Flowable>> = None;
         let out_of_flow: Vec<Box<dyn Flowable>> = self
             .children
             .iter()
@@ -6338,7 +8431,11 @@ impl Flowable for ContainerFlowable {
 
         for (index, child) in flow_children.iter().cloned().enumerate() {
             let pagination = child.pagination();
-            if matches!(pagination.break_before, BreakBefore::Page) && !placed.is_empty() {
+            if matches!(
+                pagination.break_before,
+                BreakBefore::Page | BreakBefore::Frame
+            ) && !placed.is_empty()
+            {
                 remaining.push(child);
                 for rest in flow_children[index + 1..].iter().cloned() {
                     remaining.push(rest);
@@ -6350,7 +8447,7 @@ impl Flowable for ContainerFlowable {
             if size.height <= remaining_height {
                 placed.push(child);
                 remaining_height -= size.height;
-                if matches!(pagination.break_after, BreakAfter::Page) {
+                if matches!(pagination.break_after, BreakAfter::Page | BreakAfter::Frame) {
                     for rest in flow_children[index + 1..].iter().cloned() {
                         remaining.push(rest);
                     }
@@ -6359,7 +8456,19 @@ impl Flowable for ContainerFlowable {
                 continue;
             }
 
-            if let Some((first, second)) = child.split(content_width, remaining_height) {
+            // Once something is placed, a kept-together child moves on whole.
+            let keep_whole = !placed.is_empty()
+                && (matches!(
+                    pagination.break_inside,
+                    BreakInside::Avoid | BreakInside::AvoidPage
+                ) || child.breaks_leading_keep(content_width, remaining_height));
+            let split = if keep_whole {
+                None
+            } else {
+                child.split(content_width, remaining_height)
+            };
+            if let Some((first, second)) = split {
+                split_child = Some(child);
                 placed.push(first);
                 remaining.push(second);
                 for rest in flow_children[index + 1..].iter().cloned() {
@@ -6371,11 +8480,33 @@ impl Flowable for ContainerFlowable {
                 for rest in flow_children[index + 1..].iter().cloned() {
                     remaining.push(rest);
                 }
+                // Headings kept with the child follow it.
+                while placed.len() > 1
+                    && placed
+                        .last()
+                        .is_some_and(|last| last.pagination().break_after == BreakAfter::Avoid)
+                {
+                    if let Some(last) = placed.pop() {
+                        remaining.insert(0, last);
+                    }
+                }
                 break;
             }
         }
 
-        if placed.is_empty() || remaining.is_empty() {
+        let (min_before, min_after) = self.min_items;
+        if min_after > 1 && !remaining.is_empty() {
+            while remaining.len() < min_after && placed.len() > min_before {
+                let Some(last) = placed.pop() else {
+                    break;
+                };
+                match split_child.take() {
+                    Some(original) => remaining[0] = original,
+                    None => remaining.insert(0, last),
+                }
+            }
+        }
+        if placed.is_empty() || remaining.is_empty() || placed.len() < min_before {
             return None;
         }
 
@@ -6401,8 +8532,10 @@ impl Flowable for ContainerFlowable {
             box_sizing: self.box_sizing,
             background: self.background,
             background_paint: self.background_paint.clone(),
+            background_image: self.background_image.clone(),
             clip_path_inset: self.clip_path_inset,
             box_shadow: self.box_shadow.clone(),
+            outline: self.outline,
             paint_filter: self.paint_filter,
             backdrop_filter: self.backdrop_filter,
             mix_blend_mode: self.mix_blend_mode,
@@ -6418,6 +8551,7 @@ impl Flowable for ContainerFlowable {
                 break_after: BreakAfter::Auto,
                 ..self.pagination
             },
+            min_items: self.min_items,
             layout_cache: Arc::new(Mutex::new(None)),
         };
         let second = ContainerFlowable {
@@ -6433,8 +8567,10 @@ impl Flowable for ContainerFlowable {
             box_sizing: self.box_sizing,
             background: self.background,
             background_paint: self.background_paint.clone(),
+            background_image: self.background_image.clone(),
             clip_path_inset: self.clip_path_inset,
             box_shadow: self.box_shadow.clone(),
+            outline: self.outline,
             paint_filter: self.paint_filter,
             backdrop_filter: self.backdrop_filter,
             mix_blend_mode: self.mix_blend_mode,
@@ -6449,12 +8585,33 @@ impl Flowable for ContainerFlowable {
                 break_before: BreakBefore::Auto,
                 ..self.pagination
             },
+            min_items: self.min_items,
             layout_cache: Arc::new(Mutex::new(None)),
         };
 
         Some((Box::new(first), Box::new(second)))
     }
 
+    fn breaks_leading_keep(&self, avail_width: Pt, avail_height: Pt) -> bool {
+        let (margin, border, padding, content_width, _border_box_width) =
+            self.resolve_box(avail_width);
+        let mut remaining_height = avail_height - margin.top - border.top - padding.top;
+        // Walk the leading chain of children kept with their successor.
+        for child in self.children.iter().filter(|child| !child.out_of_flow()) {
+            if remaining_height <= Pt::ZERO
+                || !can_start_in(child.as_ref(), content_width, remaining_height)
+            {
+                return true;
+            }
+            let height = child.wrap(content_width, remaining_height).height;
+            if height > remaining_height || child.pagination().break_after != BreakAfter::Avoid {
+                return false;
+            }
+            remaining_height -= height;
+        }
+        false
+    }
+
     fn draw(&self, canvas: &mut Canvas, x: Pt, y: Pt, avail_width: Pt, avail_height: Pt) {
         let tagged = self.tag_role.as_ref().map(|role| {
             canvas.begin_tag(role.as_ref(), None, None, None, None, true);
@@ -6470,32 +8627,26 @@ impl Flowable for ContainerFlowable {
 
         let border_box_x = x + margin.left;
         let border_box_y = y + margin.top;
-        let transformed = self.has_transforms();
         let blend_mode_applied = self.mix_blend_mode != MixBlendMode::Normal;
         if blend_mode_applied {
             canvas.save_state();
             canvas.set_blend_mode(self.mix_blend_mode);
         }
-        if transformed {
-            // CSS transforms apply around transform-origin (default: center center) and do
-            // not participate in wrap/split geometry in this phase.
-            let origin_dx = self.transform_origin.x.resolve_width(
-                border_box_width,
-                self.font_size,
-                self.root_font_size,
-            );
-            let origin_dy = self.transform_origin.y.resolve_height(
-                border_box_height,
-                self.font_size,
-                self.root_font_size,
-            );
-            let origin_x = border_box_x + origin_dx;
-            let origin_y = border_box_y + origin_dy;
-            canvas.save_state();
-            canvas.translate(-origin_x, -origin_y);
-            self.apply_transforms(canvas, border_box_width, border_box_height);
-            canvas.translate(origin_x, origin_y);
-        }
+        // CSS transforms apply around transform-origin (default: center center) and do
+        // not participate in wrap/split geometry in this phase.
+        let transformed = begin_css_transform(
+            canvas,
+            &self.transforms,
+            self.transform_origin,
+            Rect {
+                x: border_box_x,
+                y: border_box_y,
+                width: border_box_width,
+                height: border_box_height,
+            },
+            self.font_size,
+            self.root_font_size,
+        );
         let radius = Self::uniform_radius(self.border_radius.resolve(
             border_box_width,
             self.font_size,
@@ -6569,6 +8720,32 @@ impl Flowable for ContainerFlowable {
             }
         }
 
+        let padding_rect = Rect {
+            x: border_box_x + border.left,
+            y: border_box_y + border.top,
+            width: (border_box_width - border.left - border.right).max(Pt::ZERO),
+            height: (border_box_height - border.top - border.bottom).max(Pt::ZERO),
+        };
+        if let Some(image) = &self.background_image {
+            image.draw(canvas, padding_rect, self.font_size, self.root_font_size);
+        }
+
+        if let Some(shadow) = self.box_shadow.as_ref().filter(|shadow| shadow.inset) {
+            let mut resolved = shadow.resolve(
+                padding_rect.width,
+                padding_rect.height,
+                self.font_size,
+                self.root_font_size,
+            );
+            resolved.color = self.apply_paint_filter_color(resolved.color);
+            draw_inset_shadow(
+                canvas,
+                padding_rect,
+                (radius - border.top).max(Pt::ZERO),
+                resolved,
+            );
+        }
+
         if Self::has_border(border) {
             let uniform_width = border.top == border.right
                 && border.top == border.bottom
@@ -6651,6 +8828,14 @@ impl Flowable for ContainerFlowable {
             false
         };
 
+        // Transformed, blended and clipped boxes keep z-indexed descendants inside them;
+        // otherwise those paint in the nearest enclosing stacking context.
+        let stacking_context =
+            transformed || blend_mode_applied || clip_path_applied || self.overflow_hidden;
+        if stacking_context {
+            canvas.begin_stacking_context();
+        }
+
         let mut out_of_flow_neg: Vec<(i32, usize, Pt, Pt, &Box<dyn Flowable>)> = Vec::new();
         let mut out_of_flow_zero: Vec<(usize, Pt, Pt, &Box<dyn Flowable>)> = Vec::new();
         let mut out_of_flow_pos: Vec<(i32, usize, Pt, Pt, &Box<dyn Flowable>)> = Vec::new();
@@ -6706,96 +8891,827 @@ impl Flowable for ContainerFlowable {
             cursor_y = cursor_y + size.height;
         }
 
-        if !out_of_flow_zero.is_empty() {
-            out_of_flow_zero.sort_by(|a, b| a.0.cmp(&b.0));
-            for (_, static_x, static_y, child) in out_of_flow_zero {
-                child.draw(canvas, static_x, static_y, padding_box_w, padding_box_h);
+        if !out_of_flow_zero.is_empty() {
+            out_of_flow_zero.sort_by(|a, b| a.0.cmp(&b.0));
+            for (_, static_x, static_y, child) in out_of_flow_zero {
+                child.draw(canvas, static_x, static_y, padding_box_w, padding_box_h);
+            }
+        }
+
+        if !out_of_flow_pos.is_empty() {
+            out_of_flow_pos.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+            for (_, _, static_x, static_y, child) in out_of_flow_pos {
+                child.draw(canvas, static_x, static_y, padding_box_w, padding_box_h);
+            }
+        }
+
+        if stacking_context {
+            canvas.end_stacking_context();
+        }
+
+        if pushed_abs_cb {
+            canvas.pop_abs_containing_block();
+        }
+
+        if self.overflow_hidden {
+            canvas.restore_state();
+        }
+        if let Some(outline) = &self.outline {
+            let border_box = Rect {
+                x: border_box_x,
+                y: border_box_y,
+                width: border_box_width,
+                height: border_box_height,
+            };
+            self.draw_outline(canvas, border_box, radius, outline);
+        }
+        if clip_path_applied {
+            canvas.restore_state();
+        }
+        if transformed {
+            canvas.restore_state();
+        }
+        if blend_mode_applied {
+            canvas.restore_state();
+        }
+        if tagged.is_some() {
+            canvas.end_tag();
+        }
+    }
+
+    fn pagination(&self) -> Pagination {
+        self.pagination
+    }
+
+    // A container starts on the page its first in-flow child asks for.
+    fn page_name(&self) -> Option<String> {
+        self.children
+            .iter()
+            .find(|child| !child.out_of_flow())
+            .and_then(|child| child.page_name())
+    }
+
+    fn wrap_around_float(
+        &self,
+        avail_width: Pt,
+        exclusion: FloatExclusion,
+    ) -> Option<Box<dyn Flowable>> {
+        let (margin, border, padding, content_width, _border_box_width) =
+            self.resolve_box(avail_width);
+        let side_inset = match exclusion.side {
+            FloatSide::Left => margin.left + border.left + padding.left,
+            FloatSide::Right => margin.right + border.right + padding.right,
+        };
+        let width = exclusion.width - side_inset;
+        let mut cursor = margin.top + border.top + padding.top;
+        if width <= Pt::ZERO || cursor >= exclusion.height {
+            return Some(Box::new(self.clone()));
+        }
+        let mut children = Vec::with_capacity(self.children.len());
+        for child in &self.children {
+            if child.out_of_flow() || cursor >= exclusion.height {
+                children.push(child.clone());
+                continue;
+            }
+            let wrapped = child.wrap_around_float(
+                content_width,
+                FloatExclusion {
+                    side: exclusion.side,
+                    width,
+                    height: exclusion.height - cursor,
+                },
+            )?;
+            cursor += wrapped.wrap(content_width, huge_pt()).height;
+            children.push(wrapped);
+        }
+        let mut out = self.clone();
+        out.children = children;
+        out.layout_cache = Arc::new(Mutex::new(None));
+        Some(Box::new(out))
+    }
+}
+
+#[derive(Clone)]
+pub struct AbsolutePositionedFlowable {
+    child: Box<dyn Flowable>,
+    left: LengthSpec,
+    top: LengthSpec,
+    right: LengthSpec,
+    bottom: LengthSpec,
+    width_spec: LengthSpec,
+    height_spec: LengthSpec,
+    z_index: i32,
+    font_size: Pt,
+    root_font_size: Pt,
+    pagination: Pagination,
+    fixed_positioned: bool,
+    // Content past the page bottom continues at the same position on the next page instead
+    // of being clipped.
+    continue_overflow: bool,
+}
+
+#[derive(Clone)]
+pub struct RelativePositionedFlowable {
+    child: Box<dyn Flowable>,
+    left: LengthSpec,
+    top: LengthSpec,
+    right: LengthSpec,
+    bottom: LengthSpec,
+    font_size: Pt,
+    root_font_size: Pt,
+    z_index: i32,
+    pagination: Pagination,
+}
+
+// Metadata key carrying the named page an element starts (see `Flowable::page_name`).
+pub(crate) const META_PAGE_NAME_KEY: &str = "fb.page_name";
+// Metadata key carrying the frame an element is threaded into (see `Flowable::target_frame`).
+pub(crate) const META_FRAME_KEY: &str = "fb.frame";
+// Metadata key carrying an element's computed style as JSON, attached for `render_to_layout`.
+// A flowable carrying it also records its box under `META_LAYOUT_BOX_KEY` when drawn.
+pub(crate) const META_LAYOUT_STYLE_KEY: &str = "fb.layout.style";
+pub(crate) const META_LAYOUT_BOX_KEY: &str = "fb.layout.box";
+
+#[derive(Clone)]
+pub struct MetaFlowable {
+    child: Box<dyn Flowable>,
+    metadata: Arc<Vec<(String, String)>>,
+}
+
+impl MetaFlowable {
+    pub fn new(child: Box<dyn Flowable>, metadata: Vec<(String, String)>) -> Self {
+        Self {
+            child,
+            metadata: Arc::new(metadata),
+        }
+    }
+}
+
+impl Flowable for MetaFlowable {
+    fn wrap(&self, avail_width: Pt, avail_height: Pt) -> Size {
+        let mut size = self.child.wrap(avail_width, avail_height);
+        if !self.metadata.is_empty() && size.height <= Pt::ZERO {
+            size.height = Pt::from_f32(0.01);
+        }
+        size
+    }
+
+    fn split(
+        &self,
+        avail_width: Pt,
+        avail_height: Pt,
+    ) -> Option<(Box<dyn Flowable>, Box<dyn Flowable>)> {
+        let (first, second) = self.child.split(avail_width, avail_height)?;
+        let meta = self.metadata.as_ref().clone();
+        Some((
+            Box::new(Self::new(first, meta.clone())) as Box<dyn Flowable>,
+            Box::new(Self::new(second, meta)) as Box<dyn Flowable>,
+        ))
+    }
+
+    fn draw(&self, canvas: &mut Canvas, x: Pt, y: Pt, avail_width: Pt, avail_height: Pt) {
+        canvas.meta(META_DIAGNOSTIC_SCOPE_BEGIN_KEY, "flowable");
+        for (k, v) in self.metadata.iter() {
+            canvas.meta(k.clone(), v.clone());
+        }
+        if self
+            .metadata
+            .iter()
+            .any(|(k, _)| k == META_LAYOUT_STYLE_KEY)
+        {
+            let size = self.child.wrap(avail_width, avail_height);
+            canvas.meta(
+                META_LAYOUT_BOX_KEY,
+                format!(
+                    "{},{},{},{}",
+                    x.to_milli_i64(),
+                    y.to_milli_i64(),
+                    size.width.to_milli_i64(),
+                    size.height.to_milli_i64()
+                ),
+            );
+        }
+        self.child.draw(canvas, x, y, avail_width, avail_height);
+        canvas.meta(META_DIAGNOSTIC_SCOPE_END_KEY, "flowable");
+    }
+
+    fn intrinsic_width(&self) -> Option<Pt> {
+        self.child.intrinsic_width()
+    }
+
+    fn min_content_width(&self) -> Option<Pt> {
+        self.child.min_content_width()
+    }
+
+    fn wrap_around_float(
+        &self,
+        avail_width: Pt,
+        exclusion: FloatExclusion,
+    ) -> Option<Box<dyn Flowable>> {
+        let child = self.child.wrap_around_float(avail_width, exclusion)?;
+        Some(Box::new(Self {
+            child,
+            metadata: self.metadata.clone(),
+        }))
+    }
+
+    fn out_of_flow(&self) -> bool {
+        self.child.out_of_flow()
+    }
+
+    fn z_index(&self) -> i32 {
+        self.child.z_index()
+    }
+
+    fn pagination(&self) -> Pagination {
+        self.child.pagination()
+    }
+
+    fn breaks_leading_keep(&self, avail_width: Pt, avail_height: Pt) -> bool {
+        self.child.breaks_leading_keep(avail_width, avail_height)
+    }
+
+    fn page_name(&self) -> Option<String> {
+        self.metadata
+            .iter()
+            .find(|(key, _)| key == META_PAGE_NAME_KEY)
+            .map(|(_, value)| value.clone())
+            .or_else(|| self.child.page_name())
+    }
+
+    fn target_frame(&self) -> Option<String> {
+        self.metadata
+            .iter()
+            .find(|(key, _)| key == META_FRAME_KEY)
+            .map(|(_, value)| value.clone())
+            .or_else(|| self.child.target_frame())
+    }
+
+    fn is_fixed_positioned(&self) -> bool {
+        self.child.is_fixed_positioned()
+    }
+
+    fn diagnostic_metadata(&self) -> Vec<(String, String)> {
+        let mut out = self.child.diagnostic_metadata();
+        for (key, value) in self.metadata.iter() {
+            if let Some(existing) = out.iter_mut().find(|(k, _)| k == key) {
+                existing.1 = value.clone();
+            } else {
+                out.push((key.clone(), value.clone()));
+            }
+        }
+        out
+    }
+}
+
+// Named position recorded by LinkFlowable. Exported anchors are also published as PDF named
+// destinations so other tools can deep-link into the document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamedAnchor {
+    pub name: String,
+    pub exported: bool,
+}
+
+impl NamedAnchor {
+    pub fn internal(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            exported: false,
+        }
+    }
+
+    pub fn exported(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            exported: true,
+        }
+    }
+}
+
+// Hyperlink wrapper: records named anchors at the child's origin and a link annotation over
+// the child's drawn box. Anchors stay with the first fragment when the child splits.
+#[derive(Clone)]
+pub struct LinkFlowable {
+    child: Box<dyn Flowable>,
+    anchors: Vec<NamedAnchor>,
+    target: Option<LinkTarget>,
+}
+
+impl LinkFlowable {
+    pub fn new(
+        child: Box<dyn Flowable>,
+        anchors: Vec<NamedAnchor>,
+        target: Option<LinkTarget>,
+    ) -> Self {
+        Self {
+            child,
+            anchors,
+            target,
+        }
+    }
+}
+
+impl Flowable for LinkFlowable {
+    fn wrap(&self, avail_width: Pt, avail_height: Pt) -> Size {
+        self.child.wrap(avail_width, avail_height)
+    }
+
+    fn split(
+        &self,
+        avail_width: Pt,
+        avail_height: Pt,
+    ) -> Option<(Box<dyn Flowable>, Box<dyn Flowable>)> {
+        let (first, second) = self.child.split(avail_width, avail_height)?;
+        Some((
+            Box::new(Self::new(first, self.anchors.clone(), self.target.clone()))
+                as Box<dyn Flowable>,
+            Box::new(Self::new(second, Vec::new(), self.target.clone())) as Box<dyn Flowable>,
+        ))
+    }
+
+    fn draw(&self, canvas: &mut Canvas, x: Pt, y: Pt, avail_width: Pt, avail_height: Pt) {
+        for anchor in &self.anchors {
+            if anchor.exported {
+                canvas.exported_destination(anchor.name.clone(), x, y);
+            } else {
+                canvas.named_destination(anchor.name.clone(), x, y);
+            }
+        }
+        self.child.draw(canvas, x, y, avail_width, avail_height);
+        if let Some(target) = &self.target {
+            let size = self.child.wrap(avail_width, avail_height);
+            canvas.link_annotation(x, y, size.width, size.height, target.clone());
+        }
+    }
+
+    fn intrinsic_width(&self) -> Option<Pt> {
+        self.child.intrinsic_width()
+    }
+
+    fn min_content_width(&self) -> Option<Pt> {
+        self.child.min_content_width()
+    }
+
+    fn wrap_around_float(
+        &self,
+        avail_width: Pt,
+        exclusion: FloatExclusion,
+    ) -> Option<Box<dyn Flowable>> {
+        let child = self.child.wrap_around_float(avail_width, exclusion)?;
+        Some(Box::new(Self::new(
+            child,
+            self.anchors.clone(),
+            self.target.clone(),
+        )))
+    }
+
+    fn out_of_flow(&self) -> bool {
+        self.child.out_of_flow()
+    }
+
+    fn z_index(&self) -> i32 {
+        self.child.z_index()
+    }
+
+    fn pagination(&self) -> Pagination {
+        self.child.pagination()
+    }
+
+    fn breaks_leading_keep(&self, avail_width: Pt, avail_height: Pt) -> bool {
+        self.child.breaks_leading_keep(avail_width, avail_height)
+    }
+
+    fn page_name(&self) -> Option<String> {
+        self.child.page_name()
+    }
+
+    fn target_frame(&self) -> Option<String> {
+        self.child.target_frame()
+    }
+
+    fn prefers_containing_block_draw_space(&self) -> bool {
+        self.child.prefers_containing_block_draw_space()
+    }
+
+    fn is_fixed_positioned(&self) -> bool {
+        self.child.is_fixed_positioned()
+    }
+
+    fn diagnostic_metadata(&self) -> Vec<(String, String)> {
+        self.child.diagnostic_metadata()
+    }
+}
+
+// Outline entry wrapper for headings. The entry is recorded at the child's origin and only
+// once: continuation fragments after a split carry no bookmark.
+#[derive(Clone)]
+pub struct BookmarkFlowable {
+    child: Box<dyn Flowable>,
+    bookmark: Option<(u8, String)>,
+}
+
+impl BookmarkFlowable {
+    pub fn new(child: Box<dyn Flowable>, level: u8, title: impl Into<String>) -> Self {
+        Self {
+            child,
+            bookmark: Some((level, title.into())),
+        }
+    }
+}
+
+impl Flowable for BookmarkFlowable {
+    fn wrap(&self, avail_width: Pt, avail_height: Pt) -> Size {
+        self.child.wrap(avail_width, avail_height)
+    }
+
+    fn split(
+        &self,
+        avail_width: Pt,
+        avail_height: Pt,
+    ) -> Option<(Box<dyn Flowable>, Box<dyn Flowable>)> {
+        let (first, second) = self.child.split(avail_width, avail_height)?;
+        Some((
+            Box::new(Self {
+                child: first,
+                bookmark: self.bookmark.clone(),
+            }) as Box<dyn Flowable>,
+            Box::new(Self {
+                child: second,
+                bookmark: None,
+            }) as Box<dyn Flowable>,
+        ))
+    }
+
+    fn draw(&self, canvas: &mut Canvas, x: Pt, y: Pt, avail_width: Pt, avail_height: Pt) {
+        if let Some((level, title)) = &self.bookmark {
+            canvas.bookmark(*level, title.clone(), x, y);
+        }
+        self.child.draw(canvas, x, y, avail_width, avail_height);
+    }
+
+    fn intrinsic_width(&self) -> Option<Pt> {
+        self.child.intrinsic_width()
+    }
+
+    fn min_content_width(&self) -> Option<Pt> {
+        self.child.min_content_width()
+    }
+
+    fn wrap_around_float(
+        &self,
+        avail_width: Pt,
+        exclusion: FloatExclusion,
+    ) -> Option<Box<dyn Flowable>> {
+        let child = self.child.wrap_around_float(avail_width, exclusion)?;
+        Some(Box::new(Self {
+            child,
+            bookmark: self.bookmark.clone(),
+        }))
+    }
+
+    fn out_of_flow(&self) -> bool {
+        self.child.out_of_flow()
+    }
+
+    fn z_index(&self) -> i32 {
+        self.child.z_index()
+    }
+
+    fn pagination(&self) -> Pagination {
+        self.child.pagination()
+    }
+
+    fn breaks_leading_keep(&self, avail_width: Pt, avail_height: Pt) -> bool {
+        self.child.breaks_leading_keep(avail_width, avail_height)
+    }
+
+    fn page_name(&self) -> Option<String> {
+        self.child.page_name()
+    }
+
+    fn target_frame(&self) -> Option<String> {
+        self.child.target_frame()
+    }
+
+    fn prefers_containing_block_draw_space(&self) -> bool {
+        self.child.prefers_containing_block_draw_space()
+    }
+
+    fn is_fixed_positioned(&self) -> bool {
+        self.child.is_fixed_positioned()
+    }
+
+    fn diagnostic_metadata(&self) -> Vec<(String, String)> {
+        self.child.diagnostic_metadata()
+    }
+}
+
+// Signature field wrapper: records an unsigned signature field over the child's drawn box.
+// The field stays with the first fragment when the child splits.
+#[derive(Clone)]
+pub struct SignatureFieldFlowable {
+    child: Box<dyn Flowable>,
+    name: Option<String>,
+}
+
+impl SignatureFieldFlowable {
+    pub fn new(child: Box<dyn Flowable>, name: impl Into<String>) -> Self {
+        Self {
+            child,
+            name: Some(name.into()),
+        }
+    }
+}
+
+impl Flowable for SignatureFieldFlowable {
+    fn wrap(&self, avail_width: Pt, avail_height: Pt) -> Size {
+        self.child.wrap(avail_width, avail_height)
+    }
+
+    fn split(
+        &self,
+        avail_width: Pt,
+        avail_height: Pt,
+    ) -> Option<(Box<dyn Flowable>, Box<dyn Flowable>)> {
+        let (first, second) = self.child.split(avail_width, avail_height)?;
+        Some((
+            Box::new(Self {
+                child: first,
+                name: self.name.clone(),
+            }) as Box<dyn Flowable>,
+            Box::new(Self {
+                child: second,
+                name: None,
+            }) as Box<dyn Flowable>,
+        ))
+    }
+
+    fn draw(&self, canvas: &mut Canvas, x: Pt, y: Pt, avail_width: Pt, avail_height: Pt) {
+        self.child.draw(canvas, x, y, avail_width, avail_height);
+        if let Some(name) = &self.name {
+            let size = self.child.wrap(avail_width, avail_height);
+            canvas.signature_field(name.clone(), x, y, size.width, size.height);
+        }
+    }
+
+    fn intrinsic_width(&self) -> Option<Pt> {
+        self.child.intrinsic_width()
+    }
+
+    fn min_content_width(&self) -> Option<Pt> {
+        self.child.min_content_width()
+    }
+
+    fn wrap_around_float(
+        &self,
+        avail_width: Pt,
+        exclusion: FloatExclusion,
+    ) -> Option<Box<dyn Flowable>> {
+        let child = self.child.wrap_around_float(avail_width, exclusion)?;
+        Some(Box::new(Self {
+            child,
+            name: self.name.clone(),
+        }))
+    }
+
+    fn out_of_flow(&self) -> bool {
+        self.child.out_of_flow()
+    }
+
+    fn z_index(&self) -> i32 {
+        self.child.z_index()
+    }
+
+    fn pagination(&self) -> Pagination {
+        self.child.pagination()
+    }
+
+    fn breaks_leading_keep(&self, avail_width: Pt, avail_height: Pt) -> bool {
+        self.child.breaks_leading_keep(avail_width, avail_height)
+    }
+
+    fn page_name(&self) -> Option<String> {
+        self.child.page_name()
+    }
+
+    fn target_frame(&self) -> Option<String> {
+        self.child.target_frame()
+    }
+
+    fn prefers_containing_block_draw_space(&self) -> bool {
+        self.child.prefers_containing_block_draw_space()
+    }
+
+    fn is_fixed_positioned(&self) -> bool {
+        self.child.is_fixed_positioned()
+    }
+
+    fn diagnostic_metadata(&self) -> Vec<(String, String)> {
+        self.child.diagnostic_metadata()
+    }
+}
+
+// Footnote body lifted out of the text flow. In flow it is only a hairline carrier placed
+// after the referencing text; drawing it hands the body to the page layout, which moves it
+// to the footnote area at the bottom of the current frame.
+#[derive(Clone)]
+pub struct FootnoteFlowable {
+    body: Box<dyn Flowable>,
+}
+
+impl FootnoteFlowable {
+    pub fn new(body: Box<dyn Flowable>) -> Self {
+        Self { body }
+    }
+}
+
+impl Flowable for FootnoteFlowable {
+    fn wrap(&self, _avail_width: Pt, _avail_height: Pt) -> Size {
+        Size {
+            width: Pt::ZERO,
+            height: Pt::from_f32(0.01),
+        }
+    }
+
+    fn split(
+        &self,
+        _avail_width: Pt,
+        _avail_height: Pt,
+    ) -> Option<(Box<dyn Flowable>, Box<dyn Flowable>)> {
+        None
+    }
+
+    fn draw(&self, canvas: &mut Canvas, _x: Pt, _y: Pt, _avail_width: Pt, _avail_height: Pt) {
+        canvas.defer_footnote(self.body.clone());
+    }
+}
+
+// CSS multi-column container: children flow through `count` equal columns. When everything
+// fits on the page the column height is balanced; otherwise the columns are filled to the
+// available height and the rest continues in a new column set on the next page.
+#[derive(Clone)]
+pub struct MultiColumnFlowable {
+    children: Vec<Box<dyn Flowable>>,
+    count: usize,
+    gap: LengthSpec,
+    font_size: Pt,
+    root_font_size: Pt,
+    // Set on fragments that continue on a later page: fill to this height instead of balancing.
+    fill_height: Option<Pt>,
+}
+
+impl MultiColumnFlowable {
+    pub fn new_pt(
+        children: Vec<Box<dyn Flowable>>,
+        count: usize,
+        gap: LengthSpec,
+        font_size: Pt,
+        root_font_size: Pt,
+    ) -> Self {
+        Self {
+            children,
+            count: count.max(1),
+            gap,
+            font_size,
+            root_font_size,
+            fill_height: None,
+        }
+    }
+
+    fn column_gap(&self, avail_width: Pt) -> Pt {
+        let gap = self
+            .gap
+            .resolve_width(avail_width, self.font_size, self.root_font_size);
+        // `column-gap: normal` resolves to zero in the style engine; multicol uses 1em.
+        if gap > Pt::ZERO { gap } else { self.font_size }
+    }
+
+    fn column_width(&self, avail_width: Pt) -> Pt {
+        let gaps = self.column_gap(avail_width) * (self.count as i32 - 1);
+        ((avail_width - gaps) / self.count as i32).max(Pt::ZERO)
+    }
+
+    fn fill(&self, width: Pt, height: Pt) -> ColumnFill {
+        ColumnSet::new(self.count, width, height).fill(self.children.clone())
+    }
+
+    // Smallest column height (within half a point) that holds every child, or `None` when the
+    // content does not fit in `max_height`.
+    fn balanced_height(&self, width: Pt, max_height: Pt) -> Option<Pt> {
+        let fits = |height: Pt| {
+            let fill = self.fill(width, height);
+            fill.remaining.is_empty() && !fill.overfull
+        };
+        let stacked = self.children.iter().fold(Pt::ZERO, |acc, child| {
+            acc + child.wrap(width, huge_pt()).height
+        });
+        let mut high = stacked.min(max_height);
+        if !fits(high) {
+            if high < max_height && fits(max_height) {
+                high = max_height;
+            } else {
+                return None;
             }
         }
-
-        if !out_of_flow_pos.is_empty() {
-            out_of_flow_pos.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
-            for (_, _, static_x, static_y, child) in out_of_flow_pos {
-                child.draw(canvas, static_x, static_y, padding_box_w, padding_box_h);
+        let mut low = Pt::ZERO;
+        while high - low > Pt::from_f32(0.5) {
+            let mid = (low + high) / 2;
+            if fits(mid) {
+                high = mid;
+            } else {
+                low = mid;
             }
         }
+        Some(high)
+    }
 
-        if pushed_abs_cb {
-            canvas.pop_abs_containing_block();
-        }
+    fn column_height(&self, width: Pt, avail_height: Pt) -> Pt {
+        self.fill_height
+            .or_else(|| self.balanced_height(width, avail_height))
+            .unwrap_or(avail_height)
+    }
+}
 
-        if self.overflow_hidden {
-            canvas.restore_state();
-        }
-        if clip_path_applied {
-            canvas.restore_state();
-        }
-        if transformed {
-            canvas.restore_state();
+impl Flowable for MultiColumnFlowable {
+    fn wrap(&self, avail_width: Pt, avail_height: Pt) -> Size {
+        let width = self.column_width(avail_width);
+        let height = match self.fill_height {
+            Some(height) => height,
+            None => self
+                .balanced_height(width, avail_height)
+                .unwrap_or_else(|| {
+                    // Report more than the available height so the frame splits the columns.
+                    avail_height + Pt::from_f32(1.0)
+                }),
+        };
+        Size {
+            width: avail_width,
+            height,
         }
-        if blend_mode_applied {
-            canvas.restore_state();
+    }
+
+    fn split(
+        &self,
+        avail_width: Pt,
+        avail_height: Pt,
+    ) -> Option<(Box<dyn Flowable>, Box<dyn Flowable>)> {
+        if avail_height <= Pt::ZERO {
+            return None;
         }
-        if tagged.is_some() {
-            canvas.end_tag();
+        let width = self.column_width(avail_width);
+        let fill = self.fill(width, avail_height);
+        if fill.placements.is_empty() || fill.remaining.is_empty() {
+            return None;
         }
+        let mut first = self.clone();
+        first.children = fill
+            .placements
+            .into_iter()
+            .map(|placement| placement.flowable)
+            .collect();
+        first.fill_height = Some(avail_height);
+        let mut second = self.clone();
+        second.children = fill.remaining.into_iter().collect();
+        second.fill_height = None;
+        Some((
+            Box::new(first) as Box<dyn Flowable>,
+            Box::new(second) as Box<dyn Flowable>,
+        ))
     }
 
-    fn pagination(&self) -> Pagination {
-        self.pagination
+    fn draw(&self, canvas: &mut Canvas, x: Pt, y: Pt, avail_width: Pt, avail_height: Pt) {
+        let width = self.column_width(avail_width);
+        let step = width + self.column_gap(avail_width);
+        let height = self.column_height(width, avail_height);
+        for placement in self.fill(width, height).placements {
+            placement.flowable.draw(
+                canvas,
+                x + step * placement.column as i32,
+                y + placement.y,
+                width,
+                placement.height,
+            );
+        }
     }
 }
 
+// `float: left | right` box. On its own it lays out like its child; `FloatAreaFlowable` moves
+// it to its side and flows the content that follows around it.
 #[derive(Clone)]
-pub struct AbsolutePositionedFlowable {
-    child: Box<dyn Flowable>,
-    left: LengthSpec,
-    top: LengthSpec,
-    right: LengthSpec,
-    bottom: LengthSpec,
-    width_spec: LengthSpec,
-    height_spec: LengthSpec,
-    z_index: i32,
-    font_size: Pt,
-    root_font_size: Pt,
-    pagination: Pagination,
-    fixed_positioned: bool,
-}
-
-#[derive(Clone)]
-pub struct RelativePositionedFlowable {
-    child: Box<dyn Flowable>,
-    left: LengthSpec,
-    top: LengthSpec,
-    right: LengthSpec,
-    bottom: LengthSpec,
-    font_size: Pt,
-    root_font_size: Pt,
-    pagination: Pagination,
-}
-
-#[derive(Clone)]
-pub struct MetaFlowable {
+pub struct FloatFlowable {
     child: Box<dyn Flowable>,
-    metadata: Arc<Vec<(String, String)>>,
+    side: FloatSide,
 }
 
-impl MetaFlowable {
-    pub fn new(child: Box<dyn Flowable>, metadata: Vec<(String, String)>) -> Self {
-        Self {
-            child,
-            metadata: Arc::new(metadata),
-        }
+impl FloatFlowable {
+    pub fn new(child: Box<dyn Flowable>, side: FloatSide) -> Self {
+        Self { child, side }
     }
 }
 
-impl Flowable for MetaFlowable {
+impl Flowable for FloatFlowable {
     fn wrap(&self, avail_width: Pt, avail_height: Pt) -> Size {
-        let mut size = self.child.wrap(avail_width, avail_height);
-        if !self.metadata.is_empty() && size.height <= Pt::ZERO {
-            size.height = Pt::from_f32(0.01);
-        }
-        size
+        self.child.wrap(avail_width, avail_height)
     }
 
     fn split(
@@ -6804,52 +9720,208 @@ impl Flowable for MetaFlowable {
         avail_height: Pt,
     ) -> Option<(Box<dyn Flowable>, Box<dyn Flowable>)> {
         let (first, second) = self.child.split(avail_width, avail_height)?;
-        let meta = self.metadata.as_ref().clone();
         Some((
-            Box::new(Self::new(first, meta.clone())) as Box<dyn Flowable>,
-            Box::new(Self::new(second, meta)) as Box<dyn Flowable>,
+            Box::new(Self::new(first, self.side)) as Box<dyn Flowable>,
+            Box::new(Self::new(second, self.side)) as Box<dyn Flowable>,
         ))
     }
 
     fn draw(&self, canvas: &mut Canvas, x: Pt, y: Pt, avail_width: Pt, avail_height: Pt) {
-        canvas.meta(META_DIAGNOSTIC_SCOPE_BEGIN_KEY, "flowable");
-        for (k, v) in self.metadata.iter() {
-            canvas.meta(k.clone(), v.clone());
-        }
         self.child.draw(canvas, x, y, avail_width, avail_height);
-        canvas.meta(META_DIAGNOSTIC_SCOPE_END_KEY, "flowable");
     }
 
     fn intrinsic_width(&self) -> Option<Pt> {
         self.child.intrinsic_width()
     }
 
-    fn out_of_flow(&self) -> bool {
-        self.child.out_of_flow()
-    }
-
-    fn z_index(&self) -> i32 {
-        self.child.z_index()
+    fn min_content_width(&self) -> Option<Pt> {
+        self.child.min_content_width()
     }
 
     fn pagination(&self) -> Pagination {
         self.child.pagination()
     }
 
-    fn is_fixed_positioned(&self) -> bool {
-        self.child.is_fixed_positioned()
+    fn float_side(&self) -> Option<FloatSide> {
+        Some(self.side)
     }
 
     fn diagnostic_metadata(&self) -> Vec<(String, String)> {
-        let mut out = self.child.diagnostic_metadata();
-        for (key, value) in self.metadata.iter() {
-            if let Some(existing) = out.iter_mut().find(|(k, _)| k == key) {
-                existing.1 = value.clone();
-            } else {
-                out.push((key.clone(), value.clone()));
+        self.child.diagnostic_metadata()
+    }
+}
+
+struct FloatAreaPlacement {
+    flowable: Box<dyn Flowable>,
+    x: Pt,
+    y: Pt,
+    width: Pt,
+    height: Pt,
+}
+
+struct FloatAreaLayout {
+    float_width: Pt,
+    float_height: Pt,
+    placements: Vec<FloatAreaPlacement>,
+    height: Pt,
+}
+
+// A float plus the flowables that follow it in the same container. Content next to the float
+// wraps its lines around it when it can (`Flowable::wrap_around_float`); otherwise it is laid
+// out in the narrowed space beside the float, or below it when it does not fit there. The area
+// is at least as tall as the float.
+#[derive(Clone)]
+pub struct FloatAreaFlowable {
+    float: Box<dyn Flowable>,
+    side: FloatSide,
+    content: Vec<Box<dyn Flowable>>,
+}
+
+impl FloatAreaFlowable {
+    pub fn new(float: Box<dyn Flowable>, content: Vec<Box<dyn Flowable>>) -> Self {
+        let side = float.float_side().unwrap_or(FloatSide::Left);
+        Self {
+            float,
+            side,
+            content,
+        }
+    }
+
+    fn float_width(&self, avail_width: Pt) -> Pt {
+        self.float
+            .intrinsic_width()
+            .unwrap_or_else(|| self.float.wrap(avail_width, huge_pt()).width)
+            .min(avail_width)
+            .max(Pt::ZERO)
+    }
+
+    fn layout(&self, avail_width: Pt) -> FloatAreaLayout {
+        let float_width = self.float_width(avail_width);
+        let float_height = self.float.wrap(float_width, huge_pt()).height;
+        let beside_width = avail_width - float_width;
+        let mut cursor = Pt::ZERO;
+        let mut placements = Vec::with_capacity(self.content.len());
+        for child in &self.content {
+            let mut placement = FloatAreaPlacement {
+                flowable: child.clone(),
+                x: Pt::ZERO,
+                y: cursor,
+                width: avail_width,
+                height: Pt::ZERO,
+            };
+            if cursor < float_height && !child.out_of_flow() {
+                let exclusion = FloatExclusion {
+                    side: self.side,
+                    width: float_width,
+                    height: float_height - cursor,
+                };
+                if let Some(wrapped) = child.wrap_around_float(avail_width, exclusion) {
+                    placement.flowable = wrapped;
+                } else if beside_width > Pt::ZERO
+                    && child
+                        .intrinsic_width()
+                        .is_none_or(|width| width <= beside_width)
+                {
+                    placement.width = beside_width;
+                    if self.side == FloatSide::Left {
+                        placement.x = float_width;
+                    }
+                } else {
+                    cursor = float_height;
+                    placement.y = cursor;
+                }
             }
+            placement.height = placement.flowable.wrap(placement.width, huge_pt()).height;
+            cursor += placement.height;
+            placements.push(placement);
+        }
+        FloatAreaLayout {
+            float_width,
+            float_height,
+            placements,
+            height: cursor.max(float_height),
+        }
+    }
+}
+
+impl Flowable for FloatAreaFlowable {
+    fn wrap(&self, avail_width: Pt, _avail_height: Pt) -> Size {
+        Size {
+            width: avail_width,
+            height: self.layout(avail_width).height,
+        }
+    }
+
+    fn split(
+        &self,
+        avail_width: Pt,
+        avail_height: Pt,
+    ) -> Option<(Box<dyn Flowable>, Box<dyn Flowable>)> {
+        let layout = self.layout(avail_width);
+        // The float itself is never split; the whole area moves on when it does not fit.
+        if layout.height <= avail_height || layout.float_height > avail_height {
+            return None;
+        }
+        let mut placements = layout.placements.into_iter();
+        let mut placed = Vec::new();
+        let mut rest: Vec<Box<dyn Flowable>> = Vec::new();
+        for placement in placements.by_ref() {
+            if placement.y + placement.height <= avail_height {
+                placed.push(placement.flowable);
+                continue;
+            }
+            let room = avail_height - placement.y;
+            match (room > Pt::ZERO)
+                .then(|| placement.flowable.split(placement.width, room))
+                .flatten()
+            {
+                Some((first, second)) => {
+                    placed.push(first);
+                    rest.push(second);
+                }
+                None => rest.push(placement.flowable),
+            }
+            break;
+        }
+        rest.extend(placements.map(|placement| placement.flowable));
+        // Past the float the remaining content stacks normally.
+        let second: Box<dyn Flowable> = if rest.len() == 1 {
+            rest.pop()?
+        } else {
+            Box::new(ContainerFlowable::new_pt(rest, Pt::ZERO, Pt::ZERO))
+        };
+        let first = Self {
+            float: self.float.clone(),
+            side: self.side,
+            content: placed,
+        };
+        Some((Box::new(first) as Box<dyn Flowable>, second))
+    }
+
+    fn draw(&self, canvas: &mut Canvas, x: Pt, y: Pt, avail_width: Pt, _avail_height: Pt) {
+        let layout = self.layout(avail_width);
+        let float_x = match self.side {
+            FloatSide::Left => x,
+            FloatSide::Right => x + avail_width - layout.float_width,
+        };
+        self.float
+            .draw(canvas, float_x, y, layout.float_width, layout.float_height);
+        for placement in layout.placements {
+            placement.flowable.draw(
+                canvas,
+                x + placement.x,
+                y + placement.y,
+                placement.width,
+                placement.height,
+            );
+        }
+    }
+
+    fn pagination(&self) -> Pagination {
+        Pagination {
+            break_before: self.float.pagination().break_before,
+            ..Pagination::default()
         }
-        out
     }
 }
 
@@ -6905,6 +9977,7 @@ impl AbsolutePositionedFlowable {
             root_font_size,
             pagination: Pagination::default(),
             fixed_positioned: false,
+            continue_overflow: false,
         }
     }
 
@@ -6917,6 +9990,66 @@ impl AbsolutePositionedFlowable {
         self.fixed_positioned = fixed_positioned;
         self
     }
+
+    pub fn with_overflow_continuation(mut self, enabled: bool) -> Self {
+        self.continue_overflow = enabled;
+        self
+    }
+}
+
+// Draws absolutely positioned content of `height` at (`x`, `y`). Whatever runs past the page
+// bottom is split off and drawn at the same position on the next page when
+// `continue_overflow` is set; otherwise the clipped height is recorded as a known loss.
+fn draw_with_page_overflow(
+    canvas: &mut Canvas,
+    child: &dyn Flowable,
+    (x, y, width): (Pt, Pt, Pt),
+    height: Pt,
+    avail_height: Pt,
+    continue_overflow: bool,
+) {
+    let page_bottom = canvas.page_size().height;
+    if y + height <= page_bottom {
+        child.draw(canvas, x, y, width, avail_height);
+        return;
+    }
+    let room = page_bottom - y;
+    if continue_overflow && room > Pt::ZERO {
+        // The rest has to shrink, or continuing would never end.
+        let split = child
+            .split(width, room)
+            .filter(|(_, rest)| rest.wrap(width, huge_pt()).height < height);
+        if let Some((first, rest)) = split {
+            first.draw(canvas, x, y, width, room);
+            canvas.defer_abs_continuation(AbsContinuation {
+                x,
+                y,
+                width,
+                flowable: rest,
+            });
+            return;
+        }
+    }
+    child.draw(canvas, x, y, width, avail_height);
+    canvas.record_clipped_overflow((y + height - page_bottom).min(height));
+}
+
+pub(crate) fn draw_abs_continuation(canvas: &mut Canvas, continuation: AbsContinuation) {
+    let AbsContinuation {
+        x,
+        y,
+        width,
+        flowable,
+    } = continuation;
+    let height = flowable.wrap(width, huge_pt()).height;
+    draw_with_page_overflow(
+        canvas,
+        flowable.as_ref(),
+        (x, y, width),
+        height,
+        height,
+        true,
+    );
 }
 
 impl RelativePositionedFlowable {
@@ -6937,16 +10070,36 @@ impl RelativePositionedFlowable {
             bottom,
             font_size,
             root_font_size,
+            z_index: 0,
             pagination: Pagination::default(),
         }
     }
 
+    pub fn with_z_index(mut self, z_index: i32) -> Self {
+        self.z_index = z_index;
+        self
+    }
+
     pub fn with_pagination(mut self, pagination: Pagination) -> Self {
         self.pagination = pagination;
         self
     }
 }
 
+// Paints a positioned box: with a non-zero z-index it forms its own stacking context and
+// is layered by z-index within the enclosing one.
+fn draw_positioned(canvas: &mut Canvas, z_index: i32, draw: impl FnOnce(&mut Canvas)) {
+    if z_index == 0 {
+        draw(canvas);
+        return;
+    }
+    canvas.draw_z_layer(z_index, |canvas| {
+        canvas.begin_stacking_context();
+        draw(canvas);
+        canvas.end_stacking_context();
+    });
+}
+
 impl Flowable for AbsolutePositionedFlowable {
     fn wrap(&self, _avail_width: Pt, _avail_height: Pt) -> Size {
         Size {
@@ -7112,7 +10265,26 @@ impl Flowable for AbsolutePositionedFlowable {
             y
         };
 
-        self.child.draw(canvas, child_x, child_y, child_w, child_h);
+        draw_positioned(canvas, self.z_index, |canvas| {
+            if self.fixed_positioned {
+                self.child.draw(canvas, child_x, child_y, child_w, child_h);
+            } else {
+                // Content taller than its box overflows it; track how far it reaches.
+                let content_h = if explicit_h.is_some() {
+                    child_h
+                } else {
+                    size.height.max(child_h)
+                };
+                draw_with_page_overflow(
+                    canvas,
+                    self.child.as_ref(),
+                    (child_x, child_y, child_w),
+                    content_h,
+                    child_h,
+                    self.continue_overflow,
+                );
+            }
+        });
     }
 
     fn out_of_flow(&self) -> bool {
@@ -7151,6 +10323,7 @@ impl Flowable for RelativePositionedFlowable {
             bottom: self.bottom,
             font_size: self.font_size,
             root_font_size: self.root_font_size,
+            z_index: self.z_index,
             pagination: self.pagination,
         };
         let second = Self {
@@ -7161,6 +10334,7 @@ impl Flowable for RelativePositionedFlowable {
             bottom: self.bottom,
             font_size: self.font_size,
             root_font_size: self.root_font_size,
+            z_index: self.z_index,
             pagination: self.pagination,
         };
         Some((
@@ -7225,20 +10399,26 @@ impl Flowable for RelativePositionedFlowable {
             Pt::ZERO
         };
 
-        self.child
-            .draw(canvas, x + dx, y + dy, avail_width, avail_height);
+        draw_positioned(canvas, self.z_index, |canvas| {
+            self.child
+                .draw(canvas, x + dx, y + dy, avail_width, avail_height);
+        });
     }
 
     fn intrinsic_width(&self) -> Option<Pt> {
         self.child.intrinsic_width()
     }
 
+    fn min_content_width(&self) -> Option<Pt> {
+        self.child.min_content_width()
+    }
+
     fn out_of_flow(&self) -> bool {
         self.child.out_of_flow()
     }
 
     fn z_index(&self) -> i32 {
-        self.child.z_index()
+        self.z_index
     }
 
     fn pagination(&self) -> Pagination {
@@ -1,15 +1,32 @@
 use crate::error::FullBleedError;
+use crate::flowable::LineBreakCache;
+use crate::font_cache::{CachedFace, FontCache, FontLoadError};
 use crate::glyph_report::GlyphCoverageReport;
+use crate::instance::{AxisCoords, AxisRange, axis_ranges, named_instances};
+use crate::lru::LruCache;
 use crate::types::Pt;
 use rustybuzz::{Direction as HbDirection, Face as HbFace, UnicodeBuffer};
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::fs;
+use std::ops::Deref;
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use ttf_parser::GlyphId;
 
+// Faces an engine keeps from rendering (CSS `@font-face` rules and variable font instances).
+// Past this the least recently used face is dropped, and built again if a later render needs
+// it.
+const MAX_CSS_FACES: usize = 256;
+// CSS faces live outside `fonts`; their indices start here so they never collide with faces
+// registered through `&mut self` after a CSS face was added. Ids are never reused, so caches
+// keyed by font index cannot confuse a dropped face with a later one.
+const CSS_FACE_INDEX_BASE: usize = usize::MAX / 2;
+// Shaped runs kept per engine unless the builder sets `shape_cache_capacity`.
+pub(crate) const DEFAULT_SHAPE_CACHE_ENTRIES: usize = 8192;
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
-struct TextWidthKey {
+struct TextRunKey {
     font_index: usize,
     size_milli: i64,
     text: String,
@@ -17,8 +34,8 @@ struct TextWidthKey {
 
 #[derive(Debug)]
 struct TextWidthCache {
-    map: HashMap<TextWidthKey, Pt>,
-    order: VecDeque<TextWidthKey>,
+    map: HashMap<TextRunKey, Pt>,
+    order: VecDeque<TextRunKey>,
     max_entries: usize,
 }
 
@@ -31,11 +48,11 @@ impl TextWidthCache {
         }
     }
 
-    fn get(&mut self, key: &TextWidthKey) -> Option<Pt> {
+    fn get(&mut self, key: &TextRunKey) -> Option<Pt> {
         self.map.get(key).copied()
     }
 
-    fn insert(&mut self, key: TextWidthKey, value: Pt) {
+    fn insert(&mut self, key: TextRunKey, value: Pt) {
         if self.map.contains_key(&key) {
             return;
         }
@@ -51,12 +68,204 @@ impl TextWidthCache {
     }
 }
 
+// A text run shaped for PDF output: the `TJ` operator that draws it and the text each glyph
+// stands for, which goes into the font's `ToUnicode` map.
+#[derive(Debug)]
+pub(crate) struct ShapedRun {
+    pub(crate) tj: String,
+    pub(crate) glyph_map: BTreeMap<u16, String>,
+}
+
 #[derive(Debug)]
 pub(crate) struct FontRegistry {
     fonts: Vec<RegisteredFont>,
     lookup: HashMap<String, usize>,
     use_full_unicode_metrics: bool,
     text_width_cache: Mutex<TextWidthCache>,
+    // Shaped runs by font, size and text, reused across pages, records and renders.
+    shape_cache: Mutex<LruCache<TextRunKey, Arc<ShapedRun>>>,
+    // Paragraph line breaks, reused by paragraphs with the same text, style and width.
+    line_breaks: LineBreakCache,
+    // Faces added through a shared reference (CSS `@font-face` rules, variable font instances).
+    // `resolve` hands them out as `Arc`s, so dropping one from the cache never pulls it from
+    // under a render that is using it.
+    css_faces: Mutex<CssFaces>,
+    // Faces added so far, dropped ones included; name lookups can change when it grows.
+    css_face_count: AtomicUsize,
+    // Normalized name -> variable font and axis values: `fvar` named instances ("Inter
+    // SemiBold") and `register_variation_alias` names. Instanced on first use.
+    variable_aliases: HashMap<String, VariableFace>,
+    // Engine-wide fallback fonts consulted after a style's own font stack, in order; a script
+    // limits an entry to characters of that script.
+    fallback_chain: Vec<(Arc<str>, Option<FontScript>)>,
+    // Parsed fonts shared with other registries, and this registry's lookups in it.
+    font_cache: Arc<FontCache>,
+    font_cache_hits: AtomicU64,
+    font_cache_misses: AtomicU64,
+}
+
+// Script groups used to route characters to configured fallback fonts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FontScript {
+    Latin,
+    Greek,
+    Cyrillic,
+    Hebrew,
+    Arabic,
+    Indic,
+    Thai,
+    // Han, Kana, Hangul, CJK punctuation and fullwidth forms.
+    Cjk,
+    Emoji,
+    Symbols,
+    Other,
+}
+
+impl FontScript {
+    // Script of `ch`; None for script-neutral characters (spaces, digits, ASCII and general
+    // punctuation, combining marks, joiners and variation selectors), which stay in the
+    // surrounding run's font when it has them.
+    pub fn of(ch: char) -> Option<Self> {
+        let cp = ch as u32;
+        if ch.is_ascii() {
+            return ch.is_ascii_alphabetic().then_some(Self::Latin);
+        }
+        Some(match cp {
+            0x00A0..=0x00BF
+            | 0x00D7
+            | 0x00F7
+            | 0x0300..=0x036F
+            | 0x2000..=0x206F
+            | 0xFE00..=0xFE0F
+            | 0xE0100..=0xE01EF => return None,
+            0x00C0..=0x024F
+            | 0x1E00..=0x1EFF
+            | 0x2C60..=0x2C7F
+            | 0xA720..=0xA7FF
+            | 0xAB30..=0xAB6F
+            | 0xFB00..=0xFB06 => Self::Latin,
+            0x0370..=0x03FF | 0x1F00..=0x1FFF => Self::Greek,
+            0x0400..=0x052F | 0x1C80..=0x1C8F | 0x2DE0..=0x2DFF | 0xA640..=0xA69F => Self::Cyrillic,
+            0x0590..=0x05FF | 0xFB1D..=0xFB4F => Self::Hebrew,
+            0x0600..=0x06FF
+            | 0x0750..=0x077F
+            | 0x08A0..=0x08FF
+            | 0xFB50..=0xFDFF
+            | 0xFE70..=0xFEFF => Self::Arabic,
+            0x0900..=0x0DFF | 0xA8E0..=0xA8FF => Self::Indic,
+            0x0E00..=0x0E7F => Self::Thai,
+            0x1100..=0x11FF
+            | 0x2E80..=0x2FDF
+            | 0x3000..=0x33FF
+            | 0x3400..=0x4DBF
+            | 0x4E00..=0x9FFF
+            | 0xA960..=0xA97F
+            | 0xAC00..=0xD7FF
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFFEF
+            | 0x20000..=0x3FFFF => Self::Cjk,
+            0x1F000..=0x1FAFF => Self::Emoji,
+            0x2100..=0x2BFF | 0x1D400..=0x1D7FF => Self::Symbols,
+            _ => Self::Other,
+        })
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Latin => "latin",
+            Self::Greek => "greek",
+            Self::Cyrillic => "cyrillic",
+            Self::Hebrew => "hebrew",
+            Self::Arabic => "arabic",
+            Self::Indic => "indic",
+            Self::Thai => "thai",
+            Self::Cjk => "cjk",
+            Self::Emoji => "emoji",
+            Self::Symbols => "symbols",
+            Self::Other => "other",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CssFaces {
+    faces: LruCache<usize, Arc<RegisteredFont>>,
+    next_id: usize,
+    // Normalized alias -> (face id, match distance); a closer face replaces a farther one.
+    by_name: HashMap<String, (usize, u32)>,
+    // Declaration key (family, descriptors, src) -> face id, so repeated renders load once.
+    by_key: HashMap<String, usize>,
+    // Variable font instance key (font index and axis values) -> face id.
+    instances: HashMap<String, usize>,
+}
+
+#[derive(Debug, Clone)]
+struct VariableFace {
+    index: usize,
+    coords: AxisCoords,
+}
+
+impl CssFaces {
+    fn new() -> Self {
+        Self {
+            faces: LruCache::new(MAX_CSS_FACES),
+            next_id: 0,
+            by_name: HashMap::new(),
+            by_key: HashMap::new(),
+            instances: HashMap::new(),
+        }
+    }
+
+    fn live(&self, id: usize) -> Option<usize> {
+        self.faces.contains_key(&id).then_some(id)
+    }
+
+    // Adds `font` under a fresh id and forgets the names of any face it pushed out.
+    fn insert(&mut self, id: usize, font: RegisteredFont) {
+        let before = self.faces.len();
+        self.faces.insert(id, Arc::new(font));
+        if self.faces.len() > before {
+            return;
+        }
+        let faces = &self.faces;
+        self.by_name.retain(|_, (id, _)| faces.contains_key(id));
+        self.by_key.retain(|_, id| faces.contains_key(id));
+        self.instances.retain(|_, id| faces.contains_key(id));
+    }
+
+    fn next_id(&mut self) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+}
+
+// A font from `FontRegistry::resolve`: registered fonts are borrowed, faces added while
+// rendering are shared.
+#[derive(Debug, Clone)]
+pub(crate) enum FontRef<'a> {
+    Registered(&'a RegisteredFont),
+    Css(Arc<RegisteredFont>),
+}
+
+impl Deref for FontRef<'_> {
+    type Target = RegisteredFont;
+
+    fn deref(&self) -> &RegisteredFont {
+        match self {
+            FontRef::Registered(font) => font,
+            FontRef::Css(font) => font,
+        }
+    }
+}
+
+// Descriptors of a CSS `@font-face` rule that drive face selection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CssFaceDescriptor {
+    pub(crate) family: String,
+    pub(crate) weight: (u16, u16),
+    pub(crate) italic: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -95,11 +304,13 @@ pub(crate) struct RegisteredFontSourceInfo {
 #[derive(Debug)]
 pub(crate) struct RegisteredFont {
     pub(crate) name: String,
-    pub(crate) data: Vec<u8>,
-    pub(crate) metrics: FontMetrics,
+    pub(crate) data: Arc<[u8]>,
+    pub(crate) metrics: Arc<FontMetrics>,
     pub(crate) program_kind: FontProgramKind,
     #[cfg_attr(not(feature = "python"), allow(dead_code))]
     pub(crate) source: RegisteredFontSourceInfo,
+    // Variation axes; empty for static fonts and instances.
+    pub(crate) axes: Vec<AxisRange>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -162,9 +373,89 @@ impl FontRegistry {
             lookup: HashMap::new(),
             use_full_unicode_metrics: true,
             text_width_cache: Mutex::new(TextWidthCache::new(20_000)),
+            shape_cache: Mutex::new(LruCache::new(DEFAULT_SHAPE_CACHE_ENTRIES)),
+            line_breaks: LineBreakCache::new(),
+            css_faces: Mutex::new(CssFaces::new()),
+            css_face_count: AtomicUsize::new(0),
+            variable_aliases: HashMap::new(),
+            fallback_chain: Vec::new(),
+            font_cache: FontCache::global(),
+            font_cache_hits: AtomicU64::new(0),
+            font_cache_misses: AtomicU64::new(0),
         }
     }
 
+    pub(crate) fn set_font_cache(&mut self, cache: Arc<FontCache>) {
+        self.font_cache = cache;
+    }
+
+    // Hits and misses of this registry's font loads in the shared cache.
+    pub(crate) fn font_cache_counts(&self) -> (u64, u64) {
+        (
+            self.font_cache_hits.load(Ordering::Relaxed),
+            self.font_cache_misses.load(Ordering::Relaxed),
+        )
+    }
+
+    pub(crate) fn set_shape_cache_capacity(&mut self, entries: usize) {
+        if let Ok(cache) = self.shape_cache.get_mut() {
+            cache.set_capacity(entries);
+        }
+    }
+
+    pub(crate) fn shape_cache_len(&self) -> usize {
+        self.shape_cache.lock().map_or(0, |cache| cache.len())
+    }
+
+    pub(crate) fn line_breaks(&self) -> &LineBreakCache {
+        &self.line_breaks
+    }
+
+    // Faces added from CSS so far; name lookups can change when it grows.
+    pub(crate) fn css_face_count(&self) -> usize {
+        self.css_face_count.load(Ordering::Acquire)
+    }
+
+    // The run for `text` in font `name` at `font_size`, shaped by `shape` unless an earlier call
+    // already did; the flag tells whether it came from the cache.
+    pub(crate) fn shaped_run(
+        &self,
+        name: &str,
+        font_size: Pt,
+        text: &str,
+        shape: impl FnOnce() -> Option<ShapedRun>,
+    ) -> Option<(Arc<ShapedRun>, bool)> {
+        let Some(index) = self.lookup_index(name) else {
+            return shape().map(|run| (Arc::new(run), false));
+        };
+        let key = TextRunKey {
+            font_index: index,
+            size_milli: font_size.to_milli_i64(),
+            text: text.to_string(),
+        };
+        if let Ok(mut cache) = self.shape_cache.lock()
+            && let Some(run) = cache.get(&key)
+        {
+            return Some((run.clone(), true));
+        }
+        let run = Arc::new(shape()?);
+        if let Ok(mut cache) = self.shape_cache.lock() {
+            cache.insert(key, run.clone());
+        }
+        Some((run, false))
+    }
+
+    fn load_font(&self, data: Vec<u8>) -> Result<Arc<CachedFace>, FontLoadError> {
+        let (face, hit) = self.font_cache.load(data)?;
+        let counter = if hit {
+            &self.font_cache_hits
+        } else {
+            &self.font_cache_misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        Ok(face)
+    }
+
     pub(crate) fn set_use_full_unicode_metrics(&mut self, enabled: bool) {
         self.use_full_unicode_metrics = enabled;
     }
@@ -192,29 +483,36 @@ impl FontRegistry {
             return;
         };
         let ext = ext.to_ascii_lowercase();
-        if ext != "ttf" && ext != "otf" {
+        if !matches!(ext.as_str(), "ttf" | "otf" | "woff" | "woff2") {
             return;
         }
-        let Ok(data) = fs::read(path) else {
+        let Some(cached) = fs::read(path)
+            .ok()
+            .and_then(|data| self.load_font(data).ok())
+        else {
             return;
         };
-        let Ok(face) = ttf_parser::Face::parse(&data, 0) else {
+        let Ok(face) = ttf_parser::Face::parse(&cached.data, 0) else {
             return;
         };
 
         let (name, aliases) = font_names(&face, path);
-        let (metrics, program_kind) = FontMetrics::from_face(&face);
+        let axes = axis_ranges(&face);
+        let instances = named_instances(&face);
+        let family = typographic_family(&face).unwrap_or_else(|| name.clone());
         let index = self.fonts.len();
         self.fonts.push(RegisteredFont {
             name: name.clone(),
-            data,
-            metrics,
-            program_kind,
+            data: cached.data.clone(),
+            metrics: cached.metrics.clone(),
+            program_kind: cached.program_kind,
             source: RegisteredFontSourceInfo {
                 kind: source_kind,
                 identifier: path.to_string_lossy().to_string(),
             },
+            axes,
         });
+        self.register_named_instances(index, &family, instances);
 
         let mut all_aliases = Vec::new();
         all_aliases.push(name);
@@ -255,25 +553,39 @@ impl FontRegistry {
         source_kind: RegisteredFontSourceKind,
     ) -> Result<String, FullBleedError> {
         let source = source_name.unwrap_or("EmbeddedFont");
-        let Ok(face) = ttf_parser::Face::parse(&data, 0) else {
-            return Err(FullBleedError::Asset(format!(
-                "invalid font data for {source}"
-            )));
+        let cached = self
+            .load_font(data)
+            .map_err(|err| FullBleedError::InvalidFont {
+                source: source.to_string(),
+                message: match err {
+                    FontLoadError::Decode(message) => message,
+                    FontLoadError::NotAFont => "not a TrueType or OpenType font".to_string(),
+                },
+            })?;
+        let Ok(face) = ttf_parser::Face::parse(&cached.data, 0) else {
+            return Err(FullBleedError::InvalidFont {
+                source: source.to_string(),
+                message: "not a TrueType or OpenType font".to_string(),
+            });
         };
 
         let (name, aliases) = font_names(&face, Path::new(source));
-        let (metrics, program_kind) = FontMetrics::from_face(&face);
+        let axes = axis_ranges(&face);
+        let instances = named_instances(&face);
+        let family = typographic_family(&face).unwrap_or_else(|| name.clone());
         let index = self.fonts.len();
         self.fonts.push(RegisteredFont {
             name: name.clone(),
-            data,
-            metrics,
-            program_kind,
+            data: cached.data.clone(),
+            metrics: cached.metrics.clone(),
+            program_kind: cached.program_kind,
             source: RegisteredFontSourceInfo {
                 kind: source_kind,
                 identifier: source.to_string(),
             },
+            axes,
         });
+        self.register_named_instances(index, &family, instances);
 
         let mut all_aliases = Vec::new();
         all_aliases.push(name.clone());
@@ -289,11 +601,257 @@ impl FontRegistry {
         Ok(name)
     }
 
-    pub(crate) fn resolve(&self, name: &str) -> Option<&RegisteredFont> {
+    pub(crate) fn resolve(&self, name: &str) -> Option<FontRef<'_>> {
+        self.lookup_index(name)
+            .and_then(|index| self.font_at(index))
+    }
+
+    // CSS faces shadow registered fonts of the same name, as `@font-face` does in browsers.
+    fn lookup_index(&self, name: &str) -> Option<usize> {
         let key = normalize_name(name);
-        self.lookup
-            .get(&key)
-            .and_then(|index| self.fonts.get(*index))
+        if self.css_face_count.load(Ordering::Acquire) > 0
+            && let Ok(faces) = self.css_faces.lock()
+            && let Some(id) = faces.by_name.get(&key).and_then(|(id, _)| faces.live(*id))
+        {
+            return Some(CSS_FACE_INDEX_BASE + id);
+        }
+        if let Some(index) = self.lookup.get(&key) {
+            return Some(*index);
+        }
+        let face = self.variable_aliases.get(&key)?;
+        self.instance_index(face)
+    }
+
+    fn font_at(&self, index: usize) -> Option<FontRef<'_>> {
+        if index >= CSS_FACE_INDEX_BASE {
+            let mut faces = self.css_faces.lock().ok()?;
+            let font = faces.faces.get(&(index - CSS_FACE_INDEX_BASE))?;
+            return Some(FontRef::Css(font.clone()));
+        }
+        self.fonts.get(index).map(FontRef::Registered)
+    }
+
+    fn register_named_instances(
+        &mut self,
+        index: usize,
+        family: &str,
+        instances: Vec<(String, AxisCoords)>,
+    ) {
+        for (subfamily, coords) in instances {
+            let key = normalize_name(&format!("{family} {subfamily}"));
+            if !self.lookup.contains_key(&key) {
+                self.variable_aliases
+                    .entry(key)
+                    .or_insert(VariableFace { index, coords });
+            }
+        }
+    }
+
+    // Name a variable font at fixed axis values, e.g. "Inter Condensed" for `wdth` 75. Axes not
+    // given keep the values of `family` (itself possibly an alias or named instance).
+    pub(crate) fn register_variation_alias(
+        &mut self,
+        alias: &str,
+        family: &str,
+        coords: &[([u8; 4], f32)],
+    ) -> Result<(), FullBleedError> {
+        let mut face = self.variable_face(family).ok_or_else(|| {
+            FullBleedError::Asset(format!("{family:?} is not a registered variable font"))
+        })?;
+        let axes = self
+            .font_at(face.index)
+            .map(|font| font.axes.clone())
+            .unwrap_or_default();
+        for (tag, value) in coords {
+            if !axes.iter().any(|axis| &axis.tag == tag) {
+                return Err(FullBleedError::Asset(format!(
+                    "font {family:?} has no {:?} axis",
+                    String::from_utf8_lossy(tag)
+                )));
+            }
+            face.coords.retain(|(existing, _)| existing != tag);
+            face.coords.push((*tag, *value));
+        }
+        self.variable_aliases.insert(normalize_name(alias), face);
+        Ok(())
+    }
+
+    // Variable font behind `name` with the axis values the name implies.
+    fn variable_face(&self, name: &str) -> Option<VariableFace> {
+        if let Some(face) = self.variable_aliases.get(&normalize_name(name)) {
+            return Some(face.clone());
+        }
+        let index = self.lookup_index(name)?;
+        let font = self.font_at(index)?;
+        (!font.axes.is_empty()).then(|| VariableFace {
+            index,
+            coords: Vec::new(),
+        })
+    }
+
+    // Name of the face to use for `name` at CSS `weight` when `name` is a variable font with a
+    // `wght` axis: an instance at that weight (clamped to the axis), other axes as named. None
+    // when `name` itself is the right face.
+    pub(crate) fn weight_instance_name(&self, name: &str, weight: u16) -> Option<String> {
+        let mut face = self.variable_face(name)?;
+        let has_wght = |coords: &AxisCoords| coords.iter().any(|(tag, _)| tag == b"wght");
+        // A named weight ("Inter SemiBold") stands unless the style asks for another weight.
+        if weight == 400 && has_wght(&face.coords) {
+            return None;
+        }
+        if !self
+            .font_at(face.index)?
+            .axes
+            .iter()
+            .any(|axis| &axis.tag == b"wght")
+        {
+            return None;
+        }
+        face.coords.retain(|(tag, _)| tag != b"wght");
+        face.coords.push((*b"wght", f32::from(weight)));
+        let index = self.instance_index(&face)?;
+        // The default instance is the font `name` already resolves to.
+        if index == face.index {
+            return None;
+        }
+        self.font_at(index).map(|font| font.name.clone())
+    }
+
+    // Index of the static instance for `face`, building it on first use. The default instance
+    // is the variable font itself.
+    fn instance_index(&self, face: &VariableFace) -> Option<usize> {
+        let font = self.font_at(face.index)?;
+        let mut coords: AxisCoords = font
+            .axes
+            .iter()
+            .map(|axis| {
+                let value = face
+                    .coords
+                    .iter()
+                    .rev()
+                    .find(|(tag, _)| *tag == axis.tag)
+                    .map_or(axis.default, |(_, value)| *value);
+                (axis.tag, value.clamp(axis.min, axis.max))
+            })
+            .collect();
+        let non_default = |(tag, value): &([u8; 4], f32)| {
+            font.axes
+                .iter()
+                .any(|axis| axis.tag == *tag && axis.default != *value)
+        };
+        coords.retain(non_default);
+        if coords.is_empty() {
+            return Some(face.index);
+        }
+        let suffix = coords
+            .iter()
+            .map(|(tag, value)| format!("{}{}", String::from_utf8_lossy(tag).trim(), value))
+            .collect::<Vec<_>>()
+            .join("-");
+        let key = format!("{}@{suffix}", face.index);
+        // `font` is held as its own reference, so the variable font it came from can be
+        // dropped from the cache while its instance is built.
+        let mut faces = self.css_faces.lock().ok()?;
+        if let Some(id) = faces.instances.get(&key).and_then(|id| faces.live(*id)) {
+            return Some(CSS_FACE_INDEX_BASE + id);
+        }
+        let data = crate::instance::instantiate(&font.data, &coords)?;
+        let instance_face = ttf_parser::Face::parse(&data, 0).ok()?;
+        let (metrics, program_kind) = FontMetrics::from_face(&instance_face);
+        // Unique and self-resolving: the PDF writer re-resolves fonts by `name`.
+        let name = format!("{}-{suffix}", font.name);
+        let instance = RegisteredFont {
+            name: name.clone(),
+            data: data.into(),
+            metrics: Arc::new(metrics),
+            program_kind,
+            source: RegisteredFontSourceInfo {
+                kind: font.source.kind,
+                identifier: font.source.identifier.clone(),
+            },
+            axes: Vec::new(),
+        };
+        let id = faces.next_id();
+        faces.insert(id, instance);
+        faces.instances.insert(key, id);
+        faces.by_name.insert(normalize_name(&name), (id, 0));
+        self.css_face_count.fetch_add(1, Ordering::Release);
+        Some(CSS_FACE_INDEX_BASE + id)
+    }
+
+    // Points `family`'s aliases at the face already loaded for the declaration `key`; false
+    // when it is not loaded (or was dropped) and has to be registered.
+    pub(crate) fn alias_css_face(
+        &self,
+        key: &str,
+        family: &str,
+        descriptor: &CssFaceDescriptor,
+    ) -> bool {
+        let Ok(mut faces) = self.css_faces.lock() else {
+            return false;
+        };
+        let Some(id) = faces.by_key.get(key).and_then(|id| faces.live(*id)) else {
+            return false;
+        };
+        if alias_css_face_variants(&mut faces, id, family, descriptor) {
+            self.css_face_count.fetch_add(1, Ordering::Release);
+        }
+        true
+    }
+
+    // Register a face declared by CSS `@font-face` while rendering. It answers to `family`
+    // (the name the stylesheet's family is registered under, see `StyleResolver`) and to the
+    // "{family} Bold", "{family} Italic" and "{family} Bold Italic" variants that text styles
+    // resolve to; each alias keeps the face whose weight range and style are closest (weights
+    // from 600 count as bold, matching the text style resolver).
+    pub(crate) fn register_css_face(
+        &self,
+        key: &str,
+        family: &str,
+        descriptor: &CssFaceDescriptor,
+        data: Vec<u8>,
+        source: &str,
+    ) -> Result<String, FullBleedError> {
+        let not_a_font = || FullBleedError::InvalidFont {
+            source: source.to_string(),
+            message: format!(
+                "@font-face {:?}: not a TrueType or OpenType font",
+                descriptor.family
+            ),
+        };
+        let cached = self.load_font(data).map_err(|err| match err {
+            FontLoadError::Decode(message) => FullBleedError::InvalidFont {
+                source: source.to_string(),
+                message,
+            },
+            FontLoadError::NotAFont => not_a_font(),
+        })?;
+        let face = ttf_parser::Face::parse(&cached.data, 0).map_err(|_| not_a_font())?;
+        let axes = axis_ranges(&face);
+        let mut faces = self
+            .css_faces
+            .lock()
+            .map_err(|_| FullBleedError::Asset("font registry lock poisoned".to_string()))?;
+        let id = faces.next_id();
+        // Unique and self-resolving: the PDF writer re-resolves fonts by `name`.
+        let name = format!("{}-css{}", descriptor.family, id);
+        let font = RegisteredFont {
+            name: name.clone(),
+            data: cached.data.clone(),
+            metrics: cached.metrics.clone(),
+            program_kind: cached.program_kind,
+            source: RegisteredFontSourceInfo {
+                kind: RegisteredFontSourceKind::Bytes,
+                identifier: source.to_string(),
+            },
+            axes,
+        };
+        faces.insert(id, font);
+        faces.by_key.insert(key.to_string(), id);
+        faces.by_name.insert(normalize_name(&name), (id, 0));
+        alias_css_face_variants(&mut faces, id, family, descriptor);
+        self.css_face_count.fetch_add(1, Ordering::Release);
+        Ok(name)
     }
 
     #[cfg(feature = "python")]
@@ -322,12 +880,11 @@ impl FontRegistry {
     }
 
     pub(crate) fn measure_text_width(&self, name: &str, font_size: Pt, text: &str) -> Pt {
-        let key = normalize_name(name);
-        let Some(index) = self.lookup.get(&key).copied() else {
+        let Some(index) = self.lookup_index(name) else {
             let char_width = (font_size * 0.6).max(Pt::from_f32(1.0));
             return char_width * (text.chars().count() as i32);
         };
-        let cache_key = TextWidthKey {
+        let cache_key = TextRunKey {
             font_index: index,
             size_milli: font_size.to_milli_i64(),
             text: text.to_string(),
@@ -337,7 +894,7 @@ impl FontRegistry {
                 return value;
             }
         }
-        let Some(font) = self.fonts.get(index) else {
+        let Some(font) = self.font_at(index) else {
             let char_width = (font_size * 0.6).max(Pt::from_f32(1.0));
             return char_width * (text.chars().count() as i32);
         };
@@ -355,7 +912,7 @@ impl FontRegistry {
             }
             return value;
         }
-        let value = measure_text_width_full(font, font_size, text)
+        let value = measure_text_width_full(&font, font_size, text)
             .unwrap_or_else(|| font.metrics.measure_text_width(font_size, text));
         if let Ok(mut cache) = self.text_width_cache.lock() {
             cache.insert(cache_key, value);
@@ -394,6 +951,58 @@ impl FontRegistry {
         false
     }
 
+    // First registered font, then `@font-face` face, with a glyph for `ch`.
+    pub(crate) fn registered_font_covering(&self, ch: char) -> Option<String> {
+        let covers = |font: &RegisteredFont| {
+            ttf_parser::Face::parse(&font.data, 0).is_ok_and(|face| {
+                let (_symbolic, symbol_subtable) = select_symbol_subtable(&face);
+                glyph_index_for_codepoint(&face, ch as u32, symbol_subtable).is_some()
+            })
+        };
+        if let Some(font) = self.fonts.iter().find(|font| covers(font)) {
+            return Some(font.name.clone());
+        }
+        let faces = self.css_faces.lock().ok()?;
+        faces
+            .faces
+            .iter()
+            .filter(|(_, font)| covers(font))
+            .min_by_key(|(id, _)| **id)
+            .map(|(_, font)| font.name.clone())
+    }
+
+    pub(crate) fn add_fallback(&mut self, family: &str, script: Option<FontScript>) {
+        self.fallback_chain.push((Arc::<str>::from(family), script));
+    }
+
+    // First configured fallback (for `ch`'s script or for any script) that has a glyph for `ch`.
+    fn chain_fallback_for(
+        &self,
+        ch: char,
+        support_cache: &mut HashMap<(Arc<str>, char), bool>,
+    ) -> Option<Arc<str>> {
+        let script = FontScript::of(ch);
+        self.fallback_chain
+            .iter()
+            .filter(|(_, only)| only.is_none() || *only == script)
+            .find(|(font_name, _)| self.cached_supports_char(support_cache, font_name, ch))
+            .map(|(font_name, _)| font_name.clone())
+    }
+
+    fn cached_supports_char(
+        &self,
+        support_cache: &mut HashMap<(Arc<str>, char), bool>,
+        font_name: &Arc<str>,
+        ch: char,
+    ) -> bool {
+        *support_cache
+            .entry((font_name.clone(), ch))
+            .or_insert_with(|| self.font_supports_char(font_name, ch))
+    }
+
+    // Split `text` into runs by glyph coverage: the style's font stack first, then the
+    // configured fallback chain. Script-neutral characters stay in the current run's font when
+    // it covers them, so spaces and punctuation do not fragment CJK or emoji runs.
     pub(crate) fn split_text_by_fallbacks(
         &self,
         primary: &Arc<str>,
@@ -414,21 +1023,25 @@ impl FontRegistry {
         let mut current_font: Option<Arc<str>> = None;
         let mut buf = String::new();
 
-        // Cache glyph support decisions per font index + char to avoid repeated lookups.
-        let mut support_cache: HashMap<(usize, char), bool> = HashMap::new();
+        // Cache glyph support decisions per font + char to avoid repeated lookups.
+        let mut support_cache: HashMap<(Arc<str>, char), bool> = HashMap::new();
 
         for ch in text.chars() {
-            let mut chosen: Option<Arc<str>> = None;
-            for (idx, font_name) in stack.iter().enumerate() {
-                let supported = support_cache
-                    .entry((idx, ch))
-                    .or_insert_with(|| self.font_supports_char(font_name, ch));
-                if *supported {
-                    chosen = Some(font_name.clone());
-                    break;
-                }
-            }
-            let chosen = chosen.unwrap_or_else(|| stack[0].clone());
+            let keep_current = FontScript::of(ch).is_none()
+                && current_font.as_ref().is_some_and(|font_name| {
+                    self.cached_supports_char(&mut support_cache, font_name, ch)
+                });
+            let chosen = if keep_current {
+                current_font.clone()
+            } else {
+                stack
+                    .iter()
+                    .find(|font_name| self.cached_supports_char(&mut support_cache, font_name, ch))
+                    .cloned()
+            };
+            let chosen = chosen
+                .or_else(|| self.chain_fallback_for(ch, &mut support_cache))
+                .unwrap_or_else(|| stack[0].clone());
 
             if current_font.as_ref() != Some(&chosen) {
                 if !buf.is_empty() {
@@ -492,26 +1105,34 @@ impl FontRegistry {
             return;
         }
 
-        let mut support_cache: HashMap<(usize, char), bool> = HashMap::new();
+        let mut support_cache: HashMap<(Arc<str>, char), bool> = HashMap::new();
 
         for ch in text.chars() {
             if ch.is_ascii() {
                 continue;
             }
-            let mut supported = false;
-            for (idx, font_name) in resolved.iter().enumerate() {
-                let ok = support_cache
-                    .entry((idx, ch))
-                    .or_insert_with(|| self.font_supports_char(font_name, ch));
-                if *ok {
-                    supported = true;
-                    break;
-                }
+            let supported = resolved
+                .iter()
+                .any(|font_name| self.cached_supports_char(&mut support_cache, font_name, ch));
+            if supported {
+                continue;
             }
-            if !supported {
-                let fonts_tried = resolved.iter().map(|s| s.to_string()).collect::<Vec<_>>();
-                report.record_missing(ch, fonts_tried);
+            if let Some(fallback) = self.chain_fallback_for(ch, &mut support_cache) {
+                report.record_fallback(ch, &fallback);
+                continue;
             }
+            let script = FontScript::of(ch);
+            let fonts_tried = resolved
+                .iter()
+                .map(|s| s.to_string())
+                .chain(
+                    self.fallback_chain
+                        .iter()
+                        .filter(|(_, only)| only.is_none() || *only == script)
+                        .map(|(font_name, _)| font_name.to_string()),
+                )
+                .collect::<Vec<_>>();
+            report.record_missing(ch, fonts_tried);
         }
     }
 
@@ -530,7 +1151,7 @@ impl FontRegistry {
 }
 
 impl FontMetrics {
-    fn from_face(face: &ttf_parser::Face<'_>) -> (Self, FontProgramKind) {
+    pub(crate) fn from_face(face: &ttf_parser::Face<'_>) -> (Self, FontProgramKind) {
         let units_per_em = face.units_per_em().max(1);
         let scale = 1000.0 / units_per_em as f32;
         let first_char = 32u8;
@@ -896,11 +1517,18 @@ fn font_names(face: &ttf_parser::Face<'_>, path: &Path) -> (String, Vec<String>)
     (primary, aliases)
 }
 
-#[cfg(feature = "python")]
+#[cfg(any(feature = "python", test))]
 pub(crate) fn font_primary_name_from_bytes(
     data: &[u8],
     source_name: Option<&str>,
 ) -> Option<String> {
+    let decoded;
+    let data = if crate::woff::is_webfont(data) {
+        decoded = crate::woff::decode_webfont(data.to_vec()).ok()?;
+        &decoded
+    } else {
+        data
+    };
     let Ok(face) = ttf_parser::Face::parse(data, 0) else {
         return None;
     };
@@ -909,6 +1537,58 @@ pub(crate) fn font_primary_name_from_bytes(
     Some(primary)
 }
 
+fn typographic_family(face: &ttf_parser::Face<'_>) -> Option<String> {
+    use ttf_parser::name::name_id;
+
+    [name_id::TYPOGRAPHIC_FAMILY, name_id::FAMILY]
+        .into_iter()
+        .find_map(|id| {
+            face.names()
+                .into_iter()
+                .filter(|entry| entry.name_id == id)
+                .find_map(|entry| entry.to_string())
+        })
+}
+
+// Points `family` and its bold/italic variants at face `id` where it matches them better than
+// the face they name now; returns whether any alias moved.
+fn alias_css_face_variants(
+    faces: &mut CssFaces,
+    id: usize,
+    family: &str,
+    descriptor: &CssFaceDescriptor,
+) -> bool {
+    let (lo, hi) = descriptor.weight;
+    let mut changed = false;
+    for (suffix, ideal_weight, italic) in [
+        ("", 400u16, false),
+        (" Bold", 700, false),
+        (" Italic", 400, true),
+        (" Bold Italic", 700, true),
+    ] {
+        let mut distance = if (lo..=hi).contains(&ideal_weight) {
+            0
+        } else {
+            u32::from(lo.abs_diff(ideal_weight).min(hi.abs_diff(ideal_weight)))
+        };
+        if italic != descriptor.italic {
+            distance += 1000;
+        }
+        let alias = normalize_name(&format!("{family}{suffix}"));
+        let closer = faces
+            .by_name
+            .get(&alias)
+            .is_none_or(|(existing_id, existing)| {
+                distance < *existing || !faces.faces.contains_key(existing_id)
+            });
+        if closer {
+            faces.by_name.insert(alias, (id, distance));
+            changed = true;
+        }
+    }
+    changed
+}
+
 fn normalize_name(name: &str) -> String {
     name.trim()
         .trim_matches('"')
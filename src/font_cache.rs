@@ -0,0 +1,228 @@
+// Parsed fonts shared across engines. Services that build many `FullBleed` engines from the
+// same font files (one per tenant configuration, say) decode and measure each font once: the
+// registry keeps the decoded data and metrics behind `Arc`s keyed by the SHA-256 of the bytes
+// it was given. The cache is bounded so per-render `@font-face` and data-URI fonts do not pile up
+// in long-running processes.
+use crate::font::{FontMetrics, FontProgramKind};
+use crate::lru::LruCache;
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+// Cache of parsed font data and metrics. Engines use `FontCache::global()` unless the builder
+// is given another cache with `FullBleedBuilder::font_cache`. It holds up to
+// `FontCache::DEFAULT_CAPACITY` fonts and drops the least recently used first.
+pub struct FontCache {
+    entries: Mutex<LruCache<[u8; 32], Arc<CachedFace>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+// Lookups served by a `FontCache` so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FontCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    // Fonts held.
+    pub entries: usize,
+}
+
+// A font as registries share it: data decoded from WOFF/WOFF2 where needed.
+pub(crate) struct CachedFace {
+    pub(crate) data: Arc<[u8]>,
+    pub(crate) metrics: Arc<FontMetrics>,
+    pub(crate) program_kind: FontProgramKind,
+}
+
+pub(crate) enum FontLoadError {
+    // The WOFF/WOFF2 wrapper could not be decoded.
+    Decode(String),
+    NotAFont,
+}
+
+impl std::fmt::Debug for FontCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FontCache")
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+impl Default for FontCache {
+    fn default() -> Self {
+        Self::with_capacity(Self::DEFAULT_CAPACITY)
+    }
+}
+
+impl FontCache {
+    // Fonts held by `FontCache::new()` and `FontCache::global()`.
+    pub const DEFAULT_CAPACITY: usize = 64;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // A cache holding at most `capacity` fonts; `0` caches nothing.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(LruCache::new(capacity)),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    // The process-wide cache.
+    pub fn global() -> Arc<FontCache> {
+        static GLOBAL: OnceLock<Arc<FontCache>> = OnceLock::new();
+        GLOBAL.get_or_init(|| Arc::new(FontCache::new())).clone()
+    }
+
+    pub fn stats(&self) -> FontCacheStats {
+        FontCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.entries.lock().map_or(0, |entries| entries.len()),
+        }
+    }
+
+    // Changes how many fonts the cache holds, dropping the least recently used ones over the
+    // new bound.
+    pub fn set_capacity(&self, capacity: usize) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.set_capacity(capacity);
+        }
+    }
+
+    // Drops every cached font; engines already built keep the fonts they hold.
+    pub fn clear(&self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.clear();
+        }
+    }
+
+    // The parsed font for `data` as given to the registry (possibly WOFF), and whether it came
+    // from the cache. Fonts that fail to load are not cached.
+    pub(crate) fn load(&self, data: Vec<u8>) -> Result<(Arc<CachedFace>, bool), FontLoadError> {
+        let key: [u8; 32] = Sha256::digest(&data).into();
+        if let Some(face) = self
+            .entries
+            .lock()
+            .ok()
+            .and_then(|mut entries| entries.get(&key).cloned())
+        {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok((face, true));
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        // Parsed outside the lock; two engines loading the same font at once both parse it.
+        let data = crate::woff::decode_webfont(data)
+            .map_err(|err| FontLoadError::Decode(err.to_string()))?;
+        let face = ttf_parser::Face::parse(&data, 0).map_err(|_| FontLoadError::NotAFont)?;
+        let (metrics, program_kind) = FontMetrics::from_face(&face);
+        let face = Arc::new(CachedFace {
+            data: data.into(),
+            metrics: Arc::new(metrics),
+            program_kind,
+        });
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(key, face.clone());
+        }
+        Ok((face, false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FullBleed;
+    use std::path::PathBuf;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn temp_log_path(tag: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        std::env::temp_dir().join(format!(
+            "fullbleed_{tag}_{}_{}.jsonl",
+            std::process::id(),
+            nanos
+        ))
+    }
+
+    fn repo_font_path(file_name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("python")
+            .join("fullbleed_assets")
+            .join("fonts")
+            .join(file_name)
+    }
+
+    #[test]
+    fn engines_share_parsed_fonts_through_the_font_cache() {
+        let cache = Arc::new(FontCache::new());
+        let path = temp_log_path("font_cache");
+        let first = FullBleed::builder()
+            .font_cache(cache.clone())
+            .register_font_file(repo_font_path("Inter-Variable.ttf"))
+            .build()
+            .expect("first engine");
+        let second = FullBleed::builder()
+            .font_cache(cache.clone())
+            .register_font_file(repo_font_path("Inter-Variable.ttf"))
+            .perf_log(&path)
+            .build()
+            .expect("second engine");
+        assert_eq!(
+            cache.stats(),
+            FontCacheStats {
+                hits: 1,
+                misses: 1,
+                entries: 1
+            }
+        );
+
+        let a = first.font_registry.resolve("Inter").expect("inter");
+        let b = second.font_registry.resolve("Inter").expect("inter");
+        assert!(Arc::ptr_eq(&a.data, &b.data));
+        assert!(Arc::ptr_eq(&a.metrics, &b.metrics));
+
+        drop(second);
+        let log = std::fs::read_to_string(&path).expect("perf log");
+        assert!(log.contains("\"name\":\"font.cache\""));
+        assert!(log.contains("\"hits\":1"));
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(path.with_file_name(format!(
+            "{}_hot.log",
+            path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default()
+        )));
+    }
+
+    #[test]
+    fn font_cache_drops_least_recently_used_fonts_over_capacity() {
+        let cache = Arc::new(FontCache::with_capacity(1));
+        let build = |font: &str| {
+            FullBleed::builder()
+                .font_cache(cache.clone())
+                .register_font_file(repo_font_path(font))
+                .build()
+                .expect("engine")
+        };
+        build("Inter-Variable.ttf");
+        build("NotoSansMath-Regular.ttf");
+        assert_eq!(cache.stats().entries, 1);
+        // Inter was evicted by the math font, so loading it again parses it again.
+        build("Inter-Variable.ttf");
+        assert_eq!(
+            cache.stats(),
+            FontCacheStats {
+                hits: 0,
+                misses: 3,
+                entries: 1
+            }
+        );
+
+        cache.set_capacity(0);
+        assert_eq!(cache.stats().entries, 0);
+    }
+}
@@ -1,6 +1,7 @@
 use crate::canvas::Canvas;
 use crate::flowable::{BreakInside, Flowable};
 use crate::types::{Pt, Rect, Size};
+use std::collections::VecDeque;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AddDisposition {
@@ -58,6 +59,12 @@ impl Frame {
         self.rect
     }
 
+    // Shrink the frame from the bottom (for example to make room for footnotes). Content that
+    // is already placed keeps its space.
+    pub(crate) fn reserve_bottom(&mut self, height: Pt) {
+        self.rect.height = (self.rect.height - height).max(self.cursor_y);
+    }
+
     pub fn is_empty(&self) -> bool {
         self.cursor_y <= Pt::ZERO
     }
@@ -113,6 +120,25 @@ impl Frame {
             }
         }
 
+        if size.height > avail_height
+            && !self.is_empty()
+            && flowable.breaks_leading_keep(avail_width, avail_height)
+        {
+            return AddResult::Overflow(
+                flowable,
+                AddTrace {
+                    disposition: AddDisposition::Overflow,
+                    reason: "keep_together_move",
+                    avail_width,
+                    avail_height,
+                    frame_rect: self.rect,
+                    cursor_y_before,
+                    wrapped_size: size,
+                    placed_rect: None,
+                },
+            );
+        }
+
         if size.height <= avail_height {
             let rect = Rect {
                 x: self.rect.x,
@@ -221,3 +247,109 @@ impl Frame {
         )
     }
 }
+
+// A flowable (or fragment) assigned to one column of a `ColumnSet`. `y` is relative to the
+// top of the columns.
+pub(crate) struct ColumnPlacement {
+    pub(crate) column: usize,
+    pub(crate) y: Pt,
+    pub(crate) height: Pt,
+    pub(crate) flowable: Box<dyn Flowable>,
+}
+
+pub(crate) struct ColumnFill {
+    pub(crate) placements: Vec<ColumnPlacement>,
+    pub(crate) remaining: VecDeque<Box<dyn Flowable>>,
+    // An unsplittable flowable taller than a column was placed anyway.
+    pub(crate) overfull: bool,
+}
+
+// Row of equal-width frames filled left to right (CSS multi-column). Unlike `Frame`, filling
+// only computes placements, so callers can try several column heights before drawing.
+pub(crate) struct ColumnSet {
+    count: usize,
+    width: Pt,
+    height: Pt,
+}
+
+impl ColumnSet {
+    pub(crate) fn new(count: usize, width: Pt, height: Pt) -> Self {
+        Self {
+            count: count.max(1),
+            width,
+            height,
+        }
+    }
+
+    pub(crate) fn fill(&self, flowables: Vec<Box<dyn Flowable>>) -> ColumnFill {
+        let mut queue: VecDeque<Box<dyn Flowable>> = flowables.into();
+        let mut placements = Vec::new();
+        let mut overfull = false;
+        let mut column = 0usize;
+        let mut cursor = Pt::ZERO;
+
+        while let Some(flowable) = queue.pop_front() {
+            let avail = self.height - cursor;
+            if avail > Pt::ZERO {
+                let size = flowable.wrap(self.width, avail);
+                if size.height <= avail {
+                    placements.push(ColumnPlacement {
+                        column,
+                        y: cursor,
+                        height: size.height,
+                        flowable,
+                    });
+                    cursor += size.height;
+                    continue;
+                }
+                let avoid = matches!(
+                    flowable.pagination().break_inside,
+                    BreakInside::Avoid | BreakInside::AvoidPage
+                );
+                if avoid && cursor > Pt::ZERO && size.height <= self.height {
+                    queue.push_front(flowable);
+                } else if let Some((first, second, first_height)) = flowable
+                    .split(self.width, avail)
+                    .map(|(first, second)| {
+                        let height = first.wrap(self.width, avail).height;
+                        (first, second, height)
+                    })
+                    .filter(|(_, _, height)| *height > Pt::ZERO && *height <= avail)
+                {
+                    placements.push(ColumnPlacement {
+                        column,
+                        y: cursor,
+                        height: first_height,
+                        flowable: first,
+                    });
+                    queue.push_front(second);
+                } else if cursor > Pt::ZERO {
+                    queue.push_front(flowable);
+                } else {
+                    // Taller than a whole column and unsplittable: place it overfull, as
+                    // `Frame::add` does for an empty frame.
+                    overfull = true;
+                    placements.push(ColumnPlacement {
+                        column,
+                        y: cursor,
+                        height: size.height,
+                        flowable,
+                    });
+                }
+            } else {
+                queue.push_front(flowable);
+            }
+            column += 1;
+            cursor = Pt::ZERO;
+            if column >= self.count {
+                break;
+            }
+        }
+
+        ColumnFill {
+            placements,
+            remaining: queue,
+            overfull,
+        }
+    }
+}
@@ -1,39 +1,169 @@
+use crate::canvas::{Command, Document};
+use crate::font::{FontRegistry, FontScript};
 use std::collections::BTreeMap;
 
+// Distinct element paths kept per missing character.
+const MAX_ELEMENT_PATHS: usize = 8;
+
 #[derive(Debug, Clone, Default)]
 pub struct GlyphCoverageReport {
     missing: BTreeMap<u32, MissingGlyph>,
+    fallbacks: BTreeMap<(String, &'static str), FallbackUse>,
+    // Element whose text is being checked; set while the story is built from HTML.
+    element_path: Option<String>,
+}
+
+// Characters the style's own fonts lacked that a configured fallback font covered.
+#[derive(Debug, Clone)]
+pub struct FallbackUse {
+    pub font: String,
+    pub script: &'static str,
+    pub chars: usize,
+    // First few distinct characters routed to `font` for this script.
+    pub sample: String,
 }
 
 #[derive(Debug, Clone)]
 pub struct MissingGlyph {
     pub codepoint: u32,
     pub ch: char,
+    // The fallback chain that was attempted: the style's font stack, then the engine's
+    // fallback fonts for the character's script, in the order tried.
     pub fonts_tried: Vec<String>,
     pub count: usize,
+    // 1-based pages the character was drawn on.
+    pub pages: Vec<usize>,
+    // Elements whose text contained the character, as selector paths (first few).
+    pub element_paths: Vec<String>,
+    // A registered font that has a glyph for the character, if any.
+    pub suggested_font: Option<String>,
 }
 
-impl GlyphCoverageReport {
-    pub fn record_missing(&mut self, ch: char, fonts_tried: Vec<String>) {
-        let codepoint = ch as u32;
-        let entry = self.missing.entry(codepoint).or_insert(MissingGlyph {
-            codepoint,
+impl MissingGlyph {
+    fn new(ch: char) -> Self {
+        Self {
+            codepoint: ch as u32,
             ch,
-            fonts_tried,
+            fonts_tried: Vec::new(),
             count: 0,
-        });
+            pages: Vec::new(),
+            element_paths: Vec::new(),
+            suggested_font: None,
+        }
+    }
+
+    fn note_fonts(&mut self, fonts: Vec<String>) {
+        for font in fonts {
+            if !self.fonts_tried.contains(&font) {
+                self.fonts_tried.push(font);
+            }
+        }
+    }
+
+    fn note_element(&mut self, path: &str) {
+        if self.element_paths.len() < MAX_ELEMENT_PATHS
+            && !self.element_paths.iter().any(|known| known == path)
+        {
+            self.element_paths.push(path.to_string());
+        }
+    }
+}
+
+impl GlyphCoverageReport {
+    pub fn record_missing(&mut self, ch: char, fonts_tried: Vec<String>) {
+        let entry = self
+            .missing
+            .entry(ch as u32)
+            .or_insert_with(|| MissingGlyph::new(ch));
         entry.count = entry.count.saturating_add(1);
+        entry.note_fonts(fonts_tried);
+        if let Some(path) = self.element_path.as_deref() {
+            entry.note_element(path);
+        }
+    }
+
+    // Sets the element later `record_missing` calls are attributed to.
+    pub(crate) fn set_element_path(&mut self, path: Option<String>) {
+        self.element_path = path;
+    }
+
+    // Fills in where each missing character landed and a registered font that covers it,
+    // once the final document is laid out.
+    pub(crate) fn resolve_locations(&mut self, document: &Document, registry: &FontRegistry) {
+        self.element_path = None;
+        if self.missing.is_empty() {
+            return;
+        }
+        for (index, page) in document.pages.iter().enumerate() {
+            for command in &page.commands {
+                let text = match &*command {
+                    Command::DrawString { text, .. }
+                    | Command::DrawStringTransformed { text, .. } => text,
+                    _ => continue,
+                };
+                for ch in text.chars() {
+                    if let Some(entry) = self.missing.get_mut(&(ch as u32))
+                        && entry.pages.last() != Some(&(index + 1))
+                    {
+                        entry.pages.push(index + 1);
+                    }
+                }
+            }
+        }
+        for entry in self.missing.values_mut() {
+            entry.suggested_font = registry.registered_font_covering(entry.ch);
+        }
+    }
+
+    pub fn record_fallback(&mut self, ch: char, font: &str) {
+        let script = FontScript::of(ch).map_or("common", FontScript::as_str);
+        let entry = self
+            .fallbacks
+            .entry((font.to_string(), script))
+            .or_insert_with(|| FallbackUse {
+                font: font.to_string(),
+                script,
+                chars: 0,
+                sample: String::new(),
+            });
+        entry.chars = entry.chars.saturating_add(1);
+        if entry.sample.chars().count() < 8 && !entry.sample.contains(ch) {
+            entry.sample.push(ch);
+        }
     }
 
     pub fn merge(&mut self, other: GlyphCoverageReport) {
         for (codepoint, missing) in other.missing {
-            let entry = self.missing.entry(codepoint).or_insert(MissingGlyph {
-                codepoint,
-                ch: missing.ch,
-                fonts_tried: missing.fonts_tried.clone(),
-                count: 0,
-            });
+            let entry = self
+                .missing
+                .entry(codepoint)
+                .or_insert_with(|| MissingGlyph::new(missing.ch));
             entry.count = entry.count.saturating_add(missing.count);
+            entry.note_fonts(missing.fonts_tried);
+            for path in &missing.element_paths {
+                entry.note_element(path);
+            }
+            for page in missing.pages {
+                if let Err(at) = entry.pages.binary_search(&page) {
+                    entry.pages.insert(at, page);
+                }
+            }
+            if entry.suggested_font.is_none() {
+                entry.suggested_font = missing.suggested_font;
+            }
+        }
+        for (key, used) in other.fallbacks {
+            let entry = self.fallbacks.entry(key).or_insert(FallbackUse {
+                chars: 0,
+                sample: String::new(),
+                ..used.clone()
+            });
+            entry.chars = entry.chars.saturating_add(used.chars);
+            for ch in used.sample.chars() {
+                if entry.sample.chars().count() < 8 && !entry.sample.contains(ch) {
+                    entry.sample.push(ch);
+                }
+            }
         }
     }
 
@@ -41,6 +171,11 @@ impl GlyphCoverageReport {
         self.missing.values().cloned().collect()
     }
 
+    pub fn fallbacks(&self) -> Vec<FallbackUse> {
+        self.fallbacks.values().cloned().collect()
+    }
+
+    // True when no glyph is missing; fallback use alone does not count.
     pub fn is_empty(&self) -> bool {
         self.missing.is_empty()
     }
@@ -1,19 +1,29 @@
-use crate::assets::{AssetBundle, load_svg_xml_from_image_source, renderable_image_source};
+use crate::assets::{
+    AssetBundle, load_svg_xml_from_image_source, renderable_image_source, resolve_image_asset,
+};
+use crate::canvas::LinkTarget;
+use crate::chart::{ChartFlowable, ChartKind, ChartSeries};
+use crate::counter::{CounterState, expand_counters, list_marker};
 use crate::flowable::{
-    AbsolutePositionedFlowable, AlignContent, AlignItems, BorderRadiusSpec, BorderSpec, CalcLength,
-    ContainerFlowable, EdgeSizes, FlexDirection, FlexFlowable, ImageFlowable,
-    InlineBlockLayoutFlowable, JustifyContent, LengthSpec, ListItemFlowable, MetaFlowable,
-    Paragraph, RelativePositionedFlowable, Spacer, SvgFlowable, TableCell, TableFlowable,
-    TextAlign, TextStyle, VerticalAlign,
+    AbsolutePositionedFlowable, AlignContent, AlignItems, BookmarkFlowable, BorderRadiusSpec,
+    BorderSpec, CalcLength, ContainerFlowable, EdgeSizes, FlexDirection, FlexFlowable,
+    FloatAreaFlowable, FloatFlowable, FloatSide, FootnoteFlowable, ImageFlowable,
+    InlineBlockLayoutFlowable, JustifyContent, LeaderSpec, LengthSpec, LinkFlowable,
+    ListItemFlowable, META_FRAME_KEY, META_LAYOUT_STYLE_KEY, META_PAGE_NAME_KEY, MetaFlowable,
+    MultiColumnFlowable, NamedAnchor, Paragraph, RelativePositionedFlowable,
+    SignatureFieldFlowable, Spacer, SvgFlowable, TableCell, TableFlowable, TextAlign, TextLinkSpan,
+    TextStyle, VerticalAlign, VerticalParagraph, VerticalTextFlowable, huge_pt,
 };
 use crate::font::FontRegistry;
 use crate::glyph_report::GlyphCoverageReport;
+use crate::margin_box::{RUNNING_META_PREFIX, STRING_SET_META_PREFIX};
 use crate::style::{
-    AlignContentMode, AlignItemsMode, AlignSelfMode, ComputedStyle, DisplayMode, ElementInfo,
-    FlexDirectionMode, FlexWrapMode, JustifyContentMode, OverflowMode, PositionMode, StyleResolver,
-    TextAlignMode, WhiteSpaceMode,
+    AlignContentMode, AlignItemsMode, AlignSelfMode, COUNTER_MARK, ComputedStyle, DisplayMode,
+    ElementInfo, FlexDirectionMode, FlexWrapMode, FloatMode, JustifyContentMode, LEADER_MARK,
+    OverflowMode, PositionMode, StringSetPart, StyleResolver, TextAlignMode, WhiteSpaceMode,
+    WritingMode,
 };
-use crate::types::Pt;
+use crate::types::{Color, Pt, Size};
 use crate::{BreakAfter, BreakBefore, BreakInside, Flowable};
 use kuchiki::traits::TendrilSink;
 use kuchiki::{NodeData, NodeRef};
@@ -143,6 +153,39 @@ enum LayoutItem {
 }
 
 impl LayoutItem {
+    fn map_flowable(self, f: impl FnOnce(Box<dyn Flowable>) -> Box<dyn Flowable>) -> Self {
+        match self {
+            LayoutItem::Block {
+                flowable,
+                flex_grow,
+                flex_shrink,
+                width_spec,
+                order,
+            } => LayoutItem::Block {
+                flowable: f(flowable),
+                flex_grow,
+                flex_shrink,
+                width_spec,
+                order,
+            },
+            LayoutItem::Inline {
+                flowable,
+                valign,
+                flex_grow,
+                flex_shrink,
+                width_spec,
+                order,
+            } => LayoutItem::Inline {
+                flowable: f(flowable),
+                valign,
+                flex_grow,
+                flex_shrink,
+                width_spec,
+                order,
+            },
+        }
+    }
+
     fn flex_grow(&self) -> f32 {
         match self {
             LayoutItem::Block { flex_grow, .. } => *flex_grow,
@@ -272,6 +315,19 @@ impl LayoutItem {
     }
 }
 
+const MIN_ROWS_ATTR: &str = "data-fb-min-rows";
+
+// Rows or items kept on each side of a break in a table body or list: `data-fb-min-rows`
+// sets both, else declared CSS `orphans`/`widows`.
+fn fragment_minimums(min_rows: Option<&str>, style: &ComputedStyle) -> Option<(usize, usize)> {
+    if let Some(count) = min_rows.and_then(|value| value.trim().parse::<usize>().ok()) {
+        return Some((count, count));
+    }
+    style
+        .orphans_widows_declared
+        .then_some((style.pagination.orphans, style.pagination.widows))
+}
+
 fn flex_item_basis(style: &ComputedStyle) -> Option<LengthSpec> {
     if !matches!(
         style.flex_basis,
@@ -294,8 +350,88 @@ pub fn html_to_story_with_resolver_and_fonts_and_report(
     perf: Option<&crate::perf::PerfLogger>,
     doc_id: Option<usize>,
 ) -> Vec<Box<dyn Flowable>> {
-    let t_parse = std::time::Instant::now();
+    let t_parse = crate::platform::Instant::now();
     let document = kuchiki::parse_html().one(html);
+    story_from_document(
+        &document,
+        t_parse,
+        html.contains("counter-"),
+        html.contains("page:"),
+        resolver,
+        font_registry,
+        asset_bundle,
+        report,
+        svg_form,
+        svg_raster_fallback,
+        perf,
+        doc_id,
+    )
+}
+
+// Builds the story from an already parsed document (a data-bound template) without
+// serializing and re-parsing it. The document is modified in place.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn document_to_story_with_resolver_and_fonts(
+    document: &NodeRef,
+    resolver: &StyleResolver,
+    font_registry: Option<Arc<FontRegistry>>,
+    asset_bundle: Option<Arc<AssetBundle>>,
+    svg_form: bool,
+    svg_raster_fallback: bool,
+    perf: Option<&crate::perf::PerfLogger>,
+    doc_id: Option<usize>,
+) -> Vec<Box<dyn Flowable>> {
+    let inline_style_contains = |needle: &str| {
+        document.descendants().any(|node| {
+            node.as_element().is_some_and(|element| {
+                element
+                    .attributes
+                    .borrow()
+                    .get("style")
+                    .is_some_and(|style| style.contains(needle))
+            })
+        })
+    };
+    story_from_document(
+        document,
+        crate::platform::Instant::now(),
+        inline_style_contains("counter-"),
+        inline_style_contains("page:"),
+        resolver,
+        font_registry,
+        asset_bundle,
+        None,
+        svg_form,
+        svg_raster_fallback,
+        perf,
+        doc_id,
+    )
+}
+
+// `uses_counters` and `uses_named_pages` say whether the document's own inline styles may set
+// counters or named pages, on top of what the resolver's stylesheet does.
+#[allow(clippy::too_many_arguments)]
+fn story_from_document(
+    document: &NodeRef,
+    t_parse: crate::platform::Instant,
+    uses_counters: bool,
+    uses_named_pages: bool,
+    resolver: &StyleResolver,
+    font_registry: Option<Arc<FontRegistry>>,
+    asset_bundle: Option<Arc<AssetBundle>>,
+    report: Option<&mut GlyphCoverageReport>,
+    svg_form: bool,
+    svg_raster_fallback: bool,
+    perf: Option<&crate::perf::PerfLogger>,
+    doc_id: Option<usize>,
+) -> Vec<Box<dyn Flowable>> {
+    number_footnotes(document);
+    if resolver.uses_counters() || uses_counters {
+        resolve_counters(document, resolver);
+    }
+    if resolver.uses_named_pages() || uses_named_pages {
+        resolve_page_starts(document, resolver);
+    }
     if let Some(perf_logger) = perf {
         let ms = t_parse.elapsed().as_secs_f64() * 1000.0;
         perf_logger.log_span_ms("story.parse_html", doc_id, ms);
@@ -319,7 +455,7 @@ pub fn html_to_story_with_resolver_and_fonts_and_report(
 
     let mut root_style = base_style.clone();
     if let Ok(html_el) = document.select_first("html") {
-        let t_root = std::time::Instant::now();
+        let t_root = crate::platform::Instant::now();
         let html_node = html_el.as_node();
         let html_element = html_node.as_element().expect("html element");
         let html_info = element_info(html_node, resolver.has_sibling_selectors());
@@ -346,7 +482,7 @@ pub fn html_to_story_with_resolver_and_fonts_and_report(
             .borrow()
             .get("style")
             .map(|s| s.to_string());
-        let t_body = std::time::Instant::now();
+        let t_body = crate::platform::Instant::now();
         let body_style =
             resolver.compute_style(&body_info, &root_style, inline_style.as_deref(), &ancestors);
         ancestors.push(body_info);
@@ -354,7 +490,7 @@ pub fn html_to_story_with_resolver_and_fonts_and_report(
             let ms = t_body.elapsed().as_secs_f64() * 1000.0;
             perf_logger.log_span_ms("story.style.body", doc_id, ms);
         }
-        let t_collect = std::time::Instant::now();
+        let t_collect = crate::platform::Instant::now();
         let items = collect_children(
             body_node,
             resolver,
@@ -379,9 +515,9 @@ pub fn html_to_story_with_resolver_and_fonts_and_report(
         }
         items
     } else {
-        let t_collect = std::time::Instant::now();
+        let t_collect = crate::platform::Instant::now();
         let items = collect_children(
-            &document,
+            document,
             resolver,
             &root_style,
             &mut ancestors,
@@ -404,7 +540,7 @@ pub fn html_to_story_with_resolver_and_fonts_and_report(
         }
         items
     };
-    let t_flowables = std::time::Instant::now();
+    let t_flowables = crate::platform::Instant::now();
     let flowables = layout_children_to_flowables(items, None);
     if let Some(perf_logger) = perf {
         let ms = t_flowables.elapsed().as_secs_f64() * 1000.0;
@@ -489,8 +625,10 @@ fn authored_owner_metadata(
     ancestors: &[ElementInfo],
     explicit_meta: &[(String, String)],
     style: &ComputedStyle,
+    capture_layout: bool,
 ) -> Vec<(String, String)> {
-    let should_attach = !explicit_meta.is_empty()
+    let should_attach = capture_layout
+        || !explicit_meta.is_empty()
         || info.id.is_some()
         || !info.classes.is_empty()
         || info.attrs.contains_key("data-fb-role")
@@ -565,6 +703,12 @@ fn authored_owner_metadata(
         })
         .or_else(|| info.attrs.get("data-fb-component").cloned());
     upsert(&mut out, "fb.owner.component", component);
+    if capture_layout {
+        out.push((
+            META_LAYOUT_STYLE_KEY.to_string(),
+            crate::style::debug_style_json(style),
+        ));
+    }
 
     out
 }
@@ -622,7 +766,7 @@ fn node_to_flowables(
                 perf_logger.log_counts("story.text_nodes", doc_id, &[("count", 1)]);
             }
             let text = text.borrow();
-            let t_norm = std::time::Instant::now();
+            let t_norm = crate::platform::Instant::now();
             let cleaned = normalize_text(&text, parent_style.white_space, true);
             if let Some(perf_logger) = perf {
                 let ms = t_norm.elapsed().as_secs_f64() * 1000.0;
@@ -631,14 +775,17 @@ fn node_to_flowables(
             if cleaned.is_empty() {
                 Vec::new()
             } else {
-                let t_transform = std::time::Instant::now();
+                let t_transform = crate::platform::Instant::now();
                 let cleaned = apply_text_transform(&cleaned, parent_style.text_transform);
                 if let Some(perf_logger) = perf {
                     let ms = t_transform.elapsed().as_secs_f64() * 1000.0;
                     perf_logger.log_span_ms("story.text.transform", doc_id, ms);
                 }
                 let text_style = parent_style.to_text_style();
-                let t_glyph = std::time::Instant::now();
+                let t_glyph = crate::platform::Instant::now();
+                if let Some((parent, rest)) = ancestors.split_last() {
+                    note_glyph_report_element(report.as_deref_mut(), rest, parent);
+                }
                 report_missing_glyphs(
                     report.as_deref_mut(),
                     font_registry.as_deref(),
@@ -672,7 +819,7 @@ fn node_to_flowables(
             if let Some(perf_logger) = perf {
                 perf_logger.log_counts("story.elements", doc_id, &[("count", 1)]);
             }
-            let t_info = std::time::Instant::now();
+            let t_info = crate::platform::Instant::now();
             let info = element_info(node, resolver.has_sibling_selectors());
             if let Some(perf_logger) = perf {
                 let ms = t_info.elapsed().as_secs_f64() * 1000.0;
@@ -704,9 +851,11 @@ fn node_to_flowables(
                     perf_logger.log_counts("story.inline_style", doc_id, &[("count", 1)]);
                 }
             }
-            let t_style = std::time::Instant::now();
+            note_glyph_report_element(report.as_deref_mut(), ancestors, &info);
+            let t_style = crate::platform::Instant::now();
             let mut style =
                 resolver.compute_style(&info, parent_style, inline_style.as_deref(), ancestors);
+            resolve_background_image(&mut style, asset_bundle.as_deref());
             if let Some(perf_logger) = perf {
                 let ms = t_style.elapsed().as_secs_f64() * 1000.0;
                 perf_logger.log_span_ms("story.style.compute", doc_id, ms);
@@ -741,11 +890,46 @@ fn node_to_flowables(
             if info.classes.iter().any(|c| c == "keep-together") {
                 style.pagination.break_inside = BreakInside::Avoid;
             }
-            let node_meta = authored_owner_metadata(&info, ancestors, &explicit_node_meta, &style);
+            let mut node_meta = authored_owner_metadata(
+                &info,
+                ancestors,
+                &explicit_node_meta,
+                &style,
+                resolver.captures_layout(),
+            );
+            for (name, parts) in &style.string_set {
+                node_meta.push((
+                    format!("{STRING_SET_META_PREFIX}{name}"),
+                    string_set_value(node, parts),
+                ));
+            }
+            if let Some(name) = element.attributes.borrow().get(PAGE_START_ATTR) {
+                style.pagination.break_before = BreakBefore::Page;
+                node_meta.push((META_PAGE_NAME_KEY.to_string(), name.to_string()));
+            }
+            if let Some(frame) = info.attrs.get(FRAME_ATTR).filter(|name| !name.is_empty()) {
+                node_meta.push((META_FRAME_KEY.to_string(), frame.clone()));
+            }
+            let mut anchors = element_anchor_names(&info);
+            let link_target = element_link_target(&info);
 
             if matches!(style.display, DisplayMode::None) {
                 return Vec::new();
             }
+            if let Some(name) = &style.running
+                && !info.attrs.contains_key(RUNNING_PLACED_ATTR)
+            {
+                return vec![running_element_item(node, name)];
+            }
+            if let Some(number) = footnote_number(&info) {
+                return vec![footnote_item(
+                    node,
+                    number,
+                    &style,
+                    font_registry.clone(),
+                    report.as_deref_mut(),
+                )];
+            }
 
             let before_items = pseudo_items_for(
                 resolver,
@@ -771,9 +955,24 @@ fn node_to_flowables(
 
             // Contents/inline are usually transparent containers in our layout model, except
             // replaced/special inline elements that render atomic content.
+            let chart_kind = info
+                .attrs
+                .get(CHART_ATTR)
+                .and_then(|kind| ChartKind::parse(kind));
+            let mut custom_flowable = resolver.custom_element(&info.tag).and_then(|factory| {
+                factory(&CustomElement {
+                    tag: info.tag.clone(),
+                    attributes: info.attrs.clone(),
+                    text: extract_text(node, style.white_space),
+                    font_size: style.font_size,
+                    color: style.color,
+                })
+            });
             let transparent_inline =
                 matches!(style.display, DisplayMode::Contents | DisplayMode::Inline)
-                    && !matches!(info.tag.as_str(), "img" | "svg" | "br");
+                    && !matches!(info.tag.as_str(), "img" | "svg" | "br")
+                    && chart_kind.is_none()
+                    && custom_flowable.is_none();
             if transparent_inline {
                 let out = collect_children(
                     node,
@@ -788,12 +987,101 @@ fn node_to_flowables(
                     perf,
                     doc_id,
                 );
-                let out = inject_pseudo_items(out, &before_items, &after_items);
+                let mut out = inject_pseudo_items(out, &before_items, &after_items);
+                if out.is_empty() && !anchors.is_empty() {
+                    out.push(LayoutItem::Block {
+                        flowable: Box::new(Spacer::new_pt(Pt::from_f32(0.01))) as Box<dyn Flowable>,
+                        flex_grow: 0.0,
+                        flex_shrink: 1.0,
+                        width_spec: None,
+                        order: 0,
+                    });
+                }
                 ancestors.pop();
-                return out;
+                return wrap_link_items(out, anchors, link_target);
             }
 
+            // A vertical-rl subtree inside horizontal content is laid out as one vertical text
+            // flow; nested elements contribute their text only.
+            let vertical_root = style.writing_mode == WritingMode::VerticalRl
+                && parent_style.writing_mode != WritingMode::VerticalRl;
             let mut flowables = match info.tag.as_str() {
+                _ if vertical_root => {
+                    let mut paragraphs = Vec::new();
+                    collect_vertical_paragraphs(
+                        node,
+                        resolver,
+                        &style,
+                        ancestors,
+                        font_registry.as_deref(),
+                        report.as_deref_mut(),
+                        &mut paragraphs,
+                    );
+                    if paragraphs.is_empty() {
+                        container_flowables(Vec::new(), &style)
+                    } else {
+                        // Columns run the block's fixed height, else the page frame height.
+                        let column_length = match style.height {
+                            LengthSpec::Absolute(value) if value > Pt::ZERO => value,
+                            _ if resolver.page_content_height() > Pt::ZERO => {
+                                resolver.page_content_height()
+                            }
+                            _ => huge_pt(),
+                        };
+                        let vertical = VerticalTextFlowable::new(
+                            paragraphs,
+                            column_length,
+                            font_registry.clone(),
+                        )
+                        .with_pagination(style.pagination)
+                        .with_tag_role("P");
+                        let children = vec![LayoutItem::Block {
+                            flowable: Box::new(vertical) as Box<dyn Flowable>,
+                            flex_grow: 0.0,
+                            flex_shrink: 1.0,
+                            width_spec: None,
+                            order: 0,
+                        }];
+                        container_flowables(children, &style)
+                    }
+                }
+                _ if custom_flowable.is_some() => {
+                    let children = custom_flowable
+                        .take()
+                        .map(|flowable| LayoutItem::Block {
+                            flowable,
+                            flex_grow: 0.0,
+                            flex_shrink: 1.0,
+                            width_spec: None,
+                            order: 0,
+                        })
+                        .into_iter()
+                        .collect();
+                    container_flowables(children, &style)
+                }
+                _ if chart_kind.is_some() => {
+                    let children = chart_kind
+                        .map(|kind| {
+                            let chart = chart_flowable(
+                                node,
+                                kind,
+                                &style,
+                                resolver,
+                                ancestors,
+                                font_registry.clone(),
+                            );
+                            LayoutItem::Block {
+                                flowable: Box::new(chart) as Box<dyn Flowable>,
+                                flex_grow: 0.0,
+                                flex_shrink: 1.0,
+                                width_spec: None,
+                                order: 0,
+                            }
+                        })
+                        .into_iter()
+                        .collect();
+                    container_flowables(children, &style)
+                }
                 "p" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
                     let role = match info.tag.as_str() {
                         "h1" => "H1",
@@ -805,8 +1093,9 @@ fn node_to_flowables(
                         _ => "P",
                     };
 
-                    if inline_children_only(node, resolver, &style, ancestors) {
-                        let t_extract = std::time::Instant::now();
+                    let inline = inline_children(node, resolver, &style, ancestors);
+                    if inline == Some(InlineChildren::Text) {
+                        let t_extract = crate::platform::Instant::now();
                         let mut text = extract_text(node, style.white_space);
                         if let Some(perf_logger) = perf {
                             let ms = t_extract.elapsed().as_secs_f64() * 1000.0;
@@ -836,14 +1125,14 @@ fn node_to_flowables(
                         if text.is_empty() {
                             container_flowables_with_role(Vec::new(), &style, Some(role))
                         } else {
-                            let t_transform = std::time::Instant::now();
+                            let t_transform = crate::platform::Instant::now();
                             let text = apply_text_transform(&text, style.text_transform);
                             if let Some(perf_logger) = perf {
                                 let ms = t_transform.elapsed().as_secs_f64() * 1000.0;
                                 perf_logger.log_span_ms("story.text.transform", doc_id, ms);
                             }
                             let text_style = style.to_text_style();
-                            let t_glyph = std::time::Instant::now();
+                            let t_glyph = crate::platform::Instant::now();
                             report_missing_glyphs(
                                 report.as_deref_mut(),
                                 font_registry.as_deref(),
@@ -854,6 +1143,14 @@ fn node_to_flowables(
                                 let ms = t_glyph.elapsed().as_secs_f64() * 1000.0;
                                 perf_logger.log_span_ms("story.glyph.report", doc_id, ms);
                             }
+                            anchors.extend(descendant_anchor_names(node));
+                            let (text, leader) = take_leader(text);
+                            let links = inline_text_links(
+                                node,
+                                &text,
+                                style.white_space,
+                                style.text_transform,
+                            );
                             let paragraph = Paragraph::new(text)
                                 .with_style(text_style)
                                 .with_align(text_align_from_style(&style))
@@ -863,14 +1160,27 @@ fn node_to_flowables(
                                 )
                                 .with_pagination(style.pagination)
                                 .with_font_registry(font_registry.clone())
-                                .with_tag_role(role);
-                            let items = vec![LayoutItem::Block {
+                                .with_tag_role(role)
+                                .with_links(links);
+                            let paragraph = match leader {
+                                Some(leader) => paragraph.with_leader(leader),
+                                None => paragraph,
+                            };
+                            let mut items = vec![LayoutItem::Block {
                                 flowable: Box::new(paragraph) as Box<dyn Flowable>,
                                 flex_grow: 0.0,
                                 flex_shrink: 1.0,
                                 width_spec: None,
                                 order: 0,
                             }];
+                            items.extend(descendant_footnote_items(
+                                node,
+                                resolver,
+                                &style,
+                                ancestors,
+                                font_registry.clone(),
+                                report.as_deref_mut(),
+                            ));
                             container_flowables(items, &style)
                         }
                     } else {
@@ -887,12 +1197,17 @@ fn node_to_flowables(
                             perf,
                             doc_id,
                         );
+                        let children = if inline == Some(InlineChildren::WithBoxes) {
+                            inline_layout_items(children)
+                        } else {
+                            children
+                        };
                         let children = inject_pseudo_items(children, &before_items, &after_items);
                         container_flowables_with_role(children, &style, Some(role))
                     }
                 }
                 "pre" => {
-                    let t_extract = std::time::Instant::now();
+                    let t_extract = crate::platform::Instant::now();
                     let mut text = extract_text(node, WhiteSpaceMode::Pre);
                     if let Some(perf_logger) = perf {
                         let ms = t_extract.elapsed().as_secs_f64() * 1000.0;
@@ -922,14 +1237,14 @@ fn node_to_flowables(
                     if text.is_empty() {
                         container_flowables(Vec::new(), &style)
                     } else {
-                        let t_transform = std::time::Instant::now();
+                        let t_transform = crate::platform::Instant::now();
                         let text = apply_text_transform(&text, style.text_transform);
                         if let Some(perf_logger) = perf {
                             let ms = t_transform.elapsed().as_secs_f64() * 1000.0;
                             perf_logger.log_span_ms("story.text.transform", doc_id, ms);
                         }
                         let text_style = style.to_text_style();
-                        let t_glyph = std::time::Instant::now();
+                        let t_glyph = crate::platform::Instant::now();
                         report_missing_glyphs(
                             report.as_deref_mut(),
                             font_registry.as_deref(),
@@ -1020,6 +1335,7 @@ fn node_to_flowables(
                             if let Some(data_uri) =
                                 crate::svg::rasterize_svg_to_data_uri(&xml, width, height)
                             {
+                                warn_svg_rasterized(Some(src));
                                 let image = ImageFlowable::new_pt(width, height, data_uri)
                                     .with_pagination(style.pagination)
                                     .with_tag_role("Figure")
@@ -1033,7 +1349,7 @@ fn node_to_flowables(
                                 }]
                             } else {
                                 let xml_len = xml.len() as u64;
-                                let t_svg = std::time::Instant::now();
+                                let t_svg = crate::platform::Instant::now();
                                 let svg = SvgFlowable::new_pt(width, height, xml)
                                     .with_pagination(style.pagination)
                                     .with_form_enabled(svg_form)
@@ -1058,7 +1374,7 @@ fn node_to_flowables(
                             }
                         } else {
                             let xml_len = xml.len() as u64;
-                            let t_svg = std::time::Instant::now();
+                            let t_svg = crate::platform::Instant::now();
                             let svg = SvgFlowable::new_pt(width, height, xml)
                                 .with_pagination(style.pagination)
                                 .with_form_enabled(svg_form)
@@ -1115,6 +1431,7 @@ fn node_to_flowables(
                         if let Some(data_uri) =
                             crate::svg::rasterize_svg_to_data_uri(&xml, width, height)
                         {
+                            warn_svg_rasterized(None);
                             let image = ImageFlowable::new_pt(width, height, data_uri)
                                 .with_pagination(style.pagination)
                                 .with_tag_role("Figure")
@@ -1128,7 +1445,7 @@ fn node_to_flowables(
                             }]
                         } else {
                             let xml_len = xml.len() as u64;
-                            let t_svg = std::time::Instant::now();
+                            let t_svg = crate::platform::Instant::now();
                             let svg = SvgFlowable::new_pt(width, height, xml)
                                 .with_pagination(style.pagination)
                                 .with_form_enabled(svg_form)
@@ -1149,7 +1466,7 @@ fn node_to_flowables(
                         }
                     } else {
                         let xml_len = xml.len() as u64;
-                        let t_svg = std::time::Instant::now();
+                        let t_svg = crate::platform::Instant::now();
                         let svg = SvgFlowable::new_pt(width, height, xml)
                             .with_pagination(style.pagination)
                             .with_form_enabled(svg_form)
@@ -1292,8 +1609,10 @@ fn node_to_flowables(
                     .with_height(style.height)
                     .with_background(style.background_color)
                     .with_background_paint(style.background_paint.clone())
+                    .with_background_image(style.background_image_layer())
                     .with_clip_path_inset(style.clip_path_inset)
                     .with_box_shadow(style.box_shadow.clone())
+                    .with_outline(style.outline())
                     .with_paint_filter(style.paint_filter)
                     .with_backdrop_filter(style.backdrop_filter)
                     .with_mix_blend_mode(style.mix_blend_mode)
@@ -1311,6 +1630,13 @@ fn node_to_flowables(
                     }]
                 }
                 "ul" | "ol" => {
+                    if let Some((orphans, widows)) =
+                        fragment_minimums(info.attrs.get(MIN_ROWS_ATTR).map(String::as_str), &style)
+                    {
+                        style.pagination.orphans = orphans;
+                        style.pagination.widows = widows;
+                        style.orphans_widows_declared = true;
+                    }
                     let items = list_flowables(
                         node,
                         resolver,
@@ -1334,6 +1660,13 @@ fn node_to_flowables(
                     } else {
                         let text = apply_text_transform(&text, style.text_transform);
                         let label = format!("- {}", text);
+                        anchors.extend(descendant_anchor_names(node));
+                        let links = inline_text_links(
+                            node,
+                            &label,
+                            style.white_space,
+                            style.text_transform,
+                        );
                         let text_style = style.to_text_style();
                         report_missing_glyphs(
                             report.as_deref_mut(),
@@ -1350,14 +1683,24 @@ fn node_to_flowables(
                             )
                             .with_pagination(style.pagination)
                             .with_font_registry(font_registry.clone())
-                            .with_tag_role("LI");
-                        vec![LayoutItem::Block {
+                            .with_tag_role("LI")
+                            .with_links(links);
+                        let mut items = vec![LayoutItem::Block {
                             flowable: Box::new(paragraph) as Box<dyn Flowable>,
                             flex_grow: 0.0,
                             flex_shrink: 1.0,
                             width_spec: flex_item_basis(&style),
                             order: 0,
-                        }]
+                        }];
+                        items.extend(descendant_footnote_items(
+                            node,
+                            resolver,
+                            &style,
+                            ancestors,
+                            font_registry.clone(),
+                            report.as_deref_mut(),
+                        ));
+                        items
                     }
                 }
                 "div" | "span" | "section" | "article" | "header" | "footer" | "aside" | "nav"
@@ -1439,6 +1782,14 @@ fn node_to_flowables(
                                 &text_style,
                                 &text,
                             );
+                            anchors.extend(descendant_anchor_names(node));
+                            let (text, leader) = take_leader(text);
+                            let links = inline_text_links(
+                                node,
+                                &text,
+                                style.white_space,
+                                style.text_transform,
+                            );
                             let paragraph = Paragraph::new(text)
                                 .with_style(text_style)
                                 .with_align(text_align_from_style(&style))
@@ -1447,19 +1798,32 @@ fn node_to_flowables(
                                     no_wrap(style.white_space),
                                 )
                                 .with_pagination(style.pagination)
-                                .with_font_registry(font_registry.clone());
+                                .with_font_registry(font_registry.clone())
+                                .with_links(links);
+                            let paragraph = match leader {
+                                Some(leader) => paragraph.with_leader(leader),
+                                None => paragraph,
+                            };
                             let paragraph = if let Some(role) = dl_inline_text_role {
                                 paragraph.with_tag_role(role)
                             } else {
                                 paragraph
                             };
-                            let items = vec![LayoutItem::Block {
+                            let mut items = vec![LayoutItem::Block {
                                 flowable: Box::new(paragraph) as Box<dyn Flowable>,
                                 flex_grow: 0.0,
                                 flex_shrink: 1.0,
                                 width_spec: None,
                                 order: 0,
                             }];
+                            items.extend(descendant_footnote_items(
+                                node,
+                                resolver,
+                                &style,
+                                ancestors,
+                                font_registry.clone(),
+                                report.as_deref_mut(),
+                            ));
                             if matches!(info.tag.as_str(), "dl") {
                                 container_flowables_with_role(items, &style, dl_container_role)
                             } else {
@@ -1496,6 +1860,16 @@ fn node_to_flowables(
                                 doc_id,
                             )
                         };
+                        let children = if matches!(
+                            style.display,
+                            DisplayMode::Block | DisplayMode::InlineBlock
+                        ) && inline_children(node, resolver, &style, ancestors)
+                            == Some(InlineChildren::WithBoxes)
+                        {
+                            inline_layout_items(children)
+                        } else {
+                            children
+                        };
                         let children = inject_pseudo_items(children, &before_items, &after_items);
                         if dl_container_role.is_some() {
                             container_flowables_with_role(children, &style, dl_container_role)
@@ -1542,7 +1916,7 @@ fn node_to_flowables(
             // feature flags can be emitted even when the element has no visual content.
             // Use a tiny spacer to ensure the flowable is drawable; zero-height carriers can
             // be skipped in layout paths and lose metadata emission.
-            if flowables.is_empty() && !node_meta.is_empty() {
+            if flowables.is_empty() && (!node_meta.is_empty() || !anchors.is_empty()) {
                 let carrier = Spacer::new_pt(Pt::from_f32(0.01)).with_pagination(style.pagination);
                 flowables.push(LayoutItem::Block {
                     flowable: Box::new(carrier) as Box<dyn Flowable>,
@@ -1554,7 +1928,8 @@ fn node_to_flowables(
             }
 
             if matches!(style.position, PositionMode::Absolute | PositionMode::Fixed) {
-                flowables = wrap_absolute(flowables, &style);
+                let continue_overflow = info.attrs.contains_key(CONTINUE_OVERFLOW_ATTR);
+                flowables = wrap_absolute(flowables, &style, continue_overflow);
             } else if matches!(style.position, PositionMode::Relative) {
                 flowables = wrap_relative(flowables, &style);
             }
@@ -1643,6 +2018,14 @@ fn node_to_flowables(
                     })
                     .collect();
             }
+            items = wrap_link_items(items, anchors, link_target);
+            if let Some(level) = heading_level(&info.tag) {
+                let title = extract_text(node, WhiteSpaceMode::Normal);
+                items = wrap_bookmark_items(items, level, title);
+            }
+            if let Some(name) = info.attrs.get("data-fb-signature-field") {
+                items = wrap_signature_field_items(items, name.trim().to_string());
+            }
             if matches!(
                 style.display,
                 DisplayMode::InlineBlock
@@ -1683,6 +2066,11 @@ fn node_to_flowables(
                     })
                     .collect();
             }
+            if !parent_is_flex
+                && !matches!(style.position, PositionMode::Absolute | PositionMode::Fixed)
+            {
+                items = wrap_float(items, &style);
+            }
 
             ancestors.pop();
             items
@@ -1766,10 +2154,10 @@ fn escape_xml_text(input: &str, out: &mut String) {
 }
 
 fn text_align_from_style(style: &ComputedStyle) -> TextAlign {
-    match style.text_align {
+    match style.physical_text_align() {
         TextAlignMode::Center => TextAlign::Center,
         TextAlignMode::Right => TextAlign::Right,
-        TextAlignMode::Left => TextAlign::Left,
+        _ => TextAlign::Left,
     }
 }
 
@@ -1851,6 +2239,21 @@ fn pseudo_content_items(
     }
 }
 
+fn resolved_pseudo_style(
+    resolver: &StyleResolver,
+    info: &ElementInfo,
+    style: &ComputedStyle,
+    ancestors: &[ElementInfo],
+    pseudo: crate::style::PseudoTarget,
+) -> Option<ComputedStyle> {
+    let mut pseudo_style = resolver.compute_pseudo_style(info, style, ancestors, pseudo)?;
+    pseudo_style.content = pseudo_style
+        .content
+        .take()
+        .map(|content| generated_content(info, pseudo, content));
+    Some(pseudo_style)
+}
+
 fn pseudo_items_for(
     resolver: &StyleResolver,
     info: &ElementInfo,
@@ -1860,7 +2263,7 @@ fn pseudo_items_for(
     report: Option<&mut GlyphCoverageReport>,
     pseudo: crate::style::PseudoTarget,
 ) -> Vec<LayoutItem> {
-    let Some(pseudo_style) = resolver.compute_pseudo_style(info, style, ancestors, pseudo) else {
+    let Some(pseudo_style) = resolved_pseudo_style(resolver, info, style, ancestors, pseudo) else {
         return Vec::new();
     };
     pseudo_content_items(&pseudo_style, font_registry, report)
@@ -1875,7 +2278,7 @@ fn pseudo_text_for(
     report: Option<&mut GlyphCoverageReport>,
     pseudo: crate::style::PseudoTarget,
 ) -> String {
-    let Some(pseudo_style) = resolver.compute_pseudo_style(info, style, ancestors, pseudo) else {
+    let Some(pseudo_style) = resolved_pseudo_style(resolver, info, style, ancestors, pseudo) else {
         return String::new();
     };
     let Some(content) = pseudo_style.content.clone() else {
@@ -1887,12 +2290,48 @@ fn pseudo_text_for(
     text
 }
 
+// Generated `leader()` content arrives as `fill` between two `LEADER_MARK`s; the paragraph
+// wants the gap as a single tab with the fill carried on its LeaderSpec.
+fn take_leader(text: String) -> (String, Option<LeaderSpec>) {
+    let Some((left, rest)) = text.split_once(LEADER_MARK) else {
+        return (text, None);
+    };
+    let Some((fill, right)) = rest.split_once(LEADER_MARK) else {
+        return (text, None);
+    };
+    let leader = LeaderSpec::new(fill);
+    (
+        format!("{}\t{}", left.trim_end(), right.trim_start()),
+        Some(leader),
+    )
+}
+
 fn inline_children_only(
     node: &NodeRef,
     resolver: &StyleResolver,
     parent_style: &ComputedStyle,
     ancestors: &[ElementInfo],
 ) -> bool {
+    inline_children(node, resolver, parent_style, ancestors) == Some(InlineChildren::Text)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InlineChildren {
+    // Text and plain inline elements, flattened into one paragraph.
+    Text,
+    // Inline content with transformed inline-blocks (a rotated "PAID" stamp), which are
+    // painted as their own boxes on the line.
+    WithBoxes,
+}
+
+// How a block's children flow; None when some child is block-level or replaced.
+fn inline_children(
+    node: &NodeRef,
+    resolver: &StyleResolver,
+    parent_style: &ComputedStyle,
+    ancestors: &[ElementInfo],
+) -> Option<InlineChildren> {
+    let mut flow = InlineChildren::Text;
     for child in node.children() {
         let Some(element) = child.as_element() else {
             continue;
@@ -1914,7 +2353,7 @@ fn inline_children_only(
                 | "embed"
                 | "input"
         ) {
-            return false;
+            return None;
         }
         let info = element_info(&child, resolver.has_sibling_selectors());
         let inline_style = element
@@ -1925,38 +2364,152 @@ fn inline_children_only(
         let child_style =
             resolver.compute_style(&info, parent_style, inline_style.as_deref(), ancestors);
         match child_style.display {
-            DisplayMode::Inline
-            | DisplayMode::InlineBlock
-            | DisplayMode::InlineFlex
-            | DisplayMode::InlineGrid
-            | DisplayMode::Contents => {}
-            _ => return false,
+            DisplayMode::Inline | DisplayMode::Contents => {}
+            DisplayMode::InlineBlock | DisplayMode::InlineFlex | DisplayMode::InlineGrid => {
+                if !child_style.transform.is_empty() {
+                    flow = InlineChildren::WithBoxes;
+                }
+            }
+            _ => return None,
         }
     }
-    true
+    Some(flow)
 }
 
-fn node_has_renderable_content(node: &NodeRef) -> bool {
-    if node.text_contents().trim().is_empty() {
-        node.children()
-            .any(|child| matches!(child.data(), NodeData::Element(_)))
-    } else {
-        true
-    }
+// Lays out inline content with boxes on shared lines; text sits on the bottom of each line.
+fn inline_layout_items(items: Vec<LayoutItem>) -> Vec<LayoutItem> {
+    items
+        .into_iter()
+        .map(|item| match item {
+            LayoutItem::Block {
+                flowable,
+                flex_grow,
+                flex_shrink,
+                width_spec,
+                order,
+            } => LayoutItem::Inline {
+                flowable,
+                valign: VerticalAlign::Bottom,
+                flex_grow,
+                flex_shrink,
+                width_spec,
+                order,
+            },
+            inline => inline,
+        })
+        .collect()
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use base64::Engine;
-
-    fn approx_eq_pt(value: Pt, expected: f32) -> bool {
-        (value.to_f32() - expected).abs() <= 0.01
+// Text blocks of a vertical writing-mode subtree in document order: every element whose
+// children are all inline becomes one paragraph, as does loose text between blocks.
+fn collect_vertical_paragraphs(
+    node: &NodeRef,
+    resolver: &StyleResolver,
+    style: &ComputedStyle,
+    ancestors: &mut Vec<ElementInfo>,
+    font_registry: Option<&FontRegistry>,
+    report: Option<&mut GlyphCoverageReport>,
+    out: &mut Vec<VerticalParagraph>,
+) {
+    let mut report = report;
+    if inline_children_only(node, resolver, style, ancestors) {
+        let text = extract_text(node, style.white_space);
+        push_vertical_paragraph(out, text, style, font_registry, report);
+        return;
     }
-
-    #[test]
-    fn html_th_scope_maps_known_values_to_pdf_scope_names() {
-        assert_eq!(
+    for child in node.children() {
+        match child.data() {
+            NodeData::Text(text) => {
+                let text = normalize_text(&text.borrow(), style.white_space, true);
+                push_vertical_paragraph(out, text, style, font_registry, report.as_deref_mut());
+            }
+            NodeData::Element(element) => {
+                let info = element_info(&child, resolver.has_sibling_selectors());
+                let inline_style = element
+                    .attributes
+                    .borrow()
+                    .get("style")
+                    .map(|s| s.to_string());
+                let child_style =
+                    resolver.compute_style(&info, style, inline_style.as_deref(), ancestors);
+                if matches!(child_style.display, DisplayMode::None) {
+                    continue;
+                }
+                ancestors.push(info);
+                collect_vertical_paragraphs(
+                    &child,
+                    resolver,
+                    &child_style,
+                    ancestors,
+                    font_registry,
+                    report.as_deref_mut(),
+                    out,
+                );
+                ancestors.pop();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn push_vertical_paragraph(
+    out: &mut Vec<VerticalParagraph>,
+    text: String,
+    style: &ComputedStyle,
+    font_registry: Option<&FontRegistry>,
+    report: Option<&mut GlyphCoverageReport>,
+) {
+    if text.is_empty() {
+        return;
+    }
+    let text = apply_text_transform(&text, style.text_transform);
+    let text_style = style.to_text_style();
+    report_missing_glyphs(report, font_registry, &text_style, &text);
+    out.push(VerticalParagraph::new(text, text_style));
+}
+
+fn node_has_renderable_content(node: &NodeRef) -> bool {
+    if node.text_contents().trim().is_empty() {
+        node.children()
+            .any(|child| matches!(child.data(), NodeData::Element(_)))
+    } else {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+
+    fn approx_eq_pt(value: Pt, expected: f32) -> bool {
+        (value.to_f32() - expected).abs() <= 0.01
+    }
+
+    #[test]
+    fn href_link_target_classifies_fragments_and_absolute_uris() {
+        assert_eq!(
+            href_link_target(" #totals "),
+            Some(LinkTarget::Internal("totals".to_string()))
+        );
+        assert_eq!(
+            href_link_target("https://example.com/a?b=1"),
+            Some(LinkTarget::Uri("https://example.com/a?b=1".to_string()))
+        );
+        assert_eq!(
+            href_link_target("mailto:billing@example.com"),
+            Some(LinkTarget::Uri("mailto:billing@example.com".to_string()))
+        );
+        assert_eq!(href_link_target("#"), None);
+        assert_eq!(href_link_target("reports/2024.html"), None);
+        assert_eq!(href_link_target("JavaScript:alert(1)"), None);
+        assert_eq!(href_link_target(r"C:\docs\a.pdf"), None);
+        assert_eq!(href_link_target("c:/docs/a.pdf"), None);
+    }
+
+    #[test]
+    fn html_th_scope_maps_known_values_to_pdf_scope_names() {
+        assert_eq!(
             html_th_scope_to_pdf_scope(Some("col")).as_deref(),
             Some("Column")
         );
@@ -2157,7 +2710,8 @@ fn list_flowables(
         .as_element()
         .map(|el| el.name.local.as_ref() == "ol")
         .unwrap_or(false);
-    let mut index = 1usize;
+    let list_info = element_info(node, false);
+    let mut index = ordered_list_start(&list_info).unwrap_or(1);
 
     for child in node.children() {
         if let Some(element) = child.as_element() {
@@ -2170,8 +2724,9 @@ fn list_flowables(
                 .borrow()
                 .get("style")
                 .map(|s| s.to_string());
-            let style =
+            let mut style =
                 resolver.compute_style(&info, parent_style, inline_style.as_deref(), ancestors);
+            resolve_background_image(&mut style, asset_bundle.as_deref());
             let is_inline = matches!(
                 style.display,
                 DisplayMode::Inline
@@ -2180,9 +2735,27 @@ fn list_flowables(
                     | DisplayMode::InlineFlex
                     | DisplayMode::InlineGrid
             );
-            let show_marker =
-                matches!(style.list_style_type, crate::style::ListStyleTypeMode::Auto)
-                    && !is_inline;
+            let marker_style = resolved_pseudo_style(
+                resolver,
+                &info,
+                &style,
+                ancestors,
+                crate::style::PseudoTarget::Marker,
+            );
+            let marker_content = marker_style
+                .as_ref()
+                .and_then(|marker| marker.content.clone());
+            let show_marker = !is_inline
+                && (marker_content.is_some()
+                    || style.list_style_type != crate::style::ListStyleTypeMode::None);
+            if let Some(value) = list_item_value(&info, Some(&list_info)) {
+                index = value;
+            }
+            let item_number = info
+                .attrs
+                .get(COUNTER_LIST_ITEM_ATTR)
+                .and_then(|value| value.parse::<i32>().ok())
+                .unwrap_or(index);
 
             let mut li_ancestors = ancestors.to_vec();
             li_ancestors.push(info.clone());
@@ -2218,14 +2791,12 @@ fn list_flowables(
             );
 
             let li_flowable: Box<dyn Flowable> = if show_marker {
-                let prefix = if ordered {
-                    let label = format!("{}. ", index);
-                    index += 1;
-                    label
-                } else {
-                    "\u{2022} ".to_string()
-                };
-                let text_style = style.to_text_style();
+                let prefix = marker_content
+                    .unwrap_or_else(|| list_marker(item_number, style.list_style_type, ordered));
+                index += 1;
+                // `::marker` rules restyle the label; the item's own style otherwise.
+                let label_style = marker_style.as_ref().unwrap_or(&style);
+                let text_style = label_style.to_text_style();
                 report_missing_glyphs(
                     report.as_deref_mut(),
                     font_registry.as_deref(),
@@ -2285,6 +2856,35 @@ fn container_flowables(children: Vec<LayoutItem>, style: &ComputedStyle) -> Vec<
     container_flowables_with_role(children, style, None)
 }
 
+// Points per CSS pixel, for sizing background images from their pixel dimensions.
+const PT_PER_PX: f32 = 0.75;
+
+// Resolves `background-image: url(...)` against the asset bundle and records the image's
+// size. SVG and unresolvable sources are dropped so nothing broken is drawn.
+fn resolve_background_image(style: &mut ComputedStyle, bundle: Option<&AssetBundle>) {
+    use image::GenericImageView;
+
+    let Some(source) = style.background_image.take() else {
+        return;
+    };
+    let resolved = resolve_image_asset(bundle, &source);
+    if !resolved.trace.success || resolved.trace.content_kind == "svg" {
+        return;
+    }
+    let Some(renderable) = renderable_image_source(bundle, &source) else {
+        return;
+    };
+    style.background_image_size = image::load_from_memory(&resolved.bytes)
+        .ok()
+        .map(|decoded| decoded.dimensions())
+        .filter(|(w, h)| *w > 0 && *h > 0)
+        .map(|(w, h)| Size {
+            width: Pt::from_f32(w as f32 * PT_PER_PX),
+            height: Pt::from_f32(h as f32 * PT_PER_PX),
+        });
+    style.background_image = Some(renderable);
+}
+
 fn establishes_abs_containing_block(style: &ComputedStyle) -> bool {
     !matches!(style.position, PositionMode::Static) || !style.transform.is_empty()
 }
@@ -2298,8 +2898,10 @@ fn container_flowable_with_role(
         || !matches!(style.height, LengthSpec::Auto)
         || style.background_color.is_some()
         || style.background_paint.is_some()
+        || style.background_image.is_some()
         || style.clip_path_inset.is_some()
         || style.box_shadow.is_some()
+        || style.outline_style.is_some()
         || style.paint_filter.is_some()
         || style.backdrop_filter.is_some()
         || !matches!(style.mix_blend_mode, crate::types::MixBlendMode::Normal)
@@ -2309,7 +2911,10 @@ fn container_flowable_with_role(
     if children.is_empty() && !has_box {
         // Preserve page-break semantics even for empty elements.
         if style.pagination.break_before != BreakBefore::Auto
-            || style.pagination.break_after != BreakAfter::Auto
+            || matches!(
+                style.pagination.break_after,
+                BreakAfter::Page | BreakAfter::Frame
+            )
         {
             let mut container =
                 ContainerFlowable::new_pt(Vec::new(), style.font_size, style.root_font_size)
@@ -2330,7 +2935,17 @@ fn container_flowable_with_role(
         _ => None,
     };
 
-    let flowables = layout_children_to_flowables(children, forced_line_height);
+    let mut flowables = layout_children_to_flowables(children, forced_line_height);
+    if style.column_count > 1 && !flowables.is_empty() {
+        let columns = MultiColumnFlowable::new_pt(
+            flowables,
+            style.column_count,
+            style.gap,
+            style.font_size,
+            style.root_font_size,
+        );
+        flowables = vec![Box::new(columns) as Box<dyn Flowable>];
+    }
     let mut container = ContainerFlowable::new_pt(flowables, style.font_size, style.root_font_size)
         .with_establishes_abs_containing_block(establishes_abs_containing_block(style))
         .with_margin(style.margin)
@@ -2352,8 +2967,10 @@ fn container_flowable_with_role(
         .with_height(style.height)
         .with_background(style.background_color)
         .with_background_paint(style.background_paint.clone())
+        .with_background_image(style.background_image_layer())
         .with_clip_path_inset(style.clip_path_inset)
         .with_box_shadow(style.box_shadow.clone())
+        .with_outline(style.outline())
         .with_paint_filter(style.paint_filter)
         .with_backdrop_filter(style.backdrop_filter)
         .with_mix_blend_mode(style.mix_blend_mode)
@@ -2361,6 +2978,9 @@ fn container_flowable_with_role(
         .with_transform_origin(style.transform_origin)
         .with_overflow_hidden(matches!(style.overflow, OverflowMode::Hidden))
         .with_pagination(style.pagination);
+    if role == Some("L") && style.orphans_widows_declared {
+        container = container.with_min_items(style.pagination.orphans, style.pagination.widows);
+    }
     if let Some(role) = role {
         container = container.with_tag_role(role);
     }
@@ -2491,7 +3111,8 @@ fn layout_children_to_flowables(
     let mut out: Vec<Box<dyn Flowable>> = Vec::new();
     let mut inline_group: Vec<(Box<dyn Flowable>, VerticalAlign)> = Vec::new();
 
-    for item in items {
+    let mut items = items.into_iter();
+    while let Some(item) = items.next() {
         match item {
             LayoutItem::Inline {
                 flowable, valign, ..
@@ -2505,6 +3126,12 @@ fn layout_children_to_flowables(
                     )));
                     inline_group = Vec::new();
                 }
+                if flowable.float_side().is_some() {
+                    // Everything after a float in this container flows around it.
+                    let content = layout_children_to_flowables(items.collect(), forced_line_height);
+                    out.push(Box::new(FloatAreaFlowable::new(flowable, content)));
+                    return out;
+                }
                 out.push(flowable);
             }
         }
@@ -2521,7 +3148,14 @@ fn layout_children_to_flowables(
     out
 }
 
-fn wrap_absolute(flowables: Vec<LayoutItem>, style: &ComputedStyle) -> Vec<LayoutItem> {
+// Absolutely positioned content taller than the page continues on the next page.
+const CONTINUE_OVERFLOW_ATTR: &str = "data-fb-continue-overflow";
+
+fn wrap_absolute(
+    flowables: Vec<LayoutItem>,
+    style: &ComputedStyle,
+    continue_overflow: bool,
+) -> Vec<LayoutItem> {
     if flowables.is_empty() {
         return Vec::new();
     }
@@ -2550,7 +3184,8 @@ fn wrap_absolute(flowables: Vec<LayoutItem>, style: &ComputedStyle) -> Vec<Layou
         style.root_font_size,
     )
     .with_pagination(style.pagination)
-    .with_fixed_positioned(matches!(style.position, PositionMode::Fixed));
+    .with_fixed_positioned(matches!(style.position, PositionMode::Fixed))
+    .with_overflow_continuation(continue_overflow);
     vec![LayoutItem::Block {
         flowable: Box::new(abs) as Box<dyn Flowable>,
         flex_grow: style.flex_grow,
@@ -2560,6 +3195,37 @@ fn wrap_absolute(flowables: Vec<LayoutItem>, style: &ComputedStyle) -> Vec<Layou
     }]
 }
 
+fn wrap_float(items: Vec<LayoutItem>, style: &ComputedStyle) -> Vec<LayoutItem> {
+    let side = match style.float {
+        FloatMode::Left => FloatSide::Left,
+        FloatMode::Right => FloatSide::Right,
+        FloatMode::None => return items,
+    };
+    if items.is_empty() {
+        return items;
+    }
+    let boxed: Box<dyn Flowable> = if items.len() == 1 {
+        match items.into_iter().next().unwrap() {
+            LayoutItem::Block { flowable, .. } => flowable,
+            LayoutItem::Inline { flowable, .. } => flowable,
+        }
+    } else {
+        let flowables = layout_children_to_flowables(items, None);
+        Box::new(ContainerFlowable::new_pt(
+            flowables,
+            style.font_size,
+            style.root_font_size,
+        ))
+    };
+    vec![LayoutItem::Block {
+        flowable: Box::new(FloatFlowable::new(boxed, side)) as Box<dyn Flowable>,
+        flex_grow: style.flex_grow,
+        flex_shrink: style.flex_shrink,
+        width_spec: flex_item_basis(style),
+        order: style.order,
+    }]
+}
+
 fn wrap_relative(flowables: Vec<LayoutItem>, style: &ComputedStyle) -> Vec<LayoutItem> {
     if flowables.is_empty() {
         return Vec::new();
@@ -2585,6 +3251,7 @@ fn wrap_relative(flowables: Vec<LayoutItem>, style: &ComputedStyle) -> Vec<Layou
         style.font_size,
         style.root_font_size,
     )
+    .with_z_index(style.z_index)
     .with_pagination(style.pagination);
     vec![LayoutItem::Block {
         flowable: Box::new(rel) as Box<dyn Flowable>,
@@ -2861,8 +3528,10 @@ fn flex_container_flowables(
             .with_height(style.height)
             .with_background(style.background_color)
             .with_background_paint(style.background_paint.clone())
+            .with_background_image(style.background_image_layer())
             .with_clip_path_inset(style.clip_path_inset)
             .with_box_shadow(style.box_shadow.clone())
+            .with_outline(style.outline())
             .with_paint_filter(style.paint_filter)
             .with_backdrop_filter(style.backdrop_filter)
             .with_mix_blend_mode(style.mix_blend_mode)
@@ -3388,6 +4057,7 @@ fn table_flowable(
     let mut report = report;
     let mut header_rows: Vec<Vec<TableCell>> = Vec::new();
     let mut body_rows: Vec<Vec<TableCell>> = Vec::new();
+    let mut footer_rows: Vec<Vec<TableCell>> = Vec::new();
     let mut body_row_meta: Vec<Vec<(String, String)>> = Vec::new();
     let mut row_style_ms = 0.0;
     let mut cell_style_ms = 0.0;
@@ -3405,7 +4075,7 @@ fn table_flowable(
     let mut row_count = 0u64;
     let mut cell_count = 0u64;
     let mut text_chars = 0u64;
-    let t_table = std::time::Instant::now();
+    let t_table = crate::platform::Instant::now();
 
     fn length_spec_is_zero(spec: LengthSpec) -> bool {
         match spec {
@@ -3496,6 +4166,8 @@ fn table_flowable(
     let mut prev_row_infos: Vec<ElementInfo> = Vec::new();
     let mut header_index = 0usize;
     let mut body_index = 0usize;
+    let mut row_span_cover = 0usize;
+    let mut body_min_rows: Option<(usize, usize)> = None;
 
     for (row, is_header) in rows {
         row_count = row_count.saturating_add(1);
@@ -3537,13 +4209,20 @@ fn table_flowable(
                 _ => None,
             }
         });
+        // `tfoot` rows repeat at the bottom of every table fragment. They skip the body
+        // style caches, which assume a `tbody` parent.
+        let is_footer = section_context
+            .as_ref()
+            .is_some_and(|(section, _)| section.tag == "tfoot");
         let mut pushed_section = false;
         let mut can_cache_section = true;
         let section_style_owned: Option<ComputedStyle> =
             if let Some((section, inline_style)) = section_context {
-                can_cache_section =
-                    section.id.is_none() && section.classes.is_empty() && inline_style.is_none();
-                let t_section_style = std::time::Instant::now();
+                can_cache_section = section.id.is_none()
+                    && section.classes.is_empty()
+                    && inline_style.is_none()
+                    && !is_footer;
+                let t_section_style = crate::platform::Instant::now();
                 let computed =
                     resolver.compute_style(&section, style, inline_style.as_deref(), ancestors);
                 row_style_ms += t_section_style.elapsed().as_secs_f64() * 1000.0;
@@ -3554,6 +4233,23 @@ fn table_flowable(
                 None
             };
         let row_parent_style: &ComputedStyle = section_style_owned.as_ref().unwrap_or(style);
+        if !is_header
+            && !is_footer
+            && body_min_rows.is_none()
+            && let (Some(section_style), Some(section)) =
+                (section_style_owned.as_ref(), row.parent())
+        {
+            let min_rows = section
+                .as_element()
+                .filter(|el| el.name.local.as_ref() == "tbody")
+                .and_then(|el| {
+                    el.attributes
+                        .borrow()
+                        .get(MIN_ROWS_ATTR)
+                        .map(str::to_string)
+                });
+            body_min_rows = fragment_minimums(min_rows.as_deref(), section_style);
+        }
         if is_header {
             header_index += 1;
         } else {
@@ -3568,7 +4264,7 @@ fn table_flowable(
         let row_info = row
             .as_element()
             .map(|_| {
-                let t_info = std::time::Instant::now();
+                let t_info = crate::platform::Instant::now();
                 let base_info =
                     element_info_basic(&row, row_child_index, row_child_count, false, Vec::new());
                 let info = if include_prev_siblings {
@@ -3617,7 +4313,7 @@ fn table_flowable(
         let row_style_tmp = if can_cache_row {
             None
         } else {
-            let t_row_style = std::time::Instant::now();
+            let t_row_style = crate::platform::Instant::now();
             let computed = resolver.compute_style(
                 &row_info,
                 row_parent_style,
@@ -3639,7 +4335,7 @@ fn table_flowable(
             } else {
                 row_style_cache_miss = row_style_cache_miss.saturating_add(1);
             }
-            let t_row_style = std::time::Instant::now();
+            let t_row_style = crate::platform::Instant::now();
             let computed = slot.get_or_insert_with(|| {
                 resolver.compute_style(&row_info, row_parent_style, None, ancestors)
             });
@@ -3681,9 +4377,17 @@ fn table_flowable(
                 .and_then(|raw| raw.trim().parse::<usize>().ok())
                 .filter(|value| *value > 0)
                 .unwrap_or(1);
+            // `rowspan="0"` spans to the end of the row group.
+            let row_span = cell_el
+                .attributes
+                .borrow()
+                .get("rowspan")
+                .and_then(|raw| raw.trim().parse::<usize>().ok())
+                .map(|value| if value == 0 { usize::MAX } else { value })
+                .unwrap_or(1);
 
             let cell_info = {
-                let t_info = std::time::Instant::now();
+                let t_info = crate::platform::Instant::now();
                 let base_info =
                     element_info_basic(cell_child, cell_idx + 1, cell_total, false, Vec::new());
                 let info = if include_prev_siblings {
@@ -3726,21 +4430,21 @@ fn table_flowable(
                     } else {
                         cell_style_cache_miss = cell_style_cache_miss.saturating_add(1);
                     }
-                    let t_cell_style = std::time::Instant::now();
+                    let t_cell_style = crate::platform::Instant::now();
                     let st = slot.get_or_insert_with(|| {
                         resolver.compute_style(&cell_info, row_style, None, ancestors)
                     });
                     cell_style_ms += t_cell_style.elapsed().as_secs_f64() * 1000.0;
                     StyleRef::Borrowed(st)
                 } else {
-                    let t_cell_style = std::time::Instant::now();
+                    let t_cell_style = crate::platform::Instant::now();
                     let computed = resolver.compute_style(&cell_info, row_style, None, ancestors);
                     cell_style_ms += t_cell_style.elapsed().as_secs_f64() * 1000.0;
                     cell_style_cache_miss = cell_style_cache_miss.saturating_add(1);
                     StyleRef::Owned(computed)
                 }
             } else {
-                let t_cell_style = std::time::Instant::now();
+                let t_cell_style = crate::platform::Instant::now();
                 let computed = resolver.compute_style(
                     &cell_info,
                     row_style,
@@ -3816,7 +4520,7 @@ fn table_flowable(
             }
 
             if cell_content.is_none() {
-                let t_cell_text = std::time::Instant::now();
+                let t_cell_text = crate::platform::Instant::now();
                 let text = cell_child.text_contents();
                 cell_text_ms += t_cell_text.elapsed().as_secs_f64() * 1000.0;
                 let trimmed = text.trim();
@@ -3827,7 +4531,7 @@ fn table_flowable(
                 }
             }
 
-            let align = match cell_style.text_align {
+            let align = match cell_style.physical_text_align() {
                 crate::style::TextAlignMode::Center => TextAlign::Center,
                 crate::style::TextAlignMode::Right => TextAlign::Right,
                 _ => TextAlign::Left,
@@ -3859,7 +4563,7 @@ fn table_flowable(
 
             let text_style = cell_style.to_text_style();
             if !cell_text.is_empty() {
-                let t_report = std::time::Instant::now();
+                let t_report = crate::platform::Instant::now();
                 report_missing_glyphs(
                     report.as_deref_mut(),
                     font_registry.as_deref(),
@@ -3891,7 +4595,10 @@ fn table_flowable(
                 preserve_whitespace(cell_style.white_space),
                 no_wrap(cell_style.white_space),
             );
-            let mut cell = cell.with_row_min_height(row_min_height);
+            let mut cell = cell
+                .with_row_min_height(row_min_height)
+                .with_row_span(row_span)
+                .with_transforms(cell_style.transform.clone(), cell_style.transform_origin);
             if !matches!(
                 cell_style.width,
                 LengthSpec::Auto | LengthSpec::Inherit | LengthSpec::Initial
@@ -3912,11 +4619,19 @@ fn table_flowable(
 
         ancestors.pop();
 
-        if cells.is_empty() {
+        // A row can be made up entirely of slots covered by row spans from above.
+        let covered_by_span = row_span_cover > 0;
+        row_span_cover = row_span_cover.saturating_sub(1);
+        for cell in &cells {
+            row_span_cover = row_span_cover.max(cell.row_span() - 1);
+        }
+        if cells.is_empty() && !covered_by_span {
             continue;
         }
         if is_header {
             header_rows.push(cells);
+        } else if is_footer {
+            footer_rows.push(cells);
         } else {
             body_rows.push(cells);
             body_row_meta.push(row_meta);
@@ -3960,12 +4675,35 @@ fn table_flowable(
         );
     }
 
-    TableFlowable::new(body_rows)
+    let table = TableFlowable::new(body_rows)
         .with_header(header_rows)
         .repeat_header(true)
+        .with_footer(footer_rows)
+        .repeat_footer(true)
         .with_row_backgrounds(false)
         .with_body_row_meta(body_row_meta)
-        .with_pagination(style.pagination)
+        .with_pagination(style.pagination);
+    let table_min_rows = node.as_element().and_then(|el| {
+        el.attributes
+            .borrow()
+            .get(MIN_ROWS_ATTR)
+            .map(str::to_string)
+    });
+    match body_min_rows.or_else(|| fragment_minimums(table_min_rows.as_deref(), style)) {
+        Some((orphans, widows)) => table.with_min_rows(orphans, widows),
+        None => table,
+    }
+}
+
+// Attributes missing glyphs reported from here on to this element.
+fn note_glyph_report_element(
+    report: Option<&mut GlyphCoverageReport>,
+    ancestors: &[ElementInfo],
+    info: &ElementInfo,
+) {
+    if let Some(report) = report {
+        report.set_element_path(Some(dom_path_for_node(ancestors, info)));
+    }
 }
 
 fn report_missing_glyphs(
@@ -4255,6 +4993,689 @@ fn resolve_svg_dimensions(
     )
 }
 
+// Anchor names an element exposes to `href="#..."` links: its id, plus the legacy
+// `<a name>` form.
+fn element_anchor_names(info: &ElementInfo) -> Vec<NamedAnchor> {
+    anchor_names_from_attrs(&info.tag, |name| info.attrs.get(name).map(String::as_str))
+}
+
+// `id` (and `<a name>`) anchors resolve internal links; `data-fb-dest` additionally exports
+// the position as a PDF named destination.
+fn anchor_names_from_attrs<'a>(
+    tag: &str,
+    attr: impl Fn(&str) -> Option<&'a str>,
+) -> Vec<NamedAnchor> {
+    let mut out: Vec<NamedAnchor> = Vec::new();
+    let mut push = |anchor: NamedAnchor| {
+        if !anchor.name.is_empty() && !out.contains(&anchor) {
+            out.push(anchor);
+        }
+    };
+    if let Some(id) = attr("id") {
+        push(NamedAnchor::internal(id.trim()));
+    }
+    if tag.eq_ignore_ascii_case("a")
+        && let Some(name) = attr("name")
+    {
+        push(NamedAnchor::internal(name.trim()));
+    }
+    if let Some(dest) = attr("data-fb-dest") {
+        push(NamedAnchor::exported(dest.trim()));
+    }
+    out
+}
+
+const FOOTNOTE_NUMBER_ATTR: &str = "data-fb-footnote-number";
+
+fn is_footnote_element(node: &NodeRef) -> bool {
+    let Some(element) = node.as_element() else {
+        return false;
+    };
+    let attrs = element.attributes.borrow();
+    attrs.contains("data-fb-footnote")
+        || (element.name.local.as_ref().eq_ignore_ascii_case("span")
+            && attrs
+                .get("class")
+                .is_some_and(|class| class.split_whitespace().any(|c| c == "footnote")))
+}
+
+fn is_numbered_footnote(node: &NodeRef) -> bool {
+    node.as_element()
+        .is_some_and(|element| element.attributes.borrow().contains(FOOTNOTE_NUMBER_ATTR))
+}
+
+// Number footnote bodies (`<span class="footnote">` or `data-fb-footnote`) in document order
+// and leave the call mark in the text they were written in. Footnotes nested in another
+// footnote stay part of the outer body.
+fn number_footnotes(document: &NodeRef) {
+    let bodies = document
+        .descendants()
+        .filter(|node| {
+            is_footnote_element(node) && !node.ancestors().any(|a| is_footnote_element(&a))
+        })
+        .collect::<Vec<_>>();
+    for (idx, body) in bodies.iter().enumerate() {
+        let number = idx + 1;
+        if let Some(element) = body.as_element() {
+            element
+                .attributes
+                .borrow_mut()
+                .insert(FOOTNOTE_NUMBER_ATTR, number.to_string());
+        }
+        body.insert_before(NodeRef::new_text(footnote_mark(number)));
+    }
+}
+
+// Marks a running element already taken out of the flow, so rendering its markup inside a
+// margin box lays it out in place.
+const RUNNING_PLACED_ATTR: &str = "data-fb-running-placed";
+
+// A `position: running(name)` element leaves the flow. A carrier records its markup where it
+// would have been placed, so `element(name)` in a margin box reads it for that page.
+fn running_element_item(node: &NodeRef, name: &str) -> LayoutItem {
+    if let Some(element) = node.as_element() {
+        element
+            .attributes
+            .borrow_mut()
+            .insert(RUNNING_PLACED_ATTR, String::new());
+    }
+    let carrier = Spacer::new_pt(Pt::from_f32(0.01));
+    let meta = vec![(format!("{RUNNING_META_PREFIX}{name}"), node.to_string())];
+    LayoutItem::Block {
+        flowable: Box::new(MetaFlowable::new(Box::new(carrier), meta)) as Box<dyn Flowable>,
+        flex_grow: 0.0,
+        flex_shrink: 1.0,
+        width_spec: None,
+        order: 0,
+    }
+}
+
+fn string_set_value(node: &NodeRef, parts: &[StringSetPart]) -> String {
+    parts
+        .iter()
+        .map(|part| match part {
+            StringSetPart::Text(text) => text.clone(),
+            StringSetPart::Content => node
+                .text_contents()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" "),
+        })
+        .collect()
+}
+
+const PAGE_START_ATTR: &str = "data-fb-page-start";
+// Threads a top-level block into the named page frame instead of the main story.
+const FRAME_ATTR: &str = "data-fb-frame";
+
+// Mark each block whose `page` differs from the page in effect before it with the name it
+// switches to (empty for the unnamed page). Layout starts a new page there.
+fn resolve_page_starts(document: &NodeRef, resolver: &StyleResolver) {
+    let mut current = String::new();
+    let mut ancestors = Vec::new();
+    resolve_page_starts_in(
+        document,
+        resolver,
+        &resolver.default_style(),
+        &mut ancestors,
+        &mut current,
+    );
+}
+
+fn resolve_page_starts_in(
+    node: &NodeRef,
+    resolver: &StyleResolver,
+    parent_style: &ComputedStyle,
+    ancestors: &mut Vec<ElementInfo>,
+    current: &mut String,
+) {
+    for child in node.children() {
+        let Some(element) = child.as_element() else {
+            continue;
+        };
+        let info = element_info(&child, resolver.has_sibling_selectors());
+        let inline_style = element
+            .attributes
+            .borrow()
+            .get("style")
+            .map(|s| s.to_string());
+        let style = resolver.compute_style(&info, parent_style, inline_style.as_deref(), ancestors);
+        if matches!(style.display, DisplayMode::None) {
+            continue;
+        }
+        // Inline boxes cannot start a page, but `html` and `body` compute to inline here and
+        // hold the blocks that can.
+        let page = style.page.clone().unwrap_or_default();
+        if page != *current && !matches!(style.display, DisplayMode::Inline) {
+            element
+                .attributes
+                .borrow_mut()
+                .insert(PAGE_START_ATTR, page.clone());
+            *current = page;
+        }
+        ancestors.push(info);
+        resolve_page_starts_in(&child, resolver, &style, ancestors, current);
+        ancestors.pop();
+    }
+}
+
+const COUNTER_LIST_ITEM_ATTR: &str = "data-fb-list-item";
+const COUNTER_BEFORE_ATTR: &str = "data-fb-counter-before";
+const COUNTER_AFTER_ATTR: &str = "data-fb-counter-after";
+const COUNTER_MARKER_ATTR: &str = "data-fb-counter-marker";
+
+// Walk the document in order applying `counter-reset`/`counter-increment`/`counter-set` (with
+// the implicit `list-item` counter of lists), and record on each element its list item number
+// and any generated content that reads counters. Runs only when the CSS uses counters.
+fn resolve_counters(document: &NodeRef, resolver: &StyleResolver) {
+    let mut counters = CounterState::new();
+    let mut ancestors = Vec::new();
+    resolve_counters_in(
+        document,
+        resolver,
+        &resolver.default_style(),
+        &mut ancestors,
+        &mut counters,
+    );
+}
+
+fn resolve_counters_in(
+    node: &NodeRef,
+    resolver: &StyleResolver,
+    parent_style: &ComputedStyle,
+    ancestors: &mut Vec<ElementInfo>,
+    counters: &mut CounterState,
+) {
+    for child in node.children() {
+        let Some(element) = child.as_element() else {
+            continue;
+        };
+        let info = element_info(&child, resolver.has_sibling_selectors());
+        let inline_style = element
+            .attributes
+            .borrow()
+            .get("style")
+            .map(|s| s.to_string());
+        let style = resolver.compute_style(&info, parent_style, inline_style.as_deref(), ancestors);
+        if matches!(style.display, DisplayMode::None) {
+            continue;
+        }
+        apply_counter_properties(&info, ancestors.last(), &style, counters);
+
+        let mut resolved: Vec<(&str, String)> = Vec::new();
+        if info.tag == "li" {
+            resolved.push((
+                COUNTER_LIST_ITEM_ATTR,
+                counters.value("list-item").to_string(),
+            ));
+        }
+        for (pseudo, attr) in [
+            (crate::style::PseudoTarget::Marker, COUNTER_MARKER_ATTR),
+            (crate::style::PseudoTarget::Before, COUNTER_BEFORE_ATTR),
+        ] {
+            if let Some(text) =
+                counter_content(resolver, &info, &style, ancestors, pseudo, counters)
+            {
+                resolved.push((attr, text));
+            }
+        }
+
+        counters.enter();
+        ancestors.push(info);
+        resolve_counters_in(&child, resolver, &style, ancestors, counters);
+        let info = ancestors.pop().expect("counter walk ancestor");
+        // `::after` follows the element's content, so it sees the counters its children set.
+        if let Some(text) = counter_content(
+            resolver,
+            &info,
+            &style,
+            ancestors,
+            crate::style::PseudoTarget::After,
+            counters,
+        ) {
+            resolved.push((COUNTER_AFTER_ATTR, text));
+        }
+        counters.exit();
+
+        let mut attrs = element.attributes.borrow_mut();
+        for (name, value) in resolved {
+            attrs.insert(name, value);
+        }
+    }
+}
+
+fn apply_counter_properties(
+    info: &ElementInfo,
+    parent: Option<&ElementInfo>,
+    style: &ComputedStyle,
+    counters: &mut CounterState,
+) {
+    // Lists reset `list-item` unless the author replaced `counter-reset`; `<ol start>` sets
+    // the first number.
+    if matches!(info.tag.as_str(), "ol" | "ul" | "menu") && style.counter_reset.is_empty() {
+        let start = ordered_list_start(info).unwrap_or(1);
+        counters.reset("list-item", start.saturating_sub(1));
+    }
+    for (name, value) in &style.counter_reset {
+        counters.reset(name, *value);
+    }
+    if info.tag == "li"
+        && !style
+            .counter_increment
+            .iter()
+            .any(|(name, _)| name == "list-item")
+    {
+        counters.increment("list-item", 1);
+    }
+    for (name, by) in &style.counter_increment {
+        counters.increment(name, *by);
+    }
+    // `<li value>` in an ordered list renumbers the item; the items after it follow on.
+    if let Some(value) = list_item_value(info, parent) {
+        counters.set("list-item", value);
+    }
+    for (name, value) in &style.counter_set {
+        counters.set(name, *value);
+    }
+}
+
+fn ordered_list_start(info: &ElementInfo) -> Option<i32> {
+    if info.tag != "ol" {
+        return None;
+    }
+    info.attrs.get("start")?.trim().parse().ok()
+}
+
+fn list_item_value(info: &ElementInfo, parent: Option<&ElementInfo>) -> Option<i32> {
+    if info.tag != "li" || parent.is_none_or(|parent| parent.tag != "ol") {
+        return None;
+    }
+    info.attrs.get("value")?.trim().parse().ok()
+}
+
+fn counter_content(
+    resolver: &StyleResolver,
+    info: &ElementInfo,
+    style: &ComputedStyle,
+    ancestors: &[ElementInfo],
+    pseudo: crate::style::PseudoTarget,
+    counters: &CounterState,
+) -> Option<String> {
+    let pseudo_style = resolver.compute_pseudo_style(info, style, ancestors, pseudo)?;
+    let content = pseudo_style.content?;
+    content
+        .contains(COUNTER_MARK)
+        .then(|| expand_counters(&content, counters))
+}
+
+// Generated content with its counter references replaced by the values `resolve_counters`
+// recorded for the element.
+fn generated_content(
+    info: &ElementInfo,
+    pseudo: crate::style::PseudoTarget,
+    content: String,
+) -> String {
+    if !content.contains(COUNTER_MARK) {
+        return content;
+    }
+    let attr = match pseudo {
+        crate::style::PseudoTarget::Before => COUNTER_BEFORE_ATTR,
+        crate::style::PseudoTarget::After => COUNTER_AFTER_ATTR,
+        crate::style::PseudoTarget::Marker => COUNTER_MARKER_ATTR,
+        crate::style::PseudoTarget::None => return content,
+    };
+    match info.attrs.get(attr) {
+        Some(text) => text.clone(),
+        None => expand_counters(&content, &CounterState::new()),
+    }
+}
+
+fn footnote_mark(number: usize) -> String {
+    format!("[{number}]")
+}
+
+fn footnote_number(info: &ElementInfo) -> Option<usize> {
+    info.attrs.get(FOOTNOTE_NUMBER_ATTR)?.parse().ok()
+}
+
+// Footnote body as a single paragraph prefixed with its mark. Nested markup is flattened.
+fn footnote_item(
+    node: &NodeRef,
+    number: usize,
+    style: &ComputedStyle,
+    font_registry: Option<Arc<FontRegistry>>,
+    report: Option<&mut GlyphCoverageReport>,
+) -> LayoutItem {
+    let body = apply_text_transform(&extract_text(node, style.white_space), style.text_transform);
+    let text = format!("{} {}", footnote_mark(number), body);
+    let text_style = style.to_text_style();
+    report_missing_glyphs(report, font_registry.as_deref(), &text_style, &text);
+    let paragraph = Paragraph::new(text)
+        .with_style(text_style)
+        .with_align(text_align_from_style(style))
+        .with_whitespace(
+            preserve_whitespace(style.white_space),
+            no_wrap(style.white_space),
+        )
+        .with_font_registry(font_registry)
+        .with_tag_role("Note");
+    LayoutItem::Block {
+        flowable: Box::new(FootnoteFlowable::new(Box::new(paragraph))) as Box<dyn Flowable>,
+        flex_grow: 0.0,
+        flex_shrink: 1.0,
+        width_spec: None,
+        order: 0,
+    }
+}
+
+// Footnote bodies inside text that is flattened into one paragraph, in document order.
+fn descendant_footnote_items(
+    node: &NodeRef,
+    resolver: &StyleResolver,
+    parent_style: &ComputedStyle,
+    ancestors: &[ElementInfo],
+    font_registry: Option<Arc<FontRegistry>>,
+    report: Option<&mut GlyphCoverageReport>,
+) -> Vec<LayoutItem> {
+    let mut report = report;
+    let mut out = Vec::new();
+    for child in node.descendants() {
+        let Some(element) = child.as_element() else {
+            continue;
+        };
+        if !is_numbered_footnote(&child) {
+            continue;
+        }
+        let info = element_info(&child, resolver.has_sibling_selectors());
+        let Some(number) = footnote_number(&info) else {
+            continue;
+        };
+        let inline_style = element
+            .attributes
+            .borrow()
+            .get("style")
+            .map(|s| s.to_string());
+        let style = resolver.compute_style(&info, parent_style, inline_style.as_deref(), ancestors);
+        if matches!(style.display, DisplayMode::None) {
+            continue;
+        }
+        out.push(footnote_item(
+            &child,
+            number,
+            &style,
+            font_registry.clone(),
+            report.as_deref_mut(),
+        ));
+    }
+    out
+}
+
+fn heading_level(tag: &str) -> Option<u8> {
+    match tag {
+        "h1" => Some(1),
+        "h2" => Some(2),
+        "h3" => Some(3),
+        "h4" => Some(4),
+        "h5" => Some(5),
+        "h6" => Some(6),
+        _ => None,
+    }
+}
+
+fn element_link_target(info: &ElementInfo) -> Option<LinkTarget> {
+    if info.tag != "a" {
+        return None;
+    }
+    href_link_target(info.attrs.get("href")?)
+}
+
+// `#name` links to an anchor in the same document; absolute URIs become URI actions.
+// Relative references have no base to resolve against and script schemes are never
+// emitted, so both produce no link. A one-letter scheme is a Windows drive (`C:\docs`).
+fn href_link_target(href: &str) -> Option<LinkTarget> {
+    let href = href.trim();
+    if let Some(fragment) = href.strip_prefix('#') {
+        if fragment.is_empty() {
+            return None;
+        }
+        return Some(LinkTarget::Internal(fragment.to_string()));
+    }
+    let (scheme, rest) = href.split_once(':')?;
+    let mut chars = scheme.chars();
+    let valid_scheme = scheme.len() >= 2
+        && chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    if !valid_scheme || rest.is_empty() {
+        return None;
+    }
+    let scheme = scheme.to_ascii_lowercase();
+    if matches!(scheme.as_str(), "javascript" | "vbscript" | "data") {
+        return None;
+    }
+    Some(LinkTarget::Uri(href.to_string()))
+}
+
+// Anchors on descendants whose text was flattened into a single paragraph. They resolve to
+// the paragraph origin.
+fn descendant_anchor_names(node: &NodeRef) -> Vec<NamedAnchor> {
+    let mut out = Vec::new();
+    for child in node.descendants() {
+        if let Some(element) = child.as_element() {
+            let attrs = element.attributes.borrow();
+            out.extend(anchor_names_from_attrs(
+                element.name.local.as_ref(),
+                |name| attrs.get(name),
+            ));
+        }
+    }
+    out
+}
+
+// Link spans for `<a href>` descendants of a flattened paragraph. Each link's own text
+// is located in order within the paragraph text, so pseudo content and prefixes are skipped.
+fn inline_text_links(
+    node: &NodeRef,
+    text: &str,
+    mode: WhiteSpaceMode,
+    transform: crate::style::TextTransformMode,
+) -> Vec<TextLinkSpan> {
+    let mut out = Vec::new();
+    let mut cursor = 0usize;
+    for child in node.descendants() {
+        let Some(element) = child.as_element() else {
+            continue;
+        };
+        if !element.name.local.as_ref().eq_ignore_ascii_case("a") {
+            continue;
+        }
+        let target = element
+            .attributes
+            .borrow()
+            .get("href")
+            .and_then(href_link_target);
+        let Some(target) = target else {
+            continue;
+        };
+        let link_text = apply_text_transform(&extract_text(&child, mode), transform);
+        if link_text.is_empty() {
+            continue;
+        }
+        if let Some(pos) = text.get(cursor..).and_then(|rest| rest.find(&link_text)) {
+            let start = cursor + pos;
+            cursor = start + link_text.len();
+            out.push(TextLinkSpan {
+                range: start..cursor,
+                target,
+            });
+        }
+    }
+    out
+}
+
+fn wrap_link_items(
+    items: Vec<LayoutItem>,
+    anchors: Vec<NamedAnchor>,
+    target: Option<LinkTarget>,
+) -> Vec<LayoutItem> {
+    if anchors.is_empty() && target.is_none() {
+        return items;
+    }
+    let mut anchors = Some(anchors);
+    items
+        .into_iter()
+        .map(|item| {
+            let item_anchors = anchors.take().unwrap_or_default();
+            let target = target.clone();
+            item.map_flowable(|flowable| {
+                Box::new(LinkFlowable::new(flowable, item_anchors, target)) as Box<dyn Flowable>
+            })
+        })
+        .collect()
+}
+
+// Attach a heading's outline entry to its first layout item.
+fn wrap_bookmark_items(items: Vec<LayoutItem>, level: u8, title: String) -> Vec<LayoutItem> {
+    if title.is_empty() {
+        return items;
+    }
+    let mut title = Some(title);
+    items
+        .into_iter()
+        .map(|item| match title.take() {
+            Some(title) => item.map_flowable(|flowable| {
+                Box::new(BookmarkFlowable::new(flowable, level, title)) as Box<dyn Flowable>
+            }),
+            None => item,
+        })
+        .collect()
+}
+
+// Element handed to a registered custom element factory (`FullBleedBuilder::register_element`).
+// The returned flowable becomes the element's content, inside its CSS box (margins, borders,
+// padding, breaks); returning `None` renders the element as usual.
+#[derive(Debug, Clone)]
+pub struct CustomElement {
+    pub tag: String,
+    pub attributes: std::collections::HashMap<String, String>,
+    // Text content with the element's `white-space` handling applied.
+    pub text: String,
+    pub font_size: Pt,
+    pub color: Color,
+}
+
+pub type CustomElementFactory =
+    Arc<dyn Fn(&CustomElement) -> Option<Box<dyn Flowable>> + Send + Sync>;
+
+const CHART_ATTR: &str = "data-fb-chart";
+
+// `data-fb-chart="bar|line|pie"` renders the element as a vector chart sized by its CSS
+// width/height. Categories come from `data-fb-chart-categories` (comma-separated) and the
+// title from `data-fb-chart-title`; each child with `data-fb-series="<name>"` adds a series
+// whose numbers are in its `value` attribute and whose color is its `background-color`.
+// Labels and axes use the chart element's font and `color`.
+fn chart_flowable(
+    node: &NodeRef,
+    kind: ChartKind,
+    style: &ComputedStyle,
+    resolver: &StyleResolver,
+    ancestors: &[ElementInfo],
+    font_registry: Option<Arc<FontRegistry>>,
+) -> ChartFlowable {
+    let resolve = |spec: LengthSpec, reference: f32, vertical: bool| {
+        if matches!(
+            spec,
+            LengthSpec::Auto | LengthSpec::Inherit | LengthSpec::Initial
+        ) {
+            return None;
+        }
+        let reference = Pt::from_f32(reference);
+        let resolved = if vertical {
+            spec.resolve_height(reference, style.font_size, style.root_font_size)
+        } else {
+            spec.resolve_width(reference, style.font_size, style.root_font_size)
+        };
+        (resolved > Pt::ZERO).then_some(resolved)
+    };
+    let width = resolve(style.width, 300.0, false).unwrap_or_else(|| Pt::from_f32(300.0));
+    let height = resolve(style.height, 150.0, true).unwrap_or_else(|| width.mul_ratio(3, 5));
+
+    let element = node.as_element().expect("element node");
+    let attrs = element.attributes.borrow();
+    let categories = attrs
+        .get("data-fb-chart-categories")
+        .map(|raw| {
+            raw.split(',')
+                .map(|category| category.trim().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    let title = attrs
+        .get("data-fb-chart-title")
+        .map(|s| s.trim().to_string());
+    let alt = attrs
+        .get("aria-label")
+        .or_else(|| attrs.get("title"))
+        .map(|s| s.to_string())
+        .or_else(|| title.clone());
+    let mut chart = ChartFlowable::new_pt(kind, width, height)
+        .with_categories(categories)
+        .with_title(title)
+        .with_text_style(style.to_text_style())
+        .with_font_registry(font_registry)
+        .with_pagination(style.pagination)
+        .with_alt(alt);
+    match attrs.get("data-fb-chart-legend").map(str::trim) {
+        Some("false") | Some("none") => chart = chart.with_legend(false),
+        Some("true") => chart = chart.with_legend(true),
+        _ => {}
+    }
+
+    for child in node.children() {
+        let Some(child_el) = child.as_element() else {
+            continue;
+        };
+        let child_attrs = child_el.attributes.borrow();
+        let Some(name) = child_attrs.get("data-fb-series") else {
+            continue;
+        };
+        // Unparseable entries keep their slot so later values stay aligned with categories.
+        let values = child_attrs
+            .get("value")
+            .unwrap_or("")
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|v| !v.is_empty())
+            .map(|v| v.parse::<f32>().unwrap_or(f32::NAN))
+            .collect();
+        let mut series = ChartSeries::new(name.trim(), values);
+        let child_info = element_info(&child, resolver.has_sibling_selectors());
+        let inline_style = child_attrs.get("style").map(|s| s.to_string());
+        let child_style =
+            resolver.compute_style(&child_info, style, inline_style.as_deref(), ancestors);
+        if let Some(color) = child_style.background_color {
+            series = series.with_color(color);
+        }
+        chart = chart.with_series(series);
+    }
+    chart
+}
+
+// Attach a `data-fb-signature-field` placeholder to the element's first layout item.
+fn wrap_signature_field_items(items: Vec<LayoutItem>, name: String) -> Vec<LayoutItem> {
+    if name.is_empty() {
+        return items;
+    }
+    let mut name = Some(name);
+    items
+        .into_iter()
+        .map(|item| match name.take() {
+            Some(name) => item.map_flowable(|flowable| {
+                Box::new(SignatureFieldFlowable::new(flowable, name)) as Box<dyn Flowable>
+            }),
+            None => item,
+        })
+        .collect()
+}
+
 fn extract_text(node: &NodeRef, mode: WhiteSpaceMode) -> String {
     let mut out = String::new();
     collect_text(node, &mut out);
@@ -4276,6 +5697,9 @@ fn collect_text(node: &NodeRef, out: &mut String) {
                 return;
             }
             for child in node.children() {
+                if is_numbered_footnote(&child) {
+                    continue;
+                }
                 collect_text(&child, out);
             }
         }
@@ -4410,6 +5834,24 @@ fn inline_dimensions(style: Option<&str>) -> (Option<Pt>, Option<Pt>) {
     (width, height)
 }
 
+// An SVG drawn as an image because it uses features the vector renderer lacks.
+fn warn_svg_rasterized(src: Option<&str>) {
+    let warning = crate::warning::Warning::for_loss(
+        crate::warning::WarningKind::KnownLoss,
+        crate::known_loss::KnownLoss::SvgRasterized {
+            source: src.map(str::to_string),
+        },
+        match src {
+            Some(src) => format!("SVG {src} rasterized; it uses features drawn only as pixels"),
+            None => "inline SVG rasterized; it uses features drawn only as pixels".to_string(),
+        },
+    );
+    crate::warning::emit_scoped(match src {
+        Some(src) => warning.details(vec![src.to_string()]),
+        None => warning,
+    });
+}
+
 fn size_to_points(size: &lightningcss::properties::size::Size) -> Option<Pt> {
     match size {
         lightningcss::properties::size::Size::LengthPercentage(value) => match value {
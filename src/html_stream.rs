@@ -0,0 +1,350 @@
+use std::io::{self, Read};
+
+// Incremental splitter for large HTML sources.
+//
+// The reader scans markup as it arrives and cuts the document body at top-level element
+// boundaries. Each emitted chunk is a standalone HTML document: the original prologue
+// (everything up to and including `<body ...>`) followed by one or more complete body
+// children. Chunks are cut once they reach the configured byte budget, so memory stays
+// bounded by the largest top-level element rather than the whole source.
+//
+// The scanner only tracks element depth; it does not validate markup. Elements whose end
+// tag is implied (e.g. an unclosed `<p>`) can keep the depth above zero, which delays the
+// next cut but never splits an element.
+
+const READ_BLOCK_BYTES: usize = 64 * 1024;
+
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style", "textarea", "title"];
+
+const PROLOGUE_ELEMENTS: &[&str] = &[
+    "html", "head", "meta", "title", "link", "style", "script", "base", "noscript",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ScanState {
+    Data,
+    TagOpen,
+    TagName { closing: bool },
+    InTag { closing: bool, quote: Option<u8> },
+    MarkupDeclaration,
+    Comment { dashes: usize },
+    RawText { name: String, matched: usize },
+}
+
+pub(crate) struct HtmlChunkReader<R: Read> {
+    reader: R,
+    chunk_bytes: usize,
+    pending: Vec<u8>,
+    scan_pos: usize,
+    state: ScanState,
+    tag_start: usize,
+    tag_name: String,
+    last_tag_byte: u8,
+    depth: usize,
+    prologue: Option<String>,
+    eof: bool,
+    body_closed: bool,
+    emitted_any: bool,
+}
+
+impl<R: Read> HtmlChunkReader<R> {
+    pub(crate) fn new(reader: R, chunk_bytes: usize) -> Self {
+        Self {
+            reader,
+            chunk_bytes: chunk_bytes.max(1),
+            pending: Vec::new(),
+            scan_pos: 0,
+            state: ScanState::Data,
+            tag_start: 0,
+            tag_name: String::new(),
+            last_tag_byte: 0,
+            depth: 0,
+            prologue: None,
+            eof: false,
+            body_closed: false,
+            emitted_any: false,
+        }
+    }
+
+    // Returns the next standalone HTML chunk, or `None` once the source is exhausted.
+    // At least one chunk is always produced so an empty body still lays out a page.
+    pub(crate) fn next_chunk(&mut self) -> io::Result<Option<String>> {
+        loop {
+            if self.body_closed || (self.eof && self.scan_pos >= self.pending.len()) {
+                return Ok(self.take_final_chunk());
+            }
+            if self.scan_pos >= self.pending.len() {
+                self.fill()?;
+                continue;
+            }
+            let byte = self.pending[self.scan_pos];
+            self.scan_pos += 1;
+            let boundary = self.step(byte);
+            if self.body_closed {
+                continue;
+            }
+            if boundary && self.scan_pos >= self.chunk_bytes {
+                let end = self.scan_pos;
+                return Ok(Some(self.take_chunk(end)));
+            }
+        }
+    }
+
+    fn fill(&mut self) -> io::Result<()> {
+        let mut block = [0u8; READ_BLOCK_BYTES];
+        loop {
+            match self.reader.read(&mut block) {
+                Ok(0) => {
+                    self.eof = true;
+                    return Ok(());
+                }
+                Ok(n) => {
+                    self.pending.extend_from_slice(&block[..n]);
+                    return Ok(());
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    fn take_final_chunk(&mut self) -> Option<String> {
+        let end = if self.body_closed {
+            self.tag_start
+        } else {
+            self.pending.len()
+        };
+        if self.prologue.is_none() {
+            // No <body> was seen: treat the whole source as a fragment.
+            self.prologue = Some(String::new());
+        }
+        let has_content = self.pending[..end].iter().any(|b| !b.is_ascii_whitespace());
+        if !has_content && self.emitted_any {
+            self.finish_source();
+            return None;
+        }
+        let chunk = self.take_chunk(end);
+        self.finish_source();
+        Some(chunk)
+    }
+
+    fn finish_source(&mut self) {
+        self.pending.clear();
+        self.scan_pos = 0;
+        self.eof = true;
+        self.body_closed = false;
+    }
+
+    fn take_chunk(&mut self, end: usize) -> String {
+        let prologue = self.prologue.as_deref().unwrap_or("");
+        let body = String::from_utf8_lossy(&self.pending[..end]);
+        let mut out = String::with_capacity(prologue.len() + body.len() + 16);
+        out.push_str(prologue);
+        out.push_str(&body);
+        if !prologue.is_empty() {
+            out.push_str("</body></html>");
+        }
+        self.pending.drain(..end);
+        self.scan_pos -= end;
+        self.tag_start = self.tag_start.saturating_sub(end);
+        self.emitted_any = true;
+        out
+    }
+
+    // Advance the scanner by one byte (already consumed, at `scan_pos - 1`). Returns true
+    // when the byte completes a top-level body child, i.e. the source can be cut after it.
+    fn step(&mut self, byte: u8) -> bool {
+        let pos = self.scan_pos - 1;
+        match &mut self.state {
+            ScanState::Data => {
+                if byte == b'<' {
+                    self.tag_start = pos;
+                    self.tag_name.clear();
+                    self.state = ScanState::TagOpen;
+                }
+                false
+            }
+            ScanState::TagOpen => {
+                match byte {
+                    b'!' | b'?' => self.state = ScanState::MarkupDeclaration,
+                    b'/' => self.state = ScanState::TagName { closing: true },
+                    b if b.is_ascii_alphabetic() => {
+                        self.tag_name.push(b.to_ascii_lowercase() as char);
+                        self.state = ScanState::TagName { closing: false };
+                    }
+                    _ => self.state = ScanState::Data,
+                }
+                false
+            }
+            ScanState::TagName { closing } => {
+                let closing = *closing;
+                if byte.is_ascii_alphanumeric() || byte == b'-' || byte == b':' {
+                    self.tag_name.push(byte.to_ascii_lowercase() as char);
+                    return false;
+                }
+                self.last_tag_byte = byte;
+                if byte == b'>' {
+                    return self.finish_tag(closing);
+                }
+                self.state = ScanState::InTag {
+                    closing,
+                    quote: None,
+                };
+                false
+            }
+            ScanState::InTag { closing, quote } => {
+                let closing = *closing;
+                match *quote {
+                    Some(q) => {
+                        if byte == q {
+                            *quote = None;
+                        }
+                    }
+                    None => match byte {
+                        b'"' | b'\'' => *quote = Some(byte),
+                        b'>' => return self.finish_tag(closing),
+                        b if !b.is_ascii_whitespace() => self.last_tag_byte = b,
+                        _ => {}
+                    },
+                }
+                false
+            }
+            ScanState::MarkupDeclaration => {
+                if byte == b'-' && pos == self.tag_start + 3 {
+                    if self.pending.get(pos - 1) == Some(&b'-') {
+                        self.state = ScanState::Comment { dashes: 0 };
+                    }
+                } else if byte == b'>' {
+                    self.state = ScanState::Data;
+                    return self.prologue.is_some() && self.depth == 0;
+                }
+                false
+            }
+            ScanState::Comment { dashes } => {
+                if byte == b'-' {
+                    *dashes += 1;
+                } else if byte == b'>' && *dashes >= 2 {
+                    self.state = ScanState::Data;
+                    return self.prologue.is_some() && self.depth == 0;
+                } else {
+                    *dashes = 0;
+                }
+                false
+            }
+            ScanState::RawText { name, matched } => {
+                // Looking for "</name" (case-insensitive); the tag itself is then scanned
+                // as a regular closing tag.
+                let needle_len = name.len() + 2;
+                let expected = match *matched {
+                    0 => b'<',
+                    1 => b'/',
+                    n => name.as_bytes()[n - 2],
+                };
+                if byte.to_ascii_lowercase() == expected {
+                    *matched += 1;
+                    if *matched == needle_len {
+                        self.tag_start = pos + 1 - needle_len;
+                        self.tag_name = std::mem::take(name);
+                        self.state = ScanState::TagName { closing: true };
+                    }
+                } else {
+                    *matched = usize::from(byte == b'<');
+                }
+                false
+            }
+        }
+    }
+
+    fn finish_tag(&mut self, closing: bool) -> bool {
+        let name = std::mem::take(&mut self.tag_name);
+        let self_closing = !closing && self.last_tag_byte == b'/';
+        self.state = ScanState::Data;
+
+        if self.prologue.is_none() {
+            if closing {
+                return false;
+            }
+            if name == "body" {
+                let end = self.scan_pos;
+                self.prologue = Some(String::from_utf8_lossy(&self.pending[..end]).into_owned());
+                self.pending.drain(..end);
+                self.scan_pos = 0;
+                self.tag_start = 0;
+                return false;
+            }
+            if PROLOGUE_ELEMENTS.contains(&name.as_str()) {
+                if RAW_TEXT_ELEMENTS.contains(&name.as_str()) && !self_closing {
+                    self.state = ScanState::RawText { name, matched: 0 };
+                }
+                return false;
+            }
+            // Content before any <body>: treat the source as a body fragment.
+            self.prologue = Some(String::new());
+        }
+
+        if closing {
+            if self.depth == 0 && (name == "body" || name == "html") {
+                self.body_closed = true;
+                return false;
+            }
+            self.depth = self.depth.saturating_sub(1);
+            return self.depth == 0;
+        }
+
+        if self_closing || VOID_ELEMENTS.contains(&name.as_str()) {
+            return self.depth == 0;
+        }
+        if RAW_TEXT_ELEMENTS.contains(&name.as_str()) {
+            self.state = ScanState::RawText { name, matched: 0 };
+        }
+        self.depth += 1;
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect_chunks(source: &str, chunk_bytes: usize) -> Vec<String> {
+        let mut reader = HtmlChunkReader::new(source.as_bytes(), chunk_bytes);
+        let mut chunks = Vec::new();
+        while let Some(chunk) = reader.next_chunk().expect("chunk") {
+            chunks.push(chunk);
+        }
+        chunks
+    }
+
+    #[test]
+    fn splits_body_at_top_level_boundaries() {
+        let source = "<!doctype html><html lang=\"en\"><head><title>a<b</title></head>\
+            <body class=\"doc\"><div><p>one</p><p>two</p></div><!-- x > y --><p>three</p>\
+            <script>if (a < b) { x = \"</div>\"; }</script><hr/><p>four</p></body></html>";
+        let chunks = collect_chunks(source, 1);
+        let prologue =
+            "<!doctype html><html lang=\"en\"><head><title>a<b</title></head><body class=\"doc\">";
+        assert_eq!(
+            chunks,
+            vec![
+                format!("{prologue}<div><p>one</p><p>two</p></div></body></html>"),
+                format!("{prologue}<!-- x > y --></body></html>"),
+                format!("{prologue}<p>three</p></body></html>"),
+                format!(
+                    "{prologue}<script>if (a < b) {{ x = \"</div>\"; }}</script></body></html>"
+                ),
+                format!("{prologue}<hr/></body></html>"),
+                format!("{prologue}<p>four</p></body></html>"),
+            ]
+        );
+
+        let whole = collect_chunks(source, 1 << 20);
+        assert_eq!(whole.len(), 1);
+        assert!(whole[0].contains("<p>four</p></body></html>"));
+        assert_eq!(collect_chunks("", 16), vec![String::new()]);
+    }
+}
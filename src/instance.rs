@@ -0,0 +1,328 @@
+// Static instances of variable TrueType fonts. PDF has no font variations, so a face at given
+// axis values is baked into a plain glyf font before it is measured, shaped or embedded:
+// outlines come from ttf-parser with the variation applied (composites are flattened and hinting
+// is dropped), advances include HVAR deltas, and the variation tables are removed. GPOS/GDEF
+// keep their default-instance values. CFF2 fonts are not instanced.
+use crate::subset::{SimpleGlyph, push_simple_glyph, read_tables, read_u16, write_sfnt};
+use ttf_parser::{GlyphId, OutlineBuilder, Tag};
+
+// Axis tag and user-space value, e.g. (*b"wght", 650.0).
+pub(crate) type AxisCoords = Vec<([u8; 4], f32)>;
+
+// Rebuilt from the instance (glyf, loca, hmtx) or invalid for a static font.
+const DROPPED_TABLES: [&[u8; 4]; 17] = [
+    b"glyf", b"loca", b"hmtx", b"fvar", b"gvar", b"avar", b"HVAR", b"VVAR", b"MVAR", b"STAT",
+    b"cvar", b"cvt ", b"fpgm", b"prep", b"hdmx", b"LTSH", b"VDMX",
+];
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct AxisRange {
+    pub(crate) tag: [u8; 4],
+    pub(crate) min: f32,
+    pub(crate) default: f32,
+    pub(crate) max: f32,
+}
+
+// Axes of a variable glyf font; empty for static and CFF2 fonts.
+pub(crate) fn axis_ranges(face: &ttf_parser::Face<'_>) -> Vec<AxisRange> {
+    if !face.is_variable() || face.tables().glyf.is_none() {
+        return Vec::new();
+    }
+    face.variation_axes()
+        .into_iter()
+        .map(|axis| AxisRange {
+            tag: axis.tag.to_bytes(),
+            min: axis.min_value,
+            default: axis.def_value,
+            max: axis.max_value,
+        })
+        .collect()
+}
+
+// Named instances from `fvar` as (subfamily name, coordinates), e.g. ("SemiBold", wght 600).
+pub(crate) fn named_instances(face: &ttf_parser::Face<'_>) -> Vec<(String, AxisCoords)> {
+    let axes = axis_ranges(face);
+    let Some(fvar) = face.raw_face().table(Tag::from_bytes(b"fvar")) else {
+        return Vec::new();
+    };
+    let (Some(axes_offset), Some(axis_count), Some(axis_size), Some(count), Some(size)) = (
+        read_u16(fvar, 4),
+        read_u16(fvar, 8),
+        read_u16(fvar, 10),
+        read_u16(fvar, 12),
+        read_u16(fvar, 14),
+    ) else {
+        return Vec::new();
+    };
+    if axes.is_empty() || axis_count as usize != axes.len() {
+        return Vec::new();
+    }
+    let first = axes_offset as usize + axis_count as usize * axis_size as usize;
+    (0..count as usize)
+        .filter_map(|index| {
+            let record = fvar.get(first + index * size as usize..)?;
+            let name_id = read_u16(record, 0)?;
+            let name = face
+                .names()
+                .into_iter()
+                .filter(|entry| entry.name_id == name_id)
+                .find_map(|entry| entry.to_string())?;
+            let coords = axes
+                .iter()
+                .enumerate()
+                .map(|(axis, range)| {
+                    let raw = record.get(4 + axis * 4..8 + axis * 4)?;
+                    let fixed = i32::from_be_bytes(raw.try_into().ok()?);
+                    Some((range.tag, fixed as f32 / 65536.0))
+                })
+                .collect::<Option<AxisCoords>>()?;
+            Some((name, coords))
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct Contours {
+    contours: Vec<Vec<(i32, i32, bool)>>,
+}
+
+impl Contours {
+    fn point(&mut self, x: f32, y: f32, on_curve: bool) {
+        if let Some(contour) = self.contours.last_mut() {
+            contour.push((x.round() as i32, y.round() as i32, on_curve));
+        }
+    }
+}
+
+impl OutlineBuilder for Contours {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.contours.push(Vec::new());
+        self.point(x, y, true);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.point(x, y, true);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        self.point(x1, y1, false);
+        self.point(x, y, true);
+    }
+
+    // glyf outlines are quadratic; a cubic can only come from a malformed font and is reduced to
+    // the single quadratic through its end points.
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (x0, y0) = self
+            .contours
+            .last()
+            .and_then(|contour| contour.last())
+            .map_or((x, y), |p| (p.0 as f32, p.1 as f32));
+        let cx = (3.0 * (x1 + x2) - x0 - x) / 4.0;
+        let cy = (3.0 * (y1 + y2) - y0 - y) / 4.0;
+        self.quad_to(cx, cy, x, y);
+    }
+
+    fn close(&mut self) {
+        if let Some(contour) = self
+            .contours
+            .last_mut()
+            .filter(|contour| contour.len() > 1 && contour.first() == contour.last())
+        {
+            contour.pop();
+        }
+    }
+}
+
+// Bake `coords` (clamped to each axis range) into a static TrueType font.
+pub(crate) fn instantiate(data: &[u8], coords: &[([u8; 4], f32)]) -> Option<Vec<u8>> {
+    let mut face = ttf_parser::Face::parse(data, 0).ok()?;
+    let axes = axis_ranges(&face);
+    if axes.is_empty() {
+        return None;
+    }
+    let value = |tag: &[u8; 4]| {
+        let range = axes.iter().find(|axis| &axis.tag == tag)?;
+        let value = coords
+            .iter()
+            .rev()
+            .find(|(axis, _)| axis == tag)
+            .map_or(range.default, |(_, value)| *value);
+        Some(value.clamp(range.min, range.max))
+    };
+    for axis in &axes {
+        face.set_variation(Tag::from_bytes(&axis.tag), value(&axis.tag)?)?;
+    }
+
+    let num_glyphs = face.number_of_glyphs();
+    let mut glyf = Vec::new();
+    let mut loca = Vec::with_capacity((num_glyphs as usize + 1) * 4);
+    let mut hmtx = Vec::with_capacity(num_glyphs as usize * 4);
+    let mut font_bbox = [i16::MAX, i16::MAX, i16::MIN, i16::MIN];
+    let (mut max_points, mut max_contours, mut max_advance) = (0usize, 0usize, 0u16);
+    let (mut min_lsb, mut min_rsb, mut max_extent) = (i16::MAX, i16::MAX, i16::MIN);
+    for gid in 0..num_glyphs {
+        loca.extend_from_slice(&(glyf.len() as u32).to_be_bytes());
+        let advance = face.glyph_hor_advance(GlyphId(gid)).unwrap_or(0);
+        let mut outline = Contours::default();
+        face.outline_glyph(GlyphId(gid), &mut outline);
+        outline.contours.retain(|contour| contour.len() > 1);
+        let points: Vec<(i32, i32, bool)> = outline.contours.concat();
+        let mut lsb = 0i16;
+        if !points.is_empty() {
+            let clamp = |v: i32| v.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+            let bbox = [
+                clamp(points.iter().map(|p| p.0).min()?),
+                clamp(points.iter().map(|p| p.1).min()?),
+                clamp(points.iter().map(|p| p.0).max()?),
+                clamp(points.iter().map(|p| p.1).max()?),
+            ];
+            let mut end = 0usize;
+            let end_points = outline
+                .contours
+                .iter()
+                .map(|contour| {
+                    end += contour.len();
+                    u16::try_from(end - 1).ok()
+                })
+                .collect::<Option<Vec<u16>>>()?;
+            push_simple_glyph(
+                &mut glyf,
+                &SimpleGlyph {
+                    end_points: &end_points,
+                    points: &points,
+                    bbox,
+                    instructions: &[],
+                    overlap: true,
+                },
+            );
+            while glyf.len() % 4 != 0 {
+                glyf.push(0);
+            }
+            lsb = bbox[0];
+            font_bbox = [
+                font_bbox[0].min(bbox[0]),
+                font_bbox[1].min(bbox[1]),
+                font_bbox[2].max(bbox[2]),
+                font_bbox[3].max(bbox[3]),
+            ];
+            max_points = max_points.max(points.len());
+            max_contours = max_contours.max(end_points.len());
+            min_lsb = min_lsb.min(bbox[0]);
+            min_rsb = min_rsb.min((advance as i32 - bbox[2] as i32) as i16);
+            max_extent = max_extent.max(bbox[2]);
+        }
+        max_advance = max_advance.max(advance);
+        hmtx.extend_from_slice(&advance.to_be_bytes());
+        hmtx.extend_from_slice(&lsb.to_be_bytes());
+    }
+    loca.extend_from_slice(&(glyf.len() as u32).to_be_bytes());
+    if font_bbox[0] > font_bbox[2] {
+        font_bbox = [0; 4];
+        (min_lsb, min_rsb, max_extent) = (0, 0, 0);
+    }
+
+    let mut tables: Vec<([u8; 4], Vec<u8>)> =
+        vec![(*b"glyf", glyf), (*b"loca", loca), (*b"hmtx", hmtx)];
+    for record in read_tables(data)? {
+        if DROPPED_TABLES.contains(&&record.tag) {
+            continue;
+        }
+        let mut table = record.data.to_vec();
+        let mut put = |at: usize, value: u16| {
+            if let Some(slot) = table.get_mut(at..at + 2) {
+                slot.copy_from_slice(&value.to_be_bytes());
+            }
+        };
+        match &record.tag {
+            b"head" => {
+                for (index, value) in font_bbox.iter().enumerate() {
+                    put(36 + index * 2, *value as u16);
+                }
+                put(50, 1);
+            }
+            b"hhea" => {
+                put(10, max_advance);
+                put(12, min_lsb as u16);
+                put(14, min_rsb as u16);
+                put(16, max_extent as u16);
+                put(34, num_glyphs);
+            }
+            b"maxp" => {
+                put(6, max_points.min(u16::MAX as usize) as u16);
+                put(8, max_contours.min(u16::MAX as usize) as u16);
+                for at in [10, 12, 26, 28, 30] {
+                    put(at, 0);
+                }
+            }
+            b"OS/2" => {
+                if let Some(weight) = value(b"wght") {
+                    put(4, weight.round().clamp(1.0, 1000.0) as u16);
+                }
+                if let Some(width) = value(b"wdth") {
+                    put(6, width_class(width));
+                }
+            }
+            _ => {}
+        }
+        tables.push((record.tag, table));
+    }
+    Some(write_sfnt(0x0001_0000, tables))
+}
+
+// OS/2 usWidthClass for a `wdth` percentage (50 = ultra-condensed .. 200 = ultra-expanded).
+fn width_class(percent: f32) -> u16 {
+    const CLASSES: [f32; 9] = [50.0, 62.5, 75.0, 87.5, 100.0, 112.5, 125.0, 150.0, 200.0];
+    CLASSES
+        .iter()
+        .enumerate()
+        .min_by(|a, b| (a.1 - percent).abs().total_cmp(&(b.1 - percent).abs()))
+        .map_or(5, |(index, _)| index as u16 + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{axis_ranges, instantiate, named_instances};
+
+    fn inter() -> Vec<u8> {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("python/fullbleed_assets/fonts/Inter-Variable.ttf");
+        std::fs::read(path).expect("read inter")
+    }
+
+    #[test]
+    fn bold_instance_is_static_with_wider_stems() {
+        let data = inter();
+        let face = ttf_parser::Face::parse(&data, 0).expect("face");
+        let wght = axis_ranges(&face)
+            .into_iter()
+            .find(|axis| &axis.tag == b"wght")
+            .expect("wght axis");
+        assert!(wght.min < 400.0 && wght.max >= 700.0);
+        assert!(
+            named_instances(&face)
+                .iter()
+                .any(|(name, coords)| name == "Bold" && coords.contains(&(*b"wght", 700.0)))
+        );
+
+        let bold = instantiate(&data, &[(*b"wght", 700.0)]).expect("bold instance");
+        let bold_face = ttf_parser::Face::parse(&bold, 0).expect("instance parses");
+        assert!(!bold_face.is_variable());
+        assert_eq!(bold_face.number_of_glyphs(), face.number_of_glyphs());
+        let gid = bold_face.glyph_index('l').expect("l");
+        let regular = instantiate(&data, &[(*b"wght", 400.0)]).expect("regular instance");
+        let regular_face = ttf_parser::Face::parse(&regular, 0).expect("regular parses");
+        let stem = |face: &ttf_parser::Face<'_>| {
+            let bbox = face.glyph_bounding_box(gid).expect("outline");
+            bbox.x_max - bbox.x_min
+        };
+        assert!(stem(&bold_face) > stem(&regular_face));
+        assert!(
+            bold_face.glyph_hor_advance(gid) >= regular_face.glyph_hor_advance(gid),
+            "advances follow the weight"
+        );
+        assert_eq!(
+            bold_face.tables().os2.map(|os2| os2.weight().to_number()),
+            Some(700)
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use crate::canvas::{Command, Document, Page};
+use crate::command_buffer::CommandBuffer;
 use crate::debug::{DebugLogger, json_escape};
 use crate::font::FontRegistry;
 use crate::page_data::{PageDataContext, PageDataValue};
@@ -117,11 +118,14 @@ pub struct PagePlan {
     pub page_count: usize,
     pub page_data: Option<HashMap<String, PageDataValue>>,
     pub placements: Vec<PlacedItem>,
+    // Size of the page when it differs from the plan's `page_size`.
+    pub size: Option<Size>,
+    pub rotate: u16,
 }
 
 #[derive(Debug, Clone)]
 pub enum Paintable {
-    PageCommands { commands: Vec<Command> },
+    PageCommands { commands: CommandBuffer },
 }
 
 pub type FontFaceId = String;
@@ -138,9 +142,28 @@ pub struct DocPlan {
     pub paintables: Vec<Paintable>,
 }
 
+impl DocPlan {
+    // Versioned binary encoding, for handing a plan to another process that replays it with
+    // `FullBleed::render_from_plan`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_bytes_with_codec(crate::spill::SpillCodec::None)
+    }
+
+    // `to_bytes` compressed with `codec`; `from_bytes` reads either form.
+    pub fn to_bytes_with_codec(&self, codec: crate::spill::SpillCodec) -> Vec<u8> {
+        crate::spill::encode_doc_plan(self, codec)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> std::io::Result<Self> {
+        crate::spill::decode_doc_plan(bytes)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PageOps {
-    pub commands: Vec<Command>,
+    pub commands: CommandBuffer,
+    pub size: Option<Size>,
+    pub rotate: u16,
 }
 
 pub fn plan_document_with_overlay(
@@ -155,14 +178,15 @@ pub fn plan_document_with_overlay(
     let mut paintables = Vec::with_capacity(document.pages.len() * 2);
     let mut pages = Vec::with_capacity(document.pages.len());
     let page_count = document.pages.len();
-    let page_bbox = Rect {
-        x: Pt::ZERO,
-        y: Pt::ZERO,
-        width: document.page_size.width,
-        height: document.page_size.height,
-    };
 
     for (page_index, page) in document.pages.iter().enumerate() {
+        let page_size = document.page_size_at(page_index);
+        let page_bbox = Rect {
+            x: Pt::ZERO,
+            y: Pt::ZERO,
+            width: page_size.width,
+            height: page_size.height,
+        };
         let mut placements = Vec::new();
         let page_data_snapshot = page_data
             .as_ref()
@@ -224,6 +248,8 @@ pub fn plan_document_with_overlay(
             page_count,
             page_data: page_data_snapshot,
             placements,
+            size: page.size,
+            rotate: page.rotate,
         });
     }
 
@@ -265,17 +291,21 @@ pub fn paint_plan(plan: &DocPlan, debug: Option<Arc<DebugLogger>>) -> Vec<PageOp
     let mut out = Vec::with_capacity(plan.pages.len());
 
     for (page_index, page) in plan.pages.iter().enumerate() {
-        let mut commands = Vec::new();
+        let mut commands = CommandBuffer::new();
         let mut placements = page.placements.clone();
         sort_placements(&mut placements);
         for placement in placements {
             match &plan.paintables[placement.paintable_id] {
                 Paintable::PageCommands { commands: cmds } => {
-                    commands.extend(cmds.clone());
+                    commands.append(cmds);
                 }
             }
         }
-        out.push(PageOps { commands });
+        out.push(PageOps {
+            commands,
+            size: page.size,
+            rotate: page.rotate,
+        });
 
         if let Some(logger) = debug.as_deref() {
             let json = format!(
@@ -299,13 +329,13 @@ pub fn paint_plan_parallel(plan: &DocPlan, debug: Option<Arc<DebugLogger>>) -> V
         .par_iter()
         .enumerate()
         .map(|(page_index, page)| {
-            let mut commands = Vec::new();
+            let mut commands = CommandBuffer::new();
             let mut placements = page.placements.clone();
             sort_placements(&mut placements);
             for placement in placements {
                 match &plan.paintables[placement.paintable_id] {
                     Paintable::PageCommands { commands: cmds } => {
-                        commands.extend(cmds.clone());
+                        commands.append(cmds);
                     }
                 }
             }
@@ -318,7 +348,14 @@ pub fn paint_plan_parallel(plan: &DocPlan, debug: Option<Arc<DebugLogger>>) -> V
                 );
                 logger.log_json(&json);
             }
-            (page_index, PageOps { commands })
+            (
+                page_index,
+                PageOps {
+                    commands,
+                    size: page.size,
+                    rotate: page.rotate,
+                },
+            )
         })
         .collect();
 
@@ -502,16 +539,17 @@ fn rect_from_bounds(bounds: Option<(f32, f32, f32, f32)>) -> Option<Rect> {
     })
 }
 
-fn commands_bbox(commands: &[Command], font_registry: Option<&FontRegistry>) -> Option<Rect> {
+fn commands_bbox(commands: &CommandBuffer, font_registry: Option<&FontRegistry>) -> Option<Rect> {
     let mut bounds: Option<(f32, f32, f32, f32)> = None;
     let mut path_points: Vec<(f32, f32)> = Vec::new();
     let mut transform = Transform::identity();
     let mut stack: Vec<Transform> = Vec::new();
-    let mut font_name = "Helvetica".to_string();
+    let mut font_name: Arc<str> = Arc::from("Helvetica");
     let mut font_size = Pt::from_f32(12.0);
     let mut has_meta_bounds = false;
 
     for cmd in commands {
+        let cmd = &*cmd;
         if has_meta_bounds {
             if let Command::Meta { key, value } = cmd {
                 if key == "__fb_bbox" {
@@ -631,6 +669,20 @@ fn commands_bbox(commands: &[Command], font_registry: Option<&FontRegistry>) ->
                 width,
                 height,
                 ..
+            }
+            | Command::LinkAnnotation {
+                x,
+                y,
+                width,
+                height,
+                ..
+            }
+            | Command::SignatureField {
+                x,
+                y,
+                width,
+                height,
+                ..
             } => {
                 let x0 = x.to_f32();
                 let y0 = y.to_f32();
@@ -777,7 +829,9 @@ fn commands_bbox(commands: &[Command], font_registry: Option<&FontRegistry>) ->
             | Command::EndTag
             | Command::BeginArtifact { .. }
             | Command::BeginOptionalContent { .. }
-            | Command::EndMarkedContent => {}
+            | Command::EndMarkedContent
+            | Command::NamedDestination { .. }
+            | Command::Bookmark { .. } => {}
         }
     }
 
@@ -803,6 +857,8 @@ pub fn ops_to_document(page_size: Size, ops: Vec<PageOps>) -> Document {
     for page_ops in ops {
         pages.push(Page {
             commands: page_ops.commands,
+            size: page_ops.size,
+            rotate: page_ops.rotate,
         });
     }
     Document { page_size, pages }
@@ -0,0 +1,182 @@
+// Typed fidelity losses: the `jit.known_loss.*` events of the debug log as values. Each one
+// rides on the warning that reports it (`Warning::loss`), so the sink sees them as they happen
+// and `RenderReport` gathers one render's losses with severity, page and element.
+use crate::types::{Pt, Size};
+use crate::warning::Warning;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LossSeverity {
+    // Output is correct but not as styled (a substituted font).
+    Info,
+    // Output looks different from what was asked for (ignored CSS, a rasterized SVG).
+    Minor,
+    // Content is missing, clipped or wrong (clipped overflow, replaced characters).
+    Major,
+}
+
+impl LossSeverity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LossSeverity::Info => "info",
+            LossSeverity::Minor => "minor",
+            LossSeverity::Major => "major",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum KnownLoss {
+    // The CSS `@page` size was ignored because the engine page size was set explicitly.
+    PageSizeOverridden {
+        css: Size,
+        engine: Size,
+    },
+    // Part of an absolutely positioned element ran past the page and was clipped.
+    AbsOverflowClipped {
+        clipped_height: Pt,
+    },
+    // Lazy layout hit its pass or time budget before the layout settled.
+    LazyLayoutNoConvergence {
+        passes: usize,
+    },
+    // Page references still moved on the last pass; `unresolved` had no target.
+    PageRefNoConvergence {
+        unresolved: usize,
+    },
+    // A positioned element after the first slice of a streamed render was dropped.
+    StreamPageOneOverlay,
+    // `data-fb-frame` content of a streamed render was laid out in the main flow.
+    StreamFrameThread,
+    // An SVG was drawn as an image; `source` is its `src` (None when inline).
+    SvgRasterized {
+        source: Option<String>,
+    },
+    // Characters a non-embedded font cannot encode were drawn with WinAnsi substitutes.
+    WinAnsiFallback {
+        font: String,
+        fallbacks: usize,
+    },
+    // Text was drawn with a fallback font for `script`.
+    FontFallback {
+        font: String,
+        script: &'static str,
+    },
+    // Rules, declarations or selectors of the stylesheet could not be used (see
+    // `FullBleed::css_diagnostics`).
+    CssDiagnostics {
+        count: usize,
+    },
+    // A `display` value the engine lays out as `applied` instead.
+    LayoutModeNormalized {
+        requested: String,
+        applied: &'static str,
+    },
+    // A multi-column property the engine ignores, laying the content out in one column.
+    MulticolSingleColumnFallback {
+        property: String,
+    },
+    // A filter, clip or opacity effect that is not drawn.
+    FiltersEffectsFallback {
+        property: String,
+    },
+    // A conic gradient background that is not drawn.
+    ConicGradientFallback {
+        property: String,
+    },
+    // A declaration that parses but has no effect in the engine.
+    DeclarationParsedNoEffect {
+        property: String,
+    },
+}
+
+impl KnownLoss {
+    // The code of the warning (and debug log record) that reports this loss.
+    pub fn code(&self) -> &'static str {
+        match self {
+            KnownLoss::PageSizeOverridden { .. } => "PAGE_SIZE_OVERRIDDEN",
+            KnownLoss::AbsOverflowClipped { .. } => "ABS_OVERFLOW_CLIPPED",
+            KnownLoss::LazyLayoutNoConvergence { .. } => "LAZY_LAYOUT_NO_CONVERGENCE",
+            KnownLoss::PageRefNoConvergence { .. } => "PAGE_REF_NO_CONVERGENCE",
+            KnownLoss::StreamPageOneOverlay => "STREAM_PAGE_ONE_OVERLAY",
+            KnownLoss::StreamFrameThread => "STREAM_FRAME_THREAD",
+            KnownLoss::SvgRasterized { .. } => "SVG_RASTERIZED",
+            KnownLoss::WinAnsiFallback { .. } => "FONT_FALLBACK_USED",
+            KnownLoss::FontFallback { .. } => "FONT_FALLBACK",
+            KnownLoss::CssDiagnostics { .. } => "CSS_DIAGNOSTICS",
+            KnownLoss::LayoutModeNormalized { .. } => "LAYOUT_MODE_NORMALIZED",
+            KnownLoss::MulticolSingleColumnFallback { .. } => "MULTICOL_SINGLE_COLUMN_FALLBACK",
+            KnownLoss::FiltersEffectsFallback { .. } => "FILTERS_EFFECTS_FALLBACK",
+            KnownLoss::ConicGradientFallback { .. } => "CONIC_GRADIENT_FALLBACK",
+            KnownLoss::DeclarationParsedNoEffect { .. } => "DECLARATION_PARSED_NO_EFFECT",
+        }
+    }
+
+    pub fn severity(&self) -> LossSeverity {
+        match self {
+            KnownLoss::AbsOverflowClipped { .. }
+            | KnownLoss::PageRefNoConvergence { .. }
+            | KnownLoss::StreamPageOneOverlay
+            | KnownLoss::WinAnsiFallback { .. } => LossSeverity::Major,
+            KnownLoss::FontFallback { .. } => LossSeverity::Info,
+            _ => LossSeverity::Minor,
+        }
+    }
+}
+
+// One loss as it happened in a render.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KnownLossEvent {
+    pub loss: KnownLoss,
+    pub severity: LossSeverity,
+    pub message: String,
+    pub doc_id: Option<usize>,
+    // 1-based page, when the loss concerns one page.
+    pub page: Option<usize>,
+    // Selector or element path of the content affected, when known.
+    pub element: Option<String>,
+}
+
+// What one render reported: its warnings and, typed, the fidelity losses among them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenderReport {
+    pub known_losses: Vec<KnownLossEvent>,
+    pub warnings: Vec<Warning>,
+}
+
+impl RenderReport {
+    // Builds the report of collected warnings, such as `RecordRender::warnings`.
+    pub fn from_warnings(warnings: Vec<Warning>) -> Self {
+        let known_losses = warnings
+            .iter()
+            .filter_map(|warning| {
+                let loss = warning.loss.clone()?;
+                Some(KnownLossEvent {
+                    severity: loss.severity(),
+                    loss,
+                    message: warning.message.clone(),
+                    doc_id: warning.doc_id,
+                    page: warning.page,
+                    element: warning.element.clone(),
+                })
+            })
+            .collect();
+        Self {
+            known_losses,
+            warnings,
+        }
+    }
+
+    pub fn max_severity(&self) -> Option<LossSeverity> {
+        self.known_losses.iter().map(|event| event.severity).max()
+    }
+
+    // Number of losses per code.
+    pub fn loss_counts(&self) -> BTreeMap<&'static str, usize> {
+        let mut counts = BTreeMap::new();
+        for event in &self.known_losses {
+            *counts.entry(event.loss.code()).or_insert(0) += 1;
+        }
+        counts
+    }
+}
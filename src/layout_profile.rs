@@ -0,0 +1,253 @@
+// Opt-in layout profiler: attributes layout time to the element each top-level flowable came
+// from and to the operations under it (paragraph and table wrap/draw, line breaking). Scopes
+// are recorded on the calling thread only while `collect` runs, so a plain render pays one
+// thread-local check per scope.
+use crate::platform::Instant;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+
+// Time spent in one stack: an element path and the operations open under it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutProfileStack {
+    // Element path (`html:nth-of-type(1) > body:nth-of-type(2) > div.items:nth-of-type(1)`)
+    // of the top-level flowable being placed, or its flowable type when it carries no element
+    // metadata (elements without an id, class or role); `None` for work outside flowable
+    // placement.
+    pub element: Option<String>,
+    // Operations from outermost to innermost, such as `table.wrap` then `paragraph.lines`.
+    pub ops: Vec<&'static str>,
+    // Time in this stack itself, excluding nested operations.
+    pub self_ms: f64,
+    pub calls: u64,
+}
+
+// Time attributed to one element path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElementTiming {
+    pub path: String,
+    pub ms: f64,
+    pub calls: u64,
+}
+
+// Result of `FullBleed::render_with_layout_profile`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LayoutProfile {
+    pub stacks: Vec<LayoutProfileStack>,
+}
+
+impl LayoutProfile {
+    pub fn total_ms(&self) -> f64 {
+        self.stacks.iter().map(|stack| stack.self_ms).sum()
+    }
+
+    // Inclusive time per element subtree (every prefix of each element path), slowest first.
+    pub fn slowest_subtrees(&self, n: usize) -> Vec<ElementTiming> {
+        let mut subtrees: BTreeMap<String, (f64, u64)> = BTreeMap::new();
+        for stack in &self.stacks {
+            let Some(element) = stack.element.as_deref() else {
+                continue;
+            };
+            let mut prefix = String::new();
+            for part in element.split('>').map(str::trim) {
+                if !prefix.is_empty() {
+                    prefix.push_str(" > ");
+                }
+                prefix.push_str(part);
+                let entry = subtrees.entry(prefix.clone()).or_default();
+                entry.0 += stack.self_ms;
+                if stack.ops.is_empty() {
+                    entry.1 += stack.calls;
+                }
+            }
+        }
+        slowest(subtrees, n)
+    }
+
+    // Time under table operations per element, slowest first; `calls` counts `table.wrap`.
+    pub fn slowest_tables(&self, n: usize) -> Vec<ElementTiming> {
+        self.slowest_under("table.", "table.wrap", n)
+    }
+
+    // Time under paragraph operations per element, slowest first; `calls` counts
+    // `paragraph.wrap`, so a high count means the paragraph was re-wrapped many times.
+    pub fn slowest_paragraph_wraps(&self, n: usize) -> Vec<ElementTiming> {
+        self.slowest_under("paragraph.", "paragraph.wrap", n)
+    }
+
+    fn slowest_under(&self, prefix: &str, counted: &str, n: usize) -> Vec<ElementTiming> {
+        let mut elements: BTreeMap<String, (f64, u64)> = BTreeMap::new();
+        for stack in &self.stacks {
+            let Some(first) = stack.ops.iter().position(|op| op.starts_with(prefix)) else {
+                continue;
+            };
+            let entry = elements
+                .entry(stack.element.clone().unwrap_or_default())
+                .or_default();
+            entry.0 += stack.self_ms;
+            if stack.ops.len() == first + 1 && stack.ops[first] == counted {
+                entry.1 += stack.calls;
+            }
+        }
+        slowest(elements, n)
+    }
+
+    // Folded stacks for flamegraph tools (`inferno-flamegraph`, `flamegraph.pl`): one line
+    // per stack, frames joined by `;` under a `layout` root, then the self time in
+    // microseconds. Element paths are split into one frame per element.
+    pub fn to_folded(&self) -> String {
+        let mut lines: BTreeMap<String, u64> = BTreeMap::new();
+        for stack in &self.stacks {
+            let mut frames = vec!["layout".to_string()];
+            if let Some(element) = &stack.element {
+                frames.extend(
+                    element
+                        .split('>')
+                        .map(|part| part.trim().replace([';', ' '], "_")),
+                );
+            }
+            frames.extend(stack.ops.iter().map(|op| op.to_string()));
+            *lines.entry(frames.join(";")).or_default() += (stack.self_ms * 1000.0).round() as u64;
+        }
+        lines
+            .into_iter()
+            .filter(|(_, micros)| *micros > 0)
+            .map(|(frames, micros)| format!("{frames} {micros}\n"))
+            .collect()
+    }
+}
+
+fn slowest(entries: BTreeMap<String, (f64, u64)>, n: usize) -> Vec<ElementTiming> {
+    let mut timings: Vec<ElementTiming> = entries
+        .into_iter()
+        .map(|(path, (ms, calls))| ElementTiming { path, ms, calls })
+        .collect();
+    timings.sort_by(|a, b| b.ms.total_cmp(&a.ms));
+    timings.truncate(n);
+    timings
+}
+
+struct OpenScope {
+    start: Instant,
+    child_ms: f64,
+    // For an element scope, the element it replaced; `None` for an operation scope.
+    prev_element: Option<Option<String>>,
+}
+
+// An element path and the operations open under it.
+type StackKey = (Option<String>, Vec<&'static str>);
+
+#[derive(Default)]
+struct Recorder {
+    element: Option<String>,
+    ops: Vec<&'static str>,
+    open: Vec<OpenScope>,
+    // Self time and calls per stack.
+    totals: HashMap<StackKey, (f64, u64)>,
+}
+
+thread_local! {
+    static RECORDER: RefCell<Option<Recorder>> = const { RefCell::new(None) };
+}
+
+// Closes its scope on drop; inert when no profile is being collected.
+pub(crate) struct Scope {
+    active: bool,
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        if !self.active {
+            return;
+        }
+        RECORDER.with(|recorder| {
+            let mut recorder = recorder.borrow_mut();
+            let Some(recorder) = recorder.as_mut() else {
+                return;
+            };
+            let Some(open) = recorder.open.pop() else {
+                return;
+            };
+            let elapsed_ms = open.start.elapsed().as_secs_f64() * 1000.0;
+            let key = (recorder.element.clone(), recorder.ops.clone());
+            let entry = recorder.totals.entry(key).or_default();
+            entry.0 += (elapsed_ms - open.child_ms).max(0.0);
+            entry.1 += 1;
+            match open.prev_element {
+                Some(prev) => recorder.element = prev,
+                None => {
+                    recorder.ops.pop();
+                }
+            }
+            if let Some(parent) = recorder.open.last_mut() {
+                parent.child_ms += elapsed_ms;
+            }
+        });
+    }
+}
+
+fn open(push: impl FnOnce(&mut Recorder) -> Option<Option<String>>) -> Scope {
+    RECORDER.with(|recorder| {
+        let mut recorder = recorder.borrow_mut();
+        let Some(recorder) = recorder.as_mut() else {
+            return Scope { active: false };
+        };
+        let prev_element = push(recorder);
+        recorder.open.push(OpenScope {
+            start: Instant::now(),
+            child_ms: 0.0,
+            prev_element,
+        });
+        Scope { active: true }
+    })
+}
+
+// Placement of one top-level flowable. `element` is only called while profiling.
+pub(crate) fn element_scope(element: impl FnOnce() -> String) -> Scope {
+    open(|recorder| Some(recorder.element.replace(element())))
+}
+
+pub(crate) fn op_scope(op: &'static str) -> Scope {
+    open(|recorder| {
+        recorder.ops.push(op);
+        None
+    })
+}
+
+// Whether a profile is being collected on this thread.
+pub(crate) fn active() -> bool {
+    RECORDER.with(|recorder| recorder.borrow().is_some())
+}
+
+// Runs `f` with profiling on for this thread and returns what it recorded.
+pub(crate) fn collect<T>(f: impl FnOnce() -> T) -> (T, LayoutProfile) {
+    struct Restore(Option<Option<Recorder>>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            if let Some(prev) = self.0.take() {
+                RECORDER.with(|recorder| *recorder.borrow_mut() = prev);
+            }
+        }
+    }
+
+    let mut restore =
+        Restore(Some(RECORDER.with(|recorder| {
+            recorder.borrow_mut().replace(Recorder::default())
+        })));
+    let out = f();
+    let prev = restore.0.take().expect("set above");
+    let recorder = RECORDER
+        .with(|recorder| std::mem::replace(&mut *recorder.borrow_mut(), prev))
+        .unwrap_or_default();
+    let mut stacks: Vec<LayoutProfileStack> = recorder
+        .totals
+        .into_iter()
+        .map(|((element, ops), (self_ms, calls))| LayoutProfileStack {
+            element,
+            ops,
+            self_ms,
+            calls,
+        })
+        .collect();
+    stacks.sort_by(|a, b| (&a.element, &a.ops).cmp(&(&b.element, &b.ops)));
+    (out, LayoutProfile { stacks })
+}
@@ -0,0 +1,98 @@
+// Boxes that did not fit where layout put them, read back from the pagination trace the
+// frames record on each page.
+use crate::canvas::{Command, Document, META_PAGINATION_EVENT_KEY};
+use crate::types::Pt;
+use base64::Engine;
+use std::collections::HashMap;
+
+// Overhangs below this are rounding noise from the milli-point grid.
+const MIN_OVERFLOW_MILLI: i64 = 10;
+
+// A box laid out larger than the frame space it was given.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutOverflow {
+    // 1-based page the box was drawn on.
+    pub page_number: usize,
+    // Selector path of the element that produced the box, when it carries owner metadata
+    // (an id, a class or a `data-fb` attribute).
+    pub element_path: Option<String>,
+    pub flowable: String,
+    // Why the box was placed anyway, e.g. `forced_unsplittable_full_frame`.
+    pub reason: String,
+    // How far the box extends past the right edge of its frame.
+    pub overflow_x: Pt,
+    // How far the box extends past the bottom of its frame.
+    pub overflow_y: Pt,
+}
+
+// Overflowing boxes of one rendered document, in page order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LayoutReport {
+    overflows: Vec<LayoutOverflow>,
+}
+
+impl LayoutReport {
+    pub fn from_document(document: &Document) -> Self {
+        let mut overflows = Vec::new();
+        for (index, page) in document.pages.iter().enumerate() {
+            for command in &page.commands {
+                let Command::Meta { key, value } = &*command else {
+                    continue;
+                };
+                if key != META_PAGINATION_EVENT_KEY {
+                    continue;
+                }
+                if let Some(overflow) = overflow_from_event(index + 1, value) {
+                    overflows.push(overflow);
+                }
+            }
+        }
+        Self { overflows }
+    }
+
+    pub fn overflows(&self) -> &[LayoutOverflow] {
+        &self.overflows
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.overflows.is_empty()
+    }
+}
+
+// Only boxes that were drawn count: frame and page advances move content, they do not clip it.
+fn overflow_from_event(page_number: usize, event: &str) -> Option<LayoutOverflow> {
+    let fields: HashMap<&str, &str> = event
+        .split('|')
+        .filter_map(|chunk| chunk.split_once('='))
+        .collect();
+    if fields.get("event") != Some(&"layout")
+        || !matches!(fields.get("result"), Some(&"placed") | Some(&"split"))
+    {
+        return None;
+    }
+    let milli = |key: &str| fields.get(key).and_then(|value| value.parse::<i64>().ok());
+    let over_x = milli("wrapped_w")? - milli("avail_w")?;
+    // A split box only drew the part that fit.
+    let over_y = if fields.get("result") == Some(&"placed") {
+        milli("wrapped_h")? - milli("avail_h")?
+    } else {
+        0
+    };
+    if over_x < MIN_OVERFLOW_MILLI && over_y < MIN_OVERFLOW_MILLI {
+        return None;
+    }
+    let element_path = fields.get("owner_dom_path_b64").and_then(|raw| {
+        let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(raw)
+            .ok()?;
+        String::from_utf8(bytes).ok()
+    });
+    Some(LayoutOverflow {
+        page_number,
+        element_path,
+        flowable: fields.get("flowable").unwrap_or(&"unknown").to_string(),
+        reason: fields.get("reason").unwrap_or(&"unknown").to_string(),
+        overflow_x: Pt::from_milli_i64(over_x.max(0)),
+        overflow_y: Pt::from_milli_i64(over_y.max(0)),
+    })
+}
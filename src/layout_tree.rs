@@ -0,0 +1,146 @@
+// Box geometry of a laid-out document, for tooling that needs to know where each element
+// landed (preview overlays, template linting) without producing a PDF.
+use crate::canvas::{
+    Command, Document, META_DIAGNOSTIC_SCOPE_BEGIN_KEY, META_DIAGNOSTIC_SCOPE_END_KEY,
+};
+use crate::flowable::{META_LAYOUT_BOX_KEY, META_LAYOUT_STYLE_KEY};
+use crate::types::{Pt, Rect, Size};
+
+// One block-level box: an element, or one fragment of it when it split across frames or
+// pages.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutBox {
+    // Selector path from the root, e.g. `html:nth-of-type(1) > ... > p:nth-of-type(2)`.
+    pub element_path: String,
+    pub tag: String,
+    pub id: Option<String>,
+    pub classes: Vec<String>,
+    // 1-based page the box was drawn on.
+    pub page_number: usize,
+    // Border box in page coordinates (top-left origin), before CSS transforms.
+    pub rect: Rect,
+    // Computed style properties as `(name, value)` pairs.
+    pub computed_style: Vec<(String, String)>,
+    pub children: Vec<LayoutBox>,
+}
+
+// Boxes of a laid-out document; each page's top-level boxes in paint order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutTree {
+    pub page_size: Size,
+    pub page_count: usize,
+    pub roots: Vec<LayoutBox>,
+}
+
+impl LayoutTree {
+    pub(crate) fn from_document(document: &Document) -> Self {
+        let mut roots = Vec::new();
+        for (index, page) in document.pages.iter().enumerate() {
+            // One entry per open diagnostic scope; scopes that are not element boxes stay
+            // `None` and their boxes attach to the nearest element above them.
+            let mut stack: Vec<Option<LayoutBox>> = Vec::new();
+            for command in &page.commands {
+                let Command::Meta { key, value } = &*command else {
+                    continue;
+                };
+                match key.as_str() {
+                    META_DIAGNOSTIC_SCOPE_BEGIN_KEY => stack.push(None),
+                    META_DIAGNOSTIC_SCOPE_END_KEY => {
+                        let Some(closed) = stack.pop() else {
+                            continue;
+                        };
+                        let Some(closed) = closed.filter(|node| !node.tag.is_empty()) else {
+                            continue;
+                        };
+                        match stack.iter_mut().rev().find_map(Option::as_mut) {
+                            Some(parent) => parent.children.push(closed),
+                            None => roots.push(closed),
+                        }
+                    }
+                    _ if key.starts_with("fb.owner.") || key.starts_with("fb.layout.") => {
+                        if let Some(slot) = stack.last_mut() {
+                            apply_meta(slot, index + 1, key, value);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Self {
+            page_size: document.page_size,
+            page_count: document.pages.len(),
+            roots,
+        }
+    }
+
+    // Every box, depth first.
+    pub fn boxes(&self) -> Vec<&LayoutBox> {
+        let mut out = Vec::new();
+        let mut pending: Vec<&LayoutBox> = self.roots.iter().rev().collect();
+        while let Some(node) = pending.pop() {
+            out.push(node);
+            pending.extend(node.children.iter().rev());
+        }
+        out
+    }
+
+    // Boxes of the elements whose path ends with `suffix`, e.g. `div#totals:nth-of-type(1)`.
+    pub fn find(&self, suffix: &str) -> Vec<&LayoutBox> {
+        self.boxes()
+            .into_iter()
+            .filter(|node| node.element_path.ends_with(suffix))
+            .collect()
+    }
+}
+
+fn apply_meta(slot: &mut Option<LayoutBox>, page_number: usize, key: &str, value: &str) {
+    let node = slot.get_or_insert_with(|| LayoutBox {
+        element_path: String::new(),
+        tag: String::new(),
+        id: None,
+        classes: Vec::new(),
+        page_number,
+        rect: Rect {
+            x: Pt::ZERO,
+            y: Pt::ZERO,
+            width: Pt::ZERO,
+            height: Pt::ZERO,
+        },
+        computed_style: Vec::new(),
+        children: Vec::new(),
+    });
+    match key {
+        "fb.owner.dom_path" => node.element_path = value.to_string(),
+        "fb.owner.tag" => node.tag = value.to_string(),
+        "fb.owner.id" => node.id = Some(value.to_string()),
+        "fb.owner.classes" => {
+            node.classes = value.split_whitespace().map(str::to_string).collect();
+        }
+        META_LAYOUT_BOX_KEY => {
+            let milli: Vec<i64> = value
+                .split(',')
+                .filter_map(|part| part.parse().ok())
+                .collect();
+            if let [x, y, width, height] = milli[..] {
+                node.rect = Rect {
+                    x: Pt::from_milli_i64(x),
+                    y: Pt::from_milli_i64(y),
+                    width: Pt::from_milli_i64(width),
+                    height: Pt::from_milli_i64(height),
+                };
+            }
+        }
+        META_LAYOUT_STYLE_KEY => {
+            if let Ok(serde_json::Value::Object(fields)) = serde_json::from_str(value) {
+                node.computed_style = fields
+                    .into_iter()
+                    .map(|(name, value)| match value {
+                        serde_json::Value::String(text) => (name, text),
+                        other => (name, other.to_string()),
+                    })
+                    .collect();
+            }
+        }
+        _ => {}
+    }
+}
@@ -1,18 +1,43 @@
+mod artifact_bundle;
 mod assets;
+#[cfg(feature = "async")]
+mod async_render;
+mod bidi;
 mod canvas;
+#[cfg(feature = "capi")]
+mod capi;
+mod chart;
+mod command_buffer;
+mod counter;
+mod css_diagnostics;
+mod data_binding;
 mod debug;
 mod doc_context;
+mod doc_diff;
 mod doc_template;
+mod encrypt;
 mod error;
+mod fetch;
 mod finalize;
 mod flate_native;
 mod flowable;
 mod font;
+mod font_cache;
 mod frame;
 mod glyph_report;
 mod html;
+mod html_stream;
+mod instance;
 mod jit;
+mod known_loss;
+mod layout_profile;
+mod layout_report;
+mod layout_tree;
+mod lru;
+mod margin_box;
 mod metrics;
+#[cfg(feature = "nodejs")]
+mod nodejs;
 mod page_data;
 mod page_template;
 mod pdf;
@@ -20,20 +45,44 @@ mod pdf_raster;
 mod pdfinspect;
 mod perf;
 mod plan;
+mod platform;
+#[cfg(feature = "preview")]
+mod preview;
+mod progress;
 #[cfg(feature = "python")]
 mod python;
 mod raster;
 mod spill;
 mod style;
+mod subset;
 mod svg;
+mod text_extract;
 mod types;
-
-pub use assets::{Asset, AssetBundle, AssetKind};
-pub use canvas::{Canvas, Command, Document, Page};
+mod warning;
+#[cfg(feature = "wasm")]
+mod wasm;
+mod woff;
+
+pub use artifact_bundle::{ArtifactBundleManifest, ArtifactBundleTarget, ArtifactFile};
+pub use assets::{Asset, AssetBundle, AssetKind, ResourceResolver};
+#[cfg(feature = "async")]
+pub use async_render::{RecordPdf, RecordPdfStream};
+pub use canvas::{Canvas, Command, Document, LinkTarget, Page};
+#[cfg(feature = "capi")]
+pub use capi::{FB_ABI_VERSION, FbBuffer, FbBuilder, FbEngine, FbStatus};
+pub use chart::{ChartFlowable, ChartKind, ChartSeries};
+pub use command_buffer::CommandBuffer;
+pub use css_diagnostics::{CssDiagnostic, CssDiagnosticKind, CssDiagnostics};
+pub use data_binding::bind_template;
 use debug::DebugLogger;
 pub use doc_context::DocContext;
+pub use doc_diff::{DiffItem, DiffOptions, DocumentDiff, PageChange, PageDiff, PixelDiff};
 pub use doc_template::DocTemplate;
-pub use error::FullBleedError;
+pub use encrypt::EncryptionSpec;
+pub use error::{Diagnostic, ErrorCode, FullBleedError, SourceLocation};
+#[cfg(feature = "remote_assets")]
+pub use fetch::HttpFetcher;
+pub use fetch::{AssetFetcher, FetchResponse, RemoteAssets};
 pub use finalize::{
     BindingSource, ComposeAnnotationMode, ComposePagePlan, FinalizeComposeSummary,
     FinalizeStampSummary, META_PAGE_TEMPLATE_KEY, PageBindingDecision, TemplateAsset,
@@ -45,33 +94,62 @@ pub use finalize::{
 };
 pub use flowable::{
     AbsolutePositionedFlowable, BreakAfter, BreakBefore, BreakInside, ContainerFlowable, EdgeSizes,
-    Flowable, ImageFlowable, LengthSpec, Pagination, Paragraph, Spacer, SvgFlowable, TableFlowable,
-    TextStyle,
+    FloatExclusion, FloatSide, Flowable, ImageFlowable, LeaderSpec, LengthSpec, Pagination,
+    Paragraph, Spacer, SvgFlowable, TableFlowable, TextStyle,
 };
 use font::FontRegistry;
+pub use font::FontScript;
 #[cfg(feature = "python")]
 use font::RegisteredFontTrace;
+pub use font_cache::{FontCache, FontCacheStats};
 pub use frame::{AddResult, Frame};
 use fullbleed_audit_contract as audit_contract;
-pub use glyph_report::{GlyphCoverageReport, MissingGlyph};
+pub use fullbleed_audit_contract::{Section508HtmlCoverageSummary, Wcag20AaCoverageSummary};
+pub use glyph_report::{FallbackUse, GlyphCoverageReport, MissingGlyph};
+pub use html::{CustomElement, CustomElementFactory};
 use image::GenericImageView;
-pub use jit::JitMode;
+pub use jit::{DocPlan, JitMode};
+pub use known_loss::{KnownLoss, KnownLossEvent, LossSeverity, RenderReport};
 use kuchiki::NodeData;
 use kuchiki::traits::TendrilSink;
+pub use layout_profile::{ElementTiming, LayoutProfile, LayoutProfileStack};
+pub use layout_report::{LayoutOverflow, LayoutReport};
+pub use layout_tree::{LayoutBox, LayoutTree};
 pub use metrics::{DocumentMetrics, PageMetrics};
 pub use page_data::{PageDataContext, PageDataOp, PageDataValue, PaginatedContextSpec};
-pub use page_template::{FrameSpec, PageTemplate};
+pub use page_template::{FrameSpec, PageParity, PageTemplate};
 use pdf::PdfOptions;
-pub use pdf::{OutputIntent, PdfProfile, PdfVersion};
+pub use pdf::{
+    AfRelationship, BatchManifest, DocumentId, EmbeddedFile, OutputIntent, PageBoxSpec, PageCache,
+    PageLabelRange, PageLabelSpec, PageLabelStyle, PdfProfile, PdfVersion, RecordMetadata,
+    RecordRange, SignatureByteRange, embed_signature_contents, patch_signature_byte_range,
+};
 pub use pdfinspect::{
-    PdfInspectError, PdfInspectErrorCode, PdfInspectReport, PdfInspectWarning,
-    composition_compatibility_issues, inspect_pdf_bytes, inspect_pdf_path,
-    require_pdf_composition_compatibility,
+    PdfContentIssue, PdfContentIssueCode, PdfFontResource, PdfInspectError, PdfInspectErrorCode,
+    PdfInspectReport, PdfInspectWarning, PdfPreflightReport, PdfPreflightRule,
+    PdfPreflightViolation, PdfSignatureField, composition_compatibility_issues, extract_text,
+    inspect_pdf_bytes, inspect_pdf_bytes_with_profile, inspect_pdf_path,
+    inspect_pdf_path_with_profile, require_pdf_composition_compatibility,
 };
 use perf::PerfLogger;
+pub use perf::{
+    PERF_COMPARISON_SCHEMA_VERSION, PerfCompareOptions, PerfComparison, PerfSpanDelta,
+    PerfSpanStats, compare_perf_log_text, compare_perf_logs,
+};
+#[cfg(feature = "preview")]
+pub use preview::{PreviewFrame, PreviewServer, PreviewWatcher};
+use progress::{BatchProgress, ProgressHandle};
+pub use progress::{ProgressEvent, ProgressSink};
+pub use raster::{PageComparison, PdfComparison, compare_pdfs};
+pub use spill::{MemoryBudget, SpillCodec, SpillMetrics};
 use std::f32::consts::PI;
 use std::sync::Arc;
-pub use types::{Color, ColorSpace, Margins, Pt, Rect, Size};
+pub use text_extract::{PageText, TextLine, TextSpan};
+pub use types::{Color, ColorSpace, Margins, ProcessColor, Pt, Rect, Size, SpotName};
+use warning::WarningHandle;
+pub use warning::{Warning, WarningKind, WarningSink};
+#[cfg(feature = "wasm")]
+pub use wasm::WasmRenderer;
 
 pub struct FullBleed {
     default_page_size: Size,
@@ -79,6 +157,10 @@ pub struct FullBleed {
     page_margins: std::collections::BTreeMap<usize, Margins>,
     page_size_explicit: bool,
     margins_explicit: bool,
+    page_rotate: u16,
+    rotate_landscape_pages: bool,
+    mirror_margins: bool,
+    page_frames: Vec<FrameSpec>,
     font_registry: Arc<FontRegistry>,
     pdf_options: PdfOptions,
     svg_form_xobjects: bool,
@@ -89,24 +171,51 @@ pub struct FullBleed {
     layout_strategy: LayoutStrategy,
     lazy_max_passes: usize,
     lazy_budget_ms: f64,
+    parallel_sections: bool,
     page_header: Option<PageHeaderSpec>,
     page_header_html: Option<PageHeaderHtmlSpec>,
     page_footer: Option<PageFooterSpec>,
     paginated_context: Option<PaginatedContextSpec>,
     template_binding_spec: Option<TemplateBindingSpec>,
     watermark: Option<WatermarkSpec>,
+    page_draw: Option<PageDrawFn>,
+    on_page_start: Option<page_template::OnPageCallback>,
+    on_page_end: Option<page_template::OnPageCallback>,
+    custom_elements: std::collections::HashMap<String, CustomElementFactory>,
+    compiled_templates: Arc<std::sync::Mutex<lru::LruCache<u64, CompiledTemplate>>>,
+    // Unique per engine; compiled templates carry it so another engine cannot render them.
+    engine_id: u64,
+    stream_chunk_bytes: usize,
+    memory_budget: Option<MemoryBudget>,
+    jit_spill: Option<(std::path::PathBuf, usize)>,
+    spill_codec: SpillCodec,
+    spill_metrics: std::sync::Mutex<SpillMetrics>,
+    audit_gate_profile: Option<String>,
+    strict_fidelity: bool,
+    warnings: Option<WarningHandle>,
     asset_css: String,
     asset_bundle: Arc<AssetBundle>,
 }
 
+// `font_variation` calls: alias, family and axis settings (tag, value).
+type FontVariation = (String, String, Vec<(String, f32)>);
+
 #[derive(Clone)]
 pub struct FullBleedBuilder {
     page_size: Size,
     margins: Margins,
     page_size_explicit: bool,
     margins_explicit: bool,
+    page_rotate: u16,
+    rotate_landscape_pages: bool,
+    mirror_margins: bool,
+    page_frames: Vec<FrameSpec>,
     font_dirs: Vec<std::path::PathBuf>,
     font_files: Vec<std::path::PathBuf>,
+    font_variations: Vec<FontVariation>,
+    font_fallbacks: Vec<(String, Option<FontScript>)>,
+    font_cache: Option<Arc<FontCache>>,
+    shape_cache_capacity: usize,
     pdf_options: PdfOptions,
     svg_form_xobjects: bool,
     svg_raster_fallback: bool,
@@ -114,11 +223,13 @@ pub struct FullBleedBuilder {
     debug_path: Option<std::path::PathBuf>,
     perf_enabled: bool,
     perf_path: Option<std::path::PathBuf>,
+    perf_tracing: bool,
     jit_mode: JitMode,
     layout_strategy: LayoutStrategy,
     accept_lazy_layout_cost: bool,
     lazy_max_passes: usize,
     lazy_budget_ms: f64,
+    parallel_sections: bool,
     page_header: Option<PageHeaderSpec>,
     page_header_html: Option<PageHeaderHtmlSpec>,
     page_footer: Option<PageFooterSpec>,
@@ -126,7 +237,20 @@ pub struct FullBleedBuilder {
     template_binding_spec: Option<TemplateBindingSpec>,
     page_margins: std::collections::BTreeMap<usize, Margins>,
     watermark: Option<WatermarkSpec>,
+    page_draw: Option<PageDrawFn>,
+    on_page_start: Option<page_template::OnPageCallback>,
+    on_page_end: Option<page_template::OnPageCallback>,
+    custom_elements: std::collections::HashMap<String, CustomElementFactory>,
+    stream_chunk_bytes: usize,
+    memory_budget: Option<MemoryBudget>,
+    jit_spill: Option<(std::path::PathBuf, usize)>,
+    spill_codec: SpillCodec,
+    audit_gate_profile: Option<String>,
+    strict_fidelity: bool,
+    warnings: Option<WarningHandle>,
     asset_bundle: AssetBundle,
+    remote_assets: Option<RemoteAssets>,
+    resource_resolver: Option<assets::SharedResolver>,
 }
 
 struct RenderContext {
@@ -134,6 +258,171 @@ struct RenderContext {
     page_templates: Vec<PageTemplate>,
 }
 
+// Result of `FullBleed::render_incremental`. Pass `cache` to the next render of the same
+// engine.
+#[derive(Debug, Clone)]
+pub struct IncrementalRender {
+    pub pdf: Vec<u8>,
+    // 1-based numbers of the pages whose content changed since the previous render.
+    pub changed_pages: Vec<usize>,
+    // Pages written from the previous render's encoded content streams.
+    pub reused_pages: usize,
+    pub cache: PageCache,
+}
+
+// One record of a batch rendered into its own PDF by `FullBleed::render_many_records`.
+#[derive(Debug, Clone)]
+pub struct RecordRender {
+    // Position of the record in the input list.
+    pub index: usize,
+    pub page_count: usize,
+    pub pdf: Vec<u8>,
+    // Paginated-context values; `None` unless the engine has a paginated context.
+    pub page_data: Option<PageDataContext>,
+    // Characters no font tried could draw, as in `render_with_glyph_report`.
+    pub missing_glyphs: Vec<MissingGlyph>,
+    // Warnings emitted while rendering this record (see `render_with_warnings`).
+    pub warnings: Vec<Warning>,
+}
+
+impl RecordRender {
+    // This record's warnings as a `RenderReport`.
+    pub fn report(&self) -> RenderReport {
+        RenderReport::from_warnings(self.warnings.clone())
+    }
+}
+
+// Records of `FullBleed::render_many_records`, rendered one per `next()`.
+pub struct RecordRenders<'a> {
+    engine: &'a FullBleed,
+    html_list: &'a [String],
+    cursor: RecordCursor,
+}
+
+impl Iterator for RecordRenders<'_> {
+    type Item = Result<RecordRender, FullBleedError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.cursor.next(self.engine, self.html_list)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self
+            .html_list
+            .len()
+            .saturating_sub(self.cursor.next_index());
+        (left, Some(left))
+    }
+}
+
+// Position and shared render context of a record-by-record batch. Holds no borrows, so
+// bindings that own the engine and the HTML elsewhere can keep it between calls.
+pub(crate) struct RecordCursor {
+    context: RenderContext,
+    progress: BatchProgress,
+    next: usize,
+}
+
+impl RecordCursor {
+    pub(crate) fn new(engine: &FullBleed, css: &str, total: usize) -> Self {
+        Self {
+            context: engine.build_render_context(css, None),
+            progress: BatchProgress::new(engine.pdf_options.progress.clone(), total),
+            next: 0,
+        }
+    }
+
+    // Input position of the record the next call renders.
+    pub(crate) fn next_index(&self) -> usize {
+        self.next
+    }
+
+    pub(crate) fn next(
+        &mut self,
+        engine: &FullBleed,
+        html_list: &[String],
+    ) -> Option<Result<RecordRender, FullBleedError>> {
+        let index = self.next;
+        let html = html_list.get(index)?;
+        self.next += 1;
+        let mut report = GlyphCoverageReport::default();
+        let (rendered, warnings) = warning::collect(|| {
+            engine.render_to_document_and_page_data_with_resolver_and_report_at(
+                index,
+                html,
+                &self.context.page_templates,
+                &self.context.resolver,
+                Some(&mut report),
+            )
+        });
+        let record = rendered.and_then(|(document, page_data)| {
+            let pdf = pdf::document_to_pdf_with_metrics_and_registry_with_logs(
+                &document,
+                None,
+                Some(engine.font_registry.as_ref()),
+                &engine.pdf_options,
+                engine.debug.clone(),
+                engine.perf.clone(),
+            )?;
+            Ok(RecordRender {
+                index,
+                page_count: document.pages.len(),
+                pdf,
+                page_data,
+                missing_glyphs: report.missing(),
+                warnings,
+            })
+        });
+        if record.is_ok() {
+            self.progress.record_done(index);
+        }
+        Some(record)
+    }
+}
+
+// A template compiled by `FullBleed::compile`: the resolved CSS and page templates plus, when
+// the HTML has no page references, the story built from it. Cheap to clone and safe to share
+// across threads; renders clone the story instead of parsing the HTML again. It is only
+// valid for the engine that compiled it; other engines reject it.
+#[derive(Clone)]
+pub struct CompiledTemplate {
+    inner: Arc<CompiledTemplateInner>,
+}
+
+struct CompiledTemplateInner {
+    key: u64,
+    engine_id: u64,
+    html: String,
+    css: String,
+    context: RenderContext,
+    story: Option<Vec<Box<dyn Flowable>>>,
+}
+
+impl CompiledTemplate {
+    // Hash of the HTML and CSS the template was compiled from.
+    pub fn key(&self) -> u64 {
+        self.inner.key
+    }
+
+    // False when the HTML uses page references, which are resolved by re-parsing each pass.
+    pub fn has_story(&self) -> bool {
+        self.inner.story.is_some()
+    }
+}
+
+// Compiled templates an engine keeps; the least recently compiled is dropped first.
+const COMPILED_TEMPLATE_CACHE_CAPACITY: usize = 64;
+
+static NEXT_ENGINE_ID: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+fn compiled_template_key(html: &str, css: &str) -> u64 {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    html.hash(&mut hasher);
+    css.hash(&mut hasher);
+    hasher.finish()
+}
+
 struct LayoutBuildResult {
     document: Document,
     story_ms: f64,
@@ -236,6 +525,68 @@ pub struct A11yVerifierCoreReport {
     pub facts: A11yVerifierFacts,
 }
 
+// Gate outcome of an audit: `fail` verdicts on rules gated at `error` for the profile fail
+// it; other failures and `warn` verdicts only count as warnings.
+#[derive(Debug, Clone)]
+pub struct A11yAuditGate {
+    pub ok: bool,
+    pub error_count: usize,
+    pub warn_count: usize,
+    pub failed_rule_ids: Vec<String>,
+}
+
+// Result of `FullBleed::render_with_audit`: the rule verdicts for the rendered HTML, the
+// gate they produce under `profile`, and the WCAG 2.0 AA and Section 508 coverage they feed.
+#[derive(Debug, Clone)]
+pub struct A11yAuditReport {
+    pub profile: String,
+    pub findings: Vec<A11yVerifierFinding>,
+    pub gate: A11yAuditGate,
+    pub wcag20aa: Wcag20AaCoverageSummary,
+    pub section508: Section508HtmlCoverageSummary,
+}
+
+impl A11yAuditReport {
+    fn from_findings(profile: String, findings: Vec<A11yVerifierFinding>) -> Self {
+        let mut gate = A11yAuditGate {
+            ok: true,
+            error_count: 0,
+            warn_count: 0,
+            failed_rule_ids: Vec::new(),
+        };
+        for finding in &findings {
+            if finding.verdict != "fail" && finding.verdict != "warn" {
+                continue;
+            }
+            let level = audit_contract::a11y_effective_gate_level(&profile, &finding.rule_id);
+            if level == "off" {
+                continue;
+            }
+            if finding.verdict == "fail" && level == "error" {
+                gate.error_count += 1;
+                gate.failed_rule_ids.push(finding.rule_id.clone());
+            } else {
+                gate.warn_count += 1;
+            }
+        }
+        gate.ok = gate.error_count == 0;
+        let verdicts = || {
+            findings
+                .iter()
+                .map(|f| (f.rule_id.as_str(), f.verdict.as_str()))
+        };
+        let wcag20aa = audit_contract::wcag20aa_coverage_from_rule_verdicts(verdicts());
+        let section508 = audit_contract::section508_html_coverage_from_rule_verdicts(verdicts());
+        A11yAuditReport {
+            profile,
+            findings,
+            gate,
+            wcag20aa,
+            section508,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct PaginationTraceSummary {
     pub page_count: Option<i64>,
@@ -327,6 +678,9 @@ pub struct PmrCoreCoverage {
 #[derive(Debug, Clone, Default)]
 pub struct PmrCoreContext {
     pub overflow_count: Option<i64>,
+    // Overflowing boxes of the rendered document; when set, `pmr.layout.overflow_none` is
+    // judged on it and cites each box.
+    pub layout_report: Option<LayoutReport>,
     pub known_loss_count: Option<i64>,
     pub pagination_summary: Option<PaginationTraceSummary>,
     pub source_page_count: Option<i64>,
@@ -406,6 +760,19 @@ pub enum WatermarkSemantics {
     Ocg,
 }
 
+// Page being painted by an `on_page_draw` callback. Numbers are 1-based; the canvas uses
+// the page's displayed (upright) coordinates with the origin at the top left.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageCtx {
+    pub page_number: usize,
+    pub page_count: usize,
+    pub page_size: Size,
+}
+
+// Custom drawing callback run once per finished page; its commands paint over the page
+// content, inside their own saved graphics state.
+pub type PageDrawFn = Arc<dyn Fn(&mut Canvas, PageCtx) + Send + Sync>;
+
 #[derive(Debug, Clone)]
 pub struct WatermarkSpec {
     pub kind: WatermarkKind,
@@ -494,8 +861,7 @@ fn apply_page_header(
         }
 
         page.commands.push(Command::SetFillColor(spec.color));
-        page.commands
-            .push(Command::SetFontName(spec.font_name.clone()));
+        page.commands.push(Command::SetFontName(font_name.clone()));
         page.commands.push(Command::SetFontSize(spec.font_size));
         page.commands.push(Command::DrawString {
             x: spec.x,
@@ -517,6 +883,55 @@ fn document_layout_signature(doc: &Document) -> u64 {
     hash_bytes_local(debug_repr.as_bytes())
 }
 
+const PAGE_REF_PREFIX: &str = "{page-of:";
+
+// Replaces every `{page-of:#anchor}` in `html` with the 1-based page the anchor landed on in
+// the previous layout pass. Anchors without a known page (first pass, or no such id) render
+// as `?` and are counted in `unresolved`.
+fn resolve_page_refs<'a>(
+    html: &'a str,
+    anchor_pages: &std::collections::HashMap<String, usize>,
+    unresolved: &mut usize,
+) -> std::borrow::Cow<'a, str> {
+    if !html.contains(PAGE_REF_PREFIX) {
+        return std::borrow::Cow::Borrowed(html);
+    }
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find(PAGE_REF_PREFIX) {
+        let after = &rest[start + PAGE_REF_PREFIX.len()..];
+        let Some(end) = after.find('}') else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let name = after[..end].trim();
+        let name = name.strip_prefix('#').unwrap_or(name);
+        match anchor_pages.get(name) {
+            Some(page) => out.push_str(&page.to_string()),
+            None => {
+                *unresolved += 1;
+                out.push('?');
+            }
+        }
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    std::borrow::Cow::Owned(out)
+}
+
+// Page (1-based) of the first named destination for each anchor name in `doc`.
+fn document_anchor_pages(doc: &Document) -> std::collections::HashMap<String, usize> {
+    let mut out = std::collections::HashMap::new();
+    for (index, page) in doc.pages.iter().enumerate() {
+        for command in &page.commands {
+            if let Command::NamedDestination { name, .. } = &*command {
+                out.entry(name.clone()).or_insert(index + 1);
+            }
+        }
+    }
+    out
+}
+
 fn jit_mode_str(mode: JitMode) -> &'static str {
     match mode {
         JitMode::Off => "off",
@@ -543,12 +958,44 @@ fn pdf_profile_str(profile: PdfProfile) -> &'static str {
     match profile {
         PdfProfile::None => "none",
         PdfProfile::PdfA2b => "pdfa2b",
+        PdfProfile::PdfA3b => "pdfa3b",
         PdfProfile::PdfX4 => "pdfx4",
         PdfProfile::Tagged => "tagged",
     }
 }
 
 fn validate_pdf_options(options: &PdfOptions) -> Result<(), FullBleedError> {
+    for attachment in &options.attachments {
+        if attachment.name.trim().is_empty() {
+            return Err(FullBleedError::InvalidConfiguration(
+                "attachment name cannot be empty".to_string(),
+            ));
+        }
+        if attachment.mime.trim().is_empty() {
+            return Err(FullBleedError::InvalidConfiguration(format!(
+                "attachment {:?} requires a MIME type",
+                attachment.name
+            )));
+        }
+    }
+    if !options.attachments.is_empty() && options.pdf_profile == PdfProfile::PdfA2b {
+        return Err(FullBleedError::InvalidConfiguration(
+            "pdf_profile=pdfa2b does not allow arbitrary attachments; use pdfa3b".to_string(),
+        ));
+    }
+
+    if options.encryption.is_some()
+        && matches!(
+            options.pdf_profile,
+            PdfProfile::PdfA2b | PdfProfile::PdfA3b | PdfProfile::PdfX4
+        )
+    {
+        return Err(FullBleedError::InvalidConfiguration(format!(
+            "pdf_profile={} does not allow encryption",
+            pdf_profile_str(options.pdf_profile)
+        )));
+    }
+
     if options.pdf_profile != PdfProfile::PdfX4 {
         return Ok(());
     }
@@ -589,7 +1036,7 @@ fn count_form_commands(doc: &Document) -> (usize, usize, usize, usize) {
     let mut svg_draws = 0usize;
     for page in &doc.pages {
         for cmd in &page.commands {
-            match cmd {
+            match &*cmd {
                 Command::DefineForm { resource_id, .. } => {
                     defs += 1;
                     if resource_id.starts_with("svg:") {
@@ -630,6 +1077,7 @@ fn log_jit_metrics(
     overlay: Option<&Document>,
     plan: Option<&jit::DocPlan>,
     page_data: Option<&PageDataContext>,
+    line_breaks: flowable::LineBreakStats,
 ) {
     let pages = doc.pages.len();
     let commands = count_commands(doc);
@@ -652,7 +1100,7 @@ fn log_jit_metrics(
         .unwrap_or_else(|| "null".to_string());
 
     let json = format!(
-        "{{\"type\":\"jit.metrics\",\"doc_id\":{},\"mode\":\"{}\",\"pdf_version\":\"{}\",\"pdf_profile\":\"{}\",\"timing_ms\":{{\"story\":{:.3},\"layout\":{:.3},\"plan\":{:.3},\"finalize\":{}}},\"counts\":{{\"pages\":{},\"commands\":{},\"overlay_commands\":{},\"form_defs\":{},\"form_draws\":{},\"svg_form_defs\":{},\"svg_form_draws\":{},\"paintables\":{},\"placements\":{},\"page_data_entries\":{}}}}}",
+        "{{\"type\":\"jit.metrics\",\"doc_id\":{},\"mode\":\"{}\",\"pdf_version\":\"{}\",\"pdf_profile\":\"{}\",\"timing_ms\":{{\"story\":{:.3},\"layout\":{:.3},\"plan\":{:.3},\"finalize\":{}}},\"counts\":{{\"pages\":{},\"commands\":{},\"overlay_commands\":{},\"form_defs\":{},\"form_draws\":{},\"svg_form_defs\":{},\"svg_form_draws\":{},\"paintables\":{},\"placements\":{},\"page_data_entries\":{}}},\"line_cache\":{{\"hits\":{},\"misses\":{},\"entries\":{}}}}}",
         doc_id,
         jit_mode_str(mode),
         pdf_version_str(options.pdf_version),
@@ -670,7 +1118,10 @@ fn log_jit_metrics(
         doc_svg_draws + ov_svg_draws,
         paintables,
         placements,
-        page_data_entries
+        page_data_entries,
+        line_breaks.hits,
+        line_breaks.misses,
+        line_breaks.entries
     );
     logger.log_json(&json);
 }
@@ -688,7 +1139,40 @@ fn render_html_snippet_to_commands(
     svg_raster_fallback: bool,
     transparent_body: bool,
     perf: Option<&PerfLogger>,
-) -> Vec<Command> {
+) -> CommandBuffer {
+    render_html_snippet_with_height(
+        html_snippet,
+        resolver,
+        page_size,
+        width,
+        height,
+        font_registry,
+        asset_bundle,
+        report,
+        svg_form,
+        svg_raster_fallback,
+        transparent_body,
+        perf,
+    )
+    .0
+}
+
+// Like `render_html_snippet_to_commands`, also returning the height the snippet used.
+#[allow(clippy::too_many_arguments)]
+fn render_html_snippet_with_height(
+    html_snippet: &str,
+    resolver: &style::StyleResolver,
+    page_size: Size,
+    width: Pt,
+    height: Pt,
+    font_registry: Option<Arc<FontRegistry>>,
+    asset_bundle: Option<Arc<AssetBundle>>,
+    report: Option<&mut GlyphCoverageReport>,
+    svg_form: bool,
+    svg_raster_fallback: bool,
+    transparent_body: bool,
+    perf: Option<&PerfLogger>,
+) -> (CommandBuffer, Pt) {
     // Wrap snippet in a minimal document so the HTML parser picks up body defaults.
     let body_style = if transparent_body {
         " style=\"background: transparent;\""
@@ -727,22 +1211,25 @@ fn render_html_snippet_to_commands(
         }
     }
 
+    let used = height - frame.remaining_height();
     let doc = canvas.finish();
-    doc.pages
-        .get(0)
+    let commands = doc
+        .pages
+        .first()
         .map(|p| p.commands.clone())
-        .unwrap_or_default()
+        .unwrap_or_default();
+    (commands, used)
 }
 
 fn substitute_placeholders_in_commands(
-    commands: &[Command],
+    commands: &CommandBuffer,
     page_number: usize,
     total_pages: usize,
     page_data: Option<&PageDataContext>,
-) -> Vec<Command> {
+) -> CommandBuffer {
     commands
         .iter()
-        .map(|cmd| match cmd {
+        .map(|cmd| match &*cmd {
             Command::DrawString { x, y, text } => Command::DrawString {
                 x: *x,
                 y: *y,
@@ -764,7 +1251,7 @@ fn substitute_placeholders_in_commands(
                     page_data,
                 ),
             },
-            _ => cmd.clone(),
+            _ => cmd.into_owned(),
         })
         .collect()
 }
@@ -787,9 +1274,9 @@ fn apply_page_header_html(
     }
 
     struct HeaderTemplateEntry {
-        base_commands: Vec<Command>,
+        base_commands: CommandBuffer,
         slot_eligible: bool,
-        rendered_cache: std::collections::HashMap<u64, Vec<Command>>,
+        rendered_cache: std::collections::HashMap<u64, CommandBuffer>,
     }
 
     let mut template_cache: std::collections::HashMap<String, HeaderTemplateEntry> =
@@ -832,7 +1319,7 @@ fn apply_page_header_html(
                     perf,
                 )
             } else {
-                Vec::new()
+                CommandBuffer::new()
             };
             HeaderTemplateEntry {
                 base_commands,
@@ -894,7 +1381,8 @@ fn apply_page_header_html(
             )
         };
 
-        let form_id = format!("hdr-{:016x}", hash_bytes_local(rendered.as_bytes()));
+        let form_id: Arc<str> =
+            format!("hdr-{:016x}", hash_bytes_local(rendered.as_bytes())).into();
         page.commands.push(Command::DefineForm {
             resource_id: form_id.clone(),
             width: spec.width,
@@ -921,6 +1409,150 @@ fn apply_page_header_html(
     }
 }
 
+// PDF page label style for a page counter style; None for styles labels cannot show.
+fn page_label_style_name(style: style::ListStyleTypeMode) -> Option<&'static str> {
+    match style {
+        style::ListStyleTypeMode::Decimal => Some("D"),
+        style::ListStyleTypeMode::LowerRoman => Some("r"),
+        style::ListStyleTypeMode::UpperRoman => Some("R"),
+        style::ListStyleTypeMode::LowerAlpha => Some("a"),
+        style::ListStyleTypeMode::UpperAlpha => Some("A"),
+        _ => None,
+    }
+}
+
+// Render the CSS `@page` margin boxes onto each page. Content is expanded per page (`page` and
+// `pages` counters, `string()` and `element()` values from the base document's layout) and laid
+// out as HTML inside the box, aligned by the box's `vertical-align`.
+#[allow(clippy::too_many_arguments)]
+fn apply_page_margin_boxes(
+    doc: &mut Document,
+    base: &Document,
+    resolver: &style::StyleResolver,
+    font_registry: Option<Arc<FontRegistry>>,
+    asset_bundle: Option<Arc<AssetBundle>>,
+    report: Option<&mut GlyphCoverageReport>,
+    svg_form: bool,
+    svg_raster_fallback: bool,
+    perf: Option<&PerfLogger>,
+) {
+    let total_pages = doc.pages.len();
+    let mut report = report;
+    let use_cache = report.is_none();
+    let mut rendered_cache: std::collections::HashMap<u64, (CommandBuffer, Pt)> =
+        std::collections::HashMap::new();
+    let mut values = margin_box::PageValues::new();
+    let template_names = collect_page_template_names(base, META_PAGE_TEMPLATE_KEY);
+
+    let no_commands = CommandBuffer::new();
+    for (idx0, page) in doc.pages.iter_mut().enumerate() {
+        let base_commands = base.pages.get(idx0).map_or(&no_commands, |p| &p.commands);
+        values.begin_page(base_commands);
+        let page_size = base.page_size_at(idx0);
+        let template_name = template_names.get(idx0).cloned().flatten();
+        let (area, page_name) = resolver.page_content_area(idx0, template_name.as_deref());
+        let margin_boxes =
+            style::page_margin_boxes_for(resolver.page_margin_boxes(), page_name, idx0 + 1);
+        // Lets the PDF writer label the page the way its margin boxes number it.
+        let label = margin_boxes
+            .iter()
+            .filter_map(|margin_box| margin_box.content.as_deref())
+            .find_map(counter::page_counter_style)
+            .and_then(page_label_style_name);
+        if let Some(name) = label {
+            page.commands.push(Command::Meta {
+                key: finalize::META_PAGE_LABEL_KEY.to_string(),
+                value: name.to_string(),
+            });
+        }
+        for margin_box in &margin_boxes {
+            let Some(content) = margin_box.content.as_deref() else {
+                continue;
+            };
+            let rect = margin_box.position.rect(page_size, area);
+            if rect.width <= Pt::ZERO || rect.height <= Pt::ZERO {
+                continue;
+            }
+            let body = margin_box::margin_box_html(content, idx0 + 1, total_pages, &values);
+            if body.trim().is_empty() {
+                continue;
+            }
+            let html = format!(
+                "<div style=\"text-align: {}; {}\">{}</div>",
+                margin_box.position.default_text_align(),
+                escape_html_text(&margin_box.css),
+                body
+            );
+            let key = hash_bytes_local(
+                format!(
+                    "{html}|{}|{}",
+                    rect.width.to_milli_i64(),
+                    rect.height.to_milli_i64()
+                )
+                .as_bytes(),
+            );
+            let render = |report: Option<&mut GlyphCoverageReport>| {
+                render_html_snippet_with_height(
+                    &html,
+                    resolver,
+                    page_size,
+                    rect.width,
+                    rect.height,
+                    font_registry.clone(),
+                    asset_bundle.clone(),
+                    report,
+                    svg_form,
+                    svg_raster_fallback,
+                    true,
+                    perf,
+                )
+            };
+            let (cmds, used) = if use_cache {
+                rendered_cache
+                    .entry(key)
+                    .or_insert_with(|| render(None))
+                    .clone()
+            } else {
+                render(report.as_deref_mut())
+            };
+            let slack = (rect.height - used).max(Pt::ZERO);
+            let offset = match margin_box
+                .vertical_align
+                .unwrap_or_else(|| margin_box.position.default_vertical_align())
+            {
+                style::VerticalAlignMode::Top => Pt::ZERO,
+                style::VerticalAlignMode::Middle => slack / 2,
+                style::VerticalAlignMode::Bottom => slack,
+            };
+
+            let form_id: Arc<str> = format!("mbox-{key:016x}").into();
+            page.commands.push(Command::DefineForm {
+                resource_id: form_id.clone(),
+                width: rect.width,
+                height: rect.height,
+                commands: cmds,
+            });
+            page.commands.push(Command::SaveState);
+            page.commands
+                .push(Command::Translate(rect.x, rect.y + offset));
+            page.commands.push(Command::ClipRect {
+                x: Pt::ZERO,
+                y: Pt::ZERO,
+                width: rect.width,
+                height: rect.height - offset,
+            });
+            page.commands.push(Command::DrawForm {
+                x: Pt::ZERO,
+                y: Pt::ZERO,
+                width: rect.width,
+                height: rect.height,
+                resource_id: form_id,
+            });
+            page.commands.push(Command::RestoreState);
+        }
+    }
+}
+
 fn apply_page_footer(
     doc: &mut Document,
     spec: &PageFooterSpec,
@@ -960,11 +1592,11 @@ fn apply_page_footer(
         }
 
         // Our coordinate system is top-left origin; DrawString expects y = top of the text box.
-        let y = (doc.page_size.height - spec.y_from_bottom - spec.font_size).max(Pt::ZERO);
+        let page_height = page.size.unwrap_or(doc.page_size).height;
+        let y = (page_height - spec.y_from_bottom - spec.font_size).max(Pt::ZERO);
 
         page.commands.push(Command::SetFillColor(spec.color));
-        page.commands
-            .push(Command::SetFontName(spec.font_name.clone()));
+        page.commands.push(Command::SetFontName(font_name.clone()));
         page.commands.push(Command::SetFontSize(spec.font_size));
         page.commands
             .push(Command::DrawString { x: spec.x, y, text });
@@ -1073,7 +1705,7 @@ fn build_watermark_commands(
             if angle.abs() > f32::EPSILON {
                 commands.push(Command::Rotate(angle));
             }
-            commands.push(Command::SetFontName(spec.font_name.clone()));
+            commands.push(Command::SetFontName(spec.font_name.as_str().into()));
             commands.push(Command::SetFontSize(spec.font_size));
             commands.push(Command::DrawString {
                 x: Pt::ZERO - width.mul_ratio(1, 2),
@@ -1101,7 +1733,8 @@ fn build_watermark_commands(
                 true,
                 None,
             );
-            let form_id = format!("wm-{:016x}", hash_bytes_local(rendered.as_bytes()));
+            let form_id: Arc<str> =
+                format!("wm-{:016x}", hash_bytes_local(rendered.as_bytes())).into();
             commands.push(Command::DefineForm {
                 resource_id: form_id.clone(),
                 width,
@@ -1158,7 +1791,7 @@ fn build_watermark_commands(
                 y: compensated_y,
                 width: size.width,
                 height: size.height,
-                resource_id: resolved_path,
+                resource_id: resolved_path.into(),
             });
             commands.push(Command::RestoreState);
         }
@@ -1178,6 +1811,39 @@ fn build_watermark_commands(
     commands
 }
 
+fn apply_page_draw(
+    overlay: &mut Document,
+    base: &Document,
+    draw: &(dyn Fn(&mut Canvas, PageCtx) + Send + Sync),
+) {
+    let page_count = base.pages.len();
+    for (index, page) in overlay.pages.iter_mut().enumerate() {
+        let page_size = base.page_size_at(index);
+        let mut canvas = Canvas::new(page_size);
+        canvas.set_page_rotate(page.rotate);
+        draw(
+            &mut canvas,
+            PageCtx {
+                page_number: index + 1,
+                page_count,
+                page_size,
+            },
+        );
+        let commands = canvas
+            .finish()
+            .pages
+            .into_iter()
+            .flat_map(|page| page.commands)
+            .collect::<Vec<_>>();
+        if commands.is_empty() {
+            continue;
+        }
+        page.commands.push(Command::SaveState);
+        page.commands.extend(commands);
+        page.commands.push(Command::RestoreState);
+    }
+}
+
 fn build_watermark_document(
     base: &Document,
     spec: &WatermarkSpec,
@@ -1195,8 +1861,10 @@ fn build_watermark_document(
         pages: base
             .pages
             .iter()
-            .map(|_| Page {
-                commands: Vec::new(),
+            .map(|page| Page {
+                commands: CommandBuffer::new(),
+                size: page.size,
+                rotate: page.rotate,
             })
             .collect(),
     };
@@ -1205,7 +1873,7 @@ fn build_watermark_document(
         let page_number = idx + 1;
         let cmds = build_watermark_commands(
             spec,
-            base.page_size,
+            base.page_size_at(idx),
             page_number,
             total_pages,
             page_data,
@@ -1228,7 +1896,9 @@ fn merge_background_commands(base: &mut Document, background: &Document) {
     }
     for (base_page, bg_page) in base.pages.iter_mut().zip(background.pages.iter()) {
         if !bg_page.commands.is_empty() {
-            base_page.commands.splice(0..0, bg_page.commands.clone());
+            let mut commands = bg_page.commands.clone();
+            commands.append(&base_page.commands);
+            base_page.commands = commands;
         }
     }
 }
@@ -1272,7 +1942,7 @@ impl FullBleed {
         self.font_registry.resolve_trace(font_name)
     }
 
-    #[cfg(feature = "python")]
+    #[cfg(any(feature = "python", test))]
     pub(crate) fn asset_bundle_ref(&self) -> &AssetBundle {
         self.asset_bundle.as_ref()
     }
@@ -1292,28 +1962,95 @@ impl FullBleed {
         }
     }
 
-    fn emit_html_asset_warnings(&self, doc_id: usize, html: &str) {
-        let warnings = html::scan_html_asset_warnings(html);
-        if warnings.is_empty() {
+    // Spill store and queue limits for batch documents waiting to be written. A memory budget
+    // bounds the queue by estimated size. Without one, `jit` paths spill by queue length: as
+    // configured by `jit_spill`, else FULLBLEED_JIT_SPILL(_DIR) past `max_docs` documents.
+    fn batch_spill(
+        &self,
+        jit: bool,
+        max_docs: usize,
+    ) -> Result<(Option<spill::SpillStore>, usize, usize), FullBleedError> {
+        let open = |dir: &std::path::Path| {
+            spill::SpillStore::new(dir).map(|store| store.with_codec(self.spill_codec))
+        };
+        if let Some(budget) = self.memory_budget.as_ref() {
+            let dir = budget.spill_dir_or_default();
+            return Ok((Some(open(&dir)?), budget.max_bytes(), usize::MAX));
+        }
+        if !jit {
+            return Ok((None, usize::MAX, usize::MAX));
+        }
+        if let Some((dir, threshold)) = self.jit_spill.as_ref() {
+            let store = open(dir)?;
+            return Ok((Some(store), usize::MAX, *threshold));
+        }
+        let spill_enabled = std::env::var("FULLBLEED_JIT_SPILL")
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        let spill_dir = std::env::var("FULLBLEED_JIT_SPILL_DIR")
+            .ok()
+            .map(std::path::PathBuf::from)
+            .or_else(|| spill_enabled.then(|| std::env::temp_dir().join("fullbleed_spill")));
+        match spill_dir {
+            Some(dir) => Ok((Some(open(&dir)?), usize::MAX, max_docs)),
+            None => Ok((None, usize::MAX, usize::MAX)),
+        }
+    }
+
+    fn record_batch_spill(
+        &self,
+        event: &str,
+        store: Option<&spill::SpillStore>,
+        queue: &spill::DocumentQueue<'_>,
+    ) {
+        let (files, bytes) = store.map(|store| store.metrics()).unwrap_or((0, 0));
+        let images = store.map_or(0, |store| store.images_spilled());
+        let peak = queue.peak_resident_bytes() as u64;
+        if let Ok(mut metrics) = self.spill_metrics.lock() {
+            metrics.documents += files;
+            metrics.images += images;
+            metrics.bytes += bytes;
+            metrics.peak_resident_bytes = metrics.peak_resident_bytes.max(peak);
+        }
+        if store.is_none() {
             return;
         }
-        for warning in warnings {
-            let detail_preview = if warning.details.is_empty() {
-                String::new()
-            } else {
-                let mut preview = warning.details.clone();
-                if preview.len() > 3 {
-                    preview.truncate(3);
-                    preview.push("...".to_string());
-                }
-                format!(" ({})", preview.join(", "))
-            };
-            eprintln!(
-                "[fullbleed][assets] doc {}: {}{}",
-                doc_id, warning.message, detail_preview
+        if let Some(logger) = self.debug.as_deref() {
+            let json = format!(
+                "{{\"type\":\"{}\",\"files\":{},\"images\":{},\"bytes\":{},\"peak_resident_bytes\":{}}}",
+                event, files, images, bytes, peak
+            );
+            logger.log_json(&json);
+        }
+        if let Some(perf_logger) = self.perf.as_deref() {
+            perf_logger.log_counts(
+                event,
+                None,
+                &[
+                    ("files", files),
+                    ("images", images),
+                    ("bytes", bytes),
+                    ("peak_resident_bytes", peak),
+                ],
             );
+        }
+    }
+
+    // Spill volume of this engine's batch renders so far (see `FullBleedBuilder::memory_budget`).
+    pub fn spill_metrics(&self) -> SpillMetrics {
+        self.spill_metrics
+            .lock()
+            .map(|metrics| *metrics)
+            .unwrap_or_default()
+    }
+
+    fn emit_html_asset_warnings(&self, doc_id: usize, html: &str) {
+        if self.debug.is_none() && !warning::wanted(self.warnings.as_ref()) {
+            return;
+        }
+        for asset in html::scan_html_asset_warnings(html) {
             if let Some(logger) = self.debug.as_deref() {
-                let details = warning
+                let details = asset
                     .details
                     .iter()
                     .map(|d| format!("\"{}\"", d.replace('"', "\\\"")))
@@ -1322,16 +2059,66 @@ impl FullBleed {
                 let json = format!(
                     "{{\"type\":\"jit.html_asset_warning\",\"doc_id\":{},\"kind\":\"{}\",\"message\":\"{}\",\"details\":[{}]}}",
                     doc_id,
-                    warning.kind.replace('"', "\\\""),
-                    warning.message.replace('"', "\\\""),
+                    asset.kind.replace('"', "\\\""),
+                    asset.message.replace('"', "\\\""),
                     details
                 );
                 logger.log_json(&json);
             }
+            let code = match asset.kind.as_str() {
+                "stylesheet" => "ASSET_STYLESHEET_LINK",
+                "font-preload" => "ASSET_FONT_PRELOAD",
+                "style-tag" => "ASSET_STYLE_TAG",
+                "script" => "ASSET_SCRIPT",
+                _ => "ASSET_IGNORED",
+            };
+            self.warn(
+                Warning::new(WarningKind::Asset, code, asset.message)
+                    .doc(Some(doc_id))
+                    .details(asset.details),
+            );
         }
     }
 
-    fn has_full_page_background(doc: &Document) -> bool {
+    fn warn(&self, warning: Warning) {
+        warning::emit(self.warnings.as_ref(), warning);
+    }
+
+    fn emit_glyph_warnings(&self, doc_id: usize, report: &GlyphCoverageReport) {
+        for fallback in report.fallbacks() {
+            self.warn(
+                Warning::for_loss(
+                    WarningKind::FontSubstitution,
+                    KnownLoss::FontFallback {
+                        font: fallback.font.clone(),
+                        script: fallback.script,
+                    },
+                    format!(
+                        "{} {} character(s) drawn with fallback font {} ({})",
+                        fallback.chars, fallback.script, fallback.font, fallback.sample
+                    ),
+                )
+                .doc(Some(doc_id))
+                .details(vec![fallback.font, fallback.script.to_string()]),
+            );
+        }
+        for missing in report.missing() {
+            self.warn(
+                Warning::new(
+                    WarningKind::MissingGlyph,
+                    "MISSING_GLYPH",
+                    format!(
+                        "U+{:04X} {:?} has no glyph in any font tried",
+                        missing.codepoint, missing.ch
+                    ),
+                )
+                .doc(Some(doc_id))
+                .details(missing.fonts_tried),
+            );
+        }
+    }
+
+    fn has_full_page_background(doc: &Document) -> bool {
         let page_w = doc.page_size.width.to_f32();
         let page_h = doc.page_size.height.to_f32();
         let min_w = page_w * 0.85;
@@ -1343,7 +2130,7 @@ impl FullBleed {
                     y,
                     width,
                     height,
-                } = cmd
+                } = &*cmd
                 {
                     let w = width.to_f32();
                     let h = height.to_f32();
@@ -1358,6 +2145,39 @@ impl FullBleed {
         false
     }
 
+    // Checks `css` the way a render would (with bundle CSS, at the configured page size) and
+    // returns what the engine could not use from it, without laying anything out.
+    pub fn css_diagnostics(&self, css: &str) -> CssDiagnostics {
+        let merged_css = self.merge_css(css);
+        let page_size = self
+            .resolve_page_templates_for_css(&merged_css, None)
+            .first()
+            .map(|template| template.page_size);
+        let resolver =
+            style::StyleResolver::new_with_debug_and_viewport(&merged_css, None, page_size);
+        self.rebase_css_diagnostics(css, resolver.css_diagnostics())
+    }
+
+    // Resolver diagnostics count lines in the merged stylesheet; point them back into `css`.
+    fn rebase_css_diagnostics(&self, css: &str, diagnostics: &CssDiagnostics) -> CssDiagnostics {
+        let mut diagnostics = diagnostics.clone();
+        diagnostics.rebase(self.bundle_css_lines(css));
+        diagnostics
+    }
+
+    // Lines of bundle CSS ahead of `css` in `merge_css(css)`; `u32::MAX` when the merged
+    // stylesheet is all bundle CSS.
+    fn bundle_css_lines(&self, css: &str) -> u32 {
+        if self.asset_css.is_empty() {
+            0
+        } else if css.trim().is_empty() {
+            u32::MAX
+        } else {
+            // `merge_css` joins the two with a blank line.
+            self.asset_css.matches('\n').count() as u32 + 2
+        }
+    }
+
     fn merge_css(&self, css: &str) -> String {
         if self.asset_css.is_empty() {
             css.to_string()
@@ -3495,6 +4315,48 @@ impl FullBleed {
                 .all(|ch| ch.is_ascii_alphanumeric() || ch == '-')
     }
 
+    // Gate of `enforce_audit_gates`: the PMR audits of the laid-out document, in error mode.
+    fn enforce_audit_gates(
+        &self,
+        doc_id: usize,
+        html: &str,
+        document: &Document,
+    ) -> Result<(), FullBleedError> {
+        let Some(profile) = self.audit_gate_profile.as_deref() else {
+            return Ok(());
+        };
+        let ctx = PmrCoreContext {
+            layout_report: Some(LayoutReport::from_document(document)),
+            render_page_count: Some(document.pages.len() as i64),
+            html_artifact_bytes: Some(html.len() as u64),
+            ..PmrCoreContext::default()
+        };
+        let report = self.verify_paged_media_rank_html_core(html, profile, "error", &ctx);
+        if let Some(logger) = self.debug.as_deref() {
+            logger.increment("pmr.gate.errors", report.gate.error_count as u64);
+        }
+        if report.gate.ok {
+            return Ok(());
+        }
+        if let Some(logger) = self.debug.as_deref() {
+            let ids = report
+                .gate
+                .failed_audit_ids
+                .iter()
+                .map(|id| format!("\"{}\"", debug::json_escape(id)))
+                .collect::<Vec<_>>()
+                .join(",");
+            logger.log_json(&format!(
+                "{{\"type\":\"pmr.gate.failed\",\"doc_id\":{doc_id},\"profile\":\"{}\",\"audit_ids\":[{ids}]}}",
+                debug::json_escape(profile)
+            ));
+        }
+        Err(FullBleedError::AuditGateFailed {
+            profile: profile.to_string(),
+            audit_ids: report.gate.failed_audit_ids,
+        })
+    }
+
     fn pmr_gate(audits: &[PmrCoreAudit], profile: &str, mode: &str) -> PmrCoreGate {
         let mode_norm = {
             let m = mode.trim().to_ascii_lowercase();
@@ -3699,7 +4561,60 @@ impl FullBleed {
         let pagination_summary = ctx.pagination_summary.as_ref();
         let pagination_overflow =
             pagination_summary.and_then(|summary| summary.overflow_event_count);
-        let overflow = pagination_overflow.or(ctx.overflow_count).unwrap_or(0);
+        let layout_overflows = ctx.layout_report.as_ref().map(LayoutReport::overflows);
+        let overflow = layout_overflows
+            .map(|overflows| overflows.len() as i64)
+            .or(pagination_overflow)
+            .or(ctx.overflow_count)
+            .unwrap_or(0);
+        let mut overflow_evidence = vec![PmrCoreEvidence {
+            selector: None,
+            diagnostic_ref: Some(
+                if layout_overflows.is_some() {
+                    "layout_report.overflows"
+                } else if pagination_overflow.is_some() {
+                    "pagination_trace_summary.overflow_event_count"
+                } else {
+                    "component_validation.overflow_count"
+                }
+                .to_string(),
+            ),
+            values: {
+                let mut values = vec![("overflow_count".to_string(), overflow.to_string())];
+                if let Some(count) = pagination_overflow {
+                    values.push((
+                        "pagination_overflow_event_count".to_string(),
+                        count.to_string(),
+                    ));
+                }
+                if let Some(count) = ctx.overflow_count {
+                    values.push((
+                        "component_validation_overflow_count".to_string(),
+                        count.to_string(),
+                    ));
+                }
+                values
+            },
+        }];
+        for item in layout_overflows.unwrap_or_default() {
+            overflow_evidence.push(PmrCoreEvidence {
+                selector: item.element_path.clone(),
+                diagnostic_ref: Some("layout_report.overflows".to_string()),
+                values: vec![
+                    ("page_number".to_string(), item.page_number.to_string()),
+                    ("flowable".to_string(), item.flowable.clone()),
+                    ("reason".to_string(), item.reason.clone()),
+                    (
+                        "overflow_x_pt".to_string(),
+                        format!("{:.2}", item.overflow_x.to_f32()),
+                    ),
+                    (
+                        "overflow_y_pt".to_string(),
+                        format!("{:.2}", item.overflow_y.to_f32()),
+                    ),
+                ],
+            });
+        }
         Self::pmr_push_contract_audit(
             &mut audits,
             "pmr.layout.overflow_none",
@@ -3711,33 +4626,7 @@ impl FullBleed {
             } else {
                 format!("Overflow placements detected ({overflow}).")
             },
-            vec![PmrCoreEvidence {
-                selector: None,
-                diagnostic_ref: Some(
-                    if pagination_overflow.is_some() {
-                        "pagination_trace_summary.overflow_event_count"
-                    } else {
-                        "component_validation.overflow_count"
-                    }
-                    .to_string(),
-                ),
-                values: {
-                    let mut values = vec![("overflow_count".to_string(), overflow.to_string())];
-                    if let Some(count) = pagination_overflow {
-                        values.push((
-                            "pagination_overflow_event_count".to_string(),
-                            count.to_string(),
-                        ));
-                    }
-                    if let Some(count) = ctx.overflow_count {
-                        values.push((
-                            "component_validation_overflow_count".to_string(),
-                            count.to_string(),
-                        ));
-                    }
-                    values
-                },
-            }],
+            overflow_evidence,
             None,
         );
 
@@ -4207,6 +5096,16 @@ impl FullBleed {
                 logger.increment("jit.known_loss.lazy_layout_no_convergence", 1);
             }
         }
+        if self.layout_strategy == LayoutStrategy::Lazy && !converged {
+            self.warn(
+                Warning::for_loss(
+                    WarningKind::KnownLoss,
+                    KnownLoss::LazyLayoutNoConvergence { passes },
+                    format!("lazy layout did not settle within {passes} of {pass_limit} passes"),
+                )
+                .doc(Some(doc_id)),
+            );
+        }
         if let Some(perf) = self.perf.as_deref() {
             perf.log_span_ms("layout.strategy", Some(doc_id), elapsed_ms);
             perf.log_counts(
@@ -4230,9 +5129,94 @@ impl FullBleed {
         resolver: &style::StyleResolver,
         report: Option<&mut GlyphCoverageReport>,
     ) -> Result<LayoutBuildResult, FullBleedError> {
+        self.build_document_with_layout_strategy_and_story(
+            doc_id,
+            html,
+            None,
+            page_templates,
+            resolver,
+            report,
+        )
+    }
+
+    // `story` is a prebuilt story for `html` (see `CompiledTemplate`, or a bound record); each
+    // pass lays out a clone of it instead of parsing the HTML.
+    fn build_document_with_layout_strategy_and_story(
+        &self,
+        doc_id: usize,
+        html: &str,
+        story: Option<&[Box<dyn Flowable>]>,
+        page_templates: &[PageTemplate],
+        resolver: &style::StyleResolver,
+        report: Option<&mut GlyphCoverageReport>,
+    ) -> Result<LayoutBuildResult, FullBleedError> {
+        // Story building has no engine handle; it warns through this scope.
+        let _warning_scope = warning::scope(self.warnings.as_ref());
+        if !self.strict_fidelity {
+            return self
+                .run_layout_passes(doc_id, html, story, page_templates, resolver, report)
+                .map_err(|err| with_render_diagnostics(err, resolver, &[]));
+        }
+        let (built, warnings) = warning::collect(|| {
+            self.run_layout_passes(doc_id, html, story, page_templates, resolver, report)
+        });
+        let built = built.map_err(|err| with_render_diagnostics(err, resolver, &warnings))?;
+        let losses = resolver.known_losses().iter().cloned().chain(warnings);
+        self.enforce_fidelity(doc_id, losses)
+            .map_err(|err| with_render_diagnostics(err, resolver, &[]))?;
+        Ok(built)
+    }
+
+    // Gate of `strict_fidelity`: fails with every distinct known loss among `warnings`.
+    fn enforce_fidelity(
+        &self,
+        doc_id: usize,
+        warnings: impl IntoIterator<Item = Warning>,
+    ) -> Result<(), FullBleedError> {
+        let mut losses: Vec<Warning> = Vec::new();
+        for warning in warnings {
+            if warning.kind == WarningKind::KnownLoss && !losses.contains(&warning) {
+                losses.push(warning);
+            }
+        }
+        if losses.is_empty() {
+            return Ok(());
+        }
+        if let Some(logger) = self.debug.as_deref() {
+            let codes = losses
+                .iter()
+                .map(|loss| format!("\"{}\"", loss.code))
+                .collect::<Vec<_>>()
+                .join(",");
+            logger.log_json(&format!(
+                "{{\"type\":\"jit.strict_fidelity.failed\",\"doc_id\":{doc_id},\"codes\":[{codes}]}}"
+            ));
+        }
+        Err(FullBleedError::FidelityLoss(losses))
+    }
+
+    fn run_layout_passes(
+        &self,
+        doc_id: usize,
+        html: &str,
+        story: Option<&[Box<dyn Flowable>]>,
+        page_templates: &[PageTemplate],
+        resolver: &style::StyleResolver,
+        report: Option<&mut GlyphCoverageReport>,
+    ) -> Result<LayoutBuildResult, FullBleedError> {
+        let prebuilt_story = story;
         let lazy = self.layout_strategy == LayoutStrategy::Lazy;
-        let pass_limit = self.layout_pass_limit();
-        let started = std::time::Instant::now();
+        // Page references need at least one more pass than there are forward references to
+        // settle, so they get the lazy pass budget even under the eager strategy.
+        let has_page_refs = html.contains(PAGE_REF_PREFIX);
+        let pass_limit = if has_page_refs {
+            self.layout_pass_limit().max(self.lazy_max_passes).max(2)
+        } else {
+            self.layout_pass_limit()
+        };
+        let mut anchor_pages = std::collections::HashMap::new();
+        let mut unresolved_page_refs = 0usize;
+        let started = crate::platform::Instant::now();
         let mut story_ms = 0.0;
         let mut layout_ms = 0.0;
         let mut passes = 0usize;
@@ -4258,21 +5242,30 @@ impl FullBleed {
             };
 
             passes += 1;
-            let t_story = std::time::Instant::now();
-            let story = html::html_to_story_with_resolver_and_fonts_and_report(
-                html,
-                resolver,
-                Some(self.font_registry.clone()),
-                Some(self.asset_bundle.clone()),
-                pass_report_ref.as_deref_mut(),
-                self.svg_form_xobjects,
-                self.svg_raster_fallback,
-                self.perf.as_deref(),
-                Some(doc_id),
-            );
+            unresolved_page_refs = 0;
+            let pass_html = resolve_page_refs(html, &anchor_pages, &mut unresolved_page_refs);
+            let t_story = crate::platform::Instant::now();
+            let story = match prebuilt_story {
+                Some(story) if !has_page_refs => story.iter().map(|f| f.clone_box()).collect(),
+                _ => html::html_to_story_with_resolver_and_fonts_and_report(
+                    &pass_html,
+                    resolver,
+                    Some(self.font_registry.clone()),
+                    Some(self.asset_bundle.clone()),
+                    pass_report_ref.as_deref_mut(),
+                    self.svg_form_xobjects,
+                    self.svg_raster_fallback,
+                    self.perf.as_deref(),
+                    Some(doc_id),
+                ),
+            };
             story_ms += t_story.elapsed().as_secs_f64() * 1000.0;
 
-            let mut doc = DocTemplate::new(page_templates.to_vec());
+            let mut doc = DocTemplate::new(page_templates.to_vec())
+                .with_on_page_start(self.on_page_start.clone())
+                .with_on_page_end(self.on_page_end.clone())
+                .with_progress(self.pdf_options.progress.clone(), doc_id)
+                .with_warnings(self.warnings.clone(), doc_id);
             if let Some(logger) = self.debug.clone() {
                 doc = doc.with_debug(logger, Some(doc_id));
             }
@@ -4280,14 +5273,24 @@ impl FullBleed {
                 doc.add_flowable(flowable);
             }
 
-            let t_layout = std::time::Instant::now();
+            let t_layout = crate::platform::Instant::now();
             let _perf_guard = flowable::set_perf_context(self.perf.clone(), Some(doc_id));
-            let next_built = doc.build()?;
+            let next_built = if self.parallel_sections {
+                doc.build_sections()?
+            } else {
+                doc.build()?
+            };
             layout_ms += t_layout.elapsed().as_secs_f64() * 1000.0;
 
             let signature = document_layout_signature(&next_built);
             converged = !lazy || previous_signature.is_some_and(|last| last == signature);
             previous_signature = Some(signature);
+            if has_page_refs {
+                // Settled once the pages this pass substituted are the pages it produced.
+                let landed = document_anchor_pages(&next_built);
+                converged &= landed == anchor_pages;
+                anchor_pages = landed;
+            }
             built = Some(next_built);
             if collect_report {
                 final_report = Some(pass_report);
@@ -4306,10 +5309,33 @@ impl FullBleed {
             started.elapsed().as_secs_f64() * 1000.0,
         );
 
+        if let Some(logger) = self.debug.as_deref() {
+            if unresolved_page_refs > 0 {
+                logger.increment("jit.page_ref.unresolved", unresolved_page_refs as u64);
+            }
+            if has_page_refs && !converged {
+                logger.increment("jit.known_loss.page_ref_no_convergence", 1);
+            }
+        }
+        if has_page_refs && !converged {
+            self.warn(
+                Warning::for_loss(
+                    WarningKind::KnownLoss,
+                    KnownLoss::PageRefNoConvergence {
+                        unresolved: unresolved_page_refs,
+                    },
+                    "page references did not settle; some page numbers may be off",
+                )
+                .doc(Some(doc_id)),
+            );
+        }
+
         if let Some(report) = report.as_deref_mut() {
             if let Some(pass_report) = final_report {
                 *report = pass_report;
             }
+            // Overlay text is not attributed to the last body element.
+            report.set_element_path(None);
         }
 
         let Some(document) = built else {
@@ -4317,6 +5343,7 @@ impl FullBleed {
                 "layout pass budget prevented any layout pass".to_string(),
             ));
         };
+        self.enforce_audit_gates(doc_id, html, &document)?;
 
         Ok(LayoutBuildResult {
             document,
@@ -4356,6 +5383,23 @@ impl FullBleed {
                     logger.log_json(&json);
                     logger.increment("jit.known_loss.page_size_overridden", 1);
                 }
+                self.warn(
+                    Warning::for_loss(
+                        WarningKind::KnownLoss,
+                        KnownLoss::PageSizeOverridden {
+                            css: css_size,
+                            engine: self.default_page_size,
+                        },
+                        format!(
+                            "CSS @page size {:.1}x{:.1}pt ignored; the engine page size {:.1}x{:.1}pt was set explicitly",
+                            css_size.width.to_f32(),
+                            css_size.height.to_f32(),
+                            self.default_page_size.width.to_f32(),
+                            self.default_page_size.height.to_f32()
+                        ),
+                    )
+                    .doc(doc_id),
+                );
             } else {
                 page_size = css_size;
             }
@@ -4399,22 +5443,208 @@ impl FullBleed {
             logger.log_json(&json);
         }
 
-        build_page_templates(page_size, base_margins, &page_margins)
+        // `@page :first` margins apply to the first page; later pages keep the base margins.
+        let named_setups = style::extract_css_named_page_setups(
+            merged_css,
+            self.debug.as_deref(),
+            Some(self.default_page_size),
+        );
+        // Odd and even pages get their own templates when margins are mirrored or CSS has
+        // `:left`/`:right` rules.
+        let has_parity = self.mirror_margins
+            || named_setups
+                .iter()
+                .any(|(selector, _)| selector.parity.is_some());
+        let parities: Vec<Option<PageParity>> = if has_parity {
+            vec![Some(PageParity::Odd), Some(PageParity::Even)]
+        } else {
+            vec![None]
+        };
+        let margins_for = |setup: &style::CssPageSetup, parity: Option<PageParity>| {
+            let base = match parity {
+                Some(PageParity::Even) if self.mirror_margins => base_margins.mirror(),
+                _ => base_margins,
+            };
+            if self.margins_explicit {
+                base
+            } else {
+                setup.resolve_margins(base).unwrap_or(base)
+            }
+        };
+        if !self.margins_explicit {
+            let first_setup = named_setups
+                .iter()
+                .find(|(selector, _)| selector.name.is_none() && selector.first)
+                .map(|(_, setup)| *setup);
+            if let Some(first_setup) = first_setup.filter(|setup| setup.has_margin_override()) {
+                let parity = has_parity.then_some(PageParity::Odd);
+                let mut setup = selected_page_setup(&named_setups, None, parity);
+                setup.merge(&first_setup);
+                page_margins.insert(1, margins_for(&setup, parity));
+                if !has_parity {
+                    page_margins.entry(2).or_insert(base_margins);
+                }
+            }
+        }
+
+        let mut templates = if has_parity && page_margins.is_empty() {
+            Vec::new()
+        } else {
+            build_page_templates(page_size, base_margins, &page_margins)
+        };
+        if has_parity {
+            for parity in [PageParity::Odd, PageParity::Even] {
+                let setup = selected_page_setup(&named_setups, None, Some(parity));
+                let margins = margins_for(&setup, Some(parity));
+                templates.push(
+                    PageTemplate::new(format!("Page:{}", parity.as_str()), page_size)
+                        .with_frame(page_frame_rect(page_size, margins))
+                        .with_parity(parity),
+                );
+            }
+        }
+        let mut page_names: Vec<&str> = Vec::new();
+        for (selector, _) in &named_setups {
+            if let Some(name) = selector.name.as_deref()
+                && !page_names.contains(&name)
+            {
+                page_names.push(name);
+            }
+        }
+        for name in page_names {
+            for &parity in &parities {
+                let setup = selected_page_setup(&named_setups, Some(name), parity);
+                // An explicit builder page size applies to named pages as well.
+                let named_size = if self.page_size_explicit {
+                    page_size
+                } else {
+                    setup.size.unwrap_or(page_size)
+                };
+                let margins = margins_for(&setup, parity);
+                let mut template = PageTemplate::new(
+                    match parity {
+                        Some(parity) => format!("{name}:{}", parity.as_str()),
+                        None => name.to_string(),
+                    },
+                    named_size,
+                )
+                .with_frame(page_frame_rect(named_size, margins))
+                .with_page_name(name);
+                if let Some(parity) = parity {
+                    template = template.with_parity(parity);
+                }
+                templates.push(template);
+            }
+        }
+        templates
+            .into_iter()
+            .map(|template| {
+                let rotate = self.page_rotate_for(template.page_size);
+                template
+                    .with_rotate(i32::from(rotate))
+                    .with_frame_layout(&self.page_frames)
+            })
+            .collect()
+    }
+
+    // `/Rotate` for pages of `page_size`: the builder's page rotation, or a quarter turn for
+    // landscape pages when `rotate_landscape_pages` is on.
+    fn page_rotate_for(&self, page_size: Size) -> u16 {
+        if self.page_rotate != 0 {
+            self.page_rotate
+        } else if self.rotate_landscape_pages && page_size.width > page_size.height {
+            90
+        } else {
+            0
+        }
     }
 
     fn build_render_context(&self, css: &str, doc_id: Option<usize>) -> RenderContext {
-        let t_css = std::time::Instant::now();
+        let t_css = crate::platform::Instant::now();
         let merged_css = self.merge_css(css);
-        let page_templates = self.resolve_page_templates_for_css(&merged_css, doc_id);
+        // Under strict fidelity, losses found here (an overridden @page size, unusable CSS)
+        // travel with the resolver and fail the layout that uses it.
+        let (page_templates, mut losses) = if self.strict_fidelity {
+            warning::collect(|| self.resolve_page_templates_for_css(&merged_css, doc_id))
+        } else {
+            (
+                self.resolve_page_templates_for_css(&merged_css, doc_id),
+                Vec::new(),
+            )
+        };
         let page_size = page_templates.get(0).map(|t| t.page_size).unwrap_or(Size {
             width: Pt::ZERO,
             height: Pt::ZERO,
         });
+        let (font_face_families, font_face_failures) =
+            self.register_css_font_faces(css, &merged_css, page_size, doc_id);
         let resolver = style::StyleResolver::new_with_debug_and_viewport(
             &merged_css,
             self.debug.clone(),
             Some(page_size),
+        )
+        .with_font_face_families(font_face_families)
+        .with_font_face_failures(font_face_failures)
+        .with_custom_elements(self.custom_elements.clone())
+        .with_page_content_height(
+            page_templates
+                .first()
+                .map(|template| template.content_height())
+                .unwrap_or(page_size.height),
+        )
+        .with_page_margin_boxes(
+            style::extract_css_page_margin_boxes(
+                &merged_css,
+                self.debug.as_deref(),
+                Some(page_size),
+            ),
+            page_templates
+                .iter()
+                .map(|template| style::PageArea {
+                    template_name: template.name.clone(),
+                    page_name: template.page_name().map(str::to_string),
+                    rect: template.content_rect(),
+                })
+                .collect(),
         );
+        let css_diagnostics = self.rebase_css_diagnostics(css, resolver.css_diagnostics());
+        let resolver = resolver.with_css_diagnostics(css_diagnostics);
+        if !resolver.css_diagnostics().is_empty()
+            && (self.debug.is_some()
+                || self.strict_fidelity
+                || warning::wanted(self.warnings.as_ref()))
+        {
+            let diagnostics = resolver.css_diagnostics();
+            if let Some(logger) = self.debug.as_deref() {
+                logger.log_json(&diagnostics.to_json());
+            }
+            let warning = Warning::for_loss(
+                WarningKind::KnownLoss,
+                KnownLoss::CssDiagnostics {
+                    count: diagnostics.len(),
+                },
+                format!(
+                    "{} CSS rule(s), declaration(s) or selector(s) could not be used",
+                    diagnostics.len()
+                ),
+            )
+            .doc(doc_id)
+            .details(diagnostics.iter().map(ToString::to_string).collect());
+            if self.strict_fidelity {
+                losses.push(warning.clone());
+            }
+            self.warn(warning);
+        }
+        if self.strict_fidelity || warning::wanted(self.warnings.as_ref()) {
+            for loss in resolver.style_losses() {
+                let warning = loss.clone().doc(doc_id);
+                if self.strict_fidelity {
+                    losses.push(warning.clone());
+                }
+                self.warn(warning);
+            }
+        }
+        let resolver = resolver.with_known_losses(losses);
         if let Some(logger) = self.debug.as_deref() {
             let css_ms = t_css.elapsed().as_secs_f64() * 1000.0;
             let doc_id = doc_id
@@ -4439,13 +5669,138 @@ impl FullBleed {
         }
     }
 
+    // Register `@font-face` faces on the shared registry before the stylesheet is used. Each
+    // rule takes its first loadable `src`; a rule with none is skipped (logged as
+    // `css.font_face`) and text falls back through the font stack as before. A family answers
+    // to a name scoped by a hash of this stylesheet's rules for it, so two documents declaring
+    // the same family with different fonts each get their own; the returned map (normalized
+    // family -> scoped name) is what `StyleResolver` rewrites font stacks with. Rules that
+    // loaded nothing are also returned as diagnostics located in `css`.
+    fn register_css_font_faces(
+        &self,
+        css: &str,
+        merged_css: &str,
+        viewport: Size,
+        doc_id: Option<usize>,
+    ) -> (std::collections::HashMap<String, Arc<str>>, Vec<Diagnostic>) {
+        use sha2::{Digest, Sha256};
+        let faces =
+            style::extract_css_font_faces(merged_css, self.debug.as_deref(), Some(viewport));
+        let mut rules: std::collections::HashMap<String, Sha256> = std::collections::HashMap::new();
+        for face in &faces {
+            let descriptor = &face.descriptor;
+            let hasher = rules
+                .entry(descriptor.family.trim().to_ascii_lowercase())
+                .or_default();
+            hasher.update(format!("{:?}|{:?};", descriptor, face.sources).as_bytes());
+        }
+        let families: std::collections::HashMap<String, Arc<str>> = rules
+            .into_iter()
+            .map(|(family, hasher)| {
+                let digest = encrypt::hex(&hasher.finalize());
+                let scoped = Arc::<str>::from(format!("{family}#{}", &digest[..12]));
+                (family, scoped)
+            })
+            .collect();
+        let bundle_lines = self.bundle_css_lines(css);
+        let mut failures = Vec::new();
+        for face in &faces {
+            let descriptor = &face.descriptor;
+            let scope = &families[&descriptor.family.trim().to_ascii_lowercase()];
+            let mut error = None;
+            for source in &face.sources {
+                let (raw, label) = match source {
+                    style::CssFontSource::Url(url) => (url.as_str(), "url"),
+                    style::CssFontSource::Local(name) => (name.as_str(), "local"),
+                };
+                let key = format!(
+                    "{}|{}-{}|{}|{label}:{}",
+                    descriptor.family,
+                    descriptor.weight.0,
+                    descriptor.weight.1,
+                    descriptor.italic,
+                    encrypt::hex(&Sha256::digest(raw.as_bytes()))
+                );
+                if self.font_registry.alias_css_face(&key, scope, descriptor) {
+                    error = None;
+                    break;
+                }
+                let display = if raw.starts_with("data:") {
+                    "data:<font>"
+                } else {
+                    raw
+                };
+                let data = match source {
+                    style::CssFontSource::Url(url) => {
+                        assets::resolve_asset_bytes(Some(&self.asset_bundle), url, AssetKind::Font)
+                            .map_err(|err| (ErrorCode::Asset, err))
+                    }
+                    style::CssFontSource::Local(name) => self
+                        .font_registry
+                        .resolve(name)
+                        .map(|font| font.data.to_vec())
+                        .ok_or_else(|| {
+                            (
+                                ErrorCode::FontNotFound,
+                                format!("local({name:?}) is not a registered font"),
+                            )
+                        }),
+                };
+                match data.and_then(|data| {
+                    self.font_registry
+                        .register_css_face(&key, scope, descriptor, data, display)
+                        .map_err(|err| (err.code(), err.to_string()))
+                }) {
+                    Ok(_) => {
+                        error = None;
+                        break;
+                    }
+                    Err(err) => error = Some(err),
+                }
+            }
+            if let Some((code, err)) = &error {
+                let line = face.line.checked_sub(bundle_lines).filter(|line| *line > 0);
+                failures.push(Diagnostic {
+                    code: *code,
+                    message: format!("@font-face {:?}: {err}", descriptor.family),
+                    location: SourceLocation {
+                        selector: Some("@font-face".to_string()),
+                        line,
+                        column: line.map(|_| face.column),
+                        ..SourceLocation::default()
+                    },
+                });
+            }
+            let Some(logger) = self.debug.as_deref() else {
+                continue;
+            };
+            match error {
+                Some((_, err)) => {
+                    let doc_id = doc_id
+                        .map(|id| id.to_string())
+                        .unwrap_or_else(|| "null".to_string());
+                    let json = format!(
+                        "{{\"type\":\"css.font_face\",\"doc_id\":{},\"family\":\"{}\",\"error\":\"{}\"}}",
+                        doc_id,
+                        debug::json_escape(&descriptor.family),
+                        debug::json_escape(&err)
+                    );
+                    logger.log_json(&json);
+                    logger.increment("css.font_face.failed", 1);
+                }
+                None => logger.increment("css.font_face.registered", 1),
+            }
+        }
+        (families, failures)
+    }
+
     fn merge_overlay_commands(base: &mut Document, overlay: &Document) {
         if base.pages.len() != overlay.pages.len() {
             return;
         }
         for (base_page, overlay_page) in base.pages.iter_mut().zip(overlay.pages.iter()) {
             if !overlay_page.commands.is_empty() {
-                base_page.commands.extend(overlay_page.commands.clone());
+                base_page.commands.append(&overlay_page.commands);
             }
         }
     }
@@ -4463,8 +5818,10 @@ impl FullBleed {
             pages: base
                 .pages
                 .iter()
-                .map(|_| Page {
-                    commands: Vec::new(),
+                .map(|page| Page {
+                    commands: CommandBuffer::new(),
+                    size: page.size,
+                    rotate: page.rotate,
                 })
                 .collect(),
         };
@@ -4536,17 +5893,37 @@ impl FullBleed {
             has_overlay = true;
         }
 
-        let overlay = if has_overlay { Some(overlay) } else { None };
-
-        let background = self.watermark.as_ref().and_then(|spec| {
-            if spec.layer == WatermarkLayer::Background && !Self::has_full_page_background(base) {
-                Some(build_watermark_document(
-                    base,
-                    spec,
-                    resolver,
-                    page_data,
-                    report.as_deref_mut(),
-                    Some(self.font_registry.clone()),
+        if !resolver.page_margin_boxes().is_empty() {
+            apply_page_margin_boxes(
+                &mut overlay,
+                base,
+                resolver,
+                Some(self.font_registry.clone()),
+                Some(self.asset_bundle.clone()),
+                report.as_deref_mut(),
+                self.svg_form_xobjects,
+                self.svg_raster_fallback,
+                self.perf.as_deref(),
+            );
+            has_overlay = true;
+        }
+
+        if let Some(draw) = &self.page_draw {
+            apply_page_draw(&mut overlay, base, draw.as_ref());
+            has_overlay = true;
+        }
+
+        let overlay = if has_overlay { Some(overlay) } else { None };
+
+        let background = self.watermark.as_ref().and_then(|spec| {
+            if spec.layer == WatermarkLayer::Background && !Self::has_full_page_background(base) {
+                Some(build_watermark_document(
+                    base,
+                    spec,
+                    resolver,
+                    page_data,
+                    report.as_deref_mut(),
+                    Some(self.font_registry.clone()),
                     Some(self.asset_bundle.clone()),
                     self.svg_form_xobjects,
                     self.svg_raster_fallback,
@@ -4625,11 +6002,44 @@ impl FullBleed {
         report: Option<&mut GlyphCoverageReport>,
     ) -> Result<(Document, Option<PageDataContext>), FullBleedError> {
         let mut report = report;
+        let (document, page_data) = self.render_to_document_and_page_data_with_story_at(
+            doc_id,
+            html,
+            None,
+            page_templates,
+            resolver,
+            report.as_deref_mut(),
+        )?;
+        if let Some(report) = report {
+            report.resolve_locations(&document, &self.font_registry);
+        }
+        Ok((document, page_data))
+    }
+
+    fn render_to_document_and_page_data_with_story_at(
+        &self,
+        doc_id: usize,
+        html: &str,
+        story: Option<&[Box<dyn Flowable>]>,
+        page_templates: &[PageTemplate],
+        resolver: &style::StyleResolver,
+        report: Option<&mut GlyphCoverageReport>,
+    ) -> Result<(Document, Option<PageDataContext>), FullBleedError> {
+        // Font substitution warnings come from glyph coverage, so track it when someone is
+        // listening even if the caller did not ask for a report.
+        let warnings_wanted = warning::wanted(self.warnings.as_ref());
+        let mut own_report =
+            (report.is_none() && warnings_wanted).then(GlyphCoverageReport::default);
+        let mut report = report.or(own_report.as_mut());
         let perf = self.perf.as_deref();
-        self.emit_html_asset_warnings(doc_id, html);
-        let layout = self.build_document_with_layout_strategy(
+        // Prebuilt stories had their asset warnings reported by the caller.
+        if story.is_none() {
+            self.emit_html_asset_warnings(doc_id, html);
+        }
+        let layout = self.build_document_with_layout_strategy_and_story(
             doc_id,
             html,
+            story,
             page_templates,
             resolver,
             report.as_deref_mut(),
@@ -4643,7 +6053,7 @@ impl FullBleed {
             .as_ref()
             .map(|spec| page_data::compute_page_data_context(&built, spec));
 
-        let t_plan = std::time::Instant::now();
+        let t_plan = crate::platform::Instant::now();
         let planned = plan::plan_document_with_overlay(
             doc_id,
             &built,
@@ -4658,6 +6068,9 @@ impl FullBleed {
             },
         )?;
         let plan_ms = t_plan.elapsed().as_secs_f64() * 1000.0;
+        if warnings_wanted && let Some(report) = report.as_deref() {
+            self.emit_glyph_warnings(doc_id, report);
+        }
         let template_binding_count = planned
             .template_bindings
             .as_ref()
@@ -4665,7 +6078,7 @@ impl FullBleed {
             .unwrap_or(0);
 
         if let Some(logger) = self.debug.as_deref() {
-            let t_finalize = std::time::Instant::now();
+            let t_finalize = crate::platform::Instant::now();
             let built = self.finalize_with_jit(
                 doc_id,
                 built,
@@ -4688,6 +6101,7 @@ impl FullBleed {
                 planned.overlay.as_ref(),
                 planned.plan.as_ref(),
                 planned.page_data.as_ref().or(page_data_override.as_ref()),
+                self.font_registry.line_breaks().stats(),
             );
             if let Some(perf_logger) = perf {
                 perf_logger.log_span_ms("story", Some(doc_id), story_ms);
@@ -4760,7 +6174,7 @@ impl FullBleed {
             .as_ref()
             .map(|spec| page_data::compute_page_data_context(&built, spec));
 
-        let t_plan = std::time::Instant::now();
+        let t_plan = crate::platform::Instant::now();
         let planned = plan::plan_document_with_overlay(
             doc_id,
             &built,
@@ -4794,6 +6208,7 @@ impl FullBleed {
                 planned.overlay.as_ref(),
                 planned.plan.as_ref(),
                 planned.page_data.as_ref().or(page_data_override.as_ref()),
+                self.font_registry.line_breaks().stats(),
             );
         }
         if let Some(perf_logger) = perf {
@@ -4860,6 +6275,22 @@ impl FullBleed {
         self.render_to_document_with_resolver(html, &context.page_templates, &context.resolver)
     }
 
+    // Lays out `html` and returns the element box tree (paths, rects, pages, computed styles)
+    // without painting overlays or producing a PDF.
+    pub fn render_to_layout(&self, html: &str, css: &str) -> Result<LayoutTree, FullBleedError> {
+        let context = self.build_render_context(css, Some(0));
+        let resolver = context.resolver.with_layout_capture(true);
+        let layout = self.build_document_with_layout_strategy(
+            0,
+            html,
+            &context.page_templates,
+            &resolver,
+            None,
+        )?;
+        self.emit_debug_summary("render_to_layout");
+        Ok(LayoutTree::from_document(&layout.document))
+    }
+
     pub fn render_to_buffer(&self, html: &str, css: &str) -> Result<Vec<u8>, FullBleedError> {
         let document = self.render_to_document(html, css)?;
         let bytes = pdf::document_to_pdf_with_metrics_and_registry_with_logs(
@@ -4874,6 +6305,277 @@ impl FullBleed {
         Ok(bytes)
     }
 
+    // Renders like `render_to_buffer`, diffing each page's content stream against
+    // `previous` (the cache of an earlier render by this engine). Unchanged pages reuse the
+    // earlier encoded streams, so only changed pages are compressed again; layout still
+    // runs for the whole document. Output is identical to a fresh render.
+    pub fn render_incremental(
+        &self,
+        html: &str,
+        css: &str,
+        previous: Option<&PageCache>,
+    ) -> Result<IncrementalRender, FullBleedError> {
+        let document = self.render_to_document(html, css)?;
+        let previous = previous.cloned().unwrap_or_default();
+        let mut pdf = Vec::new();
+        let mut pdf_stream = pdf::PdfStreamWriter::new(
+            &mut pdf,
+            document.page_size,
+            Some(self.font_registry.as_ref()),
+            self.pdf_options.clone(),
+            self.debug.clone(),
+            self.perf.clone(),
+        )?
+        .with_page_cache(previous.clone());
+        pdf_stream.add_document(0, &document)?;
+        pdf_stream.finish()?;
+        let (cache, reused_pages) = pdf_stream.take_page_cache().unwrap_or_default();
+        if let Some(logger) = self.debug.as_deref() {
+            logger.increment("jit.incremental.pages_reused", reused_pages as u64);
+        }
+        self.emit_debug_summary("render_incremental");
+        Ok(IncrementalRender {
+            changed_pages: cache.changed_pages(&previous),
+            reused_pages,
+            cache,
+            pdf,
+        })
+    }
+
+    // Binds `data` into the template (see `bind_template`) and renders the result.
+    pub fn render_with_data(
+        &self,
+        html_template: &str,
+        css: &str,
+        data: &serde_json::Value,
+    ) -> Result<Vec<u8>, FullBleedError> {
+        let html = data_binding::bind_template(html_template, data);
+        self.render_to_buffer(&html, css)
+    }
+
+    // Lays out `html` and returns its JIT plan: every page's painted commands, overlays
+    // included, plus the page data. Needs a JIT mode other than `Off`.
+    pub fn plan_document(&self, html: &str, css: &str) -> Result<DocPlan, FullBleedError> {
+        if self.jit_mode == JitMode::Off {
+            return Err(FullBleedError::InvalidConfiguration(
+                "plan_document requires jit_mode PlanOnly or PlanAndReplay".to_string(),
+            ));
+        }
+        let context = self.build_render_context(css, Some(0));
+        let planned = self.render_to_planned_doc_with_resolver_and_report_at(
+            0,
+            html,
+            &context.page_templates,
+            &context.resolver,
+            None,
+        )?;
+        planned
+            .plan
+            .ok_or_else(|| FullBleedError::InvalidConfiguration("jit plan missing".to_string()))
+    }
+
+    // Writes the PDF for a plan produced by `DocPlan::to_bytes`, possibly in another
+    // process. Only painting and PDF writing run here, so this engine needs the fonts and
+    // assets the plan's commands name, but not the HTML or CSS. `page_data` replaces the
+    // page data recorded in the plan.
+    pub fn render_from_plan(
+        &self,
+        plan_bytes: &[u8],
+        page_data: Option<PageDataContext>,
+    ) -> Result<Vec<u8>, FullBleedError> {
+        let mut plan = DocPlan::from_bytes(plan_bytes)?;
+        if let Some(page_data) = page_data {
+            for (index, page) in plan.pages.iter_mut().enumerate() {
+                page.page_data = page_data.pages.get(index).cloned();
+            }
+            plan.page_data = Some(page_data);
+        }
+        let ops = jit::paint_plan(&plan, self.debug.clone());
+        let document = jit::ops_to_document(plan.page_size, ops);
+        let bytes = pdf::document_to_pdf_with_metrics_and_registry_with_logs(
+            &document,
+            None,
+            Some(self.font_registry.as_ref()),
+            &self.pdf_options,
+            self.debug.clone(),
+            self.perf.clone(),
+        )?;
+        self.emit_debug_summary("render_from_plan");
+        Ok(bytes)
+    }
+
+    // Parses and styles `html` + `css` once for repeated renders. The last 64 templates are
+    // cached per engine by a hash of both, so compiling the same template again is a lookup;
+    // a hit is only used when its source matches, so hash collisions compile afresh.
+    pub fn compile(&self, html: &str, css: &str) -> CompiledTemplate {
+        let key = compiled_template_key(html, css);
+        if let Ok(mut cache) = self.compiled_templates.lock()
+            && let Some(compiled) = cache.get(&key)
+            && compiled.inner.html == html
+            && compiled.inner.css == css
+        {
+            return compiled.clone();
+        }
+        let context = self.build_render_context(css, None);
+        // Strict renders build the story per render so its losses are checked.
+        let story = if html.contains(PAGE_REF_PREFIX) || self.strict_fidelity {
+            None
+        } else {
+            self.emit_html_asset_warnings(0, html);
+            Some(html::html_to_story_with_resolver_and_fonts_and_report(
+                html,
+                &context.resolver,
+                Some(self.font_registry.clone()),
+                Some(self.asset_bundle.clone()),
+                None,
+                self.svg_form_xobjects,
+                self.svg_raster_fallback,
+                self.perf.as_deref(),
+                None,
+            ))
+        };
+        let compiled = CompiledTemplate {
+            inner: Arc::new(CompiledTemplateInner {
+                key,
+                engine_id: self.engine_id,
+                html: html.to_string(),
+                css: css.to_string(),
+                context,
+                story,
+            }),
+        };
+        if let Ok(mut cache) = self.compiled_templates.lock() {
+            cache.insert(key, compiled.clone());
+        }
+        compiled
+    }
+
+    pub fn render_compiled_to_document(
+        &self,
+        compiled: &CompiledTemplate,
+    ) -> Result<Document, FullBleedError> {
+        let inner = compiled.inner.as_ref();
+        if inner.engine_id != self.engine_id {
+            return Err(FullBleedError::InvalidConfiguration(
+                "compiled template belongs to another engine; compile it with this one".to_string(),
+            ));
+        }
+        self.render_to_document_and_page_data_with_story_at(
+            0,
+            &inner.html,
+            inner.story.as_deref(),
+            &inner.context.page_templates,
+            &inner.context.resolver,
+            None,
+        )
+        .map(|(doc, _page_data)| doc)
+    }
+
+    pub fn render_compiled_to_buffer(
+        &self,
+        compiled: &CompiledTemplate,
+    ) -> Result<Vec<u8>, FullBleedError> {
+        let document = self.render_compiled_to_document(compiled)?;
+        let bytes = pdf::document_to_pdf_with_metrics_and_registry_with_logs(
+            &document,
+            None,
+            Some(self.font_registry.as_ref()),
+            &self.pdf_options,
+            self.debug.clone(),
+            self.perf.clone(),
+        )?;
+        self.emit_debug_summary("render_compiled_to_buffer");
+        Ok(bytes)
+    }
+
+    pub fn render_compiled_to_writer<W: std::io::Write>(
+        &self,
+        compiled: &CompiledTemplate,
+        writer: &mut W,
+    ) -> Result<usize, FullBleedError> {
+        let document = self.render_compiled_to_document(compiled)?;
+        let bytes_written = pdf::document_to_pdf_with_metrics_and_registry_to_writer_with_logs(
+            &document,
+            None,
+            Some(self.font_registry.as_ref()),
+            &self.pdf_options,
+            writer,
+            self.debug.clone(),
+            self.perf.clone(),
+        )?;
+        self.emit_debug_summary("render_compiled_to_writer");
+        Ok(bytes_written)
+    }
+
+    // Renders like `render_to_buffer` and runs the accessibility audit over the same HTML.
+    // `profile` selects the gate levels from the audit contract ("strict", "cav", ...).
+    pub fn render_with_audit(
+        &self,
+        html: &str,
+        css: &str,
+        profile: &str,
+    ) -> Result<(Vec<u8>, A11yAuditReport), FullBleedError> {
+        let bytes = self.render_to_buffer(html, css)?;
+        let core = self.verify_accessibility_html_core(html, profile);
+        let report = A11yAuditReport::from_findings(core.profile, core.findings);
+        if let Some(logger) = self.debug.as_deref() {
+            logger.increment("a11y.audit.findings", report.findings.len() as u64);
+            logger.increment("a11y.audit.gate_errors", report.gate.error_count as u64);
+        }
+        Ok((bytes, report))
+    }
+
+    // Renders like `render_to_buffer` and lists every box laid out larger than its frame space,
+    // with the page and, when known, the element that produced it.
+    pub fn render_with_layout_report(
+        &self,
+        html: &str,
+        css: &str,
+    ) -> Result<(Vec<u8>, LayoutReport), FullBleedError> {
+        let document = self.render_to_document(html, css)?;
+        let report = LayoutReport::from_document(&document);
+        let bytes = pdf::document_to_pdf_with_metrics_and_registry_with_logs(
+            &document,
+            None,
+            Some(self.font_registry.as_ref()),
+            &self.pdf_options,
+            self.debug.clone(),
+            self.perf.clone(),
+        )?;
+        if let Some(logger) = self.debug.as_deref() {
+            logger.increment("layout.overflows", report.overflows().len() as u64);
+        }
+        self.emit_debug_summary("render_with_layout_report");
+        Ok((bytes, report))
+    }
+
+    // Renders like `render_to_buffer` and writes an artifact bundle to `target`: the PDF, the
+    // exact input HTML, the merged CSS, the PMR audit report for `profile` and a manifest
+    // with the audit contract fingerprint and each file's SHA-256.
+    pub fn render_with_artifact_bundle(
+        &self,
+        html: &str,
+        css: &str,
+        profile: &str,
+        target: &ArtifactBundleTarget,
+    ) -> Result<(Vec<u8>, ArtifactBundleManifest), FullBleedError> {
+        let (bytes, layout_report) = self.render_with_layout_report(html, css)?;
+        let merged_css = self.merge_css(css);
+        let page_count = lopdf::Document::load_mem(&bytes)
+            .map(|doc| doc.get_pages().len() as i64)
+            .ok();
+        let ctx = PmrCoreContext {
+            layout_report: Some(layout_report),
+            render_page_count: page_count,
+            html_artifact_bytes: Some(html.len() as u64),
+            css_artifact_bytes: Some(merged_css.len() as u64),
+            ..PmrCoreContext::default()
+        };
+        let report = self.verify_paged_media_rank_html_core(html, profile, "error", &ctx);
+        let manifest = artifact_bundle::write_bundle(target, &bytes, html, &merged_css, &report)?;
+        Ok((bytes, manifest))
+    }
+
     pub fn render_with_metrics(
         &self,
         html: &str,
@@ -5074,6 +6776,40 @@ impl FullBleed {
         Ok((bytes, report))
     }
 
+    // Renders and returns the warnings this render emitted (also sent to the `on_warning`
+    // sink, if any), in the order they happened.
+    pub fn render_with_warnings(
+        &self,
+        html: &str,
+        css: &str,
+    ) -> Result<(Vec<u8>, Vec<Warning>), FullBleedError> {
+        let (bytes, warnings) = warning::collect(|| self.render_to_buffer(html, css));
+        Ok((bytes?, warnings))
+    }
+
+    // Renders with the layout profiler on and returns where layout time went, per element
+    // path and operation. Layout that repeats (lazy passes, page references) is counted
+    // each time it runs.
+    pub fn render_with_layout_profile(
+        &self,
+        html: &str,
+        css: &str,
+    ) -> Result<(Vec<u8>, LayoutProfile), FullBleedError> {
+        let (bytes, profile) = layout_profile::collect(|| self.render_to_buffer(html, css));
+        Ok((bytes?, profile))
+    }
+
+    // Renders and reports what was lost: the warnings of `render_with_warnings`, with the
+    // known losses among them typed and graded by severity.
+    pub fn render_with_report(
+        &self,
+        html: &str,
+        css: &str,
+    ) -> Result<(Vec<u8>, RenderReport), FullBleedError> {
+        let (bytes, warnings) = self.render_with_warnings(html, css)?;
+        Ok((bytes, RenderReport::from_warnings(warnings)))
+    }
+
     pub fn render_to_document_with_glyph_report(
         &self,
         html: &str,
@@ -5145,39 +6881,251 @@ impl FullBleed {
         self.render_to_writer(html, css, &mut file)
     }
 
-    pub fn render_image_pages(
+    // Render a single (potentially huge) HTML document read incrementally from `reader`.
+    //
+    // The body is parsed in slices of roughly `stream_chunk_bytes`, cut at top-level body
+    // children. Each slice is laid out continuing the previous one; completed pages are
+    // written to the PDF stream as soon as no later content can flow onto them, so neither
+    // the HTML source nor the laid out pages are held in memory as a whole.
+    //
+    // Features that need the whole document before painting any page (page headers/footers,
+    // watermarks, paginated context, template bindings, `@page` margin boxes) are rejected.
+    // Slices are parsed as standalone documents, so sibling selectors do not match across
+    // slice boundaries, and position:fixed elements repeat from the slice in which they appear
+    // onwards.
+    pub fn render_stream_to_writer<R: std::io::Read, W: std::io::Write>(
         &self,
-        html: &str,
+        reader: R,
         css: &str,
-        dpi: u32,
-    ) -> Result<Vec<Vec<u8>>, FullBleedError> {
+        writer: &mut W,
+    ) -> Result<usize, FullBleedError> {
+        if self.page_header.is_some()
+            || self.page_header_html.is_some()
+            || self.page_footer.is_some()
+            || self.watermark.is_some()
+            || self.page_draw.is_some()
+            || self.paginated_context.is_some()
+            || self.template_binding_spec.is_some()
+            || self.strict_fidelity
+        {
+            return Err(FullBleedError::InvalidConfiguration(
+                "render_stream_to_writer does not support page headers/footers, watermarks, on_page_draw, paginated_context, template bindings, or strict_fidelity".to_string(),
+            ));
+        }
+        let _warning_scope = warning::scope(self.warnings.as_ref());
         let context = self.build_render_context(css, Some(0));
-        let document = self.render_to_document_with_resolver(
-            html,
-            &context.page_templates,
-            &context.resolver,
-        )?;
-        let start = std::time::Instant::now();
-        let pages = raster::document_to_png_pages(
-            &document,
-            dpi,
+        let page_size = context
+            .page_templates
+            .first()
+            .ok_or(FullBleedError::MissingPageTemplate)?
+            .page_size;
+        // Margin boxes need the finished page count and per-page values.
+        if !context.resolver.page_margin_boxes().is_empty() {
+            return Err(FullBleedError::InvalidConfiguration(
+                "render_stream_to_writer does not support @page margin boxes".to_string(),
+            ));
+        }
+        let mut pdf_stream = pdf::PdfStreamWriter::new(
+            writer,
+            page_size,
             Some(self.font_registry.as_ref()),
-            self.pdf_options.shape_text,
+            self.pdf_options.clone(),
+            self.debug.clone(),
+            self.perf.clone(),
         )?;
-        if let Some(perf_logger) = self.perf.as_deref() {
-            let elapsed_ms = start.elapsed().as_secs_f64() * 1000.0;
-            perf_logger.log_span_ms("raster", Some(0), elapsed_ms);
-            perf_logger.log_counts("raster", Some(0), &[("pages", pages.len() as u64)]);
-        }
-        self.emit_debug_summary("render_image_pages");
-        Ok(pages)
-    }
 
-    pub fn render_image_pages_to_dir(
-        &self,
-        html: &str,
-        css: &str,
-        out_dir: impl AsRef<std::path::Path>,
+        let mut chunks = html_stream::HtmlChunkReader::new(reader, self.stream_chunk_bytes);
+        let mut fixed_overlays: Vec<Box<dyn Flowable>> = Vec::new();
+        let mut page_one_overlays: Vec<Box<dyn Flowable>> = Vec::new();
+        let mut pending_flow: Vec<Box<dyn Flowable>> = Vec::new();
+        let mut pages_written = 0usize;
+        let mut slices = 0usize;
+        let mut next = chunks.next_chunk()?;
+        while let Some(chunk_html) = next {
+            next = chunks.next_chunk()?;
+            let is_last = next.is_none();
+            slices += 1;
+
+            // Streamed chunks are laid out once, so page references cannot be resolved.
+            let mut unresolved_page_refs = 0usize;
+            let chunk_html = if chunk_html.contains(PAGE_REF_PREFIX) {
+                resolve_page_refs(
+                    &chunk_html,
+                    &std::collections::HashMap::new(),
+                    &mut unresolved_page_refs,
+                )
+                .into_owned()
+            } else {
+                chunk_html
+            };
+            if unresolved_page_refs > 0
+                && let Some(logger) = self.debug.as_deref()
+            {
+                logger.increment("jit.page_ref.unresolved", unresolved_page_refs as u64);
+            }
+            let t_story = crate::platform::Instant::now();
+            let story = html::html_to_story_with_resolver_and_fonts_and_report(
+                &chunk_html,
+                &context.resolver,
+                Some(self.font_registry.clone()),
+                Some(self.asset_bundle.clone()),
+                None,
+                self.svg_form_xobjects,
+                self.svg_raster_fallback,
+                self.perf.as_deref(),
+                Some(0),
+            );
+            drop(chunk_html);
+            for flowable in story {
+                if flowable.is_fixed_positioned() {
+                    fixed_overlays.push(flowable);
+                } else if flowable.out_of_flow() {
+                    if pages_written == 0 {
+                        page_one_overlays.push(flowable);
+                    } else {
+                        if let Some(logger) = self.debug.as_deref() {
+                            logger.increment("jit.known_loss.stream_page_one_overlay", 1);
+                        }
+                        self.warn(
+                            Warning::for_loss(
+                                WarningKind::KnownLoss,
+                                KnownLoss::StreamPageOneOverlay,
+                                "positioned element after the first slice dropped; streamed renders place out-of-flow content on page one only",
+                            )
+                            .doc(Some(0)),
+                        );
+                    }
+                } else {
+                    // Slices cannot hold content back for later pages' frames.
+                    if flowable.target_frame().is_some() {
+                        if let Some(logger) = self.debug.as_deref() {
+                            logger.increment("jit.known_loss.stream_frame_thread", 1);
+                        }
+                        self.warn(
+                            Warning::for_loss(
+                                WarningKind::KnownLoss,
+                                KnownLoss::StreamFrameThread,
+                                "data-fb-frame content laid out in the main flow; streamed renders do not thread frames",
+                            )
+                            .doc(Some(0)),
+                        );
+                    }
+                    pending_flow.push(flowable);
+                }
+            }
+            if let Some(perf) = self.perf.as_deref() {
+                perf.log_span_ms(
+                    "stream.story",
+                    Some(0),
+                    t_story.elapsed().as_secs_f64() * 1000.0,
+                );
+            }
+            if pending_flow.is_empty() && !(is_last && pages_written == 0) {
+                continue;
+            }
+
+            let t_layout = crate::platform::Instant::now();
+            let mut doc = DocTemplate::new(context.page_templates.clone())
+                .with_first_page_number(pages_written + 1)
+                .with_on_page_start(self.on_page_start.clone())
+                .with_on_page_end(self.on_page_end.clone())
+                .with_progress(self.pdf_options.progress.clone(), 0)
+                .with_warnings(self.warnings.clone(), 0)
+                .with_frame_threads(false);
+            if let Some(logger) = self.debug.clone() {
+                doc = doc.with_debug(logger, Some(0));
+            }
+            for flowable in fixed_overlays.iter() {
+                doc.add_flowable(flowable.clone());
+            }
+            if pages_written == 0 {
+                for flowable in page_one_overlays.iter() {
+                    doc.add_flowable(flowable.clone());
+                }
+            }
+            for flowable in pending_flow.iter() {
+                doc.add_flowable(flowable.clone());
+            }
+            let _perf_guard = flowable::set_perf_context(self.perf.clone(), Some(0));
+            let (mut built, _metrics, spans) = doc.build_with_source_spans()?;
+            if let Some(perf) = self.perf.as_deref() {
+                perf.log_span_ms(
+                    "stream.layout",
+                    Some(0),
+                    t_layout.elapsed().as_secs_f64() * 1000.0,
+                );
+            }
+
+            let (resume_source, resume_page) = if is_last {
+                (pending_flow.len(), built.pages.len())
+            } else {
+                doc_template::resume_point(&spans, built.pages.len())
+            };
+            pending_flow.drain(..resume_source);
+            built.pages.truncate(resume_page);
+            if built.pages.is_empty() {
+                continue;
+            }
+            pages_written += built.pages.len();
+            let built = self.finalize_with_jit(0, built, None, None, None, None);
+            pdf_stream.add_document(0, &built)?;
+        }
+
+        if let Some(logger) = self.debug.as_deref() {
+            let json = format!(
+                "{{\"type\":\"jit.stream\",\"doc_id\":0,\"slices\":{},\"pages\":{},\"chunk_bytes\":{}}}",
+                slices, pages_written, self.stream_chunk_bytes
+            );
+            logger.log_json(&json);
+        }
+        let bytes_written = pdf_stream.finish()?.bytes_written;
+        self.emit_debug_summary("render_stream_to_writer");
+        Ok(bytes_written)
+    }
+
+    pub fn render_stream_to_file<R: std::io::Read>(
+        &self,
+        reader: R,
+        css: &str,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<usize, FullBleedError> {
+        let mut file = std::fs::File::create(path)?;
+        self.render_stream_to_writer(reader, css, &mut file)
+    }
+
+    pub fn render_image_pages(
+        &self,
+        html: &str,
+        css: &str,
+        dpi: u32,
+    ) -> Result<Vec<Vec<u8>>, FullBleedError> {
+        let context = self.build_render_context(css, Some(0));
+        let document = self.render_to_document_with_resolver(
+            html,
+            &context.page_templates,
+            &context.resolver,
+        )?;
+        let start = crate::platform::Instant::now();
+        let pages = raster::document_to_png_pages(
+            &document,
+            dpi,
+            Some(self.font_registry.as_ref()),
+            self.pdf_options.shape_text,
+        )?;
+        if let Some(perf_logger) = self.perf.as_deref() {
+            let elapsed_ms = start.elapsed().as_secs_f64() * 1000.0;
+            perf_logger.log_span_ms("raster", Some(0), elapsed_ms);
+            perf_logger.log_counts("raster", Some(0), &[("pages", pages.len() as u64)]);
+        }
+        self.emit_debug_summary("render_image_pages");
+        Ok(pages)
+    }
+
+    pub fn render_image_pages_to_dir(
+        &self,
+        html: &str,
+        css: &str,
+        out_dir: impl AsRef<std::path::Path>,
         stem: &str,
         dpi: u32,
     ) -> Result<Vec<std::path::PathBuf>, FullBleedError> {
@@ -5204,7 +7152,7 @@ impl FullBleed {
         pdf_path: impl AsRef<std::path::Path>,
         dpi: u32,
     ) -> Result<Vec<Vec<u8>>, FullBleedError> {
-        let start = std::time::Instant::now();
+        let start = crate::platform::Instant::now();
         let pages = pdf_raster::pdf_path_to_png_pages(
             pdf_path.as_ref(),
             dpi,
@@ -5249,15 +7197,80 @@ impl FullBleed {
         Ok(paths)
     }
 
+    // Writes each document as soon as it is laid out, like `render_many_to_writer`, so only
+    // the PDF bytes accumulate.
     pub fn render_many_to_buffer(
         &self,
         html_list: &[String],
         css: &str,
     ) -> Result<Vec<u8>, FullBleedError> {
+        if html_list.is_empty() {
+            return Err(FullBleedError::EmptyDocumentSet);
+        }
+        let mut out = Vec::new();
+        self.render_many_to_stream(html_list, css, &[], &mut out, "render_many_to_buffer")?;
+        Ok(out)
+    }
+
+    pub fn render_many_to_writer<W: std::io::Write>(
+        &self,
+        html_list: &[String],
+        css: &str,
+        writer: &mut W,
+    ) -> Result<usize, FullBleedError> {
+        self.render_many_to_stream(html_list, css, &[], writer, "render_many_to_writer")
+            .map(|(manifest, _)| manifest.bytes_written)
+    }
+
+    // Like `render_many_to_writer`, with `metadata[i]` attached to record `i` (records past the
+    // end of `metadata` get none). The manifest gives each record's page range.
+    pub fn render_many_to_writer_with_metadata<W: std::io::Write>(
+        &self,
+        html_list: &[String],
+        css: &str,
+        metadata: &[RecordMetadata],
+        writer: &mut W,
+    ) -> Result<BatchManifest, FullBleedError> {
+        self.render_many_to_stream(
+            html_list,
+            css,
+            metadata,
+            writer,
+            "render_many_to_writer_with_metadata",
+        )
+        .map(|(manifest, _)| manifest)
+    }
+
+    // Sequential batch: each document is written as soon as it is laid out. Returns the
+    // manifest and each record's page data.
+    fn render_many_to_stream<W: std::io::Write>(
+        &self,
+        html_list: &[String],
+        css: &str,
+        metadata: &[RecordMetadata],
+        writer: &mut W,
+        label: &str,
+    ) -> Result<(BatchManifest, Vec<Option<PageDataContext>>), FullBleedError> {
         let context = self.build_render_context(css, None);
-        let mut documents = Vec::with_capacity(html_list.len());
+        let progress = BatchProgress::new(self.pdf_options.progress.clone(), html_list.len());
+        let page_size = context
+            .page_templates
+            .get(0)
+            .ok_or(FullBleedError::MissingPageTemplate)?
+            .page_size;
+
+        let mut pdf_stream = pdf::PdfStreamWriter::new(
+            writer,
+            page_size,
+            Some(self.font_registry.as_ref()),
+            self.pdf_options.clone(),
+            self.debug.clone(),
+            self.perf.clone(),
+        )?;
+
+        let mut page_data_list = Vec::with_capacity(html_list.len());
         for (idx, html) in html_list.iter().enumerate() {
-            let (doc, _page_data) = self
+            let (doc, page_data) = self
                 .render_to_document_and_page_data_with_resolver_and_report_at(
                     idx,
                     html,
@@ -5265,25 +7278,25 @@ impl FullBleed {
                     &context.resolver,
                     None,
                 )?;
-            documents.push(doc);
+            pdf_stream.add_document_with_metadata(idx, &doc, metadata.get(idx))?;
+            page_data_list.push(page_data);
+            progress.record_done(idx);
         }
-        let merged = merge_documents(documents)?;
-        let bytes = pdf::document_to_pdf_with_metrics_and_registry_with_logs(
-            &merged,
-            None,
-            Some(self.font_registry.as_ref()),
-            &self.pdf_options,
-            self.debug.clone(),
-            self.perf.clone(),
-        )?;
-        self.emit_debug_summary("render_many_to_buffer");
-        Ok(bytes)
+        let manifest = pdf_stream.finish()?;
+        self.emit_debug_summary(label);
+        Ok((manifest, page_data_list))
     }
 
-    pub fn render_many_to_writer<W: std::io::Write>(
+    // Renders one document per record from a single data-bound template. The template is
+    // parsed and the CSS resolved once; each record binds a copy of the parsed DOM and builds
+    // its story from it. Templates with page references, strict renders and engines with a
+    // warning listener serialize the bound DOM and render it as HTML instead, since those need
+    // the HTML re-parsed per layout pass or the story built under the layout's checks.
+    pub fn render_many_with_data_to_writer<W: std::io::Write>(
         &self,
-        html_list: &[String],
+        template_html: &str,
         css: &str,
+        records: &[serde_json::Value],
         writer: &mut W,
     ) -> Result<usize, FullBleedError> {
         let context = self.build_render_context(css, None);
@@ -5292,6 +7305,8 @@ impl FullBleed {
             .get(0)
             .ok_or(FullBleedError::MissingPageTemplate)?
             .page_size;
+        let template = data_binding::ParsedTemplate::parse(template_html);
+        let progress = BatchProgress::new(self.pdf_options.progress.clone(), records.len());
 
         let mut pdf_stream = pdf::PdfStreamWriter::new(
             writer,
@@ -5302,19 +7317,49 @@ impl FullBleed {
             self.perf.clone(),
         )?;
 
-        for (idx, html) in html_list.iter().enumerate() {
-            let (doc, _page_data) = self
-                .render_to_document_and_page_data_with_resolver_and_report_at(
+        let bind_to_story = !template_html.contains(PAGE_REF_PREFIX)
+            && !self.strict_fidelity
+            && !warning::wanted(self.warnings.as_ref());
+        if bind_to_story {
+            // Records bind text and attribute values only, so the template's assets are the
+            // same for every record.
+            self.emit_html_asset_warnings(0, template_html);
+        }
+
+        for (idx, record) in records.iter().enumerate() {
+            let (doc, _page_data) = if bind_to_story {
+                let story = html::document_to_story_with_resolver_and_fonts(
+                    &template.bind_document(record),
+                    &context.resolver,
+                    Some(self.font_registry.clone()),
+                    Some(self.asset_bundle.clone()),
+                    self.svg_form_xobjects,
+                    self.svg_raster_fallback,
+                    self.perf.as_deref(),
+                    Some(idx),
+                );
+                self.render_to_document_and_page_data_with_story_at(
                     idx,
-                    html,
+                    template_html,
+                    Some(&story),
                     &context.page_templates,
                     &context.resolver,
                     None,
-                )?;
+                )?
+            } else {
+                self.render_to_document_and_page_data_with_resolver_and_report_at(
+                    idx,
+                    &template.bind(record),
+                    &context.page_templates,
+                    &context.resolver,
+                    None,
+                )?
+            };
             pdf_stream.add_document(idx, &doc)?;
+            progress.record_done(idx);
         }
-        let bytes_written = pdf_stream.finish()?;
-        self.emit_debug_summary("render_many_to_writer");
+        let bytes_written = pdf_stream.finish()?.bytes_written;
+        self.emit_debug_summary("render_many_with_data_to_writer");
         Ok(bytes_written)
     }
 
@@ -5332,28 +7377,12 @@ impl FullBleed {
         &self,
         jobs: &[(String, String)],
     ) -> Result<Vec<u8>, FullBleedError> {
-        let mut documents = Vec::with_capacity(jobs.len());
-        for (idx, (html, css)) in jobs.iter().enumerate() {
-            let context = self.build_render_context(css, Some(idx));
-            let (doc, _page_data) = self
-                .render_to_document_and_page_data_with_resolver_and_report_at(
-                    idx,
-                    html,
-                    &context.page_templates,
-                    &context.resolver,
-                    None,
-                )?;
-            documents.push(doc);
+        if jobs.is_empty() {
+            return Err(FullBleedError::EmptyDocumentSet);
         }
-        let merged = merge_documents(documents)?;
-        Ok(pdf::document_to_pdf_with_metrics_and_registry_with_logs(
-            &merged,
-            None,
-            Some(self.font_registry.as_ref()),
-            &self.pdf_options,
-            self.debug.clone(),
-            self.perf.clone(),
-        )?)
+        let mut out = Vec::new();
+        self.render_many_to_writer_with_css(jobs, &mut out)?;
+        Ok(out)
     }
 
     pub fn render_many_to_writer_with_css<W: std::io::Write>(
@@ -5368,6 +7397,7 @@ impl FullBleed {
                 context.page_templates.get(0).map(|t| t.page_size)
             })
             .unwrap_or(self.default_page_size);
+        let progress = BatchProgress::new(self.pdf_options.progress.clone(), jobs.len());
 
         let mut pdf_stream = pdf::PdfStreamWriter::new(
             writer,
@@ -5389,9 +7419,10 @@ impl FullBleed {
                     None,
                 )?;
             pdf_stream.add_document(idx, &doc)?;
+            progress.record_done(idx);
         }
 
-        Ok(pdf_stream.finish()?)
+        Ok(pdf_stream.finish()?.bytes_written)
     }
 
     pub fn render_many_to_file_with_css(
@@ -5403,47 +7434,108 @@ impl FullBleed {
         self.render_many_to_writer_with_css(jobs, &mut file)
     }
 
-    // Parallel batch rendering: build documents in parallel, then merge in input order.
+    // Parallel batch rendering into one buffer; the same PDF `render_many_to_writer_parallel`
+    // writes.
     pub fn render_many_to_buffer_parallel(
         &self,
         html_list: &[String],
         css: &str,
     ) -> Result<Vec<u8>, FullBleedError> {
+        let mut out = Vec::new();
+        self.render_many_parallel_to_stream(html_list, css, &mut out, false)?;
+        Ok(out)
+    }
+
+    // Renders each record into its own PDF, lazily and in input order: each `next()` on the
+    // returned iterator lays out and writes one record, so callers can ship records as they
+    // finish. Records share one render context. A failed record yields its error and
+    // iteration goes on with the next one.
+    pub fn render_many_records<'a>(
+        &'a self,
+        html_list: &'a [String],
+        css: &str,
+    ) -> RecordRenders<'a> {
+        RecordRenders {
+            engine: self,
+            html_list,
+            cursor: RecordCursor::new(self, css, html_list.len()),
+        }
+    }
+
+    // Renders each record in parallel into its own PDF in `dir`, sharing one render context
+    // and font registry. `file_name(index, page_data)` names each file; without a paginated
+    // context the page data only carries the page count. Names must be plain file names and
+    // unique within the batch. Returns the paths in input order.
+    pub fn render_many_to_files<F>(
+        &self,
+        html_list: &[String],
+        css: &str,
+        dir: impl AsRef<std::path::Path>,
+        file_name: F,
+    ) -> Result<Vec<std::path::PathBuf>, FullBleedError>
+    where
+        F: Fn(usize, &PageDataContext) -> String + Sync,
+    {
         use rayon::prelude::*;
 
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
         let context = self.build_render_context(css, None);
-        let mut results: Vec<(usize, Result<Document, FullBleedError>)> = html_list
+        let progress = BatchProgress::new(self.pdf_options.progress.clone(), html_list.len());
+        let claimed = std::sync::Mutex::new(std::collections::HashSet::new());
+        let results: Vec<Result<std::path::PathBuf, FullBleedError>> = html_list
             .par_iter()
             .enumerate()
             .map(|(idx, html)| {
-                let res = self
+                let (doc, page_data) = self
                     .render_to_document_and_page_data_with_resolver_and_report_at(
                         idx,
                         html,
                         &context.page_templates,
                         &context.resolver,
                         None,
-                    )
-                    .map(|(doc, _page_data)| doc);
-                (idx, res)
+                    )?;
+                let page_data = page_data.unwrap_or_else(|| PageDataContext {
+                    page_count: doc.pages.len(),
+                    ..PageDataContext::default()
+                });
+                let name = file_name(idx, &page_data);
+                let plain = std::path::Path::new(&name)
+                    .file_name()
+                    .is_some_and(|file| file == name.as_str());
+                if !plain {
+                    return Err(FullBleedError::InvalidConfiguration(format!(
+                        "record {idx}: output name {name:?} is not a plain file name"
+                    )));
+                }
+                let fresh = claimed
+                    .lock()
+                    .map(|mut names| names.insert(name.clone()))
+                    .unwrap_or(false);
+                if !fresh {
+                    return Err(FullBleedError::InvalidConfiguration(format!(
+                        "record {idx}: output name {name:?} is used by another record"
+                    )));
+                }
+                let path = dir.join(&name);
+                let mut file = std::io::BufWriter::new(std::fs::File::create(&path)?);
+                pdf::document_to_pdf_with_metrics_and_registry_to_writer_with_logs(
+                    &doc,
+                    None,
+                    Some(self.font_registry.as_ref()),
+                    &self.pdf_options,
+                    &mut file,
+                    self.debug.clone(),
+                    self.perf.clone(),
+                )?;
+                std::io::Write::flush(&mut file)?;
+                progress.record_done(idx);
+                Ok(path)
             })
             .collect();
-        results.sort_by_key(|(idx, _)| *idx);
-
-        let mut documents = Vec::with_capacity(results.len());
-        for (_, res) in results {
-            documents.push(res?);
-        }
-
-        let merged = merge_documents(documents)?;
-        Ok(pdf::document_to_pdf_with_metrics_and_registry_with_logs(
-            &merged,
-            None,
-            Some(self.font_registry.as_ref()),
-            &self.pdf_options,
-            self.debug.clone(),
-            self.perf.clone(),
-        )?)
+        let paths = results.into_iter().collect::<Result<Vec<_>, _>>()?;
+        self.emit_debug_summary("render_many_to_files");
+        Ok(paths)
     }
 
     pub fn render_many_to_buffer_parallel_with_page_data(
@@ -5451,46 +7543,10 @@ impl FullBleed {
         html_list: &[String],
         css: &str,
     ) -> Result<(Vec<u8>, Vec<Option<PageDataContext>>), FullBleedError> {
-        use rayon::prelude::*;
-
-        let context = self.build_render_context(css, None);
-        let mut results: Vec<(
-            usize,
-            Result<(Document, Option<PageDataContext>), FullBleedError>,
-        )> = html_list
-            .par_iter()
-            .enumerate()
-            .map(|(idx, html)| {
-                let res = self.render_to_document_and_page_data_with_resolver_and_report_at(
-                    idx,
-                    html,
-                    &context.page_templates,
-                    &context.resolver,
-                    None,
-                );
-                (idx, res)
-            })
-            .collect();
-        results.sort_by_key(|(idx, _)| *idx);
-
-        let mut documents = Vec::with_capacity(results.len());
-        let mut page_data_list = Vec::with_capacity(results.len());
-        for (_, res) in results {
-            let (doc, page_data) = res?;
-            documents.push(doc);
-            page_data_list.push(page_data);
-        }
-
-        let merged = merge_documents(documents)?;
-        let bytes = pdf::document_to_pdf_with_metrics_and_registry_with_logs(
-            &merged,
-            None,
-            Some(self.font_registry.as_ref()),
-            &self.pdf_options,
-            self.debug.clone(),
-            self.perf.clone(),
-        )?;
-        Ok((bytes, page_data_list))
+        let mut out = Vec::new();
+        let (_, page_data_list) =
+            self.render_many_parallel_to_stream(html_list, css, &mut out, true)?;
+        Ok((out, page_data_list))
     }
 
     pub fn render_many_to_writer_parallel<W: std::io::Write>(
@@ -5499,15 +7555,37 @@ impl FullBleed {
         css: &str,
         writer: &mut W,
     ) -> Result<usize, FullBleedError> {
+        self.render_many_parallel_to_stream(html_list, css, writer, false)
+            .map(|(bytes_written, _)| bytes_written)
+    }
+
+    // Every parallel batch goes through here: documents are rendered on Rayon threads and
+    // written in input order through a `DocumentQueue`, so the memory budget bounds them all.
+    // Page data is collected only when `with_page_data` is set; plan-and-replay documents
+    // carry none, so those batches take the regular pipeline.
+    fn render_many_parallel_to_stream<W: std::io::Write>(
+        &self,
+        html_list: &[String],
+        css: &str,
+        writer: &mut W,
+        with_page_data: bool,
+    ) -> Result<(usize, Vec<Option<PageDataContext>>), FullBleedError> {
+        // The pipelines below need a writer thread, which wasm32 cannot spawn.
+        if cfg!(target_arch = "wasm32") {
+            return self
+                .render_many_to_stream(html_list, css, &[], writer, "render_many_parallel")
+                .map(|(manifest, page_data)| (manifest.bytes_written, page_data));
+        }
         let perf = self.perf.as_deref();
-        let t_total = std::time::Instant::now();
+        let t_total = crate::platform::Instant::now();
         let context = self.build_render_context(css, None);
         let page_size = context
             .page_templates
-            .get(0)
+            .first()
             .ok_or(FullBleedError::MissingPageTemplate)?
             .page_size;
 
+        let progress = BatchProgress::new(self.pdf_options.progress.clone(), html_list.len());
         // Streaming writer: avoids holding a merged mega-Document or a Vec<String> of PDF objects.
         let mut pdf_stream = pdf::PdfStreamWriter::new(
             writer,
@@ -5518,10 +7596,8 @@ impl FullBleed {
             self.perf.clone(),
         )?;
 
-        if matches!(self.jit_mode, JitMode::PlanAndReplay) {
+        if !with_page_data && matches!(self.jit_mode, JitMode::PlanAndReplay) {
             use rayon::prelude::*;
-            use std::collections::BTreeMap;
-            use std::path::PathBuf;
             use std::sync::mpsc;
             use std::thread;
 
@@ -5530,35 +7606,16 @@ impl FullBleed {
                 return Err(FullBleedError::EmptyDocumentSet);
             }
 
-            let spill_enabled = std::env::var("FULLBLEED_JIT_SPILL")
-                .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
-                .unwrap_or(false);
-            let spill_dir = std::env::var("FULLBLEED_JIT_SPILL_DIR")
-                .ok()
-                .map(PathBuf::from)
-                .or_else(|| {
-                    if spill_enabled {
-                        Some(std::env::temp_dir().join("fullbleed_spill"))
-                    } else {
-                        None
-                    }
-                });
-            let spill_store = match spill_dir {
-                Some(dir) => Some(Arc::new(
-                    spill::SpillStore::new(dir).map_err(FullBleedError::Io)?,
-                )),
-                None => None,
-            };
-
             // Bound in-flight documents to keep memory stable.
             let buffer_cap = (rayon::current_num_threads().max(1) * 4).min(256);
+            let (spill_store, max_bytes, max_docs) = self.batch_spill(true, buffer_cap)?;
             let (tx, rx) =
                 mpsc::sync_channel::<(usize, Result<Document, FullBleedError>)>(buffer_cap);
             let mut render_error: Option<FullBleedError> = None;
+            let mut queue = spill::DocumentQueue::new(spill_store.as_ref(), max_bytes, max_docs);
 
             thread::scope(|scope| {
                 let rx = rx;
-                let spill_store = spill_store.as_ref();
 
                 // Producer: plan + paint in parallel.
                 scope.spawn(|| {
@@ -5589,62 +7646,24 @@ impl FullBleed {
                 });
 
                 // Consumer: write in order with backpressure.
-                enum PendingDoc {
-                    InMemory(Document),
-                    Spilled(PathBuf),
-                }
-                let mut pending: BTreeMap<usize, PendingDoc> = BTreeMap::new();
                 let mut next_idx: usize = 0;
-                let spill_threshold = buffer_cap;
 
                 while next_idx < n {
                     match rx.recv() {
                         Ok((idx, res)) => match res {
                             Ok(doc) => {
-                                let entry = if let Some(store) = spill_store {
-                                    if pending.len() >= spill_threshold {
-                                        match store.spill(&doc) {
-                                            Ok(path) => PendingDoc::Spilled(path),
-                                            Err(err) => {
-                                                render_error = Some(FullBleedError::Io(err));
-                                                break;
-                                            }
-                                        }
-                                    } else {
-                                        PendingDoc::InMemory(doc)
-                                    }
-                                } else {
-                                    PendingDoc::InMemory(doc)
-                                };
-                                pending.insert(idx, entry);
-
-                                while let Some(entry) = pending.remove(&next_idx) {
-                                    let doc = match entry {
-                                        PendingDoc::InMemory(doc) => doc,
-                                        PendingDoc::Spilled(path) => {
-                                            if let Some(store) = spill_store {
-                                                match store.load(&path) {
-                                                    Ok(doc) => doc,
-                                                    Err(err) => {
-                                                        render_error =
-                                                            Some(FullBleedError::Io(err));
-                                                        break;
-                                                    }
-                                                }
-                                            } else {
-                                                render_error =
-                                                    Some(FullBleedError::Io(std::io::Error::new(
-                                                        std::io::ErrorKind::Other,
-                                                        "spill requested without spill store",
-                                                    )));
-                                                break;
-                                            }
-                                        }
-                                    };
-                                    if let Err(e) = pdf_stream.add_document(next_idx, &doc) {
+                                if let Err(err) = queue.push(idx, doc) {
+                                    render_error = Some(FullBleedError::Io(err));
+                                    break;
+                                }
+                                while let Some(entry) = queue.take(next_idx) {
+                                    let written = entry
+                                        .and_then(|doc| pdf_stream.add_document(next_idx, &doc));
+                                    if let Err(e) = written {
                                         render_error = Some(FullBleedError::Io(e));
                                         break;
                                     }
+                                    progress.record_done(next_idx);
                                     next_idx += 1;
                                 }
                                 if render_error.is_some() {
@@ -5670,29 +7689,19 @@ impl FullBleed {
             if let Some(err) = render_error {
                 return Err(err);
             }
+            self.record_batch_spill("jit.spill", spill_store.as_ref(), &queue);
 
-            if let Some(store) = spill_store {
-                let (files, bytes) = store.metrics();
-                if let Some(logger) = self.debug.as_deref() {
-                    let json = format!(
-                        "{{\"type\":\"jit.spill\",\"files\":{},\"bytes\":{}}}",
-                        files, bytes
-                    );
-                    logger.log_json(&json);
-                }
-            }
-
-            return Ok(pdf_stream.finish()?);
+            return Ok((pdf_stream.finish()?.bytes_written, Vec::new()));
         }
 
         // Pipeline: render HTML->Document on Rayon threads while a single writer thread
         // serializes to PDF in input order. This keeps memory bounded and keeps CPU busy.
+        use crate::platform::Instant;
         use rayon::prelude::*;
-        use std::collections::BTreeMap;
         use std::sync::atomic::{AtomicU64, Ordering};
         use std::sync::mpsc;
         use std::thread;
-        use std::time::{Duration, Instant};
+        use std::time::Duration;
 
         let n = html_list.len();
         if n == 0 {
@@ -5701,7 +7710,16 @@ impl FullBleed {
 
         // Bound the number of in-flight Documents so we don’t blow up memory on huge batches.
         let buffer_cap = (rayon::current_num_threads().max(1) * 4).min(256);
-        let (tx, rx) = mpsc::sync_channel::<(usize, Result<Document, FullBleedError>)>(buffer_cap);
+        let (tx, rx) = mpsc::sync_channel::<(
+            usize,
+            Result<(Document, Option<PageDataContext>), FullBleedError>,
+        )>(buffer_cap);
+        let (spill_store, max_bytes, max_docs) = self.batch_spill(false, usize::MAX)?;
+        let mut queue = spill::DocumentQueue::new(spill_store.as_ref(), max_bytes, max_docs);
+        let mut page_data_list: Vec<Option<PageDataContext>> = Vec::new();
+        if with_page_data {
+            page_data_list.resize_with(n, || None);
+        }
 
         let mut render_error: Option<FullBleedError> = None;
         let timing_enabled = std::env::var("FULLBLEED_TIMING")
@@ -5735,8 +7753,7 @@ impl FullBleed {
                                 &context.page_templates,
                                 &context.resolver,
                                 None,
-                            )
-                            .map(|(doc, _page_data)| doc);
+                            );
                         // If the receiver is gone (error), stop pushing.
                         let t_send = Instant::now();
                         let _ = tx.send((idx, res));
@@ -5750,7 +7767,6 @@ impl FullBleed {
             });
 
             // Consumer: write in order.
-            let mut pending: BTreeMap<usize, Document> = BTreeMap::new();
             let mut next_idx: usize = 0;
 
             while next_idx < n {
@@ -5759,18 +7775,27 @@ impl FullBleed {
                 recv_wait += t0.elapsed();
                 match msg {
                     Ok((idx, res)) => match res {
-                        Ok(doc) => {
-                            pending.insert(idx, doc);
-                            if pending.len() > max_pending {
-                                max_pending = pending.len();
+                        Ok((doc, page_data)) => {
+                            if with_page_data {
+                                page_data_list[idx] = page_data;
+                            }
+                            if let Err(e) = queue.push(idx, doc) {
+                                render_error = Some(FullBleedError::Io(e));
+                                break;
+                            }
+                            if queue.len() > max_pending {
+                                max_pending = queue.len();
                             }
-                            while let Some(doc) = pending.remove(&next_idx) {
+                            while let Some(entry) = queue.take(next_idx) {
                                 let t1 = Instant::now();
-                                if let Err(e) = pdf_stream.add_document(next_idx, &doc) {
+                                let written =
+                                    entry.and_then(|doc| pdf_stream.add_document(next_idx, &doc));
+                                if let Err(e) = written {
                                     render_error = Some(FullBleedError::Io(e));
                                     break;
                                 }
                                 write_time += t1.elapsed();
+                                progress.record_done(next_idx);
                                 next_idx += 1;
                             }
                         }
@@ -5797,6 +7822,7 @@ impl FullBleed {
         if let Some(e) = render_error {
             return Err(e);
         }
+        self.record_batch_spill("batch.spill", spill_store.as_ref(), &queue);
 
         if timing_enabled {
             eprintln!(
@@ -5805,7 +7831,7 @@ impl FullBleed {
             );
         }
 
-        let bytes_written = pdf_stream.finish()?;
+        let bytes_written = pdf_stream.finish()?.bytes_written;
         if let Some(perf_logger) = perf {
             perf_logger.log_span_ms("batch.recv_wait", None, recv_wait.as_secs_f64() * 1000.0);
             let send_wait_ms = send_wait.load(Ordering::Relaxed) as f64 / 1_000_000.0;
@@ -5830,7 +7856,7 @@ impl FullBleed {
             );
         }
 
-        Ok(bytes_written)
+        Ok((bytes_written, page_data_list))
     }
 
     pub fn render_many_to_writer_parallel_with_page_data<W: std::io::Write>(
@@ -5839,47 +7865,7 @@ impl FullBleed {
         css: &str,
         writer: &mut W,
     ) -> Result<(usize, Vec<Option<PageDataContext>>), FullBleedError> {
-        use rayon::prelude::*;
-
-        let context = self.build_render_context(css, None);
-        let mut results: Vec<(
-            usize,
-            Result<(Document, Option<PageDataContext>), FullBleedError>,
-        )> = html_list
-            .par_iter()
-            .enumerate()
-            .map(|(idx, html)| {
-                let res = self.render_to_document_and_page_data_with_resolver_and_report_at(
-                    idx,
-                    html,
-                    &context.page_templates,
-                    &context.resolver,
-                    None,
-                );
-                (idx, res)
-            })
-            .collect();
-        results.sort_by_key(|(idx, _)| *idx);
-
-        let mut documents = Vec::with_capacity(results.len());
-        let mut page_data_list = Vec::with_capacity(results.len());
-        for (_, res) in results {
-            let (doc, page_data) = res?;
-            documents.push(doc);
-            page_data_list.push(page_data);
-        }
-
-        let merged = merge_documents(documents)?;
-        let bytes_written = pdf::document_to_pdf_with_metrics_and_registry_to_writer_with_logs(
-            &merged,
-            None,
-            Some(self.font_registry.as_ref()),
-            &self.pdf_options,
-            writer,
-            self.debug.clone(),
-            self.perf.clone(),
-        )?;
-        Ok((bytes_written, page_data_list))
+        self.render_many_parallel_to_stream(html_list, css, writer, true)
     }
 
     pub fn render_many_to_file_parallel(
@@ -5910,20 +7896,30 @@ impl FullBleedBuilder {
             margins: Margins::all(36.0),
             page_size_explicit: false,
             margins_explicit: false,
+            page_rotate: 0,
+            rotate_landscape_pages: false,
+            mirror_margins: false,
+            page_frames: Vec::new(),
             font_dirs: Vec::new(),
             font_files: Vec::new(),
+            font_variations: Vec::new(),
+            font_fallbacks: Vec::new(),
+            font_cache: None,
+            shape_cache_capacity: font::DEFAULT_SHAPE_CACHE_ENTRIES,
             pdf_options: PdfOptions::default(),
             svg_form_xobjects: false,
             svg_raster_fallback: cfg!(feature = "svg_raster"),
             unicode_metrics: true,
             debug_path: None,
             perf_enabled: false,
+            perf_tracing: false,
             perf_path: None,
             jit_mode: JitMode::Off,
             layout_strategy: LayoutStrategy::Eager,
             accept_lazy_layout_cost: false,
             lazy_max_passes: 4,
             lazy_budget_ms: 50.0,
+            parallel_sections: false,
             page_header: None,
             page_header_html: None,
             page_footer: None,
@@ -5931,7 +7927,20 @@ impl FullBleedBuilder {
             template_binding_spec: None,
             page_margins: std::collections::BTreeMap::new(),
             watermark: None,
+            page_draw: None,
+            on_page_start: None,
+            on_page_end: None,
+            custom_elements: std::collections::HashMap::new(),
+            stream_chunk_bytes: 1 << 20,
+            memory_budget: None,
+            jit_spill: None,
+            spill_codec: SpillCodec::None,
+            audit_gate_profile: None,
+            strict_fidelity: false,
+            warnings: None,
             asset_bundle: AssetBundle::default(),
+            remote_assets: None,
+            resource_resolver: None,
         }
     }
 
@@ -5941,12 +7950,42 @@ impl FullBleedBuilder {
         self
     }
 
+    // Write every page with a `/Rotate` of `degrees` (a multiple of 90). Layout, headers,
+    // footers and watermarks use the upright page; viewers show it as laid out.
+    pub fn page_rotate(mut self, degrees: i32) -> Self {
+        self.page_rotate = canvas::normalize_rotate(degrees);
+        self
+    }
+
+    // Store landscape pages (e.g. `@page { size: A4 landscape }`) as portrait media turned by
+    // `/Rotate 90`, for print workflows that expect portrait sheets.
+    pub fn rotate_landscape_pages(mut self, enabled: bool) -> Self {
+        self.rotate_landscape_pages = enabled;
+        self
+    }
+
     pub fn margins(mut self, margins: Margins) -> Self {
         self.margins = margins;
         self.margins_explicit = true;
         self
     }
 
+    // Mirror the page margins on even (left-hand) pages for duplex printing, so the left margin
+    // of odd pages is the gutter on both sides of a spread. Pair with `Margins::mirrored`.
+    // Lay every page out in several frames instead of one box inside the margins, e.g. two
+    // columns beside a sidebar. Frame rectangles are relative to the top-left of each page's
+    // content area; the story fills them in flow order (see `FrameSpec::linked_to`) and
+    // `break-before`/`break-after: column` moves on to the next frame.
+    pub fn page_frames(mut self, frames: Vec<FrameSpec>) -> Self {
+        self.page_frames = frames;
+        self
+    }
+
+    pub fn mirror_margins(mut self, enabled: bool) -> Self {
+        self.mirror_margins = enabled;
+        self
+    }
+
     pub fn margin_all(mut self, value: f32) -> Self {
         self.margins = Margins::all(value);
         self.margins_explicit = true;
@@ -5988,6 +8027,47 @@ impl FullBleedBuilder {
         self
     }
 
+    // Append `family` to the engine-wide fallback chain, consulted for characters the CSS font
+    // stack cannot render. Fallbacks are tried in the order they were added.
+    pub fn font_fallback(mut self, family: impl Into<String>) -> Self {
+        self.font_fallbacks.push((family.into(), None));
+        self
+    }
+
+    // Like `font_fallback`, but only for characters of `script` (e.g. a CJK font for
+    // `FontScript::Cjk`, an emoji font for `FontScript::Emoji`).
+    pub fn font_fallback_for(mut self, script: FontScript, family: impl Into<String>) -> Self {
+        self.font_fallbacks.push((family.into(), Some(script)));
+        self
+    }
+
+    // Cache the engine's fonts are parsed through. By default engines share
+    // `FontCache::global()`, so building many engines with the same fonts parses each once;
+    // pass a separate cache to keep an engine's fonts apart.
+    pub fn font_cache(mut self, cache: Arc<FontCache>) -> Self {
+        self.font_cache = Some(cache);
+        self
+    }
+
+    // Name a registered variable font at fixed axis values, e.g.
+    // `font_variation("Inter Condensed", "Inter", &[("wdth", 75.0)])`, so CSS can use the alias
+    // as a font family. CSS `font-weight` still drives `wght` unless the alias sets it.
+    pub fn font_variation(
+        mut self,
+        alias: impl Into<String>,
+        family: impl Into<String>,
+        axes: &[(&str, f32)],
+    ) -> Self {
+        self.font_variations.push((
+            alias.into(),
+            family.into(),
+            axes.iter()
+                .map(|(tag, value)| (tag.to_string(), *value))
+                .collect(),
+        ));
+        self
+    }
+
     // When enabled (default), identical images are embedded once and reused via a single
     // PDF XObject resource. Turning this off can be useful for debugging or compatibility.
     pub fn reuse_xobjects(mut self, enabled: bool) -> Self {
@@ -6009,6 +8089,13 @@ impl FullBleedBuilder {
         self
     }
 
+    // Shaped text runs the engine keeps for reuse across pages, records and renders (default
+    // 8192, least recently used dropped first); 0 shapes every run anew.
+    pub fn shape_cache_capacity(mut self, entries: usize) -> Self {
+        self.shape_cache_capacity = entries;
+        self
+    }
+
     // Batch JIT pipeline mode. Off by default.
     pub fn jit_mode(mut self, mode: JitMode) -> Self {
         self.jit_mode = mode;
@@ -6040,6 +8127,15 @@ impl FullBleedBuilder {
         self
     }
 
+    // Lay out the sections between forced page breaks (`break-before: page` on a top-level
+    // block) on separate threads and join their pages in order. Renders whose pages depend on
+    // what came before (named or left/right page templates, fixed or absolutely positioned
+    // root content, frame threads, page callbacks, debug logs) are laid out in one piece.
+    pub fn parallel_sections(mut self, enabled: bool) -> Self {
+        self.parallel_sections = enabled;
+        self
+    }
+
     // PDF conformance/profile toggles (e.g. Tagged).
     pub fn pdf_profile(mut self, profile: PdfProfile) -> Self {
         self.pdf_options.pdf_profile = profile;
@@ -6057,6 +8153,98 @@ impl FullBleedBuilder {
         self
     }
 
+    // Embed a file (e.g. a Factur-X/ZUGFeRD invoice XML). Attachments are listed in the
+    // catalog /EmbeddedFiles name tree and /AF array; use PdfProfile::PdfA3b for PDF/A.
+    pub fn attach_file(
+        mut self,
+        name: impl Into<String>,
+        mime: impl Into<String>,
+        bytes: Vec<u8>,
+        relationship: AfRelationship,
+    ) -> Self {
+        self.pdf_options
+            .attachments
+            .push(EmbeddedFile::new(name, mime, bytes, relationship));
+        self
+    }
+
+    pub fn attach_embedded_file(mut self, file: EmbeddedFile) -> Self {
+        self.pdf_options.attachments.push(file);
+        self
+    }
+
+    // Reserve `len` bytes for a detached PKCS#7 signature on the first signature field (an
+    // invisible one is added when the first page has none). Sign the rendered bytes with
+    // `patch_signature_byte_range` and `embed_signature_contents`.
+    pub fn reserve_signature_byte_range(mut self, len: usize) -> Self {
+        self.pdf_options = self.pdf_options.reserve_signature_byte_range(len);
+        self
+    }
+
+    // Password-protect the output with AES-256 (/V 5 /R 6). Not allowed with the PDF/A and
+    // PDF/X profiles.
+    pub fn encryption(mut self, spec: EncryptionSpec) -> Self {
+        self.pdf_options.encryption = Some(spec);
+        self
+    }
+
+    // Trailer /ID source. `DocumentId::ContentHash` or a seed makes repeated renders of the
+    // same input byte-identical; by default no /ID is written (random when encrypted).
+    pub fn document_id(mut self, id: DocumentId) -> Self {
+        self.pdf_options.document_id = Some(id);
+        self
+    }
+
+    // Trim/bleed/crop boxes around the laid-out page, optionally with crop and registration
+    // marks. The page size set on the builder is the trim size.
+    pub fn page_boxes(mut self, spec: PageBoxSpec) -> Self {
+        self.pdf_options.page_boxes = Some(spec);
+        self
+    }
+
+    // Logical page numbers for PDF viewers (/PageLabels). Without a spec, labels follow
+    // batch record prefixes, then the page counter style of CSS margin boxes.
+    pub fn page_labels(mut self, spec: PageLabelSpec) -> Self {
+        self.pdf_options.page_labels = Some(spec);
+        self
+    }
+
+    // Live progress for long renders: pages laid out, batch records completed and PDF bytes
+    // written. The sink is called from rendering threads.
+    pub fn progress(mut self, sink: impl ProgressSink + 'static) -> Self {
+        self.pdf_options.progress = Some(ProgressHandle::new(sink));
+        self
+    }
+
+    // Resample images placed above `dpi` down to it before embedding. JPEGs within the limit
+    // are always embedded unchanged (DCTDecode).
+    pub fn max_image_dpi(mut self, dpi: u32) -> Self {
+        self.pdf_options.max_image_dpi = Some(dpi.max(1));
+        self
+    }
+
+    // Embed only the glyphs each TrueType font actually uses (default: on). Subsets are named
+    // with a tag derived from the glyph set, so identical inputs give identical output.
+    pub fn font_subsetting(mut self, enabled: bool) -> Self {
+        self.pdf_options.font_subsetting = enabled;
+        self
+    }
+
+    // Flate level of page and form content streams: 0 writes them uncompressed, 1 (fastest) to
+    // 9 (smallest) compress with zlib. By default a fast built-in encoder is used.
+    pub fn content_compression_level(mut self, level: u32) -> Self {
+        self.pdf_options.compress_content_streams = level > 0;
+        self.pdf_options.content_compression_level = (level > 0).then_some(level.min(9));
+        self
+    }
+
+    // Compress the page content streams of each document in parallel (default: on). When off,
+    // each page's content is compressed on the writing thread before its page object.
+    pub fn parallel_content_compression(mut self, enabled: bool) -> Self {
+        self.pdf_options.parallel_content_compression = enabled;
+        self
+    }
+
     // PDF version selector (default: PDF 1.7).
     pub fn pdf_version(mut self, version: PdfVersion) -> Self {
         self.pdf_options.pdf_version = version;
@@ -6099,6 +8287,12 @@ impl FullBleedBuilder {
         self.pdf_options.document_title.as_deref()
     }
 
+    // Build the PDF outline (bookmarks) from <h1>..<h{levels}> headings; 0 disables it.
+    pub fn outline_from_headings(mut self, levels: u8) -> Self {
+        self.pdf_options.outline_levels = levels.min(6);
+        self
+    }
+
     // Toggle Unicode-aware layout measurements (rustybuzz-based).
     // When disabled, layout uses basic metrics for speed.
     pub fn unicode_metrics(mut self, enabled: bool) -> Self {
@@ -6125,6 +8319,14 @@ impl FullBleedBuilder {
         self
     }
 
+    // Emit perf spans and counters as `tracing` events (target `fullbleed::perf`) without a
+    // perf log file. With a perf log, they are emitted alongside it regardless.
+    #[cfg(feature = "tracing")]
+    pub fn perf_tracing(mut self, enabled: bool) -> Self {
+        self.perf_tracing = enabled;
+        self
+    }
+
     // Header text templates. Placeholders:
     // - {page}: 1-based page number within this record/document
     // - {pages}: total pages within this record/document
@@ -6233,6 +8435,50 @@ impl FullBleedBuilder {
         self
     }
 
+    // Paints custom vector content on every page after layout, e.g. crop marks, stamps or
+    // plots drawn with the `Canvas` path, text and clipping helpers.
+    pub fn on_page_draw(
+        mut self,
+        draw: impl Fn(&mut Canvas, PageCtx) + Send + Sync + 'static,
+    ) -> Self {
+        self.page_draw = Some(Arc::new(draw));
+        self
+    }
+
+    // Page boundary hooks run during layout with the page number and template name. Each
+    // gets the page's canvas, so it can add per-page stamps or record what was laid out:
+    // `on_page_start` draws before the page content, `on_page_end` over it. Layout may run
+    // more than once (lazy layout passes, stream slices), so they can see a page twice.
+    pub fn on_page_start(
+        mut self,
+        callback: impl Fn(&mut Canvas, &DocContext) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_page_start = Some(Arc::new(callback));
+        self
+    }
+
+    pub fn on_page_end(
+        mut self,
+        callback: impl Fn(&mut Canvas, &DocContext) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_page_end = Some(Arc::new(callback));
+        self
+    }
+
+    // Render HTML elements named `tag` (e.g. `x-gantt-row`) with a user flowable. The factory
+    // sees the element's attributes, text and computed font size/color; the flowable it
+    // returns is laid out inside the element's CSS box, and `None` renders the element as
+    // usual.
+    pub fn register_element(
+        mut self,
+        tag: impl Into<String>,
+        factory: impl Fn(&CustomElement) -> Option<Box<dyn Flowable>> + Send + Sync + 'static,
+    ) -> Self {
+        self.custom_elements
+            .insert(tag.into().to_ascii_lowercase(), Arc::new(factory));
+        self
+    }
+
     pub fn paginated_context(mut self, spec: PaginatedContextSpec) -> Self {
         self.paginated_context = Some(spec);
         self
@@ -6243,12 +8489,80 @@ impl FullBleedBuilder {
         self
     }
 
+    // Approximate HTML bytes parsed per slice by render_stream_to_writer (default: 1 MiB).
+    // Slices are cut at top-level body children, so one element is never split.
+    pub fn stream_chunk_bytes(mut self, bytes: usize) -> Self {
+        self.stream_chunk_bytes = bytes.max(1);
+        self
+    }
+
+    // Bound the memory parallel batch renders spend on finished documents waiting for their
+    // turn; past the budget their large inline images, then the documents themselves, are
+    // spilled to disk.
+    pub fn memory_budget(mut self, budget: MemoryBudget) -> Self {
+        self.memory_budget = Some(budget);
+        self
+    }
+
+    // Plan-and-replay batches spill documents to `dir` once `threshold` are queued for
+    // writing. Overrides FULLBLEED_JIT_SPILL / FULLBLEED_JIT_SPILL_DIR; a memory budget
+    // takes precedence over both.
+    pub fn jit_spill(mut self, dir: impl Into<std::path::PathBuf>, threshold: usize) -> Self {
+        self.jit_spill = Some((dir.into(), threshold));
+        self
+    }
+
+    // Compression of spilled documents, for memory budgets and plan-and-replay spills alike.
+    // Spilling is raw by default; zstd trades some CPU for smaller spill files.
+    pub fn spill_codec(mut self, codec: SpillCodec) -> Self {
+        self.spill_codec = codec;
+        self
+    }
+
+    // Run the PMR audits over every HTML document after layout and fail the render with
+    // `FullBleedError::AuditGateFailed` when an audit gated at "error" for `profile` fails.
+    pub fn enforce_audit_gates(mut self, profile: &str) -> Self {
+        self.audit_gate_profile = Some(profile.to_string());
+        self
+    }
+
+    // Fail renders with `FullBleedError::FidelityLoss` instead of degrading: every known-loss
+    // warning (unusable CSS, an overridden @page size, a rasterized SVG, clipped content, ...)
+    // becomes part of the error. Streamed renders are rejected.
+    pub fn strict_fidelity(mut self, enabled: bool) -> Self {
+        self.strict_fidelity = enabled;
+        self
+    }
+
+    // Structured warnings (ignored HTML assets, known-loss events, font substitutions) as they
+    // happen, instead of stderr. The sink is called from rendering threads.
+    pub fn on_warning(mut self, sink: impl WarningSink + 'static) -> Self {
+        let handle = WarningHandle::new(sink);
+        self.pdf_options.warnings = Some(handle.clone());
+        self.warnings = Some(handle);
+        self
+    }
+
     pub fn register_bundle(mut self, bundle: AssetBundle) -> Self {
         self.asset_bundle = bundle;
         self
     }
 
-    pub fn build(self) -> Result<FullBleed, FullBleedError> {
+    // Resolve http(s) image sources through `remote` (allowlist + cache). Bundle assets
+    // registered under the same URL still win; without this, remote sources stay unresolved.
+    pub fn remote_assets(mut self, remote: RemoteAssets) -> Self {
+        self.remote_assets = Some(remote);
+        self
+    }
+
+    // Read local image paths and `font_file` paths through `resolver` instead of the
+    // filesystem. `font_dir` still lists a real directory.
+    pub fn resource_resolver(mut self, resolver: impl ResourceResolver + 'static) -> Self {
+        self.resource_resolver = Some(assets::SharedResolver(Arc::new(resolver)));
+        self
+    }
+
+    pub fn build(mut self) -> Result<FullBleed, FullBleedError> {
         if self.layout_strategy == LayoutStrategy::Lazy && !self.accept_lazy_layout_cost {
             return Err(FullBleedError::InvalidConfiguration(
                 "layout_strategy=lazy requires accept_lazy_layout_cost(true)".to_string(),
@@ -6269,16 +8583,58 @@ impl FullBleedBuilder {
         validate_pdf_options(&self.pdf_options)?;
         let mut registry = FontRegistry::new();
         registry.set_use_full_unicode_metrics(self.unicode_metrics);
+        if let Some(cache) = self.font_cache.take() {
+            registry.set_font_cache(cache);
+        }
+        registry.set_shape_cache_capacity(self.shape_cache_capacity);
         for dir in &self.font_dirs {
             registry.register_dir(dir);
         }
         for file in &self.font_files {
-            registry.register_file(file);
+            match &self.resource_resolver {
+                Some(assets::SharedResolver(resolver)) => {
+                    let path = file.to_string_lossy();
+                    let data =
+                        resolver
+                            .read(&path, AssetKind::Font)
+                            .map_err(|err| match err.kind() {
+                                std::io::ErrorKind::NotFound => {
+                                    FullBleedError::FontNotFound(path.to_string())
+                                }
+                                _ => FullBleedError::Asset(format!("{path}: {err}")),
+                            })?;
+                    registry.register_bytes(data, Some(&path))?;
+                }
+                None => registry.register_file(file),
+            }
         }
         for asset in self.asset_bundle.font_assets() {
             registry.register_bundle_font_bytes(asset.data.clone(), Some(&asset.name))?;
         }
+        for (alias, family, axes) in &self.font_variations {
+            let coords = axes
+                .iter()
+                .map(|(tag, value)| {
+                    let tag = tag.as_bytes();
+                    if tag.is_empty() || tag.len() > 4 || !tag.is_ascii() || !value.is_finite() {
+                        return Err(FullBleedError::InvalidConfiguration(format!(
+                            "font_variation {alias:?}: invalid axis {:?} = {value}",
+                            String::from_utf8_lossy(tag)
+                        )));
+                    }
+                    let mut padded = *b"    ";
+                    padded[..tag.len()].copy_from_slice(tag);
+                    Ok((padded, *value))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            registry.register_variation_alias(alias, family, &coords)?;
+        }
+        for (family, script) in &self.font_fallbacks {
+            registry.add_fallback(family, *script);
+        }
         let asset_css = self.asset_bundle.css_text();
+        self.asset_bundle.remote = self.remote_assets.take();
+        self.asset_bundle.resolver = self.resource_resolver.take();
         let debug = if let Some(path) = self.debug_path {
             Some(Arc::new(DebugLogger::new(path)?))
         } else {
@@ -6289,15 +8645,25 @@ impl FullBleedBuilder {
                 .perf_path
                 .unwrap_or_else(|| std::path::PathBuf::from("fullbleed_perf.log"));
             Some(Arc::new(PerfLogger::new(path)?))
+        } else if self.perf_tracing {
+            Some(Arc::new(PerfLogger::tracing_only()))
         } else {
             None
         };
+        if let Some(perf) = perf.as_deref() {
+            let (hits, misses) = registry.font_cache_counts();
+            perf.log_counts("font.cache", None, &[("hits", hits), ("misses", misses)]);
+        }
         Ok(FullBleed {
             default_page_size: self.page_size,
             default_margins: self.margins,
             page_margins: self.page_margins,
             page_size_explicit: self.page_size_explicit,
             margins_explicit: self.margins_explicit,
+            page_rotate: self.page_rotate,
+            rotate_landscape_pages: self.rotate_landscape_pages,
+            mirror_margins: self.mirror_margins,
+            page_frames: self.page_frames,
             font_registry: Arc::new(registry),
             pdf_options: self.pdf_options,
             svg_form_xobjects: self.svg_form_xobjects,
@@ -6308,33 +8674,103 @@ impl FullBleedBuilder {
             layout_strategy: self.layout_strategy,
             lazy_max_passes: self.lazy_max_passes,
             lazy_budget_ms: self.lazy_budget_ms,
+            parallel_sections: self.parallel_sections,
             page_header: self.page_header,
             page_header_html: self.page_header_html,
             page_footer: self.page_footer,
             paginated_context: self.paginated_context,
             template_binding_spec: self.template_binding_spec,
             watermark: self.watermark,
+            page_draw: self.page_draw,
+            on_page_start: self.on_page_start,
+            on_page_end: self.on_page_end,
+            custom_elements: self.custom_elements,
+            compiled_templates: Arc::new(std::sync::Mutex::new(lru::LruCache::new(
+                COMPILED_TEMPLATE_CACHE_CAPACITY,
+            ))),
+            engine_id: NEXT_ENGINE_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+            stream_chunk_bytes: self.stream_chunk_bytes,
+            memory_budget: self.memory_budget,
+            jit_spill: self.jit_spill,
+            spill_codec: self.spill_codec,
+            spill_metrics: std::sync::Mutex::new(SpillMetrics::default()),
+            audit_gate_profile: self.audit_gate_profile,
+            strict_fidelity: self.strict_fidelity,
+            warnings: self.warnings,
             asset_css,
             asset_bundle: Arc::new(self.asset_bundle),
         })
     }
 }
 
+// Attaches to a failed render what the engine already knew it could not honor: CSS it
+// dropped, `@font-face` rules that loaded nothing, and known losses (a `FidelityLoss`
+// error already lists those).
+fn with_render_diagnostics(
+    err: FullBleedError,
+    resolver: &style::StyleResolver,
+    warnings: &[Warning],
+) -> FullBleedError {
+    let mut related: Vec<Diagnostic> = resolver
+        .css_diagnostics()
+        .iter()
+        .map(Diagnostic::from)
+        .collect();
+    related.extend(resolver.font_face_failures().iter().cloned());
+    if err.code() != ErrorCode::FidelityLoss {
+        let mut losses: Vec<&Warning> = Vec::new();
+        for warning in resolver.known_losses().iter().chain(warnings) {
+            if warning.kind == WarningKind::KnownLoss && !losses.contains(&warning) {
+                losses.push(warning);
+            }
+        }
+        related.extend(losses.into_iter().map(Diagnostic::from));
+    }
+    related.into_iter().fold(err, FullBleedError::with_related)
+}
+
+// Cascade of the `@page` rules with a page selector that apply to pages named `name` of
+// `parity`, leaving out `:first` rules and the unnamed rule.
+fn selected_page_setup(
+    setups: &[(style::CssPageSelector, style::CssPageSetup)],
+    name: Option<&str>,
+    parity: Option<PageParity>,
+) -> style::CssPageSetup {
+    let mut matching: Vec<&(style::CssPageSelector, style::CssPageSetup)> = setups
+        .iter()
+        .filter(|(selector, _)| {
+            !selector.first
+                && selector.name.as_deref().is_none_or(|own| Some(own) == name)
+                && selector.parity.is_none_or(|own| Some(own) == parity)
+        })
+        .collect();
+    matching.sort_by_key(|(selector, _)| selector.specificity());
+    let mut merged = style::CssPageSetup::default();
+    for (_, setup) in matching {
+        merged.merge(setup);
+    }
+    merged
+}
+
+fn page_frame_rect(page_size: Size, margins: Margins) -> Rect {
+    let margins = margins.quantized();
+    let content_width = (page_size.width - margins.left - margins.right).max(Pt::ZERO);
+    let content_height = (page_size.height - margins.top - margins.bottom).max(Pt::ZERO);
+    Rect {
+        x: margins.left,
+        y: margins.top,
+        width: content_width,
+        height: content_height,
+    }
+    .quantized()
+}
+
 fn build_page_templates(
     page_size: Size,
     base_margins: Margins,
     page_margins: &std::collections::BTreeMap<usize, Margins>,
 ) -> Vec<PageTemplate> {
-    let base_margins = base_margins.quantized();
-    let content_width = (page_size.width - base_margins.left - base_margins.right).max(Pt::ZERO);
-    let content_height = (page_size.height - base_margins.top - base_margins.bottom).max(Pt::ZERO);
-    let frame_rect = Rect {
-        x: base_margins.left,
-        y: base_margins.top,
-        width: content_width,
-        height: content_height,
-    }
-    .quantized();
+    let frame_rect = page_frame_rect(page_size, base_margins);
 
     let mut templates: Vec<PageTemplate> = Vec::new();
     if page_margins.is_empty() {
@@ -6347,17 +8783,8 @@ fn build_page_templates(
         let margins = page_margins
             .get(&page_number)
             .copied()
-            .unwrap_or(base_margins)
-            .quantized();
-        let content_width = (page_size.width - margins.left - margins.right).max(Pt::ZERO);
-        let content_height = (page_size.height - margins.top - margins.bottom).max(Pt::ZERO);
-        let rect = Rect {
-            x: margins.left,
-            y: margins.top,
-            width: content_width,
-            height: content_height,
-        }
-        .quantized();
+            .unwrap_or(base_margins);
+        let rect = page_frame_rect(page_size, margins);
         templates.push(PageTemplate::new(format!("Page{page_number}"), page_size).with_frame(rect));
     }
     templates
@@ -6378,11 +8805,14 @@ pub fn merge_documents(documents: Vec<Document>) -> Result<Document, FullBleedEr
     let page_size = first.page_size;
     let mut pages = first.pages;
 
+    // Pages keep their own size; those that differ from the first document's record it.
     for doc in iter {
-        if doc.page_size != page_size {
-            return Err(FullBleedError::InconsistentPageSize);
-        }
-        pages.extend(doc.pages);
+        let doc_page_size = doc.page_size;
+        pages.extend(doc.pages.into_iter().map(|mut page| {
+            let size = page.size.unwrap_or(doc_page_size);
+            page.size = (size != page_size).then_some(size);
+            page
+        }));
     }
 
     Ok(Document { page_size, pages })
@@ -6391,7 +8821,9 @@ pub fn merge_documents(documents: Vec<Document>) -> Result<Document, FullBleedEr
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::flowable::{BorderCollapseMode, BorderSpec, TableCell, TextAlign, VerticalAlign};
+    use crate::flowable::{
+        BorderCollapseMode, BorderSpec, TableCell, TableLayoutMode, TextAlign, VerticalAlign,
+    };
     use std::path::PathBuf;
     use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -6424,7 +8856,7 @@ mod tests {
     }
 
     fn page_contains_text(page: &Page, needle: &str) -> bool {
-        page.commands.iter().any(|cmd| match cmd {
+        page.commands.iter().any(|cmd| match &*cmd {
             Command::DrawString { text, .. } => text.contains(needle),
             _ => false,
         })
@@ -6435,7 +8867,9 @@ mod tests {
             page_size: Size::a4(),
             pages: (0..page_count)
                 .map(|_| Page {
-                    commands: Vec::new(),
+                    commands: Vec::new().into(),
+                    size: None,
+                    rotate: 0,
                 })
                 .collect(),
         }
@@ -6513,1137 +8947,4999 @@ mod tests {
     }
 
     #[test]
-    fn lazy_layout_requires_explicit_cost_acceptance() {
+    fn encryption_writes_aes256_handler_and_hides_strings() {
+        let engine = FullBleed::builder()
+            .document_title("Quarterly Ledger")
+            .encryption(
+                EncryptionSpec::aes256("", "owner-secret")
+                    .no_print()
+                    .no_copy(),
+            )
+            .build()
+            .expect("engine");
+        let pdf = engine
+            .render_to_buffer("<p>Confidential</p>", "")
+            .expect("render encrypted pdf");
+
+        assert_eq!(count_token(&pdf, b"/Filter /Standard /V 5 /R 6"), 1);
+        assert_eq!(count_token(&pdf, b"/CFM /AESV3"), 1);
+        assert_eq!(count_token(&pdf, b"/P -2072 "), 1);
+        assert_eq!(count_token(&pdf, b"/Encrypt "), 1);
+        assert_eq!(count_token(&pdf, b"/ID [<"), 1);
+        assert_eq!(count_token(&pdf, b"Quarterly Ledger"), 0);
+        assert_eq!(count_token(&pdf, b"/ExtensionLevel 8"), 1);
+
         let err = match FullBleed::builder()
-            .layout_strategy(LayoutStrategy::Lazy)
+            .pdf_profile(PdfProfile::PdfA2b)
+            .encryption(EncryptionSpec::aes256("user", "owner"))
             .build()
         {
-            Ok(_) => panic!("lazy layout should require explicit opt-in"),
+            Ok(_) => panic!("pdfa2b should reject encryption"),
             Err(err) => err,
         };
         assert!(matches!(err, FullBleedError::InvalidConfiguration(_)));
-        assert!(err.to_string().contains("accept_lazy_layout_cost"));
+        assert!(err.to_string().contains("encryption"));
     }
 
     #[test]
-    fn lazy_layout_limits_are_validated() {
-        let err = match FullBleed::builder()
-            .layout_strategy(LayoutStrategy::Lazy)
-            .accept_lazy_layout_cost(true)
-            .lazy_layout_limits(1, 50.0)
+    fn document_id_is_deterministic_per_input() {
+        fn trailer_id(pdf: &[u8]) -> String {
+            let text = String::from_utf8_lossy(pdf);
+            let start = text.rfind("/ID [<").expect("trailer /ID") + 6;
+            text[start..start + 32].to_string()
+        }
+        let engine = FullBleed::builder()
+            .document_id(DocumentId::ContentHash)
             .build()
-        {
-            Ok(_) => panic!("lazy max passes must be >= 2"),
-            Err(err) => err,
-        };
-        assert!(matches!(err, FullBleedError::InvalidConfiguration(_)));
-        assert!(err.to_string().contains("lazy_max_passes"));
+            .expect("engine");
+        let first = engine
+            .render_to_buffer("<p>Invoice 42</p>", "")
+            .expect("render");
+        let second = engine
+            .render_to_buffer("<p>Invoice 42</p>", "")
+            .expect("render");
+        let other = engine
+            .render_to_buffer("<p>Invoice 43</p>", "")
+            .expect("render");
+        assert_eq!(first, second);
+        assert_ne!(trailer_id(&first), trailer_id(&other));
 
-        let err = match FullBleed::builder()
-            .layout_strategy(LayoutStrategy::Lazy)
-            .accept_lazy_layout_cost(true)
-            .lazy_layout_limits(4, 0.0)
+        let seeded = FullBleed::builder()
+            .document_id(DocumentId::Seed("INV-42".to_string()))
             .build()
-        {
-            Ok(_) => panic!("lazy budget must be positive"),
-            Err(err) => err,
-        };
-        assert!(matches!(err, FullBleedError::InvalidConfiguration(_)));
-        assert!(err.to_string().contains("lazy_budget_ms"));
-    }
+            .expect("engine");
+        let a = seeded.render_to_buffer("<p>A</p>", "").expect("render");
+        let b = seeded.render_to_buffer("<p>B</p>", "").expect("render");
+        assert_eq!(trailer_id(&a), trailer_id(&b));
 
-    #[test]
-    fn lazy_layout_configuration_builds_with_opt_in() {
-        FullBleed::builder()
-            .layout_strategy(LayoutStrategy::Lazy)
-            .accept_lazy_layout_cost(true)
-            .lazy_layout_limits(4, 50.0)
+        let fixed = FullBleed::builder()
+            .document_id(DocumentId::Fixed([0xAB; 16]))
             .build()
-            .expect("valid lazy config should build");
+            .expect("engine");
+        let pdf = fixed.render_to_buffer("<p>A</p>", "").expect("render");
+        assert_eq!(trailer_id(&pdf), "AB".repeat(16));
+
+        let plain = FullBleed::builder().build().expect("engine");
+        let pdf = plain.render_to_buffer("<p>A</p>", "").expect("render");
+        assert_eq!(count_token(&pdf, b"/ID ["), 0);
     }
 
     #[test]
-    fn batch_writer_file_and_parallel_paths_dedupe_embedded_fonts() {
-        let inter_path = repo_font_path("Inter-Variable.ttf");
-        let inter_bytes = std::fs::read(&inter_path).expect("read inter");
-
-        let mut engine = FullBleed::builder().build().expect("engine");
-        let font_name = {
-            let registry = Arc::get_mut(&mut engine.font_registry).expect("unique registry");
-            registry
-                .register_bytes(inter_bytes, Some(inter_path.to_string_lossy().as_ref()))
-                .expect("register inter")
-        };
-
-        let css = format!(
-            "@page {{ size: 8.5in 11in; margin: 0.5in; }} body {{ margin: 0; font-family: '{}'; font-size: 12pt; }}",
-            font_name
-        );
-        let html_list = vec![
-            "<html><body><p>Record 1</p></body></html>".to_string(),
-            "<html><body><p>Record 2</p></body></html>".to_string(),
-            "<html><body><p>Record 3</p></body></html>".to_string(),
-        ];
-        let jobs: Vec<(String, String)> = html_list
+    fn css_device_cmyk_and_fb_spot_reach_the_canvas() {
+        let engine = FullBleed::builder().build().expect("engine");
+        let html = r#"<p class="cmyk">Process</p><div class="spot">Brand</div>"#;
+        let css = r#"
+            :root { --brand: fb-spot("Brand Red", device-cmyk(0 91% 76% 0)); }
+            .cmyk { color: device-cmyk(0 1 1 0); }
+            .spot { background-color: var(--brand); border-width: 1pt; border-style: solid; border-color: fb-spot("Brand Red", device-cmyk(0 0.91 0.76 0), 40%); }
+        "#;
+        let doc = engine.render_to_document(html, css).expect("document");
+        let colors = doc.pages[0]
+            .commands
             .iter()
-            .map(|html| (html.clone(), css.clone()))
-            .collect();
+            .filter_map(|cmd| match &*cmd {
+                Command::SetFillColor(color) | Command::SetStrokeColor(color) => color.process,
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert!(colors.contains(&ProcessColor::Cmyk([0.0, 1.0, 1.0, 0.0])));
+        let spots = colors
+            .iter()
+            .filter_map(|process| match process {
+                ProcessColor::Spot { name, tint, .. } => Some((name.as_str().to_string(), *tint)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert!(spots.contains(&("Brand Red".to_string(), 1.0)));
+        assert!(spots.contains(&("Brand Red".to_string(), 0.4)));
 
-        let mut seq_writer = Vec::new();
-        engine
-            .render_many_to_writer(&html_list, &css, &mut seq_writer)
-            .expect("render_many_to_writer");
-        assert_eq!(count_token(&seq_writer, b"/FontFile2"), 1);
+        let pdf = engine.render_to_buffer(html, css).expect("pdf");
+        assert_eq!(count_token(&pdf, b"/Separation /Brand#20Red"), 1);
+    }
 
-        let mut seq_css_writer = Vec::new();
-        engine
-            .render_many_to_writer_with_css(&jobs, &mut seq_css_writer)
-            .expect("render_many_to_writer_with_css");
-        assert_eq!(count_token(&seq_css_writer, b"/FontFile2"), 1);
+    #[test]
+    fn stream_render_matches_buffered_page_count() {
+        let mut html = String::from("<!doctype html><html><body>");
+        for idx in 0..120 {
+            html.push_str(&format!(
+                "<div class=\"row\"><p>Statement line {idx}</p><p>Detail {idx}</p></div>"
+            ));
+        }
+        html.push_str("<div style=\"break-before: page\"><p>Summary</p></div></body></html>");
+        let css = ".row { padding: 6pt; border-bottom: 1pt solid #999; }";
 
-        let mut parallel_writer = Vec::new();
+        let engine = FullBleed::builder()
+            .stream_chunk_bytes(512)
+            .build()
+            .expect("engine");
+        let buffered = engine.render_to_buffer(&html, css).expect("buffered");
+        let mut streamed = Vec::new();
         engine
-            .render_many_to_writer_parallel(&html_list, &css, &mut parallel_writer)
-            .expect("render_many_to_writer_parallel");
-        assert_eq!(count_token(&parallel_writer, b"/FontFile2"), 1);
+            .render_stream_to_writer(html.as_bytes(), css, &mut streamed)
+            .expect("streamed");
 
-        let tmp_dir = std::env::temp_dir();
-        let stamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map(|d| d.as_nanos())
-            .unwrap_or(0);
-        let seq_path = tmp_dir.join(format!(
-            "fullbleed_batch_font_dedup_seq_{}_{}.pdf",
-            std::process::id(),
-            stamp
-        ));
-        let parallel_path = tmp_dir.join(format!(
-            "fullbleed_batch_font_dedup_parallel_{}_{}.pdf",
-            std::process::id(),
-            stamp
-        ));
+        let buffered_pages = count_token(&buffered, b"/Type /Page /Parent");
+        assert!(buffered_pages > 2, "fixture should span several pages");
+        assert_eq!(
+            count_token(&streamed, b"/Type /Page /Parent"),
+            buffered_pages
+        );
 
-        engine
-            .render_many_to_file(&html_list, &css, &seq_path)
-            .expect("render_many_to_file");
-        engine
-            .render_many_to_file_parallel(&html_list, &css, &parallel_path)
-            .expect("render_many_to_file_parallel");
+        let engine = FullBleed::builder()
+            .watermark_text("DRAFT")
+            .build()
+            .expect("engine");
+        let err = engine
+            .render_stream_to_writer(html.as_bytes(), css, &mut Vec::new())
+            .expect_err("watermark is not supported in stream mode");
+        assert!(matches!(err, FullBleedError::InvalidConfiguration(_)));
+    }
 
-        let seq_file_bytes = std::fs::read(&seq_path).expect("read seq file");
-        let parallel_file_bytes = std::fs::read(&parallel_path).expect("read parallel file");
-        assert_eq!(count_token(&seq_file_bytes, b"/FontFile2"), 1);
-        assert_eq!(count_token(&parallel_file_bytes, b"/FontFile2"), 1);
+    #[test]
+    fn internal_links_resolve_to_anchor_pages() {
+        let html = "<p>Jump to <a href=\"#details\">the details</a> below.</p>\
+            <div style=\"break-before: page\"><h2 id=\"details\">Details</h2>\
+            <p><a href=\"#missing\">Nowhere</a></p></div>";
+        let engine = FullBleed::builder().build().expect("engine");
 
-        let _ = std::fs::remove_file(seq_path);
-        let _ = std::fs::remove_file(parallel_path);
+        let doc = engine.render_to_document(html, "").expect("document");
+        let link_rect = doc.pages[0]
+            .commands
+            .iter()
+            .find_map(|cmd| match &*cmd {
+                Command::LinkAnnotation {
+                    x, width, target, ..
+                } => {
+                    assert_eq!(target, &LinkTarget::Internal("details".to_string()));
+                    Some((*x, *width))
+                }
+                _ => None,
+            })
+            .expect("link annotation on first page");
+        assert!(link_rect.0 > Pt::ZERO, "link starts after the leading text");
+        assert!(doc.pages[1].commands.iter().any(|cmd| matches!(
+            &*cmd,
+            Command::NamedDestination { name, .. } if name == "details"
+        )));
+
+        let bytes = engine.render_to_buffer(html, "").expect("pdf");
+        let parsed = lopdf::Document::load_mem(&bytes).expect("load pdf");
+        let pages = parsed.get_pages();
+        let annots = |page_no: u32| {
+            let page = parsed.get_dictionary(pages[&page_no]).expect("page");
+            page.get(b"Annots")
+                .and_then(lopdf::Object::as_array)
+                .expect("annots")
+                .iter()
+                .map(|obj| {
+                    parsed
+                        .get_dictionary(obj.as_reference().expect("annot ref"))
+                        .expect("annot")
+                        .clone()
+                })
+                .collect::<Vec<_>>()
+        };
+        let first = annots(1);
+        assert_eq!(first.len(), 1);
+        let dest = first[0]
+            .get(b"Dest")
+            .and_then(lopdf::Object::as_array)
+            .expect("dest");
+        assert_eq!(dest[0].as_reference().expect("page ref"), pages[&2]);
+        let unresolved = annots(2);
+        assert_eq!(unresolved.len(), 1);
+        assert!(unresolved[0].get(b"Dest").is_err());
     }
 
     #[test]
-    fn watermark_ocg_semantics_wrap_commands() {
-        let mut spec = WatermarkSpec::text("CONFIDENTIAL");
-        spec.semantics = WatermarkSemantics::Ocg;
-        let resolver = style::StyleResolver::new("");
-        let commands = build_watermark_commands(
-            &spec,
-            Size::a4(),
-            1,
-            1,
-            None,
-            &resolver,
-            None,
-            None,
-            None,
-            false,
-            false,
+    fn data_fb_dest_exports_named_destinations_in_single_and_batch_output() {
+        let engine = FullBleed::builder().build().expect("engine");
+        let dests = |bytes: &[u8]| {
+            let parsed = lopdf::Document::load_mem(bytes).expect("load pdf");
+            let pages = parsed.get_pages();
+            let page_numbers = pages
+                .iter()
+                .map(|(number, id)| (*id, *number))
+                .collect::<std::collections::HashMap<_, _>>();
+            let names = parsed
+                .catalog()
+                .expect("catalog")
+                .get(b"Names")
+                .and_then(lopdf::Object::as_dict)
+                .expect("names")
+                .get(b"Dests")
+                .and_then(lopdf::Object::as_dict)
+                .expect("dests")
+                .get(b"Names")
+                .and_then(lopdf::Object::as_array)
+                .expect("dest names")
+                .clone();
+            names
+                .chunks(2)
+                .map(|pair| {
+                    let name = String::from_utf8_lossy(pair[0].as_str().unwrap()).into_owned();
+                    let dest = parsed
+                        .get_object(pair[1].as_reference().expect("dest ref"))
+                        .and_then(lopdf::Object::as_array)
+                        .expect("dest array");
+                    (name, page_numbers[&dest[0].as_reference().unwrap()])
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let html = "<section data-fb-dest=\"summary\"><p>Summary</p></section>\
+            <p style=\"break-before: page\">See <span data-fb-dest=\"fees\">fees</span>.</p>\
+            <p id=\"internal-only\">Not exported</p>";
+        let bytes = engine.render_to_buffer(html, "").expect("pdf");
+        assert_eq!(
+            dests(&bytes),
+            vec![("fees".to_string(), 2), ("summary".to_string(), 1)]
         );
-        assert!(matches!(
-            commands.first(),
-            Some(Command::BeginOptionalContent { name }) if name == WATERMARK_OCG_RESOURCE_NAME
-        ));
-        assert!(matches!(
-            commands.get(1),
-            Some(Command::BeginArtifact { subtype: Some(subtype) }) if subtype == "Watermark"
-        ));
-        assert!(matches!(
-            commands.iter().rev().nth(1),
-            Some(Command::EndMarkedContent)
-        ));
-        assert!(matches!(commands.last(), Some(Command::EndMarkedContent)));
-    }
 
-    #[test]
-    fn watermark_text_applies_to_each_page() {
-        let base = empty_document(3);
-        let spec = WatermarkSpec::text("CONFIDENTIAL");
-        let resolver = style::StyleResolver::new("");
-        let wm = build_watermark_document(
-            &base, &spec, &resolver, None, None, None, None, false, false,
+        let batch = vec![
+            "<h1 data-fb-dest=\"invoice-1\">One</h1><p data-fb-dest=\"shared\">a</p>".to_string(),
+            "<h1 data-fb-dest=\"invoice-2\">Two</h1><p data-fb-dest=\"shared\">b</p>".to_string(),
+        ];
+        let bytes = engine.render_many_to_buffer(&batch, "").expect("batch pdf");
+        assert_eq!(
+            dests(&bytes),
+            vec![
+                ("invoice-1".to_string(), 1),
+                ("invoice-2".to_string(), 2),
+                ("shared".to_string(), 1),
+            ]
         );
+    }
 
-        assert_eq!(wm.pages.len(), 3);
-        for page in &wm.pages {
-            assert!(page.commands.iter().any(
-                |cmd| matches!(cmd, Command::DrawString { text, .. } if text == "CONFIDENTIAL")
-            ));
+    #[test]
+    fn signature_field_placeholders_land_on_laid_out_boxes() {
+        let engine = FullBleed::builder().build().expect("engine");
+        let html = "<p>Terms</p>\
+            <div data-fb-signature-field=\"buyer\" style=\"height: 40pt; width: 180pt\">Sign here</div>\
+            <p style=\"break-before: page\">Countersigned</p>\
+            <div data-fb-signature-field=\"buyer\" style=\"height: 40pt; width: 180pt\">Sign here</div>";
+        let css = "@page { size: 4in 4in; margin: 0.5in; } body { margin: 0; }";
+        let bytes = engine.render_to_buffer(html, css).expect("pdf");
+        let report = inspect_pdf_bytes(&bytes).expect("inspect");
+
+        let fields = &report.signature_fields;
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields[0].name, "buyer");
+        assert_eq!(fields[1].name, "buyer_2", "field names stay unique");
+        assert_eq!(fields[0].page_number, Some(1));
+        assert_eq!(fields[1].page_number, Some(2));
+        for field in fields {
+            let [x0, y0, x1, y1] = field.rect;
+            assert!(!field.signed);
+            assert!((x0 - 36.0).abs() < 0.5, "widget starts at the left margin");
+            assert!((y1 - y0 - 40.0).abs() < 0.5, "widget covers the box height");
+            assert!(x1 > x0 && y0 > 36.0);
         }
+        assert_eq!(count_token(&bytes, b"/FT /Sig"), 2);
+        assert_eq!(count_token(&bytes, b"/AcroForm"), 1);
     }
 
     #[test]
-    fn watermark_image_applies_to_each_page() {
-        let base = empty_document(4);
-        let image_source = "examples/img/full_bleed-logo_small.png".to_string();
-        let spec = WatermarkSpec::image(image_source.clone());
-        let resolver = style::StyleResolver::new("");
-        let wm = build_watermark_document(
-            &base, &spec, &resolver, None, None, None, None, false, false,
-        );
+    fn render_to_layout_exposes_nested_boxes_with_pages_and_styles() {
+        let engine = FullBleed::builder().build().expect("engine");
+        let html = "<div id=\"card\" class=\"box\"><p id=\"title\">Hello</p></div>\
+                    <p style=\"break-before: page\">Second</p>";
+        let css = ".box { padding: 10pt; } #title { font-size: 18pt; }";
+        let tree = engine.render_to_layout(html, css).expect("layout");
+        assert_eq!(tree.page_count, 2);
+
+        let card = tree.find("div#card.box:nth-of-type(1)");
+        assert_eq!(card.len(), 1);
+        let card = card[0];
+        assert_eq!(card.tag, "div");
+        assert_eq!(card.classes, vec!["box".to_string()]);
+        assert_eq!(card.page_number, 1);
+        assert_eq!(card.children.len(), 1);
+        let title = &card.children[0];
+        assert_eq!(title.id.as_deref(), Some("title"));
+        assert!(title.rect.x >= card.rect.x + Pt::from_f32(10.0));
+        assert!(title.rect.y >= card.rect.y + Pt::from_f32(10.0));
+        assert!(title.rect.height > Pt::ZERO && title.rect.height < card.rect.height);
+        let style = |name: &str| {
+            title
+                .computed_style
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.clone())
+        };
+        assert_eq!(style("display").as_deref(), Some("Block"));
+        let font_size: f32 = style("font_size")
+            .expect("font_size")
+            .parse()
+            .expect("number");
+        assert!((font_size - 18.0).abs() < 0.01);
 
-        assert_eq!(wm.pages.len(), 4);
-        for page in &wm.pages {
-            assert!(page.commands.iter().any(|cmd| {
-                matches!(
-                    cmd,
-                    Command::DrawImage { resource_id, .. } if resource_id == &image_source
-                )
-            }));
-        }
+        let second = tree
+            .boxes()
+            .into_iter()
+            .find(|node| node.tag == "p" && node.id.is_none())
+            .expect("second paragraph");
+        assert_eq!(second.page_number, 2);
     }
 
     #[test]
-    fn watermark_text_uses_transform_compatible_y_coordinate() {
-        let spec = WatermarkSpec::text("WM");
-        let resolver = style::StyleResolver::new("");
-        let page_size = Size::a4();
-        let commands = build_watermark_commands(
-            &spec, page_size, 1, 1, None, &resolver, None, None, None, false, false,
+    fn layout_report_lists_overflowing_boxes_and_backs_overflow_audit() {
+        let engine = FullBleed::builder().build().expect("engine");
+        let html = "<html><body><div id=\"chart\"><svg width=\"200\" height=\"2000\">\
+                    <rect width=\"200\" height=\"2000\" fill=\"#ccc\"/></svg></div>\
+                    </body></html>";
+        let (pdf, report) = engine.render_with_layout_report(html, "").expect("render");
+        assert!(pdf.starts_with(b"%PDF"));
+        let overflow = report
+            .overflows()
+            .iter()
+            .find(|item| {
+                item.element_path
+                    .as_deref()
+                    .is_some_and(|path| path.contains("div#chart"))
+            })
+            .expect("chart overflow");
+        assert_eq!(overflow.page_number, 1);
+        assert_eq!(overflow.reason, "forced_unsplittable_full_frame");
+        assert!(overflow.overflow_y > Pt::ZERO);
+
+        let ctx = PmrCoreContext {
+            layout_report: Some(report.clone()),
+            ..PmrCoreContext::default()
+        };
+        let pmr = engine.verify_paged_media_rank_html_core(html, "strict", "error", &ctx);
+        let audit = pmr
+            .audits
+            .iter()
+            .find(|audit| audit.audit_id == "pmr.layout.overflow_none")
+            .expect("overflow audit");
+        assert_eq!(audit.verdict, "fail");
+        assert_eq!(audit.evidence.len(), 1 + report.overflows().len());
+        assert!(
+            audit.evidence[1..]
+                .iter()
+                .any(|evidence| evidence.selector.as_deref() == overflow.element_path.as_deref())
         );
 
-        let draw = commands.iter().find_map(|cmd| match cmd {
-            Command::DrawString { x, y, text } => Some((*x, *y, text.as_str())),
-            _ => None,
-        });
-        let (_x, y, text) = draw.expect("expected DrawString command");
-        assert_eq!(text, "WM");
-        assert_eq!(y, page_size.height - spec.font_size.mul_ratio(1, 2));
+        let (_pdf, report) = engine
+            .render_with_layout_report("<p>Fits</p>", "")
+            .expect("render");
+        assert!(report.is_empty());
     }
 
     #[test]
-    fn watermark_image_uses_transform_compatible_y_coordinate() {
-        let spec = WatermarkSpec::image("missing-watermark-image.png");
-        let resolver = style::StyleResolver::new("");
-        let page_size = Size::a4();
-        let commands = build_watermark_commands(
-            &spec, page_size, 1, 1, None, &resolver, None, None, None, false, false,
+    fn render_with_audit_gates_on_failed_rules_and_feeds_coverage() {
+        let engine = FullBleed::builder().build().expect("engine");
+        let css = "@page { size: 4in 4in; margin: 0.5in; }";
+        let clean = "<!doctype html><html lang=\"en\"><head><title>Statement</title></head>\
+            <body><main><h1>Statement</h1><p>Balance due.</p></main></body></html>";
+        let (pdf, report) = engine
+            .render_with_audit(clean, css, "strict")
+            .expect("render");
+        assert!(pdf.starts_with(b"%PDF-"));
+        assert_eq!(report.profile, "strict");
+        assert!(report.gate.ok, "{:?}", report.gate.failed_rule_ids);
+        assert!(
+            report
+                .findings
+                .iter()
+                .any(|f| f.rule_id == "fb.a11y.html.lang_present_valid" && f.verdict == "pass")
         );
+        assert!(report.wcag20aa.implemented_mapped_entry_evaluated_count > 0);
 
-        let draw = commands.iter().find_map(|cmd| match cmd {
-            Command::DrawImage { y, height, .. } => Some((*y, *height)),
-            _ => None,
-        });
-        let (y, height) = draw.expect("expected DrawImage command");
-        assert_eq!(y, page_size.height - height);
+        let missing_lang = clean.replace(" lang=\"en\"", "");
+        let (_, report) = engine
+            .render_with_audit(&missing_lang, css, "strict")
+            .expect("render");
+        assert!(!report.gate.ok);
+        assert!(
+            report
+                .gate
+                .failed_rule_ids
+                .contains(&"fb.a11y.html.lang_present_valid".to_string())
+        );
+        assert!(report.wcag20aa.implemented_mapped_result_counts.fail > 0);
     }
 
     #[test]
-    fn html_img_tag_emits_draw_image_command() {
-        let html = r#"
-            <!doctype html>
-            <html>
-              <body>
-                <img class="logo" src="examples/img/full_bleed-logo_small.png" alt="logo" />
-              </body>
-            </html>
-        "#;
-        let css = r#"
-            @page { size: 8.5in 11in; margin: 0.5in; }
-            .logo { width: 210px; height: 86px; }
-        "#;
-        let engine = FullBleed::builder().build().expect("engine");
-        let doc = engine
-            .render_to_document(html, css)
-            .expect("render document");
-        let mut found = false;
-        for page in &doc.pages {
-            if page.commands.iter().any(|cmd| {
-                matches!(cmd, Command::DrawImage { resource_id, .. } if resource_id == "examples/img/full_bleed-logo_small.png")
-            }) {
-                found = true;
-                break;
+    fn enforce_audit_gates_fails_render_with_failing_audit_ids() {
+        let css = "@page { size: 4in 4in; margin: 0.5in; }";
+        let html = "<!doctype html><html lang=\"en\"><head><title>Notice</title></head>\
+            <body><main><p>Due today.</p></main></body></html>";
+        let engine = FullBleed::builder()
+            .enforce_audit_gates("strict")
+            .build()
+            .expect("engine");
+        engine
+            .render_to_buffer(html, css)
+            .expect("conforming render");
+
+        let untitled = html.replace("<title>Notice</title>", "");
+        match engine.render_to_buffer(&untitled, css) {
+            Err(FullBleedError::AuditGateFailed { profile, audit_ids }) => {
+                assert_eq!(profile, "strict");
+                assert!(audit_ids.contains(&"pmr.doc.title_present_nonempty".to_string()));
             }
+            other => panic!(
+                "expected audit gate failure, got {:?}",
+                other.map(|b| b.len())
+            ),
         }
-        assert!(found, "expected <img> to emit DrawImage command");
+
+        let ungated = FullBleed::builder().build().expect("engine");
+        ungated.render_to_buffer(&untitled, css).expect("no gate");
     }
 
     #[test]
-    fn display_table_cells_share_a_single_row() {
-        let html = r#"
-            <!doctype html>
-            <html>
-              <body>
-                <div class="t"><span>AA</span><span>BB</span><span>CC</span></div>
-              </body>
-            </html>
-        "#;
-        let css = r#"
-            @page { size: 4in 4in; margin: 0.25in; }
-            body { margin: 0; font-size: 12px; line-height: 1.2; }
-            .t { display: table; width: 240px; border: 1px solid #000; }
-            .t > span { display: table-cell; padding: 2px 4px; border-right: 1px solid #000; }
-            .t > span:last-child { border-right: 0; }
-        "#;
+    fn artifact_bundle_writes_inputs_report_and_manifest() {
         let engine = FullBleed::builder().build().expect("engine");
-        let doc = engine
-            .render_to_document(html, css)
-            .expect("render document");
-        let page = doc.pages.first().expect("page");
+        let html = "<!doctype html><html lang=\"en\"><head><title>Bundle</title></head>\
+            <body><main><p>Audited.</p></main></body></html>";
+        let css = "@page { size: 4in 4in; margin: 0.5in; } p { color: #333; }";
+        let root = std::env::temp_dir().join(format!(
+            "fullbleed_artifact_bundle_{}_{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .expect("clock")
+                .as_nanos()
+        ));
 
-        let mut aa: Option<(Pt, Pt)> = None;
-        let mut bb: Option<(Pt, Pt)> = None;
-        let mut cc: Option<(Pt, Pt)> = None;
-        for cmd in &page.commands {
-            if let Command::DrawString { text, x, y } = cmd {
-                if text.contains("AA") {
-                    aa = Some((*x, *y));
-                } else if text.contains("BB") {
-                    bb = Some((*x, *y));
-                } else if text.contains("CC") {
-                    cc = Some((*x, *y));
-                }
-            }
-        }
-        let (aa_x, aa_y) = aa.expect("missing AA draw");
-        let (bb_x, bb_y) = bb.expect("missing BB draw");
-        let (cc_x, cc_y) = cc.expect("missing CC draw");
-        assert!((aa_y.to_f32() - bb_y.to_f32()).abs() < 1.0);
-        assert!((bb_y.to_f32() - cc_y.to_f32()).abs() < 1.0);
-        assert!(aa_x < bb_x && bb_x < cc_x);
+        let dir = root.join("bundle");
+        let (pdf, manifest) = engine
+            .render_with_artifact_bundle(
+                html,
+                css,
+                "strict",
+                &ArtifactBundleTarget::Directory(dir.clone()),
+            )
+            .expect("bundle");
+        assert!(manifest.gate_ok);
+        assert_eq!(
+            manifest.contract_fingerprint_sha256,
+            audit_contract::contract_fingerprint_sha256()
+        );
+        let names: Vec<_> = manifest.files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "document.pdf",
+                "input.html",
+                "merged.css",
+                "pmr_report.json"
+            ]
+        );
+        assert_eq!(std::fs::read(dir.join("document.pdf")).expect("pdf"), pdf);
+        assert_eq!(
+            std::fs::read_to_string(dir.join("input.html")).expect("html"),
+            html
+        );
+        assert!(
+            std::fs::read_to_string(dir.join("merged.css"))
+                .expect("css")
+                .contains("color: #333")
+        );
+        let report: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(dir.join("pmr_report.json")).expect("report"))
+                .expect("report json");
+        assert_eq!(report["schema"], "fullbleed.pmr.v1");
+        assert_eq!(report["gate"]["ok"], true);
+        let manifest_json: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(dir.join("manifest.json")).expect("manifest"))
+                .expect("manifest json");
+        assert_eq!(
+            manifest_json["files"][0]["sha256"],
+            encrypt::hex(&<sha2::Sha256 as sha2::Digest>::digest(&pdf)).as_str()
+        );
+
+        let zip = root.join("bundle.zip");
+        engine
+            .render_with_artifact_bundle(
+                html,
+                css,
+                "strict",
+                &ArtifactBundleTarget::Zip(zip.clone()),
+            )
+            .expect("zip bundle");
+        let archive = std::fs::read(&zip).expect("zip");
+        assert!(archive.starts_with(b"PK\x03\x04"));
+        assert_eq!(count_token(&archive, b"PK\x01\x02"), 5);
+        assert_eq!(count_token(&archive, b"manifest.json"), 2);
+        let _ = std::fs::remove_dir_all(&root);
     }
 
     #[test]
-    fn heading_honors_display_block_on_inline_descendants() {
-        let html = r#"
-            <!doctype html>
-            <html>
-              <body>
-                <h1 class="title">
-                  <span class="line">TITLELINE_TOP</span>
-                  <span class="line">TITLELINE_BOTTOM</span>
-                </h1>
-              </body>
-            </html>
-        "#;
-        let css = r#"
-            @page { size: 4in 4in; margin: 0.25in; }
-            body { margin: 0; font-family: sans-serif; }
-            .title { margin: 0; font-size: 28px; line-height: 1.1; }
-            .title > .line { display: block; }
-        "#;
+    fn footnotes_move_to_frame_bottom_and_split_across_pages() {
+        let css = "@page { size: 4in 4in; margin: 0.25in; } \
+            body { margin: 0; font-size: 12px; line-height: 1.2; } p { margin: 0; }";
         let engine = FullBleed::builder().build().expect("engine");
-        let doc = engine
-            .render_to_document(html, css)
-            .expect("render document");
-        let page = doc.pages.first().expect("page");
-
-        let mut top_y: Option<Pt> = None;
-        let mut bottom_y: Option<Pt> = None;
-        let mut merged_line = false;
-        for cmd in &page.commands {
-            if let Command::DrawString { text, y, .. } = cmd {
-                if text.contains("TITLELINE_TOP TITLELINE_BOTTOM")
-                    || text.contains("TITLELINE_BOTTOM TITLELINE_TOP")
-                {
-                    merged_line = true;
-                }
-                if text.contains("TITLELINE_TOP") {
-                    top_y = Some(*y);
-                }
-                if text.contains("TITLELINE_BOTTOM") {
-                    bottom_y = Some(*y);
-                }
-            }
-        }
+        let strings = |page: &Page| {
+            page.commands
+                .iter()
+                .filter_map(|cmd| match &*cmd {
+                    Command::DrawString { y, text, .. } => Some((*y, text.clone())),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let find = |strings: &[(Pt, String)], needle: &str| {
+            strings
+                .iter()
+                .find(|(_, text)| text.contains(needle))
+                .map(|(y, _)| *y)
+        };
 
+        let html = "<p>Alpha<span class=\"footnote\">First note.</span> text.</p>\
+            <p>Beta<span data-fb-footnote>Second note.</span></p>";
+        let doc = engine.render_to_document(html, css).expect("document");
+        assert_eq!(doc.pages.len(), 1);
+        let page = strings(&doc.pages[0]);
+        let call = page
+            .iter()
+            .find(|(_, text)| text == "Alpha[1] text.")
+            .map(|(y, _)| *y)
+            .expect("call mark without the footnote body");
+        let first = find(&page, "[1] First note.").expect("first footnote");
+        let second = find(&page, "[2] Second note.").expect("second footnote");
+        assert!(call < first && first < second);
         assert!(
-            !merged_line,
-            "display:block spans inside heading must not collapse to one line"
+            first > Pt::from_f32(200.0),
+            "footnotes sit at the frame bottom"
         );
-        let top_y = top_y.expect("expected top heading line draw command");
-        let bottom_y = bottom_y.expect("expected bottom heading line draw command");
-        assert!(
-            (top_y.to_f32() - bottom_y.to_f32()).abs() > 1.0,
-            "expected heading block lines at different y positions, got y={} and y={}",
-            top_y.to_f32(),
-            bottom_y.to_f32()
+
+        let words = (1..=60)
+            .map(|idx| format!("word{idx:02}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let html = format!(
+            "<div style=\"height: 210pt\"></div>\
+             <p>Ref<span class=\"footnote\">{words}</span></p>"
         );
+        let doc = engine.render_to_document(&html, css).expect("document");
+        assert_eq!(doc.pages.len(), 2);
+        let first_page = strings(&doc.pages[0]);
+        assert!(find(&first_page, "Ref[1]").is_some());
+        assert!(find(&first_page, "[1] word01").is_some());
+        assert!(find(&first_page, "word60").is_none());
+        assert!(find(&strings(&doc.pages[1]), "word60").is_some());
+    }
+
+    #[test]
+    fn column_count_balances_columns_and_continues_on_next_page() {
+        let css = "@page { size: 4in 4in; margin: 0.25in; } \
+            body { margin: 0; font-size: 12px; line-height: 1.2; } p { margin: 0; } \
+            .cols { column-count: 2; column-gap: 12pt; }";
+        let engine = FullBleed::builder().build().expect("engine");
+        let items = |count: usize| {
+            (1..=count)
+                .map(|idx| format!("<p>Item {idx:02}</p>"))
+                .collect::<String>()
+        };
+        let position = |page: &Page, needle: &str| {
+            page.commands
+                .iter()
+                .find_map(|cmd| match &*cmd {
+                    Command::DrawString { x, y, text } if text == needle => Some((*x, *y)),
+                    _ => None,
+                })
+                .unwrap_or_else(|| panic!("{needle} not drawn"))
+        };
+
+        let html = format!("<div class=\"cols\">{}</div><p>After</p>", items(6));
+        let doc = engine.render_to_document(&html, css).expect("document");
+        assert_eq!(doc.pages.len(), 1);
+        let page = &doc.pages[0];
+        let (left_x, top_y) = position(page, "Item 01");
+        assert_eq!(position(page, "Item 03").0, left_x);
+        let (right_x, right_y) = position(page, "Item 04");
+        assert_eq!(right_y, top_y, "balanced columns start level");
+        assert!((right_x - left_x - Pt::from_f32(132.0)).abs() < Pt::from_f32(0.1));
+        assert_eq!(position(page, "Item 06").0, right_x);
+        assert!(position(page, "After").1 > position(page, "Item 03").1);
+
+        let html = format!("<div class=\"cols\">{}</div>", items(60));
+        let doc = engine.render_to_document(&html, css).expect("document");
+        assert_eq!(doc.pages.len(), 2);
+        let (first_x, _) = position(&doc.pages[0], "Item 01");
+        let (last_x, _) = position(&doc.pages[1], "Item 60");
+        assert_eq!(position(&doc.pages[1], "Item 48").0, first_x);
         assert!(
-            bottom_y > top_y,
-            "expected second heading block line to render below first line"
+            last_x > first_x,
+            "continuation is balanced across both columns"
         );
     }
 
     #[test]
-    fn html_table_cell_block_children_preserve_vertical_flow() {
-        let html = r#"
-            <!doctype html>
-            <html>
-              <body>
-                <table class="t">
-                  <tr>
-                    <td>
-                      <div>TBLTOPMARK</div>
-                      <div>TBLBOTTOMMARK</div>
-                    </td>
-                  </tr>
-                </table>
-              </body>
-            </html>
-        "#;
-        let css = r#"
-            @page { size: 4in 4in; margin: 0.25in; }
-            body { margin: 0; font-size: 14px; line-height: 1.2; }
-            table.t { border-collapse: collapse; width: 220px; }
-            table.t td { border: 1px solid #000; padding: 2px; }
-            table.t td > div { display: block; }
-        "#;
+    fn float_left_and_right_wrap_following_text() {
+        let css = "@page { size: 4in 4in; margin: 0.25in; } \
+            body { margin: 0; font-size: 12px; line-height: 1.2; } p { margin: 0; } \
+            .box { width: 72pt; height: 36pt; }";
         let engine = FullBleed::builder().build().expect("engine");
-        let doc = engine
-            .render_to_document(html, css)
-            .expect("render document");
-        let page = doc.pages.first().expect("page");
-
-        let mut top_y: Option<Pt> = None;
-        let mut bottom_y: Option<Pt> = None;
-        let mut merged_line = false;
-        for cmd in &page.commands {
-            if let Command::DrawString { text, y, .. } = cmd {
-                if text.contains("TBLTOPMARK TBLBOTTOMMARK")
-                    || text.contains("TBLBOTTOMMARK TBLTOPMARK")
-                {
-                    merged_line = true;
-                }
-                if text.contains("TBLTOPMARK") {
-                    top_y = Some(*y);
-                }
-                if text.contains("TBLBOTTOMMARK") {
-                    bottom_y = Some(*y);
-                }
-            }
-        }
+        let text = "lorem ".repeat(80);
+        let lines = |side: &str| {
+            let html = format!(
+                "<div class=\"box\" style=\"float: {side}\">Box</div><p>{text}</p><p>After</p>"
+            );
+            let doc = engine.render_to_document(&html, css).expect("document");
+            assert_eq!(doc.pages.len(), 1);
+            doc.pages[0]
+                .commands
+                .iter()
+                .filter_map(|cmd| match &*cmd {
+                    Command::DrawString { x, y, text } => Some((text.clone(), *x, *y)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let near = |a: Pt, b: f32| (a - Pt::from_f32(b)).abs() < Pt::from_f32(0.1);
 
+        let left = lines("left");
+        let (_, box_x, box_y) = left.iter().find(|(t, _, _)| t == "Box").expect("box");
+        assert!(near(*box_x, 18.0));
+        let body: Vec<_> = left
+            .iter()
+            .filter(|(t, _, _)| t.starts_with("lorem"))
+            .collect();
+        assert!(body.len() > 5);
+        assert_eq!(body[0].2, *box_y, "first line sits beside the float");
+        assert!(near(body[0].1, 90.0));
+        assert!(near(body[3].1, 90.0), "36pt float covers four 10.8pt lines");
         assert!(
-            !merged_line,
-            "table cell block descendants should not collapse into one text line"
+            near(body[4].1, 18.0),
+            "lines below the float use the full width"
         );
-        let top_y = top_y.expect("expected top marker draw command");
-        let bottom_y = bottom_y.expect("expected bottom marker draw command");
+        let (_, after_x, after_y) = left.iter().find(|(t, _, _)| t == "After").expect("after");
+        assert!(near(*after_x, 18.0));
+        assert!(*after_y > body[body.len() - 1].2);
+
+        let right = lines("right");
+        let (_, box_x, _) = right.iter().find(|(t, _, _)| t == "Box").expect("box");
+        assert!(near(*box_x, 198.0));
+        let body: Vec<_> = right
+            .iter()
+            .filter(|(t, _, _)| t.starts_with("lorem"))
+            .collect();
+        assert!(body.iter().all(|(_, x, _)| near(*x, 18.0)));
         assert!(
-            (top_y.to_f32() - bottom_y.to_f32()).abs() > 1.0,
-            "expected block descendants at different y positions, got y={} and y={}",
-            top_y.to_f32(),
-            bottom_y.to_f32()
+            body[0].0.len() < body[4].0.len(),
+            "lines beside the float are shorter"
         );
-        assert!(
-            bottom_y > top_y,
-            "expected second block to render below first block"
+    }
+
+    #[test]
+    fn page_of_references_resolve_to_anchor_pages() {
+        let css = "@page { size: 6in 4in; margin: 0.25in; } body { margin: 0; } \
+            .next { break-before: page; }";
+        let html = "<p>Intro on page {page-of:#intro}, details on page {page-of: #details}.</p>\
+            <p>Missing {page-of:#nope}</p>\
+            <h2 id=\"intro\" class=\"next\">Intro</h2><p>Body</p>\
+            <h2 id=\"details\" class=\"next\">Details</h2>";
+        for strategy in [LayoutStrategy::Eager, LayoutStrategy::Lazy] {
+            let engine = FullBleed::builder()
+                .layout_strategy(strategy)
+                .accept_lazy_layout_cost(true)
+                .build()
+                .expect("engine");
+            let doc = engine.render_to_document(html, css).expect("document");
+            assert_eq!(doc.pages.len(), 3);
+            let text = doc.pages[0]
+                .commands
+                .iter()
+                .filter_map(|cmd| match &*cmd {
+                    Command::DrawString { text, .. } => Some(text.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            assert!(
+                text.contains("Intro on page 2, details on page 3."),
+                "{text}"
+            );
+            assert!(text.contains("Missing ?"), "{text}");
+            assert!(!text.contains("{page-of"), "{text}");
+        }
+    }
+
+    #[test]
+    fn leader_content_fills_gap_and_right_aligns_trailing_text() {
+        let css = "@page { size: 4in 4in; margin: 0.25in; } \
+            body { margin: 0; font-size: 12px; } p { margin: 0; } \
+            .toc::after { content: leader(dotted) \"42\"; } \
+            .price::after { content: leader(space) \"$12\"; }";
+        let html = "<p class=\"toc\">Introduction</p><p class=\"price\">Coffee</p>";
+        let engine = FullBleed::builder().build().expect("engine");
+        let doc = engine.render_to_document(html, css).expect("document");
+        assert_eq!(doc.pages.len(), 1);
+        let strings: Vec<(String, Pt)> = doc.pages[0]
+            .commands
+            .iter()
+            .filter_map(|cmd| match &*cmd {
+                Command::DrawString { x, text, .. } => Some((text.clone(), *x)),
+                _ => None,
+            })
+            .collect();
+        let x_of = |needle: &str| {
+            strings
+                .iter()
+                .find(|(text, _)| text == needle)
+                .map(|(_, x)| *x)
+                .unwrap_or_else(|| panic!("missing {needle}: {strings:?}"))
+        };
+        let right_edge = Pt::from_f32(270.0);
+        // "$", "1", "2" and "4" share one advance, so right-aligned "42" and "$12" are one
+        // advance apart.
+        let advance = x_of("42") - x_of("$12");
+        assert!(advance > Pt::ZERO, "{strings:?}");
+        assert!((x_of("42") + advance + advance - right_edge).abs() < Pt::from_f32(0.1));
+        assert!(x_of("Introduction") < x_of("42"));
+        let dots = strings
+            .iter()
+            .find(|(text, _)| text.starts_with(". ."))
+            .expect("dot leader");
+        assert!(dots.1 > x_of("Introduction") && dots.1 < x_of("42"));
+        assert!(!strings.iter().any(|(text, _)| text.contains('\t')));
+
+        assert!(x_of("$12") > x_of("Coffee"));
+        assert_eq!(
+            strings
+                .iter()
+                .filter(|(text, _)| text.trim().is_empty())
+                .count(),
+            0,
+            "a space leader draws nothing"
         );
     }
 
     #[test]
-    fn html_table_second_row_starts_after_multiline_first_row() {
-        let html = r#"
-            <!doctype html>
-            <html>
-              <body>
-                <table class="t">
-                  <tr>
-                    <td>
-                      <div>ROW1A</div>
-                      <div>ROW1B</div>
-                      <div>ROW1C</div>
-                    </td>
-                  </tr>
-                  <tr>
-                    <td>ROW2ONLY</td>
-                  </tr>
-                </table>
-              </body>
-            </html>
-        "#;
-        let css = r#"
-            @page { size: 4in 4in; margin: 0.25in; }
-            body { margin: 0; font-size: 14px; line-height: 1.2; }
-            table.t { border-collapse: collapse; width: 220px; }
-            table.t td { border: 1px solid #000; padding: 2px; }
-            table.t td > div { display: block; }
-        "#;
+    fn rtl_paragraphs_right_align_and_draw_runs_in_visual_order() {
+        let css = "@page { size: 4in 4in; margin: 0.25in; } \
+            body { margin: 0; font-size: 12px; } p { margin: 0; } \
+            .rtl { direction: rtl; }";
+        let html = "<p dir=\"rtl\">abc</p>\
+            <p class=\"rtl\">\u{5d0}\u{5d1}\u{5d2} (12)</p>\
+            <p dir=\"rtl\" style=\"text-align: left\">xyz</p>\
+            <p style=\"text-align: right\">abc</p>";
         let engine = FullBleed::builder().build().expect("engine");
-        let doc = engine
-            .render_to_document(html, css)
-            .expect("render document");
-        let page = doc.pages.first().expect("page");
+        let doc = engine.render_to_document(html, css).expect("document");
+        let strings: Vec<(String, Pt)> = doc.pages[0]
+            .commands
+            .iter()
+            .filter_map(|cmd| match &*cmd {
+                Command::DrawString { x, text, .. } => Some((text.clone(), *x)),
+                _ => None,
+            })
+            .collect();
+        let x_of = |needle: &str| {
+            strings
+                .iter()
+                .find(|(text, _)| text == needle)
+                .map(|(_, x)| *x)
+                .unwrap_or_else(|| panic!("missing {needle}: {strings:?}"))
+        };
 
-        let mut row1_last_y: Option<Pt> = None;
-        let mut row2_y: Option<Pt> = None;
-        for cmd in &page.commands {
-            if let Command::DrawString { text, y, .. } = cmd {
-                if text.contains("ROW1C") {
-                    row1_last_y = Some(*y);
-                }
-                if text.contains("ROW2ONLY") {
-                    row2_y = Some(*y);
-                }
-            }
-        }
+        // `text-align: start` resolves to the right edge in a right-to-left paragraph, where
+        // a left-to-right `text-align: right` paragraph puts the same text.
+        let abc: Vec<Pt> = strings
+            .iter()
+            .filter(|(text, _)| text == "abc")
+            .map(|(_, x)| *x)
+            .collect();
+        assert_eq!(abc.len(), 2, "{strings:?}");
+        assert!((abc[0] - abc[1]).abs() < Pt::from_f32(0.1), "{abc:?}");
+        assert!(abc[0] > Pt::from_f32(135.0), "{abc:?}");
+        assert!((x_of("xyz") - Pt::from_f32(18.0)).abs() < Pt::from_f32(0.1));
 
-        let row1_last_y = row1_last_y.expect("expected ROW1C draw command");
-        let row2_y = row2_y.expect("expected ROW2ONLY draw command");
-        assert!(
-            row2_y > row1_last_y,
-            "expected second row to render below first row content, got row1={} row2={}",
-            row1_last_y.to_f32(),
-            row2_y.to_f32()
-        );
+        // The number stays left-to-right between mirrored parentheses, left of the Hebrew word.
+        assert!(x_of("(") < x_of("12"));
+        assert!(x_of("12") < x_of(") \u{5d2}\u{5d1}\u{5d0}"));
     }
 
     #[test]
-    fn html_table_colspan_preserves_following_column_alignment() {
-        let html = r#"
-            <!doctype html>
-            <html>
-              <body>
+    fn css_counters_number_headings_and_list_markers() {
+        let css = "@page { size: 5in 5in; margin: 0.25in; } \
+            body { counter-reset: section; } \
+            h2 { counter-increment: section; } \
+            h2::before { content: \"Section \" counter(section, upper-roman) \": \"; } \
+            .alpha { list-style-type: lower-alpha; } \
+            .clauses > li::marker { content: counters(list-item, \".\") \" \"; }";
+        let html = "<h2>Scope</h2><h2>Terms</h2>\
+            <ol class=\"alpha\"><li>First</li><li>Second</li></ol>\
+            <ol class=\"clauses\"><li>One<ol class=\"clauses\"><li>Nested</li></ol></li>\
+            <li>Two</li></ol>";
+        let engine = FullBleed::builder().build().expect("engine");
+        let doc = engine.render_to_document(html, css).expect("document");
+        let strings: Vec<String> = doc
+            .pages
+            .iter()
+            .flat_map(|page| page.commands.iter())
+            .filter_map(|cmd| match &*cmd {
+                Command::DrawString { text, .. } => Some(text.trim().to_string()),
+                _ => None,
+            })
+            .collect();
+        let joined = strings.join("|");
+        assert!(joined.contains("Section I: Scope"), "{joined}");
+        assert!(joined.contains("Section II: Terms"), "{joined}");
+        for marker in ["a.", "b.", "1", "1.1", "2"] {
+            assert!(
+                strings.iter().any(|text| text == marker),
+                "missing marker {marker}: {joined}"
+            );
+        }
+        assert!(!strings.iter().any(|text| text == "1."), "{joined}");
+    }
+
+    #[test]
+    fn page_margin_boxes_render_counters_strings_and_running_elements() {
+        let css = "@page { size: 5in 5in; margin: 0.75in; \
+                @top-left { content: element(brand); } \
+                @top-center { content: string(chapter); } \
+                @bottom-right { content: \"Page \" counter(page) \" of \" counter(pages); } } \
+            h1 { string-set: chapter content(); } \
+            h1.next { break-before: page; } \
+            .brand { position: running(brand); }";
+        let html = "<div class=\"brand\">ACME</div><h1>Intro</h1><p>Body</p>\
+            <h1 class=\"next\">Methods</h1><p>More</p>";
+        let engine = FullBleed::builder().build().expect("engine");
+        let doc = engine.render_to_document(html, css).expect("document");
+        assert_eq!(doc.pages.len(), 2);
+        for (index, expected) in [
+            ["ACME", "Intro", "Page 1 of 2"],
+            ["ACME", "Methods", "Page 2 of 2"],
+        ]
+        .iter()
+        .enumerate()
+        {
+            let commands = &doc.pages[index].commands;
+            let boxes: Vec<String> = commands
+                .iter()
+                .filter_map(|cmd| match &*cmd {
+                    Command::DefineForm {
+                        resource_id,
+                        commands,
+                        ..
+                    } if resource_id.starts_with("mbox-") => Some(commands.clone()),
+                    _ => None,
+                })
+                .flatten()
+                .filter_map(|cmd| match cmd {
+                    Command::DrawString { text, .. } => Some(text.trim().to_string()),
+                    _ => None,
+                })
+                .collect();
+            let joined = boxes.join(" ");
+            for text in expected {
+                assert!(joined.contains(text), "page {index}: {joined}");
+            }
+            // The running element only appears in its margin box.
+            assert!(!commands.iter().any(
+                |cmd| matches!(&*cmd, Command::DrawString { text, .. } if text.contains("ACME"))
+            ));
+        }
+
+        // Streaming cannot know the page count or per-page values up front.
+        let err = engine
+            .render_stream_to_writer(html.as_bytes(), css, &mut Vec::new())
+            .expect_err("margin boxes while streaming");
+        assert!(matches!(err, FullBleedError::InvalidConfiguration(_)));
+    }
+
+    #[test]
+    fn named_page_sizes_and_merged_documents_keep_per_page_sizes() {
+        let css = "@page { size: A4; margin: 0.5in; } \
+            @page slip { size: 220mm 110mm; } \
+            .slip { page: slip; }";
+        let html = "<p>Statement</p><div class=\"slip\"><p>Remittance</p></div>";
+        let engine = FullBleed::builder().build().expect("engine");
+        let doc = engine.render_to_document(html, css).expect("document");
+        assert_eq!(doc.pages.len(), 2);
+        assert!(doc.pages[0].size.is_none());
+        let slip = doc.page_size_at(1);
+        assert!((slip.width.to_f32() - 623.622).abs() < 0.1, "{slip:?}");
+        assert!((slip.height.to_f32() - 311.811).abs() < 0.1, "{slip:?}");
+
+        let slips = Document {
+            page_size: slip,
+            pages: vec![Page {
+                commands: Vec::new().into(),
+                size: None,
+                rotate: 0,
+            }],
+        };
+        let merged = merge_documents(vec![doc.clone(), slips]).expect("merge");
+        assert_eq!(merged.page_size, doc.page_size);
+        assert_eq!(merged.page_size_at(1), slip);
+        assert_eq!(merged.page_size_at(2), slip);
+        let bytes = engine.render_to_buffer(html, css).expect("pdf");
+        let pdf = String::from_utf8_lossy(&bytes);
+        let media_boxes: std::collections::BTreeSet<&str> = pdf
+            .match_indices("/MediaBox [")
+            .filter_map(|(start, _)| pdf[start..].split(']').next())
+            .collect();
+        assert_eq!(media_boxes.len(), 2, "{media_boxes:?}");
+    }
+
+    #[test]
+    fn landscape_pages_rotate_when_requested() {
+        let css = "@page { size: 400pt 200pt landscape; margin: 20pt; }";
+        let html = "<p>Wide</p>";
+        let plain = FullBleed::builder().build().expect("engine");
+        let doc = plain.render_to_document(html, css).expect("document");
+        assert_eq!(doc.pages[0].rotate, 0);
+
+        let engine = FullBleed::builder()
+            .rotate_landscape_pages(true)
+            .build()
+            .expect("engine");
+        let doc = engine.render_to_document(html, css).expect("document");
+        assert_eq!(doc.pages[0].rotate, 90);
+        assert!(doc.page_size.width > doc.page_size.height);
+        let bytes = engine.render_to_buffer(html, css).expect("pdf");
+        let pdf = String::from_utf8_lossy(&bytes);
+        assert!(
+            pdf.contains("/MediaBox [0 0 200 400] /Rotate 90"),
+            "portrait media"
+        );
+
+        let engine = FullBleed::builder()
+            .page_rotate(-90)
+            .build()
+            .expect("engine");
+        let doc = engine.render_to_document(html, "").expect("document");
+        assert_eq!(doc.pages[0].rotate, 270);
+    }
+
+    #[test]
+    fn page_frames_fill_in_linked_order_with_column_breaks() {
+        let frame = |x: f32, width: f32| Rect {
+            x: Pt::from_f32(x),
+            y: Pt::ZERO,
+            width: Pt::from_f32(width),
+            height: Pt::from_f32(260.0),
+        };
+        // Declared left, sidebar, right; the link makes the story use the right column
+        // before the sidebar.
+        let engine = FullBleed::builder()
+            .page_size(Size {
+                width: Pt::from_f32(400.0),
+                height: Pt::from_f32(300.0),
+            })
+            .margins(Margins::all(20.0))
+            .page_frames(vec![
+                FrameSpec::named("left", frame(0.0, 110.0)).linked_to("right"),
+                FrameSpec::named("sidebar", frame(240.0, 120.0)),
+                FrameSpec::named("right", frame(120.0, 110.0)),
+            ])
+            .build()
+            .expect("engine");
+        let text_x = |doc: &Document, page: usize, needle: &str| {
+            doc.pages[page]
+                .commands
+                .iter()
+                .find_map(|cmd| match &*cmd {
+                    Command::DrawString { x, text, .. } if text.trim() == needle => {
+                        Some(x.to_f32())
+                    }
+                    _ => None,
+                })
+                .unwrap_or_else(|| panic!("{needle} missing on page {page}"))
+        };
+        let css = "body { margin: 0; } .c { break-after: column; }";
+        let html = "<p class=\"c\">A</p><p class=\"c\">B</p><p class=\"c\">C</p><p>D</p>";
+        let doc = engine.render_to_document(html, css).expect("document");
+        assert_eq!(doc.pages.len(), 2);
+        assert!((20.0..130.0).contains(&text_x(&doc, 0, "A")));
+        assert!((140.0..250.0).contains(&text_x(&doc, 0, "B")));
+        assert!((260.0..380.0).contains(&text_x(&doc, 0, "C")));
+        assert!((20.0..130.0).contains(&text_x(&doc, 1, "D")));
+
+        // Nested breaks split their container; a break in an empty frame is a no-op.
+        let html = "<p style=\"break-before: column\">E</p>\
+            <div><p>F</p><p style=\"break-before: column\">G</p></div>";
+        let doc = engine.render_to_document(html, css).expect("document");
+        assert_eq!(doc.pages.len(), 1);
+        assert!((20.0..130.0).contains(&text_x(&doc, 0, "E")));
+        assert!((20.0..130.0).contains(&text_x(&doc, 0, "F")));
+        assert!((140.0..250.0).contains(&text_x(&doc, 0, "G")));
+    }
+
+    #[derive(Clone)]
+    struct TickRuler {
+        ticks: usize,
+    }
+
+    impl TickRuler {
+        const STEP: f32 = 20.0;
+    }
+
+    impl Flowable for TickRuler {
+        fn wrap(&self, avail_width: Pt, _avail_height: Pt) -> Size {
+            Size {
+                width: avail_width,
+                height: Pt::from_f32(Self::STEP) * self.ticks as i32,
+            }
+        }
+
+        fn split(
+            &self,
+            _avail_width: Pt,
+            avail_height: Pt,
+        ) -> Option<(Box<dyn Flowable>, Box<dyn Flowable>)> {
+            let fits = (avail_height.to_f32() / Self::STEP) as usize;
+            if fits == 0 || fits >= self.ticks {
+                return None;
+            }
+            Some((
+                Box::new(TickRuler { ticks: fits }),
+                Box::new(TickRuler {
+                    ticks: self.ticks - fits,
+                }),
+            ))
+        }
+
+        fn draw(&self, canvas: &mut Canvas, x: Pt, y: Pt, avail_width: Pt, _avail_height: Pt) {
+            for tick in 0..self.ticks {
+                let tick_y = y + Pt::from_f32(Self::STEP) * tick as i32;
+                canvas.move_to(x, tick_y);
+                canvas.line_to(x + avail_width, tick_y);
+            }
+            canvas.stroke();
+        }
+    }
+
+    #[test]
+    fn custom_flowables_split_across_pages_from_rust_and_html() {
+        let page_size = Size {
+            width: Pt::from_f32(200.0),
+            height: Pt::from_f32(300.0),
+        };
+        let count_ticks = |doc: &Document| -> Vec<usize> {
+            doc.pages
+                .iter()
+                .map(|page| {
+                    page.commands
+                        .iter()
+                        .filter(|cmd| matches!(&**cmd, Command::LineTo { .. }))
+                        .count()
+                })
+                .collect()
+        };
+
+        let frame = Rect {
+            x: Pt::from_f32(20.0),
+            y: Pt::from_f32(20.0),
+            width: Pt::from_f32(160.0),
+            height: Pt::from_f32(260.0),
+        };
+        let mut doc = DocTemplate::new(vec![
+            PageTemplate::new("Page1", page_size).with_frame(frame),
+        ]);
+        doc.add_flowable(Box::new(TickRuler { ticks: 30 }));
+        let built = doc.build().expect("document");
+        assert_eq!(count_ticks(&built), vec![13, 13, 4]);
+
+        let engine = FullBleed::builder()
+            .page_size(page_size)
+            .margins(Margins::all(20.0))
+            .register_element("X-Ruler", |element| {
+                let ticks = element.attributes.get("ticks")?.parse().ok()?;
+                Some(Box::new(TickRuler { ticks }) as Box<dyn Flowable>)
+            })
+            .build()
+            .expect("engine");
+        let html = "<x-ruler ticks=\"20\"></x-ruler><x-ruler>plain</x-ruler>";
+        let doc = engine
+            .render_to_document(html, "body { margin: 0; }")
+            .expect("document");
+        assert_eq!(count_ticks(&doc), vec![13, 7]);
+        // Without a usable `ticks` attribute the factory declines and the element renders as
+        // ordinary inline content.
+        assert!(doc.pages[1].commands.iter().any(
+            |cmd| matches!(&*cmd, Command::DrawString { text, .. } if text.trim() == "plain")
+        ));
+    }
+
+    #[test]
+    fn page_labels_come_from_the_builder_spec_or_css_page_counters() {
+        let pages = |count: usize| -> String {
+            (0..count)
+                .map(|i| {
+                    format!(
+                        "<p style=\"break-before: {}\">Page {i}</p>",
+                        if i == 0 { "auto" } else { "page" }
+                    )
+                })
+                .collect()
+        };
+        let render = |engine: &FullBleed, html: &str, css: &str| -> String {
+            let pdf = engine.render_to_buffer(html, css).expect("pdf");
+            String::from_utf8_lossy(&pdf).into_owned()
+        };
+
+        let engine = FullBleed::builder()
+            .page_labels(
+                PageLabelSpec::new()
+                    .range(PageLabelRange::new(1, PageLabelStyle::LowerRoman))
+                    .range(PageLabelRange::new(3, PageLabelStyle::Decimal))
+                    .range(PageLabelRange::new(5, PageLabelStyle::Decimal).with_prefix("A-"))
+                    .range(PageLabelRange::new(9, PageLabelStyle::UpperAlpha)),
+            )
+            .build()
+            .expect("engine");
+        assert!(render(&engine, &pages(6), "").contains(
+            "/PageLabels << /Nums [0 << /S /r >> 2 << /S /D >> 4 << /S /D /P (A-) >>] >>"
+        ));
+
+        let engine = FullBleed::builder().build().expect("engine");
+        let css = "@page :first { @bottom-center { content: counter(page, upper-roman); } } \
+            @page { @bottom-center { content: counter(page); } }";
+        assert!(
+            render(&engine, &pages(3), css)
+                .contains("/PageLabels << /Nums [0 << /S /R >> 1 << /S /D /St 2 >>] >>")
+        );
+        // Decimal page counters match the default numbering, so no labels are written.
+        let css = "@page { @bottom-center { content: counter(page); } }";
+        assert!(!render(&engine, &pages(3), css).contains("/PageLabels"));
+    }
+
+    #[test]
+    fn batch_records_carry_metadata_labels_and_page_ranges() {
+        let engine = FullBleed::builder()
+            .page_size(Size {
+                width: Pt::from_f32(200.0),
+                height: Pt::from_f32(200.0),
+            })
+            .margins(Margins::all(10.0))
+            .build()
+            .expect("engine");
+        let html_list = vec![
+            "<p>First</p>".to_string(),
+            "<p>Second</p><p style=\"break-before: page\">Second, continued</p>".to_string(),
+            "<p>Third</p>".to_string(),
+        ];
+        let metadata = vec![
+            RecordMetadata {
+                title: Some("Account 1001".to_string()),
+                author: Some("Billing & Co".to_string()),
+                page_label_prefix: Some("A-".to_string()),
+                ..RecordMetadata::default()
+            },
+            RecordMetadata {
+                title: Some("Account 1002".to_string()),
+                page_label_prefix: Some("B-".to_string()),
+                ..RecordMetadata::default()
+            },
+        ];
+        let mut out = Vec::new();
+        let manifest = engine
+            .render_many_to_writer_with_metadata(&html_list, "", &metadata, &mut out)
+            .expect("render");
+        assert_eq!(manifest.bytes_written, out.len());
+        let ranges: Vec<(usize, usize, Option<&str>)> = manifest
+            .records
+            .iter()
+            .map(|r| (r.first_page, r.page_count, r.title.as_deref()))
+            .collect();
+        assert_eq!(
+            ranges,
+            vec![
+                (1, 1, Some("Account 1001")),
+                (2, 2, Some("Account 1002")),
+                (4, 1, None)
+            ]
+        );
+
+        let text = String::from_utf8_lossy(&out);
+        assert!(text.contains(
+            "/PageLabels << /Nums [0 << /S /D /P (A-) >> 1 << /S /D /P (B-) >> 3 << /S /D >>] >>"
+        ));
+        assert!(text.contains("<dc:creator><rdf:Seq><rdf:li>Billing &amp; Co</rdf:li>"));
+        let parsed = lopdf::Document::load_mem(&out).expect("load pdf");
+        let pages = parsed.get_pages();
+        let has_metadata = |page_no: u32| {
+            parsed
+                .get_dictionary(pages[&page_no])
+                .expect("page")
+                .has(b"Metadata")
+        };
+        assert_eq!(
+            (1..=4).map(has_metadata).collect::<Vec<_>>(),
+            vec![true, true, false, false]
+        );
+        let catalog = parsed.catalog().expect("catalog");
+        let outlines = parsed
+            .get_dictionary(
+                catalog
+                    .get(b"Outlines")
+                    .and_then(lopdf::Object::as_reference)
+                    .expect("outlines"),
+            )
+            .expect("outline root");
+        let first = parsed
+            .get_dictionary(
+                outlines
+                    .get(b"First")
+                    .and_then(lopdf::Object::as_reference)
+                    .expect("first"),
+            )
+            .expect("first item");
+        assert_eq!(
+            first
+                .get(b"Title")
+                .and_then(lopdf::Object::as_str)
+                .expect("title"),
+            b"Account 1001"
+        );
+
+        // Plain batches keep their old output apart from the manifest.
+        let mut plain = Vec::new();
+        engine
+            .render_many_to_writer(&html_list, "", &mut plain)
+            .expect("render");
+        assert!(!String::from_utf8_lossy(&plain).contains("/PageLabels"));
+    }
+
+    #[test]
+    fn doc_plans_round_trip_through_bytes_and_replay_elsewhere() {
+        let planner = FullBleed::builder()
+            .jit_mode(JitMode::PlanAndReplay)
+            .build()
+            .expect("engine");
+        let html = "<h1 id=\"top\" style=\"background: #eef\">Plan</h1>\
+            <p style=\"opacity: 0.5\">Shipped to the <a href=\"#top\">writer</a> fleet.</p>\
+            <p style=\"break-before: page\">Second page</p>";
+        let plan = planner.plan_document(html, "").expect("plan");
+        assert_eq!(plan.page_count, 2);
+
+        let bytes = plan.to_bytes();
+        let decoded = DocPlan::from_bytes(&bytes).expect("decode");
+        assert_eq!(format!("{:?}", decoded.pages), format!("{:?}", plan.pages));
+        assert_eq!(
+            format!("{:?}", decoded.paintables),
+            format!("{:?}", plan.paintables)
+        );
+        assert!(DocPlan::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(DocPlan::from_bytes(b"not a plan").is_err());
+
+        // The writer engine never sees the HTML; its output matches a direct render.
+        let writer = FullBleed::builder().build().expect("engine");
+        let replayed = writer.render_from_plan(&bytes, None).expect("pdf");
+        assert_eq!(replayed, planner.render_to_buffer(html, "").expect("pdf"));
+        assert!(writer.plan_document(html, "").is_err());
+    }
+
+    #[test]
+    fn compiled_templates_are_cached_and_render_like_the_source() {
+        let engine = FullBleed::builder().build().expect("engine");
+        let html = "<h1>Statement</h1><p>Balance due</p><ul><li>A</li><li>B</li></ul>";
+        let css = "h1 { color: #336699; } li { padding: 2pt; }";
+
+        let compiled = engine.compile(html, css);
+        assert!(compiled.has_story());
+        let again = engine.compile(html, css);
+        assert_eq!(compiled.key(), again.key());
+        assert!(Arc::ptr_eq(&compiled.inner, &again.inner));
+        assert_ne!(engine.compile(html, "").key(), compiled.key());
+
+        // A cached entry whose source differs (a hash collision) is not reused.
+        let other_key = compiled_template_key("<p>Other</p>", "");
+        engine
+            .compiled_templates
+            .lock()
+            .expect("cache")
+            .insert(other_key, compiled.clone());
+        let other = engine.compile("<p>Other</p>", "");
+        assert!(!Arc::ptr_eq(&compiled.inner, &other.inner));
+        assert_eq!(other.inner.html, "<p>Other</p>");
+
+        // The cache is bounded; the least recently compiled templates are compiled again.
+        for idx in 0..COMPILED_TEMPLATE_CACHE_CAPACITY {
+            engine.compile(&format!("<p>{idx}</p>"), "");
+        }
+        assert_eq!(
+            engine.compiled_templates.lock().expect("cache").len(),
+            COMPILED_TEMPLATE_CACHE_CAPACITY
+        );
+        assert!(!Arc::ptr_eq(
+            &compiled.inner,
+            &engine.compile(html, css).inner
+        ));
+
+        let expected = format!("{:?}", engine.render_to_document(html, css).expect("doc"));
+        let rendered: Vec<String> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..2)
+                .map(|_| {
+                    let compiled = compiled.clone();
+                    let engine = &engine;
+                    scope.spawn(move || {
+                        format!(
+                            "{:?}",
+                            engine.render_compiled_to_document(&compiled).expect("doc")
+                        )
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|h| h.join().expect("thread"))
+                .collect()
+        });
+        assert!(rendered.iter().all(|doc| *doc == expected));
+
+        // Templates hold the compiling engine's resolver and fonts; other engines refuse them.
+        let other_engine = FullBleed::builder().build().expect("engine");
+        for result in [
+            other_engine
+                .render_compiled_to_document(&compiled)
+                .map(|_| ()),
+            other_engine
+                .render_compiled_to_buffer(&compiled)
+                .map(|_| ()),
+            other_engine
+                .render_compiled_to_writer(&compiled, &mut Vec::new())
+                .map(|_| ()),
+        ] {
+            assert!(matches!(
+                result,
+                Err(FullBleedError::InvalidConfiguration(_))
+            ));
+        }
+
+        let with_refs = engine.compile("<p id=\"a\">See {page-of:#a}</p>", "");
+        assert!(!with_refs.has_story());
+        let pdf = engine.render_compiled_to_buffer(&with_refs).expect("pdf");
+        assert!(pdf.starts_with(b"%PDF"));
+    }
+
+    #[test]
+    fn render_many_with_data_renders_one_document_per_record() {
+        let engine = FullBleed::builder()
+            .page_size(Size {
+                width: Pt::from_f32(200.0),
+                height: Pt::from_f32(200.0),
+            })
+            .margins(Margins::all(10.0))
+            .build()
+            .expect("engine");
+        let template = "<h1>{{name}}</h1><p data-fb-each=\"lines\">{{this}}</p>";
+        let css = "body { margin: 0; } h1, p { margin: 0; font-size: 10pt; height: 40pt; }";
+        let lines = |count: usize| -> Vec<String> { (0..count).map(|i| i.to_string()).collect() };
+        let records = vec![
+            serde_json::json!({ "name": "Short", "lines": lines(1) }),
+            serde_json::json!({ "name": "Long", "lines": lines(8) }),
+            serde_json::json!({ "name": "Empty" }),
+        ];
+
+        // The parsed template is reused, so earlier records must not leak into later ones.
+        let parsed = data_binding::ParsedTemplate::parse(template);
+        for record in &records {
+            assert_eq!(parsed.bind(record), bind_template(template, record));
+        }
+
+        // Stories built from the bound DOM lay out like the bound HTML.
+        let context = engine.build_render_context(css, None);
+        for (idx, record) in records.iter().enumerate() {
+            let story = html::document_to_story_with_resolver_and_fonts(
+                &parsed.bind_document(record),
+                &context.resolver,
+                Some(engine.font_registry.clone()),
+                None,
+                false,
+                false,
+                None,
+                Some(idx),
+            );
+            let (from_dom, _) = engine
+                .render_to_document_and_page_data_with_story_at(
+                    idx,
+                    template,
+                    Some(&story),
+                    &context.page_templates,
+                    &context.resolver,
+                    None,
+                )
+                .expect("document");
+            let from_html = engine
+                .render_to_document(&parsed.bind(record), css)
+                .expect("document");
+            assert_eq!(format!("{from_dom:?}"), format!("{from_html:?}"));
+        }
+
+        let mut out = Vec::new();
+        engine
+            .render_many_with_data_to_writer(template, css, &records, &mut out)
+            .expect("render");
+        let parsed = lopdf::Document::load_mem(&out).expect("load pdf");
+        // 2 + 9 + 1 blocks of 40pt on a 180pt-high content area.
+        assert_eq!(parsed.get_pages().len(), 1 + 3 + 1);
+    }
+
+    #[test]
+    fn data_fb_frame_threads_blocks_into_named_frames() {
+        let frame = |x: f32, width: f32| Rect {
+            x: Pt::from_f32(x),
+            y: Pt::ZERO,
+            width: Pt::from_f32(width),
+            height: Pt::from_f32(260.0),
+        };
+        let engine = FullBleed::builder()
+            .page_size(Size {
+                width: Pt::from_f32(400.0),
+                height: Pt::from_f32(300.0),
+            })
+            .margins(Margins::all(20.0))
+            .page_frames(vec![
+                FrameSpec::named("main", frame(0.0, 240.0)),
+                FrameSpec::named("sidebar", frame(250.0, 110.0)),
+            ])
+            .build()
+            .expect("engine");
+        let css = "body { margin: 0; } .page { break-after: page; }";
+        let html = "<aside data-fb-frame=\"sidebar\" class=\"page\"><p>S1</p></aside>\
+            <p class=\"page\">M1</p>\
+            <aside data-fb-frame=\"sidebar\" class=\"page\"><p>S2</p></aside>\
+            <p data-fb-frame=\"nowhere\">M2</p>\
+            <aside data-fb-frame=\"sidebar\"><p>S3</p></aside>";
+        let doc = engine.render_to_document(html, css).expect("document");
+        let texts: Vec<Vec<(String, f32)>> = doc
+            .pages
+            .iter()
+            .map(|page| {
+                page.commands
+                    .iter()
+                    .filter_map(|cmd| match &*cmd {
+                        Command::DrawString { x, text, .. } => {
+                            Some((text.trim().to_string(), x.to_f32()))
+                        }
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .collect();
+        let names = |page: &[(String, f32)], lo: f32, hi: f32| -> Vec<String> {
+            page.iter()
+                .filter(|(_, x)| (lo..hi).contains(x))
+                .map(|(text, _)| text.clone())
+                .collect()
+        };
+        // One sidebar block per page; the main flow never uses the sidebar, and a target
+        // no template declares stays in the main flow. The thread outlives the main story.
+        assert_eq!(texts.len(), 3);
+        assert_eq!(names(&texts[0], 20.0, 260.0), vec!["M1"]);
+        assert_eq!(names(&texts[0], 270.0, 380.0), vec!["S1"]);
+        assert_eq!(names(&texts[1], 20.0, 260.0), vec!["M2"]);
+        assert_eq!(names(&texts[1], 270.0, 380.0), vec!["S2"]);
+        assert!(names(&texts[2], 20.0, 260.0).is_empty());
+        assert_eq!(names(&texts[2], 270.0, 380.0), vec!["S3"]);
+    }
+
+    #[test]
+    fn page_events_see_each_page_boundary_with_its_template() {
+        let css = "@page { size: 5in 5in; } @page wide { margin: 0.25in; } .wide { page: wide; }";
+        let html = "<p>Intro</p><section class=\"wide\"><p>Table</p></section><p>Back</p>";
+        let starts = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let ends = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let start_log = starts.clone();
+        let end_log = ends.clone();
+        let engine = FullBleed::builder()
+            .on_page_start(move |canvas, ctx| {
+                start_log
+                    .lock()
+                    .unwrap()
+                    .push((ctx.page_number, ctx.template_name.clone()));
+                canvas.set_fill_color(Color::rgb(0.9, 0.9, 0.9));
+                canvas.draw_rect(Pt::ZERO, Pt::ZERO, Pt::from_f32(20.0), Pt::from_f32(20.0));
+            })
+            .on_page_end(move |canvas, ctx| {
+                end_log
+                    .lock()
+                    .unwrap()
+                    .push((ctx.page_number, ctx.template_name.clone()));
+                canvas.draw_string(
+                    Pt::from_f32(10.0),
+                    Pt::from_f32(10.0),
+                    format!("stamp {}", ctx.page_number),
+                );
+            })
+            .build()
+            .expect("engine");
+        let doc = engine.render_to_document(html, css).expect("document");
+        assert_eq!(doc.pages.len(), 3);
+        let expected = vec![
+            (1, "Page1".to_string()),
+            (2, "wide".to_string()),
+            (3, "Page1".to_string()),
+        ];
+        assert_eq!(*starts.lock().unwrap(), expected);
+        assert_eq!(*ends.lock().unwrap(), expected);
+        for (index, page) in doc.pages.iter().enumerate() {
+            // The start hook paints under the content, the end hook over it.
+            assert!(matches!(
+                page.commands
+                    .iter()
+                    .find(|cmd| matches!(
+                        &**cmd,
+                        Command::DrawRect { .. } | Command::DrawString { .. }
+                    ))
+                    .as_deref(),
+                Some(Command::DrawRect { .. })
+            ));
+            let last_text = page.commands.iter().rev().find_map(|cmd| match &*cmd {
+                Command::DrawString { text, .. } => Some(text.clone()),
+                _ => None,
+            });
+            assert_eq!(last_text, Some(format!("stamp {}", index + 1)));
+        }
+    }
+
+    #[test]
+    fn named_pages_switch_templates_and_margin_boxes() {
+        let css = "@page { size: 5in 5in; margin: 0.75in; \
+                @top-center { content: \"Report\"; } } \
+            @page :first { margin-top: 1.5in; } \
+            @page wide { margin: 0.25in; @top-center { content: \"Appendix\"; } } \
+            .wide { page: wide; }";
+        let html = "<p>Intro</p><section class=\"wide\"><p>Table</p></section><p>Back</p>";
+        let engine = FullBleed::builder().build().expect("engine");
+        let doc = engine.render_to_document(html, css).expect("document");
+        assert_eq!(doc.pages.len(), 3);
+        let names: Vec<Option<String>> = collect_page_template_names(&doc, META_PAGE_TEMPLATE_KEY);
+        assert_eq!(
+            names,
+            vec![
+                Some("Page1".to_string()),
+                Some("wide".to_string()),
+                Some("Page2".to_string())
+            ]
+        );
+        for (index, expected) in ["Report", "Appendix", "Report"].iter().enumerate() {
+            let boxes: Vec<String> = doc.pages[index]
+                .commands
+                .iter()
+                .filter_map(|cmd| match &*cmd {
+                    Command::DefineForm {
+                        resource_id,
+                        commands,
+                        ..
+                    } if resource_id.starts_with("mbox-") => Some(commands.clone()),
+                    _ => None,
+                })
+                .flatten()
+                .filter_map(|cmd| match cmd {
+                    Command::DrawString { text, .. } => Some(text.trim().to_string()),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(boxes, vec![expected.to_string()], "page {index}");
+        }
+    }
+
+    #[test]
+    fn mirrored_margins_alternate_between_odd_and_even_pages() {
+        let html = "<p>One</p><p class=\"next\">Two</p><p class=\"next\">Three</p>";
+        let first_x = |doc: &Document| -> Vec<f32> {
+            doc.pages
+                .iter()
+                .filter_map(|page| {
+                    page.commands.iter().find_map(|cmd| match &*cmd {
+                        Command::DrawString { x, .. } => Some(x.to_f32()),
+                        _ => None,
+                    })
+                })
+                .collect()
+        };
+        let css = "@page { size: 5in 5in; } body { margin: 0; } p { margin: 0; } \
+            .next { break-before: page; }";
+        let engine = FullBleed::builder()
+            .margins(Margins::mirrored(72.0, 36.0, 36.0, 36.0))
+            .mirror_margins(true)
+            .build()
+            .expect("engine");
+        let doc = engine.render_to_document(html, css).expect("document");
+        assert_eq!(
+            collect_page_template_names(&doc, META_PAGE_TEMPLATE_KEY),
+            vec![
+                Some("Page:odd".to_string()),
+                Some("Page:even".to_string()),
+                Some("Page:odd".to_string())
+            ]
+        );
+        let xs = first_x(&doc);
+        assert_eq!(xs.len(), 3);
+        assert!((xs[0] - 72.0).abs() < 0.01, "{xs:?}");
+        assert!((xs[1] - 36.0).abs() < 0.01, "{xs:?}");
+        assert!((xs[2] - 72.0).abs() < 0.01, "{xs:?}");
+
+        let css = "@page { size: 5in 5in; margin: 36pt; } @page :first { margin-top: 72pt; } \
+            @page :left { margin-left: 90pt; } body { margin: 0; } p { margin: 0; } \
+            .next { break-before: page; }";
+        let engine = FullBleed::builder().build().expect("engine");
+        let doc = engine.render_to_document(html, css).expect("document");
+        assert_eq!(
+            collect_page_template_names(&doc, META_PAGE_TEMPLATE_KEY),
+            vec![
+                Some("Page1".to_string()),
+                Some("Page:even".to_string()),
+                Some("Page:odd".to_string())
+            ]
+        );
+        let xs = first_x(&doc);
+        assert!((xs[0] - 36.0).abs() < 0.01, "{xs:?}");
+        assert!((xs[1] - 90.0).abs() < 0.01, "{xs:?}");
+        assert!((xs[2] - 36.0).abs() < 0.01, "{xs:?}");
+    }
+
+    #[test]
+    fn headings_keep_with_next_and_avoid_blocks_stay_together() {
+        let engine = FullBleed::builder().build().expect("engine");
+        let css = "@page { size: 300pt 200pt; margin: 20pt; } \
+            body { margin: 0; font-size: 12pt; } p, h2, h3 { margin: 0; } \
+            .filler { height: 130pt; } .tall { height: 140pt; }";
+        let pages_of = |html: &str, css: &str, texts: &[&str]| -> Vec<usize> {
+            let doc = engine.render_to_document(html, css).expect("document");
+            texts
+                .iter()
+                .map(|text| {
+                    doc.pages
+                        .iter()
+                        .position(|page| {
+                            page.commands.iter().any(|cmd| {
+                                matches!(&*cmd, Command::DrawString { text: drawn, .. }
+                                    if drawn.trim() == *text)
+                            })
+                        })
+                        .unwrap_or_else(|| panic!("missing {text}"))
+                })
+                .collect()
+        };
+
+        let html = "<div class=\"filler\"></div><h2>Heading</h2><p>Body</p>";
+        assert_eq!(pages_of(html, css, &["Heading", "Body"]), vec![1, 1]);
+        let loose = format!("{css} h2 {{ break-after: auto; }}");
+        assert_eq!(pages_of(html, &loose, &["Heading", "Body"]), vec![0, 1]);
+
+        let html = "<section><div class=\"filler\"></div><h3>Sub</h3><p>Text</p></section>";
+        assert_eq!(pages_of(html, css, &["Sub", "Text"]), vec![1, 1]);
+
+        let html = "<section><div class=\"tall\"></div>\
+            <div style=\"break-inside: avoid\"><p>First</p><p>Second</p></div></section>";
+        assert_eq!(pages_of(html, css, &["First", "Second"]), vec![1, 1]);
+        let html = "<div class=\"tall\"></div><section>\
+            <div style=\"break-inside: avoid\"><p>Lead</p><p>Tail</p></div><p>After</p></section>";
+        assert_eq!(pages_of(html, css, &["Lead", "Tail"]), vec![1, 1]);
+    }
+
+    #[test]
+    fn table_rows_and_list_items_keep_minimums_at_breaks() {
+        let engine = FullBleed::builder().build().expect("engine");
+        let page_of = |html: &str, css: &str, text: &str| -> usize {
+            let doc = engine.render_to_document(html, css).expect("document");
+            doc.pages
+                .iter()
+                .position(|page| {
+                    page.commands.iter().any(|cmd| {
+                        matches!(&*cmd, Command::DrawString { text: drawn, .. }
+                            if drawn.trim() == text)
+                    })
+                })
+                .unwrap_or_else(|| panic!("missing {text}"))
+        };
+        let css = "@page { size: 300pt 200pt; margin: 20pt; } \
+            body { margin: 0; font-size: 12pt; } table { break-inside: auto; } \
+            td, th { padding: 0; } tr { height: 20pt; } \
+            li { line-height: 20pt; } ul { margin: 0; padding: 0; }";
+        let table = |filler: u32, attrs: &str, rows: usize| -> String {
+            let body: String = (1..=rows)
+                .map(|row| format!("<tr><td>R{row}</td></tr>"))
+                .collect();
+            format!(
+                "<div style=\"height: {filler}pt\"></div>\
+                <table><thead><tr><th>Head</th></tr></thead><tbody {attrs}>{body}</tbody></table>"
+            )
+        };
+
+        assert_eq!(page_of(&table(95, "", 6), css, "R1"), 0);
+        let kept = table(95, "data-fb-min-rows=\"3\"", 6);
+        assert_eq!(page_of(&kept, css, "R1"), 1);
+
+        assert_eq!(page_of(&table(15, "", 7), css, "R6"), 0);
+        let widows = format!("{css} tbody {{ widows: 2; }}");
+        assert_eq!(page_of(&table(15, "", 7), &widows, "R6"), 1);
+        assert_eq!(page_of(&table(15, "", 7), &widows, "R5"), 0);
+
+        let list = "<div style=\"height: 95pt\"></div>\
+            <ul><li>I1</li><li>I2</li><li>I3</li><li>I4</li><li>I5</li></ul>";
+        assert_eq!(page_of(list, css, "I3"), 0);
+        let list_css = format!("{css} ul {{ orphans: 1; widows: 3; }}");
+        assert_eq!(page_of(list, &list_css, "I2"), 0);
+        assert_eq!(page_of(list, &list_css, "I3"), 1);
+    }
+
+    #[test]
+    fn ordered_list_attributes_set_numbers_and_styles() {
+        let engine = FullBleed::builder().build().expect("engine");
+        let drawn = |html: &str, css: &str| -> Vec<String> {
+            let doc = engine.render_to_document(html, css).expect("document");
+            doc.pages
+                .iter()
+                .flat_map(|page| page.commands.iter())
+                .filter_map(|cmd| match &*cmd {
+                    Command::DrawString { text, .. } => Some(text.trim().to_string()),
+                    _ => None,
+                })
+                .collect()
+        };
+        let html = "<ol start=\"5\"><li>Five</li><li value=\"10\">Ten</li><li>Eleven</li></ol>\
+            <ol type=\"a\"><li>Alpha</li><li>Beta</li></ol>\
+            <ol type=\"I\"><li>Roman</li></ol>";
+        // The plain list path and the counter pass (any counter rule) number the same way.
+        for css in ["", "h6 { counter-increment: unused; }"] {
+            let strings = drawn(html, css);
+            for marker in ["5.", "10.", "11.", "a.", "b.", "I."] {
+                assert!(
+                    strings.iter().any(|text| text == marker),
+                    "missing marker {marker} with css {css:?}: {strings:?}"
+                );
+            }
+            assert!(!strings.iter().any(|text| text == "6."), "{strings:?}");
+        }
+
+        let nested = "<ol start=\"3\"><li>Scope<ol><li>Terms<ol><li>Notice</li></ol></li>\
+            <li>Fees</li></ol></li></ol>";
+        let strings = drawn(
+            nested,
+            "li::marker { content: counters(list-item, \".\") \" \"; }",
+        );
+        for marker in ["3", "3.1", "3.1.1", "3.2"] {
+            assert!(
+                strings.iter().any(|text| text == marker),
+                "missing marker {marker}: {strings:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn vertical_rl_stacks_cjk_upright_and_turns_latin_sideways() {
+        let css = "@page { size: 4in 4in; margin: 0.25in; } body { margin: 0; } \
+            p { margin: 0; font-size: 9pt; line-height: 12pt; writing-mode: vertical-rl; } \
+            .short { height: 45pt; }";
+        let html = "<p class=\"short\">\u{65e5}\u{672c}\u{8a9e}\u{3067}\u{3059} abc</p>";
+        let engine = FullBleed::builder().build().expect("engine");
+        let doc = engine.render_to_document(html, css).expect("document");
+        let commands = &doc.pages[0].commands;
+        let strings: Vec<(String, Pt, Pt)> = commands
+            .iter()
+            .filter_map(|cmd| match &*cmd {
+                Command::DrawString { x, y, text } => Some((text.clone(), *x, *y)),
+                _ => None,
+            })
+            .collect();
+        let at = |needle: &str| {
+            strings
+                .iter()
+                .find(|(text, _, _)| text == needle)
+                .map(|(_, x, y)| (*x, *y))
+                .unwrap_or_else(|| panic!("missing {needle}: {strings:?}"))
+        };
+
+        // The ideographs stack one em apart in the first (rightmost) column.
+        let (first_x, first_y) = at("\u{65e5}");
+        let (second_x, second_y) = at("\u{672c}");
+        assert!((first_x - second_x).abs() < Pt::from_f32(0.1));
+        assert!((second_y - first_y - Pt::from_f32(9.0)).abs() < Pt::from_f32(0.1));
+
+        // The Latin word wraps to the next column to the left and is drawn a quarter turn
+        // clockwise.
+        let (latin_x, _) = at("abc");
+        assert!(latin_x < first_x);
+        let turn = commands.iter().position(|cmd| {
+            matches!(
+                &*cmd,
+                Command::ConcatMatrix { a, b, c, d, .. }
+                    if *a == 0.0 && *b == -1.0 && *c == 1.0 && *d == 0.0
+            )
+        });
+        let latin = commands
+            .iter()
+            .position(|cmd| matches!(&*cmd, Command::DrawString { text, .. } if text == "abc"));
+        assert!(turn.expect("rotation") < latin.expect("latin run"));
+
+        // Without a height, columns run the page frame height and extra bands paginate.
+        let long = format!("<p>{}</p>", "\u{65e5}\u{672c}\u{8a9e}".repeat(250));
+        let doc = engine.render_to_document(&long, css).expect("document");
+        assert_eq!(doc.pages.len(), 2);
+    }
+
+    #[test]
+    fn fixed_elements_repeat_on_every_page_as_one_form_xobject() {
+        let css = "@page { size: 4in 4in; margin: 0.5in; } body { margin: 0; } \
+            .letterhead { position: fixed; top: 6pt; left: 6pt; color: #aa0000; } \
+            .next { break-before: page; }";
+        let html = "<div class=\"letterhead\">LETTERHEAD</div>\
+            <p>One</p><p class=\"next\">Two</p><p class=\"next\">Three</p>";
+        let engine = FullBleed::builder().build().expect("engine");
+        let doc = engine.render_to_document(html, css).expect("document");
+        assert_eq!(doc.pages.len(), 3);
+
+        let mut form_ids = Vec::new();
+        for page in &doc.pages {
+            let draws: Vec<Arc<str>> = page
+                .commands
+                .iter()
+                .filter_map(|cmd| match &*cmd {
+                    Command::DrawForm { resource_id, .. } => Some(resource_id.clone()),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(draws.len(), 1, "one form placement per page");
+            form_ids.push(draws[0].clone());
+            assert!(!page.commands.iter().any(
+                |cmd| matches!(&*cmd, Command::DrawString { text, .. } if text == "LETTERHEAD")
+            ));
+            let form_text = page.commands.iter().any(|cmd| match &*cmd {
+                Command::DefineForm { commands, .. } => commands.iter().any(
+                    |inner| matches!(&*inner, Command::DrawString { text, .. } if text == "LETTERHEAD"),
+                ),
+                _ => false,
+            });
+            assert!(form_text, "overlay content lives in the form");
+        }
+        assert!(form_ids.iter().all(|id| id == &form_ids[0]));
+
+        let pdf = engine.render_to_buffer(html, css).expect("pdf");
+        let text = String::from_utf8_lossy(&pdf);
+        assert_eq!(text.matches("/Subtype /Form").count(), 1);
+    }
+
+    #[test]
+    fn outline_from_headings_nests_levels_and_targets_pages() {
+        let html = "<h1>Summary</h1><h2>Totals</h2><h3>Ignored</h3><h2>Fees</h2>\
+            <h1 style=\"break-before: page\">Appendix</h1>";
+        let engine = FullBleed::builder()
+            .outline_from_headings(2)
+            .build()
+            .expect("engine");
+        let bytes = engine.render_to_buffer(html, "").expect("pdf");
+        let parsed = lopdf::Document::load_mem(&bytes).expect("load pdf");
+        let pages = parsed.get_pages();
+        let dict = |obj: &lopdf::Object| {
+            parsed
+                .get_dictionary(obj.as_reference().expect("ref"))
+                .expect("dict")
+        };
+        let title = |item: &lopdf::Dictionary| {
+            String::from_utf8_lossy(item.get(b"Title").unwrap().as_str().unwrap()).into_owned()
+        };
+
+        let catalog = parsed.catalog().expect("catalog");
+        assert_eq!(
+            catalog.get(b"PageMode").unwrap().as_name().unwrap(),
+            b"UseOutlines"
+        );
+        let root = dict(catalog.get(b"Outlines").unwrap());
+        assert_eq!(root.get(b"Count").unwrap().as_i64().unwrap(), 4);
+
+        let summary = dict(root.get(b"First").unwrap());
+        assert_eq!(title(summary), "Summary");
+        assert_eq!(summary.get(b"Count").unwrap().as_i64().unwrap(), 2);
+        let totals = dict(summary.get(b"First").unwrap());
+        assert_eq!(title(totals), "Totals");
+        assert_eq!(title(dict(totals.get(b"Next").unwrap())), "Fees");
+
+        let appendix = dict(root.get(b"Last").unwrap());
+        assert_eq!(title(appendix), "Appendix");
+        let dest = appendix.get(b"Dest").unwrap().as_array().unwrap();
+        assert_eq!(dest[0].as_reference().unwrap(), pages[&2]);
+
+        let plain = FullBleed::builder()
+            .build()
+            .expect("engine")
+            .render_to_buffer(html, "")
+            .expect("pdf");
+        assert_eq!(count_token(&plain, b"/Outlines"), 0);
+    }
+
+    #[test]
+    fn lazy_layout_requires_explicit_cost_acceptance() {
+        let err = match FullBleed::builder()
+            .layout_strategy(LayoutStrategy::Lazy)
+            .build()
+        {
+            Ok(_) => panic!("lazy layout should require explicit opt-in"),
+            Err(err) => err,
+        };
+        assert!(matches!(err, FullBleedError::InvalidConfiguration(_)));
+        assert!(err.to_string().contains("accept_lazy_layout_cost"));
+    }
+
+    #[test]
+    fn lazy_layout_limits_are_validated() {
+        let err = match FullBleed::builder()
+            .layout_strategy(LayoutStrategy::Lazy)
+            .accept_lazy_layout_cost(true)
+            .lazy_layout_limits(1, 50.0)
+            .build()
+        {
+            Ok(_) => panic!("lazy max passes must be >= 2"),
+            Err(err) => err,
+        };
+        assert!(matches!(err, FullBleedError::InvalidConfiguration(_)));
+        assert!(err.to_string().contains("lazy_max_passes"));
+
+        let err = match FullBleed::builder()
+            .layout_strategy(LayoutStrategy::Lazy)
+            .accept_lazy_layout_cost(true)
+            .lazy_layout_limits(4, 0.0)
+            .build()
+        {
+            Ok(_) => panic!("lazy budget must be positive"),
+            Err(err) => err,
+        };
+        assert!(matches!(err, FullBleedError::InvalidConfiguration(_)));
+        assert!(err.to_string().contains("lazy_budget_ms"));
+    }
+
+    #[test]
+    fn lazy_layout_configuration_builds_with_opt_in() {
+        FullBleed::builder()
+            .layout_strategy(LayoutStrategy::Lazy)
+            .accept_lazy_layout_cost(true)
+            .lazy_layout_limits(4, 50.0)
+            .build()
+            .expect("valid lazy config should build");
+    }
+
+    #[test]
+    fn batch_writer_file_and_parallel_paths_dedupe_embedded_fonts() {
+        let inter_path = repo_font_path("Inter-Variable.ttf");
+        let inter_bytes = std::fs::read(&inter_path).expect("read inter");
+
+        let mut engine = FullBleed::builder().build().expect("engine");
+        let font_name = {
+            let registry = Arc::get_mut(&mut engine.font_registry).expect("unique registry");
+            registry
+                .register_bytes(inter_bytes, Some(inter_path.to_string_lossy().as_ref()))
+                .expect("register inter")
+        };
+
+        let css = format!(
+            "@page {{ size: 8.5in 11in; margin: 0.5in; }} body {{ margin: 0; font-family: '{}'; font-size: 12pt; }}",
+            font_name
+        );
+        let html_list = vec![
+            "<html><body><p>Record 1</p></body></html>".to_string(),
+            "<html><body><p>Record 2</p></body></html>".to_string(),
+            "<html><body><p>Record 3</p></body></html>".to_string(),
+        ];
+        let jobs: Vec<(String, String)> = html_list
+            .iter()
+            .map(|html| (html.clone(), css.clone()))
+            .collect();
+
+        let mut seq_writer = Vec::new();
+        engine
+            .render_many_to_writer(&html_list, &css, &mut seq_writer)
+            .expect("render_many_to_writer");
+        assert_eq!(count_token(&seq_writer, b"/FontFile2"), 1);
+
+        let mut seq_css_writer = Vec::new();
+        engine
+            .render_many_to_writer_with_css(&jobs, &mut seq_css_writer)
+            .expect("render_many_to_writer_with_css");
+        assert_eq!(count_token(&seq_css_writer, b"/FontFile2"), 1);
+
+        let mut parallel_writer = Vec::new();
+        engine
+            .render_many_to_writer_parallel(&html_list, &css, &mut parallel_writer)
+            .expect("render_many_to_writer_parallel");
+        assert_eq!(count_token(&parallel_writer, b"/FontFile2"), 1);
+
+        let tmp_dir = std::env::temp_dir();
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let seq_path = tmp_dir.join(format!(
+            "fullbleed_batch_font_dedup_seq_{}_{}.pdf",
+            std::process::id(),
+            stamp
+        ));
+        let parallel_path = tmp_dir.join(format!(
+            "fullbleed_batch_font_dedup_parallel_{}_{}.pdf",
+            std::process::id(),
+            stamp
+        ));
+
+        engine
+            .render_many_to_file(&html_list, &css, &seq_path)
+            .expect("render_many_to_file");
+        engine
+            .render_many_to_file_parallel(&html_list, &css, &parallel_path)
+            .expect("render_many_to_file_parallel");
+
+        let seq_file_bytes = std::fs::read(&seq_path).expect("read seq file");
+        let parallel_file_bytes = std::fs::read(&parallel_path).expect("read parallel file");
+        assert_eq!(count_token(&seq_file_bytes, b"/FontFile2"), 1);
+        assert_eq!(count_token(&parallel_file_bytes, b"/FontFile2"), 1);
+
+        let _ = std::fs::remove_file(seq_path);
+        let _ = std::fs::remove_file(parallel_path);
+    }
+
+    #[test]
+    fn watermark_ocg_semantics_wrap_commands() {
+        let mut spec = WatermarkSpec::text("CONFIDENTIAL");
+        spec.semantics = WatermarkSemantics::Ocg;
+        let resolver = style::StyleResolver::new("");
+        let commands = build_watermark_commands(
+            &spec,
+            Size::a4(),
+            1,
+            1,
+            None,
+            &resolver,
+            None,
+            None,
+            None,
+            false,
+            false,
+        );
+        assert!(matches!(
+            commands.first(),
+            Some(Command::BeginOptionalContent { name }) if name == WATERMARK_OCG_RESOURCE_NAME
+        ));
+        assert!(matches!(
+            commands.get(1),
+            Some(Command::BeginArtifact { subtype: Some(subtype) }) if subtype == "Watermark"
+        ));
+        assert!(matches!(
+            commands.iter().rev().nth(1),
+            Some(Command::EndMarkedContent)
+        ));
+        assert!(matches!(commands.last(), Some(Command::EndMarkedContent)));
+    }
+
+    #[test]
+    fn watermark_text_applies_to_each_page() {
+        let base = empty_document(3);
+        let spec = WatermarkSpec::text("CONFIDENTIAL");
+        let resolver = style::StyleResolver::new("");
+        let wm = build_watermark_document(
+            &base, &spec, &resolver, None, None, None, None, false, false,
+        );
+
+        assert_eq!(wm.pages.len(), 3);
+        for page in &wm.pages {
+            assert!(page.commands.iter().any(
+                |cmd| matches!(&*cmd, Command::DrawString { text, .. } if text == "CONFIDENTIAL")
+            ));
+        }
+    }
+
+    #[test]
+    fn watermark_image_applies_to_each_page() {
+        let base = empty_document(4);
+        let image_source = "examples/img/full_bleed-logo_small.png".to_string();
+        let spec = WatermarkSpec::image(image_source.clone());
+        let resolver = style::StyleResolver::new("");
+        let wm = build_watermark_document(
+            &base, &spec, &resolver, None, None, None, None, false, false,
+        );
+
+        assert_eq!(wm.pages.len(), 4);
+        for page in &wm.pages {
+            assert!(page.commands.iter().any(|cmd| {
+                matches!(
+                    &*cmd,
+                    Command::DrawImage { resource_id, .. } if **resource_id == *image_source
+                )
+            }));
+        }
+    }
+
+    #[test]
+    fn watermark_text_uses_transform_compatible_y_coordinate() {
+        let spec = WatermarkSpec::text("WM");
+        let resolver = style::StyleResolver::new("");
+        let page_size = Size::a4();
+        let commands = build_watermark_commands(
+            &spec, page_size, 1, 1, None, &resolver, None, None, None, false, false,
+        );
+
+        let draw = commands.iter().find_map(|cmd| match cmd {
+            Command::DrawString { x, y, text } => Some((*x, *y, text.as_str())),
+            _ => None,
+        });
+        let (_x, y, text) = draw.expect("expected DrawString command");
+        assert_eq!(text, "WM");
+        assert_eq!(y, page_size.height - spec.font_size.mul_ratio(1, 2));
+    }
+
+    #[test]
+    fn watermark_image_uses_transform_compatible_y_coordinate() {
+        let spec = WatermarkSpec::image("missing-watermark-image.png");
+        let resolver = style::StyleResolver::new("");
+        let page_size = Size::a4();
+        let commands = build_watermark_commands(
+            &spec, page_size, 1, 1, None, &resolver, None, None, None, false, false,
+        );
+
+        let draw = commands.iter().find_map(|cmd| match cmd {
+            Command::DrawImage { y, height, .. } => Some((*y, *height)),
+            _ => None,
+        });
+        let (y, height) = draw.expect("expected DrawImage command");
+        assert_eq!(y, page_size.height - height);
+    }
+
+    #[test]
+    fn html_img_tag_emits_draw_image_command() {
+        let html = r#"
+            <!doctype html>
+            <html>
+              <body>
+                <img class="logo" src="examples/img/full_bleed-logo_small.png" alt="logo" />
+              </body>
+            </html>
+        "#;
+        let css = r#"
+            @page { size: 8.5in 11in; margin: 0.5in; }
+            .logo { width: 210px; height: 86px; }
+        "#;
+        let engine = FullBleed::builder().build().expect("engine");
+        let doc = engine
+            .render_to_document(html, css)
+            .expect("render document");
+        let mut found = false;
+        for page in &doc.pages {
+            if page.commands.iter().any(|cmd| {
+                matches!(&*cmd, Command::DrawImage { resource_id, .. } if &**resource_id == "examples/img/full_bleed-logo_small.png")
+            }) {
+                found = true;
+                break;
+            }
+        }
+        assert!(found, "expected <img> to emit DrawImage command");
+    }
+
+    #[test]
+    fn repeated_font_names_and_images_share_one_allocation() {
+        let html = (0..20)
+            .map(|i| {
+                format!(
+                    "<div style=\"break-before: page\"><p>Page {i}</p>\
+                    <img class=\"logo\" src=\"examples/img/full_bleed-logo_small.png\"></div>"
+                )
+            })
+            .collect::<String>();
+        let css = "p { font-family: Courier; } .logo { width: 60px; height: 24px; }";
+        let engine = FullBleed::builder().build().expect("engine");
+        let doc = engine.render_to_document(&html, css).expect("document");
+        assert_eq!(doc.pages.len(), 20);
+
+        let interned = |doc: &Document, font: bool| -> Vec<Arc<str>> {
+            doc.pages
+                .iter()
+                .flat_map(|page| page.commands.iter())
+                .filter_map(|cmd| match &*cmd {
+                    Command::SetFontName(name) if font => Some(name.clone()),
+                    Command::DrawImage { resource_id, .. } if !font => Some(resource_id.clone()),
+                    _ => None,
+                })
+                .collect()
+        };
+        for values in [interned(&doc, true), interned(&doc, false)] {
+            assert!(values.len() >= 20, "{}", values.len());
+            assert!(values.iter().all(|value| Arc::ptr_eq(value, &values[0])));
+        }
+
+        // The same document with a private copy of the image id per page costs one more
+        // copy per page. Both are clones so their command buffers have the same capacity.
+        let shared = doc.clone();
+        let mut copied = doc.clone();
+        for page in &mut copied.pages {
+            for cmd in page.commands.owned_mut() {
+                if let Command::DrawImage { resource_id, .. } = cmd {
+                    *resource_id = Arc::from(&**resource_id);
+                }
+            }
+        }
+        let id_len = "examples/img/full_bleed-logo_small.png".len();
+        assert!(shared.memory_footprint() > 0);
+        assert!(copied.memory_footprint() >= shared.memory_footprint() + 19 * id_len);
+    }
+
+    #[test]
+    fn packed_commands_shrink_the_memory_footprint() {
+        let mut canvas = Canvas::new(Size::a4());
+        for i in 0..500 {
+            let x = Pt::from_f32(i as f32);
+            canvas.set_fill_color(Color::rgb(0.1, 0.2, 0.3));
+            canvas.move_to(x, Pt::ZERO);
+            canvas.line_to(x, Pt::from_f32(10.0));
+            canvas.curve_to(x, x, x, x, x, x);
+            canvas.close_path();
+            canvas.fill();
+        }
+        let doc = canvas.finish();
+        let page = &doc.pages[0];
+        let slots = page.commands.len() * std::mem::size_of::<Command>();
+        // Path and paint commands take their fields' bytes, well under a `Command` each.
+        assert!(
+            doc.memory_footprint() * 2 < slots,
+            "{} vs {slots}",
+            doc.memory_footprint()
+        );
+    }
+
+    #[test]
+    fn css_font_face_registers_faces_by_weight() {
+        use base64::Engine;
+        let inter = repo_font_path("Inter-Variable.ttf");
+        let math = std::fs::read(repo_font_path("NotoSansMath-Regular.ttf")).expect("read math");
+        let css = format!(
+            "@font-face {{ font-family: 'Brand'; src: url('{}'); }}
+             @font-face {{ font-family: Brand; font-weight: bold; src: local('Missing'), url(data:font/ttf;base64,{}); }}
+             @font-face {{ font-family: Broken; src: url('missing/Broken.ttf'); }}
+             p {{ font-family: Brand; }} .bold {{ font-weight: 700; }}",
+            inter.to_string_lossy(),
+            base64::engine::general_purpose::STANDARD.encode(&math)
+        );
+        let engine = FullBleed::builder().build().expect("engine");
+        let pdf = engine
+            .render_to_buffer("<p>Regular</p><p class=\"bold\">Bold</p>", &css)
+            .expect("render");
+
+        // Faces answer to the family scoped to this stylesheet's rules, not to the bare name.
+        let viewport = Size::from_inches(8.5, 11.0);
+        let brand = engine.register_css_font_faces(&css, &css, viewport, None).0["brand"].clone();
+        assert!(engine.font_registry.resolve("Brand").is_none());
+        let regular = engine.font_registry.resolve(&brand).expect("regular face");
+        let bold = engine
+            .font_registry
+            .resolve(&format!("{brand} Bold"))
+            .expect("bold face");
+        assert!(regular.name.starts_with("Brand-css"));
+        assert!(bold.name.starts_with("Brand-css"));
+        assert_ne!(regular.name, bold.name);
+        // An italic request without an italic face falls back to the upright regular face.
+        assert_eq!(
+            engine
+                .font_registry
+                .resolve(&format!("{brand} Italic"))
+                .map(|f| f.name.clone()),
+            Some(regular.name.clone())
+        );
+        assert!(engine.font_registry.resolve("Broken").is_none());
+        assert!(count_token(&pdf, format!("+{}", regular.name).as_bytes()) > 0);
+        assert!(count_token(&pdf, format!("+{}", bold.name).as_bytes()) > 0);
+
+        // Re-rendering the same stylesheet reuses the registered faces.
+        engine
+            .render_to_buffer("<p>Again</p>", &css)
+            .expect("render again");
+        assert_eq!(
+            engine.font_registry.resolve(&brand).map(|f| f.name.clone()),
+            Some(regular.name.clone())
+        );
+    }
+
+    #[test]
+    fn css_font_face_families_are_scoped_to_their_stylesheet() {
+        let inter = repo_font_path("Inter-Variable.ttf");
+        let math = repo_font_path("NotoSansMath-Regular.ttf");
+        let css_for = |path: &std::path::Path| {
+            format!(
+                "@font-face {{ font-family: Brand; src: url('{}'); }} p {{ font-family: Brand; }}",
+                path.to_string_lossy()
+            )
+        };
+        let (inter_css, math_css) = (css_for(&inter), css_for(&math));
+        let engine = FullBleed::builder().build().expect("engine");
+        let viewport = Size::from_inches(8.5, 11.0);
+        let face_of = |css: &str| {
+            let family =
+                engine.register_css_font_faces(css, css, viewport, None).0["brand"].clone();
+            engine
+                .font_registry
+                .resolve(&family)
+                .map(|f| f.name.clone())
+                .expect("scoped face")
+        };
+
+        let inter_pdf = engine
+            .render_to_buffer("<p>Inter</p>", &inter_css)
+            .expect("inter render");
+        let math_pdf = engine
+            .render_to_buffer("<p>Math</p>", &math_css)
+            .expect("math render");
+        let (inter_face, math_face) = (face_of(&inter_css), face_of(&math_css));
+        assert_ne!(inter_face, math_face);
+        assert!(count_token(&inter_pdf, format!("+{inter_face}").as_bytes()) > 0);
+        assert_eq!(
+            count_token(&inter_pdf, format!("+{math_face}").as_bytes()),
+            0
+        );
+        assert!(count_token(&math_pdf, format!("+{math_face}").as_bytes()) > 0);
+        assert_eq!(
+            count_token(&math_pdf, format!("+{inter_face}").as_bytes()),
+            0
+        );
+
+        // The first stylesheet keeps its face after the second one declared the family.
+        let again = engine
+            .render_to_buffer("<p>Inter again</p>", &inter_css)
+            .expect("inter again");
+        assert!(count_token(&again, format!("+{inter_face}").as_bytes()) > 0);
+        assert_eq!(count_token(&again, format!("+{math_face}").as_bytes()), 0);
+    }
+
+    #[test]
+    fn css_faces_past_the_bound_evict_the_oldest_and_reload() {
+        let registry = font::FontRegistry::new();
+        let data = std::fs::read(repo_font_path("NotoSansMath-Regular.ttf")).expect("read math");
+        let descriptor = font::CssFaceDescriptor {
+            family: "Brand".to_string(),
+            weight: (400, 400),
+            italic: false,
+        };
+        let register = |n: usize| {
+            let (key, family) = (format!("key{n}"), format!("brand{n}"));
+            if !registry.alias_css_face(&key, &family, &descriptor) {
+                registry
+                    .register_css_face(&key, &family, &descriptor, data.clone(), "math")
+                    .expect("register");
+            }
+        };
+        for n in 0..300 {
+            register(n);
+        }
+        assert!(registry.resolve("brand299").is_some());
+        assert!(registry.resolve("brand0").is_none(), "oldest face evicted");
+        register(0);
+        assert!(registry.resolve("brand0").is_some(), "evicted face reloads");
+    }
+
+    #[test]
+    fn variable_fonts_render_css_weights_as_static_instances() {
+        let engine = FullBleed::builder()
+            .register_font_file(repo_font_path("Inter-Variable.ttf"))
+            .font_variation("Inter Display", "Inter", &[("opsz", 32.0)])
+            .build()
+            .expect("engine");
+        let pdf = engine
+            .render_to_buffer(
+                "<p>Regular</p><p style=\"font-weight: 700\">Bold</p>\
+                 <p style=\"font-weight: 600\">Semi</p><p class=\"display\">Display</p>\
+                 <p style=\"font-family: 'Inter SemiBold'\">Named</p>",
+                "p { font-family: Inter; } .display { font-family: 'Inter Display'; }",
+            )
+            .expect("render");
+
+        let regular = engine.font_registry.resolve("Inter").expect("inter");
+        assert!(!regular.axes.is_empty());
+        for suffix in ["wght700", "wght600", "opsz32"] {
+            let name = format!("{}-{suffix}", regular.name);
+            let instance = engine.font_registry.resolve(&name).expect("instance");
+            assert!(instance.axes.is_empty(), "{name} is static");
+            assert!(
+                count_token(&pdf, format!("+{name}").as_bytes()) > 0,
+                "{name}"
+            );
+        }
+        assert_eq!(
+            engine
+                .font_registry
+                .resolve("Inter SemiBold")
+                .map(|f| f.name.clone()),
+            engine
+                .font_registry
+                .resolve(&format!("{}-wght600", regular.name))
+                .map(|f| f.name.clone())
+        );
+
+        let err = match FullBleed::builder()
+            .font_variation("Nope", "Missing", &[("wght", 500.0)])
+            .build()
+        {
+            Ok(_) => panic!("alias of an unknown font"),
+            Err(err) => err,
+        };
+        assert!(err.to_string().contains("not a registered variable font"));
+    }
+
+    #[test]
+    fn variable_instances_are_built_after_css_faces_fill_the_bound() {
+        let mut registry = font::FontRegistry::new();
+        registry.register_file(repo_font_path("Inter-Variable.ttf"));
+        let data = std::fs::read(repo_font_path("NotoSansMath-Regular.ttf")).expect("read math");
+        let descriptor = font::CssFaceDescriptor {
+            family: "Brand".to_string(),
+            weight: (400, 400),
+            italic: false,
+        };
+        for n in 0..300 {
+            registry
+                .register_css_face(
+                    &format!("key{n}"),
+                    "Brand",
+                    &descriptor,
+                    data.clone(),
+                    "math",
+                )
+                .expect("register");
+        }
+        let bold = registry
+            .weight_instance_name("Inter", 700)
+            .expect("instance past the bound");
+        let instance = registry.resolve(&bold).expect("resolves");
+        assert!(instance.axes.is_empty());
+        assert_eq!(registry.weight_instance_name("Inter", 700), Some(bold));
+    }
+
+    #[test]
+    fn box_shadows_blur_softly_and_outlines_stroke_outside_the_border_box() {
+        let engine = FullBleed::builder()
+            .margin_all(0.0)
+            .build()
+            .expect("engine");
+        let render = |css: &str| {
+            let css = format!("body {{ margin: 0; }} div {{ width: 40pt; height: 20pt; }} {css}");
+            let doc = engine
+                .render_to_document("<div></div>", &css)
+                .expect("render");
+            doc.pages[0].commands.clone()
+        };
+        let alphas = |commands: &CommandBuffer| -> Vec<f32> {
+            commands
+                .iter()
+                .filter_map(|cmd| match &*cmd {
+                    Command::SetOpacity { fill, .. } if *fill < 1.0 => Some(*fill),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        // Stacked blur layers composite to the shadow color's alpha at the core.
+        let outer = render("div { box-shadow: 0 0 8pt rgba(0, 0, 0, 0.5); }");
+        let layers = alphas(&outer);
+        assert!(layers.len() >= 6, "{layers:?}");
+        let core = 1.0 - layers.iter().map(|a| 1.0 - a).product::<f32>();
+        assert!((core - 0.5).abs() < 0.01, "core alpha {core}");
+
+        let inset = render("div { box-shadow: inset 0 0 6pt rgba(0, 0, 0, 0.4); }");
+        assert!(
+            inset
+                .iter()
+                .any(|cmd| matches!(&*cmd, Command::FillEvenOdd))
+        );
+        assert!(!alphas(&inset).is_empty());
+
+        let outline = render("div { outline: 2pt dashed red; outline-offset: 3pt; }");
+        assert!(
+            outline.iter().any(
+                |cmd| matches!(&*cmd, Command::SetDash { pattern, .. } if !pattern.is_empty())
+            )
+        );
+        assert!(outline.iter().any(|cmd| matches!(&*cmd, Command::Stroke)));
+        let start = outline
+            .iter()
+            .find_map(|cmd| match &*cmd {
+                Command::MoveTo { x, y } => Some((x.to_f32(), y.to_f32())),
+                _ => None,
+            })
+            .expect("outline path");
+        assert!(
+            (start.0 + 4.0).abs() < 0.01 && (start.1 + 4.0).abs() < 0.01,
+            "{start:?}"
+        );
+    }
+
+    #[test]
+    fn background_images_tile_and_scale_in_the_padding_box() {
+        // 8x8 px, i.e. 6pt square.
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgb8(image::RgbImage::new(8, 8))
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .expect("encode png");
+        let mut files = std::collections::HashMap::new();
+        files.insert("/virtual/dot.png".to_string(), png);
+        let engine = FullBleed::builder()
+            .resource_resolver(files)
+            .build()
+            .expect("engine");
+        let css = "body { margin: 0; } \
+            .tiles { width: 24pt; height: 6pt; background: url(file:///virtual/dot.png) repeat-x; } \
+            .hero { width: 40pt; height: 20pt; background-image: url(file:///virtual/dot.png); \
+                background-size: cover; background-repeat: no-repeat; background-position: center; }";
+        let html = r#"<div class="tiles"></div><div class="hero"></div>"#;
+        let doc = engine.render_to_document(html, css).expect("render");
+        let images: Vec<(f32, f32, f32, f32)> = doc.pages[0]
+            .commands
+            .iter()
+            .filter_map(|cmd| match &*cmd {
+                Command::DrawImage {
+                    x,
+                    y,
+                    width,
+                    height,
+                    resource_id,
+                } => {
+                    assert!(resource_id.starts_with("data:image/png;base64,"));
+                    Some((x.to_f32(), y.to_f32(), width.to_f32(), height.to_f32()))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(images.len(), 5, "{images:?}");
+        let tiles = &images[..4];
+        assert!(tiles.iter().all(|tile| (tile.2 - 6.0).abs() < 0.01));
+        assert!(tiles.iter().all(|tile| (tile.1 - tiles[0].1).abs() < 0.01));
+        assert!((tiles[3].0 - tiles[0].0 - 18.0).abs() < 0.01, "{tiles:?}");
+        let hero = images[4];
+        assert!((hero.2 - 40.0).abs() < 0.01 && (hero.3 - 40.0).abs() < 0.01);
+        // Centered vertically: half of the 20pt overhang sits above the box.
+        assert!(
+            (hero.1 - (tiles[0].1 + 6.0 - 10.0)).abs() < 0.01,
+            "{hero:?}"
+        );
+    }
+
+    #[test]
+    fn resource_resolver_replaces_filesystem_reads() {
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgb8(image::RgbImage::new(4, 4))
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .expect("encode png");
+        let inter = std::fs::read(repo_font_path("Inter-Variable.ttf")).expect("read inter");
+        let inter_name = font::font_primary_name_from_bytes(&inter, None).expect("font name");
+        let mut files = std::collections::HashMap::new();
+        files.insert("/virtual/logo.png".to_string(), png);
+        files.insert("fonts/Inter.ttf".to_string(), inter);
+
+        let engine = FullBleed::builder()
+            .register_font_file("fonts/Inter.ttf")
+            .resource_resolver(files)
+            .build()
+            .expect("engine");
+        assert!(engine.font_registry.resolve(&inter_name).is_some());
+
+        let html = r#"<img src="file:///virtual/logo.png" style="width: 20px; height: 20px" />
+            <img src="examples/img/full_bleed-logo_small.png" style="width: 20px; height: 20px" />"#;
+        let doc = engine.render_to_document(html, "").expect("render");
+        let sources: Vec<String> = doc
+            .pages
+            .iter()
+            .flat_map(|page| page.commands.iter())
+            .filter_map(|cmd| match &*cmd {
+                Command::DrawImage { resource_id, .. } => Some(resource_id.to_string()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(sources.len(), 2);
+        assert!(sources[0].starts_with("data:image/png;base64,"));
+        // Paths the resolver does not know are not read from disk behind its back.
+        let trace = assets::resolve_image_asset(Some(engine.asset_bundle_ref()), &sources[1]).trace;
+        assert!(!trace.success);
+        assert_eq!(trace.resolver, "resource_resolver");
+
+        let err = match FullBleed::builder()
+            .register_font_file("fonts/missing.ttf")
+            .resource_resolver(std::collections::HashMap::<String, Vec<u8>>::new())
+            .build()
+        {
+            Ok(_) => panic!("unresolvable font file should fail the build"),
+            Err(err) => err,
+        };
+        assert!(
+            matches!(err, FullBleedError::FontNotFound(ref path) if path == "fonts/missing.ttf")
+        );
+        assert_eq!(err.code(), ErrorCode::FontNotFound);
+    }
+
+    #[test]
+    fn remote_img_sources_resolve_through_allowlisted_fetcher() {
+        struct PngFetcher;
+        impl AssetFetcher for PngFetcher {
+            fn fetch(&self, _url: &str) -> std::io::Result<Vec<u8>> {
+                let mut png = Vec::new();
+                image::DynamicImage::ImageRgb8(image::RgbImage::new(4, 4))
+                    .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+                    .map_err(std::io::Error::other)?;
+                Ok(png)
+            }
+        }
+
+        let html = r#"<img src="https://cdn.example.com/logo?v=2" style="width: 20px; height: 20px" />
+            <img src="https://tracker.example.net/pixel.png" style="width: 20px; height: 20px" />"#;
+        let engine = FullBleed::builder()
+            .remote_assets(RemoteAssets::new(PngFetcher).allow_host("cdn.example.com"))
+            .build()
+            .expect("engine");
+        let doc = engine.render_to_document(html, "").expect("render");
+        let sources: Vec<String> = doc
+            .pages
+            .iter()
+            .flat_map(|page| page.commands.iter())
+            .filter_map(|cmd| match &*cmd {
+                Command::DrawImage { resource_id, .. } => Some(resource_id.to_string()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(sources.len(), 2);
+        assert!(sources[0].starts_with("data:image/png;base64,"));
+        assert_eq!(sources[1], "https://tracker.example.net/pixel.png");
+
+        let trace = assets::resolve_image_asset(
+            Some(engine.asset_bundle_ref()),
+            "https://tracker.example.net/pixel.png",
+        )
+        .trace;
+        assert!(!trace.success);
+        assert_eq!(trace.resolver, "remote");
+        assert!(trace.message.unwrap_or_default().contains("allowlist"));
+    }
+
+    #[test]
+    fn display_table_cells_share_a_single_row() {
+        let html = r#"
+            <!doctype html>
+            <html>
+              <body>
+                <div class="t"><span>AA</span><span>BB</span><span>CC</span></div>
+              </body>
+            </html>
+        "#;
+        let css = r#"
+            @page { size: 4in 4in; margin: 0.25in; }
+            body { margin: 0; font-size: 12px; line-height: 1.2; }
+            .t { display: table; width: 240px; border: 1px solid #000; }
+            .t > span { display: table-cell; padding: 2px 4px; border-right: 1px solid #000; }
+            .t > span:last-child { border-right: 0; }
+        "#;
+        let engine = FullBleed::builder().build().expect("engine");
+        let doc = engine
+            .render_to_document(html, css)
+            .expect("render document");
+        let page = doc.pages.first().expect("page");
+
+        let mut aa: Option<(Pt, Pt)> = None;
+        let mut bb: Option<(Pt, Pt)> = None;
+        let mut cc: Option<(Pt, Pt)> = None;
+        for cmd in &page.commands {
+            if let Command::DrawString { text, x, y } = &*cmd {
+                if text.contains("AA") {
+                    aa = Some((*x, *y));
+                } else if text.contains("BB") {
+                    bb = Some((*x, *y));
+                } else if text.contains("CC") {
+                    cc = Some((*x, *y));
+                }
+            }
+        }
+        let (aa_x, aa_y) = aa.expect("missing AA draw");
+        let (bb_x, bb_y) = bb.expect("missing BB draw");
+        let (cc_x, cc_y) = cc.expect("missing CC draw");
+        assert!((aa_y.to_f32() - bb_y.to_f32()).abs() < 1.0);
+        assert!((bb_y.to_f32() - cc_y.to_f32()).abs() < 1.0);
+        assert!(aa_x < bb_x && bb_x < cc_x);
+    }
+
+    #[test]
+    fn heading_honors_display_block_on_inline_descendants() {
+        let html = r#"
+            <!doctype html>
+            <html>
+              <body>
+                <h1 class="title">
+                  <span class="line">TITLELINE_TOP</span>
+                  <span class="line">TITLELINE_BOTTOM</span>
+                </h1>
+              </body>
+            </html>
+        "#;
+        let css = r#"
+            @page { size: 4in 4in; margin: 0.25in; }
+            body { margin: 0; font-family: sans-serif; }
+            .title { margin: 0; font-size: 28px; line-height: 1.1; }
+            .title > .line { display: block; }
+        "#;
+        let engine = FullBleed::builder().build().expect("engine");
+        let doc = engine
+            .render_to_document(html, css)
+            .expect("render document");
+        let page = doc.pages.first().expect("page");
+
+        let mut top_y: Option<Pt> = None;
+        let mut bottom_y: Option<Pt> = None;
+        let mut merged_line = false;
+        for cmd in &page.commands {
+            if let Command::DrawString { text, y, .. } = &*cmd {
+                if text.contains("TITLELINE_TOP TITLELINE_BOTTOM")
+                    || text.contains("TITLELINE_BOTTOM TITLELINE_TOP")
+                {
+                    merged_line = true;
+                }
+                if text.contains("TITLELINE_TOP") {
+                    top_y = Some(*y);
+                }
+                if text.contains("TITLELINE_BOTTOM") {
+                    bottom_y = Some(*y);
+                }
+            }
+        }
+
+        assert!(
+            !merged_line,
+            "display:block spans inside heading must not collapse to one line"
+        );
+        let top_y = top_y.expect("expected top heading line draw command");
+        let bottom_y = bottom_y.expect("expected bottom heading line draw command");
+        assert!(
+            (top_y.to_f32() - bottom_y.to_f32()).abs() > 1.0,
+            "expected heading block lines at different y positions, got y={} and y={}",
+            top_y.to_f32(),
+            bottom_y.to_f32()
+        );
+        assert!(
+            bottom_y > top_y,
+            "expected second heading block line to render below first line"
+        );
+    }
+
+    #[test]
+    fn html_table_cell_block_children_preserve_vertical_flow() {
+        let html = r#"
+            <!doctype html>
+            <html>
+              <body>
+                <table class="t">
+                  <tr>
+                    <td>
+                      <div>TBLTOPMARK</div>
+                      <div>TBLBOTTOMMARK</div>
+                    </td>
+                  </tr>
+                </table>
+              </body>
+            </html>
+        "#;
+        let css = r#"
+            @page { size: 4in 4in; margin: 0.25in; }
+            body { margin: 0; font-size: 14px; line-height: 1.2; }
+            table.t { border-collapse: collapse; width: 220px; }
+            table.t td { border: 1px solid #000; padding: 2px; }
+            table.t td > div { display: block; }
+        "#;
+        let engine = FullBleed::builder().build().expect("engine");
+        let doc = engine
+            .render_to_document(html, css)
+            .expect("render document");
+        let page = doc.pages.first().expect("page");
+
+        let mut top_y: Option<Pt> = None;
+        let mut bottom_y: Option<Pt> = None;
+        let mut merged_line = false;
+        for cmd in &page.commands {
+            if let Command::DrawString { text, y, .. } = &*cmd {
+                if text.contains("TBLTOPMARK TBLBOTTOMMARK")
+                    || text.contains("TBLBOTTOMMARK TBLTOPMARK")
+                {
+                    merged_line = true;
+                }
+                if text.contains("TBLTOPMARK") {
+                    top_y = Some(*y);
+                }
+                if text.contains("TBLBOTTOMMARK") {
+                    bottom_y = Some(*y);
+                }
+            }
+        }
+
+        assert!(
+            !merged_line,
+            "table cell block descendants should not collapse into one text line"
+        );
+        let top_y = top_y.expect("expected top marker draw command");
+        let bottom_y = bottom_y.expect("expected bottom marker draw command");
+        assert!(
+            (top_y.to_f32() - bottom_y.to_f32()).abs() > 1.0,
+            "expected block descendants at different y positions, got y={} and y={}",
+            top_y.to_f32(),
+            bottom_y.to_f32()
+        );
+        assert!(
+            bottom_y > top_y,
+            "expected second block to render below first block"
+        );
+    }
+
+    #[test]
+    fn html_table_second_row_starts_after_multiline_first_row() {
+        let html = r#"
+            <!doctype html>
+            <html>
+              <body>
+                <table class="t">
+                  <tr>
+                    <td>
+                      <div>ROW1A</div>
+                      <div>ROW1B</div>
+                      <div>ROW1C</div>
+                    </td>
+                  </tr>
+                  <tr>
+                    <td>ROW2ONLY</td>
+                  </tr>
+                </table>
+              </body>
+            </html>
+        "#;
+        let css = r#"
+            @page { size: 4in 4in; margin: 0.25in; }
+            body { margin: 0; font-size: 14px; line-height: 1.2; }
+            table.t { border-collapse: collapse; width: 220px; }
+            table.t td { border: 1px solid #000; padding: 2px; }
+            table.t td > div { display: block; }
+        "#;
+        let engine = FullBleed::builder().build().expect("engine");
+        let doc = engine
+            .render_to_document(html, css)
+            .expect("render document");
+        let page = doc.pages.first().expect("page");
+
+        let mut row1_last_y: Option<Pt> = None;
+        let mut row2_y: Option<Pt> = None;
+        for cmd in &page.commands {
+            if let Command::DrawString { text, y, .. } = &*cmd {
+                if text.contains("ROW1C") {
+                    row1_last_y = Some(*y);
+                }
+                if text.contains("ROW2ONLY") {
+                    row2_y = Some(*y);
+                }
+            }
+        }
+
+        let row1_last_y = row1_last_y.expect("expected ROW1C draw command");
+        let row2_y = row2_y.expect("expected ROW2ONLY draw command");
+        assert!(
+            row2_y > row1_last_y,
+            "expected second row to render below first row content, got row1={} row2={}",
+            row1_last_y.to_f32(),
+            row2_y.to_f32()
+        );
+    }
+
+    #[test]
+    fn html_table_colspan_preserves_following_column_alignment() {
+        let html = r#"
+            <!doctype html>
+            <html>
+              <body>
                 <table class="t">
                   <tr>
                     <td colspan="2">FULLSPAN HEADER</td>
                   </tr>
                   <tr>
-                    <td>LEFT</td>
-                    <td>RIGHT</td>
+                    <td>LEFT</td>
+                    <td>RIGHT</td>
+                  </tr>
+                </table>
+              </body>
+            </html>
+        "#;
+        let css = r#"
+            @page { size: 4in 4in; margin: 0.25in; }
+            body { margin: 0; font-size: 14px; line-height: 1.2; }
+            table.t { border-collapse: collapse; width: 300px; table-layout: fixed; }
+            table.t td { border: 1px solid #000; padding: 2px; }
+        "#;
+        let engine = FullBleed::builder().build().expect("engine");
+        let doc = engine
+            .render_to_document(html, css)
+            .expect("render document");
+        let page = doc.pages.first().expect("page");
+
+        let mut left_x: Option<Pt> = None;
+        let mut right_x: Option<Pt> = None;
+        for cmd in &page.commands {
+            if let Command::DrawString { text, x, .. } = &*cmd {
+                if text == "LEFT" {
+                    left_x = Some(*x);
+                }
+                if text == "RIGHT" {
+                    right_x = Some(*x);
+                }
+            }
+        }
+
+        let left_x = left_x.expect("expected LEFT draw command");
+        let right_x = right_x.expect("expected RIGHT draw command");
+        let delta = right_x - left_x;
+        assert!(
+            delta > Pt::from_f32(50.0),
+            "expected RIGHT to be in a separate column, got delta={}",
+            delta.to_f32()
+        );
+        assert!(
+            delta < Pt::from_f32(220.0),
+            "colspan should not collapse following column to page edge, got delta={}",
+            delta.to_f32()
+        );
+    }
+
+    #[test]
+    fn html_table_layout_fixed_ignores_later_row_intrinsic_pressure() {
+        let html = r#"
+            <!doctype html>
+            <html>
+              <body>
+                <table class="t">
+                  <tr><td>ROW1_LEFT</td><td>ROW1_RIGHT</td></tr>
+                  <tr><td>SUPERCALIFRAGILISTICEXTRAORDINARILYLONGTOKEN</td><td>R2</td></tr>
+                </table>
+              </body>
+            </html>
+        "#;
+        let css_fixed = r#"
+            @page { size: 4in 4in; margin: 0.25in; }
+            body { margin: 0; font-size: 12px; line-height: 1.2; }
+            table.t { border-collapse: collapse; width: 300px; table-layout: fixed; }
+            table.t td { border: 0; padding: 0; }
+        "#;
+        let css_auto = r#"
+            @page { size: 4in 4in; margin: 0.25in; }
+            body { margin: 0; font-size: 12px; line-height: 1.2; }
+            table.t { border-collapse: collapse; width: 300px; table-layout: auto; }
+            table.t td { border: 0; padding: 0; }
+        "#;
+
+        let engine = FullBleed::builder().build().expect("engine");
+
+        let doc_fixed = engine
+            .render_to_document(html, css_fixed)
+            .expect("render fixed document");
+        let page_fixed = doc_fixed.pages.first().expect("fixed page");
+        let mut right_fixed_x: Option<Pt> = None;
+        for cmd in &page_fixed.commands {
+            if let Command::DrawString { text, x, .. } = &*cmd
+                && text == "R2"
+            {
+                right_fixed_x = Some(*x);
+            }
+        }
+        let right_fixed_x = right_fixed_x.expect("expected R2 draw command in fixed layout");
+
+        let doc_auto = engine
+            .render_to_document(html, css_auto)
+            .expect("render auto document");
+        let page_auto = doc_auto.pages.first().expect("auto page");
+        let mut right_auto_x: Option<Pt> = None;
+        for cmd in &page_auto.commands {
+            if let Command::DrawString { text, x, .. } = &*cmd
+                && text == "R2"
+            {
+                right_auto_x = Some(*x);
+            }
+        }
+        let right_auto_x = right_auto_x.expect("expected R2 draw command in auto layout");
+
+        assert!(
+            right_auto_x > right_fixed_x + Pt::from_f32(15.0),
+            "expected fixed layout to keep the right column further left than auto layout, fixed={} auto={}",
+            right_fixed_x.to_f32(),
+            right_auto_x.to_f32()
+        );
+    }
+
+    #[test]
+    fn list_item_block_children_preserve_vertical_flow() {
+        let html = r#"
+            <!doctype html>
+            <html>
+              <body>
+                <ul class="menu">
+                  <li>
+                    <div class="title">ITEMHEADONLY</div>
+                    <div class="desc">ITEMDESCONLY</div>
+                  </li>
+                </ul>
+              </body>
+            </html>
+        "#;
+        let css = r#"
+            @page { size: 4in 4in; margin: 0.25in; }
+            body { margin: 0; font-size: 14px; line-height: 1.2; }
+            ul, li { margin: 0; padding: 0; list-style: none; }
+            .title, .desc { display: block; }
+        "#;
+        let engine = FullBleed::builder().build().expect("engine");
+        let doc = engine
+            .render_to_document(html, css)
+            .expect("render document");
+        let page = doc.pages.first().expect("page");
+
+        let mut title_y: Option<Pt> = None;
+        let mut desc_y: Option<Pt> = None;
+        let mut merged_line = false;
+        for cmd in &page.commands {
+            if let Command::DrawString { text, y, .. } = &*cmd {
+                if text.contains("ITEMHEADONLY ITEMDESCONLY")
+                    || text.contains("ITEMDESCONLY ITEMHEADONLY")
+                {
+                    merged_line = true;
+                }
+                if text.contains("ITEMHEADONLY") {
+                    title_y = Some(*y);
+                }
+                if text.contains("ITEMDESCONLY") {
+                    desc_y = Some(*y);
+                }
+            }
+        }
+
+        assert!(
+            !merged_line,
+            "list-item block children should not collapse into one line"
+        );
+        let title_y = title_y.expect("expected title draw command");
+        let desc_y = desc_y.expect("expected description draw command");
+        assert!(
+            (title_y.to_f32() - desc_y.to_f32()).abs() > 1.0,
+            "expected list-item block children on separate lines, got y={} and y={}",
+            title_y.to_f32(),
+            desc_y.to_f32()
+        );
+    }
+
+    #[test]
+    fn css_page_size_applies_when_builder_page_size_is_default() {
+        let html = "<!doctype html><html><body><p>hello</p></body></html>";
+        let css = "@page { size: letter; margin: 0.5in; }";
+        let engine = FullBleed::builder().build().expect("engine");
+        let doc = engine
+            .render_to_document(html, css)
+            .expect("render document");
+        assert!((doc.page_size.width.to_f32() - 612.0).abs() < 0.01);
+        assert!((doc.page_size.height.to_f32() - 792.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn explicit_builder_page_size_logs_page_size_overridden() {
+        let log_path = temp_log_path("page_size_override");
+        let html = "<!doctype html><html><body><p>hello</p></body></html>";
+        let css = "@page { size: letter; }";
+        let engine = FullBleed::builder()
+            .page_size(Size::a4())
+            .debug_log(&log_path)
+            .build()
+            .expect("engine");
+        let _ = engine
+            .render_to_document(html, css)
+            .expect("render document");
+        drop(engine);
+        let log = std::fs::read_to_string(&log_path).expect("read debug log");
+        assert!(log.contains("\"PAGE_SIZE_OVERRIDDEN\""));
+        let _ = std::fs::remove_file(log_path);
+    }
+
+    #[test]
+    fn batch_records_reuse_line_breaks_of_shared_paragraphs() {
+        let log_path = temp_log_path("line_cache");
+        let terms = "These terms apply to every statement we send. ".repeat(12);
+        let css = "p { font-size: 10pt; width: 300pt; }".to_string();
+        let jobs: Vec<(String, String)> = ["Ada", "Grace", "Edsger"]
+            .iter()
+            .map(|name| {
+                (
+                    format!("<p>Dear {name},</p><p class=\"terms\">{terms}</p>"),
+                    css.clone(),
+                )
+            })
+            .collect();
+        let engine = FullBleed::builder()
+            .debug_log(&log_path)
+            .build()
+            .expect("engine");
+        engine
+            .render_many_to_buffer_with_css(&jobs)
+            .expect("batch render");
+        let warmed = engine
+            .render_to_document(&jobs[2].0, &jobs[2].1)
+            .expect("warmed document");
+        drop(engine);
+
+        let fresh = FullBleed::builder()
+            .build()
+            .expect("fresh engine")
+            .render_to_document(&jobs[2].0, &jobs[2].1)
+            .expect("fresh document");
+        assert_eq!(
+            format!("{:?}", warmed.pages[0].commands),
+            format!("{:?}", fresh.pages[0].commands)
+        );
+
+        let log = std::fs::read_to_string(&log_path).expect("read debug log");
+        let line_cache = |line: &str| -> (u64, u64) {
+            let value: serde_json::Value = serde_json::from_str(line).expect("json");
+            let cache = &value["line_cache"];
+            (
+                cache["hits"].as_u64().expect("hits"),
+                cache["misses"].as_u64().expect("misses"),
+            )
+        };
+        let metrics: Vec<(u64, u64)> = log
+            .lines()
+            .filter(|line| line.contains("\"type\":\"jit.metrics\""))
+            .map(line_cache)
+            .collect();
+        // Each record breaks its own greeting; the terms are broken once, and the warmed single
+        // render finds both.
+        assert_eq!(metrics, vec![(0, 2), (1, 3), (2, 4), (4, 4)]);
+        let _ = std::fs::remove_file(log_path);
+    }
+
+    #[test]
+    fn absolute_overflow_continues_on_next_page_or_logs_clipping() {
+        let css = "@page { size: 300pt 200pt; margin: 20pt; } \
+            body { margin: 0; font-size: 12pt; } p { margin: 0; } \
+            .note { position: absolute; top: 120pt; left: 20pt; width: 200pt; }";
+        let lines: String = (1..=8).map(|line| format!("<p>L{line}</p>")).collect();
+        let string_ys = |page: &Page| -> Vec<(String, f32)> {
+            page.commands
+                .iter()
+                .filter_map(|cmd| match &*cmd {
+                    Command::DrawString { text, y, .. } => {
+                        Some((text.trim().to_string(), y.to_f32()))
+                    }
+                    _ => None,
+                })
+                .collect()
+        };
+
+        let html =
+            format!("<p>Flow</p><div class=\"note\" data-fb-continue-overflow>{lines}</div>");
+        let engine = FullBleed::builder().build().expect("engine");
+        let doc = engine.render_to_document(&html, css).expect("document");
+        assert_eq!(doc.pages.len(), 2);
+        let first = string_ys(&doc.pages[0]);
+        let second = string_ys(&doc.pages[1]);
+        assert!(first.iter().any(|(text, _)| text == "L1"), "{first:?}");
+        assert!(!first.iter().any(|(text, _)| text == "L8"), "{first:?}");
+        let (_, y) = second
+            .iter()
+            .find(|(text, _)| text == "L8")
+            .expect("continued line");
+        assert!(*y > 120.0, "{second:?}");
+
+        let log_path = temp_log_path("abs_overflow_clipped");
+        let html = format!("<p>Flow</p><div class=\"note\">{lines}</div>");
+        let engine = FullBleed::builder()
+            .debug_log(&log_path)
+            .build()
+            .expect("engine");
+        let doc = engine.render_to_document(&html, css).expect("document");
+        assert_eq!(doc.pages.len(), 1);
+        drop(engine);
+        let log = std::fs::read_to_string(&log_path).expect("read debug log");
+        assert!(log.contains("\"ABS_OVERFLOW_CLIPPED\""));
+        let _ = std::fs::remove_file(log_path);
+    }
+
+    #[test]
+    fn z_index_orders_positioned_elements_across_the_page() {
+        let css = "body { margin: 0; font-size: 12pt; } p { margin: 0; } \
+            .card { position: relative; height: 40pt; background: #eeeeee; } \
+            .badge { position: absolute; top: 30pt; left: 10pt; z-index: 2; } \
+            .under { position: relative; z-index: -1; }";
+        let html = "<p>Top</p><p class=\"under\">Under</p>\
+            <div class=\"card\"><p>First</p><div class=\"badge\">Badge</div></div>\
+            <div class=\"card\"><p>Later</p></div>";
+        let engine = FullBleed::builder().build().expect("engine");
+        let doc = engine.render_to_document(html, css).expect("document");
+        assert_eq!(doc.pages.len(), 1);
+        let commands = &doc.pages[0].commands;
+        let texts: Vec<String> = commands
+            .iter()
+            .filter_map(|cmd| match &*cmd {
+                Command::DrawString { text, .. } => Some(text.trim().to_string()),
+                _ => None,
+            })
+            .collect();
+        let position = |needle: &str| {
+            texts
+                .iter()
+                .position(|text| text == needle)
+                .unwrap_or_else(|| panic!("{needle} missing from {texts:?}"))
+        };
+        assert!(position("Badge") > position("Later"), "{texts:?}");
+        assert!(position("Under") < position("Top"), "{texts:?}");
+        let saves = commands
+            .iter()
+            .filter(|cmd| matches!(&**cmd, Command::SaveState))
+            .count();
+        let restores = commands
+            .iter()
+            .filter(|cmd| matches!(&**cmd, Command::RestoreState))
+            .count();
+        assert_eq!(saves, restores);
+    }
+
+    #[test]
+    fn css_transforms_rotate_stamps_and_table_headers() {
+        let css = "body { margin: 0; font-size: 12pt; } \
+            .stamp { display: inline-block; transform: rotate(-15deg) scale(1.5); \
+              transform-origin: left top; border: 2pt solid #cc0000; } \
+            th { height: 60pt; transform: rotate(-90deg); }";
+        let html = "<p>Invoice <span class=\"stamp\">PAID</span></p>\
+            <table><tr><th>Qty</th><th>Item</th></tr><tr><td>1</td><td>Pen</td></tr></table>";
+        let engine = FullBleed::builder().build().expect("engine");
+        let doc = engine.render_to_document(html, css).expect("document");
+        let commands = &doc.pages[0].commands;
+        let text_x = |needle: &str| {
+            commands.iter().find_map(|cmd| match &*cmd {
+                Command::DrawString { x, text, .. } if text.trim() == needle => Some(x.to_f32()),
+                _ => None,
+            })
+        };
+        // The stamp is laid out as its own box on the paragraph's line, after "Invoice".
+        assert!(text_x("PAID").expect("PAID") > text_x("Invoice").expect("Invoice"));
+        let rotation_before = |needle: &str, degrees: f32| {
+            let text_at = commands
+                .iter()
+                .position(
+                    |cmd| matches!(&*cmd, Command::DrawString { text, .. } if text.trim() == needle),
+                )
+                .unwrap_or_else(|| panic!("{needle} missing"));
+            commands.iter().take(text_at).any(|cmd| {
+                matches!(&*cmd, Command::Rotate(angle) if (angle.abs() - degrees.to_radians()).abs() < 1e-4)
+            })
+        };
+        assert!(rotation_before("PAID", 15.0));
+        assert!(rotation_before("Qty", 90.0));
+        assert!(commands.iter().any(|cmd| matches!(&*cmd, Command::Scale(x, y) if (*x - 1.5).abs() < 1e-4 && (*y - 1.5).abs() < 1e-4)));
+        let rotations = commands
+            .iter()
+            .filter(|cmd| matches!(&**cmd, Command::Rotate(_)))
+            .count();
+        // One for the stamp and one per header cell; body cells stay untransformed.
+        assert_eq!(rotations, 3);
+        let saves = commands
+            .iter()
+            .filter(|cmd| matches!(&**cmd, Command::SaveState))
+            .count();
+        let restores = commands
+            .iter()
+            .filter(|cmd| matches!(&**cmd, Command::RestoreState))
+            .count();
+        assert_eq!(saves, restores);
+    }
+
+    #[test]
+    fn on_page_draw_paints_custom_vector_content_on_every_page() {
+        let engine = FullBleed::builder()
+            .page_size(Size::from_inches(4.0, 3.0))
+            .on_page_draw(|canvas, ctx| {
+                let Size { width, height } = ctx.page_size;
+                canvas.set_stroke_color(Color::rgb(0.8, 0.0, 0.0));
+                canvas.circle(width * 0.5, height * 0.5, Pt::from_f32(20.0));
+                canvas.stroke();
+                canvas.save_state();
+                canvas.rect_path(Pt::ZERO, Pt::ZERO, width, Pt::from_f32(18.0));
+                canvas.clip_path(false);
+                canvas.move_to(Pt::from_f32(10.0), Pt::from_f32(10.0));
+                canvas.arc(
+                    Pt::from_f32(10.0),
+                    Pt::from_f32(10.0),
+                    Pt::from_f32(8.0),
+                    0.0,
+                    std::f32::consts::PI,
+                );
+                canvas.fill();
+                canvas.restore_state();
+                canvas.draw_string(
+                    Pt::from_f32(10.0),
+                    height - Pt::from_f32(10.0),
+                    format!("{} / {}", ctx.page_number, ctx.page_count),
+                );
+            })
+            .build()
+            .expect("engine");
+        let html = "<p style=\"break-after: page\">one</p><p>two</p>";
+        let doc = engine.render_to_document(html, "").expect("document");
+        assert_eq!(doc.pages.len(), 2);
+        for (index, page) in doc.pages.iter().enumerate() {
+            let label = format!("{} / 2", index + 1);
+            let label_at = page
+                .commands
+                .iter()
+                .position(|cmd| matches!(&*cmd, Command::DrawString { text, .. } if *text == label))
+                .expect("page label");
+            // The callback paints after the page content, inside its own graphics state.
+            let body_at = page
+                .commands
+                .iter()
+                .position(|cmd| matches!(&*cmd, Command::DrawString { .. }))
+                .expect("body text");
+            assert!(body_at < label_at);
+            assert!(matches!(
+                page.commands.iter().next_back().as_deref(),
+                Some(Command::RestoreState)
+            ));
+            let curves = page
+                .commands
+                .iter()
+                .filter(|cmd| matches!(&**cmd, Command::CurveTo { .. }))
+                .count();
+            // Four for the circle, two for the half-turn arc.
+            assert_eq!(curves, 6);
+            assert!(
+                page.commands
+                    .iter()
+                    .any(|cmd| matches!(&*cmd, Command::ClipPath { evenodd: false }))
+            );
+        }
+        let err = match engine.render_stream_to_writer("<p>x</p>".as_bytes(), "", &mut Vec::new()) {
+            Err(err) => err,
+            Ok(_) => panic!("stream mode should reject on_page_draw"),
+        };
+        assert!(matches!(err, FullBleedError::InvalidConfiguration(_)));
+    }
+
+    #[test]
+    fn pagination_emits_page_break_trigger_event() {
+        let log_path = temp_log_path("page_break_trigger");
+        let html = "<!doctype html><html><body><p>one two three four five six seven eight nine ten eleven twelve thirteen fourteen fifteen sixteen seventeen eighteen nineteen twenty.</p><p>one two three four five six seven eight nine ten eleven twelve thirteen fourteen fifteen sixteen seventeen eighteen nineteen twenty.</p><p>one two three four five six seven eight nine ten eleven twelve thirteen fourteen fifteen sixteen seventeen eighteen nineteen twenty.</p></body></html>";
+        let css = "body { margin: 0; font-size: 14px; line-height: 1.2; }";
+        let engine = FullBleed::builder()
+            .page_size(Size::from_inches(3.0, 3.0))
+            .margin_all(18.0)
+            .debug_log(&log_path)
+            .build()
+            .expect("engine");
+        let doc = engine
+            .render_to_document(html, css)
+            .expect("render document");
+        assert!(doc.pages.len() > 1);
+        drop(engine);
+        let log = std::fs::read_to_string(&log_path).expect("read debug log");
+        assert!(log.contains("\"PAGE_BREAK_TRIGGER\""));
+        let _ = std::fs::remove_file(log_path);
+    }
+
+    #[test]
+    fn html_table_row_collection_excludes_nested_tables() {
+        let log_path = temp_log_path("table_row_scope");
+        let html = r#"
+            <!doctype html>
+            <html>
+              <body>
+                <table class="outer">
+                  <tr>
+                    <td>
+                      <div>Outer left</div>
+                      <table class="inner">
+                        <tr>
+                          <td>Inner A</td>
+                          <td>Inner B</td>
+                          <td>Inner C</td>
+                        </tr>
+                      </table>
+                    </td>
+                    <td>Outer right</td>
                   </tr>
                 </table>
               </body>
             </html>
         "#;
         let css = r#"
-            @page { size: 4in 4in; margin: 0.25in; }
-            body { margin: 0; font-size: 14px; line-height: 1.2; }
-            table.t { border-collapse: collapse; width: 300px; table-layout: fixed; }
-            table.t td { border: 1px solid #000; padding: 2px; }
+            body { margin: 0; font-size: 10pt; }
+            table { border-collapse: collapse; width: 100%; }
+            td { border: 1px solid #333; padding: 2pt; }
         "#;
-        let engine = FullBleed::builder().build().expect("engine");
-        let doc = engine
+        let engine = FullBleed::builder()
+            .page_size(Size::from_inches(4.0, 4.0))
+            .margin_all(0.0)
+            .debug_log(&log_path)
+            .build()
+            .expect("engine");
+        let _doc = engine
             .render_to_document(html, css)
             .expect("render document");
-        let page = doc.pages.first().expect("page");
+        drop(engine);
+
+        let log = std::fs::read_to_string(&log_path).expect("read debug log");
+        let table_rows_events: Vec<&str> = log
+            .lines()
+            .filter(|line| line.contains("\"type\":\"table.rows\""))
+            .collect();
+        assert!(
+            table_rows_events.len() >= 2,
+            "expected outer and inner table row events, got {}",
+            table_rows_events.len()
+        );
+        assert!(
+            table_rows_events[0].contains("\"total\":1"),
+            "outer table should only collect direct rows: {}",
+            table_rows_events[0]
+        );
+        let _ = std::fs::remove_file(log_path);
+    }
+
+    #[test]
+    fn paragraph_split_produces_two_parts() {
+        let text = "one two three four five six seven eight nine ten eleven twelve";
+        let mut style = TextStyle::default();
+        style.font_size = Pt::from_f32(10.0);
+        style.line_height = Pt::from_f32(12.0);
+        let paragraph = Paragraph::new(text)
+            .with_style(style)
+            .with_pagination(Pagination {
+                orphans: 1,
+                widows: 1,
+                ..Pagination::default()
+            });
+
+        let avail_width = Pt::from_f32(60.0);
+        let avail_height = Pt::from_f32(24.0);
+        let split = paragraph.split(avail_width, avail_height);
+        assert!(split.is_some());
+
+        let (first, second) = split.unwrap();
+        let first_size = first.wrap(avail_width, avail_height);
+        let second_size = second.wrap(avail_width, avail_height);
+
+        assert!(first_size.height <= avail_height);
+        assert!(second_size.height > Pt::ZERO);
+    }
+
+    #[test]
+    fn frame_overflows_on_extra_spacer() {
+        let mut frame = Frame::new(Rect {
+            x: Pt::ZERO,
+            y: Pt::ZERO,
+            width: Pt::from_f32(200.0),
+            height: Pt::from_f32(50.0),
+        });
+        let mut canvas = Canvas::new(Size {
+            width: Pt::from_f32(200.0),
+            height: Pt::from_f32(200.0),
+        });
+
+        for _ in 0..10 {
+            let result = frame.add(Box::new(Spacer::new_pt(Pt::from_f32(5.0))), &mut canvas);
+            assert!(matches!(result, AddResult::Placed(_)));
+        }
+
+        let result = frame.add(Box::new(Spacer::new_pt(Pt::from_f32(5.0))), &mut canvas);
+        assert!(matches!(result, AddResult::Overflow(_, _)));
+    }
+
+    #[test]
+    fn table_split_repeats_header_when_enabled() {
+        let header = vec![vec![table_cell("HDR_ID"), table_cell("HDR_NAME")]];
+        let body: Vec<Vec<TableCell>> = (1..=20)
+            .map(|i| vec![table_cell(&i.to_string()), table_cell(&format!("row-{i}"))])
+            .collect();
+        let table = TableFlowable::new(body)
+            .with_header(header)
+            .repeat_header(true);
+
+        let frame_rect = Rect {
+            x: Pt::ZERO,
+            y: Pt::ZERO,
+            width: Pt::from_f32(300.0),
+            height: Pt::from_f32(72.0),
+        };
+        let mut frame1 = Frame::new(frame_rect);
+        let mut canvas = Canvas::new(Size {
+            width: Pt::from_f32(300.0),
+            height: Pt::from_f32(200.0),
+        });
+        let second = match frame1.add(Box::new(table), &mut canvas) {
+            AddResult::Split(rest, _) => rest,
+            other => panic!(
+                "expected split, got variant {:?}",
+                std::mem::discriminant(&other)
+            ),
+        };
+
+        canvas.show_page();
+        let mut frame2 = Frame::new(frame_rect);
+        let result2 = frame2.add(second, &mut canvas);
+        assert!(matches!(
+            result2,
+            AddResult::Placed(_) | AddResult::Split(_, _)
+        ));
+
+        let doc = canvas.finish();
+        assert!(doc.pages.len() >= 2);
+        assert!(page_contains_text(&doc.pages[0], "HDR_ID"));
+        assert!(page_contains_text(&doc.pages[1], "HDR_ID"));
+    }
+
+    #[test]
+    fn table_split_does_not_repeat_header_when_disabled() {
+        let header = vec![vec![table_cell("HDR_OFF"), table_cell("HDR_OFF_NAME")]];
+        let body: Vec<Vec<TableCell>> = (1..=20)
+            .map(|i| vec![table_cell(&i.to_string()), table_cell(&format!("row-{i}"))])
+            .collect();
+        let table = TableFlowable::new(body)
+            .with_header(header)
+            .repeat_header(false);
+
+        let frame_rect = Rect {
+            x: Pt::ZERO,
+            y: Pt::ZERO,
+            width: Pt::from_f32(300.0),
+            height: Pt::from_f32(72.0),
+        };
+        let mut frame1 = Frame::new(frame_rect);
+        let mut canvas = Canvas::new(Size {
+            width: Pt::from_f32(300.0),
+            height: Pt::from_f32(200.0),
+        });
+        let second = match frame1.add(Box::new(table), &mut canvas) {
+            AddResult::Split(rest, _) => rest,
+            other => panic!(
+                "expected split, got variant {:?}",
+                std::mem::discriminant(&other)
+            ),
+        };
+
+        canvas.show_page();
+        let mut frame2 = Frame::new(frame_rect);
+        let result2 = frame2.add(second, &mut canvas);
+        assert!(matches!(
+            result2,
+            AddResult::Placed(_) | AddResult::Split(_, _)
+        ));
 
-        let mut left_x: Option<Pt> = None;
-        let mut right_x: Option<Pt> = None;
-        for cmd in &page.commands {
-            if let Command::DrawString { text, x, .. } = cmd {
-                if text == "LEFT" {
-                    left_x = Some(*x);
+        let doc = canvas.finish();
+        assert!(doc.pages.len() >= 2);
+        assert!(page_contains_text(&doc.pages[0], "HDR_OFF"));
+        assert!(!page_contains_text(&doc.pages[1], "HDR_OFF"));
+    }
+
+    fn last_text_y(page: &Page) -> Option<(String, Pt)> {
+        page.commands
+            .iter()
+            .filter_map(|cmd| match &*cmd {
+                Command::DrawString { text, y, .. } => Some((text.clone(), *y)),
+                _ => None,
+            })
+            .max_by(|(_, a), (_, b)| a.to_f32().total_cmp(&b.to_f32()))
+    }
+
+    #[test]
+    fn table_split_repeats_footer_when_enabled() {
+        let body: Vec<Vec<TableCell>> = (1..=20)
+            .map(|i| vec![table_cell(&i.to_string()), table_cell(&format!("row-{i}"))])
+            .collect();
+        let footer = vec![vec![table_cell("FTR_SUM"), table_cell("FTR_TOTAL")]];
+        let table = TableFlowable::new(body)
+            .with_footer(footer)
+            .repeat_footer(true);
+
+        let frame_rect = Rect {
+            x: Pt::ZERO,
+            y: Pt::ZERO,
+            width: Pt::from_f32(300.0),
+            height: Pt::from_f32(72.0),
+        };
+        let mut canvas = Canvas::new(Size {
+            width: Pt::from_f32(300.0),
+            height: Pt::from_f32(200.0),
+        });
+        let mut pending: Box<dyn Flowable> = Box::new(table);
+        loop {
+            let mut frame = Frame::new(frame_rect);
+            match frame.add(pending, &mut canvas) {
+                AddResult::Split(rest, _) => {
+                    canvas.show_page();
+                    pending = rest;
                 }
-                if text == "RIGHT" {
-                    right_x = Some(*x);
+                AddResult::Placed(_) => break,
+                _ => panic!("table fragment did not fit"),
+            }
+        }
+
+        let doc = canvas.finish();
+        assert!(doc.pages.len() >= 3);
+        for page in &doc.pages {
+            let (text, y) = last_text_y(page).expect("text");
+            assert!(text == "FTR_SUM" || text == "FTR_TOTAL", "{text}");
+            assert!(y < Pt::from_f32(72.0));
+        }
+        assert!(page_contains_text(&doc.pages[0], "row-1"));
+        assert!(page_contains_text(
+            doc.pages.last().expect("page"),
+            "row-20"
+        ));
+    }
+
+    #[test]
+    fn table_split_draws_footer_once_when_not_repeated() {
+        let body: Vec<Vec<TableCell>> = (1..=8)
+            .map(|i| vec![table_cell(&i.to_string()), table_cell(&format!("row-{i}"))])
+            .collect();
+        let footer = vec![vec![table_cell("FTR_ONCE"), table_cell("FTR_ONCE_TOTAL")]];
+        let table = TableFlowable::new(body)
+            .with_footer(footer)
+            .repeat_footer(false);
+
+        let frame_rect = Rect {
+            x: Pt::ZERO,
+            y: Pt::ZERO,
+            width: Pt::from_f32(300.0),
+            height: Pt::from_f32(72.0),
+        };
+        let mut frame1 = Frame::new(frame_rect);
+        let mut canvas = Canvas::new(Size {
+            width: Pt::from_f32(300.0),
+            height: Pt::from_f32(200.0),
+        });
+        let second = match frame1.add(Box::new(table), &mut canvas) {
+            AddResult::Split(rest, _) => rest,
+            other => panic!(
+                "expected split, got variant {:?}",
+                std::mem::discriminant(&other)
+            ),
+        };
+
+        canvas.show_page();
+        let mut frame2 = Frame::new(frame_rect);
+        assert!(matches!(
+            frame2.add(second, &mut canvas),
+            AddResult::Placed(_)
+        ));
+
+        let doc = canvas.finish();
+        assert_eq!(doc.pages.len(), 2);
+        assert!(!page_contains_text(&doc.pages[0], "FTR_ONCE"));
+        let (text, _) = last_text_y(&doc.pages[1]).expect("text");
+        assert!(text.starts_with("FTR_ONCE"));
+    }
+
+    #[test]
+    fn html_tfoot_rows_close_every_table_fragment() {
+        let rows: String = (1..=60)
+            .map(|i| format!("<tr><td>{i}</td><td>item-{i}</td></tr>"))
+            .collect();
+        let html = format!(
+            "<table><thead><tr><th>No</th><th>Item</th></tr></thead>\
+             <tfoot><tr><td>Subtotal</td><td>carried</td></tr></tfoot>\
+             <tbody>{rows}</tbody></table>"
+        );
+        let css = "@page { size: 4in 4in; margin: 0.25in; } body { margin: 0; }";
+        let engine = FullBleed::builder().build().expect("engine");
+        let doc = engine.render_to_document(&html, css).expect("document");
+        assert!(doc.pages.len() >= 2);
+        for page in &doc.pages {
+            assert!(page_contains_text(page, "No"));
+            let (text, _) = last_text_y(page).expect("text");
+            assert!(text == "Subtotal" || text == "carried", "{text}");
+        }
+    }
+
+    #[test]
+    fn html_rowspan_cell_covers_following_rows() {
+        let html = "<table>\
+            <tr><td rowspan=\"2\" style=\"background: #ff0000\">Span</td><td>A1</td></tr>\
+            <tr><td>B1</td></tr>\
+            <tr><td>C0</td><td>C1</td></tr></table>";
+        let css = "@page { size: 4in 4in; margin: 0.25in; } body { margin: 0; } \
+            td { padding: 2pt; }";
+        let engine = FullBleed::builder().build().expect("engine");
+        let doc = engine.render_to_document(html, css).expect("document");
+        assert_eq!(doc.pages.len(), 1);
+        let commands = &doc.pages[0].commands;
+        let at = |needle: &str| {
+            commands
+                .iter()
+                .find_map(|cmd| match &*cmd {
+                    Command::DrawString { x, y, text } if text == needle => Some((*x, *y)),
+                    _ => None,
+                })
+                .unwrap_or_else(|| panic!("missing {needle}"))
+        };
+        assert_eq!(at("B1").0, at("A1").0, "B1 stays in the second column");
+        assert_eq!(at("C0").0, at("Span").0);
+        assert!(at("B1").1 > at("A1").1 && at("C0").1 > at("B1").1);
+
+        let red = Color::rgb(1.0, 0.0, 0.0);
+        let mut fill = Color::BLACK;
+        let mut span_rect = None;
+        for cmd in commands {
+            match &*cmd {
+                Command::SetFillColor(color) => fill = *color,
+                Command::DrawRect { y, height, .. } if fill == red => {
+                    span_rect = Some((*y, *height));
                 }
+                _ => {}
             }
         }
+        let (top, height) = span_rect.expect("span background");
+        assert!(top <= at("A1").1);
+        let bottom = top + height;
+        assert!(bottom > at("B1").1, "background covers the second row");
+        assert!(bottom <= at("C0").1, "background stops above the third row");
+    }
 
-        let left_x = left_x.expect("expected LEFT draw command");
-        let right_x = right_x.expect("expected RIGHT draw command");
-        let delta = right_x - left_x;
-        assert!(
-            delta > Pt::from_f32(50.0),
-            "expected RIGHT to be in a separate column, got delta={}",
-            delta.to_f32()
+    #[test]
+    fn auto_table_layout_sizes_nested_table_from_its_content() {
+        let html = "<table><tr><td>Qty</td>\
+            <td><table><tr><td>Widget description</td><td>Blue</td></tr></table></td>\
+            <td>Amount</td></tr></table>";
+        let css = "@page { size: 4in 4in; margin: 0.25in; } body { margin: 0; font-size: 10pt; } \
+            td { padding: 1pt; }";
+        let engine = FullBleed::builder().build().expect("engine");
+        let doc = engine.render_to_document(html, css).expect("document");
+        let commands = &doc.pages[0].commands;
+        let at = |needle: &str| {
+            commands
+                .iter()
+                .find_map(|cmd| match &*cmd {
+                    Command::DrawString { x, y, text } if text == needle => Some((*x, *y)),
+                    _ => None,
+                })
+                .unwrap_or_else(|| panic!("missing {needle}"))
+        };
+        let description = at("Widget description");
+        let swatch = at("Blue");
+        assert_eq!(description.1, swatch.1, "nested cells share one line");
+        assert!(at("Qty").0 < description.0);
+        assert!(swatch.0 < at("Amount").0);
+
+        let inner = TableFlowable::new(vec![vec![
+            table_cell("Widget description"),
+            table_cell("Blue"),
+        ]]);
+        let min = inner
+            .min_content_width()
+            .expect("auto tables report min-content");
+        let max = inner
+            .intrinsic_width()
+            .expect("auto tables report max-content");
+        assert!(min > Pt::ZERO && min < max);
+        let fixed = TableFlowable::new(vec![vec![table_cell("Widget")]])
+            .with_table_layout(TableLayoutMode::Fixed);
+        assert_eq!(fixed.intrinsic_width(), None);
+    }
+
+    #[test]
+    fn table_split_keeps_row_span_rows_together() {
+        let mut body: Vec<Vec<TableCell>> = (1..=3)
+            .map(|i| vec![table_cell(&format!("lead-{i}")), table_cell("x")])
+            .collect();
+        body.push(vec![
+            table_cell("SPAN_ORIGIN").with_row_span(3),
+            table_cell("s1"),
+        ]);
+        body.push(vec![table_cell("s2")]);
+        body.push(vec![table_cell("s3")]);
+        let table = TableFlowable::new(body);
+
+        let frame_rect = Rect {
+            x: Pt::ZERO,
+            y: Pt::ZERO,
+            width: Pt::from_f32(300.0),
+            height: Pt::from_f32(72.0),
+        };
+        let mut frame1 = Frame::new(frame_rect);
+        let mut canvas = Canvas::new(Size {
+            width: Pt::from_f32(300.0),
+            height: Pt::from_f32(200.0),
+        });
+        let second = match frame1.add(Box::new(table), &mut canvas) {
+            AddResult::Split(rest, _) => rest,
+            other => panic!(
+                "expected split, got variant {:?}",
+                std::mem::discriminant(&other)
+            ),
+        };
+        canvas.show_page();
+        let mut frame2 = Frame::new(frame_rect);
+        assert!(matches!(
+            frame2.add(second, &mut canvas),
+            AddResult::Placed(_)
+        ));
+
+        let doc = canvas.finish();
+        assert!(page_contains_text(&doc.pages[0], "lead-3"));
+        assert!(!page_contains_text(&doc.pages[0], "SPAN_ORIGIN"));
+        assert!(!page_contains_text(&doc.pages[0], "s1"));
+        for text in ["SPAN_ORIGIN", "s1", "s2", "s3"] {
+            assert!(page_contains_text(&doc.pages[1], text), "{text}");
+        }
+    }
+
+    #[test]
+    fn collapsed_border_prefers_wider_adjacent_edge_color() {
+        let left = table_cell_with_border(
+            "A",
+            EdgeSizes {
+                top: abs(0.0),
+                right: abs(1.0),
+                bottom: abs(0.0),
+                left: abs(0.0),
+            },
+            Color::rgb(1.0, 0.0, 0.0),
+        );
+        let right = table_cell_with_border(
+            "B",
+            EdgeSizes {
+                top: abs(0.0),
+                right: abs(0.0),
+                bottom: abs(0.0),
+                left: abs(4.0),
+            },
+            Color::rgb(0.0, 0.0, 1.0),
         );
+
+        let table = TableFlowable::new(vec![vec![left, right]])
+            .with_border_collapse(BorderCollapseMode::Collapse);
+
+        let mut frame = Frame::new(Rect {
+            x: Pt::ZERO,
+            y: Pt::ZERO,
+            width: Pt::from_f32(200.0),
+            height: Pt::from_f32(60.0),
+        });
+        let mut canvas = Canvas::new(Size {
+            width: Pt::from_f32(200.0),
+            height: Pt::from_f32(60.0),
+        });
+        let result = frame.add(Box::new(table), &mut canvas);
+        assert!(matches!(result, AddResult::Placed(_)));
+
+        let doc = canvas.finish();
+        let page = &doc.pages[0];
+        let mut current_fill = Color::BLACK;
+        let mut found_winner_edge = false;
+        for command in &page.commands {
+            match &*command {
+                Command::SetFillColor(color) => current_fill = *color,
+                Command::DrawRect { width, .. } => {
+                    if *width == Pt::from_f32(4.0) && current_fill == Color::rgb(0.0, 0.0, 1.0) {
+                        found_winner_edge = true;
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
         assert!(
-            delta < Pt::from_f32(220.0),
-            "colspan should not collapse following column to page edge, got delta={}",
-            delta.to_f32()
+            found_winner_edge,
+            "expected 4pt shared border drawn in blue"
         );
     }
 
     #[test]
-    fn html_table_layout_fixed_ignores_later_row_intrinsic_pressure() {
+    fn rendered_pages_emit_page_template_meta_for_finalize_binding() {
+        let html = "<!doctype html><html><body><p>hello</p></body></html>";
+        let css = "@page { size: letter; margin: 0.5in; }";
+        let engine = FullBleed::builder().build().expect("engine");
+        let doc = engine
+            .render_to_document(html, css)
+            .expect("render document");
+        assert!(!doc.pages.is_empty(), "expected at least one page");
+        for page in &doc.pages {
+            let has_template_meta = page.commands.iter().any(|cmd| {
+                matches!(
+                    &*cmd,
+                    Command::Meta { key, value }
+                    if key == META_PAGE_TEMPLATE_KEY && !value.trim().is_empty()
+                )
+            });
+            assert!(
+                has_template_meta,
+                "expected page to include {} metadata",
+                META_PAGE_TEMPLATE_KEY
+            );
+        }
+    }
+
+    #[test]
+    fn template_binding_accepts_feature_meta_from_plain_div_data_fb() {
+        let html = r#"
+<!doctype html>
+<html>
+  <body>
+    <section>
+      <div data-fb="fb.feature.red=1"></div>
+      <p>Page one marker</p>
+    </section>
+    <section style="page-break-before: always;">
+      <div data-fb="fb.feature.green=1"></div>
+      <p>Page two marker</p>
+    </section>
+  </body>
+</html>
+"#;
+        let css = "@page { size: letter; margin: 0.5in; }";
+
+        let spec = TemplateBindingSpec {
+            default_template_id: Some("tpl-default".to_string()),
+            by_feature: std::collections::BTreeMap::from([
+                ("red".to_string(), "tpl-red".to_string()),
+                ("green".to_string(), "tpl-green".to_string()),
+            ]),
+            ..TemplateBindingSpec::default()
+        };
+
+        let engine = FullBleed::builder()
+            .template_binding_spec(spec)
+            .build()
+            .expect("engine");
+
+        let (_pdf, _page_data, bindings) = engine
+            .render_with_page_data_and_template_bindings(html, css)
+            .expect("render");
+        let bindings = bindings.expect("expected bindings");
+        assert_eq!(bindings.len(), 2, "expected two pages");
+        assert_eq!(bindings[0].template_id, "tpl-red");
+        assert_eq!(bindings[0].source, BindingSource::Feature);
+        assert_eq!(bindings[1].template_id, "tpl-green");
+        assert_eq!(bindings[1].source, BindingSource::Feature);
+    }
+
+    #[test]
+    fn template_binding_honors_page_break_inside_transparent_div_wrapper() {
         let html = r#"
-            <!doctype html>
-            <html>
-              <body>
-                <table class="t">
-                  <tr><td>ROW1_LEFT</td><td>ROW1_RIGHT</td></tr>
-                  <tr><td>SUPERCALIFRAGILISTICEXTRAORDINARILYLONGTOKEN</td><td>R2</td></tr>
-                </table>
-              </body>
-            </html>
-        "#;
-        let css_fixed = r#"
-            @page { size: 4in 4in; margin: 0.25in; }
-            body { margin: 0; font-size: 12px; line-height: 1.2; }
-            table.t { border-collapse: collapse; width: 300px; table-layout: fixed; }
-            table.t td { border: 0; padding: 0; }
-        "#;
-        let css_auto = r#"
-            @page { size: 4in 4in; margin: 0.25in; }
-            body { margin: 0; font-size: 12px; line-height: 1.2; }
-            table.t { border-collapse: collapse; width: 300px; table-layout: auto; }
-            table.t td { border: 0; padding: 0; }
-        "#;
-
-        let engine = FullBleed::builder().build().expect("engine");
+<!doctype html>
+<html>
+  <body>
+    <div class="ui-stack">
+      <section>
+        <div data-fb="fb.feature.red=1"></div>
+        <p>Page one marker</p>
+      </section>
+      <section style="page-break-before: always;">
+        <div data-fb="fb.feature.green=1"></div>
+        <p>Page two marker</p>
+      </section>
+    </div>
+  </body>
+</html>
+"#;
+        let css = "@page { size: letter; margin: 0.5in; }";
 
-        let doc_fixed = engine
-            .render_to_document(html, css_fixed)
-            .expect("render fixed document");
-        let page_fixed = doc_fixed.pages.first().expect("fixed page");
-        let mut right_fixed_x: Option<Pt> = None;
-        for cmd in &page_fixed.commands {
-            if let Command::DrawString { text, x, .. } = cmd {
-                if text == "R2" {
-                    right_fixed_x = Some(*x);
-                }
-            }
-        }
-        let right_fixed_x = right_fixed_x.expect("expected R2 draw command in fixed layout");
+        let spec = TemplateBindingSpec {
+            default_template_id: Some("tpl-default".to_string()),
+            by_feature: std::collections::BTreeMap::from([
+                ("red".to_string(), "tpl-red".to_string()),
+                ("green".to_string(), "tpl-green".to_string()),
+            ]),
+            ..TemplateBindingSpec::default()
+        };
 
-        let doc_auto = engine
-            .render_to_document(html, css_auto)
-            .expect("render auto document");
-        let page_auto = doc_auto.pages.first().expect("auto page");
-        let mut right_auto_x: Option<Pt> = None;
-        for cmd in &page_auto.commands {
-            if let Command::DrawString { text, x, .. } = cmd {
-                if text == "R2" {
-                    right_auto_x = Some(*x);
-                }
-            }
-        }
-        let right_auto_x = right_auto_x.expect("expected R2 draw command in auto layout");
+        let engine = FullBleed::builder()
+            .template_binding_spec(spec)
+            .build()
+            .expect("engine");
 
-        assert!(
-            right_auto_x > right_fixed_x + Pt::from_f32(15.0),
-            "expected fixed layout to keep the right column further left than auto layout, fixed={} auto={}",
-            right_fixed_x.to_f32(),
-            right_auto_x.to_f32()
-        );
+        let (_pdf, _page_data, bindings) = engine
+            .render_with_page_data_and_template_bindings(html, css)
+            .expect("render");
+        let bindings = bindings.expect("expected bindings");
+        assert_eq!(bindings.len(), 2, "expected two pages");
+        assert_eq!(bindings[0].template_id, "tpl-red");
+        assert_eq!(bindings[0].source, BindingSource::Feature);
+        assert_eq!(bindings[1].template_id, "tpl-green");
+        assert_eq!(bindings[1].source, BindingSource::Feature);
     }
 
     #[test]
-    fn list_item_block_children_preserve_vertical_flow() {
+    fn template_binding_accepts_feature_meta_from_metadata_only_div_pages() {
         let html = r#"
-            <!doctype html>
-            <html>
-              <body>
-                <ul class="menu">
-                  <li>
-                    <div class="title">ITEMHEADONLY</div>
-                    <div class="desc">ITEMDESCONLY</div>
-                  </li>
-                </ul>
-              </body>
-            </html>
-        "#;
-        let css = r#"
-            @page { size: 4in 4in; margin: 0.25in; }
-            body { margin: 0; font-size: 14px; line-height: 1.2; }
-            ul, li { margin: 0; padding: 0; list-style: none; }
-            .title, .desc { display: block; }
-        "#;
-        let engine = FullBleed::builder().build().expect("engine");
-        let doc = engine
-            .render_to_document(html, css)
-            .expect("render document");
-        let page = doc.pages.first().expect("page");
+<!doctype html>
+<html>
+  <body>
+    <section>
+      <div data-fb="fb.feature.red=1"></div>
+    </section>
+    <section style="page-break-before: always;">
+      <div data-fb="fb.feature.green=1"></div>
+    </section>
+  </body>
+</html>
+"#;
+        let css = "@page { size: letter; margin: 0.5in; }";
 
-        let mut title_y: Option<Pt> = None;
-        let mut desc_y: Option<Pt> = None;
-        let mut merged_line = false;
-        for cmd in &page.commands {
-            if let Command::DrawString { text, y, .. } = cmd {
-                if text.contains("ITEMHEADONLY ITEMDESCONLY")
-                    || text.contains("ITEMDESCONLY ITEMHEADONLY")
-                {
-                    merged_line = true;
-                }
-                if text.contains("ITEMHEADONLY") {
-                    title_y = Some(*y);
-                }
-                if text.contains("ITEMDESCONLY") {
-                    desc_y = Some(*y);
-                }
-            }
-        }
+        let spec = TemplateBindingSpec {
+            default_template_id: Some("tpl-default".to_string()),
+            by_feature: std::collections::BTreeMap::from([
+                ("red".to_string(), "tpl-red".to_string()),
+                ("green".to_string(), "tpl-green".to_string()),
+            ]),
+            ..TemplateBindingSpec::default()
+        };
+
+        let engine = FullBleed::builder()
+            .template_binding_spec(spec)
+            .build()
+            .expect("engine");
+
+        let (_pdf, _page_data, bindings) = engine
+            .render_with_page_data_and_template_bindings(html, css)
+            .expect("render");
+        let bindings = bindings.expect("expected bindings");
+        assert_eq!(bindings.len(), 2, "expected two pages");
+        assert_eq!(bindings[0].template_id, "tpl-red");
+        assert_eq!(bindings[0].source, BindingSource::Feature);
+        assert_eq!(bindings[1].template_id, "tpl-green");
+        assert_eq!(bindings[1].source, BindingSource::Feature);
+    }
 
+    #[test]
+    fn fallback_chain_routes_uncovered_characters_by_script() {
+        let html = "<p>Area \u{222E} and \u{2660}</p>";
+        let css = "p { font-family: Inter; }";
+        let builder = FullBleed::builder()
+            .register_font_file(repo_font_path("Inter-Variable.ttf"))
+            .register_font_file(repo_font_path("NotoSansMath-Regular.ttf"))
+            .register_font_file(repo_font_path("NotoSansSymbols2-Regular.ttf"));
+
+        // Symbols2 is limited to CJK, so the spade has no fallback.
+        let engine = builder
+            .clone()
+            .font_fallback_for(FontScript::Cjk, "NotoSansSymbols2-Regular")
+            .font_fallback("NotoSansMath-Regular")
+            .build()
+            .expect("engine");
+        let (pdf, report) = engine.render_with_glyph_report(html, css).expect("render");
+        assert!(count_token(&pdf, b"+NotoSansMath-Regular") > 0);
+        let fallbacks = report.fallbacks();
+        assert_eq!(fallbacks.len(), 1);
+        assert_eq!(fallbacks[0].font, "NotoSansMath-Regular");
+        assert_eq!(fallbacks[0].script, "symbols");
+        assert_eq!(fallbacks[0].sample, "\u{222E}");
+        let missing = report.missing();
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].ch, '\u{2660}');
         assert!(
-            !merged_line,
-            "list-item block children should not collapse into one line"
+            missing[0]
+                .fonts_tried
+                .iter()
+                .any(|f| f == "NotoSansMath-Regular")
         );
-        let title_y = title_y.expect("expected title draw command");
-        let desc_y = desc_y.expect("expected description draw command");
         assert!(
-            (title_y.to_f32() - desc_y.to_f32()).abs() > 1.0,
-            "expected list-item block children on separate lines, got y={} and y={}",
-            title_y.to_f32(),
-            desc_y.to_f32()
+            !missing[0]
+                .fonts_tried
+                .iter()
+                .any(|f| f.contains("Symbols2"))
         );
+        assert_eq!(missing[0].pages, vec![1]);
+        assert_eq!(missing[0].element_paths.len(), 1);
+        assert!(missing[0].element_paths[0].ends_with("p:nth-of-type(1)"));
+        assert_eq!(
+            missing[0].suggested_font.as_deref(),
+            Some("NotoSansSymbols2-Regular")
+        );
+
+        let engine = builder
+            .font_fallback_for(FontScript::Symbols, "NotoSansSymbols2-Regular")
+            .font_fallback("NotoSansMath-Regular")
+            .build()
+            .expect("engine");
+        let (pdf, report) = engine.render_with_glyph_report(html, css).expect("render");
+        assert!(report.is_empty());
+        assert!(count_token(&pdf, b"+NotoSansSymbols2-Regular") > 0);
+        assert_eq!(FontScript::of(' '), None);
+        assert_eq!(FontScript::of('\u{4E2D}'), Some(FontScript::Cjk));
+        assert_eq!(FontScript::of('\u{1F600}'), Some(FontScript::Emoji));
     }
 
     #[test]
-    fn css_page_size_applies_when_builder_page_size_is_default() {
-        let html = "<!doctype html><html><body><p>hello</p></body></html>";
-        let css = "@page { size: letter; margin: 0.5in; }";
-        let engine = FullBleed::builder().build().expect("engine");
-        let doc = engine
-            .render_to_document(html, css)
-            .expect("render document");
-        assert!((doc.page_size.width.to_f32() - 612.0).abs() < 0.01);
-        assert!((doc.page_size.height.to_f32() - 792.0).abs() < 0.01);
+    fn glyph_report_locates_missing_glyphs_by_page_and_element() {
+        let html = "<p id=\"intro\">Suit \u{2660}</p>\
+                    <p style=\"break-before: page\">Plain text</p>\
+                    <ul style=\"break-before: page\"><li>One</li><li class=\"hand\">Two \u{2660}\u{2660}</li></ul>";
+        let engine = FullBleed::builder()
+            .register_font_file(repo_font_path("Inter-Variable.ttf"))
+            .register_font_file(repo_font_path("NotoSansSymbols2-Regular.ttf"))
+            .build()
+            .expect("engine");
+        let (_pdf, report) = engine
+            .render_with_glyph_report(html, "body { font-family: Inter; }")
+            .expect("render");
+
+        let missing = report.missing();
+        assert_eq!(missing.len(), 1);
+        let spade = &missing[0];
+        assert_eq!(spade.ch, '\u{2660}');
+        assert_eq!(spade.count, 3);
+        assert_eq!(spade.pages, vec![1, 3]);
+        assert_eq!(
+            spade.element_paths,
+            vec![
+                "html:nth-of-type(1) > body:nth-of-type(2) > p#intro:nth-of-type(1)",
+                "html:nth-of-type(1) > body:nth-of-type(2) > ul:nth-of-type(3) > li.hand:nth-of-type(2)",
+            ]
+        );
+        assert_eq!(
+            spade.suggested_font.as_deref(),
+            Some("NotoSansSymbols2-Regular")
+        );
     }
 
     #[test]
-    fn explicit_builder_page_size_logs_page_size_overridden() {
-        let log_path = temp_log_path("page_size_override");
-        let html = "<!doctype html><html><body><p>hello</p></body></html>";
-        let css = "@page { size: letter; }";
+    fn render_many_to_files_writes_one_named_pdf_per_record() {
         let engine = FullBleed::builder()
-            .page_size(Size::a4())
-            .debug_log(&log_path)
+            .paginated_context(PaginatedContextSpec::new(std::collections::HashMap::from(
+                [("account".to_string(), PageDataOp::Every)],
+            )))
             .build()
             .expect("engine");
-        let _ = engine
-            .render_to_document(html, css)
-            .expect("render document");
-        drop(engine);
-        let log = std::fs::read_to_string(&log_path).expect("read debug log");
-        assert!(log.contains("\"PAGE_SIZE_OVERRIDDEN\""));
-        let _ = std::fs::remove_file(log_path);
+        let html_list: Vec<String> = ["1001", "1002", "1003"]
+            .iter()
+            .map(|account| {
+                format!(
+                    "<p data-fb=\"account={account}\">Statement {account}</p>\
+                     <p style=\"break-before: page\">Details</p>"
+                )
+            })
+            .collect();
+        let account = |page_data: &PageDataContext| match page_data.totals.get("account") {
+            Some(PageDataValue::Every(values)) => values.first().cloned().unwrap_or_default(),
+            _ => String::new(),
+        };
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let dir = std::env::temp_dir().join(format!(
+            "fullbleed_render_many_to_files_{}_{}",
+            std::process::id(),
+            stamp
+        ));
+
+        let paths = engine
+            .render_many_to_files(&html_list, "", &dir, |idx, page_data| {
+                assert_eq!(page_data.page_count, 2);
+                format!("{idx:02}-{}.pdf", account(page_data))
+            })
+            .expect("render");
+        let names: Vec<String> = paths
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, vec!["00-1001.pdf", "01-1002.pdf", "02-1003.pdf"]);
+        for path in &paths {
+            let bytes = std::fs::read(path).expect("read pdf");
+            let parsed = lopdf::Document::load_mem(&bytes).expect("load pdf");
+            assert_eq!(parsed.get_pages().len(), 2);
+        }
+
+        let duplicate = engine.render_many_to_files(&html_list, "", &dir, |_, _| "same.pdf".into());
+        assert!(matches!(
+            duplicate,
+            Err(FullBleedError::InvalidConfiguration(_))
+        ));
+        let nested =
+            engine.render_many_to_files(&html_list, "", &dir, |idx, _| format!("../{idx}.pdf"));
+        assert!(matches!(
+            nested,
+            Err(FullBleedError::InvalidConfiguration(_))
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
     }
 
     #[test]
-    fn pagination_emits_page_break_trigger_event() {
-        let log_path = temp_log_path("page_break_trigger");
-        let html = "<!doctype html><html><body><p>one two three four five six seven eight nine ten eleven twelve thirteen fourteen fifteen sixteen seventeen eighteen nineteen twenty.</p><p>one two three four five six seven eight nine ten eleven twelve thirteen fourteen fifteen sixteen seventeen eighteen nineteen twenty.</p><p>one two three four five six seven eight nine ten eleven twelve thirteen fourteen fifteen sixteen seventeen eighteen nineteen twenty.</p></body></html>";
-        let css = "body { margin: 0; font-size: 14px; line-height: 1.2; }";
+    fn render_many_records_yields_each_record_with_its_page_data() {
         let engine = FullBleed::builder()
-            .page_size(Size::from_inches(3.0, 3.0))
-            .margin_all(18.0)
-            .debug_log(&log_path)
+            .paginated_context(PaginatedContextSpec::new(std::collections::HashMap::from(
+                [("account".to_string(), PageDataOp::Every)],
+            )))
             .build()
             .expect("engine");
-        let doc = engine
-            .render_to_document(html, css)
-            .expect("render document");
-        assert!(doc.pages.len() > 1);
-        drop(engine);
-        let log = std::fs::read_to_string(&log_path).expect("read debug log");
-        assert!(log.contains("\"PAGE_BREAK_TRIGGER\""));
-        let _ = std::fs::remove_file(log_path);
+        let html_list: Vec<String> = vec![
+            "<p data-fb=\"account=1001\">Statement</p>".to_string(),
+            "<p data-fb=\"account=1002\">Statement</p>\
+             <p style=\"break-before: page\">Details</p>"
+                .to_string(),
+        ];
+        let mut records = engine.render_many_records(&html_list, "");
+        assert_eq!(records.size_hint(), (2, Some(2)));
+        let first = records.next().expect("first").expect("render");
+        assert_eq!((first.index, first.page_count), (0, 1));
+        assert!(first.pdf.starts_with(b"%PDF-"));
+        assert!(first.missing_glyphs.is_empty());
+        let second = records.next().expect("second").expect("render");
+        assert_eq!((second.index, second.page_count), (1, 2));
+        let totals = second.page_data.expect("page data").totals;
+        assert!(matches!(
+            totals.get("account"),
+            Some(PageDataValue::Every(values)) if values == &["1002".to_string()]
+        ));
+        assert!(records.next().is_none());
+    }
+
+    #[test]
+    fn render_with_warnings_collects_asset_and_known_loss_warnings_and_calls_the_sink() {
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink_seen = seen.clone();
+        let engine = FullBleed::builder()
+            .page_size(Size::letter())
+            .on_warning(move |warning: &Warning| {
+                sink_seen.lock().unwrap().push(warning.code);
+            })
+            .build()
+            .expect("engine");
+        let html = "<link rel=\"stylesheet\" href=\"site.css\"><p>Hello</p>";
+        let (pdf, warnings) = engine
+            .render_with_warnings(html, "@page { size: A4; }")
+            .expect("render");
+        assert!(pdf.starts_with(b"%PDF-"));
+        let codes: Vec<&str> = warnings.iter().map(|w| w.code).collect();
+        assert!(codes.contains(&"ASSET_STYLESHEET_LINK"), "{codes:?}");
+        assert!(codes.contains(&"PAGE_SIZE_OVERRIDDEN"), "{codes:?}");
+        let link = warnings
+            .iter()
+            .find(|w| w.code == "ASSET_STYLESHEET_LINK")
+            .unwrap();
+        assert_eq!(link.kind, WarningKind::Asset);
+        assert_eq!(link.details, vec!["site.css".to_string()]);
+        assert_eq!(*seen.lock().unwrap(), codes);
+
+        // A clean render has nothing to report.
+        let (_, clean) = engine
+            .render_with_warnings("<p>Hello</p>", "")
+            .expect("render");
+        assert!(clean.is_empty(), "{clean:?}");
     }
 
     #[test]
-    fn html_table_row_collection_excludes_nested_tables() {
-        let log_path = temp_log_path("table_row_scope");
-        let html = r#"
-            <!doctype html>
-            <html>
-              <body>
-                <table class="outer">
-                  <tr>
-                    <td>
-                      <div>Outer left</div>
-                      <table class="inner">
-                        <tr>
-                          <td>Inner A</td>
-                          <td>Inner B</td>
-                          <td>Inner C</td>
-                        </tr>
-                      </table>
-                    </td>
-                    <td>Outer right</td>
-                  </tr>
-                </table>
-              </body>
-            </html>
-        "#;
-        let css = r#"
-            body { margin: 0; font-size: 10pt; }
-            table { border-collapse: collapse; width: 100%; }
-            td { border: 1px solid #333; padding: 2pt; }
-        "#;
+    fn strict_fidelity_fails_renders_that_hit_known_losses() {
         let engine = FullBleed::builder()
-            .page_size(Size::from_inches(4.0, 4.0))
-            .margin_all(0.0)
-            .debug_log(&log_path)
+            .page_size(Size::letter())
+            .strict_fidelity(true)
             .build()
             .expect("engine");
-        let _doc = engine
-            .render_to_document(html, css)
-            .expect("render document");
-        drop(engine);
+        let err = match engine.render_to_buffer("<p>Hello</p>", "@page { size: A4; }") {
+            Ok(_) => panic!("overridden @page size should fail a strict render"),
+            Err(err) => err,
+        };
+        assert_eq!(err.code(), ErrorCode::FidelityLoss);
+        let FullBleedError::FidelityLoss(losses) = err.kind() else {
+            panic!("unexpected error: {err}");
+        };
+        let codes: Vec<&str> = losses.iter().map(|loss| loss.code).collect();
+        assert_eq!(codes, vec!["PAGE_SIZE_OVERRIDDEN"]);
 
-        let log = std::fs::read_to_string(&log_path).expect("read debug log");
-        let table_rows_events: Vec<&str> = log
-            .lines()
-            .filter(|line| line.contains("\"type\":\"table.rows\""))
+        let err = engine
+            .render_to_buffer("<p>Hello</p>", "p { frobnicate: 1; }")
+            .expect_err("unsupported property should fail a strict render");
+        assert!(err.to_string().contains("frobnicate"), "{err}");
+
+        assert!(
+            engine
+                .render_to_buffer("<p>Hello</p>", "p { color: red; }")
+                .is_ok()
+        );
+        let stream = engine.render_stream_to_writer("<p>Hello</p>".as_bytes(), "", &mut Vec::new());
+        assert!(matches!(
+            stream,
+            Err(FullBleedError::InvalidConfiguration(_))
+        ));
+    }
+
+    #[test]
+    fn perf_logs_compare_per_span_and_flag_regressions() {
+        let path = temp_log_path("perf_compare");
+        {
+            let engine = FullBleed::builder()
+                .perf_log(&path)
+                .build()
+                .expect("engine");
+            engine.render_to_buffer("<p>Hello</p>", "").expect("render");
+        }
+        let same = compare_perf_logs(&path, &path, PerfCompareOptions::default()).expect("compare");
+        assert!(same.spans.iter().any(|span| span.name == "layout"));
+        assert!(!same.has_regressions());
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(path.with_file_name(format!(
+            "{}_hot.log",
+            path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default()
+        )));
+
+        let span = |name: &str, ms: f64| {
+            format!(
+                "{{\"type\":\"perf.span\",\"name\":\"{name}\",\"doc_id\":0,\"unit\":\"ms\",\"ms\":{ms}}}\n"
+            )
+        };
+        let baseline = [
+            span("layout", 10.0),
+            span("layout", 14.0),
+            span("plan", 0.2),
+        ]
+        .concat();
+        let current = [
+            span("layout", 15.0),
+            span("layout", 15.0),
+            span("plan", 0.4),
+            span("raster", 3.0),
+            "{\"type\":\"perf.counts\",\"name\":\"layout\"}\n".to_string(),
+        ]
+        .concat();
+        let comparison = compare_perf_log_text(&baseline, &current, PerfCompareOptions::default());
+        let names: Vec<&str> = comparison
+            .regressions()
+            .iter()
+            .map(|span| span.name.as_str())
+            .collect();
+        // plan doubled but by under a millisecond; raster has no baseline.
+        assert_eq!(names, vec!["layout"]);
+        let layout = &comparison.spans[0];
+        assert_eq!(layout.baseline.map(|stats| stats.count), Some(2));
+        assert!((layout.delta_ms - 3.0).abs() < 1e-9);
+        assert!((layout.delta_ratio.expect("ratio") - 0.25).abs() < 1e-9);
+
+        let report: serde_json::Value = serde_json::from_str(&comparison.to_json()).expect("json");
+        assert_eq!(report["schema_version"], PERF_COMPARISON_SCHEMA_VERSION);
+        assert_eq!(report["regressions"], serde_json::json!(["layout"]));
+        assert!(report["spans"][2]["baseline"].is_null());
+    }
+
+    #[test]
+    fn layout_profile_attributes_time_to_elements_and_operations() {
+        let engine = FullBleed::builder().build().expect("engine");
+        let rows: String = (0..40)
+            .map(|i| format!("<tr><td>Row {i}</td><td>{}</td></tr>", "word ".repeat(20)))
             .collect();
+        let html = format!(
+            "<p class=\"intro\">{}</p><table class=\"items\">{rows}</table>",
+            "Lorem ipsum ".repeat(200)
+        );
+        let (pdf, profile) = engine
+            .render_with_layout_profile(&html, "")
+            .expect("render");
+        assert!(pdf.starts_with(b"%PDF"));
+
+        let tables = profile.slowest_tables(5);
         assert!(
-            table_rows_events.len() >= 2,
-            "expected outer and inner table row events, got {}",
-            table_rows_events.len()
+            tables
+                .iter()
+                .any(|timing| timing.path.contains("> table.items") && timing.calls > 0),
+            "{tables:?}"
         );
+        let paragraphs = profile.slowest_paragraph_wraps(5);
         assert!(
-            table_rows_events[0].contains("\"total\":1"),
-            "outer table should only collect direct rows: {}",
-            table_rows_events[0]
+            paragraphs
+                .iter()
+                .any(|timing| timing.path.contains("> p.intro")),
+            "{paragraphs:?}"
         );
-        let _ = std::fs::remove_file(log_path);
+        assert!(!profile.slowest_subtrees(3).is_empty());
+        for line in profile.to_folded().lines() {
+            let (frames, micros) = line.rsplit_once(' ').expect("folded line");
+            assert!(frames.starts_with("layout;"), "{line}");
+            micros.parse::<u64>().expect("microseconds");
+        }
     }
 
     #[test]
-    fn paragraph_split_produces_two_parts() {
-        let text = "one two three four five six seven eight nine ten eleven twelve";
-        let mut style = TextStyle::default();
-        style.font_size = Pt::from_f32(10.0);
-        style.line_height = Pt::from_f32(12.0);
-        let paragraph = Paragraph::new(text)
-            .with_style(style)
-            .with_pagination(Pagination {
-                orphans: 1,
-                widows: 1,
-                ..Pagination::default()
-            });
-
-        let avail_width = Pt::from_f32(60.0);
-        let avail_height = Pt::from_f32(24.0);
-        let split = paragraph.split(avail_width, avail_height);
-        assert!(split.is_some());
-
-        let (first, second) = split.unwrap();
-        let first_size = first.wrap(avail_width, avail_height);
-        let second_size = second.wrap(avail_width, avail_height);
+    fn parallel_sections_match_single_threaded_layout() {
+        let mut html = String::from("<!doctype html><html><body>");
+        for chapter in 0..6 {
+            html.push_str(&format!(
+                "<h2 style=\"break-before: page\">Chapter {chapter}</h2>"
+            ));
+            for row in 0..40 {
+                html.push_str(&format!(
+                    "<p>Chapter {chapter} line {row} {}</p>",
+                    "text ".repeat(row % 7)
+                ));
+            }
+        }
+        html.push_str("</body></html>");
+        let render = |parallel: bool| {
+            FullBleed::builder()
+                .parallel_sections(parallel)
+                .build()
+                .expect("engine")
+                .render_to_buffer(&html, "p { margin: 4pt 0; }")
+                .expect("pdf")
+        };
 
-        assert!(first_size.height <= avail_height);
-        assert!(second_size.height > Pt::ZERO);
+        let sequential = render(false);
+        assert!(count_token(&sequential, b"/Type /Page /Parent") > 6);
+        assert_eq!(render(true), sequential);
     }
 
     #[test]
-    fn frame_overflows_on_extra_spacer() {
-        let mut frame = Frame::new(Rect {
-            x: Pt::ZERO,
-            y: Pt::ZERO,
-            width: Pt::from_f32(200.0),
-            height: Pt::from_f32(50.0),
-        });
-        let mut canvas = Canvas::new(Size {
-            width: Pt::from_f32(200.0),
-            height: Pt::from_f32(200.0),
-        });
+    fn render_report_types_known_losses_with_severity() {
+        let engine = FullBleed::builder()
+            .page_size(Size::letter())
+            .build()
+            .expect("engine");
+        let (pdf, report) = engine
+            .render_with_report(
+                "<p class=\"faded\">Hello</p>",
+                "@page { size: A4; } p.faded { opacity: 0.5; }",
+            )
+            .expect("render");
+        assert!(pdf.starts_with(b"%PDF"));
 
-        for _ in 0..10 {
-            let result = frame.add(Box::new(Spacer::new_pt(Pt::from_f32(5.0))), &mut canvas);
-            assert!(matches!(result, AddResult::Placed(_)));
-        }
+        assert!(report.known_losses.iter().any(|event| matches!(
+            event.loss,
+            KnownLoss::PageSizeOverridden { engine, .. } if engine == Size::letter()
+        )));
+        let faded = report
+            .known_losses
+            .iter()
+            .find(|event| event.loss.code() == "FILTERS_EFFECTS_FALLBACK")
+            .expect("opacity loss");
+        assert_eq!(
+            faded.loss,
+            KnownLoss::FiltersEffectsFallback {
+                property: "opacity".to_string()
+            }
+        );
+        assert_eq!(faded.severity, LossSeverity::Minor);
+        assert_eq!(faded.element.as_deref(), Some("p.faded"));
+        assert_eq!(report.max_severity(), Some(LossSeverity::Minor));
+        assert_eq!(
+            report.loss_counts().get("FILTERS_EFFECTS_FALLBACK"),
+            Some(&1)
+        );
 
-        let result = frame.add(Box::new(Spacer::new_pt(Pt::from_f32(5.0))), &mut canvas);
-        assert!(matches!(result, AddResult::Overflow(_, _)));
+        let (_pdf, clean) = engine
+            .render_with_report("<p>Hello</p>", "p { color: red; }")
+            .expect("render");
+        assert!(clean.known_losses.is_empty());
+        assert_eq!(clean.max_severity(), None);
     }
 
+    #[cfg(feature = "async")]
     #[test]
-    fn table_split_repeats_header_when_enabled() {
-        let header = vec![vec![table_cell("HDR_ID"), table_cell("HDR_NAME")]];
-        let body: Vec<Vec<TableCell>> = (1..=20)
-            .map(|i| vec![table_cell(&i.to_string()), table_cell(&format!("row-{i}"))])
-            .collect();
-        let table = TableFlowable::new(body)
-            .with_header(header)
-            .repeat_header(true);
+    fn async_facade_matches_blocking_renders_and_streams_records_in_order() {
+        let engine = Arc::new(FullBleed::builder().build().expect("engine"));
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .expect("runtime");
+        let html = "<p>Hello</p><p style=\"break-before: page\">World</p>";
+        let expected = engine.render_to_buffer(html, "").expect("blocking render");
 
-        let frame_rect = Rect {
-            x: Pt::ZERO,
-            y: Pt::ZERO,
-            width: Pt::from_f32(300.0),
-            height: Pt::from_f32(72.0),
-        };
-        let mut frame1 = Frame::new(frame_rect);
-        let mut canvas = Canvas::new(Size {
-            width: Pt::from_f32(300.0),
-            height: Pt::from_f32(200.0),
+        let mut written = Vec::new();
+        let len = runtime
+            .block_on(engine.render_to_writer_async(html, "", &mut written))
+            .expect("async render");
+        assert_eq!(len, written.len());
+        assert_eq!(written, expected);
+
+        let html_list = vec![
+            "<p>One</p>".to_string(),
+            html.to_string(),
+            "<p>Three</p>".to_string(),
+        ];
+        let records = runtime.block_on(async {
+            let mut stream = engine.render_many_stream(html_list, String::new());
+            let mut records = Vec::new();
+            while let Some(record) = stream.recv().await {
+                records.push(record.expect("record"));
+            }
+            records
         });
-        let second = match frame1.add(Box::new(table), &mut canvas) {
-            AddResult::Split(rest, _) => rest,
-            other => panic!(
-                "expected split, got variant {:?}",
-                std::mem::discriminant(&other)
-            ),
-        };
+        let indices: Vec<usize> = records.iter().map(|record| record.index).collect();
+        let page_counts: Vec<usize> = records.iter().map(|record| record.page_count).collect();
+        assert_eq!(indices, vec![0, 1, 2]);
+        assert_eq!(page_counts, vec![1, 2, 1]);
+        assert_eq!(
+            records[0].bytes,
+            engine
+                .render_to_buffer("<p>One</p>", "")
+                .expect("blocking render")
+        );
+    }
 
-        canvas.show_page();
-        let mut frame2 = Frame::new(frame_rect);
-        let result2 = frame2.add(second, &mut canvas);
-        assert!(matches!(
-            result2,
-            AddResult::Placed(_) | AddResult::Split(_, _)
-        ));
+    #[test]
+    fn progress_sink_sees_pages_records_and_bytes() {
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let engine = FullBleed::builder()
+            .progress(move |event| sink.lock().unwrap().push(event))
+            .build()
+            .expect("engine");
+        let html_list = vec![
+            "<p>One</p>".to_string(),
+            "<p>Two</p><p style=\"break-before: page\">Three</p>".to_string(),
+        ];
+        let mut out = Vec::new();
+        let written = engine
+            .render_many_to_writer(&html_list, "", &mut out)
+            .expect("render");
 
-        let doc = canvas.finish();
-        assert!(doc.pages.len() >= 2);
-        assert!(page_contains_text(&doc.pages[0], "HDR_ID"));
-        assert!(page_contains_text(&doc.pages[1], "HDR_ID"));
+        let events = events.lock().unwrap().clone();
+        let pages: Vec<(usize, usize)> = events
+            .iter()
+            .filter_map(|event| match event {
+                ProgressEvent::PageLaidOut {
+                    doc_id,
+                    page_number,
+                } => Some((*doc_id, *page_number)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(pages, vec![(0, 1), (1, 1), (1, 2)]);
+        let records: Vec<ProgressEvent> = events
+            .iter()
+            .copied()
+            .filter(|event| matches!(event, ProgressEvent::RecordCompleted { .. }))
+            .collect();
+        assert_eq!(
+            records,
+            vec![
+                ProgressEvent::RecordCompleted {
+                    index: 0,
+                    completed: 1,
+                    total: 2
+                },
+                ProgressEvent::RecordCompleted {
+                    index: 1,
+                    completed: 2,
+                    total: 2
+                },
+            ]
+        );
+        let bytes: usize = events
+            .iter()
+            .map(|event| match event {
+                ProgressEvent::BytesWritten { bytes } => *bytes,
+                _ => 0,
+            })
+            .sum();
+        assert_eq!(bytes, written);
+        assert_eq!(bytes, out.len());
     }
 
     #[test]
-    fn table_split_does_not_repeat_header_when_disabled() {
-        let header = vec![vec![table_cell("HDR_OFF"), table_cell("HDR_OFF_NAME")]];
-        let body: Vec<Vec<TableCell>> = (1..=20)
-            .map(|i| vec![table_cell(&i.to_string()), table_cell(&format!("row-{i}"))])
+    fn memory_budget_spills_queued_batch_documents_without_changing_output() {
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let dir = std::env::temp_dir().join(format!(
+            "fullbleed_memory_budget_{}_{}",
+            std::process::id(),
+            stamp
+        ));
+        let html_list: Vec<String> = (0..4)
+            .map(|idx| format!("<h1>Record {idx}</h1><p>Body {idx}</p>"))
             .collect();
-        let table = TableFlowable::new(body)
-            .with_header(header)
-            .repeat_header(false);
 
-        let frame_rect = Rect {
-            x: Pt::ZERO,
-            y: Pt::ZERO,
-            width: Pt::from_f32(300.0),
-            height: Pt::from_f32(72.0),
-        };
-        let mut frame1 = Frame::new(frame_rect);
-        let mut canvas = Canvas::new(Size {
-            width: Pt::from_f32(300.0),
-            height: Pt::from_f32(200.0),
-        });
-        let second = match frame1.add(Box::new(table), &mut canvas) {
-            AddResult::Split(rest, _) => rest,
-            other => panic!(
-                "expected split, got variant {:?}",
-                std::mem::discriminant(&other)
-            ),
+        let unbounded = FullBleed::builder().build().expect("engine");
+        let mut expected = Vec::new();
+        unbounded
+            .render_many_to_writer_parallel(&html_list, "", &mut expected)
+            .expect("render");
+        assert_eq!(unbounded.spill_metrics().documents, 0);
+        assert!(unbounded.spill_metrics().peak_resident_bytes > 0);
+
+        // A zero budget spills every document that has to wait; the first one never waits.
+        let budgeted = FullBleed::builder()
+            .memory_budget(MemoryBudget::new(0).spill_dir(&dir))
+            .build()
+            .expect("engine");
+        let mut out = Vec::new();
+        budgeted
+            .render_many_to_writer_parallel(&html_list, "", &mut out)
+            .expect("render");
+        assert_eq!(out, expected);
+        let metrics = budgeted.spill_metrics();
+        assert!(metrics.documents < html_list.len() as u64);
+        assert_eq!(metrics.bytes > 0, metrics.documents > 0);
+        let leftovers = std::fs::read_dir(&dir).expect("spill dir").count();
+        assert_eq!(leftovers, 0);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn merged_and_page_data_batches_go_through_the_document_queue() {
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let dir = std::env::temp_dir().join(format!(
+            "fullbleed_merged_budget_{}_{}",
+            std::process::id(),
+            stamp
+        ));
+        let html_list: Vec<String> = (0..4)
+            .map(|idx| format!("<h1>Record {idx}</h1><p>Body {idx}</p>"))
+            .collect();
+        let unbounded = FullBleed::builder().build().expect("engine");
+        let mut expected = Vec::new();
+        unbounded
+            .render_many_to_writer_parallel(&html_list, "", &mut expected)
+            .expect("render");
+        let (_, expected_page_data) = unbounded
+            .render_many_to_buffer_parallel_with_page_data(&html_list, "")
+            .expect("render");
+
+        let budgeted = || {
+            FullBleed::builder()
+                .memory_budget(MemoryBudget::new(0).spill_dir(&dir))
+                .build()
+                .expect("engine")
         };
+        // Every document passes through the queue, so each batch records a resident peak.
+        let engine = budgeted();
+        let merged = engine
+            .render_many_to_buffer_parallel(&html_list, "")
+            .expect("render");
+        assert_eq!(merged, expected);
+        assert!(engine.spill_metrics().peak_resident_bytes > 0);
+        assert!(engine.spill_metrics().documents < html_list.len() as u64);
+
+        let engine = budgeted();
+        let mut out = Vec::new();
+        let (bytes, page_data) = engine
+            .render_many_to_writer_parallel_with_page_data(&html_list, "", &mut out)
+            .expect("render");
+        assert_eq!(out, expected);
+        assert_eq!(bytes, out.len());
+        assert_eq!(page_data.len(), html_list.len());
+        assert_eq!(format!("{page_data:?}"), format!("{expected_page_data:?}"));
+        assert!(engine.spill_metrics().peak_resident_bytes > 0);
+        assert_eq!(std::fs::read_dir(&dir).expect("spill dir").count(), 0);
+        let _ = std::fs::remove_dir_all(&dir);
 
-        canvas.show_page();
-        let mut frame2 = Frame::new(frame_rect);
-        let result2 = frame2.add(second, &mut canvas);
         assert!(matches!(
-            result2,
-            AddResult::Placed(_) | AddResult::Split(_, _)
+            unbounded.render_many_to_buffer(&[], ""),
+            Err(FullBleedError::EmptyDocumentSet)
         ));
-
-        let doc = canvas.finish();
-        assert!(doc.pages.len() >= 2);
-        assert!(page_contains_text(&doc.pages[0], "HDR_OFF"));
-        assert!(!page_contains_text(&doc.pages[1], "HDR_OFF"));
     }
 
     #[test]
-    fn collapsed_border_prefers_wider_adjacent_edge_color() {
-        let left = table_cell_with_border(
-            "A",
-            EdgeSizes {
-                top: abs(0.0),
-                right: abs(1.0),
-                bottom: abs(0.0),
-                left: abs(0.0),
-            },
-            Color::rgb(1.0, 0.0, 0.0),
-        );
-        let right = table_cell_with_border(
-            "B",
-            EdgeSizes {
-                top: abs(0.0),
-                right: abs(0.0),
-                bottom: abs(0.0),
-                left: abs(4.0),
-            },
-            Color::rgb(0.0, 0.0, 1.0),
-        );
-
-        let table = TableFlowable::new(vec![vec![left, right]])
-            .with_border_collapse(BorderCollapseMode::Collapse);
-
-        let mut frame = Frame::new(Rect {
-            x: Pt::ZERO,
-            y: Pt::ZERO,
-            width: Pt::from_f32(200.0),
-            height: Pt::from_f32(60.0),
-        });
-        let mut canvas = Canvas::new(Size {
-            width: Pt::from_f32(200.0),
-            height: Pt::from_f32(60.0),
+    fn document_queue_spills_large_inline_images_before_whole_documents() {
+        use base64::Engine;
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let dir = std::env::temp_dir().join(format!(
+            "fullbleed_image_spill_{}_{}",
+            std::process::id(),
+            stamp
+        ));
+        // Noise does not compress, so the data URI is well past the spill threshold.
+        let mut seed = 0x2545_f491_u32;
+        let noise = image::RgbImage::from_fn(200, 200, |_, _| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            let [r, g, b, _] = seed.to_le_bytes();
+            image::Rgb([r, g, b])
         });
-        let result = frame.add(Box::new(table), &mut canvas);
-        assert!(matches!(result, AddResult::Placed(_)));
-
-        let doc = canvas.finish();
-        let page = &doc.pages[0];
-        let mut current_fill = Color::BLACK;
-        let mut found_winner_edge = false;
-        for command in &page.commands {
-            match command {
-                Command::SetFillColor(color) => current_fill = *color,
-                Command::DrawRect { width, .. } => {
-                    if *width == Pt::from_f32(4.0) && current_fill == Color::rgb(0.0, 0.0, 1.0) {
-                        found_winner_edge = true;
-                        break;
-                    }
-                }
-                _ => {}
-            }
-        }
-        assert!(
-            found_winner_edge,
-            "expected 4pt shared border drawn in blue"
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgb8(noise)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .expect("encode png");
+        let uri = format!(
+            "data:image/png;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(&png)
         );
+        let engine = FullBleed::builder().build().expect("engine");
+        let doc = engine
+            .render_to_document(
+                &format!("<p>Photo</p><img src=\"{uri}\" style=\"width: 100pt\">"),
+                "",
+            )
+            .expect("document");
+        let to_pdf = |doc: &Document| {
+            pdf::document_to_pdf_with_metrics_and_registry_with_logs(
+                doc,
+                None,
+                Some(engine.font_registry.as_ref()),
+                &engine.pdf_options,
+                None,
+                None,
+            )
+            .expect("pdf")
+        };
+        let expected = to_pdf(&doc);
+        let image_source = |doc: &Document| {
+            doc.pages[0]
+                .commands
+                .iter()
+                .find_map(|command| match &*command {
+                    Command::DrawImage { resource_id, .. } => Some(resource_id.to_string()),
+                    _ => None,
+                })
+        };
+        assert_eq!(image_source(&doc).as_deref(), Some(uri.as_str()));
+
+        let store = spill::SpillStore::new(&dir).expect("store");
+        // Room for the commands but not the image: the image goes to disk, the document stays.
+        let mut queue = spill::DocumentQueue::new(Some(&store), 64 * 1024, usize::MAX);
+        queue.push(1, doc.clone()).expect("push");
+        queue.push(2, doc.clone()).expect("push");
+        assert_eq!(store.metrics().0, 0);
+        assert_eq!(store.images_spilled(), 1);
+        let one = queue.take(1).expect("queued").expect("document");
+        let path = image_source(&one).expect("image");
+        assert_eq!(std::fs::read(&path).expect("spilled image"), png);
+        assert_eq!(to_pdf(&one), expected);
+
+        drop(queue);
+
+        // With no room at all the document follows its image to disk.
+        let mut queue = spill::DocumentQueue::new(Some(&store), 0, usize::MAX);
+        queue.push(4, doc.clone()).expect("push");
+        assert_eq!(store.metrics().0, 1);
+        assert_eq!(store.images_spilled(), 1);
+        let four = queue.take(4).expect("queued").expect("load");
+        assert_eq!(image_source(&four).as_deref(), Some(path.as_str()));
+        assert_eq!(to_pdf(&four), expected);
+
+        drop(queue);
+        drop(store);
+        assert_eq!(std::fs::read_dir(&dir).expect("spill dir").count(), 0);
+        let _ = std::fs::remove_dir_all(&dir);
     }
 
     #[test]
-    fn rendered_pages_emit_page_template_meta_for_finalize_binding() {
-        let html = "<!doctype html><html><body><p>hello</p></body></html>";
-        let css = "@page { size: letter; margin: 0.5in; }";
+    fn document_queue_spills_only_documents_that_wait_and_cleans_up_on_drop() {
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let dir = std::env::temp_dir().join(format!(
+            "fullbleed_queue_spill_{}_{}",
+            std::process::id(),
+            stamp
+        ));
         let engine = FullBleed::builder().build().expect("engine");
-        let doc = engine
-            .render_to_document(html, css)
-            .expect("render document");
-        assert!(!doc.pages.is_empty(), "expected at least one page");
-        for page in &doc.pages {
-            let has_template_meta = page.commands.iter().any(|cmd| {
-                matches!(
-                    cmd,
-                    Command::Meta { key, value }
-                    if key == META_PAGE_TEMPLATE_KEY && !value.trim().is_empty()
-                )
-            });
-            assert!(
-                has_template_meta,
-                "expected page to include {} metadata",
-                META_PAGE_TEMPLATE_KEY
-            );
-        }
+        let doc = |idx: usize| {
+            engine
+                .render_to_document(&format!("<p>Record {idx}</p>"), "")
+                .expect("document")
+        };
+        let first = spill::SpillStore::new(&dir).expect("store");
+        let second = spill::SpillStore::new(&dir).expect("store");
+        // Two stores on one directory keep their files apart.
+        assert_eq!(std::fs::read_dir(&dir).expect("spill dir").count(), 2);
+        let files = |store: &spill::SpillStore| store.metrics().0;
+
+        let mut queue = spill::DocumentQueue::new(Some(&first), 0, usize::MAX);
+        queue.push(2, doc(2)).expect("push");
+        queue.push(1, doc(1)).expect("push");
+        // Index 0 is written next, so it stays in memory despite the zero budget.
+        queue.push(0, doc(0)).expect("push");
+        assert_eq!(files(&first), 2);
+        assert!(queue.take(0).expect("first").is_ok());
+        queue.push(3, doc(3)).expect("push");
+        assert_eq!(files(&first), 3);
+        let one = queue.take(1).expect("second").expect("load");
+        assert_eq!(format!("{one:?}"), format!("{:?}", doc(1)));
+
+        let mut other = spill::DocumentQueue::new(Some(&second), 0, usize::MAX);
+        other.push(1, doc(1)).expect("push");
+        assert_eq!(files(&second), 1);
+
+        // Dropping a queue part way through removes the spill files it still holds.
+        drop(queue);
+        drop(other);
+        let spilled: usize = std::fs::read_dir(&dir)
+            .expect("spill dir")
+            .map(|entry| {
+                std::fs::read_dir(entry.expect("entry").path())
+                    .expect("store dir")
+                    .count()
+            })
+            .sum();
+        assert_eq!(spilled, 0);
+        drop(first);
+        drop(second);
+        assert_eq!(std::fs::read_dir(&dir).expect("spill dir").count(), 0);
+        let _ = std::fs::remove_dir_all(&dir);
     }
 
     #[test]
-    fn template_binding_accepts_feature_meta_from_plain_div_data_fb() {
-        let html = r#"
-<!doctype html>
-<html>
-  <body>
-    <section>
-      <div data-fb="fb.feature.red=1"></div>
-      <p>Page one marker</p>
-    </section>
-    <section style="page-break-before: always;">
-      <div data-fb="fb.feature.green=1"></div>
-      <p>Page two marker</p>
-    </section>
-  </body>
-</html>
-"#;
-        let css = "@page { size: letter; margin: 0.5in; }";
-
-        let mut spec = TemplateBindingSpec::default();
-        spec.default_template_id = Some("tpl-default".to_string());
-        spec.by_feature = std::collections::BTreeMap::from([
-            ("red".to_string(), "tpl-red".to_string()),
-            ("green".to_string(), "tpl-green".to_string()),
-        ]);
+    fn jit_spill_builder_spills_plan_and_replay_batches_per_engine() {
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let dir = std::env::temp_dir().join(format!(
+            "fullbleed_jit_spill_{}_{}",
+            std::process::id(),
+            stamp
+        ));
+        let html_list: Vec<String> = (0..3).map(|idx| format!("<p>Record {idx}</p>")).collect();
 
-        let engine = FullBleed::builder()
-            .template_binding_spec(spec)
+        let plain = FullBleed::builder()
+            .jit_mode(JitMode::PlanAndReplay)
             .build()
             .expect("engine");
+        let mut expected = Vec::new();
+        plain
+            .render_many_to_writer_parallel(&html_list, "", &mut expected)
+            .expect("render");
 
-        let (_pdf, _page_data, bindings) = engine
-            .render_with_page_data_and_template_bindings(html, css)
+        let spilling = FullBleed::builder()
+            .jit_mode(JitMode::PlanAndReplay)
+            .jit_spill(&dir, 0)
+            .build()
+            .expect("engine");
+        let mut out = Vec::new();
+        spilling
+            .render_many_to_writer_parallel(&html_list, "", &mut out)
             .expect("render");
-        let bindings = bindings.expect("expected bindings");
-        assert_eq!(bindings.len(), 2, "expected two pages");
-        assert_eq!(bindings[0].template_id, "tpl-red");
-        assert_eq!(bindings[0].source, BindingSource::Feature);
-        assert_eq!(bindings[1].template_id, "tpl-green");
-        assert_eq!(bindings[1].source, BindingSource::Feature);
+        assert_eq!(out, expected);
+        assert!(spilling.spill_metrics().documents < html_list.len() as u64);
+        assert_eq!(std::fs::read_dir(&dir).expect("spill dir").count(), 0);
+        let _ = std::fs::remove_dir_all(&dir);
     }
 
+    #[cfg(feature = "zstd")]
     #[test]
-    fn template_binding_honors_page_break_inside_transparent_div_wrapper() {
-        let html = r#"
-<!doctype html>
-<html>
-  <body>
-    <div class="ui-stack">
-      <section>
-        <div data-fb="fb.feature.red=1"></div>
-        <p>Page one marker</p>
-      </section>
-      <section style="page-break-before: always;">
-        <div data-fb="fb.feature.green=1"></div>
-        <p>Page two marker</p>
-      </section>
-    </div>
-  </body>
-</html>
-"#;
-        let css = "@page { size: letter; margin: 0.5in; }";
-
-        let mut spec = TemplateBindingSpec::default();
-        spec.default_template_id = Some("tpl-default".to_string());
-        spec.by_feature = std::collections::BTreeMap::from([
-            ("red".to_string(), "tpl-red".to_string()),
-            ("green".to_string(), "tpl-green".to_string()),
-        ]);
-
-        let engine = FullBleed::builder()
-            .template_binding_spec(spec)
+    fn zstd_spill_codec_shrinks_spills_and_plans_without_changing_output() {
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let dir = std::env::temp_dir().join(format!(
+            "fullbleed_zstd_spill_{}_{}",
+            std::process::id(),
+            stamp
+        ));
+        let html_list: Vec<String> = (0..3)
+            .map(|idx| {
+                format!(
+                    "<h1>Record {idx}</h1>{}",
+                    "<p>Repeated body text.</p>".repeat(40)
+                )
+            })
+            .collect();
+        let spill = |codec: SpillCodec| {
+            let engine = FullBleed::builder()
+                .memory_budget(MemoryBudget::new(0).spill_dir(&dir))
+                .spill_codec(codec)
+                .build()
+                .expect("engine");
+            let mut out = Vec::new();
+            engine
+                .render_many_to_writer_parallel(&html_list, "", &mut out)
+                .expect("render");
+            (out, engine.spill_metrics().bytes)
+        };
+        let (raw, raw_bytes) = spill(SpillCodec::None);
+        let (zstd, zstd_bytes) = spill(SpillCodec::Zstd { level: 3 });
+        assert_eq!(zstd, raw);
+        assert!(zstd_bytes < raw_bytes);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let plan = FullBleed::builder()
+            .jit_mode(JitMode::PlanAndReplay)
             .build()
-            .expect("engine");
+            .expect("engine")
+            .plan_document(&html_list[0], "")
+            .expect("plan");
+        let compressed = plan.to_bytes_with_codec(SpillCodec::Zstd { level: 3 });
+        assert!(compressed.len() < plan.to_bytes().len());
+        let decoded = DocPlan::from_bytes(&compressed).expect("decode");
+        assert_eq!(format!("{:?}", decoded.pages), format!("{:?}", plan.pages));
+    }
 
-        let (_pdf, _page_data, bindings) = engine
-            .render_with_page_data_and_template_bindings(html, css)
-            .expect("render");
-        let bindings = bindings.expect("expected bindings");
-        assert_eq!(bindings.len(), 2, "expected two pages");
-        assert_eq!(bindings[0].template_id, "tpl-red");
-        assert_eq!(bindings[0].source, BindingSource::Feature);
-        assert_eq!(bindings[1].template_id, "tpl-green");
-        assert_eq!(bindings[1].source, BindingSource::Feature);
+    #[test]
+    fn incremental_render_reuses_unchanged_page_streams() {
+        let engine = FullBleed::builder().build().expect("engine");
+        let html = |last: &str| {
+            format!(
+                "<p>Cover</p><p style=\"break-before: page\">Terms</p>\
+                 <p style=\"break-before: page\">{last}</p>"
+            )
+        };
+
+        let first = engine
+            .render_incremental(&html("Balance 10.00"), "", None)
+            .expect("first render");
+        assert_eq!(first.changed_pages, vec![1, 2, 3]);
+        assert_eq!(first.reused_pages, 0);
+        assert_eq!(first.cache.page_signatures().len(), 3);
+
+        let second = engine
+            .render_incremental(&html("Balance 12.50"), "", Some(&first.cache))
+            .expect("second render");
+        assert_eq!(second.changed_pages, vec![3]);
+        assert_eq!(second.reused_pages, 2);
+        assert_eq!(
+            second.pdf,
+            engine
+                .render_to_buffer(&html("Balance 12.50"), "")
+                .expect("fresh render")
+        );
     }
 
     #[test]
-    fn template_binding_accepts_feature_meta_from_metadata_only_div_pages() {
-        let html = r#"
-<!doctype html>
-<html>
-  <body>
-    <section>
-      <div data-fb="fb.feature.red=1"></div>
-    </section>
-    <section style="page-break-before: always;">
-      <div data-fb="fb.feature.green=1"></div>
-    </section>
-  </body>
-</html>
-"#;
-        let css = "@page { size: letter; margin: 0.5in; }";
+    fn compare_pdfs_scores_pages_and_draws_heatmaps() {
+        // Filled boxes rather than text, so rasterizing does not depend on system fonts.
+        let engine = FullBleed::builder().build().expect("engine");
+        let css = "div { width: 200pt; height: 40pt; margin-bottom: 20pt; background: #000; }";
+        let base = engine.render_to_buffer("<div></div>", css).expect("render");
+        let edited = engine
+            .render_to_buffer(
+                "<div></div><div></div><div style=\"break-before: page\"></div>",
+                css,
+            )
+            .expect("render");
 
-        let mut spec = TemplateBindingSpec::default();
-        spec.default_template_id = Some("tpl-default".to_string());
-        spec.by_feature = std::collections::BTreeMap::from([
-            ("red".to_string(), "tpl-red".to_string()),
-            ("green".to_string(), "tpl-green".to_string()),
-        ]);
+        let same = compare_pdfs(&base, &base, 36, 0.1).expect("compare");
+        assert!(same.is_match());
+        assert_eq!(same.pages.len(), 1);
+        assert_eq!(same.pages[0].perceptual_score, 0.0);
+
+        let diff = compare_pdfs(&base, &edited, 36, 0.1).expect("compare");
+        assert!(!diff.is_match());
+        assert_eq!(diff.differing_pages(), vec![1, 2]);
+        let first = &diff.pages[0];
+        assert!(first.pixel_score > 0.0 && first.pixel_score < 0.5);
+        assert!(first.perceptual_score > 0.0);
+        let heatmap = first.heatmap_png.as_deref().expect("heatmap");
+        assert!(heatmap.starts_with(b"\x89PNG"));
+        let second = &diff.pages[1];
+        assert_eq!(second.pixel_score, 1.0);
+        assert!(second.heatmap_png.is_none());
+        assert_eq!(diff.max_pixel_score(), 1.0);
+    }
 
-        let engine = FullBleed::builder()
-            .template_binding_spec(spec)
-            .build()
-            .expect("engine");
+    #[test]
+    fn extract_text_reads_documents_and_pdfs_in_reading_order() {
+        let engine = FullBleed::builder().build().expect("engine");
+        let html = "<h1>Invoice 1042</h1><p>Total due 12.50</p>\
+                    <p style=\"break-before: page\">Terms apply</p>";
+        let document = engine.render_to_document(html, "").expect("document");
+
+        let from_document = engine.extract_text(&document);
+        assert_eq!(from_document.len(), 2);
+        assert_eq!(from_document[0].page_number, 1);
+        assert_eq!(from_document[0].text(), "Invoice 1042\nTotal due 12.50");
+        assert_eq!(from_document[1].text(), "Terms apply");
+        let heading = &from_document[0].lines[0];
+        let total = &from_document[0].lines[1];
+        assert!(heading.bbox.y < total.bbox.y);
+        assert!(heading.bbox.height > total.bbox.height);
+        assert!(total.bbox.width > Pt::ZERO);
+
+        let estimated = document.extract_text();
+        assert_eq!(estimated.len(), 2);
+        assert_eq!(estimated[1].text(), "Terms apply");
+
+        let pdf = engine.render_to_buffer(html, "").expect("pdf");
+        let from_pdf = extract_text(&pdf).expect("extract");
+        assert_eq!(from_pdf.len(), 2);
+        assert_eq!(from_pdf[0].text(), "Invoice 1042\nTotal due 12.50");
+        assert_eq!(from_pdf[1].text(), "Terms apply");
+        let pdf_total = &from_pdf[0].lines[1];
+        assert!((pdf_total.bbox.x - total.bbox.x).abs() < Pt::from_f32(1.0));
+        assert!((pdf_total.bbox.y - total.bbox.y).abs() < Pt::from_f32(1.0));
 
-        let (_pdf, _page_data, bindings) = engine
-            .render_with_page_data_and_template_bindings(html, css)
-            .expect("render");
-        let bindings = bindings.expect("expected bindings");
-        assert_eq!(bindings.len(), 2, "expected two pages");
-        assert_eq!(bindings[0].template_id, "tpl-red");
-        assert_eq!(bindings[0].source, BindingSource::Feature);
-        assert_eq!(bindings[1].template_id, "tpl-green");
-        assert_eq!(bindings[1].source, BindingSource::Feature);
+        assert_eq!(
+            extract_text(b"not a pdf").unwrap_err().code,
+            PdfInspectErrorCode::PdfParseFailed
+        );
     }
 
     #[test]
@@ -7685,12 +13981,14 @@ mod tests {
 "#;
         let css = "@page { size: letter; margin: 0.5in; }";
 
-        let mut spec = TemplateBindingSpec::default();
-        spec.default_template_id = Some("tpl-default".to_string());
-        spec.by_feature = std::collections::BTreeMap::from([
-            ("red".to_string(), "tpl-red".to_string()),
-            ("green".to_string(), "tpl-green".to_string()),
-        ]);
+        let spec = TemplateBindingSpec {
+            default_template_id: Some("tpl-default".to_string()),
+            by_feature: std::collections::BTreeMap::from([
+                ("red".to_string(), "tpl-red".to_string()),
+                ("green".to_string(), "tpl-green".to_string()),
+            ]),
+            ..TemplateBindingSpec::default()
+        };
 
         let engine = FullBleed::builder()
             .template_binding_spec(spec)
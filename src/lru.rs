@@ -0,0 +1,139 @@
+// Least-recently-used map bounded by entry count and, optionally, by the summed weight of its
+// values (bytes for cached bodies), so a few large entries cannot pin unbounded memory.
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+#[derive(Debug)]
+pub(crate) struct LruCache<K, V> {
+    map: HashMap<K, (V, u64)>,
+    // Keys by the tick they were last used at; the first entry is evicted next.
+    order: BTreeMap<u64, K>,
+    tick: u64,
+    capacity: usize,
+    weigh: fn(&V) -> usize,
+    weight: usize,
+    max_weight: usize,
+}
+
+impl<K: Hash + Eq + Clone, V> LruCache<K, V> {
+    // A capacity of 0 keeps nothing.
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self::weighted(capacity, usize::MAX, |_| 0)
+    }
+
+    // Also evicts while the summed `weigh` of the values exceeds `max_weight`; a single value
+    // heavier than that is not kept at all.
+    pub(crate) fn weighted(capacity: usize, max_weight: usize, weigh: fn(&V) -> usize) -> Self {
+        Self {
+            map: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+            capacity,
+            weigh,
+            weight: 0,
+            max_weight,
+        }
+    }
+
+    pub(crate) fn get(&mut self, key: &K) -> Option<&V> {
+        let (value, used) = self.map.get_mut(key)?;
+        self.tick += 1;
+        if let Some(key) = self.order.remove(used) {
+            self.order.insert(self.tick, key);
+        }
+        *used = self.tick;
+        Some(value)
+    }
+
+    pub(crate) fn insert(&mut self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.tick += 1;
+        self.weight += (self.weigh)(&value);
+        if let Some((old, used)) = self.map.insert(key.clone(), (value, self.tick)) {
+            self.weight -= (self.weigh)(&old);
+            self.order.remove(&used);
+        }
+        self.order.insert(self.tick, key);
+        self.evict();
+    }
+
+    // Lookup that does not count as a use.
+    pub(crate) fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    // Entries in no particular order; iterating does not count as a use.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.map.iter().map(|(key, (value, _))| (key, value))
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.map.clear();
+        self.order.clear();
+        self.weight = 0;
+    }
+
+    pub(crate) fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.evict();
+    }
+
+    // Summed weight of the values currently kept.
+    #[cfg(test)]
+    pub(crate) fn weight(&self) -> usize {
+        self.weight
+    }
+
+    fn evict(&mut self) {
+        while self.map.len() > self.capacity || self.weight > self.max_weight {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            if let Some((value, _)) = self.map.remove(&oldest) {
+                self.weight -= (self.weigh)(&value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LruCache;
+
+    #[test]
+    fn least_recently_used_entries_are_evicted_first() {
+        let mut cache = LruCache::new(2);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        assert_eq!(cache.get(&"a"), Some(&1));
+        cache.insert("c", 3);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&"b"), None);
+        assert_eq!(cache.get(&"a"), Some(&1));
+        assert_eq!(cache.get(&"c"), Some(&3));
+    }
+
+    #[test]
+    fn weighted_cache_stays_within_its_weight_bound() {
+        let mut cache = LruCache::weighted(10, 100, |value: &Vec<u8>| value.len());
+        cache.insert("a", vec![0; 40]);
+        cache.insert("b", vec![0; 40]);
+        cache.insert("c", vec![0; 40]);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.weight(), 80);
+        assert!(cache.get(&"a").is_none());
+        // Replacing an entry swaps its weight rather than adding to it.
+        cache.insert("c", vec![0; 10]);
+        assert_eq!(cache.weight(), 50);
+        // Too heavy to keep on its own.
+        cache.insert("d", vec![0; 101]);
+        assert_eq!(cache.len(), 0);
+        assert_eq!(cache.weight(), 0);
+    }
+}
@@ -0,0 +1,268 @@
+// CSS Paged Media margin boxes (`@page { @top-center { ... } }`): the box rectangles around the
+// page area, and the per-page values of named strings (`string-set`) and running elements
+// (`position: running()`) that margin box content references.
+use crate::canvas::Command;
+use crate::command_buffer::CommandBuffer;
+use crate::counter::{CounterState, expand_counters};
+use crate::style::{COUNTER_FIELD, PAGE_REF_MARK, VerticalAlignMode};
+use crate::types::{Pt, Rect, Size};
+use std::collections::HashMap;
+
+// Layout emits one Meta command per assignment, keyed by one of these prefixes plus the name.
+pub(crate) const STRING_SET_META_PREFIX: &str = "fb.string-set.";
+pub(crate) const RUNNING_META_PREFIX: &str = "fb.running.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PageMarginBoxPosition {
+    TopLeftCorner,
+    TopLeft,
+    TopCenter,
+    TopRight,
+    TopRightCorner,
+    LeftTop,
+    LeftMiddle,
+    LeftBottom,
+    RightTop,
+    RightMiddle,
+    RightBottom,
+    BottomLeftCorner,
+    BottomLeft,
+    BottomCenter,
+    BottomRight,
+    BottomRightCorner,
+}
+
+impl PageMarginBoxPosition {
+    // The box inside the page margins around `area`. The three boxes on a side split the page
+    // area's extent into equal thirds; corner boxes take the adjoining margins.
+    pub(crate) fn rect(self, page: Size, area: Rect) -> Rect {
+        use PageMarginBoxPosition::*;
+        let right_x = area.x + area.width;
+        let bottom_y = area.y + area.height;
+        let right = (page.width - right_x).max(Pt::ZERO);
+        let bottom = (page.height - bottom_y).max(Pt::ZERO);
+        let third = |extent: Pt, index: i32| {
+            let start = extent.mul_ratio(index, 3);
+            (start, extent.mul_ratio(index + 1, 3) - start)
+        };
+        let (x, width) = match self {
+            TopLeftCorner | LeftTop | LeftMiddle | LeftBottom | BottomLeftCorner => {
+                (Pt::ZERO, area.x)
+            }
+            TopRightCorner | RightTop | RightMiddle | RightBottom | BottomRightCorner => {
+                (right_x, right)
+            }
+            TopLeft | BottomLeft => {
+                let (offset, width) = third(area.width, 0);
+                (area.x + offset, width)
+            }
+            TopCenter | BottomCenter => {
+                let (offset, width) = third(area.width, 1);
+                (area.x + offset, width)
+            }
+            TopRight | BottomRight => {
+                let (offset, width) = third(area.width, 2);
+                (area.x + offset, width)
+            }
+        };
+        let (y, height) = match self {
+            TopLeftCorner | TopLeft | TopCenter | TopRight | TopRightCorner => (Pt::ZERO, area.y),
+            BottomLeftCorner | BottomLeft | BottomCenter | BottomRight | BottomRightCorner => {
+                (bottom_y, bottom)
+            }
+            LeftTop | RightTop => {
+                let (offset, height) = third(area.height, 0);
+                (area.y + offset, height)
+            }
+            LeftMiddle | RightMiddle => {
+                let (offset, height) = third(area.height, 1);
+                (area.y + offset, height)
+            }
+            LeftBottom | RightBottom => {
+                let (offset, height) = third(area.height, 2);
+                (area.y + offset, height)
+            }
+        };
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    // Initial `text-align` from the CSS Paged Media box table.
+    pub(crate) fn default_text_align(self) -> &'static str {
+        use PageMarginBoxPosition::*;
+        match self {
+            TopLeftCorner | BottomLeftCorner | TopRight | BottomRight => "right",
+            TopRightCorner | BottomRightCorner | TopLeft | BottomLeft => "left",
+            _ => "center",
+        }
+    }
+
+    // Initial `vertical-align` from the CSS Paged Media box table.
+    pub(crate) fn default_vertical_align(self) -> VerticalAlignMode {
+        use PageMarginBoxPosition::*;
+        match self {
+            LeftTop | RightTop => VerticalAlignMode::Top,
+            LeftBottom | RightBottom => VerticalAlignMode::Bottom,
+            _ => VerticalAlignMode::Middle,
+        }
+    }
+}
+
+// Named string and running element values while pages are visited in order. `carried` holds
+// the last assignment made on earlier pages.
+#[derive(Debug)]
+pub(crate) struct PageValues {
+    carried: HashMap<String, String>,
+    page: Vec<(String, String)>,
+}
+
+impl PageValues {
+    pub(crate) fn new() -> Self {
+        Self {
+            carried: HashMap::new(),
+            page: Vec::new(),
+        }
+    }
+
+    // Starts the page whose layout commands are `commands`, carrying the previous page's
+    // assignments forward.
+    pub(crate) fn begin_page(&mut self, commands: &CommandBuffer) {
+        for (key, value) in self.page.drain(..) {
+            self.carried.insert(key, value);
+        }
+        collect_assignments(commands, &mut self.page);
+    }
+
+    // `first` (the default) takes the page's first assignment, `last` its last one, `start`
+    // the value the page opens with, and `first-except` is empty on pages that assign.
+    fn lookup(&self, key: &str, policy: &str) -> Option<&str> {
+        let mut on_page = self
+            .page
+            .iter()
+            .filter(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str());
+        let carried = self.carried.get(key).map(String::as_str);
+        match policy {
+            "last" => on_page.next_back().or(carried),
+            "start" => carried.or_else(|| on_page.next()),
+            "first-except" => match on_page.next() {
+                Some(_) => None,
+                None => carried,
+            },
+            _ => on_page.next().or(carried),
+        }
+    }
+}
+
+fn collect_assignments(commands: &CommandBuffer, out: &mut Vec<(String, String)>) {
+    for command in commands {
+        match &*command {
+            Command::Meta { key, value }
+                if key.starts_with(STRING_SET_META_PREFIX)
+                    || key.starts_with(RUNNING_META_PREFIX) =>
+            {
+                out.push((key.clone(), value.clone()));
+            }
+            Command::DefineForm { commands, .. } => collect_assignments(commands, out),
+            _ => {}
+        }
+    }
+}
+
+// Margin box content as HTML for page `page_number` of `total_pages`. Literal text and
+// `string()` values are escaped; `element()` inserts the running element's markup.
+pub(crate) fn margin_box_html(
+    content: &str,
+    page_number: usize,
+    total_pages: usize,
+    values: &PageValues,
+) -> String {
+    let mut counters = CounterState::new();
+    counters.reset("page", page_number as i32);
+    counters.reset("pages", total_pages as i32);
+    let mut out = String::new();
+    // Parts alternate between literal text and a reference, as with counters.
+    for (index, part) in content.split(PAGE_REF_MARK).enumerate() {
+        if index % 2 == 0 {
+            out.push_str(&crate::escape_html_text(&expand_counters(part, &counters)));
+            continue;
+        }
+        let mut fields = part.split(COUNTER_FIELD);
+        let kind = fields.next().unwrap_or("");
+        let name = fields.next().unwrap_or("");
+        let policy = fields.next().unwrap_or("first");
+        if kind == "element" {
+            if let Some(html) = values.lookup(&format!("{RUNNING_META_PREFIX}{name}"), policy) {
+                out.push_str(html);
+            }
+        } else if let Some(text) = values.lookup(&format!("{STRING_SET_META_PREFIX}{name}"), policy)
+        {
+            out.push_str(&crate::escape_html_text(text));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::style::COUNTER_MARK;
+
+    #[test]
+    fn margin_boxes_surround_the_page_area() {
+        let page = Size {
+            width: Pt::from_f32(600.0),
+            height: Pt::from_f32(800.0),
+        };
+        let area = Rect {
+            x: Pt::from_f32(60.0),
+            y: Pt::from_f32(50.0),
+            width: Pt::from_f32(480.0),
+            height: Pt::from_f32(690.0),
+        };
+        let center = PageMarginBoxPosition::TopCenter.rect(page, area);
+        assert_eq!(center.x, Pt::from_f32(220.0));
+        assert_eq!(center.width, Pt::from_f32(160.0));
+        assert_eq!(center.height, Pt::from_f32(50.0));
+        let corner = PageMarginBoxPosition::BottomRightCorner.rect(page, area);
+        assert_eq!(corner.x, Pt::from_f32(540.0));
+        assert_eq!(corner.y, Pt::from_f32(740.0));
+        assert_eq!(corner.width, Pt::from_f32(60.0));
+        assert_eq!(corner.height, Pt::from_f32(60.0));
+        let left = PageMarginBoxPosition::LeftMiddle.rect(page, area);
+        assert_eq!(left.y, Pt::from_f32(280.0));
+        assert_eq!(left.height, Pt::from_f32(230.0));
+        assert_eq!(PageMarginBoxPosition::TopLeft.default_text_align(), "left");
+    }
+
+    #[test]
+    fn string_references_follow_their_page_policy() {
+        let assign = |value: &str| Command::Meta {
+            key: format!("{STRING_SET_META_PREFIX}chapter"),
+            value: value.to_string(),
+        };
+        let reference = |policy: &str| {
+            format!(
+                "{PAGE_REF_MARK}string{COUNTER_FIELD}chapter{COUNTER_FIELD}{policy}{PAGE_REF_MARK}"
+            )
+        };
+        let mut values = PageValues::new();
+        values.begin_page(&vec![assign("One"), assign("Two & Three")].into());
+        assert_eq!(margin_box_html(&reference("first"), 1, 3, &values), "One");
+        assert_eq!(
+            margin_box_html(&reference("last"), 1, 3, &values),
+            "Two &amp; Three"
+        );
+        values.begin_page(&CommandBuffer::new());
+        assert_eq!(
+            margin_box_html(&reference("first-except"), 2, 3, &values),
+            "Two &amp; Three"
+        );
+        let page = format!("Page {COUNTER_MARK}page{COUNTER_FIELD}upper-roman{COUNTER_MARK}");
+        assert_eq!(margin_box_html(&page, 2, 3, &values), "Page II");
+    }
+}
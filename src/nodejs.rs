@@ -0,0 +1,315 @@
+// Node.js bindings (feature `nodejs`, napi-rs). Mirrors the python module: an engine built
+// from an options object, single and batch renders, page data as plain JS objects, and a
+// streaming batch that hands each record to a callback as soon as it is written.
+use crate::{
+    FullBleed, Margins, PageDataContext, PageDataValue, PaginatedContextSpec, PdfProfile,
+    PdfVersion, Pt, Size,
+};
+use napi::JsFunction;
+use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{
+    ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode,
+};
+use napi_derive::napi;
+use serde_json::{Map, Value, json};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+// Engine options. Lengths are points, or strings with a `pt`, `px`, `in`, `mm` or `cm` unit.
+#[napi(object)]
+#[derive(Default)]
+pub struct EngineOptions {
+    pub page_width: Option<Either<f64, String>>,
+    pub page_height: Option<Either<f64, String>>,
+    pub margin: Option<Either<f64, String>>,
+    pub font_dirs: Option<Vec<String>>,
+    pub font_files: Option<Vec<String>>,
+    pub font_fallbacks: Option<Vec<String>>,
+    pub pdf_profile: Option<String>,
+    pub pdf_version: Option<String>,
+    pub document_lang: Option<String>,
+    pub document_title: Option<String>,
+    pub shape_text: Option<bool>,
+    pub unicode_support: Option<bool>,
+    pub outline_levels: Option<u32>,
+    pub watermark_text: Option<String>,
+    // Key -> `every`, `count`, `sum` or `sum:<scale>`, as in the python `paginated_context`.
+    pub paginated_context: Option<HashMap<String, String>>,
+}
+
+#[napi(object)]
+pub struct PdfWithPageData {
+    pub pdf: Buffer,
+    pub page_data: Option<Value>,
+}
+
+// One record of `renderPdfBatchStream`; `error` is set instead of `pdf` when the record
+// failed.
+#[napi(object)]
+pub struct BatchRecord {
+    pub index: u32,
+    pub page_count: u32,
+    pub pdf: Option<Buffer>,
+    pub page_data: Option<Value>,
+    pub error: Option<String>,
+}
+
+#[napi]
+pub struct PdfEngine {
+    engine: Arc<FullBleed>,
+}
+
+#[napi]
+impl PdfEngine {
+    #[napi(constructor)]
+    pub fn new(options: Option<EngineOptions>) -> Result<Self> {
+        let engine = build_engine(options.unwrap_or_default())?;
+        Ok(Self {
+            engine: Arc::new(engine),
+        })
+    }
+
+    #[napi]
+    pub fn render_pdf(&self, html: String, css: String) -> Result<Buffer> {
+        let bytes = self.engine.render_to_buffer(&html, &css).map_err(to_js)?;
+        Ok(bytes.into())
+    }
+
+    #[napi]
+    pub fn render_pdf_with_page_data(&self, html: String, css: String) -> Result<PdfWithPageData> {
+        let (bytes, page_data) = self
+            .engine
+            .render_with_page_data(&html, &css)
+            .map_err(to_js)?;
+        Ok(PdfWithPageData {
+            pdf: bytes.into(),
+            page_data: page_data.as_ref().map(page_data_context_to_json),
+        })
+    }
+
+    // All records merged into one PDF.
+    #[napi]
+    pub fn render_pdf_batch(&self, html_list: Vec<String>, css: String) -> Result<Buffer> {
+        let bytes = self
+            .engine
+            .render_many_to_buffer(&html_list, &css)
+            .map_err(to_js)?;
+        Ok(bytes.into())
+    }
+
+    // Renders each record into its own PDF off the JS thread and calls `onRecord` with a
+    // `BatchRecord` as each one finishes. Resolves to the number of records that rendered.
+    #[napi(
+        ts_args_type = "htmlList: string[], css: string, onRecord: (record: BatchRecord) => void"
+    )]
+    pub fn render_pdf_batch_stream(
+        &self,
+        html_list: Vec<String>,
+        css: String,
+        on_record: JsFunction,
+    ) -> Result<AsyncTask<RenderBatchTask>> {
+        let on_record: ThreadsafeFunction<BatchRecord, ErrorStrategy::Fatal> = on_record
+            .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<BatchRecord>| {
+                Ok(vec![ctx.value])
+            })?;
+        Ok(AsyncTask::new(RenderBatchTask {
+            engine: self.engine.clone(),
+            html_list,
+            css,
+            on_record,
+        }))
+    }
+}
+
+pub struct RenderBatchTask {
+    engine: Arc<FullBleed>,
+    html_list: Vec<String>,
+    css: String,
+    on_record: ThreadsafeFunction<BatchRecord, ErrorStrategy::Fatal>,
+}
+
+impl Task for RenderBatchTask {
+    type Output = u32;
+    type JsValue = u32;
+
+    fn compute(&mut self) -> Result<u32> {
+        let mut rendered = 0u32;
+        for (index, record) in self
+            .engine
+            .render_many_records(&self.html_list, &self.css)
+            .enumerate()
+        {
+            let record = match record {
+                Ok(record) => {
+                    rendered += 1;
+                    BatchRecord {
+                        index: record.index as u32,
+                        page_count: record.page_count as u32,
+                        page_data: record.page_data.as_ref().map(page_data_context_to_json),
+                        pdf: Some(record.pdf.into()),
+                        error: None,
+                    }
+                }
+                Err(err) => BatchRecord {
+                    index: index as u32,
+                    page_count: 0,
+                    pdf: None,
+                    page_data: None,
+                    error: Some(err.to_string()),
+                },
+            };
+            self.on_record
+                .call(record, ThreadsafeFunctionCallMode::Blocking);
+        }
+        Ok(rendered)
+    }
+
+    fn resolve(&mut self, _env: Env, output: u32) -> Result<u32> {
+        Ok(output)
+    }
+}
+
+fn build_engine(options: EngineOptions) -> Result<FullBleed> {
+    let mut builder = FullBleed::builder();
+    match (&options.page_width, &options.page_height) {
+        (Some(width), Some(height)) => {
+            builder = builder.page_size(Size {
+                width: length(width, "pageWidth")?,
+                height: length(height, "pageHeight")?,
+            });
+        }
+        (None, None) => {}
+        _ => return Err(invalid("pageWidth and pageHeight must be given together")),
+    }
+    if let Some(margin) = &options.margin {
+        let all = length(margin, "margin")?;
+        builder = builder.margins(Margins {
+            top: all,
+            right: all,
+            bottom: all,
+            left: all,
+        });
+    }
+    for dir in options.font_dirs.unwrap_or_default() {
+        builder = builder.register_font_dir(dir);
+    }
+    for file in options.font_files.unwrap_or_default() {
+        builder = builder.register_font_file(file);
+    }
+    for family in options.font_fallbacks.unwrap_or_default() {
+        builder = builder.font_fallback(family);
+    }
+    if let Some(profile) = &options.pdf_profile {
+        builder = builder.pdf_profile(pdf_profile(profile)?);
+    }
+    if let Some(version) = &options.pdf_version {
+        builder = builder.pdf_version(pdf_version(version)?);
+    }
+    if let Some(lang) = options.document_lang {
+        builder = builder.document_lang(lang);
+    }
+    if let Some(title) = options.document_title {
+        builder = builder.document_title(title);
+    }
+    if let Some(enabled) = options.shape_text {
+        builder = builder.shape_text(enabled);
+    }
+    if let Some(enabled) = options.unicode_support {
+        builder = builder.unicode_support(enabled);
+    }
+    if let Some(levels) = options.outline_levels {
+        builder = builder.outline_from_headings(u8::try_from(levels).unwrap_or(u8::MAX));
+    }
+    if let Some(text) = options.watermark_text {
+        builder = builder.watermark_text(text);
+    }
+    if let Some(spec) = options.paginated_context {
+        let mut ops = HashMap::new();
+        for (key, raw) in spec {
+            let op = PaginatedContextSpec::parse_op(&raw).ok_or_else(|| {
+                invalid(&format!(
+                    "invalid paginatedContext op for key {key:?}: {raw:?}; expected 'every', 'count', 'sum' or 'sum:<scale>'"
+                ))
+            })?;
+            ops.insert(key, op);
+        }
+        builder = builder.paginated_context(PaginatedContextSpec::new(ops));
+    }
+    builder.build().map_err(to_js)
+}
+
+fn length(value: &Either<f64, String>, key: &str) -> Result<Pt> {
+    match value {
+        Either::A(points) => Ok(Pt::from_f32(*points as f32)),
+        Either::B(raw) => Pt::parse_length(raw, key).map_err(|err| invalid(&err.to_string())),
+    }
+}
+
+fn pdf_profile(raw: &str) -> Result<PdfProfile> {
+    match raw.trim().to_ascii_lowercase().as_str() {
+        "" | "none" => Ok(PdfProfile::None),
+        "pdfa2b" | "pdfa-2b" | "pdfa_2b" => Ok(PdfProfile::PdfA2b),
+        "pdfa3b" | "pdfa-3b" | "pdfa_3b" => Ok(PdfProfile::PdfA3b),
+        "pdfx4" | "pdfx-4" | "pdfx_4" => Ok(PdfProfile::PdfX4),
+        "tagged" | "pdfua" | "pdf/ua" => Ok(PdfProfile::Tagged),
+        other => Err(invalid(&format!("unknown pdfProfile: {other}"))),
+    }
+}
+
+fn pdf_version(raw: &str) -> Result<PdfVersion> {
+    match raw.trim().to_ascii_lowercase().as_str() {
+        "1.7" | "17" | "pdf1.7" => Ok(PdfVersion::Pdf17),
+        "2.0" | "2" | "20" | "pdf2.0" => Ok(PdfVersion::Pdf20),
+        other => Err(invalid(&format!("unknown pdfVersion: {other}"))),
+    }
+}
+
+// Same shape as the python `page_data` dict: 1-based `page` on each entry, sums carry their
+// scale and a formatted decimal string.
+fn page_data_context_to_json(ctx: &PageDataContext) -> Value {
+    let pages: Vec<Value> = ctx
+        .pages
+        .iter()
+        .enumerate()
+        .map(|(idx0, page)| {
+            let mut entry = Map::new();
+            entry.insert("page".to_string(), json!(idx0 + 1));
+            for (key, value) in page {
+                entry.insert(key.clone(), page_data_value_to_json(value));
+            }
+            Value::Object(entry)
+        })
+        .collect();
+    let totals: Map<String, Value> = ctx
+        .totals
+        .iter()
+        .map(|(key, value)| (key.clone(), page_data_value_to_json(value)))
+        .collect();
+    json!({
+        "page_count": ctx.page_count,
+        "pages": pages,
+        "totals": totals,
+    })
+}
+
+fn page_data_value_to_json(value: &PageDataValue) -> Value {
+    match value {
+        PageDataValue::Every(items) => json!({ "op": "every", "value": items }),
+        PageDataValue::Count(n) => json!({ "op": "count", "value": n }),
+        PageDataValue::Sum { scale, value } => json!({
+            "op": "sum",
+            "scale": scale,
+            "value": value,
+            "formatted": crate::page_data::format_scaled_int(*value, *scale),
+        }),
+    }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(Status::InvalidArg, message.to_string())
+}
+
+// The message leads with the stable error code so callers can match on it.
+fn to_js(err: crate::FullBleedError) -> Error {
+    Error::from_reason(format!("{}: {}", err.code(), err))
+}
@@ -74,10 +74,10 @@ pub fn compute_page_data_context(doc: &Document, spec: &PaginatedContextSpec) ->
     for page in &doc.pages {
         let mut raw: HashMap<String, Vec<String>> = HashMap::new();
         for cmd in &page.commands {
-            if let Command::Meta { key, value } = cmd {
-                if spec.ops.contains_key(key) {
-                    raw.entry(key.clone()).or_default().push(value.clone());
-                }
+            if let Command::Meta { key, value } = &*cmd
+                && spec.ops.contains_key(key)
+            {
+                raw.entry(key.clone()).or_default().push(value.clone());
             }
         }
 
@@ -348,13 +348,19 @@ mod tests {
                             key: "items.cost".to_string(),
                             value: "$2.50".to_string(),
                         },
-                    ],
+                    ]
+                    .into(),
+                    size: None,
+                    rotate: 0,
                 },
                 crate::Page {
                     commands: vec![Command::Meta {
                         key: "items.cost".to_string(),
                         value: "$3.25".to_string(),
-                    }],
+                    }]
+                    .into(),
+                    size: None,
+                    rotate: 0,
                 },
             ],
         };
@@ -1,22 +1,77 @@
 use crate::Canvas;
 use crate::doc_context::DocContext;
 use crate::frame::Frame;
-use crate::types::{Rect, Size};
+use crate::types::{Pt, Rect, Size};
 use std::sync::Arc;
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct FrameSpec {
     pub rect: Rect,
+    pub name: Option<String>,
+    // Frame the story continues in once this one is full. Unlinked frames hand over to the
+    // next frame in declaration order.
+    pub next: Option<String>,
+}
+
+impl FrameSpec {
+    pub fn new(rect: Rect) -> Self {
+        Self {
+            rect: rect.quantized(),
+            name: None,
+            next: None,
+        }
+    }
+
+    pub fn named(name: impl Into<String>, rect: Rect) -> Self {
+        Self {
+            name: Some(name.into()),
+            ..Self::new(rect)
+        }
+    }
+
+    pub fn linked_to(mut self, next: impl Into<String>) -> Self {
+        self.next = Some(next.into());
+        self
+    }
 }
 
 pub type OnPageCallback = Arc<dyn Fn(&mut Canvas, &DocContext) + Send + Sync>;
 
+// Odd pages are right-hand (recto) pages and even pages left-hand (verso) pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageParity {
+    Odd,
+    Even,
+}
+
+impl PageParity {
+    pub fn of(page_number: usize) -> Self {
+        if page_number.is_multiple_of(2) {
+            PageParity::Even
+        } else {
+            PageParity::Odd
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PageParity::Odd => "odd",
+            PageParity::Even => "even",
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct PageTemplate {
     pub name: String,
     pub page_size: Size,
     frames: Vec<FrameSpec>,
+    // Page area inside the margins when it differs from the first frame (frame layouts).
+    content_area: Option<Rect>,
     on_page: Option<OnPageCallback>,
+    page_name: Option<String>,
+    parity: Option<PageParity>,
+    rotate: u16,
 }
 
 impl PageTemplate {
@@ -25,17 +80,88 @@ impl PageTemplate {
             name: name.into(),
             page_size: page_size.quantized(),
             frames: Vec::new(),
+            content_area: None,
             on_page: None,
+            page_name: None,
+            parity: None,
+            rotate: 0,
         }
     }
 
+    // Reserve the template for content that asks for the named page (`page: <name>`) instead
+    // of selecting it by page index.
+    pub fn with_page_name(mut self, name: impl Into<String>) -> Self {
+        self.page_name = Some(name.into());
+        self
+    }
+
+    pub fn page_name(&self) -> Option<&str> {
+        self.page_name.as_deref()
+    }
+
+    // Use the template for pages of one parity: pages past the index-selected templates (or
+    // on the template's named page) alternate between the odd and even templates.
+    pub fn with_parity(mut self, parity: PageParity) -> Self {
+        self.parity = Some(parity);
+        self
+    }
+
+    pub fn parity(&self) -> Option<PageParity> {
+        self.parity
+    }
+
+    // Write pages of this template with a `/Rotate` of `degrees` (rounded to a multiple of
+    // 90). Layout still happens in the upright `page_size`; the PDF page is stored rotated
+    // back so viewers show it the way it was laid out.
+    pub fn with_rotate(mut self, degrees: i32) -> Self {
+        self.rotate = crate::canvas::normalize_rotate(degrees);
+        self
+    }
+
+    pub fn rotate(&self) -> u16 {
+        self.rotate
+    }
+
     pub fn with_frame(mut self, rect: Rect) -> Self {
+        self.frames.push(FrameSpec::new(rect));
+        self
+    }
+
+    pub fn with_frame_spec(mut self, spec: FrameSpec) -> Self {
         self.frames.push(FrameSpec {
-            rect: rect.quantized(),
+            rect: spec.rect.quantized(),
+            ..spec
         });
         self
     }
 
+    pub fn frames(&self) -> &[FrameSpec] {
+        &self.frames
+    }
+
+    // Replace the frames with `layout`, whose rectangles are relative to the top-left of the
+    // template's current content area (the page inside its margins).
+    pub(crate) fn with_frame_layout(mut self, layout: &[FrameSpec]) -> Self {
+        if layout.is_empty() {
+            return self;
+        }
+        let area = self.content_rect();
+        self.frames = layout
+            .iter()
+            .map(|spec| FrameSpec {
+                rect: Rect {
+                    x: area.x + spec.rect.x,
+                    y: area.y + spec.rect.y,
+                    ..spec.rect
+                }
+                .quantized(),
+                ..spec.clone()
+            })
+            .collect();
+        self.content_area = Some(area);
+        self
+    }
+
     pub fn set_on_page<F>(mut self, callback: F) -> Self
     where
         F: Fn(&mut Canvas, &DocContext) + Send + Sync + 'static,
@@ -48,10 +174,80 @@ impl PageTemplate {
         self.on_page.as_ref()
     }
 
+    // Height of the first frame, or the page when the template has none.
+    pub fn content_height(&self) -> Pt {
+        self.frames
+            .first()
+            .map(|spec| spec.rect.height)
+            .unwrap_or(self.page_size.height)
+    }
+
+    // Page area inside the margins: the first frame's rectangle unless a frame layout
+    // replaced the frames, or the whole page when the template has none.
+    pub fn content_rect(&self) -> Rect {
+        if let Some(area) = self.content_area {
+            return area;
+        }
+        self.frames.first().map(|spec| spec.rect).unwrap_or(Rect {
+            x: Pt::ZERO,
+            y: Pt::ZERO,
+            width: self.page_size.width,
+            height: self.page_size.height,
+        })
+    }
+
+    // Frames in the order the story fills them: from the first declared frame along `next`
+    // links. A frame without a link (or with an unknown or already used one) continues with
+    // the next unused frame in declaration order.
     pub fn instantiate_frames(&self) -> Vec<Frame> {
+        self.instantiate_flow_frames(&[])
+    }
+
+    // Main story frames, leaving out the frames named in `reserved` (those threaded content
+    // targets with `data-fb-frame`).
+    pub(crate) fn instantiate_flow_frames(&self, reserved: &[String]) -> Vec<Frame> {
+        self.flow_order(reserved)
+            .into_iter()
+            .map(|index| Frame::new(self.frames[index].rect))
+            .collect()
+    }
+
+    // Rectangles of the frames called `name`, in declaration order.
+    pub(crate) fn named_frame_rects(&self, name: &str) -> Vec<Rect> {
         self.frames
             .iter()
-            .map(|spec| Frame::new(spec.rect))
+            .filter(|spec| spec.name.as_deref() == Some(name))
+            .map(|spec| spec.rect)
             .collect()
     }
+
+    pub(crate) fn has_frame_named(&self, name: &str) -> bool {
+        self.frames
+            .iter()
+            .any(|spec| spec.name.as_deref() == Some(name))
+    }
+
+    fn flow_order(&self, reserved: &[String]) -> Vec<usize> {
+        let mut order: Vec<usize> = Vec::with_capacity(self.frames.len());
+        let mut used: Vec<bool> = self
+            .frames
+            .iter()
+            .map(|spec| {
+                spec.name
+                    .as_ref()
+                    .is_some_and(|name| reserved.contains(name))
+            })
+            .collect();
+        let mut current = used.iter().position(|used| !used);
+        while let Some(index) = current {
+            used[index] = true;
+            order.push(index);
+            let linked = self.frames[index].next.as_deref().and_then(|next| {
+                (0..self.frames.len())
+                    .find(|&i| !used[i] && self.frames[i].name.as_deref() == Some(next))
+            });
+            current = linked.or_else(|| used.iter().position(|used| !used));
+        }
+        order
+    }
 }
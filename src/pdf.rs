@@ -1,18 +1,30 @@
-use crate::canvas::{Command, Document, Page};
+use crate::canvas::{Command, Document, LinkTarget, Page};
+use crate::command_buffer::CommandBuffer;
 use crate::debug::json_escape;
-use crate::font::{FontProgramKind, FontRegistry, RegisteredFont};
+use crate::encrypt::{EncryptionSpec, PdfEncryptor};
+use crate::finalize::META_PAGE_LABEL_KEY;
+use crate::font::{FontProgramKind, FontRegistry, RegisteredFont, ShapedRun};
+use crate::known_loss::KnownLoss;
 use crate::metrics::{DocumentMetrics, PageMetrics};
 use crate::perf::PerfLogger;
-use crate::types::{Color, ColorSpace, MixBlendMode, Pt, Shading, ShadingStop, Size};
+use crate::progress::{ProgressEvent, ProgressHandle};
+use crate::types::{
+    Color, ColorSpace, MixBlendMode, ProcessColor, Pt, Shading, ShadingStop, Size, SpotName,
+};
+use crate::warning::{self, Warning, WarningHandle, WarningKind};
 use base64::Engine;
 use fixed::types::I32F32;
 use image::GenericImageView;
+use rayon::prelude::*;
 use rustybuzz::{
     Face as HbFace, Language as HbLanguage, Script as HbScript, ShapePlan, UnicodeBuffer,
 };
-use std::collections::{BTreeMap, BTreeSet, HashMap};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::Write as _;
 use std::io::{self, Write};
 use std::path::Path;
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub(crate) struct PdfOptions {
@@ -33,6 +45,42 @@ pub(crate) struct PdfOptions {
     pub compress_content_streams: bool,
     // Keep tiny streams uncompressed to avoid compression overhead.
     pub compress_content_stream_min_bytes: usize,
+    // zlib level (1..=9) for content streams; None uses the built-in fast encoder.
+    pub content_compression_level: Option<u32>,
+    // Encode the page content streams of a document together on the rayon pool.
+    pub parallel_content_compression: bool,
+    // Files embedded in the catalog /EmbeddedFiles name tree and /AF array.
+    pub attachments: Vec<EmbeddedFile>,
+    // Deepest heading level (1..=6) turned into outline entries; 0 disables the outline.
+    pub outline_levels: u8,
+    // Bytes reserved for a detached PKCS#7 signature; see `reserve_signature_byte_range`.
+    pub signature_reservation: Option<usize>,
+    // Standard security handler (AES-256) applied to every string and stream.
+    pub encryption: Option<EncryptionSpec>,
+    // Trailer /ID source; None writes no /ID unless encryption requires one.
+    pub document_id: Option<DocumentId>,
+    // Trim/bleed boxes and printer's marks around the laid-out page.
+    pub page_boxes: Option<PageBoxSpec>,
+    // Images placed above this resolution are resampled down to it before embedding.
+    pub max_image_dpi: Option<u32>,
+    // Embed only the TrueType glyphs a document uses (glyph ids preserved).
+    pub font_subsetting: bool,
+    // Logical page numbering (/PageLabels); overrides labels from batch records and CSS.
+    pub page_labels: Option<PageLabelSpec>,
+    // Receives the bytes written after each page and at the end of the file.
+    pub progress: Option<ProgressHandle>,
+    // Receives the known losses of PDF writing, such as WinAnsi character fallbacks.
+    pub warnings: Option<WarningHandle>,
+}
+
+impl PdfOptions {
+    // Emit a signature dictionary with a /ByteRange placeholder and `len` zeroed bytes of
+    // /Contents, for external signing with `patch_signature_byte_range` and
+    // `embed_signature_contents`.
+    pub fn reserve_signature_byte_range(mut self, len: usize) -> Self {
+        self.signature_reservation = Some(len.max(1));
+        self
+    }
 }
 
 impl Default for PdfOptions {
@@ -49,10 +97,107 @@ impl Default for PdfOptions {
             color_space: ColorSpace::Rgb,
             compress_content_streams: true,
             compress_content_stream_min_bytes: 128,
+            content_compression_level: None,
+            parallel_content_compression: true,
+            attachments: Vec::new(),
+            outline_levels: 0,
+            signature_reservation: None,
+            encryption: None,
+            document_id: None,
+            page_boxes: None,
+            max_image_dpi: None,
+            font_subsetting: true,
+            page_labels: None,
+            progress: None,
+            warnings: None,
         }
     }
 }
 
+// Numbering style of a page label range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageLabelStyle {
+    Decimal,
+    UpperRoman,
+    LowerRoman,
+    UpperAlpha,
+    LowerAlpha,
+    // Prefix only, no number.
+    None,
+}
+
+impl PageLabelStyle {
+    fn pdf_name(self) -> Option<&'static str> {
+        match self {
+            PageLabelStyle::Decimal => Some("D"),
+            PageLabelStyle::UpperRoman => Some("R"),
+            PageLabelStyle::LowerRoman => Some("r"),
+            PageLabelStyle::UpperAlpha => Some("A"),
+            PageLabelStyle::LowerAlpha => Some("a"),
+            PageLabelStyle::None => None,
+        }
+    }
+
+    pub(crate) fn from_pdf_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "D" => PageLabelStyle::Decimal,
+            "R" => PageLabelStyle::UpperRoman,
+            "r" => PageLabelStyle::LowerRoman,
+            "A" => PageLabelStyle::UpperAlpha,
+            "a" => PageLabelStyle::LowerAlpha,
+            _ => return None,
+        })
+    }
+}
+
+// Pages from `first_page` (1-based) up to the next range are labelled `prefix` followed by
+// `start`, `start + 1`, ... in `style`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageLabelRange {
+    pub first_page: usize,
+    pub style: PageLabelStyle,
+    pub prefix: Option<String>,
+    pub start: u32,
+}
+
+impl PageLabelRange {
+    pub fn new(first_page: usize, style: PageLabelStyle) -> Self {
+        Self {
+            first_page,
+            style,
+            prefix: None,
+            start: 1,
+        }
+    }
+
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    pub fn starting_at(mut self, start: u32) -> Self {
+        self.start = start.max(1);
+        self
+    }
+}
+
+// Logical page numbers shown by PDF viewers (/PageLabels), e.g. `i, ii, 1, 2, A-1`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PageLabelSpec {
+    pub ranges: Vec<PageLabelRange>,
+}
+
+impl PageLabelSpec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn range(mut self, range: PageLabelRange) -> Self {
+        self.ranges.push(range);
+        self
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PdfVersion {
     Pdf17,
@@ -63,6 +208,7 @@ pub enum PdfVersion {
 pub enum PdfProfile {
     None,
     PdfA2b,
+    PdfA3b,
     PdfX4,
     Tagged,
 }
@@ -86,6 +232,123 @@ struct TagRecord {
     col_index: Option<u16>,
 }
 
+// Link annotation whose object id is reserved on its page; written in finish() once every
+// named destination in the stream is known.
+#[derive(Debug, Clone)]
+struct LinkAnnotationRecord {
+    id: usize,
+    doc_id: usize,
+    page_id: usize,
+    rect: [Pt; 4],
+    target: LinkTarget,
+    // Innermost open tag when the link was drawn; its /Link element nests under it.
+    tag_parent: Option<usize>,
+}
+
+// Unsigned signature field widget; its annotation and empty appearance stream ids are
+// reserved on its page and written in finish() with the catalog /AcroForm.
+#[derive(Debug, Clone)]
+struct SignatureFieldRecord {
+    id: usize,
+    appearance_id: usize,
+    page_id: usize,
+    name: String,
+    rect: [Pt; 4],
+    tag_parent: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct OutlineRecord {
+    level: u8,
+    title: String,
+    page_index: usize,
+    x: Pt,
+    y: Pt,
+}
+
+// Metadata for one document (record) appended to a batch.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecordMetadata {
+    // Top-level outline entry at the record's first page; the record's own bookmarks nest
+    // under it. Also written as `dc:title`.
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub subject: Option<String>,
+    pub keywords: Option<String>,
+    // Page label prefix; labels restart at 1 for every record once any record sets one.
+    pub page_label_prefix: Option<String>,
+}
+
+// Pages one record occupies in the batch output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordRange {
+    pub doc_id: usize,
+    // 1-based number of the record's first page.
+    pub first_page: usize,
+    pub page_count: usize,
+    pub title: Option<String>,
+}
+
+// What a batch wrote: total bytes and one range per appended record, in order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BatchManifest {
+    pub bytes_written: usize,
+    pub records: Vec<RecordRange>,
+}
+
+// Print boxes around the laid-out page, which becomes the trim box. The media box grows by
+// the bleed, plus room for printer's marks when any are enabled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageBoxSpec {
+    // Bleed beyond the trim edge on every side.
+    pub bleed: Pt,
+    // Corner crop marks outside the bleed.
+    pub crop_marks: bool,
+    // Registration targets centered on each side, outside the bleed.
+    pub registration_marks: bool,
+}
+
+// Gap between the bleed edge and the marks, and the length of each mark.
+const PRINTER_MARK_GAP: f32 = 3.0;
+const PRINTER_MARK_LENGTH: f32 = 18.0;
+
+impl PageBoxSpec {
+    pub fn with_bleed(bleed: Pt) -> Self {
+        Self {
+            bleed: bleed.max(Pt::ZERO),
+            crop_marks: false,
+            registration_marks: false,
+        }
+    }
+
+    pub fn crop_marks(mut self) -> Self {
+        self.crop_marks = true;
+        self
+    }
+
+    pub fn registration_marks(mut self) -> Self {
+        self.registration_marks = true;
+        self
+    }
+
+    fn has_marks(&self) -> bool {
+        self.crop_marks || self.registration_marks
+    }
+
+    fn mark_offset(&self) -> Pt {
+        self.bleed + Pt::from_f32(PRINTER_MARK_GAP)
+    }
+
+    // Distance from the media box edge to the trim box edge.
+    fn media_margin(&self) -> Pt {
+        if self.has_marks() {
+            self.mark_offset() + Pt::from_f32(PRINTER_MARK_LENGTH)
+        } else {
+            self.bleed
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct OutputIntent {
     pub icc_profile: Vec<u8>,
@@ -110,6 +373,71 @@ impl OutputIntent {
     }
 }
 
+// How the trailer `/ID` is derived. Both halves are the same 16 bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DocumentId {
+    // SHA-256 of every object written, so identical input gives an identical ID.
+    ContentHash,
+    // SHA-256 of a caller-supplied seed (e.g. an invoice number).
+    Seed(String),
+    // The exact ID bytes.
+    Fixed([u8; 16]),
+}
+
+// Relationship of an embedded file to the document (PDF/A-3 `/AFRelationship`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AfRelationship {
+    Source,
+    Data,
+    Alternative,
+    Supplement,
+    Unspecified,
+}
+
+impl AfRelationship {
+    fn as_pdf_name(self) -> &'static str {
+        match self {
+            AfRelationship::Source => "Source",
+            AfRelationship::Data => "Data",
+            AfRelationship::Alternative => "Alternative",
+            AfRelationship::Supplement => "Supplement",
+            AfRelationship::Unspecified => "Unspecified",
+        }
+    }
+}
+
+// A file embedded into the output PDF (e.g. a Factur-X/ZUGFeRD invoice XML).
+#[derive(Debug, Clone)]
+pub struct EmbeddedFile {
+    pub name: String,
+    pub mime: String,
+    pub data: Vec<u8>,
+    pub relationship: AfRelationship,
+    pub description: Option<String>,
+}
+
+impl EmbeddedFile {
+    pub fn new(
+        name: impl Into<String>,
+        mime: impl Into<String>,
+        data: Vec<u8>,
+        relationship: AfRelationship,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            mime: mime.into(),
+            data,
+            relationship,
+            description: None,
+        }
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+}
+
 const PDF_CATALOG_ID: usize = 1;
 const PDF_PAGES_ID: usize = 2;
 const PDF_RESOURCES_ID: usize = 3;
@@ -117,11 +445,11 @@ const PDF_RESOURCES_ID: usize = 3;
 // Keep the page tree shallow but avoid huge /Kids arrays for large outputs.
 const PDF_PAGE_NODE_MAX_KIDS: usize = 256;
 
-#[derive(Clone)]
-struct ShapedText {
-    tj: String,
-    glyph_map: BTreeMap<u16, String>,
-}
+// Page content waiting for parallel compression is flushed past this many raw bytes, so one
+// long document does not hold all of its pages' content at once.
+const PENDING_CONTENT_LIMIT: usize = 32 << 20;
+// Content buffers kept for reuse once their pages are written.
+const SPARE_CONTENT_BUFFERS: usize = 64;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum StreamFontKind {
@@ -130,18 +458,19 @@ enum StreamFontKind {
     TrueTypeIdentityH,
 }
 
-struct StreamFont<'a> {
+struct StreamFont {
     logical_name: String,
     resource: String,
     encoding: FontEncoding,
     start_id: usize,
     kind: StreamFontKind,
     glyph_map: BTreeMap<u16, String>,
-    face: Option<HbFace<'a>>,
+    // Shaping parses the face on demand; the registry may evict @font-face data mid-render.
+    face_data: Option<Arc<[u8]>>,
     plans: HashMap<(rustybuzz::Direction, HbScript, Option<HbLanguage>), ShapePlan>,
 }
 
-impl StreamFont<'_> {
+impl StreamFont {
     fn font_object_id(&self) -> usize {
         match self.kind {
             StreamFontKind::Type1 => self.start_id,
@@ -156,19 +485,106 @@ struct PdfPageNode {
     kids: Vec<usize>,
 }
 
+// Page content streams of a render, keyed by the content they encode. Passing it to the
+// next render of the same engine lets unchanged pages reuse their encoded streams.
+#[derive(Debug, Clone, Default)]
+pub struct PageCache {
+    signatures: Vec<[u8; 32]>,
+    streams: HashMap<[u8; 32], EncodedContent>,
+}
+
+impl PageCache {
+    // SHA-256 of each page's content stream, in page order.
+    pub fn page_signatures(&self) -> &[[u8; 32]] {
+        &self.signatures
+    }
+
+    // 1-based numbers of the pages whose content differs from the same page in `previous`,
+    // including pages `previous` does not have.
+    pub fn changed_pages(&self, previous: &PageCache) -> Vec<usize> {
+        self.signatures
+            .iter()
+            .enumerate()
+            .filter(|(index, signature)| previous.signatures.get(*index) != Some(*signature))
+            .map(|(index, _)| index + 1)
+            .collect()
+    }
+}
+
+// A content stream as written: Flate-compressed, or plain below the compression threshold.
+#[derive(Debug, Clone)]
+struct EncodedContent {
+    raw_len: usize,
+    flate: bool,
+    bytes: std::sync::Arc<[u8]>,
+}
+
+// Content stream compression settings, copied out of the writer so pages can be encoded on
+// other threads.
+#[derive(Debug, Clone, Copy)]
+struct ContentCodec {
+    compress: bool,
+    min_bytes: usize,
+    level: Option<u32>,
+}
+
+impl ContentCodec {
+    fn should_compress(&self, len: usize) -> bool {
+        self.compress && len >= self.min_bytes
+    }
+
+    fn encode(&self, content: &[u8]) -> EncodedContent {
+        let flate = self.should_compress(content.len());
+        let bytes = match (flate, self.level) {
+            (false, _) => content.to_vec(),
+            (true, None) => flate_compress(content),
+            (true, Some(level)) => zlib_compress(content, level),
+        };
+        EncodedContent {
+            raw_len: content.len(),
+            flate,
+            bytes: bytes.into(),
+        }
+    }
+}
+
+struct PageCacheState {
+    previous: PageCache,
+    next: PageCache,
+    reused_pages: usize,
+}
+
+fn page_signature(content: &[u8]) -> [u8; 32] {
+    Sha256::digest(content).into()
+}
+
+// How often a stream writer drew an image or form XObject it had already embedded.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct XObjectReuse {
+    pub image_hits: u64,
+    pub form_hits: u64,
+    // Reuses of an XObject first embedded by another record of the batch.
+    pub cross_record_hits: u64,
+    // Image data and form content bytes that reuse kept from being embedded again.
+    pub bytes_saved: u64,
+}
+
 pub(crate) struct PdfStreamWriter<'a, W: Write> {
     writer: &'a mut W,
     offset: usize,
     offsets: Vec<usize>, // index by object id; 0 is the free object.
     next_id: usize,
+    // Size of the page being written; documents and pages may differ in size.
     page_size: Size,
+    // `/Rotate` of the page being written.
+    page_rotate: u16,
     options: PdfOptions,
     registry: Option<&'a FontRegistry>,
     debug: Option<std::sync::Arc<crate::debug::DebugLogger>>,
     perf: Option<std::sync::Arc<PerfLogger>>,
 
     // Resources
-    fonts: BTreeMap<String, StreamFont<'a>>,
+    fonts: BTreeMap<String, StreamFont>,
     next_font_resource: usize,
     current_doc_id: usize,
     doc_font_usage: BTreeMap<usize, BTreeSet<String>>,
@@ -185,31 +601,69 @@ pub(crate) struct PdfStreamWriter<'a, W: Write> {
     form_size_map: HashMap<String, Size>,
     next_form_index: usize,
 
+    // Image and form XObjects are shared by every record of the output. Each one's first
+    // record and embedded size, and how often later draws reused one.
+    xobject_origins: HashMap<String, (usize, usize)>,
+    xobject_reuse: XObjectReuse,
+
     gs_resources: Vec<(String, usize)>,
     gs_name_map: HashMap<(u16, u16), String>,
     gs_blend_name_map: HashMap<MixBlendMode, String>,
     next_gs_index: usize,
 
     shading_resources: Vec<(String, usize)>,
-    shading_name_map: HashMap<u64, String>,
+    shading_name_map: HashMap<(u64, i64), String>,
     next_shading_index: usize,
 
     optional_content_names: BTreeSet<String>,
 
+    separation_resources: Vec<(String, usize)>,
+    separation_name_map: HashMap<SpotName, String>,
+
     // Page tree
     page_nodes: Vec<PdfPageNode>,
     current_node: Option<PdfPageNode>,
 
-    // Text shaping cache (per document)
-    shaped_cache: HashMap<String, ShapedText>,
+    // Lookups in the registry's shaping cache.
+    shape_cache_hits: u64,
+    shape_cache_misses: u64,
+
+    // Link annotations + named destinations (keyed by doc id so batch documents stay isolated)
+    page_links: Vec<([Pt; 4], LinkTarget, Option<usize>)>,
+    link_annotations: Vec<LinkAnnotationRecord>,
+    page_signature_fields: Vec<(String, [Pt; 4], Option<usize>)>,
+    signature_fields: Vec<SignatureFieldRecord>,
+    named_destinations: HashMap<(usize, String), (usize, Pt, Pt)>,
+    // Exported destinations share one namespace across the output; the first occurrence wins.
+    exported_destinations: BTreeMap<String, (usize, Pt, Pt)>,
+    duplicate_exported_destinations: u64,
+    outline_records: Vec<OutlineRecord>,
+    // Batch record boundaries, with each record's page label prefix.
+    records: Vec<(RecordRange, Option<String>)>,
+    // Bookmarks of a titled record nest one level under its outline entry.
+    outline_level_offset: u8,
+    // XMP stream for the next page, the first page of a record with metadata.
+    pending_page_metadata: Option<usize>,
+    // Style of the page counter printed by CSS margin boxes, by page index.
+    css_page_labels: BTreeMap<usize, PageLabelStyle>,
+    // Output offset last reported to the progress sink.
+    progress_offset: usize,
 
     // Tagged PDF state
     tag_records: Vec<TagRecord>,
     page_ids: Vec<usize>,
     page_content_bytes: Vec<usize>,
+    // Rendered page content not written yet, by content object id, and buffers to reuse.
+    pending_contents: Vec<(usize, String)>,
+    pending_content_bytes: usize,
+    spare_content_buffers: Vec<String>,
     content_stream_raw_bytes: usize,
     content_stream_encoded_bytes: usize,
     content_stream_compressed_count: usize,
+
+    encryptor: Option<PdfEncryptor>,
+    content_hasher: Option<Sha256>,
+    page_cache: Option<PageCacheState>,
 }
 
 impl<'a, W: Write> PdfStreamWriter<'a, W> {
@@ -225,6 +679,16 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
         let mut offset: usize = 0;
         write_bytes(writer, pdf_header_bytes(options.pdf_version), &mut offset)?;
         write_bytes(writer, b"%\xE2\xE3\xCF\xD3\n", &mut offset)?;
+        let encryptor = options
+            .encryption
+            .as_ref()
+            .map(PdfEncryptor::new)
+            .transpose()?;
+        let content_hasher = (options.document_id == Some(DocumentId::ContentHash)).then(|| {
+            let mut hasher = Sha256::new();
+            hasher.update(pdf_header_bytes(options.pdf_version));
+            hasher
+        });
 
         let s = Self {
             writer,
@@ -232,6 +696,7 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
             offsets: vec![0; PDF_RESOURCES_ID + 1],
             next_id: PDF_RESOURCES_ID + 1,
             page_size,
+            page_rotate: 0,
             options,
             registry,
             debug,
@@ -250,6 +715,8 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
             form_content_map: HashMap::new(),
             form_size_map: HashMap::new(),
             next_form_index: 1,
+            xobject_origins: HashMap::new(),
+            xobject_reuse: XObjectReuse::default(),
             gs_resources: Vec::new(),
             gs_name_map: HashMap::new(),
             gs_blend_name_map: HashMap::new(),
@@ -258,36 +725,93 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
             shading_name_map: HashMap::new(),
             next_shading_index: 1,
             optional_content_names: BTreeSet::new(),
+            separation_resources: Vec::new(),
+            separation_name_map: HashMap::new(),
             page_nodes: Vec::new(),
             current_node: None,
-            shaped_cache: HashMap::new(),
+            shape_cache_hits: 0,
+            shape_cache_misses: 0,
+            page_links: Vec::new(),
+            link_annotations: Vec::new(),
+            page_signature_fields: Vec::new(),
+            signature_fields: Vec::new(),
+            named_destinations: HashMap::new(),
+            exported_destinations: BTreeMap::new(),
+            duplicate_exported_destinations: 0,
+            outline_records: Vec::new(),
+            records: Vec::new(),
+            outline_level_offset: 0,
+            pending_page_metadata: None,
+            css_page_labels: BTreeMap::new(),
+            progress_offset: 0,
             tag_records: Vec::new(),
             page_ids: Vec::new(),
             page_content_bytes: Vec::new(),
+            pending_contents: Vec::new(),
+            pending_content_bytes: 0,
+            spare_content_buffers: Vec::new(),
             content_stream_raw_bytes: 0,
             content_stream_encoded_bytes: 0,
             content_stream_compressed_count: 0,
+            encryptor,
+            content_hasher,
+            page_cache: None,
         };
 
         Ok(s)
     }
 
     pub(crate) fn add_document(&mut self, doc_id: usize, document: &Document) -> io::Result<()> {
-        // Guardrail: multi-doc streaming assumes a single page size.
-        if (document.page_size.width - self.page_size.width).abs() > Pt::from_f32(0.01)
-            || (document.page_size.height - self.page_size.height).abs() > Pt::from_f32(0.01)
-        {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "mixed page sizes are not supported in a single PDF stream",
-            ));
-        }
+        self.add_document_with_metadata(doc_id, document, None)
+    }
+
+    pub(crate) fn add_document_with_metadata(
+        &mut self,
+        doc_id: usize,
+        document: &Document,
+        metadata: Option<&RecordMetadata>,
+    ) -> io::Result<()> {
         validate_pdfx4_font_embedding(document, self.registry, &self.options)?;
         self.current_doc_id = doc_id;
-        self.shaped_cache.clear();
-        for page in &document.pages {
+        let first_page_index = self.page_ids.len();
+        let title = metadata.and_then(|meta| meta.title.clone());
+        self.outline_level_offset = if title.is_some() { 1 } else { 0 };
+        if !document.pages.is_empty()
+            && let Some(xmp) = metadata.and_then(record_xmp_metadata)
+        {
+            let id = self.alloc_ids(1);
+            self.write_object(id, &stream_object(&xmp))?;
+            self.pending_page_metadata = Some(id);
+        }
+        for (page_index, page) in document.pages.iter().enumerate() {
+            self.page_size = document.page_size_at(page_index);
+            self.page_rotate = page.rotate;
+            if page_index == 0
+                && let Some(title) = title.as_ref()
+            {
+                let (x, y) = self.media_point(Pt::ZERO, self.page_size.height);
+                self.outline_records.push(OutlineRecord {
+                    level: 1,
+                    title: title.clone(),
+                    page_index: first_page_index,
+                    x,
+                    y,
+                });
+            }
             self.add_page(page)?;
         }
+        self.flush_page_contents()?;
+        self.report_bytes_written();
+        self.outline_level_offset = 0;
+        self.records.push((
+            RecordRange {
+                doc_id,
+                first_page: first_page_index + 1,
+                page_count: document.pages.len(),
+                title,
+            },
+            metadata.and_then(|meta| meta.page_label_prefix.clone()),
+        ));
         Ok(())
     }
 
@@ -302,10 +826,19 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
             node.kids.push(page_id);
         }
 
-        let content_stream = self.render_page(page, page_index)?;
-        self.page_content_bytes
-            .push(content_stream.as_bytes().len());
-        self.write_content_stream_object(content_id, "", content_stream.as_bytes())?;
+        // With parallel compression, content streams are queued and encoded together when
+        // the document ends; the page object goes out first.
+        let mut content = self.spare_content_buffers.pop().unwrap_or_default();
+        content.clear();
+        self.render_page(&mut content, page, page_index)?;
+        self.page_content_bytes.push(content.len());
+        self.pending_content_bytes += content.len();
+        self.pending_contents.push((content_id, content));
+        if !self.options.parallel_content_compression
+            || self.pending_content_bytes >= PENDING_CONTENT_LIMIT
+        {
+            self.flush_page_contents()?;
+        }
         self.page_ids.push(page_id);
 
         let (struct_parents, tabs) = if self.options.pdf_profile == PdfProfile::Tagged {
@@ -313,24 +846,101 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
         } else {
             (String::new(), "")
         };
-        let page_boxes = page_box_entries(self.options.pdf_profile, self.page_size);
+        let links = std::mem::take(&mut self.page_links);
+        let mut annot_refs: Vec<String> = Vec::new();
+        if !links.is_empty() {
+            let first_id = self.alloc_ids(links.len());
+            annot_refs.extend((first_id..first_id + links.len()).map(|id| format!("{} 0 R", id)));
+            for (offset, (rect, target, tag_parent)) in links.into_iter().enumerate() {
+                self.link_annotations.push(LinkAnnotationRecord {
+                    id: first_id + offset,
+                    doc_id: self.current_doc_id,
+                    page_id,
+                    rect,
+                    target,
+                    tag_parent,
+                });
+            }
+        }
+        let mut page_fields = std::mem::take(&mut self.page_signature_fields);
+        if self.options.signature_reservation.is_some()
+            && self.page_ids.len() == 1
+            && page_fields.is_empty()
+        {
+            // The reserved signature needs a field; without one on the first page, sign
+            // through an invisible field there.
+            page_fields.push((
+                DEFAULT_SIGNATURE_FIELD_NAME.to_string(),
+                [Pt::ZERO; 4],
+                None,
+            ));
+        }
+        for (name, rect, tag_parent) in page_fields {
+            let id = self.alloc_ids(2);
+            annot_refs.push(format!("{} 0 R", id));
+            self.signature_fields.push(SignatureFieldRecord {
+                id,
+                appearance_id: id + 1,
+                page_id,
+                name,
+                rect,
+                tag_parent,
+            });
+        }
+        let annots = if annot_refs.is_empty() {
+            String::new()
+        } else {
+            format!(" /Annots [{}]", annot_refs.join(" "))
+        };
+        let media_size = self.media_size();
+        let page_boxes = page_box_entries(
+            self.options.pdf_profile,
+            media_size,
+            self.options.page_boxes.as_ref(),
+        );
+        let media_margin = self.page_origin();
+        let rotate = if self.page_rotate == 0 {
+            String::new()
+        } else {
+            format!(" /Rotate {}", self.page_rotate)
+        };
+        let metadata = self
+            .pending_page_metadata
+            .take()
+            .map(|id| format!(" /Metadata {} 0 R", id))
+            .unwrap_or_default();
         let page_obj = format!(
-            "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}]{} /Resources {} 0 R /Contents {} 0 R{}{} >>",
+            "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}]{}{} /Resources {} 0 R /Contents {} 0 R{}{}{}{} >>",
             parent_id,
-            fmt_pt(self.page_size.width),
-            fmt_pt(self.page_size.height),
+            fmt_pt(media_size.width + media_margin + media_margin),
+            fmt_pt(media_size.height + media_margin + media_margin),
             page_boxes,
+            rotate,
             PDF_RESOURCES_ID,
             content_id,
             struct_parents,
-            tabs
+            tabs,
+            annots,
+            metadata
         );
         self.write_object(page_id, &page_obj)?;
+        self.report_bytes_written();
         Ok(())
     }
 
-    pub(crate) fn finish(&mut self) -> io::Result<usize> {
-        let t_finish = std::time::Instant::now();
+    fn report_bytes_written(&mut self) {
+        if let Some(progress) = self.options.progress.as_ref() {
+            let bytes = self.offset - self.progress_offset;
+            if bytes > 0 {
+                progress.report(ProgressEvent::BytesWritten { bytes });
+            }
+        }
+        self.progress_offset = self.offset;
+    }
+
+    pub(crate) fn finish(&mut self) -> io::Result<BatchManifest> {
+        self.flush_page_contents()?;
+        let t_finish = crate::platform::Instant::now();
         if let Some(node) = self.current_node.take() {
             self.page_nodes.push(node);
         }
@@ -402,16 +1012,19 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
                         let font_file_id = font_state.start_id;
                         let descriptor_id = font_state.start_id + 1;
                         let font_id = font_state.start_id + 2;
-                        self.write_font_file_stream_object(
-                            font_file_id,
-                            &font.data,
-                            font.program_kind,
-                        )?;
+                        // Simple fonts can show any code in FirstChar..LastChar.
+                        let used_gids: BTreeSet<u16> =
+                            font.metrics.glyph_ids.iter().copied().collect();
+                        let base =
+                            self.write_font_program(font_file_id, &font, &used_gids, true)?;
                         self.write_object(
                             descriptor_id,
-                            &font_descriptor_object(font, font_file_id),
+                            &font_descriptor_object(&font, &base, font_file_id),
+                        )?;
+                        self.write_object(
+                            font_id,
+                            &truetype_font_object(&font, &base, descriptor_id),
                         )?;
-                        self.write_object(font_id, &truetype_font_object(font, descriptor_id))?;
                     }
                     StreamFontKind::TrueTypeIdentityH => {
                         let Some(font) = registry.resolve(&font_state.logical_name) else {
@@ -426,16 +1039,6 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
                         let to_unicode_id = font_state.start_id + 3;
                         let type0_font_id = font_state.start_id + 4;
 
-                        self.write_font_file_stream_object(
-                            font_file_id,
-                            &font.data,
-                            font.program_kind,
-                        )?;
-                        self.write_object(
-                            descriptor_id,
-                            &font_descriptor_object(font, font_file_id),
-                        )?;
-
                         let mut glyph_map = font_state.glyph_map.clone();
                         if glyph_map.is_empty() {
                             let gid = registry.map_glyph_id_for_char(&font.name, ' ');
@@ -444,6 +1047,12 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
                             }
                         }
                         let used_gids: BTreeSet<u16> = glyph_map.keys().copied().collect();
+                        let base =
+                            self.write_font_program(font_file_id, &font, &used_gids, false)?;
+                        self.write_object(
+                            descriptor_id,
+                            &font_descriptor_object(&font, &base, font_file_id),
+                        )?;
                         let mut w_entries: Vec<String> = Vec::new();
                         for gid in &used_gids {
                             let adv = registry.glyph_advance(&font.name, *gid);
@@ -463,7 +1072,7 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
                             cid_font_id,
                             &format!(
                                 "<< /Type /Font /Subtype /CIDFontType2 /BaseFont /{} /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >> /FontDescriptor {} 0 R {} /CIDToGIDMap /Identity >>",
-                                sanitize_font_name(&font.name),
+                                base,
                                 descriptor_id,
                                 w_array
                             ),
@@ -475,7 +1084,7 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
                             type0_font_id,
                             &format!(
                                 "<< /Type /Font /Subtype /Type0 /BaseFont /{} /Encoding /Identity-H /DescendantFonts [{} 0 R] /ToUnicode {} 0 R >>",
-                                sanitize_font_name(&font.name),
+                                base,
                                 cid_font_id,
                                 to_unicode_id
                             ),
@@ -524,6 +1133,12 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
                 shading_resources(&self.shading_resources)
             ));
         }
+        if !self.separation_resources.is_empty() {
+            resources.push(format!(
+                "/ColorSpace {}",
+                color_space_resources(&self.separation_resources)
+            ));
+        }
         if !optional_content_entries.is_empty() {
             resources.push(format!(
                 "/Properties {}",
@@ -566,18 +1181,56 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
         if self.options.pdf_profile == PdfProfile::Tagged {
             let tag_records = std::mem::take(&mut self.tag_records);
             let tag_count = tag_records.len();
-            let start_id = self.alloc_ids(tag_count + 2);
-            let parent_tree_id = start_id + tag_count;
-            let root_id = start_id + tag_count + 1;
-
+            // Annotation elements: /Link per link, then /Form per signature field.
+            let annotation_elems = self
+                .link_annotations
+                .iter()
+                .map(|link| ("Link", link.id, link.page_id, link.tag_parent))
+                .chain(
+                    self.signature_fields
+                        .iter()
+                        .map(|field| ("Form", field.id, field.page_id, field.tag_parent)),
+                )
+                .collect::<Vec<_>>();
+            let annot_count = annotation_elems.len();
+            let start_id = self.alloc_ids(tag_count + annot_count + 3);
+            let annot_start_id = start_id + tag_count;
+            let parent_tree_id = annot_start_id + annot_count;
+            let root_id = parent_tree_id + 1;
+            let document_id = root_id + 1;
+
+            // Child element object ids per tag; annotations nest under the tag they were drawn in.
             let mut children: Vec<Vec<usize>> = vec![Vec::new(); tag_count];
             for (idx, tag) in tag_records.iter().enumerate() {
                 if let Some(parent) = tag.parent {
                     if let Some(list) = children.get_mut(parent) {
-                        list.push(idx);
+                        list.push(start_id + idx);
                     }
                 }
             }
+            let mut root_kids: Vec<usize> = Vec::new();
+            for (offset, (role, annot_id, page_id, tag_parent)) in
+                annotation_elems.iter().enumerate()
+            {
+                let elem_id = annot_start_id + offset;
+                let parent_id = match tag_parent.filter(|p| *p < tag_count) {
+                    Some(parent) => {
+                        children[parent].push(elem_id);
+                        start_id + parent
+                    }
+                    None => {
+                        root_kids.push(elem_id);
+                        document_id
+                    }
+                };
+                self.write_object(
+                    elem_id,
+                    &format!(
+                        "<< /Type /StructElem /S /{} /P {} 0 R /Pg {} 0 R /K << /Type /OBJR /Obj {} 0 R >> >>",
+                        role, parent_id, page_id, annot_id
+                    ),
+                )?;
+            }
             let mut page_parent_tree: Vec<Vec<Option<usize>>> =
                 vec![Vec::new(); self.page_ids.len()];
             let mut header_map: HashMap<(u32, u16), usize> = HashMap::new();
@@ -588,19 +1241,18 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
                     }
                 }
             }
-            let mut root_kids: Vec<usize> = Vec::new();
             for (i, tag) in tag_records.iter().enumerate() {
                 if let Some(page_id) = self.page_ids.get(tag.page_index).copied() {
                     let id = start_id + i;
                     let role = escape_pdf_name(&tag.role);
-                    let parent_id = tag.parent.map(|p| start_id + p).unwrap_or(root_id);
+                    let parent_id = tag.parent.map(|p| start_id + p).unwrap_or(document_id);
                     let mut k_parts: Vec<String> = Vec::new();
                     if let Some(mcid) = tag.mcid {
                         k_parts.push(format!("{}", mcid));
                     }
                     if let Some(kids) = children.get(i) {
                         for child in kids {
-                            k_parts.push(format!("{} 0 R", start_id + *child));
+                            k_parts.push(format!("{} 0 R", child));
                         }
                     }
                     let k_entry = if k_parts.is_empty() {
@@ -656,6 +1308,15 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
                     .join(" ");
                 nums_entries.push(format!("{} [{}]", idx, refs));
             }
+            // Annotation keys follow the page keys, in element order (see /StructParent below).
+            let page_count = page_parent_tree.len();
+            for offset in 0..annot_count {
+                nums_entries.push(format!(
+                    "{} {} 0 R",
+                    page_count + offset,
+                    annot_start_id + offset
+                ));
+            }
             let parent_tree_obj = format!("<< /Nums [{}] >>", nums_entries.join(" "));
             self.write_object(parent_tree_id, &parent_tree_obj)?;
 
@@ -664,14 +1325,162 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
                 .map(|id| format!("{} 0 R", id))
                 .collect::<Vec<_>>()
                 .join(" ");
+            self.write_object(
+                document_id,
+                &format!(
+                    "<< /Type /StructElem /S /Document /P {} 0 R /K [{}] >>",
+                    root_id, kids
+                ),
+            )?;
             let root_obj = format!(
-                "<< /Type /StructTreeRoot /K [{}] /ParentTree {} 0 R >>",
-                kids, parent_tree_id
+                "<< /Type /StructTreeRoot /K [{} 0 R] /ParentTree {} 0 R /ParentTreeNextKey {} >>",
+                document_id,
+                parent_tree_id,
+                page_count + annot_count
             );
             self.write_object(root_id, &root_obj)?;
             struct_tree_root_id = Some(root_id);
         }
 
+        // Link annotations (object ids were reserved on their pages).
+        let link_annotations = std::mem::take(&mut self.link_annotations);
+        let mut unresolved_links = 0u64;
+        for (offset, link) in link_annotations.iter().enumerate() {
+            let mut action = match &link.target {
+                LinkTarget::Internal(name) => {
+                    let dest = self
+                        .named_destinations
+                        .get(&(link.doc_id, name.clone()))
+                        .and_then(|(page_index, x, y)| {
+                            self.page_ids
+                                .get(*page_index)
+                                .map(|page_id| (*page_id, *x, *y))
+                        });
+                    match dest {
+                        Some((page_id, x, y)) => format!(
+                            " /Dest [{} 0 R /XYZ {} {} null]",
+                            page_id,
+                            fmt_pt(x),
+                            fmt_pt(y)
+                        ),
+                        None => {
+                            unresolved_links += 1;
+                            String::new()
+                        }
+                    }
+                }
+                LinkTarget::Uri(uri) => format!(
+                    " /A << /Type /Action /S /URI /URI ({}) >>",
+                    escape_pdf_string(&uri_ascii(uri))
+                ),
+            };
+            if struct_tree_root_id.is_some() {
+                let contents = match &link.target {
+                    LinkTarget::Internal(name) => name.as_str(),
+                    LinkTarget::Uri(uri) => uri.as_str(),
+                };
+                action.push_str(&format!(
+                    " /Contents {} /StructParent {}",
+                    pdf_text_string(contents),
+                    self.page_ids.len() + offset
+                ));
+            }
+            self.write_object(link.id, &link_annotation_object(link.rect, &action))?;
+        }
+        if unresolved_links > 0
+            && let Some(logger) = self.debug.as_deref()
+        {
+            logger.increment("jit.link.unresolved", unresolved_links);
+        }
+
+        // Unsigned signature fields: /Sig widgets with an empty appearance, listed in the
+        // catalog /AcroForm. Field names must be unique, so repeats get a numeric suffix.
+        let signature_fields = std::mem::take(&mut self.signature_fields);
+        // The reservation always lands on the first field, which is on the first page.
+        let signature_value = match self.options.signature_reservation {
+            Some(len) if !signature_fields.is_empty() => {
+                let id = self.alloc_ids(1);
+                // Signature /Contents are never encrypted.
+                self.write_unencrypted_object(id, &signature_placeholder_object(len))?;
+                Some(id)
+            }
+            _ => None,
+        };
+        let mut field_names: HashSet<String> = HashSet::new();
+        let mut renamed_fields = 0u64;
+        for (offset, field) in signature_fields.iter().enumerate() {
+            let mut name = field.name.clone();
+            let mut suffix = 2;
+            while !field_names.insert(name.clone()) {
+                name = format!("{}_{}", field.name, suffix);
+                suffix += 1;
+            }
+            if name != field.name {
+                renamed_fields += 1;
+            }
+            let [x0, y0, x1, y1] = field.rect;
+            self.write_stream_object_bytes(
+                field.appearance_id,
+                &format!(
+                    "/Type /XObject /Subtype /Form /BBox [0 0 {} {}]",
+                    fmt_pt(x1 - x0),
+                    fmt_pt(y1 - y0)
+                ),
+                b"",
+            )?;
+            let mut obj = format!(
+                "<< /Type /Annot /Subtype /Widget /FT /Sig /T {} /Rect [{} {} {} {}] /F 4 /P {} 0 R /AP << /N {} 0 R >>",
+                pdf_text_string(&name),
+                fmt_pt(x0),
+                fmt_pt(y0),
+                fmt_pt(x1),
+                fmt_pt(y1),
+                field.page_id,
+                field.appearance_id
+            );
+            if let (0, Some(id)) = (offset, signature_value) {
+                obj.push_str(&format!(" /V {} 0 R", id));
+            }
+            if struct_tree_root_id.is_some() {
+                obj.push_str(&format!(
+                    " /TU {} /StructParent {}",
+                    pdf_text_string(&name),
+                    self.page_ids.len() + link_annotations.len() + offset
+                ));
+            }
+            obj.push_str(" >>");
+            self.write_object(field.id, &obj)?;
+        }
+        if renamed_fields > 0
+            && let Some(logger) = self.debug.as_deref()
+        {
+            logger.increment("jit.signature.renamed", renamed_fields);
+        }
+        let acroform = if signature_fields.is_empty() {
+            None
+        } else {
+            let refs = signature_fields
+                .iter()
+                .map(|field| format!("{} 0 R", field.id))
+                .collect::<Vec<_>>()
+                .join(" ");
+            // SigFlags 3: signatures exist, and the file must only be updated incrementally.
+            let sig_flags = if signature_value.is_some() {
+                " /SigFlags 3"
+            } else {
+                ""
+            };
+            Some(format!("<< /Fields [{}]{} >>", refs, sig_flags))
+        };
+
+        // Document outline (bookmarks), nested by heading level.
+        let outline_records = std::mem::take(&mut self.outline_records);
+        let outlines_id = if outline_records.is_empty() {
+            None
+        } else {
+            Some(self.write_outline(&outline_records)?)
+        };
+
         // 5) Compliance objects + Catalog.
         let mut metadata_id: Option<usize> = None;
         let mut output_intent_id: Option<usize> = None;
@@ -697,6 +1506,55 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
             }
         }
 
+        // Embedded files: stream + filespec per attachment, referenced from the
+        // /EmbeddedFiles name tree (sorted by name) and the catalog /AF array.
+        let attachments = std::mem::take(&mut self.options.attachments);
+        let mut embedded_file_entries: Vec<(String, usize)> = Vec::new();
+        for attachment in &attachments {
+            let stream_id = self.alloc_ids(1);
+            let spec_id = self.alloc_ids(1);
+            self.write_embedded_file_stream_object(stream_id, attachment)?;
+            self.write_object(spec_id, &filespec_object(attachment, stream_id))?;
+            embedded_file_entries.push((attachment.name.clone(), spec_id));
+        }
+        let af_ids = embedded_file_entries
+            .iter()
+            .map(|(_, id)| *id)
+            .collect::<Vec<_>>();
+        embedded_file_entries.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut name_trees: Vec<String> = Vec::new();
+
+        // Exported named destinations (/Dests name tree, sorted by name) for deep links
+        // such as `file.pdf#nameddest=summary`.
+        let exported_destinations = std::mem::take(&mut self.exported_destinations);
+        let mut dest_entries: Vec<(String, usize)> = Vec::new();
+        for (name, (page_index, x, y)) in &exported_destinations {
+            let Some(page_id) = self.page_ids.get(*page_index).copied() else {
+                continue;
+            };
+            let id = self.alloc_ids(1);
+            self.write_object(
+                id,
+                &format!("[{} 0 R /XYZ {} {} null]", page_id, fmt_pt(*x), fmt_pt(*y)),
+            )?;
+            dest_entries.push((name.clone(), id));
+        }
+        if !dest_entries.is_empty() {
+            name_trees.push(format!("/Dests {}", name_tree_dict(&dest_entries)));
+        }
+        if self.duplicate_exported_destinations > 0
+            && let Some(logger) = self.debug.as_deref()
+        {
+            logger.increment("jit.dest.duplicate", self.duplicate_exported_destinations);
+        }
+
+        if !embedded_file_entries.is_empty() {
+            name_trees.push(format!(
+                "/EmbeddedFiles {}",
+                name_tree_dict(&embedded_file_entries)
+            ));
+        }
+
         let mut catalog = format!("<< /Type /Catalog /Pages {} 0 R", PDF_PAGES_ID);
         if let Some(lang) = doc_lang.as_deref() {
             catalog.push_str(&format!(" /Lang ({})", escape_pdf_string(lang)));
@@ -728,9 +1586,50 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
                 id
             ));
         }
+        if let Some(id) = outlines_id {
+            catalog.push_str(&format!(" /Outlines {} 0 R /PageMode /UseOutlines", id));
+        }
+        let page_labels = match self.options.page_labels.as_ref() {
+            Some(spec) => spec_page_labels(spec, self.page_ids.len()),
+            None => record_page_labels(&self.records).or_else(|| {
+                css_page_labels(&self.css_page_labels, &self.records, self.page_ids.len())
+            }),
+        };
+        if let Some(labels) = page_labels {
+            catalog.push_str(&format!(" /PageLabels {}", labels));
+        }
+        if let Some(acroform) = acroform.as_deref() {
+            catalog.push_str(&format!(" /AcroForm {}", acroform));
+        }
+        if !name_trees.is_empty() {
+            catalog.push_str(&format!(" /Names << {} >>", name_trees.join(" ")));
+        }
+        if !af_ids.is_empty() {
+            let refs = af_ids
+                .iter()
+                .map(|id| format!("{} 0 R", id))
+                .collect::<Vec<_>>()
+                .join(" ");
+            catalog.push_str(&format!(" /AF [{}]", refs));
+        }
+        // AES-256 predates PDF 2.0 as Adobe extension level 8 on top of 1.7.
+        if self.encryptor.is_some() && self.options.pdf_version == PdfVersion::Pdf17 {
+            catalog.push_str(" /Extensions << /ADBE << /BaseVersion /1.7 /ExtensionLevel 8 >> >>");
+        }
         catalog.push_str(" >>");
         self.write_object(PDF_CATALOG_ID, &catalog)?;
 
+        // The /Encrypt dictionary itself is written in the clear.
+        let encrypt_id = match self.encryptor.as_ref() {
+            Some(encryptor) => {
+                let body = encryptor.encrypt_dict().to_string();
+                let id = self.alloc_ids(1);
+                self.write_unencrypted_object(id, &body)?;
+                Some(id)
+            }
+            None => None,
+        };
+
         // 6) XRef + trailer.
         let total_objects = self.next_id.saturating_sub(1);
         let xref_start = self.offset;
@@ -756,8 +1655,27 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
         if let Some(id) = info_id {
             trailer.push_str(&format!(" /Info {} 0 R", id));
         }
+        if let Some(id) = encrypt_id {
+            trailer.push_str(&format!(" /Encrypt {} 0 R", id));
+        }
+        let file_id = match self.options.document_id.as_ref() {
+            Some(DocumentId::ContentHash) => self
+                .content_hasher
+                .take()
+                .map(|hasher| crate::encrypt::hex(&hasher.finalize()[..16])),
+            Some(DocumentId::Seed(seed)) => {
+                Some(crate::encrypt::hex(&Sha256::digest(seed.as_bytes())[..16]))
+            }
+            Some(DocumentId::Fixed(bytes)) => Some(crate::encrypt::hex(bytes)),
+            None if encrypt_id.is_some() => Some(random_file_id()?),
+            None => None,
+        };
+        if let Some(file_id) = file_id {
+            trailer.push_str(&format!(" /ID [<{}> <{}>]", file_id, file_id));
+        }
         trailer.push_str(&format!(" >>\nstartxref\n{}\n%%EOF", xref_start));
         write_str(self.writer, &trailer, &mut self.offset)?;
+        self.report_bytes_written();
 
         let bytes_written = self.offset;
         let content_ratio_ppm = if self.content_stream_raw_bytes == 0 {
@@ -770,7 +1688,7 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
         let finish_ms = t_finish.elapsed().as_secs_f64() * 1000.0;
         if let Some(logger) = self.debug.as_deref() {
             let json = format!(
-                "{{\"type\":\"jit.link\",\"ms\":{:.3},\"bytes\":{},\"pages\":{},\"fonts\":{},\"images\":{},\"forms\":{},\"shadings\":{},\"extgstates\":{},\"image_bytes\":{},\"content_stream_raw_bytes\":{},\"content_stream_encoded_bytes\":{},\"content_stream_compressed\":{},\"content_stream_ratio_ppm\":{}}}",
+                "{{\"type\":\"jit.link\",\"ms\":{:.3},\"bytes\":{},\"pages\":{},\"fonts\":{},\"images\":{},\"forms\":{},\"shadings\":{},\"extgstates\":{},\"image_bytes\":{},\"image_reuse\":{},\"form_reuse\":{},\"xobject_cross_record_reuse\":{},\"xobject_bytes_saved\":{},\"content_stream_raw_bytes\":{},\"content_stream_encoded_bytes\":{},\"content_stream_compressed\":{},\"content_stream_ratio_ppm\":{}}}",
                 finish_ms,
                 bytes_written,
                 self.page_ids.len(),
@@ -780,6 +1698,10 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
                 self.shading_resources.len(),
                 self.gs_resources.len(),
                 self.image_bytes_total,
+                self.xobject_reuse.image_hits,
+                self.xobject_reuse.form_hits,
+                self.xobject_reuse.cross_record_hits,
+                self.xobject_reuse.bytes_saved,
                 self.content_stream_raw_bytes,
                 self.content_stream_encoded_bytes,
                 self.content_stream_compressed_count,
@@ -801,6 +1723,13 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
                     ("shadings", self.shading_resources.len() as u64),
                     ("extgstates", self.gs_resources.len() as u64),
                     ("image_bytes", self.image_bytes_total as u64),
+                    ("image_reuse", self.xobject_reuse.image_hits),
+                    ("form_reuse", self.xobject_reuse.form_hits),
+                    (
+                        "xobject_cross_record_reuse",
+                        self.xobject_reuse.cross_record_hits,
+                    ),
+                    ("xobject_bytes_saved", self.xobject_reuse.bytes_saved),
                     (
                         "content_stream_raw_bytes",
                         self.content_stream_raw_bytes as u64,
@@ -816,58 +1745,256 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
                     ("content_stream_ratio_ppm", content_ratio_ppm),
                 ],
             );
+            perf_logger.log_counts(
+                "shape.cache",
+                None,
+                &[
+                    ("hits", self.shape_cache_hits),
+                    ("misses", self.shape_cache_misses),
+                    (
+                        "entries",
+                        self.registry
+                            .map_or(0, |registry| registry.shape_cache_len())
+                            as u64,
+                    ),
+                ],
+            );
         }
-        Ok(bytes_written)
+        Ok(BatchManifest {
+            bytes_written,
+            records: self
+                .records
+                .iter()
+                .map(|(range, _prefix)| range.clone())
+                .collect(),
+        })
     }
 
-    fn render_page(&mut self, page: &Page, page_index: usize) -> io::Result<String> {
-        self.render_commands(&page.commands, self.page_size.height, Some(page_index))
+    // Writes the outline root and one item per record; returns the root object id.
+    // Every item is open, so /Count is the number of descendants.
+    fn write_outline(&mut self, records: &[OutlineRecord]) -> io::Result<usize> {
+        let root_id = self.alloc_ids(1);
+        let first_id = self.alloc_ids(records.len());
+        let mut parents: Vec<Option<usize>> = Vec::with_capacity(records.len());
+        // Index of each record among its siblings.
+        let mut sibling_pos: Vec<usize> = Vec::with_capacity(records.len());
+        let mut children: Vec<Vec<usize>> = vec![Vec::new(); records.len()];
+        let mut top_level: Vec<usize> = Vec::new();
+        let mut stack: Vec<usize> = Vec::new();
+        for (idx, record) in records.iter().enumerate() {
+            while stack
+                .last()
+                .is_some_and(|open| records[*open].level >= record.level)
+            {
+                stack.pop();
+            }
+            let parent = stack.last().copied();
+            let siblings = match parent {
+                Some(parent) => &mut children[parent],
+                None => &mut top_level,
+            };
+            sibling_pos.push(siblings.len());
+            siblings.push(idx);
+            parents.push(parent);
+            stack.push(idx);
+        }
+        // Records are in document order, so descendants always follow their ancestors.
+        let mut descendants = vec![0usize; records.len()];
+        for idx in (0..records.len()).rev() {
+            if let Some(parent) = parents[idx] {
+                descendants[parent] += 1 + descendants[idx];
+            }
+        }
+
+        for (idx, record) in records.iter().enumerate() {
+            let siblings = match parents[idx] {
+                Some(parent) => &children[parent],
+                None => &top_level,
+            };
+            let pos = sibling_pos[idx];
+            let parent_id = parents[idx].map(|p| first_id + p).unwrap_or(root_id);
+            let mut obj = format!(
+                "<< /Title {} /Parent {} 0 R",
+                pdf_text_string(&record.title),
+                parent_id
+            );
+            if pos > 0 {
+                obj.push_str(&format!(" /Prev {} 0 R", first_id + siblings[pos - 1]));
+            }
+            if let Some(next) = siblings.get(pos + 1) {
+                obj.push_str(&format!(" /Next {} 0 R", first_id + next));
+            }
+            if let (Some(first), Some(last)) = (children[idx].first(), children[idx].last()) {
+                obj.push_str(&format!(
+                    " /First {} 0 R /Last {} 0 R /Count {}",
+                    first_id + first,
+                    first_id + last,
+                    descendants[idx]
+                ));
+            }
+            if let Some(page_id) = self.page_ids.get(record.page_index) {
+                obj.push_str(&format!(
+                    " /Dest [{} 0 R /XYZ {} {} null]",
+                    page_id,
+                    fmt_pt(record.x),
+                    fmt_pt(record.y)
+                ));
+            }
+            obj.push_str(" >>");
+            self.write_object(first_id + idx, &obj)?;
+        }
+
+        let mut root = String::from("<< /Type /Outlines");
+        if let (Some(first), Some(last)) = (top_level.first(), top_level.last()) {
+            root.push_str(&format!(
+                " /First {} 0 R /Last {} 0 R",
+                first_id + first,
+                first_id + last
+            ));
+        }
+        root.push_str(&format!(" /Count {} >>", records.len()));
+        self.write_object(root_id, &root)?;
+        Ok(root_id)
+    }
+
+    fn render_page(&mut self, out: &mut String, page: &Page, page_index: usize) -> io::Result<()> {
+        let page_boxes = self.options.page_boxes;
+        if page_boxes.is_some() {
+            let origin = pt(self.page_origin());
+            let _ = writeln!(out, "q\n1 0 0 1 {origin} {origin} cm");
+        }
+        let Size { width, height } = self.page_size;
+        // Turn the upright content back onto the unrotated media box.
+        let rotated = matches!(self.page_rotate, 90 | 180 | 270);
+        let _ = match self.page_rotate {
+            90 => writeln!(out, "q\n0 1 -1 0 {} 0 cm", pt(height)),
+            180 => writeln!(out, "q\n-1 0 0 -1 {} {} cm", pt(width), pt(height)),
+            270 => writeln!(out, "q\n0 -1 1 0 0 {} cm", pt(width)),
+            _ => Ok(()),
+        };
+        self.render_commands(out, &page.commands, height, Some(page_index))?;
+        if rotated {
+            out.push_str("Q\n");
+        }
+        if let Some(spec) = page_boxes {
+            self.close_page_boxes(out, &spec)?;
+        }
+        Ok(())
+    }
+
+    // Media box size of the page being written: its displayed size turned back by `/Rotate`.
+    fn media_size(&self) -> Size {
+        match self.page_rotate {
+            90 | 270 => Size {
+                width: self.page_size.height,
+                height: self.page_size.width,
+            },
+            _ => self.page_size,
+        }
+    }
+
+    // Maps a point of the displayed page (PDF space, y up) into the page's default user
+    // space, including the page box origin.
+    fn media_point(&self, x: Pt, y: Pt) -> (Pt, Pt) {
+        let Size { width, height } = self.page_size;
+        let (x, y) = match self.page_rotate {
+            90 => (height - y, x),
+            180 => (width - x, height - y),
+            270 => (y, width - x),
+            _ => (x, y),
+        };
+        let origin = self.page_origin();
+        (x + origin, y + origin)
+    }
+
+    // `media_point` for a rectangle with bottom-left corner `(x, y)`, as `[x0, y0, x1, y1]`.
+    fn media_rect(&self, x: Pt, y: Pt, width: Pt, height: Pt) -> [Pt; 4] {
+        let (ax, ay) = self.media_point(x, y);
+        let (bx, by) = self.media_point(x + width, y + height);
+        [ax.min(bx), ay.min(by), ax.max(bx), ay.max(by)]
     }
 
+    // Appends the content stream operators for `commands` to `out`.
     fn render_commands(
         &mut self,
-        commands: &[Command],
+        out: &mut String,
+        commands: &CommandBuffer,
         page_height: Pt,
         page_index: Option<usize>,
-    ) -> io::Result<String> {
-        let mut out = String::new();
+    ) -> io::Result<()> {
         let mut current_font_size = Pt::from_f32(12.0);
-        let mut current_font_name = "Helvetica".to_string();
+        let mut current_font_name: Arc<str> = Arc::from("Helvetica");
         let mut current_fill = Color::BLACK;
         let mut tag_stack: Vec<usize> = Vec::new();
         let tag_enabled = self.options.pdf_profile == PdfProfile::Tagged && page_index.is_some();
+        // Tagged output: painting outside any MCID-bearing tag or explicit artifact is wrapped
+        // in an /Artifact sequence, so every mark is either structure content or an artifact.
+        let mut auto_artifact = false;
+        let mut marked_is_artifact: Vec<bool> = Vec::new();
 
         for cmd in commands {
+            let cmd = &*cmd;
+            if tag_enabled {
+                if auto_artifact && ends_auto_artifact(cmd) {
+                    out.push_str("EMC\n");
+                    auto_artifact = false;
+                } else if !auto_artifact
+                    && paints_content(cmd)
+                    && !marked_is_artifact.contains(&true)
+                    && !tag_stack
+                        .iter()
+                        .any(|idx| self.tag_records[*idx].mcid.is_some())
+                {
+                    out.push_str("/Artifact BMC\n");
+                    auto_artifact = true;
+                }
+                match cmd {
+                    Command::BeginArtifact { .. } => marked_is_artifact.push(true),
+                    Command::BeginOptionalContent { .. } => marked_is_artifact.push(false),
+                    Command::EndMarkedContent => {
+                        let _ = marked_is_artifact.pop();
+                    }
+                    _ => {}
+                }
+            }
             match cmd {
                 Command::SaveState => out.push_str("q\n"),
                 Command::RestoreState => out.push_str("Q\n"),
                 Command::Translate(x, y) => {
-                    out.push_str(&format!("1 0 0 1 {} {} cm\n", fmt_pt(*x), fmt_pt(*y)));
+                    let _ = writeln!(out, "1 0 0 1 {} {} cm", pt(*x), pt(*y));
                 }
                 Command::Scale(x, y) => {
-                    out.push_str(&format!("{} 0 0 {} 0 0 cm\n", fmt(*x), fmt(*y)));
+                    let _ = writeln!(out, "{} 0 0 {} 0 0 cm", num(*x), num(*y));
                 }
                 Command::Rotate(angle) => {
                     let sin = libm::sinf(*angle);
                     let cos = libm::cosf(*angle);
-                    out.push_str(&format!(
-                        "{} {} {} {} 0 0 cm\n",
-                        fmt(cos),
-                        fmt(sin),
-                        fmt(-sin),
-                        fmt(cos)
-                    ));
+                    let _ = writeln!(
+                        out,
+                        "{} {} {} {} 0 0 cm",
+                        num(cos),
+                        num(sin),
+                        num(-sin),
+                        num(cos)
+                    );
                 }
                 Command::ConcatMatrix { a, b, c, d, e, f } => {
-                    out.push_str(&format!(
-                        "{} {} {} {} {} {} cm\n",
-                        fmt(*a),
-                        fmt(*b),
-                        fmt(*c),
-                        fmt(*d),
-                        fmt_pt(*e),
-                        fmt_pt(*f)
-                    ));
+                    let _ = writeln!(
+                        out,
+                        "{} {} {} {} {} {} cm",
+                        num(*a),
+                        num(*b),
+                        num(*c),
+                        num(*d),
+                        pt(*e),
+                        pt(*f)
+                    );
+                }
+                Command::Meta { key, value } if key == META_PAGE_LABEL_KEY => {
+                    let style = PageLabelStyle::from_pdf_name(value);
+                    if let (Some(page_index), Some(style)) = (page_index, style) {
+                        self.css_page_labels.insert(page_index, style);
+                    }
                 }
                 Command::Meta { .. } => {}
                 Command::BeginTag {
@@ -883,9 +2010,9 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
                         let role_raw = role.clone();
                         let role = escape_pdf_name(role);
                         if *group_only {
-                            out.push_str(&format!("/{role} BMC\n"));
+                            let _ = writeln!(out, "/{role} BMC");
                         } else if let Some(mcid) = mcid {
-                            out.push_str(&format!("/{role} <</MCID {}>> BDC\n", mcid));
+                            let _ = writeln!(out, "/{role} <</MCID {}>> BDC", mcid);
                         }
                         let parent = tag_stack.last().copied();
                         let idx = self.tag_records.len();
@@ -910,64 +2037,136 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
                 }
                 Command::BeginArtifact { subtype } => {
                     if let Some(subtype) = subtype.as_deref() {
-                        out.push_str(&format!(
-                            "/Artifact <</Subtype /{}>> BDC\n",
+                        let _ = writeln!(
+                            out,
+                            "/Artifact <</Subtype /{}>> BDC",
                             escape_pdf_name(subtype)
-                        ));
+                        );
                     } else {
                         out.push_str("/Artifact BMC\n");
                     }
                 }
                 Command::BeginOptionalContent { name } => {
                     self.optional_content_names.insert(name.clone());
-                    out.push_str(&format!("/OC /{} BDC\n", escape_pdf_name(name)));
+                    let _ = writeln!(out, "/OC /{} BDC", escape_pdf_name(name));
                 }
                 Command::EndMarkedContent => {
                     out.push_str("EMC\n");
                 }
+                Command::LinkAnnotation {
+                    x,
+                    y,
+                    width,
+                    height,
+                    target,
+                } => {
+                    // Annotations live on the page, so links inside form content are dropped.
+                    if page_index.is_some() {
+                        let rect = self.media_rect(*x, page_height - *y - *height, *width, *height);
+                        self.page_links
+                            .push((rect, target.clone(), tag_stack.last().copied()));
+                    }
+                }
+                Command::SignatureField {
+                    name,
+                    x,
+                    y,
+                    width,
+                    height,
+                } => {
+                    // Widgets live on the page, like links.
+                    if page_index.is_some() {
+                        let rect = self.media_rect(*x, page_height - *y - *height, *width, *height);
+                        self.page_signature_fields.push((
+                            name.clone(),
+                            rect,
+                            tag_stack.last().copied(),
+                        ));
+                    }
+                }
+                Command::NamedDestination {
+                    name,
+                    x,
+                    y,
+                    exported,
+                } => {
+                    if let Some(page_index) = page_index {
+                        let (dest_x, dest_y) = self.media_point(*x, page_height - *y);
+                        let dest = (page_index, dest_x, dest_y);
+                        self.named_destinations
+                            .entry((self.current_doc_id, name.clone()))
+                            .or_insert(dest);
+                        if *exported {
+                            if self.exported_destinations.contains_key(name) {
+                                self.duplicate_exported_destinations += 1;
+                            } else {
+                                self.exported_destinations.insert(name.clone(), dest);
+                            }
+                        }
+                    }
+                }
+                Command::Bookmark { level, title, x, y } => {
+                    if let Some(page_index) = page_index
+                        && *level <= self.options.outline_levels
+                    {
+                        let (x, y) = self.media_point(*x, page_height - *y);
+                        self.outline_records.push(OutlineRecord {
+                            level: level.saturating_add(self.outline_level_offset),
+                            title: title.clone(),
+                            page_index,
+                            x,
+                            y,
+                        });
+                    }
+                }
                 Command::SetFillColor(color) => {
                     current_fill = *color;
-                    out.push_str(&color_to_pdf_fill(*color, self.options.color_space));
-                }
-                Command::SetStrokeColor(color) => {
-                    out.push_str(&color_to_pdf_stroke(*color, self.options.color_space));
+                    match self.ensure_separation(*color)? {
+                        Some((name, tint)) => {
+                            let _ = writeln!(out, "/{} cs {} scn", name, num(tint));
+                        }
+                        None => out.push_str(&color_to_pdf_fill(*color, self.options.color_space)),
+                    }
                 }
+                Command::SetStrokeColor(color) => match self.ensure_separation(*color)? {
+                    Some((name, tint)) => {
+                        let _ = writeln!(out, "/{} CS {} SCN", name, num(tint));
+                    }
+                    None => out.push_str(&color_to_pdf_stroke(*color, self.options.color_space)),
+                },
                 Command::SetLineWidth(width) => {
-                    out.push_str(&format!("{} w\n", fmt_pt(*width)));
+                    let _ = writeln!(out, "{} w", pt(*width));
                 }
                 Command::SetLineCap(cap) => {
-                    out.push_str(&format!("{} J\n", cap));
+                    let _ = writeln!(out, "{} J", cap);
                 }
                 Command::SetLineJoin(join) => {
-                    out.push_str(&format!("{} j\n", join));
+                    let _ = writeln!(out, "{} j", join);
                 }
                 Command::SetMiterLimit(limit) => {
-                    out.push_str(&format!("{} M\n", fmt_pt(*limit)));
+                    let _ = writeln!(out, "{} M", pt(*limit));
                 }
                 Command::SetDash { pattern, phase } => {
-                    let pat = if pattern.is_empty() {
-                        "[]".to_string()
-                    } else {
-                        let items = pattern
-                            .iter()
-                            .map(|v| fmt_pt(*v))
-                            .collect::<Vec<_>>()
-                            .join(" ");
-                        format!("[{}]", items)
-                    };
-                    out.push_str(&format!("{} {} d\n", pat, fmt_pt(*phase)));
+                    out.push('[');
+                    for (i, v) in pattern.iter().enumerate() {
+                        if i > 0 {
+                            out.push(' ');
+                        }
+                        let _ = write!(out, "{}", pt(*v));
+                    }
+                    let _ = writeln!(out, "] {} d", pt(*phase));
                 }
                 Command::SetOpacity { fill, stroke } => {
                     // Map opacity to an ExtGState resource. We quantize to 0..1000.
                     let k = ((*fill * 1000.0).round() as i32).clamp(0, 1000) as u16;
                     let ks = ((*stroke * 1000.0).round() as i32).clamp(0, 1000) as u16;
                     if let Some(name) = self.ensure_extgstate((k, ks))? {
-                        out.push_str(&format!("/{} gs\n", name));
+                        let _ = writeln!(out, "/{} gs", name);
                     }
                 }
                 Command::SetBlendMode { mode } => {
                     if let Some(name) = self.ensure_blend_extgstate(*mode)? {
-                        out.push_str(&format!("/{} gs\n", name));
+                        let _ = writeln!(out, "/{} gs", name);
                     }
                 }
                 Command::ApplyBackdropFilter { .. } => {}
@@ -985,13 +2184,14 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
                     height,
                 } => {
                     let draw_y = page_height - *y - *height;
-                    out.push_str(&format!(
-                        "{} {} {} {} re\nW\nn\n",
-                        fmt_pt(*x),
-                        fmt_pt(draw_y),
-                        fmt_pt(*width),
-                        fmt_pt(*height)
-                    ));
+                    let _ = writeln!(
+                        out,
+                        "{} {} {} {} re\nW\nn",
+                        pt(*x),
+                        pt(draw_y),
+                        pt(*width),
+                        pt(*height)
+                    );
                 }
                 Command::ClipPath { evenodd } => {
                     if *evenodd {
@@ -1004,14 +2204,14 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
                 Command::ShadingFill(shading) => {
                     let key = hash_shading(shading);
                     if let Some(name) = self.ensure_shading(key, shading)? {
-                        out.push_str(&format!("/{} sh\n", name));
+                        let _ = writeln!(out, "/{} sh", name);
                     }
                 }
                 Command::MoveTo { x, y } => {
-                    out.push_str(&format!("{} {} m\n", fmt_pt(*x), fmt_pt(page_height - *y)));
+                    let _ = writeln!(out, "{} {} m", pt(*x), pt(page_height - *y));
                 }
                 Command::LineTo { x, y } => {
-                    out.push_str(&format!("{} {} l\n", fmt_pt(*x), fmt_pt(page_height - *y)));
+                    let _ = writeln!(out, "{} {} l", pt(*x), pt(page_height - *y));
                 }
                 Command::CurveTo {
                     x1,
@@ -1021,15 +2221,16 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
                     x,
                     y,
                 } => {
-                    out.push_str(&format!(
-                        "{} {} {} {} {} {} c\n",
-                        fmt_pt(*x1),
-                        fmt_pt(page_height - *y1),
-                        fmt_pt(*x2),
-                        fmt_pt(page_height - *y2),
-                        fmt_pt(*x),
-                        fmt_pt(page_height - *y),
-                    ));
+                    let _ = writeln!(
+                        out,
+                        "{} {} {} {} {} {} c",
+                        pt(*x1),
+                        pt(page_height - *y1),
+                        pt(*x2),
+                        pt(page_height - *y2),
+                        pt(*x),
+                        pt(page_height - *y),
+                    );
                 }
                 Command::ClosePath => out.push_str("h\n"),
                 Command::Fill => out.push_str("f\n"),
@@ -1050,12 +2251,13 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
                         continue;
                     };
                     out.push_str("BT\n");
-                    out.push_str(&format!("/{} {} Tf\n", resource, fmt_pt(current_font_size)));
-                    out.push_str(&format!(
-                        "{} {} Td\n",
-                        fmt_pt(*x),
-                        fmt_pt(page_height - *y - current_font_size)
-                    ));
+                    let _ = writeln!(out, "/{} {} Tf", resource, pt(current_font_size));
+                    let _ = writeln!(
+                        out,
+                        "{} {} Td",
+                        pt(*x),
+                        pt(page_height - *y - current_font_size)
+                    );
 
                     match encoding {
                         FontEncoding::WinAnsi => {
@@ -1097,24 +2299,37 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
                                         encoded.fallbacks as u64,
                                     );
                                 }
+                                let mut fallback = Warning::for_loss(
+                                    WarningKind::KnownLoss,
+                                    KnownLoss::WinAnsiFallback {
+                                        font: current_font_name.to_string(),
+                                        fallbacks: encoded.fallbacks,
+                                    },
+                                    format!(
+                                        "{} character(s) outside WinAnsi drawn with substitutes in {}",
+                                        encoded.fallbacks, current_font_name
+                                    ),
+                                )
+                                .details(vec![truncate_preview(text, 80)]);
+                                if let Some(page_index) = page_index {
+                                    fallback = fallback.page(page_index + 1);
+                                }
+                                warning::emit(self.options.warnings.as_ref(), fallback);
                             }
-                            out.push_str(&format!("({}) Tj\n", encoded.text));
+                            let _ = writeln!(out, "({}) Tj", encoded.text);
                         }
                         FontEncoding::IdentityH => {
-                            if let Some(tj) = self.shape_text_to_tj(
-                                &font_key,
-                                &current_font_name,
-                                current_font_size,
-                                text,
-                            ) {
-                                out.push_str(tj);
+                            if let Some(shaped) =
+                                self.shape_text_to_tj(&font_key, current_font_size, text)
+                            {
+                                out.push_str(&shaped.tj);
                             } else {
                                 let hex = self.encode_cid_hex_fallback(
                                     &font_key,
                                     &current_font_name,
                                     text,
                                 );
-                                out.push_str(&format!("{} Tj\n", hex));
+                                let _ = writeln!(out, "{} Tj", hex);
                             }
                         }
                     }
@@ -1131,13 +2346,14 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
                     height,
                 } => {
                     let draw_y = page_height - *y - *height;
-                    out.push_str(&format!(
-                        "{} {} {} {} re\nf\n",
-                        fmt_pt(*x),
-                        fmt_pt(draw_y),
-                        fmt_pt(*width),
-                        fmt_pt(*height)
-                    ));
+                    let _ = writeln!(
+                        out,
+                        "{} {} {} {} re\nf",
+                        pt(*x),
+                        pt(draw_y),
+                        pt(*width),
+                        pt(*height)
+                    );
                 }
                 Command::DrawImage {
                     x,
@@ -1146,17 +2362,18 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
                     height,
                     resource_id,
                 } => {
-                    if let Some(name) = self.ensure_image(resource_id)? {
+                    if let Some(name) = self.ensure_image(resource_id, *width, *height)? {
                         let draw_y = page_height - *y - *height;
                         out.push_str("q\n");
-                        out.push_str(&format!(
-                            "{} 0 0 {} {} {} cm\n",
-                            fmt_pt(*width),
-                            fmt_pt(*height),
-                            fmt_pt(*x),
-                            fmt_pt(draw_y)
-                        ));
-                        out.push_str(&format!("/{} Do\n", name));
+                        let _ = writeln!(
+                            out,
+                            "{} 0 0 {} {} {} cm",
+                            pt(*width),
+                            pt(*height),
+                            pt(*x),
+                            pt(draw_y)
+                        );
+                        let _ = writeln!(out, "/{} Do", name);
                         out.push_str("Q\n");
                     } else {
                         // Image missing: draw a solid block to avoid silent layout shifts.
@@ -1178,11 +2395,11 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
                     height,
                     resource_id,
                 } => {
-                    if let Some(name) = self.form_name_map.get(resource_id) {
+                    if let Some(name) = self.form_name_map.get(resource_id.as_ref()) {
                         let draw_y = page_height - *y - *height;
                         let (sx, sy) = self
                             .form_size_map
-                            .get(resource_id)
+                            .get(resource_id.as_ref())
                             .map(|size| {
                                 let sx = if size.width.to_f32() > 0.0 {
                                     width.to_f32() / size.width.to_f32()
@@ -1199,20 +2416,24 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
                             .unwrap_or((1.0, 1.0));
 
                         out.push_str("q\n");
-                        out.push_str(&format!(
-                            "{} 0 0 {} {} {} cm\n",
-                            fmt(sx),
-                            fmt(sy),
-                            fmt_pt(*x),
-                            fmt_pt(draw_y)
-                        ));
-                        out.push_str(&format!("/{} Do\n", name));
+                        let _ = writeln!(
+                            out,
+                            "{} 0 0 {} {} {} cm",
+                            num(sx),
+                            num(sy),
+                            pt(*x),
+                            pt(draw_y)
+                        );
+                        let _ = writeln!(out, "/{} Do", name);
                         out.push_str("Q\n");
                     }
                 }
             }
         }
-        Ok(out)
+        if auto_artifact {
+            out.push_str("EMC\n");
+        }
+        Ok(())
     }
 
     fn ensure_offsets_len(&mut self, required_len: usize) {
@@ -1229,6 +2450,20 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
     }
 
     fn write_object(&mut self, obj_id: usize, body: &str) -> io::Result<()> {
+        match self.encryptor.as_mut() {
+            Some(encryptor) => {
+                let body = encryptor.encrypt_object_strings(body);
+                self.write_unencrypted_object(obj_id, &body)
+            }
+            None => self.write_unencrypted_object(obj_id, body),
+        }
+    }
+
+    fn write_unencrypted_object(&mut self, obj_id: usize, body: &str) -> io::Result<()> {
+        if let Some(hasher) = self.content_hasher.as_mut() {
+            hasher.update(obj_id.to_le_bytes());
+            hasher.update(body.as_bytes());
+        }
         write_pdf_object(
             self.writer,
             &mut self.offset,
@@ -1244,6 +2479,25 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
         dict_entries: &str,
         data: &[u8],
     ) -> io::Result<()> {
+        if let Some(encryptor) = self.encryptor.as_mut() {
+            let dict_entries = encryptor.encrypt_object_strings(dict_entries);
+            let data = encryptor.encrypt(data);
+            return self.write_unencrypted_stream_object(obj_id, &dict_entries, &data);
+        }
+        self.write_unencrypted_stream_object(obj_id, dict_entries, data)
+    }
+
+    fn write_unencrypted_stream_object(
+        &mut self,
+        obj_id: usize,
+        dict_entries: &str,
+        data: &[u8],
+    ) -> io::Result<()> {
+        if let Some(hasher) = self.content_hasher.as_mut() {
+            hasher.update(obj_id.to_le_bytes());
+            hasher.update(dict_entries.as_bytes());
+            hasher.update(data);
+        }
         write_pdf_stream_object(
             self.writer,
             &mut self.offset,
@@ -1260,29 +2514,120 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
         dict_entries: &str,
         content: &[u8],
     ) -> io::Result<()> {
-        self.content_stream_raw_bytes = self.content_stream_raw_bytes.saturating_add(content.len());
-        let should_compress = self.options.compress_content_streams
-            && content.len() >= self.options.compress_content_stream_min_bytes;
-        if should_compress {
-            let compressed = flate_compress(content);
-            self.content_stream_encoded_bytes = self
-                .content_stream_encoded_bytes
-                .saturating_add(compressed.len());
-            self.content_stream_compressed_count =
-                self.content_stream_compressed_count.saturating_add(1);
-            let mut dict = String::new();
-            if !dict_entries.trim().is_empty() {
-                dict.push_str(dict_entries.trim());
-                dict.push(' ');
+        let encoded = self.content_codec().encode(content);
+        self.write_encoded_content_stream(obj_id, dict_entries, &encoded)
+    }
+
+    fn content_codec(&self) -> ContentCodec {
+        ContentCodec {
+            compress: self.options.compress_content_streams,
+            min_bytes: self.options.compress_content_stream_min_bytes,
+            level: self.options.content_compression_level,
+        }
+    }
+
+    fn write_encoded_content_stream(
+        &mut self,
+        obj_id: usize,
+        dict_entries: &str,
+        encoded: &EncodedContent,
+    ) -> io::Result<()> {
+        self.content_stream_raw_bytes = self
+            .content_stream_raw_bytes
+            .saturating_add(encoded.raw_len);
+        self.content_stream_encoded_bytes = self
+            .content_stream_encoded_bytes
+            .saturating_add(encoded.bytes.len());
+        if !encoded.flate {
+            return self.write_stream_object_bytes(obj_id, dict_entries, &encoded.bytes);
+        }
+        self.content_stream_compressed_count =
+            self.content_stream_compressed_count.saturating_add(1);
+        let mut dict = String::new();
+        if !dict_entries.trim().is_empty() {
+            dict.push_str(dict_entries.trim());
+            dict.push(' ');
+        }
+        dict.push_str("/Filter /FlateDecode");
+        self.write_stream_object_bytes(obj_id, &dict, &encoded.bytes)
+    }
+
+    // Encodes and writes the queued page content streams, in page order. Page content goes
+    // through the page cache when there is one: a page whose content matches a page of the
+    // previous render reuses that render's encoded stream.
+    fn flush_page_contents(&mut self) -> io::Result<()> {
+        if self.pending_contents.is_empty() {
+            return Ok(());
+        }
+        let t_compress = crate::platform::Instant::now();
+        let pending = std::mem::take(&mut self.pending_contents);
+        self.pending_content_bytes = 0;
+        let codec = self.content_codec();
+        let previous = self.page_cache.as_ref().map(|cache| &cache.previous);
+        let encode = |content: &String| {
+            let Some(previous) = previous else {
+                return (None, codec.encode(content.as_bytes()), false);
+            };
+            let signature = page_signature(content.as_bytes());
+            let flate = codec.should_compress(content.len());
+            let cached = previous
+                .streams
+                .get(&signature)
+                .filter(|encoded| encoded.raw_len == content.len() && encoded.flate == flate);
+            match cached {
+                Some(encoded) => (Some(signature), encoded.clone(), true),
+                None => (Some(signature), codec.encode(content.as_bytes()), false),
             }
-            dict.push_str("/Filter /FlateDecode");
-            self.write_stream_object_bytes(obj_id, &dict, &compressed)
+        };
+        let encoded: Vec<_> = if self.options.parallel_content_compression && pending.len() > 1 {
+            pending
+                .par_iter()
+                .map(|(_, content)| encode(content))
+                .collect()
         } else {
-            self.content_stream_encoded_bytes = self
-                .content_stream_encoded_bytes
-                .saturating_add(content.len());
-            self.write_stream_object_bytes(obj_id, dict_entries, content)
+            pending.iter().map(|(_, content)| encode(content)).collect()
+        };
+        let pages = pending.len();
+        for ((obj_id, content), (signature, encoded, reused)) in pending.into_iter().zip(encoded) {
+            self.write_encoded_content_stream(obj_id, "", &encoded)?;
+            if let (Some(signature), Some(cache)) = (signature, self.page_cache.as_mut()) {
+                cache.next.signatures.push(signature);
+                cache.next.streams.insert(signature, encoded);
+                if reused {
+                    cache.reused_pages += 1;
+                }
+            }
+            if self.spare_content_buffers.len() < SPARE_CONTENT_BUFFERS {
+                self.spare_content_buffers.push(content);
+            }
+        }
+        if let Some(perf) = self.perf.as_deref() {
+            let ms = t_compress.elapsed().as_secs_f64() * 1000.0;
+            perf.log_span_ms("pdf.compress", Some(self.current_doc_id), ms);
+            perf.log_counts(
+                "pdf.compress",
+                Some(self.current_doc_id),
+                &[("pages", pages as u64)],
+            );
         }
+        Ok(())
+    }
+
+    // Diff the pages written against `previous`, the cache of an earlier render.
+    pub(crate) fn with_page_cache(mut self, previous: PageCache) -> Self {
+        self.page_cache = Some(PageCacheState {
+            previous,
+            next: PageCache::default(),
+            reused_pages: 0,
+        });
+        self
+    }
+
+    // The cache of the pages written so far and how many of them reused an encoded stream.
+    pub(crate) fn take_page_cache(&mut self) -> Option<(PageCache, usize)> {
+        self.page_cache
+            .take()
+            .map(|cache| (cache.next, cache.reused_pages))
     }
 
     fn write_image_smask_stream_object(
@@ -1306,18 +2651,64 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
         let smask = smask_id
             .map(|id| format!(" /SMask {} 0 R", id))
             .unwrap_or_default();
+        let decode = image
+            .decode
+            .map(|decode| format!(" /Decode {}", decode))
+            .unwrap_or_default();
         let dict = format!(
-            "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace {} /BitsPerComponent {} /Filter {}{}",
+            "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace {} /BitsPerComponent {} /Filter {}{}{}",
             image.width,
             image.height,
             image.color_space,
             image.bits_per_component,
             image.filter,
+            decode,
             smask
         );
         self.write_stream_object_bytes(obj_id, &dict, &image.data)
     }
 
+    // Embed `font` (subset to `used_gids` when enabled and possible) and return its PDF name:
+    // subsets carry the `ABCDEF+` tag that marks them as incomplete.
+    fn write_font_program(
+        &mut self,
+        obj_id: usize,
+        font: &RegisteredFont,
+        used_gids: &BTreeSet<u16>,
+        simple: bool,
+    ) -> io::Result<String> {
+        let base = sanitize_font_name(&font.name);
+        let subset = if self.options.font_subsetting
+            && matches!(font.program_kind, FontProgramKind::TrueType)
+        {
+            crate::subset::subset_truetype(&font.data, used_gids, simple)
+        } else {
+            None
+        };
+        let Some(subset) = subset else {
+            self.write_font_file_stream_object(obj_id, &font.data, font.program_kind)?;
+            return Ok(base);
+        };
+        if let Some(logger) = self.debug.as_deref() {
+            logger.increment(
+                "pdf.font.subset_bytes_saved",
+                font.data.len().saturating_sub(subset.len()) as u64,
+            );
+        }
+        // Unused glyph slots and the long loca compress to almost nothing.
+        let compressed = flate_compress(&subset);
+        self.write_stream_object_bytes(
+            obj_id,
+            &format!("/Length1 {} /Filter /FlateDecode", subset.len()),
+            &compressed,
+        )?;
+        Ok(format!(
+            "{}+{}",
+            crate::subset::subset_tag(&font.name, used_gids),
+            base
+        ))
+    }
+
     fn write_font_file_stream_object(
         &mut self,
         obj_id: usize,
@@ -1340,6 +2731,49 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
         self.write_stream_object_bytes(obj_id, &format!("/N {}", n_components), data)
     }
 
+    fn write_embedded_file_stream_object(
+        &mut self,
+        obj_id: usize,
+        attachment: &EmbeddedFile,
+    ) -> io::Result<()> {
+        let compressed = flate_compress(&attachment.data);
+        let dict = format!(
+            "/Type /EmbeddedFile /Subtype /{} /Params << /Size {} >> /Filter /FlateDecode",
+            escape_pdf_name(&attachment.mime),
+            attachment.data.len()
+        );
+        self.write_stream_object_bytes(obj_id, &dict, &compressed)
+    }
+
+    // Offset of the trim box inside the media box; page coordinates shift by it on both axes.
+    fn page_origin(&self) -> Pt {
+        self.options
+            .page_boxes
+            .as_ref()
+            .map_or(Pt::ZERO, PageBoxSpec::media_margin)
+    }
+
+    // Ends the move of the page content onto the trim box that `render_page` opened and draws
+    // printer's marks around it, in the /All registration colorant so they print on every plate.
+    fn close_page_boxes(&mut self, out: &mut String, spec: &PageBoxSpec) -> io::Result<()> {
+        let origin = self.page_origin();
+        out.push_str("Q\n");
+        if !spec.has_marks() {
+            return Ok(());
+        }
+        let registration = Color::spot("All", 1.0, [1.0, 1.0, 1.0, 1.0]).unwrap_or(Color::BLACK);
+        let stroke = match self.ensure_separation(registration)? {
+            Some((name, tint)) => format!("/{} CS {} SCN\n", name, fmt(tint)),
+            None => color_to_pdf_stroke(registration, self.options.color_space),
+        };
+        out.push_str("/Artifact BMC\nq\n");
+        out.push_str(&stroke);
+        out.push_str("0.25 w\n");
+        out.push_str(&printer_marks_path(spec, origin, self.media_size()));
+        out.push_str("Q\nEMC\n");
+        Ok(())
+    }
+
     fn ensure_page_node(&mut self) -> usize {
         let needs_new = self
             .current_node
@@ -1396,7 +2830,7 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
 
         let mut kind = StreamFontKind::Type1;
         let mut encoding = FontEncoding::WinAnsi;
-        let mut face = None;
+        let mut face_data = None;
 
         if self.options.pdf_profile == PdfProfile::PdfX4 {
             let Some(registry) = self.registry else {
@@ -1419,7 +2853,7 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
             {
                 kind = StreamFontKind::TrueTypeIdentityH;
                 encoding = FontEncoding::IdentityH;
-                face = HbFace::from_slice(&font.data, 0);
+                face_data = Some(font.data.clone());
             } else {
                 kind = StreamFontKind::TrueTypeWinAnsi;
                 encoding = FontEncoding::WinAnsi;
@@ -1433,7 +2867,7 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
                         if matches!(font.program_kind, FontProgramKind::TrueType) {
                             kind = StreamFontKind::TrueTypeIdentityH;
                             encoding = FontEncoding::IdentityH;
-                            face = HbFace::from_slice(&font.data, 0);
+                            face_data = Some(font.data.clone());
                         } else {
                             // OpenType CFF: keep WinAnsi for now (no full Unicode CFF path yet).
                             kind = StreamFontKind::TrueTypeWinAnsi;
@@ -1459,7 +2893,7 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
                 start_id,
                 kind,
                 glyph_map: BTreeMap::new(),
-                face,
+                face_data,
                 plans: HashMap::new(),
             },
         );
@@ -1467,12 +2901,23 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
         Ok(())
     }
 
-    fn ensure_image(&mut self, source: &str) -> io::Result<Option<String>> {
-        if let Some(name) = self.image_name_map.get(source) {
-            return Ok(Some(name.clone()));
+    fn ensure_image(&mut self, source: &str, width: Pt, height: Pt) -> io::Result<Option<String>> {
+        // With a DPI cap the pixel budget depends on the placed size, so each size gets its own
+        // entry; identical results still share one XObject through the content hash.
+        let max_pixels = self.options.max_image_dpi.map(|dpi| {
+            let pixels = |len: Pt| ((len.to_f32().max(0.0) / 72.0) * dpi as f32).ceil() as u32;
+            (pixels(width).max(1), pixels(height).max(1))
+        });
+        let cache_key = match max_pixels {
+            Some((w, h)) => format!("{}#{}x{}", source, w, h),
+            None => source.to_string(),
+        };
+        if let Some(name) = self.image_name_map.get(&cache_key).cloned() {
+            self.note_xobject_reuse(&name, false);
+            return Ok(Some(name));
         }
-        let t_decode = std::time::Instant::now();
-        let image = load_image(source);
+        let t_decode = crate::platform::Instant::now();
+        let image = load_image(source, max_pixels);
         if let Some(perf) = self.perf.as_deref() {
             let ms = t_decode.elapsed().as_secs_f64() * 1000.0;
             perf.log_span_ms("image.decode", None, ms);
@@ -1494,20 +2939,27 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
                     .and_then(|p| p.to_str().map(|s| s.to_string()))
                     .unwrap_or_else(|| "<unknown>".to_string());
                 let json = format!(
-                    "{{\"type\":\"pdf.image.missing\",\"source\":{},\"cwd\":{}}}",
+                    "{{\"type\":\"pdf.image.missing\",\"source\":{},\"cwd\":{},\"error\":{}}}",
                     json_escape(source),
-                    json_escape(&cwd)
+                    json_escape(&cwd),
+                    json_escape(&image_failure_reason(source))
                 );
                 logger.log_json(&json);
             }
             return Ok(None);
         };
 
+        if image.downsampled
+            && let Some(logger) = self.debug.as_deref()
+        {
+            logger.increment("jit.image.downsampled", 1);
+        }
         let hash = hash_image(&image);
         if self.options.reuse_xobjects {
-            if let Some((name, _obj_id)) = self.image_content_map.get(&hash) {
-                self.image_name_map.insert(source.to_string(), name.clone());
-                return Ok(Some(name.clone()));
+            if let Some((name, _obj_id)) = self.image_content_map.get(&hash).cloned() {
+                self.image_name_map.insert(cache_key, name.clone());
+                self.note_xobject_reuse(&name, false);
+                return Ok(Some(name));
             }
         }
 
@@ -1516,16 +2968,16 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
         let name = format!("Im{}", self.next_image_index);
         self.next_image_index += 1;
 
-        self.image_bytes_total += image.data.len();
-        if let Some(alpha) = &image.alpha {
-            self.image_bytes_total += alpha.data.len();
-        }
+        let image_bytes = image.data.len() + image.alpha.as_ref().map_or(0, |a| a.data.len());
+        self.image_bytes_total += image_bytes;
+        self.xobject_origins
+            .insert(name.clone(), (self.current_doc_id, image_bytes));
         if let (Some(alpha), Some(mask_id)) = (image.alpha.as_ref(), smask_id) {
             self.write_image_smask_stream_object(mask_id, alpha)?;
         }
         self.write_image_stream_object(obj_id, &image, smask_id)?;
         self.image_resources.push((name.clone(), obj_id));
-        self.image_name_map.insert(source.to_string(), name.clone());
+        self.image_name_map.insert(cache_key, name.clone());
         if self.options.reuse_xobjects {
             self.image_content_map.insert(hash, (name.clone(), obj_id));
         }
@@ -1537,21 +2989,24 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
         resource_id: &str,
         width: Pt,
         height: Pt,
-        commands: &[Command],
+        commands: &CommandBuffer,
     ) -> io::Result<Option<String>> {
-        if let Some(name) = self.form_name_map.get(resource_id) {
-            return Ok(Some(name.clone()));
+        if let Some(name) = self.form_name_map.get(resource_id).cloned() {
+            self.note_xobject_reuse(&name, true);
+            return Ok(Some(name));
         }
 
-        let content = self.render_commands(commands, height, None)?;
+        let mut content = String::new();
+        self.render_commands(&mut content, commands, height, None)?;
         let hash = hash_bytes(content.as_bytes());
         if self.options.reuse_xobjects {
-            if let Some((name, _obj_id)) = self.form_content_map.get(&hash) {
+            if let Some((name, _obj_id)) = self.form_content_map.get(&hash).cloned() {
                 self.form_name_map
                     .insert(resource_id.to_string(), name.clone());
                 self.form_size_map
                     .insert(resource_id.to_string(), Size { width, height });
-                return Ok(Some(name.clone()));
+                self.note_xobject_reuse(&name, true);
+                return Ok(Some(name));
             }
         }
 
@@ -1567,6 +3022,8 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
         );
 
         self.write_content_stream_object(obj_id, &dict, content.as_bytes())?;
+        self.xobject_origins
+            .insert(name.clone(), (self.current_doc_id, content.len()));
         self.form_resources.push((name.clone(), obj_id));
         self.form_name_map
             .insert(resource_id.to_string(), name.clone());
@@ -1579,6 +3036,20 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
         Ok(Some(name))
     }
 
+    fn note_xobject_reuse(&mut self, name: &str, form: bool) {
+        if form {
+            self.xobject_reuse.form_hits += 1;
+        } else {
+            self.xobject_reuse.image_hits += 1;
+        }
+        if let Some((doc_id, bytes)) = self.xobject_origins.get(name) {
+            if *doc_id != self.current_doc_id {
+                self.xobject_reuse.cross_record_hits += 1;
+            }
+            self.xobject_reuse.bytes_saved += *bytes as u64;
+        }
+    }
+
     fn ensure_extgstate(&mut self, key: (u16, u16)) -> io::Result<Option<String>> {
         if let Some(name) = self.gs_name_map.get(&key) {
             return Ok(Some(name.clone()));
@@ -1602,6 +3073,28 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
         Ok(Some(name))
     }
 
+    // Separation color space resource for a spot color, with its tint. None for other colors.
+    fn ensure_separation(&mut self, color: Color) -> io::Result<Option<(String, f32)>> {
+        let Some(ProcessColor::Spot {
+            name: spot,
+            tint,
+            alternate,
+        }) = color.process
+        else {
+            return Ok(None);
+        };
+        if let Some(name) = self.separation_name_map.get(&spot) {
+            return Ok(Some((name.clone(), tint)));
+        }
+
+        let obj_id = self.alloc_ids(1);
+        let name = format!("CS{}", self.separation_resources.len() + 1);
+        self.write_object(obj_id, &separation_color_space(spot.as_str(), alternate))?;
+        self.separation_resources.push((name.clone(), obj_id));
+        self.separation_name_map.insert(spot, name.clone());
+        Ok(Some((name, tint)))
+    }
+
     fn ensure_blend_extgstate(&mut self, mode: MixBlendMode) -> io::Result<Option<String>> {
         if matches!(mode, MixBlendMode::Normal) {
             return Ok(None);
@@ -1626,6 +3119,9 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
     }
 
     fn ensure_shading(&mut self, key: u64, shading: &Shading) -> io::Result<Option<String>> {
+        // Shading coordinates are flipped against the page height, so pages of another height
+        // need their own shading object.
+        let key = (key, self.page_size.height.to_milli_i64());
         if let Some(name) = self.shading_name_map.get(&key) {
             return Ok(Some(name.clone()));
         }
@@ -1655,30 +3151,34 @@ impl<'a, W: Write> PdfStreamWriter<'a, W> {
     fn shape_text_to_tj(
         &mut self,
         font_key: &str,
-        _font_name: &str,
         font_size: Pt,
         text: &str,
-    ) -> Option<&str> {
+    ) -> Option<Arc<ShapedRun>> {
         if !self.options.shape_text {
             return None;
         }
-        let key = tj_cache_key(font_key, font_size, text);
-        if !self.shaped_cache.contains_key(&key) {
-            let shaped = {
-                let font_state = self.fonts.get_mut(font_key)?;
-                let face = font_state.face.as_ref()?;
-                let shaped = shape_text_with_plans(face, &mut font_state.plans, text)?;
-                for (gid, s) in &shaped.glyph_map {
-                    font_state
-                        .glyph_map
-                        .entry(*gid)
-                        .or_insert_with(|| s.clone());
-                }
-                shaped
-            };
-            self.shaped_cache.insert(key.clone(), shaped);
+        let registry = self.registry?;
+        let font_state = self.fonts.get_mut(font_key)?;
+        let data = font_state.face_data.as_ref()?;
+        let plans = &mut font_state.plans;
+        let (shaped, hit) =
+            registry.shaped_run(&font_state.logical_name, font_size, text, || {
+                let face = HbFace::from_slice(data, 0)?;
+                shape_text_with_plans(&face, plans, text)
+            })?;
+        if hit {
+            self.shape_cache_hits += 1;
+        } else {
+            self.shape_cache_misses += 1;
+        }
+        // Glyphs shaped for an earlier render or another writer still need their text here.
+        for (gid, s) in &shaped.glyph_map {
+            font_state
+                .glyph_map
+                .entry(*gid)
+                .or_insert_with(|| s.clone());
         }
-        self.shaped_cache.get(&key).map(|s| s.tj.as_str())
+        Some(shaped)
     }
 
     fn encode_cid_hex_fallback(&mut self, font_key: &str, font_name: &str, text: &str) -> String {
@@ -1743,7 +3243,7 @@ fn shape_text_with_plans(
     face: &HbFace<'_>,
     plans: &mut HashMap<(rustybuzz::Direction, HbScript, Option<HbLanguage>), ShapePlan>,
     text: &str,
-) -> Option<ShapedText> {
+) -> Option<ShapedRun> {
     use rustybuzz::ttf_parser::GlyphId;
 
     let units_per_em = face.units_per_em().max(1);
@@ -1826,7 +3326,7 @@ fn shape_text_with_plans(
         return None;
     }
 
-    Some(ShapedText {
+    Some(ShapedRun {
         tj: format!("[{}] TJ\n", parts.join(" ")),
         glyph_map,
     })
@@ -1922,7 +3422,7 @@ pub(crate) fn document_to_pdf_with_metrics_and_registry_to_writer_with_logs<W: W
         perf,
     )?;
     pdf_stream.add_document(0, document)?;
-    let total_bytes = pdf_stream.finish()?;
+    let total_bytes = pdf_stream.finish()?.bytes_written;
 
     if let Some(metrics) = metrics.as_deref_mut() {
         metrics.total_bytes = total_bytes;
@@ -1943,13 +3443,13 @@ pub(crate) fn document_to_pdf_with_metrics_and_registry_to_writer_with_logs<W: W
     Ok(total_bytes)
 }
 
-fn collect_used_font_names_in_commands(commands: &[Command], names: &mut BTreeSet<String>) {
-    let mut current_font = "Helvetica".to_string();
+fn collect_used_font_names_in_commands(commands: &CommandBuffer, names: &mut BTreeSet<String>) {
+    let mut current_font: Arc<str> = Arc::from("Helvetica");
     for cmd in commands {
-        match cmd {
+        match &*cmd {
             Command::SetFontName(name) => current_font = name.clone(),
             Command::DrawString { .. } => {
-                names.insert(current_font.clone());
+                names.insert(current_font.to_string());
             }
             Command::DefineForm {
                 commands: form_commands,
@@ -2040,9 +3540,12 @@ fn validate_pdfx4_output_intent(options: &PdfOptions) -> io::Result<()> {
 }
 
 #[allow(dead_code)]
-fn collect_optional_content_names_in_commands(commands: &[Command], names: &mut BTreeSet<String>) {
+fn collect_optional_content_names_in_commands(
+    commands: &CommandBuffer,
+    names: &mut BTreeSet<String>,
+) {
     for cmd in commands {
-        match cmd {
+        match &*cmd {
             Command::BeginOptionalContent { name } => {
                 names.insert(name.clone());
             }
@@ -2070,7 +3573,7 @@ fn collect_tag_records(document: &Document) -> Vec<TagRecord> {
     for (page_index, page) in document.pages.iter().enumerate() {
         let mut stack: Vec<usize> = Vec::new();
         for cmd in &page.commands {
-            match cmd {
+            match &*cmd {
                 Command::BeginTag {
                     role,
                     mcid,
@@ -2112,11 +3615,11 @@ fn collect_font_usage(
     options: &PdfOptions,
 ) -> HashMap<String, FontUsage> {
     let mut map: HashMap<String, FontUsage> = HashMap::new();
-    let mut current_font = "Helvetica".to_string();
+    let mut current_font: Arc<str> = Arc::from("Helvetica");
 
     for page in &document.pages {
         for cmd in &page.commands {
-            match cmd {
+            match &*cmd {
                 Command::SetFontName(name) => current_font = name.clone(),
                 Command::DrawString { text, .. } => {
                     let Some(registry) = registry else {
@@ -2125,7 +3628,7 @@ fn collect_font_usage(
                     let Some(font) = registry.resolve(&current_font) else {
                         continue;
                     };
-                    let usage = map.entry(current_font.clone()).or_default();
+                    let usage = map.entry(current_font.to_string()).or_default();
                     let cache_key = glyph_cache_key(&current_font, text);
                     let glyph_map = if let Some(cached) = glyph_cache.get(&cache_key) {
                         cached.clone()
@@ -2172,8 +3675,8 @@ fn collect_image_sources(document: &Document) -> Vec<String> {
     let mut sources = BTreeSet::new();
     for page in &document.pages {
         for cmd in &page.commands {
-            if let Command::DrawImage { resource_id, .. } = cmd {
-                sources.insert(resource_id.clone());
+            if let Command::DrawImage { resource_id, .. } = &*cmd {
+                sources.insert(resource_id.to_string());
             }
         }
     }
@@ -2267,7 +3770,7 @@ fn build_extgstate_objects(
     let mut pairs: BTreeSet<(u16, u16)> = BTreeSet::new();
     for page in &document.pages {
         for cmd in &page.commands {
-            if let Command::SetOpacity { fill, stroke } = cmd {
+            if let Command::SetOpacity { fill, stroke } = &*cmd {
                 let f = ((*fill * 1000.0).round() as i32).clamp(0, 1000) as u16;
                 let s = ((*stroke * 1000.0).round() as i32).clamp(0, 1000) as u16;
                 pairs.insert((f, s));
@@ -2317,7 +3820,7 @@ fn build_shading_objects(
     let mut unique: BTreeMap<u64, Shading> = BTreeMap::new();
     for page in &document.pages {
         for cmd in &page.commands {
-            if let Command::ShadingFill(sh) = cmd {
+            if let Command::ShadingFill(sh) = &*cmd {
                 unique.entry(hash_shading(sh)).or_insert_with(|| sh.clone());
             }
         }
@@ -2351,8 +3854,10 @@ struct ImageData {
     color_space: &'static str,
     bits_per_component: u8,
     filter: &'static str,
+    decode: Option<&'static str>,
     data: Vec<u8>,
     alpha: Option<AlphaData>,
+    downsampled: bool,
 }
 
 struct AlphaData {
@@ -2363,22 +3868,98 @@ struct AlphaData {
     data: Vec<u8>,
 }
 
-fn load_image(source: &str) -> Option<ImageData> {
+fn load_image(source: &str, max_pixels: Option<(u32, u32)>) -> Option<ImageData> {
     if let Some((mime, data)) = parse_data_uri(source) {
-        return decode_image_bytes(&data, Some(&mime));
+        return decode_image_bytes(&data, Some(&mime), max_pixels);
     }
 
     let path = Path::new(source);
     let bytes = std::fs::read(path).ok()?;
-    decode_image_bytes(&bytes, None)
+    decode_image_bytes(&bytes, None, max_pixels)
+}
+
+// Why `load_image` returned None: the read error, or the decode failure with the supported formats.
+fn image_failure_reason(source: &str) -> String {
+    if let Some((_mime, data)) = parse_data_uri(source) {
+        return crate::assets::image_decode_error_message(&data);
+    }
+    match std::fs::read(source) {
+        Ok(bytes) => crate::assets::image_decode_error_message(&bytes),
+        Err(err) => err.to_string(),
+    }
+}
+
+// Scale factor (< 1) that fits `width`x`height` into `max_pixels`, or None if it already fits.
+fn downsample_scale(width: u32, height: u32, max_pixels: Option<(u32, u32)>) -> Option<f32> {
+    let (max_w, max_h) = max_pixels?;
+    let scale = (max_w as f32 / width.max(1) as f32).min(max_h as f32 / height.max(1) as f32);
+    (scale < 1.0).then_some(scale)
+}
+
+struct JpegHeader {
+    width: u32,
+    height: u32,
+    components: u8,
+    adobe: bool,
+}
+
+// Frame size and component count from the SOF marker, without decoding the scan data.
+fn jpeg_header(data: &[u8]) -> Option<JpegHeader> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut adobe = false;
+    let mut pos = 2usize;
+    while pos + 4 <= data.len() {
+        if data[pos] != 0xFF {
+            return None;
+        }
+        let marker = data[pos + 1];
+        if marker == 0xFF {
+            pos += 1;
+            continue;
+        }
+        if matches!(marker, 0x01 | 0xD0..=0xD7) {
+            pos += 2;
+            continue;
+        }
+        let len = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        let segment = data.get(pos + 4..pos + 2 + len)?;
+        match marker {
+            0xEE if segment.starts_with(b"Adobe") => adobe = true,
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                if segment.len() < 6 || segment[0] != 8 {
+                    return None;
+                }
+                return Some(JpegHeader {
+                    height: u16::from_be_bytes([segment[1], segment[2]]) as u32,
+                    width: u16::from_be_bytes([segment[3], segment[4]]) as u32,
+                    components: segment[5],
+                    adobe,
+                });
+            }
+            0xDA => return None,
+            _ => {}
+        }
+        pos += 2 + len;
+    }
+    None
 }
 
-fn decode_image_bytes(data: &[u8], mime: Option<&str>) -> Option<ImageData> {
+fn decode_image_bytes(
+    data: &[u8],
+    mime: Option<&str>,
+    max_pixels: Option<(u32, u32)>,
+) -> Option<ImageData> {
     let format = if let Some(mime) = mime {
         if mime.contains("png") {
             Some(image::ImageFormat::Png)
         } else if mime.contains("jpeg") || mime.contains("jpg") {
             Some(image::ImageFormat::Jpeg)
+        } else if mime.contains("webp") {
+            Some(image::ImageFormat::WebP)
+        } else if mime.contains("avif") {
+            Some(image::ImageFormat::Avif)
         } else {
             None
         }
@@ -2386,22 +3967,74 @@ fn decode_image_bytes(data: &[u8], mime: Option<&str>) -> Option<ImageData> {
         image::guess_format(data).ok()
     };
 
-    let decoded = image::load_from_memory(data).ok()?;
+    let is_jpeg = matches!(format, Some(image::ImageFormat::Jpeg));
+    // JPEGs within the pixel budget are embedded as-is (DCTDecode), without decoding.
+    if is_jpeg
+        && let Some(header) = jpeg_header(data)
+        && downsample_scale(header.width, header.height, max_pixels).is_none()
+    {
+        let (color_space, decode) = match header.components {
+            1 => ("/DeviceGray", None),
+            3 => ("/DeviceRGB", None),
+            // Adobe CMYK JPEGs store inverted samples.
+            4 if header.adobe => ("/DeviceCMYK", Some("[1 0 1 0 1 0 1 0]")),
+            4 => ("/DeviceCMYK", None),
+            _ => return None,
+        };
+        return Some(ImageData {
+            width: header.width,
+            height: header.height,
+            color_space,
+            bits_per_component: 8,
+            filter: "/DCTDecode",
+            decode,
+            data: data.to_vec(),
+            alpha: None,
+            downsampled: false,
+        });
+    }
+
+    let mut decoded = image::load_from_memory(data).ok()?;
+    let mut downsampled = false;
+    let (source_width, source_height) = decoded.dimensions();
+    if let Some(scale) = downsample_scale(source_width, source_height, max_pixels) {
+        let target_w = ((source_width as f32 * scale).round() as u32).max(1);
+        let target_h = ((source_height as f32 * scale).round() as u32).max(1);
+        decoded = decoded.resize_exact(target_w, target_h, image::imageops::FilterType::Triangle);
+        downsampled = true;
+    }
     let (width, height) = decoded.dimensions();
 
-    if matches!(format, Some(image::ImageFormat::Jpeg)) {
-        let color_space = match decoded.color() {
-            image::ColorType::L8 | image::ColorType::La8 => "/DeviceGray",
-            _ => "/DeviceRGB",
+    // Downsampled JPEGs stay JPEG so photos do not balloon through Flate.
+    if is_jpeg {
+        let (color_space, pixels) = if decoded.color().has_color() {
+            (
+                "/DeviceRGB",
+                image::DynamicImage::ImageRgb8(decoded.to_rgb8()),
+            )
+        } else {
+            (
+                "/DeviceGray",
+                image::DynamicImage::ImageLuma8(decoded.to_luma8()),
+            )
         };
+        let mut encoded = Vec::new();
+        pixels
+            .write_with_encoder(image::codecs::jpeg::JpegEncoder::new_with_quality(
+                &mut encoded,
+                JPEG_RESAMPLE_QUALITY,
+            ))
+            .ok()?;
         return Some(ImageData {
             width,
             height,
             color_space,
             bits_per_component: 8,
             filter: "/DCTDecode",
-            data: data.to_vec(),
+            decode: None,
+            data: encoded,
             alpha: None,
+            downsampled,
         });
     }
 
@@ -2436,11 +4069,15 @@ fn decode_image_bytes(data: &[u8], mime: Option<&str>) -> Option<ImageData> {
         color_space: "/DeviceRGB",
         bits_per_component: 8,
         filter: "/FlateDecode",
+        decode: None,
         data: compressed,
         alpha,
+        downsampled,
     })
 }
 
+const JPEG_RESAMPLE_QUALITY: u8 = 85;
+
 fn parse_data_uri(uri: &str) -> Option<(String, Vec<u8>)> {
     if !uri.starts_with("data:") {
         return None;
@@ -2471,6 +4108,14 @@ fn flate_compress(data: &[u8]) -> Vec<u8> {
     crate::flate_native::zlib_deflate_parallel(data)
 }
 
+fn zlib_compress(data: &[u8], level: u32) -> Vec<u8> {
+    let mut encoder =
+        flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::new(level.min(9)));
+    // Writing into a Vec cannot fail.
+    let _ = encoder.write_all(data);
+    encoder.finish().unwrap_or_default()
+}
+
 fn hash_bytes(data: &[u8]) -> u64 {
     use std::hash::{Hash, Hasher};
     let mut hasher = std::collections::hash_map::DefaultHasher::new();
@@ -2488,8 +4133,7 @@ fn hash_image(image: &ImageData) -> u64 {
     hasher.finish()
 }
 
-fn truetype_font_object(font: &RegisteredFont, descriptor_id: usize) -> String {
-    let base = sanitize_font_name(&font.name);
+fn truetype_font_object(font: &RegisteredFont, base: &str, descriptor_id: usize) -> String {
     let metrics = &font.metrics;
     let subtype = match font.program_kind {
         FontProgramKind::OpenTypeCff => "Type1",
@@ -2512,8 +4156,7 @@ fn truetype_font_object(font: &RegisteredFont, descriptor_id: usize) -> String {
     )
 }
 
-fn font_descriptor_object(font: &RegisteredFont, font_file_id: usize) -> String {
-    let base = sanitize_font_name(&font.name);
+fn font_descriptor_object(font: &RegisteredFont, base: &str, font_file_id: usize) -> String {
     let metrics = &font.metrics;
     let mut flags = if metrics.is_symbolic() { 4 } else { 32 };
     if metrics.is_fixed_pitch {
@@ -2561,6 +4204,211 @@ fn output_intent_object(oi: &OutputIntent, icc_id: usize, profile: PdfProfile) -
     dict
 }
 
+fn filespec_object(attachment: &EmbeddedFile, stream_id: usize) -> String {
+    let name = pdf_text_string(&attachment.name);
+    let mut dict = format!(
+        "<< /Type /Filespec /F {} /UF {} /EF << /F {} 0 R /UF {} 0 R >> /AFRelationship /{}",
+        name,
+        name,
+        stream_id,
+        stream_id,
+        attachment.relationship.as_pdf_name()
+    );
+    if let Some(desc) = attachment.description.as_deref() {
+        dict.push_str(&format!(" /Desc {}", pdf_text_string(desc)));
+    }
+    dict.push_str(" >>");
+    dict
+}
+
+// Commands that put marks on the page (or start a path that will). Path construction
+// counts so an artifact sequence never opens between a path and its painting operator.
+fn paints_content(cmd: &Command) -> bool {
+    matches!(
+        cmd,
+        Command::MoveTo { .. }
+            | Command::LineTo { .. }
+            | Command::CurveTo { .. }
+            | Command::ClosePath
+            | Command::Fill
+            | Command::FillEvenOdd
+            | Command::Stroke
+            | Command::FillStroke
+            | Command::FillStrokeEvenOdd
+            | Command::ShadingFill(_)
+            | Command::DrawString { .. }
+            | Command::DrawStringTransformed { .. }
+            | Command::DrawGlyphRun { .. }
+            | Command::DrawRect { .. }
+            | Command::DrawImage { .. }
+            | Command::DrawForm { .. }
+            | Command::ApplyBackdropFilter { .. }
+    )
+}
+
+// Commands an automatic artifact sequence must close before, to keep marked content
+// nested inside graphics-state saves and other marked-content sequences.
+fn ends_auto_artifact(cmd: &Command) -> bool {
+    matches!(
+        cmd,
+        Command::SaveState
+            | Command::RestoreState
+            | Command::BeginTag { .. }
+            | Command::EndTag
+            | Command::BeginArtifact { .. }
+            | Command::BeginOptionalContent { .. }
+            | Command::EndMarkedContent
+    )
+}
+
+// Encrypted files require a trailer /ID; without a `DocumentId` it is random.
+fn random_file_id() -> io::Result<String> {
+    let mut id = [0u8; 16];
+    getrandom::getrandom(&mut id).map_err(|err| io::Error::other(err.to_string()))?;
+    Ok(crate::encrypt::hex(&id))
+}
+
+const DEFAULT_SIGNATURE_FIELD_NAME: &str = "Signature1";
+const SIGNATURE_BYTE_RANGE_PLACEHOLDER: &str = "/ByteRange [0 0000000000 0000000000 0000000000]";
+
+fn signature_placeholder_object(contents_len: usize) -> String {
+    format!(
+        "<< /Type /Sig /Filter /Adobe.PPKLite /SubFilter /adbe.pkcs7.detached {} /Contents <{}> >>",
+        SIGNATURE_BYTE_RANGE_PLACEHOLDER,
+        "0".repeat(contents_len * 2)
+    )
+}
+
+// Signed byte ranges of a PDF written with a reserved signature: everything except the
+// hex `/Contents` string of the signature dictionary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignatureByteRange {
+    // `[offset1, length1, offset2, length2]`, as written to `/ByteRange`.
+    pub byte_range: [usize; 4],
+    // Signature bytes that fit in the reserved `/Contents`.
+    pub contents_capacity: usize,
+}
+
+impl SignatureByteRange {
+    // The bytes covered by the signature, in order, for hashing by the signer.
+    pub fn signed_data<'a>(&self, pdf: &'a [u8]) -> [&'a [u8]; 2] {
+        let [start1, len1, start2, len2] = self.byte_range;
+        [&pdf[start1..start1 + len1], &pdf[start2..start2 + len2]]
+    }
+}
+
+// Fill in the `/ByteRange` placeholder of a PDF rendered with a reserved signature and
+// return the ranges to sign. The file length must not change afterwards.
+pub fn patch_signature_byte_range(pdf: &mut [u8]) -> io::Result<SignatureByteRange> {
+    let placeholder = SIGNATURE_BYTE_RANGE_PLACEHOLDER.as_bytes();
+    let range_at = pdf
+        .windows(placeholder.len())
+        .rposition(|window| window == placeholder)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "pdf has no reserved signature byte range",
+            )
+        })?;
+    let contents_marker = b"/Contents <";
+    let contents_at = pdf[range_at..]
+        .windows(contents_marker.len())
+        .position(|window| window == contents_marker)
+        .map(|pos| range_at + pos + contents_marker.len() - 1)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "reserved signature has no /Contents",
+            )
+        })?;
+    let contents_end = pdf[contents_at..]
+        .iter()
+        .position(|b| *b == b'>')
+        .map(|pos| contents_at + pos + 1)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "reserved signature /Contents is not terminated",
+            )
+        })?;
+    let byte_range = [0, contents_at, contents_end, pdf.len() - contents_end];
+    let patched = format!(
+        "/ByteRange [0 {:010} {:010} {:010}]",
+        byte_range[1], byte_range[2], byte_range[3]
+    );
+    if patched.len() != placeholder.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "pdf is too large for the reserved byte range",
+        ));
+    }
+    pdf[range_at..range_at + placeholder.len()].copy_from_slice(patched.as_bytes());
+    Ok(SignatureByteRange {
+        byte_range,
+        contents_capacity: (contents_end - contents_at - 2) / 2,
+    })
+}
+
+// Write a DER-encoded detached PKCS#7 signature into the reserved `/Contents`. The rest
+// of the reservation stays zero-padded.
+pub fn embed_signature_contents(
+    pdf: &mut [u8],
+    range: &SignatureByteRange,
+    signature: &[u8],
+) -> io::Result<()> {
+    if signature.len() > range.contents_capacity {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "signature is {} bytes but only {} were reserved",
+                signature.len(),
+                range.contents_capacity
+            ),
+        ));
+    }
+    let start = range.byte_range[1] + 1;
+    let hex = signature
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<String>();
+    pdf[start..start + hex.len()].copy_from_slice(hex.as_bytes());
+    Ok(())
+}
+
+fn link_annotation_object(rect: [Pt; 4], action: &str) -> String {
+    format!(
+        "<< /Type /Annot /Subtype /Link /Rect [{} {} {} {}] /Border [0 0 0] /F 4{} >>",
+        fmt_pt(rect[0]),
+        fmt_pt(rect[1]),
+        fmt_pt(rect[2]),
+        fmt_pt(rect[3]),
+        action
+    )
+}
+
+// URI actions must be 7-bit ASCII; percent-encode anything else (and spaces).
+fn uri_ascii(uri: &str) -> String {
+    let mut out = String::with_capacity(uri.len());
+    for byte in uri.bytes() {
+        if byte.is_ascii_graphic() {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    out
+}
+
+// Flat name tree; `entries` must already be sorted by key.
+fn name_tree_dict(entries: &[(String, usize)]) -> String {
+    let names = entries
+        .iter()
+        .map(|(name, id)| format!("{} {} 0 R", pdf_text_string(name), id))
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!("<< /Names [{}] >>", names)
+}
+
 fn font_object(name: &str) -> String {
     let base = sanitize_font_name(name);
     format!(
@@ -2601,6 +4449,14 @@ fn shading_resources(shadings: &[(String, usize)]) -> String {
     format!("<< {} >>", entries.join(" "))
 }
 
+fn color_space_resources(spaces: &[(String, usize)]) -> String {
+    let mut entries = Vec::new();
+    for (resource, obj_id) in spaces {
+        entries.push(format!("/{} {} 0 R", resource, obj_id));
+    }
+    format!("<< {} >>", entries.join(" "))
+}
+
 fn optional_content_resources(entries: &[(String, usize)]) -> String {
     let mut out = Vec::new();
     for (resource, obj_id) in entries {
@@ -2665,12 +4521,12 @@ fn render_page(
 ) -> String {
     let mut out = String::new();
     let mut current_font_size = Pt::from_f32(12.0);
-    let mut current_font_name = "Helvetica".to_string();
+    let mut current_font_name: Arc<str> = Arc::from("Helvetica");
     let mut current_fill = Color::BLACK;
     let mut tag_stack: Vec<usize> = Vec::new();
 
     for cmd in &page.commands {
-        match cmd {
+        match &*cmd {
             Command::SaveState => out.push_str("q\n"),
             Command::RestoreState => out.push_str("Q\n"),
             Command::Translate(x, y) => {
@@ -2758,6 +4614,10 @@ fn render_page(
             Command::EndMarkedContent => {
                 out.push_str("EMC\n");
             }
+            Command::LinkAnnotation { .. }
+            | Command::NamedDestination { .. }
+            | Command::Bookmark { .. }
+            | Command::SignatureField { .. } => {}
             Command::SetFillColor(color) => {
                 current_fill = *color;
                 out.push_str(&color_to_pdf_fill(*color, options.color_space));
@@ -2872,7 +4732,7 @@ fn render_page(
             Command::FillStrokeEvenOdd => out.push_str("B*\n"),
             Command::DrawString { x, y, text } => {
                 out.push_str("BT\n");
-                let font_res = font_map.get(&current_font_name);
+                let font_res = font_map.get(current_font_name.as_ref());
                 let resource = font_res.map(|v| v.resource.as_str()).unwrap_or("F1");
                 out.push_str(&format!("/{} {} Tf\n", resource, fmt_pt(current_font_size)));
                 out.push_str(&format!(
@@ -2903,7 +4763,7 @@ fn render_page(
                                 continue;
                             }
                         }
-                        let cmap = font_glyph_maps.get(&current_font_name);
+                        let cmap = font_glyph_maps.get(current_font_name.as_ref());
                         let hex = encode_cid_hex(text, cmap);
                         out.push_str(&format!("{} Tj\n", hex));
                     }
@@ -2935,7 +4795,7 @@ fn render_page(
                 height,
                 resource_id,
             } => {
-                if let Some(name) = image_map.get(resource_id) {
+                if let Some(name) = image_map.get(resource_id.as_ref()) {
                     let draw_y = page_height - *y - *height;
                     out.push_str("q\n");
                     out.push_str(&format!(
@@ -3196,12 +5056,189 @@ fn build_gradient_function_objects(
     (objects, stitch_id, next_id)
 }
 
+// /PageLabels number tree restarting at each record, when any record sets a label prefix.
+fn record_page_labels(records: &[(RecordRange, Option<String>)]) -> Option<String> {
+    if records.iter().all(|(_, prefix)| prefix.is_none()) {
+        return None;
+    }
+    let entries: Vec<String> = records
+        .iter()
+        .filter(|(range, _)| range.page_count > 0)
+        .map(|(range, prefix)| {
+            page_label_entry(
+                range.first_page - 1,
+                PageLabelStyle::Decimal,
+                prefix.as_deref(),
+                1,
+            )
+        })
+        .collect();
+    Some(format!("<< /Nums [{}] >>", entries.join(" ")))
+}
+
+fn page_label_entry(
+    page_index: usize,
+    style: PageLabelStyle,
+    prefix: Option<&str>,
+    start: u32,
+) -> String {
+    let mut entry = format!("{} <<", page_index);
+    if let Some(name) = style.pdf_name() {
+        entry.push_str(&format!(" /S /{}", name));
+    }
+    if let Some(prefix) = prefix {
+        entry.push_str(&format!(" /P {}", pdf_text_string(prefix)));
+    }
+    if start > 1 {
+        entry.push_str(&format!(" /St {}", start));
+    }
+    entry.push_str(" >>");
+    entry
+}
+
+// /PageLabels from the builder's spec. Ranges past the last page are dropped; pages before
+// the first range keep decimal page numbers.
+fn spec_page_labels(spec: &PageLabelSpec, page_count: usize) -> Option<String> {
+    let mut ranges: Vec<&PageLabelRange> = spec
+        .ranges
+        .iter()
+        .filter(|range| range.first_page >= 1 && range.first_page <= page_count)
+        .collect();
+    if ranges.is_empty() {
+        return None;
+    }
+    ranges.sort_by_key(|range| range.first_page);
+    // The last range given for a page wins.
+    ranges.reverse();
+    ranges.dedup_by_key(|range| range.first_page);
+    ranges.reverse();
+    let mut entries = Vec::new();
+    if ranges[0].first_page > 1 {
+        entries.push(page_label_entry(0, PageLabelStyle::Decimal, None, 1));
+    }
+    for range in ranges {
+        entries.push(page_label_entry(
+            range.first_page - 1,
+            range.style,
+            range.prefix.as_deref(),
+            range.start,
+        ));
+    }
+    Some(format!("<< /Nums [{}] >>", entries.join(" ")))
+}
+
+// /PageLabels matching the page counters CSS margin boxes print, when any is not decimal.
+// The counter restarts with each batch record, as it does in the margin boxes.
+fn css_page_labels(
+    styles: &BTreeMap<usize, PageLabelStyle>,
+    records: &[(RecordRange, Option<String>)],
+    page_count: usize,
+) -> Option<String> {
+    if styles
+        .values()
+        .all(|style| *style == PageLabelStyle::Decimal)
+    {
+        return None;
+    }
+    let record_starts: BTreeSet<usize> = records
+        .iter()
+        .map(|(range, _)| range.first_page - 1)
+        .collect();
+    let mut entries = Vec::new();
+    let mut record_start = 0usize;
+    let mut current: Option<PageLabelStyle> = None;
+    for page_index in 0..page_count {
+        let style = styles
+            .get(&page_index)
+            .copied()
+            .unwrap_or(PageLabelStyle::Decimal);
+        let new_record = record_starts.contains(&page_index);
+        if new_record {
+            record_start = page_index;
+        }
+        if new_record || current != Some(style) {
+            let counter = (page_index - record_start + 1) as u32;
+            entries.push(page_label_entry(page_index, style, None, counter));
+            current = Some(style);
+        }
+    }
+    Some(format!("<< /Nums [{}] >>", entries.join(" ")))
+}
+
+// XMP packet for a record's first page, mirroring the document Info keys it sets.
+fn record_xmp_metadata(meta: &RecordMetadata) -> Option<String> {
+    if meta.title.is_none()
+        && meta.author.is_none()
+        && meta.subject.is_none()
+        && meta.keywords.is_none()
+    {
+        return None;
+    }
+    let mut out = String::new();
+    out.push_str(r#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?>"#);
+    out.push_str("\n<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n");
+    out.push_str("<rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n");
+    out.push_str("<rdf:Description xmlns:dc=\"http://purl.org/dc/elements/1.1/\" ");
+    out.push_str("xmlns:pdf=\"http://ns.adobe.com/pdf/1.3/\">");
+    if let Some(title) = meta.title.as_deref() {
+        out.push_str("<dc:title><rdf:Alt><rdf:li xml:lang=\"x-default\">");
+        out.push_str(&escape_xml_text(title));
+        out.push_str("</rdf:li></rdf:Alt></dc:title>");
+    }
+    if let Some(author) = meta.author.as_deref() {
+        out.push_str("<dc:creator><rdf:Seq><rdf:li>");
+        out.push_str(&escape_xml_text(author));
+        out.push_str("</rdf:li></rdf:Seq></dc:creator>");
+    }
+    if let Some(subject) = meta.subject.as_deref() {
+        out.push_str("<dc:description><rdf:Alt><rdf:li xml:lang=\"x-default\">");
+        out.push_str(&escape_xml_text(subject));
+        out.push_str("</rdf:li></rdf:Alt></dc:description>");
+    }
+    if let Some(keywords) = meta.keywords.as_deref() {
+        out.push_str("<pdf:Keywords>");
+        out.push_str(&escape_xml_text(keywords));
+        out.push_str("</pdf:Keywords>");
+    }
+    out.push_str("</rdf:Description>\n</rdf:RDF>\n</x:xmpmeta>\n");
+    out.push_str(r#"<?xpacket end="w"?>"#);
+    Some(out)
+}
+
 fn stream_object(content: &str) -> String {
     let length = content.as_bytes().len();
     format!("<< /Length {} >>\nstream\n{}\nendstream", length, content)
 }
 
-fn page_box_entries(profile: PdfProfile, page_size: Size) -> String {
+fn page_box_entries(profile: PdfProfile, page_size: Size, boxes: Option<&PageBoxSpec>) -> String {
+    if let Some(spec) = boxes {
+        let margin = spec.media_margin();
+        let trim = [
+            margin,
+            margin,
+            margin + page_size.width,
+            margin + page_size.height,
+        ];
+        let bleed = [
+            trim[0] - spec.bleed,
+            trim[1] - spec.bleed,
+            trim[2] + spec.bleed,
+            trim[3] + spec.bleed,
+        ];
+        let media = [
+            Pt::ZERO,
+            Pt::ZERO,
+            page_size.width + margin + margin,
+            page_size.height + margin + margin,
+        ];
+        let rect = |r: [Pt; 4]| r.map(fmt_pt).join(" ");
+        return format!(
+            " /TrimBox [{}] /BleedBox [{}] /CropBox [{}]",
+            rect(trim),
+            rect(bleed),
+            rect(media)
+        );
+    }
     if profile != PdfProfile::PdfX4 {
         return String::new();
     }
@@ -3216,6 +5253,77 @@ fn page_box_entries(profile: PdfProfile, page_size: Size) -> String {
     )
 }
 
+// Crop marks at each trim corner and registration targets on each side, all outside the bleed.
+fn printer_marks_path(spec: &PageBoxSpec, origin: Pt, page_size: Size) -> String {
+    let offset = spec.mark_offset().to_f32();
+    let length = PRINTER_MARK_LENGTH;
+    let (x0, y0) = (origin.to_f32(), origin.to_f32());
+    let (x1, y1) = (
+        x0 + page_size.width.to_f32(),
+        y0 + page_size.height.to_f32(),
+    );
+    let mut out = String::new();
+    if spec.crop_marks {
+        for (x, dx) in [(x0, -1.0), (x1, 1.0)] {
+            for (y, dy) in [(y0, -1.0), (y1, 1.0)] {
+                push_line(&mut out, x + dx * offset, y, x + dx * (offset + length), y);
+                push_line(&mut out, x, y + dy * offset, x, y + dy * (offset + length));
+            }
+        }
+    }
+    if spec.registration_marks {
+        let center = offset + length / 2.0;
+        let radius = length / 4.0;
+        let (mid_x, mid_y) = ((x0 + x1) / 2.0, (y0 + y1) / 2.0);
+        let targets = [
+            (mid_x, y0 - center),
+            (mid_x, y1 + center),
+            (x0 - center, mid_y),
+            (x1 + center, mid_y),
+        ];
+        for (cx, cy) in targets {
+            push_line(&mut out, cx - length / 2.0, cy, cx + length / 2.0, cy);
+            push_line(&mut out, cx, cy - length / 2.0, cx, cy + length / 2.0);
+            push_circle(&mut out, cx, cy, radius);
+        }
+    }
+    out
+}
+
+fn push_line(out: &mut String, ax: f32, ay: f32, bx: f32, by: f32) {
+    out.push_str(&format!(
+        "{} {} m {} {} l S\n",
+        fmt(ax),
+        fmt(ay),
+        fmt(bx),
+        fmt(by)
+    ));
+}
+
+fn push_circle(out: &mut String, cx: f32, cy: f32, r: f32) {
+    // Four cubic Beziers; 0.5523 is the usual quarter-circle control distance.
+    let k = r * 0.5523;
+    out.push_str(&format!("{} {} m\n", fmt(cx + r), fmt(cy)));
+    let quarters = [
+        (cx + r, cy + k, cx + k, cy + r, cx, cy + r),
+        (cx - k, cy + r, cx - r, cy + k, cx - r, cy),
+        (cx - r, cy - k, cx - k, cy - r, cx, cy - r),
+        (cx + k, cy - r, cx + r, cy - k, cx + r, cy),
+    ];
+    for (ax, ay, bx, by, ex, ey) in quarters {
+        out.push_str(&format!(
+            "{} {} {} {} {} {} c\n",
+            fmt(ax),
+            fmt(ay),
+            fmt(bx),
+            fmt(by),
+            fmt(ex),
+            fmt(ey)
+        ));
+    }
+    out.push_str("S\n");
+}
+
 fn info_object(title: Option<&str>, profile: PdfProfile) -> String {
     let mut entries: Vec<String> = Vec::new();
     if let Some(title) = title {
@@ -3282,10 +5390,13 @@ fn write_pdf_object<W: Write>(
     if let Some(slot) = offsets.get_mut(obj_id) {
         *slot = *offset;
     }
-    write_str(writer, &format!("{} 0 obj\n", obj_id), offset)?;
-    write_bytes(writer, body.as_bytes(), offset)?;
-    write_bytes(writer, b"\nendobj\n", offset)?;
-    Ok(())
+    let mut head = StackBuf::<32>::new();
+    let _ = writeln!(head, "{} 0 obj", obj_id);
+    write_parts(
+        writer,
+        [head.as_bytes(), body.as_bytes(), b"\nendobj\n"],
+        offset,
+    )
 }
 
 fn write_pdf_stream_object<W: Write>(
@@ -3299,27 +5410,76 @@ fn write_pdf_stream_object<W: Write>(
     if let Some(slot) = offsets.get_mut(obj_id) {
         *slot = *offset;
     }
-    write_str(writer, &format!("{} 0 obj\n", obj_id), offset)?;
-    if dict_entries.trim().is_empty() {
-        write_str(
-            writer,
-            &format!("<< /Length {} >>\nstream\n", stream_data.len()),
-            offset,
-        )?;
-    } else {
-        write_str(
-            writer,
-            &format!(
-                "<< {} /Length {} >>\nstream\n",
-                dict_entries.trim(),
-                stream_data.len()
-            ),
-            offset,
-        )?;
+    let dict_entries = dict_entries.trim();
+    let mut head = StackBuf::<32>::new();
+    let _ = write!(head, "{} 0 obj\n<<", obj_id);
+    let mut length = StackBuf::<48>::new();
+    let _ = write!(length, " /Length {} >>\nstream\n", stream_data.len());
+    let separator: &[u8] = if dict_entries.is_empty() { b"" } else { b" " };
+    write_parts(
+        writer,
+        [
+            head.as_bytes(),
+            separator,
+            dict_entries.as_bytes(),
+            length.as_bytes(),
+            stream_data,
+            b"\nendstream\nendobj\n",
+        ],
+        offset,
+    )
+}
+
+// Writes `parts` back to back without joining them first; writers that support vectored
+// output (`BufWriter`, `Vec<u8>`, files) take them in as few calls as they can.
+fn write_parts<W: Write, const N: usize>(
+    writer: &mut W,
+    parts: [&[u8]; N],
+    offset: &mut usize,
+) -> io::Result<()> {
+    let total: usize = parts.iter().map(|part| part.len()).sum();
+    let mut slices = parts.map(io::IoSlice::new);
+    let mut remaining = &mut slices[..];
+    io::IoSlice::advance_slices(&mut remaining, 0);
+    while !remaining.is_empty() {
+        match writer.write_vectored(remaining) {
+            Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+            Ok(written) => io::IoSlice::advance_slices(&mut remaining, written),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    *offset += total;
+    Ok(())
+}
+
+// Fixed-capacity text buffer for object headers, formatted without a heap allocation.
+struct StackBuf<const N: usize> {
+    bytes: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> StackBuf<N> {
+    fn new() -> Self {
+        Self {
+            bytes: [0; N],
+            len: 0,
+        }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+impl<const N: usize> std::fmt::Write for StackBuf<N> {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        let end = self.len + s.len();
+        let slot = self.bytes.get_mut(self.len..end).ok_or(std::fmt::Error)?;
+        slot.copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
     }
-    write_bytes(writer, stream_data, offset)?;
-    write_bytes(writer, b"\nendstream\nendobj\n", offset)?;
-    Ok(())
 }
 
 fn write_bytes<W: Write>(writer: &mut W, data: &[u8], offset: &mut usize) -> io::Result<()> {
@@ -3347,6 +5507,19 @@ fn escape_pdf_string(input: &str) -> String {
     out
 }
 
+// PDF text string: literal for ASCII, UTF-16BE with BOM otherwise.
+fn pdf_text_string(input: &str) -> String {
+    if input.is_ascii() {
+        return format!("({})", escape_pdf_string(input));
+    }
+    let mut out = String::from("<FEFF");
+    for unit in input.encode_utf16() {
+        out.push_str(&format!("{:04X}", unit));
+    }
+    out.push('>');
+    out
+}
+
 struct WinAnsiEncoded {
     text: String,
     replaced: usize,
@@ -3502,6 +5675,10 @@ fn build_xmp_metadata(
             out.push_str("<rdf:Description xmlns:pdfaid=\"http://www.aiim.org/pdfa/ns/id/\" ");
             out.push_str("pdfaid:part=\"2\" pdfaid:conformance=\"B\"/>\n");
         }
+        PdfProfile::PdfA3b => {
+            out.push_str("<rdf:Description xmlns:pdfaid=\"http://www.aiim.org/pdfa/ns/id/\" ");
+            out.push_str("pdfaid:part=\"3\" pdfaid:conformance=\"B\"/>\n");
+        }
         PdfProfile::PdfX4 => {
             out.push_str("<rdf:Description xmlns:pdfxid=\"http://www.npes.org/pdfx/ns/id/\" ");
             out.push_str("pdfxid:part=\"4\" pdfxid:GTS_PDFXVersion=\"PDF/X-4\"/>\n");
@@ -3686,39 +5863,45 @@ fn detect_direction(text: &str) -> rustybuzz::Direction {
 }
 
 fn fmt(value: f32) -> String {
-    if !value.is_finite() {
-        return "0".to_string();
-    }
-    let fixed = I32F32::from_num(value);
-    let scaled = (fixed * I32F32::from_num(1000)).round();
-    let milli: i64 = scaled.to_num();
-    format_milli(milli)
+    num(value).to_string()
 }
 
-fn format_milli(milli: i64) -> String {
-    if milli == 0 {
-        return "0".to_string();
-    }
-    let sign = if milli < 0 { "-" } else { "" };
-    let abs = milli.abs();
-    let int_part = abs / 1000;
-    let frac_part = abs % 1000;
-    if frac_part == 0 {
-        format!("{}{}", sign, int_part)
-    } else {
-        let mut s = format!("{}{}.{:03}", sign, int_part, frac_part);
-        while s.ends_with('0') {
-            s.pop();
+fn fmt_pt(value: Pt) -> String {
+    pt(value).to_string()
+}
+
+// A number as content streams write it: thousandths, without trailing zeros. Formats in place,
+// so content generation writes numbers without allocating a string for each.
+#[derive(Clone, Copy)]
+struct Milli(i64);
+
+impl std::fmt::Display for Milli {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0 < 0 {
+            f.write_str("-")?;
         }
-        if s.ends_with('.') {
-            s.pop();
+        let abs = self.0.unsigned_abs();
+        write!(f, "{}", abs / 1000)?;
+        match abs % 1000 {
+            0 => Ok(()),
+            frac if frac % 100 == 0 => write!(f, ".{}", frac / 100),
+            frac if frac % 10 == 0 => write!(f, ".{:02}", frac / 10),
+            frac => write!(f, ".{:03}", frac),
         }
-        s
     }
 }
 
-fn fmt_pt(value: Pt) -> String {
-    format_milli(value.to_milli_i64())
+fn num(value: f32) -> Milli {
+    if !value.is_finite() {
+        return Milli(0);
+    }
+    let fixed = I32F32::from_num(value);
+    let scaled = (fixed * I32F32::from_num(1000)).round();
+    Milli(scaled.to_num())
+}
+
+fn pt(value: Pt) -> Milli {
+    Milli(value.to_milli_i64())
 }
 
 fn clamp_unit(value: f32) -> f32 {
@@ -3733,7 +5916,27 @@ fn clamp_unit(value: f32) -> f32 {
     }
 }
 
+// Named colorant printed on its own plate; viewers and proofers use the CMYK alternate,
+// scaled linearly by tint.
+fn separation_color_space(name: &str, alternate: [f32; 4]) -> String {
+    format!(
+        "[/Separation /{} /DeviceCMYK << /FunctionType 2 /Domain [0 1] /C0 [0 0 0 0] /C1 [{}] /N 1 >>]",
+        escape_pdf_name(name),
+        alternate.map(fmt).join(" ")
+    )
+}
+
 fn rgb_to_cmyk(color: Color) -> (f32, f32, f32, f32) {
+    match color.process {
+        Some(ProcessColor::Cmyk([c, m, y, k])) => return (c, m, y, k),
+        Some(ProcessColor::Spot {
+            tint, alternate, ..
+        }) => {
+            let [c, m, y, k] = alternate.map(|v| v * tint);
+            return (c, m, y, k);
+        }
+        None => {}
+    }
     let r = clamp_unit(color.r);
     let g = clamp_unit(color.g);
     let b = clamp_unit(color.b);
@@ -3757,7 +5960,14 @@ fn color_components(color: Color, space: ColorSpace) -> Vec<f32> {
     }
 }
 
+// Device CMYK and spot colors are written as DeviceCMYK whatever the output color space;
+// spot colors only reach here where no Separation resource is available.
 fn color_to_pdf_fill(color: Color, space: ColorSpace) -> String {
+    let space = if color.process.is_none() {
+        space
+    } else {
+        ColorSpace::Cmyk
+    };
     match space {
         ColorSpace::Rgb => format!("{} {} {} rg\n", fmt(color.r), fmt(color.g), fmt(color.b)),
         ColorSpace::Cmyk => {
@@ -3768,6 +5978,11 @@ fn color_to_pdf_fill(color: Color, space: ColorSpace) -> String {
 }
 
 fn color_to_pdf_stroke(color: Color, space: ColorSpace) -> String {
+    let space = if color.process.is_none() {
+        space
+    } else {
+        ColorSpace::Cmyk
+    };
     match space {
         ColorSpace::Rgb => format!("{} {} {} RG\n", fmt(color.r), fmt(color.g), fmt(color.b)),
         ColorSpace::Cmyk => {
@@ -3780,6 +5995,7 @@ fn color_to_pdf_stroke(color: Color, space: ColorSpace) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::FullBleed;
     use crate::canvas::Page;
     use std::path::PathBuf;
     use std::sync::Arc;
@@ -3795,10 +6011,62 @@ mod tests {
         assert!(cmap.contains("<0004> <D83DDE00>"));
     }
 
+    #[test]
+    fn content_numbers_and_vectored_objects_write_the_same_bytes() {
+        let formatted: Vec<String> = [0.0, 12.0, 1.5, -0.25, 1.05, 0.0004, -0.0004, f32::NAN]
+            .into_iter()
+            .map(fmt)
+            .collect();
+        assert_eq!(
+            formatted,
+            ["0", "12", "1.5", "-0.25", "1.05", "0", "0", "0"]
+        );
+        assert_eq!(fmt_pt(Pt::from_f32(-3.125)), "-3.125");
+
+        // Accepts one byte per call, so every part boundary is a partial write.
+        struct Trickle(Vec<u8>);
+        impl Write for Trickle {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                let Some(byte) = buf.first() else {
+                    return Ok(0);
+                };
+                self.0.push(*byte);
+                Ok(1)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        let mut out = Trickle(Vec::new());
+        let mut offset = 0;
+        let mut offsets = vec![0; 8];
+        write_pdf_object(&mut out, &mut offset, &mut offsets, 3, "<< /Type /Page >>").unwrap();
+        write_pdf_stream_object(&mut out, &mut offset, &mut offsets, 7, " ", b"q Q").unwrap();
+        write_pdf_stream_object(
+            &mut out,
+            &mut offset,
+            &mut offsets,
+            5,
+            "/Subtype /Form",
+            b"",
+        )
+        .unwrap();
+        let expected = "3 0 obj\n<< /Type /Page >>\nendobj\n\
+                        7 0 obj\n<< /Length 3 >>\nstream\nq Q\nendstream\nendobj\n\
+                        5 0 obj\n<< /Subtype /Form /Length 0 >>\nstream\n\nendstream\nendobj\n";
+        assert_eq!(String::from_utf8(out.0).unwrap(), expected);
+        assert_eq!(offset, expected.len());
+        assert_eq!((offsets[3], offsets[7]), (0, 33));
+    }
+
     fn one_page_document(commands: Vec<Command>) -> Document {
         Document {
             page_size: Size::a4(),
-            pages: vec![Page { commands }],
+            pages: vec![Page {
+                commands: commands.into(),
+                size: None,
+                rotate: 0,
+            }],
         }
     }
 
@@ -3881,7 +6149,7 @@ mod tests {
 
     fn text_page(font_name: &str, text: &str) -> Document {
         one_page_document(vec![
-            Command::SetFontName(font_name.to_string()),
+            Command::SetFontName(font_name.into()),
             Command::SetFontSize(Pt::from_f32(11.0)),
             Command::DrawString {
                 x: Pt::from_f32(72.0),
@@ -3926,6 +6194,186 @@ mod tests {
         assert!(pdf.contains("/GTS_PDFXVersion (PDF/X-4)"));
     }
 
+    #[test]
+    fn uri_link_annotations_emit_ascii_uri_actions() {
+        let doc = one_page_document(vec![Command::LinkAnnotation {
+            x: Pt::from_f32(72.0),
+            y: Pt::from_f32(100.0),
+            width: Pt::from_f32(120.0),
+            height: Pt::from_f32(14.0),
+            target: LinkTarget::Uri("https://example.com/r\u{e9}sum\u{e9} (v2)".to_string()),
+        }]);
+        let bytes =
+            document_to_pdf_with_metrics_and_registry(&doc, None, None, &PdfOptions::default())
+                .unwrap();
+        let pdf = String::from_utf8_lossy(&bytes);
+        assert_eq!(count_token(&bytes, b"/Subtype /Link"), 1);
+        assert!(pdf.contains(" /Annots ["));
+        assert!(pdf.contains("/Rect [72 "));
+        assert!(pdf.contains("/S /URI /URI (https://example.com/r%C3%A9sum%C3%A9%20\\(v2\\))"));
+    }
+
+    #[test]
+    fn tagged_profile_wires_document_root_links_and_artifacts() {
+        let doc = one_page_document(vec![
+            Command::SetFillColor(Color::rgb(0.9, 0.9, 0.9)),
+            Command::DrawRect {
+                x: Pt::from_f32(72.0),
+                y: Pt::from_f32(72.0),
+                width: Pt::from_f32(200.0),
+                height: Pt::from_f32(40.0),
+            },
+            Command::BeginTag {
+                role: "P".to_string(),
+                mcid: Some(0),
+                alt: None,
+                scope: None,
+                table_id: None,
+                col_index: None,
+                group_only: false,
+            },
+            Command::DrawString {
+                x: Pt::from_f32(80.0),
+                y: Pt::from_f32(90.0),
+                text: "Read the docs".to_string(),
+            },
+            Command::LinkAnnotation {
+                x: Pt::from_f32(80.0),
+                y: Pt::from_f32(80.0),
+                width: Pt::from_f32(60.0),
+                height: Pt::from_f32(12.0),
+                target: LinkTarget::Uri("https://example.com".to_string()),
+            },
+            Command::EndTag,
+        ]);
+        let options = PdfOptions {
+            pdf_profile: PdfProfile::Tagged,
+            ..PdfOptions::default()
+        };
+        let bytes = document_to_pdf_with_metrics_and_registry(&doc, None, None, &options).unwrap();
+        let pdf = String::from_utf8_lossy(&bytes);
+
+        assert_eq!(count_token(&bytes, b"/S /Document"), 1);
+        assert_eq!(count_token(&bytes, b"/S /Link"), 1);
+        assert!(pdf.contains("/K << /Type /OBJR /Obj "));
+        assert!(
+            pdf.contains("/S /P /P "),
+            "paragraph nests under the document root"
+        );
+        assert!(
+            pdf.contains("/K [0 "),
+            "paragraph holds its MCID and the link element"
+        );
+        assert!(pdf.contains("/Contents (https://example.com) /StructParent 1"));
+        assert!(pdf.contains("/ParentTreeNextKey 2"));
+
+        let content = String::from_utf8_lossy(&page_content_bytes(&bytes)).into_owned();
+        assert_eq!(count_token(content.as_bytes(), b"/Artifact BMC"), 1);
+        let artifact = content.find("/Artifact BMC").unwrap();
+        let paragraph = content
+            .find("/P <</MCID 0>> BDC")
+            .expect("tagged paragraph");
+        assert!(artifact < paragraph, "untagged background is an artifact");
+        assert!(content[artifact..paragraph].contains("EMC"));
+    }
+
+    #[test]
+    fn reserved_signature_byte_range_is_patched_and_filled_in_place() {
+        let doc = one_page_document(vec![]);
+        let options = PdfOptions::default().reserve_signature_byte_range(64);
+        let mut bytes =
+            document_to_pdf_with_metrics_and_registry(&doc, None, None, &options).expect("pdf");
+        let len = bytes.len();
+        assert_eq!(
+            count_token(&bytes, b"/FT /Sig /T (Signature1) /Rect [0 0 0 0]"),
+            1
+        );
+        assert_eq!(count_token(&bytes, b"/SigFlags 3"), 1);
+
+        let range = patch_signature_byte_range(&mut bytes).expect("patch");
+        assert_eq!(bytes.len(), len);
+        assert_eq!(range.contents_capacity, 64);
+        let [start1, len1, start2, len2] = range.byte_range;
+        assert_eq!(start1, 0);
+        assert_eq!(start2 + len2, len);
+        assert_eq!(
+            start2 - len1,
+            64 * 2 + 2,
+            "only the hex /Contents is excluded"
+        );
+        assert_eq!(bytes[len1], b'<');
+        assert_eq!(bytes[start2 - 1], b'>');
+        let expected = format!("/ByteRange [0 {:010} {:010} {:010}]", len1, start2, len2);
+        assert_eq!(count_token(&bytes, expected.as_bytes()), 1);
+        let signed = range.signed_data(&bytes);
+        assert_eq!(signed[0].len() + signed[1].len(), len - 130);
+
+        let too_long = vec![0u8; 65];
+        assert!(embed_signature_contents(&mut bytes, &range, &too_long).is_err());
+        embed_signature_contents(&mut bytes, &range, &[0xAB; 4]).expect("embed");
+        assert_eq!(count_token(&bytes, b"/Contents <ABABABAB0000"), 1);
+        let report = crate::inspect_pdf_bytes(&bytes).expect("still a valid pdf");
+        assert_eq!(report.signature_fields.len(), 1);
+        assert!(report.signature_fields[0].signed);
+
+        let mut unreserved =
+            document_to_pdf_with_metrics_and_registry(&doc, None, None, &PdfOptions::default())
+                .expect("pdf");
+        assert!(patch_signature_byte_range(&mut unreserved).is_err());
+    }
+
+    #[test]
+    fn pdfa3b_embeds_attachments_with_af_relationship() {
+        let doc = one_page_document(vec![]);
+        let xml = b"<rsm:CrossIndustryInvoice/>".to_vec();
+        let options = PdfOptions {
+            pdf_profile: PdfProfile::PdfA3b,
+            attachments: vec![
+                EmbeddedFile::new(
+                    "factur-x.xml",
+                    "text/xml",
+                    xml.clone(),
+                    AfRelationship::Data,
+                )
+                .with_description("Factur-X invoice"),
+                EmbeddedFile::new(
+                    "notes.txt",
+                    "text/plain",
+                    b"n".to_vec(),
+                    AfRelationship::Supplement,
+                ),
+            ],
+            ..PdfOptions::default()
+        };
+
+        let bytes = document_to_pdf_with_metrics_and_registry(&doc, None, None, &options).unwrap();
+        let pdf = String::from_utf8_lossy(&bytes);
+        assert!(pdf.contains("pdfaid:part=\"3\" pdfaid:conformance=\"B\""));
+        assert!(pdf.contains("/Type /EmbeddedFile /Subtype /text#2Fxml"));
+        assert!(pdf.contains("/AFRelationship /Data"));
+        assert!(pdf.contains("/AFRelationship /Supplement"));
+        assert!(pdf.contains("/Desc (Factur-X invoice)"));
+        assert!(pdf.contains("/EmbeddedFiles << /Names [(factur-x.xml)"));
+        assert!(pdf.contains(" /AF ["));
+
+        let parsed = lopdf::Document::load_mem(&bytes).expect("load pdf");
+        let embedded_payloads = parsed
+            .objects
+            .values()
+            .filter_map(|obj| obj.as_stream().ok())
+            .filter(|stream| {
+                stream
+                    .dict
+                    .get(b"Type")
+                    .and_then(lopdf::Object::as_name)
+                    .is_ok_and(|name| name == b"EmbeddedFile")
+            })
+            .map(|stream| stream.decompressed_content().expect("inflate attachment"))
+            .collect::<Vec<_>>();
+        assert_eq!(embedded_payloads.len(), 2);
+        assert!(embedded_payloads.contains(&xml));
+    }
+
     #[test]
     fn ocg_and_artifact_marked_content_emit_tokens() {
         let doc = one_page_document(vec![
@@ -3956,6 +6404,155 @@ mod tests {
         assert!(count_page_content_token(&bytes, b"/OC /WM BDC") > 0);
     }
 
+    #[test]
+    fn cmyk_and_spot_colors_emit_device_cmyk_and_separation() {
+        let spot = Color::spot("PANTONE 185 C", 0.5, [0.0, 0.91, 0.76, 0.0]).unwrap();
+        let full = Color::spot("PANTONE 185 C", 1.0, [0.0, 0.91, 0.76, 0.0]).unwrap();
+        let rect = Command::DrawRect {
+            x: Pt::from_f32(12.0),
+            y: Pt::from_f32(16.0),
+            width: Pt::from_f32(40.0),
+            height: Pt::from_f32(20.0),
+        };
+        let doc = one_page_document(vec![
+            Command::SetFillColor(spot),
+            Command::SetStrokeColor(Color::cmyk(0.1, 0.2, 0.3, 0.4)),
+            rect.clone(),
+            Command::SetFillColor(full),
+            Command::SetStrokeColor(full),
+            rect,
+        ]);
+
+        let bytes =
+            document_to_pdf_with_metrics_and_registry(&doc, None, None, &PdfOptions::default())
+                .unwrap();
+        assert_eq!(
+            count_token(
+                &bytes,
+                b"[/Separation /PANTONE#20185#20C /DeviceCMYK << /FunctionType 2 /Domain [0 1] /C0 [0 0 0 0] /C1 [0 0.91 0.76 0] /N 1 >>]"
+            ),
+            1
+        );
+        assert_eq!(count_token(&bytes, b"/ColorSpace << /CS1 "), 1);
+        assert_eq!(count_page_content_token(&bytes, b"/CS1 cs 0.5 scn"), 1);
+        assert_eq!(count_page_content_token(&bytes, b"/CS1 cs 1 scn"), 1);
+        assert_eq!(count_page_content_token(&bytes, b"/CS1 CS 1 SCN"), 1);
+        // Device CMYK survives RGB output unconverted.
+        assert_eq!(count_page_content_token(&bytes, b"0.1 0.2 0.3 0.4 K"), 1);
+    }
+
+    #[test]
+    fn pages_of_different_sizes_get_their_own_media_box() {
+        let rect = Command::DrawRect {
+            x: Pt::from_f32(10.0),
+            y: Pt::from_f32(10.0),
+            width: Pt::from_f32(20.0),
+            height: Pt::from_f32(20.0),
+        };
+        let doc = Document {
+            page_size: Size {
+                width: Pt::from_f32(200.0),
+                height: Pt::from_f32(100.0),
+            },
+            pages: vec![
+                Page {
+                    commands: vec![rect.clone()].into(),
+                    size: None,
+                    rotate: 0,
+                },
+                Page {
+                    commands: vec![rect].into(),
+                    size: Some(Size {
+                        width: Pt::from_f32(300.0),
+                        height: Pt::from_f32(150.0),
+                    }),
+                    rotate: 0,
+                },
+            ],
+        };
+        let bytes =
+            document_to_pdf_with_metrics_and_registry(&doc, None, None, &PdfOptions::default())
+                .unwrap();
+        let pdf = String::from_utf8_lossy(&bytes);
+        assert!(pdf.contains("/MediaBox [0 0 200 100]"));
+        assert!(pdf.contains("/MediaBox [0 0 300 150]"));
+        // Each page flips y against its own height.
+        assert_eq!(count_page_content_token(&bytes, b"10 70 20 20 re"), 1);
+        assert_eq!(count_page_content_token(&bytes, b"10 120 20 20 re"), 1);
+    }
+
+    #[test]
+    fn rotated_pages_store_unrotated_media_and_map_annotations() {
+        let doc = Document {
+            page_size: Size {
+                width: Pt::from_f32(300.0),
+                height: Pt::from_f32(150.0),
+            },
+            pages: vec![Page {
+                commands: vec![Command::LinkAnnotation {
+                    x: Pt::from_f32(10.0),
+                    y: Pt::from_f32(10.0),
+                    width: Pt::from_f32(50.0),
+                    height: Pt::from_f32(20.0),
+                    target: LinkTarget::Uri("https://example.com".to_string()),
+                }]
+                .into(),
+                size: None,
+                rotate: 90,
+            }],
+        };
+        let bytes =
+            document_to_pdf_with_metrics_and_registry(&doc, None, None, &PdfOptions::default())
+                .unwrap();
+        let pdf = String::from_utf8_lossy(&bytes);
+        assert!(pdf.contains("/MediaBox [0 0 150 300] /Rotate 90"));
+        assert_eq!(count_page_content_token(&bytes, b"0 1 -1 0 150 0 cm"), 1);
+        assert!(pdf.contains("/Rect [10 10 30 60]"));
+    }
+
+    #[test]
+    fn page_boxes_grow_media_box_and_draw_printer_marks() {
+        let doc = Document {
+            page_size: Size {
+                width: Pt::from_f32(200.0),
+                height: Pt::from_f32(100.0),
+            },
+            pages: vec![Page {
+                commands: vec![Command::LinkAnnotation {
+                    x: Pt::from_f32(10.0),
+                    y: Pt::from_f32(10.0),
+                    width: Pt::from_f32(50.0),
+                    height: Pt::from_f32(20.0),
+                    target: LinkTarget::Uri("https://example.com".to_string()),
+                }]
+                .into(),
+                size: None,
+                rotate: 0,
+            }],
+        };
+        let options = PdfOptions {
+            page_boxes: Some(
+                PageBoxSpec::with_bleed(Pt::from_f32(9.0))
+                    .crop_marks()
+                    .registration_marks(),
+            ),
+            ..PdfOptions::default()
+        };
+        let bytes = document_to_pdf_with_metrics_and_registry(&doc, None, None, &options).unwrap();
+        let pdf = String::from_utf8_lossy(&bytes);
+        assert!(pdf.contains("/MediaBox [0 0 260 160]"));
+        assert!(pdf.contains("/TrimBox [30 30 230 130]"));
+        assert!(pdf.contains("/BleedBox [21 21 239 139]"));
+        assert!(pdf.contains("/CropBox [0 0 260 160]"));
+        // Link rect follows the content onto the trim box.
+        assert!(pdf.contains("/Rect [40 100 90 120]"));
+        assert!(pdf.contains("[/Separation /All /DeviceCMYK"));
+        assert_eq!(count_page_content_token(&bytes, b"1 0 0 1 30 30 cm"), 1);
+        // Eight crop marks plus two crosshair lines per registration target.
+        assert_eq!(count_page_content_token(&bytes, b" l S"), 16);
+        assert_eq!(count_page_content_token(&bytes, b"/CS1 CS 1 SCN"), 1);
+    }
+
     #[test]
     fn image_xobject_reused_across_pages_for_same_source() {
         let image_source = "examples/img/full_bleed-logo_small.png".to_string();
@@ -3964,23 +6561,29 @@ mod tests {
             y: Pt::from_f32(16.0),
             width: Pt::from_f32(60.0),
             height: Pt::from_f32(30.0),
-            resource_id,
+            resource_id: resource_id.into(),
         };
 
         let doc_one = Document {
             page_size: Size::a4(),
             pages: vec![Page {
-                commands: vec![image_cmd(image_source.clone())],
+                commands: vec![image_cmd(image_source.clone())].into(),
+                size: None,
+                rotate: 0,
             }],
         };
         let doc_multi = Document {
             page_size: Size::a4(),
             pages: vec![
                 Page {
-                    commands: vec![image_cmd(image_source.clone())],
+                    commands: vec![image_cmd(image_source.clone())].into(),
+                    size: None,
+                    rotate: 0,
                 },
                 Page {
-                    commands: vec![image_cmd(image_source)],
+                    commands: vec![image_cmd(image_source)].into(),
+                    size: None,
+                    rotate: 0,
                 },
             ],
         };
@@ -4013,29 +6616,26 @@ mod tests {
         let form_id = "wm-form".to_string();
         let doc = one_page_document(vec![
             Command::DefineForm {
-                resource_id: form_id.clone(),
+                resource_id: form_id.as_str().into(),
                 width: Pt::from_f32(64.0),
                 height: Pt::from_f32(24.0),
                 commands: vec![
-                    Command::SetFillColor(Color {
-                        r: 1.0,
-                        g: 0.0,
-                        b: 0.0,
-                    }),
+                    Command::SetFillColor(Color::rgb(1.0, 0.0, 0.0)),
                     Command::DrawRect {
                         x: Pt::from_f32(0.0),
                         y: Pt::from_f32(0.0),
                         width: Pt::from_f32(64.0),
                         height: Pt::from_f32(24.0),
                     },
-                ],
+                ]
+                .into(),
             },
             Command::DrawForm {
                 x: Pt::from_f32(72.0),
                 y: Pt::from_f32(88.0),
                 width: Pt::from_f32(64.0),
                 height: Pt::from_f32(24.0),
-                resource_id: form_id,
+                resource_id: form_id.into(),
             },
         ]);
 
@@ -4061,7 +6661,7 @@ mod tests {
         let form_id = "wm-form".to_string();
         let doc = one_page_document(vec![
             Command::DefineForm {
-                resource_id: form_id.clone(),
+                resource_id: form_id.as_str().into(),
                 width: Pt::from_f32(50.0),
                 height: Pt::from_f32(20.0),
                 commands: vec![Command::DrawRect {
@@ -4069,14 +6669,15 @@ mod tests {
                     y: Pt::from_f32(0.0),
                     width: Pt::from_f32(50.0),
                     height: Pt::from_f32(20.0),
-                }],
+                }]
+                .into(),
             },
             Command::DrawForm {
                 x: Pt::from_f32(40.0),
                 y: Pt::from_f32(40.0),
                 width: Pt::from_f32(50.0),
                 height: Pt::from_f32(20.0),
-                resource_id: form_id,
+                resource_id: form_id.into(),
             },
         ]);
 
@@ -4091,7 +6692,7 @@ mod tests {
     #[test]
     fn large_page_content_stream_is_flate_compressed_by_default() {
         let mut commands = Vec::new();
-        commands.push(Command::SetFontName("Helvetica".to_string()));
+        commands.push(Command::SetFontName("Helvetica".into()));
         commands.push(Command::SetFontSize(Pt::from_f32(10.0)));
         for i in 0..240 {
             commands.push(Command::DrawString {
@@ -4125,6 +6726,52 @@ mod tests {
         assert!(first_page_content_filter(&bytes).is_none());
     }
 
+    #[test]
+    fn parallel_and_leveled_content_compression_keep_page_content() {
+        let pages = (0..12)
+            .map(|page| Page {
+                commands: (0..60)
+                    .map(|line| Command::DrawRect {
+                        x: Pt::from_f32(page as f32),
+                        y: Pt::from_f32(line as f32 * 10.0),
+                        width: Pt::from_f32(100.0),
+                        height: Pt::from_f32(4.0),
+                    })
+                    .collect(),
+                size: None,
+                rotate: 0,
+            })
+            .collect();
+        let doc = Document {
+            page_size: Size::a4(),
+            pages,
+        };
+        let render = |options: PdfOptions| {
+            document_to_pdf_with_metrics_and_registry(&doc, None, None, &options)
+                .expect("pdf bytes")
+        };
+
+        let parallel = render(PdfOptions::default());
+        let sequential = render(PdfOptions {
+            parallel_content_compression: false,
+            ..PdfOptions::default()
+        });
+        let best = render(PdfOptions {
+            content_compression_level: Some(9),
+            ..PdfOptions::default()
+        });
+        let expected = page_content_bytes(&sequential);
+        assert_eq!(page_content_bytes(&parallel), expected);
+        assert_eq!(page_content_bytes(&best), expected);
+        assert_eq!(render(PdfOptions::default()), parallel);
+        assert_eq!(
+            first_page_content_filter(&best).as_deref(),
+            Some(&b"FlateDecode"[..])
+        );
+        // The fixed-Huffman built-in encoder never beats zlib at its best level.
+        assert!(best.len() < parallel.len());
+    }
+
     #[test]
     fn image_streams_emit_binary_filters_without_asciihex() {
         let image_source = "examples/img/full_bleed-logo_small.png".to_string();
@@ -4133,7 +6780,7 @@ mod tests {
             y: Pt::from_f32(16.0),
             width: Pt::from_f32(60.0),
             height: Pt::from_f32(30.0),
-            resource_id: image_source,
+            resource_id: image_source.into(),
         }]);
 
         let bytes =
@@ -4144,6 +6791,138 @@ mod tests {
         assert_eq!(count_token(&bytes, b"/ASCIIHexDecode"), 0);
     }
 
+    fn jpeg_data_uri(width: u32, height: u32) -> (Vec<u8>, String) {
+        let pixels = image::RgbImage::from_fn(width, height, |x, y| {
+            image::Rgb([(x % 256) as u8, (y % 256) as u8, 128])
+        });
+        let mut jpeg = Vec::new();
+        image::DynamicImage::ImageRgb8(pixels)
+            .write_with_encoder(image::codecs::jpeg::JpegEncoder::new_with_quality(
+                &mut jpeg, 90,
+            ))
+            .expect("encode jpeg");
+        let uri = format!(
+            "data:image/jpeg;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(&jpeg)
+        );
+        (jpeg, uri)
+    }
+
+    #[test]
+    fn jpeg_passes_through_and_oversized_images_downsample_to_max_dpi() {
+        let (jpeg, uri) = jpeg_data_uri(400, 200);
+        let draw = |resource_id: String| Command::DrawImage {
+            x: Pt::from_f32(12.0),
+            y: Pt::from_f32(16.0),
+            width: Pt::from_f32(144.0),
+            height: Pt::from_f32(72.0),
+            resource_id: resource_id.into(),
+        };
+        let doc = one_page_document(vec![draw(uri.clone())]);
+
+        let bytes =
+            document_to_pdf_with_metrics_and_registry(&doc, None, None, &PdfOptions::default())
+                .expect("pdf bytes");
+        assert_eq!(
+            count_token(&bytes, &jpeg),
+            1,
+            "original JPEG bytes embedded as-is"
+        );
+        assert!(count_token(&bytes, b"/Width 400 /Height 200 /ColorSpace /DeviceRGB") > 0);
+        assert!(count_token(&bytes, b"/Filter /DCTDecode") > 0);
+
+        // 144pt wide at 72 dpi is 144 pixels: the JPEG is re-encoded as a smaller JPEG and the
+        // PNG resampled and kept on Flate.
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgb8(image::RgbImage::from_pixel(
+            600,
+            300,
+            image::Rgb([10, 20, 30]),
+        ))
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .expect("encode png");
+        let png_uri = format!(
+            "data:image/png;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(&png)
+        );
+        let doc = one_page_document(vec![draw(uri), draw(png_uri)]);
+        let options = PdfOptions {
+            max_image_dpi: Some(72),
+            ..PdfOptions::default()
+        };
+        let bytes =
+            document_to_pdf_with_metrics_and_registry(&doc, None, None, &options).expect("pdf");
+        assert_eq!(count_token(&bytes, &jpeg), 0);
+        assert_eq!(
+            count_token(
+                &bytes,
+                b"/Width 144 /Height 72 /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /DCTDecode"
+            ),
+            1
+        );
+        assert_eq!(
+            count_token(
+                &bytes,
+                b"/Width 144 /Height 72 /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /FlateDecode"
+            ),
+            1
+        );
+        assert_eq!(count_token(&bytes, b"/Width 600"), 0);
+    }
+
+    #[test]
+    fn webp_images_transcode_to_flate() {
+        let mut webp = Vec::new();
+        image::DynamicImage::ImageRgb8(image::RgbImage::from_pixel(
+            40,
+            20,
+            image::Rgb([200, 40, 10]),
+        ))
+        .write_to(
+            &mut std::io::Cursor::new(&mut webp),
+            image::ImageFormat::WebP,
+        )
+        .expect("encode webp");
+        let uri = format!(
+            "data:image/webp;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(&webp)
+        );
+        let doc = one_page_document(vec![Command::DrawImage {
+            x: Pt::from_f32(12.0),
+            y: Pt::from_f32(16.0),
+            width: Pt::from_f32(40.0),
+            height: Pt::from_f32(20.0),
+            resource_id: uri.into(),
+        }]);
+        let bytes =
+            document_to_pdf_with_metrics_and_registry(&doc, None, None, &PdfOptions::default())
+                .expect("pdf bytes");
+        assert_eq!(
+            count_token(
+                &bytes,
+                b"/Width 40 /Height 20 /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /FlateDecode"
+            ),
+            1
+        );
+
+        let reason = image_failure_reason("data:image/webp;base64,AAAA");
+        assert!(
+            reason.contains("supported formats: png, jpeg, webp"),
+            "{reason}"
+        );
+    }
+
+    #[test]
+    fn jpeg_header_reads_frame_without_decoding() {
+        let (jpeg, _) = jpeg_data_uri(33, 17);
+        let header = jpeg_header(&jpeg).expect("header");
+        assert_eq!(
+            (header.width, header.height, header.components),
+            (33, 17, 3)
+        );
+        assert!(jpeg_header(b"not a jpeg").is_none());
+    }
+
     #[test]
     fn embedded_font_streams_emit_without_asciihex() {
         let inter_path = repo_font_path("Inter-Variable.ttf");
@@ -4188,7 +6967,7 @@ mod tests {
     #[test]
     fn pdf_link_perf_reports_content_stream_compression_counters() {
         let mut commands = Vec::new();
-        commands.push(Command::SetFontName("Helvetica".to_string()));
+        commands.push(Command::SetFontName("Helvetica".into()));
         commands.push(Command::SetFontSize(Pt::from_f32(10.0)));
         for i in 0..220 {
             commands.push(Command::DrawString {
@@ -4249,7 +7028,7 @@ mod tests {
         .expect("stream writer");
         stream.add_document(0, &doc_a).expect("add doc a");
         stream.add_document(1, &doc_b).expect("add doc b");
-        let written = stream.finish().expect("finish stream");
+        let written = stream.finish().expect("finish stream").bytes_written;
         assert_eq!(written, out.len());
 
         // One embedded TrueType program and one Type0 wrapper for the shared font.
@@ -4257,6 +7036,81 @@ mod tests {
         assert_eq!(count_token(&out, b"/Subtype /Type0"), 1);
     }
 
+    #[test]
+    fn streaming_writer_embeds_images_and_forms_shared_by_records_once() {
+        let record = || {
+            one_page_document(vec![
+                Command::DefineForm {
+                    resource_id: "badge".into(),
+                    width: Pt::from_f32(20.0),
+                    height: Pt::from_f32(10.0),
+                    commands: vec![
+                        Command::MoveTo {
+                            x: Pt::ZERO,
+                            y: Pt::ZERO,
+                        },
+                        Command::LineTo {
+                            x: Pt::from_f32(20.0),
+                            y: Pt::from_f32(10.0),
+                        },
+                        Command::LineTo {
+                            x: Pt::ZERO,
+                            y: Pt::from_f32(10.0),
+                        },
+                        Command::ClosePath,
+                        Command::Fill,
+                    ]
+                    .into(),
+                },
+                Command::DrawForm {
+                    x: Pt::from_f32(100.0),
+                    y: Pt::from_f32(20.0),
+                    width: Pt::from_f32(20.0),
+                    height: Pt::from_f32(10.0),
+                    resource_id: "badge".into(),
+                },
+                Command::DrawImage {
+                    x: Pt::from_f32(12.0),
+                    y: Pt::from_f32(16.0),
+                    width: Pt::from_f32(60.0),
+                    height: Pt::from_f32(30.0),
+                    resource_id: "examples/img/full_bleed-logo_small.png".into(),
+                },
+            ])
+        };
+        let write = |records: usize| {
+            let mut out = Vec::new();
+            let mut stream = PdfStreamWriter::new(
+                &mut out,
+                Size::a4(),
+                None,
+                PdfOptions::default(),
+                None,
+                None,
+            )
+            .expect("stream writer");
+            for doc_id in 0..records {
+                stream.add_document(doc_id, &record()).expect("add record");
+            }
+            let reuse = stream.xobject_reuse;
+            stream.finish().expect("finish stream");
+            (out, reuse)
+        };
+
+        let (single, single_reuse) = write(1);
+        let (batch, reuse) = write(3);
+        assert_eq!(single_reuse, XObjectReuse::default());
+        assert_eq!(
+            count_token(&batch, b"/Subtype /Image"),
+            count_token(&single, b"/Subtype /Image")
+        );
+        assert_eq!(count_token(&batch, b"/Subtype /Form"), 1);
+        assert_eq!(reuse.image_hits, 2);
+        assert_eq!(reuse.form_hits, 2);
+        assert_eq!(reuse.cross_record_hits, 4);
+        assert!(reuse.bytes_saved > 0);
+    }
+
     #[test]
     fn streaming_writer_keeps_distinct_embedded_fonts_distinct() {
         let inter_path = repo_font_path("Inter-Variable.ttf");
@@ -4287,17 +7141,78 @@ mod tests {
         .expect("stream writer");
         stream.add_document(0, &doc_a).expect("add doc a");
         stream.add_document(1, &doc_b).expect("add doc b");
-        let written = stream.finish().expect("finish stream");
+        let written = stream.finish().expect("finish stream").bytes_written;
         assert_eq!(written, out.len());
 
         // Two distinct embedded font programs for two distinct logical fonts.
         assert_eq!(count_token(&out, b"/FontFile2"), 2);
     }
 
+    #[test]
+    fn embedded_truetype_fonts_are_subset_with_tagged_names() {
+        let inter_path = repo_font_path("Inter-Variable.ttf");
+        let inter_bytes = std::fs::read(&inter_path).expect("read inter");
+        let full_len = inter_bytes.len();
+        let mut registry = FontRegistry::new();
+        let inter_name = registry
+            .register_bytes(inter_bytes, Some(inter_path.to_string_lossy().as_ref()))
+            .expect("register inter");
+        let doc = text_page(&inter_name, "Subset");
+
+        let render = |font_subsetting: bool| {
+            let mut out = Vec::new();
+            let mut stream = PdfStreamWriter::new(
+                &mut out,
+                Size::a4(),
+                Some(&registry),
+                PdfOptions {
+                    font_subsetting,
+                    ..PdfOptions::default()
+                },
+                None,
+                None,
+            )
+            .expect("stream writer");
+            stream.add_document(0, &doc).expect("add doc");
+            stream.finish().expect("finish stream");
+            out
+        };
+        let length1 = |pdf: &[u8]| -> usize {
+            let text = String::from_utf8_lossy(pdf);
+            let at = text.find("/Length1 ").expect("font program") + "/Length1 ".len();
+            text[at..]
+                .split(|c: char| !c.is_ascii_digit())
+                .next()
+                .and_then(|n| n.parse().ok())
+                .expect("Length1 value")
+        };
+
+        let subset = render(true);
+        assert!(length1(&subset) < full_len / 4);
+        let base = format!("+{}", sanitize_font_name(&inter_name));
+        // FontDescriptor, CIDFontType2 and Type0 all carry the same tagged name.
+        assert_eq!(count_token(&subset, base.as_bytes()), 3);
+        let tag = |pdf: &[u8]| -> String {
+            let text = String::from_utf8_lossy(pdf);
+            let at = text.find(&base).expect("tagged name");
+            text[at - 6..at].to_string()
+        };
+        assert!(tag(&subset).chars().all(|c| c.is_ascii_uppercase()));
+        assert_eq!(
+            tag(&subset),
+            tag(&render(true)),
+            "subset tags are deterministic"
+        );
+
+        let full = render(false);
+        assert_eq!(length1(&full), full_len);
+        assert_eq!(count_token(&full, base.as_bytes()), 0);
+    }
+
     #[test]
     fn winansi_fallback_emits_font_fallback_known_loss() {
         let doc = one_page_document(vec![
-            Command::SetFontName("Helvetica".to_string()),
+            Command::SetFontName("Helvetica".into()),
             Command::SetFontSize(Pt::from_f32(12.0)),
             Command::DrawString {
                 x: Pt::from_f32(72.0),
@@ -4327,4 +7242,60 @@ mod tests {
         assert!(log.contains("\"FONT_FALLBACK_USED\""));
         let _ = std::fs::remove_file(path);
     }
+
+    #[test]
+    fn shaped_runs_are_reused_across_renders_within_the_cache_bound() {
+        let path = temp_log_path("shape_cache");
+        let html = "<p>Page 1 of 2</p><p>Page 2 of 2</p><p>Page 1 of 2</p>";
+        let css = "p { font-family: Inter; }";
+        let uncached = FullBleed::builder()
+            .register_font_file(repo_font_path("Inter-Variable.ttf"))
+            .shape_cache_capacity(0)
+            .build()
+            .expect("uncached engine");
+        let expected = uncached.render_to_buffer(html, css).expect("render");
+        assert_eq!(uncached.font_registry.shape_cache_len(), 0);
+        {
+            let engine = FullBleed::builder()
+                .register_font_file(repo_font_path("Inter-Variable.ttf"))
+                .perf_log(&path)
+                .build()
+                .expect("engine");
+            for _ in 0..2 {
+                let pdf = engine.render_to_buffer(html, css).expect("render");
+                assert_eq!(pdf, expected);
+            }
+            assert_eq!(engine.font_registry.shape_cache_len(), 2);
+        }
+        let log = std::fs::read_to_string(&path).expect("perf log");
+        let counts: Vec<&str> = log
+            .lines()
+            .filter(|line| line.contains("\"name\":\"shape.cache\""))
+            .collect();
+        assert_eq!(counts.len(), 2);
+        assert!(
+            counts[0].contains("\"hits\":1,\"misses\":2"),
+            "{}",
+            counts[0]
+        );
+        assert!(
+            counts[1].contains("\"hits\":3,\"misses\":0"),
+            "{}",
+            counts[1]
+        );
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(path.with_file_name(format!(
+            "{}_hot.log",
+            path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default()
+        )));
+
+        let bounded = FullBleed::builder()
+            .register_font_file(repo_font_path("Inter-Variable.ttf"))
+            .shape_cache_capacity(1)
+            .build()
+            .expect("bounded engine");
+        let pdf = bounded.render_to_buffer(html, css).expect("render");
+        assert_eq!(pdf, expected);
+        assert_eq!(bounded.font_registry.shape_cache_len(), 1);
+    }
 }
@@ -2,7 +2,8 @@ use crate::canvas::{Command, Document, Page};
 use crate::error::FullBleedError;
 use crate::font::FontRegistry;
 use crate::raster;
-use crate::types::{Color, Pt, Size};
+use crate::text_extract::TextSpan;
+use crate::types::{Color, Pt, Rect, Size};
 use base64::Engine;
 use image::codecs::png::PngEncoder;
 use image::{ColorType, ImageEncoder};
@@ -210,6 +211,8 @@ struct ParsedPage {
 #[derive(Default)]
 struct PdfRasterCache {
     image_data_uri_by_object: HashMap<ObjectId, String>,
+    // Set when extracting text: every shown string is also recorded here.
+    text_spans: Option<Vec<TextSpan>>,
 }
 
 pub(crate) fn pdf_path_to_png_pages(
@@ -228,6 +231,22 @@ pub(crate) fn pdf_bytes_to_png_pages(
     registry: Option<&FontRegistry>,
     shape_text: bool,
 ) -> Result<Vec<Vec<u8>>, FullBleedError> {
+    pdf_bytes_to_pixmaps(bytes, dpi, registry, shape_text)?
+        .iter()
+        .map(|pixmap| {
+            pixmap
+                .encode_png()
+                .map_err(|e| FullBleedError::Asset(format!("png encode failed: {e}")))
+        })
+        .collect()
+}
+
+pub(crate) fn pdf_bytes_to_pixmaps(
+    bytes: &[u8],
+    dpi: u32,
+    registry: Option<&FontRegistry>,
+    shape_text: bool,
+) -> Result<Vec<tiny_skia::Pixmap>, FullBleedError> {
     let doc = LoDocument::load_mem(bytes).map_err(lopdf_err)?;
     let (pages, embedded_fonts) = parse_pdf_pages(&doc)?;
     if pages.is_empty() {
@@ -251,13 +270,15 @@ pub(crate) fn pdf_bytes_to_png_pages(
         let document = Document {
             page_size: parsed.size,
             pages: vec![Page {
-                commands: parsed.commands,
+                commands: parsed.commands.into(),
+                size: None,
+                rotate: 0,
             }],
         };
-        let mut pngs =
-            raster::document_to_png_pages(&document, dpi, effective_registry, shape_text)?;
-        if let Some(page_png) = pngs.pop() {
-            out.push(page_png);
+        let mut pixmaps =
+            raster::document_to_pixmaps(&document, dpi, effective_registry, shape_text)?;
+        if let Some(pixmap) = pixmaps.pop() {
+            out.push(pixmap);
         } else {
             return Err(FullBleedError::InvalidConfiguration(
                 "pdf raster error: no rendered page output".to_string(),
@@ -280,6 +301,20 @@ fn parse_pdf_pages(
     Ok((out, embedded_fonts))
 }
 
+// Shown strings of each page, decoded through the fonts' ToUnicode maps.
+pub(crate) fn pdf_bytes_to_text_spans(bytes: &[u8]) -> Result<Vec<Vec<TextSpan>>, FullBleedError> {
+    let doc = LoDocument::load_mem(bytes).map_err(lopdf_err)?;
+    let mut cache = PdfRasterCache::default();
+    let mut embedded_fonts: HashMap<String, Arc<Vec<u8>>> = HashMap::new();
+    let mut pages = Vec::new();
+    for (_page_no, page_id) in doc.get_pages() {
+        cache.text_spans = Some(Vec::new());
+        parse_page(&doc, page_id, &mut cache, &mut embedded_fonts)?;
+        pages.push(cache.text_spans.take().unwrap_or_default());
+    }
+    Ok(pages)
+}
+
 fn parse_page(
     doc: &LoDocument,
     page_id: ObjectId,
@@ -616,7 +651,7 @@ fn parse_operations(
                     state.font_resource = Some(font_res_name);
                     state.font_name = font_res.font_name.clone();
                     state.font_size = Pt::from_f32(size.max(0.0));
-                    commands.push(Command::SetFontName(state.font_name.clone()));
+                    commands.push(Command::SetFontName(state.font_name.as_str().into()));
                     commands.push(Command::SetFontSize(state.font_size));
                 }
             }
@@ -674,6 +709,7 @@ fn parse_operations(
                     .unwrap_or_else(|| {
                         estimate_text_advance_fallback(&decoded_text, state, current_font)
                     });
+                record_text_span(cache, state, page_height, &decoded_text, advance);
                 advance_text_matrix(state, advance);
             }
             "TJ" => {
@@ -715,6 +751,7 @@ fn parse_operations(
                             .unwrap_or_else(|| {
                                 estimate_text_advance_fallback(&decoded_text, state, current_font)
                             });
+                            record_text_span(cache, state, page_height, &decoded_text, advance);
                             advance_text_matrix(state, advance);
                         } else if let Some(adj) = obj_to_f32(item) {
                             // TJ adjustment is thousandths of text-space units.
@@ -852,7 +889,7 @@ fn parse_xobject(
                     y: Pt::from_f32(y_top),
                     width: Pt::from_f32(width),
                     height: Pt::from_f32(height),
-                    resource_id: data_uri,
+                    resource_id: data_uri.into(),
                 });
             }
         }
@@ -861,6 +898,35 @@ fn parse_xobject(
     Ok(())
 }
 
+// Box of a shown string from its start point and advance, in top-left page space.
+fn record_text_span(
+    cache: &mut PdfRasterCache,
+    state: &ParseState,
+    page_height: f32,
+    text: &str,
+    advance: f32,
+) {
+    let Some(spans) = cache.text_spans.as_mut() else {
+        return;
+    };
+    if text.is_empty() {
+        return;
+    }
+    let combined = state.ctm.concat(state.text_matrix);
+    let (x0, y0) = combined.transform_point(0.0, state.text_rise);
+    let (x1, y1) = combined.transform_point(advance, state.text_rise);
+    let size = effective_font_size(state);
+    spans.push(TextSpan {
+        text: text.to_string(),
+        bbox: Rect {
+            x: Pt::from_f32(x0.min(x1)),
+            y: Pt::from_f32(page_height - y0.max(y1) - size),
+            width: Pt::from_f32((x1 - x0).abs()),
+            height: Pt::from_f32(size),
+        },
+    });
+}
+
 fn emit_text(commands: &mut Vec<Command>, state: &ParseState, page_height: f32, text: &str) {
     if text.is_empty() {
         return;
@@ -2220,7 +2286,7 @@ fn parse_separation_sampled_lookup(
         .get(b"Range")
         .ok()
         .and_then(parse_f32_array)
-        .unwrap_or_else(|| vec![0.0, 1.0].repeat(channels));
+        .unwrap_or_else(|| [0.0, 1.0].repeat(channels));
     let decode = tint_dict
         .get(b"Decode")
         .ok()
@@ -2660,7 +2726,7 @@ fn read_bits(data: &[u8], bit_cursor: &mut usize, bits_per_component: u8) -> Opt
     Some(value)
 }
 
-fn decode_content_with_fallback(bytes: &[u8]) -> Result<Content, FullBleedError> {
+pub(crate) fn decode_content_with_fallback(bytes: &[u8]) -> Result<Content, FullBleedError> {
     match Content::decode(bytes) {
         Ok(content) => Ok(content),
         Err(primary_err) => {
@@ -3158,7 +3224,7 @@ fn cmyk_to_rgb(c: f32, m: f32, y: f32, k: f32) -> (f32, f32, f32) {
     (r, g, b)
 }
 
-fn lopdf_err(err: lopdf::Error) -> FullBleedError {
+pub(crate) fn lopdf_err(err: lopdf::Error) -> FullBleedError {
     FullBleedError::InvalidConfiguration(format!("pdf raster error: {err}"))
 }
 #[cfg(test)]
@@ -1,4 +1,9 @@
-use lopdf::Document as LoDocument;
+use crate::pdf::PdfProfile;
+use crate::pdf_raster;
+use crate::text_extract::{self, PageText};
+use lopdf::content::Operation;
+use lopdf::{Dictionary as LoDictionary, Document as LoDocument, Object as LoObject, ObjectId};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::path::Path;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -40,28 +45,1045 @@ pub struct PdfInspectWarning {
     pub message: String,
 }
 
+// Signature form field listed in the catalog /AcroForm. `page_number` is 1-based and absent
+// when the widget names no page; `signed` is set once the field carries a /V value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdfSignatureField {
+    pub name: String,
+    pub page_number: Option<usize>,
+    pub rect: [f32; 4],
+    pub signed: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PdfContentIssueCode {
+    ContentDecodeFailed,
+    UnbalancedSaveRestore,
+    UnbalancedTextObject,
+    TextOutsideTextObject,
+    UndefinedResource,
+    XObjectMissing,
+    FontNotEmbedded,
+}
+
+impl PdfContentIssueCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PdfContentIssueCode::ContentDecodeFailed => "CONTENT_DECODE_FAILED",
+            PdfContentIssueCode::UnbalancedSaveRestore => "CONTENT_UNBALANCED_SAVE_RESTORE",
+            PdfContentIssueCode::UnbalancedTextObject => "CONTENT_UNBALANCED_TEXT_OBJECT",
+            PdfContentIssueCode::TextOutsideTextObject => "CONTENT_TEXT_OUTSIDE_TEXT_OBJECT",
+            PdfContentIssueCode::UndefinedResource => "CONTENT_UNDEFINED_RESOURCE",
+            PdfContentIssueCode::XObjectMissing => "CONTENT_XOBJECT_MISSING",
+            PdfContentIssueCode::FontNotEmbedded => "FONT_NOT_EMBEDDED",
+        }
+    }
+}
+
+// Problem found while walking a page's content stream (including the form XObjects it
+// paints). `resource` names the resource entry involved, when there is one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdfContentIssue {
+    pub code: PdfContentIssueCode,
+    pub page_number: usize,
+    pub resource: Option<String>,
+    pub message: String,
+}
+
+// Font selected with `Tf` somewhere in the document. Fonts shared by reference across pages
+// are listed once, with every 1-based page that uses them.
 #[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdfFontResource {
+    pub base_font: String,
+    pub subtype: String,
+    pub embedded: bool,
+    pub pages: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct PdfInspectReport {
     pub pdf_version: String,
     pub page_count: usize,
     pub encrypted: bool,
     pub file_size_bytes: usize,
+    pub signature_fields: Vec<PdfSignatureField>,
+    pub content_issues: Vec<PdfContentIssue>,
+    pub fonts: Vec<PdfFontResource>,
     pub warnings: Vec<PdfInspectWarning>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PdfPreflightRule {
+    EncryptionForbidden,
+    OutputIntentMissing,
+    OutputIntentProfileMissing,
+    MetadataMissing,
+    ConformanceIdentifierMissing,
+    FontNotEmbedded,
+    DeviceColorWithoutOutputIntent,
+    DeviceRgbForbidden,
+    BlendModeNotStandard,
+    TransparencyGroupColorSpaceMissing,
+    PageBoxMissing,
+    TrappedKeyMissing,
+    JavaScriptForbidden,
+    EmbeddedFileForbidden,
+    AfRelationshipMissing,
+}
+
+impl PdfPreflightRule {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PdfPreflightRule::EncryptionForbidden => "ENCRYPTION_FORBIDDEN",
+            PdfPreflightRule::OutputIntentMissing => "OUTPUT_INTENT_MISSING",
+            PdfPreflightRule::OutputIntentProfileMissing => "OUTPUT_INTENT_PROFILE_MISSING",
+            PdfPreflightRule::MetadataMissing => "METADATA_MISSING",
+            PdfPreflightRule::ConformanceIdentifierMissing => "CONFORMANCE_IDENTIFIER_MISSING",
+            PdfPreflightRule::FontNotEmbedded => "FONT_NOT_EMBEDDED",
+            PdfPreflightRule::DeviceColorWithoutOutputIntent => {
+                "DEVICE_COLOR_WITHOUT_OUTPUT_INTENT"
+            }
+            PdfPreflightRule::DeviceRgbForbidden => "DEVICE_RGB_FORBIDDEN",
+            PdfPreflightRule::BlendModeNotStandard => "BLEND_MODE_NOT_STANDARD",
+            PdfPreflightRule::TransparencyGroupColorSpaceMissing => {
+                "TRANSPARENCY_GROUP_COLOR_SPACE_MISSING"
+            }
+            PdfPreflightRule::PageBoxMissing => "PAGE_BOX_MISSING",
+            PdfPreflightRule::TrappedKeyMissing => "TRAPPED_KEY_MISSING",
+            PdfPreflightRule::JavaScriptForbidden => "JAVASCRIPT_FORBIDDEN",
+            PdfPreflightRule::EmbeddedFileForbidden => "EMBEDDED_FILE_FORBIDDEN",
+            PdfPreflightRule::AfRelationshipMissing => "AF_RELATIONSHIP_MISSING",
+        }
+    }
+}
+
+// One failed profile rule. `object` is the offending object's (number, generation) when the
+// violation belongs to a specific indirect object; `page_number` is 1-based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdfPreflightViolation {
+    pub rule: PdfPreflightRule,
+    pub page_number: Option<usize>,
+    pub object: Option<(u32, u16)>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdfPreflightReport {
+    pub profile: PdfProfile,
+    pub report: PdfInspectReport,
+    pub violations: Vec<PdfPreflightViolation>,
+}
+
+impl PdfPreflightReport {
+    pub fn passed(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
 pub fn inspect_pdf_bytes(bytes: &[u8]) -> Result<PdfInspectReport, PdfInspectError> {
-    let pdf = LoDocument::load_mem(bytes).map_err(|err| PdfInspectError {
+    let pdf = load_pdf(bytes)?;
+    let audit = audit_content(&pdf);
+    Ok(build_report(&pdf, bytes.len(), audit))
+}
+
+// Inspects the PDF and checks it against the rule set of `profile` (PDF/A-2b, PDF/A-3b or
+// PDF/X-4). `PdfProfile::None` and `PdfProfile::Tagged` have no preflight rules.
+pub fn inspect_pdf_bytes_with_profile(
+    bytes: &[u8],
+    profile: PdfProfile,
+) -> Result<PdfPreflightReport, PdfInspectError> {
+    let pdf = load_pdf(bytes)?;
+    let audit = audit_content(&pdf);
+    let violations = match profile {
+        PdfProfile::PdfA2b | PdfProfile::PdfA3b | PdfProfile::PdfX4 => {
+            preflight_violations(&pdf, profile, &audit)
+        }
+        PdfProfile::None | PdfProfile::Tagged => Vec::new(),
+    };
+    Ok(PdfPreflightReport {
+        profile,
+        report: build_report(&pdf, bytes.len(), audit),
+        violations,
+    })
+}
+
+fn load_pdf(bytes: &[u8]) -> Result<LoDocument, PdfInspectError> {
+    LoDocument::load_mem(bytes).map_err(|err| PdfInspectError {
         code: PdfInspectErrorCode::PdfParseFailed,
         message: err.to_string(),
-    })?;
+    })
+}
+
+fn audit_content(pdf: &LoDocument) -> ContentAudit {
+    let mut audit = ContentAudit::default();
+    if !pdf.is_encrypted() {
+        for (page_number, page_id) in pdf.get_pages() {
+            audit.audit_page(pdf, page_number as usize, page_id);
+        }
+    }
+    audit
+}
 
-    Ok(PdfInspectReport {
+fn build_report(pdf: &LoDocument, file_size_bytes: usize, audit: ContentAudit) -> PdfInspectReport {
+    PdfInspectReport {
         pdf_version: pdf.version.clone(),
         page_count: pdf.get_pages().len(),
         encrypted: pdf.is_encrypted(),
-        file_size_bytes: bytes.len(),
+        file_size_bytes,
+        signature_fields: signature_fields(pdf),
+        content_issues: audit.issues,
+        fonts: audit.fonts.into_values().collect(),
         warnings: Vec::new(),
-    })
+    }
+}
+
+fn preflight_violations(
+    pdf: &LoDocument,
+    profile: PdfProfile,
+    audit: &ContentAudit,
+) -> Vec<PdfPreflightViolation> {
+    let mut out = Vec::new();
+    let mut violation = |rule, page_number, object: Option<ObjectId>, message: String| {
+        out.push(PdfPreflightViolation {
+            rule,
+            page_number,
+            object,
+            message,
+        });
+    };
+    let pdfx = profile == PdfProfile::PdfX4;
+    let catalog = pdf.catalog().ok();
+    let catalog_id = pdf.trailer.get(b"Root").and_then(|r| r.as_reference()).ok();
+
+    if pdf.is_encrypted() {
+        violation(
+            PdfPreflightRule::EncryptionForbidden,
+            None,
+            None,
+            "encryption is not allowed".to_string(),
+        );
+    }
+
+    // Output intent of the profile's family and its destination ICC profile.
+    let intent_subtype: &[u8] = if pdfx { b"GTS_PDFX" } else { b"GTS_PDFA1" };
+    let intent = catalog
+        .and_then(|c| c.get(b"OutputIntents").ok())
+        .and_then(|i| resolve(pdf, i))
+        .and_then(|i| i.as_array().ok())
+        .into_iter()
+        .flatten()
+        .filter_map(|i| pdf.dereference(i).ok())
+        .filter_map(|(id, i)| i.as_dict().ok().map(|d| (id, d)))
+        .find(|(_, d)| {
+            d.get(b"S")
+                .and_then(|s| s.as_name())
+                .is_ok_and(|s| s == intent_subtype)
+        });
+    let intent_subtype = String::from_utf8_lossy(intent_subtype);
+    match intent {
+        Some((id, dict)) => {
+            let profile_stream = dict
+                .get(b"DestOutputProfile")
+                .ok()
+                .and_then(|p| resolve(pdf, p))
+                .and_then(|p| p.as_stream().ok());
+            if profile_stream.is_none() {
+                violation(
+                    PdfPreflightRule::OutputIntentProfileMissing,
+                    None,
+                    id,
+                    format!("{intent_subtype} output intent has no DestOutputProfile"),
+                );
+            }
+        }
+        None if pdfx => violation(
+            PdfPreflightRule::OutputIntentMissing,
+            None,
+            catalog_id,
+            format!("catalog has no {intent_subtype} output intent"),
+        ),
+        None => {
+            for (space, page_number) in &audit.device_colors {
+                violation(
+                    PdfPreflightRule::DeviceColorWithoutOutputIntent,
+                    Some(*page_number),
+                    None,
+                    format!("{space} is used without a {intent_subtype} output intent"),
+                );
+            }
+        }
+    }
+
+    // XMP metadata with the profile's identification schema.
+    let metadata = catalog
+        .and_then(|c| c.get(b"Metadata").ok())
+        .and_then(|m| resolve(pdf, m))
+        .and_then(|m| m.as_stream().ok())
+        .and_then(|m| m.get_plain_content().ok());
+    match metadata {
+        None => violation(
+            PdfPreflightRule::MetadataMissing,
+            None,
+            catalog_id,
+            "catalog has no XMP /Metadata stream".to_string(),
+        ),
+        Some(xmp) => {
+            let xmp = String::from_utf8_lossy(&xmp);
+            let identified = match profile {
+                PdfProfile::PdfA2b => xmp_identifies_pdfa(&xmp, "2"),
+                PdfProfile::PdfA3b => xmp_identifies_pdfa(&xmp, "3"),
+                _ => xmp.contains("PDF/X-4"),
+            };
+            if !identified {
+                violation(
+                    PdfPreflightRule::ConformanceIdentifierMissing,
+                    None,
+                    catalog_id,
+                    format!("XMP metadata does not identify the file as {profile:?}"),
+                );
+            }
+        }
+    }
+
+    for (key, font) in &audit.fonts {
+        if font.embedded {
+            continue;
+        }
+        let object = match key {
+            FontKey::Object(id) => Some(*id),
+            FontKey::Inline(..) => None,
+        };
+        violation(
+            PdfPreflightRule::FontNotEmbedded,
+            font.pages.first().copied(),
+            object,
+            format!("font {} is not embedded", font.base_font),
+        );
+    }
+
+    if pdfx && let Some(page_number) = audit.device_colors.get("DeviceRGB") {
+        violation(
+            PdfPreflightRule::DeviceRgbForbidden,
+            Some(*page_number),
+            None,
+            "DeviceRGB is not allowed in PDF/X-4; use an ICC-based colour space".to_string(),
+        );
+    }
+
+    for (page_number, id, mode) in &audit.blend_modes {
+        violation(
+            PdfPreflightRule::BlendModeNotStandard,
+            Some(*page_number),
+            *id,
+            format!("blend mode /{mode} is not a standard blend mode"),
+        );
+    }
+
+    let pages = pdf.get_pages();
+    for (page_number, page_id) in &pages {
+        let page_number = *page_number as usize;
+        let Ok(page) = pdf.get_dictionary(*page_id) else {
+            continue;
+        };
+        // Without an output intent, PDF/A needs the page group to name the blending space.
+        if !pdfx && intent.is_none() && audit.transparent_pages.contains(&page_number) {
+            let group_space = page
+                .get(b"Group")
+                .ok()
+                .and_then(|g| resolve(pdf, g))
+                .and_then(|g| g.as_dict().ok())
+                .is_some_and(|g| g.has(b"CS"));
+            if !group_space {
+                violation(
+                    PdfPreflightRule::TransparencyGroupColorSpaceMissing,
+                    Some(page_number),
+                    Some(*page_id),
+                    "page uses transparency without a page group /CS".to_string(),
+                );
+            }
+        }
+        if pdfx && !page.has(b"TrimBox") && !page.has(b"ArtBox") {
+            violation(
+                PdfPreflightRule::PageBoxMissing,
+                Some(page_number),
+                Some(*page_id),
+                "page has neither /TrimBox nor /ArtBox".to_string(),
+            );
+        }
+    }
+
+    if pdfx {
+        let trapped = pdf
+            .trailer
+            .get(b"Info")
+            .ok()
+            .and_then(|i| resolve(pdf, i))
+            .and_then(|i| i.as_dict().ok())
+            .and_then(|i| i.get(b"Trapped").ok())
+            .and_then(|t| t.as_name().ok())
+            .is_some_and(|t| t == b"True" || t == b"False");
+        if !trapped {
+            violation(
+                PdfPreflightRule::TrappedKeyMissing,
+                None,
+                pdf.trailer.get(b"Info").and_then(|i| i.as_reference()).ok(),
+                "document info /Trapped must be /True or /False".to_string(),
+            );
+        }
+    }
+
+    let names = catalog
+        .and_then(|c| c.get(b"Names").ok())
+        .and_then(|n| resolve(pdf, n))
+        .and_then(|n| n.as_dict().ok());
+    if names.is_some_and(|n| n.has(b"JavaScript")) {
+        violation(
+            PdfPreflightRule::JavaScriptForbidden,
+            None,
+            catalog_id,
+            "catalog name tree contains JavaScript".to_string(),
+        );
+    }
+    let embedded_files = names
+        .and_then(|n| n.get(b"EmbeddedFiles").ok())
+        .and_then(|e| resolve(pdf, e))
+        .and_then(|e| e.as_dict().ok())
+        .map(|tree| name_tree_values(pdf, tree))
+        .unwrap_or_default();
+    for (id, filespec) in embedded_files {
+        match profile {
+            PdfProfile::PdfA2b => violation(
+                PdfPreflightRule::EmbeddedFileForbidden,
+                None,
+                id,
+                "PDF/A-2b does not allow embedded files".to_string(),
+            ),
+            PdfProfile::PdfA3b if !filespec.has(b"AFRelationship") => violation(
+                PdfPreflightRule::AfRelationshipMissing,
+                None,
+                id,
+                "embedded file spec has no /AFRelationship".to_string(),
+            ),
+            _ => {}
+        }
+    }
+
+    out
+}
+
+fn xmp_identifies_pdfa(xmp: &str, part: &str) -> bool {
+    let part_attr = format!("pdfaid:part=\"{part}\"");
+    let part_elem = format!("<pdfaid:part>{part}</pdfaid:part>");
+    (xmp.contains(&part_attr) || xmp.contains(&part_elem))
+        && (xmp.contains("pdfaid:conformance=\"B\"")
+            || xmp.contains("<pdfaid:conformance>B</pdfaid:conformance>"))
+}
+
+// Values of a name tree, walking /Kids; each value is returned with its object id when it
+// is stored by reference.
+fn name_tree_values<'a>(
+    pdf: &'a LoDocument,
+    root: &'a LoDictionary,
+) -> Vec<(Option<ObjectId>, &'a LoDictionary)> {
+    let mut out = Vec::new();
+    let mut stack = vec![root];
+    let mut seen = HashSet::new();
+    while let Some(node) = stack.pop() {
+        if let Ok(names) = node.get(b"Names").and_then(|n| n.as_array()) {
+            for value in names.iter().skip(1).step_by(2) {
+                if let Ok((id, LoObject::Dictionary(dict))) = pdf.dereference(value) {
+                    out.push((id, dict));
+                }
+            }
+        }
+        if let Ok(kids) = node.get(b"Kids").and_then(|k| k.as_array()) {
+            for kid in kids {
+                if let Ok((id, LoObject::Dictionary(dict))) = pdf.dereference(kid)
+                    && id.is_none_or(|id| seen.insert(id))
+                {
+                    stack.push(dict);
+                }
+            }
+        }
+    }
+    out
+}
+
+fn resolve<'a>(pdf: &'a LoDocument, obj: &'a LoObject) -> Option<&'a LoObject> {
+    pdf.dereference(obj).ok().map(|(_, obj)| obj)
+}
+
+fn signature_fields(pdf: &LoDocument) -> Vec<PdfSignatureField> {
+    let fields = pdf
+        .catalog()
+        .ok()
+        .and_then(|catalog| catalog.get(b"AcroForm").ok())
+        .and_then(|form| resolve(pdf, form))
+        .and_then(|form| form.as_dict().ok())
+        .and_then(|form| form.get(b"Fields").ok())
+        .and_then(|fields| resolve(pdf, fields))
+        .and_then(|fields| fields.as_array().ok());
+    let Some(fields) = fields else {
+        return Vec::new();
+    };
+    let page_numbers: HashMap<ObjectId, usize> = pdf
+        .get_pages()
+        .into_iter()
+        .map(|(number, id)| (id, number as usize))
+        .collect();
+
+    let mut out = Vec::new();
+    for field in fields {
+        let Some(dict) = resolve(pdf, field).and_then(|obj| obj.as_dict().ok()) else {
+            continue;
+        };
+        if !dict
+            .get(b"FT")
+            .and_then(|ft| ft.as_name())
+            .is_ok_and(|ft| ft == b"Sig")
+        {
+            continue;
+        }
+        let name = dict
+            .get(b"T")
+            .and_then(|t| t.as_str())
+            .map(decode_pdf_text)
+            .unwrap_or_default();
+        let mut rect = [0.0f32; 4];
+        if let Ok(values) = dict.get(b"Rect").and_then(|r| r.as_array()) {
+            for (slot, value) in rect.iter_mut().zip(values) {
+                *slot = value.as_float().unwrap_or(0.0);
+            }
+        }
+        let page_number = dict
+            .get(b"P")
+            .and_then(|p| p.as_reference())
+            .ok()
+            .and_then(|id| page_numbers.get(&id).copied());
+        out.push(PdfSignatureField {
+            name,
+            page_number,
+            rect,
+            signed: dict.get(b"V").is_ok(),
+        });
+    }
+    out
+}
+
+// PDF text strings are UTF-16BE with a byte order mark, or single-byte otherwise.
+fn decode_pdf_text(bytes: &[u8]) -> String {
+    match bytes.strip_prefix(&[0xFE, 0xFF]) {
+        Some(utf16) => {
+            let units = utf16
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect::<Vec<_>>();
+            String::from_utf16_lossy(&units)
+        }
+        None => bytes.iter().map(|b| char::from(*b)).collect(),
+    }
+}
+
+// Font identity for the audit: the object id when the font is shared by reference, otherwise
+// the page and resource name it was declared under.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum FontKey {
+    Object(ObjectId),
+    Inline(usize, Vec<u8>),
+}
+
+#[derive(Default)]
+struct ContentAudit {
+    issues: Vec<PdfContentIssue>,
+    fonts: BTreeMap<FontKey, PdfFontResource>,
+    // First page painting in each device colour space, pages that use transparency and
+    // non-standard blend modes; read by the profile preflight.
+    device_colors: BTreeMap<&'static str, usize>,
+    transparent_pages: BTreeSet<usize>,
+    blend_modes: Vec<(usize, Option<ObjectId>, String)>,
+}
+
+impl ContentAudit {
+    fn audit_page(&mut self, pdf: &LoDocument, page_number: usize, page_id: ObjectId) {
+        let resources = page_resources(pdf, page_id);
+        let content = pdf
+            .get_page_content(page_id)
+            .map_err(pdf_raster::lopdf_err)
+            .and_then(|bytes| pdf_raster::decode_content_with_fallback(&bytes));
+        let content = match content {
+            Ok(content) => content,
+            Err(err) => {
+                self.push(
+                    PdfContentIssueCode::ContentDecodeFailed,
+                    page_number,
+                    None,
+                    format!("page content stream could not be decoded: {err}"),
+                );
+                return;
+            }
+        };
+        let mut visited = HashSet::new();
+        self.audit_operations(
+            pdf,
+            page_number,
+            &content.operations,
+            resources,
+            &mut visited,
+        );
+    }
+
+    // Checks one content stream. Form XObjects are followed with their own resources
+    // (falling back to the caller's) and must balance q/Q and BT/ET on their own.
+    fn audit_operations(
+        &mut self,
+        pdf: &LoDocument,
+        page_number: usize,
+        operations: &[Operation],
+        resources: Option<&LoDictionary>,
+        visited: &mut HashSet<ObjectId>,
+    ) {
+        let mut save_depth = 0usize;
+        let mut in_text = false;
+        for op in operations {
+            match op.operator.as_str() {
+                "q" => save_depth += 1,
+                "Q" => {
+                    if save_depth == 0 {
+                        self.push(
+                            PdfContentIssueCode::UnbalancedSaveRestore,
+                            page_number,
+                            None,
+                            "Q without a matching q".to_string(),
+                        );
+                    } else {
+                        save_depth -= 1;
+                    }
+                }
+                "BT" => {
+                    if in_text {
+                        self.push(
+                            PdfContentIssueCode::UnbalancedTextObject,
+                            page_number,
+                            None,
+                            "BT inside an open text object".to_string(),
+                        );
+                    }
+                    in_text = true;
+                }
+                "ET" => {
+                    if !in_text {
+                        self.push(
+                            PdfContentIssueCode::UnbalancedTextObject,
+                            page_number,
+                            None,
+                            "ET without a matching BT".to_string(),
+                        );
+                    }
+                    in_text = false;
+                }
+                "Tj" | "TJ" | "'" | "\"" | "Td" | "TD" | "Tm" | "T*" if !in_text => {
+                    self.push(
+                        PdfContentIssueCode::TextOutsideTextObject,
+                        page_number,
+                        None,
+                        format!("{} used outside BT/ET", op.operator),
+                    );
+                }
+                "Tf" => {
+                    if let Some(name) = first_name(op) {
+                        self.audit_font(pdf, page_number, resources, name);
+                    }
+                }
+                "Do" => {
+                    if let Some(name) = first_name(op) {
+                        self.audit_xobject(pdf, page_number, resources, name, visited);
+                    }
+                }
+                "gs" => {
+                    if let Some(name) = first_name(op) {
+                        match resource_entry(pdf, resources, b"ExtGState", name) {
+                            Some(entry) => self.audit_ext_gstate(pdf, page_number, entry),
+                            None => self.undefined(page_number, b"ExtGState", name),
+                        }
+                    }
+                }
+                "g" | "G" => self.note_color("DeviceGray", page_number),
+                "rg" | "RG" => self.note_color("DeviceRGB", page_number),
+                "k" | "K" => self.note_color("DeviceCMYK", page_number),
+                "sh" => self.require_resource(pdf, page_number, resources, b"Shading", op),
+                "cs" | "CS" => match first_name(op).and_then(device_color_space) {
+                    Some(space) => self.note_color(space, page_number),
+                    None if first_name(op) == Some(&b"Pattern"[..]) => {}
+                    None => self.require_resource(pdf, page_number, resources, b"ColorSpace", op),
+                },
+                "scn" | "SCN" => {
+                    if let Some(name) = op.operands.last().and_then(|o| o.as_name().ok()) {
+                        self.require_named(pdf, page_number, resources, b"Pattern", name);
+                    }
+                }
+                _ => {}
+            }
+        }
+        if save_depth > 0 {
+            self.push(
+                PdfContentIssueCode::UnbalancedSaveRestore,
+                page_number,
+                None,
+                format!("{save_depth} q operator(s) left open at end of stream"),
+            );
+        }
+        if in_text {
+            self.push(
+                PdfContentIssueCode::UnbalancedTextObject,
+                page_number,
+                None,
+                "BT left open at end of stream".to_string(),
+            );
+        }
+    }
+
+    fn audit_font(
+        &mut self,
+        pdf: &LoDocument,
+        page_number: usize,
+        resources: Option<&LoDictionary>,
+        name: &[u8],
+    ) {
+        let Some(entry) = resource_entry(pdf, resources, b"Font", name) else {
+            self.undefined(page_number, b"Font", name);
+            return;
+        };
+        let key = match entry {
+            LoObject::Reference(id) => FontKey::Object(*id),
+            _ => FontKey::Inline(page_number, name.to_vec()),
+        };
+        if let Some(font) = self.fonts.get_mut(&key) {
+            if font.pages.last() != Some(&page_number) {
+                font.pages.push(page_number);
+            }
+            return;
+        }
+        let Some(dict) = resolve(pdf, entry).and_then(|obj| obj.as_dict().ok()) else {
+            self.undefined(page_number, b"Font", name);
+            return;
+        };
+        let font = PdfFontResource {
+            base_font: name_entry(dict, b"BaseFont"),
+            subtype: name_entry(dict, b"Subtype"),
+            embedded: font_is_embedded(pdf, dict),
+            pages: vec![page_number],
+        };
+        if !font.embedded {
+            self.push(
+                PdfContentIssueCode::FontNotEmbedded,
+                page_number,
+                Some(String::from_utf8_lossy(name).into_owned()),
+                format!("font {} is used but not embedded", font.base_font),
+            );
+        }
+        self.fonts.insert(key, font);
+    }
+
+    fn audit_xobject(
+        &mut self,
+        pdf: &LoDocument,
+        page_number: usize,
+        resources: Option<&LoDictionary>,
+        name: &[u8],
+        visited: &mut HashSet<ObjectId>,
+    ) {
+        let Some(entry) = resource_entry(pdf, resources, b"XObject", name) else {
+            self.push(
+                PdfContentIssueCode::XObjectMissing,
+                page_number,
+                Some(String::from_utf8_lossy(name).into_owned()),
+                format!(
+                    "Do /{} has no XObject resource entry",
+                    String::from_utf8_lossy(name)
+                ),
+            );
+            return;
+        };
+        let Ok((id, LoObject::Stream(stream))) = pdf.dereference(entry) else {
+            self.push(
+                PdfContentIssueCode::XObjectMissing,
+                page_number,
+                Some(String::from_utf8_lossy(name).into_owned()),
+                format!(
+                    "XObject /{} does not resolve to a stream",
+                    String::from_utf8_lossy(name)
+                ),
+            );
+            return;
+        };
+        let is_form = stream
+            .dict
+            .get(b"Subtype")
+            .and_then(|s| s.as_name())
+            .is_ok_and(|s| s == b"Form");
+        if !is_form {
+            self.audit_image(page_number, &stream.dict);
+            return;
+        }
+        // A form that (indirectly) paints itself is only walked once.
+        if id.is_some_and(|id| !visited.insert(id)) {
+            return;
+        }
+        let form_resources = stream
+            .dict
+            .get(b"Resources")
+            .ok()
+            .and_then(|r| resolve(pdf, r))
+            .and_then(|r| r.as_dict().ok())
+            .or(resources);
+        let content = stream
+            .get_plain_content()
+            .map_err(pdf_raster::lopdf_err)
+            .and_then(|bytes| pdf_raster::decode_content_with_fallback(&bytes));
+        match content {
+            Ok(content) => self.audit_operations(
+                pdf,
+                page_number,
+                &content.operations,
+                form_resources,
+                visited,
+            ),
+            Err(err) => self.push(
+                PdfContentIssueCode::ContentDecodeFailed,
+                page_number,
+                Some(String::from_utf8_lossy(name).into_owned()),
+                format!("form XObject content could not be decoded: {err}"),
+            ),
+        }
+        if let Some(id) = id {
+            visited.remove(&id);
+        }
+    }
+
+    fn audit_ext_gstate(&mut self, pdf: &LoDocument, page_number: usize, entry: &LoObject) {
+        let Ok((id, LoObject::Dictionary(dict))) = pdf.dereference(entry) else {
+            return;
+        };
+        let alpha = [&b"CA"[..], b"ca"].iter().any(|key| {
+            dict.get(key)
+                .and_then(|v| v.as_float())
+                .is_ok_and(|v| v < 1.0)
+        });
+        let soft_mask = dict
+            .get(b"SMask")
+            .is_ok_and(|mask| !matches!(mask, LoObject::Name(name) if name == b"None"));
+        let blend = match dict.get(b"BM") {
+            Ok(LoObject::Array(modes)) => modes.first().and_then(|m| m.as_name().ok()),
+            Ok(mode) => mode.as_name().ok(),
+            Err(_) => None,
+        };
+        let blended = blend.is_some_and(|mode| mode != b"Normal" && mode != b"Compatible");
+        if let Some(mode) = blend
+            && !STANDARD_BLEND_MODES.contains(&mode)
+        {
+            self.blend_modes
+                .push((page_number, id, String::from_utf8_lossy(mode).into_owned()));
+        }
+        if alpha || soft_mask || blended {
+            self.transparent_pages.insert(page_number);
+        }
+    }
+
+    fn audit_image(&mut self, page_number: usize, dict: &LoDictionary) {
+        if let Some(space) = dict
+            .get(b"ColorSpace")
+            .and_then(|c| c.as_name())
+            .ok()
+            .and_then(device_color_space)
+        {
+            self.note_color(space, page_number);
+        }
+        if dict.has(b"SMask")
+            || dict
+                .get(b"SMaskInData")
+                .is_ok_and(|v| v.as_i64().is_ok_and(|v| v != 0))
+        {
+            self.transparent_pages.insert(page_number);
+        }
+    }
+
+    fn note_color(&mut self, space: &'static str, page_number: usize) {
+        self.device_colors.entry(space).or_insert(page_number);
+    }
+
+    fn require_resource(
+        &mut self,
+        pdf: &LoDocument,
+        page_number: usize,
+        resources: Option<&LoDictionary>,
+        category: &[u8],
+        op: &Operation,
+    ) {
+        if let Some(name) = first_name(op) {
+            self.require_named(pdf, page_number, resources, category, name);
+        }
+    }
+
+    fn require_named(
+        &mut self,
+        pdf: &LoDocument,
+        page_number: usize,
+        resources: Option<&LoDictionary>,
+        category: &[u8],
+        name: &[u8],
+    ) {
+        if resource_entry(pdf, resources, category, name).is_none() {
+            self.undefined(page_number, category, name);
+        }
+    }
+
+    fn undefined(&mut self, page_number: usize, category: &[u8], name: &[u8]) {
+        let category = String::from_utf8_lossy(category);
+        let name = String::from_utf8_lossy(name);
+        self.push(
+            PdfContentIssueCode::UndefinedResource,
+            page_number,
+            Some(name.to_string()),
+            format!("/{name} is not defined in the {category} resources"),
+        );
+    }
+
+    fn push(
+        &mut self,
+        code: PdfContentIssueCode,
+        page_number: usize,
+        resource: Option<String>,
+        message: String,
+    ) {
+        self.issues.push(PdfContentIssue {
+            code,
+            page_number,
+            resource,
+            message,
+        });
+    }
+}
+
+const STANDARD_BLEND_MODES: [&[u8]; 17] = [
+    b"Normal",
+    b"Compatible",
+    b"Multiply",
+    b"Screen",
+    b"Overlay",
+    b"Darken",
+    b"Lighten",
+    b"ColorDodge",
+    b"ColorBurn",
+    b"HardLight",
+    b"SoftLight",
+    b"Difference",
+    b"Exclusion",
+    b"Hue",
+    b"Saturation",
+    b"Color",
+    b"Luminosity",
+];
+
+fn device_color_space(name: &[u8]) -> Option<&'static str> {
+    match name {
+        b"DeviceGray" => Some("DeviceGray"),
+        b"DeviceRGB" => Some("DeviceRGB"),
+        b"DeviceCMYK" => Some("DeviceCMYK"),
+        _ => None,
+    }
+}
+
+fn first_name(op: &Operation) -> Option<&[u8]> {
+    op.operands.first().and_then(|o| o.as_name().ok())
+}
+
+// Resources are inherited whole from the nearest ancestor in the page tree that has them.
+fn page_resources(pdf: &LoDocument, page_id: ObjectId) -> Option<&LoDictionary> {
+    let mut node = pdf.get_dictionary(page_id).ok()?;
+    let mut seen = HashSet::new();
+    loop {
+        if let Some(resources) = node
+            .get(b"Resources")
+            .ok()
+            .and_then(|r| resolve(pdf, r))
+            .and_then(|r| r.as_dict().ok())
+        {
+            return Some(resources);
+        }
+        let parent = node.get(b"Parent").and_then(|p| p.as_reference()).ok()?;
+        if !seen.insert(parent) {
+            return None;
+        }
+        node = pdf.get_dictionary(parent).ok()?;
+    }
+}
+
+fn resource_entry<'a>(
+    pdf: &'a LoDocument,
+    resources: Option<&'a LoDictionary>,
+    category: &[u8],
+    name: &[u8],
+) -> Option<&'a LoObject> {
+    resources?
+        .get(category)
+        .ok()
+        .and_then(|c| resolve(pdf, c))
+        .and_then(|c| c.as_dict().ok())?
+        .get(name)
+        .ok()
+}
+
+fn name_entry(dict: &LoDictionary, key: &[u8]) -> String {
+    dict.get(key)
+        .and_then(|v| v.as_name())
+        .map(|v| String::from_utf8_lossy(v).into_owned())
+        .unwrap_or_default()
+}
+
+// Type3 glyphs live in the font dictionary itself; Type0 fonts carry the program on their
+// descendant CIDFont.
+fn font_is_embedded(pdf: &LoDocument, font: &LoDictionary) -> bool {
+    let font = match font.get(b"Subtype").and_then(|s| s.as_name()) {
+        Ok(b"Type3") => return true,
+        Ok(b"Type0") => {
+            let descendant = font
+                .get(b"DescendantFonts")
+                .ok()
+                .and_then(|d| resolve(pdf, d))
+                .and_then(|d| d.as_array().ok())
+                .and_then(|d| d.first())
+                .and_then(|d| resolve(pdf, d))
+                .and_then(|d| d.as_dict().ok());
+            match descendant {
+                Some(descendant) => descendant,
+                None => return false,
+            }
+        }
+        _ => font,
+    };
+    font.get(b"FontDescriptor")
+        .ok()
+        .and_then(|d| resolve(pdf, d))
+        .and_then(|d| d.as_dict().ok())
+        .is_some_and(|descriptor| {
+            [&b"FontFile"[..], b"FontFile2", b"FontFile3"]
+                .iter()
+                .any(|key| descriptor.has(key))
+        })
+}
+
+// Text of every page in reading order, with boxes in top-left page space. Text is decoded
+// through each font's ToUnicode map, so glyphs without one come out as their raw codes.
+pub fn extract_text(bytes: &[u8]) -> Result<Vec<PageText>, PdfInspectError> {
+    let pages = pdf_raster::pdf_bytes_to_text_spans(bytes).map_err(|err| PdfInspectError {
+        code: PdfInspectErrorCode::PdfParseFailed,
+        message: err.to_string(),
+    })?;
+    Ok(pages
+        .into_iter()
+        .enumerate()
+        .map(|(index, spans)| text_extract::page_text(index + 1, spans))
+        .collect())
 }
 
 pub fn inspect_pdf_path(path: &Path) -> Result<PdfInspectReport, PdfInspectError> {
@@ -72,6 +1094,17 @@ pub fn inspect_pdf_path(path: &Path) -> Result<PdfInspectReport, PdfInspectError
     inspect_pdf_bytes(&data)
 }
 
+pub fn inspect_pdf_path_with_profile(
+    path: &Path,
+    profile: PdfProfile,
+) -> Result<PdfPreflightReport, PdfInspectError> {
+    let data = std::fs::read(path).map_err(|err| PdfInspectError {
+        code: PdfInspectErrorCode::PdfIoError,
+        message: err.to_string(),
+    })?;
+    inspect_pdf_bytes_with_profile(&data, profile)
+}
+
 pub fn composition_compatibility_issues(report: &PdfInspectReport) -> Vec<PdfInspectErrorCode> {
     let mut issues = Vec::new();
     if report.encrypted {
@@ -113,6 +1146,10 @@ mod tests {
     use std::io::Write;
 
     fn make_single_page_pdf_bytes(text: &str) -> Vec<u8> {
+        make_pdf_with_content(&format!("BT /F1 18 Tf 72 720 Td ({}) Tj ET", text))
+    }
+
+    fn make_pdf_with_content(content: &str) -> Vec<u8> {
         let mut doc = LoDocument::with_version("1.5");
         let pages_id = doc.new_object_id();
         let font_id = doc.add_object(dictionary! {
@@ -123,8 +1160,7 @@ mod tests {
         let resources_id = doc.add_object(dictionary! {
             "Font" => dictionary! { "F1" => font_id },
         });
-        let content = format!("BT /F1 18 Tf 72 720 Td ({}) Tj ET", text).into_bytes();
-        let content_id = doc.add_object(LoStream::new(dictionary! {}, content));
+        let content_id = doc.add_object(LoStream::new(dictionary! {}, content.as_bytes().to_vec()));
         let page_id = doc.add_object(dictionary! {
             "Type" => "Page",
             "Parent" => pages_id,
@@ -158,6 +1194,95 @@ mod tests {
         assert!(!report.encrypted);
         assert_eq!(report.file_size_bytes, bytes.len());
         assert!(!report.pdf_version.is_empty());
+        assert!(report.signature_fields.is_empty());
+    }
+
+    #[test]
+    fn inspect_pdf_bytes_audits_font_embedding() {
+        let bytes = make_single_page_pdf_bytes("FONTS");
+        let report = inspect_pdf_bytes(&bytes).expect("inspect");
+        assert_eq!(
+            report.fonts,
+            vec![PdfFontResource {
+                base_font: "Helvetica".to_string(),
+                subtype: "Type1".to_string(),
+                embedded: false,
+                pages: vec![1],
+            }]
+        );
+        let codes: Vec<_> = report.content_issues.iter().map(|i| i.code).collect();
+        assert_eq!(codes, vec![PdfContentIssueCode::FontNotEmbedded]);
+        assert_eq!(report.content_issues[0].resource.as_deref(), Some("F1"));
+    }
+
+    #[test]
+    fn inspect_pdf_bytes_flags_content_stream_errors() {
+        let bytes = make_pdf_with_content("q BT /F2 12 Tf (a) Tj ET (b) Tj /Im1 Do /GS1 gs Q Q BT");
+        let report = inspect_pdf_bytes(&bytes).expect("inspect");
+        let codes: Vec<_> = report.content_issues.iter().map(|i| i.code).collect();
+        assert_eq!(
+            codes,
+            vec![
+                PdfContentIssueCode::UndefinedResource,
+                PdfContentIssueCode::TextOutsideTextObject,
+                PdfContentIssueCode::XObjectMissing,
+                PdfContentIssueCode::UndefinedResource,
+                PdfContentIssueCode::UnbalancedSaveRestore,
+                PdfContentIssueCode::UnbalancedTextObject,
+            ]
+        );
+        assert_eq!(report.content_issues[0].resource.as_deref(), Some("F2"));
+        assert_eq!(report.content_issues[2].resource.as_deref(), Some("Im1"));
+        assert_eq!(report.content_issues[3].resource.as_deref(), Some("GS1"));
+        assert!(report.content_issues.iter().all(|i| i.page_number == 1));
+        assert!(report.fonts.is_empty());
+    }
+
+    #[test]
+    fn preflight_pdfx4_reports_missing_intent_fonts_and_rgb() {
+        let bytes = make_pdf_with_content("1 0 0 rg BT /F1 12 Tf 72 720 Td (X4) Tj ET");
+        let preflight =
+            inspect_pdf_bytes_with_profile(&bytes, PdfProfile::PdfX4).expect("preflight");
+        assert!(!preflight.passed());
+        let rules: Vec<_> = preflight.violations.iter().map(|v| v.rule).collect();
+        for expected in [
+            PdfPreflightRule::OutputIntentMissing,
+            PdfPreflightRule::MetadataMissing,
+            PdfPreflightRule::FontNotEmbedded,
+            PdfPreflightRule::DeviceRgbForbidden,
+            PdfPreflightRule::PageBoxMissing,
+            PdfPreflightRule::TrappedKeyMissing,
+        ] {
+            assert!(
+                rules.contains(&expected),
+                "missing {expected:?} in {rules:?}"
+            );
+        }
+        let font = preflight
+            .violations
+            .iter()
+            .find(|v| v.rule == PdfPreflightRule::FontNotEmbedded)
+            .expect("font violation");
+        assert!(font.object.is_some());
+        assert_eq!(font.page_number, Some(1));
+    }
+
+    #[test]
+    fn preflight_pdfa_requires_intent_only_for_device_color() {
+        let bytes = make_pdf_with_content("0 0 0 rg 0 0 10 10 re f");
+        let preflight =
+            inspect_pdf_bytes_with_profile(&bytes, PdfProfile::PdfA2b).expect("preflight");
+        let rules: Vec<_> = preflight.violations.iter().map(|v| v.rule).collect();
+        assert_eq!(
+            rules,
+            vec![
+                PdfPreflightRule::DeviceColorWithoutOutputIntent,
+                PdfPreflightRule::MetadataMissing,
+            ]
+        );
+
+        let none = inspect_pdf_bytes_with_profile(&bytes, PdfProfile::None).expect("preflight");
+        assert!(none.passed());
     }
 
     #[test]
@@ -187,6 +1312,9 @@ mod tests {
             page_count: 1,
             encrypted: true,
             file_size_bytes: 0,
+            signature_fields: Vec::new(),
+            content_issues: Vec::new(),
+            fonts: Vec::new(),
             warnings: Vec::new(),
         };
         let issues = composition_compatibility_issues(&report);
@@ -203,6 +1331,9 @@ mod tests {
             page_count: 0,
             encrypted: false,
             file_size_bytes: 0,
+            signature_fields: Vec::new(),
+            content_issues: Vec::new(),
+            fonts: Vec::new(),
             warnings: Vec::new(),
         };
         let issues = composition_compatibility_issues(&report);
@@ -1,10 +1,12 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
 use std::io::{self, BufWriter, Write};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
 use crate::debug::json_escape;
+use crate::error::FullBleedError;
+use serde_json::{Value, json};
 
 #[derive(Clone)]
 pub(crate) struct PerfLogger {
@@ -12,7 +14,8 @@ pub(crate) struct PerfLogger {
 }
 
 struct PerfState {
-    writer: BufWriter<File>,
+    // None for a tracing-only logger, which keeps no file and writes no hot summary.
+    writer: Option<BufWriter<File>>,
     path: PathBuf,
     span_totals: HashMap<String, f64>,
     span_counts: HashMap<String, u64>,
@@ -25,7 +28,7 @@ impl PerfLogger {
         let file = File::create(&path)?;
         Ok(Self {
             inner: Arc::new(Mutex::new(PerfState {
-                writer: BufWriter::new(file),
+                writer: Some(BufWriter::new(file)),
                 path,
                 span_totals: HashMap::new(),
                 span_counts: HashMap::new(),
@@ -34,14 +37,31 @@ impl PerfLogger {
         })
     }
 
+    // No file: spans and counts go only to `tracing` (target `fullbleed::perf`).
+    pub fn tracing_only() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(PerfState {
+                writer: None,
+                path: PathBuf::new(),
+                span_totals: HashMap::new(),
+                span_counts: HashMap::new(),
+                count_totals: HashMap::new(),
+            })),
+        }
+    }
+
     #[allow(dead_code)]
     pub fn log_json(&self, json: &str) {
-        if let Ok(mut state) = self.inner.lock() {
-            let _ = writeln!(state.writer, "{json}");
+        if let Ok(mut state) = self.inner.lock()
+            && let Some(writer) = state.writer.as_mut()
+        {
+            let _ = writeln!(writer, "{json}");
         }
     }
 
     pub fn log_span_ms(&self, name: &str, doc_id: Option<usize>, ms: f64) {
+        #[cfg(feature = "tracing")]
+        trace_span(name, doc_id, ms);
         let doc = doc_id
             .map(|v| v.to_string())
             .unwrap_or_else(|| "null".to_string());
@@ -55,11 +75,15 @@ impl PerfLogger {
             *state.span_totals.entry(name.to_string()).or_insert(0.0) += ms;
             let entry = state.span_counts.entry(name.to_string()).or_insert(0);
             *entry = entry.saturating_add(1);
-            let _ = writeln!(state.writer, "{json}");
+            if let Some(writer) = state.writer.as_mut() {
+                let _ = writeln!(writer, "{json}");
+            }
         }
     }
 
     pub fn log_counts(&self, name: &str, doc_id: Option<usize>, counts: &[(&str, u64)]) {
+        #[cfg(feature = "tracing")]
+        trace_counts(name, doc_id, counts);
         let doc = doc_id
             .map(|v| v.to_string())
             .unwrap_or_else(|| "null".to_string());
@@ -81,19 +105,26 @@ impl PerfLogger {
                 let entry = state.count_totals.entry(full_key).or_insert(0);
                 *entry = entry.saturating_add(*value);
             }
-            let _ = writeln!(state.writer, "{out}");
+            if let Some(writer) = state.writer.as_mut() {
+                let _ = writeln!(writer, "{out}");
+            }
         }
     }
 
     pub fn flush(&self) {
-        if let Ok(mut state) = self.inner.lock() {
-            let _ = state.writer.flush();
+        if let Ok(mut state) = self.inner.lock()
+            && let Some(writer) = state.writer.as_mut()
+        {
+            let _ = writer.flush();
         }
     }
 }
 
 impl Drop for PerfState {
     fn drop(&mut self) {
+        if self.writer.is_none() {
+            return;
+        }
         let hot_path = hot_path_for(&self.path);
         let Ok(file) = File::create(&hot_path) else {
             return;
@@ -130,6 +161,41 @@ impl Drop for PerfState {
     }
 }
 
+// One event per span, with its duration in `ms`. The timings are taken before the event is
+// emitted, so they are events rather than `tracing` spans.
+#[cfg(feature = "tracing")]
+fn trace_span(name: &str, doc_id: Option<usize>, ms: f64) {
+    match doc_id {
+        Some(doc_id) => {
+            tracing::info!(target: "fullbleed::perf", span_name = name, doc_id, ms, "perf.span")
+        }
+        None => tracing::info!(target: "fullbleed::perf", span_name = name, ms, "perf.span"),
+    }
+}
+
+#[cfg(feature = "tracing")]
+fn trace_counts(name: &str, doc_id: Option<usize>, counts: &[(&str, u64)]) {
+    for (counter, value) in counts {
+        match doc_id {
+            Some(doc_id) => tracing::info!(
+                target: "fullbleed::perf",
+                span_name = name,
+                doc_id,
+                counter = *counter,
+                value = *value,
+                "perf.counts"
+            ),
+            None => tracing::info!(
+                target: "fullbleed::perf",
+                span_name = name,
+                counter = *counter,
+                value = *value,
+                "perf.counts"
+            ),
+        }
+    }
+}
+
 fn hot_path_for(path: &Path) -> PathBuf {
     let file_name = path
         .file_name()
@@ -142,3 +208,215 @@ fn hot_path_for(path: &Path) -> PathBuf {
     let hot_name = format!("{stem}_hot.log");
     path.with_file_name(hot_name)
 }
+
+// Version of the `PerfComparison::to_json` layout. Bumped only when a field changes meaning
+// or is removed; new fields may be added within a version.
+pub const PERF_COMPARISON_SCHEMA_VERSION: u32 = 1;
+
+// When a span counts as a regression in [`compare_perf_logs`]: its mean time grew by more
+// than `threshold` (0.10 = 10%) and by at least `min_delta_ms`, which keeps sub-millisecond
+// spans from flagging on noise.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PerfCompareOptions {
+    pub threshold: f64,
+    pub min_delta_ms: f64,
+}
+
+impl Default for PerfCompareOptions {
+    fn default() -> Self {
+        Self {
+            threshold: 0.10,
+            min_delta_ms: 1.0,
+        }
+    }
+}
+
+// Totals of one span name over a perf log.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PerfSpanStats {
+    pub count: u64,
+    pub total_ms: f64,
+}
+
+impl PerfSpanStats {
+    pub fn mean_ms(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.total_ms / self.count as f64
+        }
+    }
+}
+
+// One span name in both logs. `None` stats mean the span is missing from that log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PerfSpanDelta {
+    pub name: String,
+    pub baseline: Option<PerfSpanStats>,
+    pub current: Option<PerfSpanStats>,
+    // Change of the mean time, current minus baseline; 0.0 unless both logs have the span.
+    pub delta_ms: f64,
+    // `delta_ms` relative to the baseline mean; `None` without a baseline mean above zero.
+    pub delta_ratio: Option<f64>,
+    pub regression: bool,
+}
+
+// Result of [`compare_perf_logs`], one entry per span name of either log, by name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PerfComparison {
+    pub schema_version: u32,
+    pub options: PerfCompareOptions,
+    pub spans: Vec<PerfSpanDelta>,
+}
+
+impl PerfComparison {
+    pub fn has_regressions(&self) -> bool {
+        self.spans.iter().any(|span| span.regression)
+    }
+
+    // The regressed spans, largest `delta_ms` first.
+    pub fn regressions(&self) -> Vec<&PerfSpanDelta> {
+        let mut regressions: Vec<&PerfSpanDelta> =
+            self.spans.iter().filter(|span| span.regression).collect();
+        regressions.sort_by(|a, b| b.delta_ms.total_cmp(&a.delta_ms));
+        regressions
+    }
+
+    // Machine-readable report (`type` `perf.comparison`, `schema_version`
+    // [`PERF_COMPARISON_SCHEMA_VERSION`]).
+    pub fn to_json(&self) -> String {
+        self.to_value().to_string()
+    }
+
+    fn to_value(&self) -> Value {
+        let stats = |stats: &Option<PerfSpanStats>| match stats {
+            Some(stats) => json!({
+                "count": stats.count,
+                "total_ms": stats.total_ms,
+                "mean_ms": stats.mean_ms(),
+            }),
+            None => Value::Null,
+        };
+        json!({
+            "type": "perf.comparison",
+            "schema_version": self.schema_version,
+            "threshold": self.options.threshold,
+            "min_delta_ms": self.options.min_delta_ms,
+            "has_regressions": self.has_regressions(),
+            "regressions": self
+                .regressions()
+                .iter()
+                .map(|span| span.name.as_str())
+                .collect::<Vec<_>>(),
+            "spans": self
+                .spans
+                .iter()
+                .map(|span| json!({
+                    "name": span.name,
+                    "baseline": stats(&span.baseline),
+                    "current": stats(&span.current),
+                    "delta_ms": span.delta_ms,
+                    "delta_ratio": span.delta_ratio,
+                    "regression": span.regression,
+                }))
+                .collect::<Vec<_>>(),
+        })
+    }
+}
+
+// Loads two perf logs (as written by `FullBleedBuilder::perf_log`) and compares their
+// `perf.span` records per span name. Fails when a log cannot be read or has no spans.
+pub fn compare_perf_logs(
+    baseline: impl AsRef<Path>,
+    current: impl AsRef<Path>,
+    options: PerfCompareOptions,
+) -> Result<PerfComparison, FullBleedError> {
+    let load = |path: &Path| -> Result<BTreeMap<String, PerfSpanStats>, FullBleedError> {
+        let spans = perf_span_stats(&std::fs::read_to_string(path)?);
+        if spans.is_empty() {
+            return Err(FullBleedError::InvalidConfiguration(format!(
+                "no perf.span records in {}",
+                path.display()
+            )));
+        }
+        Ok(spans)
+    };
+    Ok(compare_span_stats(
+        &load(baseline.as_ref())?,
+        &load(current.as_ref())?,
+        options,
+    ))
+}
+
+// [`compare_perf_logs`] on log text already in memory.
+pub fn compare_perf_log_text(
+    baseline: &str,
+    current: &str,
+    options: PerfCompareOptions,
+) -> PerfComparison {
+    compare_span_stats(
+        &perf_span_stats(baseline),
+        &perf_span_stats(current),
+        options,
+    )
+}
+
+// Lines that are not `perf.span` records (counts, hot summaries, other JSONL) are skipped.
+fn perf_span_stats(log: &str) -> BTreeMap<String, PerfSpanStats> {
+    let mut spans: BTreeMap<String, PerfSpanStats> = BTreeMap::new();
+    for line in log.lines() {
+        let Ok(record) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        if record["type"] != "perf.span" {
+            continue;
+        }
+        let (Some(name), Some(ms)) = (record["name"].as_str(), record["ms"].as_f64()) else {
+            continue;
+        };
+        let stats = spans.entry(name.to_string()).or_default();
+        stats.count += 1;
+        stats.total_ms += ms;
+    }
+    spans
+}
+
+fn compare_span_stats(
+    baseline: &BTreeMap<String, PerfSpanStats>,
+    current: &BTreeMap<String, PerfSpanStats>,
+    options: PerfCompareOptions,
+) -> PerfComparison {
+    let mut names: Vec<&String> = baseline.keys().chain(current.keys()).collect();
+    names.sort();
+    names.dedup();
+    let spans = names
+        .into_iter()
+        .map(|name| {
+            let before = baseline.get(name).copied();
+            let after = current.get(name).copied();
+            let (delta_ms, delta_ratio, regression) = match (before, after) {
+                (Some(before), Some(after)) => {
+                    let delta = after.mean_ms() - before.mean_ms();
+                    let ratio = (before.mean_ms() > 0.0).then(|| delta / before.mean_ms());
+                    let regression = delta >= options.min_delta_ms
+                        && ratio.is_none_or(|ratio| ratio > options.threshold);
+                    (delta, ratio, regression)
+                }
+                _ => (0.0, None, false),
+            };
+            PerfSpanDelta {
+                name: name.clone(),
+                baseline: before,
+                current: after,
+                delta_ms,
+                delta_ratio,
+                regression,
+            }
+        })
+        .collect();
+    PerfComparison {
+        schema_version: PERF_COMPARISON_SCHEMA_VERSION,
+        options,
+        spans,
+    }
+}
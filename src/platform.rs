@@ -0,0 +1,21 @@
+// Target differences kept in one place. wasm32-unknown-unknown has no clock, threads or
+// filesystem; file and env access already fail softly there, but `std::time::Instant::now`
+// panics, so engine timing goes through this `Instant`. On wasm it never advances and perf
+// spans read 0 ms.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use std::time::Instant;
+
+#[cfg(target_arch = "wasm32")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct Instant;
+
+#[cfg(target_arch = "wasm32")]
+impl Instant {
+    pub(crate) fn now() -> Self {
+        Instant
+    }
+
+    pub(crate) fn elapsed(&self) -> std::time::Duration {
+        std::time::Duration::ZERO
+    }
+}
@@ -0,0 +1,411 @@
+// Hot-reload preview (feature `preview`): watch a template and its stylesheets, re-render
+// when one changes, and serve the page PNGs over a small local HTTP endpoint. Plain std
+// networking and mtime polling; meant for a developer's machine, not for production traffic.
+use crate::{FullBleed, FullBleedError};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime};
+
+const DEFAULT_PREVIEW_DPI: u32 = 96;
+// Requests are served one at a time, so a client that stalls may hold the server (and `stop`)
+// for at most this long per read or write.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(2);
+// Request line plus headers; anything longer is refused with 431.
+const MAX_REQUEST_HEAD_BYTES: u64 = 8 * 1024;
+
+// One render of the watched inputs.
+#[derive(Debug, Clone)]
+pub struct PreviewFrame {
+    // Increments with every render, starting at 1.
+    pub generation: u64,
+    // One PNG per page.
+    pub pages: Vec<Vec<u8>>,
+    pub render_ms: f64,
+}
+
+// Re-renders an HTML file and its CSS files whenever one of them changes on disk.
+pub struct PreviewWatcher {
+    engine: Arc<FullBleed>,
+    html_path: PathBuf,
+    css_paths: Vec<PathBuf>,
+    dpi: u32,
+    generation: u64,
+    // Modification time and length of every input at the last render.
+    stamps: Vec<Option<(SystemTime, u64)>>,
+}
+
+impl PreviewWatcher {
+    pub fn new(
+        engine: Arc<FullBleed>,
+        html_path: impl Into<PathBuf>,
+        css_paths: impl IntoIterator<Item = impl Into<PathBuf>>,
+    ) -> Self {
+        Self {
+            engine,
+            html_path: html_path.into(),
+            css_paths: css_paths.into_iter().map(Into::into).collect(),
+            dpi: DEFAULT_PREVIEW_DPI,
+            generation: 0,
+            stamps: Vec::new(),
+        }
+    }
+
+    pub fn with_dpi(mut self, dpi: u32) -> Self {
+        self.dpi = dpi.max(1);
+        self
+    }
+
+    // Renders if any input changed since the last render (or nothing was rendered yet);
+    // `Ok(None)` when nothing changed. A failed render is retried on the next change.
+    pub fn poll(&mut self) -> Result<Option<PreviewFrame>, FullBleedError> {
+        let stamps = self.input_stamps();
+        if !self.stamps.is_empty() && stamps == self.stamps {
+            return Ok(None);
+        }
+        self.stamps = stamps;
+        let started = Instant::now();
+        let html = std::fs::read_to_string(&self.html_path)?;
+        let mut css = String::new();
+        for path in &self.css_paths {
+            css.push_str(&std::fs::read_to_string(path)?);
+            css.push('\n');
+        }
+        let pages = self.engine.render_image_pages(&html, &css, self.dpi)?;
+        self.generation += 1;
+        Ok(Some(PreviewFrame {
+            generation: self.generation,
+            pages,
+            render_ms: started.elapsed().as_secs_f64() * 1000.0,
+        }))
+    }
+
+    // Polls every `interval` and hands each render (or render error) to `on_frame` until it
+    // returns `false`.
+    pub fn watch<F>(&mut self, interval: Duration, mut on_frame: F)
+    where
+        F: FnMut(Result<PreviewFrame, FullBleedError>) -> bool,
+    {
+        loop {
+            let keep_going = match self.poll() {
+                Ok(Some(frame)) => on_frame(Ok(frame)),
+                Ok(None) => true,
+                Err(err) => on_frame(Err(err)),
+            };
+            if !keep_going {
+                return;
+            }
+            std::thread::sleep(interval);
+        }
+    }
+
+    fn input_stamps(&self) -> Vec<Option<(SystemTime, u64)>> {
+        std::iter::once(&self.html_path)
+            .chain(&self.css_paths)
+            .map(|path| {
+                let meta = std::fs::metadata(path).ok()?;
+                Some((meta.modified().ok()?, meta.len()))
+            })
+            .collect()
+    }
+}
+
+// Shared between the watcher and the server. Requests copy it out and release the lock before
+// writing, so a slow client never holds up the next render.
+#[derive(Default, Clone)]
+struct PreviewState {
+    frame: Option<Arc<PreviewFrame>>,
+    error: Option<String>,
+}
+
+// A running preview endpoint. `GET /` shows every page and reloads when the inputs are
+// re-rendered; `GET /page/<n>.png` is page `n` (1-based); `GET /generation` is the current
+// render generation.
+pub struct PreviewServer {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl PreviewServer {
+    // Binds `addr` (use port 0 for any free port) and starts watching with `watcher`,
+    // polling every `interval`.
+    pub fn start(
+        addr: impl ToSocketAddrs,
+        mut watcher: PreviewWatcher,
+        interval: Duration,
+    ) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let state = Arc::new(Mutex::new(PreviewState::default()));
+
+        let watch_state = Arc::clone(&state);
+        let watch_stop = Arc::clone(&stop);
+        let watch_thread = std::thread::spawn(move || {
+            while !watch_stop.load(Ordering::Acquire) {
+                let result = watcher.poll();
+                if let Ok(mut state) = watch_state.lock() {
+                    match result {
+                        Ok(Some(frame)) => {
+                            state.frame = Some(Arc::new(frame));
+                            state.error = None;
+                        }
+                        Ok(None) => {}
+                        Err(err) => state.error = Some(err.to_string()),
+                    }
+                }
+                std::thread::sleep(interval);
+            }
+        });
+
+        let serve_stop = Arc::clone(&stop);
+        let serve_thread = std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                if serve_stop.load(Ordering::Acquire) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    let _ = handle_request(stream, &state);
+                }
+            }
+        });
+
+        Ok(Self {
+            addr,
+            stop,
+            threads: vec![watch_thread, serve_thread],
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    // Stops serving and watching. The watcher finishes a render already in progress first.
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        // Wake the accept loop so it sees the flag.
+        let _ = TcpStream::connect(self.addr);
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for PreviewServer {
+    fn drop(&mut self) {
+        if !self.threads.is_empty() {
+            self.shutdown();
+        }
+    }
+}
+
+fn handle_request(stream: TcpStream, state: &Mutex<PreviewState>) -> io::Result<()> {
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.take(MAX_REQUEST_HEAD_BYTES));
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Drain the headers; requests carry no body we care about.
+    let mut header = String::new();
+    let mut complete = request_line.ends_with('\n');
+    while complete {
+        header.clear();
+        reader.read_line(&mut header)?;
+        complete = header.ends_with('\n');
+        if header.len() <= 2 {
+            break;
+        }
+    }
+    let mut stream = reader.into_inner().into_inner();
+    if !complete {
+        return respond(
+            &mut stream,
+            "431 Request Header Fields Too Large",
+            "text/plain",
+            b"request head too large",
+        );
+    }
+    let mut parts = request_line.split_whitespace();
+    let (Some("GET"), Some(target)) = (parts.next(), parts.next()) else {
+        return respond(
+            &mut stream,
+            "405 Method Not Allowed",
+            "text/plain",
+            b"GET only",
+        );
+    };
+    let path = target.split('?').next().unwrap_or(target);
+
+    let state = state
+        .lock()
+        .map_err(|_| io::Error::other("preview state poisoned"))?
+        .clone();
+    let generation = state.frame.as_ref().map_or(0, |frame| frame.generation);
+    if path == "/" {
+        let body = index_html(&state, generation);
+        return respond(
+            &mut stream,
+            "200 OK",
+            "text/html; charset=utf-8",
+            body.as_bytes(),
+        );
+    }
+    if path == "/generation" {
+        let body = generation.to_string();
+        return respond(&mut stream, "200 OK", "text/plain", body.as_bytes());
+    }
+    let page = path
+        .strip_prefix("/page/")
+        .and_then(|rest| rest.strip_suffix(".png"))
+        .and_then(|n| n.parse::<usize>().ok())
+        .and_then(|n| n.checked_sub(1))
+        .and_then(|index| state.frame.as_ref()?.pages.get(index));
+    match page {
+        Some(png) => respond(&mut stream, "200 OK", "image/png", png),
+        None => respond(&mut stream, "404 Not Found", "text/plain", b"not found"),
+    }
+}
+
+fn index_html(state: &PreviewState, generation: u64) -> String {
+    let mut body = String::from(
+        "<!doctype html><html><head><meta charset=\"utf-8\"><title>fullbleed preview</title>\
+         <style>body{background:#888;margin:0;padding:16px;font-family:sans-serif}\
+         img{display:block;margin:0 auto 16px;box-shadow:0 2px 8px #0006;background:#fff}\
+         pre{background:#fee;padding:8px;white-space:pre-wrap}</style></head><body>",
+    );
+    if let Some(error) = &state.error {
+        body.push_str("<pre>");
+        body.push_str(&escape_html(error));
+        body.push_str("</pre>");
+    }
+    let pages = state.frame.as_ref().map_or(0, |frame| frame.pages.len());
+    for page in 1..=pages {
+        body.push_str(&format!(
+            "<img src=\"/page/{page}.png?g={generation}\" alt=\"page {page}\">"
+        ));
+    }
+    body.push_str(&format!(
+        "<script>setInterval(()=>fetch('/generation').then(r=>r.text())\
+         .then(g=>{{if(g!=='{generation}')location.reload()}}).catch(()=>{{}}),500)</script>\
+         </body></html>"
+    ));
+    body
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n\
+         Cache-Control: no-store\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn preview_watcher_rerenders_on_change_and_server_serves_pages() {
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let dir = std::env::temp_dir().join(format!(
+            "fullbleed_preview_{}_{}",
+            std::process::id(),
+            stamp
+        ));
+        std::fs::create_dir_all(&dir).expect("dir");
+        let html_path = dir.join("page.html");
+        let css_path = dir.join("page.css");
+        std::fs::write(
+            &html_path,
+            "<p>One</p><p style=\"break-before: page\">Two</p>",
+        )
+        .expect("html");
+        std::fs::write(&css_path, "p { color: red; }").expect("css");
+        let engine = Arc::new(FullBleed::builder().build().expect("engine"));
+
+        let mut watcher = PreviewWatcher::new(engine.clone(), &html_path, [&css_path]).with_dpi(36);
+        let first = watcher.poll().expect("render").expect("first frame");
+        assert_eq!(first.generation, 1);
+        assert_eq!(first.pages.len(), 2);
+        assert!(first.pages[0].starts_with(b"\x89PNG"));
+        assert!(watcher.poll().expect("poll").is_none());
+        std::fs::write(&css_path, "p { color: blue; font-size: 20pt; }").expect("css");
+        let second = watcher.poll().expect("render").expect("second frame");
+        assert_eq!(second.generation, 2);
+
+        let watcher = PreviewWatcher::new(engine, &html_path, [&css_path]).with_dpi(36);
+        let server = PreviewServer::start("127.0.0.1:0", watcher, Duration::from_millis(20))
+            .expect("server");
+        let get = |path: &str| {
+            let mut stream = TcpStream::connect(server.local_addr()).expect("connect");
+            write!(stream, "GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").expect("send");
+            let mut response = Vec::new();
+            stream.read_to_end(&mut response).expect("read");
+            let split = response
+                .windows(4)
+                .position(|w| w == b"\r\n\r\n")
+                .expect("headers");
+            (
+                String::from_utf8_lossy(&response[..split]).to_string(),
+                response[split + 4..].to_vec(),
+            )
+        };
+        let deadline = Instant::now() + Duration::from_secs(20);
+        while get("/generation").1 != b"1" {
+            assert!(Instant::now() < deadline, "preview never rendered");
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        let (head, body) = get("/page/2.png");
+        assert!(head.starts_with("HTTP/1.1 200"));
+        assert!(body.starts_with(b"\x89PNG"));
+        assert!(get("/page/3.png").0.starts_with("HTTP/1.1 404"));
+        assert!(String::from_utf8_lossy(&get("/").1).contains("/page/2.png?g=1"));
+
+        // An oversized request head is refused rather than buffered.
+        let mut stream = TcpStream::connect(server.local_addr()).expect("connect");
+        // Exactly the cap, so the server reads all of it and the reply is not lost to a reset.
+        let long = "a".repeat(8 * 1024 - "GET /".len());
+        write!(stream, "GET /{long}").expect("send");
+        let mut response = Vec::new();
+        let _ = stream.read_to_end(&mut response);
+        assert!(response.starts_with(b"HTTP/1.1 431"));
+
+        // A client that connects and sends nothing neither blocks others nor `stop`.
+        let _idle = TcpStream::connect(server.local_addr()).expect("idle");
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(get("/generation").1, b"1");
+        let _idle = TcpStream::connect(server.local_addr()).expect("idle");
+        std::thread::sleep(Duration::from_millis(50));
+        let stopping = Instant::now();
+        server.stop();
+        assert!(stopping.elapsed() < Duration::from_secs(10));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,91 @@
+// Live progress from long renders. Layout reports each finished page, batch renders each
+// finished record, and the PDF writer the bytes it has written. Events come from the
+// rendering threads, so parallel batches report concurrently.
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// One step of a render, reported to the engine's [`ProgressSink`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressEvent {
+    // Layout finished page `page_number` of document `doc_id`. Layout that runs more than
+    // once (lazy passes, page references) reports its pages again.
+    PageLaidOut {
+        doc_id: usize,
+        page_number: usize,
+    },
+    // Batch record `index` is done: laid out, and written when the batch streams its
+    // output in order. `completed` of `total` records are done so far.
+    RecordCompleted {
+        index: usize,
+        completed: usize,
+        total: usize,
+    },
+    // `bytes` more PDF bytes were written. Each output reports its own increments, so the
+    // sum over all events is the total output size.
+    BytesWritten {
+        bytes: usize,
+    },
+}
+
+// Receives progress events; any `Fn(ProgressEvent) + Send + Sync` closure is a sink.
+pub trait ProgressSink: Send + Sync {
+    fn report(&self, event: ProgressEvent);
+}
+
+impl<F> ProgressSink for F
+where
+    F: Fn(ProgressEvent) + Send + Sync,
+{
+    fn report(&self, event: ProgressEvent) {
+        self(event)
+    }
+}
+
+// Shared sink carried in the engine's writer options.
+#[derive(Clone)]
+pub(crate) struct ProgressHandle(Arc<dyn ProgressSink>);
+
+impl ProgressHandle {
+    pub(crate) fn new(sink: impl ProgressSink + 'static) -> Self {
+        Self(Arc::new(sink))
+    }
+
+    pub(crate) fn report(&self, event: ProgressEvent) {
+        self.0.report(event);
+    }
+}
+
+impl fmt::Debug for ProgressHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressHandle")
+    }
+}
+
+// Counts finished records of one batch; safe to share across the batch's worker threads.
+pub(crate) struct BatchProgress {
+    handle: Option<ProgressHandle>,
+    total: usize,
+    completed: AtomicUsize,
+}
+
+impl BatchProgress {
+    pub(crate) fn new(handle: Option<ProgressHandle>, total: usize) -> Self {
+        Self {
+            handle,
+            total,
+            completed: AtomicUsize::new(0),
+        }
+    }
+
+    pub(crate) fn record_done(&self, index: usize) {
+        if let Some(handle) = self.handle.as_ref() {
+            let completed = self.completed.fetch_add(1, Ordering::Relaxed) + 1;
+            handle.report(ProgressEvent::RecordCompleted {
+                index,
+                completed,
+                total: self.total,
+            });
+        }
+    }
+}
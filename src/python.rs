@@ -8,12 +8,12 @@ use crate::canvas::{
 use crate::jit::Transform;
 use crate::{
     A11yVerifierCoreReport, A11yVerifierEvidence, A11yVerifierFinding, Asset, AssetBundle,
-    AssetKind, Color, ColorSpace, Command, Document, FullBleed, FullBleedBuilder, FullBleedError,
-    GlyphCoverageReport, JitMode, LayoutStrategy, Margins, OutputIntent, PageDataContext,
-    PageDataValue, PaginationTraceSummary, PdfProfile, PdfVersion, PmrCoreAudit, PmrCoreContext,
-    PmrCoreEvidence, PmrCoreReport, Pt, Size, WatermarkLayer, WatermarkSemantics, WatermarkSpec,
-    composition_compatibility_issues, inspect_pdf_bytes, inspect_pdf_path,
-    require_pdf_composition_compatibility,
+    AssetKind, Color, ColorSpace, Command, CommandBuffer, Document, FullBleed, FullBleedBuilder,
+    FullBleedError, GlyphCoverageReport, JitMode, LayoutBox, LayoutStrategy, Margins, OutputIntent,
+    PageDataContext, PageDataValue, PaginationTraceSummary, PdfProfile, PdfVersion, PmrCoreAudit,
+    PmrCoreContext, PmrCoreEvidence, PmrCoreReport, Pt, Size, WatermarkLayer, WatermarkSemantics,
+    WatermarkSpec, composition_compatibility_issues, inspect_pdf_bytes, inspect_pdf_path,
+    inspect_pdf_path_with_profile, require_pdf_composition_compatibility,
 };
 use base64::Engine;
 use fullbleed_audit_contract as audit_contract;
@@ -26,6 +26,7 @@ use sha2::{Digest, Sha256};
 use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 #[pyclass(name = "AssetKind")]
 struct PyAssetKind;
@@ -204,7 +205,7 @@ fn build_asset(
         let path = Path::new(source);
         if !is_supported_font_path(path) {
             return Err(PyValueError::new_err(
-                "unsupported font format (expected .ttf or .otf)",
+                "unsupported font format (expected .ttf, .otf, .woff or .woff2)",
             ));
         }
     }
@@ -246,6 +247,39 @@ fn inspect_report_to_py(
     out.set_item("encrypted", report.encrypted)?;
     out.set_item("file_size_bytes", report.file_size_bytes)?;
 
+    let signature_fields = PyList::empty_bound(py);
+    for field in &report.signature_fields {
+        let d = PyDict::new_bound(py);
+        d.set_item("name", field.name.clone())?;
+        d.set_item("page_number", field.page_number)?;
+        d.set_item("rect", field.rect.to_vec())?;
+        d.set_item("signed", field.signed)?;
+        signature_fields.append(d)?;
+    }
+    out.set_item("signature_fields", signature_fields)?;
+
+    let content_issues = PyList::empty_bound(py);
+    for issue in &report.content_issues {
+        let d = PyDict::new_bound(py);
+        d.set_item("code", issue.code.as_str())?;
+        d.set_item("page_number", issue.page_number)?;
+        d.set_item("resource", issue.resource.clone())?;
+        d.set_item("message", issue.message.clone())?;
+        content_issues.append(d)?;
+    }
+    out.set_item("content_issues", content_issues)?;
+
+    let fonts = PyList::empty_bound(py);
+    for font in &report.fonts {
+        let d = PyDict::new_bound(py);
+        d.set_item("base_font", font.base_font.clone())?;
+        d.set_item("subtype", font.subtype.clone())?;
+        d.set_item("embedded", font.embedded)?;
+        d.set_item("pages", font.pages.clone())?;
+        fonts.append(d)?;
+    }
+    out.set_item("fonts", fonts)?;
+
     let warnings = PyList::empty_bound(py);
     for warning in &report.warnings {
         let d = PyDict::new_bound(py);
@@ -380,6 +414,32 @@ fn inspect_pdf(py: Python<'_>, path: &str) -> PyResult<PyObject> {
     Ok(out)
 }
 
+#[pyfunction]
+fn preflight_pdf(py: Python<'_>, path: &str, profile: &Bound<'_, PyAny>) -> PyResult<PyObject> {
+    let profile = parse_pdf_profile(Some(profile))?.unwrap_or(PdfProfile::None);
+    let preflight =
+        inspect_pdf_path_with_profile(Path::new(path), profile).map_err(pdf_inspect_err_to_py)?;
+    let out = inspect_report_to_py(py, path, &preflight.report)?;
+    let dict = out.bind(py).downcast::<PyDict>()?;
+    let violations = PyList::empty_bound(py);
+    for violation in &preflight.violations {
+        let d = PyDict::new_bound(py);
+        d.set_item("rule", violation.rule.as_str())?;
+        d.set_item("page_number", violation.page_number)?;
+        d.set_item(
+            "object",
+            violation
+                .object
+                .map(|(number, generation)| format!("{number} {generation} R")),
+        )?;
+        d.set_item("message", violation.message.clone())?;
+        violations.append(d)?;
+    }
+    dict.set_item("violations", violations)?;
+    dict.set_item("ok", preflight.passed())?;
+    Ok(out)
+}
+
 #[pyfunction]
 fn extract_pdf_page_texts(py: Python<'_>, pdf_path: &str) -> PyResult<PyObject> {
     let path = Path::new(pdf_path);
@@ -1319,7 +1379,7 @@ fn parse_color_hex(s: &str) -> Option<Color> {
     let r = u8::from_str_radix(&hex[0..2], 16).ok()? as f32 / 255.0;
     let g = u8::from_str_radix(&hex[2..4], 16).ok()? as f32 / 255.0;
     let b = u8::from_str_radix(&hex[4..6], 16).ok()? as f32 / 255.0;
-    Some(Color { r, g, b })
+    Some(Color::rgb(r, g, b))
 }
 
 fn parse_color_space(raw: &str) -> PyResult<ColorSpace> {
@@ -1544,11 +1604,12 @@ fn parse_pdf_profile(arg: Option<&Bound<'_, PyAny>>) -> PyResult<Option<PdfProfi
         let profile = match raw.as_str() {
             "" | "none" => PdfProfile::None,
             "pdfa2b" | "pdfa-2b" | "pdfa_2b" => PdfProfile::PdfA2b,
+            "pdfa3b" | "pdfa-3b" | "pdfa_3b" => PdfProfile::PdfA3b,
             "pdfx4" | "pdfx-4" | "pdfx_4" => PdfProfile::PdfX4,
             "tagged" | "pdfua" | "pdf/ua" => PdfProfile::Tagged,
             _ => {
                 return Err(PyValueError::new_err(format!(
-                    "Invalid pdf_profile: {s:?}. Expected one of: none, pdfa2b, pdfx4, tagged"
+                    "Invalid pdf_profile: {s:?}. Expected one of: none, pdfa2b, pdfa3b, pdfx4, tagged"
                 )));
             }
         };
@@ -1916,7 +1977,7 @@ fn collect_render_time_text_blocks_for_page(
     let mut owner_stack: Vec<TraceOwnerContext> = Vec::new();
 
     for (cmd_index, cmd) in page.commands.iter().enumerate() {
-        match cmd {
+        match &*cmd {
             Command::SaveState => state_stack.push(state.clone()),
             Command::RestoreState => {
                 state = state_stack.pop().unwrap_or_else(TraceTextState::new);
@@ -1938,7 +1999,7 @@ fn collect_render_time_text_blocks_for_page(
                         .transform
                         .mul(Transform::matrix(*a, *b, *c, *d, e.to_f32(), f.to_f32()));
             }
-            Command::SetFontName(name) => state.font_name = name.clone(),
+            Command::SetFontName(name) => state.font_name = name.to_string(),
             Command::SetFontSize(size) => state.font_size = *size,
             Command::Meta { key, value } if key == "font.requested_name" => {
                 pending_requested_font_name = Some(value.clone());
@@ -2585,13 +2646,16 @@ fn record_font_usage_sample(entry: &mut RenderTimeFontUsage, sample: Option<&str
     entry.sample_text = Some(trace_text_sample(sample));
 }
 
-fn collect_form_definitions(commands: &[Command], definitions: &mut HashMap<String, Vec<Command>>) {
+fn collect_form_definitions(
+    commands: &CommandBuffer,
+    definitions: &mut HashMap<Arc<str>, CommandBuffer>,
+) {
     for cmd in commands {
         if let Command::DefineForm {
             resource_id,
             commands: form_commands,
             ..
-        } = cmd
+        } = &*cmd
         {
             definitions
                 .entry(resource_id.clone())
@@ -2602,17 +2666,17 @@ fn collect_form_definitions(commands: &[Command], definitions: &mut HashMap<Stri
 }
 
 fn collect_font_usage_in_commands(
-    commands: &[Command],
-    form_definitions: &HashMap<String, Vec<Command>>,
+    commands: &CommandBuffer,
+    form_definitions: &HashMap<Arc<str>, CommandBuffer>,
     current_font: &mut String,
     pending_requested_name: &mut Option<String>,
     pending_fallback_reason: &mut Option<String>,
     usage: &mut BTreeMap<RenderTimeFontUsageKey, RenderTimeFontUsage>,
-    active_forms: &mut BTreeSet<String>,
+    active_forms: &mut BTreeSet<Arc<str>>,
 ) {
     for cmd in commands {
-        match cmd {
-            Command::SetFontName(name) => *current_font = name.clone(),
+        match &*cmd {
+            Command::SetFontName(name) => *current_font = name.to_string(),
             Command::Meta { key, value } => {
                 if key == "font.requested_name" {
                     *pending_requested_name = Some(value.clone());
@@ -2686,7 +2750,7 @@ fn collect_render_time_font_usage(
 ) -> BTreeMap<RenderTimeFontUsageKey, RenderTimeFontUsage> {
     let mut usage: BTreeMap<RenderTimeFontUsageKey, RenderTimeFontUsage> = BTreeMap::new();
     for page in &doc.pages {
-        let mut form_definitions: HashMap<String, Vec<Command>> = HashMap::new();
+        let mut form_definitions: HashMap<Arc<str>, CommandBuffer> = HashMap::new();
         collect_form_definitions(&page.commands, &mut form_definitions);
         let mut current_font = "Helvetica".to_string();
         let mut pending_requested_name = None;
@@ -3219,10 +3283,10 @@ fn build_render_time_pagination_trace_py(
         let text_collection = collect_render_time_text_blocks_for_page(page, Some(engine));
 
         for cmd in &page.commands {
-            if is_visible_command(cmd) {
+            if is_visible_command(&cmd) {
                 visible_command_count = visible_command_count.saturating_add(1);
             }
-            match cmd {
+            match &*cmd {
                 Command::Meta { key, value } if key == crate::META_PAGE_TEMPLATE_KEY => {
                     template_name = Some(value.clone());
                 }
@@ -3535,8 +3599,8 @@ fn build_render_time_pagination_trace_py(
         let page_row = PyDict::new_bound(py);
         page_row.set_item("page_index", page_index)?;
         page_row.set_item("page", page_index + 1)?;
-        page_row.set_item("width", doc.page_size.width.to_f32())?;
-        page_row.set_item("height", doc.page_size.height.to_f32())?;
+        page_row.set_item("width", doc.page_size_at(page_index).width.to_f32())?;
+        page_row.set_item("height", doc.page_size_at(page_index).height.to_f32())?;
         page_row.set_item("template_name", template_name)?;
         page_row.set_item("layout_event_count", layout_event_count)?;
         page_row.set_item("event_count", page_event_count)?;
@@ -3792,8 +3856,8 @@ fn build_render_time_reading_order_trace_py(
         let page_row = PyDict::new_bound(py);
         page_row.set_item("page_index", page_index)?;
         page_row.set_item("page", page_index + 1)?;
-        page_row.set_item("width", doc.page_size.width.to_f32())?;
-        page_row.set_item("height", doc.page_size.height.to_f32())?;
+        page_row.set_item("width", doc.page_size_at(page_index).width.to_f32())?;
+        page_row.set_item("height", doc.page_size_at(page_index).height.to_f32())?;
         page_row.set_item("block_count", block_count)?;
         page_row.set_item("blocks", blocks)?;
         page_row.set_item("draw_form_count", page_draw_form_count)?;
@@ -3866,7 +3930,7 @@ fn build_render_time_structure_trace_py(py: Python<'_>, doc: &Document) -> PyRes
         let mut page_tagged_text_draws = 0usize;
 
         for (cmd_index, cmd) in page.commands.iter().enumerate() {
-            match cmd {
+            match &*cmd {
                 Command::BeginTag {
                     role,
                     mcid,
@@ -3984,16 +4048,148 @@ fn build_render_time_structure_trace_py(py: Python<'_>, doc: &Document) -> PyRes
 fn glyph_report_to_py(py: Python<'_>, report: &GlyphCoverageReport) -> PyResult<PyObject> {
     let list = PyList::empty_bound(py);
     for missing in report.missing() {
+        list.append(missing_glyph_to_py(py, &missing)?)?;
+    }
+    Ok(list.to_object(py))
+}
+
+fn warnings_to_py(py: Python<'_>, warnings: &[crate::Warning]) -> PyResult<PyObject> {
+    let list = PyList::empty_bound(py);
+    for warning in warnings {
+        let d = PyDict::new_bound(py);
+        d.set_item("kind", warning.kind.as_str())?;
+        d.set_item("code", warning.code)?;
+        d.set_item("message", warning.message.clone())?;
+        d.set_item("doc_id", warning.doc_id)?;
+        d.set_item("page", warning.page)?;
+        d.set_item("details", warning.details.clone())?;
+        d.set_item("loss", warning.loss.as_ref().map(|loss| loss.code()))?;
+        d.set_item("element", warning.element.clone())?;
+        list.append(d)?;
+    }
+    Ok(list.to_object(py))
+}
+
+fn element_timings_to_py(py: Python<'_>, timings: &[crate::ElementTiming]) -> PyResult<PyObject> {
+    let list = PyList::empty_bound(py);
+    for timing in timings {
+        let d = PyDict::new_bound(py);
+        d.set_item("path", timing.path.clone())?;
+        d.set_item("ms", timing.ms)?;
+        d.set_item("calls", timing.calls)?;
+        list.append(d)?;
+    }
+    Ok(list.to_object(py))
+}
+
+fn layout_profile_to_py(
+    py: Python<'_>,
+    profile: &crate::LayoutProfile,
+    top: usize,
+) -> PyResult<PyObject> {
+    let out = PyDict::new_bound(py);
+    out.set_item("total_ms", profile.total_ms())?;
+    out.set_item(
+        "slowest_subtrees",
+        element_timings_to_py(py, &profile.slowest_subtrees(top))?,
+    )?;
+    out.set_item(
+        "slowest_tables",
+        element_timings_to_py(py, &profile.slowest_tables(top))?,
+    )?;
+    out.set_item(
+        "slowest_paragraph_wraps",
+        element_timings_to_py(py, &profile.slowest_paragraph_wraps(top))?,
+    )?;
+    out.set_item("folded", profile.to_folded())?;
+    Ok(out.to_object(py))
+}
+
+fn render_report_to_py(py: Python<'_>, report: &crate::RenderReport) -> PyResult<PyObject> {
+    let losses = PyList::empty_bound(py);
+    for event in &report.known_losses {
+        let d = PyDict::new_bound(py);
+        d.set_item("code", event.loss.code())?;
+        d.set_item("severity", event.severity.as_str())?;
+        d.set_item("message", event.message.clone())?;
+        d.set_item("doc_id", event.doc_id)?;
+        d.set_item("page", event.page)?;
+        d.set_item("element", event.element.clone())?;
+        losses.append(d)?;
+    }
+    let out = PyDict::new_bound(py);
+    out.set_item("known_losses", losses)?;
+    out.set_item(
+        "max_severity",
+        report.max_severity().map(|severity| severity.as_str()),
+    )?;
+    out.set_item("warnings", warnings_to_py(py, &report.warnings)?)?;
+    Ok(out.to_object(py))
+}
+
+fn css_diagnostics_to_py(
+    py: Python<'_>,
+    diagnostics: &crate::CssDiagnostics,
+) -> PyResult<PyObject> {
+    let list = PyList::empty_bound(py);
+    for diagnostic in diagnostics {
         let d = PyDict::new_bound(py);
-        d.set_item("codepoint", missing.codepoint)?;
-        d.set_item("char", missing.ch.to_string())?;
-        d.set_item("fonts_tried", missing.fonts_tried)?;
-        d.set_item("count", missing.count)?;
+        d.set_item("kind", diagnostic.kind.as_str())?;
+        d.set_item("selector", diagnostic.selector.clone())?;
+        d.set_item("property", diagnostic.property.clone())?;
+        d.set_item("line", diagnostic.line)?;
+        d.set_item("column", diagnostic.column)?;
+        d.set_item("reason", diagnostic.reason.clone())?;
+        d.set_item("from_bundle", diagnostic.from_bundle)?;
         list.append(d)?;
     }
     Ok(list.to_object(py))
 }
 
+fn missing_glyph_to_py<'py>(
+    py: Python<'py>,
+    missing: &crate::MissingGlyph,
+) -> PyResult<Bound<'py, PyDict>> {
+    let d = PyDict::new_bound(py);
+    d.set_item("codepoint", missing.codepoint)?;
+    d.set_item("char", missing.ch.to_string())?;
+    d.set_item("fonts_tried", missing.fonts_tried.clone())?;
+    d.set_item("count", missing.count)?;
+    d.set_item("pages", missing.pages.clone())?;
+    d.set_item("element_paths", missing.element_paths.clone())?;
+    d.set_item("suggested_font", missing.suggested_font.clone())?;
+    Ok(d)
+}
+
+fn layout_box_to_py(py: Python<'_>, node: &LayoutBox) -> PyResult<PyObject> {
+    let d = PyDict::new_bound(py);
+    d.set_item("element_path", node.element_path.clone())?;
+    d.set_item("tag", node.tag.clone())?;
+    d.set_item("id", node.id.clone())?;
+    d.set_item("classes", node.classes.clone())?;
+    d.set_item("page_number", node.page_number)?;
+    d.set_item(
+        "rect",
+        (
+            node.rect.x.to_f32(),
+            node.rect.y.to_f32(),
+            node.rect.width.to_f32(),
+            node.rect.height.to_f32(),
+        ),
+    )?;
+    let style = PyDict::new_bound(py);
+    for (name, value) in &node.computed_style {
+        style.set_item(name, value)?;
+    }
+    d.set_item("computed_style", style)?;
+    let children = PyList::empty_bound(py);
+    for child in &node.children {
+        children.append(layout_box_to_py(py, child)?)?;
+    }
+    d.set_item("children", children)?;
+    Ok(d.to_object(py))
+}
+
 #[pyclass]
 struct PdfEngine {
     engine: FullBleed,
@@ -9100,6 +9296,9 @@ impl PdfEngine {
             page_height=None,
             margin=None,
             page_margins=None,
+            page_rotate=0,
+            rotate_landscape_pages=false,
+            mirror_margins=false,
             font_dirs=None,
             font_files=None,
             reuse_xobjects=true,
@@ -9117,6 +9316,7 @@ impl PdfEngine {
             color_space=None,
             document_lang=None,
             document_title=None,
+            outline_from_headings=None,
             header_first=None,
             header_each=None,
             header_last=None,
@@ -9157,7 +9357,10 @@ impl PdfEngine {
             accept_lazy_layout_cost=false,
             lazy_max_passes=4,
             lazy_budget_ms=50.0,
+            parallel_sections=false,
             jit_mode=None,
+            enforce_audit_gates=None,
+            strict_fidelity=false,
             debug=false,
             debug_out=None,
             perf=false,
@@ -9169,6 +9372,9 @@ impl PdfEngine {
         page_height: Option<&Bound<'_, PyAny>>,
         margin: Option<&Bound<'_, PyAny>>,
         page_margins: Option<&Bound<'_, PyAny>>,
+        page_rotate: i32,
+        rotate_landscape_pages: bool,
+        mirror_margins: bool,
         font_dirs: Option<Vec<String>>,
         font_files: Option<Vec<String>>,
         reuse_xobjects: bool,
@@ -9186,6 +9392,7 @@ impl PdfEngine {
         color_space: Option<String>,
         document_lang: Option<String>,
         document_title: Option<String>,
+        outline_from_headings: Option<u8>,
         header_first: Option<String>,
         header_each: Option<String>,
         header_last: Option<String>,
@@ -9226,7 +9433,10 @@ impl PdfEngine {
         accept_lazy_layout_cost: bool,
         lazy_max_passes: usize,
         lazy_budget_ms: f64,
+        parallel_sections: bool,
         jit_mode: Option<String>,
+        enforce_audit_gates: Option<String>,
+        strict_fidelity: bool,
         debug: bool,
         debug_out: Option<String>,
         perf: bool,
@@ -9246,6 +9456,15 @@ impl PdfEngine {
                 builder = builder.margins(m);
             }
         }
+        if page_rotate % 90 != 0 {
+            return Err(PyValueError::new_err(
+                "page_rotate must be a multiple of 90 degrees",
+            ));
+        }
+        builder = builder
+            .page_rotate(page_rotate)
+            .rotate_landscape_pages(rotate_landscape_pages)
+            .mirror_margins(mirror_margins);
 
         if let Some(pm) = page_margins {
             let dict = pm.downcast::<PyDict>().map_err(|_| {
@@ -9331,6 +9550,9 @@ impl PdfEngine {
         if let Some(title) = document_title {
             builder = builder.document_title(title);
         }
+        if let Some(levels) = outline_from_headings {
+            builder = builder.outline_from_headings(levels);
+        }
 
         // Prefer HTML header if provided; otherwise fall back to plain text header.
         if header_html_first.is_some() || header_html_each.is_some() || header_html_last.is_some() {
@@ -9355,11 +9577,7 @@ impl PdfEngine {
             let header_color = header_color
                 .as_deref()
                 .and_then(parse_color_hex)
-                .unwrap_or(Color {
-                    r: 0.333,
-                    g: 0.333,
-                    b: 0.333,
-                });
+                .unwrap_or(Color::rgb(0.333, 0.333, 0.333));
 
             if header_first.is_some() || header_each.is_some() || header_last.is_some() {
                 builder = builder.page_header(
@@ -9382,11 +9600,7 @@ impl PdfEngine {
         let footer_color = footer_color
             .as_deref()
             .and_then(parse_color_hex)
-            .unwrap_or(Color {
-                r: 0.333,
-                g: 0.333,
-                b: 0.333,
-            });
+            .unwrap_or(Color::rgb(0.333, 0.333, 0.333));
 
         if footer_first.is_some() || footer_each.is_some() || footer_last.is_some() {
             builder = builder.page_footer(
@@ -9478,6 +9692,15 @@ impl PdfEngine {
             };
             builder = builder.jit_mode(jit_mode);
         }
+        if let Some(profile) = enforce_audit_gates {
+            builder = builder.enforce_audit_gates(profile.trim());
+        }
+        if strict_fidelity {
+            builder = builder.strict_fidelity(true);
+        }
+        if parallel_sections {
+            builder = builder.parallel_sections(true);
+        }
         if debug {
             let path = debug_out.unwrap_or_else(|| "fullbleed_jit.log".to_string());
             builder = builder.debug_log(path);
@@ -9835,6 +10058,7 @@ impl PdfEngine {
             diagnostic_signals_from_py(diagnostic_signals.as_ref().map(|v| v.bind(py)))?;
         let ctx = PmrCoreContext {
             overflow_count,
+            layout_report: None,
             known_loss_count,
             pagination_summary: pagination_trace_summary.clone(),
             source_page_count,
@@ -9904,6 +10128,7 @@ impl PdfEngine {
             diagnostic_signals_from_py(diagnostic_signals.as_ref().map(|v| v.bind(py)))?;
         let ctx = PmrCoreContext {
             overflow_count,
+            layout_report: None,
             known_loss_count,
             pagination_summary: pagination_trace_summary.clone(),
             source_page_count,
@@ -10286,6 +10511,84 @@ impl PdfEngine {
         Ok(out.to_object(py))
     }
 
+    #[pyo3(signature = (html, css, profile="strict"))]
+    fn render_pdf_with_audit(
+        &self,
+        py: Python<'_>,
+        html: &str,
+        css: &str,
+        profile: &str,
+    ) -> PyResult<(Py<PyBytes>, PyObject)> {
+        let (bytes, report) = py
+            .allow_threads(|| self.engine.render_with_audit(html, css, profile))
+            .map_err(to_py_err)?;
+        let out = PyDict::new_bound(py);
+        out.set_item("profile", report.profile.clone())?;
+        let findings = PyList::empty_bound(py);
+        for finding in &report.findings {
+            findings.append(a11y_core_finding_to_py(py, finding)?)?;
+        }
+        out.set_item("findings", findings)?;
+        let gate = PyDict::new_bound(py);
+        gate.set_item("ok", report.gate.ok)?;
+        gate.set_item("error_count", report.gate.error_count)?;
+        gate.set_item("warn_count", report.gate.warn_count)?;
+        gate.set_item("failed_rule_ids", report.gate.failed_rule_ids.clone())?;
+        out.set_item("gate", gate)?;
+        let coverage = PyDict::new_bound(py);
+        coverage.set_item(
+            "wcag20aa",
+            wcag20aa_coverage_summary_to_py(py, &report.wcag20aa)?,
+        )?;
+        coverage.set_item(
+            "section508",
+            section508_html_coverage_summary_to_py(py, &report.section508)?,
+        )?;
+        out.set_item("coverage", coverage)?;
+        Ok((PyBytes::new_bound(py, &bytes).unbind(), out.to_object(py)))
+    }
+
+    #[pyo3(signature = (html, css, bundle_path, profile="strict"))]
+    fn render_pdf_with_artifact_bundle(
+        &self,
+        py: Python<'_>,
+        html: &str,
+        css: &str,
+        bundle_path: &str,
+        profile: &str,
+    ) -> PyResult<(Py<PyBytes>, PyObject)> {
+        let path = std::path::PathBuf::from(bundle_path);
+        let target = if bundle_path.to_ascii_lowercase().ends_with(".zip") {
+            crate::ArtifactBundleTarget::Zip(path)
+        } else {
+            crate::ArtifactBundleTarget::Directory(path)
+        };
+        let (bytes, manifest) = py
+            .allow_threads(|| {
+                self.engine
+                    .render_with_artifact_bundle(html, css, profile, &target)
+            })
+            .map_err(to_py_err)?;
+        let out = PyDict::new_bound(py);
+        out.set_item("bundle_path", bundle_path)?;
+        out.set_item("profile", manifest.profile.clone())?;
+        out.set_item(
+            "contract_fingerprint_sha256",
+            manifest.contract_fingerprint_sha256.clone(),
+        )?;
+        out.set_item("gate_ok", manifest.gate_ok)?;
+        let files = PyList::empty_bound(py);
+        for file in &manifest.files {
+            let d = PyDict::new_bound(py);
+            d.set_item("name", file.name.clone())?;
+            d.set_item("bytes", file.bytes)?;
+            d.set_item("sha256", file.sha256.clone())?;
+            files.append(d)?;
+        }
+        out.set_item("files", files)?;
+        Ok((PyBytes::new_bound(py, &bytes).unbind(), out.to_object(py)))
+    }
+
     #[pyo3(signature = (html, css))]
     fn render_pdf_with_glyph_report(
         &self,
@@ -10300,6 +10603,107 @@ impl PdfEngine {
         Ok((PyBytes::new_bound(py, &bytes).unbind(), report_obj))
     }
 
+    #[pyo3(signature = (html, css))]
+    fn render_pdf_with_warnings(
+        &self,
+        py: Python<'_>,
+        html: &str,
+        css: &str,
+    ) -> PyResult<(Py<PyBytes>, PyObject)> {
+        let (bytes, warnings) = py
+            .allow_threads(|| self.engine.render_with_warnings(html, css))
+            .map_err(to_py_err)?;
+        Ok((
+            PyBytes::new_bound(py, &bytes).unbind(),
+            warnings_to_py(py, &warnings)?,
+        ))
+    }
+
+    #[pyo3(signature = (html, css, top=10))]
+    fn render_pdf_with_layout_profile(
+        &self,
+        py: Python<'_>,
+        html: &str,
+        css: &str,
+        top: usize,
+    ) -> PyResult<(Py<PyBytes>, PyObject)> {
+        let (bytes, profile) = py
+            .allow_threads(|| self.engine.render_with_layout_profile(html, css))
+            .map_err(to_py_err)?;
+        Ok((
+            PyBytes::new_bound(py, &bytes).unbind(),
+            layout_profile_to_py(py, &profile, top)?,
+        ))
+    }
+
+    #[pyo3(signature = (html, css))]
+    fn render_pdf_with_report(
+        &self,
+        py: Python<'_>,
+        html: &str,
+        css: &str,
+    ) -> PyResult<(Py<PyBytes>, PyObject)> {
+        let (bytes, report) = py
+            .allow_threads(|| self.engine.render_with_report(html, css))
+            .map_err(to_py_err)?;
+        Ok((
+            PyBytes::new_bound(py, &bytes).unbind(),
+            render_report_to_py(py, &report)?,
+        ))
+    }
+
+    #[pyo3(signature = (css))]
+    fn css_diagnostics(&self, py: Python<'_>, css: &str) -> PyResult<PyObject> {
+        let diagnostics = py.allow_threads(|| self.engine.css_diagnostics(css));
+        css_diagnostics_to_py(py, &diagnostics)
+    }
+
+    #[pyo3(signature = (html, css))]
+    fn render_to_layout(&self, py: Python<'_>, html: &str, css: &str) -> PyResult<PyObject> {
+        let tree = py
+            .allow_threads(|| self.engine.render_to_layout(html, css))
+            .map_err(to_py_err)?;
+        let out = PyDict::new_bound(py);
+        out.set_item(
+            "page_size",
+            (
+                tree.page_size.width.to_f32(),
+                tree.page_size.height.to_f32(),
+            ),
+        )?;
+        out.set_item("page_count", tree.page_count)?;
+        let roots = PyList::empty_bound(py);
+        for node in &tree.roots {
+            roots.append(layout_box_to_py(py, node)?)?;
+        }
+        out.set_item("roots", roots)?;
+        Ok(out.to_object(py))
+    }
+
+    #[pyo3(signature = (html, css))]
+    fn render_pdf_with_layout_report(
+        &self,
+        py: Python<'_>,
+        html: &str,
+        css: &str,
+    ) -> PyResult<(Py<PyBytes>, PyObject)> {
+        let (bytes, report) = py
+            .allow_threads(|| self.engine.render_with_layout_report(html, css))
+            .map_err(to_py_err)?;
+        let list = PyList::empty_bound(py);
+        for overflow in report.overflows() {
+            let d = PyDict::new_bound(py);
+            d.set_item("page_number", overflow.page_number)?;
+            d.set_item("element_path", overflow.element_path.clone())?;
+            d.set_item("flowable", overflow.flowable.clone())?;
+            d.set_item("reason", overflow.reason.clone())?;
+            d.set_item("overflow_x", overflow.overflow_x.to_f32())?;
+            d.set_item("overflow_y", overflow.overflow_y.to_f32())?;
+            list.append(d)?;
+        }
+        Ok((PyBytes::new_bound(py, &bytes).unbind(), list.to_object(py)))
+    }
+
     #[pyo3(signature = (html, css))]
     fn render_pdf_with_glyph_report_and_render_time_reading_order_trace(
         &self,
@@ -10458,6 +10862,32 @@ impl PdfEngine {
         })
     }
 
+    // Per-record batch: returns an iterator that renders one record per step and yields a
+    // dict for it, so pipelines can ship each PDF as soon as it exists. With `out_dir` the PDF
+    // is written to `<out_dir>/<stem>_<index>.pdf` and the dict has `path` instead of `pdf`.
+    #[pyo3(signature = (html_list, css, out_dir=None, stem=None))]
+    fn render_pdf_batch_iter(
+        slf: PyRef<'_, Self>,
+        html_list: Vec<String>,
+        css: &str,
+        out_dir: Option<String>,
+        stem: Option<String>,
+    ) -> PyResult<PyBatchRecordIterator> {
+        if let Some(dir) = out_dir.as_deref() {
+            std::fs::create_dir_all(dir).map_err(|e| {
+                PyValueError::new_err(format!("failed to create batch output dir {dir:?}: {e}"))
+            })?;
+        }
+        let cursor = crate::RecordCursor::new(&slf.engine, css, html_list.len());
+        Ok(PyBatchRecordIterator {
+            engine: slf.into(),
+            html_list,
+            cursor,
+            out_dir: out_dir.map(PathBuf::from),
+            stem: stem.unwrap_or_else(|| "record".to_string()),
+        })
+    }
+
     #[pyo3(signature = (html_list, css, path, deterministic_hash=None))]
     fn render_pdf_batch_to_file_parallel(
         &self,
@@ -10481,6 +10911,66 @@ impl PdfEngine {
     }
 }
 
+// Iterator returned by `PdfEngine.render_pdf_batch_iter`. Each step releases the GIL while
+// the record renders. A record that fails raises `ValueError`; calling `next()` again moves
+// on to the following record.
+#[pyclass(name = "BatchRecordIterator")]
+struct PyBatchRecordIterator {
+    engine: Py<PdfEngine>,
+    html_list: Vec<String>,
+    cursor: crate::RecordCursor,
+    out_dir: Option<PathBuf>,
+    stem: String,
+}
+
+#[pymethods]
+impl PyBatchRecordIterator {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __len__(&self) -> usize {
+        self.html_list
+            .len()
+            .saturating_sub(self.cursor.next_index())
+    }
+
+    fn __next__(mut slf: PyRefMut<'_, Self>, py: Python<'_>) -> PyResult<Option<PyObject>> {
+        let this = &mut *slf;
+        let owner = this.engine.borrow(py);
+        let engine = &owner.engine;
+        let index = this.cursor.next_index();
+        let html_list = &this.html_list;
+        let cursor = &mut this.cursor;
+        let Some(record) = py.allow_threads(|| cursor.next(engine, html_list)) else {
+            return Ok(None);
+        };
+        let record =
+            record.map_err(|err| PyValueError::new_err(format!("record {index}: {err}")))?;
+
+        let d = PyDict::new_bound(py);
+        d.set_item("index", record.index)?;
+        d.set_item("page_count", record.page_count)?;
+        match this.out_dir.as_deref() {
+            Some(dir) => {
+                let path = dir.join(format!("{}_{}.pdf", this.stem, record.index));
+                std::fs::write(&path, &record.pdf).map_err(|e| {
+                    PyValueError::new_err(format!("record {index}: failed to write {path:?}: {e}"))
+                })?;
+                d.set_item("path", path.to_string_lossy().to_string())?;
+            }
+            None => d.set_item("pdf", PyBytes::new_bound(py, &record.pdf))?,
+        }
+        let page_data = match record.page_data.as_ref() {
+            Some(ctx) => page_data_context_to_py(py, ctx)?,
+            None => py.None(),
+        };
+        d.set_item("page_data", page_data)?;
+        d.set_item("warnings", warnings_to_py(py, &record.warnings)?)?;
+        Ok(Some(d.to_object(py)))
+    }
+}
+
 #[pymodule]
 fn _fullbleed(_py: Python<'_>, module: &Bound<'_, PyModule>) -> PyResult<()> {
     module.add_class::<PdfEngine>()?;
@@ -10488,7 +10978,9 @@ fn _fullbleed(_py: Python<'_>, module: &Bound<'_, PyModule>) -> PyResult<()> {
     module.add_class::<PyAsset>()?;
     module.add_class::<PyAssetBundle>()?;
     module.add_class::<PyWatermarkSpec>()?;
+    module.add_class::<PyBatchRecordIterator>()?;
     module.add_function(wrap_pyfunction!(inspect_pdf, module)?)?;
+    module.add_function(wrap_pyfunction!(preflight_pdf, module)?)?;
     module.add_function(wrap_pyfunction!(inspect_template_catalog, module)?)?;
     module.add_function(wrap_pyfunction!(vendored_asset, module)?)?;
     module.add_function(wrap_pyfunction!(fetch_asset, module)?)?;
@@ -10511,8 +11003,24 @@ fn _fullbleed(_py: Python<'_>, module: &Bound<'_, PyModule>) -> PyResult<()> {
     Ok(())
 }
 
+// ValueError carrying the stable `code` and, when known, `location` (a dict) as attributes.
 fn to_py_err(err: FullBleedError) -> PyErr {
-    PyValueError::new_err(err.to_string())
+    let py_err = PyValueError::new_err(err.to_string());
+    Python::with_gil(|py| {
+        let value = py_err.value_bound(py);
+        let _ = value.setattr("code", err.code().as_str());
+        let location = err.location().map(|location| {
+            let dict = PyDict::new_bound(py);
+            let _ = dict.set_item("element_path", location.element_path.as_deref());
+            let _ = dict.set_item("selector", location.selector.as_deref());
+            let _ = dict.set_item("byte_offset", location.byte_offset);
+            let _ = dict.set_item("line", location.line);
+            let _ = dict.set_item("column", location.column);
+            dict
+        });
+        let _ = value.setattr("location", location);
+    });
+    py_err
 }
 
 fn pdf_asset_inspect_err_to_py(err: crate::PdfInspectError) -> PyErr {
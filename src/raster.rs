@@ -1,7 +1,9 @@
 use crate::canvas::{Command, Document};
+use crate::command_buffer::CommandBuffer;
 use crate::error::FullBleedError;
 use crate::flowable::PaintFilterSpec;
 use crate::font::FontRegistry;
+use crate::pdf_raster;
 use crate::types::{Color, MixBlendMode, Pt, Shading, ShadingStop};
 use base64::Engine;
 use rustybuzz::{Direction as HbDirection, Face as HbFace, UnicodeBuffer};
@@ -29,7 +31,7 @@ struct RasterState {
     fill_opacity: f32,
     stroke_opacity: f32,
     blend_mode: MixBlendMode,
-    font_name: String,
+    font_name: Arc<str>,
     font_size: Pt,
     clip_mask: Option<Mask>,
 }
@@ -49,7 +51,7 @@ impl Default for RasterState {
             fill_opacity: 1.0,
             stroke_opacity: 1.0,
             blend_mode: MixBlendMode::Normal,
-            font_name: "Helvetica".to_string(),
+            font_name: Arc::from("Helvetica"),
             font_size: Pt::from_f32(12.0),
             clip_mask: None,
         }
@@ -60,7 +62,7 @@ impl Default for RasterState {
 struct FormDefinition {
     width: Pt,
     height: Pt,
-    commands: Vec<Command>,
+    commands: CommandBuffer,
 }
 
 pub(crate) fn document_to_png_pages(
@@ -69,19 +71,37 @@ pub(crate) fn document_to_png_pages(
     registry: Option<&FontRegistry>,
     shape_text: bool,
 ) -> Result<Vec<Vec<u8>>, FullBleedError> {
+    document_to_pixmaps(document, dpi, registry, shape_text)?
+        .iter()
+        .map(|pixmap| {
+            pixmap
+                .encode_png()
+                .map_err(|e| FullBleedError::Asset(format!("png encode failed: {e}")))
+        })
+        .collect()
+}
+
+pub(crate) fn document_to_pixmaps(
+    document: &Document,
+    dpi: u32,
+    registry: Option<&FontRegistry>,
+    shape_text: bool,
+) -> Result<Vec<Pixmap>, FullBleedError> {
     let dpi = if dpi == 0 { 150 } else { dpi };
-    let width_px = pt_milli_to_px_u32(document.page_size.width.to_milli_i64(), dpi)?;
-    let height_px = pt_milli_to_px_u32(document.page_size.height.to_milli_i64(), dpi)?;
-    let page_height_pt = document.page_size.height.to_f32();
-    let page_width_pt = document.page_size.width.to_f32();
     let scale = dpi as f32 / 72.0;
-    let base_transform = Transform::from_row(scale, 0.0, 0.0, -scale, 0.0, page_height_pt * scale);
-
-    let mut png_pages = Vec::with_capacity(document.pages.len());
-    let mut image_cache: HashMap<String, Option<Pixmap>> = HashMap::new();
-    let mut forms: HashMap<String, FormDefinition> = HashMap::new();
 
-    for page in &document.pages {
+    let mut pixmaps = Vec::with_capacity(document.pages.len());
+    let mut image_cache: HashMap<Arc<str>, Option<Pixmap>> = HashMap::new();
+    let mut forms: HashMap<Arc<str>, FormDefinition> = HashMap::new();
+
+    for (page_index, page) in document.pages.iter().enumerate() {
+        let page_size = document.page_size_at(page_index);
+        let width_px = pt_milli_to_px_u32(page_size.width.to_milli_i64(), dpi)?;
+        let height_px = pt_milli_to_px_u32(page_size.height.to_milli_i64(), dpi)?;
+        let page_height_pt = page_size.height.to_f32();
+        let page_width_pt = page_size.width.to_f32();
+        let base_transform =
+            Transform::from_row(scale, 0.0, 0.0, -scale, 0.0, page_height_pt * scale);
         let mut pixmap = Pixmap::new(width_px, height_px).ok_or_else(|| {
             FullBleedError::InvalidConfiguration(format!(
                 "invalid raster size {}x{} at {} DPI",
@@ -111,13 +131,10 @@ pub(crate) fn document_to_png_pages(
             shape_text,
         )?;
 
-        let png = pixmap
-            .encode_png()
-            .map_err(|e| FullBleedError::Asset(format!("png encode failed: {e}")))?;
-        png_pages.push(png);
+        pixmaps.push(pixmap);
     }
 
-    Ok(png_pages)
+    Ok(pixmaps)
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -125,19 +142,19 @@ fn render_commands(
     pixmap: &mut Pixmap,
     page_height_pt: f32,
     page_width_pt: f32,
-    commands: &[Command],
+    commands: &CommandBuffer,
     base_transform: Transform,
     state: &mut RasterState,
     stack: &mut Vec<RasterState>,
     path_builder: &mut PathBuilder,
     has_path: &mut bool,
-    forms: &mut HashMap<String, FormDefinition>,
-    image_cache: &mut HashMap<String, Option<Pixmap>>,
+    forms: &mut HashMap<Arc<str>, FormDefinition>,
+    image_cache: &mut HashMap<Arc<str>, Option<Pixmap>>,
     registry: Option<&FontRegistry>,
     shape_text: bool,
 ) -> Result<(), FullBleedError> {
     for cmd in commands {
-        match cmd {
+        match &*cmd {
             Command::SaveState => stack.push(state.clone()),
             Command::RestoreState => {
                 if let Some(restored) = stack.pop() {
@@ -172,6 +189,10 @@ fn render_commands(
             Command::BeginArtifact { .. } => {}
             Command::BeginOptionalContent { .. } => {}
             Command::EndMarkedContent => {}
+            Command::LinkAnnotation { .. }
+            | Command::NamedDestination { .. }
+            | Command::Bookmark { .. }
+            | Command::SignatureField { .. } => {}
             Command::SetFillColor(color) => state.fill_color = *color,
             Command::SetStrokeColor(color) => state.stroke_color = *color,
             Command::SetLineWidth(width) => {
@@ -1030,7 +1051,7 @@ fn draw_string(
 
     if let Some(registry) = registry {
         if let Some(font) = registry.resolve(&state.font_name) {
-            match try_draw(font.data.as_slice(), false) {
+            match try_draw(&font.data[..], false) {
                 Ok(()) => return,
                 Err(reason) => {
                     if let Some(system_bytes) = resolve_system_font_bytes(&state.font_name) {
@@ -1141,7 +1162,7 @@ fn draw_string_transformed(
 
     if let Some(registry) = registry {
         if let Some(font) = registry.resolve(&state.font_name) {
-            if try_draw(font.data.as_slice()).is_ok() {
+            if try_draw(&font.data[..]).is_ok() {
                 return;
             }
         }
@@ -1264,7 +1285,7 @@ fn draw_glyph_run(
 
     if let Some(registry) = registry {
         if let Some(font) = registry.resolve(&state.font_name) {
-            match try_draw(font.data.as_slice(), false) {
+            match try_draw(&font.data[..], false) {
                 Ok(()) => return,
                 Err(_reason) => {
                     if let Some(system_bytes) = resolve_system_font_bytes(&state.font_name) {
@@ -2204,6 +2225,10 @@ fn decode_image_to_pixmap(data: &[u8], mime: Option<&str>) -> Option<Pixmap> {
             Some(image::ImageFormat::Png)
         } else if mime.contains("jpeg") || mime.contains("jpg") {
             Some(image::ImageFormat::Jpeg)
+        } else if mime.contains("webp") {
+            Some(image::ImageFormat::WebP)
+        } else if mime.contains("avif") {
+            Some(image::ImageFormat::Avif)
         } else {
             None
         }
@@ -2261,6 +2286,169 @@ fn parse_data_uri(uri: &str) -> Option<(String, Vec<u8>)> {
     Some((mime, data))
 }
 
+// Largest possible YIQ delta between two colors.
+const MAX_YIQ_DELTA: f32 = 35215.0;
+
+// Per-page result of [`compare_pdfs`].
+#[derive(Debug, Clone)]
+pub struct PageComparison {
+    // 1-based page number.
+    pub page_number: usize,
+    pub width: u32,
+    pub height: u32,
+    // Pixels whose perceptual difference exceeds the threshold.
+    pub differing_pixels: u64,
+    // `differing_pixels` as a share of the page, from 0.0 to 1.0.
+    pub pixel_score: f64,
+    // Mean perceptual (YIQ) difference over the page, from 0.0 (identical) to 1.0.
+    pub perceptual_score: f64,
+    // PNG of the first page faded to gray with differing pixels in red; `None` when the
+    // page exists in only one of the PDFs.
+    pub heatmap_png: Option<Vec<u8>>,
+}
+
+// Result of [`compare_pdfs`], one entry per page of the longer PDF.
+#[derive(Debug, Clone, Default)]
+pub struct PdfComparison {
+    pub pages: Vec<PageComparison>,
+}
+
+impl PdfComparison {
+    // True when no page has a differing pixel.
+    pub fn is_match(&self) -> bool {
+        self.pages.iter().all(|page| page.differing_pixels == 0)
+    }
+
+    // The highest `pixel_score` of any page; the usual value to gate CI on.
+    pub fn max_pixel_score(&self) -> f64 {
+        self.pages
+            .iter()
+            .map(|page| page.pixel_score)
+            .fold(0.0, f64::max)
+    }
+
+    // 1-based numbers of the pages with differing pixels.
+    pub fn differing_pages(&self) -> Vec<usize> {
+        self.pages
+            .iter()
+            .filter(|page| page.differing_pixels > 0)
+            .map(|page| page.page_number)
+            .collect()
+    }
+}
+
+// Rasterizes two PDFs at `dpi` and compares them page by page. `threshold` (0.0 to 1.0) is
+// the perceptual difference a pixel may have before it counts as changed, on the same
+// scale as pixelmatch: 0.0 flags any change, 0.1 ignores antialiasing noise. Fonts
+// embedded in the PDFs are used for text.
+pub fn compare_pdfs(
+    a: &[u8],
+    b: &[u8],
+    dpi: u32,
+    threshold: f32,
+) -> Result<PdfComparison, FullBleedError> {
+    let first = pdf_raster::pdf_bytes_to_pixmaps(a, dpi, None, true)?;
+    let second = pdf_raster::pdf_bytes_to_pixmaps(b, dpi, None, true)?;
+    let threshold = threshold.clamp(0.0, 1.0);
+    let limit = threshold * threshold * MAX_YIQ_DELTA;
+    let mut pages = Vec::with_capacity(first.len().max(second.len()));
+    for index in 0..first.len().max(second.len()) {
+        let page = match (first.get(index), second.get(index)) {
+            (Some(old), Some(new)) => compare_page(index + 1, old, new, limit)?,
+            (Some(only), None) | (None, Some(only)) => PageComparison {
+                page_number: index + 1,
+                width: only.width(),
+                height: only.height(),
+                differing_pixels: only.width() as u64 * only.height() as u64,
+                pixel_score: 1.0,
+                perceptual_score: 1.0,
+                heatmap_png: None,
+            },
+            (None, None) => unreachable!("index is below the longer page count"),
+        };
+        pages.push(page);
+    }
+    Ok(PdfComparison { pages })
+}
+
+// Pixels outside the overlap of differently sized pages count as fully different.
+fn compare_page(
+    page_number: usize,
+    old: &Pixmap,
+    new: &Pixmap,
+    limit: f32,
+) -> Result<PageComparison, FullBleedError> {
+    let width = old.width().max(new.width());
+    let height = old.height().max(new.height());
+    let mut heatmap = Pixmap::new(width, height).ok_or_else(|| {
+        FullBleedError::InvalidConfiguration(format!("invalid heatmap size {width}x{height}"))
+    })?;
+    let mut differing_pixels = 0u64;
+    let mut delta_sum = 0f64;
+    for y in 0..height {
+        for x in 0..width {
+            let (delta, base) = match (old.pixel(x, y), new.pixel(x, y)) {
+                (Some(a), Some(b)) => {
+                    let a = on_white(a);
+                    let b = on_white(b);
+                    (yiq_delta(a, b), yiq_luma(a))
+                }
+                (Some(a), None) => (MAX_YIQ_DELTA, yiq_luma(on_white(a))),
+                _ => (MAX_YIQ_DELTA, 255.0),
+            };
+            delta_sum += (delta / MAX_YIQ_DELTA) as f64;
+            let color = if delta > limit {
+                differing_pixels += 1;
+                tiny_skia::PremultipliedColorU8::from_rgba(255, 0, 0, 255)
+            } else {
+                // Unchanged content stays visible as a faint gray for orientation.
+                let gray = (255.0 - (255.0 - base) * 0.1) as u8;
+                tiny_skia::PremultipliedColorU8::from_rgba(gray, gray, gray, 255)
+            };
+            if let Some(color) = color {
+                heatmap.pixels_mut()[(y * width + x) as usize] = color;
+            }
+        }
+    }
+    let total = (width as u64 * height as u64).max(1);
+    let heatmap_png = heatmap
+        .encode_png()
+        .map_err(|e| FullBleedError::Asset(format!("png encode failed: {e}")))?;
+    Ok(PageComparison {
+        page_number,
+        width,
+        height,
+        differing_pixels,
+        pixel_score: differing_pixels as f64 / total as f64,
+        perceptual_score: delta_sum / total as f64,
+        heatmap_png: Some(heatmap_png),
+    })
+}
+
+// Composites a premultiplied pixel over the white page background.
+fn on_white(pixel: tiny_skia::PremultipliedColorU8) -> [f32; 3] {
+    let backdrop = 255.0 - pixel.alpha() as f32;
+    [
+        pixel.red() as f32 + backdrop,
+        pixel.green() as f32 + backdrop,
+        pixel.blue() as f32 + backdrop,
+    ]
+}
+
+fn yiq_luma([r, g, b]: [f32; 3]) -> f32 {
+    r * 0.298_895_3 + g * 0.586_622_5 + b * 0.114_482_23
+}
+
+// Squared YIQ distance weighted for perceived difference (Kotsarenko and Ramos, 2010).
+fn yiq_delta(a: [f32; 3], b: [f32; 3]) -> f32 {
+    let y = yiq_luma(a) - yiq_luma(b);
+    let i = (a[0] * 0.595_977_99 - a[1] * 0.274_176_1 - a[2] * 0.321_801_9)
+        - (b[0] * 0.595_977_99 - b[1] * 0.274_176_1 - b[2] * 0.321_801_9);
+    let q = (a[0] * 0.211_470_17 - a[1] * 0.522_617_1 + a[2] * 0.311_146_94)
+        - (b[0] * 0.211_470_17 - b[1] * 0.522_617_1 + b[2] * 0.311_146_94);
+    0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -2313,14 +2501,17 @@ mod tests {
             pages: vec![crate::canvas::Page {
                 commands: vec![
                     Command::SetFillColor(Color::BLACK),
-                    Command::SetFontName("Helvetica-Bold".to_string()),
+                    Command::SetFontName("Helvetica-Bold".into()),
                     Command::SetFontSize(Pt::from_f32(24.0)),
                     Command::DrawString {
                         x: Pt::from_f32(72.0),
                         y: Pt::from_f32(72.0),
                         text: "Hello".to_string(),
                     },
-                ],
+                ]
+                .into(),
+                size: None,
+                rotate: 0,
             }],
         };
         let pngs = document_to_png_pages(&doc, 150, None, true).unwrap();
@@ -2412,10 +2603,12 @@ mod tests {
                         y: Pt::from_f32(20.0),
                         width: Pt::from_f32(40.0),
                         height: Pt::from_f32(30.0),
-                        resource_id: "missing-image-for-raster-parity-should-not-exist.png"
-                            .to_string(),
+                        resource_id: "missing-image-for-raster-parity-should-not-exist.png".into(),
                     },
-                ],
+                ]
+                .into(),
+                size: None,
+                rotate: 0,
             }],
         };
 
@@ -2448,7 +2641,7 @@ mod tests {
             pages: vec![crate::canvas::Page {
                 commands: vec![
                     Command::DefineForm {
-                        resource_id: "test-form-img".to_string(),
+                        resource_id: "test-form-img".into(),
                         width: Pt::from_f32(120.0),
                         height: Pt::from_f32(48.0),
                         commands: form_commands,
@@ -2458,9 +2651,12 @@ mod tests {
                         y: Pt::from_f32(72.0),
                         width: Pt::from_f32(120.0),
                         height: Pt::from_f32(48.0),
-                        resource_id: "test-form-img".to_string(),
+                        resource_id: "test-form-img".into(),
                     },
-                ],
+                ]
+                .into(),
+                size: None,
+                rotate: 0,
             }],
         };
 
@@ -2500,8 +2696,11 @@ mod tests {
                     y: Pt::from_f32(10.0),
                     width: Pt::from_f32(20.0),
                     height: Pt::from_f32(20.0),
-                    resource_id: data_uri,
-                }],
+                    resource_id: data_uri.into(),
+                }]
+                .into(),
+                size: None,
+                rotate: 0,
             }],
         };
 
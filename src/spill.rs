@@ -1,35 +1,149 @@
-use crate::canvas::{Command, Document, Page};
+use crate::canvas::{Command, Document, LinkTarget, Page, StringTable};
+use crate::command_buffer::CommandBuffer;
 use crate::flowable::PaintFilterSpec;
-use crate::types::{Color, MixBlendMode, Pt, Shading, ShadingStop, Size};
+use crate::jit::{DocPlan, Layer, PagePlan, Paintable, PlacedItem, Transform};
+use crate::page_data::{PageDataContext, PageDataValue};
+use crate::types::{Color, MixBlendMode, ProcessColor, Pt, Rect, Shading, ShadingStop, Size};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs::{self, File};
 use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 
+// Compression of spill files and serialized plans. Independent of the PDF's Flate streams:
+// both hold drawing commands, not PDF bytes, and are read back by the engine.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SpillCodec {
+    // Written as encoded; the fastest to spill and read back.
+    #[default]
+    None,
+    // Zstandard at `level` (1..=22; zstd's default is 3). Needs the `zstd` feature.
+    #[cfg(feature = "zstd")]
+    Zstd { level: i32 },
+}
+
+// Leading bytes of a zstd frame, which is how a compressed plan starts.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+impl SpillCodec {
+    // Runs `write` with a writer that encodes into `out`.
+    fn encode<W: Write>(
+        self,
+        mut out: W,
+        write: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+    ) -> io::Result<W> {
+        match self {
+            SpillCodec::None => {
+                write(&mut out)?;
+                Ok(out)
+            }
+            #[cfg(feature = "zstd")]
+            SpillCodec::Zstd { level } => {
+                let mut encoder = zstd::Encoder::new(out, level)?;
+                write(&mut encoder)?;
+                encoder.finish()
+            }
+        }
+    }
+
+    // Runs `read` on `input` decoded with the codec `zstd` says it was written with.
+    fn decode<R: io::BufRead, T>(
+        input: R,
+        zstd: bool,
+        read: impl FnOnce(&mut dyn Read) -> io::Result<T>,
+    ) -> io::Result<T> {
+        if !zstd {
+            let mut input = input;
+            return read(&mut input);
+        }
+        #[cfg(feature = "zstd")]
+        {
+            read(&mut zstd::Decoder::with_buffer(input)?)
+        }
+        #[cfg(not(feature = "zstd"))]
+        {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "zstd-compressed data needs the `zstd` feature",
+            ))
+        }
+    }
+
+    fn tag(self) -> u8 {
+        match self {
+            SpillCodec::None => 0,
+            #[cfg(feature = "zstd")]
+            SpillCodec::Zstd { .. } => 1,
+        }
+    }
+}
+
+// Stores opened by this process so far; part of each store's directory name.
+static STORE_SEQ: AtomicU64 = AtomicU64::new(0);
+
+// Inline images with a data URI at least this long are worth a file of their own.
+const SPILL_IMAGE_MIN_BYTES: usize = 64 * 1024;
+
+// Spill files live in a directory of their own per store, so concurrent batches of one engine
+// and other processes sharing the spill dir never open each other's files. The directory and
+// whatever is still in it are removed when the store is dropped.
 pub struct SpillStore {
     dir: PathBuf,
+    codec: SpillCodec,
     counter: AtomicU64,
     files: AtomicU64,
     bytes: AtomicU64,
+    // Spilled image files by the SHA-256 of their data URI, so every document that inlines
+    // the same image points at one file.
+    images: Mutex<HashMap<[u8; 32], Arc<str>>>,
 }
 
 impl SpillStore {
     pub fn new(dir: impl AsRef<Path>) -> io::Result<Self> {
-        let dir = dir.as_ref().to_path_buf();
-        fs::create_dir_all(&dir)?;
+        fs::create_dir_all(dir.as_ref())?;
+        let dir = loop {
+            let mut random = [0u8; 8];
+            getrandom::getrandom(&mut random).map_err(|err| io::Error::other(err.to_string()))?;
+            let candidate = dir.as_ref().join(format!(
+                "fullbleed_spill_{}_{}_{}",
+                std::process::id(),
+                STORE_SEQ.fetch_add(1, Ordering::Relaxed),
+                crate::encrypt::hex(&random)
+            ));
+            match fs::create_dir(&candidate) {
+                Ok(()) => break candidate,
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err),
+            }
+        };
         Ok(Self {
             dir,
+            codec: SpillCodec::None,
             counter: AtomicU64::new(0),
             files: AtomicU64::new(0),
             bytes: AtomicU64::new(0),
+            images: Mutex::new(HashMap::new()),
         })
     }
 
+    pub fn with_codec(mut self, codec: SpillCodec) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    // Spill files start with a codec tag byte, then the document as the codec wrote it.
     pub fn spill(&self, doc: &Document) -> io::Result<PathBuf> {
         let id = self.counter.fetch_add(1, Ordering::Relaxed);
         let path = self.dir.join(format!("fullbleed_spill_{id}.bin"));
-        let mut file = File::create(&path)?;
-        write_document(&mut file, doc)?;
+        let file = File::options().write(true).create_new(true).open(&path)?;
+        let mut out = io::BufWriter::new(file);
+        write_u8(&mut out, self.codec.tag())?;
+        let out = self
+            .codec
+            .encode(out, |mut out| write_document(&mut out, doc))?;
+        let file = out.into_inner().map_err(|err| err.into_error())?;
         let size = file.metadata().map(|m| m.len()).unwrap_or(0);
         self.files.fetch_add(1, Ordering::Relaxed);
         self.bytes.fetch_add(size, Ordering::Relaxed);
@@ -37,8 +151,13 @@ impl SpillStore {
     }
 
     pub fn load(&self, path: &Path) -> io::Result<Document> {
-        let mut file = File::open(path)?;
-        let doc = read_document(&mut file)?;
+        let mut input = io::BufReader::new(File::open(path)?);
+        let zstd = match read_u8(&mut input)? {
+            0 => false,
+            1 => true,
+            tag => return Err(invalid_tag("spill codec", tag)),
+        };
+        let doc = SpillCodec::decode(input, zstd, |mut input| read_document(&mut input))?;
         fs::remove_file(path)?;
         Ok(doc)
     }
@@ -49,6 +168,216 @@ impl SpillStore {
             self.bytes.load(Ordering::Relaxed),
         )
     }
+
+    // Image files written so far; their bytes are part of `metrics`.
+    pub(crate) fn images_spilled(&self) -> u64 {
+        self.images
+            .lock()
+            .map(|images| images.len() as u64)
+            .unwrap_or(0)
+    }
+
+    // Moves the large inline raster images of `doc` into files of the store and points its
+    // image commands at them; the PDF writer reads image paths like data URIs. The files stay
+    // until the store is dropped.
+    pub(crate) fn spill_images(&self, doc: &mut Document) -> io::Result<()> {
+        for page in &mut doc.pages {
+            self.spill_command_images(&mut page.commands)?;
+        }
+        Ok(())
+    }
+
+    fn spill_command_images(&self, commands: &mut CommandBuffer) -> io::Result<()> {
+        // Images and forms are never packed, so they are all among the owned commands.
+        for command in commands.owned_mut() {
+            match command {
+                Command::DrawImage { resource_id, .. } => {
+                    if let Some(path) = self.spill_image(resource_id)? {
+                        *resource_id = path;
+                    }
+                }
+                Command::DefineForm { commands, .. } => self.spill_command_images(commands)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn spill_image(&self, source: &str) -> io::Result<Option<Arc<str>>> {
+        if source.len() < SPILL_IMAGE_MIN_BYTES {
+            return Ok(None);
+        }
+        let Some((mime, data)) = crate::assets::parse_data_uri_bytes(source) else {
+            return Ok(None);
+        };
+        if !mime.starts_with("image/") || mime.contains("svg") {
+            return Ok(None);
+        }
+        let key: [u8; 32] = Sha256::digest(source.as_bytes()).into();
+        let mut images = self
+            .images
+            .lock()
+            .map_err(|_| io::Error::other("spill image table poisoned"))?;
+        if let Some(path) = images.get(&key) {
+            return Ok(Some(path.clone()));
+        }
+        let id = self.counter.fetch_add(1, Ordering::Relaxed);
+        let path = self.dir.join(format!("fullbleed_image_{id}.bin"));
+        let Some(name) = path.to_str().map(Arc::<str>::from) else {
+            return Ok(None);
+        };
+        let mut file = File::options().write(true).create_new(true).open(&path)?;
+        file.write_all(&data)?;
+        self.bytes.fetch_add(data.len() as u64, Ordering::Relaxed);
+        images.insert(key, name.clone());
+        Ok(Some(name))
+    }
+}
+
+impl Drop for SpillStore {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+// Caps the memory batch renders spend on finished documents waiting to be written. Past the
+// budget, documents go to disk and are read back when their turn comes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryBudget {
+    max_bytes: usize,
+    spill_dir: Option<PathBuf>,
+}
+
+impl MemoryBudget {
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            spill_dir: None,
+        }
+    }
+
+    // Where spilled documents go; defaults to `fullbleed_spill` in the system temp dir.
+    pub fn spill_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.spill_dir = Some(dir.into());
+        self
+    }
+
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    pub(crate) fn spill_dir_or_default(&self) -> PathBuf {
+        self.spill_dir
+            .clone()
+            .unwrap_or_else(|| std::env::temp_dir().join("fullbleed_spill"))
+    }
+}
+
+// Spill volume of an engine's batch renders so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SpillMetrics {
+    // Documents written to disk.
+    pub documents: u64,
+    // Inline images written to disk on their own (see `DocumentQueue`).
+    pub images: u64,
+    // Bytes written to disk.
+    pub bytes: u64,
+    // Largest estimated size of the documents held in memory at once by one batch.
+    pub peak_resident_bytes: u64,
+}
+
+enum PendingDoc {
+    InMemory(Document, usize),
+    Spilled(PathBuf),
+}
+
+// Finished documents waiting for their turn to be written, by input index. A document that
+// would take the resident total past `max_bytes`, or the queue past `max_docs` entries, first
+// has its large inline images moved to the spill store; if it still does not fit, the whole
+// document goes there.
+pub(crate) struct DocumentQueue<'a> {
+    store: Option<&'a SpillStore>,
+    max_bytes: usize,
+    max_docs: usize,
+    pending: BTreeMap<usize, PendingDoc>,
+    // Index the writer takes next; that document is never spilled.
+    next_take: usize,
+    resident_bytes: usize,
+    peak_resident_bytes: usize,
+}
+
+impl<'a> DocumentQueue<'a> {
+    pub(crate) fn new(store: Option<&'a SpillStore>, max_bytes: usize, max_docs: usize) -> Self {
+        Self {
+            store,
+            max_bytes,
+            max_docs,
+            pending: BTreeMap::new(),
+            next_take: 0,
+            resident_bytes: 0,
+            peak_resident_bytes: 0,
+        }
+    }
+
+    pub(crate) fn push(&mut self, idx: usize, mut doc: Document) -> io::Result<()> {
+        doc.shrink_to_fit();
+        let mut size = doc.memory_footprint();
+        // The writer takes the next document right away; spilling it would only add a disk
+        // round trip.
+        let over_budget = |size: usize| {
+            idx != self.next_take
+                && (self.resident_bytes.saturating_add(size) > self.max_bytes
+                    || self.pending.len() >= self.max_docs)
+        };
+        if let Some(store) = self.store.filter(|_| over_budget(size)) {
+            store.spill_images(&mut doc)?;
+            size = doc.memory_footprint();
+        }
+        let entry = match self.store {
+            Some(store) if over_budget(size) => PendingDoc::Spilled(store.spill(&doc)?),
+            _ => {
+                self.resident_bytes += size;
+                self.peak_resident_bytes = self.peak_resident_bytes.max(self.resident_bytes);
+                PendingDoc::InMemory(doc, size)
+            }
+        };
+        self.pending.insert(idx, entry);
+        Ok(())
+    }
+
+    pub(crate) fn take(&mut self, idx: usize) -> Option<io::Result<Document>> {
+        let entry = self.pending.remove(&idx)?;
+        self.next_take = idx + 1;
+        match entry {
+            PendingDoc::InMemory(doc, size) => {
+                self.resident_bytes -= size;
+                Some(Ok(doc))
+            }
+            PendingDoc::Spilled(path) => {
+                let store = self.store.expect("spilled documents come from the store");
+                Some(store.load(&path))
+            }
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub(crate) fn peak_resident_bytes(&self) -> usize {
+        self.peak_resident_bytes
+    }
+}
+
+// A batch that fails part way leaves documents in the queue; their spill files go with it.
+impl Drop for DocumentQueue<'_> {
+    fn drop(&mut self) {
+        for entry in self.pending.values() {
+            if let PendingDoc::Spilled(path) = entry {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
 }
 
 fn write_document<W: Write>(out: &mut W, doc: &Document) -> io::Result<()> {
@@ -64,27 +393,356 @@ fn read_document<R: Read>(input: &mut R) -> io::Result<Document> {
     let page_size = read_size(input)?;
     let pages_len = read_u32(input)? as usize;
     let mut pages = Vec::with_capacity(pages_len);
+    // Reloaded pages share font names and resource ids again, as they did when spilled.
+    let mut strings = StringTable::default();
     for _ in 0..pages_len {
-        pages.push(read_page(input)?);
+        pages.push(read_page(input, &mut strings)?);
     }
     Ok(Document { page_size, pages })
 }
 
+// Persisted plans outlive the process that wrote them, so unlike spill files they carry a
+// format tag; bump the last byte whenever the layout below or a command encoding changes.
+const PLAN_MAGIC: &[u8; 8] = b"FBPLAN\0\x01";
+
+// A plan compressed with `codec`: a zstd frame around the plan encoding, or the encoding itself.
+pub(crate) fn encode_doc_plan(plan: &DocPlan, codec: SpillCodec) -> Vec<u8> {
+    codec
+        .encode(Vec::new(), |mut out| write_doc_plan(&mut out, plan))
+        .expect("writing to a Vec cannot fail")
+}
+
+pub(crate) fn decode_doc_plan(bytes: &[u8]) -> io::Result<DocPlan> {
+    let zstd = bytes.starts_with(&ZSTD_MAGIC);
+    SpillCodec::decode(bytes, zstd, |mut input| {
+        let plan = read_doc_plan(&mut input)?;
+        let mut rest = [0u8; 1];
+        if input.read(&mut rest)? != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "trailing bytes after plan",
+            ));
+        }
+        Ok(plan)
+    })
+}
+
+pub(crate) fn write_doc_plan<W: Write>(out: &mut W, plan: &DocPlan) -> io::Result<()> {
+    out.write_all(PLAN_MAGIC)?;
+    write_u32(out, plan.doc_id as u32)?;
+    write_size(out, plan.page_size)?;
+    write_u32(out, plan.page_count as u32)?;
+    write_u32(out, plan.paintables.len() as u32)?;
+    for paintable in &plan.paintables {
+        match paintable {
+            Paintable::PageCommands { commands } => {
+                write_u8(out, 1)?;
+                write_u32(out, commands.len() as u32)?;
+                for command in commands {
+                    write_command(out, &command)?;
+                }
+            }
+        }
+    }
+    write_u32(out, plan.pages.len() as u32)?;
+    for page in &plan.pages {
+        write_page_plan(out, page)?;
+    }
+    write_bool(out, plan.page_data.is_some())?;
+    if let Some(page_data) = &plan.page_data {
+        write_page_data_context(out, page_data)?;
+    }
+    let mut fonts: Vec<_> = plan.font_use.iter().collect();
+    fonts.sort_by(|a, b| a.0.cmp(b.0));
+    write_u32(out, fonts.len() as u32)?;
+    for (font, glyphs) in fonts {
+        write_string(out, font)?;
+        write_u32(out, glyphs.len() as u32)?;
+        for glyph in glyphs {
+            write_u32(out, *glyph)?;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn read_doc_plan<R: Read>(input: &mut R) -> io::Result<DocPlan> {
+    let mut magic = [0u8; 8];
+    input.read_exact(&mut magic)?;
+    if &magic != PLAN_MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a fullbleed plan or unsupported plan version",
+        ));
+    }
+    let doc_id = read_u32(input)? as usize;
+    let page_size = read_size(input)?;
+    let page_count = read_u32(input)? as usize;
+    let paintables_len = read_u32(input)? as usize;
+    let mut paintables = Vec::with_capacity(paintables_len);
+    let mut strings = StringTable::default();
+    for _ in 0..paintables_len {
+        match read_u8(input)? {
+            1 => {
+                let len = read_u32(input)? as usize;
+                let mut commands = CommandBuffer::new();
+                for _ in 0..len {
+                    commands.push(read_command(input, &mut strings)?);
+                }
+                paintables.push(Paintable::PageCommands { commands });
+            }
+            tag => return Err(invalid_tag("paintable", tag)),
+        }
+    }
+    let pages_len = read_u32(input)? as usize;
+    let mut pages = Vec::with_capacity(pages_len);
+    for _ in 0..pages_len {
+        let page = read_page_plan(input)?;
+        if page
+            .placements
+            .iter()
+            .any(|placement| placement.paintable_id >= paintables.len())
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "plan placement references a missing paintable",
+            ));
+        }
+        pages.push(page);
+    }
+    let page_data = if read_bool(input)? {
+        Some(read_page_data_context(input)?)
+    } else {
+        None
+    };
+    let fonts_len = read_u32(input)? as usize;
+    let mut font_use = HashMap::with_capacity(fonts_len);
+    for _ in 0..fonts_len {
+        let font = read_string(input)?;
+        let glyphs_len = read_u32(input)? as usize;
+        let mut glyphs = BTreeSet::new();
+        for _ in 0..glyphs_len {
+            glyphs.insert(read_u32(input)?);
+        }
+        font_use.insert(font, glyphs);
+    }
+    Ok(DocPlan {
+        doc_id,
+        page_size,
+        page_count,
+        pages,
+        page_data,
+        font_use,
+        paintables,
+    })
+}
+
+fn write_page_plan<W: Write>(out: &mut W, page: &PagePlan) -> io::Result<()> {
+    write_u32(out, page.page_number as u32)?;
+    write_u32(out, page.page_count as u32)?;
+    write_option_size(out, page.size)?;
+    write_u16(out, page.rotate)?;
+    write_bool(out, page.page_data.is_some())?;
+    if let Some(values) = &page.page_data {
+        write_page_data_values(out, values)?;
+    }
+    write_u32(out, page.placements.len() as u32)?;
+    for placement in &page.placements {
+        write_u32(out, placement.paintable_id as u32)?;
+        write_u8(
+            out,
+            match placement.layer {
+                Layer::Background => 0,
+                Layer::Content => 1,
+                Layer::Overlay => 2,
+            },
+        )?;
+        write_bool(out, placement.bbox.is_some())?;
+        if let Some(bbox) = placement.bbox {
+            write_pt(out, bbox.x)?;
+            write_pt(out, bbox.y)?;
+            write_pt(out, bbox.width)?;
+            write_pt(out, bbox.height)?;
+        }
+        write_bool(out, placement.transform.is_some())?;
+        if let Some(t) = placement.transform {
+            for value in [t.a, t.b, t.c, t.d, t.e, t.f] {
+                write_f32(out, value)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn read_page_plan<R: Read>(input: &mut R) -> io::Result<PagePlan> {
+    let page_number = read_u32(input)? as usize;
+    let page_count = read_u32(input)? as usize;
+    let size = read_option_size(input)?;
+    let rotate = read_u16(input)?;
+    let page_data = if read_bool(input)? {
+        Some(read_page_data_values(input)?)
+    } else {
+        None
+    };
+    let len = read_u32(input)? as usize;
+    let mut placements = Vec::with_capacity(len);
+    for _ in 0..len {
+        let paintable_id = read_u32(input)? as usize;
+        let layer = match read_u8(input)? {
+            0 => Layer::Background,
+            1 => Layer::Content,
+            2 => Layer::Overlay,
+            tag => return Err(invalid_tag("layer", tag)),
+        };
+        let bbox = if read_bool(input)? {
+            Some(Rect {
+                x: read_pt(input)?,
+                y: read_pt(input)?,
+                width: read_pt(input)?,
+                height: read_pt(input)?,
+            })
+        } else {
+            None
+        };
+        let transform = if read_bool(input)? {
+            Some(Transform {
+                a: read_f32(input)?,
+                b: read_f32(input)?,
+                c: read_f32(input)?,
+                d: read_f32(input)?,
+                e: read_f32(input)?,
+                f: read_f32(input)?,
+            })
+        } else {
+            None
+        };
+        placements.push(PlacedItem {
+            paintable_id,
+            layer,
+            bbox,
+            transform,
+        });
+    }
+    Ok(PagePlan {
+        page_number,
+        page_count,
+        page_data,
+        placements,
+        size,
+        rotate,
+    })
+}
+
+fn write_page_data_context<W: Write>(out: &mut W, context: &PageDataContext) -> io::Result<()> {
+    write_u32(out, context.page_count as u32)?;
+    write_u32(out, context.pages.len() as u32)?;
+    for values in &context.pages {
+        write_page_data_values(out, values)?;
+    }
+    write_page_data_values(out, &context.totals)
+}
+
+fn read_page_data_context<R: Read>(input: &mut R) -> io::Result<PageDataContext> {
+    let page_count = read_u32(input)? as usize;
+    let len = read_u32(input)? as usize;
+    let mut pages = Vec::with_capacity(len);
+    for _ in 0..len {
+        pages.push(read_page_data_values(input)?);
+    }
+    let totals = read_page_data_values(input)?;
+    Ok(PageDataContext {
+        page_count,
+        pages,
+        totals,
+    })
+}
+
+fn write_page_data_values<W: Write>(
+    out: &mut W,
+    values: &HashMap<String, PageDataValue>,
+) -> io::Result<()> {
+    let mut entries: Vec<_> = values.iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    write_u32(out, entries.len() as u32)?;
+    for (key, value) in entries {
+        write_string(out, key)?;
+        match value {
+            PageDataValue::Every(items) => {
+                write_u8(out, 1)?;
+                write_u32(out, items.len() as u32)?;
+                for item in items {
+                    write_string(out, item)?;
+                }
+            }
+            PageDataValue::Count(count) => {
+                write_u8(out, 2)?;
+                write_u32(out, *count as u32)?;
+            }
+            PageDataValue::Sum { scale, value } => {
+                write_u8(out, 3)?;
+                write_u32(out, *scale)?;
+                write_i64(out, *value)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn read_page_data_values<R: Read>(input: &mut R) -> io::Result<HashMap<String, PageDataValue>> {
+    let len = read_u32(input)? as usize;
+    let mut values = HashMap::with_capacity(len);
+    for _ in 0..len {
+        let key = read_string(input)?;
+        let value = match read_u8(input)? {
+            1 => {
+                let items_len = read_u32(input)? as usize;
+                let mut items = Vec::with_capacity(items_len);
+                for _ in 0..items_len {
+                    items.push(read_string(input)?);
+                }
+                PageDataValue::Every(items)
+            }
+            2 => PageDataValue::Count(read_u32(input)? as usize),
+            3 => PageDataValue::Sum {
+                scale: read_u32(input)?,
+                value: read_i64(input)?,
+            },
+            tag => return Err(invalid_tag("page data value", tag)),
+        };
+        values.insert(key, value);
+    }
+    Ok(values)
+}
+
+fn invalid_tag(what: &str, tag: u8) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid {what} tag {tag}"),
+    )
+}
+
 fn write_page<W: Write>(out: &mut W, page: &Page) -> io::Result<()> {
+    write_option_size(out, page.size)?;
+    write_u16(out, page.rotate)?;
     write_u32(out, page.commands.len() as u32)?;
     for command in &page.commands {
-        write_command(out, command)?;
+        write_command(out, &command)?;
     }
     Ok(())
 }
 
-fn read_page<R: Read>(input: &mut R) -> io::Result<Page> {
+fn read_page<R: Read>(input: &mut R, strings: &mut StringTable) -> io::Result<Page> {
+    let size = read_option_size(input)?;
+    let rotate = read_u16(input)?;
     let len = read_u32(input)? as usize;
-    let mut commands = Vec::with_capacity(len);
+    let mut commands = CommandBuffer::new();
     for _ in 0..len {
-        commands.push(read_command(input)?);
+        commands.push(read_command(input, strings)?);
     }
-    Ok(Page { commands })
+    Ok(Page {
+        commands,
+        size,
+        rotate,
+    })
 }
 
 fn write_command<W: Write>(out: &mut W, command: &Command) -> io::Result<()> {
@@ -352,7 +1010,7 @@ fn write_command<W: Write>(out: &mut W, command: &Command) -> io::Result<()> {
             write_pt(out, *height)?;
             write_u32(out, commands.len() as u32)?;
             for cmd in commands {
-                write_command(out, cmd)?;
+                write_command(out, &cmd)?;
             }
             Ok(())
         }
@@ -379,10 +1037,57 @@ fn write_command<W: Write>(out: &mut W, command: &Command) -> io::Result<()> {
             write_string(out, name)
         }
         Command::EndMarkedContent => write_u8(out, 38),
+        Command::LinkAnnotation {
+            x,
+            y,
+            width,
+            height,
+            target,
+        } => {
+            write_u8(out, 44)?;
+            write_pt(out, *x)?;
+            write_pt(out, *y)?;
+            write_pt(out, *width)?;
+            write_pt(out, *height)?;
+            write_link_target(out, target)
+        }
+        Command::NamedDestination {
+            name,
+            x,
+            y,
+            exported,
+        } => {
+            write_u8(out, 45)?;
+            write_string(out, name)?;
+            write_pt(out, *x)?;
+            write_pt(out, *y)?;
+            write_bool(out, *exported)
+        }
+        Command::Bookmark { level, title, x, y } => {
+            write_u8(out, 46)?;
+            write_u8(out, *level)?;
+            write_string(out, title)?;
+            write_pt(out, *x)?;
+            write_pt(out, *y)
+        }
+        Command::SignatureField {
+            name,
+            x,
+            y,
+            width,
+            height,
+        } => {
+            write_u8(out, 47)?;
+            write_string(out, name)?;
+            write_pt(out, *x)?;
+            write_pt(out, *y)?;
+            write_pt(out, *width)?;
+            write_pt(out, *height)
+        }
     }
 }
 
-fn read_command<R: Read>(input: &mut R) -> io::Result<Command> {
+fn read_command<R: Read>(input: &mut R, strings: &mut StringTable) -> io::Result<Command> {
     let tag = read_u8(input)?;
     let command = match tag {
         1 => Command::SaveState,
@@ -437,7 +1142,7 @@ fn read_command<R: Read>(input: &mut R) -> io::Result<Command> {
             radius: read_pt(input)?,
             filter: read_paint_filter(input)?,
         },
-        15 => Command::SetFontName(read_string(input)?),
+        15 => Command::SetFontName(strings.intern(&read_string(input)?)),
         16 => Command::SetFontSize(read_pt(input)?),
         17 => Command::ClipRect {
             x: read_pt(input)?,
@@ -524,7 +1229,7 @@ fn read_command<R: Read>(input: &mut R) -> io::Result<Command> {
             y: read_pt(input)?,
             width: read_pt(input)?,
             height: read_pt(input)?,
-            resource_id: read_string(input)?,
+            resource_id: strings.intern(&read_string(input)?),
         },
         32 => Command::BeginTag {
             role: read_string(input)?,
@@ -537,13 +1242,13 @@ fn read_command<R: Read>(input: &mut R) -> io::Result<Command> {
         },
         33 => Command::EndTag,
         34 => {
-            let resource_id = read_string(input)?;
+            let resource_id = strings.intern(&read_string(input)?);
             let width = read_pt(input)?;
             let height = read_pt(input)?;
             let len = read_u32(input)? as usize;
-            let mut commands = Vec::with_capacity(len);
+            let mut commands = CommandBuffer::new();
             for _ in 0..len {
-                commands.push(read_command(input)?);
+                commands.push(read_command(input, strings)?);
             }
             Command::DefineForm {
                 resource_id,
@@ -557,7 +1262,7 @@ fn read_command<R: Read>(input: &mut R) -> io::Result<Command> {
             y: read_pt(input)?,
             width: read_pt(input)?,
             height: read_pt(input)?,
-            resource_id: read_string(input)?,
+            resource_id: strings.intern(&read_string(input)?),
         },
         36 => Command::BeginArtifact {
             subtype: read_option_string(input)?,
@@ -566,6 +1271,32 @@ fn read_command<R: Read>(input: &mut R) -> io::Result<Command> {
             name: read_string(input)?,
         },
         38 => Command::EndMarkedContent,
+        44 => Command::LinkAnnotation {
+            x: read_pt(input)?,
+            y: read_pt(input)?,
+            width: read_pt(input)?,
+            height: read_pt(input)?,
+            target: read_link_target(input)?,
+        },
+        45 => Command::NamedDestination {
+            name: read_string(input)?,
+            x: read_pt(input)?,
+            y: read_pt(input)?,
+            exported: read_bool(input)?,
+        },
+        46 => Command::Bookmark {
+            level: read_u8(input)?,
+            title: read_string(input)?,
+            x: read_pt(input)?,
+            y: read_pt(input)?,
+        },
+        47 => Command::SignatureField {
+            name: read_string(input)?,
+            x: read_pt(input)?,
+            y: read_pt(input)?,
+            width: read_pt(input)?,
+            height: read_pt(input)?,
+        },
         _ => {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidData,
@@ -576,6 +1307,31 @@ fn read_command<R: Read>(input: &mut R) -> io::Result<Command> {
     Ok(command)
 }
 
+fn write_link_target<W: Write>(out: &mut W, target: &LinkTarget) -> io::Result<()> {
+    match target {
+        LinkTarget::Internal(name) => {
+            write_u8(out, 1)?;
+            write_string(out, name)
+        }
+        LinkTarget::Uri(uri) => {
+            write_u8(out, 2)?;
+            write_string(out, uri)
+        }
+    }
+}
+
+fn read_link_target<R: Read>(input: &mut R) -> io::Result<LinkTarget> {
+    let tag = read_u8(input)?;
+    match tag {
+        1 => Ok(LinkTarget::Internal(read_string(input)?)),
+        2 => Ok(LinkTarget::Uri(read_string(input)?)),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unknown spill link target tag {tag}"),
+        )),
+    }
+}
+
 fn write_shading<W: Write>(out: &mut W, shading: &Shading) -> io::Result<()> {
     match shading {
         Shading::Axial {
@@ -693,15 +1449,55 @@ fn read_size<R: Read>(input: &mut R) -> io::Result<Size> {
 fn write_color<W: Write>(out: &mut W, color: Color) -> io::Result<()> {
     write_f32(out, color.r)?;
     write_f32(out, color.g)?;
-    write_f32(out, color.b)
+    write_f32(out, color.b)?;
+    match color.process {
+        None => write_u8(out, 0),
+        Some(ProcessColor::Cmyk(cmyk)) => {
+            write_u8(out, 1)?;
+            cmyk.iter().try_for_each(|v| write_f32(out, *v))
+        }
+        Some(ProcessColor::Spot {
+            name,
+            tint,
+            alternate,
+        }) => {
+            write_u8(out, 2)?;
+            write_string(out, name.as_str())?;
+            write_f32(out, tint)?;
+            alternate.iter().try_for_each(|v| write_f32(out, *v))
+        }
+    }
 }
 
 fn read_color<R: Read>(input: &mut R) -> io::Result<Color> {
-    Ok(Color {
-        r: read_f32(input)?,
-        g: read_f32(input)?,
-        b: read_f32(input)?,
-    })
+    let rgb = Color::rgb(read_f32(input)?, read_f32(input)?, read_f32(input)?);
+    let read_cmyk = |input: &mut R| -> io::Result<[f32; 4]> {
+        Ok([
+            read_f32(input)?,
+            read_f32(input)?,
+            read_f32(input)?,
+            read_f32(input)?,
+        ])
+    };
+    let process = match read_u8(input)? {
+        0 => None,
+        1 => Some(ProcessColor::Cmyk(read_cmyk(input)?)),
+        2 => {
+            let name = read_string(input)?;
+            let tint = read_f32(input)?;
+            let alternate = read_cmyk(input)?;
+            Color::spot(&name, tint, alternate)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid spot color"))?
+                .process
+        }
+        tag => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unknown spill color tag {tag}"),
+            ));
+        }
+    };
+    Ok(Color { process, ..rgb })
 }
 
 fn write_pt<W: Write>(out: &mut W, value: Pt) -> io::Result<()> {
@@ -757,6 +1553,25 @@ fn read_option_string<R: Read>(input: &mut R) -> io::Result<Option<String>> {
     }
 }
 
+fn write_option_size<W: Write>(out: &mut W, value: Option<Size>) -> io::Result<()> {
+    match value {
+        Some(v) => {
+            write_u8(out, 1)?;
+            write_size(out, v)
+        }
+        None => write_u8(out, 0),
+    }
+}
+
+fn read_option_size<R: Read>(input: &mut R) -> io::Result<Option<Size>> {
+    let flag = read_u8(input)?;
+    if flag == 0 {
+        Ok(None)
+    } else {
+        read_size(input).map(Some)
+    }
+}
+
 fn write_option_u32<W: Write>(out: &mut W, value: Option<u32>) -> io::Result<()> {
     match value {
         Some(v) => {
@@ -1,12 +1,22 @@
+use crate::css_diagnostics::{CssDiagnostic, CssDiagnosticKind, CssDiagnostics};
 use crate::debug::{DebugLogger, json_escape};
+use crate::error::Diagnostic;
 use crate::flowable::CalcLength;
 use crate::flowable::{
-    BackgroundPaint, BorderCollapseMode, BorderRadiusSpec, BorderSpacingSpec, BoxShadowSpec,
-    BreakAfter, BreakBefore, BreakInside, ClipPathInsetSpec, CssTransformOp, CssTransformOrigin,
-    EdgeSizes, LengthSpec, Pagination, PaintFilterSpec, TableLayoutMode, TextStyle,
+    BackgroundImage, BackgroundPaint, BackgroundRepeat, BackgroundSize, BorderCollapseMode,
+    BorderRadiusSpec, BorderSpacingSpec, BoxShadowSpec, BreakAfter, BreakBefore, BreakInside,
+    ClipPathInsetSpec, CssTransformOp, CssTransformOrigin, EdgeSizes, LengthSpec, OutlineSpec,
+    OutlineStyle, Pagination, PaintFilterSpec, TableLayoutMode, TextStyle,
 };
-use crate::types::{BoxSizingMode, Color, Margins, MixBlendMode, Pt, ShadingStop, Size};
+use crate::font::CssFaceDescriptor;
+use crate::html::CustomElementFactory;
+use crate::known_loss::KnownLoss;
+use crate::margin_box::PageMarginBoxPosition;
+use crate::page_template::PageParity;
+use crate::types::{BoxSizingMode, Color, Margins, MixBlendMode, Pt, Rect, ShadingStop, Size};
+use crate::warning::{Warning, WarningKind};
 use fixed::types::I32F32;
+use lightningcss::error::ParserError;
 use lightningcss::media_query::{
     MediaCondition, MediaFeature, MediaFeatureComparison, MediaFeatureId, MediaFeatureName,
     MediaFeatureValue, MediaList, MediaQuery, MediaType, Operator, Qualifier,
@@ -21,14 +31,17 @@ use lightningcss::properties::font::{
     LineHeight, RelativeFontSize, VerticalAlign as CssVerticalAlign, VerticalAlignKeyword,
 };
 use lightningcss::properties::list::ListStyleType;
+use lightningcss::properties::outline::OutlineStyle as CssOutlineStyle;
 use lightningcss::properties::overflow as css_overflow;
 use lightningcss::properties::position::Position as CssPosition;
 use lightningcss::properties::size as css_size;
 use lightningcss::properties::text::{
-    OverflowWrap, TextAlign, TextDecorationLine, WhiteSpace, WordBreak,
+    Direction as CssDirection, OverflowWrap, TextAlign, TextDecorationLine, WhiteSpace, WordBreak,
 };
 use lightningcss::properties::transform as css_transform;
 use lightningcss::properties::{Property, PropertyId};
+use lightningcss::rules::font_face::{FontFaceProperty, FontFaceRule, Source as FontFaceSource};
+use lightningcss::rules::page::{PageMarginBox, PageMarginRule, PagePseudoClass};
 use lightningcss::rules::{CssRule, CssRuleList};
 use lightningcss::stylesheet::{ParserOptions, PrinterOptions, StyleAttribute, StyleSheet};
 use lightningcss::traits::{Parse, ToCss, Zero};
@@ -38,7 +51,7 @@ use lightningcss::values::length::{LengthPercentage, LengthValue};
 use lightningcss::values::percentage::NumberOrPercentage;
 use lightningcss::values::position as css_position;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 struct Specificity(u16, u16, u16);
@@ -122,6 +135,7 @@ enum PseudoClass {
     Hover,
     Before,
     After,
+    Marker,
     Unsupported,
     Not(SimpleSelector),
 }
@@ -131,6 +145,7 @@ pub(crate) enum PseudoTarget {
     None,
     Before,
     After,
+    Marker,
 }
 
 impl PseudoClass {
@@ -174,6 +189,7 @@ impl PseudoClass {
             PseudoClass::Hover => false,
             PseudoClass::Before => matches!(pseudo, PseudoTarget::Before),
             PseudoClass::After => matches!(pseudo, PseudoTarget::After),
+            PseudoClass::Marker => matches!(pseudo, PseudoTarget::Marker),
             PseudoClass::Unsupported => false,
             PseudoClass::Not(selector) => !selector.matches_with_pseudo(element, pseudo),
         }
@@ -274,6 +290,7 @@ impl SelectorPattern {
                 match pseudo {
                     PseudoClass::Before => return Some(PseudoTarget::Before),
                     PseudoClass::After => return Some(PseudoTarget::After),
+                    PseudoClass::Marker => return Some(PseudoTarget::Marker),
                     _ => {}
                 }
             }
@@ -516,6 +533,21 @@ pub enum TextAlignMode {
     Left,
     Center,
     Right,
+    // `start`/`end` follow the inline direction; `start` is the initial value.
+    Start,
+    End,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirectionMode {
+    Ltr,
+    Rtl,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WritingMode {
+    HorizontalTb,
+    VerticalRl,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -547,6 +579,28 @@ enum ContentSpec {
     Initial,
 }
 
+// Generated content encodes `leader(<fill>)` as the fill between two of these marks. Text
+// layout turns the first mark into a tab whose gap is filled with the leader.
+pub(crate) const LEADER_MARK: char = '\t';
+
+// Generated content encodes `counter()`/`counters()` references between two of these marks,
+// with fields split by `COUNTER_FIELD`. Both are Unicode noncharacters, so they never occur in
+// authored text.
+pub(crate) const COUNTER_MARK: char = '\u{FDD0}';
+pub(crate) const COUNTER_FIELD: char = '\u{FDD1}';
+
+// Margin box content encodes `string(name, policy)` and `element(name, policy)` between two of
+// these marks, as kind, name and policy fields split by `COUNTER_FIELD`.
+pub(crate) const PAGE_REF_MARK: char = '\u{FDD2}';
+
+// One value part of a `string-set` assignment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StringSetPart {
+    Text(String),
+    // `content()`: the element's text.
+    Content,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub struct TextDecorationMode {
     pub underline: bool,
@@ -598,6 +652,13 @@ pub enum PositionMode {
     Fixed,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatMode {
+    None,
+    Left,
+    Right,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FlexDirectionMode {
     Row,
@@ -726,8 +787,45 @@ enum TransformOriginSpec {
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ListStyleTypeMode {
+    // Discs in unordered lists, decimals in ordered ones.
     Auto,
     None,
+    Disc,
+    Circle,
+    Square,
+    Decimal,
+    DecimalLeadingZero,
+    LowerAlpha,
+    UpperAlpha,
+    LowerRoman,
+    UpperRoman,
+    LowerGreek,
+}
+
+impl ListStyleTypeMode {
+    pub(crate) fn from_keyword(value: &str) -> Option<Self> {
+        Some(match value.trim().to_ascii_lowercase().as_str() {
+            "none" => ListStyleTypeMode::None,
+            "disc" => ListStyleTypeMode::Disc,
+            "circle" => ListStyleTypeMode::Circle,
+            "square" => ListStyleTypeMode::Square,
+            "decimal" => ListStyleTypeMode::Decimal,
+            "decimal-leading-zero" => ListStyleTypeMode::DecimalLeadingZero,
+            "lower-alpha" | "lower-latin" => ListStyleTypeMode::LowerAlpha,
+            "upper-alpha" | "upper-latin" => ListStyleTypeMode::UpperAlpha,
+            "lower-roman" => ListStyleTypeMode::LowerRoman,
+            "upper-roman" => ListStyleTypeMode::UpperRoman,
+            "lower-greek" => ListStyleTypeMode::LowerGreek,
+            _ => return None,
+        })
+    }
+
+    pub fn is_bullet(self) -> bool {
+        matches!(
+            self,
+            ListStyleTypeMode::Disc | ListStyleTypeMode::Circle | ListStyleTypeMode::Square
+        )
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -756,6 +854,8 @@ struct StyleDelta {
     max_height_var: Option<String>,
     max_width_var: Option<String>,
     text_align: Option<TextAlignMode>,
+    direction: Option<DirectionMode>,
+    writing_mode: Option<WritingMode>,
     vertical_align: Option<VerticalAlignMode>,
     font_weight: Option<u16>,
     font_style: Option<FontStyleMode>,
@@ -774,6 +874,12 @@ struct StyleDelta {
     content: Option<ContentSpec>,
     word_break: Option<WordBreakMode>,
     list_style_type: Option<ListStyleTypeMode>,
+    counter_reset: Option<Vec<(String, i32)>>,
+    counter_increment: Option<Vec<(String, i32)>>,
+    counter_set: Option<Vec<(String, i32)>>,
+    running: Option<Option<String>>,
+    string_set: Option<Vec<(String, Vec<StringSetPart>)>>,
+    page: Option<String>,
     letter_spacing: Option<LengthSpec>,
     border_width: EdgeDelta,
     border_color: Option<ColorSpec>,
@@ -790,10 +896,21 @@ struct StyleDelta {
     font_name: Option<FontSpec>,
     font_name_var: Option<String>,
     box_shadow: Option<BoxShadowSpec>,
+    outline_width: Option<LengthSpec>,
+    // `Some(None)` is `outline-style: none`.
+    outline_style: Option<Option<OutlineStyle>>,
+    // `Some(None)` is `currentColor`.
+    outline_color: Option<Option<Color>>,
+    outline_offset: Option<LengthSpec>,
     paint_filter: Option<PaintFilterSpec>,
     backdrop_filter: Option<PaintFilterSpec>,
     mix_blend_mode: Option<MixBlendMode>,
     background_paint: Option<BackgroundPaint>,
+    // `Some(None)` is `background-image: none`.
+    background_image: Option<Option<String>>,
+    background_size: Option<BackgroundSize>,
+    background_position: Option<(LengthSpec, LengthSpec)>,
+    background_repeat: Option<BackgroundRepeat>,
     clip_path: Option<ClipPathSpec>,
     pagination: PaginationDelta,
     margin: EdgeDelta,
@@ -801,6 +918,7 @@ struct StyleDelta {
     white_space: Option<WhiteSpaceSpec>,
     display: Option<DisplaySpec>,
     position: Option<PositionMode>,
+    float: Option<FloatMode>,
     z_index: Option<i32>,
     inset_left: Option<LengthSpec>,
     inset_top: Option<LengthSpec>,
@@ -822,6 +940,7 @@ struct StyleDelta {
     align_content: Option<AlignContentMode>,
     grid_columns: Option<usize>,
     grid_rows: Option<usize>,
+    column_count: Option<usize>,
     grid_column_start: Option<usize>,
     grid_row_start: Option<usize>,
     gap: Option<LengthSpec>,
@@ -852,6 +971,13 @@ pub struct ComputedStyle {
     pub color: Color,
     pub background_color: Option<Color>,
     pub background_paint: Option<BackgroundPaint>,
+    // `background-image: url(...)` source and its layout. The HTML layer swaps in the
+    // resolved source and records the decoded image size.
+    pub background_image: Option<String>,
+    pub background_image_size: Option<Size>,
+    pub background_size: BackgroundSize,
+    pub background_position: (LengthSpec, LengthSpec),
+    pub background_repeat: BackgroundRepeat,
     pending_color_var: Option<String>,
     pending_background_color_var: Option<String>,
     pending_border_color_var: Option<String>,
@@ -861,6 +987,9 @@ pub struct ComputedStyle {
     pending_border_left_color_var: Option<String>,
     pending_font_name_var: Option<String>,
     pub pagination: Pagination,
+    // `orphans` or `widows` was declared on this element (tables and lists apply them to
+    // rows and items only when asked).
+    pub orphans_widows_declared: bool,
     pub margin: EdgeSizes,
     pub padding: EdgeSizes,
     pub width: LengthSpec,
@@ -876,6 +1005,8 @@ pub struct ComputedStyle {
     pending_max_height_var: Option<String>,
     pending_max_width_var: Option<String>,
     pub text_align: TextAlignMode,
+    pub direction: DirectionMode,
+    pub writing_mode: WritingMode,
     pub vertical_align: VerticalAlignMode,
     pub font_weight: u16,
     pub font_style: FontStyleMode,
@@ -895,6 +1026,14 @@ pub struct ComputedStyle {
     pub content: Option<String>,
     pub word_break: WordBreakMode,
     pub list_style_type: ListStyleTypeMode,
+    pub counter_reset: Vec<(String, i32)>,
+    pub counter_increment: Vec<(String, i32)>,
+    pub counter_set: Vec<(String, i32)>,
+    // `position: running(name)`: the element leaves the flow and feeds `element(name)`.
+    pub running: Option<String>,
+    pub string_set: Vec<(String, Vec<StringSetPart>)>,
+    // Named page (`page: <name>`). `auto` keeps the parent's page, so descendants carry it.
+    pub page: Option<String>,
     pub letter_spacing: Pt,
     pub border_width: EdgeSizes,
     pub border_color: Option<Color>,
@@ -909,6 +1048,11 @@ pub struct ComputedStyle {
     pub table_layout: TableLayoutMode,
     pub border_radius: BorderRadiusSpec,
     pub box_shadow: Option<BoxShadowSpec>,
+    pub outline_width: LengthSpec,
+    pub outline_style: Option<OutlineStyle>,
+    // `None` draws the outline in the text color.
+    pub outline_color: Option<Color>,
+    pub outline_offset: LengthSpec,
     pub paint_filter: Option<PaintFilterSpec>,
     pub backdrop_filter: Option<PaintFilterSpec>,
     pub mix_blend_mode: MixBlendMode,
@@ -919,6 +1063,7 @@ pub struct ComputedStyle {
     pub font_stack: Vec<Arc<str>>,
     pub font_name: Arc<str>,
     pub position: PositionMode,
+    pub float: FloatMode,
     pub z_index: i32,
     pub inset_left: LengthSpec,
     pub inset_top: LengthSpec,
@@ -940,6 +1085,8 @@ pub struct ComputedStyle {
     pub align_content: AlignContentMode,
     pub grid_columns: Option<usize>,
     pub grid_rows: Option<usize>,
+    // CSS multi-column count; 1 means a single column. Not inherited.
+    pub column_count: usize,
     pub grid_column_start: Option<usize>,
     pub grid_row_start: Option<usize>,
     pub gap: LengthSpec,
@@ -984,9 +1131,42 @@ impl ComputedStyle {
             text_overflow: self.text_overflow,
             word_break: self.word_break,
             letter_spacing: self.letter_spacing,
+            direction: self.direction,
+        }
+    }
+
+    // Resolves `start`/`end` against the inline direction to a physical alignment.
+    pub fn physical_text_align(&self) -> TextAlignMode {
+        let rtl = self.direction == DirectionMode::Rtl;
+        match self.text_align {
+            TextAlignMode::Start if rtl => TextAlignMode::Right,
+            TextAlignMode::End if rtl => TextAlignMode::Left,
+            TextAlignMode::Start => TextAlignMode::Left,
+            TextAlignMode::End => TextAlignMode::Right,
+            align => align,
         }
     }
 
+    pub fn background_image_layer(&self) -> Option<BackgroundImage> {
+        let source = self.background_image.as_ref()?;
+        Some(
+            BackgroundImage::new(source.clone())
+                .with_intrinsic_size(self.background_image_size)
+                .with_size(self.background_size)
+                .with_position(self.background_position.0, self.background_position.1)
+                .with_repeat(self.background_repeat),
+        )
+    }
+
+    pub fn outline(&self) -> Option<OutlineSpec> {
+        Some(OutlineSpec {
+            width: self.outline_width,
+            offset: self.outline_offset,
+            style: self.outline_style?,
+            color: self.outline_color.unwrap_or(self.color),
+        })
+    }
+
     pub fn resolved_border_colors(&self, fallback: Color) -> ComputedBorderColors {
         let uniform = self.border_color.unwrap_or(fallback);
         ComputedBorderColors {
@@ -1136,7 +1316,8 @@ fn apply_border_color_from_raw(
         }
         return true;
     }
-    if let Some((color, alpha)) = parse_color_string(&lowered) {
+    // Parse the original case: spot colorant names are case-sensitive.
+    if let Some((color, alpha)) = parse_color_string(raw) {
         set_delta_border_color_spec(
             delta,
             target,
@@ -1217,11 +1398,40 @@ pub struct StyleResolver {
     important_index: RuleIndex,
     root_font_size: Pt,
     viewport: Size,
+    // Height of the first page frame; vertical writing mode sizes its columns to it.
+    page_content_height: Pt,
+    // `@page` margin boxes and the page area of each page template they are laid out around.
+    page_margin_boxes: Vec<CssPageMarginBox>,
+    page_content_areas: Vec<PageArea>,
+    // Factories for registered custom elements, keyed by lowercase tag name.
+    custom_elements: HashMap<String, CustomElementFactory>,
     debug: Option<Arc<DebugLogger>>,
     root_normal: Vec<StyleDelta>,
     root_important: Vec<StyleDelta>,
     has_positional_selectors: bool,
     has_sibling_selectors: bool,
+    uses_counters: bool,
+    uses_named_pages: bool,
+    // `@font-face` rules none of whose sources could be loaded.
+    font_face_failures: Vec<Diagnostic>,
+    // `@font-face` families of this stylesheet (normalized) -> the name the engine registered
+    // their faces under, so renders declaring one family with different fonts stay apart.
+    font_face_families: HashMap<String, Arc<str>>,
+    // Set by `render_to_layout`: every element records its box and computed style.
+    capture_layout: bool,
+    css_diagnostics: CssDiagnostics,
+    // Declarations of the user stylesheet the engine ignores or draws differently.
+    style_losses: Vec<Warning>,
+    // Known losses found while the render context was built (strict fidelity only).
+    known_losses: Vec<Warning>,
+}
+
+// Page area of one page template, used to place margin boxes.
+#[derive(Debug, Clone)]
+pub(crate) struct PageArea {
+    pub template_name: String,
+    pub page_name: Option<String>,
+    pub rect: Rect,
 }
 
 #[derive(Debug, Clone, Copy, Default)]
@@ -1241,6 +1451,15 @@ impl CssPageSetup {
             || self.margin_left.is_some()
     }
 
+    // Overrides the fields `other` sets.
+    pub fn merge(&mut self, other: &CssPageSetup) {
+        self.size = other.size.or(self.size);
+        self.margin_top = other.margin_top.or(self.margin_top);
+        self.margin_right = other.margin_right.or(self.margin_right);
+        self.margin_bottom = other.margin_bottom.or(self.margin_bottom);
+        self.margin_left = other.margin_left.or(self.margin_left);
+    }
+
     pub fn resolve_margins(self, base: Margins) -> Option<Margins> {
         if !self.has_margin_override() {
             return None;
@@ -1344,6 +1563,8 @@ impl StyleResolver {
         let mut root_important = Vec::new();
         let mut has_positional_selectors = false;
         let mut has_sibling_selectors = false;
+        let mut css_diagnostics = CssDiagnostics::default();
+        let mut style_losses = Vec::new();
 
         fn append_rule_list(
             rules: CssRuleList,
@@ -1357,6 +1578,8 @@ impl StyleResolver {
             has_sibling_selectors: &mut bool,
             viewport: Size,
             prefer_print: bool,
+            mut diagnostics: Option<&mut CssDiagnostics>,
+            mut losses: Option<&mut Vec<Warning>>,
         ) {
             for rule in rules.0 {
                 match rule {
@@ -1367,6 +1590,12 @@ impl StyleResolver {
                             .selectors
                             .to_css_string(PrinterOptions::default())
                             .unwrap_or_default();
+                        if let Some(diagnostics) = diagnostics.as_deref_mut() {
+                            collect_rule_diagnostics(&style, &selectors, diagnostics);
+                        }
+                        if let Some(losses) = losses.as_deref_mut() {
+                            collect_rule_losses(&style.declarations, &selectors, losses);
+                        }
                         if let Some(logger) = debug {
                             log_declaration_no_effects(&style.declarations, &selectors, logger);
                             for selector in selectors.split(',') {
@@ -1458,6 +1687,8 @@ impl StyleResolver {
                                 has_sibling_selectors,
                                 viewport,
                                 prefer_print,
+                                diagnostics.as_deref_mut(),
+                                losses.as_deref_mut(),
                             );
                         }
                     }
@@ -1470,7 +1701,7 @@ impl StyleResolver {
         let user_sheet = if css.trim().is_empty() {
             None
         } else {
-            StyleSheet::parse(css, ParserOptions::default()).ok()
+            parse_stylesheet(css, Some(&mut css_diagnostics))
         };
         let prefer_print = ua_sheet
             .as_ref()
@@ -1494,6 +1725,8 @@ impl StyleResolver {
                 &mut has_sibling_selectors,
                 viewport,
                 prefer_print,
+                None,
+                None,
             );
         }
 
@@ -1510,11 +1743,27 @@ impl StyleResolver {
                 &mut has_sibling_selectors,
                 viewport,
                 prefer_print,
+                Some(&mut css_diagnostics),
+                Some(&mut style_losses),
             );
         }
 
         let normal_index = RuleIndex::new(&normal_rules);
         let important_index = RuleIndex::new(&important_rules);
+        let uses_counters = normal_rules
+            .iter()
+            .chain(important_rules.iter())
+            .map(|rule| &rule.delta)
+            .chain(root_normal.iter())
+            .chain(root_important.iter())
+            .any(StyleDelta::uses_counters);
+        let uses_named_pages = normal_rules
+            .iter()
+            .chain(important_rules.iter())
+            .map(|rule| &rule.delta)
+            .chain(root_normal.iter())
+            .chain(root_important.iter())
+            .any(|delta| delta.page.is_some());
 
         if let Some(logger) = debug.as_deref() {
             let json = format!(
@@ -1548,14 +1797,89 @@ impl StyleResolver {
             important_index,
             root_font_size: TextStyle::default().font_size,
             viewport,
+            page_content_height: viewport.height,
+            page_margin_boxes: Vec::new(),
+            page_content_areas: Vec::new(),
+            custom_elements: HashMap::new(),
             debug,
             root_normal,
             root_important,
             has_positional_selectors,
             has_sibling_selectors,
+            uses_counters,
+            uses_named_pages,
+            font_face_failures: Vec::new(),
+            font_face_families: HashMap::new(),
+            capture_layout: false,
+            css_diagnostics,
+            style_losses,
+            known_losses: Vec::new(),
         }
     }
 
+    pub(crate) fn with_font_face_families(mut self, families: HashMap<String, Arc<str>>) -> Self {
+        self.font_face_families = families;
+        self
+    }
+
+    pub(crate) fn with_font_face_failures(mut self, failures: Vec<Diagnostic>) -> Self {
+        self.font_face_failures = failures;
+        self
+    }
+
+    pub(crate) fn font_face_failures(&self) -> &[Diagnostic] {
+        &self.font_face_failures
+    }
+
+    // Points `@font-face` families in the font stack at this stylesheet's faces.
+    fn scope_font_families(&self, style: &mut ComputedStyle) {
+        if self.font_face_families.is_empty() {
+            return;
+        }
+        let scoped = |name: &Arc<str>| {
+            let key = name
+                .trim()
+                .trim_matches('"')
+                .trim_matches('\'')
+                .to_ascii_lowercase();
+            self.font_face_families.get(&key).cloned()
+        };
+        for name in &mut style.font_stack {
+            if let Some(family) = scoped(name) {
+                *name = family;
+            }
+        }
+        if let Some(family) = scoped(&style.font_name) {
+            style.font_name = family;
+        }
+    }
+
+    pub(crate) fn with_known_losses(mut self, losses: Vec<Warning>) -> Self {
+        self.known_losses = losses;
+        self
+    }
+
+    pub(crate) fn known_losses(&self) -> &[Warning] {
+        &self.known_losses
+    }
+
+    // What the user stylesheet had that the engine could not use.
+    pub(crate) fn css_diagnostics(&self) -> &CssDiagnostics {
+        &self.css_diagnostics
+    }
+
+    // Replaces the diagnostics with the same ones counted against the caller's CSS.
+    pub(crate) fn with_css_diagnostics(mut self, diagnostics: CssDiagnostics) -> Self {
+        self.css_diagnostics = diagnostics;
+        self
+    }
+
+    // Known losses of user stylesheet declarations, one warning per declaration naming the
+    // rule's selector as its element.
+    pub(crate) fn style_losses(&self) -> &[Warning] {
+        &self.style_losses
+    }
+
     pub fn has_positional_selectors(&self) -> bool {
         self.has_positional_selectors
     }
@@ -1564,6 +1888,89 @@ impl StyleResolver {
         self.has_sibling_selectors
     }
 
+    // Whether any rule sets a counter property or reads `counter()`/`counters()`.
+    pub fn uses_counters(&self) -> bool {
+        self.uses_counters
+    }
+
+    // Whether any rule sets `page: <name>`.
+    pub fn uses_named_pages(&self) -> bool {
+        self.uses_named_pages
+    }
+
+    pub fn with_custom_elements(mut self, elements: HashMap<String, CustomElementFactory>) -> Self {
+        self.custom_elements = elements;
+        self
+    }
+
+    pub fn custom_element(&self, tag: &str) -> Option<&CustomElementFactory> {
+        self.custom_elements.get(tag)
+    }
+
+    pub(crate) fn with_layout_capture(mut self, capture: bool) -> Self {
+        self.capture_layout = capture;
+        self
+    }
+
+    pub(crate) fn captures_layout(&self) -> bool {
+        self.capture_layout
+    }
+
+    pub fn with_page_content_height(mut self, height: Pt) -> Self {
+        self.page_content_height = height;
+        self
+    }
+
+    pub fn page_content_height(&self) -> Pt {
+        self.page_content_height
+    }
+
+    pub(crate) fn with_page_margin_boxes(
+        mut self,
+        boxes: Vec<CssPageMarginBox>,
+        areas: Vec<PageArea>,
+    ) -> Self {
+        self.page_margin_boxes = boxes;
+        self.page_content_areas = areas;
+        self
+    }
+
+    pub(crate) fn page_margin_boxes(&self) -> &[CssPageMarginBox] {
+        &self.page_margin_boxes
+    }
+
+    // Page area and page name of the page at `index` laid out with `template`. Pages are found
+    // by template name; without one, pages past the last template reuse its area.
+    pub(crate) fn page_content_area(
+        &self,
+        index: usize,
+        template: Option<&str>,
+    ) -> (Rect, Option<&str>) {
+        if let Some(area) = self
+            .page_content_areas
+            .iter()
+            .find(|area| template == Some(area.template_name.as_str()))
+        {
+            return (area.rect, area.page_name.as_deref());
+        }
+        let indexed: Vec<&PageArea> = self
+            .page_content_areas
+            .iter()
+            .filter(|area| area.page_name.is_none())
+            .collect();
+        let rect = indexed
+            .get(index)
+            .or(indexed.last())
+            .map(|area| area.rect)
+            .unwrap_or(Rect {
+                x: Pt::ZERO,
+                y: Pt::ZERO,
+                width: self.viewport.width,
+                height: self.viewport.height,
+            });
+        (rect, None)
+    }
+
     pub fn debug_logger(&self) -> Option<Arc<DebugLogger>> {
         self.debug.clone()
     }
@@ -1575,6 +1982,11 @@ impl StyleResolver {
             color: Color::BLACK,
             background_color: None,
             background_paint: None,
+            background_image: None,
+            background_image_size: None,
+            background_size: BackgroundSize::Auto,
+            background_position: (LengthSpec::Percent(0.0), LengthSpec::Percent(0.0)),
+            background_repeat: BackgroundRepeat::Repeat,
             pending_color_var: None,
             pending_background_color_var: None,
             pending_border_color_var: None,
@@ -1584,6 +1996,7 @@ impl StyleResolver {
             pending_border_left_color_var: None,
             pending_font_name_var: None,
             pagination: Pagination::default(),
+            orphans_widows_declared: false,
             margin: EdgeSizes::zero(),
             padding: EdgeSizes::zero(),
             width: LengthSpec::Auto,
@@ -1598,7 +2011,9 @@ impl StyleResolver {
             pending_min_height_var: None,
             pending_max_height_var: None,
             pending_max_width_var: None,
-            text_align: TextAlignMode::Left,
+            text_align: TextAlignMode::Start,
+            direction: DirectionMode::Ltr,
+            writing_mode: WritingMode::HorizontalTb,
             vertical_align: VerticalAlignMode::Top,
             font_weight: 400,
             font_style: FontStyleMode::Normal,
@@ -1618,6 +2033,12 @@ impl StyleResolver {
             content: None,
             word_break: WordBreakMode::Normal,
             list_style_type: ListStyleTypeMode::Auto,
+            counter_reset: Vec::new(),
+            counter_increment: Vec::new(),
+            counter_set: Vec::new(),
+            running: None,
+            string_set: Vec::new(),
+            page: None,
             letter_spacing: Pt::ZERO,
             border_width: EdgeSizes::zero(),
             border_color: None,
@@ -1632,6 +2053,10 @@ impl StyleResolver {
             table_layout: TableLayoutMode::Auto,
             border_radius: BorderRadiusSpec::zero(),
             box_shadow: None,
+            outline_width: LengthSpec::Absolute(Pt::from_f32(1.5)),
+            outline_style: None,
+            outline_color: None,
+            outline_offset: LengthSpec::Absolute(Pt::ZERO),
             paint_filter: None,
             backdrop_filter: None,
             mix_blend_mode: MixBlendMode::Normal,
@@ -1642,6 +2067,7 @@ impl StyleResolver {
             font_stack: vec![Arc::<str>::from("Helvetica")],
             font_name: Arc::<str>::from("Helvetica"),
             position: PositionMode::Static,
+            float: FloatMode::None,
             z_index: 0,
             inset_left: LengthSpec::Auto,
             inset_top: LengthSpec::Auto,
@@ -1663,6 +2089,7 @@ impl StyleResolver {
             align_content: AlignContentMode::FlexStart,
             grid_columns: None,
             grid_rows: None,
+            column_count: 1,
             grid_column_start: None,
             grid_row_start: None,
             gap: LengthSpec::Absolute(Pt::ZERO),
@@ -1694,6 +2121,11 @@ impl StyleResolver {
             color: parent.color,
             background_color: None,
             background_paint: None,
+            background_image: None,
+            background_image_size: None,
+            background_size: BackgroundSize::Auto,
+            background_position: (LengthSpec::Percent(0.0), LengthSpec::Percent(0.0)),
+            background_repeat: BackgroundRepeat::Repeat,
             pending_color_var: None,
             pending_background_color_var: None,
             pending_border_color_var: None,
@@ -1703,6 +2135,7 @@ impl StyleResolver {
             pending_border_left_color_var: None,
             pending_font_name_var: None,
             pagination: Pagination::default(),
+            orphans_widows_declared: false,
             margin: EdgeSizes::zero(),
             padding: EdgeSizes::zero(),
             width: LengthSpec::Auto,
@@ -1718,6 +2151,8 @@ impl StyleResolver {
             pending_max_height_var: None,
             pending_max_width_var: None,
             text_align: parent.text_align,
+            direction: parent.direction,
+            writing_mode: parent.writing_mode,
             vertical_align: parent.vertical_align,
             font_weight: parent.font_weight,
             font_style: parent.font_style,
@@ -1737,6 +2172,12 @@ impl StyleResolver {
             content: None,
             word_break: parent.word_break,
             list_style_type: parent.list_style_type,
+            counter_reset: Vec::new(),
+            counter_increment: Vec::new(),
+            counter_set: Vec::new(),
+            running: None,
+            string_set: Vec::new(),
+            page: parent.page.clone(),
             letter_spacing: parent.letter_spacing,
             border_width: EdgeSizes::zero(),
             border_color: None,
@@ -1751,6 +2192,10 @@ impl StyleResolver {
             table_layout: TableLayoutMode::Auto,
             border_radius: BorderRadiusSpec::zero(),
             box_shadow: None,
+            outline_width: LengthSpec::Absolute(Pt::from_f32(1.5)),
+            outline_style: None,
+            outline_color: None,
+            outline_offset: LengthSpec::Absolute(Pt::ZERO),
             paint_filter: None,
             backdrop_filter: None,
             mix_blend_mode: MixBlendMode::Normal,
@@ -1761,6 +2206,7 @@ impl StyleResolver {
             font_stack: parent.font_stack.clone(),
             font_name: parent.font_name.clone(),
             position: PositionMode::Static,
+            float: FloatMode::None,
             z_index: 0,
             inset_left: LengthSpec::Auto,
             inset_top: LengthSpec::Auto,
@@ -1782,6 +2228,7 @@ impl StyleResolver {
             align_content: parent.align_content,
             grid_columns: None,
             grid_rows: None,
+            column_count: 1,
             grid_column_start: None,
             grid_row_start: None,
             gap: parent.gap,
@@ -1800,6 +2247,29 @@ impl StyleResolver {
         let parent_line_height = parent.line_height.clone();
         let root_font_size = parent.root_font_size;
 
+        // The HTML `dir` attribute is a presentational hint, so any author rule overrides it.
+        // `dir="auto"` keeps the inherited direction.
+        match element
+            .attrs
+            .get("dir")
+            .map(|value| value.trim().to_ascii_lowercase())
+        {
+            Some(value) if value == "rtl" => computed.direction = DirectionMode::Rtl,
+            Some(value) if value == "ltr" => computed.direction = DirectionMode::Ltr,
+            _ => {}
+        }
+        // `<ol type="a">` and `<li type="i">` pick the numbering style the same way.
+        let type_hint = match element.tag.as_str() {
+            "ol" | "ul" | "li" => element
+                .attrs
+                .get("type")
+                .and_then(|value| list_style_type_from_html_type(value)),
+            _ => None,
+        };
+        if let Some(list_style_type) = type_hint {
+            computed.list_style_type = list_style_type;
+        }
+
         if element.is_root {
             for delta in &self.root_normal {
                 apply_delta(
@@ -2165,6 +2635,7 @@ impl StyleResolver {
             computed.root_font_size = computed.font_size;
         }
         resolve_pending_vars(&mut computed);
+        self.scope_font_families(&mut computed);
         apply_border_style_mask(&mut computed);
         if let (Some(logger), Some(node)) = (debug, debug_node.as_ref()) {
             let style_json = debug_style_json(&computed);
@@ -2197,6 +2668,11 @@ impl StyleResolver {
             color: parent.color,
             background_color: None,
             background_paint: None,
+            background_image: None,
+            background_image_size: None,
+            background_size: BackgroundSize::Auto,
+            background_position: (LengthSpec::Percent(0.0), LengthSpec::Percent(0.0)),
+            background_repeat: BackgroundRepeat::Repeat,
             pending_color_var: None,
             pending_background_color_var: None,
             pending_border_color_var: None,
@@ -2206,6 +2682,7 @@ impl StyleResolver {
             pending_border_left_color_var: None,
             pending_font_name_var: None,
             pagination: Pagination::default(),
+            orphans_widows_declared: false,
             margin: EdgeSizes::zero(),
             padding: EdgeSizes::zero(),
             width: LengthSpec::Auto,
@@ -2221,6 +2698,8 @@ impl StyleResolver {
             pending_max_height_var: None,
             pending_max_width_var: None,
             text_align: parent.text_align,
+            direction: parent.direction,
+            writing_mode: parent.writing_mode,
             vertical_align: parent.vertical_align,
             font_weight: parent.font_weight,
             font_style: parent.font_style,
@@ -2240,6 +2719,12 @@ impl StyleResolver {
             content: None,
             word_break: parent.word_break,
             list_style_type: parent.list_style_type,
+            counter_reset: Vec::new(),
+            counter_increment: Vec::new(),
+            counter_set: Vec::new(),
+            running: None,
+            string_set: Vec::new(),
+            page: parent.page.clone(),
             letter_spacing: parent.letter_spacing,
             border_width: EdgeSizes::zero(),
             border_color: None,
@@ -2254,6 +2739,10 @@ impl StyleResolver {
             table_layout: TableLayoutMode::Auto,
             border_radius: BorderRadiusSpec::zero(),
             box_shadow: None,
+            outline_width: LengthSpec::Absolute(Pt::from_f32(1.5)),
+            outline_style: None,
+            outline_color: None,
+            outline_offset: LengthSpec::Absolute(Pt::ZERO),
             paint_filter: None,
             backdrop_filter: None,
             mix_blend_mode: MixBlendMode::Normal,
@@ -2264,6 +2753,7 @@ impl StyleResolver {
             font_stack: parent.font_stack.clone(),
             font_name: parent.font_name.clone(),
             position: PositionMode::Static,
+            float: FloatMode::None,
             z_index: 0,
             inset_left: LengthSpec::Auto,
             inset_top: LengthSpec::Auto,
@@ -2285,6 +2775,7 @@ impl StyleResolver {
             align_content: parent.align_content,
             grid_columns: None,
             grid_rows: None,
+            column_count: 1,
             grid_column_start: None,
             grid_row_start: None,
             gap: parent.gap,
@@ -2335,10 +2826,13 @@ impl StyleResolver {
                 self.viewport,
             );
         }
+        self.scope_font_families(&mut computed);
         apply_border_style_mask(&mut computed);
 
+        // A `::marker` rule restyles the list marker even without `content`.
         match computed.content.as_deref() {
             Some(text) if !text.is_empty() => Some(computed),
+            _ if pseudo == PseudoTarget::Marker => Some(computed),
             _ => None,
         }
     }
@@ -2650,7 +3144,7 @@ pub(crate) fn extract_css_page_setup(
     if css.trim().is_empty() {
         return CssPageSetup::default();
     }
-    let Ok(sheet) = StyleSheet::parse(css, ParserOptions::default()) else {
+    let Some(sheet) = parse_stylesheet(css, None) else {
         return CssPageSetup::default();
     };
     let viewport = viewport.unwrap_or(Size {
@@ -2694,6 +3188,473 @@ fn extract_css_page_setup_from_rules(
     }
 }
 
+// Page selector of an `@page` rule: `@page`, `@page :first`, `@page <name>` or
+// `@page <name>:first`. Names are matched case-insensitively.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct CssPageSelector {
+    pub name: Option<String>,
+    pub first: bool,
+    // `:right` pages are odd and `:left` pages even.
+    pub parity: Option<PageParity>,
+}
+
+impl CssPageSelector {
+    pub fn matches(&self, page_name: Option<&str>, first_page: bool, parity: PageParity) -> bool {
+        (!self.first || first_page)
+            && self.parity.is_none_or(|own| own == parity)
+            && self
+                .name
+                .as_deref()
+                .is_none_or(|name| Some(name) == page_name)
+    }
+
+    // Cascade order between matching rules: a page name outranks `:first`, which outranks
+    // `:left`/`:right`.
+    pub fn specificity(&self) -> (bool, bool, bool) {
+        (self.name.is_some(), self.first, self.parity.is_some())
+    }
+}
+
+// Selectors of an `@page` rule that the engine supports; `:blank` selectors are skipped.
+fn page_rule_selectors(rule: &lightningcss::rules::page::PageRule) -> Vec<CssPageSelector> {
+    if rule.selectors.is_empty() {
+        return vec![CssPageSelector::default()];
+    }
+    rule.selectors
+        .iter()
+        .filter_map(|selector| {
+            let mut first = false;
+            let mut parity = None;
+            for pseudo in &selector.pseudo_classes {
+                let side = match pseudo {
+                    PagePseudoClass::First => {
+                        first = true;
+                        continue;
+                    }
+                    PagePseudoClass::Right => PageParity::Odd,
+                    PagePseudoClass::Left => PageParity::Even,
+                    _ => return None,
+                };
+                if parity.is_some_and(|existing| existing != side) {
+                    return None;
+                }
+                parity = Some(side);
+            }
+            Some(CssPageSelector {
+                name: selector.name.as_ref().map(|name| name.to_ascii_lowercase()),
+                first,
+                parity,
+            })
+        })
+        .collect()
+}
+
+// Page setups of `@page` rules with a page selector (`:first`, `:left`, `:right`, a page name
+// or a combination), merged per selector in source order. The unnamed rule is read by `extract_css_page_setup`.
+pub(crate) fn extract_css_named_page_setups(
+    css: &str,
+    debug: Option<&DebugLogger>,
+    viewport: Option<Size>,
+) -> Vec<(CssPageSelector, CssPageSetup)> {
+    if !css.contains("@page") {
+        return Vec::new();
+    }
+    let Some(sheet) = parse_stylesheet(css, None) else {
+        return Vec::new();
+    };
+    let viewport = viewport.unwrap_or(Size {
+        width: Pt::ZERO,
+        height: Pt::ZERO,
+    });
+    let prefer_print = stylesheet_has_print_media(&sheet.rules);
+    let mut setups = Vec::new();
+    extract_css_named_page_setups_from_rules(
+        &sheet.rules,
+        &mut setups,
+        viewport,
+        prefer_print,
+        debug,
+    );
+    setups
+}
+
+fn extract_css_named_page_setups_from_rules(
+    rules: &CssRuleList,
+    setups: &mut Vec<(CssPageSelector, CssPageSetup)>,
+    viewport: Size,
+    prefer_print: bool,
+    debug: Option<&DebugLogger>,
+) {
+    for rule in &rules.0 {
+        match rule {
+            CssRule::Page(page_rule) => {
+                for selector in page_rule_selectors(page_rule) {
+                    if selector == CssPageSelector::default() {
+                        continue;
+                    }
+                    let index = match setups
+                        .iter()
+                        .position(|(existing, _)| *existing == selector)
+                    {
+                        Some(index) => index,
+                        None => {
+                            setups.push((selector, CssPageSetup::default()));
+                            setups.len() - 1
+                        }
+                    };
+                    apply_page_rule_declarations(page_rule, &mut setups[index].1);
+                }
+            }
+            CssRule::Media(media)
+                if media_list_matches(&media.query, viewport, prefer_print, debug) =>
+            {
+                extract_css_named_page_setups_from_rules(
+                    &media.rules,
+                    setups,
+                    viewport,
+                    prefer_print,
+                    debug,
+                );
+            }
+            _ => {}
+        }
+    }
+}
+
+// One `@page` margin box such as `@top-center`. `content` is encoded like other generated
+// content (empty for `none`); `css` holds the box's remaining declarations as an inline style.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CssPageMarginBox {
+    pub selector: CssPageSelector,
+    pub position: PageMarginBoxPosition,
+    pub content: Option<String>,
+    pub css: String,
+    pub vertical_align: Option<VerticalAlignMode>,
+}
+
+pub(crate) fn extract_css_page_margin_boxes(
+    css: &str,
+    debug: Option<&DebugLogger>,
+    viewport: Option<Size>,
+) -> Vec<CssPageMarginBox> {
+    if !css.contains("@page") {
+        return Vec::new();
+    }
+    let Some(sheet) = parse_stylesheet(css, None) else {
+        return Vec::new();
+    };
+    let viewport = viewport.unwrap_or(Size {
+        width: Pt::ZERO,
+        height: Pt::ZERO,
+    });
+    let prefer_print = stylesheet_has_print_media(&sheet.rules);
+    let mut boxes = Vec::new();
+    extract_css_page_margin_boxes_from_rules(
+        &sheet.rules,
+        &mut boxes,
+        viewport,
+        prefer_print,
+        debug,
+    );
+    boxes
+}
+
+// Margin boxes of a page: the boxes of every matching rule cascaded in selector order, keeping
+// those with content.
+pub(crate) fn page_margin_boxes_for(
+    boxes: &[CssPageMarginBox],
+    page_name: Option<&str>,
+    page_number: usize,
+) -> Vec<CssPageMarginBox> {
+    let parity = PageParity::of(page_number);
+    let mut matching: Vec<&CssPageMarginBox> = boxes
+        .iter()
+        .filter(|margin_box| {
+            margin_box
+                .selector
+                .matches(page_name, page_number == 1, parity)
+        })
+        .collect();
+    matching.sort_by_key(|margin_box| margin_box.selector.specificity());
+    let mut out: Vec<CssPageMarginBox> = Vec::new();
+    for margin_box in matching {
+        match out
+            .iter_mut()
+            .find(|existing| existing.position == margin_box.position)
+        {
+            Some(existing) => {
+                if margin_box.content.is_some() {
+                    existing.content = margin_box.content.clone();
+                }
+                if !margin_box.css.is_empty() {
+                    if !existing.css.is_empty() {
+                        existing.css.push_str("; ");
+                    }
+                    existing.css.push_str(&margin_box.css);
+                }
+                if margin_box.vertical_align.is_some() {
+                    existing.vertical_align = margin_box.vertical_align;
+                }
+            }
+            None => out.push(margin_box.clone()),
+        }
+    }
+    out.retain(|margin_box| {
+        margin_box
+            .content
+            .as_deref()
+            .is_some_and(|content| !content.is_empty())
+    });
+    out
+}
+
+fn extract_css_page_margin_boxes_from_rules(
+    rules: &CssRuleList,
+    boxes: &mut Vec<CssPageMarginBox>,
+    viewport: Size,
+    prefer_print: bool,
+    debug: Option<&DebugLogger>,
+) {
+    for rule in &rules.0 {
+        match rule {
+            CssRule::Page(page_rule) => {
+                for selector in page_rule_selectors(page_rule) {
+                    for margin_rule in &page_rule.rules {
+                        apply_page_margin_rule(margin_rule, &selector, boxes);
+                    }
+                }
+            }
+            CssRule::Media(media)
+                if media_list_matches(&media.query, viewport, prefer_print, debug) =>
+            {
+                extract_css_page_margin_boxes_from_rules(
+                    &media.rules,
+                    boxes,
+                    viewport,
+                    prefer_print,
+                    debug,
+                );
+            }
+            _ => {}
+        }
+    }
+}
+
+// Later rules for the same box cascade over earlier ones: `content` is replaced and other
+// declarations are appended.
+fn apply_page_margin_rule(
+    rule: &PageMarginRule,
+    selector: &CssPageSelector,
+    boxes: &mut Vec<CssPageMarginBox>,
+) {
+    let position = page_margin_box_position(&rule.margin_box);
+    let index = match boxes
+        .iter()
+        .position(|existing| existing.position == position && existing.selector == *selector)
+    {
+        Some(index) => index,
+        None => {
+            boxes.push(CssPageMarginBox {
+                selector: selector.clone(),
+                position,
+                content: None,
+                css: String::new(),
+                vertical_align: None,
+            });
+            boxes.len() - 1
+        }
+    };
+    let entry = &mut boxes[index];
+    let properties = rule
+        .declarations
+        .declarations
+        .iter()
+        .map(|property| (property, false))
+        .chain(
+            rule.declarations
+                .important_declarations
+                .iter()
+                .map(|property| (property, true)),
+        );
+    for (property, important) in properties {
+        match property {
+            Property::Custom(custom) if custom.name.as_ref().eq_ignore_ascii_case("content") => {
+                match content_from_tokens(&custom.value.0) {
+                    Some(ContentSpec::Text(text)) => entry.content = Some(text),
+                    Some(_) => entry.content = Some(String::new()),
+                    None => {}
+                }
+            }
+            Property::VerticalAlign(align) => {
+                entry.vertical_align = Some(vertical_align_mode_from_css(align));
+            }
+            _ => {
+                if let Ok(text) = property.to_css_string(important, PrinterOptions::default()) {
+                    if !entry.css.is_empty() {
+                        entry.css.push_str("; ");
+                    }
+                    entry.css.push_str(&text);
+                }
+            }
+        }
+    }
+}
+
+fn page_margin_box_position(margin_box: &PageMarginBox) -> PageMarginBoxPosition {
+    match margin_box {
+        PageMarginBox::TopLeftCorner => PageMarginBoxPosition::TopLeftCorner,
+        PageMarginBox::TopLeft => PageMarginBoxPosition::TopLeft,
+        PageMarginBox::TopCenter => PageMarginBoxPosition::TopCenter,
+        PageMarginBox::TopRight => PageMarginBoxPosition::TopRight,
+        PageMarginBox::TopRightCorner => PageMarginBoxPosition::TopRightCorner,
+        PageMarginBox::LeftTop => PageMarginBoxPosition::LeftTop,
+        PageMarginBox::LeftMiddle => PageMarginBoxPosition::LeftMiddle,
+        PageMarginBox::LeftBottom => PageMarginBoxPosition::LeftBottom,
+        PageMarginBox::RightTop => PageMarginBoxPosition::RightTop,
+        PageMarginBox::RightMiddle => PageMarginBoxPosition::RightMiddle,
+        PageMarginBox::RightBottom => PageMarginBoxPosition::RightBottom,
+        PageMarginBox::BottomLeftCorner => PageMarginBoxPosition::BottomLeftCorner,
+        PageMarginBox::BottomLeft => PageMarginBoxPosition::BottomLeft,
+        PageMarginBox::BottomCenter => PageMarginBoxPosition::BottomCenter,
+        PageMarginBox::BottomRight => PageMarginBoxPosition::BottomRight,
+        PageMarginBox::BottomRightCorner => PageMarginBoxPosition::BottomRightCorner,
+    }
+}
+
+// One `@font-face` rule: selection descriptors plus `src` candidates in declaration order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CssFontFace {
+    pub descriptor: CssFaceDescriptor,
+    pub sources: Vec<CssFontSource>,
+    // 1-based line and column of the rule.
+    pub line: u32,
+    pub column: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum CssFontSource {
+    Url(String),
+    Local(String),
+}
+
+pub(crate) fn extract_css_font_faces(
+    css: &str,
+    debug: Option<&DebugLogger>,
+    viewport: Option<Size>,
+) -> Vec<CssFontFace> {
+    if !css.contains("@font-face") {
+        return Vec::new();
+    }
+    let Some(sheet) = parse_stylesheet(css, None) else {
+        return Vec::new();
+    };
+    let viewport = viewport.unwrap_or(Size {
+        width: Pt::ZERO,
+        height: Pt::ZERO,
+    });
+    let prefer_print = stylesheet_has_print_media(&sheet.rules);
+    let mut faces = Vec::new();
+    extract_css_font_faces_from_rules(&sheet.rules, &mut faces, viewport, prefer_print, debug);
+    faces
+}
+
+fn extract_css_font_faces_from_rules(
+    rules: &CssRuleList,
+    faces: &mut Vec<CssFontFace>,
+    viewport: Size,
+    prefer_print: bool,
+    debug: Option<&DebugLogger>,
+) {
+    for rule in &rules.0 {
+        match rule {
+            CssRule::FontFace(font_face) => {
+                if let Some(face) = css_font_face_from_rule(font_face) {
+                    faces.push(face);
+                } else if let Some(logger) = debug {
+                    logger.increment("css.font_face.skipped", 1);
+                }
+            }
+            CssRule::Media(media)
+                if media_list_matches(&media.query, viewport, prefer_print, debug) =>
+            {
+                extract_css_font_faces_from_rules(
+                    &media.rules,
+                    faces,
+                    viewport,
+                    prefer_print,
+                    debug,
+                );
+            }
+            _ => {}
+        }
+    }
+}
+
+// Requires `font-family` and at least one `src`; `font-weight` defaults to 400 (a range such
+// as `100 900` is kept for variable fonts) and `font-style` to normal.
+fn css_font_face_from_rule(rule: &FontFaceRule) -> Option<CssFontFace> {
+    let mut family = None;
+    let mut sources = Vec::new();
+    let mut weight = (400, 400);
+    let mut italic = false;
+    for property in &rule.properties {
+        match property {
+            FontFaceProperty::FontFamily(value) => {
+                let raw = value.to_css_string(PrinterOptions::default()).ok()?;
+                let cleaned = raw.trim().trim_matches('"').trim_matches('\'');
+                if !cleaned.is_empty() {
+                    family = Some(cleaned.to_string());
+                }
+            }
+            FontFaceProperty::Source(list) => {
+                sources = list
+                    .iter()
+                    .filter_map(|source| match source {
+                        FontFaceSource::Url(url) => {
+                            Some(CssFontSource::Url(url.url.url.as_ref().to_string()))
+                        }
+                        FontFaceSource::Local(name) => {
+                            let raw = name.to_css_string(PrinterOptions::default()).ok()?;
+                            Some(CssFontSource::Local(
+                                raw.trim().trim_matches('"').trim_matches('\'').to_string(),
+                            ))
+                        }
+                    })
+                    .collect();
+            }
+            FontFaceProperty::FontWeight(value) => {
+                let raw = value.to_css_string(PrinterOptions::default()).ok()?;
+                let weights: Vec<u16> = raw
+                    .split_whitespace()
+                    .filter_map(parse_font_weight_str)
+                    .collect();
+                if let (Some(lo), Some(hi)) = (weights.first(), weights.last()) {
+                    weight = ((*lo).min(*hi), (*lo).max(*hi));
+                }
+            }
+            FontFaceProperty::FontStyle(value) => {
+                let raw = value.to_css_string(PrinterOptions::default()).ok()?;
+                let raw = raw.to_ascii_lowercase();
+                italic = raw.contains("italic") || raw.contains("oblique");
+            }
+            _ => {}
+        }
+    }
+    if sources.is_empty() {
+        return None;
+    }
+    Some(CssFontFace {
+        descriptor: CssFaceDescriptor {
+            family: family?,
+            weight,
+            italic,
+        },
+        sources,
+        line: rule.loc.line + 1,
+        column: rule.loc.column,
+    })
+}
+
 fn page_rule_targets_default(rule: &lightningcss::rules::page::PageRule) -> bool {
     if rule.selectors.is_empty() {
         return true;
@@ -2880,6 +3841,178 @@ fn page_named_size(name: &str) -> Option<Size> {
     }
 }
 
+// Parses with error recovery, so an invalid rule or declaration is skipped instead of
+// discarding the whole sheet. Skipped input is recorded in `diagnostics` when given.
+fn parse_stylesheet<'i>(
+    css: &'i str,
+    diagnostics: Option<&mut CssDiagnostics>,
+) -> Option<StyleSheet<'i>> {
+    let warnings = diagnostics
+        .is_some()
+        .then(|| Arc::new(RwLock::new(Vec::new())));
+    let options = ParserOptions {
+        error_recovery: true,
+        warnings: warnings.clone(),
+        ..ParserOptions::default()
+    };
+    let parsed = StyleSheet::parse(css, options);
+    if let Some(diagnostics) = diagnostics {
+        let mut record = |error: &lightningcss::error::Error<ParserError<'i>>| {
+            let mut diagnostic =
+                CssDiagnostic::new(CssDiagnosticKind::ParseError, error.kind.to_string());
+            if let Some(loc) = &error.loc {
+                diagnostic = diagnostic.at(loc.line, loc.column);
+            }
+            diagnostics.push(diagnostic);
+        };
+        if let Some(skipped) = warnings.as_ref().and_then(|warnings| warnings.read().ok()) {
+            skipped.iter().for_each(&mut record);
+        }
+        if let Err(error) = &parsed {
+            record(error);
+        }
+    }
+    parsed.ok()
+}
+
+// Declarations of one style rule the engine drops, and its selectors it cannot match.
+fn collect_rule_diagnostics(
+    rule: &lightningcss::rules::style::StyleRule,
+    selectors: &str,
+    diagnostics: &mut CssDiagnostics,
+) {
+    let (line, column) = (rule.loc.line, rule.loc.column);
+    for selector in selectors.split(',') {
+        let selector = selector.trim();
+        if parse_selector_pattern(selector).is_none()
+            && !selector.to_ascii_lowercase().contains(":root")
+        {
+            diagnostics.push(
+                CssDiagnostic::new(
+                    CssDiagnosticKind::UnsupportedSelector,
+                    "selector is not supported; the rule matches nothing",
+                )
+                .selector(selector)
+                .at(line, column),
+            );
+        }
+    }
+    let selector = selectors.trim();
+    for property in rule
+        .declarations
+        .declarations
+        .iter()
+        .chain(rule.declarations.important_declarations.iter())
+    {
+        let diagnostic = if let Some(name) = declaration_no_effect_property_name(property) {
+            CssDiagnostic::new(
+                CssDiagnosticKind::UnknownProperty,
+                "property is not supported",
+            )
+            .property(name)
+        } else if let Some((name, raw)) = declaration_dropped_value(property) {
+            CssDiagnostic::new(
+                CssDiagnosticKind::InvalidValue,
+                format!("invalid value {:?}; declaration ignored", raw.trim()),
+            )
+            .property(name)
+        } else {
+            continue;
+        };
+        diagnostics.push(diagnostic.selector(selector).at(line, column));
+    }
+}
+
+// A known property lightningcss could not type and the engine did not apply from the raw
+// tokens either. `var()`/`env()` references and CSS-wide keywords resolve later and are not
+// reported.
+fn declaration_dropped_value(property: &Property) -> Option<(String, String)> {
+    let Property::Unparsed(unparsed) = property else {
+        return None;
+    };
+    if matches!(unparsed.property_id, PropertyId::Custom(_)) {
+        return None;
+    }
+    let raw = tokens_debug_string(&unparsed.value.0);
+    let lower = raw.trim().to_ascii_lowercase();
+    if lower.contains("var(")
+        || lower.contains("env(")
+        || matches!(
+            lower.as_str(),
+            "inherit" | "initial" | "unset" | "revert" | "revert-layer"
+        )
+    {
+        return None;
+    }
+    let mut delta = StyleDelta::default();
+    apply_properties(std::slice::from_ref(property), &mut delta);
+    if !delta.is_empty() {
+        return None;
+    }
+    Some((unparsed.property_id.name().to_string(), raw))
+}
+
+// The known losses of one rule's declarations. Unknown properties are left to the CSS
+// diagnostics, which already report them.
+fn collect_rule_losses(
+    declarations: &lightningcss::declaration::DeclarationBlock,
+    selectors: &str,
+    losses: &mut Vec<Warning>,
+) {
+    let selector = selectors.trim();
+    let mut push = |loss: KnownLoss, message: String| {
+        losses.push(Warning::for_loss(WarningKind::KnownLoss, loss, message).element(selector));
+    };
+    for property in declarations
+        .declarations
+        .iter()
+        .chain(declarations.important_declarations.iter())
+    {
+        if let Some((requested, applied, reason)) = declaration_layout_mode_normalization(property)
+        {
+            push(
+                KnownLoss::LayoutModeNormalized {
+                    requested: requested.clone(),
+                    applied,
+                },
+                format!("display: {requested} laid out as {applied} ({reason})"),
+            );
+        }
+        if let Some(property) = declaration_multicol_fallback_property_name(property) {
+            push(
+                KnownLoss::MulticolSingleColumnFallback {
+                    property: property.clone(),
+                },
+                format!("{property} ignored; content laid out in a single column"),
+            );
+        }
+        if let Some(property) = declaration_filters_effects_fallback_property_name(property) {
+            push(
+                KnownLoss::FiltersEffectsFallback {
+                    property: property.clone(),
+                },
+                format!("{property} effect not drawn"),
+            );
+        }
+        if let Some(property) = declaration_conic_gradient_fallback_property_name(property) {
+            push(
+                KnownLoss::ConicGradientFallback {
+                    property: property.clone(),
+                },
+                format!("conic gradient in {property} not drawn"),
+            );
+        }
+        if let Some(property) = declaration_parsed_no_effect_property_name(property) {
+            push(
+                KnownLoss::DeclarationParsedNoEffect {
+                    property: property.to_string(),
+                },
+                format!("{property} parses but has no effect"),
+            );
+        }
+    }
+}
+
 fn orient_page_size(size: Size, orientation: &str) -> Size {
     match orientation {
         "landscape" => {
@@ -2997,10 +4130,8 @@ fn declaration_multicol_fallback_property_name(property: &Property) -> Option<St
 
 fn is_multicol_fallback_property_name(name: &str) -> bool {
     matches!(
-        name,
-        "columns"
-            | "column-count"
-            | "column-width"
+        name,
+        "column-width"
             | "column-fill"
             | "column-span"
             | "column-rule"
@@ -3305,6 +4436,12 @@ fn is_engine_supported_unknown_property(name: &str) -> bool {
             | "caption-side"
             | "border-spacing"
             | "table-layout"
+            | "writing-mode"
+            | "counter-reset"
+            | "counter-increment"
+            | "counter-set"
+            | "string-set"
+            | "page"
             | "border-radius"
             | "border"
             | "border-top"
@@ -3337,6 +4474,7 @@ fn is_engine_supported_unknown_property(name: &str) -> bool {
             | "widows"
             | "display"
             | "position"
+            | "float"
             | "z-index"
             | "left"
             | "right"
@@ -3375,6 +4513,7 @@ fn is_engine_supported_unknown_property(name: &str) -> bool {
             | "mask-image"
             | "mask-composite"
             | "box-shadow"
+            | "outline-offset"
             | "transform-origin"
             | "content"
             | "size"
@@ -3649,6 +4788,9 @@ fn parse_pseudo_class(raw: &str) -> Option<PseudoClass> {
     if raw.eq_ignore_ascii_case("after") {
         return Some(PseudoClass::After);
     }
+    if raw.eq_ignore_ascii_case("marker") {
+        return Some(PseudoClass::Marker);
+    }
     if let Some(args) = raw.strip_prefix("nth-child(") {
         let args = args.trim_end_matches(')').trim();
         if args.eq_ignore_ascii_case("even") {
@@ -3839,9 +4981,42 @@ fn apply_properties(props: &[Property], delta: &mut StyleDelta) {
                     }
                 }
                 if let Ok(raw) = background.to_css_string(PrinterOptions::default()) {
+                    apply_background_image_layer(&raw, delta);
                     apply_background_from_string(&raw, delta);
                 }
             }
+            Property::BackgroundImage(images) => {
+                if let Ok(raw) = images.to_css_string(PrinterOptions::default()) {
+                    if let Some(source) = parse_background_url(&raw) {
+                        delta.background_image = Some(Some(source));
+                    } else if let Some(paint) = parse_background_paint_str(&raw) {
+                        delta.background_paint = Some(paint);
+                    } else if raw.trim().eq_ignore_ascii_case("none") {
+                        delta.background_image = Some(None);
+                    }
+                }
+            }
+            Property::BackgroundSize(sizes) => {
+                if let Ok(raw) = sizes.to_css_string(PrinterOptions::default())
+                    && let Some(size) = parse_background_size_str(first_layer(&raw))
+                {
+                    delta.background_size = Some(size);
+                }
+            }
+            Property::BackgroundPosition(positions) => {
+                if let Ok(raw) = positions.to_css_string(PrinterOptions::default())
+                    && let Some(position) = parse_background_position_str(first_layer(&raw))
+                {
+                    delta.background_position = Some(position);
+                }
+            }
+            Property::BackgroundRepeat(repeats) => {
+                if let Ok(raw) = repeats.to_css_string(PrinterOptions::default())
+                    && let Some(repeat) = parse_background_repeat_str(first_layer(&raw))
+                {
+                    delta.background_repeat = Some(repeat);
+                }
+            }
             Property::TextTransform(value) => {
                 if let Ok(raw) = value.to_css_string(PrinterOptions::default()) {
                     delta.text_transform = parse_text_transform_str(&raw);
@@ -4220,11 +5395,18 @@ fn apply_properties(props: &[Property], delta: &mut StyleDelta) {
             Property::TextAlign(align) => {
                 delta.text_align = Some(text_align_mode_from_css(align));
             }
+            Property::Direction(direction) => {
+                delta.direction = Some(match direction {
+                    CssDirection::Rtl => DirectionMode::Rtl,
+                    CssDirection::Ltr => DirectionMode::Ltr,
+                });
+            }
             Property::VerticalAlign(align) => {
                 delta.vertical_align = Some(vertical_align_mode_from_css(align));
             }
             Property::Position(position) => {
                 delta.position = Some(position_mode_from_css(position));
+                delta.running = Some(None);
             }
             Property::ZIndex(value) => {
                 delta.z_index = Some(match value {
@@ -4711,6 +5893,26 @@ fn apply_properties(props: &[Property], delta: &mut StyleDelta) {
                 }
                 delta.border_style.right = Some(border_line_style_from_line_style(&value.style));
             }
+            Property::Outline(value) => {
+                delta.outline_width = border_width_spec(&value.width);
+                delta.outline_style = Some(outline_style_from_css(&value.style));
+                delta.outline_color = Some(css_color_to_color(&value.color));
+            }
+            Property::OutlineWidth(value) => {
+                if let Some(width) = border_width_spec(value) {
+                    delta.outline_width = Some(width);
+                }
+            }
+            Property::OutlineStyle(value) => {
+                delta.outline_style = Some(outline_style_from_css(value));
+            }
+            Property::OutlineColor(value) => {
+                if let Some(color) = css_color_to_color(value) {
+                    delta.outline_color = Some(Some(color));
+                } else if matches!(value, CssColor::CurrentColor) {
+                    delta.outline_color = Some(None);
+                }
+            }
             Property::Custom(custom) => {
                 let property_name = custom.name.as_ref().to_ascii_lowercase();
                 apply_custom_property(&property_name, &custom.value.0, delta);
@@ -4719,6 +5921,10 @@ fn apply_properties(props: &[Property], delta: &mut StyleDelta) {
                 PropertyId::FontSize => {
                     apply_inherit_initial_font_size(&unparsed.value.0, delta);
                 }
+                PropertyId::Position => {
+                    // `running(name)` is not a typed position value.
+                    delta.running = Some(running_name_from_tokens(&unparsed.value.0));
+                }
                 PropertyId::LineHeight => {
                     apply_inherit_initial_line_height(&unparsed.value.0, delta);
                 }
@@ -5572,6 +6778,16 @@ fn apply_custom_property(property_name: &str, tokens: &[TokenOrValue], delta: &m
                 delta.border_spacing = Some(spacing);
             }
         }
+        "writing-mode" => {
+            // Only vertical-rl is laid out vertically; vertical-lr and sideways modes keep
+            // horizontal lines.
+            if let Some(value) = first_ident(tokens) {
+                delta.writing_mode = Some(match value.as_str() {
+                    "vertical-rl" | "tb-rl" | "tb" => WritingMode::VerticalRl,
+                    _ => WritingMode::HorizontalTb,
+                });
+            }
+        }
         "table-layout" => {
             if let Some(value) = first_ident(tokens) {
                 delta.table_layout = Some(match value.as_str() {
@@ -5580,6 +6796,11 @@ fn apply_custom_property(property_name: &str, tokens: &[TokenOrValue], delta: &m
                 });
             }
         }
+        "outline-offset" => {
+            if let Some(offset) = length_spec_from_custom_tokens(tokens) {
+                delta.outline_offset = Some(offset);
+            }
+        }
         "border-radius" => {
             let raw = tokens_debug_string(tokens);
             if let Some(radius) = parse_border_radius_str(&raw) {
@@ -5879,6 +7100,7 @@ fn apply_custom_property(property_name: &str, tokens: &[TokenOrValue], delta: &m
             if let Some(value) = first_ident(tokens) {
                 delta.pagination.break_before = Some(match value.as_str() {
                     "page" | "always" => BreakBefore::Page,
+                    "column" | "frame" => BreakBefore::Frame,
                     _ => BreakBefore::Auto,
                 });
             }
@@ -5887,6 +7109,8 @@ fn apply_custom_property(property_name: &str, tokens: &[TokenOrValue], delta: &m
             if let Some(value) = first_ident(tokens) {
                 delta.pagination.break_after = Some(match value.as_str() {
                     "page" | "always" => BreakAfter::Page,
+                    "column" | "frame" => BreakAfter::Frame,
+                    "avoid" | "avoid-page" => BreakAfter::Avoid,
                     _ => BreakAfter::Auto,
                 });
             }
@@ -5899,6 +7123,22 @@ fn apply_custom_property(property_name: &str, tokens: &[TokenOrValue], delta: &m
                 });
             }
         }
+        "column-count" | "columns" => {
+            if let Some(value) = first_integer(tokens) {
+                delta.column_count = Some(value.max(1) as usize);
+            } else if matches!(
+                first_ident(tokens).as_deref(),
+                Some("auto" | "initial" | "unset")
+            ) {
+                delta.column_count = Some(1);
+            }
+        }
+        "float" => match first_ident(tokens).as_deref() {
+            Some("left" | "inline-start") => delta.float = Some(FloatMode::Left),
+            Some("right" | "inline-end") => delta.float = Some(FloatMode::Right),
+            Some("none" | "initial" | "unset") => delta.float = Some(FloatMode::None),
+            _ => {}
+        },
         "orphans" => {
             if let Some(value) = first_integer(tokens) {
                 delta.pagination.orphans = Some(value.max(1) as usize);
@@ -5914,6 +7154,34 @@ fn apply_custom_property(property_name: &str, tokens: &[TokenOrValue], delta: &m
                 delta.content = Some(spec);
             }
         }
+        "counter-reset" => {
+            if let Some(counters) = counter_list_from_tokens(tokens, 0) {
+                delta.counter_reset = Some(counters);
+            }
+        }
+        "counter-increment" => {
+            if let Some(counters) = counter_list_from_tokens(tokens, 1) {
+                delta.counter_increment = Some(counters);
+            }
+        }
+        "page" => {
+            // `auto` keeps the inherited page name.
+            if let Some(name) =
+                first_ident(tokens).filter(|name| !name.eq_ignore_ascii_case("auto"))
+            {
+                delta.page = Some(name);
+            }
+        }
+        "string-set" => {
+            if let Some(assignments) = string_set_from_tokens(tokens) {
+                delta.string_set = Some(assignments);
+            }
+        }
+        "counter-set" => {
+            if let Some(counters) = counter_list_from_tokens(tokens, 0) {
+                delta.counter_set = Some(counters);
+            }
+        }
         _ => {
             if property_name.starts_with("--") {
                 let name = property_name.to_string();
@@ -6060,6 +7328,129 @@ fn first_number(tokens: &[TokenOrValue]) -> Option<f32> {
     None
 }
 
+// `counter-reset`/`counter-increment`/`counter-set`: counter names, each optionally followed
+// by an integer. `none` clears the list.
+fn counter_list_from_tokens(
+    tokens: &[TokenOrValue],
+    default_value: i32,
+) -> Option<Vec<(String, i32)>> {
+    let mut out: Vec<(String, i32)> = Vec::new();
+    for token in tokens {
+        match token {
+            TokenOrValue::Token(Token::Ident(ident)) => {
+                let name = ident.as_ref();
+                if name.eq_ignore_ascii_case("none")
+                    || name.eq_ignore_ascii_case("initial")
+                    || name.eq_ignore_ascii_case("unset")
+                {
+                    return Some(Vec::new());
+                }
+                out.push((name.to_string(), default_value));
+            }
+            TokenOrValue::Token(Token::Number {
+                value, int_value, ..
+            }) => {
+                let last = out.last_mut()?;
+                last.1 = int_value.unwrap_or(*value as i32);
+            }
+            TokenOrValue::Token(Token::WhiteSpace(_)) => continue,
+            _ => return None,
+        }
+    }
+    Some(out)
+}
+
+// `counter(name, style)` and `counters(name, "sep", style)` become a field list between two
+// `COUNTER_MARK`s: name, style, and for `counters()` the separator. The story builder swaps in
+// the counter values for the element.
+fn counter_reference_from_function(args: &[TokenOrValue], nested: bool) -> Option<String> {
+    let mut name: Option<String> = None;
+    let mut separator: Option<String> = None;
+    let mut list_style = "decimal".to_string();
+    for arg in args {
+        match arg {
+            TokenOrValue::Token(Token::Ident(ident)) if name.is_none() => {
+                name = Some(ident.as_ref().to_string());
+            }
+            TokenOrValue::Token(Token::Ident(ident)) => {
+                list_style = ident.as_ref().to_ascii_lowercase();
+            }
+            TokenOrValue::Token(Token::String(value)) if nested => {
+                separator = Some(value.as_ref().to_string());
+            }
+            _ => {}
+        }
+    }
+    let name = name?;
+    let mut out = String::new();
+    out.push(COUNTER_MARK);
+    out.push_str(&name);
+    out.push(COUNTER_FIELD);
+    out.push_str(&list_style);
+    if nested {
+        out.push(COUNTER_FIELD);
+        out.push_str(separator.as_deref().unwrap_or(""));
+    }
+    out.push(COUNTER_MARK);
+    Some(out)
+}
+
+fn running_name_from_tokens(tokens: &[TokenOrValue]) -> Option<String> {
+    tokens.iter().find_map(|token| match token {
+        TokenOrValue::Function(func) if func.name.as_ref().eq_ignore_ascii_case("running") => {
+            func.arguments.0.iter().find_map(|arg| match arg {
+                TokenOrValue::Token(Token::Ident(ident)) => Some(ident.as_ref().to_string()),
+                _ => None,
+            })
+        }
+        _ => None,
+    })
+}
+
+// `string-set: chapter content(), section "Part " content()`; `none` clears the assignments.
+fn string_set_from_tokens(tokens: &[TokenOrValue]) -> Option<Vec<(String, Vec<StringSetPart>)>> {
+    let mut out: Vec<(String, Vec<StringSetPart>)> = Vec::new();
+    let mut expect_name = true;
+    for token in tokens {
+        match token {
+            TokenOrValue::Token(Token::Ident(ident)) if expect_name => {
+                if ident.as_ref().eq_ignore_ascii_case("none") && out.is_empty() {
+                    return Some(Vec::new());
+                }
+                out.push((ident.as_ref().to_string(), Vec::new()));
+                expect_name = false;
+            }
+            TokenOrValue::Token(Token::String(value)) if !expect_name => {
+                out.last_mut()?
+                    .1
+                    .push(StringSetPart::Text(value.as_ref().to_string()));
+            }
+            TokenOrValue::Function(func)
+                if !expect_name && func.name.as_ref().eq_ignore_ascii_case("content") =>
+            {
+                out.last_mut()?.1.push(StringSetPart::Content);
+            }
+            TokenOrValue::Token(Token::Comma) => expect_name = true,
+            TokenOrValue::Token(Token::WhiteSpace(_)) => continue,
+            _ => return None,
+        }
+    }
+    if out.is_empty() { None } else { Some(out) }
+}
+
+// `string(name, policy)` and `element(name, policy)`; the policy defaults to `first`.
+fn page_reference_from_function(kind: &str, args: &[TokenOrValue]) -> Option<String> {
+    let mut idents = args.iter().filter_map(|arg| match arg {
+        TokenOrValue::Token(Token::Ident(ident)) => Some(ident.as_ref()),
+        _ => None,
+    });
+    let name = idents.next()?;
+    let policy = idents.next().unwrap_or("first").to_ascii_lowercase();
+    Some(format!(
+        "{PAGE_REF_MARK}{kind}{COUNTER_FIELD}{name}{COUNTER_FIELD}{policy}{PAGE_REF_MARK}"
+    ))
+}
+
 fn content_from_tokens(tokens: &[TokenOrValue]) -> Option<ContentSpec> {
     let mut out = String::new();
     let mut saw_string = false;
@@ -6085,6 +7476,47 @@ fn content_from_tokens(tokens: &[TokenOrValue]) -> Option<ContentSpec> {
                 out.push_str(value.as_ref());
                 saw_string = true;
             }
+            TokenOrValue::Function(func) if func.name.as_ref().eq_ignore_ascii_case("leader") => {
+                let fill = func.arguments.0.iter().find_map(|arg| match arg {
+                    TokenOrValue::Token(Token::String(value)) => Some(value.as_ref().to_string()),
+                    TokenOrValue::Token(Token::Ident(ident)) => {
+                        match ident.as_ref().to_ascii_lowercase().as_str() {
+                            "dotted" => Some(". ".to_string()),
+                            "solid" => Some("_".to_string()),
+                            "space" => Some(" ".to_string()),
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                });
+                if let Some(fill) = fill.filter(|fill| !fill.is_empty()) {
+                    out.push(LEADER_MARK);
+                    out.push_str(&fill);
+                    out.push(LEADER_MARK);
+                    saw_string = true;
+                }
+            }
+            TokenOrValue::Function(func)
+                if func.name.as_ref().eq_ignore_ascii_case("counter")
+                    || func.name.as_ref().eq_ignore_ascii_case("counters") =>
+            {
+                let nested = func.name.as_ref().eq_ignore_ascii_case("counters");
+                if let Some(reference) = counter_reference_from_function(&func.arguments.0, nested)
+                {
+                    out.push_str(&reference);
+                    saw_string = true;
+                }
+            }
+            TokenOrValue::Function(func)
+                if func.name.as_ref().eq_ignore_ascii_case("string")
+                    || func.name.as_ref().eq_ignore_ascii_case("element") =>
+            {
+                let kind = func.name.as_ref().to_ascii_lowercase();
+                if let Some(reference) = page_reference_from_function(&kind, &func.arguments.0) {
+                    out.push_str(&reference);
+                    saw_string = true;
+                }
+            }
             TokenOrValue::Token(Token::WhiteSpace(_)) => continue,
             _ => {}
         }
@@ -6877,7 +8309,7 @@ fn filter_spec_debug(spec: PaintFilterSpec) -> String {
     }
 }
 
-fn debug_style_json(style: &ComputedStyle) -> String {
+pub(crate) fn debug_style_json(style: &ComputedStyle) -> String {
     let mut fields = Vec::new();
     fields.push(format!(
         "\"display\":{}",
@@ -6921,6 +8353,14 @@ fn debug_style_json(style: &ComputedStyle) -> String {
         "\"text_align\":{}",
         json_string(&format!("{:?}", style.text_align))
     ));
+    fields.push(format!(
+        "\"direction\":{}",
+        json_string(&format!("{:?}", style.direction))
+    ));
+    fields.push(format!(
+        "\"writing_mode\":{}",
+        json_string(&format!("{:?}", style.writing_mode))
+    ));
     fields.push(format!(
         "\"vertical_align\":{}",
         json_string(&format!("{:?}", style.vertical_align))
@@ -6947,6 +8387,9 @@ fn debug_style_json(style: &ComputedStyle) -> String {
             json_string(&format!("{:?}", paint))
         ));
     }
+    if let Some(image) = &style.background_image {
+        fields.push(format!("\"background_image\":{}", json_string(image)));
+    }
     fields.push(format!(
         "\"clip_path_inset\":{}",
         json_opt_string(style.clip_path_inset.map(clip_path_inset_debug))
@@ -7209,6 +8652,18 @@ fn apply_delta(
     if let Some(paint) = &delta.background_paint {
         computed.background_paint = Some(paint.clone());
     }
+    if let Some(image) = &delta.background_image {
+        computed.background_image = image.clone();
+    }
+    if let Some(size) = delta.background_size {
+        computed.background_size = size;
+    }
+    if let Some(position) = delta.background_position {
+        computed.background_position = position;
+    }
+    if let Some(repeat) = delta.background_repeat {
+        computed.background_repeat = repeat;
+    }
     if let Some(var) = &delta.background_color_var {
         computed.pending_background_color_var = Some(var.clone());
     }
@@ -7330,6 +8785,24 @@ fn apply_delta(
     if let Some(list_style_type) = delta.list_style_type {
         computed.list_style_type = list_style_type;
     }
+    if let Some(counters) = &delta.counter_reset {
+        computed.counter_reset = counters.clone();
+    }
+    if let Some(counters) = &delta.counter_increment {
+        computed.counter_increment = counters.clone();
+    }
+    if let Some(counters) = &delta.counter_set {
+        computed.counter_set = counters.clone();
+    }
+    if let Some(running) = &delta.running {
+        computed.running = running.clone();
+    }
+    if let Some(assignments) = &delta.string_set {
+        computed.string_set = assignments.clone();
+    }
+    if let Some(page) = &delta.page {
+        computed.page = Some(page.clone());
+    }
     if let Some(spacing) = &delta.letter_spacing {
         let resolved = normalize_length_spec(*spacing, LengthSpec::Absolute(parent.letter_spacing));
         computed.letter_spacing = match resolved {
@@ -7354,9 +8827,11 @@ fn apply_delta(
     }
     if let Some(value) = delta.pagination.orphans {
         computed.pagination.orphans = value;
+        computed.orphans_widows_declared = true;
     }
     if let Some(value) = delta.pagination.widows {
         computed.pagination.widows = value;
+        computed.orphans_widows_declared = true;
     }
 
     apply_edge_delta(&mut computed.margin, &delta.margin, &parent.margin);
@@ -7408,6 +8883,12 @@ fn apply_delta(
     if let Some(align) = delta.text_align {
         computed.text_align = align;
     }
+    if let Some(direction) = delta.direction {
+        computed.direction = direction;
+    }
+    if let Some(mode) = delta.writing_mode {
+        computed.writing_mode = mode;
+    }
     if let Some(align) = delta.vertical_align {
         computed.vertical_align = align;
     }
@@ -7552,6 +9033,18 @@ fn apply_delta(
     if let Some(mode) = delta.mix_blend_mode {
         computed.mix_blend_mode = mode;
     }
+    if let Some(width) = delta.outline_width {
+        computed.outline_width = width;
+    }
+    if let Some(style) = delta.outline_style {
+        computed.outline_style = style;
+    }
+    if let Some(color) = delta.outline_color {
+        computed.outline_color = color;
+    }
+    if let Some(offset) = delta.outline_offset {
+        computed.outline_offset = offset;
+    }
     if let Some(clip_path) = delta.clip_path {
         computed.clip_path_inset = match clip_path {
             ClipPathSpec::Inset(spec) => Some(spec),
@@ -7578,6 +9071,9 @@ fn apply_delta(
     if let Some(position) = delta.position {
         computed.position = position;
     }
+    if let Some(float) = delta.float {
+        computed.float = float;
+    }
     if let Some(z_index) = delta.z_index {
         computed.z_index = z_index;
     }
@@ -7647,6 +9143,9 @@ fn apply_delta(
     if let Some(rows) = delta.grid_rows {
         computed.grid_rows = if rows == 0 { None } else { Some(rows) };
     }
+    if let Some(count) = delta.column_count {
+        computed.column_count = count.max(1);
+    }
     if let Some(column_start) = delta.grid_column_start {
         computed.grid_column_start = if column_start == 0 {
             None
@@ -9256,13 +10755,17 @@ fn transform_ops_from_translate(value: &css_transform::Translate) -> Option<Vec<
 }
 
 fn transform_ops_from_rotate(value: &css_transform::Rotate) -> Option<Vec<CssTransformOp>> {
-    if value.x.abs() > f32::EPSILON || value.y.abs() > f32::EPSILON || value.z.abs() <= f32::EPSILON
-    {
-        return None;
+    match value {
+        css_transform::Rotate::None => Some(Vec::new()),
+        css_transform::Rotate::XYZ { x, y, z, angle } => {
+            if x.abs() > f32::EPSILON || y.abs() > f32::EPSILON || z.abs() <= f32::EPSILON {
+                return None;
+            }
+            Some(vec![CssTransformOp::Rotate {
+                radians: angle.to_radians() * z.signum(),
+            }])
+        }
     }
-    Some(vec![CssTransformOp::Rotate {
-        radians: value.angle.to_radians() * value.z.signum(),
-    }])
 }
 
 fn transform_ops_from_scale(value: &css_transform::Scale) -> Option<Vec<CssTransformOp>> {
@@ -10262,6 +11765,163 @@ fn apply_background_from_string(raw: &str, delta: &mut StyleDelta) {
     }
 }
 
+// The `background` shorthand resets the image layer; only the first layer with a
+// `url(...)` is drawn.
+fn apply_background_image_layer(raw: &str, delta: &mut StyleDelta) {
+    let layers = split_args(raw);
+    let layer = layers
+        .iter()
+        .find(|layer| parse_background_url(layer).is_some())
+        .cloned()
+        .unwrap_or_default();
+    let mut position_tokens: Vec<String> = Vec::new();
+    let mut size_tokens: Vec<String> = Vec::new();
+    let mut repeat_tokens: Vec<String> = Vec::new();
+    let mut after_slash = false;
+    for token in split_ws_preserve_parens(&layer) {
+        let lower = token.trim().to_ascii_lowercase();
+        if lower.starts_with("url(") {
+            continue;
+        }
+        let (before, after) = match lower.split_once('/') {
+            Some((before, after)) => (before.trim().to_string(), Some(after.trim().to_string())),
+            None => (lower.clone(), None),
+        };
+        for (part, is_size) in [(Some(before), after_slash), (after, true)] {
+            let Some(part) = part.filter(|part| !part.is_empty()) else {
+                continue;
+            };
+            if matches!(
+                part.as_str(),
+                "repeat" | "repeat-x" | "repeat-y" | "no-repeat" | "space" | "round"
+            ) {
+                repeat_tokens.push(part);
+            } else if is_size {
+                if matches!(part.as_str(), "auto" | "cover" | "contain")
+                    || length_spec_from_string(&part).is_some()
+                {
+                    size_tokens.push(part);
+                }
+            } else if matches!(
+                part.as_str(),
+                "left" | "right" | "top" | "bottom" | "center"
+            ) || length_spec_from_string(&part).is_some()
+            {
+                position_tokens.push(part);
+            }
+        }
+        if lower.contains('/') {
+            after_slash = true;
+        }
+    }
+    delta.background_image = Some(parse_background_url(&layer));
+    delta.background_size =
+        Some(parse_background_size_str(&size_tokens.join(" ")).unwrap_or(BackgroundSize::Auto));
+    delta.background_position = Some(
+        parse_background_position_str(&position_tokens.join(" "))
+            .unwrap_or((LengthSpec::Percent(0.0), LengthSpec::Percent(0.0))),
+    );
+    delta.background_repeat = Some(
+        parse_background_repeat_str(&repeat_tokens.join(" ")).unwrap_or(BackgroundRepeat::Repeat),
+    );
+}
+
+fn first_layer(raw: &str) -> &str {
+    raw.split(',').next().unwrap_or(raw).trim()
+}
+
+fn parse_background_url(raw: &str) -> Option<String> {
+    let lower = raw.to_ascii_lowercase();
+    let start = lower.find("url(")? + 4;
+    let end = start + raw[start..].find(')')?;
+    let source = raw[start..end]
+        .trim()
+        .trim_matches(|c| c == '"' || c == '\'')
+        .trim();
+    (!source.is_empty()).then(|| source.to_string())
+}
+
+fn parse_background_size_str(raw: &str) -> Option<BackgroundSize> {
+    let tokens: Vec<&str> = raw.split_whitespace().collect();
+    let size = |token: &str| {
+        if token.eq_ignore_ascii_case("auto") {
+            Some(LengthSpec::Auto)
+        } else {
+            length_spec_from_string(token)
+        }
+    };
+    match tokens.as_slice() {
+        [] => None,
+        [one] if one.eq_ignore_ascii_case("cover") => Some(BackgroundSize::Cover),
+        [one] if one.eq_ignore_ascii_case("contain") => Some(BackgroundSize::Contain),
+        [one] if one.eq_ignore_ascii_case("auto") => Some(BackgroundSize::Auto),
+        [one] => Some(BackgroundSize::Explicit(size(one)?, LengthSpec::Auto)),
+        [width, height, ..] => Some(BackgroundSize::Explicit(size(width)?, size(height)?)),
+    }
+}
+
+fn parse_background_position_str(raw: &str) -> Option<(LengthSpec, LengthSpec)> {
+    fn keyword(token: &str) -> Option<(bool, LengthSpec)> {
+        // (is vertical, offset)
+        match token {
+            "left" => Some((false, LengthSpec::Percent(0.0))),
+            "right" => Some((false, LengthSpec::Percent(1.0))),
+            "top" => Some((true, LengthSpec::Percent(0.0))),
+            "bottom" => Some((true, LengthSpec::Percent(1.0))),
+            _ => None,
+        }
+    }
+    let center = LengthSpec::Percent(0.5);
+    let tokens: Vec<String> = raw
+        .split_whitespace()
+        .map(|token| token.to_ascii_lowercase())
+        .collect();
+    let value = |token: &str| {
+        if token == "center" {
+            Some(center)
+        } else {
+            keyword(token)
+                .map(|(_, spec)| spec)
+                .or_else(|| length_spec_from_string(token))
+        }
+    };
+    match tokens.as_slice() {
+        [] => None,
+        [one] => match keyword(one) {
+            Some((true, spec)) => Some((center, spec)),
+            Some((false, spec)) => Some((spec, center)),
+            None => Some((value(one)?, center)),
+        },
+        [first, second, ..] => {
+            let swap = matches!(keyword(first), Some((true, _)))
+                || matches!(keyword(second), Some((false, _)));
+            if swap {
+                Some((value(second)?, value(first)?))
+            } else {
+                Some((value(first)?, value(second)?))
+            }
+        }
+    }
+}
+
+fn parse_background_repeat_str(raw: &str) -> Option<BackgroundRepeat> {
+    let repeats = |token: &str| !token.eq_ignore_ascii_case("no-repeat");
+    let tokens: Vec<&str> = raw.split_whitespace().collect();
+    match tokens.as_slice() {
+        [] => None,
+        [one] if one.eq_ignore_ascii_case("repeat-x") => Some(BackgroundRepeat::RepeatX),
+        [one] if one.eq_ignore_ascii_case("repeat-y") => Some(BackgroundRepeat::RepeatY),
+        [one] if one.eq_ignore_ascii_case("no-repeat") => Some(BackgroundRepeat::NoRepeat),
+        [_] => Some(BackgroundRepeat::Repeat),
+        [x, y, ..] => Some(match (repeats(x), repeats(y)) {
+            (true, true) => BackgroundRepeat::Repeat,
+            (true, false) => BackgroundRepeat::RepeatX,
+            (false, true) => BackgroundRepeat::RepeatY,
+            (false, false) => BackgroundRepeat::NoRepeat,
+        }),
+    }
+}
+
 fn var_name_from_string(raw: &str) -> Option<String> {
     let lower = raw.to_ascii_lowercase();
     let start = lower.find("var(")?;
@@ -11277,12 +12937,66 @@ fn parse_color_string(raw: &str) -> Option<(Color, f32)> {
         };
         return Some((Color::rgb(r, g, b), a));
     }
+    if lower.starts_with("device-cmyk(") {
+        return parse_device_cmyk(s).map(|[c, m, y, k]| (Color::cmyk(c, m, y, k), 1.0));
+    }
+    if lower.starts_with("fb-spot(") {
+        return parse_fb_spot(s).map(|color| (color, 1.0));
+    }
     if let Ok(color) = CssColor::parse_string(s) {
         return css_color_to_color_with_alpha(&color);
     }
     None
 }
 
+// `device-cmyk(c m y k)` with numbers (0..1) or percentages, space- or comma-separated.
+fn parse_device_cmyk(raw: &str) -> Option<[f32; 4]> {
+    let open = raw.find('(')?;
+    let inner = raw[open + 1..].trim_end().strip_suffix(')')?;
+    // An alpha after `/` is not representable in DeviceCMYK; drop it.
+    let inner = inner.split('/').next().unwrap_or(inner);
+    let parts = inner
+        .split(|ch: char| ch == ',' || ch.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .map(parse_unit_component)
+        .collect::<Option<Vec<_>>>()?;
+    match parts.as_slice() {
+        [c, m, y, k] => Some([*c, *m, *y, *k]),
+        _ => None,
+    }
+}
+
+// `fb-spot("PANTONE 185 C", device-cmyk(...), 50%)`: a named Separation colorant with its
+// full-tint CMYK alternate and an optional tint (default 100%).
+fn parse_fb_spot(raw: &str) -> Option<Color> {
+    let open = raw.find('(')?;
+    let inner = raw[open + 1..].trim_end().strip_suffix(')')?;
+    let args = split_args(inner);
+    let name = args
+        .first()?
+        .trim()
+        .trim_matches(|ch| ch == '"' || ch == '\'');
+    let mut alternate = None;
+    let mut tint = 1.0;
+    for arg in &args[1..] {
+        if arg.to_ascii_lowercase().starts_with("device-cmyk(") {
+            alternate = Some(parse_device_cmyk(arg)?);
+        } else {
+            tint = parse_unit_component(arg)?;
+        }
+    }
+    Color::spot(name, tint, alternate?)
+}
+
+fn parse_unit_component(raw: &str) -> Option<f32> {
+    let raw = raw.trim();
+    let value = match raw.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f32>().ok()? / 100.0,
+        None => raw.parse::<f32>().ok()?,
+    };
+    value.is_finite().then(|| value.clamp(0.0, 1.0))
+}
+
 fn split_args(raw: &str) -> Vec<String> {
     let mut out = Vec::new();
     let mut current = String::new();
@@ -11462,8 +13176,10 @@ fn font_spec_from_family(families: &[FontFamily]) -> Option<FontSpec> {
 fn text_align_mode_from_css(value: &TextAlign) -> TextAlignMode {
     match value {
         TextAlign::Center => TextAlignMode::Center,
-        TextAlign::Right | TextAlign::End => TextAlignMode::Right,
-        _ => TextAlignMode::Left,
+        TextAlign::Left => TextAlignMode::Left,
+        TextAlign::Right => TextAlignMode::Right,
+        TextAlign::End => TextAlignMode::End,
+        _ => TextAlignMode::Start,
     }
 }
 
@@ -11487,7 +13203,29 @@ fn word_break_mode_from_overflow_wrap(value: &OverflowWrap) -> WordBreakMode {
 fn list_style_type_mode_from_css(value: &ListStyleType) -> ListStyleTypeMode {
     match value {
         ListStyleType::None => ListStyleTypeMode::None,
-        _ => ListStyleTypeMode::Auto,
+        _ => value
+            .to_css_string(PrinterOptions::default())
+            .ok()
+            .and_then(|raw| ListStyleTypeMode::from_keyword(&raw))
+            .unwrap_or(ListStyleTypeMode::Auto),
+    }
+}
+
+// HTML list `type` values; the single-letter forms are case-sensitive.
+fn list_style_type_from_html_type(value: &str) -> Option<ListStyleTypeMode> {
+    match value.trim() {
+        "1" => Some(ListStyleTypeMode::Decimal),
+        "a" => Some(ListStyleTypeMode::LowerAlpha),
+        "A" => Some(ListStyleTypeMode::UpperAlpha),
+        "i" => Some(ListStyleTypeMode::LowerRoman),
+        "I" => Some(ListStyleTypeMode::UpperRoman),
+        other => match other.to_ascii_lowercase().as_str() {
+            "disc" => Some(ListStyleTypeMode::Disc),
+            "circle" => Some(ListStyleTypeMode::Circle),
+            "square" => Some(ListStyleTypeMode::Square),
+            "none" => Some(ListStyleTypeMode::None),
+            _ => None,
+        },
     }
 }
 
@@ -11507,6 +13245,16 @@ fn border_line_style_from_line_style(value: &LineStyle) -> BorderLineStyle {
     }
 }
 
+fn outline_style_from_css(value: &CssOutlineStyle) -> Option<OutlineStyle> {
+    match value {
+        CssOutlineStyle::Auto => Some(OutlineStyle::Solid),
+        CssOutlineStyle::LineStyle(LineStyle::None | LineStyle::Hidden) => None,
+        CssOutlineStyle::LineStyle(LineStyle::Dashed) => Some(OutlineStyle::Dashed),
+        CssOutlineStyle::LineStyle(LineStyle::Dotted) => Some(OutlineStyle::Dotted),
+        CssOutlineStyle::LineStyle(_) => Some(OutlineStyle::Solid),
+    }
+}
+
 fn border_line_style_from_ident(value: &str) -> Option<BorderLineStyle> {
     match value {
         "none" | "hidden" => Some(BorderLineStyle::None),
@@ -12529,6 +14277,13 @@ fn apply_inherit_initial_display(tokens: &[TokenOrValue], delta: &mut StyleDelta
 }
 
 impl StyleDelta {
+    fn uses_counters(&self) -> bool {
+        self.counter_reset.is_some()
+            || self.counter_increment.is_some()
+            || self.counter_set.is_some()
+            || matches!(&self.content, Some(ContentSpec::Text(text)) if text.contains(COUNTER_MARK))
+    }
+
     fn is_empty(&self) -> bool {
         self.font_size.is_none()
             && self.line_height.is_none()
@@ -12554,6 +14309,8 @@ impl StyleDelta {
             && self.max_height_var.is_none()
             && self.max_width_var.is_none()
             && self.text_align.is_none()
+            && self.direction.is_none()
+            && self.writing_mode.is_none()
             && self.vertical_align.is_none()
             && self.font_weight.is_none()
             && self.font_style.is_none()
@@ -12572,6 +14329,12 @@ impl StyleDelta {
             && self.content.is_none()
             && self.word_break.is_none()
             && self.list_style_type.is_none()
+            && self.counter_reset.is_none()
+            && self.counter_increment.is_none()
+            && self.counter_set.is_none()
+            && self.running.is_none()
+            && self.string_set.is_none()
+            && self.page.is_none()
             && self.letter_spacing.is_none()
             && self.border_width.top.is_none()
             && self.border_width.right.is_none()
@@ -12598,10 +14361,18 @@ impl StyleDelta {
             && self.font_name.is_none()
             && self.font_name_var.is_none()
             && self.box_shadow.is_none()
+            && self.outline_width.is_none()
+            && self.outline_style.is_none()
+            && self.outline_color.is_none()
+            && self.outline_offset.is_none()
             && self.paint_filter.is_none()
             && self.backdrop_filter.is_none()
             && self.mix_blend_mode.is_none()
             && self.background_paint.is_none()
+            && self.background_image.is_none()
+            && self.background_size.is_none()
+            && self.background_position.is_none()
+            && self.background_repeat.is_none()
             && self.clip_path.is_none()
             && self.pagination.break_before.is_none()
             && self.pagination.break_after.is_none()
@@ -12627,6 +14398,7 @@ impl StyleDelta {
             && self.white_space.is_none()
             && self.display.is_none()
             && self.position.is_none()
+            && self.float.is_none()
             && self.z_index.is_none()
             && self.box_sizing.is_none()
             && self.inset_left.is_none()
@@ -12648,6 +14420,7 @@ impl StyleDelta {
             && self.align_content.is_none()
             && self.grid_columns.is_none()
             && self.grid_rows.is_none()
+            && self.column_count.is_none()
             && self.grid_column_start.is_none()
             && self.grid_row_start.is_none()
             && self.gap.is_none()
@@ -12683,6 +14456,7 @@ fn default_ua_css() -> &'static str {
     div, p, section, article, header, footer, aside, nav, main, blockquote,
     h1, h2, h3, h4, h5, h6,
     ul, ol, dl, dt, dd, li, table, thead, tbody, tfoot, tr, td, th, pre, hr { display: block; }
+    h1, h2, h3, h4, h5, h6 { break-after: avoid; }
     table { break-inside: avoid; }
     thead { break-inside: avoid; }
     "#
@@ -12814,6 +14588,109 @@ mod tests {
         assert_eq!(p_style.color, Color::rgb(0.0, 1.0, 0.0));
     }
 
+    #[test]
+    fn counter_properties_and_marker_rules_parse() {
+        let css = "ol { counter-reset: clause 4 item; list-style-type: lower-roman; } \
+            li { counter-increment: clause; } \
+            li::before { content: counters(clause, \".\", upper-alpha) \" \"; } \
+            li::marker { color: red; }";
+        let resolver = StyleResolver::new(css);
+        assert!(resolver.uses_counters());
+        let root = resolver.default_style();
+        let ol_info = element("ol", None, &[]);
+        let ol_style = resolver.compute_style(&ol_info, &root, None, &[]);
+        assert_eq!(
+            ol_style.counter_reset,
+            vec![("clause".to_string(), 4), ("item".to_string(), 0)]
+        );
+        assert_eq!(ol_style.list_style_type, ListStyleTypeMode::LowerRoman);
+
+        let li_info = element("li", None, &[]);
+        let ancestors = [ol_info];
+        let li_style = resolver.compute_style(&li_info, &ol_style, None, &ancestors);
+        assert_eq!(li_style.counter_increment, vec![("clause".to_string(), 1)]);
+        assert_eq!(li_style.list_style_type, ListStyleTypeMode::LowerRoman);
+        let before = resolver
+            .compute_pseudo_style(&li_info, &li_style, &ancestors, PseudoTarget::Before)
+            .expect("before");
+        assert_eq!(
+            before.content.as_deref(),
+            Some(
+                format!(
+                    "{COUNTER_MARK}clause{COUNTER_FIELD}upper-alpha{COUNTER_FIELD}.{COUNTER_MARK} "
+                )
+                .as_str()
+            )
+        );
+        let marker = resolver
+            .compute_pseudo_style(&li_info, &li_style, &ancestors, PseudoTarget::Marker)
+            .expect("marker");
+        assert!(marker.content.is_none());
+        assert_eq!(marker.color, Color::rgb(1.0, 0.0, 0.0));
+        assert!(!StyleResolver::new("p { color: red; }").uses_counters());
+    }
+
+    #[test]
+    fn page_margin_boxes_and_string_set_parse() {
+        let css = "@page { margin: 1in; \
+                @top-center { content: string(chapter, last); font-size: 9pt; } \
+                @bottom-right { content: \"Page \" counter(page); vertical-align: bottom; } \
+                @bottom-left { color: red; } } \
+            @page :first { @top-right { content: \"first\"; } } \
+            h1 { string-set: chapter content(), part \"Part \" content(); } \
+            .brand { position: running(brand); }";
+        let all_boxes = extract_css_page_margin_boxes(css, None, None);
+        let boxes = page_margin_boxes_for(&all_boxes, None, 3);
+        assert_eq!(boxes.len(), 2);
+        assert_eq!(boxes[0].position, PageMarginBoxPosition::TopCenter);
+        assert_eq!(
+            boxes[0].content.as_deref(),
+            Some(
+                format!(
+                    "{PAGE_REF_MARK}string{COUNTER_FIELD}chapter{COUNTER_FIELD}last{PAGE_REF_MARK}"
+                )
+                .as_str()
+            )
+        );
+        assert!(boxes[0].css.contains("font-size"), "{}", boxes[0].css);
+        assert_eq!(boxes[1].position, PageMarginBoxPosition::BottomRight);
+        assert_eq!(boxes[1].vertical_align, Some(VerticalAlignMode::Bottom));
+        let first = page_margin_boxes_for(&all_boxes, None, 1);
+        assert_eq!(first.len(), 3);
+        assert_eq!(first[2].position, PageMarginBoxPosition::TopRight);
+
+        let facing = "@page :left { margin-left: 1in; @bottom-left { content: \"even\"; } } \
+            @page wide:right { margin-right: 2in; } @page :left:right { margin: 0; }";
+        let setups = extract_css_named_page_setups(facing, None, None);
+        assert_eq!(setups.len(), 2);
+        assert_eq!(setups[0].0.parity, Some(PageParity::Even));
+        assert_eq!(setups[1].0.name.as_deref(), Some("wide"));
+        assert_eq!(setups[1].0.parity, Some(PageParity::Odd));
+        let facing_boxes = extract_css_page_margin_boxes(facing, None, None);
+        assert!(page_margin_boxes_for(&facing_boxes, None, 3).is_empty());
+        assert_eq!(page_margin_boxes_for(&facing_boxes, None, 4).len(), 1);
+
+        let resolver = StyleResolver::new(css);
+        let root = resolver.default_style();
+        let h1 = resolver.compute_style(&element("h1", None, &[]), &root, None, &[]);
+        assert_eq!(
+            h1.string_set,
+            vec![
+                ("chapter".to_string(), vec![StringSetPart::Content]),
+                (
+                    "part".to_string(),
+                    vec![
+                        StringSetPart::Text("Part ".to_string()),
+                        StringSetPart::Content
+                    ]
+                ),
+            ]
+        );
+        assert!(h1.running.is_none());
+        let brand = resolver.compute_style(&element("div", None, &["brand"]), &root, None, &[]);
+        assert_eq!(brand.running.as_deref(), Some("brand"));
+    }
+
     #[test]
     fn ua_css_sets_img_display_inline_block() {
         let resolver = StyleResolver::new("");
@@ -12965,6 +14842,44 @@ mod tests {
         assert!(filter.blur_radius > Pt::ZERO);
     }
 
+    #[test]
+    fn background_image_longhands_and_shorthand_resolve() {
+        let css = ".a { background-image: url(\"logo.png\"); background-size: contain; \
+            background-position: right bottom; background-repeat: repeat-y; } \
+            .b { background: #eee url(tile.png) no-repeat center / 50% auto; } \
+            .c { background-image: linear-gradient(red, blue); }";
+        let resolver = StyleResolver::new(css);
+        let root = resolver.default_style();
+        let a = resolver.compute_style(&element("div", None, &["a"]), &root, None, &[]);
+        assert_eq!(a.background_image.as_deref(), Some("logo.png"));
+        assert_eq!(a.background_size, BackgroundSize::Contain);
+        assert_eq!(
+            a.background_position,
+            (LengthSpec::Percent(1.0), LengthSpec::Percent(1.0))
+        );
+        assert_eq!(a.background_repeat, BackgroundRepeat::RepeatY);
+
+        let b = resolver.compute_style(&element("div", None, &["b"]), &root, None, &[]);
+        assert_eq!(b.background_image.as_deref(), Some("tile.png"));
+        assert!(b.background_color.is_some());
+        assert_eq!(
+            b.background_size,
+            BackgroundSize::Explicit(LengthSpec::Percent(0.5), LengthSpec::Auto)
+        );
+        assert_eq!(
+            b.background_position,
+            (LengthSpec::Percent(0.5), LengthSpec::Percent(0.5))
+        );
+        assert_eq!(b.background_repeat, BackgroundRepeat::NoRepeat);
+
+        let c = resolver.compute_style(&element("div", None, &["c"]), &root, None, &[]);
+        assert!(c.background_image.is_none());
+        assert!(matches!(
+            c.background_paint,
+            Some(BackgroundPaint::LinearGradient { .. })
+        ));
+    }
+
     #[test]
     fn mix_blend_mode_property_resolves_on_computed_style() {
         let css = ".x { mix-blend-mode: screen; }";
@@ -0,0 +1,354 @@
+// TrueType (glyf) subsetting for embedded fonts. Glyph ids are preserved, so /CIDToGIDMap
+// stays /Identity and /W and /Widths need no remapping: unused glyphs keep their loca slot
+// with an empty outline, composite glyphs pull in their components, and tables a PDF consumer
+// never reads (layout, variations, glyph names, signatures) are dropped. CFF-flavoured and
+// collection fonts are returned as None and embedded whole.
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+
+// Tables kept in the subset; `cmap` only for simple (single-byte) fonts, which need it to map
+// codes to glyphs.
+const KEPT_TABLES: [&[u8; 4]; 10] = [
+    b"OS/2", b"cvt ", b"fpgm", b"glyf", b"head", b"hhea", b"hmtx", b"loca", b"maxp", b"prep",
+];
+
+pub(crate) struct TableRecord<'a> {
+    pub(crate) tag: [u8; 4],
+    pub(crate) data: &'a [u8],
+}
+
+pub(crate) fn subset_truetype(
+    data: &[u8],
+    used: &BTreeSet<u16>,
+    keep_cmap: bool,
+) -> Option<Vec<u8>> {
+    let tables = read_tables(data)?;
+    let table = |tag: &[u8; 4]| tables.iter().find(|t| &t.tag == tag).map(|t| t.data);
+    let head = table(b"head")?;
+    let glyf = table(b"glyf")?;
+    let loca = table(b"loca")?;
+    let num_glyphs = read_u16(table(b"maxp")?, 4)? as usize;
+    let long_loca = read_u16(head, 50)? != 0;
+    let glyph_range = |gid: usize| -> Option<(usize, usize)> {
+        let (start, end) = if long_loca {
+            (
+                read_u32(loca, gid * 4)? as usize,
+                read_u32(loca, gid * 4 + 4)? as usize,
+            )
+        } else {
+            (
+                read_u16(loca, gid * 2)? as usize * 2,
+                read_u16(loca, gid * 2 + 2)? as usize * 2,
+            )
+        };
+        (start <= end && end <= glyf.len()).then_some((start, end))
+    };
+
+    // Closure over composite components; .notdef is always kept.
+    let mut keep: BTreeSet<u16> = used
+        .iter()
+        .copied()
+        .filter(|gid| (*gid as usize) < num_glyphs)
+        .collect();
+    keep.insert(0);
+    let mut pending: Vec<u16> = keep.iter().copied().collect();
+    while let Some(gid) = pending.pop() {
+        let (start, end) = glyph_range(gid as usize)?;
+        for component in composite_components(&glyf[start..end]) {
+            if (component as usize) < num_glyphs && keep.insert(component) {
+                pending.push(component);
+            }
+        }
+    }
+
+    let mut new_glyf = Vec::new();
+    let mut new_loca = Vec::with_capacity((num_glyphs + 1) * 4);
+    for gid in 0..num_glyphs {
+        new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+        if keep.contains(&(gid as u16)) {
+            let (start, end) = glyph_range(gid)?;
+            new_glyf.extend_from_slice(&glyf[start..end]);
+            while new_glyf.len() % 4 != 0 {
+                new_glyf.push(0);
+            }
+        }
+    }
+    new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+
+    let mut new_head = head.to_vec();
+    new_head
+        .get_mut(50..52)?
+        .copy_from_slice(&1u16.to_be_bytes());
+    // Version 3 `post` carries no glyph names.
+    let new_post = table(b"post").and_then(|post| {
+        let mut post = post.get(..32)?.to_vec();
+        post[..4].copy_from_slice(&0x0003_0000u32.to_be_bytes());
+        Some(post)
+    });
+
+    let mut out_tables: Vec<([u8; 4], Vec<u8>)> = Vec::new();
+    for record in &tables {
+        let data = match &record.tag {
+            b"glyf" => std::mem::take(&mut new_glyf),
+            b"loca" => std::mem::take(&mut new_loca),
+            b"head" => std::mem::take(&mut new_head),
+            b"post" => match &new_post {
+                Some(post) => post.clone(),
+                None => continue,
+            },
+            b"cmap" if keep_cmap => record.data.to_vec(),
+            tag if KEPT_TABLES.contains(&tag) => record.data.to_vec(),
+            _ => continue,
+        };
+        out_tables.push((record.tag, data));
+    }
+    Some(write_sfnt(0x0001_0000, out_tables))
+}
+
+// Outline of a simple glyf record: contour end indices and (x, y, on-curve) points in font
+// units.
+pub(crate) struct SimpleGlyph<'a> {
+    pub(crate) end_points: &'a [u16],
+    pub(crate) points: &'a [(i32, i32, bool)],
+    pub(crate) bbox: [i16; 4],
+    pub(crate) instructions: &'a [u8],
+    pub(crate) overlap: bool,
+}
+
+// Append `glyph` as a glyf record with unpacked flags and short/long deltas (no repeat runs).
+// The caller pads the table.
+pub(crate) fn push_simple_glyph(glyf: &mut Vec<u8>, glyph: &SimpleGlyph<'_>) {
+    const ON_CURVE: u8 = 0x01;
+    const X_SHORT: u8 = 0x02;
+    const Y_SHORT: u8 = 0x04;
+    const X_SAME_OR_POSITIVE: u8 = 0x10;
+    const Y_SAME_OR_POSITIVE: u8 = 0x20;
+    const OVERLAP_SIMPLE: u8 = 0x40;
+
+    glyf.extend_from_slice(&(glyph.end_points.len() as i16).to_be_bytes());
+    for value in glyph.bbox {
+        glyf.extend_from_slice(&value.to_be_bytes());
+    }
+    for end in glyph.end_points {
+        glyf.extend_from_slice(&end.to_be_bytes());
+    }
+    glyf.extend_from_slice(&(glyph.instructions.len() as u16).to_be_bytes());
+    glyf.extend_from_slice(glyph.instructions);
+
+    let mut flags = Vec::with_capacity(glyph.points.len());
+    let mut xs = Vec::new();
+    let mut ys = Vec::new();
+    let (mut last_x, mut last_y) = (0i32, 0i32);
+    for (index, (x, y, on_curve)) in glyph.points.iter().copied().enumerate() {
+        let mut flag = if on_curve { ON_CURVE } else { 0 };
+        if index == 0 && glyph.overlap {
+            flag |= OVERLAP_SIMPLE;
+        }
+        for (delta, short, same, out) in [
+            (x - last_x, X_SHORT, X_SAME_OR_POSITIVE, &mut xs),
+            (y - last_y, Y_SHORT, Y_SAME_OR_POSITIVE, &mut ys),
+        ] {
+            if delta == 0 {
+                flag |= same;
+            } else if delta.abs() < 256 {
+                flag |= short;
+                if delta > 0 {
+                    flag |= same;
+                }
+                out.push(delta.unsigned_abs() as u8);
+            } else {
+                out.extend_from_slice(&(delta as i16).to_be_bytes());
+            }
+        }
+        flags.push(flag);
+        (last_x, last_y) = (x, y);
+    }
+    glyf.extend_from_slice(&flags);
+    glyf.extend_from_slice(&xs);
+    glyf.extend_from_slice(&ys);
+}
+
+// Six uppercase letters derived from the font and its glyph set, so the same subset always
+// gets the same tag and different subsets of one font get different ones.
+pub(crate) fn subset_tag(font_name: &str, used: &BTreeSet<u16>) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(font_name.as_bytes());
+    for gid in used {
+        hasher.update(gid.to_be_bytes());
+    }
+    hasher
+        .finalize()
+        .iter()
+        .take(6)
+        .map(|byte| (b'A' + byte % 26) as char)
+        .collect()
+}
+
+pub(crate) fn read_tables(data: &[u8]) -> Option<Vec<TableRecord<'_>>> {
+    let version = read_u32(data, 0)?;
+    if version != 0x0001_0000 && version != u32::from_be_bytes(*b"true") {
+        return None;
+    }
+    let num_tables = read_u16(data, 4)? as usize;
+    let mut tables = Vec::with_capacity(num_tables);
+    for index in 0..num_tables {
+        let record = 12 + index * 16;
+        let tag: [u8; 4] = data.get(record..record + 4)?.try_into().ok()?;
+        let offset = read_u32(data, record + 8)? as usize;
+        let length = read_u32(data, record + 12)? as usize;
+        tables.push(TableRecord {
+            tag,
+            data: data.get(offset..offset.checked_add(length)?)?,
+        });
+    }
+    Some(tables)
+}
+
+fn composite_components(glyph: &[u8]) -> Vec<u16> {
+    const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+    const WE_HAVE_A_SCALE: u16 = 0x0008;
+    const MORE_COMPONENTS: u16 = 0x0020;
+    const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+    const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+
+    let mut out = Vec::new();
+    if read_u16(glyph, 0).is_none_or(|contours| (contours as i16) >= 0) {
+        return out;
+    }
+    let mut pos = 10;
+    while let (Some(flags), Some(gid)) = (read_u16(glyph, pos), read_u16(glyph, pos + 2)) {
+        out.push(gid);
+        pos += 4;
+        pos += if flags & ARG_1_AND_2_ARE_WORDS != 0 {
+            4
+        } else {
+            2
+        };
+        if flags & WE_HAVE_A_SCALE != 0 {
+            pos += 2;
+        } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+            pos += 4;
+        } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+            pos += 8;
+        }
+        if flags & MORE_COMPONENTS == 0 {
+            break;
+        }
+    }
+    out
+}
+
+// Assemble an sfnt from whole tables: sorts the directory, pads tables to four bytes and
+// recomputes table checksums and head.checkSumAdjustment.
+pub(crate) fn write_sfnt(flavor: u32, mut tables: Vec<([u8; 4], Vec<u8>)>) -> Vec<u8> {
+    tables.sort_by_key(|table| table.0);
+    if let Some(adjustment) = tables
+        .iter_mut()
+        .find(|(tag, _)| tag == b"head")
+        .and_then(|(_, head)| head.get_mut(8..12))
+    {
+        adjustment.copy_from_slice(&[0; 4]);
+    }
+    let num_tables = tables.len() as u16;
+    let entry_selector = 15 - num_tables.max(1).leading_zeros() as u16;
+    let search_range = (1u16 << entry_selector) * 16;
+    let mut out = Vec::new();
+    out.extend_from_slice(&flavor.to_be_bytes());
+    out.extend_from_slice(&num_tables.to_be_bytes());
+    out.extend_from_slice(&search_range.to_be_bytes());
+    out.extend_from_slice(&entry_selector.to_be_bytes());
+    out.extend_from_slice(&(num_tables * 16 - search_range).to_be_bytes());
+
+    let mut offset = 12 + tables.len() * 16;
+    let mut head_offset = None;
+    for (tag, data) in &tables {
+        if tag == b"head" {
+            head_offset = Some(offset);
+        }
+        out.extend_from_slice(tag);
+        out.extend_from_slice(&checksum(data).to_be_bytes());
+        out.extend_from_slice(&(offset as u32).to_be_bytes());
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        offset += data.len().div_ceil(4) * 4;
+    }
+    for (_, data) in &tables {
+        out.extend_from_slice(data);
+        while out.len() % 4 != 0 {
+            out.push(0);
+        }
+    }
+    if let Some(head) = head_offset {
+        let adjustment = 0xB1B0_AFBAu32.wrapping_sub(checksum(&out));
+        out[head + 8..head + 12].copy_from_slice(&adjustment.to_be_bytes());
+    }
+    out
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+pub(crate) fn read_u16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+pub(crate) fn read_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{subset_tag, subset_truetype};
+    use std::collections::BTreeSet;
+
+    fn inter() -> Vec<u8> {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("python/fullbleed_assets/fonts/Inter-Variable.ttf");
+        std::fs::read(path).expect("read inter")
+    }
+
+    #[test]
+    fn subset_keeps_glyph_ids_and_drops_unused_outlines() {
+        let data = inter();
+        let face = ttf_parser::Face::parse(&data, 0).expect("face");
+        let a = face.glyph_index('A').expect("A");
+        let z = face.glyph_index('z').expect("z");
+        let used: BTreeSet<u16> = [a.0].into_iter().collect();
+
+        let subset = subset_truetype(&data, &used, false).expect("subset");
+        assert!(
+            subset.len() < data.len() / 4,
+            "{} vs {}",
+            subset.len(),
+            data.len()
+        );
+        let sub_face = ttf_parser::Face::parse(&subset, 0).expect("subset parses");
+        assert_eq!(sub_face.number_of_glyphs(), face.number_of_glyphs());
+        assert_eq!(
+            sub_face.glyph_hor_advance(a),
+            face.glyph_hor_advance(a),
+            "metrics keep their glyph ids"
+        );
+        assert!(sub_face.glyph_bounding_box(a).is_some());
+        assert!(sub_face.glyph_bounding_box(z).is_none());
+        assert!(sub_face.tables().cmap.is_none());
+        assert!(
+            subset_truetype(&data, &used, true).is_some_and(|with_cmap| {
+                ttf_parser::Face::parse(&with_cmap, 0).is_ok_and(|f| f.glyph_index('A') == Some(a))
+            })
+        );
+
+        let other: BTreeSet<u16> = [z.0].into_iter().collect();
+        assert_eq!(subset_tag("Inter", &used), subset_tag("Inter", &used));
+        assert_ne!(subset_tag("Inter", &used), subset_tag("Inter", &other));
+        assert!(
+            subset_tag("Inter", &used)
+                .chars()
+                .all(|c| c.is_ascii_uppercase())
+        );
+    }
+}
@@ -7,7 +7,7 @@ use lightningcss::properties::svg::{
 };
 use lightningcss::rules::CssRule;
 use lightningcss::stylesheet::{ParserOptions, StyleAttribute, StyleSheet};
-use lightningcss::traits::ToCss;
+use lightningcss::traits::{Parse, ToCss};
 use lightningcss::values::alpha::AlphaValue;
 use lightningcss::values::color::{CssColor, SRGB};
 use lightningcss::values::shape::FillRule;
@@ -26,8 +26,8 @@ pub(crate) fn svg_needs_raster_fallback(svg_xml: &str) -> bool {
             "text" | "foreignObject" => return true,
             // Filter/mask pipelines are raster-only for us.
             "filter" | "mask" => return true,
-            // Pattern/marker/symbol are not implemented in our subset.
-            "pattern" | "marker" | "symbol" => return true,
+            // Pattern/marker are not implemented in our subset.
+            "pattern" | "marker" => return true,
             _ => {}
         }
 
@@ -95,15 +95,17 @@ pub(crate) fn rasterize_svg_to_data_uri(_svg_xml: &str, _width: Pt, _height: Pt)
 // while mapping cleanly to PDF primitives (paths, fills, strokes).
 //
 // Supported (v1):
-// - <svg> root with viewBox
-// - <g> grouping
+// - <svg> root with viewBox; nested <svg> viewports
+// - <g> grouping; <defs>, <symbol> and <use> references
 // - <path d="..."> with commands: M/m, L/l, H/h, V/v, C/c, Z/z
 // - <rect>, <circle>, <ellipse>, <line>, <polyline>, <polygon> (converted to paths)
 // - presentation attributes + style="" for: fill, stroke, stroke-width, stroke-linecap, stroke-linejoin
 // - transform="" on elements: translate, scale, rotate, matrix
+// - linearGradient/radialGradient fills (gradient strokes use the mean stop color)
+// - clip-path="url(#id)" on shapes, groups, <use> and <image>
 //
 // Not supported (yet):
-// - <text>, <clipPath>, <mask>, <filter>, <foreignObject>, arcs (A/a), gradients
+// - <text>, <mask>, <filter>, <pattern>, <marker>, <foreignObject>
 
 #[derive(Debug, Clone, Copy)]
 struct Matrix {
@@ -277,11 +279,18 @@ enum PathSeg {
     Close,
 }
 
+// A resolved clipPath in viewport space; an item is clipped by the intersection of its clips.
+#[derive(Debug, Clone)]
+struct SvgClip {
+    segs: Vec<PathSeg>,
+    evenodd: bool,
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct CompiledPath {
     segs: Vec<PathSeg>,
     style: SvgStyle,
-    clip: Option<(Vec<PathSeg>, bool)>,
+    clips: Vec<std::sync::Arc<SvgClip>>,
 }
 
 #[derive(Debug, Clone)]
@@ -291,14 +300,31 @@ pub(crate) struct CompiledImage {
     width: f32,
     height: f32,
     source: String,
+    clips: Vec<std::sync::Arc<SvgClip>>,
 }
 
 #[derive(Debug, Clone)]
 pub(crate) enum CompiledItem {
-    Path(CompiledPath),
+    Path(Box<CompiledPath>),
     Image(CompiledImage),
 }
 
+impl CompiledItem {
+    fn clips_mut(&mut self) -> &mut Vec<std::sync::Arc<SvgClip>> {
+        match self {
+            CompiledItem::Path(path) => &mut path.clips,
+            CompiledItem::Image(img) => &mut img.clips,
+        }
+    }
+
+    fn bbox(&self) -> Option<(f32, f32, f32, f32)> {
+        match self {
+            CompiledItem::Path(path) => bbox_of_segs(&path.segs),
+            CompiledItem::Image(img) => Some((img.x, img.y, img.width, img.height)),
+        }
+    }
+}
+
 pub(crate) fn compile_svg(svg_xml: &str, width: Pt, height: Pt) -> Vec<CompiledItem> {
     let Ok(doc) = roxmltree::Document::parse(svg_xml) else {
         return Vec::new();
@@ -311,23 +337,18 @@ pub(crate) fn compile_svg(svg_xml: &str, width: Pt, height: Pt) -> Vec<CompiledI
     };
 
     let stylesheet = extract_svg_stylesheet(&doc);
-    let gradients = extract_gradients(&doc, &stylesheet);
-    let id_map = build_id_map(&doc);
+    let defs = SvgDefs {
+        gradients: extract_gradients(&doc, &stylesheet),
+        id_map: build_id_map(&doc),
+        stylesheet,
+    };
     let view_box = parse_viewbox(root.attribute("viewBox"));
     let viewport = viewbox_to_viewport_matrix(view_box, width.to_f32(), height.to_f32());
     let base = viewport;
 
     let style = SvgStyle::default();
     let mut out = Vec::new();
-    compile_element(
-        &mut out,
-        root,
-        base,
-        &style,
-        &gradients,
-        &id_map,
-        &stylesheet,
-    );
+    compile_element(&mut out, root, base, &style, &defs, 0);
     out
 }
 
@@ -336,6 +357,7 @@ pub(crate) fn render_compiled_items(items: &[CompiledItem], canvas: &mut Canvas,
         match it {
             CompiledItem::Path(path) => draw_compiled_path(canvas, path, x, y),
             CompiledItem::Image(img) => {
+                let clipped = push_svg_clips(canvas, &img.clips, x, y);
                 canvas.draw_image(
                     x + Pt::from_f32(img.x),
                     y + Pt::from_f32(img.y),
@@ -343,6 +365,9 @@ pub(crate) fn render_compiled_items(items: &[CompiledItem], canvas: &mut Canvas,
                     Pt::from_f32(img.height),
                     img.source.clone(),
                 );
+                if clipped {
+                    canvas.restore_state();
+                }
             }
         }
     }
@@ -361,114 +386,99 @@ pub(crate) fn render_svg_to_canvas(
     render_compiled_items(&compiled, canvas, x, y);
 }
 
+// Document-wide lookups shared by every element compiled from one SVG.
+struct SvgDefs<'a> {
+    gradients: std::collections::HashMap<String, GradientDef>,
+    id_map: std::collections::HashMap<String, roxmltree::Node<'a, 'a>>,
+    stylesheet: SvgStylesheet,
+}
+
+// Deeper `<use>` chains are treated as reference cycles and dropped.
+const MAX_SVG_USE_DEPTH: u8 = 16;
+
 fn compile_element(
     out: &mut Vec<CompiledItem>,
     node: roxmltree::Node<'_, '_>,
     ctm: Matrix,
     style: &SvgStyle,
-    gradients: &std::collections::HashMap<String, GradientDef>,
-    id_map: &std::collections::HashMap<String, roxmltree::Node<'_, '_>>,
-    stylesheet: &SvgStylesheet,
+    defs: &SvgDefs<'_>,
+    use_depth: u8,
 ) {
     if !node.is_element() {
         return;
     }
 
     let mut local_style = style.clone();
-    apply_presentation_and_style(node, stylesheet, &mut local_style);
+    apply_presentation_and_style(node, &defs.stylesheet, &mut local_style);
 
     let mut local_ctm = ctm;
     if let Some(transform) = node.attribute("transform") {
         local_ctm = local_ctm.mul(parse_transform(transform));
     }
 
+    let start = out.len();
     let tag = node.tag_name().name();
     match tag {
-        "defs" => {
+        "defs" | "symbol" => {
             // Definitions should not render directly. <use> resolves these by id.
         }
-        "g" | "svg" => {
+        "g" | "a" | "svg" => {
+            let mut content_ctm = local_ctm;
+            let nested_svg = tag == "svg"
+                && node
+                    .ancestors()
+                    .skip(1)
+                    .any(|n| n.is_element() && n.tag_name().name().eq_ignore_ascii_case("svg"));
+            if nested_svg {
+                // A nested <svg> establishes its own viewport at x/y.
+                let x = parse_number(node.attribute("x").unwrap_or("0")).unwrap_or(0.0);
+                let y = parse_number(node.attribute("y").unwrap_or("0")).unwrap_or(0.0);
+                content_ctm = content_ctm.mul(Matrix::translate(x, y));
+                let w = node.attribute("width").and_then(parse_number);
+                let h = node.attribute("height").and_then(parse_number);
+                if let (Some(w), Some(h)) = (w, h) {
+                    let view_box = parse_viewbox(node.attribute("viewBox"));
+                    content_ctm = content_ctm.mul(viewbox_to_viewport_matrix(view_box, w, h));
+                }
+            }
             for child in node.children().filter(|n| n.is_element()) {
-                compile_element(
-                    out,
-                    child,
-                    local_ctm,
-                    &local_style,
-                    gradients,
-                    id_map,
-                    stylesheet,
-                );
+                compile_element(out, child, content_ctm, &local_style, defs, use_depth);
             }
         }
         "use" => {
-            // Minimal <use> support: href/xlink:href with "#id", optional x/y.
-            if let Some(id) = href_id(node) {
-                if let Some(target) = id_map.get(&id).copied() {
-                    let x = parse_number(node.attribute("x").unwrap_or("0")).unwrap_or(0.0);
-                    let y = parse_number(node.attribute("y").unwrap_or("0")).unwrap_or(0.0);
-                    let use_ctm = local_ctm.mul(Matrix::translate(x, y));
-                    compile_element(
-                        out,
-                        target,
-                        use_ctm,
-                        &local_style,
-                        gradients,
-                        id_map,
-                        stylesheet,
-                    );
+            // href/xlink:href with "#id", optional x/y; <symbol> targets also map their
+            // viewBox onto the use (or symbol) width/height.
+            let target = href_id(node).and_then(|id| defs.id_map.get(&id).copied());
+            if let Some(target) = target.filter(|_| use_depth < MAX_SVG_USE_DEPTH) {
+                let x = parse_number(node.attribute("x").unwrap_or("0")).unwrap_or(0.0);
+                let y = parse_number(node.attribute("y").unwrap_or("0")).unwrap_or(0.0);
+                let mut use_ctm = local_ctm.mul(Matrix::translate(x, y));
+                if target.tag_name().name() == "symbol" {
+                    let w = node
+                        .attribute("width")
+                        .or_else(|| target.attribute("width"))
+                        .and_then(parse_number);
+                    let h = node
+                        .attribute("height")
+                        .or_else(|| target.attribute("height"))
+                        .and_then(parse_number);
+                    if let (Some(w), Some(h)) = (w, h) {
+                        let view_box = parse_viewbox(target.attribute("viewBox"));
+                        use_ctm = use_ctm.mul(viewbox_to_viewport_matrix(view_box, w, h));
+                    }
+                    let mut symbol_style = local_style.clone();
+                    apply_presentation_and_style(target, &defs.stylesheet, &mut symbol_style);
+                    for child in target.children().filter(|n| n.is_element()) {
+                        compile_element(out, child, use_ctm, &symbol_style, defs, use_depth + 1);
+                    }
+                } else {
+                    compile_element(out, target, use_ctm, &local_style, defs, use_depth + 1);
                 }
             }
         }
-        "path" => {
-            if let Some(d) = node.attribute("d") {
-                let segs = parse_path_data(d);
-                let clip = compile_clip_for_node(node, local_ctm, id_map);
-                push_compiled_path(out, &segs, &local_style, local_ctm, gradients, clip);
-            }
-        }
-        "rect" => {
-            if let Some(segs) = rect_to_path(node) {
-                let clip = compile_clip_for_node(node, local_ctm, id_map);
-                push_compiled_path(out, &segs, &local_style, local_ctm, gradients, clip);
-            }
-        }
-        "circle" => {
-            if let Some(segs) = circle_to_path(node) {
-                let clip = compile_clip_for_node(node, local_ctm, id_map);
-                push_compiled_path(out, &segs, &local_style, local_ctm, gradients, clip);
-            }
-        }
-        "ellipse" => {
-            if let Some(segs) = ellipse_to_path(node) {
-                let clip = compile_clip_for_node(node, local_ctm, id_map);
-                push_compiled_path(out, &segs, &local_style, local_ctm, gradients, clip);
-            }
-        }
-        "line" => {
-            if let Some(segs) = line_to_path(node) {
-                let clip = compile_clip_for_node(node, local_ctm, id_map);
-                push_compiled_path(out, &segs, &local_style, local_ctm, gradients, clip);
-            }
-        }
-        "polyline" => {
-            if let Some(segs) = poly_points_to_path(node, false) {
-                let clip = compile_clip_for_node(node, local_ctm, id_map);
-                push_compiled_path(out, &segs, &local_style, local_ctm, gradients, clip);
-            }
-        }
-        "polygon" => {
-            if let Some(segs) = poly_points_to_path(node, true) {
-                let clip = compile_clip_for_node(node, local_ctm, id_map);
-                push_compiled_path(out, &segs, &local_style, local_ctm, gradients, clip);
-            }
-        }
         "image" => {
             // Raster image inside SVG (PNG/JPEG/data URI). We only support axis-aligned transforms for now.
-            let href = node
-                .attribute("href")
-                .or_else(|| node.attribute("xlink:href"))
-                .unwrap_or("")
-                .to_string();
+            let href = href_attr(node).unwrap_or("").to_string();
             if href.is_empty() {
                 return;
             }
@@ -513,14 +523,47 @@ fn compile_element(
                 width: iw,
                 height: ih,
                 source: href,
+                clips: Vec::new(),
             }));
         }
         _ => {
-            // Ignore unknown tags in our subset.
+            if let Some(segs) = shape_to_path(node) {
+                push_compiled_path(out, &segs, &local_style, local_ctm, &defs.gradients);
+            }
+            // Other tags are ignored in our subset.
+        }
+    }
+
+    // clip-path clips everything the element painted, including group and <use> content.
+    if out.len() > start
+        && let Some(clip) = compile_clip_for_node(node, local_ctm, &out[start..], &defs.id_map)
+    {
+        if clip.segs.is_empty() {
+            // A clipPath without geometry clips the element away entirely.
+            out.truncate(start);
+        } else {
+            let clip = std::sync::Arc::new(clip);
+            for item in &mut out[start..] {
+                item.clips_mut().push(clip.clone());
+            }
         }
     }
 }
 
+// Geometry for the basic shapes, in the element's user space.
+fn shape_to_path(node: roxmltree::Node<'_, '_>) -> Option<Vec<PathSeg>> {
+    match node.tag_name().name() {
+        "path" => node.attribute("d").map(parse_path_data),
+        "rect" => rect_to_path(node),
+        "circle" => circle_to_path(node),
+        "ellipse" => ellipse_to_path(node),
+        "line" => line_to_path(node),
+        "polyline" => poly_points_to_path(node, false),
+        "polygon" => poly_points_to_path(node, true),
+        _ => None,
+    }
+}
+
 fn build_id_map<'a>(
     doc: &'a roxmltree::Document<'a>,
 ) -> std::collections::HashMap<String, roxmltree::Node<'a, 'a>> {
@@ -534,11 +577,16 @@ fn build_id_map<'a>(
     out
 }
 
+const XLINK_NS: &str = "http://www.w3.org/1999/xlink";
+
+// Plain href (SVG 2) wins over the namespaced xlink:href that most exporters still emit.
+fn href_attr<'a>(node: roxmltree::Node<'a, '_>) -> Option<&'a str> {
+    node.attribute("href")
+        .or_else(|| node.attribute((XLINK_NS, "href")))
+}
+
 fn href_id(node: roxmltree::Node<'_, '_>) -> Option<String> {
-    // Prefer plain href, then xlink:href.
-    let raw = node
-        .attribute("href")
-        .or_else(|| node.attribute("xlink:href"))?;
+    let raw = href_attr(node)?;
     let raw = raw.trim().trim_matches('"').trim_matches('\'');
     let id = raw.strip_prefix('#')?;
     if id.is_empty() {
@@ -553,10 +601,20 @@ fn push_compiled_path(
     style: &SvgStyle,
     ctm: Matrix,
     gradients: &std::collections::HashMap<String, GradientDef>,
-    clip: Option<(Vec<PathSeg>, bool)>,
 ) {
-    let has_fill = style.fill.color.is_some() || style.fill.gradient_id.is_some();
-    let has_stroke = style.stroke.color.is_some() && style.stroke_width > 0.0;
+    let mut out_style = style.clone();
+    out_style.fill_shading = None;
+    if out_style.stroke.color.is_none() {
+        // Strokes cannot be painted with a shading directly; a gradient stroke uses the
+        // gradient's mean stop color instead of disappearing.
+        out_style.stroke.color = out_style
+            .stroke
+            .gradient_id
+            .as_deref()
+            .and_then(|id| gradient_mean_color(id, gradients));
+    }
+    let has_fill = out_style.fill.color.is_some() || out_style.fill.gradient_id.is_some();
+    let has_stroke = out_style.stroke.color.is_some() && out_style.stroke_width > 0.0;
     if !has_fill && !has_stroke {
         return;
     }
@@ -593,8 +651,6 @@ fn push_compiled_path(
         }
     }
 
-    let mut out_style = style.clone();
-    out_style.fill_shading = None;
     if has_stroke {
         let sf = ctm.scale_factor();
         out_style.stroke_width = out_style.stroke_width * sf;
@@ -606,21 +662,22 @@ fn push_compiled_path(
         }
     }
 
-    // Resolve gradient fills into a concrete shading for this path instance.
+    // Resolve gradient fills into a concrete shading for this path instance. Gradient
+    // geometry is defined against the element's user-space bounding box.
     if out_style.fill.color.is_none() {
         if let Some(ref id) = out_style.fill.gradient_id {
-            if let Some(b) = bbox_of_segs(&out_segs) {
-                if let Some(sh) = resolve_gradient_fill(id, gradients, b) {
-                    out_style.fill_shading = Some(sh);
-                }
+            if let Some(b) = bbox_of_segs(segs)
+                && let Some(sh) = resolve_gradient_fill(id, gradients, b, ctm)
+            {
+                out_style.fill_shading = Some(sh);
             }
         }
     }
-    out.push(CompiledItem::Path(CompiledPath {
+    out.push(CompiledItem::Path(Box::new(CompiledPath {
         segs: out_segs,
         style: out_style,
-        clip,
-    }));
+        clips: Vec::new(),
+    })));
 }
 
 fn bbox_of_segs(segs: &[PathSeg]) -> Option<(f32, f32, f32, f32)> {
@@ -661,43 +718,47 @@ fn resolve_gradient_fill(
     id: &str,
     gradients: &std::collections::HashMap<String, GradientDef>,
     bbox: (f32, f32, f32, f32),
+    ctm: Matrix,
 ) -> Option<crate::types::Shading> {
     use crate::types::Shading;
 
     let def = gradients.get(id)?;
     let (bx, by, bw, bh) = bbox;
-    if bw <= 0.0 || bh <= 0.0 {
-        return None;
-    }
+    let (units, transform) = match def {
+        GradientDef::Linear {
+            units, transform, ..
+        }
+        | GradientDef::Radial {
+            units, transform, ..
+        } => (*units, *transform),
+    };
 
-    fn frac(c: Coord) -> f32 {
-        // For objectBoundingBox we treat both percent and unitless as fraction.
-        c.v
-    }
-    fn coord_x(c: Coord, units: GradientUnits, bx: f32, bw: f32) -> f32 {
-        match units {
-            GradientUnits::ObjectBoundingBox => bx + bw * frac(c),
-            GradientUnits::UserSpaceOnUse => {
-                if c.is_percent {
-                    bx + bw * c.v
-                } else {
-                    c.v
-                }
+    // objectBoundingBox coordinates are fractions of the bbox; userSpaceOnUse coordinates
+    // are user units (percentages still resolve against the bbox). gradientTransform applies
+    // in that gradient space, before the element's CTM.
+    let gradient_space = match units {
+        GradientUnits::ObjectBoundingBox => {
+            if bw <= 0.0 || bh <= 0.0 {
+                return None;
             }
+            Matrix::translate(bx, by).mul(Matrix::scale(bw, bh))
         }
-    }
-    fn coord_y(c: Coord, units: GradientUnits, by: f32, bh: f32) -> f32 {
-        match units {
-            GradientUnits::ObjectBoundingBox => by + bh * frac(c),
-            GradientUnits::UserSpaceOnUse => {
-                if c.is_percent {
-                    by + bh * c.v
-                } else {
-                    c.v
-                }
+        GradientUnits::UserSpaceOnUse => Matrix::identity(),
+    };
+    let to_device = ctm
+        .mul(gradient_space)
+        .mul(transform.unwrap_or_else(Matrix::identity));
+
+    let resolve = |c: Coord, origin: f32, extent: f32| match units {
+        GradientUnits::ObjectBoundingBox => c.v,
+        GradientUnits::UserSpaceOnUse => {
+            if c.is_percent {
+                origin + extent * c.v
+            } else {
+                c.v
             }
         }
-    }
+    };
 
     match def {
         GradientDef::Linear {
@@ -705,21 +766,14 @@ fn resolve_gradient_fill(
             y1,
             x2,
             y2,
-            units,
-            transform,
             stops,
+            ..
         } => {
             if stops.is_empty() {
                 return None;
             }
-            let mut x0 = coord_x(*x1, *units, bx, bw);
-            let mut y0 = coord_y(*y1, *units, by, bh);
-            let mut x1 = coord_x(*x2, *units, bx, bw);
-            let mut y1 = coord_y(*y2, *units, by, bh);
-            if let Some(m) = transform {
-                (x0, y0) = m.apply(x0, y0);
-                (x1, y1) = m.apply(x1, y1);
-            }
+            let (x0, y0) = to_device.apply(resolve(*x1, bx, bw), resolve(*y1, by, bh));
+            let (x1, y1) = to_device.apply(resolve(*x2, bx, bw), resolve(*y2, by, bh));
             Some(Shading::Axial {
                 x0: q(x0),
                 y0: q(y0),
@@ -732,33 +786,30 @@ fn resolve_gradient_fill(
             cx,
             cy,
             r,
-            units,
-            transform,
+            fx,
+            fy,
+            fr,
             stops,
+            ..
         } => {
             if stops.is_empty() {
                 return None;
             }
-            let mut cxv = coord_x(*cx, *units, bx, bw);
-            let mut cyv = coord_y(*cy, *units, by, bh);
-            let mut rv = match units {
-                GradientUnits::ObjectBoundingBox => bw.min(bh) * frac(*r),
-                GradientUnits::UserSpaceOnUse => {
-                    if r.is_percent {
-                        bw.min(bh) * r.v
-                    } else {
-                        r.v
-                    }
-                }
-            };
-            if let Some(m) = transform {
-                (cxv, cyv) = m.apply(cxv, cyv);
-                rv *= m.scale_factor();
-            }
+            let extent = bw.min(bh);
+            let cxv = resolve(*cx, bx, bw);
+            let cyv = resolve(*cy, by, bh);
+            let fxv = fx.map(|c| resolve(c, bx, bw)).unwrap_or(cxv);
+            let fyv = fy.map(|c| resolve(c, by, bh)).unwrap_or(cyv);
+            let (cxv, cyv) = to_device.apply(cxv, cyv);
+            let (fxv, fyv) = to_device.apply(fxv, fyv);
+            // Non-uniform scales would make the circle an ellipse; use the mean scale.
+            let scale = to_device.scale_factor();
+            let rv = resolve(*r, 0.0, extent) * scale;
+            let frv = resolve(*fr, 0.0, extent) * scale;
             Some(Shading::Radial {
-                x0: q(cxv),
-                y0: q(cyv),
-                r0: 0.0,
+                x0: q(fxv),
+                y0: q(fyv),
+                r0: q(frv.max(0.0)),
                 x1: q(cxv),
                 y1: q(cyv),
                 r1: q(rv.max(0.0)),
@@ -768,6 +819,23 @@ fn resolve_gradient_fill(
     }
 }
 
+fn gradient_mean_color(
+    id: &str,
+    gradients: &std::collections::HashMap<String, GradientDef>,
+) -> Option<Color> {
+    let stops = match gradients.get(id)? {
+        GradientDef::Linear { stops, .. } | GradientDef::Radial { stops, .. } => stops,
+    };
+    if stops.is_empty() {
+        return None;
+    }
+    let n = stops.len() as f32;
+    let (r, g, b) = stops.iter().fold((0.0, 0.0, 0.0), |(r, g, b), stop| {
+        (r + stop.color.r, g + stop.color.g, b + stop.color.b)
+    });
+    Some(Color::rgb(r / n, g / n, b / n))
+}
+
 fn translate_shading(sh: &crate::types::Shading, dx: f32, dy: f32) -> crate::types::Shading {
     use crate::types::Shading;
     match sh {
@@ -804,31 +872,68 @@ fn translate_shading(sh: &crate::types::Shading, dx: f32, dy: f32) -> crate::typ
     }
 }
 
+fn clip_path_ref(node: roxmltree::Node<'_, '_>) -> Option<String> {
+    if let Some(clip) = node.attribute("clip-path") {
+        return parse_url_ref(clip);
+    }
+    // d3 exports often set clip-path through style="".
+    let style = node.attribute("style")?;
+    style.split(';').find_map(|decl| {
+        let (key, value) = decl.split_once(':')?;
+        if key.trim().eq_ignore_ascii_case("clip-path") {
+            parse_url_ref(value)
+        } else {
+            None
+        }
+    })
+}
+
+// Resolves the element's clip-path against what it painted. An empty `segs` means the
+// referenced clipPath has no geometry, which clips everything away.
 fn compile_clip_for_node(
     node: roxmltree::Node<'_, '_>,
     ctm: Matrix,
+    painted: &[CompiledItem],
     id_map: &std::collections::HashMap<String, roxmltree::Node<'_, '_>>,
-) -> Option<(Vec<PathSeg>, bool)> {
-    let clip = node.attribute("clip-path")?;
-    let id = parse_url_ref(clip)?;
+) -> Option<SvgClip> {
+    let id = clip_path_ref(node)?;
     let clip_node = id_map.get(&id).copied()?;
     if !clip_node.is_element() || clip_node.tag_name().name() != "clipPath" {
         return None;
     }
 
-    // clip-rule defaults to nonzero. (SVG also supports clip-rule on children; ignore for now.)
+    // clip-rule defaults to nonzero; it may sit on the clipPath or its first shape.
     let evenodd = clip_node
         .attribute("clip-rule")
+        .or_else(|| {
+            clip_node
+                .children()
+                .find(|n| n.is_element())
+                .and_then(|n| n.attribute("clip-rule"))
+        })
         .map(|v| v.trim().eq_ignore_ascii_case("evenodd"))
         .unwrap_or(false);
 
-    let mut out = Vec::new();
-    compile_clip_subtree(&mut out, clip_node, ctm, id_map);
-    if out.is_empty() {
-        None
+    let clip_ctm = if clip_node.attribute("clipPathUnits") == Some("objectBoundingBox") {
+        // Painted geometry is already in viewport space, so the bbox mapping is exact for
+        // axis-aligned transforms.
+        let (bx, by, bw, bh) = painted.iter().filter_map(CompiledItem::bbox).reduce(
+            |(ax, ay, aw, ah), (bx, by, bw, bh)| {
+                let x0 = ax.min(bx);
+                let y0 = ay.min(by);
+                let x1 = (ax + aw).max(bx + bw);
+                let y1 = (ay + ah).max(by + bh);
+                (x0, y0, x1 - x0, y1 - y0)
+            },
+        )?;
+        Matrix::translate(bx, by).mul(Matrix::scale(bw, bh))
     } else {
-        Some((out, evenodd))
-    }
+        ctm
+    };
+
+    let mut segs = Vec::new();
+    compile_clip_subtree(&mut segs, clip_node, clip_ctm, id_map);
+    Some(SvgClip { segs, evenodd })
 }
 
 fn compile_clip_subtree(
@@ -862,43 +967,11 @@ fn compile_clip_subtree(
                 }
             }
         }
-        "path" => {
-            if let Some(d) = node.attribute("d") {
-                let segs = parse_path_data(d);
-                out.extend(transform_path_segs(&segs, local_ctm));
-            }
-        }
-        "rect" => {
-            if let Some(segs) = rect_to_path(node) {
-                out.extend(transform_path_segs(&segs, local_ctm));
-            }
-        }
-        "circle" => {
-            if let Some(segs) = circle_to_path(node) {
-                out.extend(transform_path_segs(&segs, local_ctm));
-            }
-        }
-        "ellipse" => {
-            if let Some(segs) = ellipse_to_path(node) {
-                out.extend(transform_path_segs(&segs, local_ctm));
-            }
-        }
-        "line" => {
-            if let Some(segs) = line_to_path(node) {
-                out.extend(transform_path_segs(&segs, local_ctm));
-            }
-        }
-        "polyline" => {
-            if let Some(segs) = poly_points_to_path(node, false) {
-                out.extend(transform_path_segs(&segs, local_ctm));
-            }
-        }
-        "polygon" => {
-            if let Some(segs) = poly_points_to_path(node, true) {
+        _ => {
+            if let Some(segs) = shape_to_path(node) {
                 out.extend(transform_path_segs(&segs, local_ctm));
             }
         }
-        _ => {}
     }
 }
 
@@ -926,6 +999,48 @@ fn transform_path_segs(segs: &[PathSeg], ctm: Matrix) -> Vec<PathSeg> {
     out
 }
 
+fn emit_svg_path(canvas: &mut Canvas, segs: &[PathSeg], x_off: Pt, y_off: Pt) {
+    for seg in segs {
+        match *seg {
+            PathSeg::MoveTo(px, py) => {
+                canvas.move_to(x_off + Pt::from_f32(px), y_off + Pt::from_f32(py))
+            }
+            PathSeg::LineTo(px, py) => {
+                canvas.line_to(x_off + Pt::from_f32(px), y_off + Pt::from_f32(py))
+            }
+            PathSeg::CurveTo(x1, y1, x2, y2, x3, y3) => {
+                canvas.curve_to(
+                    x_off + Pt::from_f32(x1),
+                    y_off + Pt::from_f32(y1),
+                    x_off + Pt::from_f32(x2),
+                    y_off + Pt::from_f32(y2),
+                    x_off + Pt::from_f32(x3),
+                    y_off + Pt::from_f32(y3),
+                );
+            }
+            PathSeg::Close => canvas.close_path(),
+        }
+    }
+}
+
+// Saves the graphics state and intersects every clip; returns whether a restore is owed.
+fn push_svg_clips(
+    canvas: &mut Canvas,
+    clips: &[std::sync::Arc<SvgClip>],
+    x_off: Pt,
+    y_off: Pt,
+) -> bool {
+    if clips.is_empty() {
+        return false;
+    }
+    canvas.save_state();
+    for clip in clips {
+        emit_svg_path(canvas, &clip.segs, x_off, y_off);
+        canvas.clip_path(clip.evenodd);
+    }
+    true
+}
+
 fn draw_compiled_path(canvas: &mut Canvas, path: &CompiledPath, x_off: Pt, y_off: Pt) {
     let has_fill = path.style.fill.color.is_some() || path.style.fill_shading.is_some();
     let has_stroke = path.style.stroke.color.is_some() && path.style.stroke_width > 0.0;
@@ -933,37 +1048,7 @@ fn draw_compiled_path(canvas: &mut Canvas, path: &CompiledPath, x_off: Pt, y_off
         return;
     }
 
-    fn emit_path(canvas: &mut Canvas, segs: &[PathSeg], x_off: Pt, y_off: Pt) {
-        for seg in segs {
-            match *seg {
-                PathSeg::MoveTo(px, py) => {
-                    canvas.move_to(x_off + Pt::from_f32(px), y_off + Pt::from_f32(py))
-                }
-                PathSeg::LineTo(px, py) => {
-                    canvas.line_to(x_off + Pt::from_f32(px), y_off + Pt::from_f32(py))
-                }
-                PathSeg::CurveTo(x1, y1, x2, y2, x3, y3) => {
-                    canvas.curve_to(
-                        x_off + Pt::from_f32(x1),
-                        y_off + Pt::from_f32(y1),
-                        x_off + Pt::from_f32(x2),
-                        y_off + Pt::from_f32(y2),
-                        x_off + Pt::from_f32(x3),
-                        y_off + Pt::from_f32(y3),
-                    );
-                }
-                PathSeg::Close => canvas.close_path(),
-            }
-        }
-    }
-
-    let mut clipped = false;
-    if let Some((clip_segs, evenodd)) = &path.clip {
-        canvas.save_state();
-        emit_path(canvas, clip_segs, x_off, y_off);
-        canvas.clip_path(*evenodd);
-        clipped = true;
-    }
+    let clipped = push_svg_clips(canvas, &path.clips, x_off, y_off);
 
     if has_stroke {
         canvas.set_miter_limit(Pt::from_f32(path.style.miter_limit));
@@ -991,7 +1076,7 @@ fn draw_compiled_path(canvas: &mut Canvas, path: &CompiledPath, x_off: Pt, y_off
     if let Some(sh) = &path.style.fill_shading {
         let sh = translate_shading(sh, x_off.to_f32(), y_off.to_f32());
         canvas.save_state();
-        emit_path(canvas, &path.segs, x_off, y_off);
+        emit_svg_path(canvas, &path.segs, x_off, y_off);
         canvas.clip_path(path.style.fill_rule_evenodd);
         canvas.shading_fill(sh);
         canvas.restore_state();
@@ -1003,7 +1088,7 @@ fn draw_compiled_path(canvas: &mut Canvas, path: &CompiledPath, x_off: Pt, y_off
                 canvas.set_line_cap(path.style.line_cap);
                 canvas.set_line_join(path.style.line_join);
             }
-            emit_path(canvas, &path.segs, x_off, y_off);
+            emit_svg_path(canvas, &path.segs, x_off, y_off);
             canvas.stroke();
         }
         if clipped {
@@ -1022,7 +1107,7 @@ fn draw_compiled_path(canvas: &mut Canvas, path: &CompiledPath, x_off: Pt, y_off
         canvas.set_line_join(path.style.line_join);
     }
 
-    emit_path(canvas, &path.segs, x_off, y_off);
+    emit_svg_path(canvas, &path.segs, x_off, y_off);
 
     match (has_fill, has_stroke) {
         (true, true) => {
@@ -1768,6 +1853,10 @@ enum GradientDef {
         cx: Coord,
         cy: Coord,
         r: Coord,
+        // Focal point and radius; the focal point defaults to the center.
+        fx: Option<Coord>,
+        fy: Option<Coord>,
+        fr: Coord,
         units: GradientUnits,
         transform: Option<Matrix>,
         stops: Vec<crate::types::ShadingStop>,
@@ -1894,10 +1983,7 @@ fn extract_gradients(
         let Some(id) = node.attribute("id") else {
             continue;
         };
-        if let Some(href) = node
-            .attribute("href")
-            .or_else(|| node.attribute("xlink:href"))
-        {
+        if let Some(href) = href_attr(node) {
             let href = href.trim().trim_matches('"').trim_matches('\'');
             if let Some(base) = href.strip_prefix('#') {
                 if !base.is_empty() {
@@ -1929,6 +2015,10 @@ fn extract_gradients(
             stops.push(crate::types::ShadingStop { offset, color });
         }
 
+        let default_zero = Coord {
+            v: 0.0,
+            is_percent: true,
+        };
         let def = if name == "linearGradient" {
             GradientDef::Linear {
                 x1: parse_coord(
@@ -1986,6 +2076,13 @@ fn extract_gradients(
                         is_percent: true,
                     },
                 ),
+                fx: node
+                    .attribute("fx")
+                    .map(|v| parse_coord(Some(v), default_zero)),
+                fy: node
+                    .attribute("fy")
+                    .map(|v| parse_coord(Some(v), default_zero)),
+                fr: parse_coord(node.attribute("fr"), default_zero),
                 units,
                 transform,
                 stops,
@@ -2028,33 +2125,21 @@ fn parse_color(input: &str) -> Option<Color> {
             let r = u8::from_str_radix(&hex[0..2], 16).ok()? as f32 / 255.0;
             let g = u8::from_str_radix(&hex[2..4], 16).ok()? as f32 / 255.0;
             let b = u8::from_str_radix(&hex[4..6], 16).ok()? as f32 / 255.0;
-            return Some(Color { r, g, b });
+            return Some(Color::rgb(r, g, b));
         }
     }
     // Minimal named color set (enough for common exports).
     match v.to_ascii_lowercase().as_str() {
         "black" => Some(Color::BLACK),
-        "white" => Some(Color {
-            r: 1.0,
-            g: 1.0,
-            b: 1.0,
-        }),
-        "red" => Some(Color {
-            r: 1.0,
-            g: 0.0,
-            b: 0.0,
-        }),
-        "green" => Some(Color {
-            r: 0.0,
-            g: 0.5,
-            b: 0.0,
-        }),
-        "blue" => Some(Color {
-            r: 0.0,
-            g: 0.0,
-            b: 1.0,
-        }),
-        _ => None,
+        "white" => Some(Color::rgb(1.0, 1.0, 1.0)),
+        "red" => Some(Color::rgb(1.0, 0.0, 0.0)),
+        "green" => Some(Color::rgb(0.0, 0.5, 0.0)),
+        "blue" => Some(Color::rgb(0.0, 0.0, 1.0)),
+        // rgb()/hsl()/short hex/the full named set, as chart libraries emit them.
+        _ => CssColor::parse_string(v)
+            .ok()
+            .and_then(|color| css_color_to_svg_color(&color))
+            .map(|(color, _)| color),
     }
 }
 
@@ -2992,4 +3077,112 @@ mod tests {
         assert!((path.style.fill_opacity - 0.2).abs() < 0.001);
         assert!((path.style.stroke_opacity - 0.0625).abs() < 0.001);
     }
+
+    #[test]
+    fn group_clip_paths_clip_every_painted_child() {
+        let svg = r##"
+        <svg width="40" height="20" viewBox="0 0 40 20">
+          <defs>
+            <clipPath id="plot"><rect x="5" y="0" width="30" height="20"/></clipPath>
+            <clipPath id="empty"></clipPath>
+          </defs>
+          <g clip-path="url(#plot)">
+            <rect x="0" y="0" width="40" height="10" fill="#ff0000"/>
+            <g style="clip-path: url(#plot)"><circle cx="20" cy="10" r="4" fill="#00ff00"/></g>
+          </g>
+          <rect x="0" y="0" width="4" height="4" fill="#0000ff" clip-path="url(#empty)"/>
+        </svg>
+        "##;
+        let compiled = compile_svg(svg, Pt::from_f32(40.0), Pt::from_f32(20.0));
+        let clip_counts: Vec<usize> = compiled
+            .iter()
+            .map(|item| match item {
+                CompiledItem::Path(path) => path.clips.len(),
+                CompiledItem::Image(img) => img.clips.len(),
+            })
+            .collect();
+        // The clipped-away rect is dropped; the nested circle carries both clips.
+        assert_eq!(clip_counts, vec![1, 2]);
+        let CompiledItem::Path(rect) = &compiled[0] else {
+            panic!("expected path");
+        };
+        assert_eq!(
+            bbox_of_segs(&rect.clips[0].segs),
+            Some((5.0, 0.0, 30.0, 20.0))
+        );
+    }
+
+    #[test]
+    fn use_maps_symbol_view_box_and_stops_at_cycles() {
+        let svg = r##"
+        <svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink"
+          width="40" height="20" viewBox="0 0 40 20">
+          <symbol id="dot" viewBox="0 0 10 10"><rect width="10" height="10" fill="#ff0000"/></symbol>
+          <g id="loop"><use href="#loop"/></g>
+          <use xlink:href="#dot" x="20" y="0" width="20" height="20"/>
+        </svg>
+        "##;
+        assert!(!svg_needs_raster_fallback(svg));
+        let compiled = compile_svg(svg, Pt::from_f32(40.0), Pt::from_f32(20.0));
+        assert_eq!(compiled.len(), 1, "symbols only render through <use>");
+        let CompiledItem::Path(path) = &compiled[0] else {
+            panic!("expected path");
+        };
+        assert_eq!(bbox_of_segs(&path.segs), Some((20.0, 0.0, 20.0, 20.0)));
+    }
+
+    #[test]
+    fn gradients_resolve_in_user_space_through_the_ctm() {
+        let svg = r##"
+        <svg width="20" height="20" viewBox="0 0 10 10">
+          <defs>
+            <linearGradient id="user" gradientUnits="userSpaceOnUse" x1="0" y1="0" x2="10" y2="0">
+              <stop offset="0" stop-color="rgb(255, 0, 0)"/>
+              <stop offset="1" style="stop-color: hsl(240, 100%, 50%)"/>
+            </linearGradient>
+            <radialGradient id="focal" fx="0.25" fy="0.5">
+              <stop offset="0" stop-color="#fff"/>
+              <stop offset="1" stop-color="#000"/>
+            </radialGradient>
+          </defs>
+          <rect x="2" y="2" width="6" height="6" fill="url(#user)" stroke="url(#user)"/>
+          <rect x="0" y="0" width="4" height="4" fill="url(#focal)"/>
+        </svg>
+        "##;
+        let compiled = compile_svg(svg, Pt::from_f32(20.0), Pt::from_f32(20.0));
+        let paths: Vec<&CompiledPath> = compiled
+            .iter()
+            .filter_map(|item| match item {
+                CompiledItem::Path(path) => Some(&**path),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(paths.len(), 2);
+        match paths[0]
+            .style
+            .fill_shading
+            .as_ref()
+            .expect("linear shading")
+        {
+            crate::types::Shading::Axial { x0, x1, stops, .. } => {
+                // The viewBox doubles user units.
+                assert_eq!((*x0, *x1), (0.0, 20.0));
+                assert!((stops[1].color.b - 1.0).abs() < 0.001);
+            }
+            other => panic!("unexpected shading {other:?}"),
+        }
+        let stroke = paths[0].style.stroke.color.expect("gradient stroke color");
+        assert!((stroke.r - 0.5).abs() < 0.01 && (stroke.b - 0.5).abs() < 0.01);
+        match paths[1]
+            .style
+            .fill_shading
+            .as_ref()
+            .expect("radial shading")
+        {
+            crate::types::Shading::Radial { x0, x1, r1, .. } => {
+                assert_eq!((*x0, *x1, *r1), (2.0, 4.0, 4.0));
+            }
+            other => panic!("unexpected shading {other:?}"),
+        }
+    }
 }
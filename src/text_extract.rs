@@ -0,0 +1,191 @@
+// Plain-text extraction with positions, from rendered documents and from PDFs. Spans are
+// grouped into lines by vertical overlap and read top to bottom, then left to right; there
+// is no column detection, so side-by-side columns interleave line by line.
+use crate::FullBleed;
+use crate::canvas::{Command, Document};
+use crate::command_buffer::CommandBuffer;
+use crate::font::FontRegistry;
+use crate::types::{Pt, Rect};
+use std::sync::Arc;
+
+// Horizontal gap, as a share of the text height, read as a word break between two spans.
+const WORD_GAP_RATIO: f32 = 0.25;
+
+// A run of text and its box in page space (points, top-left origin).
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextSpan {
+    pub text: String,
+    pub bbox: Rect,
+}
+
+// Spans that share a line, left to right.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextLine {
+    // The spans joined, with a space wherever they are a word gap apart.
+    pub text: String,
+    pub bbox: Rect,
+    pub spans: Vec<TextSpan>,
+}
+
+// The text of one page in reading order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageText {
+    // 1-based page number.
+    pub page_number: usize,
+    pub lines: Vec<TextLine>,
+}
+
+impl PageText {
+    // All lines joined with newlines.
+    pub fn text(&self) -> String {
+        self.lines
+            .iter()
+            .map(|line| line.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl Document {
+    // Text of every page in reading order. Without font metrics, span widths are estimated
+    // from the font size; [`FullBleed::extract_text`] measures with the engine's fonts.
+    pub fn extract_text(&self) -> Vec<PageText> {
+        document_text(self, None)
+    }
+}
+
+impl FullBleed {
+    // [`Document::extract_text`] with span widths measured in this engine's fonts.
+    pub fn extract_text(&self, document: &Document) -> Vec<PageText> {
+        document_text(document, Some(self.font_registry.as_ref()))
+    }
+}
+
+fn document_text(document: &Document, registry: Option<&FontRegistry>) -> Vec<PageText> {
+    document
+        .pages
+        .iter()
+        .enumerate()
+        .map(|(index, page)| page_text(index + 1, page_spans(&page.commands, registry)))
+        .collect()
+}
+
+// Only translations are applied to positions. Form content is not extracted.
+fn page_spans(commands: &CommandBuffer, registry: Option<&FontRegistry>) -> Vec<TextSpan> {
+    let mut spans = Vec::new();
+    let mut font_name: Arc<str> = Arc::from("Helvetica");
+    let mut font_size = Pt::from_f32(12.0);
+    // Translations are in PDF space, where y grows upwards.
+    let mut offset = (Pt::ZERO, Pt::ZERO);
+    let mut stack = Vec::new();
+    for command in commands {
+        match &*command {
+            Command::SaveState => stack.push((offset, font_name.clone(), font_size)),
+            Command::RestoreState => {
+                if let Some(saved) = stack.pop() {
+                    (offset, font_name, font_size) = saved;
+                }
+            }
+            Command::Translate(x, y) => offset = (offset.0 + *x, offset.1 + *y),
+            Command::ConcatMatrix { e, f, .. } => offset = (offset.0 + *e, offset.1 + *f),
+            Command::SetFontName(name) => font_name = name.clone(),
+            Command::SetFontSize(size) => font_size = *size,
+            Command::DrawString { x, y, text } => {
+                let width = match registry {
+                    Some(registry) => registry.measure_text_width(&font_name, font_size, text),
+                    None => font_size * 0.5 * text.chars().count() as i32,
+                };
+                spans.push(TextSpan {
+                    text: text.clone(),
+                    bbox: Rect {
+                        x: offset.0 + *x,
+                        y: *y - offset.1,
+                        width,
+                        height: font_size,
+                    },
+                });
+            }
+            _ => {}
+        }
+    }
+    spans
+}
+
+pub(crate) fn page_text(page_number: usize, spans: Vec<TextSpan>) -> PageText {
+    let mut spans: Vec<TextSpan> = spans
+        .into_iter()
+        .filter(|span| !span.text.trim().is_empty())
+        .collect();
+    spans.sort_by(|a, b| {
+        a.bbox
+            .y
+            .partial_cmp(&b.bbox.y)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    // A span joins a line when its vertical center falls within the line's first span.
+    let mut lines: Vec<Vec<TextSpan>> = Vec::new();
+    for span in spans {
+        let center = span.bbox.y + span.bbox.height * 0.5;
+        match lines.last_mut() {
+            Some(line) if center >= line[0].bbox.y && center <= bottom(&line[0].bbox) => {
+                line.push(span)
+            }
+            _ => lines.push(vec![span]),
+        }
+    }
+
+    let lines = lines
+        .into_iter()
+        .map(|mut spans| {
+            spans.sort_by(|a, b| {
+                a.bbox
+                    .x
+                    .partial_cmp(&b.bbox.x)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+            let mut text = String::new();
+            let mut bbox = spans[0].bbox;
+            for (index, span) in spans.iter().enumerate() {
+                if index > 0 {
+                    let previous = &spans[index - 1];
+                    let gap = span.bbox.x - right(&previous.bbox);
+                    let height = previous.bbox.height.min(span.bbox.height);
+                    if gap > height * WORD_GAP_RATIO
+                        && !text.ends_with(char::is_whitespace)
+                        && !span.text.starts_with(char::is_whitespace)
+                    {
+                        text.push(' ');
+                    }
+                }
+                text.push_str(&span.text);
+                bbox = union(bbox, span.bbox);
+            }
+            TextLine {
+                text: text.trim().to_string(),
+                bbox,
+                spans,
+            }
+        })
+        .collect();
+    PageText { page_number, lines }
+}
+
+fn right(rect: &Rect) -> Pt {
+    rect.x + rect.width
+}
+
+fn bottom(rect: &Rect) -> Pt {
+    rect.y + rect.height
+}
+
+fn union(a: Rect, b: Rect) -> Rect {
+    let x = a.x.min(b.x);
+    let y = a.y.min(b.y);
+    Rect {
+        x,
+        y,
+        width: right(&a).max(right(&b)) - x,
+        height: bottom(&a).max(bottom(&b)) - y,
+    }
+}
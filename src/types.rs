@@ -23,6 +23,15 @@ impl Pt {
         self.0.to_num()
     }
 
+    // Exact fixed-point bits, for packed command storage.
+    pub(crate) fn to_bits(self) -> i64 {
+        self.0.to_bits()
+    }
+
+    pub(crate) fn from_bits(bits: i64) -> Pt {
+        Pt(I32F32::from_bits(bits))
+    }
+
     pub fn to_milli_i64(self) -> i64 {
         let bits = self.0.to_bits() as i128;
         let denom = 1i128 << 32;
@@ -59,6 +68,38 @@ impl Pt {
         Pt::from_milli_i128(value)
     }
 
+    // A length as written in options: a bare number is points, otherwise one of the units
+    // pt, px, in, mm or cm. `context` names the option for the error, whose location is the
+    // byte offset in `raw` of the number or unit that could not be read.
+    pub fn parse_length(raw: &str, context: &str) -> Result<Pt, crate::FullBleedError> {
+        let start = raw.len() - raw.trim_start().len();
+        let raw = raw.trim();
+        let invalid = |offset: usize| {
+            crate::FullBleedError::InvalidLength {
+                value: raw.to_string(),
+                context: context.to_string(),
+            }
+            .at(crate::SourceLocation {
+                byte_offset: Some(start + offset),
+                ..crate::SourceLocation::default()
+            })
+        };
+        let split = raw
+            .find(|ch: char| !(ch.is_ascii_digit() || ch == '.' || ch == '-' || ch == '+'))
+            .unwrap_or(raw.len());
+        let (number, unit) = raw.split_at(split);
+        let number: f32 = number.parse().map_err(|_| invalid(0))?;
+        let points = match unit.trim().to_ascii_lowercase().as_str() {
+            "" | "pt" => number,
+            "px" => number * 0.75,
+            "in" => number * 72.0,
+            "mm" => number * 72.0 / 25.4,
+            "cm" => number * 72.0 / 2.54,
+            _ => return Err(invalid(split + unit.len() - unit.trim_start().len())),
+        };
+        Ok(Pt::from_f32(points))
+    }
+
     pub fn from_milli_i64(milli: i64) -> Pt {
         Pt::from_milli_i128(milli as i128)
     }
@@ -253,6 +294,26 @@ impl Margins {
         }
     }
 
+    // Margins of a right-hand (odd) page of a bound document, with the gutter `inside` on the
+    // left. `mirror` gives the facing left-hand page.
+    pub fn mirrored(inside: f32, outside: f32, top: f32, bottom: f32) -> Self {
+        Self {
+            top: Pt::from_f32(top),
+            right: Pt::from_f32(outside),
+            bottom: Pt::from_f32(bottom),
+            left: Pt::from_f32(inside),
+        }
+    }
+
+    pub fn mirror(self) -> Self {
+        Self {
+            top: self.top,
+            right: self.left,
+            bottom: self.bottom,
+            left: self.right,
+        }
+    }
+
     pub fn quantized(self) -> Self {
         Self {
             top: self.top,
@@ -263,11 +324,14 @@ impl Margins {
     }
 }
 
+// `r`/`g`/`b` are always an sRGB rendering of the color (used by raster output and color
+// math); `process` keeps a device CMYK or spot intent for PDF output (None for plain RGB).
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Color {
     pub r: f32,
     pub g: f32,
     pub b: f32,
+    pub process: Option<ProcessColor>,
 }
 
 impl Color {
@@ -275,10 +339,97 @@ impl Color {
         r: 0.0,
         g: 0.0,
         b: 0.0,
+        process: None,
     };
 
     pub fn rgb(r: f32, g: f32, b: f32) -> Self {
-        Self { r, g, b }
+        Self {
+            r,
+            g,
+            b,
+            process: None,
+        }
+    }
+
+    // DeviceCMYK color; components are clamped to 0..=1.
+    pub fn cmyk(c: f32, m: f32, y: f32, k: f32) -> Self {
+        let cmyk = [c, m, y, k].map(|v| v.clamp(0.0, 1.0));
+        let [r, g, b] = cmyk_preview(cmyk);
+        Self {
+            r,
+            g,
+            b,
+            process: Some(ProcessColor::Cmyk(cmyk)),
+        }
+    }
+
+    // Named Separation colorant at `tint` (0..=1), with the CMYK equivalent of full tint used
+    // as the alternate space. None when the name is empty or longer than 31 bytes.
+    pub fn spot(name: &str, tint: f32, alternate: [f32; 4]) -> Option<Self> {
+        let name = SpotName::new(name)?;
+        let tint = tint.clamp(0.0, 1.0);
+        let alternate = alternate.map(|v| v.clamp(0.0, 1.0));
+        let [r, g, b] = cmyk_preview(alternate.map(|v| v * tint));
+        Some(Self {
+            r,
+            g,
+            b,
+            process: Some(ProcessColor::Spot {
+                name,
+                tint,
+                alternate,
+            }),
+        })
+    }
+}
+
+fn cmyk_preview([c, m, y, k]: [f32; 4]) -> [f32; 3] {
+    [
+        (1.0 - c) * (1.0 - k),
+        (1.0 - m) * (1.0 - k),
+        (1.0 - y) * (1.0 - k),
+    ]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProcessColor {
+    Cmyk([f32; 4]),
+    Spot {
+        name: SpotName,
+        tint: f32,
+        alternate: [f32; 4],
+    },
+}
+
+// Separation colorant name, stored inline so `Color` stays `Copy`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SpotName {
+    len: u8,
+    bytes: [u8; 31],
+}
+
+impl SpotName {
+    pub fn new(name: &str) -> Option<Self> {
+        let name = name.trim();
+        if name.is_empty() || name.len() > 31 {
+            return None;
+        }
+        let mut bytes = [0u8; 31];
+        bytes[..name.len()].copy_from_slice(name.as_bytes());
+        Some(Self {
+            len: name.len() as u8,
+            bytes,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        std::str::from_utf8(&self.bytes[..self.len as usize]).unwrap_or("")
+    }
+}
+
+impl std::fmt::Debug for SpotName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.as_str())
     }
 }
 
@@ -0,0 +1,217 @@
+// Non-fatal render issues for callers: HTML asset references the engine ignores, known-loss
+// events (content or settings the layout could not honor) and font substitutions. Each
+// warning goes to the sink registered with `FullBleedBuilder::on_warning` and is collected
+// into results such as `FullBleed::render_with_warnings`. The debug log keeps its own
+// `jit.*` records.
+use crate::known_loss::KnownLoss;
+use std::cell::RefCell;
+use std::fmt;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WarningKind {
+    // HTML references an asset the engine does not load (linked stylesheets, `<style>`,
+    // font preloads, scripts).
+    Asset,
+    // The output differs from what the input asked for (clipped content, an overridden page
+    // size, layout that did not converge).
+    KnownLoss,
+    // Text was drawn with a fallback font because the styled fonts lack its characters.
+    FontSubstitution,
+    // No font tried has a glyph for a character; it is drawn as `.notdef`.
+    MissingGlyph,
+}
+
+impl WarningKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WarningKind::Asset => "asset",
+            WarningKind::KnownLoss => "known_loss",
+            WarningKind::FontSubstitution => "font_substitution",
+            WarningKind::MissingGlyph => "missing_glyph",
+        }
+    }
+}
+
+// One warning. `code` is stable and machine-matchable (`PAGE_SIZE_OVERRIDDEN`,
+// `ASSET_STYLESHEET`, ...); `message` is for people.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub kind: WarningKind,
+    pub code: &'static str,
+    pub message: String,
+    // Document (batch record) the warning came from, when known.
+    pub doc_id: Option<usize>,
+    // 1-based page, when the warning concerns one page.
+    pub page: Option<usize>,
+    // Supporting values, such as the ignored URLs or the fonts tried.
+    pub details: Vec<String>,
+    // The fidelity loss this warning reports, typed.
+    pub loss: Option<KnownLoss>,
+    // Selector or element path of the content concerned, when known.
+    pub element: Option<String>,
+}
+
+impl Warning {
+    pub(crate) fn new(kind: WarningKind, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            code,
+            message: message.into(),
+            doc_id: None,
+            page: None,
+            details: Vec::new(),
+            loss: None,
+            element: None,
+        }
+    }
+
+    // A warning for `loss`, under the loss's code.
+    pub(crate) fn for_loss(kind: WarningKind, loss: KnownLoss, message: impl Into<String>) -> Self {
+        let mut warning = Self::new(kind, loss.code(), message);
+        warning.loss = Some(loss);
+        warning
+    }
+
+    pub(crate) fn element(mut self, element: impl Into<String>) -> Self {
+        self.element = Some(element.into());
+        self
+    }
+
+    pub(crate) fn doc(mut self, doc_id: Option<usize>) -> Self {
+        self.doc_id = doc_id;
+        self
+    }
+
+    pub(crate) fn page(mut self, page: usize) -> Self {
+        self.page = Some(page);
+        self
+    }
+
+    pub(crate) fn details(mut self, details: Vec<String>) -> Self {
+        self.details = details;
+        self
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.code, self.message)?;
+        if let Some(page) = self.page {
+            write!(f, " (page {page})")?;
+        }
+        Ok(())
+    }
+}
+
+// Receives warnings; any `Fn(&Warning) + Send + Sync` closure is a sink. Called from the
+// rendering threads.
+pub trait WarningSink: Send + Sync {
+    fn warn(&self, warning: &Warning);
+}
+
+impl<F> WarningSink for F
+where
+    F: Fn(&Warning) + Send + Sync,
+{
+    fn warn(&self, warning: &Warning) {
+        self(warning)
+    }
+}
+
+// Shared sink held by the engine.
+#[derive(Clone)]
+pub(crate) struct WarningHandle(Arc<dyn WarningSink>);
+
+impl WarningHandle {
+    pub(crate) fn new(sink: impl WarningSink + 'static) -> Self {
+        Self(Arc::new(sink))
+    }
+}
+
+impl fmt::Debug for WarningHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("WarningHandle")
+    }
+}
+
+thread_local! {
+    static COLLECTED: RefCell<Option<Vec<Warning>>> = const { RefCell::new(None) };
+    static SCOPED_SINK: RefCell<Option<WarningHandle>> = const { RefCell::new(None) };
+}
+
+// Sends `warning` to the sink and to the collection running on this thread, if any.
+pub(crate) fn emit(sink: Option<&WarningHandle>, warning: Warning) {
+    if let Some(sink) = sink {
+        sink.0.warn(&warning);
+    }
+    COLLECTED.with(|collected| {
+        if let Some(list) = collected.borrow_mut().as_mut() {
+            list.push(warning);
+        }
+    });
+}
+
+// Makes `sink` the target of `emit_scoped` on this thread until the guard drops. Set around
+// story building, whose code has no engine handle.
+pub(crate) fn scope(sink: Option<&WarningHandle>) -> ScopeGuard {
+    ScopeGuard(SCOPED_SINK.with(|scoped| scoped.replace(sink.cloned())))
+}
+
+pub(crate) struct ScopeGuard(Option<WarningHandle>);
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        let prev = self.0.take();
+        SCOPED_SINK.with(|scoped| *scoped.borrow_mut() = prev);
+    }
+}
+
+// `emit` to the sink of the enclosing `scope`.
+pub(crate) fn emit_scoped(warning: Warning) {
+    let sink = SCOPED_SINK.with(|scoped| scoped.borrow().clone());
+    emit(sink.as_ref(), warning);
+}
+
+// Whether a warning emitted now would reach anyone; lets callers skip building one.
+pub(crate) fn wanted(sink: Option<&WarningHandle>) -> bool {
+    sink.is_some() || COLLECTED.with(|collected| collected.borrow().is_some())
+}
+
+// Runs `f` and returns the warnings emitted on this thread meanwhile. Renders lay out on the
+// calling thread, so this captures one render's warnings even when others run concurrently.
+pub(crate) fn collect<T>(f: impl FnOnce() -> T) -> (T, Vec<Warning>) {
+    collect_into_outer(f, true)
+}
+
+// Like `collect`, but an enclosing collection does not see the warnings: the caller emits
+// them itself once it has put them in order (layout sections built on other threads).
+pub(crate) fn collect_detached<T>(f: impl FnOnce() -> T) -> (T, Vec<Warning>) {
+    collect_into_outer(f, false)
+}
+
+fn collect_into_outer<T>(f: impl FnOnce() -> T, nested: bool) -> (T, Vec<Warning>) {
+    struct Restore(Option<Option<Vec<Warning>>>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            if let Some(prev) = self.0.take() {
+                COLLECTED.with(|collected| *collected.borrow_mut() = prev);
+            }
+        }
+    }
+
+    let mut restore = Restore(Some(
+        COLLECTED.with(|collected| collected.borrow_mut().replace(Vec::new())),
+    ));
+    let out = f();
+    let mut prev = restore.0.take().expect("set above");
+    let warnings = COLLECTED
+        .with(|collected| collected.borrow_mut().take())
+        .unwrap_or_default();
+    // An enclosing collection sees nested warnings too.
+    if let Some(outer) = prev.as_mut().filter(|_| nested) {
+        outer.extend(warnings.iter().cloned());
+    }
+    COLLECTED.with(|collected| *collected.borrow_mut() = prev);
+    (out, warnings)
+}
@@ -0,0 +1,164 @@
+// In-memory render API for the browser (feature `wasm`, built for wasm32-unknown-unknown).
+// Fonts, images and stylesheets arrive from JS as bytes and are served from an asset bundle,
+// so the same templates render client-side without a filesystem.
+use crate::{Asset, AssetBundle, AssetKind, FullBleed, Margins, Pt, Size};
+use wasm_bindgen::prelude::*;
+
+// A reusable renderer. The engine is built on first render and rebuilt after an option or
+// asset changes.
+#[wasm_bindgen]
+pub struct WasmRenderer {
+    page_size: Option<Size>,
+    margins: Option<Margins>,
+    bundle: AssetBundle,
+    engine: Option<FullBleed>,
+}
+
+#[wasm_bindgen]
+impl WasmRenderer {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> WasmRenderer {
+        WasmRenderer {
+            page_size: None,
+            margins: None,
+            bundle: AssetBundle::default(),
+            engine: None,
+        }
+    }
+
+    #[wasm_bindgen(js_name = setPageSize)]
+    pub fn set_page_size(&mut self, width_pt: f32, height_pt: f32) {
+        self.page_size = Some(Size {
+            width: Pt::from_f32(width_pt),
+            height: Pt::from_f32(height_pt),
+        });
+        self.engine = None;
+    }
+
+    #[wasm_bindgen(js_name = setMargins)]
+    pub fn set_margins(&mut self, top_pt: f32, right_pt: f32, bottom_pt: f32, left_pt: f32) {
+        self.margins = Some(Margins {
+            top: Pt::from_f32(top_pt),
+            right: Pt::from_f32(right_pt),
+            bottom: Pt::from_f32(bottom_pt),
+            left: Pt::from_f32(left_pt),
+        });
+        self.engine = None;
+    }
+
+    // Adds an asset that templates reference by `name` (an `<img src>`, a CSS `url()`).
+    // `kind` is `font`, `image`, `svg`, `css`, `pdf` or `other`; fonts are registered with
+    // the engine and `css` assets are applied to every render.
+    #[wasm_bindgen(js_name = addAsset)]
+    pub fn add_asset(&mut self, name: String, kind: &str, bytes: Vec<u8>) -> Result<(), JsError> {
+        self.add_asset_bytes(name, kind, bytes)
+            .map_err(|err| JsError::new(&err))
+    }
+
+    #[wasm_bindgen(js_name = renderPdf)]
+    pub fn render_pdf(&mut self, html: &str, css: &str) -> Result<Vec<u8>, JsError> {
+        self.render_pdf_bytes(html, css)
+            .map_err(|err| JsError::new(&err))
+    }
+
+    // One PNG per page, for on-screen preview.
+    #[wasm_bindgen(js_name = renderPngPages)]
+    pub fn render_png_pages(
+        &mut self,
+        html: &str,
+        css: &str,
+        dpi: u32,
+    ) -> Result<js_sys::Array, JsError> {
+        let pages = self
+            .engine()
+            .and_then(|engine| {
+                engine
+                    .render_image_pages(html, css, dpi.max(1))
+                    .map_err(|err| err.to_string())
+            })
+            .map_err(|err| JsError::new(&err))?;
+        Ok(pages
+            .iter()
+            .map(|png| js_sys::Uint8Array::from(png.as_slice()))
+            .collect())
+    }
+}
+
+impl Default for WasmRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// The exported methods wrap these; errors stay plain messages until they cross into JS, so
+// the mapping can be checked off wasm32 where `JsError` cannot be built.
+impl WasmRenderer {
+    pub(crate) fn add_asset_bytes(
+        &mut self,
+        name: String,
+        kind: &str,
+        bytes: Vec<u8>,
+    ) -> Result<(), String> {
+        let kind =
+            AssetKind::from_str(kind).ok_or_else(|| format!("unknown asset kind: {kind}"))?;
+        self.bundle.add(Asset::new(name, kind, bytes, None, true));
+        self.engine = None;
+        Ok(())
+    }
+
+    pub(crate) fn render_pdf_bytes(&mut self, html: &str, css: &str) -> Result<Vec<u8>, String> {
+        self.engine()?
+            .render_to_buffer(html, css)
+            .map_err(|err| err.to_string())
+    }
+
+    fn engine(&mut self) -> Result<&FullBleed, String> {
+        if self.engine.is_none() {
+            let mut builder = FullBleed::builder().register_bundle(self.bundle.clone());
+            if let Some(size) = self.page_size {
+                builder = builder.page_size(size);
+            }
+            if let Some(margins) = self.margins {
+                builder = builder.margins(margins);
+            }
+            let engine = builder.build().map_err(|err| err.to_string())?;
+            self.engine = Some(engine);
+        }
+        Ok(self.engine.as_ref().expect("built above"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wasm_renderer_renders_pdf_bytes_and_maps_errors_to_messages() {
+        let mut renderer = WasmRenderer::new();
+        renderer.set_page_size(300.0, 400.0);
+        renderer
+            .add_asset(
+                "brand.css".to_string(),
+                "css",
+                b"p { color: #336699; }".to_vec(),
+            )
+            .expect("css asset");
+        let pdf = renderer.render_pdf("<p>Hello</p>", "").expect("render");
+        assert!(pdf.starts_with(b"%PDF-"));
+        let media_box = b"/MediaBox [0 0 300 400]";
+        assert!(pdf.windows(media_box.len()).any(|w| w == media_box));
+
+        // `JsError` only exists on wasm32; the messages it would carry come from here.
+        assert_eq!(
+            renderer
+                .add_asset_bytes("x".to_string(), "movie", Vec::new())
+                .unwrap_err(),
+            "unknown asset kind: movie"
+        );
+        renderer
+            .add_asset_bytes("broken.ttf".to_string(), "font", b"not a font".to_vec())
+            .expect("asset kind");
+        let err = renderer.render_pdf_bytes("<p>Hello</p>", "").unwrap_err();
+        assert!(err.contains("broken.ttf"), "{err}");
+    }
+}
@@ -0,0 +1,554 @@
+// WOFF and WOFF2 webfont containers, unpacked to a plain sfnt before a font is registered so
+// metrics, shaping, subsetting and embedding only ever see TrueType/OpenType data. WOFF 1.0
+// tables are zlib-compressed one by one; WOFF2 is one Brotli stream whose glyf/loca (and
+// optionally hmtx) tables are stored transformed and are rebuilt here. Collections are rejected.
+use crate::subset::{SimpleGlyph, push_simple_glyph, read_u16, read_u32, write_sfnt};
+use std::io::Read;
+
+const WOFF_SIGNATURE: u32 = u32::from_be_bytes(*b"wOFF");
+const WOFF2_SIGNATURE: u32 = u32::from_be_bytes(*b"wOF2");
+const COLLECTION_FLAVOR: u32 = u32::from_be_bytes(*b"ttcf");
+// Decompressed fonts larger than this are refused rather than inflated.
+const MAX_SFNT_BYTES: usize = 256 * 1024 * 1024;
+// Output buffers reserve at most this multiple of the compressed bytes up front; the sizes a
+// header declares are only trusted once decompression has produced them.
+const MAX_RESERVE_RATIO: usize = 4;
+
+type Tables = Vec<([u8; 4], Vec<u8>)>;
+
+#[cfg(any(feature = "python", test))]
+pub(crate) fn is_webfont(data: &[u8]) -> bool {
+    matches!(read_u32(data, 0), Some(WOFF_SIGNATURE | WOFF2_SIGNATURE))
+}
+
+// The sfnt inside a WOFF/WOFF2 container; any other data is returned unchanged.
+pub(crate) fn decode_webfont(data: Vec<u8>) -> Result<Vec<u8>, String> {
+    match read_u32(&data, 0) {
+        Some(WOFF_SIGNATURE) => decode_woff(&data).ok_or_else(|| "malformed WOFF font".to_string()),
+        Some(WOFF2_SIGNATURE) => decode_woff2(&data),
+        _ => Ok(data),
+    }
+}
+
+fn decode_woff(data: &[u8]) -> Option<Vec<u8>> {
+    let flavor = read_u32(data, 4)?;
+    if flavor == COLLECTION_FLAVOR {
+        return None;
+    }
+    let num_tables = read_u16(data, 12)? as usize;
+    let mut tables = Vec::with_capacity(num_tables);
+    let mut total = 0usize;
+    for index in 0..num_tables {
+        let entry = 44 + index * 20;
+        let tag: [u8; 4] = data.get(entry..entry + 4)?.try_into().ok()?;
+        let offset = read_u32(data, entry + 4)? as usize;
+        let comp_length = read_u32(data, entry + 8)? as usize;
+        let orig_length = read_u32(data, entry + 12)? as usize;
+        total = total.checked_add(orig_length)?;
+        if total > MAX_SFNT_BYTES || comp_length > orig_length {
+            return None;
+        }
+        let stored = data.get(offset..offset.checked_add(comp_length)?)?;
+        let table = if comp_length < orig_length {
+            let mut table =
+                Vec::with_capacity(orig_length.min(comp_length.saturating_mul(MAX_RESERVE_RATIO)));
+            flate2::read::ZlibDecoder::new(stored)
+                .take(orig_length as u64)
+                .read_to_end(&mut table)
+                .ok()?;
+            table
+        } else {
+            stored.to_vec()
+        };
+        if table.len() != orig_length {
+            return None;
+        }
+        tables.push((tag, table));
+    }
+    Some(write_sfnt(flavor, tables))
+}
+
+// Tags of the WOFF2 known-table index (flags & 0x3f), in specification order.
+const WOFF2_KNOWN_TAGS: [&[u8; 4]; 63] = [
+    b"cmap", b"head", b"hhea", b"hmtx", b"maxp", b"name", b"OS/2", b"post", b"cvt ", b"fpgm",
+    b"glyf", b"loca", b"prep", b"CFF ", b"VORG", b"EBDT", b"EBLC", b"gasp", b"hdmx", b"kern",
+    b"LTSH", b"PCLT", b"VDMX", b"vhea", b"vmtx", b"BASE", b"GDEF", b"GPOS", b"GSUB", b"EBSC",
+    b"JSTF", b"MATH", b"CBDT", b"CBLC", b"COLR", b"CPAL", b"SVG ", b"sbix", b"acnt", b"avar",
+    b"bdat", b"bloc", b"bsln", b"cvar", b"fdsc", b"feat", b"fmtx", b"fvar", b"gvar", b"hsty",
+    b"just", b"lcar", b"mort", b"morx", b"opbd", b"prop", b"trak", b"Zapf", b"Silf", b"Glat",
+    b"Gloc", b"Feat", b"Sill",
+];
+
+struct Woff2Table {
+    tag: [u8; 4],
+    transformed: bool,
+    orig_length: usize,
+    stored_length: usize,
+}
+
+fn decode_woff2(data: &[u8]) -> Result<Vec<u8>, String> {
+    let malformed = || "malformed WOFF2 font".to_string();
+    let flavor = read_u32(data, 4).ok_or_else(malformed)?;
+    if flavor == COLLECTION_FLAVOR {
+        return Err("WOFF2 font collections are not supported".to_string());
+    }
+    let num_tables = read_u16(data, 12).ok_or_else(malformed)? as usize;
+    let compressed_size = read_u32(data, 20).ok_or_else(malformed)? as usize;
+
+    let mut cursor = Cursor { data, pos: 48 };
+    let mut directory = Vec::with_capacity(num_tables);
+    for _ in 0..num_tables {
+        let flags = cursor.u8().ok_or_else(malformed)?;
+        let tag = match flags & 0x3f {
+            0x3f => cursor.tag().ok_or_else(malformed)?,
+            known => *WOFF2_KNOWN_TAGS[known as usize],
+        };
+        let version = flags >> 6;
+        // glyf and loca use version 0 for their transform and 3 for "stored as is".
+        let transformed = if &tag == b"glyf" || &tag == b"loca" {
+            version == 0
+        } else {
+            version != 0
+        };
+        let orig_length = cursor.base128().ok_or_else(malformed)? as usize;
+        let stored_length = if transformed {
+            cursor.base128().ok_or_else(malformed)? as usize
+        } else {
+            orig_length
+        };
+        directory.push(Woff2Table {
+            tag,
+            transformed,
+            orig_length,
+            stored_length,
+        });
+    }
+
+    let compressed = cursor.bytes(compressed_size).ok_or_else(malformed)?;
+    let expected = directory
+        .iter()
+        .try_fold(0usize, |sum, table| sum.checked_add(table.stored_length))
+        .filter(|expected| *expected <= MAX_SFNT_BYTES)
+        .ok_or_else(|| "WOFF2 font is too large".to_string())?;
+    let mut stream =
+        Vec::with_capacity(expected.min(compressed.len().saturating_mul(MAX_RESERVE_RATIO)));
+    brotli_decompressor::Decompressor::new(compressed, 4096)
+        .take(expected as u64)
+        .read_to_end(&mut stream)
+        .map_err(|err| format!("WOFF2 decompression failed: {err}"))?;
+    if stream.len() != expected {
+        return Err(malformed());
+    }
+
+    let mut stored = Vec::with_capacity(directory.len());
+    let mut offset = 0;
+    for table in &directory {
+        stored.push(&stream[offset..offset + table.stored_length]);
+        offset += table.stored_length;
+    }
+    let find = |tag: &[u8; 4]| directory.iter().position(|table| &table.tag == tag);
+
+    let mut tables: Tables = Vec::with_capacity(directory.len());
+    let mut glyph_x_min = Vec::new();
+    if let Some(glyf) = find(b"glyf").filter(|index| directory[*index].transformed) {
+        let rebuilt = rebuild_glyf(stored[glyf]).ok_or_else(malformed)?;
+        glyph_x_min = rebuilt.x_min;
+        tables.push((*b"glyf", rebuilt.glyf));
+        tables.push((*b"loca", rebuilt.loca));
+    }
+    for (index, table) in directory.iter().enumerate() {
+        if table.transformed && (&table.tag == b"glyf" || &table.tag == b"loca") {
+            continue;
+        }
+        let data = if table.transformed && &table.tag == b"hmtx" {
+            let hhea = find(b"hhea").map(|i| stored[i]).ok_or_else(malformed)?;
+            let num_h_metrics = read_u16(hhea, 34).ok_or_else(malformed)? as usize;
+            rebuild_hmtx(stored[index], num_h_metrics, &glyph_x_min).ok_or_else(malformed)?
+        } else if table.transformed {
+            return Err(format!(
+                "unsupported WOFF2 transform for table {}",
+                String::from_utf8_lossy(&table.tag)
+            ));
+        } else {
+            stored[index].to_vec()
+        };
+        if !table.transformed && data.len() != table.orig_length {
+            return Err(malformed());
+        }
+        tables.push((table.tag, data));
+    }
+    Ok(write_sfnt(flavor, tables))
+}
+
+struct RebuiltGlyf {
+    glyf: Vec<u8>,
+    loca: Vec<u8>,
+    x_min: Vec<i16>,
+}
+
+// WOFF2 section 5.1: split glyph streams back into glyf records, with loca in the format the
+// transform header names.
+fn rebuild_glyf(data: &[u8]) -> Option<RebuiltGlyf> {
+    const WE_HAVE_INSTRUCTIONS: u16 = 0x0100;
+
+    let option_flags = read_u16(data, 2)?;
+    let num_glyphs = read_u16(data, 4)? as usize;
+    let long_loca = read_u16(data, 6)? != 0;
+    let mut streams = Vec::with_capacity(7);
+    let mut offset = 36usize;
+    for index in 0..7 {
+        let length = read_u32(data, 8 + index * 4)? as usize;
+        streams.push(Cursor {
+            data: data.get(offset..offset.checked_add(length)?)?,
+            pos: 0,
+        });
+        offset += length;
+    }
+    let overlap_bitmap = if option_flags & 1 != 0 {
+        data.get(offset..offset + num_glyphs.div_ceil(8))?
+    } else {
+        &[]
+    };
+    let [
+        mut n_contours,
+        mut n_points,
+        mut flags,
+        mut glyphs,
+        mut composites,
+        mut bboxes,
+        mut instructions,
+    ] = <[Cursor; 7]>::try_from(streams).ok()?;
+    let bbox_bitmap = bboxes.bytes(num_glyphs.div_ceil(32) * 4)?;
+    let bit = |bitmap: &[u8], gid: usize| {
+        bitmap
+            .get(gid >> 3)
+            .is_some_and(|byte| byte & (0x80 >> (gid & 7)) != 0)
+    };
+
+    let mut glyf = Vec::new();
+    let mut loca = Vec::with_capacity((num_glyphs + 1) * 4);
+    let mut x_min = vec![0i16; num_glyphs];
+    let push_loca = |loca: &mut Vec<u8>, at: usize| {
+        if long_loca {
+            loca.extend_from_slice(&(at as u32).to_be_bytes());
+        } else {
+            loca.extend_from_slice(&((at / 2) as u16).to_be_bytes());
+        }
+    };
+    for (gid, glyph_x_min) in x_min.iter_mut().enumerate() {
+        push_loca(&mut loca, glyf.len());
+        let contours = n_contours.u16()? as i16;
+        if contours == 0 {
+            continue;
+        }
+        let explicit_bbox = bit(bbox_bitmap, gid);
+        if contours < 0 {
+            // Composite: the component records are stored verbatim; the bbox is always explicit.
+            let bbox = bboxes.bytes(8)?;
+            let start = composites.pos;
+            let mut has_instructions = false;
+            loop {
+                let component_flags = composites.u16()?;
+                composites.u16()?;
+                let mut skip = if component_flags & 0x0001 != 0 { 4 } else { 2 };
+                if component_flags & 0x0008 != 0 {
+                    skip += 2;
+                } else if component_flags & 0x0040 != 0 {
+                    skip += 4;
+                } else if component_flags & 0x0080 != 0 {
+                    skip += 8;
+                }
+                composites.bytes(skip)?;
+                has_instructions |= component_flags & WE_HAVE_INSTRUCTIONS != 0;
+                if component_flags & 0x0020 == 0 {
+                    break;
+                }
+            }
+            *glyph_x_min = read_u16(bbox, 0)? as i16;
+            glyf.extend_from_slice(&contours.to_be_bytes());
+            glyf.extend_from_slice(bbox);
+            glyf.extend_from_slice(&composites.data[start..composites.pos]);
+            if has_instructions {
+                let length = glyphs.u255()?;
+                glyf.extend_from_slice(&length.to_be_bytes());
+                glyf.extend_from_slice(instructions.bytes(length as usize)?);
+            }
+        } else {
+            let mut end_points = Vec::with_capacity(contours as usize);
+            let mut total = 0usize;
+            for _ in 0..contours {
+                total += n_points.u255()? as usize;
+                end_points.push(u16::try_from(total.checked_sub(1)?).ok()?);
+            }
+            let mut points = Vec::with_capacity(total);
+            let (mut x, mut y) = (0i32, 0i32);
+            for _ in 0..total {
+                let flag = flags.u8()?;
+                let (dx, dy) = decode_triplet(flag & 0x7f, &mut glyphs)?;
+                x += dx;
+                y += dy;
+                points.push((x, y, flag & 0x80 == 0));
+            }
+            let instruction_length = glyphs.u255()?;
+            let bbox: [i16; 4] = if explicit_bbox {
+                let raw = bboxes.bytes(8)?;
+                [0, 2, 4, 6].map(|at| i16::from_be_bytes([raw[at], raw[at + 1]]))
+            } else {
+                let (xs, ys) = (points.iter().map(|p| p.0), points.iter().map(|p| p.1));
+                [
+                    xs.clone().min()? as i16,
+                    ys.clone().min()? as i16,
+                    xs.max()? as i16,
+                    ys.max()? as i16,
+                ]
+            };
+            *glyph_x_min = bbox[0];
+            push_simple_glyph(
+                &mut glyf,
+                &SimpleGlyph {
+                    end_points: &end_points,
+                    points: &points,
+                    bbox,
+                    instructions: instructions.bytes(instruction_length as usize)?,
+                    overlap: bit(overlap_bitmap, gid),
+                },
+            );
+        }
+        while glyf.len() % 4 != 0 {
+            glyf.push(0);
+        }
+    }
+    push_loca(&mut loca, glyf.len());
+    Some(RebuiltGlyf { glyf, loca, x_min })
+}
+
+// WOFF2 section 5.4: coordinate triplets (flag byte with the on-curve bit removed).
+fn decode_triplet(flag: u8, glyphs: &mut Cursor<'_>) -> Option<(i32, i32)> {
+    let with_sign = |flag: u8, value: i32| if flag & 1 != 0 { value } else { -value };
+    let flag_value = flag as i32;
+    Some(if flag < 10 {
+        let b0 = glyphs.u8()? as i32;
+        (0, with_sign(flag, ((flag_value & 14) << 7) + b0))
+    } else if flag < 20 {
+        let b0 = glyphs.u8()? as i32;
+        (with_sign(flag, (((flag_value - 10) & 14) << 7) + b0), 0)
+    } else if flag < 84 {
+        let selector = flag_value - 20;
+        let b0 = glyphs.u8()? as i32;
+        (
+            with_sign(flag, 1 + (selector & 0x30) + (b0 >> 4)),
+            with_sign(flag >> 1, 1 + ((selector & 0x0c) << 2) + (b0 & 0x0f)),
+        )
+    } else if flag < 120 {
+        let selector = flag_value - 84;
+        let (b0, b1) = (glyphs.u8()? as i32, glyphs.u8()? as i32);
+        (
+            with_sign(flag, 1 + ((selector / 12) << 8) + b0),
+            with_sign(flag >> 1, 1 + (((selector % 12) >> 2) << 8) + b1),
+        )
+    } else if flag < 124 {
+        let (b0, b1, b2) = (
+            glyphs.u8()? as i32,
+            glyphs.u8()? as i32,
+            glyphs.u8()? as i32,
+        );
+        (
+            with_sign(flag, (b0 << 4) + (b1 >> 4)),
+            with_sign(flag >> 1, ((b1 & 0x0f) << 8) + b2),
+        )
+    } else {
+        let (x, y) = (glyphs.u16()? as i32, glyphs.u16()? as i32);
+        (with_sign(flag, x), with_sign(flag >> 1, y))
+    })
+}
+
+// WOFF2 section 5.4 (hmtx): left side bearings omitted from the stream equal the glyph xMin.
+fn rebuild_hmtx(data: &[u8], num_h_metrics: usize, x_min: &[i16]) -> Option<Vec<u8>> {
+    let flags = *data.first()?;
+    let num_glyphs = x_min.len();
+    let mut cursor = Cursor { data, pos: 1 };
+    let advances = (0..num_h_metrics)
+        .map(|_| cursor.u16())
+        .collect::<Option<Vec<_>>>()?;
+    let mut bearing = |gid: usize, omitted: bool| -> Option<i16> {
+        if omitted {
+            x_min.get(gid).copied()
+        } else {
+            cursor.u16().map(|value| value as i16)
+        }
+    };
+    let mut out =
+        Vec::with_capacity(num_h_metrics * 4 + num_glyphs.saturating_sub(num_h_metrics) * 2);
+    let proportional = (0..num_h_metrics)
+        .map(|gid| bearing(gid, flags & 1 != 0))
+        .collect::<Option<Vec<_>>>()?;
+    for (advance, lsb) in advances.iter().zip(proportional) {
+        out.extend_from_slice(&advance.to_be_bytes());
+        out.extend_from_slice(&lsb.to_be_bytes());
+    }
+    for gid in num_h_metrics..num_glyphs {
+        out.extend_from_slice(&bearing(gid, flags & 2 != 0)?.to_be_bytes());
+    }
+    Some(out)
+}
+
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let out = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(out)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.bytes(1).map(|bytes| bytes[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.bytes(2)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn tag(&mut self) -> Option<[u8; 4]> {
+        self.bytes(4)?.try_into().ok()
+    }
+
+    // UIntBase128: at most five bytes, no leading zero byte, must fit in 32 bits.
+    fn base128(&mut self) -> Option<u32> {
+        let mut value = 0u32;
+        for index in 0..5 {
+            let byte = self.u8()?;
+            if index == 0 && byte == 0x80 {
+                return None;
+            }
+            if value & 0xFE00_0000 != 0 {
+                return None;
+            }
+            value = (value << 7) | (byte & 0x7f) as u32;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    // 255UInt16.
+    fn u255(&mut self) -> Option<u16> {
+        Some(match self.u8()? {
+            253 => self.u16()?,
+            254 => self.u8()? as u16 + 506,
+            255 => self.u8()? as u16 + 253,
+            code => code as u16,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_webfont, is_webfont};
+    use std::io::Write;
+
+    // A WOFF 1.0 container around `sfnt`; tables are zlib-compressed where that saves space.
+    fn to_woff(sfnt: &[u8]) -> Vec<u8> {
+        let num_tables = u16::from_be_bytes([sfnt[4], sfnt[5]]) as usize;
+        let mut entries = Vec::new();
+        let mut bodies = Vec::new();
+        let body_start = 44 + num_tables * 20;
+        for index in 0..num_tables {
+            let record = &sfnt[12 + index * 16..28 + index * 16];
+            let offset = u32::from_be_bytes(record[8..12].try_into().unwrap()) as usize;
+            let length = u32::from_be_bytes(record[12..16].try_into().unwrap()) as usize;
+            let mut encoder =
+                flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(&sfnt[offset..offset + length]).unwrap();
+            let mut compressed = encoder.finish().unwrap();
+            if compressed.len() >= length {
+                compressed = sfnt[offset..offset + length].to_vec();
+            }
+            entries.extend_from_slice(&record[..4]);
+            entries.extend_from_slice(&((body_start + bodies.len()) as u32).to_be_bytes());
+            entries.extend_from_slice(&(compressed.len() as u32).to_be_bytes());
+            entries.extend_from_slice(&(length as u32).to_be_bytes());
+            entries.extend_from_slice(&record[4..8]);
+            bodies.extend_from_slice(&compressed);
+            while bodies.len() % 4 != 0 {
+                bodies.push(0);
+            }
+        }
+        let mut out = Vec::new();
+        out.extend_from_slice(b"wOFF");
+        out.extend_from_slice(&sfnt[..4]);
+        out.extend_from_slice(&((body_start + bodies.len()) as u32).to_be_bytes());
+        out.extend_from_slice(&(num_tables as u16).to_be_bytes());
+        out.extend_from_slice(&[0; 2]);
+        out.extend_from_slice(&(sfnt.len() as u32).to_be_bytes());
+        out.extend_from_slice(&[0; 24]);
+        out.extend_from_slice(&entries);
+        out.extend_from_slice(&bodies);
+        out
+    }
+
+    #[test]
+    fn woff_tables_inflate_to_an_equivalent_sfnt() {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("python/fullbleed_assets/fonts/NotoSans-Regular.ttf");
+        let sfnt = std::fs::read(path).expect("read noto");
+        let woff = to_woff(&sfnt);
+        assert!(is_webfont(&woff) && !is_webfont(&sfnt));
+        assert!(woff.len() < sfnt.len());
+
+        let decoded = decode_webfont(woff).expect("decode woff");
+        let original = ttf_parser::Face::parse(&sfnt, 0).expect("original");
+        let face = ttf_parser::Face::parse(&decoded, 0).expect("decoded parses");
+        assert_eq!(face.number_of_glyphs(), original.number_of_glyphs());
+        let gid = face.glyph_index('g').expect("g");
+        assert_eq!(Some(gid), original.glyph_index('g'));
+        assert_eq!(
+            face.glyph_bounding_box(gid),
+            original.glyph_bounding_box(gid)
+        );
+
+        assert_eq!(decode_webfont(sfnt.clone()).expect("passthrough"), sfnt);
+        let mut truncated = to_woff(&sfnt);
+        truncated.truncate(200);
+        assert!(decode_webfont(truncated).is_err());
+    }
+
+    #[test]
+    fn malformed_woff2_is_rejected_without_panicking() {
+        let mut data = b"wOF2\x00\x01\x00\x00".to_vec();
+        data.extend_from_slice(&[0; 40]);
+        data[13] = 1;
+        data.extend_from_slice(&[0x0a, 0x80]);
+        assert!(decode_webfont(data).is_err());
+    }
+
+    #[test]
+    fn declared_sizes_are_not_trusted() {
+        // Two WOFF2 tables of u32::MAX bytes each: the sum is refused, not allocated.
+        let mut data = b"wOF2\x00\x01\x00\x00".to_vec();
+        data.extend_from_slice(&[0; 40]);
+        data[13] = 2;
+        for _ in 0..2 {
+            data.extend_from_slice(&[0x00, 0x8f, 0xff, 0xff, 0xff, 0x7f]);
+        }
+        let err = decode_webfont(data).expect_err("oversized woff2");
+        assert!(err.contains("too large"), "{err}");
+
+        // A WOFF table claiming 200 MB behind ten bytes of zlib data.
+        let mut data = b"wOFF\x00\x01\x00\x00".to_vec();
+        data.extend_from_slice(&[0; 36]);
+        data[13] = 1;
+        data.extend_from_slice(b"cmap");
+        data.extend_from_slice(&64u32.to_be_bytes());
+        data.extend_from_slice(&10u32.to_be_bytes());
+        data.extend_from_slice(&(200u32 << 20).to_be_bytes());
+        data.extend_from_slice(&[0; 4]);
+        data.extend_from_slice(&[0x78, 0x9c, 0x03, 0, 0, 0, 0, 0, 0, 1]);
+        assert!(decode_webfont(data).is_err());
+    }
+}
@@ -0,0 +1,75 @@
+from __future__ import annotations
+
+from pathlib import Path
+
+import pytest
+
+import fullbleed
+
+
+def _require_pdf_engine() -> None:
+    if not hasattr(fullbleed, "PdfEngine"):
+        pytest.skip("fullbleed native extension is not available in this test environment")
+
+
+def _records() -> list[str]:
+    return [
+        "<p>One</p>",
+        '<p>Two</p><p style="break-before: page">Two, page 2</p>',
+        "<p>Three</p>",
+    ]
+
+
+def test_render_pdf_batch_iter_yields_records_in_order_then_stops() -> None:
+    _require_pdf_engine()
+
+    engine = fullbleed.PdfEngine()
+    records = engine.render_pdf_batch_iter(_records(), "p { color: #333; }")
+    assert len(records) == 3
+
+    first = next(records)
+    assert first["index"] == 0
+    assert first["page_count"] == 1
+    assert first["pdf"].startswith(b"%PDF-")
+    assert len(records) == 2
+
+    rest = list(records)
+    assert [record["index"] for record in rest] == [1, 2]
+    assert [record["page_count"] for record in rest] == [2, 1]
+    assert all(record["pdf"].startswith(b"%PDF-") for record in rest)
+
+    assert len(records) == 0
+    with pytest.raises(StopIteration):
+        next(records)
+
+
+def test_render_pdf_batch_iter_writes_files_when_given_out_dir(tmp_path: Path) -> None:
+    _require_pdf_engine()
+
+    engine = fullbleed.PdfEngine()
+    out_dir = tmp_path / "batch"
+    paths = [
+        record["path"]
+        for record in engine.render_pdf_batch_iter(_records(), "", str(out_dir), "invoice")
+    ]
+
+    assert paths == [str(out_dir / f"invoice_{index}.pdf") for index in range(3)]
+    assert all(Path(path).read_bytes().startswith(b"%PDF-") for path in paths)
+
+
+def test_render_pdf_batch_iter_raises_for_a_failed_record_and_continues() -> None:
+    _require_pdf_engine()
+
+    engine = fullbleed.PdfEngine(strict_fidelity=True)
+    clipped = (
+        '<div style="position: absolute; top: 0; left: 0; width: 10pt; height: 5000pt">'
+        "Too tall</div>"
+    )
+    records = engine.render_pdf_batch_iter(["<p>One</p>", clipped, "<p>Three</p>"], "")
+
+    assert next(records)["index"] == 0
+    with pytest.raises(ValueError, match=r"record 1: .*ABS_OVERFLOW_CLIPPED"):
+        next(records)
+    assert next(records)["index"] == 2
+    with pytest.raises(StopIteration):
+        next(records)